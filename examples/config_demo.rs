@@ -6,6 +6,7 @@ use kizuna::cli::config::{
     TOMLConfigParser, DefaultPeerConfig, TransferSettingsConfig,
 };
 use kizuna::cli::types::{CLIConfig, ConfigProfile, OutputFormat, ColorMode};
+use kizuna::security::SecuritySystem;
 use std::collections::HashMap;
 
 #[tokio::main]
@@ -14,7 +15,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Demo 1: Load or create default configuration
     println!("1. Loading or creating default configuration...");
-    let config = load_or_create_config().await?;
+    // The config file is encrypted at rest with the security system's storage key
+    let storage = SecuritySystem::new()?.storage();
+    let config = load_or_create_config(storage.clone()).await?;
     println!("   Output format: {:?}", config.output_format);
     println!("   Color mode: {:?}", config.color_mode);
     println!("   Compression: {}", config.transfer_settings.compression);
@@ -23,7 +26,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Demo 2: TOML parser
     println!("2. Demonstrating TOML parser...");
-    let parser = TOMLConfigParser::new(None)?;
+    let parser = TOMLConfigParser::new(None, storage.clone())?;
     let toml_str = parser.serialize_toml(&config)?;
     println!("   Configuration as TOML:");
     println!("{}", toml_str);
@@ -121,7 +124,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Demo 6: Specialized configuration managers
     println!("6. Demonstrating specialized configuration managers...");
-    let unified = UnifiedConfigManager::new(config.clone());
+    let unified = UnifiedConfigManager::new(config.clone(), storage);
     
     // Default peer config
     let peer_config = unified.default_peer();
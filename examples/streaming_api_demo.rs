@@ -6,7 +6,7 @@
 use kizuna::streaming::{
     Streaming, StreamingApi, StreamConfig, ScreenConfig, StreamQuality,
     QualityPreset, ScreenRegion, StreamEvent, StreamEventHandler,
-    ViewerPermissions, RecordingConfig, VideoFormat,
+    ViewerPermissions, WatermarkConfig, RecordingConfig, VideoFormat,
 };
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -129,6 +129,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         capture_audio: false,
         monitor_index: Some(0),
         quality: QualityPreset::High.to_quality(),
+        privacy: kizuna::streaming::PrivacyMaskConfig::sensitive_apps(),
     };
     
     let screen_session = api.start_screen_stream(screen_config).await?;
@@ -145,6 +146,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             can_record: false,
             can_control_quality: false,
             max_quality: QualityPreset::Medium,
+            watermark: None,
         },
     ).await?;
     println!("   Viewer 1 added: {}", viewer1);
@@ -157,6 +159,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             can_record: true,
             can_control_quality: true,
             max_quality: QualityPreset::High,
+            watermark: Some(WatermarkConfig::default()),
         },
     ).await?;
     println!("   Viewer 2 added: {}", viewer2);
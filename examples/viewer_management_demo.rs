@@ -8,7 +8,7 @@
 
 use kizuna::streaming::{
     ViewerManager, ViewerPermissions, QualityPreset, StreamQuality,
-    Resolution, VideoStream, StreamSource, ScreenRegion,
+    Resolution, VideoStream, StreamSource, ScreenRegion, WatermarkConfig,
 };
 use kizuna::streaming::viewer::{
     ViewerManagerImpl, ViewerManagementControls, ViewerConnectionResult,
@@ -32,6 +32,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         can_record: true,
         can_control_quality: true,
         max_quality: QualityPreset::High,
+        watermark: Some(WatermarkConfig::default()),
     };
     
     match controls.handle_viewer_connection(
@@ -51,6 +52,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         can_record: false,
         can_control_quality: false,
         max_quality: QualityPreset::Medium,
+        watermark: None,
     };
     
     match controls.handle_viewer_connection(
@@ -70,6 +72,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         can_record: false,
         can_control_quality: false,
         max_quality: QualityPreset::Low,
+        watermark: None,
     };
     
     match controls.handle_viewer_connection(
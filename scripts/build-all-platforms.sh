@@ -15,6 +15,8 @@ BUILD_TYPE="${BUILD_TYPE:-release}"
 OUTPUT_DIR="${OUTPUT_DIR:-dist}"
 SKIP_TESTS="${SKIP_TESTS:-false}"
 PLATFORMS="${PLATFORMS:-all}"
+# Desktop packages ship every subsystem; set FEATURES=default for a minimal build
+FEATURES="${FEATURES:-desktop}"
 
 # Platform targets
 LINUX_TARGETS=("x86_64-unknown-linux-gnu" "aarch64-unknown-linux-gnu")
@@ -62,7 +64,7 @@ build_target() {
     install_target "$target"
     
     # Build command
-    local build_cmd="cargo build --target $target"
+    local build_cmd="cargo build --target $target --features $FEATURES"
     if [ "$BUILD_TYPE" = "release" ]; then
         build_cmd="$build_cmd --release"
     fi
@@ -103,7 +105,7 @@ run_tests() {
     
     log_info "Running tests for $target..."
     
-    local test_cmd="cargo test --target $target"
+    local test_cmd="cargo test --target $target --features $FEATURES"
     if [ "$BUILD_TYPE" = "release" ]; then
         test_cmd="$test_cmd --release"
     fi
@@ -272,6 +274,7 @@ Kizuna Cross-Platform Build Report
 ===================================
 Build Date: $(date)
 Build Type: $BUILD_TYPE
+Features: $FEATURES
 Host System: $(uname -s) $(uname -m)
 
 Artifacts:
@@ -317,6 +320,7 @@ ENVIRONMENT VARIABLES:
     BUILD_TYPE          Build type (release or debug)
     OUTPUT_DIR          Output directory for artifacts
     SKIP_TESTS          Skip tests (true or false)
+    FEATURES            Cargo features to build with (default: desktop)
     PLATFORMS           Platforms to build (comma-separated)
 
 EOF
@@ -6,13 +6,16 @@ pub mod server;
 pub mod handlers;
 pub mod websocket;
 
-use crate::browser_support::{BrowserResult, discovery::BrowserDiscovery};
+use crate::browser_support::{BrowserResult, discovery::BrowserDiscovery, dropbox::DropBoxManager};
+use crate::security::policy::IpFilter;
 use std::sync::Arc;
 
 /// API server for browser clients
 pub struct APIServer {
     server: Option<server::WebServer>,
     discovery_manager: Arc<BrowserDiscovery>,
+    drop_boxes: Option<Arc<DropBoxManager>>,
+    ip_filter: Option<Arc<IpFilter>>,
 }
 
 impl APIServer {
@@ -21,12 +24,31 @@ impl APIServer {
         Self {
             server: None,
             discovery_manager,
+            drop_boxes: None,
+            ip_filter: None,
         }
     }
+
+    /// Serve the upload pages of these drop boxes once started
+    pub fn set_drop_boxes(&mut self, drop_boxes: Arc<DropBoxManager>) {
+        self.drop_boxes = Some(drop_boxes);
+    }
+
+    /// Refuse requests from addresses the filter blocks once started
+    pub fn set_ip_filter(&mut self, ip_filter: Arc<IpFilter>) {
+        self.ip_filter = Some(ip_filter);
+    }
     
     /// Initialize the API server
     pub async fn initialize(&mut self) -> BrowserResult<()> {
-        self.server = Some(server::WebServer::new(self.discovery_manager.clone()));
+        let mut server = server::WebServer::new(self.discovery_manager.clone());
+        if let Some(drop_boxes) = &self.drop_boxes {
+            server = server.with_drop_boxes(drop_boxes.clone());
+        }
+        if let Some(ip_filter) = &self.ip_filter {
+            server = server.with_ip_filter(ip_filter.clone());
+        }
+        self.server = Some(server);
         Ok(())
     }
     
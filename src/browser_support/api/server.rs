@@ -5,10 +5,14 @@
 use crate::browser_support::{BrowserResult, BrowserSupportError, discovery::BrowserDiscovery};
 use crate::browser_support::types::*;
 use crate::browser_support::api::handlers::APIHandlers;
+use crate::browser_support::dropbox::{self, DropBoxManager};
+use crate::security::policy::{IpEntryPoint, IpFilter};
+use crate::supervisor::Supervisor;
 use axum::{
-    extract::{Path, Query, State},
+    extract::{ConnectInfo, Path, Query, Request, State},
     http::{StatusCode, HeaderMap},
-    response::{Html, Json, Response},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
@@ -23,6 +27,9 @@ use uuid::Uuid;
 /// Web server for browser API
 pub struct WebServer {
     discovery_manager: Arc<BrowserDiscovery>,
+    supervisor: Option<Arc<Supervisor>>,
+    drop_boxes: Option<Arc<DropBoxManager>>,
+    ip_filter: Option<Arc<IpFilter>>,
     shutdown_signal: Option<tokio::sync::oneshot::Sender<()>>,
 }
 
@@ -31,6 +38,8 @@ pub struct WebServer {
 pub struct ServerState {
     pub handlers: Arc<APIHandlers>,
     pub discovery_manager: Arc<BrowserDiscovery>,
+    /// Supervisor whose components back the readiness check
+    pub supervisor: Option<Arc<Supervisor>>,
 }
 
 /// Query parameters for connection setup
@@ -50,9 +59,37 @@ impl WebServer {
     pub fn new(discovery_manager: Arc<BrowserDiscovery>) -> Self {
         Self {
             discovery_manager,
+            supervisor: None,
+            drop_boxes: None,
+            ip_filter: None,
             shutdown_signal: None,
         }
     }
+
+    /// Whether browsers must reach the server over HTTPS
+    ///
+    /// The server has no TLS listener; it answers plain HTTP on loopback.
+    pub fn enforces_https() -> bool {
+        false
+    }
+
+    /// Report the supervisor's components on `/health/ready`
+    pub fn with_supervisor(mut self, supervisor: Arc<Supervisor>) -> Self {
+        self.supervisor = Some(supervisor);
+        self
+    }
+
+    /// Serve the upload pages of these drop boxes under `/drop/<token>`
+    pub fn with_drop_boxes(mut self, drop_boxes: Arc<DropBoxManager>) -> Self {
+        self.drop_boxes = Some(drop_boxes);
+        self
+    }
+
+    /// Refuse requests from addresses the filter blocks
+    pub fn with_ip_filter(mut self, ip_filter: Arc<IpFilter>) -> Self {
+        self.ip_filter = Some(ip_filter);
+        self
+    }
     
     /// Start the web server
     pub async fn start(&mut self, port: u16) -> BrowserResult<()> {
@@ -70,9 +107,17 @@ impl WebServer {
         let state = ServerState {
             handlers,
             discovery_manager: self.discovery_manager.clone(),
+            supervisor: self.supervisor.clone(),
         };
 
-        let app = create_router(state);
+        let mut app = create_router(state);
+        // Drop-box routes stay outside the permissive CORS layer
+        if let Some(drop_boxes) = &self.drop_boxes {
+            app = app.merge(dropbox::router(Arc::clone(drop_boxes)));
+        }
+        if let Some(ip_filter) = &self.ip_filter {
+            app = filter_addresses(app, Arc::clone(ip_filter), IpEntryPoint::BrowserServer);
+        }
 
         let listener = tokio::net::TcpListener::bind(&addr).await
             .map_err(|e| BrowserSupportError::NetworkError {
@@ -87,7 +132,7 @@ impl WebServer {
 
         // Start the server
         tokio::spawn(async move {
-            axum::serve(listener, app)
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
                 .with_graceful_shutdown(async {
                     shutdown_rx.await.ok();
                 })
@@ -107,9 +152,34 @@ impl WebServer {
     }
 }
 
+/// Answer 403 to requests from addresses the filter blocks
+///
+/// The router has to be served with connect info so the middleware can see
+/// the remote address.
+pub fn filter_addresses(router: Router, ip_filter: Arc<IpFilter>, entry_point: IpEntryPoint) -> Router {
+    router.layer(middleware::from_fn(
+        move |ConnectInfo(remote): ConnectInfo<SocketAddr>, request: Request, next: Next| {
+            let ip_filter = Arc::clone(&ip_filter);
+            async move {
+                match ip_filter.check(remote.ip(), entry_point) {
+                    Ok(()) => next.run(request).await,
+                    Err(e) => {
+                        log::debug!("Refused {} request: {}", entry_point, e);
+                        StatusCode::FORBIDDEN.into_response()
+                    }
+                }
+            }
+        },
+    ))
+}
+
 /// Create the Axum router with all endpoints
 fn create_router(state: ServerState) -> Router {
     Router::new()
+        // Liveness and readiness probes
+        .route("/health/live", get(health_live))
+        .route("/health/ready", get(health_ready))
+
         // Discovery and connection setup endpoints
         .route("/api/setup/create", post(create_connection_setup))
         .route("/api/setup/:setup_id", get(get_connection_setup))
@@ -144,6 +214,24 @@ fn create_router(state: ServerState) -> Router {
         .with_state(state)
 }
 
+/// Liveness probe; answering at all means the server is up
+async fn health_live() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Readiness probe; 503 while a supervised component is restarting or has failed
+async fn health_ready(
+    State(state): State<ServerState>,
+) -> (StatusCode, Json<Value>) {
+    let Some(supervisor) = &state.supervisor else {
+        return (StatusCode::OK, Json(serde_json::json!({ "ready": true, "components": [] })));
+    };
+
+    let report = supervisor.health();
+    let status = if report.is_ready() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(serde_json::json!({ "ready": report.is_ready(), "components": report.components })))
+}
+
 /// Create a new connection setup
 async fn create_connection_setup(
     State(state): State<ServerState>,
@@ -13,7 +13,7 @@ use uuid::Uuid;
 use crate::browser_support::{BrowserResult, BrowserSupportError, BrowserSession};
 use crate::command_execution::{
     CommandExecution, CommandRequest, CommandResult, ExecutionStatus,
-    PeerId, RiskLevel,
+    HandoffRequest, HandoffResult, PeerId, RiskLevel,
 };
 use crate::browser_support::webrtc::data_channel::DataChannelManager;
 
@@ -165,6 +165,26 @@ impl BrowserCommandIntegration {
         Ok(request_id)
     }
 
+    /// Open a URL on a peer from the browser's "open on device" button
+    ///
+    /// Handoff carries no shell command, so any granted authorization level is
+    /// enough; the receiving peer still applies its own peer and scheme policy.
+    pub async fn open_url_on_peer(
+        &self,
+        browser_session: &BrowserSession,
+        url: String,
+        peer_address: &crate::transport::PeerAddress,
+    ) -> BrowserResult<HandoffResult> {
+        self.get_authorization(&browser_session.session_id.to_string()).await
+            .ok_or_else(|| BrowserSupportError::permission_denied("No command execution authorization"))?;
+
+        let request = HandoffRequest::url(url, browser_session.session_id.to_string());
+        self.command_system
+            .open_on_peer(request, peer_address)
+            .await
+            .map_err(|e| BrowserSupportError::integration("command_execution", format!("Failed to open URL: {}", e)))
+    }
+
     /// Validate command based on authorization level
     fn validate_command(&self, command: &str, auth_level: &RiskLevel) -> BrowserResult<()> {
         match auth_level {
@@ -0,0 +1,629 @@
+//! Public Drop-Box Mode
+//!
+//! A drop box is a receive-only endpoint reached through a capability URL
+//! (`/drop/<token>`), so someone without Kizuna can upload files from any
+//! browser. The token is the only credential: it grants nothing but uploading
+//! into that one box, and only its SHA-256 hash is kept, so a leaked listing
+//! of boxes cannot be turned back into working links. Unknown, revoked and
+//! expired tokens all answer 404.
+//!
+//! Uploads land in the file transfer quarantine and stay in the inbox until
+//! the owner accepts them, at which point the quarantine's scan hooks run
+//! before the file is moved to its destination. Each box has a lifetime and
+//! limits on file size, total size and number of uploads, enforced while the
+//! body streams in.
+
+use crate::file_transfer::sanitize::PathSanitizer;
+use crate::file_transfer::scan::{Quarantine, ScanReport};
+use crate::browser_support::{BrowserResult, BrowserSupportError};
+use crate::browser_support::api::server::filter_addresses;
+use crate::security::policy::{IpEntryPoint, IpFilter};
+use crate::util::format_size;
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Json, Response},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Route prefix drop boxes are served under
+pub const DROP_PATH: &str = "/drop";
+
+/// Limits for one drop box
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DropBoxConfig {
+    /// Name shown to uploaders, e.g. the device or owner
+    pub label: String,
+    /// Largest single upload in bytes
+    pub max_file_size: u64,
+    /// Most bytes the box accepts over its lifetime
+    pub max_total_size: u64,
+    /// Most files the box accepts; `None` for no limit
+    pub max_uploads: Option<usize>,
+    /// How long the link works after it is created
+    pub lifetime: Duration,
+}
+
+impl Default for DropBoxConfig {
+    fn default() -> Self {
+        Self {
+            label: "Kizuna".to_string(),
+            max_file_size: 1024 * 1024 * 1024,
+            max_total_size: 4 * 1024 * 1024 * 1024,
+            max_uploads: None,
+            lifetime: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+/// A newly created drop box and the capability that opens it
+///
+/// The token is shown once; only its hash is kept.
+#[derive(Debug, Clone)]
+pub struct DropBoxLink {
+    pub id: Uuid,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl DropBoxLink {
+    /// Path of the upload page
+    pub fn path(&self) -> String {
+        format!("{}/{}", DROP_PATH, self.token)
+    }
+
+    /// Full URL to share, given the server's public base URL
+    pub fn url(&self, base_url: &str) -> String {
+        format!("{}{}", base_url.trim_end_matches('/'), self.path())
+    }
+}
+
+/// State of a drop box, without its token
+#[derive(Debug, Clone, Serialize)]
+pub struct DropBoxInfo {
+    pub id: Uuid,
+    pub config: DropBoxConfig,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub uploads: usize,
+    pub bytes_received: u64,
+    pub revoked: bool,
+}
+
+impl DropBoxInfo {
+    /// Whether the link still accepts uploads
+    pub fn is_open(&self) -> bool {
+        !self.revoked && Utc::now() < self.expires_at
+    }
+}
+
+/// An upload waiting in the quarantine for the owner's decision
+#[derive(Debug, Clone, Serialize)]
+pub struct InboxItem {
+    pub id: Uuid,
+    pub drop_box: Uuid,
+    /// Sanitized name the uploader gave the file
+    pub file_name: String,
+    pub size: u64,
+    pub received_at: DateTime<Utc>,
+    /// Address the upload came from, when known
+    pub uploader: Option<SocketAddr>,
+    /// Quarantined copy of the file
+    pub path: PathBuf,
+}
+
+/// Why an upload or lookup was refused
+#[derive(Debug, thiserror::Error)]
+pub enum DropBoxError {
+    /// Unknown, revoked or expired token; deliberately indistinguishable
+    #[error("no such drop box")]
+    NotFound,
+    #[error("file is larger than the {limit} bytes this drop box accepts")]
+    FileTooLarge { limit: u64 },
+    #[error("drop box is full")]
+    Full,
+    #[error("invalid upload: {0}")]
+    Invalid(String),
+    #[error("upload failed: {0}")]
+    Io(String),
+}
+
+impl DropBoxError {
+    /// HTTP status the error is answered with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            DropBoxError::NotFound => StatusCode::NOT_FOUND,
+            DropBoxError::FileTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            DropBoxError::Full => StatusCode::FORBIDDEN,
+            DropBoxError::Invalid(_) => StatusCode::BAD_REQUEST,
+            DropBoxError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for DropBoxError {
+    fn into_response(self) -> Response {
+        (self.status(), self.to_string()).into_response()
+    }
+}
+
+/// Drop box with its running totals
+struct DropBoxState {
+    info: DropBoxInfo,
+    /// Uploads started, including ones still streaming
+    reserved_uploads: usize,
+}
+
+/// Creates drop boxes, receives their uploads and holds the inbox
+pub struct DropBoxManager {
+    quarantine: Quarantine,
+    /// Keyed by the hex SHA-256 of the token
+    boxes: RwLock<HashMap<String, DropBoxState>>,
+    inbox: RwLock<HashMap<Uuid, InboxItem>>,
+    sanitizer: PathSanitizer,
+}
+
+impl DropBoxManager {
+    /// Receive uploads into `quarantine`
+    pub fn new(quarantine: Quarantine) -> Self {
+        Self {
+            quarantine,
+            boxes: RwLock::new(HashMap::new()),
+            inbox: RwLock::new(HashMap::new()),
+            sanitizer: PathSanitizer::new(),
+        }
+    }
+
+    /// Open a new drop box and return its capability link
+    pub async fn create(&self, config: DropBoxConfig) -> BrowserResult<DropBoxLink> {
+        let lifetime = chrono::Duration::from_std(config.lifetime).map_err(|_| {
+            BrowserSupportError::ConfigurationError {
+                parameter: "lifetime".to_string(),
+                issue: "drop box lifetime is too long".to_string(),
+            }
+        })?;
+        if config.max_file_size == 0 || config.max_total_size == 0 {
+            return Err(BrowserSupportError::ConfigurationError {
+                parameter: "max_file_size".to_string(),
+                issue: "drop box size limits must be above zero".to_string(),
+            });
+        }
+
+        let token = new_token();
+        let created_at = Utc::now();
+        let info = DropBoxInfo {
+            id: Uuid::new_v4(),
+            config,
+            created_at,
+            expires_at: created_at + lifetime,
+            uploads: 0,
+            bytes_received: 0,
+            revoked: false,
+        };
+        let link = DropBoxLink {
+            id: info.id,
+            token: token.clone(),
+            expires_at: info.expires_at,
+        };
+
+        self.boxes.write().await.insert(
+            token_hash(&token),
+            DropBoxState {
+                info,
+                reserved_uploads: 0,
+            },
+        );
+        Ok(link)
+    }
+
+    /// Close a drop box; its link stops working but its uploads stay in the inbox
+    pub async fn revoke(&self, id: Uuid) -> BrowserResult<()> {
+        let mut boxes = self.boxes.write().await;
+        let state = boxes
+            .values_mut()
+            .find(|state| state.info.id == id)
+            .ok_or_else(|| BrowserSupportError::SessionNotFound(id.to_string()))?;
+        state.info.revoked = true;
+        Ok(())
+    }
+
+    /// Every drop box, open or not
+    pub async fn list(&self) -> Vec<DropBoxInfo> {
+        let mut boxes: Vec<DropBoxInfo> = self.boxes.read().await.values().map(|state| state.info.clone()).collect();
+        boxes.sort_by_key(|info| info.created_at);
+        boxes
+    }
+
+    /// Forget drop boxes that have expired or been revoked
+    pub async fn purge_closed(&self) -> usize {
+        let mut boxes = self.boxes.write().await;
+        let before = boxes.len();
+        boxes.retain(|_, state| state.info.is_open() || state.reserved_uploads > state.info.uploads);
+        before - boxes.len()
+    }
+
+    /// The open drop box a token opens
+    pub async fn lookup(&self, token: &str) -> Result<DropBoxInfo, DropBoxError> {
+        self.boxes
+            .read()
+            .await
+            .get(&token_hash(token))
+            .map(|state| state.info.clone())
+            .filter(DropBoxInfo::is_open)
+            .ok_or(DropBoxError::NotFound)
+    }
+
+    /// Stream one upload into the quarantine
+    ///
+    /// `declared_size` is the request's Content-Length, used to refuse
+    /// oversized uploads before reading them; the limits are enforced on
+    /// the bytes actually received either way.
+    pub async fn receive<S, E>(
+        &self,
+        token: &str,
+        file_name: &str,
+        declared_size: Option<u64>,
+        uploader: Option<SocketAddr>,
+        mut body: S,
+    ) -> Result<InboxItem, DropBoxError>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let hash = token_hash(token);
+        let file_name = self.clean_name(file_name)?;
+        let (box_id, file_limit) = self.reserve(&hash, declared_size).await?;
+
+        let id = Uuid::new_v4();
+        let path = self.quarantine.quarantine_path(id, &file_name);
+        let mut size = 0u64;
+        let result = async {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await.map_err(|e| DropBoxError::Io(e.to_string()))?;
+            }
+            let mut file = tokio::fs::File::create(&path).await.map_err(|e| DropBoxError::Io(e.to_string()))?;
+
+            while let Some(data) = body.next().await {
+                let data = data.map_err(|e| DropBoxError::Io(e.to_string()))?;
+                if size + data.len() as u64 > file_limit {
+                    return Err(DropBoxError::FileTooLarge { limit: file_limit });
+                }
+                self.charge(&hash, data.len() as u64).await?;
+                size += data.len() as u64;
+                file.write_all(&data).await.map_err(|e| DropBoxError::Io(e.to_string()))?;
+            }
+            file.flush().await.map_err(|e| DropBoxError::Io(e.to_string()))
+        }
+        .await;
+
+        if let Err(e) = result {
+            self.release_reservation(&hash, size).await;
+            let _ = tokio::fs::remove_dir_all(path.parent().unwrap_or(&path)).await;
+            return Err(e);
+        }
+
+        if let Some(state) = self.boxes.write().await.get_mut(&hash) {
+            state.info.uploads += 1;
+        }
+        let item = InboxItem {
+            id,
+            drop_box: box_id,
+            file_name,
+            size,
+            received_at: Utc::now(),
+            uploader,
+            path,
+        };
+        self.inbox.write().await.insert(id, item.clone());
+        log::info!("Drop box {} received {} ({} bytes)", box_id, item.file_name, size);
+        Ok(item)
+    }
+
+    /// Uploads waiting for a decision, oldest first
+    pub async fn inbox(&self) -> Vec<InboxItem> {
+        let mut items: Vec<InboxItem> = self.inbox.read().await.values().cloned().collect();
+        items.sort_by_key(|item| item.received_at);
+        items
+    }
+
+    /// Scan an upload and move it into `destination_dir`
+    ///
+    /// Follows the quarantine's scan policy, so a file can also be held or
+    /// rejected; it leaves the inbox unless it is held.
+    pub async fn accept(&self, item_id: Uuid, destination_dir: &FsPath) -> BrowserResult<ScanReport> {
+        let item = self
+            .inbox
+            .read()
+            .await
+            .get(&item_id)
+            .cloned()
+            .ok_or_else(|| BrowserSupportError::SessionNotFound(item_id.to_string()))?;
+
+        let destination = self
+            .sanitizer
+            .resolve(destination_dir, FsPath::new(&item.file_name))
+            .map_err(|e| BrowserSupportError::integration("file_transfer", e.to_string()))?;
+        let released = self.quarantine.release(item.id, &item.path, &destination.path).await;
+
+        match &released {
+            Ok(report) if report.path.as_deref() == Some(item.path.as_path()) => {}
+            _ => {
+                self.inbox.write().await.remove(&item_id);
+                if let Some(dir) = item.path.parent() {
+                    let _ = tokio::fs::remove_dir(dir).await;
+                }
+            }
+        }
+        released.map_err(|e| BrowserSupportError::integration("file_transfer", e.to_string()))
+    }
+
+    /// Delete an upload without looking at it
+    pub async fn discard(&self, item_id: Uuid) -> BrowserResult<()> {
+        let item = self
+            .inbox
+            .write()
+            .await
+            .remove(&item_id)
+            .ok_or_else(|| BrowserSupportError::SessionNotFound(item_id.to_string()))?;
+        if let Some(dir) = item.path.parent() {
+            let _ = tokio::fs::remove_dir_all(dir).await;
+        }
+        Ok(())
+    }
+
+    /// Count an upload against its box before any bytes are read
+    async fn reserve(&self, hash: &str, declared_size: Option<u64>) -> Result<(Uuid, u64), DropBoxError> {
+        let mut boxes = self.boxes.write().await;
+        let state = boxes
+            .get_mut(hash)
+            .filter(|state| state.info.is_open())
+            .ok_or(DropBoxError::NotFound)?;
+
+        let config = &state.info.config;
+        if config.max_uploads.is_some_and(|max| state.reserved_uploads >= max) {
+            return Err(DropBoxError::Full);
+        }
+        let remaining = config.max_total_size.saturating_sub(state.info.bytes_received);
+        if remaining == 0 {
+            return Err(DropBoxError::Full);
+        }
+        let file_limit = config.max_file_size;
+        if declared_size.is_some_and(|size| size > file_limit) {
+            return Err(DropBoxError::FileTooLarge { limit: file_limit });
+        }
+        if declared_size.is_some_and(|size| size > remaining) {
+            return Err(DropBoxError::Full);
+        }
+
+        state.reserved_uploads += 1;
+        Ok((state.info.id, file_limit))
+    }
+
+    /// Add received bytes to a box's total, refusing them once it is full
+    ///
+    /// Also stops uploads in flight when the box expires or is revoked.
+    async fn charge(&self, hash: &str, bytes: u64) -> Result<(), DropBoxError> {
+        let mut boxes = self.boxes.write().await;
+        let state = boxes
+            .get_mut(hash)
+            .filter(|state| state.info.is_open())
+            .ok_or(DropBoxError::NotFound)?;
+        if state.info.bytes_received + bytes > state.info.config.max_total_size {
+            return Err(DropBoxError::Full);
+        }
+        state.info.bytes_received += bytes;
+        Ok(())
+    }
+
+    /// Undo the reservation of an upload that failed, refunding its bytes
+    async fn release_reservation(&self, hash: &str, bytes: u64) {
+        if let Some(state) = self.boxes.write().await.get_mut(hash) {
+            state.reserved_uploads = state.reserved_uploads.saturating_sub(1);
+            state.info.bytes_received = state.info.bytes_received.saturating_sub(bytes);
+        }
+    }
+
+    /// Last component of the uploader's file name, made safe for this platform
+    fn clean_name(&self, file_name: &str) -> Result<String, DropBoxError> {
+        let base = file_name.rsplit(['/', '\\']).next().unwrap_or("").trim();
+        if base.is_empty() {
+            return Err(DropBoxError::Invalid("missing file name".to_string()));
+        }
+        self.sanitizer
+            .sanitize_component(base)
+            .map_err(|e| DropBoxError::Invalid(e.to_string()))
+    }
+}
+
+/// Random capability token, 256 bits as hex
+fn new_token() -> String {
+    use rand::RngCore;
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Query of an upload request
+#[derive(Debug, Deserialize)]
+struct UploadQuery {
+    name: String,
+}
+
+/// Routes for the drop-box upload pages, and nothing else
+///
+/// Mounted on the browser server, and safe to serve on its own publicly
+/// reachable listener with `serve`.
+pub fn router(manager: Arc<DropBoxManager>) -> Router {
+    Router::new()
+        .route(&format!("{}/:token", DROP_PATH), get(upload_page).post(upload))
+        .with_state(manager)
+}
+
+/// Serve only the drop-box routes on `addr`, e.g. a public interface
+///
+/// Requests from addresses `ip_filter` blocks are refused.
+pub async fn serve(
+    manager: Arc<DropBoxManager>,
+    addr: SocketAddr,
+    ip_filter: Option<Arc<IpFilter>>,
+) -> BrowserResult<tokio::task::JoinHandle<()>> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| BrowserSupportError::NetworkError {
+            details: format!("Failed to bind drop box listener to {}: {}", addr, e),
+        })?;
+    let mut app = router(manager);
+    if let Some(ip_filter) = ip_filter {
+        app = filter_addresses(app, ip_filter, IpEntryPoint::DropBox);
+    }
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+
+    Ok(tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            log::warn!("Drop box listener stopped: {}", e);
+        }
+    }))
+}
+
+/// Upload page for a drop box
+async fn upload_page(
+    State(manager): State<Arc<DropBoxManager>>,
+    Path(token): Path<String>,
+) -> Result<Html<String>, DropBoxError> {
+    let info = manager.lookup(&token).await?;
+    let page = include_str!("static/drop.html")
+        .replace("{{label}}", &escape_html(&info.config.label))
+        .replace("{{max_file_size}}", &format_size(info.config.max_file_size))
+        .replace("{{expires_at}}", &info.expires_at.format("%Y-%m-%d %H:%M UTC").to_string());
+    Ok(Html(page))
+}
+
+/// Receive one file into the drop box's quarantine
+async fn upload(
+    State(manager): State<Arc<DropBoxManager>>,
+    Path(token): Path<String>,
+    Query(query): Query<UploadQuery>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: axum::http::HeaderMap,
+    body: Body,
+) -> Result<(StatusCode, Json<serde_json::Value>), DropBoxError> {
+    let declared_size = headers
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    let uploader = connect_info.map(|ConnectInfo(addr)| addr);
+
+    let item = manager
+        .receive(&token, &query.name, declared_size, uploader, body.into_data_stream())
+        .await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({ "received": item.file_name, "size": item.size })),
+    ))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn body(parts: &[&'static [u8]]) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Unpin {
+        futures::stream::iter(parts.iter().map(|part| Ok(Bytes::from_static(part))).collect::<Vec<_>>())
+    }
+
+    fn manager(dir: &TempDir) -> DropBoxManager {
+        DropBoxManager::new(Quarantine::new(dir.path().join("quarantine")))
+    }
+
+    #[tokio::test]
+    async fn test_upload_lands_in_quarantined_inbox() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager(&dir);
+        let link = manager.create(DropBoxConfig::default()).await.unwrap();
+        assert!(link.url("https://example.net/").ends_with(&format!("/drop/{}", link.token)));
+
+        // The uploader's path is reduced to a plain file name
+        let item = manager
+            .receive(&link.token, "../../etc/report.pdf", Some(11), None, body(&[b"hello ", b"world"]))
+            .await
+            .unwrap();
+        assert_eq!(item.file_name, "report.pdf");
+        assert!(item.path.starts_with(dir.path().join("quarantine")));
+        assert_eq!(std::fs::read(&item.path).unwrap(), b"hello world");
+
+        // Nothing reaches the destination until the owner accepts it
+        let downloads = dir.path().join("downloads");
+        assert_eq!(manager.inbox().await.len(), 1);
+        let report = manager.accept(item.id, &downloads).await.unwrap();
+        assert_eq!(report.path, Some(downloads.join("report.pdf")));
+        assert!(manager.inbox().await.is_empty());
+        assert_eq!(manager.list().await[0].uploads, 1);
+    }
+
+    #[tokio::test]
+    async fn test_limits_and_bad_tokens() {
+        let dir = TempDir::new().unwrap();
+        let manager = manager(&dir);
+        let config = DropBoxConfig {
+            max_file_size: 8,
+            max_total_size: 12,
+            max_uploads: Some(2),
+            ..Default::default()
+        };
+        let link = manager.create(config).await.unwrap();
+
+        assert!(matches!(
+            manager.receive("not-a-token", "a.txt", None, None, body(&[b"x"])).await,
+            Err(DropBoxError::NotFound)
+        ));
+        // Oversized uploads are refused whether or not they declare a length
+        assert!(matches!(
+            manager.receive(&link.token, "a.txt", Some(9), None, body(&[b"123456789"])).await,
+            Err(DropBoxError::FileTooLarge { limit: 8 })
+        ));
+        assert!(matches!(
+            manager.receive(&link.token, "a.txt", None, None, body(&[b"12345", b"6789"])).await,
+            Err(DropBoxError::FileTooLarge { .. })
+        ));
+        assert!(manager.inbox().await.is_empty());
+
+        // Failed uploads are not counted against the total
+        manager.receive(&link.token, "a.txt", None, None, body(&[b"12345678"])).await.unwrap();
+        assert!(matches!(
+            manager.receive(&link.token, "b.txt", None, None, body(&[b"12345"])).await,
+            Err(DropBoxError::Full)
+        ));
+
+        // A revoked box looks like one that never existed
+        manager.revoke(link.id).await.unwrap();
+        assert!(matches!(manager.lookup(&link.token).await, Err(DropBoxError::NotFound)));
+        assert_eq!(manager.purge_closed().await, 1);
+        assert_eq!(manager.inbox().await.len(), 1);
+    }
+}
@@ -12,7 +12,7 @@ use std::collections::HashMap;
 use crate::browser_support::{BrowserResult, BrowserSupportError, BrowserSession};
 use crate::file_transfer::{
     FileTransfer, FileTransferSystem, TransferManifest, TransferSession,
-    TransferProgress, PeerId, SessionId, ResumeToken, FileEntry,
+    TransferProgress, PeerId, SessionId, ResumeToken, FileEntry, HashAlgorithm, MetadataPreservation,
 };
 use crate::browser_support::webrtc::data_channel::DataChannelManager;
 
@@ -94,8 +94,16 @@ impl BrowserFileTransferIntegration {
                     .unwrap()
                     .as_secs(),
                 chunk_count: ((file_size + 65535) / 65536) as usize,
+                extended: None,
             }],
             directories: vec![],
+            hash_algorithm: HashAlgorithm::default(),
+            symlinks: Vec::new(),
+            preservation: MetadataPreservation::default(),
+            sender_platform: None,
+            swarm: None,
+            previews: Vec::new(),
+            pipe: None,
             checksum: [0u8; 32],
         };
 
@@ -171,8 +179,16 @@ impl BrowserFileTransferIntegration {
                     .unwrap()
                     .as_secs(),
                 chunk_count: 0,
+                extended: None,
             }],
             directories: vec![],
+            hash_algorithm: HashAlgorithm::default(),
+            symlinks: Vec::new(),
+            preservation: MetadataPreservation::default(),
+            sender_platform: None,
+            swarm: None,
+            previews: Vec::new(),
+            pipe: None,
             checksum: [0u8; 32],
         };
 
@@ -428,6 +444,13 @@ mod tests {
             file_count: 0,
             files: vec![],
             directories: vec![],
+            hash_algorithm: HashAlgorithm::default(),
+            symlinks: Vec::new(),
+            preservation: MetadataPreservation::default(),
+            sender_platform: None,
+            swarm: None,
+            previews: Vec::new(),
+            pipe: None,
             checksum: [0u8; 32],
         };
 
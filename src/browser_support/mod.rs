@@ -21,6 +21,7 @@ pub mod clipboard_integration;
 #[cfg(feature = "streaming")]
 pub mod streaming_integration;
 pub mod command_integration;
+pub mod dropbox;
 
 #[cfg(test)]
 mod tests;
@@ -39,8 +40,9 @@ pub use clipboard_integration::{BrowserClipboardIntegration, BrowserClipboard};
 #[cfg(feature = "streaming")]
 pub use streaming_integration::{BrowserStreamingIntegration, BrowserStreaming};
 pub use command_integration::{BrowserCommandIntegration, BrowserCommand};
+pub use dropbox::{DropBoxConfig, DropBoxError, DropBoxInfo, DropBoxLink, DropBoxManager, InboxItem};
 
-use crate::Result;
+use crate::{KizunaError, Result};
 use std::sync::Arc;
 
 /// Main browser support system interface
@@ -89,8 +91,7 @@ impl BrowserSupport {
 #[async_trait::async_trait]
 impl BrowserSupportSystem for BrowserSupport {
     async fn initialize(&mut self) -> Result<()> {
-        self.communication_manager.write().await.initialize().await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to initialize communication manager: {:?}", e))) as Box<dyn std::error::Error + Send + Sync>)?;
+        self.communication_manager.write().await.initialize().await?;
         self.api_server.write().await.initialize().await?;
         self.pwa_controller.initialize().await?;
         Ok(())
@@ -99,26 +100,22 @@ impl BrowserSupportSystem for BrowserSupport {
     async fn start_web_server(&self, port: u16) -> Result<()> {
         // Initialize discovery manager with server address
         let addr = format!("127.0.0.1:{}", port).parse()
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid address: {}", e))) as Box<dyn std::error::Error + Send + Sync>)?;
+            .map_err(|e| KizunaError::invalid_input(format!("Invalid address: {}", e)))?;
         
         // Initialize the discovery manager
-        self.discovery_manager.initialize(addr).await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to initialize discovery: {:?}", e))) as Box<dyn std::error::Error + Send + Sync>)?;
+        self.discovery_manager.initialize(addr).await?;
         
-        self.api_server.write().await.start(port).await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to start API server: {:?}", e))) as Box<dyn std::error::Error + Send + Sync>)
+        Ok(self.api_server.write().await.start(port).await?)
     }
     
     async fn handle_browser_connection(&self, connection_info: BrowserConnectionInfo) -> Result<BrowserSession> {
-        self.communication_manager.write().await.establish_connection(connection_info).await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to establish browser connection: {:?}", e))) as Box<dyn std::error::Error + Send + Sync>)
+        Ok(self.communication_manager.write().await.establish_connection(connection_info).await?)
     }
     
     async fn shutdown(&mut self) -> Result<()> {
         self.pwa_controller.shutdown().await?;
         self.api_server.write().await.shutdown().await?;
-        self.communication_manager.write().await.shutdown().await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to shutdown communication manager: {:?}", e))) as Box<dyn std::error::Error + Send + Sync>)?;
+        self.communication_manager.write().await.shutdown().await?;
         Ok(())
     }
 }
\ No newline at end of file
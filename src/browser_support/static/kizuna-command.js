@@ -89,6 +89,33 @@
             }
         }
 
+        /**
+         * Open a URL on a peer in its default browser
+         * @param {string} url - Absolute http or https URL
+         * @param {string} peerId - Target peer ID
+         * @returns {Promise<string>} Request ID
+         */
+        async openOnPeer(url, peerId) {
+            const parsed = new URL(url);
+            if (parsed.protocol !== 'http:' && parsed.protocol !== 'https:') {
+                throw new Error(`Only http and https URLs can be opened on a peer, got ${parsed.protocol}`);
+            }
+
+            const requestId = this.sdk._generateUUID();
+            await this.sdk.sendMessage({
+                message_type: 'CommandExecution',
+                payload: {
+                    command_id: requestId,
+                    action: 'open_url',
+                    url: parsed.href,
+                    peer_id: peerId
+                }
+            }, 'command');
+
+            this.sdk._emit('handoffRequested', { requestId, url: parsed.href, peerId });
+            return requestId;
+        }
+
         /**
          * Handle command messages
          * @private
@@ -722,12 +749,92 @@
         }
     }
 
+    /**
+     * Open On Device Button
+     *
+     * Button that sends a URL to a peer's default browser. Without an explicit
+     * URL it sends the page shared to the PWA, or else the current page.
+     */
+    class HandoffButton {
+        constructor(commandManager, container, options = {}) {
+            this.commandManager = commandManager;
+            this.container = container;
+            this.peerId = options.peerId || null;
+            this.url = options.url || null;
+
+            this.button = document.createElement('button');
+            this.button.type = 'button';
+            this.button.className = 'kizuna-button kizuna-handoff-button';
+            this.button.textContent = options.label || 'Open on device';
+            this.button.addEventListener('click', () => this.send());
+            this.container.appendChild(this.button);
+            this._updateState();
+        }
+
+        /**
+         * Set target peer ID
+         * @param {string} peerId - Peer ID
+         */
+        setPeerId(peerId) {
+            this.peerId = peerId;
+            this._updateState();
+        }
+
+        /**
+         * Set the URL to send
+         * @param {string} url - URL
+         */
+        setUrl(url) {
+            this.url = url;
+        }
+
+        /**
+         * URL the button will send
+         * @returns {string} URL
+         */
+        targetUrl() {
+            if (this.url) {
+                return this.url;
+            }
+            // Share target launches carry the shared link in the query string
+            const params = new URLSearchParams(global.location.search);
+            return params.get('url') || params.get('text') || global.location.href;
+        }
+
+        /**
+         * Send the URL to the peer
+         */
+        async send() {
+            if (!this.peerId) {
+                return;
+            }
+            this.button.disabled = true;
+            try {
+                await this.commandManager.openOnPeer(this.targetUrl(), this.peerId);
+                this.button.classList.add('kizuna-handoff-sent');
+            } catch (error) {
+                this.commandManager.sdk._error('Failed to open URL on peer:', error);
+                this.button.classList.add('kizuna-handoff-failed');
+            } finally {
+                this._updateState();
+            }
+        }
+
+        /**
+         * @private
+         */
+        _updateState() {
+            this.button.disabled = !this.peerId;
+        }
+    }
+
     // Export
     if (typeof module !== 'undefined' && module.exports) {
-        module.exports = { CommandExecutionManager, TerminalInterface };
+        module.exports = { CommandExecutionManager, TerminalInterface, HandoffButton };
     } else {
         global.KizunaCommand = CommandExecutionManager;
         global.KizunaTerminal = TerminalInterface;
+        global.KizunaHandoffButton = HandoffButton;
     }
 
 })(typeof window !== 'undefined' ? window : this);
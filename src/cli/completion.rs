@@ -142,6 +142,13 @@ fn build_cli_app() -> Command {
         .about("Seamless device connectivity and file sharing")
         .arg_required_else_help(true)
         .subcommand_required(true)
+        .arg(
+            Arg::new("non-interactive")
+                .long("non-interactive")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Never prompt; fail when an answer is needed")
+        )
         .subcommand(
             Command::new("discover")
                 .about("Discover available peers on the network")
@@ -189,7 +196,7 @@ fn build_cli_app() -> Command {
                         .value_name("FILES")
                         .required(true)
                         .num_args(1..)
-                        .help("Files to send")
+                        .help("Files to send, or - for stdin")
                 )
                 .arg(
                     Arg::new("peer")
@@ -198,12 +205,48 @@ fn build_cli_app() -> Command {
                         .value_name("PEER")
                         .help("Target peer name or ID")
                 )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("PEERS")
+                        .help("Send to several peers at once, comma-separated")
+                )
+                .arg(
+                    Arg::new("swarm")
+                        .long("swarm")
+                        .action(ArgAction::SetTrue)
+                        .help("Let receivers on the same LAN share chunks")
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .help("File name for data sent from stdin")
+                )
                 .arg(
                     Arg::new("no-compression")
                         .long("no-compression")
                         .action(ArgAction::SetTrue)
                         .help("Disable compression")
                 )
+                .arg(
+                    Arg::new("allow-metered")
+                        .long("allow-metered")
+                        .action(ArgAction::SetTrue)
+                        .help("Send large files even on a metered connection")
+                )
+                .arg(
+                    Arg::new("no-notify")
+                        .long("no-notify")
+                        .action(ArgAction::SetTrue)
+                        .help("Don't notify when the transfer finishes")
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Show what would be sent without sending")
+                )
         )
         .subcommand(
             Command::new("receive")
@@ -222,6 +265,18 @@ fn build_cli_app() -> Command {
                         .action(ArgAction::SetTrue)
                         .help("Auto-accept from trusted peers")
                 )
+                .arg(
+                    Arg::new("to-stdout")
+                        .long("to-stdout")
+                        .action(ArgAction::SetTrue)
+                        .help("Write the received data to stdout")
+                )
+                .arg(
+                    Arg::new("no-notify")
+                        .long("no-notify")
+                        .action(ArgAction::SetTrue)
+                        .help("Don't notify when the transfer finishes")
+                )
         )
         .subcommand(
             Command::new("stream")
@@ -237,6 +292,12 @@ fn build_cli_app() -> Command {
                                 .value_parser(["low", "medium", "high", "ultra"])
                                 .help("Stream quality")
                         )
+                        .arg(
+                            Arg::new("no-notify")
+                                .long("no-notify")
+                                .action(ArgAction::SetTrue)
+                                .help("Don't notify when the stream ends")
+                        )
                 )
         )
         .subcommand(
@@ -296,6 +357,332 @@ fn build_cli_app() -> Command {
                 .subcommand(Command::new("status").about("Show clipboard status"))
                 .subcommand(Command::new("history").about("View clipboard history"))
         )
+        .subcommand(
+            Command::new("msg")
+                .about("Send a text message to a peer")
+                .arg(Arg::new("peer").required(true).help("Target peer"))
+                .arg(Arg::new("text").help("Message text"))
+                .arg(
+                    Arg::new("history")
+                        .long("history")
+                        .action(ArgAction::SetTrue)
+                        .help("Show the conversation")
+                )
+        )
+        .subcommand(
+            Command::new("open-on")
+                .about("Open a URL on a peer")
+                .arg(Arg::new("peer").required(true).help("Target peer"))
+                .arg(Arg::new("url").required(true).help("URL to open"))
+        )
+        .subcommand(
+            Command::new("locate")
+                .about("Ring a peer to find it")
+                .arg(Arg::new("peer").required(true).help("Peer to ring"))
+                .arg(
+                    Arg::new("message")
+                        .short('m')
+                        .long("message")
+                        .help("Text to show in the notification")
+                )
+                .arg(
+                    Arg::new("stop")
+                        .long("stop")
+                        .action(ArgAction::SetTrue)
+                        .help("Stop the peer ringing")
+                )
+        )
+        .subcommand(
+            Command::new("get")
+                .about("Fetch a file from a peer's share")
+                .arg(Arg::new("remote").required(true).help("File as PEER:SHARE/PATH"))
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("Directory to save the file in")
+                )
+        )
+        .subcommand(
+            Command::new("security")
+                .about("Check this device's security")
+                .subcommand(
+                    Command::new("check")
+                        .about("Print a scored hardening report")
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .action(ArgAction::SetTrue)
+                                .help("Output the report as JSON")
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("transport")
+                .about("Diagnose network transports")
+                .subcommand(
+                    Command::new("diagnose")
+                        .about("Detect the NAT type and connection prospects")
+                        .arg(
+                            Arg::new("refresh")
+                                .long("refresh")
+                                .action(ArgAction::SetTrue)
+                                .help("Run detection again instead of using the cached result")
+                        )
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .action(ArgAction::SetTrue)
+                                .help("Output the report as JSON")
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Diagnose connectivity problems")
+                .arg(
+                    Arg::new("peer")
+                        .short('p')
+                        .long("peer")
+                        .help("Also ping this peer")
+                )
+                .arg(
+                    Arg::new("proxy")
+                        .long("proxy")
+                        .help("Check connectivity through this proxy")
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Output the report as JSON")
+                )
+        )
+        .subcommand(
+            Command::new("bench")
+                .about("Benchmark the link to a peer")
+                .arg(
+                    Arg::new("peer")
+                        .required(true)
+                        .help("Peer to benchmark")
+                )
+                .arg(
+                    Arg::new("duration")
+                        .short('d')
+                        .long("duration")
+                        .help("Throughput test length in seconds")
+                )
+                .arg(
+                    Arg::new("payload-size")
+                        .long("payload-size")
+                        .help("Throughput test message size in bytes")
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Output the results as JSON")
+                )
+        )
+        .subcommand(
+            Command::new("ping")
+                .about("Check that a peer is alive")
+                .arg(
+                    Arg::new("peer")
+                        .required(true)
+                        .help("Peer to ping")
+                )
+                .arg(
+                    Arg::new("timeout")
+                        .short('t')
+                        .long("timeout")
+                        .help("Seconds to wait for a reply")
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Output the result as JSON")
+                )
+        )
+        .subcommand(
+            Command::new("wipe")
+                .about("Securely erase Kizuna data")
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .action(ArgAction::SetTrue)
+                        .help("Erase all Kizuna data on this device")
+                )
+                .arg(
+                    Arg::new("remote")
+                        .long("remote")
+                        .help("Ask one of your devices to erase itself")
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Skip the confirmation prompt")
+                )
+        )
+        .subcommand(
+            Command::new("recordings")
+                .about("Manage stream recordings")
+                .subcommand(
+                    Command::new("list")
+                        .about("List and search recordings")
+                        .arg(Arg::new("since").long("since").help("Only recordings made since then"))
+                        .arg(Arg::new("until").long("until").help("Only recordings made before then"))
+                        .arg(Arg::new("peer").long("peer").help("Only recordings made with this peer"))
+                        .arg(Arg::new("min-length").long("min-length").help("Only recordings at least this long"))
+                        .arg(Arg::new("max-length").long("max-length").help("Only recordings at most this long"))
+                        .arg(
+                            Arg::new("tag")
+                                .short('t')
+                                .long("tag")
+                                .action(ArgAction::Append)
+                                .help("Only recordings with this tag")
+                        )
+                        .arg(Arg::new("limit").short('n').long("limit").help("Show at most this many recordings"))
+                        .arg(Arg::new("json").long("json").action(ArgAction::SetTrue).help("Output as JSON"))
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Show a recording's details")
+                        .arg(Arg::new("id").required(true))
+                        .arg(Arg::new("json").long("json").action(ArgAction::SetTrue).help("Output as JSON"))
+                )
+                .subcommand(
+                    Command::new("tag")
+                        .about("Tag and annotate a recording")
+                        .arg(Arg::new("id").required(true))
+                        .arg(Arg::new("add").short('a').long("add").action(ArgAction::Append).help("Tags to add"))
+                        .arg(
+                            Arg::new("remove")
+                                .short('r')
+                                .long("remove")
+                                .action(ArgAction::Append)
+                                .help("Tags to remove")
+                        )
+                        .arg(Arg::new("note").long("note").help("Replace the recording's notes"))
+                        .arg(
+                            Arg::new("clear-note")
+                                .long("clear-note")
+                                .action(ArgAction::SetTrue)
+                                .help("Remove the recording's notes")
+                        )
+                        .arg(Arg::new("json").long("json").action(ArgAction::SetTrue).help("Output as JSON"))
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("Convert a recording to another format")
+                        .arg(Arg::new("id").required(true))
+                        .arg(
+                            Arg::new("format")
+                                .short('f')
+                                .long("format")
+                                .value_parser(["mp4", "webm", "avi", "mov"])
+                                .help("Container to export to")
+                        )
+                        .arg(
+                            Arg::new("crf")
+                                .long("crf")
+                                .help("Constant quality, 0-51")
+                        )
+                        .arg(
+                            Arg::new("bitrate")
+                                .long("bitrate")
+                                .help("Target video bitrate in kbps")
+                        )
+                        .arg(
+                            Arg::new("start")
+                                .long("start")
+                                .help("Cut everything before this point")
+                        )
+                        .arg(
+                            Arg::new("end")
+                                .long("end")
+                                .help("Cut everything after this point")
+                        )
+                        .arg(
+                            Arg::new("thumbnail")
+                                .long("thumbnail")
+                                .action(ArgAction::SetTrue)
+                                .help("Also save a JPEG thumbnail")
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE")
+                                .help("Where to write the export")
+                        )
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .action(ArgAction::SetTrue)
+                                .help("Output the result as JSON")
+                        )
+                )
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export statistics and history as CSV or JSON")
+                .arg(
+                    Arg::new("data")
+                        .required(true)
+                        .value_parser(["discovery", "transfers", "bandwidth", "audit"])
+                )
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_parser(["csv", "json"])
+                        .help("File format")
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .help("Only records from this date on (YYYY-MM-DD)")
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .help("Only records up to this date (YYYY-MM-DD)")
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write to a file instead of standard output")
+                )
+                .arg(
+                    Arg::new("audit-log")
+                        .long("audit-log")
+                        .value_name("FILE")
+                        .help("Audit log to read for audit exports")
+                )
+        )
+        .subcommand(
+            Command::new("self-update")
+                .about("Update kizuna to the latest release")
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .action(ArgAction::SetTrue)
+                        .help("Only show whether an update is available and what changed")
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Install without asking for confirmation")
+                )
+        )
         .subcommand(Command::new("tui").about("Launch interactive TUI"))
         .subcommand(
             Command::new("config")
@@ -0,0 +1,131 @@
+// Config schema versions and the migrations between them
+//
+// Every config file records the schema version it was written with. Files
+// from before versioning count as version 1. On load, registered migrations
+// run one version step at a time on the raw TOML until the file matches the
+// current schema, so older files keep working after fields move or change.
+
+use crate::cli::error::{CLIError, CLIResult};
+use std::collections::BTreeMap;
+use toml::Table;
+
+/// Schema version written by this build
+pub const CONFIG_SCHEMA_VERSION: u32 = 2;
+
+/// Top-level key holding a config file's schema version
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Version assumed for files written before the schema was versioned
+const UNVERSIONED_SCHEMA: u32 = 1;
+
+/// Rewrites a config table from one schema version to the next
+pub type MigrationFn = fn(&mut Table) -> CLIResult<()>;
+
+/// One registered version step
+#[derive(Clone)]
+pub struct ConfigMigration {
+    /// Version the step upgrades from, to `from_version + 1`
+    pub from_version: u32,
+    /// What the step changes, shown when a file is migrated
+    pub description: &'static str,
+    apply: MigrationFn,
+}
+
+/// Upgrades config files to the current schema version
+#[derive(Clone)]
+pub struct ConfigMigrator {
+    migrations: BTreeMap<u32, ConfigMigration>,
+    target_version: u32,
+}
+
+impl ConfigMigrator {
+    /// Create a migrator with no steps, targeting `target_version`
+    pub fn new(target_version: u32) -> Self {
+        Self {
+            migrations: BTreeMap::new(),
+            target_version,
+        }
+    }
+
+    /// Migrator with every step this build knows about
+    pub fn standard() -> Self {
+        Self::new(CONFIG_SCHEMA_VERSION)
+            .register(1, "Add the profiles table that unversioned files could omit", add_profiles_table)
+    }
+
+    /// Register the step upgrading `from_version` to the next version
+    pub fn register(mut self, from_version: u32, description: &'static str, apply: MigrationFn) -> Self {
+        self.migrations.insert(from_version, ConfigMigration { from_version, description, apply });
+        self
+    }
+
+    /// Schema version the migrator upgrades to
+    pub fn target_version(&self) -> u32 {
+        self.target_version
+    }
+
+    /// Schema version recorded in a config table
+    pub fn schema_version(table: &Table) -> CLIResult<u32> {
+        match table.get(SCHEMA_VERSION_KEY) {
+            None => Ok(UNVERSIONED_SCHEMA),
+            Some(toml::Value::Integer(version)) if *version >= 1 => u32::try_from(*version)
+                .map_err(|_| CLIError::config(format!("Invalid config {}: {}", SCHEMA_VERSION_KEY, version))),
+            Some(other) => Err(CLIError::config(format!(
+                "Invalid config {}: {} (expected a positive integer)",
+                SCHEMA_VERSION_KEY, other
+            ))),
+        }
+    }
+
+    /// Check whether a table is older than the target schema
+    pub fn needs_migration(&self, table: &Table) -> CLIResult<bool> {
+        Ok(Self::schema_version(table)? < self.target_version)
+    }
+
+    /// Upgrade a table in place, returning the steps that ran
+    ///
+    /// A file from a newer build is refused rather than guessed at, since
+    /// its fields may mean something this build does not understand.
+    pub fn migrate(&self, table: &mut Table) -> CLIResult<Vec<&'static str>> {
+        let mut version = Self::schema_version(table)?;
+        if version > self.target_version {
+            return Err(CLIError::config(format!(
+                "Config file uses schema version {} but this version of kizuna only understands up to {}; \
+                 upgrade kizuna or restore an older config",
+                version, self.target_version
+            )));
+        }
+
+        let mut applied = Vec::new();
+        while version < self.target_version {
+            let migration = self.migrations.get(&version).ok_or_else(|| {
+                CLIError::config(format!("No migration from config schema version {}", version))
+            })?;
+            (migration.apply)(table).map_err(|e| {
+                CLIError::config(format!(
+                    "Migrating config from schema version {} failed: {}",
+                    version, e
+                ))
+            })?;
+            applied.push(migration.description);
+            version += 1;
+        }
+
+        table.insert(SCHEMA_VERSION_KEY.to_string(), toml::Value::Integer(i64::from(version)));
+        Ok(applied)
+    }
+}
+
+impl Default for ConfigMigrator {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+/// 1 -> 2: the generated default file left `[profiles]` commented out
+fn add_profiles_table(table: &mut Table) -> CLIResult<()> {
+    table
+        .entry("profiles")
+        .or_insert_with(|| toml::Value::Table(Table::new()));
+    Ok(())
+}
@@ -2,9 +2,16 @@
 
 use crate::cli::error::{CLIError, CLIResult};
 use crate::cli::types::{CLIConfig, ConfigProfile, OutputFormat, ColorMode};
+use crate::security::storage::EncryptedStorage;
 use async_trait::async_trait;
 use std::path::PathBuf;
 
+pub mod migration;
+pub mod secrets;
+
+pub use migration::{ConfigMigrator, CONFIG_SCHEMA_VERSION, SCHEMA_VERSION_KEY};
+pub use secrets::{EnvLookup, ResolvedSecret, SecretRef, CONFIG_SECRET_SERVICE};
+
 /// Configuration manager trait
 #[async_trait]
 pub trait ConfigurationManager {
@@ -103,25 +110,127 @@ pub struct MergedConfig {
 /// TOML configuration parser
 pub struct TOMLConfigParser {
     config_path: PathBuf,
+    storage: Option<EncryptedStorage>,
+    migrator: ConfigMigrator,
+    /// Values filled in from secret references by the last parse
+    secrets: std::sync::Mutex<Vec<ResolvedSecret>>,
+    /// Where `env:` references are looked up
+    env: EnvLookup,
 }
 
+/// Context label binding the sealed config file to its purpose
+const CONFIG_CONTEXT: &str = "cli:config";
+
 impl TOMLConfigParser {
     /// Create a new TOML configuration parser
-    pub fn new(config_path: Option<PathBuf>) -> CLIResult<Self> {
+    ///
+    /// `storage` encrypts the config file at rest; a plaintext config file is
+    /// encrypted the first time it is loaded. `None` only when encryption at
+    /// rest is turned off.
+    pub fn new(config_path: Option<PathBuf>, storage: Option<EncryptedStorage>) -> CLIResult<Self> {
         let path = config_path.unwrap_or_else(|| default_config_path().unwrap());
-        Ok(Self { config_path: path })
+        Ok(Self {
+            config_path: path,
+            storage,
+            migrator: ConfigMigrator::standard(),
+            secrets: std::sync::Mutex::new(Vec::new()),
+            env: secrets::process_env(),
+        })
+    }
+
+    /// Look up `env:` references somewhere other than the process environment
+    pub fn with_env(mut self, env: EnvLookup) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Use a custom set of schema migrations
+    pub fn with_migrator(mut self, migrator: ConfigMigrator) -> Self {
+        self.migrator = migrator;
+        self
+    }
+
+    /// Parse a TOML string into its raw table
+    fn parse_table(content: &str) -> CLIResult<toml::Table> {
+        content
+            .parse::<toml::Table>()
+            .map_err(|e| CLIError::config(format!("Failed to parse TOML: {}", e)))
     }
 
     /// Parse configuration from TOML string
+    ///
+    /// Content written with an older schema is migrated in memory first.
+    /// `keyring:` and `env:` values are replaced with the secrets they name,
+    /// failing if any of them cannot be found.
     pub fn parse_toml(&self, content: &str) -> CLIResult<CLIConfig> {
-        toml::from_str(content)
+        let mut table = Self::parse_table(content)?;
+        self.migrator.migrate(&mut table)?;
+        table.remove(SCHEMA_VERSION_KEY);
+        let resolved = secrets::resolve_secrets(&mut table, &self.env)?;
+        *self.secrets.lock().unwrap_or_else(|e| e.into_inner()) = resolved;
+        toml::Value::Table(table)
+            .try_into()
             .map_err(|e| CLIError::config(format!("Failed to parse TOML: {}", e)))
     }
 
-    /// Serialize configuration to TOML string
+    /// Serialize configuration to TOML string, stamped with the schema version
+    ///
+    /// Values that were loaded from secret references are written back as
+    /// the references, never as the secrets themselves.
     pub fn serialize_toml(&self, config: &CLIConfig) -> CLIResult<String> {
-        toml::to_string_pretty(config)
-            .map_err(|e| CLIError::config(format!("Failed to serialize to TOML: {}", e)))
+        let mut value = toml::Value::try_from(config)
+            .map_err(|e| CLIError::config(format!("Failed to serialize to TOML: {}", e)))?;
+        if let Some(table) = value.as_table_mut() {
+            let resolved = self.secrets.lock().unwrap_or_else(|e| e.into_inner());
+            secrets::restore_references(table, &resolved);
+        }
+        let body = toml::to_string_pretty(&value)
+            .map_err(|e| CLIError::config(format!("Failed to serialize to TOML: {}", e)))?;
+        Ok(format!(
+            "{} = {}\n{}",
+            SCHEMA_VERSION_KEY,
+            self.migrator.target_version(),
+            body
+        ))
+    }
+
+    /// Read the raw config file, decrypting it if storage is set
+    async fn read_content(&self) -> CLIResult<String> {
+        let content = match &self.storage {
+            Some(storage) => storage
+                .read_file(&self.config_path, CONFIG_CONTEXT)
+                .await
+                .map_err(|e| CLIError::config(format!("Failed to read config file: {}", e)))?
+                .unwrap_or_default(),
+            None => tokio::fs::read(&self.config_path)
+                .await
+                .map_err(|e| CLIError::config(format!("Failed to read config file: {}", e)))?,
+        };
+        if self.storage.is_none() && EncryptedStorage::is_sealed(&content) {
+            return Err(CLIError::config("Config file is encrypted but no storage key was given"));
+        }
+
+        String::from_utf8(content)
+            .map_err(|_| CLIError::config("Config file is not valid UTF-8"))
+    }
+
+    /// Write raw content to the config file, encrypting it if storage is set
+    async fn write_content(&self, content: &str) -> CLIResult<()> {
+        match &self.storage {
+            Some(storage) => storage
+                .write_file(&self.config_path, content.as_bytes(), CONFIG_CONTEXT)
+                .await
+                .map_err(|e| CLIError::config(format!("Failed to write config file: {}", e))),
+            None => {
+                tokio::fs::write(&self.config_path, content)
+                    .await
+                    .map_err(|e| CLIError::config(format!("Failed to write config file: {}", e)))?;
+                // Plaintext again, so enabling encryption later migrates it once more
+                EncryptedStorage::mark_sealed(&self.config_path, false)
+                    .await
+                    .map_err(|e| CLIError::config(format!("Failed to write config file: {}", e)))
+            }
+        }
     }
 
     /// Load configuration from file
@@ -133,10 +242,16 @@ impl TOMLConfigParser {
             )));
         }
 
-        let content = tokio::fs::read_to_string(&self.config_path)
-            .await
-            .map_err(|e| CLIError::config(format!("Failed to read config file: {}", e)))?;
+        if let Some(backup) = self.migrate().await? {
+            log::info!(
+                "Migrated {} to config schema version {}, previous file kept at {}",
+                self.config_path.display(),
+                self.migrator.target_version(),
+                backup.display()
+            );
+        }
 
+        let content = self.read_content().await?;
         self.parse_toml(&content)
     }
 
@@ -150,12 +265,7 @@ impl TOMLConfigParser {
         }
 
         let content = self.serialize_toml(config)?;
-        
-        tokio::fs::write(&self.config_path, content)
-            .await
-            .map_err(|e| CLIError::config(format!("Failed to write config file: {}", e)))?;
-
-        Ok(())
+        self.write_content(&content).await
     }
 
     /// Validate configuration
@@ -206,6 +316,87 @@ impl TOMLConfigParser {
             }
         }
 
+        // Validate shares
+        if let Err(e) = crate::remote_fs::ShareRoots::from_config(&config.shares) {
+            result.add_error(e.to_string());
+            result.add_suggestion("Check that each share names an existing folder and only defined groups".to_string());
+        }
+        for share in &config.shares.shares {
+            if share.peers.is_empty() && share.groups.is_empty() {
+                result.add_warning(format!("Share '{}' is not exported to any peer or group", share.name));
+            }
+        }
+
+        // Validate presence
+        if config.presence.refresh_interval.is_zero() {
+            result.add_error("Presence refresh_interval must be greater than zero".to_string());
+        }
+        if config.presence.away_after < config.presence.refresh_interval {
+            result.add_warning("Presence away_after is shorter than refresh_interval; away status will lag".to_string());
+        }
+
+        // Validate retention
+        if config.retention.sweep_interval_secs == 0 {
+            result.add_error("Retention sweep_interval_secs must be greater than zero".to_string());
+        }
+        if config.retention.allow_remote_wipe {
+            result.add_warning("Remote wipe is enabled; any device in your device group can erase this one".to_string());
+        }
+
+        // Validate traffic policy
+        if config.traffic.check_interval_secs == 0 {
+            result.add_error("Traffic check_interval_secs must be greater than zero".to_string());
+        }
+
+        // Validate resource limits
+        if config.resources.chunk_size == Some(0) {
+            result.add_error("Resources chunk_size must be greater than zero".to_string());
+        }
+        if config.resources.max_connections == Some(0) {
+            result.add_error("Resources max_connections must be greater than zero".to_string());
+        }
+
+        // Validate the lockdown hotkey
+        if let Err(e) = config.lockdown.hotkey.parse::<crate::platform::Hotkey>() {
+            result.add_error(format!("Lockdown hotkey: {}", e));
+        }
+
+        // Validate TUI key bindings and display settings
+        #[cfg(feature = "tui")]
+        {
+            let (_, warnings) = crate::cli::tui::KeyBindings::from_settings(&config.tui);
+            if !warnings.is_empty() {
+                result.add_suggestion("Press ? in the TUI to see action names and rebind keys".to_string());
+            }
+            for warning in warnings {
+                result.add_warning(format!("TUI key bindings: {}", warning));
+            }
+
+            let (_, warnings) = crate::cli::tui::Theme::from_settings(&config.tui, config.color_mode);
+            for warning in warnings {
+                result.add_warning(format!("TUI colors: {}", warning));
+            }
+        }
+        if config.tui.frame_interval_ms == 0 {
+            result.add_error("TUI frame_interval_ms must be greater than zero".to_string());
+        }
+        if config.tui.stats_refresh_ms == 0 {
+            result.add_error("TUI stats_refresh_ms must be greater than zero".to_string());
+        }
+
+        // Validate per-peer overrides
+        for (alias, overrides) in config.peers.iter() {
+            if alias.trim().is_empty() {
+                result.add_error("Peer overrides need a peer alias".to_string());
+            }
+            if overrides.bandwidth_limit == Some(0) {
+                result.add_error(format!(
+                    "Peer '{}' bandwidth_limit must be greater than zero; remove it for no limit",
+                    alias
+                ));
+            }
+        }
+
         // Validate profiles
         for (name, profile) in &config.profiles {
             if profile.name != *name {
@@ -229,6 +420,9 @@ impl TOMLConfigParser {
         r#"# Kizuna CLI Configuration
 # This file configures the behavior of the Kizuna command-line interface
 
+# Config format version, used to upgrade this file after kizuna updates
+schema_version = 2
+
 # Default peer to connect to (optional)
 # default_peer = "my-laptop"
 
@@ -266,8 +460,111 @@ auto_record = false
 # Directory for stream recordings (optional)
 # recording_path = "/home/user/Videos/kizuna"
 
+# Data retention settings
+[retention]
+# Days to keep each kind of data; remove a line to keep that data forever
+clipboard_history_days = 30
+transfer_history_days = 90
+audit_log_days = 90
+# recording_days = 30
+
+# Seconds between purges of old data
+sweep_interval_secs = 3600
+
+# Let your other devices wipe this one if it is lost or stolen
+allow_remote_wipe = false
+
+# Behaviour on metered connections such as phone hotspots
+[traffic]
+# Treat the connection as metered (true) or not (false); leave unset to detect
+# metered = true
+
+# Hold back transfers of at least bulk_transfer_bytes until the connection is unmetered
+pause_bulk_transfers = true
+bulk_transfer_bytes = 10485760
+
+# Keep copied images local instead of syncing them automatically
+pause_image_clipboard_sync = true
+
+# Highest stream bitrate in bits per second
+metered_stream_bitrate = 1000000
+
+# Seconds between connection checks
+check_interval_secs = 60
+
+# Buffer, cache and connection limits
+[resources]
+# "auto" picks "constrained" on single-board computers, routers and devices
+# with 1 GB of memory or less; "standard" and "constrained" force a profile
+profile = "auto"
+
+# Remote mount read size in bytes (standard 131072, constrained 32768)
+# chunk_size = 32768
+
+# Most connections open at once (standard 100, constrained 16)
+# max_connections = 16
+
+# Probe GPUs, video codecs and Bluetooth adapters; off by default on
+# constrained devices because some SBC drivers hang when probed
+# hardware_probing = false
+
+# Panic switch that refuses clipboard writes, commands, handoffs, stream
+# viewers and remote control from peers until lifted in the TUI
+[lockdown]
+# Press anywhere on the desktop to lock down; needs Windows or X11
+hotkey_enabled = true
+hotkey = "Ctrl+Alt+Shift+K"
+
+# Desktop notifications when transfers and streams started from the CLI finish
+# Pass --no-notify to a command to skip its notification
+[notifications]
+enabled = true
+
+# Operations shorter than this many seconds are not announced
+min_duration_secs = 10
+
+# Interactive interface (kizuna tui)
+[tui]
+# Color theme: "default", "high-contrast" or "no-color"
+# color_mode = "never", or NO_COLOR with color_mode = "auto", turns colors off
+theme = "default"
+
+# Milliseconds between redraws; larger values are easier on screen readers
+frame_interval_ms = 50
+
+# Milliseconds between streaming statistics refreshes
+stats_refresh_ms = 1000
+
+# Plain text output without borders or tabs, one item per line
+linear = false
+
+# Replace colors in the theme
+[tui.colors]
+# yellow = "magenta"
+# darkgray = "gray"
+
+[tui.keybindings]
+# Keys for an action replace its defaults; press ? in the TUI to list actions
+# quit = ["ctrl+q"]
+# move_down = ["down", "j"]
+# command_palette = ["ctrl+p", ":"]
+
+# Settings for single peers, by alias; unset values use the settings above
+# [peers."nas"]
+# bandwidth_limit = 5000000   # bytes per second
+# [peers."phone"]
+# compression = true
+# [peers."work-laptop"]
+# streaming = false
+# clipboard_sync = false
+
+# Secrets such as passwords can be kept out of this file: a value of
+# "keyring:<name>" is read from the system keyring (service "kizuna.config")
+# and "env:<NAME>" from an environment variable when the config is loaded
+
 # Configuration profiles
 # Profiles allow you to define different configurations for different use cases
+[profiles]
 # [profiles.work]
 # name = "work"
 # description = "Work environment settings"
@@ -275,11 +572,40 @@ auto_record = false
 "#.to_string()
     }
 
-    /// Migrate old configuration to new format
-    pub async fn migrate(&self, old_config: CLIConfig) -> CLIResult<CLIConfig> {
-        // For now, just return the config as-is
-        // In the future, this could handle version migrations
-        Ok(old_config)
+    /// Upgrade the config file on disk to the current schema version
+    ///
+    /// The original file is copied next to it as `<name>.v<version>.bak`
+    /// before being rewritten, and the backup path is returned. Files already
+    /// at the current version are left untouched and `None` is returned.
+    pub async fn migrate(&self) -> CLIResult<Option<PathBuf>> {
+        let content = self.read_content().await?;
+        let mut table = Self::parse_table(&content)?;
+        let from_version = ConfigMigrator::schema_version(&table)?;
+        let applied = self.migrator.migrate(&mut table)?;
+        if from_version == self.migrator.target_version() {
+            return Ok(None);
+        }
+
+        let file_name = self
+            .config_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "config.toml".to_string());
+        let backup = self
+            .config_path
+            .with_file_name(format!("{}.v{}.bak", file_name, from_version));
+        tokio::fs::copy(&self.config_path, &backup)
+            .await
+            .map_err(|e| CLIError::config(format!("Failed to back up config file: {}", e)))?;
+
+        for step in &applied {
+            log::debug!("Config migration: {}", step);
+        }
+
+        let migrated = toml::to_string_pretty(&table)
+            .map_err(|e| CLIError::config(format!("Failed to serialize to TOML: {}", e)))?;
+        self.write_content(&migrated).await?;
+        Ok(Some(backup))
     }
 }
 
@@ -306,8 +632,10 @@ pub fn ensure_config_dir() -> CLIResult<PathBuf> {
 }
 
 /// Load configuration from default location or create default
-pub async fn load_or_create_config() -> CLIResult<CLIConfig> {
-    let parser = TOMLConfigParser::new(None)?;
+///
+/// `storage` is the shared encryption at rest, see [`TOMLConfigParser::new`].
+pub async fn load_or_create_config(storage: Option<EncryptedStorage>) -> CLIResult<CLIConfig> {
+    let parser = TOMLConfigParser::new(None, storage)?;
     
     if parser.config_path.exists() {
         let config = parser.load().await?;
@@ -329,6 +657,11 @@ pub async fn load_or_create_config() -> CLIResult<CLIConfig> {
             }
         }
         
+        // Apply [resources] before any subsystem reads the profile. Later
+        // loads in the same process keep the profile that was installed first
+        crate::platform::ResourceProfile::resolve(&config.resources, &crate::platform::DeviceTraits::detect())
+            .install();
+        
         Ok(config)
     } else {
         // Create default configuration
@@ -339,17 +672,15 @@ pub async fn load_or_create_config() -> CLIResult<CLIConfig> {
         
         // Save default config with comments
         let content = TOMLConfigParser::generate_default_with_comments();
-        tokio::fs::write(&parser.config_path, content)
-            .await
-            .map_err(|e| CLIError::config(format!("Failed to write config file: {}", e)))?;
+        parser.write_content(&content).await?;
         
         Ok(config)
     }
 }
 
 /// Load configuration from specific path
-pub async fn load_config_from_path(path: PathBuf) -> CLIResult<CLIConfig> {
-    let parser = TOMLConfigParser::new(Some(path))?;
+pub async fn load_config_from_path(path: PathBuf, storage: Option<EncryptedStorage>) -> CLIResult<CLIConfig> {
+    let parser = TOMLConfigParser::new(Some(path), storage)?;
     let config = parser.load().await?;
     
     // Validate the loaded configuration
@@ -365,8 +696,8 @@ pub async fn load_config_from_path(path: PathBuf) -> CLIResult<CLIConfig> {
 }
 
 /// Save configuration to default location
-pub async fn save_config(config: &CLIConfig) -> CLIResult<()> {
-    let parser = TOMLConfigParser::new(None)?;
+pub async fn save_config(config: &CLIConfig, storage: Option<EncryptedStorage>) -> CLIResult<()> {
+    let parser = TOMLConfigParser::new(None, storage)?;
     
     // Validate before saving
     let validation = parser.validate(config);
@@ -381,8 +712,12 @@ pub async fn save_config(config: &CLIConfig) -> CLIResult<()> {
 }
 
 /// Save configuration to specific path
-pub async fn save_config_to_path(config: &CLIConfig, path: PathBuf) -> CLIResult<()> {
-    let parser = TOMLConfigParser::new(Some(path))?;
+pub async fn save_config_to_path(
+    config: &CLIConfig,
+    path: PathBuf,
+    storage: Option<EncryptedStorage>,
+) -> CLIResult<()> {
+    let parser = TOMLConfigParser::new(Some(path), storage)?;
     
     // Validate before saving
     let validation = parser.validate(config);
@@ -504,6 +839,11 @@ impl ProfileManager {
                         config.stream_settings.auto_record = val;
                     }
                 }
+                "notifications" => {
+                    if let Some(val) = value.as_bool() {
+                        config.notifications.enabled = val;
+                    }
+                }
                 _ => {
                     // Unknown setting, ignore
                 }
@@ -554,7 +894,7 @@ impl ProfileManager {
                         result.add_error("default_peer must be a string");
                     }
                 }
-                "compression" | "encryption" | "auto_accept_trusted" | "auto_record" => {
+                "compression" | "encryption" | "auto_accept_trusted" | "auto_record" | "notifications" => {
                     if !value.is_boolean() {
                         result.add_error(format!("{} must be a boolean", key));
                     }
@@ -817,7 +1157,8 @@ impl ConfigMerger {
         }
 
         // Validate merged configuration
-        let parser = TOMLConfigParser::new(None)?;
+        // Only validates, nothing is read from or written to disk
+        let parser = TOMLConfigParser::new(None, None)?;
         let validation = parser.validate(&config);
         
         if !validation.is_valid() {
@@ -882,7 +1223,8 @@ impl ConfigMerger {
 
     /// Validate merged configuration
     fn validate_merged_config(&self, config: &CLIConfig) -> CLIResult<()> {
-        let parser = TOMLConfigParser::new(None)?;
+        // Only validates, nothing is read from or written to disk
+        let parser = TOMLConfigParser::new(None, None)?;
         let validation = parser.validate(config);
         
         if !validation.is_valid() {
@@ -910,12 +1252,15 @@ impl ConfigMerger {
 }
 
 /// Load configuration with command-line overrides
-pub async fn load_config_with_overrides(args: ParsedArgs) -> CLIResult<MergedConfig> {
+pub async fn load_config_with_overrides(
+    args: ParsedArgs,
+    storage: Option<EncryptedStorage>,
+) -> CLIResult<MergedConfig> {
     // Load base configuration from file or use custom path
     let base_config = if let Some(ref config_path) = args.config_file {
-        load_config_from_path(config_path.clone()).await?
+        load_config_from_path(config_path.clone(), storage).await?
     } else {
-        load_or_create_config().await?
+        load_or_create_config(storage).await?
     };
 
     // Merge with command-line arguments
@@ -929,7 +1274,8 @@ pub struct RuntimeConfigValidator;
 impl RuntimeConfigValidator {
     /// Validate configuration at runtime
     pub fn validate_runtime(config: &CLIConfig) -> CLIResult<()> {
-        let parser = TOMLConfigParser::new(None)?;
+        // Only validates, nothing is read from or written to disk
+        let parser = TOMLConfigParser::new(None, None)?;
         let validation = parser.validate(config);
         
         if !validation.is_valid() {
@@ -944,7 +1290,8 @@ impl RuntimeConfigValidator {
 
     /// Validate and suggest fixes for configuration errors
     pub fn validate_with_suggestions(config: &CLIConfig) -> ValidationResult {
-        let parser = TOMLConfigParser::new(None).unwrap();
+        // Only validates, nothing is read from or written to disk
+        let parser = TOMLConfigParser::new(None, None).unwrap();
         parser.validate(config)
     }
 
@@ -1270,12 +1617,13 @@ impl OutputFormatConfig {
 /// Unified configuration manager that provides access to all configuration aspects
 pub struct UnifiedConfigManager {
     config: CLIConfig,
+    storage: Option<EncryptedStorage>,
 }
 
 impl UnifiedConfigManager {
-    /// Create a new unified configuration manager
-    pub fn new(config: CLIConfig) -> Self {
-        Self { config }
+    /// Create a new unified configuration manager, saving through `storage`
+    pub fn new(config: CLIConfig, storage: Option<EncryptedStorage>) -> Self {
+        Self { config, storage }
     }
 
     /// Get default peer configuration manager
@@ -1315,12 +1663,12 @@ impl UnifiedConfigManager {
 
     /// Save the current configuration
     pub async fn save(&self) -> CLIResult<()> {
-        save_config(&self.config).await
+        save_config(&self.config, self.storage.clone()).await
     }
 
     /// Reload configuration from disk
     pub async fn reload(&mut self) -> CLIResult<()> {
-        self.config = load_or_create_config().await?;
+        self.config = load_or_create_config(self.storage.clone()).await?;
         Ok(())
     }
 }
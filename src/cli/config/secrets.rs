@@ -0,0 +1,233 @@
+// Secret references in config values
+//
+// A string value of the form `keyring:<name>` or `env:<NAME>` is replaced at
+// load time with the secret it names, so credentials never have to sit in
+// the TOML file. The references are remembered and written back on save in
+// place of the resolved values.
+
+use crate::cli::error::{CLIError, CLIResult};
+use std::fmt;
+use std::sync::Arc;
+use toml::{Table, Value};
+
+/// Looks up environment variables for `env:` references
+///
+/// Defaults to the process environment; tests inject their own so they never
+/// have to modify it.
+pub type EnvLookup = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Lookup reading the process environment
+pub fn process_env() -> EnvLookup {
+    Arc::new(|name| std::env::var(name).ok())
+}
+
+/// Keyring service that config secrets are stored under
+pub const CONFIG_SECRET_SERVICE: &str = "kizuna.config";
+
+const KEYRING_PREFIX: &str = "keyring:";
+const ENV_PREFIX: &str = "env:";
+
+/// Where a config value's secret lives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretRef {
+    /// Entry in the OS keyring under [`CONFIG_SECRET_SERVICE`]
+    Keyring(String),
+    /// Environment variable
+    Env(String),
+}
+
+impl SecretRef {
+    /// Parse a config string, `None` if it is a plain value
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Some(name) = value.strip_prefix(KEYRING_PREFIX) {
+            Some(SecretRef::Keyring(name.trim().to_string()))
+        } else {
+            value
+                .strip_prefix(ENV_PREFIX)
+                .map(|name| SecretRef::Env(name.trim().to_string()))
+        }
+    }
+
+    /// Name of the keyring entry or environment variable
+    pub fn name(&self) -> &str {
+        match self {
+            SecretRef::Keyring(name) | SecretRef::Env(name) => name,
+        }
+    }
+
+    /// Look the secret up, reading environment variables through `env`
+    pub fn resolve(&self, env: &EnvLookup) -> CLIResult<String> {
+        if self.name().is_empty() {
+            return Err(CLIError::config(format!("{} does not name a secret", self)));
+        }
+
+        match self {
+            SecretRef::Env(name) => env(name)
+                .ok_or_else(|| CLIError::config(format!("environment variable {} is not set", name))),
+            SecretRef::Keyring(name) => keyring::Entry::new(CONFIG_SECRET_SERVICE, name)
+                .and_then(|entry| entry.get_password())
+                .map_err(|e| CLIError::config(format!("keyring entry {} not found: {}", name, e))),
+        }
+    }
+}
+
+impl fmt::Display for SecretRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretRef::Keyring(name) => write!(f, "{}{}", KEYRING_PREFIX, name),
+            SecretRef::Env(name) => write!(f, "{}{}", ENV_PREFIX, name),
+        }
+    }
+}
+
+/// A config value that was filled in from a secret reference
+#[derive(Clone, PartialEq, Eq)]
+pub struct ResolvedSecret {
+    /// Dotted key path of the value, e.g. `relay.password`
+    pub path: String,
+    pub reference: SecretRef,
+    value: String,
+}
+
+impl fmt::Debug for ResolvedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResolvedSecret")
+            .field("path", &self.path)
+            .field("reference", &self.reference)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Replace every secret reference in a config table with its value
+///
+/// All references are checked before failing, so a single error lists every
+/// secret that is missing.
+pub fn resolve_secrets(table: &mut Table, env: &EnvLookup) -> CLIResult<Vec<ResolvedSecret>> {
+    let mut resolved = Vec::new();
+    let mut missing = Vec::new();
+    for (key, value) in table.iter_mut() {
+        resolve_value(key.clone(), value, env, &mut resolved, &mut missing);
+    }
+
+    if !missing.is_empty() {
+        return Err(CLIError::config(format!(
+            "Config references secrets that could not be found:\n  {}",
+            missing.join("\n  ")
+        )));
+    }
+    Ok(resolved)
+}
+
+fn resolve_value(
+    path: String,
+    value: &mut Value,
+    env: &EnvLookup,
+    resolved: &mut Vec<ResolvedSecret>,
+    missing: &mut Vec<String>,
+) {
+    match value {
+        Value::String(text) => {
+            let Some(reference) = SecretRef::parse(text) else {
+                return;
+            };
+            match reference.resolve(env) {
+                Ok(secret) => {
+                    *text = secret.clone();
+                    resolved.push(ResolvedSecret { path, reference, value: secret });
+                }
+                // The outer error already says this is a config problem
+                Err(CLIError::ConfigError(reason)) => missing.push(format!("{}: {}", path, reason)),
+                Err(e) => missing.push(format!("{}: {}", path, e)),
+            }
+        }
+        Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                resolve_value(format!("{}.{}", path, key), value, env, resolved, missing);
+            }
+        }
+        Value::Array(items) => {
+            for (index, value) in items.iter_mut().enumerate() {
+                resolve_value(format!("{}[{}]", path, index), value, env, resolved, missing);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Put secret references back in place of their resolved values
+///
+/// A value changed since loading is kept as-is, since it no longer comes
+/// from the secret.
+pub fn restore_references(table: &mut Table, secrets: &[ResolvedSecret]) {
+    for secret in secrets {
+        let mut current = Some(&mut *table);
+        let mut keys = secret.path.split('.').peekable();
+        while let (Some(key), Some(node)) = (keys.next(), current.take()) {
+            // Arrays are addressed as `key[index]`
+            let (key, index) = match key.split_once('[') {
+                Some((key, rest)) => (key, rest.trim_end_matches(']').parse::<usize>().ok()),
+                None => (key, None),
+            };
+            let Some(mut value) = node.get_mut(key) else { break };
+            if let Some(index) = index {
+                match value.get_mut(index) {
+                    Some(item) => value = item,
+                    None => break,
+                }
+            }
+
+            if keys.peek().is_none() {
+                if value.as_str() == Some(secret.value.as_str()) {
+                    *value = Value::String(secret.reference.to_string());
+                }
+            } else {
+                current = value.as_table_mut();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_with(name: &'static str, value: &'static str) -> EnvLookup {
+        Arc::new(move |key| (key == name).then(|| value.to_string()))
+    }
+
+    #[test]
+    fn test_env_reference_resolves_and_restores() {
+        let env = env_with("KIZUNA_TEST_RELAY_PASS", "hunter2");
+        let mut table: Table = r#"
+            output_format = "table"
+            [relay]
+            password = "env:KIZUNA_TEST_RELAY_PASS"
+        "#
+        .parse()
+        .unwrap();
+
+        let secrets = resolve_secrets(&mut table, &env).unwrap();
+        assert_eq!(secrets.len(), 1);
+        assert_eq!(secrets[0].path, "relay.password");
+        assert_eq!(table["relay"]["password"].as_str(), Some("hunter2"));
+        assert!(!format!("{:?}", secrets).contains("hunter2"));
+
+        restore_references(&mut table, &secrets);
+        assert_eq!(table["relay"]["password"].as_str(), Some("env:KIZUNA_TEST_RELAY_PASS"));
+    }
+
+    #[test]
+    fn test_missing_secrets_are_all_reported() {
+        let mut table: Table = r#"
+            a = "env:KIZUNA_TEST_UNSET_ONE"
+            b = ["plain", "env:KIZUNA_TEST_UNSET_TWO"]
+        "#
+        .parse()
+        .unwrap();
+
+        let env: EnvLookup = Arc::new(|_| None);
+        let err = resolve_secrets(&mut table, &env).unwrap_err().to_string();
+        assert!(err.contains("a: environment variable KIZUNA_TEST_UNSET_ONE is not set"));
+        assert!(err.contains("b[1]: environment variable KIZUNA_TEST_UNSET_TWO is not set"));
+    }
+}
@@ -52,6 +52,22 @@ pub enum CLIError {
     #[error("Format error: {0}")]
     FormatError(String),
 
+    /// A prompt was needed but the CLI is running non-interactively
+    #[error("Input required: {0}")]
+    InputRequired(String),
+
+    /// The operation is not allowed for this user or peer
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// A peer, file, recording or other resource does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// The peer or network did not answer in time
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
     /// Generic error with context
     #[error("{0}")]
     Other(String),
@@ -128,6 +144,21 @@ impl CLIError {
 
     /// Create a not found error
     pub fn not_found(msg: impl Into<String>) -> Self {
-        CLIError::Other(format!("Not found: {}", msg.into()))
+        CLIError::NotFound(msg.into())
+    }
+
+    /// Create a permission denied error
+    pub fn permission_denied(msg: impl Into<String>) -> Self {
+        CLIError::PermissionDenied(msg.into())
+    }
+
+    /// Create a timeout error
+    pub fn timeout(msg: impl Into<String>) -> Self {
+        CLIError::Timeout(msg.into())
+    }
+
+    /// Create an error for a prompt that cannot be shown non-interactively
+    pub fn input_required(msg: impl Into<String>) -> Self {
+        CLIError::InputRequired(msg.into())
     }
 }
@@ -570,6 +570,8 @@ mod tests {
             capabilities: vec![],
             trust_status: TrustStatus::Trusted,
             last_seen: None,
+            presence: None,
+            version: None,
         };
 
         let peer2 = PeerInfo {
@@ -580,6 +582,8 @@ mod tests {
             capabilities: vec![],
             trust_status: TrustStatus::Trusted,
             last_seen: None,
+            presence: None,
+            version: None,
         };
 
         assert!(filter.matches(&peer1));
@@ -601,6 +605,8 @@ mod tests {
             capabilities: vec![],
             trust_status: TrustStatus::Trusted,
             last_seen: None,
+            presence: None,
+            version: None,
         };
 
         assert!(filter.matches(&peer));
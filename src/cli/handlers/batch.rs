@@ -147,6 +147,12 @@ impl BatchOperationHandler {
                 peer: operation.peer.clone(),
                 compression: args.compression,
                 encryption: args.encryption,
+                allow_metered: false,
+                notify: false,
+                dry_run: false,
+                also_to: Vec::new(),
+                swarm: false,
+                name: None,
             };
 
             match self.transfer_handler.handle_send(send_args).await {
@@ -183,6 +189,12 @@ impl BatchOperationHandler {
                 peer: operation.peer.clone(),
                 compression: args.compression,
                 encryption: args.encryption,
+                allow_metered: false,
+                notify: false,
+                dry_run: false,
+                also_to: Vec::new(),
+                swarm: false,
+                name: None,
             };
 
             let task = tokio::spawn(async move {
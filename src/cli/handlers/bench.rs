@@ -0,0 +1,188 @@
+// Bench command handler
+//
+// Implements "kizuna bench": resolves the peer, benchmarks every transport
+// protocol both sides support, stores the results so the negotiator prefers
+// the fastest protocol for that peer, and renders a comparison table.
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::discovery::KizunaDiscovery;
+use crate::transport::{
+    BenchmarkConfig, BenchmarkReport, BenchmarkStore, ConnectionManager, PeerAddress, TransportCapabilities,
+};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// How long to look for a named peer before giving up
+const PEER_LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Capability key discovery records use to advertise a transport protocol
+const TRANSPORT_PROTOCOL_CAPABILITY: &str = "transport_protocol";
+
+/// Bench command handler
+pub struct BenchHandler {
+    config: BenchmarkConfig,
+}
+
+impl BenchHandler {
+    /// Create a handler with the default benchmark settings
+    pub fn new() -> Self {
+        Self {
+            config: BenchmarkConfig::default(),
+        }
+    }
+
+    /// How long to stream data for the throughput test
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.config.duration = duration;
+        self
+    }
+
+    /// Size of each data frame sent during the throughput test
+    pub fn with_payload_size(mut self, payload_size: usize) -> Self {
+        self.config.payload_size = payload_size;
+        self
+    }
+
+    /// Benchmark a peer given as `host:port` or a discovered name or ID
+    pub async fn run(&self, peer: &str) -> CLIResult<BenchmarkReport> {
+        let mut manager = ConnectionManager::new();
+        manager
+            .register_all_transports()
+            .await
+            .map_err(|e| CLIError::other(format!("Failed to set up transports: {}", e)))?;
+
+        let address = Self::resolve_peer(peer, manager.get_available_protocols()).await?;
+        let report = manager.benchmark_peer(&address, &self.config).await;
+
+        // Results only matter to future negotiations, so a store that cannot
+        // be written is not a reason to fail the benchmark itself
+        if !report.results.is_empty() && let Ok(mut store) = BenchmarkStore::open_default() {
+            store.record(&report);
+            let _ = store.save();
+        }
+
+        Ok(report)
+    }
+
+    /// Build the address to benchmark
+    ///
+    /// A literal address is tried with every local protocol; a discovered
+    /// peer with the protocols it advertises.
+    async fn resolve_peer(peer: &str, local_protocols: Vec<String>) -> CLIResult<PeerAddress> {
+        if let Ok(addr) = peer.parse::<SocketAddr>() {
+            return Ok(PeerAddress::new(
+                addr.to_string(),
+                vec![addr],
+                local_protocols,
+                TransportCapabilities::default(),
+            ));
+        }
+
+        let mut discovery = KizunaDiscovery::new();
+        discovery
+            .initialize()
+            .await
+            .map_err(|e| CLIError::discovery(format!("Failed to initialize discovery: {}", e)))?;
+
+        let records = discovery.discover_once(Some(PEER_LOOKUP_TIMEOUT)).await.unwrap_or_default();
+        let _ = discovery.shutdown().await;
+
+        let record = records
+            .into_iter()
+            .find(|record| record.peer_id == peer || record.name.eq_ignore_ascii_case(peer))
+            .ok_or_else(|| CLIError::not_found(format!("Peer '{}' was not found on the network", peer)))?;
+
+        let hints = match record.capabilities.get(TRANSPORT_PROTOCOL_CAPABILITY) {
+            Some(protocol) => vec![protocol.clone()],
+            None => local_protocols,
+        };
+
+        Ok(PeerAddress::new(record.peer_id, record.addresses, hints, TransportCapabilities::default()))
+    }
+
+    /// Format a benchmark report as a comparison table
+    pub fn format_report(report: &BenchmarkReport) -> String {
+        let mut output = format!(
+            "Benchmark with {} ({}s, {} byte payloads)\n\n",
+            report.peer_id,
+            report.config.duration.as_secs(),
+            report.config.payload_size
+        );
+
+        if report.results.is_empty() {
+            output.push_str("No protocol could be benchmarked.\n");
+        } else {
+            let best = report.best_protocol();
+            output.push_str(&format!(
+                "  {:<10} {:>10} {:>10} {:>7} {:>14}\n",
+                "PROTOCOL", "RTT", "JITTER", "LOSS", "THROUGHPUT"
+            ));
+            for result in &report.results {
+                let marker = if Some(result.protocol.as_str()) == best { " *" } else { "" };
+                output.push_str(&format!(
+                    "  {:<10} {:>7.2} ms {:>7.2} ms {:>6.1}% {:>8.1} Mbit/s{}\n",
+                    result.protocol,
+                    result.rtt_ms,
+                    result.jitter_ms,
+                    result.ping_loss * 100.0,
+                    result.throughput_mbps(),
+                    marker
+                ));
+            }
+            if let Some(best) = best {
+                output.push_str(&format!("\n* {} will be preferred for this peer.\n", best));
+            }
+        }
+
+        if !report.failures.is_empty() {
+            output.push_str("\nNot measured:\n");
+            for failure in &report.failures {
+                output.push_str(&format!("  {}: {}\n", failure.protocol, failure.error));
+            }
+        }
+
+        output
+    }
+}
+
+impl Default for BenchHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{BenchmarkFailure, ProtocolBenchmark};
+
+    fn measured(protocol: &str, rtt_ms: f64, throughput: f64) -> ProtocolBenchmark {
+        ProtocolBenchmark {
+            protocol: protocol.to_string(),
+            rtt_ms,
+            min_rtt_ms: rtt_ms,
+            max_rtt_ms: rtt_ms,
+            jitter_ms: 0.5,
+            ping_loss: 0.0,
+            throughput_bytes_per_sec: throughput,
+            bytes_transferred: 0,
+            measured_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_format_report_marks_best_protocol() {
+        let mut report = BenchmarkReport::new("peer-1".to_string(), BenchmarkConfig::default());
+        report.results.push(measured("tcp", 2.0, 12_500_000.0));
+        report.results.push(measured("quic", 3.0, 2_500_000.0));
+        report.failures.push(BenchmarkFailure {
+            protocol: "webrtc".to_string(),
+            error: "no signaling".to_string(),
+        });
+
+        let output = BenchHandler::format_report(&report);
+        assert!(output.contains("100.0 Mbit/s *"));
+        assert!(output.contains("tcp will be preferred"));
+        assert!(output.contains("webrtc: no signaling"));
+    }
+}
@@ -6,7 +6,7 @@
 // Requirements: 4.1, 4.2, 4.3, 4.4, 4.5
 
 use crate::cli::error::{CLIError, CLIResult};
-use crate::cli::types::{ConnectionStatus, PeerInfo};
+use crate::cli::types::{ConnectionStatus, PeerInfo, PeerOverrideTable};
 use crate::clipboard::api::{ClipboardSystem, ClipboardSystemStatus};
 use crate::clipboard::{ClipboardContent, ContentSource, TextContent};
 use crate::clipboard::history::HistoryEntry;
@@ -58,12 +58,40 @@ pub struct ClipboardResult {
 /// Clipboard command handler implementation
 pub struct ClipboardHandler {
     clipboard_system: Arc<ClipboardSystem>,
+    /// Settings for single peers from the user configuration
+    peer_overrides: PeerOverrideTable,
 }
 
 impl ClipboardHandler {
     /// Create a new clipboard handler
     pub fn new(clipboard_system: Arc<ClipboardSystem>) -> Self {
-        Self { clipboard_system }
+        Self {
+            clipboard_system,
+            peer_overrides: PeerOverrideTable::default(),
+        }
+    }
+
+    /// Apply per-peer settings, such as peers the clipboard never syncs with
+    pub fn with_peer_overrides(mut self, peer_overrides: PeerOverrideTable) -> Self {
+        self.peer_overrides = peer_overrides;
+        self
+    }
+
+    /// Turn sync off for every peer whose settings exclude it
+    ///
+    /// Returns the number of peers sync was turned off for.
+    pub async fn apply_peer_overrides(&self) -> CLIResult<usize> {
+        let mut disabled = 0;
+        for (peer, overrides) in self.peer_overrides.iter() {
+            if !overrides.allows_clipboard_sync() {
+                self.clipboard_system
+                    .disable_sync_for_device(peer.clone())
+                    .await
+                    .map_err(|e| CLIError::clipboard(format!("Failed to disable device: {}", e)))?;
+                disabled += 1;
+            }
+        }
+        Ok(disabled)
     }
 
     /// Handle clipboard command
@@ -164,6 +192,13 @@ impl ClipboardHandler {
 
     /// Enable clipboard sync for a device
     async fn enable_device(&self, device_id: String) -> CLIResult<ClipboardResult> {
+        if !self.peer_overrides.for_peer(&device_id).allows_clipboard_sync() {
+            return Err(CLIError::permission_denied(format!(
+                "Clipboard sync with '{}' is turned off in its peer settings",
+                device_id
+            )));
+        }
+
         self.clipboard_system
             .enable_sync_for_device(device_id.clone())
             .await
@@ -330,6 +365,8 @@ impl ClipboardHandler {
                     crate::cli::types::TrustStatus::Untrusted
                 },
                 last_seen: status.last_sync.map(|st| chrono::DateTime::from(st)),
+                presence: None,
+                version: None,
             })
             .collect();
 
@@ -356,7 +393,7 @@ mod tests {
         let db_path = temp_dir
             .path()
             .join(format!("test_clipboard_{}.db", Uuid::new_v4()));
-        let history_manager = Arc::new(SqliteHistoryManager::new(db_path, 50).unwrap());
+        let history_manager = Arc::new(SqliteHistoryManager::new(db_path, security_system.storage()).unwrap());
 
         let clipboard_system = Arc::new(
             ClipboardSystemBuilder::new()
@@ -8,8 +8,10 @@
 
 use crate::cli::error::{CLIError, CLIResult};
 use crate::cli::handlers::{DiscoverArgs, DiscoverResult};
-use crate::cli::types::{ConnectionStatus, PeerInfo, TrustStatus};
+use crate::cli::types::{ConnectionStatus, PeerInfo, TableData, TrustStatus};
 use crate::discovery::api::{DiscoveryBuilder, DiscoveryEvent, KizunaDiscovery};
+use crate::discovery::manager::StrategyStats;
+use crate::discovery::Presence;
 use crate::security::api::SecuritySystem;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -143,6 +145,8 @@ impl DiscoverHandler {
                     .unwrap_or_default(),
                 trust_status,
                 last_seen: Some(chrono::Utc::now()),
+                presence: Presence::from_record(&record).map(|p| p.status),
+                version: record.software_version().map(str::to_string),
             };
             peers.push(peer_info);
         }
@@ -164,6 +168,58 @@ impl DiscoverHandler {
         })
     }
 
+    /// Run one discovery pass and report how each strategy performed
+    pub async fn strategy_stats(
+        &mut self,
+        timeout: Duration,
+    ) -> CLIResult<std::collections::HashMap<String, StrategyStats>> {
+        self.discovery
+            .initialize()
+            .await
+            .map_err(|e| CLIError::discovery(format!("Failed to initialize discovery: {}", e)))?;
+        // Failed strategies are part of the statistics, so the pass itself may fail
+        if let Err(e) = self.discovery.discover_once(Some(timeout)).await {
+            log::debug!("Discovery pass for statistics failed: {}", e);
+        }
+        Ok(self.discovery.strategy_stats().await)
+    }
+
+    /// Tabulate peers for `kizuna peers`, including the release each runs
+    pub fn peers_table(peers: &[PeerInfo]) -> TableData {
+        TableData {
+            headers: ["Name", "Type", "Status", "Trust", "Version"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            rows: peers
+                .iter()
+                .map(|peer| {
+                    vec![
+                        peer.name.clone(),
+                        peer.device_type.clone(),
+                        format!("{:?}", peer.connection_status),
+                        format!("{:?}", peer.trust_status),
+                        peer.version_label(),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// Version skew warnings for a set of peers, each prefixed with the peer name
+    pub fn version_warnings(peers: &[PeerInfo]) -> Vec<String> {
+        peers
+            .iter()
+            .filter_map(|peer| Some((peer, peer.version_skew()?)))
+            .filter(|(_, skew)| skew.is_significant())
+            .flat_map(|(peer, skew)| {
+                skew.warnings
+                    .into_iter()
+                    .map(move |warning| format!("warning: {}: {}", peer.name, warning))
+            })
+            .collect()
+    }
+
     /// Get cached peers without performing new discovery
     pub async fn get_cached_peers(&self) -> CLIResult<Vec<PeerInfo>> {
         let service_records = self.discovery.get_cached_peers().await;
@@ -190,6 +246,8 @@ impl DiscoverHandler {
                     .unwrap_or_default(),
                 trust_status: TrustStatus::Untrusted,
                 last_seen: Some(chrono::Utc::now()),
+                presence: Presence::from_record(&record).map(|p| p.status),
+                version: record.software_version().map(str::to_string),
             })
             .collect();
 
@@ -268,6 +326,8 @@ impl DiscoverHandler {
                                     .unwrap_or_default(),
                                 trust_status,
                                 last_seen: Some(chrono::Utc::now()),
+                                presence: Presence::from_record(&service_record).map(|p| p.status),
+                                version: service_record.software_version().map(str::to_string),
                             };
 
                             let mut peers = cached_peers.write().await;
@@ -352,6 +412,39 @@ impl Default for DiscoverHandler {
 mod tests {
     use super::*;
 
+    fn peer(name: &str, version: Option<&str>) -> PeerInfo {
+        PeerInfo {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            device_type: "laptop".to_string(),
+            connection_status: ConnectionStatus::Connected,
+            capabilities: vec![],
+            trust_status: TrustStatus::Trusted,
+            last_seen: None,
+            presence: None,
+            version: version.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_peers_table_marks_version_skew() {
+        let peers = vec![
+            peer("current", Some(env!("CARGO_PKG_VERSION"))),
+            peer("old", Some("0.0.1")),
+            peer("silent", None),
+        ];
+
+        let table = DiscoverHandler::peers_table(&peers);
+        assert_eq!(table.headers.last().map(String::as_str), Some("Version"));
+        assert_eq!(table.rows[0][4], env!("CARGO_PKG_VERSION"));
+        assert_eq!(table.rows[1][4], "0.0.1 (outdated)");
+        assert_eq!(table.rows[2][4], "unknown");
+
+        let warnings = DiscoverHandler::version_warnings(&peers);
+        assert!(!warnings.is_empty());
+        assert!(warnings.iter().all(|w| w.starts_with("warning: old: ")));
+    }
+
     #[tokio::test]
     async fn test_discover_handler_creation() {
         let handler = DiscoverHandler::new();
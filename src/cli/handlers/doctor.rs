@@ -0,0 +1,136 @@
+// Doctor command handler
+//
+// Implements "kizuna doctor": fills in the diagnostic configuration from the
+// transport and discovery settings and the proxy environment, resolves the
+// optional peer to ping and renders the results with a suggestion for every
+// problem found.
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::discovery::{DiscoveryConfigFile, KizunaDiscovery};
+use crate::doctor::{CheckStatus, Doctor, DoctorConfig, DoctorReport, PeerTarget};
+use crate::transport::{KizunaTransportConfig, ProxyConfig};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// How long to look for a named peer before giving up
+const PEER_LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Doctor command handler
+pub struct DoctorHandler {
+    config: DoctorConfig,
+}
+
+impl DoctorHandler {
+    /// Create a handler using the configured STUN, relay and rendezvous servers
+    pub fn new() -> Self {
+        let mut config = DoctorConfig::default();
+
+        let transport = KizunaTransportConfig::default();
+        config.proxy = transport.effective_proxy();
+        if let Some(nat) = transport.nat_traversal_config {
+            config.stun_servers = nat.stun_servers;
+        }
+        if let Some(relay) = transport.relay_config {
+            config.relay_servers = relay.relay_servers;
+        }
+        if let Ok(discovery) = DiscoveryConfigFile::load_from_default_locations() {
+            config.relay_servers.extend(discovery.discovery.rendezvous_servers);
+            config.proxy = discovery.discovery.proxy.or(config.proxy);
+        }
+
+        Self { config }
+    }
+
+    /// Check connectivity through a proxy given as a `socks5://` or `http://` URL
+    pub fn with_proxy(mut self, proxy: &str) -> CLIResult<Self> {
+        let proxy = proxy.parse::<ProxyConfig>().map_err(|e| CLIError::InvalidArgumentValue {
+            arg: "proxy".to_string(),
+            reason: e.to_string(),
+        })?;
+        self.config.proxy = Some(proxy);
+        Ok(self)
+    }
+
+    /// Use a specific diagnostic configuration
+    pub fn with_config(mut self, config: DoctorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Also ping a peer, given as `host:port` or a discovered name or ID
+    pub async fn with_peer(mut self, peer: &str) -> CLIResult<Self> {
+        let addresses = match peer.parse::<SocketAddr>() {
+            Ok(addr) => vec![addr],
+            Err(_) => Self::discover_peer(peer).await?,
+        };
+        self.config.peer = Some(PeerTarget {
+            name: peer.to_string(),
+            addresses,
+        });
+        Ok(self)
+    }
+
+    /// Addresses of a peer found by name or ID; empty if it did not show up
+    async fn discover_peer(peer: &str) -> CLIResult<Vec<SocketAddr>> {
+        let mut discovery = KizunaDiscovery::new();
+        discovery
+            .initialize()
+            .await
+            .map_err(|e| CLIError::discovery(format!("Failed to initialize discovery: {}", e)))?;
+
+        let records = discovery.discover_once(Some(PEER_LOOKUP_TIMEOUT)).await.unwrap_or_default();
+        let _ = discovery.shutdown().await;
+
+        Ok(records
+            .into_iter()
+            .find(|record| record.peer_id == peer || record.name.eq_ignore_ascii_case(peer))
+            .map(|record| record.addresses)
+            .unwrap_or_default())
+    }
+
+    /// Run all checks
+    pub async fn run(&self) -> DoctorReport {
+        Doctor::new(self.config.clone()).run().await
+    }
+
+    /// Format a doctor report for display
+    pub fn format_report(report: &DoctorReport) -> String {
+        let mut output = String::from("Kizuna connectivity check\n\n");
+
+        let width = report.checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        for check in &report.checks {
+            output.push_str(&format!(
+                "  [{:<4}] {:<width$}  {}\n",
+                check.status.as_str().to_uppercase(),
+                check.name,
+                check.detail,
+                width = width
+            ));
+        }
+
+        let problems = report.problems();
+        if problems.is_empty() {
+            output.push_str("\nNo problems found.\n");
+        } else {
+            output.push_str("\nSuggestions:\n");
+            for check in problems {
+                if let Some(suggestion) = &check.suggestion {
+                    output.push_str(&format!("  - {}: {}\n", check.name, suggestion));
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Exit code for scripted runs: non-zero only when a check failed
+    pub fn exit_code(report: &DoctorReport) -> i32 {
+        if report.overall() == CheckStatus::Fail { 1 } else { 0 }
+    }
+}
+
+impl Default for DoctorHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,471 @@
+// Export command handler
+//
+// Implements "kizuna export": writes discovery statistics, transfer history,
+// bandwidth usage and audit events as CSV or JSON for spreadsheets and SIEM
+// tools. Records are limited to a range of UTC dates. Discovery statistics
+// come from a discovery pass run for the export; everything else is read
+// from what this device has stored.
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::handlers::DiscoverHandler;
+use crate::cli::output::{CSVFormatter, JSONFormatter};
+use crate::cli::types::TableData;
+use crate::discovery::manager::StrategyStats;
+use crate::file_transfer::session::SessionManager;
+use crate::file_transfer::types::TransferSession;
+use crate::security::policy::AuditLogEntry;
+use crate::security::storage::EncryptedStorage;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Data sets that can be exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportKind {
+    /// Per-strategy discovery statistics
+    Discovery,
+    /// One row per transfer session
+    Transfers,
+    /// Bytes moved per day and peer
+    Bandwidth,
+    /// Security audit log events
+    Audit,
+}
+
+impl ExportKind {
+    /// Every kind, in the order they are listed in help
+    pub const ALL: [ExportKind; 4] = [
+        ExportKind::Discovery,
+        ExportKind::Transfers,
+        ExportKind::Bandwidth,
+        ExportKind::Audit,
+    ];
+
+    /// Name used on the command line
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportKind::Discovery => "discovery",
+            ExportKind::Transfers => "transfers",
+            ExportKind::Bandwidth => "bandwidth",
+            ExportKind::Audit => "audit",
+        }
+    }
+
+    /// Parse a command-line name
+    pub fn parse(value: &str) -> CLIResult<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str().eq_ignore_ascii_case(value))
+            .ok_or_else(|| CLIError::InvalidArgumentValue {
+                arg: "data".to_string(),
+                reason: format!("'{}' cannot be exported; use discovery, transfers, bandwidth or audit", value),
+            })
+    }
+}
+
+/// File formats an export can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// Parse a command-line name
+    pub fn parse(value: &str) -> CLIResult<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(CLIError::InvalidArgumentValue {
+                arg: "format".to_string(),
+                reason: format!("'{}' is not an export format; use csv or json", value),
+            }),
+        }
+    }
+}
+
+/// Inclusive range of UTC dates; an open end is unbounded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    /// Parse `YYYY-MM-DD` bounds as given to --from and --to
+    pub fn parse(from: Option<&str>, to: Option<&str>) -> CLIResult<Self> {
+        let date = |arg: &str, value: Option<&str>| -> CLIResult<Option<NaiveDate>> {
+            value
+                .map(|value| {
+                    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").map_err(|_| {
+                        CLIError::InvalidArgumentValue {
+                            arg: arg.to_string(),
+                            reason: format!("'{}' is not a date; use YYYY-MM-DD", value),
+                        }
+                    })
+                })
+                .transpose()
+        };
+
+        let range = Self {
+            from: date("from", from)?,
+            to: date("to", to)?,
+        };
+        if let (Some(from), Some(to)) = (range.from, range.to) && to < from {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "to".to_string(),
+                reason: "--to must not be before --from".to_string(),
+            });
+        }
+        Ok(range)
+    }
+
+    /// Check whether a moment falls on a date in the range
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        let date = time.date_naive();
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+
+    /// Check whether a Unix timestamp in seconds falls in the range
+    pub fn contains_unix(&self, secs: u64) -> bool {
+        unix_to_utc(secs).is_some_and(|time| self.contains(time))
+    }
+
+    fn is_unbounded(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+}
+
+/// Export command handler
+pub struct ExportHandler {
+    session_dir: PathBuf,
+    storage: Option<EncryptedStorage>,
+    audit_log: PathBuf,
+    discovery_timeout: Duration,
+}
+
+impl ExportHandler {
+    /// Read from the locations Kizuna stores data in by default
+    ///
+    /// `storage` opens the transfer journal, which is encrypted at rest.
+    pub fn new(storage: Option<EncryptedStorage>) -> CLIResult<Self> {
+        let data_dir = dirs::data_local_dir()
+            .ok_or_else(|| CLIError::config("Failed to get local data directory"))?
+            .join("kizuna");
+
+        Ok(Self {
+            session_dir: data_dir.join("sessions"),
+            storage,
+            audit_log: data_dir.join("audit.log"),
+            discovery_timeout: Duration::from_secs(5),
+        })
+    }
+
+    /// Read transfer history from another session directory
+    pub fn with_session_dir(mut self, session_dir: impl Into<PathBuf>) -> Self {
+        self.session_dir = session_dir.into();
+        self
+    }
+
+    /// Read audit events from another JSON lines log
+    pub fn with_audit_log(mut self, audit_log: impl Into<PathBuf>) -> Self {
+        self.audit_log = audit_log.into();
+        self
+    }
+
+    /// How long the discovery pass behind a discovery export runs
+    pub fn with_discovery_timeout(mut self, timeout: Duration) -> Self {
+        self.discovery_timeout = timeout;
+        self
+    }
+
+    /// Collect the rows of an export
+    pub async fn export(&self, kind: ExportKind, range: DateRange) -> CLIResult<TableData> {
+        match kind {
+            ExportKind::Discovery => Ok(discovery_table(&self.discovery_stats().await?, range)),
+            ExportKind::Transfers => Ok(transfers_table(&self.sessions().await?, range)),
+            ExportKind::Bandwidth => Ok(bandwidth_table(&self.sessions().await?, range)),
+            ExportKind::Audit => audit_table(&self.audit_log, range).await,
+        }
+    }
+
+    /// Render exported rows in a file format
+    pub fn render(table: TableData, format: ExportFormat) -> CLIResult<String> {
+        match format {
+            ExportFormat::Csv => CSVFormatter::new().format(table),
+            ExportFormat::Json => {
+                let formatter = JSONFormatter::new();
+                let rows = formatter.table_to_json(table)?;
+                formatter.format(rows, true).map(|json| json + "\n")
+            }
+        }
+    }
+
+    async fn sessions(&self) -> CLIResult<Vec<TransferSession>> {
+        if !self.session_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let manager = SessionManager::new(self.session_dir.clone(), self.storage.clone());
+        manager
+            .initialize()
+            .await
+            .map_err(|e| CLIError::transfer(format!("Failed to read transfer history: {}", e)))?;
+        Ok(manager.get_all_sessions().await)
+    }
+
+    async fn discovery_stats(&self) -> CLIResult<Vec<StrategyStats>> {
+        let mut handler = DiscoverHandler::new();
+        let stats = handler.strategy_stats(self.discovery_timeout).await?;
+        Ok(stats.into_values().collect())
+    }
+}
+
+/// One row per strategy, kept if it succeeded or failed within the range
+fn discovery_table(stats: &[StrategyStats], range: DateRange) -> TableData {
+    let mut stats: Vec<&StrategyStats> = stats
+        .iter()
+        .filter(|s| {
+            range.is_unbounded()
+                || [s.last_success, s.last_failure]
+                    .into_iter()
+                    .flatten()
+                    .any(|time| range.contains(time.into()))
+        })
+        .collect();
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+
+    TableData {
+        headers: headers(&[
+            "strategy",
+            "successes",
+            "failures",
+            "timeouts",
+            "peers_discovered",
+            "average_response_ms",
+            "availability_score",
+            "last_success",
+            "last_failure",
+        ]),
+        rows: stats
+            .into_iter()
+            .map(|s| {
+                vec![
+                    s.name.clone(),
+                    s.success_count.to_string(),
+                    s.failure_count.to_string(),
+                    s.timeout_count.to_string(),
+                    s.total_peers_discovered.to_string(),
+                    s.average_response_time.as_millis().to_string(),
+                    format!("{:.3}", s.availability_score),
+                    s.last_success.map(format_system_time).unwrap_or_default(),
+                    s.last_failure.map(format_system_time).unwrap_or_default(),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// One row per transfer session started within the range, oldest first
+fn transfers_table(sessions: &[TransferSession], range: DateRange) -> TableData {
+    let mut sessions: Vec<&TransferSession> = sessions
+        .iter()
+        .filter(|session| range.contains_unix(session.created_at))
+        .collect();
+    sessions.sort_by_key(|session| session.created_at);
+
+    TableData {
+        headers: headers(&[
+            "session_id",
+            "started_at",
+            "peer_id",
+            "state",
+            "transport",
+            "files",
+            "total_bytes",
+            "bytes_transferred",
+            "average_bytes_per_sec",
+        ]),
+        rows: sessions
+            .into_iter()
+            .map(|session| {
+                vec![
+                    session.session_id.to_string(),
+                    format_unix(session.created_at),
+                    session.peer_id.clone(),
+                    format!("{:?}", session.state).to_lowercase(),
+                    session.transport.as_str().to_string(),
+                    session.manifest.file_count.to_string(),
+                    session.manifest.total_size.to_string(),
+                    session.progress.bytes_transferred.to_string(),
+                    session.progress.average_speed.to_string(),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Bytes moved per UTC day and peer, from the transfer history
+fn bandwidth_table(sessions: &[TransferSession], range: DateRange) -> TableData {
+    let mut usage: BTreeMap<(NaiveDate, &str), (u64, u64)> = BTreeMap::new();
+    for session in sessions {
+        let Some(started) = unix_to_utc(session.created_at) else { continue };
+        if !range.contains(started) {
+            continue;
+        }
+        let entry = usage
+            .entry((started.date_naive(), session.peer_id.as_str()))
+            .or_default();
+        entry.0 += 1;
+        entry.1 += session.progress.bytes_transferred;
+    }
+
+    TableData {
+        headers: headers(&["date", "peer_id", "transfers", "bytes"]),
+        rows: usage
+            .into_iter()
+            .map(|((date, peer), (transfers, bytes))| {
+                vec![date.to_string(), peer.to_string(), transfers.to_string(), bytes.to_string()]
+            })
+            .collect(),
+    }
+}
+
+/// Audit events within the range, in the order they were logged
+///
+/// Lines that are not audit entries are skipped rather than failing the
+/// whole export.
+async fn audit_table(path: &Path, range: DateRange) -> CLIResult<TableData> {
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(CLIError::security(format!(
+                "Failed to read audit log {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    };
+
+    let rows = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<AuditLogEntry>(line).ok())
+        .filter(|entry| range.contains_unix(entry.event.timestamp))
+        .map(|entry| {
+            vec![
+                format_unix(entry.event.timestamp),
+                format!("{:?}", entry.severity).to_lowercase(),
+                format!("{:?}", entry.event.event_type),
+                entry.event.peer_id.map(|peer| peer.to_string()).unwrap_or_default(),
+                entry.event.details,
+            ]
+        })
+        .collect();
+
+    Ok(TableData {
+        headers: headers(&["timestamp", "severity", "event_type", "peer_id", "details"]),
+        rows,
+    })
+}
+
+fn headers(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn unix_to_utc(secs: u64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(i64::try_from(secs).ok()?, 0)
+}
+
+fn format_unix(secs: u64) -> String {
+    unix_to_utc(secs).map(|time| time.to_rfc3339()).unwrap_or_default()
+}
+
+fn format_system_time(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .map(|since| format_unix(since.as_secs()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_transfer::types::{TransferManifest, TransportProtocol};
+    use crate::security::policy::{SecurityEvent, SecurityEventType, Severity};
+
+    fn session(peer: &str, created_at: u64, bytes: u64) -> TransferSession {
+        let mut session = TransferSession::new(
+            TransferManifest::new("me".to_string()),
+            peer.to_string(),
+            TransportProtocol::Quic,
+        );
+        session.created_at = created_at;
+        session.progress.bytes_transferred = bytes;
+        session
+    }
+
+    #[test]
+    fn test_date_range_is_inclusive_and_validated() {
+        let range = DateRange::parse(Some("2024-01-01"), Some("2024-01-31")).unwrap();
+        assert!(range.contains_unix(1_704_067_200)); // 2024-01-01T00:00:00Z
+        assert!(range.contains_unix(1_706_745_599)); // 2024-01-31T23:59:59Z
+        assert!(!range.contains_unix(1_706_745_600)); // 2024-02-01T00:00:00Z
+
+        assert!(DateRange::parse(Some("01/02/2024"), None).is_err());
+        assert!(DateRange::parse(Some("2024-02-01"), Some("2024-01-01")).is_err());
+    }
+
+    #[test]
+    fn test_bandwidth_sums_per_day_and_peer() {
+        let sessions = vec![
+            session("nas", 1_704_070_000, 100),
+            session("nas", 1_704_080_000, 50),
+            session("phone", 1_704_080_000, 7),
+            session("nas", 1_704_200_000, 1),
+        ];
+        let range = DateRange::parse(None, Some("2024-01-01")).unwrap();
+
+        let table = bandwidth_table(&sessions, range);
+        assert_eq!(
+            table.rows,
+            vec![
+                vec!["2024-01-01", "nas", "2", "150"],
+                vec!["2024-01-01", "phone", "1", "7"],
+            ]
+        );
+        assert_eq!(transfers_table(&sessions, range).rows.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_audit_export_filters_and_renders_csv() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("audit.log");
+        let mut old = SecurityEvent::new(SecurityEventType::PairingFailure, None, "old".to_string());
+        old.timestamp = 1_600_000_000;
+        let mut recent = SecurityEvent::new(
+            SecurityEventType::ConnectionRejected,
+            None,
+            "blocked, by policy".to_string(),
+        );
+        recent.timestamp = 1_704_070_000;
+        let log = [old, recent]
+            .into_iter()
+            .map(|event| serde_json::to_string(&AuditLogEntry::new(event, Severity::Warning)).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(&path, format!("{}\nnot json\n", log)).unwrap();
+
+        let handler = ExportHandler::new(None).unwrap().with_audit_log(&path);
+        let range = DateRange::parse(Some("2024-01-01"), None).unwrap();
+        let table = handler.export(ExportKind::Audit, range).await.unwrap();
+        assert_eq!(table.rows.len(), 1);
+
+        let csv = ExportHandler::render(table, ExportFormat::Csv).unwrap();
+        assert_eq!(
+            csv,
+            "timestamp,severity,event_type,peer_id,details\n\
+             2024-01-01T00:46:40+00:00,warning,ConnectionRejected,,\"blocked, by policy\"\n"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+// Get command handler
+//
+// Implements "kizuna get PEER:SHARE/PATH": asks the peer to send one file from
+// a folder it shares with us. The peer checks its share exports and starts a
+// normal transfer, so the file arrives through the incoming transfer flow.
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::handlers::peer::{require_peer, PeerSessions};
+use crate::remote_fs::{BrowseConfig, RemoteEntry, RemoteFileSystem, RemoteSpec};
+use crate::security::api::SecuritySystem;
+use crate::util::format_size;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A fetch the peer agreed to
+#[derive(Debug, Clone)]
+pub struct FetchStarted {
+    pub spec: RemoteSpec,
+    pub entry: RemoteEntry,
+    /// Transfer ID the peer assigned
+    pub transfer_id: Uuid,
+}
+
+/// Get command handler
+pub struct GetHandler;
+
+impl GetHandler {
+    /// Parse a `PEER:SHARE/PATH` argument
+    pub fn parse_remote(remote: &str) -> CLIResult<RemoteSpec> {
+        RemoteSpec::parse(remote).map_err(|e| CLIError::InvalidArgumentValue {
+            arg: "remote".to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Ask the peer named in `spec` to send the file
+    pub async fn fetch(spec: RemoteSpec) -> CLIResult<FetchStarted> {
+        let security = SecuritySystem::new()
+            .map_err(|e| CLIError::security(format!("Failed to open security system: {}", e)))?;
+        let sessions = PeerSessions::open(&security).await?;
+        let remote_fs = RemoteFileSystem::new(
+            BrowseConfig::default(),
+            Arc::clone(&sessions.provider),
+            security.trust_manager(),
+        )
+        .map_err(|e| CLIError::integration(format!("Remote files: {}", e)))?;
+
+        let address = require_peer(&spec.peer).await?;
+        let result = remote_fs.fetch(&address, &spec.path).await;
+        sessions.close("fetch requested").await;
+
+        let (entry, transfer_id) =
+            result.map_err(|e| CLIError::transfer(format!("Could not fetch {}: {}", spec, e)))?;
+        Ok(FetchStarted {
+            spec,
+            entry,
+            transfer_id,
+        })
+    }
+
+    /// Format a started fetch for display
+    pub fn format_started(started: &FetchStarted, output: Option<&str>) -> String {
+        let mut text = format!(
+            "{} is sending {} ({})\n  Transfer: {}\n",
+            started.spec.peer,
+            started.entry.path,
+            format_size(started.entry.size),
+            started.transfer_id
+        );
+        match output {
+            Some(dir) => text.push_str(&format!("Accept it with: kizuna receive --output {}\n", dir)),
+            None => text.push_str("Accept it with: kizuna receive\n"),
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote_fs::EntryKind;
+
+    #[test]
+    fn test_parse_remote() {
+        let spec = GetHandler::parse_remote("laptop:projects/report.pdf").unwrap();
+        assert_eq!(spec.peer, "laptop");
+        assert_eq!(spec.path, "projects/report.pdf");
+
+        assert!(matches!(
+            GetHandler::parse_remote("projects/report.pdf"),
+            Err(CLIError::InvalidArgumentValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_format_started() {
+        let started = FetchStarted {
+            spec: GetHandler::parse_remote("laptop:projects/report.pdf").unwrap(),
+            entry: RemoteEntry {
+                name: "report.pdf".to_string(),
+                path: "projects/report.pdf".to_string(),
+                kind: EntryKind::File,
+                size: 2048,
+                modified: None,
+            },
+            transfer_id: Uuid::nil(),
+        };
+        let output = GetHandler::format_started(&started, Some("downloads"));
+        assert!(output.starts_with("laptop is sending projects/report.pdf"));
+        assert!(output.contains("kizuna receive --output downloads"));
+    }
+}
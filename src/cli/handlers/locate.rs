@@ -0,0 +1,101 @@
+// Locate command handler
+//
+// Implements "kizuna locate PEER": asks the peer to ring until someone
+// acknowledges it there, or with --stop to end a ring early. The peer decides
+// whether we may locate it; its reply says whether it is ringing.
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::handlers::peer::{command_api, require_peer};
+use crate::command_execution::{LocateAction, LocateRequest, LocateResult};
+
+/// Locate command handler
+pub struct LocateHandler;
+
+impl LocateHandler {
+    /// Build the request for `action`, checking the message length
+    pub fn request(action: LocateAction, message: Option<&str>, requester: String) -> CLIResult<LocateRequest> {
+        let mut request = match action {
+            LocateAction::Ring => LocateRequest::ring(requester),
+            LocateAction::Stop => LocateRequest::stop(requester),
+        };
+        if let Some(message) = message {
+            request = request.with_message(message);
+        }
+        request.validate().map_err(|e| CLIError::InvalidArgumentValue {
+            arg: "message".to_string(),
+            reason: e.to_string(),
+        })?;
+        Ok(request)
+    }
+
+    /// Send the request to a peer, returning its acknowledgement
+    pub async fn locate(peer: &str, action: LocateAction, message: Option<&str>) -> CLIResult<LocateResult> {
+        let (api, local_peer_id) = command_api().await?;
+        let request = Self::request(action, message, local_peer_id)?;
+        let address = require_peer(peer).await?;
+        let result = api
+            .locate(request, &address)
+            .await
+            .map_err(|e| CLIError::execution(format!("Locate request to {} failed: {}", peer, e)));
+        let _ = api.disconnect(&address.peer_id).await;
+        result
+    }
+
+    /// Whether the peer did what was asked
+    pub fn acknowledged(action: LocateAction, result: &LocateResult) -> bool {
+        result.success && result.ringing == (action == LocateAction::Ring)
+    }
+
+    /// Format the peer's acknowledgement for display
+    pub fn format_result(peer: &str, action: LocateAction, result: &LocateResult) -> String {
+        if !result.success {
+            return format!(
+                "{} refused the request: {}\n",
+                peer,
+                result.error.as_deref().unwrap_or("no reason given")
+            );
+        }
+        match (action, result.ringing) {
+            (LocateAction::Ring, true) => format!("{} is ringing; acknowledge it on that device to stop\n", peer),
+            (LocateAction::Ring, false) => format!("{} acknowledged the request but is not ringing\n", peer),
+            (LocateAction::Stop, false) => format!("{} stopped ringing\n", peer),
+            (LocateAction::Stop, true) => format!("{} acknowledged the request but is still ringing\n", peer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_execution::MAX_LOCATE_MESSAGE_LEN;
+
+    fn result(success: bool, ringing: bool) -> LocateResult {
+        LocateResult {
+            request_id: uuid::Uuid::nil(),
+            success,
+            ringing,
+            error: (!success).then(|| "not allowed".to_string()),
+            completed_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_request_checks_message_length() {
+        let request = LocateHandler::request(LocateAction::Ring, Some("it's under the sofa"), "me".to_string()).unwrap();
+        assert_eq!(request.action, LocateAction::Ring);
+        assert_eq!(request.message.as_deref(), Some("it's under the sofa"));
+
+        let long = "x".repeat(MAX_LOCATE_MESSAGE_LEN + 1);
+        assert!(LocateHandler::request(LocateAction::Ring, Some(&long), "me".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_acknowledged() {
+        assert!(LocateHandler::acknowledged(LocateAction::Ring, &result(true, true)));
+        assert!(!LocateHandler::acknowledged(LocateAction::Ring, &result(true, false)));
+        assert!(LocateHandler::acknowledged(LocateAction::Stop, &result(true, false)));
+        assert!(!LocateHandler::acknowledged(LocateAction::Ring, &result(false, false)));
+        assert!(LocateHandler::format_result("phone", LocateAction::Ring, &result(false, false))
+            .contains("refused the request: not allowed"));
+    }
+}
@@ -1,23 +1,64 @@
 // Command handler module
 
 mod batch;
+mod bench;
+#[cfg(feature = "clipboard")]
 mod clipboard;
 mod discover;
+mod doctor;
+mod export;
+mod get;
+#[cfg(feature = "command-execution")]
+mod locate;
+mod msg;
+mod notify;
+#[cfg(feature = "command-execution")]
+mod open_on;
+mod peer;
+mod ping;
+#[cfg(feature = "streaming")]
+mod recordings;
+mod security;
 #[cfg(feature = "streaming")]
 mod streaming;
 mod transfer;
+mod transport;
+#[cfg(feature = "self-update")]
+mod update;
+mod wipe;
 
 pub use batch::{
     BatchOperationArgs, BatchOperationHandler, BatchOperationItem, BatchOperationResult,
     BatchOperationStatus, BatchProgressInfo,
 };
+pub use bench::BenchHandler;
+#[cfg(feature = "clipboard")]
 pub use clipboard::{ClipboardAction, ClipboardArgs, ClipboardHandler, ClipboardResult};
 pub use discover::DiscoverHandler;
+pub use doctor::DoctorHandler;
+pub use export::{DateRange, ExportFormat, ExportHandler, ExportKind};
+pub use get::{FetchStarted, GetHandler};
+#[cfg(feature = "command-execution")]
+pub use locate::LocateHandler;
+pub use msg::MsgHandler;
+pub use notify::{completion_notification, progress_notification, CompletionNotifier};
+#[cfg(feature = "command-execution")]
+pub use open_on::OpenOnHandler;
+pub use peer::PeerSessions;
+pub(crate) use peer::discover_peers;
+pub use ping::{PingHandler, PingOutcome, PING_EXIT_OK, PING_EXIT_REJECTED, PING_EXIT_UNREACHABLE};
+#[cfg(feature = "streaming")]
+pub use recordings::RecordingsHandler;
+pub use security::SecurityCheckHandler;
 #[cfg(feature = "streaming")]
 pub use streaming::{
     ExecHandler, NetworkDiagnostics, PeersHandler, StatusHandler, StreamingHandler, SystemStatus,
 };
 pub use transfer::TransferHandler;
+pub use transport::TransportDiagnoseHandler;
+#[cfg(feature = "self-update")]
+pub use update::{ReleaseAsset, ReleaseManifest, UpdateCheck, UpdateHandler, UpdateOutcome};
+pub use wipe::{WipeHandler, WIPE_CONFIRMATION};
 
 use crate::cli::error::{CLIError, CLIResult};
 use crate::cli::types::{CommandResult, OperationStatus, PeerInfo};
@@ -65,6 +106,36 @@ pub struct SendArgs {
     pub peer: String,
     pub compression: Option<bool>,
     pub encryption: Option<bool>,
+    /// Send even if the connection is metered
+    pub allow_metered: bool,
+    /// Show a desktop notification when the transfer finishes
+    pub notify: bool,
+    /// Only report what would be sent; see `TransferHandler::plan_send`
+    pub dry_run: bool,
+    /// Further peers that get the same files; see `TransferHandler::handle_send_many`
+    pub also_to: Vec<String>,
+    /// Let receivers on the same LAN share chunks with each other
+    pub swarm: bool,
+    /// Name piped data is saved under when `files` is just "-" for stdin
+    pub name: Option<String>,
+}
+
+impl SendArgs {
+    /// Every peer the files go to, in order and without repeats
+    pub fn targets(&self) -> Vec<String> {
+        let mut targets: Vec<String> = Vec::new();
+        for peer in std::iter::once(&self.peer).chain(&self.also_to) {
+            if !targets.contains(peer) {
+                targets.push(peer.clone());
+            }
+        }
+        targets
+    }
+
+    /// Whether the data comes from stdin rather than files
+    pub fn is_piped(&self) -> bool {
+        matches!(self.files.as_slice(), [file] if crate::file_transfer::pipe::is_stdio(file))
+    }
 }
 
 /// Transfer result
@@ -74,11 +145,24 @@ pub struct TransferResult {
     pub status: OperationStatus,
 }
 
+/// Result of sending the same files to several peers
+#[derive(Debug, Clone)]
+pub struct FanOutResult {
+    /// Peers the send started for, each with its own operation
+    pub transfers: Vec<(String, TransferResult)>,
+    /// Peers the send could not start for, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
 /// Receive command arguments
 #[derive(Debug, Clone)]
 pub struct ReceiveArgs {
     pub download_path: Option<std::path::PathBuf>,
     pub auto_accept: bool,
+    /// Show a desktop notification when the transfer finishes
+    pub notify: bool,
+    /// Write the received data to stdout instead of saving it
+    pub to_stdout: bool,
 }
 
 /// Receive command result
@@ -95,6 +179,8 @@ pub struct StreamArgs {
     pub quality: Option<String>,
     pub record: bool,
     pub output_file: Option<std::path::PathBuf>,
+    /// Show a desktop notification when the stream ends
+    pub notify: bool,
 }
 
 /// Stream command result
@@ -0,0 +1,113 @@
+// Msg command handler
+//
+// Implements "kizuna msg": sends one text message to a trusted peer, or shows
+// the conversation with it. A message to a peer that cannot be reached stays
+// queued in the local history unless --no-queue was given.
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::handlers::peer::{require_peer, PeerSessions};
+use crate::messaging::{ChatMessage, Direction, MessageStatus, MessagingConfig, MessagingSystem};
+use crate::security::api::SecuritySystem;
+use std::sync::Arc;
+
+/// Msg command handler
+pub struct MsgHandler {
+    system: MessagingSystem,
+    sessions: PeerSessions,
+}
+
+impl MsgHandler {
+    /// Open the message history and the security system
+    pub async fn new() -> CLIResult<Self> {
+        let security = SecuritySystem::new()
+            .map_err(|e| CLIError::security(format!("Failed to open security system: {}", e)))?;
+        let storage = security.storage().ok_or_else(|| {
+            CLIError::security("Message history is always encrypted; turn encryption at rest back on")
+        })?;
+        let sessions = PeerSessions::open(&security).await?;
+        let system = MessagingSystem::new(
+            MessagingConfig::default(),
+            Arc::clone(&sessions.provider),
+            security.encryption_engine(),
+            security.trust_manager(),
+            storage,
+        )
+        .await
+        .map_err(|e| CLIError::integration(format!("Messaging: {}", e)))?;
+
+        Ok(Self { system, sessions })
+    }
+
+    /// Send `text` to a peer, returning the message as stored
+    ///
+    /// With `no_queue` a peer that cannot be reached is an error and the
+    /// message is marked failed instead of waiting in the queue.
+    pub async fn send(&self, peer: &str, text: &str, no_queue: bool) -> CLIResult<ChatMessage> {
+        let address = require_peer(peer).await?;
+        let result = self.system.send(&address, text).await;
+        self.sessions.close("message sent").await;
+
+        let message = result.map_err(|e| CLIError::integration(format!("Messaging: {}", e)))?;
+        if no_queue && message.status == MessageStatus::Queued {
+            let _ = self.system.history().advance(&message.id, MessageStatus::Failed).await;
+            return Err(CLIError::execution(format!("Peer '{}' could not be reached", peer)));
+        }
+        Ok(message)
+    }
+
+    /// Stored conversation with a peer, oldest first
+    pub async fn conversation(&self, peer: &str) -> CLIResult<Vec<ChatMessage>> {
+        let address = require_peer(peer).await?;
+        Ok(self.system.conversation(&address.peer_id).await)
+    }
+
+    /// Format the result of a send for display
+    pub fn format_sent(peer: &str, message: &ChatMessage) -> String {
+        match message.status {
+            MessageStatus::Queued => format!("Queued for {}; it will be sent when the peer is reachable\n", peer),
+            status => format!("Message to {} {}\n", peer, status),
+        }
+    }
+
+    /// Format a conversation for display
+    pub fn format_conversation(peer: &str, messages: &[ChatMessage]) -> String {
+        if messages.is_empty() {
+            return format!("No messages with {}\n", peer);
+        }
+
+        let mut output = String::new();
+        for message in messages {
+            let time = chrono::DateTime::from_timestamp_millis(message.sent_at as i64)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            let author = match message.direction {
+                Direction::Outgoing => "me",
+                Direction::Incoming => peer,
+            };
+            output.push_str(&format!("[{}] {} ({}): {}\n", time, author, message.status, message.text));
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_sent_reports_queueing() {
+        let mut message = ChatMessage::outgoing("ab".repeat(32), "on my way");
+        assert!(MsgHandler::format_sent("laptop", &message).contains("Queued"));
+
+        message.advance(MessageStatus::Sent);
+        assert_eq!(MsgHandler::format_sent("laptop", &message), "Message to laptop sent\n");
+    }
+
+    #[test]
+    fn test_format_conversation() {
+        let outgoing = ChatMessage::outgoing("ab".repeat(32), "on my way");
+        let output = MsgHandler::format_conversation("laptop", &[outgoing]);
+        assert!(output.contains("me (queued): on my way"));
+        assert_eq!(MsgHandler::format_conversation("laptop", &[]), "No messages with laptop\n");
+    }
+}
@@ -0,0 +1,498 @@
+// Desktop notifications for CLI operations
+//
+// Transfers and streams started from the CLI can run for a long time. When
+// one the user asked to hear about completes or fails, a desktop
+// notification is shown through the command execution NotificationManager,
+// so the user does not have to keep watching the terminal. Operations that
+// finish quickly are not announced. Clicking a notification brings the
+// terminal the operation was started from back to the front where the
+// platform supports it.
+//
+// Where the platform can replace a notification in place, a long transfer
+// gets a single progress notification that is updated every few seconds
+// and turns into the completion notification, with an "Open folder" action,
+// when the transfer ends.
+//
+// Devices banned by attack detection are announced as well, whether or not
+// an operation is running.
+
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::cli::types::{NotificationSettings, OperationState, OperationStatus, OperationType};
+use crate::command_execution::handoff::{LaunchBackend, SystemLauncher};
+use crate::command_execution::notification::{create_notification, NotificationActivation, NotificationManager};
+use crate::command_execution::types::{Notification, NotificationAction, NotificationPriority, NotificationType};
+use crate::security::policy::AttackAlert;
+use crate::util::format_size;
+
+/// Sender recorded on notifications about local operations
+const LOCAL_SENDER: &str = "local";
+
+/// `launch` argument prefix identifying the operation a notification is about
+const OPERATION_LAUNCH_PREFIX: &str = "operation/";
+
+/// Action on completed transfer notifications that opens the transfer's folder
+const OPEN_FOLDER_ACTION: &str = "open-folder";
+
+/// Shortest time between two updates of a progress notification
+const PROGRESS_UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Folders of finished transfers, looked up when "Open folder" is clicked
+type FolderMap = Arc<Mutex<HashMap<Uuid, PathBuf>>>;
+
+/// An operation whose outcome should be announced
+#[derive(Debug, Default)]
+struct Watched {
+    /// Folder offered by the "Open folder" action once the operation completes
+    folder: Option<PathBuf>,
+    /// Progress notification on screen for the operation
+    progress: Option<ShownProgress>,
+}
+
+#[derive(Debug)]
+struct ShownProgress {
+    notification_id: Uuid,
+    updated_at: Instant,
+    percent: Option<u8>,
+}
+
+/// Announces finished operations on the desktop
+pub struct CompletionNotifier {
+    manager: Option<Arc<NotificationManager>>,
+    min_duration: Duration,
+    /// Whether the platform can update progress notifications in place
+    live_updates: bool,
+    watched: Mutex<HashMap<Uuid, Watched>>,
+    folders: FolderMap,
+}
+
+impl CompletionNotifier {
+    /// Create a notifier using the platform's notification system
+    ///
+    /// The notifier does nothing when notifications are turned off in the
+    /// config or the platform has no notification system.
+    pub fn new(settings: &NotificationSettings) -> Self {
+        let folders = FolderMap::default();
+        let manager = if settings.enabled {
+            match NotificationManager::new() {
+                Ok(manager) => {
+                    let clicked_folders = Arc::clone(&folders);
+                    manager.on_activation(move |activation| focus_operation(&clicked_folders, activation));
+                    Some(Arc::new(manager))
+                }
+                Err(e) => {
+                    log::debug!("Desktop notifications unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        Self::build(manager, settings, folders)
+    }
+
+    /// Create a notifier using an existing notification manager
+    ///
+    /// Clicks are handled by whatever activation handler the manager has.
+    pub fn with_manager(manager: Arc<NotificationManager>, settings: &NotificationSettings) -> Self {
+        Self::build(settings.enabled.then_some(manager), settings, FolderMap::default())
+    }
+
+    fn build(manager: Option<Arc<NotificationManager>>, settings: &NotificationSettings, folders: FolderMap) -> Self {
+        let live_updates = manager
+            .as_ref()
+            .is_some_and(|manager| manager.get_capabilities().supports_replace);
+        Self {
+            manager,
+            min_duration: Duration::from_secs(settings.min_duration_secs),
+            live_updates,
+            watched: Mutex::new(HashMap::new()),
+            folders,
+        }
+    }
+
+    /// Whether notifications will be shown
+    pub fn is_enabled(&self) -> bool {
+        self.manager.is_some()
+    }
+
+    /// Announce the outcome of an operation when it finishes
+    pub fn watch(&self, operation_id: Uuid) {
+        self.watch_transfer(operation_id, None);
+    }
+
+    /// Announce a transfer, offering to open `folder` when it completes
+    pub fn watch_transfer(&self, operation_id: Uuid, folder: Option<PathBuf>) {
+        if self.is_enabled() {
+            self.watched
+                .lock()
+                .expect("Lock poisoned")
+                .insert(operation_id, Watched { folder, progress: None });
+        }
+    }
+
+    /// Called when an operation reports progress
+    ///
+    /// Once a watched operation has run for the minimum duration, its
+    /// progress is shown in a notification that is updated in place at most
+    /// every few seconds. Nothing is shown on platforms that would stack up
+    /// a new notification for every update.
+    pub async fn progress(&self, operation: &OperationStatus) {
+        let Some(manager) = &self.manager else {
+            return;
+        };
+        if !self.live_updates || operation.status != OperationState::InProgress {
+            return;
+        }
+        let elapsed = (Utc::now() - operation.started_at).to_std().unwrap_or_default();
+        if elapsed < self.min_duration {
+            return;
+        }
+
+        let percent = progress_percent(operation);
+        let (notification, first) = {
+            let mut watched = self.watched.lock().expect("Lock poisoned");
+            let Some(entry) = watched.get_mut(&operation.operation_id) else {
+                return;
+            };
+            let notification_id = match &entry.progress {
+                Some(shown) if shown.updated_at.elapsed() < PROGRESS_UPDATE_INTERVAL || shown.percent == percent => {
+                    return;
+                }
+                Some(shown) => shown.notification_id,
+                None => Uuid::new_v4(),
+            };
+            let first = entry.progress.is_none();
+            entry.progress = Some(ShownProgress {
+                notification_id,
+                updated_at: Instant::now(),
+                percent,
+            });
+            (progress_notification(operation, notification_id), first)
+        };
+
+        let result = if first {
+            manager
+                .send_notification(notification, LOCAL_SENDER.to_string())
+                .await
+                .map(|_| ())
+        } else {
+            manager.update_notification(notification).await
+        };
+        if let Err(e) = result {
+            log::debug!("Failed to update progress notification: {}", e);
+        }
+    }
+
+    /// Called when an operation ends; announces it if it was watched
+    ///
+    /// Each operation is announced at most once, and cancelled operations
+    /// are only forgotten.
+    pub async fn finished(&self, operation: &OperationStatus) {
+        let Some(manager) = &self.manager else {
+            return;
+        };
+        if matches!(operation.status, OperationState::Starting | OperationState::InProgress) {
+            return;
+        }
+        let Some(watched) = self.watched.lock().expect("Lock poisoned").remove(&operation.operation_id) else {
+            return;
+        };
+        let shown_progress = watched.progress.map(|shown| shown.notification_id);
+
+        let elapsed = (Utc::now() - operation.started_at).to_std().unwrap_or_default();
+        let Some(mut notification) = completion_notification(operation, elapsed, self.min_duration) else {
+            // Cancelled operations take their progress notification with them
+            if let Some(notification_id) = shown_progress {
+                let _ = manager.cancel_notification(notification_id).await;
+            }
+            return;
+        };
+
+        if operation.status == OperationState::Completed && let Some(folder) = watched.folder {
+            notification.actions.push(NotificationAction {
+                id: OPEN_FOLDER_ACTION.to_string(),
+                label: "Open folder".to_string(),
+            });
+            self.folders
+                .lock()
+                .expect("Lock poisoned")
+                .insert(operation.operation_id, folder);
+        }
+
+        // The progress notification turns into the completion notification
+        let result = match shown_progress {
+            Some(notification_id) => {
+                notification.notification_id = notification_id;
+                manager.update_notification(notification).await
+            }
+            None => manager
+                .send_notification(notification, LOCAL_SENDER.to_string())
+                .await
+                .map(|_| ()),
+        };
+        if let Err(e) = result {
+            log::warn!("Failed to show desktop notification: {}", e);
+        }
+    }
+
+    /// Tell the user a device was banned for suspicious activity
+    pub async fn attack_alert(&self, alert: &AttackAlert) {
+        let Some(manager) = &self.manager else {
+            return;
+        };
+        if let Err(e) = manager
+            .send_notification(attack_notification(alert), LOCAL_SENDER.to_string())
+            .await
+        {
+            log::warn!("Failed to show desktop notification: {}", e);
+        }
+    }
+}
+
+/// Notification for a device banned by attack detection
+pub fn attack_notification(alert: &AttackAlert) -> Notification {
+    let mut notification = create_notification(
+        "Blocked suspicious device".to_string(),
+        alert.describe(),
+        NotificationType::Warning,
+        LOCAL_SENDER.to_string(),
+    );
+    notification.priority = NotificationPriority::Critical;
+    notification
+}
+
+/// Progress notification for a running operation, shown as `notification_id`
+pub fn progress_notification(operation: &OperationStatus, notification_id: Uuid) -> Notification {
+    let subject = operation_subject(operation.operation_type);
+    let title = match progress_percent(operation) {
+        Some(percent) => format!("{} {}% done", subject, percent),
+        None => format!("{} in progress", subject),
+    };
+
+    let mut details = Vec::new();
+    if let Some(progress) = &operation.progress {
+        details.push(match progress.total {
+            Some(total) => format!("{} of {}", format_size(progress.current), format_size(total)),
+            None => format_size(progress.current),
+        });
+        if let Some(rate) = progress.rate.filter(|rate| *rate > 0.0) {
+            details.push(format!("{}/s", format_size(rate as u64)));
+        }
+        if let Some(eta) = progress.eta {
+            details.push(format!("{} left", format_elapsed(eta)));
+        }
+    }
+
+    let mut notification = create_notification(title, details.join(" · "), NotificationType::Info, LOCAL_SENDER.to_string());
+    notification.notification_id = notification_id;
+    // Updates should not make a sound or interrupt
+    notification.priority = NotificationPriority::Low;
+    notification.launch = Some(format!("{}{}", OPERATION_LAUNCH_PREFIX, operation.operation_id));
+    notification
+}
+
+/// Percentage done, if the operation knows its total size
+fn progress_percent(operation: &OperationStatus) -> Option<u8> {
+    let progress = operation.progress.as_ref()?;
+    let total = progress.total.filter(|total| *total > 0)?;
+    Some((progress.current.min(total) * 100 / total) as u8)
+}
+
+fn operation_subject(operation_type: OperationType) -> &'static str {
+    match operation_type {
+        OperationType::FileTransfer => "Transfer",
+        OperationType::CameraStream => "Stream",
+        OperationType::CommandExecution => "Command",
+        OperationType::ClipboardSync => "Clipboard sync",
+    }
+}
+
+/// Notification for an operation that finished after `elapsed`
+///
+/// Returns `None` for operations still running, cancelled by the user, or
+/// finished in less than `min_duration`.
+pub fn completion_notification(
+    operation: &OperationStatus,
+    elapsed: Duration,
+    min_duration: Duration,
+) -> Option<Notification> {
+    if elapsed < min_duration {
+        return None;
+    }
+
+    let subject = operation_subject(operation.operation_type);
+    let (title, message, notification_type) = match &operation.status {
+        OperationState::Completed => {
+            let detail = operation
+                .progress
+                .as_ref()
+                .and_then(|progress| progress.message.clone())
+                .unwrap_or_else(|| "Done".to_string());
+            (
+                format!("{} finished", subject),
+                format!("{} after {}", detail, format_elapsed(elapsed)),
+                NotificationType::Success,
+            )
+        }
+        OperationState::Failed(reason) => (
+            format!("{} failed", subject),
+            format!("{} after {}", reason, format_elapsed(elapsed)),
+            NotificationType::Error,
+        ),
+        _ => return None,
+    };
+
+    let mut notification = create_notification(title, message, notification_type, LOCAL_SENDER.to_string());
+    if notification_type == NotificationType::Error {
+        notification.priority = NotificationPriority::High;
+    }
+    notification.launch = Some(format!("{}{}", OPERATION_LAUNCH_PREFIX, operation.operation_id));
+    Some(notification)
+}
+
+/// Activation handler for operation notifications
+fn focus_operation(folders: &FolderMap, activation: NotificationActivation) {
+    let Some(operation_id) = activation
+        .launch
+        .as_deref()
+        .and_then(|launch| launch.strip_prefix(OPERATION_LAUNCH_PREFIX))
+    else {
+        return;
+    };
+
+    if activation.action_id.as_deref() == Some(OPEN_FOLDER_ACTION) {
+        let folder = Uuid::parse_str(operation_id)
+            .ok()
+            .and_then(|id| folders.lock().expect("Lock poisoned").get(&id).cloned());
+        if let Some(folder) = folder && let Err(e) = SystemLauncher::new().open_path(&folder) {
+            log::warn!("Failed to open {}: {}", folder.display(), e);
+        }
+        return;
+    }
+
+    #[cfg(target_os = "windows")]
+    if !crate::command_execution::notification::windows::focus_console_window() {
+        log::debug!("Could not bring the terminal to the front");
+    }
+    log::debug!("Notification for operation {} clicked", operation_id);
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::types::ProgressInfo;
+
+    fn operation(status: OperationState) -> OperationStatus {
+        OperationStatus {
+            operation_id: Uuid::new_v4(),
+            operation_type: OperationType::FileTransfer,
+            peer_id: Uuid::new_v4(),
+            status,
+            progress: Some(ProgressInfo {
+                current: 0,
+                total: None,
+                rate: None,
+                eta: None,
+                message: Some("Transferring 2 files".to_string()),
+            }),
+            started_at: Utc::now(),
+            estimated_completion: None,
+        }
+    }
+
+    #[test]
+    fn test_completion_notification() {
+        let min = Duration::from_secs(10);
+
+        let completed = operation(OperationState::Completed);
+        let done = completion_notification(&completed, Duration::from_secs(75), min).unwrap();
+        assert_eq!(done.title, "Transfer finished");
+        assert_eq!(done.message, "Transferring 2 files after 1m 15s");
+        assert_eq!(done.notification_type, NotificationType::Success);
+        assert_eq!(done.launch, Some(format!("operation/{}", completed.operation_id)));
+
+        let failed = OperationState::Failed("Peer disconnected".to_string());
+        let failed = completion_notification(&operation(failed), Duration::from_secs(30), min).unwrap();
+        assert_eq!(failed.title, "Transfer failed");
+        assert_eq!(failed.priority, NotificationPriority::High);
+
+        // Quick, cancelled and running operations are not announced
+        assert!(completion_notification(&operation(OperationState::Completed), Duration::from_secs(3), min).is_none());
+        assert!(completion_notification(&operation(OperationState::Cancelled), Duration::from_secs(30), min).is_none());
+        assert!(completion_notification(&operation(OperationState::InProgress), Duration::from_secs(30), min).is_none());
+    }
+
+    #[test]
+    fn test_progress_notification() {
+        let mut running = operation(OperationState::InProgress);
+        running.progress = Some(ProgressInfo {
+            current: 512 * 1024 * 1024,
+            total: Some(2048 * 1024 * 1024),
+            rate: Some(4.0 * 1024.0 * 1024.0),
+            eta: Some(Duration::from_secs(384)),
+            message: None,
+        });
+
+        let notification_id = Uuid::new_v4();
+        let notification = progress_notification(&running, notification_id);
+        assert_eq!(notification.notification_id, notification_id);
+        assert_eq!(notification.title, "Transfer 25% done");
+        assert_eq!(notification.message, "512.0 MB of 2.0 GB · 4.0 MB/s · 6m 24s left");
+        assert_eq!(notification.priority, NotificationPriority::Low);
+        assert_eq!(notification.launch, Some(format!("operation/{}", running.operation_id)));
+
+        // Without a total the size so far is all there is to show
+        running.progress.as_mut().unwrap().total = None;
+        running.progress.as_mut().unwrap().rate = None;
+        running.progress.as_mut().unwrap().eta = None;
+        let notification = progress_notification(&running, notification_id);
+        assert_eq!(notification.title, "Transfer in progress");
+        assert_eq!(notification.message, "512.0 MB");
+    }
+
+    #[test]
+    fn test_attack_notification() {
+        let alert = AttackAlert {
+            peer_id: None,
+            address: Some("198.51.100.4".parse().unwrap()),
+            patterns: vec![crate::security::policy::SuspiciousPattern::PortScan],
+            banned_for_secs: 3600,
+        };
+        let notification = attack_notification(&alert);
+        assert_eq!(notification.title, "Blocked suspicious device");
+        assert!(notification.message.starts_with("Address 198.51.100.4 blocked for 60 minutes"));
+        assert_eq!(notification.priority, NotificationPriority::Critical);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_notifier_ignores_operations() {
+        let settings = NotificationSettings {
+            enabled: false,
+            ..NotificationSettings::default()
+        };
+        let notifier = CompletionNotifier::new(&settings);
+        assert!(!notifier.is_enabled());
+
+        let op = operation(OperationState::Completed);
+        notifier.watch(op.operation_id);
+        assert!(notifier.watched.lock().unwrap().is_empty());
+        notifier.progress(&op).await;
+        notifier.finished(&op).await;
+    }
+}
@@ -0,0 +1,77 @@
+// Open-on command handler
+//
+// Implements "kizuna open-on PEER URL": sends a handoff request asking the peer
+// to open the URL in its default browser. The peer decides whether we may hand
+// off and which URL schemes it accepts.
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::handlers::peer::{command_api, require_peer};
+use crate::command_execution::{HandoffRequest, HandoffResult, HandoffTarget};
+
+/// Open-on command handler
+pub struct OpenOnHandler;
+
+impl OpenOnHandler {
+    /// Ask a peer to open `url`, returning the peer's answer
+    pub async fn open(peer: &str, url: &str) -> CLIResult<HandoffResult> {
+        HandoffTarget::Url(url.to_string())
+            .validate()
+            .map_err(|e| CLIError::InvalidArgumentValue {
+                arg: "url".to_string(),
+                reason: e.to_string(),
+            })?;
+
+        let (api, local_peer_id) = command_api().await?;
+        let address = require_peer(peer).await?;
+        let result = api
+            .open_on(HandoffRequest::url(url, local_peer_id), &address)
+            .await
+            .map_err(|e| CLIError::execution(format!("Handoff to {} failed: {}", peer, e)));
+        let _ = api.disconnect(&address.peer_id).await;
+        result
+    }
+
+    /// Format the peer's answer for display
+    pub fn format_result(peer: &str, url: &str, result: &HandoffResult) -> String {
+        if result.success {
+            format!("Opened {} on {}\n", url, peer)
+        } else {
+            format!(
+                "{} did not open {}: {}\n",
+                peer,
+                url,
+                result.error.as_deref().unwrap_or("refused")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_open_rejects_malformed_url() {
+        let result = OpenOnHandler::open("laptop", "not a url").await;
+        assert!(matches!(result, Err(CLIError::InvalidArgumentValue { .. })));
+    }
+
+    #[test]
+    fn test_format_result() {
+        let mut result = HandoffResult {
+            request_id: uuid::Uuid::nil(),
+            success: true,
+            error: None,
+            completed_at: chrono::Utc::now(),
+        };
+        assert_eq!(
+            OpenOnHandler::format_result("laptop", "https://example.com", &result),
+            "Opened https://example.com on laptop\n"
+        );
+
+        result.success = false;
+        result.error = Some("peer is not allowed to hand off".to_string());
+        assert!(OpenOnHandler::format_result("laptop", "https://example.com", &result)
+            .ends_with("peer is not allowed to hand off\n"));
+    }
+}
@@ -0,0 +1,150 @@
+// Helpers shared by handlers that talk to one peer
+//
+// A peer can be named on the command line as `host:port`, by its peer ID or by
+// the name it advertises. Names and IDs are looked up with a short discovery
+// round. Sessions to peers come from `PeerSessions`, which also spreads
+// revocations to every trusted peer a command reaches.
+
+use crate::cli::error::{CLIError, CLIResult};
+#[cfg(feature = "command-execution")]
+use crate::command_execution::{
+    CommandExecutionApi, CommandExecutionConfig, CommandSecurityIntegration, CommandTransportIntegration,
+};
+use crate::discovery::{KizunaDiscovery, ServiceRecord};
+use crate::security::api::SecuritySystem;
+use crate::security::trust::RevocationGossip;
+#[cfg(feature = "command-execution")]
+use crate::transport::KizunaTransport;
+use crate::transport::{
+    ConnectionProvider, InboundRouter, PeerAddress, ProviderConfig, TcpTransport, TransportCapabilities,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long to look for a named peer before giving up
+const PEER_LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a command waits for revocation gossip before closing its sessions
+const GOSSIP_GRACE: Duration = Duration::from_secs(2);
+
+/// Sessions a command opens to peers
+///
+/// Streams peers open on these sessions are dispatched through `inbound`.
+pub struct PeerSessions {
+    pub provider: Arc<ConnectionProvider>,
+    pub inbound: InboundRouter,
+    gossip: Option<RevocationGossip>,
+}
+
+impl PeerSessions {
+    /// Sessions over TCP that exchange revocations with every trusted peer
+    pub async fn open(security: &SecuritySystem) -> CLIResult<Self> {
+        let sessions = Self::new(Arc::new(ConnectionProvider::new(
+            Arc::new(TcpTransport::new()),
+            ProviderConfig::default(),
+        )));
+        let gossip = security
+            .revocation_gossip(Arc::clone(&sessions.provider))
+            .await
+            .map_err(|e| CLIError::security(format!("Failed to load device identity: {}", e)))?;
+        gossip.serve(&sessions.inbound);
+
+        Ok(Self {
+            gossip: Some(gossip),
+            ..sessions
+        })
+    }
+
+    /// Sessions on `provider`, without revocation gossip
+    pub fn new(provider: Arc<ConnectionProvider>) -> Self {
+        let inbound = InboundRouter::new();
+        inbound.attach(&provider);
+        Self {
+            provider,
+            inbound,
+            gossip: None,
+        }
+    }
+
+    /// Let revocation gossip finish, then close every session
+    pub async fn close(&self, reason: &str) {
+        if let Some(gossip) = &self.gossip {
+            gossip.finish(GOSSIP_GRACE).await;
+        }
+        self.provider.close_all(reason).await;
+    }
+}
+
+/// Address of a peer found by name or ID, or given as `host:port`
+pub(crate) async fn resolve_peer(peer: &str) -> CLIResult<Option<PeerAddress>> {
+    if let Ok(addr) = peer.parse::<SocketAddr>() {
+        return Ok(Some(PeerAddress::new(
+            addr.to_string(),
+            vec![addr],
+            vec!["tcp".to_string()],
+            TransportCapabilities::tcp(),
+        )));
+    }
+
+    Ok(discover_peers()
+        .await?
+        .into_iter()
+        .find(|record| record.peer_id == peer || record.name.eq_ignore_ascii_case(peer))
+        .map(|record| {
+            PeerAddress::new(
+                record.peer_id,
+                record.addresses,
+                vec!["tcp".to_string()],
+                TransportCapabilities::tcp(),
+            )
+        }))
+}
+
+/// Peers that answer a short discovery round
+pub(crate) async fn discover_peers() -> CLIResult<Vec<ServiceRecord>> {
+    let mut discovery = KizunaDiscovery::new();
+    discovery
+        .initialize()
+        .await
+        .map_err(|e| CLIError::discovery(format!("Failed to initialize discovery: {}", e)))?;
+
+    let records = discovery.discover_once(Some(PEER_LOOKUP_TIMEOUT)).await.unwrap_or_default();
+    let _ = discovery.shutdown().await;
+    Ok(records)
+}
+
+/// Like [`resolve_peer`], but a peer that cannot be found is an error
+pub(crate) async fn require_peer(peer: &str) -> CLIResult<PeerAddress> {
+    resolve_peer(peer)
+        .await?
+        .ok_or_else(|| CLIError::not_found(format!("Peer '{}' was not found on the network", peer)))
+}
+
+/// Command execution API for sending requests to peers, and this device's peer ID
+#[cfg(feature = "command-execution")]
+pub(crate) async fn command_api() -> CLIResult<(CommandExecutionApi, String)> {
+    let security = Arc::new(
+        SecuritySystem::new()
+            .map_err(|e| CLIError::security(format!("Failed to open security system: {}", e)))?,
+    );
+    let local_peer_id = security
+        .get_or_create_identity()
+        .await
+        .map_err(|e| CLIError::security(format!("Failed to load device identity: {}", e)))?
+        .derive_peer_id()
+        .to_hex();
+
+    let transport = KizunaTransport::new()
+        .await
+        .map_err(|e| CLIError::integration(format!("Failed to start transport: {}", e)))?;
+    let integration = CommandTransportIntegration::new(
+        Arc::new(transport),
+        Arc::new(CommandSecurityIntegration::new(security)),
+    );
+
+    Ok((
+        CommandExecutionApi::new(Arc::new(integration), CommandExecutionConfig::default()),
+        local_peer_id,
+    ))
+}
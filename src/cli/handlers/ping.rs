@@ -0,0 +1,161 @@
+// Ping command handler
+//
+// Implements "kizuna ping": resolves the peer, sends one authenticated
+// application-level ping and renders the reply. The exit code tells scripts
+// whether the peer's Kizuna process answered, not just whether a port is open.
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::handlers::peer::{resolve_peer, PeerSessions};
+use crate::liveness::{PingError, PingResult};
+use crate::security::api::SecuritySystem;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Exit code when the peer answered and the reply verified
+pub const PING_EXIT_OK: i32 = 0;
+/// Exit code when the peer could not be reached or did not answer in time
+pub const PING_EXIT_UNREACHABLE: i32 = 1;
+/// Exit code when the peer answered but refused or failed verification
+pub const PING_EXIT_REJECTED: i32 = 2;
+
+/// Result of a ping as reported to the user
+#[derive(Debug, Clone, Serialize)]
+pub struct PingOutcome {
+    pub peer: String,
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<PingResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub exit_code: i32,
+}
+
+/// Ping command handler
+pub struct PingHandler {
+    timeout: Duration,
+}
+
+impl PingHandler {
+    /// Create a handler with the default five second timeout
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// How long to wait for the reply, including connecting
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Ping a peer given as `host:port` or a discovered name or ID
+    pub async fn run(&self, peer: &str) -> CLIResult<PingOutcome> {
+        let security = SecuritySystem::new()
+            .map_err(|e| CLIError::security(format!("Failed to open security system: {}", e)))?;
+        let sessions = PeerSessions::open(&security).await?;
+        let liveness = security
+            .liveness(Arc::clone(&sessions.provider))
+            .await
+            .map_err(|e| CLIError::security(format!("Failed to load device identity: {}", e)))?;
+
+        let result = match resolve_peer(peer).await? {
+            Some(address) => liveness.ping(&address, self.timeout).await,
+            None => Err(PingError::Unreachable("Peer was not found on the network".to_string())),
+        };
+        sessions.close("ping finished").await;
+
+        Ok(Self::outcome(peer, result))
+    }
+
+    /// Turn a ping result into what gets reported
+    pub fn outcome(peer: &str, result: Result<PingResult, PingError>) -> PingOutcome {
+        match result {
+            Ok(reply) => PingOutcome {
+                peer: peer.to_string(),
+                reachable: true,
+                reply: Some(reply),
+                error: None,
+                exit_code: PING_EXIT_OK,
+            },
+            Err(e) => PingOutcome {
+                peer: peer.to_string(),
+                reachable: e.peer_responded(),
+                reply: None,
+                error: Some(e.to_string()),
+                exit_code: if e.peer_responded() { PING_EXIT_REJECTED } else { PING_EXIT_UNREACHABLE },
+            },
+        }
+    }
+
+    /// Format a ping outcome for display
+    pub fn format_outcome(outcome: &PingOutcome) -> String {
+        let Some(reply) = &outcome.reply else {
+            return format!(
+                "{}: {}\n",
+                outcome.peer,
+                outcome.error.as_deref().unwrap_or("no reply")
+            );
+        };
+
+        let mut output = format!("Reply from {} in {:.2} ms\n", outcome.peer, reply.rtt_ms);
+        output.push_str(&format!("  Peer ID:   {}\n", reply.peer_id));
+        output.push_str(&format!("  Software:  {}\n", reply.software_version));
+        output.push_str(&format!("  Protocol:  {}\n", reply.protocol_version));
+
+        let load = &reply.load;
+        let mut load_line = format!(
+            "{} sessions, {} streams",
+            load.active_sessions, load.open_streams
+        );
+        if let Some(average) = load.load_average {
+            load_line.push_str(&format!(", load {:.2} on {} CPUs", average, load.cpu_count));
+        }
+        if load.is_busy() {
+            load_line.push_str(" (busy)");
+        }
+        output.push_str(&format!("  Load:      {}\n", load_line));
+
+        if !reply.trusted {
+            output.push_str("  Note:      this device is not in your trust database\n");
+        }
+
+        output
+    }
+}
+
+impl Default for PingHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::liveness::LoadHints;
+
+    #[test]
+    fn test_outcome_exit_codes() {
+        let reply = PingResult {
+            peer_id: "ab".repeat(32),
+            rtt_ms: 1.5,
+            software_version: "kizuna/0.1.0".to_string(),
+            protocol_version: "1.0".to_string(),
+            load: LoadHints::default(),
+            trusted: true,
+        };
+        let ok = PingHandler::outcome("laptop", Ok(reply));
+        assert_eq!(ok.exit_code, PING_EXIT_OK);
+        assert!(PingHandler::format_outcome(&ok).contains("kizuna/0.1.0"));
+
+        let refused = PingHandler::outcome("laptop", Err(PingError::Refused("not trusted".to_string())));
+        assert!(refused.reachable);
+        assert_eq!(refused.exit_code, PING_EXIT_REJECTED);
+
+        let down = PingHandler::outcome("laptop", Err(PingError::Timeout(Duration::from_secs(5))));
+        assert!(!down.reachable);
+        assert_eq!(down.exit_code, PING_EXIT_UNREACHABLE);
+    }
+}
@@ -0,0 +1,254 @@
+// Recordings command handler
+//
+// Implements "kizuna recordings": lists and searches the recordings in the
+// local index, shows their playback details, edits their tags and notes, and
+// exports them to other formats. Recordings are named by ID or unique ID
+// prefix. Exporting runs ffmpeg and needs a build with the recording-export
+// feature.
+
+use chrono::{Local, NaiveDate, TimeZone};
+use std::time::{Duration, SystemTime};
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::types::CLIConfig;
+use crate::streaming::recording::{
+    default_recording_dir, RecordingCatalog, RecordingDetails, RecordingMetadata, RecordingQuery, PEER_TAG_PREFIX,
+};
+use crate::streaming::StreamError;
+use crate::util::format_size;
+
+/// Recordings command handler
+pub struct RecordingsHandler {
+    catalog: RecordingCatalog,
+}
+
+impl RecordingsHandler {
+    /// Open the recording index in the configured recording directory
+    pub fn new(config: &CLIConfig) -> CLIResult<Self> {
+        let path = config
+            .stream_settings
+            .recording_path
+            .clone()
+            .unwrap_or_else(default_recording_dir);
+        let catalog = RecordingCatalog::open(path)
+            .map_err(|e| CLIError::streaming(format!("Failed to open recordings: {}", e)))?;
+
+        Ok(Self { catalog })
+    }
+
+    /// Use an already opened catalog
+    pub fn with_catalog(catalog: RecordingCatalog) -> Self {
+        Self { catalog }
+    }
+
+    /// Look up a recording by full ID or unique ID prefix
+    pub async fn find(&self, id: &str) -> CLIResult<RecordingMetadata> {
+        self.catalog.find(id).await.map_err(catalog_error)
+    }
+
+    /// Recordings matching a query, newest first
+    pub async fn list(&self, query: &RecordingQuery) -> CLIResult<Vec<RecordingMetadata>> {
+        self.catalog.search(query).await.map_err(catalog_error)
+    }
+
+    /// A recording with its playback details
+    pub async fn show(&self, id: &str) -> CLIResult<RecordingDetails> {
+        self.catalog.details(id).await.map_err(catalog_error)
+    }
+
+    /// Edit a recording's tags and notes
+    ///
+    /// `notes` of `Some(None)` clears the notes; `None` leaves them alone.
+    pub async fn tag(
+        &self,
+        id: &str,
+        add: &[String],
+        remove: &[String],
+        notes: Option<Option<String>>,
+    ) -> CLIResult<RecordingMetadata> {
+        let mut recording = self.find(id).await?;
+        if !add.is_empty() || !remove.is_empty() {
+            recording = self
+                .catalog
+                .tag(recording.session_id, add, remove)
+                .await
+                .map_err(catalog_error)?;
+        }
+        if let Some(notes) = notes {
+            recording = self
+                .catalog
+                .set_notes(recording.session_id, notes)
+                .await
+                .map_err(catalog_error)?;
+        }
+        Ok(recording)
+    }
+
+    /// Transcode a recording
+    #[cfg(feature = "recording-export")]
+    pub async fn export(
+        &self,
+        id: &str,
+        options: crate::streaming::recording::ExportOptions,
+    ) -> CLIResult<crate::streaming::recording::ExportResult> {
+        let recording = self.find(id).await?;
+        crate::streaming::recording::RecordingExporter::new()
+            .export(&recording, &options)
+            .await
+            .map_err(|e| CLIError::streaming(format!("Export failed: {}", e)))
+    }
+
+    /// Render an export result for the terminal
+    #[cfg(feature = "recording-export")]
+    pub fn format_export(result: &crate::streaming::recording::ExportResult) -> String {
+        let mut output = format!(
+            "Exported to {} ({})\n",
+            result.output.display(),
+            format_size(result.file_size)
+        );
+        if let Some(thumbnail) = &result.thumbnail {
+            output.push_str(&format!("Thumbnail: {}\n", thumbnail.display()));
+        }
+        output
+    }
+
+    /// Render a recording list for the terminal
+    pub fn format_list(recordings: &[RecordingMetadata]) -> String {
+        if recordings.is_empty() {
+            return "No recordings found\n".to_string();
+        }
+
+        let mut output = format!(
+            "{:<10} {:<17} {:>9} {:>9}  {:<16} {}\n",
+            "ID", "CREATED", "LENGTH", "SIZE", "PEER", "TAGS"
+        );
+        for recording in recordings {
+            let id = recording.session_id.to_string();
+            output.push_str(&format!(
+                "{:<10} {:<17} {:>9} {:>9}  {:<16} {}\n",
+                &id[..8],
+                format_time(recording.created_at),
+                format_length(recording.duration),
+                format_size(recording.file_size),
+                recording.peer().unwrap_or("-"),
+                user_tags(recording).join(", ")
+            ));
+        }
+        output.push_str(&format!("\n{} recording(s)\n", recordings.len()));
+        output
+    }
+
+    /// Render one recording's details for the terminal
+    pub fn format_details(details: &RecordingDetails) -> String {
+        let recording = &details.recording;
+        let playback = &details.playback;
+
+        let mut output = format!("Recording {}\n", recording.session_id);
+        output.push_str(&format!("  Created:  {}\n", format_time(recording.created_at)));
+        output.push_str(&format!("  Length:   {}\n", format_length(recording.duration)));
+        output.push_str(&format!("  Size:     {}\n", format_size(recording.file_size)));
+        output.push_str(&format!("  Kind:     {:?}\n", recording.kind));
+        output.push_str(&format!("  Source:   {}\n", recording.stream_source));
+        if let Some(peer) = &details.peer {
+            output.push_str(&format!("  Peer:     {}\n", peer));
+        }
+        output.push_str(&format!("  File:     {}\n", playback.path.display()));
+        output.push_str(&format!(
+            "  Playback: {}{}{}\n",
+            playback.mime_type,
+            playback
+                .average_bitrate_kbps
+                .map(|kbps| format!(", {} kbps", kbps))
+                .unwrap_or_default(),
+            if playback.available { "" } else { " (file missing)" }
+        ));
+        let tags = user_tags(recording);
+        if !tags.is_empty() {
+            output.push_str(&format!("  Tags:     {}\n", tags.join(", ")));
+        }
+        if let Some(notes) = &recording.notes {
+            output.push_str(&format!("  Notes:    {}\n", notes));
+        }
+        output
+    }
+
+    /// Parse a `--since`/`--until` value
+    ///
+    /// Accepts a date (`2026-10-01`, local midnight), an RFC 3339 timestamp,
+    /// or an age such as `12h` or `7d` counted back from now.
+    pub fn parse_time(arg: &str, value: &str) -> CLIResult<SystemTime> {
+        let value = value.trim();
+        if let Some(age) = parse_span(value) {
+            return SystemTime::now()
+                .checked_sub(age)
+                .ok_or_else(|| invalid(arg, "is too far in the past"));
+        }
+        if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+            return Ok(time.into());
+        }
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+            .map(SystemTime::from)
+            .ok_or_else(|| invalid(arg, "must be a date (YYYY-MM-DD), a timestamp, or an age such as 7d"))
+    }
+
+    /// Parse a `--min-length`/`--max-length` value: seconds, or a number with
+    /// an `s`, `m` or `h` suffix
+    pub fn parse_length(arg: &str, value: &str) -> CLIResult<Duration> {
+        parse_span(value.trim()).ok_or_else(|| invalid(arg, "must be a length such as 90, 90s, 5m or 1h"))
+    }
+}
+
+/// Parse `<number>[s|m|h|d|w]`, where a bare number is seconds
+fn parse_span(value: &str) -> Option<Duration> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let number: u64 = number.parse().ok()?;
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number.checked_mul(unit).map(Duration::from_secs)
+}
+
+fn invalid(arg: &str, reason: &str) -> CLIError {
+    CLIError::InvalidArgumentValue {
+        arg: arg.to_string(),
+        reason: format!("{} {}", arg, reason),
+    }
+}
+
+fn catalog_error(error: StreamError) -> CLIError {
+    match error {
+        StreamError::SessionNotFound(id) => CLIError::not_found(format!("No recording with ID {}", id)),
+        StreamError::Configuration(reason) => CLIError::InvalidArgumentValue { arg: "recording".to_string(), reason },
+        error => CLIError::streaming(format!("Failed to read recordings: {}", error)),
+    }
+}
+
+/// Tags the user set, without the peer tag
+fn user_tags(recording: &RecordingMetadata) -> Vec<&str> {
+    recording
+        .tags
+        .iter()
+        .map(String::as_str)
+        .filter(|tag| !tag.starts_with(PEER_TAG_PREFIX))
+        .collect()
+}
+
+fn format_time(time: SystemTime) -> String {
+    chrono::DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn format_length(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
@@ -0,0 +1,178 @@
+// Security check command handler
+//
+// Implements "kizuna security check": collects the running security
+// configuration and file permissions, runs the hardening checks and
+// renders a scored report with remediation hints.
+
+use crate::cli::config::default_config_path;
+use crate::cli::error::CLIResult;
+use crate::security::api::SecuritySystem;
+use crate::security::policy::{
+    run_hardening_checks, CommandExecutionPosture, HardeningInput, HardeningInputs, HardeningReport,
+};
+use crate::transport::wire::{WIRE_VERSION_MAJOR, WIRE_VERSION_MINOR};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Security check command handler
+pub struct SecurityCheckHandler {
+    security: HardeningInput<Arc<SecuritySystem>>,
+    command_execution: HardeningInput<CommandExecutionPosture>,
+    browser_https_enforced: HardeningInput<bool>,
+    peer_protocols: Vec<(u8, u8)>,
+    extra_files: Vec<PathBuf>,
+}
+
+impl SecurityCheckHandler {
+    /// Create a handler that checks only local files and the protocol version
+    pub fn new() -> Self {
+        Self {
+            security: HardeningInput::NotApplicable,
+            command_execution: HardeningInput::NotApplicable,
+            browser_https_enforced: HardeningInput::NotApplicable,
+            peer_protocols: Vec::new(),
+            extra_files: Vec::new(),
+        }
+    }
+
+    /// Also check the security policy, encryption at rest and trusted peers
+    pub fn with_security(mut self, security: Arc<SecuritySystem>) -> Self {
+        self.security = HardeningInput::Known(security);
+        self
+    }
+
+    /// Report the checks that need the security system as not evaluated
+    pub fn security_unavailable(mut self, reason: impl Into<String>) -> Self {
+        self.security = HardeningInput::Unavailable(reason.into());
+        self
+    }
+
+    /// Check how command execution is configured
+    pub fn with_command_execution(mut self, posture: CommandExecutionPosture) -> Self {
+        self.command_execution = HardeningInput::Known(posture);
+        self
+    }
+
+    /// Check whether the browser server enforces HTTPS
+    pub fn with_browser_https(mut self, enforced: bool) -> Self {
+        self.browser_https_enforced = HardeningInput::Known(enforced);
+        self
+    }
+
+    /// Protocol versions peers on the network advertise
+    pub fn with_peer_protocols(mut self, versions: Vec<(u8, u8)>) -> Self {
+        self.peer_protocols = versions;
+        self
+    }
+
+    /// Check the permissions of an additional file
+    pub fn with_sensitive_file(mut self, path: PathBuf) -> Self {
+        self.extra_files.push(path);
+        self
+    }
+
+    /// Run the checks
+    pub async fn check(&self) -> CLIResult<HardeningReport> {
+        let mut inputs = HardeningInputs {
+            command_execution: self.command_execution.clone(),
+            browser_https_enforced: self.browser_https_enforced.clone(),
+            sensitive_files: Self::default_sensitive_files(),
+            local_protocol: (WIRE_VERSION_MAJOR, WIRE_VERSION_MINOR),
+            peer_protocols: self.peer_protocols.clone(),
+            ..Default::default()
+        };
+        inputs.sensitive_files.extend(self.extra_files.iter().cloned());
+
+        match &self.security {
+            HardeningInput::Known(security) => {
+                inputs.policy = security
+                    .get_policy()
+                    .await
+                    .map(HardeningInput::Known)
+                    .unwrap_or_else(|e| HardeningInput::Unavailable(format!("Failed to read security policy: {}", e)));
+                inputs.encrypt_at_rest = HardeningInput::Known(security.storage().is_some());
+                inputs.trusted_peers = security
+                    .trust_manager()
+                    .trust_database()
+                    .get_all_peers()
+                    .map(HardeningInput::Known)
+                    .unwrap_or_else(|e| HardeningInput::Unavailable(format!("Failed to read trusted peers: {}", e)));
+            }
+            HardeningInput::Unavailable(reason) => {
+                inputs.policy = HardeningInput::Unavailable(reason.clone());
+                inputs.encrypt_at_rest = HardeningInput::Unavailable(reason.clone());
+                inputs.trusted_peers = HardeningInput::Unavailable(reason.clone());
+            }
+            HardeningInput::NotApplicable => {}
+        }
+
+        Ok(run_hardening_checks(&inputs))
+    }
+
+    /// Config and data files created by Kizuna
+    fn default_sensitive_files() -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        if let Ok(config) = default_config_path() {
+            // Command history sits next to the config file
+            files.push(config.with_file_name("history"));
+            files.push(config);
+        }
+        if let Some(data_dir) = dirs::data_local_dir() {
+            files.push(data_dir.join("kizuna").join("trust.db"));
+        }
+        files
+    }
+
+    /// Render a report for the terminal
+    pub fn format_report(report: &HardeningReport) -> String {
+        let failures = report.failures();
+        let skipped = report.not_evaluated();
+        let mut output = format!("Security score: {}/100 ({})", report.score, report.grade());
+        if !skipped.is_empty() {
+            output.push_str(&format!(", {} checks not evaluated", skipped.len()));
+        }
+        output.push('\n');
+
+        if failures.is_empty() && skipped.is_empty() {
+            output.push_str("\nAll checks passed.\n");
+        } else if failures.is_empty() {
+            output.push_str("\nNo issues found by the checks that ran.\n");
+        } else {
+            output.push_str("\nIssues:\n");
+            for finding in failures {
+                output.push_str(&format!(
+                    "  [{}] {}: {}\n",
+                    finding.severity.as_str().to_uppercase(),
+                    finding.title,
+                    finding.detail
+                ));
+                if let Some(remediation) = &finding.remediation {
+                    output.push_str(&format!("      fix: {}\n", remediation));
+                }
+            }
+        }
+
+        if !skipped.is_empty() {
+            output.push_str("\nNot evaluated:\n");
+            for finding in skipped {
+                output.push_str(&format!("  [--] {}: {}\n", finding.title, finding.detail));
+            }
+        }
+
+        let passed = report.passed();
+        if !passed.is_empty() {
+            output.push_str("\nPassed:\n");
+            for finding in passed {
+                output.push_str(&format!("  [OK] {}\n", finding.title));
+            }
+        }
+
+        output
+    }
+}
+
+impl Default for SecurityCheckHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -9,10 +9,10 @@
 #![cfg(feature = "streaming")]
 
 use crate::cli::error::{CLIError, CLIResult};
-use crate::cli::handlers::{ExecArgs, ExecResult, StreamArgs, StreamResult};
+use crate::cli::handlers::{CompletionNotifier, ExecArgs, ExecResult, StreamArgs, StreamResult};
 use crate::cli::types::{
-    ConnectionStatus, OperationState, OperationStatus, OperationType, PeerInfo, ProgressInfo,
-    TrustStatus,
+    ConnectionStatus, OperationState, OperationStatus, OperationType, PeerInfo, PeerOverrideTable,
+    ProgressInfo, TrustStatus,
 };
 use crate::streaming::api::{Streaming, StreamingApi, StreamEvent, StreamEventHandler};
 use crate::streaming::{
@@ -31,6 +31,7 @@ use uuid::Uuid;
 struct CLIStreamEventHandler {
     active_operations: Arc<RwLock<std::collections::HashMap<Uuid, OperationStatus>>>,
     event_tx: Arc<RwLock<Option<mpsc::UnboundedSender<StreamEvent>>>>,
+    notifier: Arc<std::sync::RwLock<Option<Arc<CompletionNotifier>>>>,
 }
 
 #[async_trait]
@@ -102,8 +103,22 @@ impl StreamEventHandler for CLIStreamEventHandler {
             }
             _ => {}
         }
+
+        // Announce streams that ended on the desktop
+        let session_id = match &event {
+            StreamEvent::SessionStopped { session_id, .. }
+            | StreamEvent::StateChanged { session_id, .. }
+            | StreamEvent::Error { session_id: Some(session_id), .. } => Some(*session_id),
+            _ => None,
+        };
+        let finished = session_id.and_then(|id| operations.get(&id).cloned());
         drop(operations);
 
+        let notifier = self.notifier.read().expect("Lock poisoned").clone();
+        if let (Some(notifier), Some(op)) = (notifier, finished) {
+            notifier.finished(&op).await;
+        }
+
         // Send event notification
         if let Some(tx) = self.event_tx.read().await.as_ref() {
             let _ = tx.send(event);
@@ -121,6 +136,10 @@ pub struct StreamingHandler {
     security: Option<Arc<SecuritySystem>>,
     /// Event notification channel for CLI/TUI updates
     event_tx: Arc<RwLock<Option<mpsc::UnboundedSender<StreamEvent>>>>,
+    /// Desktop notifications for ended streams
+    notifier: Arc<std::sync::RwLock<Option<Arc<CompletionNotifier>>>>,
+    /// Settings for single peers from the user configuration
+    peer_overrides: PeerOverrideTable,
 }
 
 impl StreamingHandler {
@@ -135,6 +154,8 @@ impl StreamingHandler {
             active_operations,
             security: None,
             event_tx,
+            notifier: Arc::new(std::sync::RwLock::new(None)),
+            peer_overrides: PeerOverrideTable::default(),
         };
 
         // Register event handler for real-time updates
@@ -153,6 +174,8 @@ impl StreamingHandler {
             active_operations,
             security: None,
             event_tx,
+            notifier: Arc::new(std::sync::RwLock::new(None)),
+            peer_overrides: PeerOverrideTable::default(),
         };
 
         // Register event handler for real-time updates
@@ -172,6 +195,8 @@ impl StreamingHandler {
             active_operations,
             security: Some(security),
             event_tx,
+            notifier: Arc::new(std::sync::RwLock::new(None)),
+            peer_overrides: PeerOverrideTable::default(),
         };
 
         // Register event handler for real-time updates
@@ -185,6 +210,17 @@ impl StreamingHandler {
         self.security = Some(security);
     }
 
+    /// Apply per-peer settings, such as peers never allowed to view streams
+    pub fn with_peer_overrides(mut self, peer_overrides: PeerOverrideTable) -> Self {
+        self.peer_overrides = peer_overrides;
+        self
+    }
+
+    /// Announce ended streams on the desktop
+    pub fn set_notifier(&self, notifier: Arc<CompletionNotifier>) {
+        *self.notifier.write().expect("Lock poisoned") = Some(notifier);
+    }
+
     /// Subscribe to streaming events
     /// Returns a receiver that will get notified of streaming events
     pub async fn subscribe_events(&self) -> mpsc::UnboundedReceiver<StreamEvent> {
@@ -198,6 +234,7 @@ impl StreamingHandler {
         let event_handler = Arc::new(CLIStreamEventHandler {
             active_operations: Arc::clone(&self.active_operations),
             event_tx: Arc::clone(&self.event_tx),
+            notifier: Arc::clone(&self.notifier),
         });
 
         let streaming_api = Arc::clone(&self.streaming_api);
@@ -257,6 +294,11 @@ impl StreamingHandler {
             .write()
             .await
             .insert(operation_status.operation_id, operation_status.clone());
+        if args.notify {
+            if let Some(notifier) = self.notifier.read().expect("Lock poisoned").as_ref() {
+                notifier.watch(operation_id);
+            }
+        }
 
         Ok(StreamResult {
             operation_id,
@@ -352,6 +394,13 @@ impl StreamingHandler {
 
     /// Add viewer to stream
     pub async fn add_viewer(&self, session_id: Uuid, peer_id: String) -> CLIResult<Uuid> {
+        if !self.peer_overrides.for_peer(&peer_id).allows_streaming() {
+            return Err(CLIError::permission_denied(format!(
+                "Streaming to '{}' is turned off in its peer settings",
+                peer_id
+            )));
+        }
+
         let viewer_id = self
             .streaming_api
             .add_viewer(session_id, peer_id, ViewerPermissions::default())
@@ -525,6 +574,8 @@ impl PeersHandler {
                 capabilities: vec![],
                 trust_status: TrustStatus::Untrusted,
                 last_seen: None,
+                presence: None,
+                version: None,
             })
         }
     }
@@ -606,6 +657,7 @@ impl StatusHandler {
             active_streams,
             clipboard_sync_enabled: false, // Placeholder - would need clipboard handler
             discovery_enabled: true,
+            lockdown: crate::lockdown::Lockdown::global().state(),
         })
     }
 
@@ -640,6 +692,8 @@ pub struct SystemStatus {
     pub active_streams: usize,
     pub clipboard_sync_enabled: bool,
     pub discovery_enabled: bool,
+    /// Whether inbound remote access is locked down
+    pub lockdown: crate::lockdown::LockdownState,
 }
 
 /// Network diagnostics information
@@ -672,6 +726,7 @@ mod tests {
             quality: Some("medium".to_string()),
             record: false,
             output_file: None,
+            notify: false,
         };
 
         let result = handler.handle_stream(args).await;
@@ -686,6 +741,7 @@ mod tests {
             quality: Some("high".to_string()),
             record: true,
             output_file: Some(PathBuf::from("test_recording.mp4")),
+            notify: false,
         };
 
         let result = handler.handle_stream(args).await;
@@ -7,18 +7,27 @@
 // Requirements: 2.1, 2.2, 2.3, 2.5, 3.1, 3.2, 3.4, 3.5
 
 use crate::cli::error::{CLIError, CLIResult};
-use crate::cli::handlers::{ReceiveArgs, ReceiveResult, SendArgs, TransferResult};
-use crate::cli::types::{OperationState, OperationStatus, OperationType, ProgressInfo};
+use crate::cli::handlers::{CompletionNotifier, FanOutResult, ReceiveArgs, ReceiveResult, SendArgs, TransferResult};
+use crate::cli::types::{CLIConfig, OperationState, OperationStatus, OperationType, ProgressInfo};
 use crate::file_transfer::api::FileTransferSystem;
 use crate::file_transfer::progress::{ProgressCallback, EventCallback, TransferEvent};
-use crate::file_transfer::types::{PeerId, TransferState};
+use crate::file_transfer::dry_run::{ConflictKind, TransferPlan};
+use crate::file_transfer::pipe;
+use crate::file_transfer::types::{PeerId, TransferSession, TransferState};
+use crate::metered::{Initiator, TrafficDecision, TrafficMonitor, TrafficOperation};
 use crate::security::api::SecuritySystem;
+use crate::transport::{MuxStream, StreamHandler};
+use crate::util::format_size;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{mpsc, RwLock};
+use async_trait::async_trait;
+use tokio::sync::{mpsc, Mutex, RwLock};
 use uuid::Uuid;
 
+/// Inbound streams queued until a receive picks them up
+const INBOUND_STREAM_BACKLOG: usize = 16;
+
 /// File transfer command handler implementation with real-time event support
 /// Fully integrated with core file transfer system
 pub struct TransferHandler {
@@ -29,6 +38,15 @@ pub struct TransferHandler {
     event_tx: Arc<RwLock<Option<mpsc::UnboundedSender<TransferEvent>>>>,
     /// Progress notification channel for real-time updates
     progress_tx: Arc<RwLock<Option<mpsc::UnboundedSender<(Uuid, ProgressInfo)>>>>,
+    /// Connection cost policy checked before sending
+    traffic: Option<Arc<TrafficMonitor>>,
+    /// Desktop notifications for finished transfers
+    notifier: Arc<std::sync::RwLock<Option<Arc<CompletionNotifier>>>>,
+    /// Transfer settings and per-peer overrides from the user configuration
+    config: CLIConfig,
+    /// File transfer streams peers opened to us, handed over by the embedding app
+    inbound_tx: mpsc::Sender<(PeerId, MuxStream)>,
+    inbound_streams: Arc<Mutex<mpsc::Receiver<(PeerId, MuxStream)>>>,
 }
 
 impl TransferHandler {
@@ -39,11 +57,17 @@ impl TransferHandler {
             session_dir,
         ));
 
+        let (inbound_tx, inbound_rx) = mpsc::channel(INBOUND_STREAM_BACKLOG);
         let handler = Self {
             file_transfer,
             active_operations: Arc::new(RwLock::new(std::collections::HashMap::new())),
             event_tx: Arc::new(RwLock::new(None)),
             progress_tx: Arc::new(RwLock::new(None)),
+            traffic: None,
+            notifier: Arc::new(std::sync::RwLock::new(None)),
+            config: CLIConfig::default(),
+            inbound_tx,
+            inbound_streams: Arc::new(Mutex::new(inbound_rx)),
         };
 
         // Register event callbacks for real-time updates
@@ -54,11 +78,17 @@ impl TransferHandler {
 
     /// Create a new transfer handler with custom file transfer system
     pub fn with_file_transfer(file_transfer: Arc<FileTransferSystem>) -> Self {
+        let (inbound_tx, inbound_rx) = mpsc::channel(INBOUND_STREAM_BACKLOG);
         let handler = Self {
             file_transfer,
             active_operations: Arc::new(RwLock::new(std::collections::HashMap::new())),
             event_tx: Arc::new(RwLock::new(None)),
             progress_tx: Arc::new(RwLock::new(None)),
+            traffic: None,
+            notifier: Arc::new(std::sync::RwLock::new(None)),
+            config: CLIConfig::default(),
+            inbound_tx,
+            inbound_streams: Arc::new(Mutex::new(inbound_rx)),
         };
 
         // Register event callbacks for real-time updates
@@ -67,6 +97,46 @@ impl TransferHandler {
         handler
     }
 
+    /// Ask before large sends on metered connections
+    pub fn with_traffic_monitor(mut self, traffic: Arc<TrafficMonitor>) -> Self {
+        self.traffic = Some(traffic);
+        self
+    }
+
+    /// Apply the user's transfer settings and per-peer overrides, such as
+    /// bandwidth caps, to sends
+    pub fn with_config(mut self, config: CLIConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Announce finished transfers on the desktop
+    pub fn set_notifier(&self, notifier: Arc<CompletionNotifier>) {
+        *self.notifier.write().expect("Lock poisoned") = Some(notifier);
+    }
+
+    /// Ask the notifier, if any, to show a transfer's progress and announce
+    /// it when it finishes, offering to open `folder`
+    fn watch_operation(&self, operation_id: Uuid, folder: Option<PathBuf>) {
+        if let Some(notifier) = self.notifier.read().expect("Lock poisoned").as_ref() {
+            notifier.watch_transfer(operation_id, folder);
+        }
+    }
+
+    /// Total size of the files and directories being sent
+    fn total_size(paths: &[PathBuf]) -> u64 {
+        fn size_of(path: &std::path::Path) -> u64 {
+            match std::fs::symlink_metadata(path) {
+                Ok(meta) if meta.is_dir() => std::fs::read_dir(path)
+                    .map(|entries| entries.flatten().map(|entry| size_of(&entry.path())).sum())
+                    .unwrap_or(0),
+                Ok(meta) => meta.len(),
+                Err(_) => 0,
+            }
+        }
+        paths.iter().map(|path| size_of(path)).sum()
+    }
+
     /// Subscribe to transfer events
     /// Returns a receiver that will get notified of transfer events
     pub async fn subscribe_events(&self) -> mpsc::UnboundedReceiver<TransferEvent> {
@@ -93,10 +163,12 @@ impl TransferHandler {
         // Register progress callback
         let progress_ops = Arc::clone(&active_operations);
         let progress_notif = Arc::clone(&progress_tx);
+        let progress_notifier = Arc::clone(&self.notifier);
         tokio::spawn(async move {
             let callback: crate::file_transfer::progress::ProgressCallback = Arc::new(move |session_id, progress| {
                 let ops = Arc::clone(&progress_ops);
                 let notif = Arc::clone(&progress_notif);
+                let notifier = progress_notifier.read().expect("Lock poisoned").clone();
                 
                 tokio::spawn(async move {
                     let progress_info = ProgressInfo {
@@ -108,11 +180,17 @@ impl TransferHandler {
                     };
 
                     let mut operations = ops.write().await;
-                    if let Some(op) = operations.get_mut(&session_id) {
+                    let updated = operations.get_mut(&session_id).map(|op| {
                         op.progress = Some(progress_info.clone());
-                    }
+                        op.clone()
+                    });
                     drop(operations);
 
+                    // Keep the desktop progress notification current
+                    if let (Some(notifier), Some(op)) = (notifier, updated) {
+                        notifier.progress(&op).await;
+                    }
+
                     // Send progress notification
                     if let Some(tx) = notif.read().await.as_ref() {
                         let _ = tx.send((session_id, progress_info));
@@ -126,13 +204,16 @@ impl TransferHandler {
         // Register event callback
         let event_ops = Arc::clone(&active_operations);
         let event_tx = Arc::clone(&self.event_tx);
+        let event_notifier = Arc::clone(&self.notifier);
         tokio::spawn(async move {
             let callback: crate::file_transfer::progress::EventCallback = Arc::new(move |event| {
                 let ops = Arc::clone(&event_ops);
                 let tx = Arc::clone(&event_tx);
+                let notifier = event_notifier.read().expect("Lock poisoned").clone();
                 let event_clone = event.clone();
                 
                 tokio::spawn(async move {
+                    let mut finished = None;
                     match &event {
                         TransferEvent::Started { session_id, .. } => {
                             let mut operations = ops.write().await;
@@ -144,24 +225,32 @@ impl TransferHandler {
                             let mut operations = ops.write().await;
                             if let Some(op) = operations.get_mut(session_id) {
                                 op.status = OperationState::Completed;
+                                finished = Some(op.clone());
                             }
                         }
                         TransferEvent::Failed { session_id, error } => {
                             let mut operations = ops.write().await;
                             if let Some(op) = operations.get_mut(session_id) {
                                 op.status = OperationState::Failed(error.clone());
+                                finished = Some(op.clone());
                             }
                         }
                         TransferEvent::Cancelled { session_id } => {
                             let mut operations = ops.write().await;
                             if let Some(op) = operations.get_mut(session_id) {
                                 op.status = OperationState::Cancelled;
+                                finished = Some(op.clone());
                             }
                         }
                         _ => {}
                     }
                     drop(ops);
 
+                    // Announce finished transfers on the desktop
+                    if let (Some(notifier), Some(op)) = (notifier, finished) {
+                        notifier.finished(&op).await;
+                    }
+
                     // Send event notification
                     if let Some(event_tx) = tx.read().await.as_ref() {
                         let _ = event_tx.send(event_clone);
@@ -173,24 +262,109 @@ impl TransferHandler {
         });
     }
 
-    /// Handle send command
-    pub async fn handle_send(&self, args: SendArgs) -> CLIResult<TransferResult> {
-        // Validate files exist
+    /// Handle "send --dry-run": report what a send would transfer
+    ///
+    /// Builds the manifest and negotiates with the peer exactly as a send
+    /// would, but no data is moved and no operation is tracked.
+    pub async fn plan_send(&self, args: &SendArgs) -> CLIResult<TransferPlan> {
+        if args.is_piped() {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "dry-run".to_string(),
+                reason: "data from stdin cannot be planned without reading it".to_string(),
+            });
+        }
         for file in &args.files {
             if !file.exists() {
                 return Err(CLIError::file_not_found(file.display().to_string()));
             }
         }
 
-        // Initialize file transfer system
         self.file_transfer
-            .initialize()
+            .plan_send(args.files.clone(), args.peer.clone())
             .await
-            .map_err(|e| CLIError::transfer(format!("Failed to initialize file transfer: {}", e)))?;
+            .map_err(|e| CLIError::transfer(format!("Dry run failed: {}", e)))
+    }
+
+    /// Render a dry-run plan for the terminal
+    pub fn format_plan(plan: &TransferPlan) -> String {
+        let manifest = &plan.manifest;
+        let mut output = format!("Dry run: nothing was sent to {}\n", plan.peer_id);
+        output.push_str(&format!("  Transport: {}\n", plan.protocol.as_str()));
+        output.push_str(&format!(
+            "  Files:     {} ({})\n",
+            manifest.file_count,
+            format_size(manifest.total_size)
+        ));
+        match (plan.estimated_duration, plan.throughput_bps) {
+            (Some(duration), Some(rate)) => output.push_str(&format!(
+                "  Estimate:  {} at {}/s\n",
+                format_duration(duration),
+                format_size(rate)
+            )),
+            _ => output.push_str("  Estimate:  unknown, the link could not be measured\n"),
+        }
+
+        output.push('\n');
+        for file in &manifest.files {
+            output.push_str(&format!("  {:>10}  {}\n", format_size(file.size), file.path.display()));
+        }
+
+        if !plan.conflicts.is_empty() {
+            output.push_str(&format!("\n{} conflict(s):\n", plan.conflicts.len()));
+            for conflict in &plan.conflicts {
+                let detail = match &conflict.kind {
+                    ConflictKind::Duplicate { other } => format!("same destination as {}", other.display()),
+                    ConflictKind::Renamed { to } => format!("renamed to {} on Windows", to.display()),
+                    ConflictKind::Unusable { reason } => format!("cannot be sent: {}", reason),
+                };
+                output.push_str(&format!("  {}: {}\n", conflict.path.display(), detail));
+            }
+        }
+        output
+    }
+
+    /// Handle send command
+    pub async fn handle_send(&self, args: SendArgs) -> CLIResult<TransferResult> {
+        self.handle_send_from(args, tokio::io::stdin()).await
+    }
+
+    /// Handle send command, reading the data for "-" from `input`
+    pub async fn handle_send_from<R>(&self, args: SendArgs, input: R) -> CLIResult<TransferResult>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        // A dry run must never move data
+        if args.dry_run {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "dry-run".to_string(),
+                reason: "dry runs are planned with plan_send, not sent".to_string(),
+            });
+        }
+        if args.targets().len() > 1 {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "to".to_string(),
+                reason: "sends to several peers are started with handle_send_many".to_string(),
+            });
+        }
+
+        self.prepare_send(&args).await?;
 
         // Determine peer ID
         let peer_id = args.peer.clone();
 
+        // Data from stdin is chunked as it arrives, however long it turns out
+        if args.is_piped() {
+            let session = self
+                .file_transfer
+                .send_pipe(input, args.name.as_deref(), peer_id)
+                .await
+                .map_err(|e| CLIError::transfer(format!("Failed to start transfer: {}", e)))?;
+
+            let name = session.manifest.pipe.as_ref().map(|pipe| pipe.name.display().to_string());
+            let message = format!("Sending {} from stdin", name.unwrap_or_default());
+            return Ok(self.track_session(&session, message, args.notify).await);
+        }
+
         // Send files
         let session = if args.files.len() == 1 {
             // Single file transfer
@@ -205,6 +379,155 @@ impl TransferHandler {
         }
         .map_err(|e| CLIError::transfer(format!("Failed to start transfer: {}", e)))?;
 
+        let message = format!("Transferring {} files", args.files.len());
+        Ok(self.track_session(&session, message, args.notify).await)
+    }
+
+    /// Handle "send --to a,b,c": send the same files to every target at once
+    ///
+    /// Each chunk is read once and written to all targets, and with
+    /// `args.swarm` targets on the same LAN share chunks with each other.
+    /// Every target gets its own operation so progress is shown per peer; a
+    /// target that cannot be started is listed in `failed` without stopping
+    /// the others.
+    pub async fn handle_send_many(&self, args: SendArgs) -> CLIResult<FanOutResult> {
+        if args.dry_run {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "dry-run".to_string(),
+                reason: "dry runs are planned with plan_send, not sent".to_string(),
+            });
+        }
+
+        if args.is_piped() {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "to".to_string(),
+                reason: "stdin can only be read once, so it goes to a single peer".to_string(),
+            });
+        }
+
+        self.prepare_send(&args).await?;
+
+        let sessions = self
+            .file_transfer
+            .send_files_to(args.files.clone(), args.targets(), args.swarm)
+            .await
+            .map_err(|e| CLIError::transfer(format!("Failed to start transfer: {}", e)))?;
+
+        let mut result = FanOutResult {
+            transfers: Vec::new(),
+            failed: Vec::new(),
+        };
+        for (peer, session) in sessions {
+            match session {
+                Ok(session) => {
+                    let message = format!("Sending {} files to {}", args.files.len(), peer);
+                    let transfer = self.track_session(&session, message, args.notify).await;
+                    result.transfers.push((peer, transfer));
+                }
+                Err(e) => result.failed.push((peer, e.to_string())),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Render the current state of every target of a fan-out send
+    pub async fn format_fan_out(&self, result: &FanOutResult) -> String {
+        let operations = self.active_operations.read().await;
+        let width = result
+            .transfers
+            .iter()
+            .map(|(peer, _)| peer.len())
+            .chain(result.failed.iter().map(|(peer, _)| peer.len()))
+            .max()
+            .unwrap_or(0);
+
+        let mut output = String::new();
+        for (peer, transfer) in &result.transfers {
+            let status = operations.get(&transfer.operation_id).unwrap_or(&transfer.status);
+            let line = match &status.status {
+                OperationState::Completed => "done".to_string(),
+                OperationState::Failed(error) => format!("failed: {}", error),
+                OperationState::Cancelled => "cancelled".to_string(),
+                OperationState::Starting | OperationState::InProgress => match &status.progress {
+                    Some(ProgressInfo { current, total: Some(total), rate, .. }) if *total > 0 => format!(
+                        "{:>3}%  {} of {}{}",
+                        current * 100 / total,
+                        format_size(*current),
+                        format_size(*total),
+                        rate.filter(|rate| *rate > 0.0)
+                            .map_or(String::new(), |rate| format!(" at {}/s", format_size(rate as u64)))
+                    ),
+                    _ => "starting".to_string(),
+                },
+            };
+            output.push_str(&format!("  {:<width$}  {}\n", peer, line, width = width));
+        }
+        for (peer, error) in &result.failed {
+            output.push_str(&format!("  {:<width$}  not started: {}\n", peer, error, width = width));
+        }
+        output
+    }
+
+    /// Checks shared by every kind of send, then bring up the transfer system
+    async fn prepare_send(&self, args: &SendArgs) -> CLIResult<()> {
+        if args.name.is_some() && !args.is_piped() {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "name".to_string(),
+                reason: "only applies when sending stdin with -".to_string(),
+            });
+        }
+
+        // Validate files exist
+        for file in &args.files {
+            if pipe::is_stdio(file) {
+                if !args.is_piped() {
+                    return Err(CLIError::InvalidArgumentValue {
+                        arg: "files".to_string(),
+                        reason: "- (stdin) cannot be sent together with files".to_string(),
+                    });
+                }
+                continue;
+            }
+            if !file.exists() {
+                return Err(CLIError::file_not_found(file.display().to_string()));
+            }
+        }
+
+        // Large sends on a metered connection need the user's go-ahead; a
+        // fan-out uploads the files once per target
+        if let Some(traffic) = &self.traffic {
+            let bytes = Self::total_size(&args.files) * args.targets().len() as u64;
+            let operation = TrafficOperation::Transfer { bytes };
+            if let TrafficDecision::Confirm { reason } = traffic.policy().check(operation, Initiator::User)
+                && !args.allow_metered
+            {
+                return Err(CLIError::transfer(format!(
+                    "{}; use --allow-metered to send anyway",
+                    reason
+                )));
+            }
+        }
+
+        // A --compression flag wins over the configured settings
+        for target in args.targets() {
+            let compression = args
+                .compression
+                .unwrap_or_else(|| self.config.transfer_settings_for(&target).compression);
+            let limit = self.config.peers.get(&target).and_then(|overrides| overrides.bandwidth_limit);
+            self.file_transfer.set_peer_compression(target.clone(), Some(compression)).await;
+            self.file_transfer.set_peer_bandwidth_limit(target, limit).await;
+        }
+
+        // Initialize file transfer system
+        self.file_transfer
+            .initialize()
+            .await
+            .map_err(|e| CLIError::transfer(format!("Failed to initialize file transfer: {}", e)))
+    }
+
+    /// Record a started session as an operation for real-time tracking
+    async fn track_session(&self, session: &TransferSession, message: String, notify: bool) -> TransferResult {
         // Convert transfer state to operation state
         let operation_state = match session.state {
             TransferState::Pending => OperationState::Starting,
@@ -226,7 +549,7 @@ impl TransferHandler {
                 total: Some(session.manifest.total_size),
                 rate: None,
                 eta: None,
-                message: Some(format!("Transferring {} files", args.files.len())),
+                message: Some(message),
             }),
             started_at: chrono::Utc::now(),
             estimated_completion: None,
@@ -237,21 +560,45 @@ impl TransferHandler {
             .write()
             .await
             .insert(session.session_id, operation_status.clone());
+        if notify {
+            self.watch_operation(session.session_id, None);
+        }
 
-        Ok(TransferResult {
+        TransferResult {
             operation_id: session.session_id,
             status: operation_status,
-        })
+        }
     }
 
     /// Handle receive command
     pub async fn handle_receive(&self, args: ReceiveArgs) -> CLIResult<ReceiveResult> {
+        self.handle_receive_into(args, &mut tokio::io::stdout()).await
+    }
+
+    /// Handle receive command, writing `--to-stdout` data to `output`
+    pub async fn handle_receive_into<W>(&self, args: ReceiveArgs, output: &mut W) -> CLIResult<ReceiveResult>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send,
+    {
+        if args.to_stdout && args.download_path.is_some() {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "to-stdout".to_string(),
+                reason: "cannot be combined with an output directory".to_string(),
+            });
+        }
+
         // Initialize file transfer system
         self.file_transfer
             .initialize()
             .await
             .map_err(|e| CLIError::transfer(format!("Failed to initialize file transfer: {}", e)))?;
 
+        // Piped data is written out as it arrives, so this returns only once
+        // the sender closes the pipe
+        if args.to_stdout {
+            return self.receive_pipe_into(output).await;
+        }
+
         // For now, create a placeholder operation
         // In a real implementation, this would listen for incoming transfers
         let operation_id = Uuid::new_v4();
@@ -273,6 +620,72 @@ impl TransferHandler {
         };
 
         // Store operation for real-time tracking
+        self.active_operations
+            .write()
+            .await
+            .insert(operation_id, operation_status.clone());
+        if args.notify {
+            self.watch_operation(operation_id, args.download_path.clone());
+        }
+
+        Ok(ReceiveResult {
+            operation_id,
+            status: operation_status,
+        })
+    }
+
+    /// Hand over a file transfer stream a peer opened to us
+    ///
+    /// `receive --to-stdout` writes the pipe on the next stream it is given;
+    /// streams attached before that wait in a short queue.
+    pub async fn attach_stream(&self, peer_id: PeerId, stream: MuxStream) -> CLIResult<()> {
+        self.inbound_tx
+            .send((peer_id, stream))
+            .await
+            .map_err(|_| CLIError::transfer("Incoming transfer queue is closed"))
+    }
+
+    /// Route the file transfer streams peers open to this handler
+    pub fn stream_handler(&self) -> Arc<dyn StreamHandler> {
+        Arc::new(TransferInbox(self.inbound_tx.clone()))
+    }
+
+    /// Wait for the next attached stream and write the pipe on it to `writer`
+    async fn receive_pipe_into<W>(&self, writer: &mut W) -> CLIResult<ReceiveResult>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send,
+    {
+        let (peer_id, stream) = self
+            .inbound_streams
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or_else(|| CLIError::transfer("Incoming transfer queue is closed"))?;
+        let started_at = chrono::Utc::now();
+
+        let (pipe, summary) = self
+            .file_transfer
+            .receive_pipe(stream, writer)
+            .await
+            .map_err(|e| CLIError::transfer(format!("Failed to receive from {}: {}", peer_id, e)))?;
+
+        let operation_id = Uuid::new_v4();
+        let operation_status = OperationStatus {
+            operation_id,
+            operation_type: OperationType::FileTransfer,
+            peer_id: Uuid::new_v4(), // Convert string peer_id to UUID
+            status: OperationState::Completed,
+            progress: Some(ProgressInfo {
+                current: summary.bytes,
+                total: Some(summary.bytes),
+                rate: None,
+                eta: None,
+                message: Some(format!("Received {} from {}", pipe.name.display(), peer_id)),
+            }),
+            started_at,
+            estimated_completion: None,
+        };
         self.active_operations
             .write()
             .await
@@ -407,15 +820,49 @@ impl TransferHandler {
     }
 }
 
+/// Queues inbound file transfer streams for [`TransferHandler`]
+struct TransferInbox(mpsc::Sender<(PeerId, MuxStream)>);
+
+#[async_trait]
+impl StreamHandler for TransferInbox {
+    async fn handle_stream(&self, peer_id: PeerId, stream: MuxStream) -> Result<(), String> {
+        self.0
+            .send((peer_id, stream))
+            .await
+            .map_err(|_| "incoming transfer queue is closed".to_string())
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{}s", secs.max(1))
+    } else if secs < 3600 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::security::PeerId as SecurityPeerId;
+    use crate::testing::MemorySecurity;
     use tempfile::TempDir;
 
+    /// A peer ID as the transfer system expects one, a hex fingerprint
+    fn peer(byte: u8) -> String {
+        SecurityPeerId::from_fingerprint([byte; 32]).to_hex()
+    }
+
     fn create_test_handler() -> (TransferHandler, TempDir) {
         let temp_dir = TempDir::new().unwrap();
-        let security_system = Arc::new(SecuritySystem::new().unwrap());
-        let handler = TransferHandler::new(security_system, temp_dir.path().to_path_buf());
+        let file_transfer = FileTransferSystem::new(
+            Arc::new(MemorySecurity::new().unwrap()),
+            temp_dir.path().to_path_buf(),
+        );
+        let handler = TransferHandler::with_file_transfer(Arc::new(file_transfer));
         (handler, temp_dir)
     }
 
@@ -434,6 +881,12 @@ mod tests {
             peer: "test-peer".to_string(),
             compression: Some(true),
             encryption: Some(true),
+            allow_metered: false,
+            notify: false,
+            dry_run: false,
+            also_to: Vec::new(),
+            swarm: false,
+            name: None,
         };
 
         let result = handler.handle_send(args).await;
@@ -450,27 +903,183 @@ mod tests {
 
         let args = SendArgs {
             files: vec![test_file],
-            peer: "test-peer".to_string(),
+            peer: peer(1),
             compression: Some(true),
             encryption: Some(true),
+            allow_metered: false,
+            notify: false,
+            dry_run: false,
+            also_to: Vec::new(),
+            swarm: false,
+            name: None,
         };
 
         let result = handler.handle_send(args).await;
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_dry_run_sends_nothing() {
+        let (handler, temp_dir) = create_test_handler();
+
+        let test_file = temp_dir.path().join("test.txt");
+        std::fs::write(&test_file, b"test content").unwrap();
+
+        let args = SendArgs {
+            files: vec![test_file],
+            peer: peer(1),
+            compression: Some(true),
+            encryption: Some(true),
+            allow_metered: false,
+            notify: false,
+            dry_run: true,
+            also_to: Vec::new(),
+            swarm: false,
+            name: None,
+        };
+
+        let plan = handler.plan_send(&args).await.unwrap();
+        assert_eq!(plan.manifest.file_count, 1);
+        assert_eq!(plan.manifest.total_size, 12);
+        assert!(TransferHandler::format_plan(&plan).contains(&format!("nothing was sent to {}", peer(1))));
+
+        // No session was started, and handle_send refuses dry runs
+        assert!(handler.get_all_operations().await.unwrap().is_empty());
+        assert!(handler.handle_send(args).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_many_tracks_each_target() {
+        let (handler, temp_dir) = create_test_handler();
+
+        let test_file = temp_dir.path().join("test.txt");
+        std::fs::write(&test_file, b"test content").unwrap();
+
+        let (laptop, phone, nas) = (peer(1), peer(2), peer(3));
+        let args = SendArgs {
+            files: vec![test_file],
+            peer: laptop.clone(),
+            compression: Some(true),
+            encryption: Some(true),
+            allow_metered: false,
+            notify: false,
+            dry_run: false,
+            also_to: vec![phone.clone(), laptop.clone(), nas.clone()],
+            swarm: true,
+            name: None,
+        };
+        assert_eq!(args.targets(), vec![laptop.clone(), phone.clone(), nas.clone()]);
+
+        // A plain send refuses several targets
+        assert!(handler.handle_send(args.clone()).await.is_err());
+
+        let result = handler.handle_send_many(args).await.unwrap();
+        let peers: Vec<&String> = result.transfers.iter().map(|(peer, _)| peer).collect();
+        assert_eq!(peers, vec![&laptop, &phone, &nas]);
+        assert_eq!(handler.get_all_operations().await.unwrap().len(), 3);
+
+        let output = handler.format_fan_out(&result).await;
+        assert_eq!(output.lines().count(), 3);
+        assert!(output.contains(&phone));
+    }
+
+    #[tokio::test]
+    async fn test_send_applies_peer_overrides() {
+        use crate::file_transfer::FileTransfer;
+
+        let (handler, temp_dir) = create_test_handler();
+        let (nas, phone) = (peer(3), peer(2));
+        let mut config = CLIConfig::default();
+        config.peers.insert(
+            &nas,
+            crate::cli::types::PeerOverrides {
+                bandwidth_limit: Some(500_000),
+                compression: Some(false),
+                ..Default::default()
+            },
+        );
+        let handler = handler.with_config(config);
+
+        let test_file = temp_dir.path().join("test.txt");
+        std::fs::write(&test_file, b"test content").unwrap();
+        let args = SendArgs {
+            files: vec![test_file],
+            peer: nas.clone(),
+            compression: None,
+            encryption: Some(true),
+            allow_metered: false,
+            notify: false,
+            dry_run: false,
+            also_to: vec![phone.clone()],
+            swarm: false,
+            name: None,
+        };
+
+        let result = handler.handle_send_many(args).await.unwrap();
+        assert_eq!(result.transfers.len(), 2);
+        let sessions = handler.file_transfer.get_active_transfers().await.unwrap();
+        let nas = sessions.iter().find(|s| s.peer_id == nas).unwrap();
+        assert_eq!((nas.bandwidth_limit, nas.compression), (Some(500_000), false));
+        let phone = sessions.iter().find(|s| s.peer_id == phone).unwrap();
+        assert_eq!((phone.bandwidth_limit, phone.compression), (None, true));
+    }
+
     #[tokio::test]
     async fn test_receive_command() {
         let (handler, _temp_dir) = create_test_handler();
         let args = ReceiveArgs {
             download_path: None,
             auto_accept: false,
+            notify: false,
+            to_stdout: false,
         };
 
         let result = handler.handle_receive(args).await;
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_receive_pipe_from_attached_stream() {
+        use crate::file_transfer::chunk::ChunkEngineImpl;
+        use crate::file_transfer::pipe::{PipeEntry, PipeSender};
+        use crate::file_transfer::progress::ProgressTracker;
+        use crate::file_transfer::transport_integration::FileTransferTransport;
+        use crate::testing::MemoryConnection;
+        use crate::transport::{Multiplexer, MuxConfig, MuxRole, CHANNEL_FILE_TRANSFER};
+
+        let (handler, _temp_dir) = create_test_handler();
+        let (a, b) = MemoryConnection::pair(
+            "alice".to_string(),
+            "10.0.0.1:41000".parse().unwrap(),
+            "bob".to_string(),
+            "10.0.0.2:41000".parse().unwrap(),
+        );
+        let alice = Multiplexer::new(Box::new(a), MuxRole::Initiator, MuxConfig::default());
+        let bob = Multiplexer::new(Box::new(b), MuxRole::Responder, MuxConfig::default());
+
+        let mut outbound = FileTransferTransport::mux_chunk_stream(alice.open_stream(CHANNEL_FILE_TRANSFER).unwrap());
+        let sender = PipeSender::new(ChunkEngineImpl::new(), Arc::new(ProgressTracker::new()));
+        let pipe = PipeEntry {
+            name: PathBuf::from("dump.sql"),
+        };
+        let sending = tokio::spawn(async move {
+            let mut data: &[u8] = b"select 1;";
+            sender.send(&mut data, &pipe, Uuid::new_v4(), outbound.as_mut()).await
+        });
+
+        handler
+            .attach_stream("alice".to_string(), bob.accept_stream().await.unwrap())
+            .await
+            .unwrap();
+        let mut written = Vec::new();
+        let result = handler.receive_pipe_into(&mut written).await.unwrap();
+
+        assert_eq!(written, b"select 1;");
+        assert_eq!(sending.await.unwrap().unwrap().bytes, 9);
+        assert_eq!(result.status.status, OperationState::Completed);
+        assert_eq!(result.status.progress.unwrap().current, 9);
+    }
+
     #[tokio::test]
     async fn test_set_bandwidth_limit() {
         let (handler, _temp_dir) = create_test_handler();
@@ -0,0 +1,108 @@
+// Transport diagnostics command handler
+//
+// Implements "kizuna transport diagnose": classifies the NAT in front of
+// this device with STUN and explains what it means for direct connections
+// and protocol selection.
+
+use crate::cli::error::{CLIError, CLIResult};
+use crate::transport::{KizunaTransport, NatReport, NatType};
+use std::sync::Arc;
+
+/// Transport diagnose command handler
+pub struct TransportDiagnoseHandler {
+    transport: Option<Arc<KizunaTransport>>,
+}
+
+impl TransportDiagnoseHandler {
+    /// Create a handler that sets up its own transport when run
+    pub fn new() -> Self {
+        Self { transport: None }
+    }
+
+    /// Diagnose through an existing transport, reusing its cached NAT report
+    pub fn with_transport(mut self, transport: Arc<KizunaTransport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Classify the NAT, running STUN detection unless a cached report exists
+    pub async fn diagnose(&self, refresh: bool) -> CLIResult<NatReport> {
+        let transport = match &self.transport {
+            Some(transport) => Arc::clone(transport),
+            None => Arc::new(
+                KizunaTransport::new()
+                    .await
+                    .map_err(|e| CLIError::other(format!("Failed to start transport: {}", e)))?,
+            ),
+        };
+
+        transport
+            .diagnose_nat(refresh)
+            .await
+            .map_err(|e| CLIError::other(format!("NAT detection failed: {}", e)))
+    }
+
+    /// Format a NAT report for display
+    pub fn format_report(report: &NatReport) -> String {
+        let mut output = format!("NAT type: {}\n", report.nat_type.description());
+
+        let address = |addr: Option<std::net::SocketAddr>| {
+            addr.map(|a| a.to_string()).unwrap_or_else(|| "unknown".to_string())
+        };
+        output.push_str(&format!("  Local address:  {}\n", address(report.local_address)));
+        output.push_str(&format!("  Public address: {}\n", address(report.mapped_address)));
+        if let Some(server) = report.stun_server {
+            output.push_str(&format!("  STUN server:    {}\n", server));
+        }
+
+        output.push_str(&format!(
+            "  Direct connections: {}\n",
+            if report.direct_connection_feasible() { "feasible" } else { "unlikely" }
+        ));
+
+        let mut untested = Vec::new();
+        if !report.mapping_tested && report.nat_type != NatType::Open {
+            untested.push("mapping (needs a second STUN server)");
+        }
+        if !report.filtering_tested && report.nat_type != NatType::Open && report.nat_type != NatType::Symmetric {
+            untested.push("filtering (server lacks RFC 5780 support)");
+        }
+        if !untested.is_empty() && report.nat_type != NatType::Unknown {
+            output.push_str(&format!("  Not tested: {}\n", untested.join(", ")));
+        }
+
+        output.push_str(&format!("\n{}\n", report.recommendation()));
+        output
+    }
+}
+
+impl Default for TransportDiagnoseHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_symmetric_report() {
+        let report = NatReport {
+            nat_type: NatType::Symmetric,
+            local_address: Some("192.168.1.20:50000".parse().unwrap()),
+            mapped_address: Some("203.0.113.9:61000".parse().unwrap()),
+            stun_server: Some("192.0.2.1:3478".parse().unwrap()),
+            mapping_tested: true,
+            filtering_tested: false,
+            detected_at: 0,
+        };
+
+        let output = TransportDiagnoseHandler::format_report(&report);
+        assert!(output.starts_with("NAT type: symmetric"));
+        assert!(output.contains("Public address: 203.0.113.9:61000"));
+        assert!(output.contains("Direct connections: unlikely"));
+        assert!(output.contains("relay"));
+        assert!(!output.contains("Not tested"));
+    }
+}
@@ -0,0 +1,402 @@
+// Self-update command handler
+//
+// Implements "kizuna self-update": reads the release manifest from the
+// release endpoint, downloads the binary for this platform, checks it
+// against the manifest digest and the publisher's Ed25519 signature, and
+// swaps it in place of the running executable. The previous binary is kept
+// next to it as `<name>.old`.
+//
+// The publisher key and endpoint are fixed at build time through the
+// KIZUNA_UPDATE_PUBLIC_KEY and KIZUNA_UPDATE_ENDPOINT environment variables.
+// A build without a publisher key refuses to install anything. Distribution
+// packages build without the self-update feature so the package manager
+// stays in charge of the binary.
+
+use crate::cli::error::{CLIError, CLIResult};
+use ed25519_dalek::{Signature, VerifyingKey};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Manifest published with every release
+pub const DEFAULT_RELEASE_ENDPOINT: &str =
+    "https://github.com/ayushjaipuriyar/kizuna/releases/latest/download/release.json";
+
+/// Hex-encoded Ed25519 key release binaries are signed with, if this build has one
+pub const PUBLISHER_KEY: Option<&str> = option_env!("KIZUNA_UPDATE_PUBLIC_KEY");
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Lines of changelog shown before asking to install
+const CHANGELOG_PREVIEW_LINES: usize = 20;
+
+/// Description of the latest release
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseManifest {
+    pub version: String,
+    /// Release date as published, e.g. `2024-05-01`
+    #[serde(default)]
+    pub published: Option<String>,
+    /// Markdown release notes
+    #[serde(default)]
+    pub changelog: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// Binary for one platform
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseAsset {
+    /// Platform as `<os>-<arch>`, e.g. `linux-x86_64`
+    pub platform: String,
+    pub url: String,
+    /// Hex SHA-256 of the binary
+    pub sha256: String,
+    /// Hex Ed25519 signature over [`ReleaseAsset::signed_message`]
+    pub signature: String,
+}
+
+impl ReleaseManifest {
+    /// Parse a manifest as served by the release endpoint
+    pub fn from_json(data: &[u8]) -> CLIResult<Self> {
+        let manifest: Self = serde_json::from_slice(data)
+            .map_err(|e| CLIError::other(format!("Invalid release manifest: {}", e)))?;
+        manifest.parsed_version()?;
+        Ok(manifest)
+    }
+
+    /// Release version as semver
+    pub fn parsed_version(&self) -> CLIResult<Version> {
+        Version::parse(self.version.trim_start_matches('v')).map_err(|e| {
+            CLIError::other(format!("Release manifest has an invalid version '{}': {}", self.version, e))
+        })
+    }
+
+    /// Asset for a platform
+    pub fn asset_for(&self, platform: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| asset.platform == platform)
+    }
+}
+
+impl ReleaseAsset {
+    /// What the publisher signs for an asset
+    ///
+    /// Covering the version and platform as well as the digest stops a
+    /// correctly signed older or foreign binary from being passed off as
+    /// this release.
+    pub fn signed_message(version: &str, platform: &str, sha256: &str) -> Vec<u8> {
+        format!("kizuna-release:{}:{}:{}", version, platform, sha256.to_ascii_lowercase()).into_bytes()
+    }
+
+    /// Check downloaded bytes against the digest and signature
+    pub fn verify(&self, version: &str, key: &VerifyingKey, binary: &[u8]) -> CLIResult<()> {
+        let digest = hex::encode(Sha256::digest(binary));
+        if !digest.eq_ignore_ascii_case(&self.sha256) {
+            return Err(CLIError::security(format!(
+                "Downloaded binary does not match the release digest (expected {}, got {})",
+                self.sha256, digest
+            )));
+        }
+
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or_else(|| CLIError::security("Release signature is malformed"))?;
+        key.verify_strict(&Self::signed_message(version, &self.platform, &self.sha256), &signature)
+            .map_err(|_| CLIError::security("Release signature does not match the publisher key"))
+    }
+}
+
+/// Platform name used in release manifests
+pub fn current_platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Parse a hex-encoded publisher key
+pub fn parse_publisher_key(key: &str) -> CLIResult<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(key.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| CLIError::security("Publisher key must be 32 hex-encoded bytes"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| CLIError::security(format!("Invalid publisher key: {}", e)))
+}
+
+/// Result of asking the release endpoint for the latest version
+#[derive(Debug, Clone)]
+pub struct UpdateCheck {
+    pub current: Version,
+    pub latest: Version,
+    pub manifest: ReleaseManifest,
+}
+
+impl UpdateCheck {
+    /// Whether the release is newer than the running binary
+    pub fn is_newer(&self) -> bool {
+        self.latest > self.current
+    }
+}
+
+/// What an installed update changed
+#[derive(Debug, Clone)]
+pub struct UpdateOutcome {
+    pub version: Version,
+    pub installed: PathBuf,
+    /// Previous binary, kept for rolling back by hand
+    pub backup: PathBuf,
+}
+
+/// Self-update command handler
+pub struct UpdateHandler {
+    client: reqwest::Client,
+    endpoint: String,
+    publisher_key: Option<VerifyingKey>,
+    current_version: Version,
+    install_path: PathBuf,
+    platform: String,
+}
+
+impl UpdateHandler {
+    /// Create a handler that replaces the running executable
+    pub fn new() -> CLIResult<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("kizuna/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| CLIError::other(format!("Failed to create HTTP client: {}", e)))?;
+        let install_path = std::env::current_exe()
+            .and_then(|path| path.canonicalize())
+            .map_err(|e| CLIError::other(format!("Cannot locate the running executable: {}", e)))?;
+
+        Ok(Self {
+            client,
+            endpoint: option_env!("KIZUNA_UPDATE_ENDPOINT")
+                .unwrap_or(DEFAULT_RELEASE_ENDPOINT)
+                .to_string(),
+            publisher_key: PUBLISHER_KEY.map(parse_publisher_key).transpose()?,
+            current_version: Version::parse(env!("CARGO_PKG_VERSION"))
+                .expect("crate version is valid semver"),
+            install_path,
+            platform: current_platform(),
+        })
+    }
+
+    /// Read the release manifest from another URL
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Verify releases against another key
+    pub fn with_publisher_key(mut self, key: VerifyingKey) -> Self {
+        self.publisher_key = Some(key);
+        self
+    }
+
+    /// Replace another binary instead of the running one
+    pub fn with_install_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.install_path = path.into();
+        self
+    }
+
+    /// Compare releases against another version
+    pub fn with_current_version(mut self, version: Version) -> Self {
+        self.current_version = version;
+        self
+    }
+
+    /// Fetch the release manifest and compare it with this build
+    pub async fn check(&self) -> CLIResult<UpdateCheck> {
+        let body = self.fetch(&self.endpoint).await?;
+        let manifest = ReleaseManifest::from_json(&body)?;
+        Ok(UpdateCheck {
+            current: self.current_version.clone(),
+            latest: manifest.parsed_version()?,
+            manifest,
+        })
+    }
+
+    /// Download, verify and install the release from a check
+    pub async fn apply(&self, check: &UpdateCheck) -> CLIResult<UpdateOutcome> {
+        let key = self.publisher_key.as_ref().ok_or_else(|| {
+            CLIError::security(
+                "This build has no publisher key to verify releases with; \
+                 download the new version by hand",
+            )
+        })?;
+        let asset = check.manifest.asset_for(&self.platform).ok_or_else(|| {
+            CLIError::not_found(format!(
+                "Release {} has no binary for {}",
+                check.manifest.version, self.platform
+            ))
+        })?;
+
+        log::info!("Downloading kizuna {} from {}", check.latest, asset.url);
+        let binary = self.fetch(&asset.url).await?;
+        asset.verify(&check.manifest.version, key, &binary)?;
+
+        let backup = install_binary(&self.install_path, &binary)?;
+        log::info!(
+            "Installed kizuna {} at {}, previous binary kept at {}",
+            check.latest,
+            self.install_path.display(),
+            backup.display()
+        );
+
+        Ok(UpdateOutcome {
+            version: check.latest.clone(),
+            installed: self.install_path.clone(),
+            backup,
+        })
+    }
+
+    async fn fetch(&self, url: &str) -> CLIResult<Vec<u8>> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| CLIError::other(format!("Failed to fetch {}: {}", url, e)))?;
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| CLIError::other(format!("Failed to read {}: {}", url, e)))?;
+        Ok(body.to_vec())
+    }
+
+    /// Render the version change and the start of the changelog
+    pub fn format_preview(check: &UpdateCheck) -> String {
+        let mut output = format!("kizuna {} -> {}", check.current, check.latest);
+        if let Some(published) = &check.manifest.published {
+            output.push_str(&format!(" (released {})", published));
+        }
+        output.push('\n');
+
+        let lines: Vec<&str> = check.manifest.changelog.lines().collect();
+        if !lines.is_empty() {
+            output.push('\n');
+            for line in lines.iter().take(CHANGELOG_PREVIEW_LINES) {
+                output.push_str(&format!("  {}\n", line));
+            }
+            if lines.len() > CHANGELOG_PREVIEW_LINES {
+                output.push_str(&format!("  ... {} more line(s)\n", lines.len() - CHANGELOG_PREVIEW_LINES));
+            }
+        }
+        output
+    }
+}
+
+/// Write `binary` over `target`, returning where the old binary was kept
+///
+/// The new binary is written next to the target first so the final rename
+/// stays on one filesystem; the target is never left half-written.
+pub fn install_binary(target: &Path, binary: &[u8]) -> CLIResult<PathBuf> {
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| CLIError::other(format!("{} is not a file", target.display())))?
+        .to_string_lossy()
+        .into_owned();
+    let staged = target.with_file_name(format!(".{}.update", file_name));
+    let backup = target.with_file_name(format!("{}.old", file_name));
+    let io_error = |action: &str, path: &Path, e: std::io::Error| {
+        CLIError::other(format!("Failed to {} {}: {}", action, path.display(), e))
+    };
+
+    std::fs::write(&staged, binary).map_err(|e| io_error("write", &staged, e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(target).map(|m| m.permissions().mode()).unwrap_or(0o755);
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))
+            .map_err(|e| io_error("set permissions on", &staged, e))?;
+    }
+
+    // A running executable can be renamed but not overwritten on Windows,
+    // so move it aside first; elsewhere a copy keeps the target in place
+    // until the atomic rename below
+    let _ = std::fs::remove_file(&backup);
+    let saved = if cfg!(windows) {
+        std::fs::rename(target, &backup)
+    } else {
+        std::fs::copy(target, &backup).map(|_| ())
+    };
+    if let Err(e) = saved {
+        let _ = std::fs::remove_file(&staged);
+        return Err(io_error("back up", target, e));
+    }
+
+    if let Err(e) = std::fs::rename(&staged, target) {
+        if cfg!(windows) {
+            let _ = std::fs::rename(&backup, target);
+        }
+        let _ = std::fs::remove_file(&staged);
+        return Err(io_error("replace", target, e));
+    }
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use rand::rngs::OsRng;
+
+    fn signed_asset(key: &SigningKey, version: &str, binary: &[u8]) -> ReleaseAsset {
+        let sha256 = hex::encode(Sha256::digest(binary));
+        let message = ReleaseAsset::signed_message(version, "linux-x86_64", &sha256);
+        ReleaseAsset {
+            platform: "linux-x86_64".to_string(),
+            url: "https://example.com/kizuna".to_string(),
+            sha256,
+            signature: hex::encode(key.sign(&message).to_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_tampering_and_replay() {
+        let key = SigningKey::generate(&mut OsRng);
+        let asset = signed_asset(&key, "1.2.0", b"new binary");
+        asset.verify("1.2.0", &key.verifying_key(), b"new binary").unwrap();
+
+        // Different bytes, an older release's signature, or another key
+        assert!(asset.verify("1.2.0", &key.verifying_key(), b"evil binary").is_err());
+        assert!(asset.verify("1.3.0", &key.verifying_key(), b"new binary").is_err());
+        let other = SigningKey::generate(&mut OsRng);
+        assert!(asset.verify("1.2.0", &other.verifying_key(), b"new binary").is_err());
+    }
+
+    #[test]
+    fn test_manifest_parse_and_preview() {
+        let json = br#"{
+            "version": "v9.1.0",
+            "published": "2024-05-01",
+            "changelog": "- Faster transfers\n- Fixed pairing",
+            "assets": []
+        }"#;
+        let manifest = ReleaseManifest::from_json(json).unwrap();
+        let check = UpdateCheck {
+            current: Version::new(0, 1, 0),
+            latest: manifest.parsed_version().unwrap(),
+            manifest,
+        };
+        assert!(check.is_newer());
+
+        let preview = UpdateHandler::format_preview(&check);
+        assert!(preview.starts_with("kizuna 0.1.0 -> 9.1.0 (released 2024-05-01)"));
+        assert!(preview.contains("- Fixed pairing"));
+
+        assert!(ReleaseManifest::from_json(br#"{"version": "latest", "assets": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_install_binary_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("kizuna");
+        std::fs::write(&target, b"old").unwrap();
+
+        let backup = install_binary(&target, b"new").unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), b"new");
+        assert_eq!(std::fs::read(&backup).unwrap(), b"old");
+        assert!(!dir.path().join(".kizuna.update").exists());
+    }
+}
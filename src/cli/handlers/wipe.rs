@@ -0,0 +1,102 @@
+// Wipe command handler
+//
+// Implements "kizuna wipe --all": securely deletes the device identity,
+// trust database, histories, logs and recordings. "kizuna wipe --remote PEER"
+// sends a signed wipe request to another of the user's devices instead.
+// Confirmation is the caller's job; by the time wipe() or wipe_remote() runs
+// the user has agreed.
+
+use crate::cli::config::default_config_path;
+use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::handlers::peer::{require_peer, PeerSessions};
+use crate::cli::types::CLIConfig;
+use crate::retention::{
+    DataCategory, FileTarget, RemoteWipe, RetentionEngine, RetentionReport, RetentionTarget,
+};
+use crate::security::api::SecuritySystem;
+use std::sync::Arc;
+
+/// Phrase the user types to confirm a wipe
+pub const WIPE_CONFIRMATION: &str = "WIPE";
+
+/// Wipe command handler
+pub struct WipeHandler {
+    engine: Arc<RetentionEngine>,
+}
+
+impl WipeHandler {
+    /// Create a handler covering every store Kizuna keeps by default
+    ///
+    /// A user-chosen recording_path is left alone since it may hold other files.
+    pub async fn new(config: &CLIConfig) -> Self {
+        let engine = RetentionEngine::with_default_targets(config.retention.clone()).await;
+
+        if let Ok(config_path) = default_config_path() {
+            engine
+                .register(Arc::new(FileTarget::new("configuration", DataCategory::Configuration, config_path)))
+                .await;
+        }
+
+        Self { engine: Arc::new(engine) }
+    }
+
+    /// Also wipe another store, such as a clipboard history database
+    pub async fn with_target(self, target: Arc<dyn RetentionTarget>) -> Self {
+        self.engine.register(target).await;
+        self
+    }
+
+    /// Whether the typed confirmation matches
+    pub fn is_confirmed(input: &str) -> bool {
+        input.trim() == WIPE_CONFIRMATION
+    }
+
+    /// Securely delete everything
+    pub async fn wipe(&self) -> CLIResult<RetentionReport> {
+        Ok(self.engine.wipe_all().await)
+    }
+
+    /// Ask another of the user's devices to wipe itself
+    ///
+    /// Succeeds once the request has been delivered. The peer checks it against
+    /// its own policy and wipes without answering, so a refusal shows up only
+    /// in the peer's log.
+    pub async fn wipe_remote(&self, peer: &str) -> CLIResult<()> {
+        let security = SecuritySystem::new()
+            .map_err(|e| CLIError::security(format!("Failed to open security system: {}", e)))?;
+        let identity = security
+            .get_or_create_identity()
+            .await
+            .map_err(|e| CLIError::security(format!("Failed to load device identity: {}", e)))?;
+        let sessions = PeerSessions::open(&security).await?;
+        let remote = RemoteWipe::new(
+            Arc::clone(&sessions.provider),
+            security.trust_manager(),
+            Arc::clone(&self.engine),
+            identity.derive_peer_id(),
+        );
+
+        let address = require_peer(peer).await?;
+        let result = remote.request_wipe(&identity, &address).await;
+        sessions.close("wipe request sent").await;
+        result.map_err(|e| CLIError::execution(format!("Wipe request to {} failed: {}", peer, e)))
+    }
+
+    /// Render a report for the terminal
+    pub fn format_report(report: &RetentionReport) -> String {
+        let mut output = String::new();
+        for (name, _) in &report.purged {
+            output.push_str(&format!("  [WIPED] {}\n", name));
+        }
+        for (name, error) in &report.errors {
+            output.push_str(&format!("  [FAILED] {}: {}\n", name, error));
+        }
+
+        if report.is_success() {
+            output.push_str("\nAll Kizuna data on this device has been erased.\n");
+        } else {
+            output.push_str("\nSome data could not be erased; delete the failed items by hand.\n");
+        }
+        output
+    }
+}
@@ -7,13 +7,21 @@
 
 use crate::cli::error::{CLIError, CLIResult};
 use crate::cli::handlers::{
-    DiscoverHandler, TransferHandler,
+    CompletionNotifier, DiscoverHandler, TransferHandler,
 };
 
 #[cfg(feature = "streaming")]
 use crate::cli::handlers::{StreamingHandler, ExecHandler, PeersHandler, StatusHandler};
+#[cfg(feature = "clipboard")]
+use crate::cli::handlers::ClipboardHandler;
 use crate::cli::security_integration::CLISecurityIntegration;
+use crate::cli::types::{CLIConfig, NotificationSettings};
+#[cfg(feature = "clipboard")]
+use crate::cli::types::PeerOverrideTable;
+use crate::lockdown::{Lockdown, LockdownConfig};
+use crate::platform::Hotkey;
 use crate::security::api::SecuritySystem;
+use crate::security::storage::EncryptedStorage;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -41,6 +49,9 @@ pub struct CLISystemIntegration {
     pub status_handler: Arc<RwLock<StatusHandler>>,
     /// Security integration
     pub security: Arc<CLISecurityIntegration>,
+    /// Settings for single peers, for handlers created later
+    #[cfg(feature = "clipboard")]
+    peer_overrides: PeerOverrideTable,
 }
 
 impl CLISystemIntegration {
@@ -56,6 +67,12 @@ impl CLISystemIntegration {
 
     /// Create a new CLI system integration with custom security system
     pub fn with_security(security_system: Arc<SecuritySystem>) -> CLIResult<Self> {
+        Self::with_config(security_system, &CLIConfig::default())
+    }
+
+    /// Create a new CLI system integration that applies the loaded user
+    /// configuration, including per-peer overrides, to its handlers
+    pub fn with_config(security_system: Arc<SecuritySystem>, config: &CLIConfig) -> CLIResult<Self> {
         // Create security integration
         let security = Arc::new(CLISecurityIntegration::new(Arc::clone(&security_system)));
 
@@ -66,15 +83,14 @@ impl CLISystemIntegration {
 
         // Create transfer handler with security
         let session_dir = Self::get_session_dir()?;
-        let transfer_handler = Arc::new(TransferHandler::new(
-            Arc::clone(&security_system),
-            session_dir,
-        ));
+        let transfer_handler = Arc::new(
+            TransferHandler::new(Arc::clone(&security_system), session_dir).with_config(config.clone()),
+        );
 
         // Create streaming handler with security (if feature enabled)
         #[cfg(feature = "streaming")]
         let streaming_handler = {
-            let mut handler = StreamingHandler::new();
+            let mut handler = StreamingHandler::new().with_peer_overrides(config.peers.clone());
             handler.set_security(Arc::clone(&security_system));
             Arc::new(handler)
         };
@@ -116,11 +132,13 @@ impl CLISystemIntegration {
             #[cfg(feature = "streaming")]
             status_handler,
             security,
+            #[cfg(feature = "clipboard")]
+            peer_overrides: config.peers.clone(),
         })
     }
 
     /// Get session directory for file transfers
-    fn get_session_dir() -> CLIResult<PathBuf> {
+    pub(crate) fn get_session_dir() -> CLIResult<PathBuf> {
         let mut path = dirs::data_local_dir()
             .ok_or_else(|| CLIError::config("Failed to get local data directory".to_string()))?;
         path.push("kizuna");
@@ -133,6 +151,14 @@ impl CLISystemIntegration {
         Ok(path)
     }
 
+    /// Encryption at rest for the config file and other stores the CLI opens
+    /// without a security system of its own
+    pub(crate) fn get_storage() -> CLIResult<Option<EncryptedStorage>> {
+        let security_system = SecuritySystem::new()
+            .map_err(|e| CLIError::security(format!("Failed to initialize security: {}", e)))?;
+        Ok(security_system.storage())
+    }
+
     /// Initialize all handlers and start background services
     pub async fn initialize(&self) -> CLIResult<()> {
         // Initialize discovery
@@ -173,6 +199,55 @@ impl CLISystemIntegration {
         handler.stop_continuous_discovery().await
     }
 
+    /// Show desktop notifications when transfers and streams that asked for
+    /// them finish, and when attack detection bans a device
+    pub fn enable_notifications(&self, settings: &NotificationSettings) {
+        let notifier = Arc::new(CompletionNotifier::new(settings));
+        self.transfer_handler.set_notifier(Arc::clone(&notifier));
+        #[cfg(feature = "streaming")]
+        self.streaming_handler.set_notifier(Arc::clone(&notifier));
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let mut alerts = self.security.security_system().policy_engine().subscribe_alerts();
+        runtime.spawn(async move {
+            loop {
+                match alerts.recv().await {
+                    Ok(alert) => notifier.attack_alert(&alert).await,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// Register the hotkey that locks down inbound remote access
+    ///
+    /// A missing hotkey is not fatal; the lockdown can still be engaged from
+    /// the TUI.
+    pub fn enable_lockdown_hotkey(&self, config: &LockdownConfig) -> Option<Hotkey> {
+        match Lockdown::global().arm_hotkey(config) {
+            Ok(hotkey) => hotkey,
+            Err(e) => {
+                log::warn!("Lockdown hotkey unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Clipboard handler for `clipboard_system` with the configured per-peer
+    /// overrides; peers excluded from sync are turned off straight away
+    #[cfg(feature = "clipboard")]
+    pub async fn clipboard_handler(
+        &self,
+        clipboard_system: Arc<crate::clipboard::api::ClipboardSystem>,
+    ) -> CLIResult<ClipboardHandler> {
+        let handler = ClipboardHandler::new(clipboard_system).with_peer_overrides(self.peer_overrides.clone());
+        handler.apply_peer_overrides().await?;
+        Ok(handler)
+    }
+
     /// Check if an operation is authorized
     pub async fn authorize_operation(&self, operation: &str, peer_id: String) -> CLIResult<bool> {
         // Convert String peer_id to PeerId
@@ -227,6 +302,28 @@ mod tests {
         assert!(!status.version.is_empty());
     }
 
+    #[cfg(feature = "streaming")]
+    #[tokio::test]
+    async fn test_peer_overrides_reach_handlers() {
+        let mut config = CLIConfig::default();
+        config.peers.insert(
+            "work-laptop",
+            crate::cli::types::PeerOverrides {
+                streaming: Some(false),
+                ..Default::default()
+            },
+        );
+        let security_system = Arc::new(SecuritySystem::new().unwrap());
+        let integration = CLISystemIntegration::with_config(security_system, &config).unwrap();
+
+        let err = integration
+            .streaming_handler
+            .add_viewer(uuid::Uuid::new_v4(), "work-laptop".to_string())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("peer settings"));
+    }
+
     #[tokio::test]
     async fn test_shutdown() {
         let integration = CLISystemIntegration::new().unwrap();
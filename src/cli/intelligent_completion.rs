@@ -174,6 +174,19 @@ impl IntelligentCompletion {
             ("peers", "List connected peers"),
             ("status", "Show system status"),
             ("clipboard", "Manage clipboard sharing"),
+            ("msg", "Send a text message to a peer"),
+            ("get", "Fetch a file from a peer's share"),
+            ("open-on", "Open a URL on a peer"),
+            ("locate", "Ring a peer to find it"),
+            ("security", "Check this device's security"),
+            ("transport", "Diagnose network transports"),
+            ("doctor", "Diagnose connectivity problems"),
+            ("bench", "Benchmark the link to a peer"),
+            ("ping", "Check that a peer is alive"),
+            ("wipe", "Securely erase Kizuna data"),
+            ("recordings", "Manage stream recordings"),
+            ("export", "Export statistics and history"),
+            ("self-update", "Update kizuna to the latest release"),
             ("tui", "Launch interactive TUI"),
             ("config", "Manage configuration"),
             ("completion", "Generate shell completion scripts"),
@@ -217,6 +230,20 @@ impl IntelligentCompletion {
                 ("status", "Show clipboard status"),
                 ("history", "View clipboard history"),
             ],
+            "security" => vec![("check", "Print a scored hardening report")],
+            "transport" => vec![("diagnose", "Detect the NAT type and connection prospects")],
+            "recordings" => vec![
+                ("list", "List and search recordings"),
+                ("show", "Show a recording's details"),
+                ("tag", "Tag and annotate a recording"),
+                ("export", "Convert a recording to another format"),
+            ],
+            "export" => vec![
+                ("discovery", "Discovery strategy statistics"),
+                ("transfers", "Transfer history"),
+                ("bandwidth", "Bytes moved per day and peer"),
+                ("audit", "Security audit events"),
+            ],
             "config" => vec![
                 ("get", "Get configuration value"),
                 ("set", "Set configuration value"),
@@ -236,7 +263,7 @@ impl IntelligentCompletion {
 
     /// Complete option names
     fn complete_option(&self, command: &str, partial: &str) -> CLIResult<Vec<Completion>> {
-        let options = match command {
+        let mut options = match command {
             "discover" => vec![
                 ("--type", "Filter by device type"),
                 ("--name", "Filter by device name"),
@@ -246,17 +273,94 @@ impl IntelligentCompletion {
             ],
             "send" => vec![
                 ("--peer", "Target peer name or ID"),
+                ("--to", "Send to several peers at once, comma-separated"),
+                ("--swarm", "Let receivers on the same LAN share chunks"),
+                ("--name", "File name for data sent from stdin"),
                 ("--no-compression", "Disable compression"),
                 ("--no-encryption", "Disable encryption"),
+                ("--no-notify", "Don't notify when the transfer finishes"),
+                ("--dry-run", "Show what would be sent without sending"),
                 ("--verbose", "Show detailed progress"),
             ],
             "receive" => vec![
                 ("--output", "Output directory"),
                 ("--auto-accept", "Auto-accept from trusted peers"),
+                ("--to-stdout", "Write the received data to stdout"),
                 ("--from", "Only accept from specific peer"),
+                ("--no-notify", "Don't notify when the transfer finishes"),
+            ],
+            "msg" => vec![
+                ("--history", "Show the conversation"),
+                ("--no-queue", "Fail instead of queueing for offline peers"),
+            ],
+            "get" => vec![
+                ("--output", "Directory to save the file in"),
+            ],
+            "locate" => vec![
+                ("--message", "Text to show in the notification"),
+                ("--stop", "Stop the peer ringing"),
+            ],
+            "security" => vec![
+                ("--json", "Output the report as JSON"),
+            ],
+            "transport" => vec![
+                ("--refresh", "Run detection again"),
+                ("--json", "Output the report as JSON"),
+            ],
+            "doctor" => vec![
+                ("--peer", "Also ping this peer"),
+                ("--proxy", "Check connectivity through this proxy"),
+                ("--json", "Output the report as JSON"),
+            ],
+            "bench" => vec![
+                ("--duration", "Throughput test length in seconds"),
+                ("--payload-size", "Throughput test message size in bytes"),
+                ("--json", "Output the results as JSON"),
+            ],
+            "ping" => vec![
+                ("--timeout", "Seconds to wait for a reply"),
+                ("--json", "Output the result as JSON"),
+            ],
+            "wipe" => vec![
+                ("--all", "Erase all Kizuna data on this device"),
+                ("--remote", "Ask one of your devices to erase itself"),
+                ("--yes", "Skip the confirmation prompt"),
+            ],
+            "export" => vec![
+                ("--format", "File format, csv or json"),
+                ("--from", "Only records from this date on"),
+                ("--to", "Only records up to this date"),
+                ("--output", "Write to a file"),
+                ("--audit-log", "Audit log to read"),
+            ],
+            "self-update" => vec![
+                ("--check", "Only show what the update changes"),
+                ("--yes", "Install without asking"),
+            ],
+            "recordings" => vec![
+                ("--since", "Only recordings made since then"),
+                ("--until", "Only recordings made before then"),
+                ("--peer", "Only recordings made with this peer"),
+                ("--min-length", "Only recordings at least this long"),
+                ("--max-length", "Only recordings at most this long"),
+                ("--tag", "Only recordings with this tag"),
+                ("--limit", "Show at most this many recordings"),
+                ("--add", "Tags to add"),
+                ("--remove", "Tags to remove"),
+                ("--note", "Replace the recording's notes"),
+                ("--clear-note", "Remove the recording's notes"),
+                ("--format", "Container to export to"),
+                ("--crf", "Constant quality, 0-51"),
+                ("--bitrate", "Target video bitrate in kbps"),
+                ("--start", "Cut everything before this point"),
+                ("--end", "Cut everything after this point"),
+                ("--thumbnail", "Also save a JPEG thumbnail"),
+                ("--output", "Where to write the export"),
+                ("--json", "Output the result as JSON"),
             ],
             _ => vec![],
         };
+        options.push(("--non-interactive", "Never prompt; fail when an answer is needed"));
 
         let completions: Vec<Completion> = options
             .iter()
@@ -344,7 +448,7 @@ impl IntelligentCompletion {
     fn is_peer_argument(&self, context: &CompletionContext) -> bool {
         // Check if previous argument was --peer or -p
         if let Some(last_arg) = context.previous_args.last() {
-            if last_arg == "--peer" || last_arg == "-p" {
+            if last_arg == "--peer" || last_arg == "-p" || last_arg == "--to" {
                 return true;
             }
         }
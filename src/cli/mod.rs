@@ -14,7 +14,9 @@ pub mod output;
 pub mod parser;
 pub mod pipeline;
 pub mod powershell_completion;
+pub mod prompt;
 pub mod security_integration;
+#[cfg(feature = "tui")]
 pub mod tui;
 pub mod types;
 
@@ -26,6 +28,7 @@ pub use completion::CompletionGenerator;
 pub use history::{HistoryEntry, HistoryManager, HistoryStatistics};
 pub use intelligent_completion::{Completion, CompletionContext, IntelligentCompletion};
 pub use powershell_completion::PowerShellCompletion;
+pub use prompt::Prompter;
 
 pub use parser::{
     ClapCommandParser, CommandContext, CommandExecutor, CommandParser, CommandPipeline,
@@ -1,6 +1,7 @@
 // JSON, CSV, and minimal output formatters
 
 use crate::cli::{CLIError, CLIResult, TableData};
+use crate::error::KizunaError;
 use serde_json;
 
 /// JSON output formatter
@@ -38,6 +39,11 @@ impl JSONFormatter {
 
         Ok(serde_json::Value::Array(rows))
     }
+
+    /// Format an error as `{"error": {...}}` with its stable code and retryability
+    pub fn format_error(&self, error: &KizunaError, pretty: bool) -> CLIResult<String> {
+        self.format(error.report().to_json(), pretty)
+    }
 }
 
 /// CSV output formatter
@@ -127,6 +133,17 @@ mod tests {
         assert!(!compact.contains("  ")); // No indentation
     }
 
+    #[test]
+    fn test_error_to_json() {
+        let formatter = JSONFormatter::new();
+        let error = KizunaError::from(CLIError::MissingArgument("peer".to_string()));
+
+        let output = formatter.format_error(&error, false).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["error"]["code"], "cli.invalid_input");
+        assert_eq!(value["error"]["exit_code"], 64);
+    }
+
     #[test]
     fn test_table_to_json() {
         let formatter = JSONFormatter::new();
@@ -95,8 +95,9 @@ mod tests {
             "laptop".to_string(),
         ];
 
+        // "laptop" was never paired, so the transfer is refused
         let result = executor.execute_from_args(args).await;
-        assert!(result.is_ok());
+        assert!(result.is_err());
     }
 
     #[tokio::test]
@@ -1,10 +1,15 @@
 // Command routing and handler dispatch system
 
 use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::handlers::{PeerSessions, ReceiveArgs, SendArgs, TransferHandler};
 use crate::cli::parser::ValidatedCommand;
+use crate::cli::prompt::Prompter;
 use crate::cli::types::{CommandOutput, CommandResult, CommandType};
 use std::time::{Duration, Instant};
 
+/// How long `kizuna peers` listens for peers
+const PEERS_DISCOVERY_TIMEOUT_SECS: u64 = 3;
+
 /// Command execution context
 #[derive(Debug, Clone)]
 pub struct CommandContext {
@@ -12,6 +17,8 @@ pub struct CommandContext {
     pub start_time: Instant,
     pub verbose: bool,
     pub quiet: bool,
+    /// Asks for confirmations, or fails fast under --non-interactive
+    pub prompter: Prompter,
 }
 
 impl CommandContext {
@@ -19,12 +26,14 @@ impl CommandContext {
     pub fn new(validated_command: ValidatedCommand) -> Self {
         let verbose = validated_command.command.has_flag("verbose");
         let quiet = validated_command.command.has_flag("quiet");
+        let prompter = Prompter::new(validated_command.command.has_flag("non-interactive"));
 
         Self {
             validated_command,
             start_time: Instant::now(),
             verbose,
             quiet,
+            prompter,
         }
     }
 
@@ -72,6 +81,21 @@ impl CommandRouter {
             }
         }
 
+        // Commands that only work with someone at the keyboard fail fast
+        if !context.prompter.is_interactive() {
+            let interactive = match context.command_type() {
+                CommandType::TUI => Some("the TUI"),
+                CommandType::Exec if context.has_flag("interactive") => Some("exec --interactive"),
+                _ => None,
+            };
+            if let Some(command) = interactive {
+                return Err(CLIError::input_required(format!(
+                    "{} needs a terminal and cannot run with --non-interactive",
+                    command
+                )));
+            }
+        }
+
         // Route to appropriate handler based on command type
         let result = match context.command_type() {
             CommandType::Discover => Self::route_discover(context).await,
@@ -82,6 +106,19 @@ impl CommandRouter {
             CommandType::Peers => Self::route_peers(context).await,
             CommandType::Status => Self::route_status(context).await,
             CommandType::Clipboard => Self::route_clipboard(context).await,
+            CommandType::Msg => Self::route_msg(context).await,
+            CommandType::Get => Self::route_get(context).await,
+            CommandType::OpenOn => Self::route_open_on(context).await,
+            CommandType::Locate => Self::route_locate(context).await,
+            CommandType::Security => Self::route_security(context).await,
+            CommandType::Transport => Self::route_transport(context).await,
+            CommandType::Doctor => Self::route_doctor(context).await,
+            CommandType::Bench => Self::route_bench(context).await,
+            CommandType::Ping => Self::route_ping(context).await,
+            CommandType::Wipe => Self::route_wipe(context).await,
+            CommandType::Recordings => Self::route_recordings(context).await,
+            CommandType::Export => Self::route_export(context).await,
+            CommandType::SelfUpdate => Self::route_self_update(context).await,
             CommandType::TUI => Self::route_tui(context).await,
             CommandType::Config => Self::route_config(context).await,
         };
@@ -107,38 +144,204 @@ impl CommandRouter {
     }
 
     async fn route_send(context: CommandContext) -> CLIResult<CommandResult> {
-        // Placeholder implementation - will be replaced by actual handler
-        let execution_time = context.elapsed();
+        use crate::cli::config::load_or_create_config;
+        use crate::cli::integration::CLISystemIntegration;
+        use crate::security::api::SecuritySystem;
+        use std::sync::Arc;
 
-        let files = context.arguments();
-        let peer = context.get_option("peer");
+        let args = Self::send_args(&context)?;
+        let security = Arc::new(
+            SecuritySystem::new()
+                .map_err(|e| CLIError::security(format!("Failed to open security system: {}", e)))?,
+        );
+        let config = load_or_create_config(security.storage()).await?;
+        let handler = TransferHandler::new(security, CLISystemIntegration::get_session_dir()?).with_config(config);
+
+        Self::send_with(&context, &handler, args, tokio::io::stdin()).await
+    }
+
+    /// Send arguments from the command line
+    fn send_args(context: &CommandContext) -> CLIResult<SendArgs> {
+        let mut targets = context
+            .get_option("peer")
+            .into_iter()
+            .chain(context.get_option("to"))
+            .flat_map(|list| list.split(','))
+            .map(str::trim)
+            .filter(|target| !target.is_empty())
+            .map(String::from);
+        let peer = targets
+            .next()
+            .ok_or_else(|| CLIError::MissingArgument("peer - use --peer or --to".to_string()))?;
+        Ok(SendArgs {
+            files: context.arguments().iter().map(std::path::PathBuf::from).collect(),
+            peer,
+            compression: context.has_flag("no-compression").then_some(false),
+            encryption: context.has_flag("no-encryption").then_some(false),
+            allow_metered: context.has_flag("allow-metered"),
+            notify: !context.has_flag("no-notify"),
+            dry_run: context.has_flag("dry-run"),
+            also_to: targets.collect(),
+            swarm: context.has_flag("swarm"),
+            name: context.get_option("name").cloned(),
+        })
+    }
+
+    /// Run a send on `handler`, reading the data for "-" from `input`
+    async fn send_with<R>(
+        context: &CommandContext,
+        handler: &TransferHandler,
+        args: SendArgs,
+        input: R,
+    ) -> CLIResult<CommandResult>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+
+        // A dry run plans the send to each target and moves no data
+        if args.dry_run {
+            let mut output = String::new();
+            for target in args.targets() {
+                let plan = handler
+                    .plan_send(&SendArgs {
+                        peer: target,
+                        also_to: Vec::new(),
+                        ..args.clone()
+                    })
+                    .await?;
+                output.push_str(&TransferHandler::format_plan(&plan));
+            }
+            return Ok(CommandResult {
+                success: true,
+                output: CommandOutput::Text(output),
+                execution_time: context.elapsed(),
+                exit_code: 0,
+            });
+        }
+
+        let (output, success) = if args.targets().len() > 1 {
+            let result = handler.handle_send_many(args).await?;
+            (handler.format_fan_out(&result).await, result.failed.is_empty())
+        } else {
+            let peer = args.peer.clone();
+            let transfer = handler.handle_send_from(args, input).await?;
+            let message = transfer
+                .status
+                .progress
+                .as_ref()
+                .and_then(|progress| progress.message.clone())
+                .unwrap_or_default();
+            (
+                format!("{} to {}\n  Transfer: {}\n", message, peer, transfer.operation_id),
+                true,
+            )
+        };
+
+        Ok(CommandResult {
+            success,
+            output: CommandOutput::Text(output),
+            execution_time: context.elapsed(),
+            exit_code: if success { 0 } else { 1 },
+        })
+    }
+
+    async fn route_receive(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::config::load_or_create_config;
+        use crate::cli::integration::CLISystemIntegration;
+        use crate::security::api::SecuritySystem;
+        use crate::transport::{select_port, ListenPorts, PortProtocol, TcpConfig, TcpListener};
+        use std::net::Ipv4Addr;
+        use std::sync::Arc;
+
+        let args = Self::receive_args(&context);
+        let security = Arc::new(
+            SecuritySystem::new()
+                .map_err(|e| CLIError::security(format!("Failed to open security system: {}", e)))?,
+        );
+        let config = load_or_create_config(security.storage()).await?;
+        let handler =
+            TransferHandler::new(Arc::clone(&security), CLISystemIntegration::get_session_dir()?).with_config(config);
+
+        if args.to_stdout {
+            let listener = async {
+                let address = select_port(Ipv4Addr::UNSPECIFIED.into(), &ListenPorts::default().tcp, PortProtocol::Tcp)?;
+                TcpListener::bind(address, TcpConfig::default()).await
+            }
+            .await
+            .map_err(|e| CLIError::transfer(format!("Failed to listen for transfers: {}", e)))?;
+            let sessions = PeerSessions::open(&security).await?;
+            return Self::receive_piped(&context, &handler, args, listener, &sessions, &mut tokio::io::stdout()).await;
+        }
+
+        let receive = handler.handle_receive(args).await?;
+        let message = receive
+            .status
+            .progress
+            .as_ref()
+            .and_then(|progress| progress.message.clone())
+            .unwrap_or_default();
 
         Ok(CommandResult {
             success: true,
-            output: CommandOutput::Text(format!(
-                "Send command executed (placeholder)\nFiles: {:?}\nPeer: {:?}\nCompression: {}\nEncryption: {}",
-                files,
-                peer,
-                !context.has_flag("no-compression"),
-                !context.has_flag("no-encryption")
-            )),
-            execution_time,
+            output: CommandOutput::Text(format!("{}\n  Transfer: {}\n", message, receive.operation_id)),
+            execution_time: context.elapsed(),
             exit_code: 0,
         })
     }
 
-    async fn route_receive(context: CommandContext) -> CLIResult<CommandResult> {
-        // Placeholder implementation - will be replaced by actual handler
-        let execution_time = context.elapsed();
+    /// Receive arguments from the command line
+    fn receive_args(context: &CommandContext) -> ReceiveArgs {
+        ReceiveArgs {
+            download_path: context.get_option("output").map(std::path::PathBuf::from),
+            auto_accept: context.has_flag("auto-accept"),
+            notify: !context.has_flag("no-notify"),
+            to_stdout: context.has_flag("to-stdout"),
+        }
+    }
+
+    /// Accept one piped transfer on `listener` and write its data to `output`
+    ///
+    /// Only the data goes to `output`; progress and the summary go to stderr
+    /// so `kizuna receive --to-stdout > file` captures nothing else.
+    async fn receive_piped<W>(
+        context: &CommandContext,
+        handler: &TransferHandler,
+        args: ReceiveArgs,
+        listener: crate::transport::TcpListener,
+        sessions: &PeerSessions,
+        output: &mut W,
+    ) -> CLIResult<CommandResult>
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send,
+    {
+        use crate::transport::{accept_connections, CHANNEL_FILE_TRANSFER};
+        use std::sync::Arc;
+
+        let address = listener
+            .local_addr()
+            .map_err(|e| CLIError::transfer(format!("Failed to listen for transfers: {}", e)))?;
+        if !context.quiet {
+            eprintln!("Waiting for a transfer on port {}", address.port());
+        }
+
+        sessions.inbound.register(CHANNEL_FILE_TRANSFER, handler.stream_handler());
+        let accepting = accept_connections(listener, Arc::clone(&sessions.provider));
+
+        let result = handler.handle_receive_into(args, output).await;
+        accepting.abort();
+        sessions.close("transfer received").await;
+        let receive = result?;
+
+        if !context.quiet
+            && let Some(message) = receive.status.progress.as_ref().and_then(|progress| progress.message.as_ref())
+        {
+            eprintln!("{}", message);
+        }
 
         Ok(CommandResult {
             success: true,
-            output: CommandOutput::Text(format!(
-                "Receive command executed (placeholder)\nOutput: {:?}\nAuto-accept: {}",
-                context.get_option("output"),
-                context.has_flag("auto-accept")
-            )),
-            execution_time,
+            output: CommandOutput::Text(String::new()),
+            execution_time: context.elapsed(),
             exit_code: 0,
         })
     }
@@ -162,6 +365,7 @@ impl CommandRouter {
         })
     }
 
+    #[cfg(feature = "command-execution")]
     async fn route_exec(context: CommandContext) -> CLIResult<CommandResult> {
         // Placeholder implementation - will be replaced by actual handler
         let execution_time = context.elapsed();
@@ -182,18 +386,47 @@ impl CommandRouter {
         })
     }
 
+    #[cfg(not(feature = "command-execution"))]
+    async fn route_exec(_context: CommandContext) -> CLIResult<CommandResult> {
+        Err(CLIError::execution(
+            "This build cannot run remote commands; rebuild with the command-execution feature",
+        ))
+    }
+
     async fn route_peers(context: CommandContext) -> CLIResult<CommandResult> {
-        // Placeholder implementation - will be replaced by actual handler
-        let execution_time = context.elapsed();
+        use crate::cli::handlers::{DiscoverArgs, DiscoverHandler};
+
+        let result = DiscoverHandler::new()
+            .handle_discover(DiscoverArgs {
+                filter_type: None,
+                filter_name: context.get_option("filter").cloned(),
+                timeout: Some(PEERS_DISCOVERY_TIMEOUT_SECS),
+                continuous: false,
+            })
+            .await?;
+
+        // Peers on another release are listed, but the user hears why
+        // operations with them may be refused
+        if !context.quiet {
+            for warning in DiscoverHandler::version_warnings(&result.peers) {
+                eprintln!("{}", warning);
+            }
+        }
+
+        let json = context.has_flag("json") || context.get_option("format").map(String::as_str) == Some("json");
+        let output = if json {
+            CommandOutput::JSON(
+                serde_json::to_value(&result.peers)
+                    .map_err(|e| CLIError::other(format!("Failed to serialize peers: {}", e)))?,
+            )
+        } else {
+            CommandOutput::Table(DiscoverHandler::peers_table(&result.peers))
+        };
 
         Ok(CommandResult {
             success: true,
-            output: CommandOutput::Text(format!(
-                "Peers command executed (placeholder)\nWatch: {}\nFilter: {:?}",
-                context.has_flag("watch"),
-                context.get_option("filter")
-            )),
-            execution_time,
+            output,
+            execution_time: context.elapsed(),
             exit_code: 0,
         })
     }
@@ -213,6 +446,7 @@ impl CommandRouter {
         })
     }
 
+    #[cfg(feature = "clipboard")]
     async fn route_clipboard(context: CommandContext) -> CLIResult<CommandResult> {
         // Placeholder implementation - will be replaced by actual handler
         let execution_time = context.elapsed();
@@ -232,6 +466,675 @@ impl CommandRouter {
         })
     }
 
+    #[cfg(not(feature = "clipboard"))]
+    async fn route_clipboard(_context: CommandContext) -> CLIResult<CommandResult> {
+        Err(CLIError::clipboard(
+            "This build has no clipboard sync; rebuild with the clipboard feature",
+        ))
+    }
+
+    async fn route_msg(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::MsgHandler;
+
+        let peer = context
+            .get_option("peer")
+            .cloned()
+            .ok_or_else(|| CLIError::MissingArgument("peer".to_string()))?;
+        let handler = MsgHandler::new().await?;
+
+        let output = if context.has_flag("history") {
+            let messages = handler.conversation(&peer).await?;
+            MsgHandler::format_conversation(&peer, &messages)
+        } else {
+            let text = context
+                .arguments()
+                .first()
+                .ok_or_else(|| CLIError::MissingArgument("text".to_string()))?;
+            let message = handler.send(&peer, text, context.has_flag("no-queue")).await?;
+            MsgHandler::format_sent(&peer, &message)
+        };
+
+        Ok(CommandResult {
+            success: true,
+            output: CommandOutput::Text(output),
+            execution_time: context.elapsed(),
+            exit_code: 0,
+        })
+    }
+
+    async fn route_get(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::GetHandler;
+
+        let remote = context
+            .arguments()
+            .first()
+            .ok_or_else(|| CLIError::MissingArgument("remote".to_string()))?;
+        let spec = GetHandler::parse_remote(remote)?;
+        let started = GetHandler::fetch(spec).await?;
+
+        Ok(CommandResult {
+            success: true,
+            output: CommandOutput::Text(GetHandler::format_started(
+                &started,
+                context.get_option("output").map(String::as_str),
+            )),
+            execution_time: context.elapsed(),
+            exit_code: 0,
+        })
+    }
+
+    #[cfg(feature = "command-execution")]
+    async fn route_open_on(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::OpenOnHandler;
+
+        let peer = context
+            .get_option("peer")
+            .cloned()
+            .ok_or_else(|| CLIError::MissingArgument("peer".to_string()))?;
+        let url = context
+            .arguments()
+            .first()
+            .ok_or_else(|| CLIError::MissingArgument("url".to_string()))?;
+        let result = OpenOnHandler::open(&peer, url).await?;
+
+        Ok(CommandResult {
+            success: result.success,
+            output: CommandOutput::Text(OpenOnHandler::format_result(&peer, url, &result)),
+            execution_time: context.elapsed(),
+            exit_code: if result.success { 0 } else { 1 },
+        })
+    }
+
+    #[cfg(not(feature = "command-execution"))]
+    async fn route_open_on(_context: CommandContext) -> CLIResult<CommandResult> {
+        Err(CLIError::execution(
+            "This build cannot hand off to peers; rebuild with the command-execution feature",
+        ))
+    }
+
+    #[cfg(feature = "command-execution")]
+    async fn route_locate(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::LocateHandler;
+        use crate::command_execution::LocateAction;
+
+        let peer = context
+            .get_option("peer")
+            .cloned()
+            .ok_or_else(|| CLIError::MissingArgument("peer".to_string()))?;
+        let action = if context.has_flag("stop") {
+            LocateAction::Stop
+        } else {
+            LocateAction::Ring
+        };
+        let result =
+            LocateHandler::locate(&peer, action, context.get_option("message").map(String::as_str)).await?;
+        let acknowledged = LocateHandler::acknowledged(action, &result);
+
+        Ok(CommandResult {
+            success: acknowledged,
+            output: CommandOutput::Text(LocateHandler::format_result(&peer, action, &result)),
+            execution_time: context.elapsed(),
+            exit_code: if acknowledged { 0 } else { 1 },
+        })
+    }
+
+    #[cfg(not(feature = "command-execution"))]
+    async fn route_locate(_context: CommandContext) -> CLIResult<CommandResult> {
+        Err(CLIError::execution(
+            "This build cannot locate peers; rebuild with the command-execution feature",
+        ))
+    }
+
+    async fn route_security(context: CommandContext) -> CLIResult<CommandResult> {
+        if matches!(context.subcommand(), Some("revoke" | "keep-trusted")) {
+            return Self::route_revocation(context).await;
+        }
+
+        use crate::cli::handlers::SecurityCheckHandler;
+        use crate::security::api::SecuritySystem;
+        use crate::security::policy::FindingSeverity;
+        use std::sync::Arc;
+
+        let mut handler = SecurityCheckHandler::new();
+        match SecuritySystem::new() {
+            Ok(security) => handler = handler.with_security(Arc::new(security)),
+            Err(e) => handler = handler.security_unavailable(format!("Security system unavailable: {}", e)),
+        }
+        #[cfg(feature = "command-execution")]
+        {
+            // Peers' commands are authorized under the default policy
+            let policy = crate::command_execution::auth::SecurityPolicy::default();
+            handler = handler.with_command_execution(policy.posture());
+        }
+        #[cfg(feature = "browser-support")]
+        {
+            handler = handler.with_browser_https(crate::browser_support::api::server::WebServer::enforces_https());
+        }
+        // Peers that cannot be discovered leave the protocol check unevaluated
+        let peers = crate::cli::handlers::discover_peers().await.unwrap_or_default();
+        handler = handler.with_peer_protocols(peers.iter().filter_map(|peer| peer.protocol_version()).collect());
+
+        let report = handler.check().await?;
+        // Fail scripted runs only for issues worth blocking on
+        let serious = report.failures().iter().any(|f| f.severity >= FindingSeverity::High);
+
+        let output = if context.has_flag("json") {
+            CommandOutput::JSON(
+                serde_json::to_value(&report)
+                    .map_err(|e| CLIError::other(format!("Failed to serialize report: {}", e)))?,
+            )
+        } else {
+            CommandOutput::Text(SecurityCheckHandler::format_report(&report))
+        };
+
+        Ok(CommandResult {
+            success: true,
+            output,
+            execution_time: context.elapsed(),
+            exit_code: if serious { 1 } else { 0 },
+        })
+    }
+
+    async fn route_revocation(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::security_integration::CLISecurityIntegration;
+        use crate::security::api::SecuritySystem;
+        use crate::security::PeerId;
+        use std::sync::Arc;
+
+        let peer = context
+            .arguments()
+            .first()
+            .ok_or_else(|| CLIError::MissingArgument("peer".to_string()))?;
+        let peer_id = PeerId::from_string(peer).map_err(|e| CLIError::InvalidArgumentValue {
+            arg: "peer".to_string(),
+            reason: e.to_string(),
+        })?;
+        let security = Arc::new(
+            SecuritySystem::new()
+                .map_err(|e| CLIError::security(format!("Failed to open security system: {}", e)))?,
+        );
+        let integration = CLISecurityIntegration::new(security).with_prompter(context.prompter);
+
+        let message = if context.subcommand() == Some("revoke") {
+            let record = integration
+                .revoke_peer(&peer_id, context.get_option("reason").cloned())
+                .await?;
+            format!(
+                "Revoked {}\n  Your other devices stop trusting it when they next connect to this one.\n",
+                record.revoked
+            )
+        } else if context.has_flag("clear") {
+            integration.set_revocation_override(&peer_id, false)?;
+            format!("Revocations of {} from your other devices apply here again\n", peer_id)
+        } else {
+            integration.set_revocation_override(&peer_id, true)?;
+            format!("{} stays trusted here even if another device revokes it\n", peer_id)
+        };
+
+        Ok(CommandResult {
+            success: true,
+            output: CommandOutput::Text(message),
+            execution_time: context.elapsed(),
+            exit_code: 0,
+        })
+    }
+
+    async fn route_transport(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::TransportDiagnoseHandler;
+
+        let report = TransportDiagnoseHandler::new()
+            .diagnose(context.has_flag("refresh"))
+            .await?;
+
+        let output = if context.has_flag("json") {
+            CommandOutput::JSON(
+                serde_json::to_value(&report)
+                    .map_err(|e| CLIError::other(format!("Failed to serialize report: {}", e)))?,
+            )
+        } else {
+            CommandOutput::Text(TransportDiagnoseHandler::format_report(&report))
+        };
+
+        Ok(CommandResult {
+            success: true,
+            output,
+            execution_time: context.elapsed(),
+            exit_code: 0,
+        })
+    }
+
+    async fn route_doctor(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::DoctorHandler;
+
+        let mut handler = DoctorHandler::new();
+        if let Some(proxy) = context.get_option("proxy") {
+            handler = handler.with_proxy(proxy)?;
+        }
+        if let Some(peer) = context.get_option("peer") {
+            handler = handler.with_peer(peer).await?;
+        }
+        let report = handler.run().await;
+
+        let output = if context.has_flag("json") {
+            CommandOutput::JSON(
+                serde_json::to_value(&report)
+                    .map_err(|e| CLIError::other(format!("Failed to serialize report: {}", e)))?,
+            )
+        } else {
+            CommandOutput::Text(DoctorHandler::format_report(&report))
+        };
+
+        Ok(CommandResult {
+            success: true,
+            output,
+            execution_time: context.elapsed(),
+            exit_code: DoctorHandler::exit_code(&report),
+        })
+    }
+
+    async fn route_bench(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::BenchHandler;
+
+        let peer = context
+            .arguments()
+            .first()
+            .cloned()
+            .ok_or_else(|| CLIError::MissingArgument("peer".to_string()))?;
+
+        let mut handler = BenchHandler::new();
+        if let Some(duration) = context.get_option("duration").and_then(|d| d.parse().ok()) {
+            handler = handler.with_duration(std::time::Duration::from_secs(duration));
+        }
+        if let Some(payload_size) = context.get_option("payload-size").and_then(|p| p.parse().ok()) {
+            handler = handler.with_payload_size(payload_size);
+        }
+        let report = handler.run(&peer).await?;
+
+        let output = if context.has_flag("json") {
+            CommandOutput::JSON(
+                serde_json::to_value(&report)
+                    .map_err(|e| CLIError::other(format!("Failed to serialize report: {}", e)))?,
+            )
+        } else {
+            CommandOutput::Text(BenchHandler::format_report(&report))
+        };
+
+        Ok(CommandResult {
+            success: !report.results.is_empty(),
+            output,
+            execution_time: context.elapsed(),
+            exit_code: if report.results.is_empty() { 1 } else { 0 },
+        })
+    }
+
+    async fn route_ping(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::PingHandler;
+
+        let peer = context
+            .arguments()
+            .first()
+            .cloned()
+            .ok_or_else(|| CLIError::MissingArgument("peer".to_string()))?;
+
+        let mut handler = PingHandler::new();
+        if let Some(timeout) = context.get_option("timeout").and_then(|t| t.parse().ok()) {
+            handler = handler.with_timeout(std::time::Duration::from_secs(timeout));
+        }
+        let outcome = handler.run(&peer).await?;
+
+        let output = if context.has_flag("json") {
+            CommandOutput::JSON(
+                serde_json::to_value(&outcome)
+                    .map_err(|e| CLIError::other(format!("Failed to serialize result: {}", e)))?,
+            )
+        } else {
+            CommandOutput::Text(PingHandler::format_outcome(&outcome))
+        };
+
+        Ok(CommandResult {
+            success: outcome.reply.is_some(),
+            output,
+            execution_time: context.elapsed(),
+            exit_code: outcome.exit_code,
+        })
+    }
+
+    async fn route_wipe(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::config::load_or_create_config;
+        use crate::cli::handlers::{WipeHandler, WIPE_CONFIRMATION};
+        use crate::cli::integration::CLISystemIntegration;
+
+        let remote = context.get_option("remote");
+        if !context.has_flag("yes") {
+            if context.prompter.is_interactive() {
+                match remote {
+                    Some(peer) => {
+                        println!("This asks {} to permanently erase its identity, trusted peers, histories,", peer);
+                        println!("messages, logs and recordings. It will need to be set up again.");
+                    }
+                    None => {
+                        println!("This permanently erases the device identity, trusted peers, histories,");
+                        println!("messages, logs and recordings. Peers will need to pair with this device again.");
+                    }
+                }
+            }
+            let input = context.prompter.ask(
+                &format!("Type {} to continue: ", WIPE_CONFIRMATION),
+                "pass --yes to wipe without confirmation",
+            )?;
+            if !WipeHandler::is_confirmed(&input) {
+                return Err(CLIError::Cancelled);
+            }
+        }
+
+        let loaded = match CLISystemIntegration::get_storage() {
+            Ok(storage) => load_or_create_config(storage).await,
+            Err(e) => Err(e),
+        };
+        let config = match loaded {
+            Ok(config) => config,
+            Err(e) => {
+                // A broken config or keystore must not stop a panic wipe
+                log::warn!("Wiping with default locations: {}", e);
+                Default::default()
+            }
+        };
+        let handler = WipeHandler::new(&config).await;
+
+        if let Some(peer) = remote {
+            handler.wipe_remote(peer).await?;
+            return Ok(CommandResult {
+                success: true,
+                output: CommandOutput::Text(format!(
+                    "Wipe request delivered to {}. It wipes itself if it allows remote wipe\nand this device is in its device group.\n",
+                    peer
+                )),
+                execution_time: context.elapsed(),
+                exit_code: 0,
+            });
+        }
+
+        let report = handler.wipe().await?;
+
+        Ok(CommandResult {
+            success: report.is_success(),
+            output: CommandOutput::Text(WipeHandler::format_report(&report)),
+            execution_time: context.elapsed(),
+            exit_code: if report.is_success() { 0 } else { 1 },
+        })
+    }
+
+    async fn route_export(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::{DateRange, ExportFormat, ExportHandler, ExportKind};
+        use crate::cli::integration::CLISystemIntegration;
+
+        let kind = ExportKind::parse(
+            context
+                .arguments()
+                .first()
+                .ok_or_else(|| CLIError::MissingArgument("what to export".to_string()))?,
+        )?;
+        let format = ExportFormat::parse(context.get_option("format").map(String::as_str).unwrap_or("csv"))?;
+        let range = DateRange::parse(
+            context.get_option("from").map(String::as_str),
+            context.get_option("to").map(String::as_str),
+        )?;
+
+        let mut handler = ExportHandler::new(CLISystemIntegration::get_storage()?)?;
+        if let Some(path) = context.get_option("audit-log") {
+            handler = handler.with_audit_log(path);
+        }
+        let table = handler.export(kind, range).await?;
+        let rows = table.rows.len();
+        let rendered = ExportHandler::render(table, format)?;
+
+        let output = match context.get_option("output") {
+            Some(path) => {
+                tokio::fs::write(path, rendered)
+                    .await
+                    .map_err(|e| CLIError::other(format!("Failed to write {}: {}", path, e)))?;
+                format!("Exported {} {} record(s) to {}", rows, kind.as_str(), path)
+            }
+            None => rendered,
+        };
+
+        Ok(CommandResult {
+            success: true,
+            output: CommandOutput::Text(output),
+            execution_time: context.elapsed(),
+            exit_code: 0,
+        })
+    }
+
+    #[cfg(feature = "self-update")]
+    async fn route_self_update(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::handlers::UpdateHandler;
+
+        let handler = UpdateHandler::new()?;
+        let check = handler.check().await?;
+        if !check.is_newer() {
+            return Ok(CommandResult {
+                success: true,
+                output: CommandOutput::Text(format!("kizuna {} is up to date", check.current)),
+                execution_time: context.elapsed(),
+                exit_code: 0,
+            });
+        }
+
+        let preview = UpdateHandler::format_preview(&check);
+        if context.has_flag("check") {
+            return Ok(CommandResult {
+                success: true,
+                output: CommandOutput::Text(preview),
+                execution_time: context.elapsed(),
+                exit_code: 0,
+            });
+        }
+
+        if !context.has_flag("yes") {
+            if context.prompter.is_interactive() {
+                println!("{}", preview);
+            }
+            let confirmed = context.prompter.confirm(
+                &format!("Install kizuna {}?", check.latest),
+                "pass --yes to update without confirmation",
+            )?;
+            if !confirmed {
+                return Err(CLIError::Cancelled);
+            }
+        }
+
+        let outcome = handler.apply(&check).await?;
+        Ok(CommandResult {
+            success: true,
+            output: CommandOutput::Text(format!(
+                "Updated to kizuna {}\nPrevious binary kept at {}",
+                outcome.version,
+                outcome.backup.display()
+            )),
+            execution_time: context.elapsed(),
+            exit_code: 0,
+        })
+    }
+
+    #[cfg(not(feature = "self-update"))]
+    async fn route_self_update(_context: CommandContext) -> CLIResult<CommandResult> {
+        Err(CLIError::other(
+            "This build of kizuna was packaged without self-update; update it with your package manager",
+        ))
+    }
+
+    async fn route_recordings(context: CommandContext) -> CLIResult<CommandResult> {
+        match context.subcommand() {
+            Some("list") | Some("show") | Some("tag") => Self::route_recordings_catalog(context).await,
+            Some("export") => Self::route_recordings_export(context).await,
+            other => Err(CLIError::InvalidCommand(format!(
+                "Unknown recordings subcommand: {}",
+                other.unwrap_or("none")
+            ))),
+        }
+    }
+
+    #[cfg(feature = "streaming")]
+    async fn route_recordings_catalog(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::config::load_or_create_config;
+        use crate::cli::handlers::RecordingsHandler;
+        use crate::cli::integration::CLISystemIntegration;
+        use crate::streaming::recording::RecordingQuery;
+
+        let config = load_or_create_config(CLISystemIntegration::get_storage()?).await?;
+        let handler = RecordingsHandler::new(&config)?;
+        let json = context.has_flag("json");
+        let id = || {
+            context
+                .arguments()
+                .first()
+                .cloned()
+                .ok_or_else(|| CLIError::MissingArgument("recording ID".to_string()))
+        };
+        let tags = |key: &str| -> Vec<String> {
+            context
+                .get_option(key)
+                .map(|v| v.split(',').map(str::to_string).collect())
+                .unwrap_or_default()
+        };
+        let to_json = |value: serde_json::Result<serde_json::Value>| {
+            value
+                .map(CommandOutput::JSON)
+                .map_err(|e| CLIError::other(format!("Failed to serialize result: {}", e)))
+        };
+
+        let output = match context.subcommand() {
+            Some("list") => {
+                let mut query = RecordingQuery::new();
+                query.tags = tags("tag");
+                if let Some(value) = context.get_option("since") {
+                    query.since = Some(RecordingsHandler::parse_time("since", value)?);
+                }
+                if let Some(value) = context.get_option("until") {
+                    query.until = Some(RecordingsHandler::parse_time("until", value)?);
+                }
+                if let Some(peer) = context.get_option("peer") {
+                    query.peer = Some(peer.clone());
+                }
+                if let Some(value) = context.get_option("min-length") {
+                    query.min_duration = Some(RecordingsHandler::parse_length("min-length", value)?);
+                }
+                if let Some(value) = context.get_option("max-length") {
+                    query.max_duration = Some(RecordingsHandler::parse_length("max-length", value)?);
+                }
+                query.limit = context.get_option("limit").and_then(|v| v.parse().ok());
+
+                let recordings = handler.list(&query).await?;
+                if json {
+                    to_json(serde_json::to_value(&recordings))?
+                } else {
+                    CommandOutput::Text(RecordingsHandler::format_list(&recordings))
+                }
+            }
+            Some("show") => {
+                let details = handler.show(&id()?).await?;
+                if json {
+                    to_json(serde_json::to_value(&details))?
+                } else {
+                    CommandOutput::Text(RecordingsHandler::format_details(&details))
+                }
+            }
+            _ => {
+                let notes = if context.has_flag("clear-note") {
+                    Some(None)
+                } else {
+                    context.get_option("note").map(|note| Some(note.clone()))
+                };
+                let recording = handler.tag(&id()?, &tags("add"), &tags("remove"), notes).await?;
+                if json {
+                    to_json(serde_json::to_value(&recording))?
+                } else {
+                    let details = handler.show(&recording.session_id.to_string()).await?;
+                    CommandOutput::Text(RecordingsHandler::format_details(&details))
+                }
+            }
+        };
+
+        Ok(CommandResult {
+            success: true,
+            output,
+            execution_time: context.elapsed(),
+            exit_code: 0,
+        })
+    }
+
+    #[cfg(not(feature = "streaming"))]
+    async fn route_recordings_catalog(_context: CommandContext) -> CLIResult<CommandResult> {
+        Err(CLIError::streaming(
+            "This build has no recording support; rebuild with the streaming feature",
+        ))
+    }
+
+    #[cfg(feature = "recording-export")]
+    async fn route_recordings_export(context: CommandContext) -> CLIResult<CommandResult> {
+        use crate::cli::config::load_or_create_config;
+        use crate::cli::handlers::RecordingsHandler;
+        use crate::cli::integration::CLISystemIntegration;
+        use crate::streaming::recording::export::parse_format;
+        use crate::streaming::recording::{ExportOptions, ThumbnailOptions};
+
+        let id = context
+            .arguments()
+            .first()
+            .cloned()
+            .ok_or_else(|| CLIError::MissingArgument("recording ID".to_string()))?;
+        let seconds = |key: &str| {
+            context
+                .get_option(key)
+                .and_then(|v| v.parse::<f64>().ok())
+                .map(Duration::from_secs_f64)
+        };
+
+        let format = parse_format(context.get_option("format").map(String::as_str).unwrap_or("mp4"))
+            .map_err(|e| CLIError::InvalidArgumentValue { arg: "format".to_string(), reason: e.to_string() })?;
+        let mut options = ExportOptions::new(format).with_trim(seconds("start"), seconds("end"));
+        if let Some(crf) = context.get_option("crf").and_then(|v| v.parse().ok()) {
+            options = options.with_crf(crf);
+        }
+        if let Some(kbps) = context.get_option("bitrate").and_then(|v| v.parse().ok()) {
+            options = options.with_bitrate(kbps);
+        }
+        if let Some(output) = context.get_option("output") {
+            options = options.with_output(output);
+        }
+        if context.has_flag("thumbnail") {
+            options = options.with_thumbnail(ThumbnailOptions::default());
+        }
+
+        let config = load_or_create_config(CLISystemIntegration::get_storage()?).await?;
+        let result = RecordingsHandler::new(&config)?.export(&id, options).await?;
+
+        let output = if context.has_flag("json") {
+            CommandOutput::JSON(
+                serde_json::to_value(&result)
+                    .map_err(|e| CLIError::other(format!("Failed to serialize result: {}", e)))?,
+            )
+        } else {
+            CommandOutput::Text(RecordingsHandler::format_export(&result))
+        };
+
+        Ok(CommandResult {
+            success: true,
+            output,
+            execution_time: context.elapsed(),
+            exit_code: 0,
+        })
+    }
+
+    #[cfg(not(feature = "recording-export"))]
+    async fn route_recordings_export(_context: CommandContext) -> CLIResult<CommandResult> {
+        Err(CLIError::streaming(
+            "This build cannot export recordings; rebuild with the recording-export feature",
+        ))
+    }
+
+    #[cfg(feature = "tui")]
     async fn route_tui(context: CommandContext) -> CLIResult<CommandResult> {
         // Placeholder implementation - will be replaced by actual handler
         let execution_time = context.elapsed();
@@ -246,6 +1149,13 @@ impl CommandRouter {
         })
     }
 
+    #[cfg(not(feature = "tui"))]
+    async fn route_tui(_context: CommandContext) -> CLIResult<CommandResult> {
+        Err(CLIError::tui(
+            "This build has no interactive mode; rebuild with the tui feature",
+        ))
+    }
+
     async fn route_config(context: CommandContext) -> CLIResult<CommandResult> {
         // Placeholder implementation - will be replaced by actual handler
         let execution_time = context.elapsed();
@@ -328,7 +1238,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_route_send() {
+    async fn test_route_send_missing_file() {
         let parsed = ParsedCommand::new(CommandType::Send)
             .with_argument("file.txt")
             .with_option("peer", "laptop");
@@ -337,10 +1247,89 @@ mod tests {
         let context = CommandContext::new(validated);
 
         let result = CommandRouter::route_send(context).await;
-        assert!(result.is_ok());
+        assert!(result.is_err());
+    }
 
-        let cmd_result = result.unwrap();
-        assert!(cmd_result.success);
+    #[tokio::test]
+    async fn test_route_send_needs_a_peer() {
+        let parsed = ParsedCommand::new(CommandType::Send).with_argument("file.txt");
+        let context = CommandContext::new(ValidatedCommand::new(parsed));
+
+        let error = CommandRouter::route_send(context).await.unwrap_err();
+        assert!(matches!(error, CLIError::MissingArgument(_)));
+    }
+
+    #[tokio::test]
+    async fn test_pipe_from_send_to_receive() {
+        use crate::cli::parser::{ClapCommandParser, CommandParser};
+        use crate::file_transfer::api::FileTransferSystem;
+        use crate::security::PeerId;
+        use crate::testing::MemorySecurity;
+        use crate::transport::{
+            ConnectionProvider, PeerAddress, ProviderConfig, TcpConfig, TcpListener, TcpTransport,
+            TransportCapabilities,
+        };
+        use std::sync::Arc;
+
+        async fn parse(args: &[&str]) -> CLIResult<CommandContext> {
+            let parser = ClapCommandParser::new();
+            let parsed = parser.parse_args(args.iter().map(|arg| arg.to_string()).collect()).await?;
+            Ok(CommandContext::new(parser.validate_command(parsed).await?))
+        }
+        fn handler(dir: &tempfile::TempDir, name: &str, provider: Option<Arc<ConnectionProvider>>) -> TransferHandler {
+            let security = Arc::new(MemorySecurity::new().unwrap());
+            let mut file_transfer = FileTransferSystem::new(security, dir.path().join(name));
+            if let Some(provider) = provider {
+                file_transfer = file_transfer.with_connection_provider(provider);
+            }
+            TransferHandler::with_file_transfer(Arc::new(file_transfer))
+        }
+        let dir = tempfile::TempDir::new().unwrap();
+        let receiver = PeerId::from_fingerprint([7; 32]).to_hex();
+
+        // stdin has no file name, so the validator asks for one
+        let error = parse(&["kizuna", "send", "-", "--peer", &receiver]).await.unwrap_err();
+        assert!(matches!(error, CLIError::MissingArgument(_)));
+
+        let context = parse(&["kizuna", "receive", "--to-stdout"]).await.unwrap();
+        let args = CommandRouter::receive_args(&context);
+        let listener = TcpListener::bind("127.0.0.1:0".parse().unwrap(), TcpConfig::default()).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let receiving = tokio::spawn({
+            let handler = handler(&dir, "receiver", None);
+            let sessions = PeerSessions::new(Arc::new(ConnectionProvider::new(
+                Arc::new(TcpTransport::new()),
+                ProviderConfig::default(),
+            )));
+            async move {
+                let mut written = Vec::new();
+                let result =
+                    CommandRouter::receive_piped(&context, &handler, args, listener, &sessions, &mut written).await;
+                result.map(|result| (result, written))
+            }
+        });
+
+        let context = parse(&["kizuna", "send", "-", "--peer", &receiver, "--name", "dump.sql"])
+            .await
+            .unwrap();
+        let provider = Arc::new(ConnectionProvider::new(Arc::new(TcpTransport::new()), ProviderConfig::default()));
+        provider
+            .session(&PeerAddress::new(
+                receiver.clone(),
+                vec![address],
+                vec!["tcp".to_string()],
+                TransportCapabilities::tcp(),
+            ))
+            .await
+            .unwrap();
+        let sender = handler(&dir, "sender", Some(provider));
+        let args = CommandRouter::send_args(&context).unwrap();
+        let sent = CommandRouter::send_with(&context, &sender, args, &b"select 1;"[..]).await.unwrap();
+        assert!(sent.success);
+
+        let (received, written) = receiving.await.unwrap().unwrap();
+        assert!(received.success);
+        assert_eq!(written, b"select 1;");
     }
 
     #[tokio::test]
@@ -370,4 +1359,17 @@ mod tests {
         let cmd_result = result.unwrap();
         assert!(cmd_result.success);
     }
+
+    #[tokio::test]
+    async fn test_non_interactive_fails_fast() {
+        let parsed = ParsedCommand::new(CommandType::Wipe).with_flag("non-interactive");
+        let error = CommandPipeline::execute(ValidatedCommand::new(parsed)).await.unwrap_err();
+        assert!(matches!(error, CLIError::InputRequired(_)));
+        assert!(error.to_string().contains("--yes"));
+        assert_eq!(error.exit_code(), 64);
+
+        let parsed = ParsedCommand::new(CommandType::TUI).with_flag("non-interactive");
+        let error = CommandPipeline::execute(ValidatedCommand::new(parsed)).await.unwrap_err();
+        assert!(matches!(error, CLIError::InputRequired(_)));
+    }
 }
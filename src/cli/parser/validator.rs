@@ -2,6 +2,8 @@
 
 use crate::cli::error::{CLIError, CLIResult};
 use crate::cli::types::{CommandType, ParsedCommand};
+use crate::file_transfer::pipe::is_stdio;
+use crate::transport::MAX_BENCHMARK_PAYLOAD;
 use std::path::Path;
 
 /// Command validator with enhanced error messages and suggestions
@@ -37,6 +39,45 @@ impl CommandValidator {
             CommandType::Clipboard => {
                 Self::validate_clipboard(command, &mut warnings)?;
             }
+            CommandType::Msg => {
+                Self::validate_msg(command, &mut warnings)?;
+            }
+            CommandType::Get => {
+                Self::validate_get(command, &mut warnings)?;
+            }
+            CommandType::OpenOn => {
+                Self::validate_open_on(command, &mut warnings)?;
+            }
+            CommandType::Locate => {
+                Self::validate_locate(command, &mut warnings)?;
+            }
+            CommandType::Security => {
+                Self::validate_security(command, &mut warnings)?;
+            }
+            CommandType::Transport => {
+                Self::validate_transport(command, &mut warnings)?;
+            }
+            CommandType::Doctor => {
+                Self::validate_doctor(command, &mut warnings)?;
+            }
+            CommandType::Bench => {
+                Self::validate_bench(command, &mut warnings)?;
+            }
+            CommandType::Ping => {
+                Self::validate_ping(command, &mut warnings)?;
+            }
+            CommandType::Wipe => {
+                Self::validate_wipe(command, &mut warnings)?;
+            }
+            CommandType::Recordings => {
+                Self::validate_recordings(command, &mut warnings)?;
+            }
+            CommandType::Export => {
+                Self::validate_export(command, &mut warnings)?;
+            }
+            CommandType::SelfUpdate => {
+                Self::validate_self_update(command, &mut warnings)?;
+            }
             CommandType::TUI => {
                 Self::validate_tui(command, &mut warnings)?;
             }
@@ -109,8 +150,24 @@ impl CommandValidator {
             ));
         }
 
+        // "-" sends stdin, which has no file name of its own to give the peer
+        let piped = command.arguments.iter().any(|file| is_stdio(Path::new(file)));
+        if piped {
+            if command.arguments.len() > 1 {
+                return Err(CLIError::InvalidArgumentValue {
+                    arg: "files".to_string(),
+                    reason: "- (stdin) cannot be sent together with files".to_string(),
+                });
+            }
+            if command.get_option("name").is_none() {
+                return Err(CLIError::MissingArgument(
+                    "name - data from stdin needs a name, e.g. --name dump.sql".to_string(),
+                ));
+            }
+        }
+
         // Validate file paths exist
-        for file_path in &command.arguments {
+        for file_path in command.arguments.iter().filter(|file| !is_stdio(Path::new(file))) {
             let path = Path::new(file_path);
             if !path.exists() {
                 return Err(CLIError::InvalidArgumentValue {
@@ -135,6 +192,23 @@ impl CommandValidator {
             }
         }
 
+        // Fan-out targets must be named, and swarming needs more than one
+        if let Some(targets) = command.get_option("to")
+            && targets.split(',').any(|peer| peer.trim().is_empty())
+        {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "to".to_string(),
+                reason: format!("'{}' contains an empty peer name", targets),
+            });
+        }
+        if command.has_flag("swarm") && command.get_option("to").is_none() {
+            warnings.push(ValidationWarning {
+                field: "swarm".to_string(),
+                message: "--swarm has no effect when sending to a single peer".to_string(),
+                suggestion: Some("List several peers with --to, e.g. --to laptop,phone".to_string()),
+            });
+        }
+
         // Warn if encryption is disabled
         if command.has_flag("no-encryption") {
             warnings.push(ValidationWarning {
@@ -349,6 +423,155 @@ impl CommandValidator {
         Ok(())
     }
 
+    fn validate_msg(
+        command: &ParsedCommand,
+        warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        if command.get_option("peer").is_none() {
+            return Err(CLIError::MissingArgument(
+                "peer - the peer to message must be specified".to_string(),
+            ));
+        }
+
+        if command.has_flag("history") {
+            return Ok(());
+        }
+
+        let text = command.arguments.first().map(|s| s.trim()).unwrap_or("");
+        if text.is_empty() {
+            return Err(CLIError::MissingArgument(
+                "text - message text must not be empty".to_string(),
+            ));
+        }
+
+        if text.len() > 16 * 1024 {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "text".to_string(),
+                reason: format!("message is {} bytes, the limit is 16384", text.len()),
+            });
+        }
+
+        if text.len() > 2000 {
+            warnings.push(ValidationWarning {
+                field: "text".to_string(),
+                message: "Long message".to_string(),
+                suggestion: Some("Consider sending long text as a file with 'kizuna send'".to_string()),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_get(
+        command: &ParsedCommand,
+        warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        let remote = command.arguments.first().ok_or_else(|| {
+            CLIError::MissingArgument("remote - the file to fetch must be given as PEER:SHARE/PATH".to_string())
+        })?;
+
+        let spec = crate::remote_fs::RemoteSpec::parse(remote).map_err(|e| CLIError::InvalidArgumentValue {
+            arg: "remote".to_string(),
+            reason: e.to_string(),
+        })?;
+
+        if spec.path == spec.share() {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "remote".to_string(),
+                reason: format!("'{}' names a share, not a file in it", spec),
+            });
+        }
+
+        if let Some(output) = command.get_option("output") {
+            let path = std::path::Path::new(output);
+            if path.exists() && !path.is_dir() {
+                return Err(CLIError::InvalidArgumentValue {
+                    arg: "output".to_string(),
+                    reason: format!("'{}' is not a directory", output),
+                });
+            }
+            if !path.exists() {
+                warnings.push(ValidationWarning {
+                    field: "output".to_string(),
+                    message: format!("Directory '{}' does not exist", output),
+                    suggestion: Some("It will be created when the file arrives".to_string()),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_open_on(
+        command: &ParsedCommand,
+        warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        use crate::command_execution::handoff::{url_scheme, HandoffTarget, DEFAULT_ALLOWED_SCHEMES};
+
+        if command.get_option("peer").is_none() {
+            return Err(CLIError::MissingArgument(
+                "peer - the peer to open the URL on must be specified".to_string(),
+            ));
+        }
+
+        let url = command.arguments.first().ok_or_else(|| {
+            CLIError::MissingArgument("url - the URL to open must be specified".to_string())
+        })?;
+
+        HandoffTarget::Url(url.clone())
+            .validate()
+            .map_err(|e| CLIError::InvalidArgumentValue {
+                arg: "url".to_string(),
+                reason: e.to_string(),
+            })?;
+
+        let scheme = url_scheme(url).unwrap_or_default();
+        if !DEFAULT_ALLOWED_SCHEMES.contains(&scheme.as_str()) {
+            warnings.push(ValidationWarning {
+                field: "url".to_string(),
+                message: format!("Peers refuse '{}' URLs unless they allow the scheme", scheme),
+                suggestion: Some("Use an http or https URL".to_string()),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_locate(
+        command: &ParsedCommand,
+        warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        use crate::command_execution::locate::MAX_LOCATE_MESSAGE_LEN;
+
+        if command.get_option("peer").is_none() {
+            return Err(CLIError::MissingArgument(
+                "peer - the peer to ring must be specified".to_string(),
+            ));
+        }
+
+        if let Some(message) = command.get_option("message") {
+            if command.has_flag("stop") {
+                warnings.push(ValidationWarning {
+                    field: "message".to_string(),
+                    message: "The message is ignored when stopping a ring".to_string(),
+                    suggestion: None,
+                });
+            }
+            if message.len() > MAX_LOCATE_MESSAGE_LEN {
+                return Err(CLIError::InvalidArgumentValue {
+                    arg: "message".to_string(),
+                    reason: format!(
+                        "message is {} bytes, the limit is {}",
+                        message.len(),
+                        MAX_LOCATE_MESSAGE_LEN
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     fn validate_tui(
         _command: &ParsedCommand,
         _warnings: &mut Vec<ValidationWarning>,
@@ -396,11 +619,314 @@ impl CommandValidator {
         Ok(())
     }
 
+    fn validate_security(
+        command: &ParsedCommand,
+        _warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        match command.subcommand.as_deref() {
+            Some("check") => Ok(()),
+            Some("revoke" | "keep-trusted") => {
+                let peer = command
+                    .arguments
+                    .first()
+                    .ok_or_else(|| CLIError::MissingArgument("peer - the ID of the peer".to_string()))?;
+                crate::security::PeerId::from_string(peer)
+                    .map(|_| ())
+                    .map_err(|e| CLIError::InvalidArgumentValue {
+                        arg: "peer".to_string(),
+                        reason: e.to_string(),
+                    })
+            }
+            Some(other) => Err(CLIError::InvalidCommand(format!(
+                "Unknown security subcommand: {}",
+                other
+            ))),
+            None => Err(CLIError::MissingArgument(
+                "subcommand - use 'security check', 'security revoke' or 'security keep-trusted'".to_string(),
+            )),
+        }
+    }
+
+    fn validate_transport(
+        command: &ParsedCommand,
+        _warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        match command.subcommand.as_deref() {
+            Some("diagnose") => Ok(()),
+            Some(other) => Err(CLIError::InvalidCommand(format!(
+                "Unknown transport subcommand: {}",
+                other
+            ))),
+            None => Err(CLIError::MissingArgument(
+                "subcommand - use 'transport diagnose'".to_string(),
+            )),
+        }
+    }
+
+    fn validate_doctor(
+        command: &ParsedCommand,
+        _warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        if let Some(peer) = command.get_option("peer") && peer.trim().is_empty() {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "peer".to_string(),
+                reason: "Peer name cannot be empty".to_string(),
+            });
+        }
+
+        if let Some(proxy) = command.get_option("proxy")
+            && let Err(e) = proxy.parse::<crate::transport::ProxyConfig>()
+        {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "proxy".to_string(),
+                reason: e.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_bench(
+        command: &ParsedCommand,
+        warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        match command.arguments.first() {
+            Some(peer) if !peer.trim().is_empty() => {}
+            _ => return Err(CLIError::MissingArgument("peer".to_string())),
+        }
+
+        if let Some(duration) = command.get_option("duration") {
+            match duration.parse::<u64>() {
+                Ok(0) | Err(_) => {
+                    return Err(CLIError::InvalidArgumentValue {
+                        arg: "duration".to_string(),
+                        reason: "duration must be a whole number of seconds greater than 0".to_string(),
+                    });
+                }
+                Ok(val) if val > 120 => {
+                    warnings.push(ValidationWarning {
+                        field: "duration".to_string(),
+                        message: "duration applies to every protocol, so the benchmark will take several minutes"
+                            .to_string(),
+                        suggestion: Some("A few seconds per protocol is usually enough".to_string()),
+                    });
+                }
+                Ok(_) => {}
+            }
+        }
+
+        if let Some(payload_size) = command.get_option("payload-size") {
+            match payload_size.parse::<usize>() {
+                Ok(val) if val > 0 && val <= MAX_BENCHMARK_PAYLOAD => {}
+                _ => {
+                    return Err(CLIError::InvalidArgumentValue {
+                        arg: "payload-size".to_string(),
+                        reason: format!("payload-size must be between 1 and {} bytes", MAX_BENCHMARK_PAYLOAD),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_ping(
+        command: &ParsedCommand,
+        _warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        match command.arguments.first() {
+            Some(peer) if !peer.trim().is_empty() => {}
+            _ => return Err(CLIError::MissingArgument("peer".to_string())),
+        }
+
+        if let Some(timeout) = command.get_option("timeout") {
+            match timeout.parse::<u64>() {
+                Ok(val) if val > 0 => {}
+                _ => {
+                    return Err(CLIError::InvalidArgumentValue {
+                        arg: "timeout".to_string(),
+                        reason: "timeout must be a whole number of seconds greater than 0".to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_wipe(
+        command: &ParsedCommand,
+        warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        let remote = command.get_option("remote");
+        if !command.has_flag("all") && remote.is_none() {
+            return Err(CLIError::MissingArgument(
+                "'--all' to wipe this device or '--remote <peer>' to wipe another".to_string(),
+            ));
+        }
+
+        if let Some(peer) = remote {
+            if peer.trim().is_empty() {
+                return Err(CLIError::InvalidArgumentValue {
+                    arg: "remote".to_string(),
+                    reason: "Peer name cannot be empty".to_string(),
+                });
+            }
+            warnings.push(ValidationWarning {
+                field: "remote".to_string(),
+                message: format!("'{}' will erase all Kizuna data as soon as it receives the request", peer),
+                suggestion: Some("The peer must have allow_remote_wipe enabled in its retention settings".to_string()),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_export(
+        command: &ParsedCommand,
+        warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        use crate::cli::handlers::{DateRange, ExportFormat, ExportKind};
+
+        let kind = command
+            .arguments
+            .first()
+            .ok_or_else(|| CLIError::MissingArgument("what to export: discovery, transfers, bandwidth or audit".to_string()))
+            .and_then(|data| ExportKind::parse(data))?;
+        if let Some(format) = command.get_option("format") {
+            ExportFormat::parse(format)?;
+        }
+        DateRange::parse(
+            command.get_option("from").map(String::as_str),
+            command.get_option("to").map(String::as_str),
+        )?;
+
+        if command.get_option("audit-log").is_some() && kind != ExportKind::Audit {
+            warnings.push(ValidationWarning {
+                field: "audit-log".to_string(),
+                message: format!("--audit-log is only read by audit exports, not {}", kind.as_str()),
+                suggestion: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_self_update(
+        command: &ParsedCommand,
+        _warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        if command.has_flag("check") && command.has_flag("yes") {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "yes".to_string(),
+                reason: "--check never installs anything, so there is nothing to confirm".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_recordings(
+        command: &ParsedCommand,
+        _warnings: &mut Vec<ValidationWarning>,
+    ) -> CLIResult<()> {
+        match command.subcommand.as_deref() {
+            Some("list") => {
+                if let Some(limit) = command.get_option("limit") {
+                    match limit.parse::<usize>() {
+                        Ok(val) if val > 0 => {}
+                        _ => {
+                            return Err(CLIError::InvalidArgumentValue {
+                                arg: "limit".to_string(),
+                                reason: "limit must be a whole number greater than 0".to_string(),
+                            });
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            Some("show") | Some("tag") | Some("export") => {}
+            Some(other) => {
+                return Err(CLIError::InvalidCommand(format!(
+                    "Unknown recordings subcommand: {}",
+                    other
+                )));
+            }
+            None => {
+                return Err(CLIError::MissingArgument(
+                    "subcommand - use 'recordings list', 'show', 'tag' or 'export'".to_string(),
+                ));
+            }
+        }
+
+        match command.arguments.first() {
+            Some(id) if !id.trim().is_empty() => {}
+            _ => return Err(CLIError::MissingArgument("recording ID".to_string())),
+        }
+
+        if command.subcommand.as_deref() == Some("tag")
+            && !["add", "remove", "note"].iter().any(|opt| command.get_option(opt).is_some())
+            && !command.has_flag("clear-note")
+        {
+            return Err(CLIError::MissingArgument(
+                "--add, --remove, --note or --clear-note".to_string(),
+            ));
+        }
+
+        if let Some(crf) = command.get_option("crf") {
+            match crf.parse::<u8>() {
+                Ok(val) if val <= 51 => {}
+                _ => {
+                    return Err(CLIError::InvalidArgumentValue {
+                        arg: "crf".to_string(),
+                        reason: "crf must be a whole number from 0 to 51".to_string(),
+                    });
+                }
+            }
+        }
+
+        if let Some(bitrate) = command.get_option("bitrate") {
+            match bitrate.parse::<u32>() {
+                Ok(val) if val > 0 => {}
+                _ => {
+                    return Err(CLIError::InvalidArgumentValue {
+                        arg: "bitrate".to_string(),
+                        reason: "bitrate must be a whole number of kbps greater than 0".to_string(),
+                    });
+                }
+            }
+        }
+
+        let mut bounds = Vec::new();
+        for arg in ["start", "end"] {
+            if let Some(value) = command.get_option(arg) {
+                match value.parse::<f64>() {
+                    Ok(val) if val.is_finite() && val >= 0.0 => bounds.push(val),
+                    _ => {
+                        return Err(CLIError::InvalidArgumentValue {
+                            arg: arg.to_string(),
+                            reason: format!("{} must be a number of seconds", arg),
+                        });
+                    }
+                }
+            }
+        }
+        if let [start, end] = bounds[..] && end <= start {
+            return Err(CLIError::InvalidArgumentValue {
+                arg: "end".to_string(),
+                reason: "end must be after start".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
     /// Suggest similar commands for typos
     pub fn suggest_similar_commands(invalid: &str) -> Vec<String> {
         let commands = vec![
             "discover", "send", "receive", "stream", "exec", "peers", "status", "clipboard",
-            "tui", "config",
+            "msg", "get", "open-on", "locate", "security", "transport", "doctor", "bench",
+            "ping", "wipe", "recordings", "export", "self-update", "tui", "config",
         ];
 
         let mut suggestions: Vec<(String, usize)> = commands
@@ -418,18 +944,39 @@ impl CommandValidator {
 
     /// Suggest similar option names for typos
     pub fn suggest_similar_options(invalid: &str, command_type: CommandType) -> Vec<String> {
-        let options = match command_type {
+        let mut options = match command_type {
             CommandType::Discover => vec!["type", "name", "timeout", "watch", "format", "json"],
-            CommandType::Send => vec!["peer", "no-compression", "no-encryption", "verbose"],
-            CommandType::Receive => vec!["output", "auto-accept", "from"],
-            CommandType::Stream => vec!["camera", "quality", "record", "output"],
+            CommandType::Send => vec![
+                "peer", "to", "swarm", "name", "no-compression", "no-encryption", "allow-metered", "no-notify",
+                "dry-run", "verbose",
+            ],
+            CommandType::Receive => vec!["output", "auto-accept", "to-stdout", "from", "no-notify"],
+            CommandType::Stream => vec!["camera", "quality", "record", "output", "no-notify"],
             CommandType::Exec => vec!["peer", "interactive"],
             CommandType::Peers => vec!["watch", "filter", "format"],
             CommandType::Status => vec!["detailed", "json"],
             CommandType::Clipboard => vec!["peer", "enable", "disable"],
+            CommandType::Msg => vec!["history", "no-queue"],
+            CommandType::Get => vec!["output"],
+            CommandType::OpenOn => vec![],
+            CommandType::Locate => vec!["message", "stop"],
+            CommandType::Security => vec!["json", "reason", "clear"],
+            CommandType::Transport => vec!["refresh", "json"],
+            CommandType::Doctor => vec!["peer", "proxy", "json"],
+            CommandType::Bench => vec!["duration", "payload-size", "json"],
+            CommandType::Ping => vec!["timeout", "json"],
+            CommandType::Wipe => vec!["all", "remote", "yes"],
+            CommandType::Recordings => vec![
+                "since", "until", "peer", "min-length", "max-length", "tag", "limit", "add", "remove", "note",
+                "clear-note", "format", "crf", "bitrate", "start", "end", "thumbnail", "output", "json",
+            ],
+            CommandType::Export => vec!["format", "from", "to", "output", "audit-log"],
+            CommandType::SelfUpdate => vec!["check", "yes"],
             CommandType::TUI => vec![],
             CommandType::Config => vec!["key", "value"],
         };
+        // Global options
+        options.push("non-interactive");
 
         let mut suggestions: Vec<(String, usize)> = options
             .iter()
@@ -487,6 +1034,76 @@ impl CommandValidator {
                  'clipboard status' to view current state, and 'clipboard history' to view past items."
                     .to_string()
             }
+            CommandType::Msg => {
+                "Send an encrypted text message to a trusted peer with 'msg <peer> <text>'. \
+                 Messages to offline peers are queued; 'msg <peer> --history' shows the conversation."
+                    .to_string()
+            }
+            CommandType::Get => {
+                "Fetch a file from a peer's shared folder with 'get <peer>:<share>/<path>'. \
+                 The peer only sends files from shares it exports to you; use '-o <dir>' to choose \
+                 where the file is saved."
+                    .to_string()
+            }
+            CommandType::OpenOn => {
+                "Open a URL in a peer's default browser with 'open-on <peer> <url>'. \
+                 The peer must allow you to hand off and accepts only http and https URLs by default."
+                    .to_string()
+            }
+            CommandType::Locate => {
+                "Ring a peer with 'locate <peer>' to find it. The peer plays an alert at full volume \
+                 until someone acknowledges it there; use '--stop' once you have found it."
+                    .to_string()
+            }
+            CommandType::Security => {
+                "Check this device's security with 'security check'. The report scores the \
+                 configuration and lists each issue with a fix; use '--json' for scripting. \
+                 'security revoke <peer-id>' stops trusting a peer on all of your devices."
+                    .to_string()
+            }
+            CommandType::Transport => {
+                "Check how this device reaches peers with 'transport diagnose'. It reports the NAT \
+                 type and whether direct connections need a relay; '--refresh' re-runs detection."
+                    .to_string()
+            }
+            CommandType::Doctor => {
+                "Run 'doctor' to check ports, firewall, multicast, STUN, relays, clock and keyring \
+                 in one go. Add '--peer <peer>' to also ping a device; each problem comes with a fix."
+                    .to_string()
+            }
+            CommandType::Bench => {
+                "Compare protocols with 'bench <peer>'. It measures round-trip time, jitter and \
+                 throughput for each one; the fastest is then preferred when connecting to that peer."
+                    .to_string()
+            }
+            CommandType::Ping => {
+                "Check a peer with 'ping <peer>'. Its Kizuna process answers with a signed reply; the \
+                 exit code is 0 if it answered, 1 if unreachable and 2 if it refused the ping."
+                    .to_string()
+            }
+            CommandType::Wipe => {
+                "Erase all Kizuna data on this device with 'wipe --all', or ask a lost device to \
+                 erase itself with 'wipe --remote <peer>'. You are asked to type WIPE unless '--yes' is given."
+                    .to_string()
+            }
+            CommandType::Recordings => {
+                "Find recordings with 'recordings list' and filter them with --since, --peer, \
+                 --min-length or --tag. See one with 'recordings show <id>', label it with \
+                 'recordings tag <id> --add <tag> --note <text>', and convert it with \
+                 'recordings export <id> --format mp4'."
+                    .to_string()
+            }
+            CommandType::Export => {
+                "Export data for analysis with 'export <discovery|transfers|bandwidth|audit>'. \
+                 Choose CSV or JSON with --format, limit the dates with --from and --to \
+                 (YYYY-MM-DD) and write to a file with --output."
+                    .to_string()
+            }
+            CommandType::SelfUpdate => {
+                "Update to the latest release with 'self-update'. Use '--check' to see the \
+                 changelog without installing, or '--yes' to install without being asked."
+                    .to_string()
+            }
             CommandType::TUI => {
                 "Launch the interactive Text User Interface for visual peer management \
                  and file operations."
@@ -557,6 +1174,21 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_send_stdin_needs_a_name() {
+        let command = ParsedCommand::new(CommandType::Send)
+            .with_argument("-")
+            .with_option("peer", "laptop");
+        let error = CommandValidator::validate(&command).unwrap_err();
+        assert!(matches!(error, CLIError::MissingArgument(_)));
+
+        let command = command.with_option("name", "dump.sql");
+        assert!(CommandValidator::validate(&command).is_ok());
+
+        let command = command.with_argument("notes.txt");
+        assert!(CommandValidator::validate(&command).is_err());
+    }
+
     #[test]
     fn test_validate_exec_missing_peer() {
         let mut command = ParsedCommand::new(CommandType::Exec);
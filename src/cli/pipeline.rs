@@ -153,7 +153,7 @@ impl PipelineOutput {
                 // Write CSV header
                 writeln!(
                     self.writer,
-                    "id,name,device_type,connection_status,trust_status"
+                    "id,name,device_type,connection_status,trust_status,version"
                 )
                 .map_err(|e| CLIError::IOError(e))?;
 
@@ -161,8 +161,13 @@ impl PipelineOutput {
                 for peer in peers {
                     writeln!(
                         self.writer,
-                        "{},{},{},{:?},{:?}",
-                        peer.id, peer.name, peer.device_type, peer.connection_status, peer.trust_status
+                        "{},{},{},{:?},{:?},{}",
+                        peer.id,
+                        peer.name,
+                        peer.device_type,
+                        peer.connection_status,
+                        peer.trust_status,
+                        peer.version.as_deref().unwrap_or_default()
                     )
                     .map_err(|e| CLIError::IOError(e))?;
                 }
@@ -420,6 +425,8 @@ mod tests {
             capabilities: vec!["transfer".to_string()],
             trust_status: crate::cli::types::TrustStatus::Trusted,
             last_seen: Some(chrono::Utc::now()),
+            presence: None,
+            version: None,
         }];
 
         pipeline.write_peer_list(&peers).unwrap();
@@ -440,6 +447,8 @@ mod tests {
             capabilities: vec!["transfer".to_string()],
             trust_status: crate::cli::types::TrustStatus::Trusted,
             last_seen: Some(chrono::Utc::now()),
+            presence: None,
+            version: None,
         }];
 
         pipeline.write_peer_list(&peers).unwrap();
@@ -462,6 +471,8 @@ mod tests {
                 capabilities: vec![],
                 trust_status: crate::cli::types::TrustStatus::Trusted,
                 last_seen: None,
+                presence: None,
+                version: None,
             },
             PeerInfo {
                 id: uuid::Uuid::new_v4(),
@@ -471,6 +482,8 @@ mod tests {
                 capabilities: vec![],
                 trust_status: crate::cli::types::TrustStatus::Trusted,
                 last_seen: None,
+                presence: None,
+                version: None,
             },
         ];
 
@@ -0,0 +1,127 @@
+// Confirmation prompts
+//
+// Commands that need an answer from the user ask through a Prompter. When
+// the CLI runs non-interactively (--non-interactive, or KIZUNA_NON_INTERACTIVE
+// set in the environment, as in cron jobs and configuration management),
+// nothing is read from the terminal: the prompt fails straight away with
+// CLIError::InputRequired naming the option that supplies the answer, so the
+// script exits with a usage error instead of hanging on stdin.
+
+use std::io::{self, BufRead, Write};
+
+use crate::cli::error::{CLIError, CLIResult};
+
+/// Environment variable that turns on non-interactive mode
+pub const NON_INTERACTIVE_ENV: &str = "KIZUNA_NON_INTERACTIVE";
+
+/// Asks the user questions, or refuses to when running non-interactively
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prompter {
+    non_interactive: bool,
+}
+
+impl Prompter {
+    /// Create a prompter; `non_interactive` is usually the `--non-interactive` flag
+    ///
+    /// Non-interactive mode is also on when KIZUNA_NON_INTERACTIVE is set to
+    /// anything other than an empty string, `0` or `false`.
+    pub fn new(non_interactive: bool) -> Self {
+        Self {
+            non_interactive: non_interactive || non_interactive_env(),
+        }
+    }
+
+    /// A prompter that never asks
+    pub fn non_interactive() -> Self {
+        Self { non_interactive: true }
+    }
+
+    /// Whether questions may be asked
+    pub fn is_interactive(&self) -> bool {
+        !self.non_interactive
+    }
+
+    /// Print `question` and read one line of input
+    ///
+    /// `instead` tells a non-interactive caller how to supply the answer,
+    /// e.g. "pass --yes".
+    pub fn ask(&self, question: &str, instead: &str) -> CLIResult<String> {
+        let stdin = io::stdin();
+        self.ask_from(question, instead, &mut stdin.lock())
+    }
+
+    /// Ask a yes/no question; only `y` or `yes` count as yes
+    pub fn confirm(&self, question: &str, instead: &str) -> CLIResult<bool> {
+        let answer = self.ask(&format!("{} (y/n): ", question), instead)?;
+        Ok(is_yes(&answer))
+    }
+
+    fn ask_from(&self, question: &str, instead: &str, input: &mut impl BufRead) -> CLIResult<String> {
+        if self.non_interactive {
+            let question = question.trim().trim_end_matches(':').trim_end_matches("(y/n)").trim();
+            return Err(CLIError::input_required(format!(
+                "'{}' needs an answer; {}",
+                question, instead
+            )));
+        }
+
+        print!("{}", question);
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            // stdin closed before an answer arrived
+            return Err(CLIError::Cancelled);
+        }
+        Ok(answer.trim().to_string())
+    }
+}
+
+impl Default for Prompter {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+fn non_interactive_env() -> bool {
+    std::env::var(NON_INTERACTIVE_ENV)
+        .map(|value| !matches!(value.trim().to_lowercase().as_str(), "" | "0" | "false"))
+        .unwrap_or(false)
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_interactive_fails_fast() {
+        let prompter = Prompter::non_interactive();
+        assert!(!prompter.is_interactive());
+
+        let mut input = "y\n".as_bytes();
+        let error = prompter
+            .ask_from("Type WIPE to continue: ", "pass --yes to skip the confirmation", &mut input)
+            .unwrap_err();
+        assert!(matches!(error, CLIError::InputRequired(_)));
+        assert!(error.to_string().contains("'Type WIPE to continue' needs an answer; pass --yes"));
+        // Nothing was read
+        assert_eq!(input, "y\n".as_bytes());
+    }
+
+    #[test]
+    fn test_interactive_reads_answer() {
+        let prompter = Prompter { non_interactive: false };
+
+        let mut input = " yes \n".as_bytes();
+        let answer = prompter.ask_from("Confirm (y/n): ", "", &mut input).unwrap();
+        assert!(is_yes(&answer));
+        assert!(!is_yes("n"));
+
+        let mut closed = "".as_bytes();
+        assert!(matches!(prompter.ask_from("Confirm (y/n): ", "", &mut closed), Err(CLIError::Cancelled)));
+    }
+}
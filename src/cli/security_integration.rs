@@ -6,11 +6,12 @@
 // Requirements: 6.5
 
 use crate::cli::error::{CLIError, CLIResult};
+use crate::cli::prompt::Prompter;
 use crate::security::api::SecuritySystem;
 use crate::security::identity::{DeviceIdentity, PeerId};
 use crate::security::policy::{ConnectionType, SecurityPolicy};
 use crate::security::trust::ServicePermissions;
-use crate::security::trust::{PairingCode, TrustEntry, TrustLevel};
+use crate::security::trust::{PairingCode, RevocationRecord, TotpSalt, TrustEntry, TrustLevel};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
@@ -20,6 +21,8 @@ pub struct CLISecurityIntegration {
     security_system: Arc<SecuritySystem>,
     /// Current authenticated session
     current_session: Arc<RwLock<Option<CLISession>>>,
+    /// Asks the user to confirm trust and permission changes
+    prompter: Prompter,
 }
 
 /// CLI session information
@@ -43,9 +46,21 @@ impl CLISecurityIntegration {
         Self {
             security_system,
             current_session: Arc::new(RwLock::new(None)),
+            prompter: Prompter::default(),
         }
     }
 
+    /// Use a specific prompter, e.g. one that never asks
+    pub fn with_prompter(mut self, prompter: Prompter) -> Self {
+        self.prompter = prompter;
+        self
+    }
+
+    /// The security system behind the CLI
+    pub fn security_system(&self) -> &Arc<SecuritySystem> {
+        &self.security_system
+    }
+
     /// Authenticate and create a CLI session
     pub async fn authenticate(&self) -> CLIResult<CLISession> {
         // Get or create device identity
@@ -108,17 +123,11 @@ impl CLISecurityIntegration {
     /// Add a trusted peer with authorization prompt
     pub async fn add_trusted_peer(&self, peer_id: PeerId, nickname: String) -> CLIResult<()> {
         // Prompt user for confirmation
-        println!("Add peer '{}' ({}) to trusted list?", nickname, peer_id);
-        println!("This will allow the peer to connect and interact with your device.");
-        print!("Confirm (y/n): ");
-        
-        use std::io::{self, Write};
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        
-        if input.trim().to_lowercase() != "y" {
+        if self.prompter.is_interactive() {
+            println!("This will allow the peer to connect and interact with your device.");
+        }
+        let question = format!("Add peer '{}' ({}) to trusted list?", nickname, peer_id);
+        if !self.prompter.confirm(&question, "pair with a pairing code instead")? {
             return Err(CLIError::security("User declined to add trusted peer".to_string()));
         }
 
@@ -132,31 +141,31 @@ impl CLISecurityIntegration {
         Ok(())
     }
 
-    /// Remove a trusted peer with authorization prompt
-    pub async fn remove_trusted_peer(&self, peer_id: &PeerId) -> CLIResult<()> {
+    /// Revoke a trusted peer with authorization prompt
+    ///
+    /// The signed revocation spreads to the user's other devices the next
+    /// time they connect to this one.
+    pub async fn revoke_peer(&self, peer_id: &PeerId, reason: Option<String>) -> CLIResult<RevocationRecord> {
         // Prompt user for confirmation
-        println!("Remove peer '{}' from trusted list?", peer_id);
-        println!("This will prevent the peer from connecting to your device.");
-        print!("Confirm (y/n): ");
-        
-        use std::io::{self, Write};
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        
-        if input.trim().to_lowercase() != "y" {
-            return Err(CLIError::security("User declined to remove trusted peer".to_string()));
+        if self.prompter.is_interactive() {
+            println!("This will prevent the peer from connecting to any of your devices.");
+        }
+        let question = format!("Revoke peer '{}'?", peer_id);
+        if !self.prompter.confirm(&question, "run the command interactively to revoke a peer")? {
+            return Err(CLIError::security("User declined to revoke peer".to_string()));
         }
 
-        // Remove from trust list
         self.security_system
-            .remove_trusted_peer(peer_id)
+            .revoke_peer(peer_id, reason)
             .await
-            .map_err(|e| CLIError::security(format!("Failed to remove trusted peer: {}", e)))?;
+            .map_err(|e| CLIError::security(format!("Failed to revoke peer: {}", e)))
+    }
 
-        println!("Peer removed from trusted list successfully.");
-        Ok(())
+    /// Keep trusting a peer here even if another device revokes it
+    pub fn set_revocation_override(&self, peer_id: &PeerId, keep_trusted: bool) -> CLIResult<()> {
+        self.security_system
+            .set_revocation_override(peer_id, keep_trusted)
+            .map_err(|e| CLIError::security(format!("Failed to update revocation override: {}", e)))
     }
 
     /// Get all trusted peers
@@ -175,6 +184,49 @@ impl CLISecurityIntegration {
             .map_err(|e| CLIError::security(format!("Failed to generate pairing code: {}", e)))
     }
 
+    /// Start pairing with a secret typed on both devices, for peers without a
+    /// screen or camera
+    ///
+    /// Returns the salt the other device joins with and the current code.
+    pub fn start_totp_pairing(&self, secret: &str) -> CLIResult<(TotpSalt, String)> {
+        self.security_system
+            .start_totp_pairing(secret)
+            .and_then(|salt| Ok((salt, self.security_system.totp_pairing_code()?)))
+            .map_err(|e| CLIError::security(format!("Failed to start pairing: {}", e)))
+    }
+
+    /// Join pairing another device started, with the salt it showed
+    ///
+    /// Returns the current code to enter on the other device.
+    pub fn join_totp_pairing(&self, secret: &str, salt: &str) -> CLIResult<String> {
+        TotpSalt::from_hex(salt)
+            .and_then(|salt| self.security_system.join_totp_pairing(secret, &salt))
+            .and_then(|()| self.security_system.totp_pairing_code())
+            .map_err(|e| CLIError::security(format!("Failed to join pairing: {}", e)))
+    }
+
+    /// Verify a rolling pairing code and add peer to trust list
+    pub async fn verify_totp_and_trust_peer(
+        &self,
+        code: &str,
+        peer_id: &PeerId,
+        nickname: String,
+    ) -> CLIResult<bool> {
+        let verified = self
+            .security_system
+            .verify_totp_and_trust_peer(code, peer_id, nickname)
+            .await
+            .map_err(|e| CLIError::security(format!("Failed to verify pairing code: {}", e)))?;
+
+        if verified {
+            println!("Pairing successful! Peer added to trusted list.");
+        } else {
+            println!("Pairing failed. The code did not match; check both devices use the same secret.");
+        }
+
+        Ok(verified)
+    }
+
     /// Verify a pairing code and add peer to trust list
     pub async fn verify_and_trust_peer(
         &self,
@@ -204,17 +256,11 @@ impl CLISecurityIntegration {
         permissions: ServicePermissions,
     ) -> CLIResult<()> {
         // Prompt user for confirmation
-        println!("Update permissions for peer '{}'?", peer_id);
-        println!("New permissions: {:?}", permissions);
-        print!("Confirm (y/n): ");
-        
-        use std::io::{self, Write};
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        
-        if input.trim().to_lowercase() != "y" {
+        if self.prompter.is_interactive() {
+            println!("New permissions: {:?}", permissions);
+        }
+        let question = format!("Update permissions for peer '{}'?", peer_id);
+        if !self.prompter.confirm(&question, "run the command interactively to change permissions")? {
             return Err(CLIError::security("User declined to update permissions".to_string()));
         }
 
@@ -247,18 +293,15 @@ impl CLISecurityIntegration {
             println!("Warning: Peer '{}' is not trusted!", peer_id);
         }
 
+        // Nobody can answer, so fall back to the safe default
+        if !self.prompter.is_interactive() {
+            log::info!("Denied '{}' for peer {}: running non-interactively", operation, peer_id);
+            return Ok(false);
+        }
+
         // Prompt user for authorization
-        println!("Authorize operation: {}", operation);
         println!("Peer: {}", peer_id);
-        print!("Allow (y/n): ");
-        
-        use std::io::{self, Write};
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        
-        Ok(input.trim().to_lowercase() == "y")
+        self.prompter.confirm(&format!("Authorize operation: {}?", operation), "")
     }
 
     /// Get security policy
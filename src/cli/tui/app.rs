@@ -1,13 +1,26 @@
 // TUI Application and Manager
 
+use crate::cli::config::{load_or_create_config, save_config};
 use crate::cli::error::{CLIError, CLIResult};
-use crate::cli::types::{PeerInfo, OperationStatus, TUIState, ViewType, PeerId};
-use crate::cli::tui::events::{EventHandler, EventLoop};
+use crate::cli::integration::CLISystemIntegration;
+use crate::cli::types::{
+    ColorMode, ConnectionStatus, OperationState, PeerInfo, OperationStatus, TUIState, TrustStatus, TuiSettings, ViewType,
+    PeerId,
+};
+use crate::cli::tui::events::{
+    Action, CommandPalette, EventHandler, EventLoop, HelpCommand, HelpOverlay, KeyBindings, PaletteCommand,
+};
 use crate::cli::tui::widgets::{PeerListWidget, FileBrowserWidget, ProgressWidget};
 use crate::cli::tui::peer_view::PeerView;
 use crate::cli::tui::file_browser_view::FileBrowserView;
 use crate::cli::tui::transfer_view::TransferView;
-use crate::cli::tui::operation_monitor::OperationMonitor;
+use crate::cli::tui::operation_monitor::{LogLevel, OperationMonitor};
+use crate::cli::tui::streaming_view::{StreamingView, StreamStatsSource};
+use crate::cli::tui::theme::Theme;
+use crate::error_aggregator::ErrorAggregator;
+use crate::lockdown::{Lockdown, LockdownTrigger};
+use crate::remote_fs::RemoteFileSystem;
+use crate::transport::{PeerAddress, TransportCapabilities};
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
@@ -18,11 +31,13 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Tabs},
+    widgets::{Block, Borders, Paragraph, Tabs, Wrap},
     Frame, Terminal,
 };
 use std::io;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 /// TUI Application
@@ -34,8 +49,24 @@ pub struct TUIApp {
     file_browser_view: FileBrowserView,
     transfer_view: TransferView,
     operation_monitor: OperationMonitor,
+    streaming_view: StreamingView,
+    keybindings: KeyBindings,
+    /// Set when the user rebinds a key, so the bindings get saved
+    keybindings_changed: bool,
+    palette: Option<CommandPalette>,
+    help: Option<HelpOverlay>,
+    theme: Theme,
+    /// Plain one-item-per-line output for screen readers
+    linear: bool,
+    frame_interval: Duration,
+    stats_refresh_interval: Duration,
+    /// Shown in the footer until the next key press
+    notice: Option<String>,
 }
 
+/// Redraw intervals the refresh rate toggle steps through, in milliseconds
+const FRAME_INTERVAL_STEPS_MS: [u64; 4] = [50, 100, 250, 500];
+
 impl TUIApp {
     /// Create a new TUI application
     pub fn new() -> Self {
@@ -55,81 +86,220 @@ impl TUIApp {
             file_browser_view: FileBrowserView::new(initial_path),
             transfer_view: TransferView::new(Vec::new()),
             operation_monitor: OperationMonitor::new(),
+            streaming_view: StreamingView::new(),
+            keybindings: KeyBindings::defaults(),
+            keybindings_changed: false,
+            palette: None,
+            help: None,
+            theme: Theme::default(),
+            linear: false,
+            frame_interval: Duration::from_millis(50),
+            stats_refresh_interval: Duration::from_secs(1),
+            notice: None,
         }
     }
 
+    /// Use key bindings loaded from the config
+    pub fn with_keybindings(mut self, keybindings: KeyBindings) -> Self {
+        self.keybindings = keybindings;
+        self
+    }
+
+    /// Key bindings in effect
+    pub fn keybindings(&self) -> &KeyBindings {
+        &self.keybindings
+    }
+
+    /// Whether the user rebound keys since the bindings were loaded
+    pub fn keybindings_changed(&self) -> bool {
+        self.keybindings_changed
+    }
+
+    /// Use a color theme
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Start in linear mode
+    pub fn with_linear_mode(mut self, linear: bool) -> Self {
+        self.linear = linear;
+        self
+    }
+
+    /// Redraw and statistics refresh intervals
+    pub fn with_refresh_intervals(mut self, frame: Duration, stats: Duration) -> Self {
+        self.frame_interval = frame;
+        self.stats_refresh_interval = stats;
+        self
+    }
+
+    /// Color theme in effect
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Whether linear mode is on
+    pub fn linear_mode(&self) -> bool {
+        self.linear
+    }
+
+    /// Time between screen redraws
+    pub fn frame_interval(&self) -> Duration {
+        self.frame_interval
+    }
+
+    /// Time between streaming statistics refreshes
+    pub fn stats_refresh_interval(&self) -> Duration {
+        self.stats_refresh_interval
+    }
+
     /// Handle keyboard input
     pub fn handle_key(&mut self, key: crossterm::event::KeyEvent) -> CLIResult<()> {
-        use crossterm::event::{KeyCode, KeyModifiers};
+        use crossterm::event::KeyCode;
 
-        match key.code {
-            KeyCode::Char('q') | KeyCode::Esc => {
-                self.running = false;
+        self.notice = None;
+
+        // Open overlays take every key
+        if let Some(palette) = self.palette.as_mut() {
+            match palette.handle_key(&key) {
+                PaletteCommand::None => {}
+                PaletteCommand::Close => self.palette = None,
+                PaletteCommand::Run(action) => {
+                    self.palette = None;
+                    self.perform(action)?;
+                }
+            }
+            return Ok(());
+        }
+        if let Some(help) = self.help.as_mut() {
+            match help.handle_key(&key) {
+                HelpCommand::None => {}
+                HelpCommand::Close => self.help = None,
+                HelpCommand::Bind(action, binding) => {
+                    self.keybindings.bind(action, binding);
+                    self.keybindings_changed = true;
+                }
+                HelpCommand::Reset(action) => {
+                    self.keybindings.reset(action);
+                    self.keybindings_changed = true;
+                }
+            }
+            return Ok(());
+        }
+
+        if let Some(action) = self.keybindings.action_for(&key) {
+            return self.perform(action);
+        }
+
+        // Unbound characters go to the view-specific actions
+        if let KeyCode::Char(c) = key.code {
+            match self.state.current_view {
+                ViewType::PeerList => {
+                    self.handle_peer_action(c)?;
+                }
+                ViewType::TransferProgress => {
+                    self.handle_operation_control(c)?;
+                }
+                _ => {}
             }
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+        }
+
+        Ok(())
+    }
+
+    /// Carry out an action chosen by key or from the palette
+    pub fn perform(&mut self, action: Action) -> CLIResult<()> {
+        match action {
+            Action::Quit => {
                 self.running = false;
             }
-            KeyCode::Tab => {
+            Action::NextView => {
                 self.next_view();
             }
-            KeyCode::BackTab => {
+            Action::PreviousView => {
                 self.previous_view();
             }
-            KeyCode::Up => {
+            Action::MoveUp => {
                 self.handle_up();
             }
-            KeyCode::Down => {
+            Action::MoveDown => {
                 self.handle_down();
             }
-            KeyCode::Enter => {
+            Action::Activate => {
                 self.handle_enter()?;
             }
-            KeyCode::Char('1') => {
+            Action::ShowPeers => {
                 self.state.current_view = ViewType::PeerList;
             }
-            KeyCode::Char('2') => {
+            Action::ShowFiles => {
                 self.state.current_view = ViewType::FileBrowser;
             }
-            KeyCode::Char('3') => {
+            Action::ShowTransfers => {
                 self.state.current_view = ViewType::TransferProgress;
             }
-            KeyCode::Char('l') => {
+            Action::ShowStreams => {
+                self.state.current_view = ViewType::StreamViewer;
+            }
+            Action::ToggleLogs => {
                 // Toggle logs in operation monitor or transfer view
                 if self.state.current_view == ViewType::TransferProgress {
                     self.operation_monitor.toggle_logs();
                 }
             }
-            KeyCode::Char(' ') => {
-                // Handle space key for file selection
+            Action::ToggleSelection => {
                 if self.state.current_view == ViewType::FileBrowser {
                     self.file_browser_view.toggle_selection();
                 }
             }
-            KeyCode::Char('h') => {
-                // Toggle hidden files in file browser
+            Action::ToggleHidden => {
                 if self.state.current_view == ViewType::FileBrowser {
                     self.file_browser_view.toggle_hidden();
                 }
             }
-            KeyCode::Char('s') => {
-                // Send selected files
+            Action::SendFiles => {
                 if self.state.current_view == ViewType::FileBrowser {
                     self.handle_send_files()?;
                 }
             }
-            KeyCode::Char(c) => {
-                // Handle view-specific actions
-                match self.state.current_view {
-                    ViewType::PeerList => {
-                        self.handle_peer_action(c)?;
-                    }
-                    ViewType::TransferProgress => {
-                        self.handle_operation_control(c)?;
-                    }
-                    _ => {}
+            Action::ShowLocalFiles => {
+                if self.state.current_view == ViewType::FileBrowser {
+                    self.file_browser_view.show_local();
                 }
             }
-            _ => {}
+            Action::CommandPalette => {
+                self.palette = Some(CommandPalette::new());
+            }
+            Action::Help => {
+                self.help = Some(HelpOverlay::new());
+            }
+            Action::CycleTheme => {
+                let before = self.theme.kind();
+                self.theme.cycle();
+                self.notice = Some(if self.theme.kind() == before {
+                    "Colors are turned off by the color mode or NO_COLOR".to_string()
+                } else {
+                    format!("Theme: {}", self.theme.label())
+                });
+            }
+            Action::ToggleLinearMode => {
+                self.linear = !self.linear;
+                self.notice = Some(format!("Linear mode {}", if self.linear { "on" } else { "off" }));
+            }
+            Action::CycleRefreshRate => {
+                let current = self.frame_interval.as_millis() as u64;
+                let next = FRAME_INTERVAL_STEPS_MS
+                    .iter()
+                    .copied()
+                    .find(|&ms| ms > current)
+                    .unwrap_or(FRAME_INTERVAL_STEPS_MS[0]);
+                self.frame_interval = Duration::from_millis(next);
+                self.notice = Some(format!("Redrawing every {} ms", next));
+            }
+            Action::ToggleLockdown => {
+                Lockdown::global().toggle(LockdownTrigger::User);
+                self.notice = Some(Lockdown::global().state().describe());
+            }
         }
 
         Ok(())
@@ -139,7 +309,7 @@ impl TUIApp {
     fn handle_peer_action(&mut self, key: char) -> CLIResult<()> {
         use crate::cli::tui::peer_view::PeerAction;
 
-        if let Some(peer) = self.peer_view.get_selected() {
+        if let Some(peer) = self.peer_view.get_selected().cloned() {
             if let Some(action) = PeerAction::from_char(key, peer.connection_status) {
                 // Store the action for processing
                 // In a real implementation, this would trigger actual peer operations
@@ -165,6 +335,10 @@ impl TUIApp {
                     PeerAction::Cancel => {
                         // TODO: Cancel connection attempt
                     }
+                    PeerAction::BrowseFiles => {
+                        self.file_browser_view.request_remote(peer.id.to_string(), String::new());
+                        self.state.current_view = ViewType::FileBrowser;
+                    }
                 }
             }
         }
@@ -213,8 +387,8 @@ impl TUIApp {
         self.state.current_view = match self.state.current_view {
             ViewType::PeerList => ViewType::FileBrowser,
             ViewType::FileBrowser => ViewType::TransferProgress,
-            ViewType::TransferProgress => ViewType::PeerList,
-            ViewType::StreamViewer => ViewType::CommandTerminal,
+            ViewType::TransferProgress => ViewType::StreamViewer,
+            ViewType::StreamViewer => ViewType::PeerList,
             ViewType::CommandTerminal => ViewType::Settings,
             ViewType::Settings => ViewType::PeerList,
         };
@@ -223,10 +397,10 @@ impl TUIApp {
     /// Navigate to previous view
     fn previous_view(&mut self) {
         self.state.current_view = match self.state.current_view {
-            ViewType::PeerList => ViewType::TransferProgress,
+            ViewType::PeerList => ViewType::StreamViewer,
             ViewType::FileBrowser => ViewType::PeerList,
             ViewType::TransferProgress => ViewType::FileBrowser,
-            ViewType::StreamViewer => ViewType::Settings,
+            ViewType::StreamViewer => ViewType::TransferProgress,
             ViewType::CommandTerminal => ViewType::StreamViewer,
             ViewType::Settings => ViewType::CommandTerminal,
         };
@@ -247,6 +421,9 @@ impl TUIApp {
                     self.operation_monitor.select_previous();
                 }
             }
+            ViewType::StreamViewer => {
+                self.streaming_view.select_previous();
+            }
             _ => {}
         }
     }
@@ -266,6 +443,9 @@ impl TUIApp {
                     self.operation_monitor.select_next();
                 }
             }
+            ViewType::StreamViewer => {
+                self.streaming_view.select_next();
+            }
             _ => {}
         }
     }
@@ -290,6 +470,23 @@ impl TUIApp {
     /// Render the TUI
     pub fn render(&self, frame: &mut Frame) {
         let area = frame.size();
+        if self.linear {
+            self.render_linear(frame, area);
+        } else {
+            self.render_views(frame, area);
+        }
+
+        if let Some(palette) = &self.palette {
+            palette.render(frame, area, &self.keybindings);
+        } else if let Some(help) = &self.help {
+            help.render(frame, area, &self.keybindings);
+        }
+
+        self.theme.apply(frame.buffer_mut());
+    }
+
+    /// Render the header, the current view and the footer
+    fn render_views(&self, frame: &mut Frame, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -328,18 +525,161 @@ impl TUIApp {
         self.render_footer(frame, chunks[2]);
     }
 
+    /// Render the current view as plain lines, without borders or tabs
+    fn render_linear(&self, frame: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = self.linear_lines().into_iter().map(Line::from).collect();
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, area);
+    }
+
+    /// Text of the current view, one item per line
+    ///
+    /// The selected item is marked with "> " and a trailing "(selected)", so
+    /// it can be found by screen readers as well as by eye.
+    pub fn linear_lines(&self) -> Vec<String> {
+        let lockdown = Lockdown::global().state();
+        let mut lines = vec![match &self.notice {
+            Some(notice) => notice.clone(),
+            None => format!(
+                "Kizuna, {} view. Key bindings: {}.",
+                self.view_name(),
+                self.keybindings.describe(Action::Help)
+            ),
+        }];
+        if lockdown.engaged {
+            lines.push(lockdown.describe());
+        }
+
+        let mut items: Vec<String> = Vec::new();
+        let selected;
+        match self.state.current_view {
+            ViewType::PeerList => {
+                let peers = &self.peer_view.peers;
+                lines.push(format!("{} peer(s)", peers.len()));
+                items.extend(peers.iter().map(|peer| {
+                    let connection = match peer.connection_status {
+                        ConnectionStatus::Connected => "connected",
+                        ConnectionStatus::Disconnected => "disconnected",
+                        ConnectionStatus::Connecting => "connecting",
+                        ConnectionStatus::Error => "connection error",
+                    };
+                    let trust = match peer.trust_status {
+                        TrustStatus::Trusted => "trusted",
+                        TrustStatus::Untrusted => "untrusted",
+                        TrustStatus::Blocked => "blocked",
+                    };
+                    format!("{}, {}, {}, {}", peer.name, peer.device_type, connection, trust)
+                }));
+                selected = self.peer_view.selected_index;
+            }
+            ViewType::FileBrowser => {
+                let view = &self.file_browser_view;
+                lines.push(match &view.remote {
+                    Some(remote) => match &remote.error {
+                        Some(error) => format!("Shares of {}: {}", remote.peer_id, error),
+                        None => format!("Shares of {}, folder /{}", remote.peer_id, remote.path),
+                    },
+                    None => format!("Folder {}", view.current_path.display()),
+                });
+                items.extend(view.entries.iter().map(|entry| {
+                    let marked = if view.selected_files.contains(&entry.path) { ", marked" } else { "" };
+                    if entry.is_directory {
+                        format!("{}, folder{}", entry.name, marked)
+                    } else {
+                        format!("{}, {} bytes{}", entry.name, entry.size.unwrap_or(0), marked)
+                    }
+                }));
+                selected = view.selected_index;
+            }
+            ViewType::TransferProgress => {
+                let monitor = &self.operation_monitor;
+                lines.push(format!("{} operation(s)", monitor.operations.len()));
+                items.extend(monitor.operations.iter().map(|operation| {
+                    let state = match &operation.status {
+                        OperationState::Starting => "starting".to_string(),
+                        OperationState::InProgress => "in progress".to_string(),
+                        OperationState::Completed => "completed".to_string(),
+                        OperationState::Failed(reason) => format!("failed: {}", reason),
+                        OperationState::Cancelled => "cancelled".to_string(),
+                    };
+                    let percent = operation
+                        .progress
+                        .as_ref()
+                        .and_then(|p| p.total.filter(|&t| t > 0).map(|t| p.current * 100 / t))
+                        .map(|percent| format!(", {}%", percent))
+                        .unwrap_or_default();
+                    format!("{:?} with {}, {}{}", operation.operation_type, operation.peer_id, state, percent)
+                }));
+                selected = monitor.selected_index;
+            }
+            ViewType::StreamViewer => {
+                let sessions = &self.streaming_view.sessions;
+                lines.push(format!("{} stream(s)", sessions.len()));
+                items.extend(sessions.iter().map(|session| {
+                    format!(
+                        "Stream {}, {}x{} at {} fps, {} kbps, {} viewer(s)",
+                        &session.session_id.to_string()[..8],
+                        session.resolution.0,
+                        session.resolution.1,
+                        session.framerate,
+                        session.bitrate / 1000,
+                        session.viewers.len()
+                    )
+                }));
+                selected = self.streaming_view.selected_index;
+            }
+            ViewType::CommandTerminal | ViewType::Settings => {
+                lines.push("Not available yet".to_string());
+                selected = 0;
+            }
+        }
+
+        for (index, item) in items.into_iter().enumerate() {
+            lines.push(if index == selected {
+                format!("> {} (selected)", item)
+            } else {
+                format!("  {}", item)
+            });
+        }
+        lines
+    }
+
+    /// Name of the current view
+    fn view_name(&self) -> &'static str {
+        match self.state.current_view {
+            ViewType::PeerList => "peers",
+            ViewType::FileBrowser => "files",
+            ViewType::TransferProgress => "transfers",
+            ViewType::StreamViewer => "streams",
+            ViewType::CommandTerminal => "command terminal",
+            ViewType::Settings => "settings",
+        }
+    }
+
     /// Render header with tabs
     fn render_header(&self, frame: &mut Frame, area: Rect) {
-        let titles = vec!["Peers (1)", "Files (2)", "Transfers (3)"];
+        let titles = vec!["Peers (1)", "Files (2)", "Transfers (3)", "Streams (4)"];
         let index = match self.state.current_view {
             ViewType::PeerList => 0,
             ViewType::FileBrowser => 1,
             ViewType::TransferProgress => 2,
+            ViewType::StreamViewer => 3,
             _ => 0,
         };
 
+        // Keep the lockdown in sight whichever view is open
+        let lockdown = Lockdown::global().state();
+        let title = if lockdown.engaged {
+            Span::styled(
+                format!("Kizuna TUI - {}", lockdown.describe()),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::raw("Kizuna TUI")
+        };
+
         let tabs = Tabs::new(titles)
-            .block(Block::default().borders(Borders::ALL).title("Kizuna TUI"))
+            .block(Block::default().borders(Borders::ALL).title(title))
             .select(index)
             .style(Style::default().fg(Color::White))
             .highlight_style(
@@ -369,13 +709,7 @@ impl TUIApp {
 
     /// Render stream viewer
     fn render_stream_viewer(&self, frame: &mut Frame, area: Rect) {
-        let block = Block::default()
-            .borders(Borders::ALL)
-            .title("Stream Viewer");
-        let paragraph = Paragraph::new("Stream viewer not yet implemented")
-            .block(block)
-            .style(Style::default().fg(Color::Gray));
-        frame.render_widget(paragraph, area);
+        self.streaming_view.render(frame, area);
     }
 
     /// Render command terminal
@@ -400,16 +734,31 @@ impl TUIApp {
         frame.render_widget(paragraph, area);
     }
 
-    /// Render footer with help text
+    /// Render footer with help text, or the latest notice
     fn render_footer(&self, frame: &mut Frame, area: Rect) {
+        if let Some(notice) = &self.notice {
+            let paragraph = Paragraph::new(notice.as_str())
+                .block(Block::default().borders(Borders::ALL))
+                .style(Style::default().fg(Color::White));
+            frame.render_widget(paragraph, area);
+            return;
+        }
+
+        let key = |action: Action| {
+            let keys = self.keybindings.keys_for(action);
+            let text = keys.first().map(|k| k.to_string()).unwrap_or_else(|| "unbound".to_string());
+            Span::styled(text, Style::default().fg(Color::Yellow))
+        };
         let help_text = vec![
             Span::raw("Press "),
-            Span::styled("q", Style::default().fg(Color::Yellow)),
+            key(Action::Quit),
             Span::raw(" to quit, "),
-            Span::styled("Tab", Style::default().fg(Color::Yellow)),
+            key(Action::NextView),
             Span::raw(" to switch views, "),
-            Span::styled("1-3", Style::default().fg(Color::Yellow)),
-            Span::raw(" for quick navigation"),
+            key(Action::CommandPalette),
+            Span::raw(" for the command palette, "),
+            key(Action::Help),
+            Span::raw(" for key bindings"),
         ];
 
         let paragraph = Paragraph::new(Line::from(help_text))
@@ -432,6 +781,16 @@ impl TUIApp {
         self.operation_monitor.update_operations(operations);
     }
 
+    /// Update streaming session statistics
+    pub fn update_streams(&mut self, sessions: Vec<crate::cli::tui::streaming_view::StreamSessionEntry>) {
+        self.streaming_view.update_sessions(sessions);
+    }
+
+    /// Get streaming view
+    pub fn streaming_view(&self) -> &StreamingView {
+        &self.streaming_view
+    }
+
     /// Get file browser view
     pub fn file_browser_view(&self) -> &FileBrowserView {
         &self.file_browser_view
@@ -474,10 +833,17 @@ impl Default for TUIApp {
     }
 }
 
+/// How long the file browser waits for a peer's directory listing
+const REMOTE_LIST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 /// TUI Manager handles terminal setup and event loop
 pub struct TUIManager {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     app: TUIApp,
+    remote_fs: Option<RemoteFileSystem>,
+    stream_stats: Option<Arc<dyn StreamStatsSource>>,
+    errors: Option<Arc<ErrorAggregator>>,
+    last_stream_refresh: Option<Instant>,
 }
 
 impl TUIManager {
@@ -495,9 +861,53 @@ impl TUIManager {
         Ok(Self {
             terminal,
             app: TUIApp::new(),
+            remote_fs: None,
+            stream_stats: None,
+            errors: None,
+            last_stream_refresh: None,
         })
     }
 
+    /// Let the file browser show peers' shared folders
+    pub fn with_remote_browser(mut self, remote_fs: RemoteFileSystem) -> Self {
+        self.remote_fs = Some(remote_fs);
+        self
+    }
+
+    /// Apply the TUI preferences from the CLI config
+    ///
+    /// Key bindings and colors the config gets wrong are skipped with a
+    /// warning. `ColorMode::Never` turns colors off whatever the theme.
+    pub fn with_settings(mut self, settings: &TuiSettings, color_mode: ColorMode) -> Self {
+        let (keybindings, warnings) = KeyBindings::from_settings(settings);
+        for warning in warnings {
+            log::warn!("Ignoring TUI key binding: {}", warning);
+        }
+        let (theme, warnings) = Theme::from_settings(settings, color_mode);
+        for warning in warnings {
+            log::warn!("Ignoring TUI color: {}", warning);
+        }
+
+        self.app.keybindings = keybindings;
+        self.app.theme = theme;
+        self.app.linear = settings.linear;
+        self.app.frame_interval = Duration::from_millis(settings.frame_interval_ms.max(1));
+        self.app.stats_refresh_interval = Duration::from_millis(settings.stats_refresh_ms.max(1));
+        self
+    }
+
+    /// Feed the streaming view from live session statistics
+    pub fn with_stream_stats(mut self, source: Arc<dyn StreamStatsSource>) -> Self {
+        self.stream_stats = Some(source);
+        self
+    }
+
+    /// Show summaries of suppressed errors in the log panel
+    pub fn with_error_summaries(mut self, errors: Arc<ErrorAggregator>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
     /// Run the TUI application
     pub async fn run(&mut self) -> CLIResult<()> {
         let (tx, mut rx) = mpsc::channel(100);
@@ -528,17 +938,90 @@ impl TUIManager {
                 }
             }
 
-            // Small delay to prevent busy loop
-            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+            self.load_remote_listing().await;
+            self.refresh_stream_stats().await;
+            self.show_error_summary();
+
+            // Wait for the next frame
+            tokio::time::sleep(self.app.frame_interval()).await;
         }
 
         // Cleanup
         event_handle.abort();
         self.cleanup()?;
 
+        if self.app.keybindings_changed() {
+            self.save_keybindings().await?;
+        }
+
         Ok(())
     }
 
+    /// Write keys rebound from the help overlay back to the CLI config
+    async fn save_keybindings(&self) -> CLIResult<()> {
+        let storage = CLISystemIntegration::get_storage()?;
+        let mut config = load_or_create_config(storage.clone()).await?;
+        self.app.keybindings().to_settings(&mut config.tui);
+        save_config(&config, storage).await
+    }
+
+    /// Fetch the remote directory the file browser asked for, if any
+    async fn load_remote_listing(&mut self) {
+        let Some(request) = self.app.file_browser_view_mut().take_remote_request() else {
+            return;
+        };
+
+        let result = match &self.remote_fs {
+            Some(remote_fs) => {
+                let peer = PeerAddress::new(
+                    request.peer_id.clone(),
+                    vec![],
+                    vec![],
+                    TransportCapabilities::default(),
+                );
+                match tokio::time::timeout(REMOTE_LIST_TIMEOUT, remote_fs.list(&peer, &request.path)).await {
+                    Ok(result) => result.map_err(|e| e.to_string()),
+                    Err(_) => Err("Timed out waiting for the peer".to_string()),
+                }
+            }
+            None => Err("Remote browsing is not available".to_string()),
+        };
+
+        let view = self.app.file_browser_view_mut();
+        match result {
+            Ok(listing) => view.show_remote(request.peer_id, listing),
+            Err(error) => view.show_remote_error(request.peer_id, request.path, error),
+        }
+    }
+
+    /// Pull fresh streaming statistics while the streaming view is open
+    async fn refresh_stream_stats(&mut self) {
+        let Some(source) = &self.stream_stats else {
+            return;
+        };
+        if self.app.state.current_view != ViewType::StreamViewer {
+            return;
+        }
+        if self
+            .last_stream_refresh
+            .is_some_and(|last| last.elapsed() < self.app.stats_refresh_interval())
+        {
+            return;
+        }
+
+        let sessions = source.sessions().await;
+        self.app.update_streams(sessions);
+        self.last_stream_refresh = Some(Instant::now());
+    }
+
+    /// Log a summary of suppressed errors when one is due
+    fn show_error_summary(&mut self) {
+        let Some(summary) = self.errors.as_ref().and_then(|errors| errors.summary_if_due()) else {
+            return;
+        };
+        self.app.add_log(LogLevel::Warning, uuid::Uuid::nil(), summary.to_string());
+    }
+
     /// Cleanup terminal state
     fn cleanup(&mut self) -> CLIResult<()> {
         disable_raw_mode().map_err(|e| CLIError::TUIError(e.to_string()))?;
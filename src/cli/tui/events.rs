@@ -1,9 +1,27 @@
 // Event handling for TUI
+//
+// Besides polling the terminal, this maps keys to actions. Bindings have
+// defaults that the user can override per action in the CLI config, the
+// command palette finds any action by fuzzy search, and the help overlay
+// lists the bindings in effect.
 
-use crossterm::event::{self, Event, KeyEvent, MouseEvent};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Row, Table},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
+use crate::cli::types::TuiSettings;
+
 /// Event handler for TUI input
 pub struct EventHandler {
     // Future: Add event filtering and processing
@@ -59,3 +77,731 @@ impl EventLoop {
         }
     }
 }
+
+/// Something the user can ask the TUI to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    NextView,
+    PreviousView,
+    ShowPeers,
+    ShowFiles,
+    ShowTransfers,
+    ShowStreams,
+    MoveUp,
+    MoveDown,
+    Activate,
+    ToggleLogs,
+    ToggleSelection,
+    ToggleHidden,
+    SendFiles,
+    ShowLocalFiles,
+    CommandPalette,
+    Help,
+    CycleTheme,
+    ToggleLinearMode,
+    CycleRefreshRate,
+    ToggleLockdown,
+}
+
+impl Action {
+    /// Every action, in the order the palette and help list them
+    pub const ALL: [Action; 21] = [
+        Action::ShowPeers,
+        Action::ShowFiles,
+        Action::ShowTransfers,
+        Action::ShowStreams,
+        Action::NextView,
+        Action::PreviousView,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Activate,
+        Action::ToggleLogs,
+        Action::ToggleSelection,
+        Action::ToggleHidden,
+        Action::SendFiles,
+        Action::ShowLocalFiles,
+        Action::CycleTheme,
+        Action::ToggleLinearMode,
+        Action::CycleRefreshRate,
+        Action::ToggleLockdown,
+        Action::CommandPalette,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    /// Name used in the config file, e.g. "next_view"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::NextView => "next_view",
+            Action::PreviousView => "previous_view",
+            Action::ShowPeers => "show_peers",
+            Action::ShowFiles => "show_files",
+            Action::ShowTransfers => "show_transfers",
+            Action::ShowStreams => "show_streams",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::Activate => "activate",
+            Action::ToggleLogs => "toggle_logs",
+            Action::ToggleSelection => "toggle_selection",
+            Action::ToggleHidden => "toggle_hidden",
+            Action::SendFiles => "send_files",
+            Action::ShowLocalFiles => "show_local_files",
+            Action::CommandPalette => "command_palette",
+            Action::Help => "help",
+            Action::CycleTheme => "cycle_theme",
+            Action::ToggleLinearMode => "toggle_linear_mode",
+            Action::CycleRefreshRate => "cycle_refresh_rate",
+            Action::ToggleLockdown => "toggle_lockdown",
+        }
+    }
+
+    /// Human-readable description
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit Kizuna",
+            Action::NextView => "Switch to the next view",
+            Action::PreviousView => "Switch to the previous view",
+            Action::ShowPeers => "Go to peers",
+            Action::ShowFiles => "Go to files",
+            Action::ShowTransfers => "Go to transfers",
+            Action::ShowStreams => "Go to streams",
+            Action::MoveUp => "Move selection up",
+            Action::MoveDown => "Move selection down",
+            Action::Activate => "Open or expand the selection",
+            Action::ToggleLogs => "Show or hide transfer logs",
+            Action::ToggleSelection => "Select or deselect the file",
+            Action::ToggleHidden => "Show or hide hidden files",
+            Action::SendFiles => "Send the selected files",
+            Action::ShowLocalFiles => "Browse local files",
+            Action::CommandPalette => "Open the command palette",
+            Action::Help => "Show key bindings",
+            Action::CycleTheme => "Switch color theme",
+            Action::ToggleLinearMode => "Turn screen reader (linear) mode on or off",
+            Action::CycleRefreshRate => "Change how often the screen redraws",
+            Action::ToggleLockdown => "Lock down or allow inbound remote access",
+        }
+    }
+
+    /// Keys bound to the action unless the config says otherwise
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q", "esc", "ctrl+c"],
+            Action::NextView => &["tab"],
+            Action::PreviousView => &["shift+tab"],
+            Action::ShowPeers => &["1"],
+            Action::ShowFiles => &["2"],
+            Action::ShowTransfers => &["3"],
+            Action::ShowStreams => &["4"],
+            Action::MoveUp => &["up"],
+            Action::MoveDown => &["down"],
+            Action::Activate => &["enter"],
+            Action::ToggleLogs => &["l"],
+            Action::ToggleSelection => &["space"],
+            Action::ToggleHidden => &["h"],
+            Action::SendFiles => &["s"],
+            Action::ShowLocalFiles => &["L"],
+            Action::CommandPalette => &["ctrl+p", ":"],
+            Action::Help => &["?", "f1"],
+            Action::CycleTheme => &["f2"],
+            Action::ToggleLinearMode => &["f3"],
+            Action::CycleRefreshRate => &["f4"],
+            Action::ToggleLockdown => &["ctrl+k"],
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| format!("Unknown TUI action '{}'", s))
+    }
+}
+
+/// A key together with its modifiers
+///
+/// Written in the config as e.g. `ctrl+p`, `shift+tab`, `f1` or `?`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Create a binding, normalizing how terminals report shifted keys
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        // The case of a character, and back-tab itself, already carry the shift
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self { code, modifiers }
+    }
+
+    /// Binding matching a key press
+    pub fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (prefix, key) = match s.rfind('+') {
+            // A trailing '+' is the plus key itself
+            Some(i) if i + 1 == s.len() => (&s[..i.saturating_sub(1)], "+"),
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => ("", s),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for part in prefix.split('+').filter(|p| !p.is_empty()) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+                "alt" => modifiers |= KeyModifiers::ALT,
+                "shift" => modifiers |= KeyModifiers::SHIFT,
+                other => return Err(format!("Unknown modifier '{}' in key '{}'", other, s)),
+            }
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_ascii_uppercase()),
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=24) => KeyCode::F(n),
+                    _ => return Err(format!("Unknown key '{}'", s)),
+                },
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "shift+tab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Insert => write!(f, "insert"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// Key bindings in effect
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    bindings: BTreeMap<Action, Vec<KeyBinding>>,
+}
+
+impl KeyBindings {
+    /// The built-in bindings
+    pub fn defaults() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = action
+                    .default_keys()
+                    .iter()
+                    .map(|k| k.parse().expect("default key bindings are valid"))
+                    .collect();
+                (action, keys)
+            })
+            .collect();
+        Self { bindings }
+    }
+
+    /// Defaults with the overrides from the CLI config applied
+    ///
+    /// Each configured action replaces that action's default keys. Entries
+    /// that cannot be understood are skipped and described in the returned
+    /// warnings.
+    pub fn from_settings(settings: &TuiSettings) -> (Self, Vec<String>) {
+        let mut bindings = Self::defaults();
+        let mut warnings = Vec::new();
+
+        for (name, keys) in &settings.keybindings {
+            let action = match name.parse::<Action>() {
+                Ok(action) => action,
+                Err(e) => {
+                    warnings.push(e);
+                    continue;
+                }
+            };
+            let mut parsed = Vec::new();
+            for key in keys {
+                match key.parse() {
+                    Ok(key) => parsed.push(key),
+                    Err(e) => warnings.push(format!("{} (bound to {})", e, name)),
+                }
+            }
+            bindings.set(action, parsed);
+        }
+
+        // Overrides may take a key another action still has by default
+        let mut seen: BTreeMap<String, Action> = BTreeMap::new();
+        for (action, keys) in &bindings.bindings {
+            for key in keys {
+                if let Some(other) = seen.insert(key.to_string(), *action) {
+                    warnings.push(format!(
+                        "Key '{}' is bound to both {} and {}; {} wins",
+                        key,
+                        other.name(),
+                        action.name(),
+                        other.name()
+                    ));
+                }
+            }
+        }
+
+        (bindings, warnings)
+    }
+
+    /// Config overrides that reproduce these bindings
+    pub fn to_settings(&self, settings: &mut TuiSettings) {
+        let defaults = Self::defaults();
+        settings.keybindings = self
+            .bindings
+            .iter()
+            .filter(|(action, keys)| defaults.keys_for(**action) != keys.as_slice())
+            .map(|(action, keys)| (action.name().to_string(), keys.iter().map(|k| k.to_string()).collect()))
+            .collect();
+    }
+
+    /// Action bound to a key press
+    pub fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+        let binding = KeyBinding::from_event(key);
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// Keys bound to an action
+    pub fn keys_for(&self, action: Action) -> &[KeyBinding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Replace an action's keys
+    pub fn set(&mut self, action: Action, keys: Vec<KeyBinding>) {
+        self.bindings.insert(action, keys);
+    }
+
+    /// Bind a key to an action, taking it away from any other action
+    pub fn bind(&mut self, action: Action, key: KeyBinding) {
+        for keys in self.bindings.values_mut() {
+            keys.retain(|k| *k != key);
+        }
+        self.bindings.entry(action).or_default().push(key);
+    }
+
+    /// Restore an action's default keys
+    pub fn reset(&mut self, action: Action) {
+        let defaults = Self::defaults();
+        self.set(action, defaults.keys_for(action).to_vec());
+    }
+
+    /// Keys of an action joined for display, e.g. "ctrl+p, :"
+    pub fn describe(&self, action: Action) -> String {
+        self.keys_for(action)
+            .iter()
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::defaults()
+    }
+}
+
+/// What the command palette wants done after a key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteCommand {
+    None,
+    Close,
+    Run(Action),
+}
+
+/// Fuzzy-searchable list of every action
+#[derive(Debug, Clone, Default)]
+pub struct CommandPalette {
+    pub query: String,
+    matches: Vec<Action>,
+    pub selected_index: usize,
+}
+
+impl CommandPalette {
+    /// Open with all actions listed
+    pub fn new() -> Self {
+        let mut palette = Self::default();
+        palette.refresh();
+        palette
+    }
+
+    /// Handle a key; Enter runs the selected action and Escape closes
+    pub fn handle_key(&mut self, key: &KeyEvent) -> PaletteCommand {
+        match key.code {
+            KeyCode::Esc => return PaletteCommand::Close,
+            KeyCode::Enter => {
+                return match self.matches.get(self.selected_index) {
+                    Some(action) => PaletteCommand::Run(*action),
+                    None => PaletteCommand::None,
+                };
+            }
+            KeyCode::Up => self.selected_index = self.selected_index.saturating_sub(1),
+            KeyCode::Down => {
+                if self.selected_index + 1 < self.matches.len() {
+                    self.selected_index += 1;
+                }
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refresh();
+            }
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                self.query.push(c);
+                self.refresh();
+            }
+            _ => {}
+        }
+        PaletteCommand::None
+    }
+
+    /// Actions matching the query, best first
+    pub fn matches(&self) -> &[Action] {
+        &self.matches
+    }
+
+    fn refresh(&mut self) {
+        let mut scored: Vec<(i32, Action)> = Action::ALL
+            .into_iter()
+            .filter_map(|action| {
+                let by_description = fuzzy_score(action.description(), &self.query);
+                let by_name = fuzzy_score(action.name(), &self.query);
+                by_description.max(by_name).map(|score| (score, action))
+            })
+            .collect();
+        // Stable, so equal scores keep the palette order
+        scored.sort_by(|a, b| b.0.cmp(&a.0));
+
+        self.matches = scored.into_iter().map(|(_, action)| action).collect();
+        self.selected_index = 0;
+    }
+
+    /// Render the palette over the middle of `area`
+    pub fn render(&self, frame: &mut Frame, area: Rect, bindings: &KeyBindings) {
+        let area = centered_rect(60, 60, area);
+        frame.render_widget(Clear, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+
+        let input = Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Yellow)),
+            Span::raw(self.query.as_str()),
+        ]))
+        .block(Block::default().borders(Borders::ALL).title("Command Palette"));
+        frame.render_widget(input, chunks[0]);
+
+        let items: Vec<ListItem> = self
+            .matches
+            .iter()
+            .enumerate()
+            .map(|(i, action)| {
+                let line = Line::from(vec![
+                    Span::raw(format!("{:<32}", action.description())),
+                    Span::styled(bindings.describe(*action), Style::default().fg(Color::Cyan)),
+                ]);
+                let style = if i == self.selected_index {
+                    Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(line).style(style)
+            })
+            .collect();
+
+        let list = List::new(items).block(Block::default().borders(Borders::ALL));
+        frame.render_widget(list, chunks[1]);
+    }
+}
+
+/// Overlay listing the bindings in effect, with rebinding
+#[derive(Debug, Clone, Default)]
+pub struct HelpOverlay {
+    pub selected_index: usize,
+    /// Waiting for the key to bind to the selected action
+    pub capturing: bool,
+}
+
+/// What the help overlay wants done after a key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpCommand {
+    None,
+    Close,
+    Bind(Action, KeyBinding),
+    Reset(Action),
+}
+
+impl HelpOverlay {
+    /// Open with the first action selected
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle a key
+    ///
+    /// Enter starts capturing a new key for the selected action, Delete
+    /// restores its defaults and Escape closes the overlay.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> HelpCommand {
+        let action = Action::ALL[self.selected_index];
+        if self.capturing {
+            self.capturing = false;
+            return match key.code {
+                KeyCode::Esc => HelpCommand::None,
+                _ => HelpCommand::Bind(action, KeyBinding::from_event(key)),
+            };
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => HelpCommand::Close,
+            KeyCode::Up => {
+                self.selected_index = self.selected_index.saturating_sub(1);
+                HelpCommand::None
+            }
+            KeyCode::Down => {
+                if self.selected_index + 1 < Action::ALL.len() {
+                    self.selected_index += 1;
+                }
+                HelpCommand::None
+            }
+            KeyCode::Enter => {
+                self.capturing = true;
+                HelpCommand::None
+            }
+            KeyCode::Delete | KeyCode::Backspace => HelpCommand::Reset(action),
+            _ => HelpCommand::None,
+        }
+    }
+
+    /// Render the overlay over the middle of `area`
+    pub fn render(&self, frame: &mut Frame, area: Rect, bindings: &KeyBindings) {
+        let area = centered_rect(70, 80, area);
+        frame.render_widget(Clear, area);
+
+        let rows: Vec<Row> = Action::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, action)| {
+                let keys = if self.capturing && i == self.selected_index {
+                    "press a key…".to_string()
+                } else {
+                    bindings.describe(*action)
+                };
+                let style = if i == self.selected_index {
+                    Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Row::new(vec![action.description().to_string(), keys]).style(style)
+            })
+            .collect();
+
+        let table = Table::new(rows, [Constraint::Percentage(60), Constraint::Percentage(40)])
+            .header(
+                Row::new(vec!["Action", "Keys"])
+                    .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            )
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Key Bindings (Enter: rebind, Del: reset, Esc: close)"),
+            );
+
+        frame.render_widget(table, area);
+    }
+}
+
+/// Score how well `query` fuzzily matches `target`; `None` if it does not
+///
+/// Every query character must appear in order. Matches at the start of a
+/// word and runs of consecutive characters score higher.
+fn fuzzy_score(target: &str, query: &str) -> Option<i32> {
+    let target: Vec<char> = target.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (position..target.len()).find(|&i| target[i] == q)?;
+        score += 1;
+        if found == 0 || !target[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 4;
+        }
+        score -= (found - position) as i32;
+        previous = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Rectangle of the given percentage size centered in `area`
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_key_binding_parse_and_display() {
+        for text in ["ctrl+p", "shift+tab", "f1", "?", "space", "L", "alt+enter"] {
+            let binding: KeyBinding = text.parse().unwrap();
+            assert_eq!(binding.to_string(), text);
+        }
+        assert_eq!("shift+l".parse::<KeyBinding>().unwrap().to_string(), "L");
+        assert!("hyper+x".parse::<KeyBinding>().is_err());
+        assert!("f99".parse::<KeyBinding>().is_err());
+
+        let bindings = KeyBindings::defaults();
+        assert_eq!(bindings.action_for(&key(KeyCode::Char('p'), KeyModifiers::CONTROL)), Some(Action::CommandPalette));
+        // Terminals report shifted characters with the shift modifier set
+        assert_eq!(bindings.action_for(&key(KeyCode::Char('L'), KeyModifiers::SHIFT)), Some(Action::ShowLocalFiles));
+        assert_eq!(bindings.action_for(&key(KeyCode::BackTab, KeyModifiers::SHIFT)), Some(Action::PreviousView));
+    }
+
+    #[test]
+    fn test_bindings_from_settings_round_trip() {
+        let mut settings = TuiSettings::default();
+        settings.keybindings.insert("quit".to_string(), vec!["ctrl+q".to_string()]);
+        settings.keybindings.insert("move_down".to_string(), vec!["down".to_string(), "j".to_string()]);
+        settings.keybindings.insert("fly".to_string(), vec!["x".to_string()]);
+
+        let (mut bindings, warnings) = KeyBindings::from_settings(&settings);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(bindings.action_for(&key(KeyCode::Char('q'), KeyModifiers::NONE)), None);
+        assert_eq!(bindings.action_for(&key(KeyCode::Char('j'), KeyModifiers::NONE)), Some(Action::MoveDown));
+
+        // Binding a taken key moves it
+        bindings.bind(Action::MoveUp, "j".parse().unwrap());
+        assert_eq!(bindings.describe(Action::MoveDown), "down");
+        bindings.reset(Action::MoveDown);
+
+        let mut saved = TuiSettings::default();
+        bindings.to_settings(&mut saved);
+        assert_eq!(saved.keybindings.keys().collect::<Vec<_>>(), vec!["move_up", "quit"]);
+        assert_eq!(KeyBindings::from_settings(&saved).0, bindings);
+    }
+
+    #[test]
+    fn test_palette_fuzzy_search() {
+        let mut palette = CommandPalette::new();
+        assert_eq!(palette.matches().len(), Action::ALL.len());
+
+        fn type_text(palette: &mut CommandPalette, text: &str) {
+            for c in text.chars() {
+                palette.handle_key(&key(KeyCode::Char(c), KeyModifiers::NONE));
+            }
+        }
+
+        type_text(&mut palette, "tran");
+        assert_eq!(palette.matches()[0], Action::ShowTransfers);
+
+        for _ in 0..4 {
+            palette.handle_key(&key(KeyCode::Backspace, KeyModifiers::NONE));
+        }
+        type_text(&mut palette, "stream");
+        assert_eq!(palette.matches()[0], Action::ShowStreams);
+        assert_eq!(
+            palette.handle_key(&key(KeyCode::Enter, KeyModifiers::NONE)),
+            PaletteCommand::Run(Action::ShowStreams)
+        );
+
+        type_text(&mut palette, "zzz");
+        assert!(palette.matches().is_empty());
+        assert_eq!(palette.handle_key(&key(KeyCode::Enter, KeyModifiers::NONE)), PaletteCommand::None);
+        assert_eq!(palette.handle_key(&key(KeyCode::Esc, KeyModifiers::NONE)), PaletteCommand::Close);
+    }
+}
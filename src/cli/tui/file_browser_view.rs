@@ -1,6 +1,7 @@
 // File browser view for TUI
 
 use crate::cli::tui::widgets::FileEntry;
+use crate::remote_fs::{parent_path, DirectoryListing, RemoteEntry};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -19,6 +20,30 @@ pub struct FileBrowserView {
     pub selected_index: usize,
     pub selected_files: Vec<PathBuf>,
     pub show_hidden: bool,
+    /// Set while browsing a peer's shares instead of the local disk
+    pub remote: Option<RemoteLocation>,
+    /// Remote directory waiting to be fetched by the TUI manager
+    remote_request: Option<RemoteRequest>,
+}
+
+/// The peer directory currently shown
+#[derive(Debug, Clone)]
+pub struct RemoteLocation {
+    pub peer_id: String,
+    /// Share-relative path; empty for the list of shares
+    pub path: String,
+    pub entries: Vec<RemoteEntry>,
+    /// The peer returned only part of the directory
+    pub truncated: bool,
+    /// Why the last fetch failed, shown in place of the listing
+    pub error: Option<String>,
+}
+
+/// A remote directory to fetch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteRequest {
+    pub peer_id: String,
+    pub path: String,
 }
 
 impl FileBrowserView {
@@ -30,6 +55,8 @@ impl FileBrowserView {
             selected_index: 0,
             selected_files: Vec::new(),
             show_hidden: false,
+            remote: None,
+            remote_request: None,
         };
         view.refresh_entries();
         view
@@ -37,6 +64,16 @@ impl FileBrowserView {
 
     /// Refresh directory entries
     pub fn refresh_entries(&mut self) {
+        if let Some(remote) = &self.remote {
+            // Re-fetch the listing; show what we have until it arrives
+            self.remote_request = Some(RemoteRequest {
+                peer_id: remote.peer_id.clone(),
+                path: remote.path.clone(),
+            });
+            self.apply_remote_entries();
+            return;
+        }
+
         self.entries.clear();
 
         // Add parent directory entry if not at root
@@ -96,6 +133,112 @@ impl FileBrowserView {
         }
     }
 
+    /// Start browsing a peer's shares at `path`
+    ///
+    /// The listing is fetched asynchronously; the TUI manager picks the request
+    /// up with [`take_remote_request`](Self::take_remote_request) and answers with
+    /// [`show_remote`](Self::show_remote) or [`show_remote_error`](Self::show_remote_error).
+    pub fn request_remote(&mut self, peer_id: String, path: String) {
+        self.remote_request = Some(RemoteRequest { peer_id, path });
+    }
+
+    /// Remote directory waiting to be fetched
+    pub fn take_remote_request(&mut self) -> Option<RemoteRequest> {
+        self.remote_request.take()
+    }
+
+    /// Show a directory listing fetched from a peer
+    pub fn show_remote(&mut self, peer_id: String, listing: DirectoryListing) {
+        let same_location = self
+            .remote
+            .as_ref()
+            .is_some_and(|r| r.peer_id == peer_id && r.path == listing.path);
+        if !same_location {
+            self.selected_index = 0;
+            self.selected_files.clear();
+        }
+        self.remote = Some(RemoteLocation {
+            peer_id,
+            path: listing.path,
+            entries: listing.entries,
+            truncated: listing.truncated,
+            error: None,
+        });
+        self.apply_remote_entries();
+    }
+
+    /// Report a failed remote fetch
+    pub fn show_remote_error(&mut self, peer_id: String, path: String, error: String) {
+        if let Some(remote) = self.remote.as_mut().filter(|r| r.peer_id == peer_id) {
+            remote.error = Some(error);
+        } else {
+            self.remote = Some(RemoteLocation {
+                peer_id,
+                path,
+                entries: Vec::new(),
+                truncated: false,
+                error: Some(error),
+            });
+        }
+        self.apply_remote_entries();
+    }
+
+    /// Return to browsing the local disk
+    pub fn show_local(&mut self) {
+        if self.remote.take().is_some() {
+            self.remote_request = None;
+            self.selected_index = 0;
+            self.selected_files.clear();
+            self.refresh_entries();
+        }
+    }
+
+    /// Whether a peer's shares are being shown
+    pub fn is_remote(&self) -> bool {
+        self.remote.is_some()
+    }
+
+    /// Rebuild `entries` from the current remote listing
+    fn apply_remote_entries(&mut self) {
+        self.entries.clear();
+        let Some(remote) = &self.remote else {
+            return;
+        };
+
+        if let Some(parent) = parent_path(&remote.path) {
+            self.entries.push(FileEntry {
+                name: "..".to_string(),
+                path: PathBuf::from(parent),
+                is_directory: true,
+                size: None,
+            });
+        }
+        self.entries.extend(
+            remote
+                .entries
+                .iter()
+                .filter(|entry| self.show_hidden || !entry.name.starts_with('.'))
+                .map(|entry| FileEntry {
+                    name: entry.name.clone(),
+                    path: PathBuf::from(&entry.path),
+                    is_directory: entry.is_dir(),
+                    size: if entry.is_dir() { None } else { Some(entry.size) },
+                }),
+        );
+
+        if self.selected_index >= self.entries.len() {
+            self.selected_index = self.entries.len().saturating_sub(1);
+        }
+    }
+
+    /// Title for the file list
+    fn location_label(&self) -> String {
+        match &self.remote {
+            Some(remote) => format!("{}:/{}", remote.peer_id, remote.path),
+            None => self.current_path.display().to_string(),
+        }
+    }
+
     /// Render the file browser view
     pub fn render(&self, frame: &mut Frame, area: Rect) {
         let chunks = Layout::default()
@@ -112,12 +255,14 @@ impl FileBrowserView {
 
     /// Render file list
     fn render_file_list(&self, frame: &mut Frame, area: Rect) {
-        if self.entries.is_empty() {
+        let remote_error = self.remote.as_ref().and_then(|r| r.error.clone());
+        if self.entries.is_empty() || remote_error.is_some() {
             let block = Block::default()
                 .borders(Borders::ALL)
-                .title(format!("Files: {}", self.current_path.display()));
+                .title(format!("Files: {}", self.location_label()));
+            let message = remote_error.unwrap_or_else(|| "Directory is empty or cannot be read.".to_string());
             let paragraph = Paragraph::new(vec![
-                Line::from("Directory is empty or cannot be read."),
+                Line::from(message),
                 Line::from(""),
                 Line::from(vec![
                     Span::raw("Press "),
@@ -193,9 +338,15 @@ impl FileBrowserView {
             })
             .collect();
 
+        let truncated = if self.remote.as_ref().is_some_and(|r| r.truncated) {
+            " [partial listing]"
+        } else {
+            ""
+        };
         let title = format!(
-            "Files: {} (Press Space to select, Enter to open)",
-            self.current_path.display()
+            "Files: {}{} (Press Space to select, Enter to open)",
+            self.location_label(),
+            truncated
         );
 
         let list = List::new(items)
@@ -212,13 +363,21 @@ impl FileBrowserView {
     /// Render selection info
     fn render_selection_info(&self, frame: &mut Frame, area: Rect) {
         let selected_count = self.selected_files.len();
-        let total_size: u64 = self
-            .selected_files
-            .iter()
-            .filter_map(|path| fs::metadata(path).ok())
-            .filter(|m| m.is_file())
-            .map(|m| m.len())
-            .sum();
+        let total_size: u64 = match &self.remote {
+            Some(remote) => remote
+                .entries
+                .iter()
+                .filter(|entry| !entry.is_dir() && self.selected_files.contains(&PathBuf::from(&entry.path)))
+                .map(|entry| entry.size)
+                .sum(),
+            None => self
+                .selected_files
+                .iter()
+                .filter_map(|path| fs::metadata(path).ok())
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+                .sum(),
+        };
 
         let lines = vec![
             Line::from(vec![
@@ -243,7 +402,9 @@ impl FileBrowserView {
                 Span::styled("[h]", Style::default().fg(Color::Yellow)),
                 Span::raw(" Toggle Hidden  "),
                 Span::styled("[s]", Style::default().fg(Color::Yellow)),
-                Span::raw(" Send Selected"),
+                Span::raw(" Send Selected  "),
+                Span::styled("[L]", Style::default().fg(Color::Yellow)),
+                Span::raw(" Local Files"),
             ]),
         ];
 
@@ -293,6 +454,11 @@ impl FileBrowserView {
     pub fn open_selected(&mut self) -> Option<FileAction> {
         if let Some(entry) = self.entries.get(self.selected_index).cloned() {
             if entry.is_directory {
+                if let Some(peer_id) = self.remote.as_ref().map(|r| r.peer_id.clone()) {
+                    let path = entry.path.to_string_lossy().to_string();
+                    self.request_remote(peer_id.clone(), path.clone());
+                    return Some(FileAction::BrowseRemote { peer_id, path });
+                }
                 self.current_path = entry.path.clone();
                 self.selected_index = 0;
                 self.refresh_entries();
@@ -311,7 +477,11 @@ impl FileBrowserView {
     /// Toggle hidden files visibility
     pub fn toggle_hidden(&mut self) {
         self.show_hidden = !self.show_hidden;
-        self.refresh_entries();
+        if self.remote.is_some() {
+            self.apply_remote_entries();
+        } else {
+            self.refresh_entries();
+        }
     }
 
     /// Get selected entry
@@ -331,6 +501,15 @@ impl FileBrowserView {
 
     /// Navigate to parent directory
     pub fn navigate_up(&mut self) {
+        if let Some(remote) = &self.remote {
+            if let Some(parent) = parent_path(&remote.path) {
+                self.remote_request = Some(RemoteRequest {
+                    peer_id: remote.peer_id.clone(),
+                    path: parent,
+                });
+            }
+            return;
+        }
         if let Some(parent) = self.current_path.parent() {
             self.current_path = parent.to_path_buf();
             self.selected_index = 0;
@@ -354,6 +533,8 @@ pub enum FileAction {
     NavigateToDirectory(PathBuf),
     SelectFile(PathBuf),
     SendFiles(Vec<PathBuf>),
+    /// Fetch a directory from a peer's shares
+    BrowseRemote { peer_id: String, path: String },
 }
 
 /// Format byte size for display
@@ -7,14 +7,20 @@ mod peer_view;
 mod file_browser_view;
 mod transfer_view;
 mod operation_monitor;
+mod streaming_view;
+mod theme;
 
 pub use app::{TUIApp, TUIManager};
-pub use events::{EventHandler, EventLoop};
+pub use events::{
+    Action, CommandPalette, EventHandler, EventLoop, HelpCommand, HelpOverlay, KeyBinding, KeyBindings, PaletteCommand,
+};
 pub use widgets::{PeerListWidget, FileBrowserWidget, ProgressWidget, FileEntry};
 pub use peer_view::{PeerView, PeerAction};
-pub use file_browser_view::{FileBrowserView, FileAction};
+pub use file_browser_view::{FileBrowserView, FileAction, RemoteLocation, RemoteRequest};
 pub use transfer_view::{TransferView, TransferAction};
 pub use operation_monitor::{OperationMonitor, OperationControl, LogLevel, LogEntry};
+pub use streaming_view::{StreamingView, StreamSessionEntry, StreamViewerEntry, StreamStatsSource};
+pub use theme::Theme;
 
 use crate::cli::error::{CLIError, CLIResult};
 use crate::cli::types::{PeerInfo, OperationStatus, TUIState, ViewType};
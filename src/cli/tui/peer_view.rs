@@ -1,6 +1,7 @@
 // Peer management view for TUI
 
 use crate::cli::types::{ConnectionStatus, PeerInfo, TrustStatus};
+use crate::discovery::PresenceStatus;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -112,6 +113,11 @@ impl PeerView {
                         format!("{:<12}", truncate(&peer.device_type, 12)),
                         Style::default().fg(Color::Cyan),
                     ),
+                    Span::raw(" "),
+                    Span::styled(
+                        peer.presence.map(|status| status.to_string()).unwrap_or_default(),
+                        Style::default().fg(peer.presence.map(presence_color).unwrap_or(Color::Gray)),
+                    ),
                 ]);
 
                 let style = if i == self.selected_index {
@@ -183,6 +189,11 @@ impl PeerView {
             TrustStatus::Blocked => Color::Red,
         };
 
+        let (presence_text, presence_fg) = match peer.presence {
+            Some(status) => (status.to_string(), presence_color(status)),
+            None => ("Not shared".to_string(), Color::DarkGray),
+        };
+
         let last_seen = if let Some(ts) = peer.last_seen {
             ts.format("%Y-%m-%d %H:%M:%S").to_string()
         } else {
@@ -206,6 +217,21 @@ impl PeerView {
                 Span::styled("Trust: ", Style::default().fg(Color::Gray)),
                 Span::styled(trust_text, Style::default().fg(trust_color)),
             ]),
+            Line::from(vec![
+                Span::styled("Presence: ", Style::default().fg(Color::Gray)),
+                Span::styled(presence_text, Style::default().fg(presence_fg)),
+            ]),
+            Line::from(vec![
+                Span::styled("Version: ", Style::default().fg(Color::Gray)),
+                Span::styled(
+                    peer.version_label(),
+                    Style::default().fg(if peer.version_skew().is_some_and(|skew| skew.is_significant()) {
+                        Color::Yellow
+                    } else {
+                        Color::White
+                    }),
+                ),
+            ]),
             Line::from(vec![
                 Span::styled("Last Seen: ", Style::default().fg(Color::Gray)),
                 Span::styled(last_seen, Style::default().fg(Color::White)),
@@ -249,6 +275,7 @@ impl PeerView {
     fn render_actions(&self, frame: &mut Frame, area: Rect, peer: &PeerInfo) {
         let actions = match peer.connection_status {
             ConnectionStatus::Connected => vec![
+                ("f", "Browse Files", Color::Cyan),
                 ("d", "Disconnect", Color::Red),
                 ("t", "Toggle Trust", Color::Yellow),
                 ("b", "Block", Color::Red),
@@ -322,6 +349,16 @@ impl PeerView {
     }
 }
 
+/// Color for a peer's presence status
+fn presence_color(status: PresenceStatus) -> Color {
+    match status {
+        PresenceStatus::Online => Color::Green,
+        PresenceStatus::Away => Color::Yellow,
+        PresenceStatus::Busy => Color::Red,
+        PresenceStatus::Streaming => Color::Magenta,
+    }
+}
+
 /// Truncate string to max length
 fn truncate(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
@@ -341,6 +378,7 @@ pub enum PeerAction {
     Unblock,
     Retry,
     Cancel,
+    BrowseFiles,
 }
 
 impl PeerAction {
@@ -353,6 +391,7 @@ impl PeerAction {
             ('b', _) => Some(PeerAction::Block),
             ('r', ConnectionStatus::Error) => Some(PeerAction::Retry),
             ('x', ConnectionStatus::Connecting) => Some(PeerAction::Cancel),
+            ('f', ConnectionStatus::Connected) => Some(PeerAction::BrowseFiles),
             _ => None,
         }
     }
@@ -0,0 +1,323 @@
+// Streaming session statistics dashboard for TUI
+
+use async_trait::async_trait;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table},
+    Frame,
+};
+use std::time::{Duration, SystemTime};
+
+/// Snapshot of one active streaming session
+#[derive(Debug, Clone)]
+pub struct StreamSessionEntry {
+    pub session_id: uuid::Uuid,
+    pub resolution: (u32, u32),
+    pub framerate: u32,
+    /// Current bitrate in bits per second
+    pub bitrate: u32,
+    pub encoder_backend: String,
+    pub frames_sent: u64,
+    pub frames_dropped: u64,
+    pub started_at: SystemTime,
+    pub viewers: Vec<StreamViewerEntry>,
+}
+
+/// Snapshot of one viewer's connection to a session
+#[derive(Debug, Clone)]
+pub struct StreamViewerEntry {
+    pub name: String,
+    pub connection_quality: String,
+    pub bitrate: u32,
+    pub latency_ms: u32,
+    pub packet_loss_rate: f32,
+    pub frames_dropped: u64,
+}
+
+impl StreamSessionEntry {
+    /// Override the encoder backend name, e.g. "nvh264enc"
+    pub fn with_encoder_backend(mut self, backend: impl Into<String>) -> Self {
+        self.encoder_backend = backend.into();
+        self
+    }
+
+    /// Add a viewer row
+    pub fn with_viewer(mut self, viewer: StreamViewerEntry) -> Self {
+        self.viewers.push(viewer);
+        self
+    }
+
+    /// How long the session has been running
+    pub fn uptime(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.started_at)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl StreamSessionEntry {
+    /// Build a session row from the broadcaster's and transport's statistics
+    pub fn from_stats(
+        broadcast: &crate::streaming::viewer::BroadcastStats,
+        stats: &crate::streaming::StreamStats,
+    ) -> Self {
+        let quality = &broadcast.current_quality;
+        let bitrate = if stats.current_bitrate > 0 {
+            stats.current_bitrate
+        } else {
+            quality.bitrate
+        };
+
+        Self {
+            session_id: broadcast.session_id,
+            resolution: (quality.resolution.width, quality.resolution.height),
+            framerate: quality.framerate,
+            bitrate,
+            encoder_backend: if quality.hardware_acceleration { "hardware" } else { "software" }.to_string(),
+            frames_sent: broadcast.total_frames_sent,
+            frames_dropped: stats.frames_dropped,
+            started_at: broadcast.started_at,
+            viewers: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl StreamViewerEntry {
+    /// Build a viewer row from its status and connection statistics
+    pub fn from_stats(status: &crate::streaming::ViewerStatus, stats: &crate::streaming::StreamStats) -> Self {
+        Self {
+            name: status.device_name.clone(),
+            connection_quality: format!("{:?}", status.connection_quality),
+            bitrate: stats.current_bitrate,
+            latency_ms: stats.latency_ms,
+            packet_loss_rate: stats.packet_loss_rate,
+            frames_dropped: stats.frames_dropped,
+        }
+    }
+}
+
+/// Supplies fresh session snapshots to the streaming view
+#[async_trait]
+pub trait StreamStatsSource: Send + Sync {
+    /// Current statistics for every active session
+    async fn sessions(&self) -> Vec<StreamSessionEntry>;
+}
+
+/// Streaming dashboard state
+#[derive(Debug, Clone, Default)]
+pub struct StreamingView {
+    pub sessions: Vec<StreamSessionEntry>,
+    pub selected_index: usize,
+}
+
+impl StreamingView {
+    /// Create an empty streaming view
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render the streaming dashboard
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(area);
+
+        self.render_session_list(frame, chunks[0]);
+
+        match self.get_selected() {
+            Some(session) => self.render_session_details(frame, chunks[1], session),
+            None => {
+                let paragraph = Paragraph::new("Select a session to see its viewers")
+                    .block(Block::default().borders(Borders::ALL).title("Viewers"))
+                    .style(Style::default().fg(Color::Gray));
+                frame.render_widget(paragraph, chunks[1]);
+            }
+        }
+    }
+
+    /// Render the list of active sessions
+    fn render_session_list(&self, frame: &mut Frame, area: Rect) {
+        if self.sessions.is_empty() {
+            let paragraph = Paragraph::new("No active streaming sessions.")
+                .block(Block::default().borders(Borders::ALL).title("Streaming Sessions (0)"))
+                .style(Style::default().fg(Color::Gray));
+            frame.render_widget(paragraph, area);
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .sessions
+            .iter()
+            .enumerate()
+            .map(|(i, session)| {
+                let id = session.session_id.to_string();
+                let line = Line::from(vec![
+                    Span::styled(format!("{:<10}", &id[..8]), Style::default().fg(Color::White)),
+                    Span::styled(
+                        format!("{}x{}@{}fps ", session.resolution.0, session.resolution.1, session.framerate),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::styled(format!("{:>10} ", format_bitrate(session.bitrate)), Style::default().fg(Color::Yellow)),
+                    Span::raw(format!("{} viewer(s) ", session.viewers.len())),
+                    Span::styled(
+                        format!("{} dropped", session.frames_dropped),
+                        Style::default().fg(if session.frames_dropped > 0 { Color::Red } else { Color::Green }),
+                    ),
+                ]);
+
+                let style = if i == self.selected_index {
+                    Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+
+                ListItem::new(line).style(style)
+            })
+            .collect();
+
+        let title = format!("Streaming Sessions ({})", self.sessions.len());
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title(title));
+
+        frame.render_widget(list, area);
+    }
+
+    /// Render the selected session's encoder stats and viewers
+    fn render_session_details(&self, frame: &mut Frame, area: Rect, session: &StreamSessionEntry) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(4), Constraint::Min(0)])
+            .split(area);
+
+        let drop_rate = if session.frames_sent + session.frames_dropped > 0 {
+            session.frames_dropped as f64 / (session.frames_sent + session.frames_dropped) as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        let lines = vec![
+            Line::from(vec![
+                Span::styled("Encoder: ", Style::default().fg(Color::Gray)),
+                Span::styled(&session.encoder_backend, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                Span::raw("  "),
+                Span::styled("Uptime: ", Style::default().fg(Color::Gray)),
+                Span::raw(format_duration(session.uptime())),
+            ]),
+            Line::from(vec![
+                Span::styled("Frames sent: ", Style::default().fg(Color::Gray)),
+                Span::raw(session.frames_sent.to_string()),
+                Span::raw("  "),
+                Span::styled("Dropped: ", Style::default().fg(Color::Gray)),
+                Span::styled(
+                    format!("{} ({:.1}%)", session.frames_dropped, drop_rate),
+                    Style::default().fg(if session.frames_dropped > 0 { Color::Red } else { Color::Green }),
+                ),
+            ]),
+        ];
+
+        let stats = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Session"));
+        frame.render_widget(stats, chunks[0]);
+
+        let rows: Vec<Row> = session
+            .viewers
+            .iter()
+            .map(|viewer| {
+                Row::new(vec![
+                    viewer.name.clone(),
+                    viewer.connection_quality.clone(),
+                    format_bitrate(viewer.bitrate),
+                    format!("{} ms", viewer.latency_ms),
+                    format!("{:.1}%", viewer.packet_loss_rate * 100.0),
+                    viewer.frames_dropped.to_string(),
+                ])
+                .style(Style::default().fg(quality_color(&viewer.connection_quality)))
+            })
+            .collect();
+
+        let widths = [
+            Constraint::Percentage(30),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
+        ];
+
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(vec!["Viewer", "Quality", "Bitrate", "Latency", "Loss", "Dropped"])
+                    .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            )
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Viewers ({})", session.viewers.len())),
+            );
+
+        frame.render_widget(table, chunks[1]);
+    }
+
+    /// Replace the session snapshots
+    pub fn update_sessions(&mut self, sessions: Vec<StreamSessionEntry>) {
+        self.sessions = sessions;
+
+        if self.selected_index >= self.sessions.len() && !self.sessions.is_empty() {
+            self.selected_index = self.sessions.len() - 1;
+        }
+    }
+
+    /// Select next session
+    pub fn select_next(&mut self) {
+        if !self.sessions.is_empty() {
+            self.selected_index = (self.selected_index + 1) % self.sessions.len();
+        }
+    }
+
+    /// Select previous session
+    pub fn select_previous(&mut self) {
+        if !self.sessions.is_empty() {
+            if self.selected_index == 0 {
+                self.selected_index = self.sessions.len() - 1;
+            } else {
+                self.selected_index -= 1;
+            }
+        }
+    }
+
+    /// Get selected session
+    pub fn get_selected(&self) -> Option<&StreamSessionEntry> {
+        self.sessions.get(self.selected_index)
+    }
+}
+
+/// Color for a connection quality label
+fn quality_color(quality: &str) -> Color {
+    match quality {
+        "Excellent" => Color::Green,
+        "Good" => Color::Cyan,
+        "Fair" => Color::Yellow,
+        "Poor" | "Disconnected" => Color::Red,
+        _ => Color::White,
+    }
+}
+
+/// Format a bitrate for display
+fn format_bitrate(bps: u32) -> String {
+    if bps >= 1_000_000 {
+        format!("{:.1} Mbps", bps as f64 / 1_000_000.0)
+    } else if bps >= 1_000 {
+        format!("{} kbps", bps / 1_000)
+    } else {
+        format!("{} bps", bps)
+    }
+}
+
+/// Format a duration as HH:MM:SS
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
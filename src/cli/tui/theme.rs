@@ -0,0 +1,200 @@
+// Color themes for the TUI
+//
+// Views draw with their usual colors; the theme then rewrites the finished
+// frame. High contrast brightens text and turns selections into solid white
+// bars, no-color strips all colors and marks highlighted cells with reverse
+// video, and user color replacements from the config apply on top.
+// ColorMode::Never, or NO_COLOR with ColorMode::Auto, locks the TUI to
+// no-color.
+
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+use std::str::FromStr;
+
+use crate::cli::types::{ColorMode, TuiSettings, TuiTheme};
+
+/// Theme applied to every rendered frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    kind: TuiTheme,
+    /// Color replacements from the config
+    overrides: Vec<(Color, Color)>,
+    /// Colors are disabled, so the theme cannot be changed
+    locked: bool,
+}
+
+impl Theme {
+    /// Build the theme from the TUI settings and the CLI color mode
+    ///
+    /// Color replacements the config gets wrong are skipped and returned as
+    /// warnings.
+    pub fn from_settings(settings: &TuiSettings, color_mode: ColorMode) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut overrides = Vec::new();
+        for (from, to) in &settings.colors {
+            match (Color::from_str(from), Color::from_str(to)) {
+                (Ok(from), Ok(to)) => overrides.push((from, to)),
+                (Err(_), _) => warnings.push(format!("unknown color '{}'", from)),
+                (_, Err(_)) => warnings.push(format!("unknown color '{}' for {}", to, from)),
+            }
+        }
+
+        let locked = match color_mode {
+            ColorMode::Always => false,
+            ColorMode::Never => true,
+            ColorMode::Auto => std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+        };
+        let kind = if locked { TuiTheme::NoColor } else { settings.theme };
+
+        (Self { kind, overrides, locked }, warnings)
+    }
+
+    /// Theme in effect
+    pub fn kind(&self) -> TuiTheme {
+        self.kind
+    }
+
+    /// Switch to the next theme, unless colors are disabled
+    pub fn cycle(&mut self) -> TuiTheme {
+        if !self.locked {
+            self.kind = match self.kind {
+                TuiTheme::Default => TuiTheme::HighContrast,
+                TuiTheme::HighContrast => TuiTheme::NoColor,
+                TuiTheme::NoColor => TuiTheme::Default,
+            };
+        }
+        self.kind
+    }
+
+    /// Human-readable theme name
+    pub fn label(&self) -> &'static str {
+        match self.kind {
+            TuiTheme::Default => "default",
+            TuiTheme::HighContrast => "high contrast",
+            TuiTheme::NoColor => "no color",
+        }
+    }
+
+    /// Recolor a rendered frame
+    pub fn apply(&self, buffer: &mut Buffer) {
+        if self.kind == TuiTheme::Default && self.overrides.is_empty() {
+            return;
+        }
+
+        for cell in buffer.content.iter_mut() {
+            if self.kind == TuiTheme::NoColor {
+                if cell.bg != Color::Reset {
+                    cell.modifier.insert(Modifier::REVERSED);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+                continue;
+            }
+
+            let fg = self.replace(cell.fg);
+            let bg = self.replace(cell.bg);
+            if self.kind == TuiTheme::HighContrast {
+                if bg == Color::Reset || bg == Color::Black {
+                    cell.fg = high_contrast(fg);
+                    cell.bg = Color::Black;
+                } else {
+                    // Selections and highlights become solid bars
+                    cell.fg = Color::Black;
+                    cell.bg = Color::White;
+                    cell.modifier.insert(Modifier::BOLD);
+                }
+            } else {
+                cell.fg = fg;
+                cell.bg = bg;
+            }
+        }
+    }
+
+    fn replace(&self, color: Color) -> Color {
+        self.overrides
+            .iter()
+            .find(|(from, _)| *from == color)
+            .map_or(color, |(_, to)| *to)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            kind: TuiTheme::Default,
+            overrides: Vec::new(),
+            locked: false,
+        }
+    }
+}
+
+/// Brighter variant of a foreground color
+fn high_contrast(color: Color) -> Color {
+    match color {
+        Color::Reset | Color::Gray | Color::DarkGray | Color::Black => Color::White,
+        Color::Red => Color::LightRed,
+        Color::Green => Color::LightGreen,
+        Color::Yellow => Color::LightYellow,
+        Color::Blue => Color::LightBlue,
+        Color::Magenta => Color::LightMagenta,
+        Color::Cyan => Color::LightCyan,
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::style::Style;
+
+    fn frame() -> Buffer {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 4, 1));
+        buffer.set_string(0, 0, "ab", Style::default().fg(Color::Gray));
+        buffer.set_string(2, 0, "cd", Style::default().fg(Color::White).bg(Color::DarkGray));
+        buffer
+    }
+
+    #[test]
+    fn test_color_mode_never_locks_no_color() {
+        let settings = TuiSettings {
+            theme: TuiTheme::HighContrast,
+            ..TuiSettings::default()
+        };
+        let (mut theme, _) = Theme::from_settings(&settings, ColorMode::Never);
+        assert_eq!(theme.kind(), TuiTheme::NoColor);
+        assert_eq!(theme.cycle(), TuiTheme::NoColor);
+
+        let mut buffer = frame();
+        theme.apply(&mut buffer);
+        assert_eq!(buffer.get(0, 0).fg, Color::Reset);
+        assert!(!buffer.get(0, 0).modifier.contains(Modifier::REVERSED));
+        assert_eq!(buffer.get(2, 0).bg, Color::Reset);
+        assert!(buffer.get(2, 0).modifier.contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn test_high_contrast_and_overrides() {
+        let mut settings = TuiSettings {
+            theme: TuiTheme::HighContrast,
+            ..TuiSettings::default()
+        };
+        settings.colors.insert("gray".to_string(), "yellow".to_string());
+        settings.colors.insert("mauve".to_string(), "red".to_string());
+        let (mut theme, warnings) = Theme::from_settings(&settings, ColorMode::Always);
+        assert_eq!(warnings, vec!["unknown color 'mauve'".to_string()]);
+
+        let mut buffer = frame();
+        theme.apply(&mut buffer);
+        assert_eq!(buffer.get(0, 0).fg, Color::LightYellow);
+        assert_eq!(buffer.get(0, 0).bg, Color::Black);
+        assert_eq!((buffer.get(2, 0).fg, buffer.get(2, 0).bg), (Color::Black, Color::White));
+
+        assert_eq!(theme.cycle(), TuiTheme::NoColor);
+        assert_eq!(theme.cycle(), TuiTheme::Default);
+        let mut buffer = frame();
+        theme.apply(&mut buffer);
+        assert_eq!(buffer.get(0, 0).fg, Color::Yellow);
+        assert_eq!(buffer.get(2, 0).bg, Color::DarkGray);
+    }
+}
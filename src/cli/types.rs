@@ -24,6 +24,36 @@ pub struct CLIConfig {
     pub transfer_settings: TransferSettings,
     pub stream_settings: StreamSettings,
     pub profiles: HashMap<String, ConfigProfile>,
+    /// Folders exported to peers
+    #[serde(default)]
+    pub shares: crate::remote_fs::ShareConfig,
+    /// Status shared with peers
+    #[serde(default)]
+    pub presence: crate::discovery::PresenceConfig,
+    /// How long history, logs and recordings are kept
+    #[serde(default)]
+    pub retention: crate::retention::RetentionConfig,
+    /// Restrictions on metered connections
+    #[serde(default)]
+    pub traffic: crate::metered::TrafficPolicyConfig,
+    /// Buffer, cache and connection limits for the device
+    #[serde(default)]
+    pub resources: crate::platform::ResourceConfig,
+    /// Hotkey that locks down inbound remote access
+    #[serde(default)]
+    pub lockdown: crate::lockdown::LockdownConfig,
+    /// Local services trusted peers may forward connections to
+    #[serde(default)]
+    pub forwarding: crate::forwarding::ForwardConfig,
+    /// Interactive interface preferences
+    #[serde(default)]
+    pub tui: TuiSettings,
+    /// Desktop notifications for finished transfers and streams
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    /// Settings scoped to single peers, under `[peers."<alias>"]`
+    #[serde(default)]
+    pub peers: PeerOverrideTable,
 }
 
 impl Default for CLIConfig {
@@ -35,10 +65,31 @@ impl Default for CLIConfig {
             transfer_settings: TransferSettings::default(),
             stream_settings: StreamSettings::default(),
             profiles: HashMap::new(),
+            shares: crate::remote_fs::ShareConfig::default(),
+            presence: crate::discovery::PresenceConfig::default(),
+            retention: crate::retention::RetentionConfig::default(),
+            traffic: crate::metered::TrafficPolicyConfig::default(),
+            resources: crate::platform::ResourceConfig::default(),
+            lockdown: crate::lockdown::LockdownConfig::default(),
+            forwarding: crate::forwarding::ForwardConfig::default(),
+            tui: TuiSettings::default(),
+            notifications: NotificationSettings::default(),
+            peers: PeerOverrideTable::default(),
         }
     }
 }
 
+impl CLIConfig {
+    /// Transfer settings for sending to `peer`, with its overrides applied
+    pub fn transfer_settings_for(&self, peer: &str) -> TransferSettings {
+        let mut settings = self.transfer_settings.clone();
+        if let Some(compression) = self.peers.get(peer).and_then(|overrides| overrides.compression) {
+            settings.compression = compression;
+        }
+        settings
+    }
+}
+
 /// Output format options
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -96,6 +147,148 @@ impl Default for StreamSettings {
     }
 }
 
+/// TUI color theme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TuiTheme {
+    #[default]
+    Default,
+    HighContrast,
+    NoColor,
+}
+
+/// TUI settings configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TuiSettings {
+    /// Keys per action name, replacing that action's default keys
+    #[serde(default)]
+    pub keybindings: std::collections::BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub theme: TuiTheme,
+    /// Color replacements applied on top of the theme, e.g. yellow = "magenta"
+    #[serde(default)]
+    pub colors: std::collections::BTreeMap<String, String>,
+    /// Milliseconds between screen redraws
+    #[serde(default = "default_frame_interval_ms")]
+    pub frame_interval_ms: u64,
+    /// Milliseconds between streaming statistics refreshes
+    #[serde(default = "default_stats_refresh_ms")]
+    pub stats_refresh_ms: u64,
+    /// Start in linear mode: plain text without borders or tabs, one item per
+    /// line, for screen readers
+    #[serde(default)]
+    pub linear: bool,
+}
+
+fn default_frame_interval_ms() -> u64 {
+    50
+}
+
+fn default_stats_refresh_ms() -> u64 {
+    1000
+}
+
+impl Default for TuiSettings {
+    fn default() -> Self {
+        Self {
+            keybindings: std::collections::BTreeMap::new(),
+            theme: TuiTheme::default(),
+            colors: std::collections::BTreeMap::new(),
+            frame_interval_ms: default_frame_interval_ms(),
+            stats_refresh_ms: default_stats_refresh_ms(),
+            linear: false,
+        }
+    }
+}
+
+/// Settings that apply only when working with one peer
+///
+/// Unset fields fall back to the global settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerOverrides {
+    /// Highest transfer rate to this peer, in bytes per second
+    pub bandwidth_limit: Option<u64>,
+    /// Compress transfers to this peer, whatever `transfer_settings` says
+    pub compression: Option<bool>,
+    /// Sync the clipboard with this peer
+    pub clipboard_sync: Option<bool>,
+    /// Let this peer view streams
+    pub streaming: Option<bool>,
+}
+
+impl PeerOverrides {
+    /// Whether the clipboard may be synced with the peer
+    pub fn allows_clipboard_sync(&self) -> bool {
+        self.clipboard_sync != Some(false)
+    }
+
+    /// Whether the peer may view streams
+    pub fn allows_streaming(&self) -> bool {
+        self.streaming != Some(false)
+    }
+}
+
+/// Per-peer overrides keyed by peer alias, name or ID
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PeerOverrideTable(pub std::collections::BTreeMap<String, PeerOverrides>);
+
+impl PeerOverrideTable {
+    /// Overrides for a peer; aliases match case-insensitively
+    pub fn get(&self, peer: &str) -> Option<&PeerOverrides> {
+        self.0.get(peer).or_else(|| {
+            self.0
+                .iter()
+                .find(|(alias, _)| alias.eq_ignore_ascii_case(peer))
+                .map(|(_, overrides)| overrides)
+        })
+    }
+
+    /// Overrides for a peer, or the empty set when it has none
+    pub fn for_peer(&self, peer: &str) -> PeerOverrides {
+        self.get(peer).cloned().unwrap_or_default()
+    }
+
+    /// Set the overrides for a peer alias
+    pub fn insert(&mut self, alias: impl Into<String>, overrides: PeerOverrides) {
+        self.0.insert(alias.into(), overrides);
+    }
+
+    /// Aliases with their overrides
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &PeerOverrides)> {
+        self.0.iter()
+    }
+}
+
+/// Desktop notification settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    /// Notify when a transfer or stream started from the CLI finishes
+    #[serde(default = "default_notifications_enabled")]
+    pub enabled: bool,
+    /// Operations that finish sooner than this are not announced
+    #[serde(default = "default_notify_after_secs")]
+    pub min_duration_secs: u64,
+}
+
+fn default_notifications_enabled() -> bool {
+    true
+}
+
+fn default_notify_after_secs() -> u64 {
+    10
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_notifications_enabled(),
+            min_duration_secs: default_notify_after_secs(),
+        }
+    }
+}
+
 /// Configuration profile
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigProfile {
@@ -125,6 +318,19 @@ pub enum CommandType {
     Peers,
     Status,
     Clipboard,
+    Msg,
+    Get,
+    OpenOn,
+    Locate,
+    Security,
+    Transport,
+    Doctor,
+    Bench,
+    Ping,
+    Wipe,
+    Recordings,
+    Export,
+    SelfUpdate,
     TUI,
     Config,
 }
@@ -161,6 +367,37 @@ pub struct PeerInfo {
     pub capabilities: Vec<String>,
     pub trust_status: TrustStatus,
     pub last_seen: Option<Timestamp>,
+    /// Status the peer shares, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence: Option<crate::discovery::PresenceStatus>,
+    /// Kizuna release the peer advertises, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl PeerInfo {
+    /// How the peer's release compares with this build, if it advertises one
+    pub fn version_skew(&self) -> Option<crate::transport::VersionSkew> {
+        let version = self.version.as_deref()?;
+        Some(
+            crate::transport::CompatibilityTable::standard()
+                .assess(&crate::transport::compatibility::local_version(), version),
+        )
+    }
+
+    /// Version for peer listings, marked when it differs enough to matter
+    pub fn version_label(&self) -> String {
+        let Some(skew) = self.version_skew() else {
+            return "unknown".to_string();
+        };
+        let version = self.version.as_deref().unwrap_or_default();
+        match &skew.remote {
+            _ if !skew.is_significant() => version.to_string(),
+            Some(remote) if *remote < skew.local => format!("{} (outdated)", version),
+            Some(_) => format!("{} (newer)", version),
+            None => format!("{} (unrecognised)", version),
+        }
+    }
 }
 
 /// Connection status
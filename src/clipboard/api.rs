@@ -12,14 +12,23 @@ use crate::clipboard::{
     Clipboard, ClipboardContent, ClipboardResult, ClipboardError,
     PeerId, DeviceId, DeviceSyncStatus, SyncPolicy, ConnectionStatus, HistoryId,
 };
+use crate::clipboard::content::{ClipboardCapabilities, FormatConverter, ImageProcessor};
+#[cfg(feature = "clipboard-ocr")]
+use crate::clipboard::content::ocr::OcrPipeline;
 use crate::clipboard::monitor::ClipboardMonitor;
 use crate::clipboard::sync::{SyncManager, DefaultSyncManager};
 use crate::clipboard::privacy::PrivacyPolicyManager;
 use crate::clipboard::history::{HistoryManager, HistoryEntry};
+use crate::clipboard::delivery::{content_hash, DeliveryStatus, DeliveryTracker};
+use crate::clipboard::transform::{
+    ConvertPaths, NormalizeLineEndings, PathMapping, TransformContext, TransformPipeline,
+};
 use crate::clipboard::security_integration::ClipboardSecurityIntegration;
 use crate::clipboard::transport_integration::{ClipboardTransportIntegration, ClipboardMessage};
 use crate::clipboard::platform::UnifiedClipboard;
+use crate::error::KizunaResult;
 use crate::security::SecuritySystem;
+use crate::shutdown::{ShutdownHook, ShutdownReason, ShutdownStage};
 use crate::transport::{KizunaTransport, PeerAddress};
 
 /// Unified clipboard system configuration
@@ -37,6 +46,10 @@ pub struct ClipboardSystemConfig {
     pub enable_privacy_filter: bool,
     /// Enable notifications
     pub enable_notifications: bool,
+    /// Translate line endings and paths for peers on another OS
+    pub translate_between_os: bool,
+    /// Directories that correspond between Windows and Unix devices
+    pub path_mappings: Vec<PathMapping>,
 }
 
 impl Default for ClipboardSystemConfig {
@@ -48,6 +61,8 @@ impl Default for ClipboardSystemConfig {
             history_limit: 50,
             enable_privacy_filter: true,
             enable_notifications: true,
+            translate_between_os: false,
+            path_mappings: Vec::new(),
         }
     }
 }
@@ -70,10 +85,19 @@ pub struct ClipboardSystem {
     security_integration: Arc<ClipboardSecurityIntegration>,
     /// Transport integration
     transport_integration: Arc<ClipboardTransportIntegration>,
+    /// Devices that acknowledged each synced item
+    deliveries: Arc<DeliveryTracker>,
+    /// Rewrites applied to content before it is sent to a peer
+    transforms: Arc<RwLock<TransformPipeline>>,
     /// Peer addresses for connection management
     peer_addresses: Arc<RwLock<HashMap<PeerId, PeerAddress>>>,
+    /// Clipboard formats each peer advertised
+    peer_capabilities: Arc<RwLock<HashMap<PeerId, ClipboardCapabilities>>>,
     /// Monitoring state
     is_monitoring: Arc<RwLock<bool>>,
+    /// Text recognition for clipboard images
+    #[cfg(feature = "clipboard-ocr")]
+    ocr: Option<OcrPipeline>,
 }
 
 impl ClipboardSystem {
@@ -89,7 +113,11 @@ impl ClipboardSystem {
         let sync_manager = Arc::new(DefaultSyncManager::new());
         let privacy_manager = Arc::new(PrivacyPolicyManager::new());
         let security_integration = Arc::new(ClipboardSecurityIntegration::new(security_system));
-        let transport_integration = Arc::new(ClipboardTransportIntegration::new(transport));
+        let transport_integration = Arc::new(ClipboardTransportIntegration::new(
+            transport,
+            Arc::clone(&security_integration),
+        ));
+        let transforms = Self::default_transforms(&config);
         
         Self {
             config: Arc::new(RwLock::new(config)),
@@ -100,11 +128,39 @@ impl ClipboardSystem {
             history_manager,
             security_integration,
             transport_integration,
+            deliveries: Arc::new(DeliveryTracker::new()),
+            transforms: Arc::new(RwLock::new(transforms)),
             peer_addresses: Arc::new(RwLock::new(HashMap::new())),
+            peer_capabilities: Arc::new(RwLock::new(HashMap::new())),
             is_monitoring: Arc::new(RwLock::new(false)),
+            #[cfg(feature = "clipboard-ocr")]
+            ocr: None,
         }
     }
     
+    /// Recognize text in clipboard images as they are set
+    #[cfg(feature = "clipboard-ocr")]
+    pub fn with_ocr(mut self, pipeline: OcrPipeline) -> Self {
+        self.ocr = Some(pipeline);
+        self
+    }
+    
+    /// Built-in transformations enabled by the configuration
+    fn default_transforms(config: &ClipboardSystemConfig) -> TransformPipeline {
+        if !config.translate_between_os {
+            return TransformPipeline::new();
+        }
+        TransformPipeline::new()
+            .with_stage(NormalizeLineEndings::new())
+            .with_stage(ConvertPaths::with_mappings(config.path_mappings.clone()))
+    }
+    
+    /// Transform content through this pipeline before it is sent to peers
+    pub fn with_transforms(mut self, pipeline: TransformPipeline) -> Self {
+        self.transforms = Arc::new(RwLock::new(pipeline));
+        self
+    }
+    
     /// Get current clipboard content
     pub async fn get_content(&self) -> ClipboardResult<Option<ClipboardContent>> {
         self.platform_clipboard.get_content().await
@@ -112,6 +168,12 @@ impl ClipboardSystem {
     
     /// Set clipboard content locally
     pub async fn set_content(&self, content: ClipboardContent) -> ClipboardResult<()> {
+        #[cfg(feature = "clipboard-ocr")]
+        let content = match &self.ocr {
+            Some(ocr) => ocr.process(content).await,
+            None => content,
+        };
+        
         // Set content on platform clipboard
         self.platform_clipboard.set_content(content.clone()).await?;
         
@@ -126,6 +188,30 @@ impl ClipboardSystem {
         Ok(())
     }
     
+    /// Replace an image on the clipboard with the text recognized in it
+    ///
+    /// Returns `false` when the clipboard holds no image with text.
+    pub async fn paste_image_text(&self) -> ClipboardResult<bool> {
+        let Some(content @ ClipboardContent::Image(_)) = self.get_content().await? else {
+            return Ok(false);
+        };
+        
+        #[cfg(feature = "clipboard-ocr")]
+        let content = match &self.ocr {
+            Some(ocr) => ocr.process(content).await,
+            None => content,
+        };
+        
+        match content.as_text() {
+            Some(text) => {
+                let text = crate::clipboard::TextContent::new(text.to_string());
+                self.set_content(ClipboardContent::Text(text)).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    
     /// Start monitoring clipboard changes
     pub async fn start_monitoring(&self) -> ClipboardResult<()> {
         {
@@ -168,10 +254,42 @@ impl ClipboardSystem {
             ));
         }
         
-        // Encrypt content
-        let encrypted_content = self.security_integration
-            .encrypt_content(peer_id, &content)
-            .await?;
+        // Deliveries are tracked against the item as it sits in local history
+        let item_hash = content_hash(&content)?;
+        
+        // Adapt custom formats to what the peer can paste
+        let capabilities = self.peer_capabilities.read().await.get(peer_id).cloned();
+        let content = match &capabilities {
+            Some(capabilities) => match FormatConverter::new().negotiate(&content, capabilities)? {
+                Some(content) => content,
+                None => {
+                    log::debug!("Skipping clipboard sync to {}: no format the peer can hold", peer_id);
+                    return Ok(());
+                }
+            },
+            None => content,
+        };
+        
+        // Run the configured transformations for this peer
+        let content = {
+            let context = TransformContext::new(peer_id, capabilities.as_ref());
+            self.transforms.read().await.apply(content, &context)?
+        };
+        
+        // Shrink images for the receiver; local history keeps the original
+        let content = match content {
+            ClipboardContent::Image(image) => {
+                let capabilities = capabilities.unwrap_or_else(|| ClipboardCapabilities::text_only("unknown"));
+                let threshold = self.config.read().await.sync_policy.image_compression_threshold;
+                let image = tokio::task::spawn_blocking(move || {
+                    ImageProcessor::new().transcode_for_receiver(&image, &capabilities, threshold)
+                })
+                .await
+                .map_err(|e| ClipboardError::internal(format!("Image transcoding task failed: {}", e)))??;
+                ClipboardContent::Image(image)
+            }
+            content => content,
+        };
         
         // Get peer address
         let peer_address = {
@@ -182,12 +300,32 @@ impl ClipboardSystem {
                 .clone()
         };
         
-        // Send content via transport
+        // Send content over the peer's authenticated session
         self.transport_integration
-            .send_content(peer_id, &peer_address, encrypted_content)
+            .send_content(peer_id, &peer_address, &content)
             .await?;
         
-        Ok(())
+        self.deliveries.record(&item_hash, peer_id)
+    }
+    
+    /// Get a copy of the transformation pipeline applied before sync
+    pub async fn transform_pipeline(&self) -> TransformPipeline {
+        self.transforms.read().await.clone()
+    }
+    
+    /// Replace the transformation pipeline applied before sync
+    pub async fn set_transform_pipeline(&self, pipeline: TransformPipeline) {
+        *self.transforms.write().await = pipeline;
+    }
+    
+    /// Enable or disable a transformation stage for one peer
+    pub async fn set_transform_enabled_for_peer(&self, peer_id: &PeerId, stage: &str, enabled: bool) -> ClipboardResult<()> {
+        self.transforms.write().await.set_enabled_for_peer(peer_id, stage, enabled)
+    }
+    
+    /// Get which devices received a clipboard item
+    pub fn delivery_status(&self, entry: &HistoryEntry) -> ClipboardResult<DeliveryStatus> {
+        self.deliveries.status_for(&entry.content)
     }
     
     /// Sync clipboard content to all enabled peers
@@ -202,10 +340,15 @@ impl ClipboardSystem {
             .receive_message(peer_id)
             .await?;
         
+        if let Some(ClipboardMessage::Capabilities { capabilities }) = message {
+            self.peer_capabilities.write().await.insert(peer_id.clone(), capabilities);
+            return Ok(());
+        }
+        
         if let Some(ClipboardMessage::SyncContent { content: encrypted_content, sequence, .. }) = message {
             // Decrypt content
-            let content = self.security_integration
-                .decrypt_content(peer_id, &encrypted_content)
+            let content = self.transport_integration
+                .open_content(peer_id, &encrypted_content)
                 .await?;
             
             // Process received content through sync manager
@@ -265,6 +408,18 @@ impl ClipboardSystem {
         Ok(())
     }
     
+    /// Advertise the local clipboard's formats to a peer
+    pub async fn send_capabilities(&self, peer_id: &PeerId) -> ClipboardResult<()> {
+        self.transport_integration
+            .send_capabilities(peer_id, self.platform_clipboard.capabilities())
+            .await
+    }
+    
+    /// Formats a peer advertised, if it has done so
+    pub async fn peer_capabilities(&self, peer_id: &PeerId) -> Option<ClipboardCapabilities> {
+        self.peer_capabilities.read().await.get(peer_id).cloned()
+    }
+    
     /// Remove a device from the sync allowlist
     pub async fn remove_device(&self, device_id: &DeviceId) -> ClipboardResult<()> {
         // Remove from sync manager
@@ -275,6 +430,9 @@ impl ClipboardSystem {
             let mut addresses = self.peer_addresses.write().await;
             addresses.remove(device_id);
         }
+        self.peer_capabilities.write().await.remove(device_id);
+        self.deliveries.forget_device(device_id)?;
+        self.transforms.write().await.clear_peer_overrides(device_id);
         
         // Remove from trusted peers
         self.security_integration
@@ -369,6 +527,9 @@ impl ClipboardSystem {
         // Stop monitoring
         self.stop_monitoring().await?;
         
+        // Persist history before peers go away
+        self.history_manager.flush().await?;
+        
         // Disconnect all peers
         self.transport_integration.disconnect_all().await?;
         
@@ -402,6 +563,22 @@ impl Clipboard for ClipboardSystem {
     }
 }
 
+#[async_trait]
+impl ShutdownHook for ClipboardSystem {
+    fn name(&self) -> &str {
+        "clipboard"
+    }
+
+    fn stage(&self) -> ShutdownStage {
+        ShutdownStage::Flush
+    }
+
+    async fn shutdown(&self, _reason: &ShutdownReason) -> KizunaResult<()> {
+        ClipboardSystem::shutdown(self).await?;
+        Ok(())
+    }
+}
+
 /// Clipboard system status information
 #[derive(Debug, Clone)]
 pub struct ClipboardSystemStatus {
@@ -434,6 +611,9 @@ pub struct ClipboardSystemBuilder {
     transport: Option<Arc<KizunaTransport>>,
     monitor: Option<Arc<dyn ClipboardMonitor>>,
     history_manager: Option<Arc<dyn HistoryManager>>,
+    transforms: Option<TransformPipeline>,
+    #[cfg(feature = "clipboard-ocr")]
+    ocr: Option<OcrPipeline>,
 }
 
 impl ClipboardSystemBuilder {
@@ -445,6 +625,9 @@ impl ClipboardSystemBuilder {
             transport: None,
             monitor: None,
             history_manager: None,
+            transforms: None,
+            #[cfg(feature = "clipboard-ocr")]
+            ocr: None,
         }
     }
     
@@ -508,6 +691,31 @@ impl ClipboardSystemBuilder {
         self
     }
     
+    /// Translate line endings and paths for peers on another OS
+    pub fn translate_between_os(mut self, enabled: bool) -> Self {
+        self.config.translate_between_os = enabled;
+        self
+    }
+    
+    /// Map a Windows directory to its Unix counterpart for path translation
+    pub fn path_mapping(mut self, windows: impl Into<String>, unix: impl Into<String>) -> Self {
+        self.config.path_mappings.push(PathMapping::new(windows, unix));
+        self
+    }
+    
+    /// Set transformation pipeline applied before sync
+    pub fn transforms(mut self, pipeline: TransformPipeline) -> Self {
+        self.transforms = Some(pipeline);
+        self
+    }
+    
+    /// Set OCR pipeline for clipboard images
+    #[cfg(feature = "clipboard-ocr")]
+    pub fn ocr(mut self, pipeline: OcrPipeline) -> Self {
+        self.ocr = Some(pipeline);
+        self
+    }
+    
     /// Build the clipboard system
     pub fn build(self) -> ClipboardResult<ClipboardSystem> {
         let security_system = self.security_system
@@ -522,13 +730,26 @@ impl ClipboardSystemBuilder {
         let history_manager = self.history_manager
             .ok_or_else(|| ClipboardError::config("builder", "History manager is required"))?;
         
-        Ok(ClipboardSystem::new(
+        let system = ClipboardSystem::new(
             self.config,
             security_system,
             transport,
             monitor,
             history_manager,
-        ))
+        );
+        
+        let system = match self.transforms {
+            Some(pipeline) => system.with_transforms(pipeline),
+            None => system,
+        };
+        
+        #[cfg(feature = "clipboard-ocr")]
+        let system = match self.ocr {
+            Some(pipeline) => system.with_ocr(pipeline),
+            None => system,
+        };
+        
+        Ok(system)
     }
 }
 
@@ -553,7 +774,7 @@ mod tests {
         // Create temporary database for testing
         let temp_dir = std::env::temp_dir();
         let db_path = temp_dir.join(format!("test_clipboard_history_{}.db", uuid::Uuid::new_v4()));
-        let history_manager = Arc::new(SqliteHistoryManager::new(db_path, 50).unwrap());
+        let history_manager = Arc::new(SqliteHistoryManager::new(db_path, security_system.storage()).unwrap());
         
         ClipboardSystem::new(
             ClipboardSystemConfig::default(),
@@ -615,7 +836,7 @@ mod tests {
         
         let temp_dir = std::env::temp_dir();
         let db_path = temp_dir.join(format!("test_clipboard_history_{}.db", uuid::Uuid::new_v4()));
-        let history_manager = Arc::new(SqliteHistoryManager::new(db_path, 50).unwrap());
+        let history_manager = Arc::new(SqliteHistoryManager::new(db_path, security_system.storage()).unwrap());
         
         let system = ClipboardSystemBuilder::new()
             .auto_monitor(false)
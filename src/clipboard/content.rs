@@ -8,6 +8,9 @@ use crate::clipboard::{
     TextContent, ImageContent, ImageFormat, TextFormat, TextEncoding
 };
 
+#[cfg(feature = "clipboard-ocr")]
+pub mod ocr;
+
 /// Text processor for handling various text formats and encodings
 pub struct TextProcessor {
     max_text_size: usize,
@@ -425,6 +428,7 @@ impl ImageProcessor {
             width,
             height,
             compressed: false,
+            ocr_text: None,
         })
     }
     
@@ -458,6 +462,7 @@ impl ImageProcessor {
             width: image.width,
             height: image.height,
             compressed: true,
+            ocr_text: image.ocr_text.clone(),
         })
     }
     
@@ -473,36 +478,107 @@ impl ImageProcessor {
             .map_err(|e| ClipboardError::content(format!("Failed to load image for conversion: {}", e)))?;
         
         // Convert to target format
-        let mut converted_data = Vec::new();
-        let mut cursor = Cursor::new(&mut converted_data);
+        let converted_data = self.encode(&img, &target_format)?;
+        let is_jpeg = matches!(target_format, ImageFormat::Jpeg);
         
-        let img_format = self.to_image_format(&target_format);
+        Ok(ImageContent {
+            data: converted_data,
+            format: target_format,
+            width: image.width,
+            height: image.height,
+            compressed: is_jpeg,
+            ocr_text: image.ocr_text.clone(),
+        })
+    }
+    
+    /// Encode a decoded image in the given format
+    fn encode(&self, img: &image::DynamicImage, format: &ImageFormat) -> ClipboardResult<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut cursor = Cursor::new(&mut data);
         
         // Use appropriate encoder based on format
-        let is_jpeg = matches!(target_format, ImageFormat::Jpeg);
-        match &target_format {
+        match format {
             ImageFormat::Jpeg => {
+                // JPEG has no alpha channel
+                let rgb = image::DynamicImage::ImageRgb8(img.to_rgb8());
                 let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut cursor, self.jpeg_quality);
-                img.write_with_encoder(encoder)
+                rgb.write_with_encoder(encoder)
                     .map_err(|e| ClipboardError::content(format!("Failed to encode JPEG: {}", e)))?;
             }
             ImageFormat::Png => {
                 img.write_to(&mut cursor, ImgFormat::Png)
                     .map_err(|e| ClipboardError::content(format!("Failed to encode PNG: {}", e)))?;
             }
+            ImageFormat::WebP => {
+                let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut cursor);
+                img.write_with_encoder(encoder)
+                    .map_err(|e| ClipboardError::content(format!("Failed to encode WebP: {}", e)))?;
+            }
             _ => {
-                img.write_to(&mut cursor, img_format)
+                img.write_to(&mut cursor, self.to_image_format(format))
                     .map_err(|e| ClipboardError::content(format!("Failed to encode image: {}", e)))?;
             }
         }
         
-        Ok(ImageContent {
-            data: converted_data,
+        Ok(data)
+    }
+    
+    /// Re-encode and downscale an image for a receiving device
+    ///
+    /// Images larger than the receiver's maximum dimension are scaled to fit.
+    /// Images above `threshold` bytes, or in a format the receiver cannot
+    /// decode, are re-encoded: lossless WebP when the receiver supports it,
+    /// JPEG otherwise. The input is left untouched so the sender keeps the
+    /// original in its own history.
+    pub fn transcode_for_receiver(
+        &self,
+        image: &ImageContent,
+        capabilities: &ClipboardCapabilities,
+        threshold: usize,
+    ) -> ClipboardResult<ImageContent> {
+        let oversized = image.data.len() > threshold;
+        let too_large = capabilities
+            .max_image_dimension
+            .is_some_and(|max| image.width.max(image.height) > max);
+        let unsupported = !capabilities.accepts_image_format(&image.format);
+        
+        if !oversized && !too_large && !unsupported {
+            return Ok(image.clone());
+        }
+        
+        let mut img = image::load_from_memory(&image.data)
+            .map_err(|e| ClipboardError::content(format!("Failed to load image for transcoding: {}", e)))?;
+        
+        if let Some(max) = capabilities.max_image_dimension.filter(|_| too_large) {
+            img = img.resize(max, max, image::imageops::FilterType::Lanczos3);
+        }
+        
+        let target_format = if !oversized && !unsupported {
+            // Only downscaled; keep the sender's encoding
+            image.format.clone()
+        } else if capabilities.accepts_image_format(&ImageFormat::WebP) {
+            ImageFormat::WebP
+        } else if capabilities.accepts_image_format(&ImageFormat::Jpeg) {
+            ImageFormat::Jpeg
+        } else {
+            ImageFormat::Png
+        };
+        
+        let transcoded = ImageContent {
+            data: self.encode(&img, &target_format)?,
             format: target_format,
-            width: image.width,
-            height: image.height,
-            compressed: is_jpeg,
-        })
+            width: img.width(),
+            height: img.height(),
+            compressed: true,
+            ocr_text: image.ocr_text.clone(),
+        };
+        
+        // Re-encoding alone is only worth it when it actually saves bytes
+        if !too_large && !unsupported && transcoded.data.len() >= image.data.len() {
+            return Ok(image.clone());
+        }
+        
+        Ok(transcoded)
     }
     
     /// Detect image format from data
@@ -557,7 +633,7 @@ impl ImageProcessor {
             ImageFormat::Png | ImageFormat::Jpeg => {
                 // These are well-supported formats
             }
-            ImageFormat::Bmp | ImageFormat::Gif | ImageFormat::Tiff => {
+            ImageFormat::Bmp | ImageFormat::Gif | ImageFormat::Tiff | ImageFormat::WebP => {
                 warnings.push(format!(
                     "Image format {:?} may not be supported on all platforms",
                     image.format
@@ -590,6 +666,7 @@ impl ImageProcessor {
             ImageFormat::Bmp => ImgFormat::Bmp,
             ImageFormat::Gif => ImgFormat::Gif,
             ImageFormat::Tiff => ImgFormat::Tiff,
+            ImageFormat::WebP => ImgFormat::WebP,
         }
     }
     
@@ -601,6 +678,7 @@ impl ImageProcessor {
             ImgFormat::Bmp => ImageFormat::Bmp,
             ImgFormat::Gif => ImageFormat::Gif,
             ImgFormat::Tiff => ImageFormat::Tiff,
+            ImgFormat::WebP => ImageFormat::WebP,
             _ => ImageFormat::Png, // Default fallback
         }
     }
@@ -674,7 +752,7 @@ impl FormatConverter {
             ClipboardContent::Image(image) => self.image_to_platform_formats(image, platform),
             ClipboardContent::Files(files) => self.files_to_platform_formats(files, platform),
             ClipboardContent::Custom { mime_type, data } => {
-                Ok(vec![(PlatformFormat::Generic(mime_type.clone()), data.clone())])
+                Ok(self.custom_to_platform_formats(mime_type, data, platform))
             }
         }
     }
@@ -835,6 +913,7 @@ impl FormatConverter {
                     ImageFormat::Bmp => "image/bmp",
                     ImageFormat::Gif => "image/gif",
                     ImageFormat::Tiff => "image/tiff",
+                    ImageFormat::WebP => "image/webp",
                 };
                 
                 formats.push((
@@ -893,7 +972,7 @@ impl FormatConverter {
             WindowsFormat::Html => {
                 let html = String::from_utf8(data)
                     .map_err(|e| ClipboardError::content(format!("Invalid UTF-8 HTML: {}", e)))?;
-                let text_content = self.text_processor.process_text(&html, TextFormat::Html)?;
+                let text_content = self.text_processor.process_text(self.decode_windows_html(&html), TextFormat::Html)?;
                 Ok(ClipboardContent::Text(text_content))
             }
             WindowsFormat::Rtf => {
@@ -909,7 +988,7 @@ impl FormatConverter {
             }
             WindowsFormat::Custom(name) => {
                 Ok(ClipboardContent::Custom {
-                    mime_type: format!("windows/{}", name),
+                    mime_type: custom_format_mime("windows", &name),
                     data,
                 })
             }
@@ -948,7 +1027,7 @@ impl FormatConverter {
             }
             MacOSFormat::Custom(name) => {
                 Ok(ClipboardContent::Custom {
-                    mime_type: format!("macos/{}", name),
+                    mime_type: custom_format_mime("macos", &name),
                     data,
                 })
             }
@@ -1021,6 +1100,130 @@ impl FormatConverter {
         full_html.as_bytes().to_vec()
     }
     
+    /// Strip the `Version:`/`StartHTML:` header Windows puts before HTML
+    fn decode_windows_html<'a>(&self, html: &'a str) -> &'a str {
+        if !html.starts_with("Version:") {
+            return html;
+        }
+        let mut rest = html;
+        while let Some((line, remaining)) = rest.split_once('\n') {
+            let line = line.trim_end_matches('\r');
+            let is_header = line
+                .split_once(':')
+                .is_some_and(|(key, _)| !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric()));
+            if !is_header {
+                break;
+            }
+            rest = remaining;
+        }
+        rest
+    }
+    
+    /// Map a custom MIME type onto each platform's closest native format
+    ///
+    /// Formats without a native equivalent are registered under the MIME type
+    /// itself, so a receiver on the same platform reads back the same type.
+    fn custom_to_platform_formats(
+        &self,
+        mime_type: &str,
+        data: &[u8],
+        platform: &str,
+    ) -> Vec<(PlatformFormat, Vec<u8>)> {
+        let essence = mime_essence(mime_type);
+        let format = match platform {
+            "windows" => PlatformFormat::Windows(match essence.as_str() {
+                "text/html" => {
+                    let html = String::from_utf8_lossy(data);
+                    return vec![(PlatformFormat::Windows(WindowsFormat::Html), self.encode_windows_html(&html))];
+                }
+                "text/rtf" => WindowsFormat::Rtf,
+                "image/png" => WindowsFormat::Png,
+                _ => WindowsFormat::Custom(mime_type.to_string()),
+            }),
+            "macos" => PlatformFormat::MacOS(match essence.as_str() {
+                "text/html" => MacOSFormat::Html,
+                "text/rtf" => MacOSFormat::Rtf,
+                "image/png" => MacOSFormat::Png,
+                "image/tiff" => MacOSFormat::Tiff,
+                "application/pdf" => MacOSFormat::Pdf,
+                _ => MacOSFormat::Custom(mime_type.to_string()),
+            }),
+            "linux" => PlatformFormat::Linux(mime_type.to_string()),
+            _ => PlatformFormat::Generic(mime_type.to_string()),
+        };
+        
+        vec![(format, data.to_vec())]
+    }
+    
+    /// Adapt content to what a receiving device can place on its clipboard
+    ///
+    /// Custom formats the receiver understands pass through untouched; others
+    /// fall back to the nearest standard representation (HTML to text, image
+    /// MIME types to images, URI lists to files). Returns `None` when nothing
+    /// usable remains, in which case the content should not be synced.
+    pub fn negotiate(
+        &self,
+        content: &ClipboardContent,
+        capabilities: &ClipboardCapabilities,
+    ) -> ClipboardResult<Option<ClipboardContent>> {
+        match content {
+            ClipboardContent::Custom { mime_type, .. } if capabilities.supports_mime(mime_type) => {
+                Ok(Some(content.clone()))
+            }
+            ClipboardContent::Custom { mime_type, data } => {
+                match self.custom_fallback(mime_type, data)? {
+                    Some(fallback) => self.negotiate(&fallback, capabilities),
+                    None => Ok(None),
+                }
+            }
+            ClipboardContent::Text(text) => {
+                let mime = text_format_mime(&text.format);
+                if text.format == TextFormat::Plain || capabilities.supports_mime(mime) {
+                    Ok(Some(content.clone()))
+                } else {
+                    Ok(Some(ClipboardContent::Text(self.text_processor.to_plain_text(text)?)))
+                }
+            }
+            _ => Ok(Some(content.clone())),
+        }
+    }
+    
+    /// Nearest standard content for a custom MIME type, if any
+    pub(crate) fn custom_fallback(&self, mime_type: &str, data: &[u8]) -> ClipboardResult<Option<ClipboardContent>> {
+        let essence = mime_essence(mime_type);
+        
+        if essence == "text/uri-list" {
+            let text = String::from_utf8_lossy(data);
+            let files: Vec<String> = text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from)
+                .collect();
+            return Ok((!files.is_empty()).then_some(ClipboardContent::Files(files)));
+        }
+        
+        if essence.starts_with("text/") {
+            return self.from_linux_format(&essence, data.to_vec()).map(Some);
+        }
+        
+        if essence.starts_with("image/") {
+            return Ok(self.from_linux_format(&essence, data.to_vec()).ok());
+        }
+        
+        // Structured text such as JSON or XML is still readable as plain text
+        let textual = essence.ends_with("+json") || essence.ends_with("+xml")
+            || essence == "application/json" || essence == "application/xml";
+        if textual {
+            if let Ok(text) = std::str::from_utf8(data) {
+                let text_content = self.text_processor.process_text(text, TextFormat::Plain)?;
+                return Ok(Some(ClipboardContent::Text(text_content)));
+            }
+        }
+        
+        Ok(None)
+    }
+    
     /// Detect common format from content
     pub fn detect_content_format(&self, data: &[u8]) -> ClipboardResult<String> {
         // Try to detect if it's text
@@ -1042,6 +1245,7 @@ impl FormatConverter {
                 ImageFormat::Bmp => "image/bmp",
                 ImageFormat::Gif => "image/gif",
                 ImageFormat::Tiff => "image/tiff",
+                ImageFormat::WebP => "image/webp",
             }.to_string());
         }
         
@@ -1073,6 +1277,123 @@ impl Default for FormatConverter {
     }
 }
 
+/// Clipboard formats a device can hold natively
+///
+/// Exchanged between peers so the sender can pick a representation the
+/// receiver's platform is able to paste.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ClipboardCapabilities {
+    /// Platform name as reported by the clipboard backend
+    pub platform: String,
+    /// MIME types accepted as-is; `type/*` matches a whole family
+    pub mime_types: Vec<String>,
+    /// Backend can hold formats it has never seen, e.g. X11 selection targets
+    pub arbitrary_mime_types: bool,
+    /// Image encodings the device can decode; empty means PNG and JPEG
+    #[serde(default)]
+    pub image_formats: Vec<ImageFormat>,
+    /// Longest image edge worth sending, e.g. a phone's screen size
+    #[serde(default)]
+    pub max_image_dimension: Option<u32>,
+}
+
+impl ClipboardCapabilities {
+    /// Capabilities of a backend that only handles plain text
+    pub fn text_only(platform: impl Into<String>) -> Self {
+        Self {
+            platform: platform.into(),
+            mime_types: vec!["text/plain".to_string()],
+            arbitrary_mime_types: false,
+            image_formats: Vec::new(),
+            max_image_dimension: None,
+        }
+    }
+    
+    /// Add a natively supported MIME type
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_types.push(mime_type.into());
+        self
+    }
+    
+    /// Mark the backend as able to hold any MIME type
+    pub fn with_arbitrary_mime_types(mut self, arbitrary: bool) -> Self {
+        self.arbitrary_mime_types = arbitrary;
+        self
+    }
+    
+    /// Declare the image encodings the device can decode
+    pub fn with_image_formats(mut self, formats: Vec<ImageFormat>) -> Self {
+        self.image_formats = formats;
+        self
+    }
+    
+    /// Limit the longest edge of images sent to the device
+    pub fn with_max_image_dimension(mut self, max: u32) -> Self {
+        self.max_image_dimension = Some(max);
+        self
+    }
+    
+    /// Whether the device can decode images in this format
+    pub fn accepts_image_format(&self, format: &ImageFormat) -> bool {
+        if self.image_formats.is_empty() {
+            matches!(format, ImageFormat::Png | ImageFormat::Jpeg)
+        } else {
+            self.image_formats.contains(format)
+        }
+    }
+    
+    /// Whether content of this MIME type can be placed without conversion
+    pub fn supports_mime(&self, mime_type: &str) -> bool {
+        if self.arbitrary_mime_types {
+            return true;
+        }
+        
+        let essence = mime_essence(mime_type);
+        self.mime_types.iter().any(|supported| {
+            let supported = mime_essence(supported);
+            match supported.strip_suffix("/*") {
+                Some(family) => essence.split('/').next() == Some(family),
+                None => supported == essence,
+            }
+        })
+    }
+}
+
+/// MIME type without parameters, lowercased (`text/html; charset=utf-8` -> `text/html`)
+fn mime_essence(mime_type: &str) -> String {
+    mime_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// MIME type matching a text format
+fn text_format_mime(format: &TextFormat) -> &'static str {
+    match format {
+        TextFormat::Plain => "text/plain",
+        TextFormat::Html => "text/html",
+        TextFormat::Rtf => "text/rtf",
+        TextFormat::Markdown => "text/markdown",
+    }
+}
+
+/// MIME type for a platform-registered custom format
+///
+/// Formats Kizuna registered carry their MIME type as the name and round-trip
+/// unchanged; other native names are namespaced by platform.
+fn custom_format_mime(platform: &str, name: &str) -> String {
+    let looks_like_mime = name
+        .split_once('/')
+        .is_some_and(|(kind, sub)| !kind.is_empty() && !sub.is_empty() && !name.contains(char::is_whitespace));
+    if looks_like_mime {
+        name.to_string()
+    } else {
+        format!("{}/{}", platform, name)
+    }
+}
+
 /// Processed content ready for transmission
 #[derive(Debug, Clone)]
 pub struct ProcessedContent {
@@ -1285,4 +1606,120 @@ impl Default for DefaultContentProcessor {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(mime_type: &str, data: &[u8]) -> ClipboardContent {
+        ClipboardContent::Custom {
+            mime_type: mime_type.to_string(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_custom_formats_round_trip() {
+        let converter = FormatConverter::new();
+        let original = custom("application/x-custom", b"\x00\x01payload");
+
+        for platform in ["windows", "macos", "linux", "other"] {
+            let formats = converter.to_platform_format(&original, platform).unwrap();
+            assert_eq!(formats.len(), 1);
+            let (format, data) = formats.into_iter().next().unwrap();
+            assert_eq!(converter.from_platform_format(format, data).unwrap(), original, "{}", platform);
+        }
+
+        // Native names that are not MIME types stay namespaced
+        let native = converter
+            .from_platform_format(PlatformFormat::Windows(WindowsFormat::Custom("FileNameW".to_string())), vec![1])
+            .unwrap();
+        assert_eq!(native, custom("windows/FileNameW", &[1]));
+    }
+
+    #[test]
+    fn test_windows_html_round_trip() {
+        let converter = FormatConverter::new();
+        let html = "<p>hello <img src=\"data:image/png;base64,AAAA\"></p>";
+        let formats = converter.to_platform_format(&custom("text/html", html.as_bytes()), "windows").unwrap();
+        let (format, data) = formats.into_iter().next().unwrap();
+
+        match converter.from_platform_format(format, data).unwrap() {
+            ClipboardContent::Text(text) => {
+                assert_eq!(text.format, TextFormat::Html);
+                assert_eq!(text.text, html);
+            }
+            other => panic!("unexpected content {:?}", other),
+        }
+    }
+
+    fn test_png(width: u32, height: u32) -> ImageContent {
+        let pixels = image::RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([(x * 7) as u8, (y * 3) as u8, ((x + y) % 256) as u8, 255])
+        });
+        let mut data = Vec::new();
+        image::DynamicImage::ImageRgba8(pixels)
+            .write_to(&mut Cursor::new(&mut data), ImgFormat::Png)
+            .unwrap();
+        ImageContent::new(data, ImageFormat::Png, width, height)
+    }
+
+    #[test]
+    fn test_transcode_for_receiver() {
+        let processor = ImageProcessor::new();
+        let original = test_png(64, 32);
+
+        // Small enough and decodable: sent as-is
+        let desktop = ClipboardCapabilities::text_only("linux-x11");
+        assert_eq!(processor.transcode_for_receiver(&original, &desktop, usize::MAX).unwrap(), original);
+
+        // Downscaled to the phone's limit, keeping the aspect ratio
+        let phone = ClipboardCapabilities::text_only("android")
+            .with_image_formats(vec![ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP])
+            .with_max_image_dimension(16);
+        let scaled = processor.transcode_for_receiver(&original, &phone, usize::MAX).unwrap();
+        assert_eq!((scaled.width, scaled.height), (16, 8));
+        assert_eq!(scaled.format, ImageFormat::Png);
+
+        // Over the threshold: re-encoded in a format the receiver decodes
+        let webp = processor.transcode_for_receiver(&original, &phone, 0).unwrap();
+        assert_eq!(webp.format, ImageFormat::WebP);
+        assert_eq!(processor.detect_format(&webp.data).unwrap(), ImageFormat::WebP);
+
+        let bmp = processor.convert_format(&original, ImageFormat::Bmp).unwrap();
+        let jpeg = processor.transcode_for_receiver(&bmp, &desktop, usize::MAX).unwrap();
+        assert_eq!(jpeg.format, ImageFormat::Jpeg);
+        assert_eq!((jpeg.width, jpeg.height), (64, 32));
+    }
+
+    #[test]
+    fn test_negotiate_falls_back_per_receiver() {
+        let converter = FormatConverter::new();
+        let text_only = ClipboardCapabilities::text_only("windows");
+        let with_html = ClipboardCapabilities::text_only("linux-wayland").with_mime_type("text/html");
+        let x11 = ClipboardCapabilities::text_only("linux-x11").with_arbitrary_mime_types(true);
+
+        let html = custom("text/html; charset=utf-8", b"<b>bold</b> &amp; more");
+        assert_eq!(converter.negotiate(&html, &with_html).unwrap(), Some(html.clone()));
+        match converter.negotiate(&html, &text_only).unwrap() {
+            Some(ClipboardContent::Text(text)) => {
+                assert_eq!(text.format, TextFormat::Plain);
+                assert_eq!(text.text, "bold & more");
+            }
+            other => panic!("unexpected content {:?}", other),
+        }
+
+        let opaque = custom("application/x-custom", &[0xff, 0x00]);
+        assert_eq!(converter.negotiate(&opaque, &x11).unwrap(), Some(opaque.clone()));
+        assert_eq!(converter.negotiate(&opaque, &text_only).unwrap(), None);
+
+        let uris = custom("text/uri-list", b"# comment\nfile:///tmp/a.txt\n");
+        assert_eq!(
+            converter.negotiate(&uris, &text_only).unwrap(),
+            Some(ClipboardContent::Files(vec!["file:///tmp/a.txt".to_string()]))
+        );
+
+        assert!(ClipboardCapabilities::text_only("x").with_mime_type("image/*").supports_mime("IMAGE/PNG"));
+    }
+}
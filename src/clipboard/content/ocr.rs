@@ -0,0 +1,209 @@
+//! Optical character recognition for clipboard images
+//!
+//! When an image lands in the clipboard, an [`OcrPipeline`] runs it through a
+//! pluggable [`OcrEngine`] and stores the recognized text on the image as a
+//! secondary representation, so a screenshot can be pasted or synced as text.
+
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use crate::clipboard::{ClipboardContent, ClipboardError, ClipboardResult, ImageContent, ImageFormat};
+
+/// Text recognition backend
+#[async_trait]
+pub trait OcrEngine: Send + Sync {
+    /// Engine name for logs
+    fn name(&self) -> &str;
+
+    /// Whether the engine can read images in this format
+    fn supports_format(&self, format: &ImageFormat) -> bool;
+
+    /// Recognize text in an image, returning `None` when there is none
+    async fn recognize(&self, image: &ImageContent) -> ClipboardResult<Option<String>>;
+}
+
+/// Runs the `tesseract` command-line tool, feeding the image on stdin
+#[derive(Debug, Clone)]
+pub struct TesseractEngine {
+    program: PathBuf,
+    languages: Vec<String>,
+    timeout: Duration,
+}
+
+impl TesseractEngine {
+    /// Use `tesseract` from `PATH` with English
+    pub fn new() -> Self {
+        Self {
+            program: PathBuf::from("tesseract"),
+            languages: vec!["eng".to_string()],
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Use a specific tesseract executable
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Set recognition languages, e.g. `["eng", "deu"]`
+    pub fn with_languages(mut self, languages: Vec<String>) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    /// Limit how long a single recognition may take
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn run(&self, data: &[u8]) -> ClipboardResult<Vec<u8>> {
+        let mut child = tokio::process::Command::new(&self.program)
+            .arg("stdin")
+            .arg("stdout")
+            .arg("-l")
+            .arg(self.languages.join("+"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ClipboardError::platform(format!("Failed to start {}: {}", self.program.display(), e)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(data).await
+                .map_err(|e| ClipboardError::platform(format!("Failed to send image to tesseract: {}", e)))?;
+        }
+
+        let output = child.wait_with_output().await
+            .map_err(|e| ClipboardError::platform(format!("Tesseract failed: {}", e)))?;
+        if !output.status.success() {
+            return Err(ClipboardError::platform(format!("Tesseract exited with {}", output.status)));
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+impl Default for TesseractEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl OcrEngine for TesseractEngine {
+    fn name(&self) -> &str {
+        "tesseract"
+    }
+
+    fn supports_format(&self, format: &ImageFormat) -> bool {
+        // Leptonica builds without WebP support are common
+        !matches!(format, ImageFormat::WebP)
+    }
+
+    async fn recognize(&self, image: &ImageContent) -> ClipboardResult<Option<String>> {
+        let stdout = tokio::time::timeout(self.timeout, self.run(&image.data))
+            .await
+            .map_err(|_| ClipboardError::timeout("ocr", self.timeout.as_millis() as u64))??;
+
+        let text = String::from_utf8_lossy(&stdout).trim().to_string();
+        Ok((!text.is_empty()).then_some(text))
+    }
+}
+
+/// Attaches recognized text to clipboard images
+#[derive(Clone)]
+pub struct OcrPipeline {
+    engine: Arc<dyn OcrEngine>,
+    max_image_size: usize,
+}
+
+impl OcrPipeline {
+    /// Create a pipeline around an engine
+    pub fn new(engine: Arc<dyn OcrEngine>) -> Self {
+        Self {
+            engine,
+            max_image_size: 20 * 1024 * 1024, // 20MB
+        }
+    }
+
+    /// Skip images larger than this many encoded bytes
+    pub fn with_max_image_size(mut self, max: usize) -> Self {
+        self.max_image_size = max;
+        self
+    }
+
+    /// Engine in use
+    pub fn engine(&self) -> &Arc<dyn OcrEngine> {
+        &self.engine
+    }
+
+    /// Add recognized text to image content
+    ///
+    /// OCR is best effort: other content, images that already carry text and
+    /// images the engine cannot read are returned unchanged, and engine
+    /// failures are logged rather than surfaced.
+    pub async fn process(&self, content: ClipboardContent) -> ClipboardContent {
+        let ClipboardContent::Image(image) = content else {
+            return content;
+        };
+
+        if image.ocr_text.is_some()
+            || image.data.len() > self.max_image_size
+            || !self.engine.supports_format(&image.format)
+        {
+            return ClipboardContent::Image(image);
+        }
+
+        match self.engine.recognize(&image).await {
+            Ok(Some(text)) => ClipboardContent::Image(image.with_ocr_text(text)),
+            Ok(None) => ClipboardContent::Image(image),
+            Err(e) => {
+                log::debug!("OCR with {} failed: {}", self.engine.name(), e);
+                ClipboardContent::Image(image)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedEngine(&'static str);
+
+    #[async_trait]
+    impl OcrEngine for FixedEngine {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn supports_format(&self, format: &ImageFormat) -> bool {
+            *format == ImageFormat::Png
+        }
+
+        async fn recognize(&self, _image: &ImageContent) -> ClipboardResult<Option<String>> {
+            Ok(Some(self.0.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_attaches_text_to_images() {
+        let pipeline = OcrPipeline::new(Arc::new(FixedEngine("hello world")));
+
+        let png = ClipboardContent::Image(ImageContent::new(vec![1, 2, 3], ImageFormat::Png, 1, 1));
+        let processed = pipeline.process(png).await;
+        assert_eq!(processed.as_text(), Some("hello world"));
+
+        let webp = ClipboardContent::Image(ImageContent::new(vec![1, 2, 3], ImageFormat::WebP, 1, 1));
+        assert_eq!(pipeline.process(webp).await.as_text(), None);
+
+        let text = ClipboardContent::Text(crate::clipboard::TextContent::new("plain".to_string()));
+        assert_eq!(pipeline.process(text.clone()).await, text);
+    }
+}
@@ -0,0 +1,191 @@
+//! Per-item delivery tracking for clipboard sync
+//!
+//! Records which devices acknowledged each synced clipboard item so history
+//! views can show where an item went. Items are keyed by a SHA-256 hash of
+//! their content, which is also the only form of the content that reaches
+//! the audit log.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::time::SystemTime;
+use sha2::{Digest, Sha256};
+use crate::clipboard::{ClipboardContent, ClipboardResult, ClipboardError, DeviceId, Timestamp};
+
+/// Default number of clipboard items whose deliveries are remembered
+const DEFAULT_TRACKED_ITEMS: usize = 200;
+
+/// Hex-encoded SHA-256 of clipboard content, stable across devices
+pub fn content_hash(content: &ClipboardContent) -> ClipboardResult<String> {
+    let bytes = serde_json::to_vec(content)
+        .map_err(|e| ClipboardError::serialization("clipboard_content", e))?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
+}
+
+/// A device that acknowledged a clipboard item
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryReceipt {
+    pub device_id: DeviceId,
+    pub delivered_at: Timestamp,
+}
+
+/// Devices a clipboard item reached
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeliveryStatus {
+    pub receipts: Vec<DeliveryReceipt>,
+}
+
+impl DeliveryStatus {
+    /// Check if any device received the item
+    pub fn is_delivered(&self) -> bool {
+        !self.receipts.is_empty()
+    }
+
+    /// Check if a specific device received the item
+    pub fn received_by(&self, device_id: &str) -> bool {
+        self.receipts.iter().any(|receipt| receipt.device_id == device_id)
+    }
+
+    /// Devices that received the item, in delivery order
+    pub fn devices(&self) -> Vec<&str> {
+        self.receipts.iter().map(|receipt| receipt.device_id.as_str()).collect()
+    }
+
+    /// Get a visual indicator of where the item was delivered
+    pub fn indicator(&self) -> String {
+        if self.receipts.is_empty() {
+            "⏳ not synced".to_string()
+        } else {
+            format!("✅ {}", self.devices().join(", "))
+        }
+    }
+}
+
+/// Bounded record of clipboard deliveries, keyed by content hash
+pub struct DeliveryTracker {
+    /// Delivery status by content hash
+    items: RwLock<HashMap<String, DeliveryStatus>>,
+    /// Content hashes in the order they were first delivered
+    order: RwLock<VecDeque<String>>,
+    /// Maximum number of items remembered
+    max_items: usize,
+}
+
+impl DeliveryTracker {
+    /// Create a tracker with the default capacity
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_TRACKED_ITEMS)
+    }
+
+    /// Create a tracker remembering at most `max_items` items
+    pub fn with_capacity(max_items: usize) -> Self {
+        Self {
+            items: RwLock::new(HashMap::new()),
+            order: RwLock::new(VecDeque::new()),
+            max_items: max_items.max(1),
+        }
+    }
+
+    /// Record that a device acknowledged the item with this content hash
+    pub fn record(&self, content_hash: &str, device_id: &DeviceId) -> ClipboardResult<()> {
+        let mut items = self.items.write()
+            .map_err(|_| ClipboardError::internal("Failed to acquire write lock on deliveries"))?;
+        let mut order = self.order.write()
+            .map_err(|_| ClipboardError::internal("Failed to acquire write lock on delivery order"))?;
+
+        let status = items.entry(content_hash.to_string()).or_insert_with(|| {
+            order.push_back(content_hash.to_string());
+            DeliveryStatus::default()
+        });
+        if !status.received_by(device_id) {
+            status.receipts.push(DeliveryReceipt {
+                device_id: device_id.clone(),
+                delivered_at: SystemTime::now(),
+            });
+        }
+
+        while order.len() > self.max_items {
+            if let Some(oldest) = order.pop_front() {
+                items.remove(&oldest);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get where the item with this content hash was delivered
+    pub fn status(&self, content_hash: &str) -> ClipboardResult<DeliveryStatus> {
+        let items = self.items.read()
+            .map_err(|_| ClipboardError::internal("Failed to acquire read lock on deliveries"))?;
+        Ok(items.get(content_hash).cloned().unwrap_or_default())
+    }
+
+    /// Get where this clipboard content was delivered
+    pub fn status_for(&self, content: &ClipboardContent) -> ClipboardResult<DeliveryStatus> {
+        self.status(&content_hash(content)?)
+    }
+
+    /// Forget deliveries to a device, e.g. after it is removed
+    pub fn forget_device(&self, device_id: &str) -> ClipboardResult<()> {
+        let mut items = self.items.write()
+            .map_err(|_| ClipboardError::internal("Failed to acquire write lock on deliveries"))?;
+        for status in items.values_mut() {
+            status.receipts.retain(|receipt| receipt.device_id != device_id);
+        }
+        Ok(())
+    }
+
+    /// Number of items with recorded deliveries
+    pub fn tracked_count(&self) -> usize {
+        self.items.read().map(|items| items.len()).unwrap_or(0)
+    }
+}
+
+impl Default for DeliveryTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::{TextContent, TextEncoding, TextFormat};
+
+    fn text(value: &str) -> ClipboardContent {
+        ClipboardContent::Text(TextContent {
+            text: value.to_string(),
+            encoding: TextEncoding::Utf8,
+            format: TextFormat::Plain,
+            size: value.len(),
+        })
+    }
+
+    #[test]
+    fn test_records_each_device_once() {
+        let tracker = DeliveryTracker::new();
+        let hash = content_hash(&text("hello")).unwrap();
+
+        tracker.record(&hash, &"laptop".to_string()).unwrap();
+        tracker.record(&hash, &"phone".to_string()).unwrap();
+        tracker.record(&hash, &"laptop".to_string()).unwrap();
+
+        let status = tracker.status_for(&text("hello")).unwrap();
+        assert_eq!(status.devices(), vec!["laptop", "phone"]);
+        assert_eq!(status.indicator(), "✅ laptop, phone");
+        assert!(!tracker.status_for(&text("other")).unwrap().is_delivered());
+    }
+
+    #[test]
+    fn test_hash_hides_content_and_capacity_is_bounded() {
+        let hash = content_hash(&text("secret password")).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert!(!hash.contains("secret"));
+
+        let tracker = DeliveryTracker::with_capacity(2);
+        for value in ["a", "b", "c"] {
+            tracker.record(&content_hash(&text(value)).unwrap(), &"phone".to_string()).unwrap();
+        }
+        assert_eq!(tracker.tracked_count(), 2);
+        assert!(!tracker.status_for(&text("a")).unwrap().is_delivered());
+        assert!(tracker.status_for(&text("c")).unwrap().is_delivered());
+    }
+}
@@ -7,6 +7,16 @@ use crate::clipboard::{
     ClipboardContent, ClipboardResult, ClipboardError,
     HistoryId, ContentSource, Timestamp
 };
+use crate::security::storage::EncryptedStorage;
+
+/// Context label binding sealed history content to this store
+const HISTORY_CONTEXT: &str = "clipboard:history";
+
+/// Entries read per query while searching
+const SEARCH_PAGE_SIZE: usize = 200;
+
+/// Most entries a search returns
+const SEARCH_LIMIT: usize = 50;
 
 /// Clipboard history entry
 #[derive(Debug, Clone)]
@@ -76,6 +86,7 @@ impl HistoryEntry {
                         crate::clipboard::ImageFormat::Bmp => "BMP",
                         crate::clipboard::ImageFormat::Gif => "GIF",
                         crate::clipboard::ImageFormat::Tiff => "TIFF",
+                        crate::clipboard::ImageFormat::WebP => "WebP",
                     })
             }
             ClipboardContent::Files(files) => {
@@ -145,6 +156,14 @@ pub trait HistoryManager: Send + Sync {
     
     /// Get count of entries by source type
     async fn get_source_count(&self, source_type: &str) -> ClipboardResult<u64>;
+
+    /// Write any buffered entries to durable storage
+    ///
+    /// Called on shutdown. Managers that persist each entry as it is added have
+    /// nothing to do.
+    async fn flush(&self) -> ClipboardResult<()> {
+        Ok(())
+    }
 }
 
 /// History statistics
@@ -276,16 +295,117 @@ impl HistoryBrowser {
 /// SQLite-based history manager implementation
 pub struct SqliteHistoryManager {
     db_path: PathBuf,
+    storage: Option<EncryptedStorage>,
 }
 
 impl SqliteHistoryManager {
-    /// Create new SQLite history manager
-    pub fn new(db_path: PathBuf) -> ClipboardResult<Self> {
-        let manager = Self { db_path };
+    /// Create new SQLite history manager that encrypts clipboard content at rest
+    ///
+    /// Entries stored in plaintext before encryption was enabled are sealed on
+    /// the first encrypted open; plaintext found after that is refused. `storage`
+    /// is `None` only when encryption at rest is turned off.
+    pub fn new(db_path: PathBuf, storage: Option<EncryptedStorage>) -> ClipboardResult<Self> {
+        let manager = Self { db_path, storage };
         manager.initialize_database()?;
+        manager.migrate_plaintext()?;
         Ok(manager)
     }
     
+    /// Seal content stored before encryption was enabled, once
+    fn migrate_plaintext(&self) -> ClipboardResult<()> {
+        let conn = Connection::open(&self.db_path)
+            .map_err(|e| ClipboardError::database("open database", e))?;
+        let Some(storage) = &self.storage else {
+            // New entries are plaintext, so the next encrypted open migrates again
+            return EncryptedStorage::mark_db_sealed(&conn, HISTORY_CONTEXT, false)
+                .map_err(|e| ClipboardError::database("update sealed marker", e));
+        };
+        if EncryptedStorage::is_db_sealed(&conn, HISTORY_CONTEXT)
+            .map_err(|e| ClipboardError::database("read sealed marker", e))?
+        {
+            return Ok(());
+        }
+        
+        let rows: Vec<(String, Vec<u8>)> = {
+            let mut stmt = conn.prepare("SELECT id, content_data FROM clipboard_history")
+                .map_err(|e| ClipboardError::database("prepare statement", e))?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(|e| ClipboardError::database("query history", e))?;
+            rows.collect::<Result<_, _>>()
+                .map_err(|e| ClipboardError::database("read row", e))?
+        };
+        
+        for (id, content_data) in rows.into_iter().filter(|(_, data)| !EncryptedStorage::is_sealed(data)) {
+            let sealed = storage.seal(&content_data, HISTORY_CONTEXT)
+                .map_err(|e| ClipboardError::security(e.to_string()))?;
+            conn.execute(
+                "UPDATE clipboard_history SET content_data = ? WHERE id = ?",
+                params![sealed, id],
+            ).map_err(|e| ClipboardError::database("encrypt history entry", e))?;
+        }
+        
+        EncryptedStorage::mark_db_sealed(&conn, HISTORY_CONTEXT, true)
+            .map_err(|e| ClipboardError::database("update sealed marker", e))
+    }
+    
+    /// Entries newest first, skipping the newest `offset`
+    fn history_page(&self, limit: usize, offset: usize) -> ClipboardResult<Vec<HistoryEntry>> {
+        let conn = Connection::open(&self.db_path)
+            .map_err(|e| ClipboardError::database("open database", e))?;
+            
+        let mut stmt = conn.prepare(
+            "SELECT id, content_data, source_type, source_data, created_at, access_count, last_accessed, tags
+             FROM clipboard_history 
+             ORDER BY created_at DESC, id
+             LIMIT ? OFFSET ?"
+        ).map_err(|e| ClipboardError::database("prepare statement", e))?;
+        
+        let rows = stmt.query_map(params![limit, offset], |row| {
+            let id_str: String = row.get(0)?;
+            let content_data: Vec<u8> = row.get(1)?;
+            let source_type: String = row.get(2)?;
+            let source_data: String = row.get(3)?;
+            let created_at: i64 = row.get(4)?;
+            let access_count: u32 = row.get(5)?;
+            let last_accessed: i64 = row.get(6)?;
+            let tags_str: String = row.get(7)?;
+            
+            Ok((id_str, content_data, source_type, source_data, created_at, access_count, last_accessed, tags_str))
+        }).map_err(|e| ClipboardError::database("query history", e))?;
+        
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id_str, content_data, source_type, source_data, created_at, access_count, last_accessed, tags_str) = 
+                row.map_err(|e| ClipboardError::database("read row", e))?;
+                
+            let entry_id = id_str.parse()
+                .map_err(|_| ClipboardError::content("Invalid entry ID"))?;
+            let content = self.deserialize_content(&content_data)?;
+            let source = self.deserialize_source(&source_type, &source_data)?;
+            
+            let created_timestamp = std::time::UNIX_EPOCH + std::time::Duration::from_secs(created_at as u64);
+            let last_accessed_timestamp = std::time::UNIX_EPOCH + std::time::Duration::from_secs(last_accessed as u64);
+            
+            let tags: Vec<String> = if tags_str.is_empty() {
+                vec![]
+            } else {
+                tags_str.split(',').map(|s| s.trim().to_string()).collect()
+            };
+            
+            entries.push(HistoryEntry {
+                entry_id,
+                content,
+                source,
+                created_at: created_timestamp,
+                access_count,
+                last_accessed: last_accessed_timestamp,
+                tags,
+            });
+        }
+        
+        Ok(entries)
+    }
+    
     /// Initialize the database schema
     fn initialize_database(&self) -> ClipboardResult<()> {
         let conn = Connection::open(&self.db_path)
@@ -332,12 +452,26 @@ impl SqliteHistoryManager {
             ClipboardContent::Custom { .. } => "custom",
         };
         
-        Ok((content_type.to_string(), content_json.into_bytes()))
+        let content_data = match &self.storage {
+            Some(storage) => storage.seal(content_json.as_bytes(), HISTORY_CONTEXT)
+                .map_err(|e| ClipboardError::security(e.to_string()))?,
+            None => content_json.into_bytes(),
+        };
+        
+        Ok((content_type.to_string(), content_data))
     }
     
     /// Deserialize clipboard content from storage
     fn deserialize_content(&self, content_data: &[u8]) -> ClipboardResult<ClipboardContent> {
-        let content_json = String::from_utf8(content_data.to_vec())
+        let content_data = match &self.storage {
+            Some(storage) => storage.open(content_data, HISTORY_CONTEXT)
+                .map_err(|e| ClipboardError::security(e.to_string()))?,
+            None if EncryptedStorage::is_sealed(content_data) => {
+                return Err(ClipboardError::security("Clipboard history is encrypted but no storage key was given"));
+            }
+            None => content_data.to_vec(),
+        };
+        let content_json = String::from_utf8(content_data)
             .map_err(|_| ClipboardError::content("Invalid UTF-8 in stored content"))?;
             
         serde_json::from_str(&content_json)
@@ -423,119 +557,31 @@ impl HistoryManager for SqliteHistoryManager {
     }
     
     async fn get_history(&self, limit: usize) -> ClipboardResult<Vec<HistoryEntry>> {
-        let conn = Connection::open(&self.db_path)
-            .map_err(|e| ClipboardError::database("open database", e))?;
-            
-        let mut stmt = conn.prepare(
-            "SELECT id, content_data, source_type, source_data, created_at, access_count, last_accessed, tags
-             FROM clipboard_history 
-             ORDER BY created_at DESC 
-             LIMIT ?"
-        ).map_err(|e| ClipboardError::database("prepare statement", e))?;
-        
-        let rows = stmt.query_map(params![limit], |row| {
-            let id_str: String = row.get(0)?;
-            let content_data: Vec<u8> = row.get(1)?;
-            let source_type: String = row.get(2)?;
-            let source_data: String = row.get(3)?;
-            let created_at: i64 = row.get(4)?;
-            let access_count: u32 = row.get(5)?;
-            let last_accessed: i64 = row.get(6)?;
-            let tags_str: String = row.get(7)?;
-            
-            Ok((id_str, content_data, source_type, source_data, created_at, access_count, last_accessed, tags_str))
-        }).map_err(|e| ClipboardError::database("query history", e))?;
-        
-        let mut entries = Vec::new();
-        for row in rows {
-            let (id_str, content_data, source_type, source_data, created_at, access_count, last_accessed, tags_str) = 
-                row.map_err(|e| ClipboardError::database("read row", e))?;
-                
-            let entry_id = id_str.parse()
-                .map_err(|_| ClipboardError::content("Invalid entry ID"))?;
-            let content = self.deserialize_content(&content_data)?;
-            let source = self.deserialize_source(&source_type, &source_data)?;
-            
-            let created_timestamp = std::time::UNIX_EPOCH + std::time::Duration::from_secs(created_at as u64);
-            let last_accessed_timestamp = std::time::UNIX_EPOCH + std::time::Duration::from_secs(last_accessed as u64);
-            
-            let tags: Vec<String> = if tags_str.is_empty() {
-                vec![]
-            } else {
-                tags_str.split(',').map(|s| s.trim().to_string()).collect()
-            };
-            
-            entries.push(HistoryEntry {
-                entry_id,
-                content,
-                source,
-                created_at: created_timestamp,
-                access_count,
-                last_accessed: last_accessed_timestamp,
-                tags,
-            });
-        }
-        
-        Ok(entries)
+        self.history_page(limit, 0)
     }
     
     async fn search_history(&self, query: &str) -> ClipboardResult<Vec<HistoryEntry>> {
-        let conn = Connection::open(&self.db_path)
-            .map_err(|e| ClipboardError::database("open database", e))?;
-            
-        let mut stmt = conn.prepare(
-            "SELECT id, content_data, source_type, source_data, created_at, access_count, last_accessed, tags
-             FROM clipboard_history 
-             WHERE content_type = 'text' AND content_data LIKE ?
-             ORDER BY created_at DESC 
-             LIMIT 50"
-        ).map_err(|e| ClipboardError::database("prepare search statement", e))?;
-        
-        let search_pattern = format!("%{}%", query);
-        let rows = stmt.query_map(params![search_pattern], |row| {
-            let id_str: String = row.get(0)?;
-            let content_data: Vec<u8> = row.get(1)?;
-            let source_type: String = row.get(2)?;
-            let source_data: String = row.get(3)?;
-            let created_at: i64 = row.get(4)?;
-            let access_count: u32 = row.get(5)?;
-            let last_accessed: i64 = row.get(6)?;
-            let tags_str: String = row.get(7)?;
-            
-            Ok((id_str, content_data, source_type, source_data, created_at, access_count, last_accessed, tags_str))
-        }).map_err(|e| ClipboardError::database("search history", e))?;
-        
-        let mut entries = Vec::new();
-        for row in rows {
-            let (id_str, content_data, source_type, source_data, created_at, access_count, last_accessed, tags_str) = 
-                row.map_err(|e| ClipboardError::database("read search row", e))?;
-                
-            let entry_id = id_str.parse()
-                .map_err(|_| ClipboardError::content("Invalid entry ID"))?;
-            let content = self.deserialize_content(&content_data)?;
-            let source = self.deserialize_source(&source_type, &source_data)?;
-            
-            let created_timestamp = std::time::UNIX_EPOCH + std::time::Duration::from_secs(created_at as u64);
-            let last_accessed_timestamp = std::time::UNIX_EPOCH + std::time::Duration::from_secs(last_accessed as u64);
-            
-            let tags: Vec<String> = if tags_str.is_empty() {
-                vec![]
-            } else {
-                tags_str.split(',').map(|s| s.trim().to_string()).collect()
-            };
-            
-            entries.push(HistoryEntry {
-                entry_id,
-                content,
-                source,
-                created_at: created_timestamp,
-                access_count,
-                last_accessed: last_accessed_timestamp,
-                tags,
-            });
+        // Sealed content cannot be matched in SQL, so every store is searched
+        // by decrypting the entries; that keeps results the same either way
+        let query = query.to_ascii_lowercase();
+        let mut results = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = self.history_page(SEARCH_PAGE_SIZE, offset)?;
+            offset += page.len();
+            let last_page = page.len() < SEARCH_PAGE_SIZE;
+            
+            results.extend(page.into_iter().filter(|entry| {
+                // Case-insensitive for ASCII only, like SQLite's LIKE
+                entry.content.as_text().is_some_and(|text| text.to_ascii_lowercase().contains(&query))
+            }));
+            if last_page || results.len() >= SEARCH_LIMIT {
+                break;
+            }
         }
         
-        Ok(entries)
+        results.truncate(SEARCH_LIMIT);
+        Ok(results)
     }
     
     async fn restore_content(&self, entry_id: HistoryId) -> ClipboardResult<()> {
@@ -834,4 +880,76 @@ impl HistoryManager for SqliteHistoryManager {
         
         Ok(count as u64)
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl crate::retention::RetentionTarget for SqliteHistoryManager {
+    fn name(&self) -> &str {
+        "clipboard history"
+    }
+
+    fn category(&self) -> crate::retention::DataCategory {
+        crate::retention::DataCategory::ClipboardHistory
+    }
+
+    async fn purge_older_than(&self, cutoff: std::time::SystemTime) -> std::io::Result<usize> {
+        let cutoff = cutoff.duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let conn = Connection::open(&self.db_path)
+            .map_err(std::io::Error::other)?;
+
+        conn.execute(
+            "DELETE FROM clipboard_history WHERE created_at < ?",
+            params![cutoff],
+        ).map_err(std::io::Error::other)
+    }
+
+    async fn wipe(&self) -> std::io::Result<()> {
+        crate::retention::secure_delete(&self.db_path).await?;
+        // Deleted rows may linger in the write-ahead log next to the database
+        for suffix in ["-wal", "-journal"] {
+            let mut sidecar = self.db_path.clone().into_os_string();
+            sidecar.push(suffix);
+            crate::retention::secure_delete(std::path::Path::new(&sidecar)).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::TextContent;
+    use crate::security::storage::StorageKey;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_search_matches_the_same_with_and_without_encryption() {
+        let dir = TempDir::new().unwrap();
+        let storage = EncryptedStorage::new(StorageKey::from_bytes([9; 32]));
+        let plain = SqliteHistoryManager::new(dir.path().join("plain.db"), None).unwrap();
+        let sealed = SqliteHistoryManager::new(dir.path().join("sealed.db"), Some(storage)).unwrap();
+
+        for manager in [&plain, &sealed] {
+            for text in ["Hello World", "say HELLO", "goodbye"] {
+                let content = ClipboardContent::Text(TextContent::new(text.to_string()));
+                manager.add_to_history(content, ContentSource::Local).await.unwrap();
+            }
+        }
+
+        for query in ["hello", "WORLD", "bye", "missing"] {
+            let texts = |entries: Vec<HistoryEntry>| {
+                let mut texts: Vec<String> = entries
+                    .iter()
+                    .filter_map(|entry| entry.content.as_text().map(str::to_string))
+                    .collect();
+                texts.sort();
+                texts
+            };
+            let expected = texts(plain.search_history(query).await.unwrap());
+            assert_eq!(texts(sealed.search_history(query).await.unwrap()), expected, "{}", query);
+        }
+        assert_eq!(plain.search_history("hello").await.unwrap().len(), 2);
+    }
+}
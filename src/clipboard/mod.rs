@@ -6,6 +6,8 @@ pub mod monitor;
 pub mod sync;
 pub mod privacy;
 pub mod history;
+pub mod delivery;
+pub mod transform;
 pub mod content;
 pub mod platform;
 pub mod notification;
@@ -19,7 +21,9 @@ use std::time::SystemTime;
 use uuid::Uuid;
 
 pub use error::{ClipboardError, ClipboardResult};
-pub use security_integration::{ClipboardSecurityIntegration, SecureClipboard};
+pub use security_integration::{ClipboardSecurityIntegration, SecureClipboard, SyncAuditEvent};
+pub use delivery::{DeliveryStatus, DeliveryTracker};
+pub use transform::{TransformContext, TransformPipeline, TransformStage};
 pub use transport_integration::{ClipboardTransportIntegration, ClipboardTransport, ClipboardMessage};
 pub use api::{ClipboardSystem, ClipboardSystemConfig, ClipboardSystemBuilder, ClipboardSystemStatus};
 
@@ -64,6 +68,9 @@ pub struct ImageContent {
     pub width: u32,
     pub height: u32,
     pub compressed: bool,
+    /// Text recognized in the image, kept so it can be pasted or synced as text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr_text: Option<String>,
 }
 
 /// Text encoding types
@@ -91,6 +98,7 @@ pub enum ImageFormat {
     Bmp,
     Gif,
     Tiff,
+    WebP,
 }
 
 /// Clipboard change events
@@ -215,6 +223,15 @@ impl ClipboardContent {
             ClipboardContent::Custom { mime_type, .. } => ContentType::Custom(mime_type.clone()),
         }
     }
+    
+    /// Text form of the content, including text recognized in images
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            ClipboardContent::Text(text) => Some(&text.text),
+            ClipboardContent::Image(image) => image.ocr_text.as_deref(),
+            _ => None,
+        }
+    }
 }
 
 impl TextContent {
@@ -239,6 +256,13 @@ impl ImageContent {
             width,
             height,
             compressed: false,
+            ocr_text: None,
         }
     }
+    
+    /// Attach text recognized in the image
+    pub fn with_ocr_text(mut self, text: impl Into<String>) -> Self {
+        self.ocr_text = Some(text.into());
+        self
+    }
 }
\ No newline at end of file
@@ -7,6 +7,7 @@ use crate::clipboard::{
     ClipboardContent, ClipboardResult, ClipboardError,
     TextContent, ImageContent, ImageFormat, TextFormat, TextEncoding
 };
+use crate::clipboard::content::ClipboardCapabilities;
 use super::PlatformClipboard;
 
 /// Generic clipboard implementation using arboard
@@ -57,6 +58,7 @@ impl PlatformClipboard for GenericClipboard {
                 width: image_data.width as u32,
                 height: image_data.height as u32,
                 compressed: false,
+                ocr_text: None,
             };
             return Ok(Some(ClipboardContent::Image(content)));
         }
@@ -65,6 +67,13 @@ impl PlatformClipboard for GenericClipboard {
     }
     
     async fn set_content(&self, content: ClipboardContent) -> ClipboardResult<()> {
+        let content = match content {
+            ClipboardContent::Custom { ref mime_type, .. } if !self.capabilities().supports_mime(mime_type) => {
+                super::native_fallback(&content, &self.capabilities())?
+            }
+            other => other,
+        };
+        
         let mut clipboard = self.clipboard.lock()
             .map_err(|_| ClipboardError::internal("Failed to lock clipboard"))?;
             
@@ -83,8 +92,13 @@ impl PlatformClipboard for GenericClipboard {
             ClipboardContent::Files(_) => {
                 return Err(ClipboardError::format("File clipboard not supported in generic implementation"));
             }
-            ClipboardContent::Custom { .. } => {
-                return Err(ClipboardError::format("Custom clipboard formats not supported in generic implementation"));
+            ClipboardContent::Custom { mime_type, data } => {
+                // Only HTML and plain text get past the fallback above
+                if mime_type.starts_with("text/html") {
+                    clipboard.set_html(String::from_utf8_lossy(&data), None)?;
+                } else {
+                    clipboard.set_text(String::from_utf8_lossy(&data))?;
+                }
             }
         }
         
@@ -114,6 +128,10 @@ impl PlatformClipboard for GenericClipboard {
             .unwrap_or(false)
     }
     
+    fn capabilities(&self) -> ClipboardCapabilities {
+        ClipboardCapabilities::text_only(self.platform_name()).with_mime_type("text/html")
+    }
+    
     fn platform_name(&self) -> &'static str {
         "generic"
     }
@@ -7,6 +7,7 @@ use crate::clipboard::{
     ClipboardContent, ClipboardResult, ClipboardError,
     TextContent, ImageContent, ImageFormat, TextFormat, TextEncoding
 };
+use crate::clipboard::content::ClipboardCapabilities;
 use super::PlatformClipboard;
 
 #[cfg(target_os = "linux")]
@@ -66,9 +67,23 @@ impl LinuxClipboard {
         }
     }
     
-    #[cfg(target_os = "linux")]
     /// Read text from X11 clipboard
     fn read_x11_text() -> ClipboardResult<Option<String>> {
+        Ok(Self::read_x11_target("UTF8_STRING")?
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string()))
+    }
+    
+    /// Write text to X11 clipboard
+    fn write_x11_text(text: &str) -> ClipboardResult<()> {
+        Self::write_x11_target("UTF8_STRING", text.as_bytes())
+    }
+    
+    #[cfg(target_os = "linux")]
+    /// Read the clipboard converted to a selection target (a MIME type or
+    /// an X11 name such as `UTF8_STRING`)
+    fn read_x11_target(target: &str) -> ClipboardResult<Option<Vec<u8>>> {
+        let target_name = CString::new(target)
+            .map_err(|_| ClipboardError::format(format!("Invalid selection target {:?}", target)))?;
         unsafe {
             let display = XOpenDisplay(ptr::null());
             if display.is_null() {
@@ -76,7 +91,7 @@ impl LinuxClipboard {
             }
             
             let clipboard_atom = XInternAtom(display, b"CLIPBOARD\0".as_ptr() as *const i8, 0);
-            let utf8_atom = XInternAtom(display, b"UTF8_STRING\0".as_ptr() as *const i8, 0);
+            let target_atom = XInternAtom(display, target_name.as_ptr(), 0);
             let owner = XGetSelectionOwner(display, clipboard_atom);
             
             if owner == 0 {
@@ -87,7 +102,7 @@ impl LinuxClipboard {
             let root = XDefaultRootWindow(display);
             let property = XInternAtom(display, b"KIZUNA_CLIPBOARD\0".as_ptr() as *const i8, 0);
             
-            XConvertSelection(display, clipboard_atom, utf8_atom, property, root, 0);
+            XConvertSelection(display, clipboard_atom, target_atom, property, root, 0);
             XFlush(display);
             
             // Wait a bit for the selection to be converted
@@ -114,11 +129,11 @@ impl LinuxClipboard {
                 &mut prop,
             );
             
-            let text = if result == Success as i32 && !prop.is_null() && nitems > 0 {
+            let data = if result == Success as i32 && !prop.is_null() && nitems > 0 {
                 let slice = std::slice::from_raw_parts(prop, nitems as usize);
-                let text = String::from_utf8_lossy(slice).to_string();
+                let data = slice.to_vec();
                 XFree(prop as *mut _);
-                Some(text)
+                Some(data)
             } else {
                 None
             };
@@ -126,13 +141,15 @@ impl LinuxClipboard {
             XDeleteProperty(display, root, property);
             XCloseDisplay(display);
             
-            Ok(text)
+            Ok(data)
         }
     }
     
     #[cfg(target_os = "linux")]
-    /// Write text to X11 clipboard
-    fn write_x11_text(text: &str) -> ClipboardResult<()> {
+    /// Offer data on the clipboard under a selection target
+    fn write_x11_target(target: &str, data: &[u8]) -> ClipboardResult<()> {
+        let target_name = CString::new(target)
+            .map_err(|_| ClipboardError::format(format!("Invalid selection target {:?}", target)))?;
         unsafe {
             let display = XOpenDisplay(ptr::null());
             if display.is_null() {
@@ -140,20 +157,20 @@ impl LinuxClipboard {
             }
             
             let clipboard_atom = XInternAtom(display, b"CLIPBOARD\0".as_ptr() as *const i8, 0);
-            let utf8_atom = XInternAtom(display, b"UTF8_STRING\0".as_ptr() as *const i8, 0);
+            let target_atom = XInternAtom(display, target_name.as_ptr(), 0);
             let root = XDefaultRootWindow(display);
             let property = XInternAtom(display, b"KIZUNA_CLIPBOARD\0".as_ptr() as *const i8, 0);
             
-            // Store the text in a property
+            // Store the data in a property
             XChangeProperty(
                 display,
                 root,
                 property,
-                utf8_atom,
+                target_atom,
                 8,
                 PropModeReplace,
-                text.as_ptr(),
-                text.len() as i32,
+                data.as_ptr(),
+                data.len() as i32,
             );
             
             // Claim ownership of the clipboard
@@ -201,13 +218,30 @@ impl LinuxClipboard {
         }
     }
     
+    #[cfg(target_os = "linux")]
+    /// Write HTML to Wayland clipboard, with a plain-text alternative
+    fn write_wayland_html(html: &str) -> ClipboardResult<()> {
+        match arboard::Clipboard::new() {
+            Ok(mut clipboard) => {
+                clipboard.set_html(html, None)
+                    .map_err(|e| ClipboardError::platform(format!("Failed to set Wayland clipboard: {}", e)))
+            }
+            Err(e) => Err(ClipboardError::platform(format!("Failed to access Wayland clipboard: {}", e))),
+        }
+    }
+    
     #[cfg(not(target_os = "linux"))]
-    fn read_x11_text() -> ClipboardResult<Option<String>> {
+    fn read_x11_target(_target: &str) -> ClipboardResult<Option<Vec<u8>>> {
         Ok(None)
     }
     
     #[cfg(not(target_os = "linux"))]
-    fn write_x11_text(_text: &str) -> ClipboardResult<()> {
+    fn write_x11_target(_target: &str, _data: &[u8]) -> ClipboardResult<()> {
+        Ok(())
+    }
+    
+    #[cfg(not(target_os = "linux"))]
+    fn write_wayland_html(_html: &str) -> ClipboardResult<()> {
         Ok(())
     }
     
@@ -261,6 +295,19 @@ impl PlatformClipboard for LinuxClipboard {
             ClipboardContent::Image(_) => {
                 Err(ClipboardError::format("Image clipboard writing not yet implemented on Linux"))
             }
+            ClipboardContent::Custom { ref mime_type, ref data } => {
+                match self.backend {
+                    // X11 selections carry any target, so the MIME type is kept as-is
+                    DisplayBackend::X11 => Self::write_x11_target(mime_type, data),
+                    DisplayBackend::Wayland if mime_type.starts_with("text/html") => {
+                        Self::write_wayland_html(&String::from_utf8_lossy(data))
+                    }
+                    _ => {
+                        let fallback = super::native_fallback(&content, &self.capabilities())?;
+                        self.set_content(fallback).await
+                    }
+                }
+            }
             _ => {
                 Err(ClipboardError::format("Unsupported clipboard content type"))
             }
@@ -311,6 +358,22 @@ impl PlatformClipboard for LinuxClipboard {
             .unwrap_or(false)
     }
     
+    fn capabilities(&self) -> ClipboardCapabilities {
+        let capabilities = ClipboardCapabilities::text_only(self.platform_name());
+        match self.backend {
+            DisplayBackend::X11 => capabilities.with_arbitrary_mime_types(true),
+            DisplayBackend::Wayland => capabilities.with_mime_type("text/html"),
+            DisplayBackend::Unknown => capabilities,
+        }
+    }
+    
+    async fn get_custom(&self, mime_type: &str) -> ClipboardResult<Option<Vec<u8>>> {
+        match self.backend {
+            DisplayBackend::X11 => Self::read_x11_target(mime_type),
+            _ => Ok(None),
+        }
+    }
+    
     fn platform_name(&self) -> &'static str {
         match self.backend {
             DisplayBackend::X11 => "linux-x11",
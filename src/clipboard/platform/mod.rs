@@ -1,7 +1,8 @@
 //! Platform-specific clipboard implementations
 
 use async_trait::async_trait;
-use crate::clipboard::{ClipboardContent, ClipboardResult, Clipboard};
+use crate::clipboard::{ClipboardContent, ClipboardError, ClipboardResult, Clipboard};
+use crate::clipboard::content::{ClipboardCapabilities, FormatConverter};
 
 #[cfg(windows)]
 pub mod windows;
@@ -34,6 +35,41 @@ pub trait PlatformClipboard: Send + Sync {
     
     /// Get platform name
     fn platform_name(&self) -> &'static str;
+    
+    /// Formats this backend can hold without conversion
+    fn capabilities(&self) -> ClipboardCapabilities {
+        ClipboardCapabilities::text_only(self.platform_name())
+    }
+    
+    /// Read the clipboard in a specific custom MIME type, if the backend can
+    async fn get_custom(&self, _mime_type: &str) -> ClipboardResult<Option<Vec<u8>>> {
+        Ok(None)
+    }
+}
+
+/// Convert custom content into something a backend without native support can hold
+pub(crate) fn native_fallback(
+    content: &ClipboardContent,
+    capabilities: &ClipboardCapabilities,
+) -> ClipboardResult<ClipboardContent> {
+    let converter = FormatConverter::new();
+    let standard = match content {
+        ClipboardContent::Custom { mime_type, data } => converter.custom_fallback(mime_type, data)?,
+        other => Some(other.clone()),
+    };
+    let negotiated = match standard {
+        Some(standard) => converter.negotiate(&standard, capabilities)?,
+        None => None,
+    };
+    
+    match negotiated {
+        Some(ClipboardContent::Custom { .. }) | None => Err(ClipboardError::format(format!(
+            "{:?} has no representation on {}",
+            content.content_type(),
+            capabilities.platform
+        ))),
+        Some(fallback) => Ok(fallback),
+    }
 }
 
 /// Create platform-specific clipboard implementation
@@ -76,6 +112,16 @@ impl UnifiedClipboard {
     pub fn platform_name(&self) -> &'static str {
         self.platform_clipboard.platform_name()
     }
+    
+    /// Formats the platform clipboard can hold, as advertised to peers
+    pub fn capabilities(&self) -> ClipboardCapabilities {
+        self.platform_clipboard.capabilities()
+    }
+    
+    /// Read the clipboard in a specific custom MIME type
+    pub async fn get_custom(&self, mime_type: &str) -> ClipboardResult<Option<Vec<u8>>> {
+        self.platform_clipboard.get_custom(mime_type).await
+    }
 }
 
 #[async_trait]
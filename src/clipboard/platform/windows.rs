@@ -191,6 +191,10 @@ impl PlatformClipboard for WindowsClipboard {
                 // Image writing would require additional implementation
                 Err(ClipboardError::format("Image clipboard writing not yet implemented on Windows"))
             }
+            ClipboardContent::Custom { .. } => {
+                let fallback = super::native_fallback(&content, &self.capabilities())?;
+                self.set_content(fallback).await
+            }
             _ => {
                 Err(ClipboardError::format("Unsupported clipboard content type"))
             }
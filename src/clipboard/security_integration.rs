@@ -6,8 +6,20 @@
 use async_trait::async_trait;
 use std::sync::Arc;
 use crate::clipboard::{ClipboardContent, ClipboardResult, ClipboardError, PeerId};
-use crate::security::{Security, SecuritySystem, SessionId};
+use crate::security::{Security, SecurityEvent, SecurityEventType, SecuritySystem, SessionId};
 use crate::security::identity::PeerId as SecurityPeerId;
+use crate::security::policy::RateLimitedOperation;
+
+/// Clipboard sync outcome recorded in the audit log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncAuditEvent {
+    /// Content was delivered to and acknowledged by the peer
+    Sent,
+    /// Content from the peer was decrypted and accepted
+    Received,
+    /// Content could not be delivered to or accepted from the peer
+    Failed { reason: String },
+}
 
 /// Security integration for clipboard operations
 pub struct ClipboardSecurityIntegration {
@@ -113,6 +125,13 @@ impl ClipboardSecurityIntegration {
             )));
         }
         
+        // Refuse peers pushing faster than the clipboard push limit
+        let security_peer_id = self.to_security_peer_id(peer_id)?;
+        self.security_system
+            .check_operation_rate(&security_peer_id, RateLimitedOperation::ClipboardPush)
+            .await
+            .map_err(|e| ClipboardError::permission(format!("Clipboard push from {} refused: {}", peer_id, e)))?;
+        
         // Get or establish session
         let session_id = self.get_or_establish_session(peer_id).await?;
         
@@ -122,11 +141,52 @@ impl ClipboardSecurityIntegration {
             .await
             .map_err(|e| ClipboardError::security(format!("Failed to decrypt content: {}", e)))?;
         
-        // Deserialize content
-        let content = serde_json::from_slice(&plaintext)
-            .map_err(|e| ClipboardError::serialization("clipboard_content", e))?;
-        
-        Ok(content)
+        // Deserialize content; garbage from a trusted peer counts towards a ban
+        match serde_json::from_slice(&plaintext) {
+            Ok(content) => Ok(content),
+            Err(e) => {
+                let _ = self.security_system
+                    .report_malformed_message(&security_peer_id, "undecodable clipboard content")
+                    .await;
+                Err(ClipboardError::serialization("clipboard_content", e))
+            }
+        }
+    }
+    
+    /// Record a clipboard sync in the security audit log
+    ///
+    /// Entries carry the SHA-256 hash of the content, never the content itself.
+    /// The hash is `None` when received content could not be decrypted.
+    pub async fn record_sync(
+        &self,
+        peer_id: &PeerId,
+        event: SyncAuditEvent,
+        content_hash: Option<&str>,
+    ) -> ClipboardResult<()> {
+        let item = match content_hash {
+            Some(hash) => format!("Clipboard item sha256:{}", hash),
+            None => "Unreadable clipboard item".to_string(),
+        };
+        let (event_type, details) = match event {
+            SyncAuditEvent::Sent => (
+                SecurityEventType::ClipboardSyncSent,
+                format!("{} sent to {}", item, peer_id),
+            ),
+            SyncAuditEvent::Received => (
+                SecurityEventType::ClipboardSyncReceived,
+                format!("{} received from {}", item, peer_id),
+            ),
+            SyncAuditEvent::Failed { reason } => (
+                SecurityEventType::ClipboardSyncFailed,
+                format!("{} failed to sync with {}: {}", item, peer_id, reason),
+            ),
+        };
+        
+        let security_peer_id = self.to_security_peer_id(peer_id).ok();
+        self.security_system
+            .log_event(SecurityEvent::new(event_type, security_peer_id, details))
+            .await
+            .map_err(|e| ClipboardError::security(format!("Failed to record clipboard sync: {}", e)))
     }
     
     /// Add a peer to the trusted list for clipboard operations
@@ -361,6 +421,29 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("not trusted"));
     }
     
+    #[tokio::test]
+    async fn test_sync_audit_records_hash_only() {
+        let security_system = Arc::new(SecuritySystem::new().unwrap());
+        let integration = ClipboardSecurityIntegration::new(security_system.clone());
+        let test_peer_id = DeviceIdentity::generate().unwrap().derive_peer_id().to_hex();
+        
+        let content = ClipboardContent::Text(TextContent {
+            text: "hunter2".to_string(),
+            encoding: TextEncoding::Utf8,
+            format: TextFormat::Plain,
+            size: 7,
+        });
+        let hash = crate::clipboard::delivery::content_hash(&content).unwrap();
+        integration.record_sync(&test_peer_id, SyncAuditEvent::Sent, Some(&hash)).await.unwrap();
+        
+        let log = security_system.get_audit_log(10).await.unwrap();
+        let entry = log.iter()
+            .find(|event| matches!(event.event_type, SecurityEventType::ClipboardSyncSent))
+            .expect("clipboard sync should be audited");
+        assert!(entry.details.contains(&hash));
+        assert!(!entry.details.contains("hunter2"));
+    }
+    
     #[tokio::test]
     async fn test_trusted_peers_list() {
         let security_system = Arc::new(SecuritySystem::new().unwrap());
@@ -8,6 +8,7 @@ use crate::clipboard::{
     ClipboardContent, ClipboardResult, ClipboardError, DeviceId, PeerId, DeviceSyncStatus, ConnectionStatus
 };
 use crate::clipboard::privacy::{PrivacyPolicyManager, SyncDecision, SensitivePattern};
+use crate::metered::{Initiator, TrafficDecision, TrafficOperation, TrafficPolicy};
 
 /// Clipboard sync manager trait
 #[async_trait]
@@ -265,6 +266,8 @@ pub struct DefaultSyncManager {
     retry_config: Arc<RwLock<RetryConfig>>,
     /// Pending retry operations
     pending_retries: Arc<RwLock<Vec<PendingRetry>>>,
+    /// Restrictions for the current connection
+    traffic_policy: Arc<RwLock<TrafficPolicy>>,
 }
 
 impl DefaultSyncManager {
@@ -281,6 +284,7 @@ impl DefaultSyncManager {
             last_content: Arc::new(RwLock::new(None)),
             retry_config: Arc::new(RwLock::new(RetryConfig::default())),
             pending_retries: Arc::new(RwLock::new(Vec::new())),
+            traffic_policy: Arc::new(RwLock::new(TrafficPolicy::unrestricted(None))),
         }
     }
     
@@ -297,6 +301,7 @@ impl DefaultSyncManager {
             last_content: Arc::new(RwLock::new(None)),
             retry_config: Arc::new(RwLock::new(RetryConfig::default())),
             pending_retries: Arc::new(RwLock::new(Vec::new())),
+            traffic_policy: Arc::new(RwLock::new(TrafficPolicy::unrestricted(None))),
         }
     }
     
@@ -305,6 +310,15 @@ impl DefaultSyncManager {
         &self.privacy_manager
     }
     
+    /// Apply a new traffic policy, such as pausing image sync on metered connections
+    pub fn set_traffic_policy(&self, policy: TrafficPolicy) -> ClipboardResult<()> {
+        let mut current = self.traffic_policy.write()
+            .map_err(|_| ClipboardError::internal("Failed to acquire write lock on traffic policy"))?;
+        
+        *current = policy;
+        Ok(())
+    }
+    
     /// Get reference to violation logger
     pub fn violation_logger(&self) -> &PrivacyViolationLogger {
         &self.violation_logger
@@ -841,6 +855,18 @@ impl SyncManager for DefaultSyncManager {
     }
     
     async fn sync_content_to_peers(&self, content: ClipboardContent) -> ClipboardResult<()> {
+        // Copied images are large; leave them local on metered connections
+        if matches!(content, ClipboardContent::Image(_)) {
+            let decision = self.traffic_policy.read()
+                .map_err(|_| ClipboardError::internal("Failed to acquire read lock on traffic policy"))?
+                .check(TrafficOperation::ImageClipboardSync, Initiator::Background);
+            
+            if let TrafficDecision::Defer { reason } = decision {
+                log::debug!("Skipping clipboard sync: {}", reason);
+                return Ok(());
+            }
+        }
+        
         // Perform privacy analysis before sync
         let decision = self.analyze_content_for_sync(&content).await?;
         
@@ -956,6 +982,10 @@ impl SyncManager for DefaultSyncManager {
     }
     
     async fn receive_content_from_peer(&self, content: ClipboardContent, peer_id: PeerId) -> ClipboardResult<()> {
+        crate::lockdown::Lockdown::global()
+            .check(crate::lockdown::RemoteCapability::ClipboardWrite)
+            .map_err(|e| ClipboardError::permission(e.to_string()))?;
+
         // Check if peer is in allowlist and enabled
         if !self.is_device_enabled(&peer_id)? {
             return Err(ClipboardError::sync(
@@ -0,0 +1,648 @@
+//! Content transformation pipeline for clipboard sync
+//!
+//! Stages rewrite clipboard content on its way to a peer, e.g. stripping
+//! tracking parameters from URLs or converting paths to the receiver's
+//! conventions. Stages run in a configurable order and can be switched on or
+//! off globally or for individual peers. Local clipboard and history always
+//! keep the original content.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use regex::Regex;
+use crate::clipboard::{ClipboardContent, ClipboardResult, ClipboardError, PeerId, TextContent};
+use crate::clipboard::content::ClipboardCapabilities;
+
+/// Query parameters removed by [`StripTrackingParams`] by default; a trailing
+/// `*` matches any parameter with that prefix
+const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid",
+    "mc_cid", "mc_eid", "igshid", "_hsenc", "_hsmi", "mkt_tok", "ref_src",
+];
+
+/// Peer a clipboard item is being transformed for
+#[derive(Debug, Clone, Copy)]
+pub struct TransformContext<'a> {
+    /// Receiving peer
+    pub peer_id: &'a PeerId,
+    /// Formats the peer advertised, if known
+    pub capabilities: Option<&'a ClipboardCapabilities>,
+}
+
+impl<'a> TransformContext<'a> {
+    /// Create a context for a peer
+    pub fn new(peer_id: &'a PeerId, capabilities: Option<&'a ClipboardCapabilities>) -> Self {
+        Self { peer_id, capabilities }
+    }
+
+    /// Platform the peer reported, e.g. `windows` or `linux-wayland`
+    pub fn peer_platform(&self) -> Option<&str> {
+        self.capabilities.map(|capabilities| capabilities.platform.as_str())
+    }
+
+    /// Path and line ending conventions of the peer's platform, if known
+    pub fn peer_path_style(&self) -> Option<PathStyle> {
+        self.peer_platform().map(PathStyle::for_platform)
+    }
+}
+
+/// A single step of the transformation pipeline
+pub trait TransformStage: Send + Sync {
+    /// Stable name used to order and enable the stage
+    fn name(&self) -> &str;
+
+    /// Transform content bound for a peer, returning it unchanged when the stage does not apply
+    fn apply(&self, content: ClipboardContent, context: &TransformContext<'_>) -> ClipboardResult<ClipboardContent>;
+}
+
+/// Ordered set of transformation stages with per-peer enablement
+#[derive(Clone, Default)]
+pub struct TransformPipeline {
+    /// Stages in the order they run
+    stages: Vec<PipelineStage>,
+    /// Per-peer overrides of stage enablement, by stage name
+    peer_overrides: HashMap<PeerId, HashMap<String, bool>>,
+}
+
+#[derive(Clone)]
+struct PipelineStage {
+    stage: Arc<dyn TransformStage>,
+    enabled: bool,
+}
+
+impl TransformPipeline {
+    /// Create an empty pipeline that leaves content untouched
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pipeline with the built-in stages: tracking parameter removal, line
+    /// ending normalization and path conversion
+    pub fn standard() -> Self {
+        Self::new()
+            .with_stage(StripTrackingParams::new())
+            .with_stage(NormalizeLineEndings::new())
+            .with_stage(ConvertPaths::new())
+    }
+
+    /// Append a stage, replacing any stage with the same name in place
+    pub fn with_stage(mut self, stage: impl TransformStage + 'static) -> Self {
+        self.add_stage(Arc::new(stage));
+        self
+    }
+
+    /// Append a stage, replacing any stage with the same name in place
+    pub fn add_stage(&mut self, stage: Arc<dyn TransformStage>) {
+        match self.position(stage.name()) {
+            Some(index) => self.stages[index].stage = stage,
+            None => self.stages.push(PipelineStage { stage, enabled: true }),
+        }
+    }
+
+    /// Remove a stage, returning whether it was present
+    pub fn remove_stage(&mut self, name: &str) -> bool {
+        let before = self.stages.len();
+        self.stages.retain(|entry| entry.stage.name() != name);
+        for overrides in self.peer_overrides.values_mut() {
+            overrides.remove(name);
+        }
+        self.stages.len() != before
+    }
+
+    /// Move a stage to a new position in the run order
+    pub fn move_stage(&mut self, name: &str, index: usize) -> ClipboardResult<()> {
+        let current = self.require(name)?;
+        let entry = self.stages.remove(current);
+        self.stages.insert(index.min(self.stages.len()), entry);
+        Ok(())
+    }
+
+    /// Set the run order; every stage must be named exactly once
+    pub fn reorder(&mut self, order: &[&str]) -> ClipboardResult<()> {
+        if order.len() != self.stages.len() {
+            return Err(ClipboardError::config(
+                "transform_pipeline",
+                format!("Expected {} stages in the new order, got {}", self.stages.len(), order.len()),
+            ));
+        }
+
+        let mut remaining = self.stages.clone();
+        let mut reordered = Vec::with_capacity(order.len());
+        for name in order {
+            let index = remaining.iter()
+                .position(|entry| entry.stage.name() == *name)
+                .ok_or_else(|| ClipboardError::config(
+                    "transform_pipeline",
+                    format!("Stage '{}' is unknown or listed twice", name),
+                ))?;
+            reordered.push(remaining.remove(index));
+        }
+        self.stages = reordered;
+        Ok(())
+    }
+
+    /// Names of the stages in run order
+    pub fn stage_names(&self) -> Vec<&str> {
+        self.stages.iter().map(|entry| entry.stage.name()).collect()
+    }
+
+    /// Enable or disable a stage for all peers without an override
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> ClipboardResult<()> {
+        let index = self.require(name)?;
+        self.stages[index].enabled = enabled;
+        Ok(())
+    }
+
+    /// Enable or disable a stage for one peer, overriding the global setting
+    pub fn set_enabled_for_peer(&mut self, peer_id: &PeerId, name: &str, enabled: bool) -> ClipboardResult<()> {
+        self.require(name)?;
+        self.peer_overrides
+            .entry(peer_id.clone())
+            .or_default()
+            .insert(name.to_string(), enabled);
+        Ok(())
+    }
+
+    /// Drop a peer's overrides so it follows the global settings again
+    pub fn clear_peer_overrides(&mut self, peer_id: &PeerId) {
+        self.peer_overrides.remove(peer_id);
+    }
+
+    /// Check whether a stage runs for content bound for a peer
+    pub fn is_enabled_for(&self, name: &str, peer_id: &PeerId) -> bool {
+        let Some(index) = self.position(name) else {
+            return false;
+        };
+        self.peer_overrides
+            .get(peer_id)
+            .and_then(|overrides| overrides.get(name).copied())
+            .unwrap_or(self.stages[index].enabled)
+    }
+
+    /// Run the enabled stages over content bound for a peer
+    pub fn apply(&self, content: ClipboardContent, context: &TransformContext<'_>) -> ClipboardResult<ClipboardContent> {
+        let mut content = content;
+        for entry in &self.stages {
+            if self.is_enabled_for(entry.stage.name(), context.peer_id) {
+                content = entry.stage.apply(content, context)?;
+            }
+        }
+        Ok(content)
+    }
+
+    /// Check if no stages are configured
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.stages.iter().position(|entry| entry.stage.name() == name)
+    }
+
+    fn require(&self, name: &str) -> ClipboardResult<usize> {
+        self.position(name).ok_or_else(|| ClipboardError::config(
+            "transform_pipeline",
+            format!("No stage named '{}'", name),
+        ))
+    }
+}
+
+impl std::fmt::Debug for TransformPipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransformPipeline")
+            .field("stages", &self.stage_names())
+            .field("peer_overrides", &self.peer_overrides)
+            .finish()
+    }
+}
+
+/// Path and line ending conventions of an operating system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStyle {
+    /// `/` separators and `\n` line endings
+    Unix,
+    /// `\` separators and `\r\n` line endings
+    Windows,
+}
+
+impl PathStyle {
+    /// Conventions for a platform name reported in clipboard capabilities
+    pub fn for_platform(platform: &str) -> Self {
+        if platform.starts_with("windows") {
+            PathStyle::Windows
+        } else {
+            PathStyle::Unix
+        }
+    }
+
+    /// Conventions of the local platform
+    pub fn local() -> Self {
+        if cfg!(windows) { PathStyle::Windows } else { PathStyle::Unix }
+    }
+
+    /// Guess the style of an absolute path, `None` when it is not one
+    fn detect(path: &str) -> Option<Self> {
+        let bytes = path.as_bytes();
+        let drive = bytes.len() >= 3
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && (bytes[2] == b'\\' || bytes[2] == b'/');
+        if drive || path.starts_with("\\\\") {
+            Some(PathStyle::Windows)
+        } else if path.starts_with('/') || path.starts_with("~/") {
+            Some(PathStyle::Unix)
+        } else {
+            None
+        }
+    }
+
+    fn separator(self) -> char {
+        match self {
+            PathStyle::Unix => '/',
+            PathStyle::Windows => '\\',
+        }
+    }
+}
+
+/// Removes tracking query parameters such as `utm_source` from URLs in text
+pub struct StripTrackingParams {
+    params: Vec<String>,
+    url_pattern: Regex,
+}
+
+impl StripTrackingParams {
+    /// Stage removing the common ad and analytics parameters
+    pub fn new() -> Self {
+        Self::with_params(DEFAULT_TRACKING_PARAMS.iter().map(|param| param.to_string()).collect())
+    }
+
+    /// Stage removing exactly these parameters; a trailing `*` matches a prefix
+    pub fn with_params(params: Vec<String>) -> Self {
+        Self {
+            params,
+            url_pattern: Regex::new(r#"(?i)\bhttps?://[^\s<>"'`]+"#).expect("valid URL pattern"),
+        }
+    }
+
+    /// Also remove this parameter
+    pub fn with_param(mut self, param: impl Into<String>) -> Self {
+        self.params.push(param.into());
+        self
+    }
+
+    fn is_tracking(&self, key: &str) -> bool {
+        self.params.iter().any(|param| match param.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == param,
+        })
+    }
+
+    /// Strip tracking parameters from a single URL
+    pub fn clean_url(&self, url: &str) -> String {
+        let (rest, fragment) = match url.find('#') {
+            Some(index) => url.split_at(index),
+            None => (url, ""),
+        };
+        let Some((base, query)) = rest.split_once('?') else {
+            return url.to_string();
+        };
+
+        // HTML clipboard content escapes separators as `&amp;`
+        let separator = if query.contains("&amp;") { "&amp;" } else { "&" };
+        let kept: Vec<&str> = query
+            .split(separator)
+            .filter(|pair| !pair.is_empty())
+            .filter(|pair| !self.is_tracking(pair.split('=').next().unwrap_or(pair)))
+            .collect();
+        if kept.is_empty() {
+            format!("{}{}", base, fragment)
+        } else {
+            format!("{}?{}{}", base, kept.join(separator), fragment)
+        }
+    }
+}
+
+impl Default for StripTrackingParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransformStage for StripTrackingParams {
+    fn name(&self) -> &str {
+        "strip_tracking_params"
+    }
+
+    fn apply(&self, content: ClipboardContent, _context: &TransformContext<'_>) -> ClipboardResult<ClipboardContent> {
+        Ok(map_text(content, |text| {
+            self.url_pattern
+                .replace_all(text, |captures: &regex::Captures<'_>| self.clean_url(&captures[0]))
+                .into_owned()
+        }))
+    }
+}
+
+/// Rewrites line endings to the receiver's convention
+pub struct NormalizeLineEndings {
+    /// Fixed target style; `None` follows the peer's platform
+    target: Option<PathStyle>,
+}
+
+impl NormalizeLineEndings {
+    /// Stage following the peer's platform
+    pub fn new() -> Self {
+        Self { target: None }
+    }
+
+    /// Stage always producing this style's line endings
+    pub fn to_style(style: PathStyle) -> Self {
+        Self { target: Some(style) }
+    }
+}
+
+impl Default for NormalizeLineEndings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransformStage for NormalizeLineEndings {
+    fn name(&self) -> &str {
+        "normalize_line_endings"
+    }
+
+    fn apply(&self, content: ClipboardContent, context: &TransformContext<'_>) -> ClipboardResult<ClipboardContent> {
+        let Some(style) = self.target.or_else(|| context.peer_path_style()) else {
+            return Ok(content);
+        };
+        Ok(map_text(content, |text| {
+            let unix = text.replace("\r\n", "\n").replace('\r', "\n");
+            match style {
+                PathStyle::Unix => unix,
+                PathStyle::Windows => unix.replace('\n', "\r\n"),
+            }
+        }))
+    }
+}
+
+/// Equivalent directories on Windows and Unix devices, e.g. `C:\Users\me` and `/home/me`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PathMapping {
+    pub windows: String,
+    pub unix: String,
+}
+
+impl PathMapping {
+    /// Map a Windows directory to a Unix one
+    pub fn new(windows: impl Into<String>, unix: impl Into<String>) -> Self {
+        Self {
+            windows: windows.into(),
+            unix: unix.into(),
+        }
+    }
+
+    fn root(&self, style: PathStyle) -> &str {
+        match style {
+            PathStyle::Windows => &self.windows,
+            PathStyle::Unix => &self.unix,
+        }
+    }
+
+    /// Length of the prefix of `path` covered by this mapping's `style` root
+    fn matched_len(&self, path: &str, style: PathStyle) -> Option<usize> {
+        let root = self.root(style).trim_end_matches(['/', '\\']);
+        if root.is_empty() || path.len() < root.len() || !path.is_char_boundary(root.len()) {
+            return None;
+        }
+        let (head, tail) = path.split_at(root.len());
+        let same = match style {
+            // Windows paths are case-insensitive and accept either separator
+            PathStyle::Windows => head.replace('/', "\\").eq_ignore_ascii_case(&root.replace('/', "\\")),
+            PathStyle::Unix => head == root,
+        };
+        let boundary = tail.is_empty() || tail.starts_with(['/', '\\']);
+        (same && boundary).then_some(root.len())
+    }
+}
+
+/// Converts file paths between Unix and Windows conventions
+///
+/// Separators are always rewritten; configured [`PathMapping`]s also
+/// translate the root, so `C:\Users\me\src` becomes `/home/me/src`. Applies
+/// to file lists, to text that is a single absolute path, and to mapped
+/// paths inside longer snippets such as shell commands.
+pub struct ConvertPaths {
+    /// Fixed target style; `None` follows the peer's platform
+    target: Option<PathStyle>,
+    /// Directory roots translated between platforms
+    mappings: Vec<PathMapping>,
+    /// Candidate path tokens inside multi-word text
+    token_pattern: Regex,
+}
+
+impl ConvertPaths {
+    /// Stage following the peer's platform
+    pub fn new() -> Self {
+        Self::with_mappings(Vec::new())
+    }
+
+    /// Stage always producing paths in this style
+    pub fn to_style(style: PathStyle) -> Self {
+        Self {
+            target: Some(style),
+            ..Self::new()
+        }
+    }
+
+    /// Stage following the peer's platform and translating these roots
+    pub fn with_mappings(mappings: Vec<PathMapping>) -> Self {
+        Self {
+            target: None,
+            mappings,
+            token_pattern: Regex::new(r#"[^\s"'`<>|;]+"#).expect("valid path token pattern"),
+        }
+    }
+
+    /// Also translate this root
+    pub fn with_mapping(mut self, mapping: PathMapping) -> Self {
+        self.mappings.push(mapping);
+        self
+    }
+
+    /// Convert one path, leaving it alone if it is not absolute or already matches
+    pub fn convert(&self, path: &str, target: PathStyle) -> Option<String> {
+        let source = PathStyle::detect(path)?;
+        if source == target {
+            return None;
+        }
+        let separators = |rest: &str| rest
+            .replace(['/', '\\'], &target.separator().to_string());
+
+        // Longest mapped root wins so nested mappings behave predictably
+        let mapped = self.mappings.iter()
+            .filter_map(|mapping| mapping.matched_len(path, source).map(|len| (len, mapping)))
+            .max_by_key(|(len, _)| *len);
+        match mapped {
+            Some((len, mapping)) => {
+                let root = mapping.root(target).trim_end_matches(['/', '\\']);
+                Some(format!("{}{}", root, separators(&path[len..])))
+            }
+            None => Some(path.replace(source.separator(), &target.separator().to_string())),
+        }
+    }
+
+    /// Convert a path only if a mapping covers it
+    fn convert_mapped(&self, path: &str, target: PathStyle) -> Option<String> {
+        let source = PathStyle::detect(path)?;
+        self.mappings.iter()
+            .any(|mapping| mapping.matched_len(path, source).is_some())
+            .then(|| self.convert(path, target))
+            .flatten()
+    }
+
+    fn convert_text(&self, text: &str, target: PathStyle) -> String {
+        let trimmed = text.trim();
+        if !trimmed.contains('\n') && PathStyle::detect(trimmed).is_some() {
+            return self.convert(trimmed, target).unwrap_or_else(|| text.to_string());
+        }
+
+        // Inside snippets only mapped paths are rewritten; anything else that
+        // merely starts with a slash may be an option or a URL path
+        self.token_pattern
+            .replace_all(text, |captures: &regex::Captures<'_>| {
+                self.convert_mapped(&captures[0], target).unwrap_or_else(|| captures[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+impl Default for ConvertPaths {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransformStage for ConvertPaths {
+    fn name(&self) -> &str {
+        "convert_paths"
+    }
+
+    fn apply(&self, content: ClipboardContent, context: &TransformContext<'_>) -> ClipboardResult<ClipboardContent> {
+        let Some(style) = self.target.or_else(|| context.peer_path_style()) else {
+            return Ok(content);
+        };
+        Ok(match content {
+            ClipboardContent::Files(files) => ClipboardContent::Files(
+                files.into_iter()
+                    .map(|path| self.convert(&path, style).unwrap_or(path))
+                    .collect(),
+            ),
+            content => map_text(content, |text| self.convert_text(text, style)),
+        })
+    }
+}
+
+/// Apply a rewrite to text content, keeping the size in step
+fn map_text(content: ClipboardContent, rewrite: impl FnOnce(&str) -> String) -> ClipboardContent {
+    match content {
+        ClipboardContent::Text(text) => {
+            let rewritten = rewrite(&text.text);
+            ClipboardContent::Text(TextContent {
+                size: rewritten.len(),
+                text: rewritten,
+                ..text
+            })
+        }
+        content => content,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::{TextEncoding, TextFormat};
+
+    fn text(value: &str) -> ClipboardContent {
+        ClipboardContent::Text(TextContent {
+            text: value.to_string(),
+            encoding: TextEncoding::Utf8,
+            format: TextFormat::Plain,
+            size: value.len(),
+        })
+    }
+
+    fn as_text(content: ClipboardContent) -> String {
+        match content {
+            ClipboardContent::Text(text) => text.text,
+            other => panic!("Expected text, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_strip_tracking_params() {
+        let stage = StripTrackingParams::new();
+        assert_eq!(
+            stage.clean_url("https://example.com/a?id=7&utm_source=news&fbclid=x#top"),
+            "https://example.com/a?id=7#top",
+        );
+        assert_eq!(stage.clean_url("https://example.com/?utm_medium=mail"), "https://example.com/");
+
+        let peer = "peer".to_string();
+        let context = TransformContext::new(&peer, None);
+        let cleaned = stage.apply(text("see https://example.com/?gclid=1&q=rust now"), &context).unwrap();
+        assert_eq!(as_text(cleaned), "see https://example.com/?q=rust now");
+    }
+
+    #[test]
+    fn test_paths_and_line_endings_follow_peer_platform() {
+        let peer = "peer".to_string();
+        let windows = ClipboardCapabilities::text_only("windows");
+        let context = TransformContext::new(&peer, Some(&windows));
+
+        let files = ConvertPaths::new()
+            .apply(ClipboardContent::Files(vec!["/home/me/a.txt".to_string(), "notes.txt".to_string()]), &context)
+            .unwrap();
+        assert_eq!(files, ClipboardContent::Files(vec!["\\home\\me\\a.txt".to_string(), "notes.txt".to_string()]));
+
+        let lines = NormalizeLineEndings::new().apply(text("a\nb\r\nc"), &context).unwrap();
+        assert_eq!(as_text(lines), "a\r\nb\r\nc");
+
+        let unknown = TransformContext::new(&peer, None);
+        let untouched = ConvertPaths::new().apply(text("C:\\Users\\me"), &unknown).unwrap();
+        assert_eq!(as_text(untouched), "C:\\Users\\me");
+    }
+
+    #[test]
+    fn test_mapped_paths_translate_roots() {
+        let stage = ConvertPaths::new().with_mapping(PathMapping::new("C:\\Users\\me", "/home/me"));
+        assert_eq!(stage.convert("c:/users/me/src/main.rs", PathStyle::Unix).unwrap(), "/home/me/src/main.rs");
+        assert_eq!(stage.convert("/home/me/notes", PathStyle::Windows).unwrap(), "C:\\Users\\me\\notes");
+        assert_eq!(stage.convert("/home/meg", PathStyle::Windows).unwrap(), "\\home\\meg");
+
+        let peer = "peer".to_string();
+        let linux = ClipboardCapabilities::text_only("linux-x11");
+        let context = TransformContext::new(&peer, Some(&linux));
+        let snippet = stage.apply(text("cd C:\\Users\\me\\proj && cargo build\r\n/opt/tool"), &context).unwrap();
+        assert_eq!(as_text(snippet), "cd /home/me/proj && cargo build\r\n/opt/tool");
+    }
+
+    #[test]
+    fn test_pipeline_order_and_per_peer_enablement() {
+        let mut pipeline = TransformPipeline::standard();
+        assert_eq!(pipeline.stage_names(), vec!["strip_tracking_params", "normalize_line_endings", "convert_paths"]);
+
+        pipeline.move_stage("convert_paths", 0).unwrap();
+        assert_eq!(pipeline.stage_names()[0], "convert_paths");
+        assert!(pipeline.reorder(&["convert_paths", "convert_paths", "strip_tracking_params"]).is_err());
+
+        let phone = "phone".to_string();
+        let laptop = "laptop".to_string();
+        pipeline.set_enabled_for_peer(&phone, "strip_tracking_params", false).unwrap();
+        assert!(pipeline.set_enabled_for_peer(&phone, "missing", false).is_err());
+
+        let url = "https://example.com/?utm_source=x";
+        let for_phone = pipeline.apply(text(url), &TransformContext::new(&phone, None)).unwrap();
+        let for_laptop = pipeline.apply(text(url), &TransformContext::new(&laptop, None)).unwrap();
+        assert_eq!(as_text(for_phone), url);
+        assert_eq!(as_text(for_laptop), "https://example.com/");
+    }
+}
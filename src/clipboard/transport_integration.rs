@@ -1,14 +1,22 @@
 //! Transport layer integration for clipboard synchronization
 //! 
 //! Provides peer communication, connection management, and optimized
-//! content transmission for clipboard operations.
+//! content transmission for clipboard operations. Content only leaves or
+//! enters this layer through an authenticated security session with the
+//! peer, and every sync is recorded in the audit log by content hash.
 
 use async_trait::async_trait;
 use std::sync::Arc;
 use std::collections::HashMap;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, Mutex, RwLock};
 use crate::clipboard::{ClipboardContent, ClipboardResult, ClipboardError, PeerId, DeviceId};
-use crate::transport::{KizunaTransport, ConnectionHandle, PeerAddress, TransportCapabilities};
+use crate::clipboard::content::ClipboardCapabilities;
+use crate::clipboard::delivery::content_hash;
+use crate::clipboard::security_integration::{ClipboardSecurityIntegration, SyncAuditEvent};
+use crate::transport::{
+    KizunaTransport, ConnectionProvider, DeliveryMode, MuxStream, PeerAddress, ProviderConfig,
+    TransportCapabilities, CHANNEL_CLIPBOARD,
+};
 
 /// Message types for clipboard synchronization protocol
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -55,74 +63,195 @@ pub enum ClipboardMessage {
         /// Pong timestamp
         pong_timestamp: u64,
     },
+    /// Formats the sender's clipboard can hold, sent when a stream opens
+    Capabilities {
+        capabilities: ClipboardCapabilities,
+    },
 }
 
 /// Transport integration for clipboard operations
 pub struct ClipboardTransportIntegration {
-    /// Transport system for peer communication
-    transport: Arc<KizunaTransport>,
-    /// Active connections by peer ID
-    connections: Arc<RwLock<HashMap<PeerId, ConnectionHandle>>>,
+    /// Shared provider of multiplexed peer sessions
+    provider: Arc<ConnectionProvider>,
+    /// Authenticated sessions used to seal content for each peer
+    security: Arc<ClipboardSecurityIntegration>,
+    /// Clipboard streams by peer ID
+    connections: Arc<RwLock<HashMap<PeerId, Arc<MuxStream>>>>,
     /// Pending acknowledgments by sequence number
     pending_acks: Arc<RwLock<HashMap<u64, tokio::sync::oneshot::Sender<bool>>>>,
     /// Next sequence number for outgoing messages
     next_sequence: Arc<RwLock<u64>>,
     /// Message size limit for optimization (default 64KB)
     max_message_size: usize,
+    /// Delivery for small latency-sensitive messages (sync events, acks, pings)
+    event_delivery: DeliveryMode,
+    /// Clipboard datagrams received from each peer's session
+    event_subscriptions: Arc<RwLock<HashMap<PeerId, Arc<Mutex<mpsc::Receiver<Vec<u8>>>>>>>,
 }
 
 impl ClipboardTransportIntegration {
-    /// Create new transport integration with provided transport system
-    pub fn new(transport: Arc<KizunaTransport>) -> Self {
-        Self {
-            transport,
-            connections: Arc::new(RwLock::new(HashMap::new())),
-            pending_acks: Arc::new(RwLock::new(HashMap::new())),
-            next_sequence: Arc::new(RwLock::new(0)),
-            max_message_size: 65536, // 64KB default
-        }
+    /// Create new transport integration with provided transport and security systems
+    pub fn new(transport: Arc<KizunaTransport>, security: Arc<ClipboardSecurityIntegration>) -> Self {
+        Self::with_max_message_size(transport, security, 65536) // 64KB default
     }
     
     /// Create with custom message size limit
-    pub fn with_max_message_size(transport: Arc<KizunaTransport>, max_size: usize) -> Self {
+    pub fn with_max_message_size(
+        transport: Arc<KizunaTransport>,
+        security: Arc<ClipboardSecurityIntegration>,
+        max_size: usize,
+    ) -> Self {
+        let provider = Arc::new(ConnectionProvider::new(transport, ProviderConfig::default()));
+        let mut integration = Self::with_provider(provider, security);
+        integration.max_message_size = max_size;
+        integration
+    }
+    
+    /// Create over a connection provider shared with other subsystems
+    pub fn with_provider(provider: Arc<ConnectionProvider>, security: Arc<ClipboardSecurityIntegration>) -> Self {
         Self {
-            transport,
+            provider,
+            security,
             connections: Arc::new(RwLock::new(HashMap::new())),
             pending_acks: Arc::new(RwLock::new(HashMap::new())),
             next_sequence: Arc::new(RwLock::new(0)),
-            max_message_size: max_size,
+            max_message_size: 65536,
+            event_delivery: DeliveryMode::Reliable,
+            event_subscriptions: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+    
+    /// Connection provider used by this integration
+    pub fn provider(&self) -> &Arc<ConnectionProvider> {
+        &self.provider
+    }
+    
+    /// Security integration sealing content for peers
+    pub fn security(&self) -> &Arc<ClipboardSecurityIntegration> {
+        &self.security
+    }
+    
+    /// Attach a clipboard stream opened by a remote peer
+    pub async fn attach_stream(&self, peer_id: PeerId, stream: MuxStream) -> ClipboardResult<()> {
+        if stream.channel() != CHANNEL_CLIPBOARD {
+            return Err(ClipboardError::sync(
+                "attach_stream",
+                format!("Stream channel {} is not a clipboard stream", stream.channel()),
+            ));
         }
+        
+        self.subscribe_events(&peer_id).await;
+        let mut connections = self.connections.write().await;
+        connections.insert(peer_id, Arc::new(stream));
+        Ok(())
     }
     
-    /// Get or establish connection to a peer
-    pub async fn get_or_connect(&self, peer_id: &PeerId, peer_address: &PeerAddress) -> ClipboardResult<ConnectionHandle> {
-        // Check if we already have an active connection
+    /// Get or establish a clipboard stream to a peer
+    pub async fn get_or_connect(&self, peer_id: &PeerId, peer_address: &PeerAddress) -> ClipboardResult<Arc<MuxStream>> {
+        // Check if we already have an open stream
         {
             let connections = self.connections.read().await;
             if let Some(handle) = connections.get(peer_id) {
-                if handle.is_connected().await {
-                    return Ok(handle.clone());
+                if handle.is_open() {
+                    return Ok(Arc::clone(handle));
                 }
             }
         }
         
-        // Establish new connection
-        let handle = self.transport
-            .connect_to_peer(peer_address)
+        // Open a stream on the peer's shared session
+        let handle = self.provider
+            .open_stream(peer_address, CHANNEL_CLIPBOARD)
             .await
+            .map(Arc::new)
             .map_err(|e| ClipboardError::sync("connect_to_peer", format!("Failed to connect: {}", e)))?;
         
-        // Store connection
+        // Store stream
         {
             let mut connections = self.connections.write().await;
-            connections.insert(peer_id.clone(), handle.clone());
+            connections.insert(peer_id.clone(), Arc::clone(&handle));
         }
+        self.subscribe_events(peer_id).await;
         
         Ok(handle)
     }
     
-    /// Send encrypted clipboard content to a peer
+    /// Start listening for clipboard datagrams on the peer's session
+    async fn subscribe_events(&self, peer_id: &PeerId) {
+        let Some(session) = self.provider.existing_session(peer_id) else {
+            return;
+        };
+        let mut subscriptions = self.event_subscriptions.write().await;
+        if subscriptions.contains_key(peer_id) {
+            return;
+        }
+        if let Some(rx) = session.subscribe_datagrams(CHANNEL_CLIPBOARD) {
+            subscriptions.insert(peer_id.clone(), Arc::new(Mutex::new(rx)));
+        }
+    }
+    
+    /// Send a latency-sensitive message, as a datagram when configured and possible
+    ///
+    /// Falls back to the reliable stream when the session has no datagram support,
+    /// the message is too large, or the datagram cannot be sent.
+    async fn send_event(&self, peer_id: &PeerId, handle: &MuxStream, message_bytes: &[u8]) -> Result<(), crate::transport::TransportError> {
+        if let Some(session) = self.provider.existing_session(peer_id) {
+            let max = session.max_datagram_payload(CHANNEL_CLIPBOARD);
+            if self.event_delivery.use_datagram(message_bytes.len(), max)
+                && session.send_datagram(CHANNEL_CLIPBOARD, message_bytes).await.is_ok()
+            {
+                return Ok(());
+            }
+        }
+        handle.send_message(message_bytes).await
+    }
+    
+    /// Send clipboard content to a peer and wait for its acknowledgment
+    ///
+    /// The content is encrypted under the authenticated session with the peer;
+    /// untrusted peers are refused before anything is sent. The outcome is
+    /// recorded in the audit log by content hash.
     pub async fn send_content(
+        &self,
+        peer_id: &PeerId,
+        peer_address: &PeerAddress,
+        content: &ClipboardContent,
+    ) -> ClipboardResult<()> {
+        let hash = content_hash(content)?;
+        let result = match self.security.encrypt_content(peer_id, content).await {
+            Ok(encrypted_content) => self.send_sealed(peer_id, peer_address, encrypted_content).await,
+            Err(e) => Err(e),
+        };
+        
+        let event = match &result {
+            Ok(()) => SyncAuditEvent::Sent,
+            Err(e) => SyncAuditEvent::Failed { reason: e.to_string() },
+        };
+        if let Err(e) = self.security.record_sync(peer_id, event, Some(&hash)).await {
+            log::warn!("Failed to audit clipboard sync to {}: {}", peer_id, e);
+        }
+        
+        result
+    }
+    
+    /// Decrypt clipboard content a peer sent over its authenticated session
+    ///
+    /// The outcome is recorded in the audit log by content hash.
+    pub async fn open_content(&self, peer_id: &PeerId, encrypted_content: &[u8]) -> ClipboardResult<ClipboardContent> {
+        let result = self.security.decrypt_content(peer_id, encrypted_content).await;
+        
+        let (event, hash) = match &result {
+            Ok(content) => (SyncAuditEvent::Received, content_hash(content).ok()),
+            Err(e) => (SyncAuditEvent::Failed { reason: e.to_string() }, None),
+        };
+        if let Err(e) = self.security.record_sync(peer_id, event, hash.as_deref()).await {
+            log::warn!("Failed to audit clipboard sync from {}: {}", peer_id, e);
+        }
+        
+        result
+    }
+    
+    /// Send already-sealed content and wait for the peer's acknowledgment
+    async fn send_sealed(
         &self,
         peer_id: &PeerId,
         peer_address: &PeerAddress,
@@ -173,16 +302,10 @@ impl ClipboardTransportIntegration {
         }
         
         // Send message
-        handle
-            .write(&message_bytes)
+        self.send_event(peer_id, &handle, &message_bytes)
             .await
             .map_err(|e| ClipboardError::sync("send_content", format!("Failed to send: {}", e)))?;
         
-        handle
-            .flush()
-            .await
-            .map_err(|e| ClipboardError::sync("send_content", format!("Failed to flush: {}", e)))?;
-        
         // Wait for acknowledgment with timeout
         match tokio::time::timeout(std::time::Duration::from_secs(10), rx).await {
             Ok(Ok(success)) => {
@@ -199,25 +322,53 @@ impl ClipboardTransportIntegration {
     
     /// Receive and process clipboard messages from peers
     pub async fn receive_message(&self, peer_id: &PeerId) -> ClipboardResult<Option<ClipboardMessage>> {
-        // Get connection
-        let connections = self.connections.read().await;
-        let handle = connections
-            .get(peer_id)
-            .ok_or_else(|| ClipboardError::sync("receive_message", format!("No connection to peer {}", peer_id)))?;
+        // Get stream without holding the map lock while waiting
+        let handle = {
+            let connections = self.connections.read().await;
+            connections
+                .get(peer_id)
+                .cloned()
+                .ok_or_else(|| ClipboardError::sync("receive_message", format!("No connection to peer {}", peer_id)))?
+        };
         
         // Read message
-        let mut buffer = vec![0u8; self.max_message_size];
-        let bytes_read = handle
-            .read(&mut buffer)
+        let message_bytes = match handle
+            .recv_message(self.max_message_size)
             .await
-            .map_err(|e| ClipboardError::sync("receive_message", format!("Failed to read: {}", e)))?;
+            .map_err(|e| ClipboardError::sync("receive_message", format!("Failed to read: {}", e)))?
+        {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        
+        // Deserialize message
+        let message: ClipboardMessage = serde_json::from_slice(&message_bytes)
+            .map_err(|e| ClipboardError::serialization("clipboard_message", e))?;
         
-        if bytes_read == 0 {
+        Ok(Some(message))
+    }
+    
+    /// Receive the next clipboard message a peer sent as a datagram
+    ///
+    /// Datagrams bypass the clipboard stream, so callers using unreliable delivery
+    /// should poll this alongside [`receive_message`](Self::receive_message). Returns
+    /// `None` once the peer's session has ended.
+    pub async fn receive_event(&self, peer_id: &PeerId) -> ClipboardResult<Option<ClipboardMessage>> {
+        self.subscribe_events(peer_id).await;
+        let subscription = {
+            let subscriptions = self.event_subscriptions.read().await;
+            subscriptions
+                .get(peer_id)
+                .cloned()
+                .ok_or_else(|| ClipboardError::sync("receive_event", format!("No datagram session with peer {}", peer_id)))?
+        };
+        
+        let Some(message_bytes) = subscription.lock().await.recv().await else {
+            self.event_subscriptions.write().await.remove(peer_id);
             return Ok(None);
-        }
+        };
         
-        // Deserialize message
-        let message: ClipboardMessage = serde_json::from_slice(&buffer[..bytes_read])
+        let message: ClipboardMessage = serde_json::from_slice(&message_bytes)
             .map_err(|e| ClipboardError::serialization("clipboard_message", e))?;
         
         Ok(Some(message))
@@ -248,16 +399,10 @@ impl ClipboardTransportIntegration {
         let message_bytes = serde_json::to_vec(&message)
             .map_err(|e| ClipboardError::serialization("clipboard_message", e))?;
         
-        handle
-            .write(&message_bytes)
+        self.send_event(peer_id, handle, &message_bytes)
             .await
             .map_err(|e| ClipboardError::sync("send_ack", format!("Failed to send: {}", e)))?;
         
-        handle
-            .flush()
-            .await
-            .map_err(|e| ClipboardError::sync("send_ack", format!("Failed to flush: {}", e)))?;
-        
         Ok(())
     }
     
@@ -291,15 +436,10 @@ impl ClipboardTransportIntegration {
             .map_err(|e| ClipboardError::serialization("clipboard_message", e))?;
         
         handle
-            .write(&message_bytes)
+            .send_message(&message_bytes)
             .await
             .map_err(|e| ClipboardError::sync("request_content", format!("Failed to send: {}", e)))?;
         
-        handle
-            .flush()
-            .await
-            .map_err(|e| ClipboardError::sync("request_content", format!("Failed to flush: {}", e)))?;
-        
         // Wait for response with timeout
         let timeout = tokio::time::timeout(
             std::time::Duration::from_secs(5),
@@ -360,15 +500,10 @@ impl ClipboardTransportIntegration {
             .map_err(|e| ClipboardError::serialization("clipboard_message", e))?;
         
         handle
-            .write(&message_bytes)
+            .send_message(&message_bytes)
             .await
             .map_err(|e| ClipboardError::sync("send_content_response", format!("Failed to send: {}", e)))?;
         
-        handle
-            .flush()
-            .await
-            .map_err(|e| ClipboardError::sync("send_content_response", format!("Failed to flush: {}", e)))?;
-        
         Ok(())
     }
     
@@ -392,16 +527,10 @@ impl ClipboardTransportIntegration {
         let message_bytes = serde_json::to_vec(&message)
             .map_err(|e| ClipboardError::serialization("clipboard_message", e))?;
         
-        handle
-            .write(&message_bytes)
+        self.send_event(peer_id, handle, &message_bytes)
             .await
             .map_err(|e| ClipboardError::sync("send_ping", format!("Failed to send: {}", e)))?;
         
-        handle
-            .flush()
-            .await
-            .map_err(|e| ClipboardError::sync("send_ping", format!("Failed to flush: {}", e)))?;
-        
         Ok(timestamp)
     }
     
@@ -428,15 +557,31 @@ impl ClipboardTransportIntegration {
         let message_bytes = serde_json::to_vec(&message)
             .map_err(|e| ClipboardError::serialization("clipboard_message", e))?;
         
-        handle
-            .write(&message_bytes)
+        self.send_event(peer_id, handle, &message_bytes)
             .await
             .map_err(|e| ClipboardError::sync("send_pong", format!("Failed to send: {}", e)))?;
         
+        Ok(())
+    }
+    
+    /// Advertise the formats the local clipboard can hold
+    pub async fn send_capabilities(&self, peer_id: &PeerId, capabilities: ClipboardCapabilities) -> ClipboardResult<()> {
+        // Get connection
+        let connections = self.connections.read().await;
+        let handle = connections
+            .get(peer_id)
+            .ok_or_else(|| ClipboardError::sync("send_capabilities", format!("No connection to peer {}", peer_id)))?;
+        
+        let message = ClipboardMessage::Capabilities { capabilities };
+        
+        // Serialize and send
+        let message_bytes = serde_json::to_vec(&message)
+            .map_err(|e| ClipboardError::serialization("clipboard_message", e))?;
+        
         handle
-            .flush()
+            .send_message(&message_bytes)
             .await
-            .map_err(|e| ClipboardError::sync("send_pong", format!("Failed to flush: {}", e)))?;
+            .map_err(|e| ClipboardError::sync("send_capabilities", format!("Failed to send: {}", e)))?;
         
         Ok(())
     }
@@ -447,7 +592,6 @@ impl ClipboardTransportIntegration {
         if let Some(handle) = connections.remove(peer_id) {
             handle
                 .close()
-                .await
                 .map_err(|e| ClipboardError::sync("disconnect", format!("Failed to close connection: {}", e)))?;
         }
         Ok(())
@@ -457,8 +601,9 @@ impl ClipboardTransportIntegration {
     pub async fn disconnect_all(&self) -> ClipboardResult<()> {
         let mut connections = self.connections.write().await;
         for (_, handle) in connections.drain() {
-            let _ = handle.close().await;
+            let _ = handle.close();
         }
+        self.event_subscriptions.write().await.clear();
         Ok(())
     }
     
@@ -472,7 +617,7 @@ impl ClipboardTransportIntegration {
     pub async fn is_connected(&self, peer_id: &PeerId) -> bool {
         let connections = self.connections.read().await;
         if let Some(handle) = connections.get(peer_id) {
-            handle.is_connected().await
+            handle.is_open()
         } else {
             false
         }
@@ -493,17 +638,31 @@ impl ClipboardTransportIntegration {
     pub fn set_max_message_size(&mut self, size: usize) {
         self.max_message_size = size;
     }
+    
+    /// Delivery used for sync events, acknowledgments and pings
+    pub fn event_delivery(&self) -> DeliveryMode {
+        self.event_delivery
+    }
+    
+    /// Choose delivery for sync events, acknowledgments and pings
+    ///
+    /// With [`DeliveryMode::Unreliable`], messages small enough for a datagram skip
+    /// the ordered clipboard stream; a lost sync event surfaces as an acknowledgment
+    /// timeout. Content requests and responses always use the stream.
+    pub fn set_event_delivery(&mut self, delivery: DeliveryMode) {
+        self.event_delivery = delivery;
+    }
 }
 
 /// Trait for transport-based clipboard operations
 #[async_trait]
 pub trait ClipboardTransport: Send + Sync {
-    /// Send encrypted content to a peer
+    /// Send content to a peer over an authenticated session
     async fn send_to_peer(
         &self,
         peer_id: &PeerId,
         peer_address: &PeerAddress,
-        encrypted_content: Vec<u8>,
+        content: &ClipboardContent,
     ) -> ClipboardResult<()>;
     
     /// Receive message from a peer
@@ -519,9 +678,9 @@ impl ClipboardTransport for ClipboardTransportIntegration {
         &self,
         peer_id: &PeerId,
         peer_address: &PeerAddress,
-        encrypted_content: Vec<u8>,
+        content: &ClipboardContent,
     ) -> ClipboardResult<()> {
-        self.send_content(peer_id, peer_address, encrypted_content).await
+        self.send_content(peer_id, peer_address, content).await
     }
     
     async fn receive_from_peer(&self, peer_id: &PeerId) -> ClipboardResult<Option<ClipboardMessage>> {
@@ -537,11 +696,16 @@ impl ClipboardTransport for ClipboardTransportIntegration {
 mod tests {
     use super::*;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use crate::security::{SecuritySystem, SecurityEventType};
+    
+    fn security() -> Arc<ClipboardSecurityIntegration> {
+        Arc::new(ClipboardSecurityIntegration::new(Arc::new(SecuritySystem::new().unwrap())))
+    }
     
     #[tokio::test]
     async fn test_transport_integration_creation() {
         let transport = Arc::new(KizunaTransport::new().await.unwrap());
-        let integration = ClipboardTransportIntegration::new(transport);
+        let integration = ClipboardTransportIntegration::new(transport, security());
         
         assert_eq!(integration.connection_count().await, 0);
         assert_eq!(integration.max_message_size(), 65536);
@@ -620,17 +784,109 @@ mod tests {
     #[tokio::test]
     async fn test_custom_message_size() {
         let transport = Arc::new(KizunaTransport::new().await.unwrap());
-        let integration = ClipboardTransportIntegration::with_max_message_size(transport, 32768);
+        let integration = ClipboardTransportIntegration::with_max_message_size(transport, security(), 32768);
         
         assert_eq!(integration.max_message_size(), 32768);
     }
     
+    #[tokio::test]
+    async fn test_streams_reuse_shared_provider() {
+        use crate::testing::{MemoryTransport, VirtualNetwork};
+        use crate::transport::Transport;
+        
+        let network = VirtualNetwork::new();
+        let server = MemoryTransport::new("server", network.clone());
+        let addr = network.allocate_address(41000);
+        server.listen(&addr).await.unwrap();
+        let peer = PeerAddress::new("server".to_string(), vec![addr], vec![], server.capabilities());
+        
+        let client = Arc::new(MemoryTransport::new("client", network.clone()));
+        let provider = Arc::new(ConnectionProvider::new(client, ProviderConfig::default()));
+        let integration = ClipboardTransportIntegration::with_provider(Arc::clone(&provider), security());
+        
+        let first = integration.get_or_connect(&"server".to_string(), &peer).await.unwrap();
+        let second = integration.get_or_connect(&"server".to_string(), &peer).await.unwrap();
+        assert_eq!(first.stream_id(), second.stream_id());
+        assert_eq!(first.channel(), CHANNEL_CLIPBOARD);
+        assert!(integration.is_connected(&"server".to_string()).await);
+        assert_eq!(provider.stats().dialed, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_unreliable_events_use_datagrams() {
+        use crate::testing::{MemoryTransport, VirtualNetwork};
+        use crate::transport::Transport;
+        
+        let network = VirtualNetwork::new();
+        let server = MemoryTransport::new("server", network.clone());
+        let addr = network.allocate_address(41000);
+        server.listen(&addr).await.unwrap();
+        let peer = PeerAddress::new("server".to_string(), vec![addr], vec![], server.capabilities());
+        
+        let client = Arc::new(MemoryTransport::new("client", network.clone()));
+        let mut sender = ClipboardTransportIntegration::with_provider(
+            Arc::new(ConnectionProvider::new(client, ProviderConfig::default())),
+            security(),
+        );
+        sender.set_event_delivery(DeliveryMode::Unreliable);
+        sender.get_or_connect(&"server".to_string(), &peer).await.unwrap();
+        
+        let inbound = server.accept().await.unwrap();
+        let server_provider = Arc::new(ConnectionProvider::new(Arc::new(server), ProviderConfig::default()));
+        let session = server_provider.adopt(Box::new(inbound)).await.unwrap();
+        let receiver = ClipboardTransportIntegration::with_provider(Arc::clone(&server_provider), security());
+        let stream = session.accept_stream().await.unwrap();
+        receiver.attach_stream("client".to_string(), stream).await.unwrap();
+        
+        sender.send_ping(&"server".to_string()).await.unwrap();
+        match receiver.receive_event(&"client".to_string()).await.unwrap() {
+            Some(ClipboardMessage::Ping { .. }) => {}
+            other => panic!("Expected ping datagram, got {:?}", other),
+        }
+    }
+    
     #[tokio::test]
     async fn test_connected_peers_list() {
         let transport = Arc::new(KizunaTransport::new().await.unwrap());
-        let integration = ClipboardTransportIntegration::new(transport);
+        let integration = ClipboardTransportIntegration::new(transport, security());
         
         let peers = integration.get_connected_peers().await;
         assert_eq!(peers.len(), 0);
     }
+    
+    #[tokio::test]
+    async fn test_untrusted_peer_is_refused_and_audited() {
+        use crate::clipboard::{TextContent, TextEncoding, TextFormat};
+        use crate::security::identity::DeviceIdentity;
+        use crate::testing::{MemoryTransport, VirtualNetwork};
+        use crate::transport::Transport;
+        
+        let network = VirtualNetwork::new();
+        let client = Arc::new(MemoryTransport::new("client", network.clone()));
+        let peer_id = DeviceIdentity::generate().unwrap().derive_peer_id().to_hex();
+        let peer = PeerAddress::new(peer_id.clone(), vec![network.allocate_address(41000)], vec![], client.capabilities());
+        
+        let security_system = Arc::new(SecuritySystem::new().unwrap());
+        let integration = ClipboardTransportIntegration::with_provider(
+            Arc::new(ConnectionProvider::new(client, ProviderConfig::default())),
+            Arc::new(ClipboardSecurityIntegration::new(Arc::clone(&security_system))),
+        );
+        
+        let content = ClipboardContent::Text(TextContent {
+            text: "do not leak".to_string(),
+            encoding: TextEncoding::Utf8,
+            format: TextFormat::Plain,
+            size: 11,
+        });
+        let err = integration.send_content(&peer_id, &peer, &content).await.unwrap_err();
+        assert!(err.to_string().contains("not trusted"));
+        assert_eq!(integration.connection_count().await, 0);
+        
+        let log = security_system.get_audit_log(10).await.unwrap();
+        let failure = log.iter()
+            .find(|event| matches!(event.event_type, SecurityEventType::ClipboardSyncFailed))
+            .expect("failed sync should be audited");
+        assert!(failure.details.contains(&content_hash(&content).unwrap()));
+        assert!(!failure.details.contains("do not leak"));
+    }
 }
@@ -14,7 +14,8 @@ use crate::command_execution::{
     CommandRequest, CommandResult, ScriptRequest, ScriptResult, Notification,
     NotificationResult, SystemInfo, SystemInfoQuery, PeerId, ExecutionStatus,
     CommandManager, AuthorizationManager, SandboxEngine, ScriptEngine,
-    UnifiedCommandManager,
+    UnifiedCommandManager, MediaRequest, MediaResult, MediaCommand, HandoffRequest, HandoffResult,
+    LocateAction, LocateRequest, LocateResult,
 };
 use crate::command_execution::system_info::SystemInfoProvider;
 use crate::command_execution::notification::NotificationManager;
@@ -88,6 +89,24 @@ pub enum CommandExecutionEvent {
         peer_id: PeerId,
         title: String,
     },
+    /// Media control command sent to a peer
+    MediaControlRequested {
+        request_id: Uuid,
+        peer_id: PeerId,
+        command: MediaCommand,
+    },
+    /// URL or file handed off to a peer
+    HandoffRequested {
+        request_id: Uuid,
+        peer_id: PeerId,
+        target: String,
+    },
+    /// A peer was asked to ring or stop ringing
+    LocateRequested {
+        request_id: Uuid,
+        peer_id: PeerId,
+        action: LocateAction,
+    },
     /// Connection established
     ConnectionEstablished {
         peer_id: PeerId,
@@ -401,6 +420,66 @@ impl CommandExecution {
         self.notification_manager.send_notification(notification, sender).await
     }
 
+    /// Send a media control command to a remote peer
+    pub async fn control_remote_media(
+        &self,
+        request: MediaRequest,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<MediaResult> {
+        request.command.validate()?;
+
+        // Emit event
+        self.emit_event(CommandExecutionEvent::MediaControlRequested {
+            request_id: request.request_id,
+            peer_id: peer_address.peer_id.clone(),
+            command: request.command,
+        }).await;
+
+        self.transport_integration
+            .send_media_request(request, peer_address)
+            .await
+    }
+
+    /// Open a URL or file on a remote peer
+    pub async fn open_on_peer(
+        &self,
+        request: HandoffRequest,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<HandoffResult> {
+        request.target.validate()?;
+
+        // Emit event
+        self.emit_event(CommandExecutionEvent::HandoffRequested {
+            request_id: request.request_id,
+            peer_id: peer_address.peer_id.clone(),
+            target: request.target.describe(),
+        }).await;
+
+        self.transport_integration
+            .send_handoff_request(request, peer_address)
+            .await
+    }
+
+    /// Make a remote peer ring so it can be found, or stop it ringing
+    pub async fn locate_peer(
+        &self,
+        request: LocateRequest,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<LocateResult> {
+        request.validate()?;
+
+        // Emit event
+        self.emit_event(CommandExecutionEvent::LocateRequested {
+            request_id: request.request_id,
+            peer_id: peer_address.peer_id.clone(),
+            action: request.action,
+        }).await;
+
+        self.transport_integration
+            .send_locate_request(request, peer_address)
+            .await
+    }
+
     /// Get execution status
     pub async fn get_execution_status(&self, request_id: &Uuid) -> Option<ExecutionStatus> {
         let executions = self.active_executions.read().await;
@@ -29,6 +29,8 @@ pub enum AuditEventType {
     SecurityViolationAttempt,
     SandboxEscape,
     UnauthorizedAccess,
+    SupportRecordingStarted,
+    SupportRecordingStopped,
 }
 
 /// Audit log entry
@@ -224,6 +226,8 @@ impl SqliteAuditLogger {
             "SecurityViolationAttempt" => AuditEventType::SecurityViolationAttempt,
             "SandboxEscape" => AuditEventType::SandboxEscape,
             "UnauthorizedAccess" => AuditEventType::UnauthorizedAccess,
+            "SupportRecordingStarted" => AuditEventType::SupportRecordingStarted,
+            "SupportRecordingStopped" => AuditEventType::SupportRecordingStopped,
             _ => return Err(CommandError::Internal(format!("Unknown event type: {}", event_type_str))),
         };
 
@@ -7,6 +7,8 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{RwLock, mpsc, oneshot};
 use tokio::time::{timeout, Duration};
+use crate::lockdown::{Lockdown, RemoteCapability};
+use crate::security::policy::CommandExecutionPosture;
 use chrono::Utc;
 use regex::Regex;
 
@@ -232,6 +234,13 @@ impl AuthorizationManager for DefaultAuthorizationManager {
         let request_id = request.request_id;
         let timeout_duration = request.timeout;
         
+        // Refuse without prompting while remote access is locked down
+        if let Err(locked) = Lockdown::global().check(RemoteCapability::CommandExecution) {
+            let decision = AuthorizationDecision::Denied(locked.to_string());
+            self.record_authorization(request_id, decision.clone()).await;
+            return Ok(decision);
+        }
+        
         // Create a oneshot channel for the response
         let (tx, rx) = oneshot::channel();
         
@@ -424,6 +433,16 @@ pub struct SecurityPolicy {
     pub log_all_decisions: bool,
 }
 
+impl SecurityPolicy {
+    /// What the security check needs to know about this policy
+    pub fn posture(&self) -> CommandExecutionPosture {
+        CommandExecutionPosture {
+            require_authorization: self.require_authorization,
+            auto_approves_above_low_risk: self.max_auto_approve_risk > RiskLevel::Low,
+        }
+    }
+}
+
 impl Default for SecurityPolicy {
     fn default() -> Self {
         Self {
@@ -500,6 +519,13 @@ impl AuthorizationManager for PolicyEnforcedAuthorizationManager {
     ) -> CmdResult<AuthorizationDecision> {
         let policy = self.policy.read().await;
         
+        // A lockdown overrides auto-approval
+        if let Err(locked) = Lockdown::global().check(RemoteCapability::CommandExecution) {
+            let decision = AuthorizationDecision::Denied(locked.to_string());
+            self.inner.record_authorization(request.request_id, decision.clone()).await;
+            return Ok(decision);
+        }
+        
         // Check if we should auto-approve based on policy
         let should_auto = self.should_auto_approve(
             &request.command_preview,
@@ -0,0 +1,436 @@
+// URL and File Handoff Between Devices
+//
+// A peer can ask this device to open a URL in the default browser or a file in its
+// default application, so a page or document moves to whichever screen is in use.
+// Only allowed peers may hand off, URLs must use an allowed scheme (http and https
+// by default), and files must lie under a folder the user has opted in to.
+
+use crate::command_execution::error::{CommandError, CommandResult};
+use crate::command_execution::types::*;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Unique identifier for a handoff request
+pub type HandoffRequestId = Uuid;
+
+/// URL schemes accepted unless configured otherwise
+pub const DEFAULT_ALLOWED_SCHEMES: &[&str] = &["http", "https"];
+
+/// Longest URL accepted for handoff
+const MAX_URL_LEN: usize = 8 * 1024;
+
+/// What to open on the receiving device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HandoffTarget {
+    /// Open in the default browser or URL handler
+    Url(String),
+    /// Open a file on the receiving device in its default application
+    Path(PathBuf),
+}
+
+impl HandoffTarget {
+    /// Check the target is well formed; policy is checked by the receiver
+    pub fn validate(&self) -> CommandResult<()> {
+        match self {
+            HandoffTarget::Url(url) => {
+                if url.len() > MAX_URL_LEN {
+                    return Err(CommandError::invalid_request(format!(
+                        "URL is {} bytes, the limit is {}",
+                        url.len(),
+                        MAX_URL_LEN
+                    )));
+                }
+                if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                    return Err(CommandError::invalid_request("URL contains whitespace or control characters"));
+                }
+                url_scheme(url)
+                    .map(|_| ())
+                    .ok_or_else(|| CommandError::invalid_request(format!("'{}' is not an absolute URL", url)))
+            }
+            HandoffTarget::Path(path) if !path.is_absolute() => Err(CommandError::invalid_request(format!(
+                "Path {} must be absolute",
+                path.display()
+            ))),
+            HandoffTarget::Path(_) => Ok(()),
+        }
+    }
+
+    /// Short description for logs and events
+    pub fn describe(&self) -> String {
+        match self {
+            HandoffTarget::Url(url) => url.clone(),
+            HandoffTarget::Path(path) => path.display().to_string(),
+        }
+    }
+}
+
+/// Lowercased scheme of an absolute URL, or `None` if it has none
+pub fn url_scheme(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    (valid && !rest.is_empty()).then(|| scheme.to_ascii_lowercase())
+}
+
+/// Handoff request sent by a peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandoffRequest {
+    pub request_id: HandoffRequestId,
+    pub target: HandoffTarget,
+    pub requester: PeerId,
+    pub created_at: Timestamp,
+}
+
+impl HandoffRequest {
+    /// Create a request to open a URL
+    pub fn url(url: impl Into<String>, requester: PeerId) -> Self {
+        Self::new(HandoffTarget::Url(url.into()), requester)
+    }
+
+    /// Create a request to open a file on the receiving device
+    pub fn path(path: impl Into<PathBuf>, requester: PeerId) -> Self {
+        Self::new(HandoffTarget::Path(path.into()), requester)
+    }
+
+    fn new(target: HandoffTarget, requester: PeerId) -> Self {
+        Self {
+            request_id: Uuid::new_v4(),
+            target,
+            requester,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Result of a handoff request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandoffResult {
+    pub request_id: HandoffRequestId,
+    pub success: bool,
+    pub error: Option<String>,
+    pub completed_at: Timestamp,
+}
+
+/// Platform-specific launcher trait
+pub trait LaunchBackend: Send + Sync {
+    /// Open a URL with the default handler for its scheme
+    fn open_url(&self, url: &str) -> CommandResult<()>;
+
+    /// Open a file with its default application
+    fn open_path(&self, path: &Path) -> CommandResult<()>;
+
+    /// Check if launching is supported on this platform
+    fn is_supported(&self) -> bool;
+}
+
+/// Launcher using the desktop's opener: `xdg-open`, `open` or `explorer`
+pub struct SystemLauncher;
+
+impl SystemLauncher {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn program() -> Option<&'static str> {
+        if cfg!(target_os = "macos") {
+            Some("open")
+        } else if cfg!(target_os = "windows") {
+            Some("explorer")
+        } else if cfg!(unix) {
+            Some("xdg-open")
+        } else {
+            None
+        }
+    }
+
+    fn launch(&self, target: &std::ffi::OsStr) -> CommandResult<()> {
+        let program = Self::program()
+            .ok_or_else(|| CommandError::platform_error("Opening URLs is not supported on this platform"))?;
+        // The target is passed as a single argument, never through a shell
+        let status = std::process::Command::new(program)
+            .arg(target)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map_err(|e| CommandError::platform_error(format!("Failed to run {}: {}", program, e)))?;
+        // explorer exits non-zero even when it opened the target
+        if !status.success() && !cfg!(target_os = "windows") {
+            return Err(CommandError::platform_error(format!("{} exited with {}", program, status)));
+        }
+        Ok(())
+    }
+}
+
+impl Default for SystemLauncher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LaunchBackend for SystemLauncher {
+    fn open_url(&self, url: &str) -> CommandResult<()> {
+        self.launch(std::ffi::OsStr::new(url))
+    }
+
+    fn open_path(&self, path: &Path) -> CommandResult<()> {
+        self.launch(path.as_os_str())
+    }
+
+    fn is_supported(&self) -> bool {
+        Self::program().is_some()
+    }
+}
+
+/// Handoff controller enforcing per-peer access and target policy
+pub struct HandoffController {
+    backend: Box<dyn LaunchBackend>,
+    allowed_peers: Arc<Mutex<HashSet<PeerId>>>,
+    allowed_schemes: HashSet<String>,
+    path_roots: Vec<PathBuf>,
+}
+
+impl HandoffController {
+    /// Create a handoff controller with the system launcher
+    pub fn new() -> Self {
+        Self::with_backend(Box::new(SystemLauncher::new()))
+    }
+
+    /// Create a handoff controller with a custom backend
+    pub fn with_backend(backend: Box<dyn LaunchBackend>) -> Self {
+        Self {
+            backend,
+            allowed_peers: Arc::new(Mutex::new(HashSet::new())),
+            allowed_schemes: DEFAULT_ALLOWED_SCHEMES.iter().map(|s| s.to_string()).collect(),
+            path_roots: Vec::new(),
+        }
+    }
+
+    /// Allow a peer to open URLs and files on this device
+    pub fn allow_peer(&self, peer_id: impl Into<PeerId>) {
+        self.allowed_peers.lock().unwrap().insert(peer_id.into());
+    }
+
+    /// Revoke handoff access for a peer
+    pub fn revoke_peer(&self, peer_id: &str) -> bool {
+        self.allowed_peers.lock().unwrap().remove(peer_id)
+    }
+
+    /// Check whether a peer may hand off to this device
+    pub fn is_peer_allowed(&self, peer_id: &str) -> bool {
+        self.allowed_peers.lock().unwrap().contains(peer_id)
+    }
+
+    /// Accept URLs with an additional scheme, such as `mailto`
+    pub fn allow_scheme(&mut self, scheme: &str) {
+        self.allowed_schemes.insert(scheme.to_ascii_lowercase());
+    }
+
+    /// Replace the accepted URL schemes
+    pub fn set_allowed_schemes<I, S>(&mut self, schemes: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.allowed_schemes = schemes.into_iter().map(|s| s.as_ref().to_ascii_lowercase()).collect();
+    }
+
+    /// URL schemes currently accepted
+    pub fn allowed_schemes(&self) -> Vec<String> {
+        let mut schemes: Vec<String> = self.allowed_schemes.iter().cloned().collect();
+        schemes.sort();
+        schemes
+    }
+
+    /// Let peers open files under `root`; no files can be opened until one is added
+    pub fn allow_path_root(&mut self, root: impl Into<PathBuf>) {
+        self.path_roots.push(root.into());
+    }
+
+    /// Handle a handoff request from a peer
+    pub fn handle_request(&self, request: &HandoffRequest) -> CommandResult<HandoffResult> {
+        crate::lockdown::Lockdown::global()
+            .check(crate::lockdown::RemoteCapability::Handoff)
+            .map_err(|e| CommandError::authorization_denied(e.to_string()))?;
+
+        if !self.is_peer_allowed(&request.requester) {
+            return Err(CommandError::authorization_denied(format!(
+                "Peer {} is not allowed to open URLs or files",
+                request.requester
+            )));
+        }
+
+        request.target.validate()?;
+
+        if !self.backend.is_supported() {
+            return Err(CommandError::platform_error("Opening URLs is not available"));
+        }
+
+        let outcome = match &request.target {
+            HandoffTarget::Url(url) => {
+                self.check_url(url)?;
+                self.backend.open_url(url)
+            }
+            HandoffTarget::Path(path) => {
+                let path = self.check_path(path)?;
+                self.backend.open_path(&path)
+            }
+        };
+
+        Ok(HandoffResult {
+            request_id: request.request_id,
+            success: outcome.is_ok(),
+            error: outcome.err().map(|e| e.to_string()),
+            completed_at: Utc::now(),
+        })
+    }
+
+    fn check_url(&self, url: &str) -> CommandResult<()> {
+        let scheme = url_scheme(url).unwrap_or_default();
+        if !self.allowed_schemes.contains(&scheme) {
+            return Err(CommandError::permission_error(format!(
+                "URLs with scheme '{}' are not allowed",
+                scheme
+            )));
+        }
+        Ok(())
+    }
+
+    /// Resolve a requested file and check it lies under an allowed root
+    fn check_path(&self, path: &Path) -> CommandResult<PathBuf> {
+        let resolved = path
+            .canonicalize()
+            .map_err(|_| CommandError::invalid_request(format!("File {} not found", path.display())))?;
+        if !resolved.is_file() {
+            return Err(CommandError::invalid_request(format!("{} is not a file", path.display())));
+        }
+        let allowed = self
+            .path_roots
+            .iter()
+            .filter_map(|root| root.canonicalize().ok())
+            .any(|root| resolved.starts_with(root));
+        if !allowed {
+            return Err(CommandError::permission_error(format!(
+                "Opening files from {} is not allowed",
+                path.display()
+            )));
+        }
+        Ok(resolved)
+    }
+}
+
+impl Default for HandoffController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingLauncher {
+        opened: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl LaunchBackend for RecordingLauncher {
+        fn open_url(&self, url: &str) -> CommandResult<()> {
+            self.opened.lock().unwrap().push(url.to_string());
+            Ok(())
+        }
+
+        fn open_path(&self, path: &Path) -> CommandResult<()> {
+            self.opened.lock().unwrap().push(path.display().to_string());
+            Ok(())
+        }
+
+        fn is_supported(&self) -> bool {
+            true
+        }
+    }
+
+    fn controller() -> (HandoffController, Arc<Mutex<Vec<String>>>) {
+        let launcher = RecordingLauncher::default();
+        let opened = launcher.opened.clone();
+        (HandoffController::with_backend(Box::new(launcher)), opened)
+    }
+
+    #[test]
+    fn test_url_scheme() {
+        assert_eq!(url_scheme("https://example.com").as_deref(), Some("https"));
+        assert_eq!(url_scheme("HTTP://example.com").as_deref(), Some("http"));
+        assert_eq!(url_scheme("mailto:someone@example.com").as_deref(), Some("mailto"));
+        assert_eq!(url_scheme("example.com"), None);
+        assert_eq!(url_scheme("-x:foo"), None);
+        assert_eq!(url_scheme("https:"), None);
+    }
+
+    #[test]
+    fn test_unknown_peer_denied() {
+        let (controller, opened) = controller();
+        let request = HandoffRequest::url("https://example.com", "stranger".to_string());
+
+        let result = controller.handle_request(&request);
+        assert!(matches!(result, Err(CommandError::AuthorizationDenied(_))));
+        assert!(opened.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scheme_allowlist() {
+        let (mut controller, opened) = controller();
+        controller.allow_peer("phone");
+
+        let request = HandoffRequest::url("https://example.com/page", "phone".to_string());
+        assert!(controller.handle_request(&request).unwrap().success);
+
+        for url in ["javascript:alert(1)", "file:///etc/passwd", "smb://server/share"] {
+            let request = HandoffRequest::url(url, "phone".to_string());
+            assert!(matches!(
+                controller.handle_request(&request),
+                Err(CommandError::PermissionError(_))
+            ));
+        }
+
+        controller.allow_scheme("mailto");
+        let request = HandoffRequest::url("mailto:someone@example.com", "phone".to_string());
+        assert!(controller.handle_request(&request).unwrap().success);
+        assert_eq!(opened.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_paths_need_allowed_root() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let shared = dir.path().join("shared");
+        std::fs::create_dir_all(&shared).unwrap();
+        std::fs::write(shared.join("notes.txt"), b"notes").unwrap();
+        std::fs::write(dir.path().join("private.txt"), b"private").unwrap();
+
+        let (mut controller, opened) = controller();
+        controller.allow_peer("phone");
+
+        let request = HandoffRequest::path(shared.join("notes.txt"), "phone".to_string());
+        assert!(matches!(
+            controller.handle_request(&request),
+            Err(CommandError::PermissionError(_))
+        ));
+
+        controller.allow_path_root(&shared);
+        assert!(controller.handle_request(&request).unwrap().success);
+
+        let escape = HandoffRequest::path(shared.join("../private.txt"), "phone".to_string());
+        assert!(matches!(
+            controller.handle_request(&escape),
+            Err(CommandError::PermissionError(_))
+        ));
+
+        let relative = HandoffRequest::path("notes.txt", "phone".to_string());
+        assert!(controller.handle_request(&relative).is_err());
+        assert_eq!(opened.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,643 @@
+// Find My Device
+//
+// A peer can make this device ring so it can be found on the LAN: output volume is
+// raised to the maximum, an alert sound repeats, and a critical notification is shown
+// again and again until someone acknowledges it here, the requesting peer cancels, or
+// the ring times out. Volume and mute state are restored afterwards. Only allowed
+// peers may ring the device.
+
+use crate::command_execution::error::{CommandError, CommandResult};
+use crate::command_execution::media::{MediaBackend, MediaCommand, MediaController, MediaState};
+use crate::command_execution::notification::{NotificationBackend, NotificationManager};
+use crate::command_execution::types::*;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, watch};
+use uuid::Uuid;
+
+/// Unique identifier for a locate request
+pub type LocateRequestId = Uuid;
+
+/// Longest custom message shown in the locate notification
+pub const MAX_LOCATE_MESSAGE_LEN: usize = 256;
+
+/// What the requesting peer wants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LocateAction {
+    /// Start ringing
+    Ring,
+    /// Stop a ring that is in progress
+    Stop,
+}
+
+/// Locate request sent by a peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocateRequest {
+    pub request_id: LocateRequestId,
+    pub action: LocateAction,
+    /// Shown in the notification instead of the default text
+    pub message: Option<String>,
+    pub requester: PeerId,
+    pub created_at: Timestamp,
+}
+
+impl LocateRequest {
+    /// Ask the peer to start ringing
+    pub fn ring(requester: PeerId) -> Self {
+        Self::new(LocateAction::Ring, requester)
+    }
+
+    /// Ask the peer to stop ringing
+    pub fn stop(requester: PeerId) -> Self {
+        Self::new(LocateAction::Stop, requester)
+    }
+
+    /// Show a custom message in the notification
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Check the request before sending or acting on it
+    pub fn validate(&self) -> CommandResult<()> {
+        match &self.message {
+            Some(message) if message.len() > MAX_LOCATE_MESSAGE_LEN => Err(CommandError::invalid_request(format!(
+                "Locate message exceeds {} bytes",
+                MAX_LOCATE_MESSAGE_LEN
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn new(action: LocateAction, requester: PeerId) -> Self {
+        Self {
+            request_id: Uuid::new_v4(),
+            action,
+            message: None,
+            requester,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Result of a locate request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocateResult {
+    pub request_id: LocateRequestId,
+    pub success: bool,
+    /// Whether the device is ringing after the request
+    pub ringing: bool,
+    pub error: Option<String>,
+    pub completed_at: Timestamp,
+}
+
+/// Why a ring ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LocateStopReason {
+    /// Someone at this device acknowledged the alert
+    Acknowledged,
+    /// The requesting peer stopped it
+    Cancelled,
+    /// Nobody acknowledged it in time
+    TimedOut,
+}
+
+/// Ring lifecycle events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LocateEvent {
+    Started {
+        request_id: LocateRequestId,
+        requester: PeerId,
+    },
+    Stopped {
+        request_id: LocateRequestId,
+        reason: LocateStopReason,
+    },
+}
+
+/// Ring timing
+#[derive(Debug, Clone)]
+pub struct LocateConfig {
+    /// Pause between alert sounds
+    pub ring_interval: Duration,
+    /// How often the notification is shown again
+    pub notification_interval: Duration,
+    /// Longest a ring lasts without acknowledgement
+    pub max_duration: Duration,
+    /// Raise output volume to the maximum and unmute while ringing
+    pub raise_volume: bool,
+}
+
+impl Default for LocateConfig {
+    fn default() -> Self {
+        Self {
+            ring_interval: Duration::from_secs(2),
+            notification_interval: Duration::from_secs(10),
+            max_duration: Duration::from_secs(5 * 60),
+            raise_volume: true,
+        }
+    }
+}
+
+/// Platform-specific alert sound trait
+pub trait AlertSound: Send + Sync {
+    /// Play the alert once, returning when it has finished
+    fn play(&self) -> CommandResult<()>;
+
+    /// Check if sound playback is supported on this platform
+    fn is_supported(&self) -> bool;
+}
+
+/// Alert sound using the desktop's sound player
+pub struct SystemAlertSound;
+
+impl SystemAlertSound {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Players to try in order, as program and arguments
+    fn players() -> Vec<(&'static str, Vec<&'static str>)> {
+        if cfg!(target_os = "macos") {
+            vec![("afplay", vec!["/System/Library/Sounds/Sosumi.aiff"])]
+        } else if cfg!(target_os = "windows") {
+            vec![(
+                "powershell",
+                vec![
+                    "-NoProfile",
+                    "-Command",
+                    "[System.Media.SystemSounds]::Hand.Play(); Start-Sleep -Milliseconds 800",
+                ],
+            )]
+        } else if cfg!(unix) {
+            vec![
+                ("canberra-gtk-play", vec!["-i", "alarm-clock-elapsed"]),
+                ("paplay", vec!["/usr/share/sounds/freedesktop/stereo/alarm-clock-elapsed.oga"]),
+            ]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl Default for SystemAlertSound {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlertSound for SystemAlertSound {
+    fn play(&self) -> CommandResult<()> {
+        let mut last_error = None;
+        for (program, args) in Self::players() {
+            match std::process::Command::new(program)
+                .args(&args)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+            {
+                Ok(status) if status.success() => return Ok(()),
+                Ok(status) => last_error = Some(format!("{} exited with {}", program, status)),
+                Err(e) => last_error = Some(format!("Failed to run {}: {}", program, e)),
+            }
+        }
+        Err(CommandError::platform_error(
+            last_error.unwrap_or_else(|| "No alert sound player on this platform".to_string()),
+        ))
+    }
+
+    fn is_supported(&self) -> bool {
+        !Self::players().is_empty()
+    }
+}
+
+/// A ring in progress
+struct Ringing {
+    request_id: LocateRequestId,
+    stop: watch::Sender<Option<LocateStopReason>>,
+}
+
+/// Locate controller enforcing per-peer access and driving the ring
+pub struct LocateController {
+    sound: Arc<dyn AlertSound>,
+    notifications: Option<Arc<dyn NotificationBackend>>,
+    media: Option<Arc<dyn MediaBackend>>,
+    allowed_peers: Arc<Mutex<HashSet<PeerId>>>,
+    config: LocateConfig,
+    ringing: Arc<Mutex<Option<Ringing>>>,
+    events: broadcast::Sender<LocateEvent>,
+}
+
+impl LocateController {
+    /// Create a locate controller using the platform's sound, notification and
+    /// volume backends; any that are unavailable are skipped while ringing
+    pub fn new(config: LocateConfig) -> Self {
+        Self::with_backends(
+            Box::new(SystemAlertSound::new()),
+            NotificationManager::create_platform_backend().ok(),
+            MediaController::create_platform_backend().ok(),
+            config,
+        )
+    }
+
+    /// Create a locate controller with custom backends
+    pub fn with_backends(
+        sound: Box<dyn AlertSound>,
+        notifications: Option<Box<dyn NotificationBackend>>,
+        media: Option<Box<dyn MediaBackend>>,
+        config: LocateConfig,
+    ) -> Self {
+        let (events, _) = broadcast::channel(16);
+        Self {
+            sound: Arc::from(sound),
+            notifications: notifications.map(Arc::from),
+            media: media.map(Arc::from),
+            allowed_peers: Arc::new(Mutex::new(HashSet::new())),
+            config,
+            ringing: Arc::new(Mutex::new(None)),
+            events,
+        }
+    }
+
+    /// Allow a peer to ring this device
+    pub fn allow_peer(&self, peer_id: impl Into<PeerId>) {
+        self.allowed_peers.lock().unwrap().insert(peer_id.into());
+    }
+
+    /// Revoke locate access for a peer
+    pub fn revoke_peer(&self, peer_id: &str) -> bool {
+        self.allowed_peers.lock().unwrap().remove(peer_id)
+    }
+
+    /// Check whether a peer may ring this device
+    pub fn is_peer_allowed(&self, peer_id: &str) -> bool {
+        self.allowed_peers.lock().unwrap().contains(peer_id)
+    }
+
+    /// Whether the device is ringing
+    pub fn is_ringing(&self) -> bool {
+        self.ringing.lock().unwrap().is_some()
+    }
+
+    /// Subscribe to ring start and stop events
+    pub fn subscribe(&self) -> broadcast::Receiver<LocateEvent> {
+        self.events.subscribe()
+    }
+
+    /// Handle a locate request from a peer
+    pub async fn handle_request(&self, request: &LocateRequest) -> CommandResult<LocateResult> {
+        if !self.is_peer_allowed(&request.requester) {
+            return Err(CommandError::authorization_denied(format!(
+                "Peer {} is not allowed to locate this device",
+                request.requester
+            )));
+        }
+        request.validate()?;
+
+        let ringing = match request.action {
+            LocateAction::Ring => {
+                self.start(request)?;
+                true
+            }
+            LocateAction::Stop => {
+                self.stop(LocateStopReason::Cancelled);
+                false
+            }
+        };
+
+        Ok(LocateResult {
+            request_id: request.request_id,
+            success: true,
+            ringing,
+            error: None,
+            completed_at: Utc::now(),
+        })
+    }
+
+    /// Stop the ring from this device; returns whether one was in progress
+    pub fn acknowledge(&self) -> bool {
+        self.stop(LocateStopReason::Acknowledged)
+    }
+
+    fn stop(&self, reason: LocateStopReason) -> bool {
+        match self.ringing.lock().unwrap().take() {
+            Some(ringing) => {
+                let _ = ringing.stop.send(Some(reason));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn start(&self, request: &LocateRequest) -> CommandResult<()> {
+        if !self.sound.is_supported() && self.notifications.is_none() {
+            return Err(CommandError::platform_error("Neither sound nor notifications are available"));
+        }
+
+        let (stop, mut stopped) = watch::channel(None);
+        {
+            let mut ringing = self.ringing.lock().unwrap();
+            if ringing.is_some() {
+                // Already ringing; a second request does not restart it
+                return Ok(());
+            }
+            *ringing = Some(Ringing {
+                request_id: request.request_id,
+                stop,
+            });
+        }
+
+        let previous_volume = self.raise_volume();
+        let notification = Notification {
+            notification_id: Uuid::new_v4(),
+            title: "Someone is looking for this device".to_string(),
+            message: request.message.clone().unwrap_or_else(|| {
+                format!("{} is trying to find this device. Acknowledge to stop the alert.", request.requester)
+            }),
+            notification_type: NotificationType::Warning,
+            priority: NotificationPriority::Critical,
+            duration: None,
+            actions: vec![NotificationAction {
+                id: "acknowledge".to_string(),
+                label: "Found it".to_string(),
+            }],
+            sender: request.requester.clone(),
+            image: None,
+            launch: None,
+        };
+
+        let request_id = request.request_id;
+        let sound = Arc::clone(&self.sound);
+        let notifications = self.notifications.clone();
+        let media = self.media.clone();
+        let config = self.config.clone();
+        let ringing = Arc::clone(&self.ringing);
+        let events = self.events.clone();
+        let _ = events.send(LocateEvent::Started {
+            request_id,
+            requester: request.requester.clone(),
+        });
+
+        tokio::spawn(async move {
+            let deadline = Instant::now() + config.max_duration;
+            let mut last_notice: Option<Instant> = None;
+            let reason = loop {
+                if last_notice.is_none_or(|at| at.elapsed() >= config.notification_interval) {
+                    if let Some(backend) = &notifications
+                        && let Err(e) = backend.show_notification(&notification)
+                    {
+                        log::warn!("Failed to show locate notification: {}", e);
+                    }
+                    last_notice = Some(Instant::now());
+                }
+
+                let player = Arc::clone(&sound);
+                match tokio::task::spawn_blocking(move || player.play()).await {
+                    Ok(Err(e)) => log::debug!("Alert sound failed: {}", e),
+                    Err(e) => log::debug!("Alert sound task failed: {}", e),
+                    Ok(Ok(())) => {}
+                }
+
+                if let Some(reason) = *stopped.borrow() {
+                    break reason;
+                }
+                if Instant::now() >= deadline {
+                    break LocateStopReason::TimedOut;
+                }
+                tokio::select! {
+                    _ = stopped.changed() => {
+                        break stopped.borrow().unwrap_or(LocateStopReason::Cancelled);
+                    }
+                    _ = tokio::time::sleep(config.ring_interval) => {}
+                }
+            };
+
+            {
+                let mut current = ringing.lock().unwrap();
+                if current.as_ref().is_some_and(|r| r.request_id == request_id) {
+                    *current = None;
+                }
+            }
+            if let (Some(media), Some(previous)) = (media, previous_volume) {
+                restore_volume(media.as_ref(), previous);
+            }
+            log::info!("Locate ring {} stopped: {:?}", request_id, reason);
+            let _ = events.send(LocateEvent::Stopped { request_id, reason });
+        });
+
+        Ok(())
+    }
+
+    /// Unmute and raise volume to the maximum, returning the state to restore
+    fn raise_volume(&self) -> Option<MediaState> {
+        if !self.config.raise_volume {
+            return None;
+        }
+        let media = self.media.as_ref()?;
+        let previous = media.query_state(None).ok();
+        for command in [MediaCommand::Unmute, MediaCommand::SetVolume(100)] {
+            if let Err(e) = media.execute(command, None) {
+                log::debug!("Could not apply {:?} for locate: {}", command, e);
+            }
+        }
+        previous
+    }
+}
+
+fn restore_volume(media: &dyn MediaBackend, previous: MediaState) {
+    if let Some(volume) = previous.volume {
+        let _ = media.execute(MediaCommand::SetVolume(volume), None);
+    }
+    if previous.muted == Some(true) {
+        let _ = media.execute(MediaCommand::Mute, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_execution::media::{MediaCapabilities, PlaybackStatus};
+    use crate::command_execution::notification::NotificationCapabilities;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingSound(Arc<AtomicUsize>);
+
+    impl AlertSound for CountingSound {
+        fn play(&self) -> CommandResult<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn is_supported(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingNotifications(Arc<Mutex<Vec<String>>>);
+
+    impl NotificationBackend for RecordingNotifications {
+        fn show_notification(&self, notification: &Notification) -> CommandResult<()> {
+            self.0.lock().unwrap().push(notification.message.clone());
+            Ok(())
+        }
+
+        fn is_supported(&self) -> bool {
+            true
+        }
+
+        fn get_capabilities(&self) -> NotificationCapabilities {
+            NotificationCapabilities {
+                supports_actions: true,
+                supports_duration: false,
+                supports_priority: true,
+                supports_icons: false,
+                supports_images: false,
+                supports_activation: false,
+                supports_replace: false,
+                supports_mirroring: false,
+                max_title_length: None,
+                max_message_length: None,
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingMedia(Arc<Mutex<Vec<MediaCommand>>>);
+
+    impl MediaBackend for RecordingMedia {
+        fn execute(&self, command: MediaCommand, _player: Option<&str>) -> CommandResult<()> {
+            self.0.lock().unwrap().push(command);
+            Ok(())
+        }
+
+        fn query_state(&self, _player: Option<&str>) -> CommandResult<MediaState> {
+            Ok(MediaState {
+                status: PlaybackStatus::Paused,
+                volume: Some(30),
+                muted: Some(true),
+                player: None,
+            })
+        }
+
+        fn is_supported(&self) -> bool {
+            true
+        }
+
+        fn get_capabilities(&self) -> MediaCapabilities {
+            MediaCapabilities {
+                supports_playback: false,
+                supports_absolute_volume: true,
+                supports_mute: true,
+                supports_player_selection: false,
+                supports_state_query: true,
+            }
+        }
+    }
+
+    type Recorders = (Arc<AtomicUsize>, Arc<Mutex<Vec<String>>>, Arc<Mutex<Vec<MediaCommand>>>);
+
+    fn setup(config: LocateConfig) -> (LocateController, Recorders) {
+        let sound = CountingSound::default();
+        let notifications = RecordingNotifications::default();
+        let media = RecordingMedia::default();
+        let recorders = (sound.0.clone(), notifications.0.clone(), media.0.clone());
+        let controller = LocateController::with_backends(
+            Box::new(sound),
+            Some(Box::new(notifications)),
+            Some(Box::new(media)),
+            config,
+        );
+        (controller, recorders)
+    }
+
+    fn fast() -> LocateConfig {
+        LocateConfig {
+            ring_interval: Duration::from_millis(5),
+            notification_interval: Duration::from_secs(60),
+            max_duration: Duration::from_secs(60),
+            raise_volume: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_peer_denied() {
+        let (controller, (plays, _, _)) = setup(fast());
+        let result = controller.handle_request(&LocateRequest::ring("stranger".to_string())).await;
+
+        assert!(matches!(result, Err(CommandError::AuthorizationDenied(_))));
+        assert!(!controller.is_ringing());
+        assert_eq!(plays.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_rings_until_acknowledged() {
+        let (controller, (plays, shown, volume)) = setup(fast());
+        controller.allow_peer("phone");
+        let mut events = controller.subscribe();
+
+        let request = LocateRequest::ring("phone".to_string()).with_message("Where is my laptop?");
+        let result = controller.handle_request(&request).await.unwrap();
+        assert!(result.ringing);
+        assert!(matches!(events.recv().await.unwrap(), LocateEvent::Started { .. }));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(controller.is_ringing());
+        assert!(plays.load(Ordering::SeqCst) >= 2);
+        assert_eq!(*shown.lock().unwrap(), vec!["Where is my laptop?".to_string()]);
+
+        assert!(controller.acknowledge());
+        match events.recv().await.unwrap() {
+            LocateEvent::Stopped { request_id, reason } => {
+                assert_eq!(request_id, request.request_id);
+                assert_eq!(reason, LocateStopReason::Acknowledged);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(!controller.is_ringing());
+        assert_eq!(
+            *volume.lock().unwrap(),
+            vec![
+                MediaCommand::Unmute,
+                MediaCommand::SetVolume(100),
+                MediaCommand::SetVolume(30),
+                MediaCommand::Mute,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_requester_can_stop_and_ring_times_out() {
+        let (controller, _) = setup(fast());
+        controller.allow_peer("phone");
+        let mut events = controller.subscribe();
+
+        controller.handle_request(&LocateRequest::ring("phone".to_string())).await.unwrap();
+        let result = controller.handle_request(&LocateRequest::stop("phone".to_string())).await.unwrap();
+        assert!(!result.ringing);
+        let _ = events.recv().await.unwrap();
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            LocateEvent::Stopped { reason: LocateStopReason::Cancelled, .. }
+        ));
+
+        let (controller, _) = setup(LocateConfig {
+            max_duration: Duration::from_millis(20),
+            ..fast()
+        });
+        controller.allow_peer("phone");
+        let mut events = controller.subscribe();
+        controller.handle_request(&LocateRequest::ring("phone".to_string())).await.unwrap();
+        let _ = events.recv().await.unwrap();
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            LocateEvent::Stopped { reason: LocateStopReason::TimedOut, .. }
+        ));
+        assert!(!controller.is_ringing());
+    }
+}
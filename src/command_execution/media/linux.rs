@@ -0,0 +1,257 @@
+// Linux Media Backend
+//
+// Implements playback control over MPRIS (org.mpris.MediaPlayer2) on the session bus
+// and volume control through the PulseAudio/PipeWire `pactl` utility.
+
+use crate::command_execution::error::{CommandError, CommandResult};
+use super::{apply_volume_delta, MediaBackend, MediaCapabilities, MediaCommand, MediaState, PlaybackStatus};
+use std::process::Command;
+
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const MPRIS_PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const DEFAULT_SINK: &str = "@DEFAULT_SINK@";
+
+/// Linux media backend using MPRIS and pactl
+pub struct LinuxMediaBackend;
+
+impl LinuxMediaBackend {
+    /// Create a new Linux media backend
+    pub fn new() -> CommandResult<Self> {
+        Ok(Self)
+    }
+
+    /// Run a helper program and return its stdout
+    fn run(&self, program: &str, args: &[&str]) -> CommandResult<String> {
+        let output = Command::new(program)
+            .args(args)
+            .output()
+            .map_err(|e| CommandError::platform_error(format!("Failed to run {}: {}", program, e)))?;
+
+        if !output.status.success() {
+            return Err(CommandError::platform_error(format!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// List MPRIS players currently on the session bus
+    fn list_players(&self) -> CommandResult<Vec<String>> {
+        let reply = self.run("dbus-send", &[
+            "--session",
+            "--dest=org.freedesktop.DBus",
+            "--type=method_call",
+            "--print-reply",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus.ListNames",
+        ])?;
+
+        Ok(parse_mpris_names(&reply))
+    }
+
+    /// Resolve the bus name of the requested (or first available) player
+    fn resolve_player(&self, player: Option<&str>) -> CommandResult<String> {
+        let players = self.list_players()?;
+
+        match player {
+            Some(name) => {
+                let bus_name = if name.starts_with(MPRIS_PREFIX) {
+                    name.to_string()
+                } else {
+                    format!("{}{}", MPRIS_PREFIX, name)
+                };
+                players
+                    .into_iter()
+                    .find(|p| *p == bus_name)
+                    .ok_or_else(|| CommandError::CommandNotFound(format!("Media player {} not found", name)))
+            }
+            None => players
+                .into_iter()
+                .next()
+                .ok_or_else(|| CommandError::CommandNotFound("No active media player".to_string())),
+        }
+    }
+
+    /// Invoke a method on the MPRIS player interface
+    fn call_player(&self, bus_name: &str, method: &str) -> CommandResult<()> {
+        let dest = format!("--dest={}", bus_name);
+        let member = format!("{}.{}", MPRIS_PLAYER_INTERFACE, method);
+        self.run("dbus-send", &[
+            "--session",
+            "--type=method_call",
+            &dest,
+            MPRIS_PATH,
+            &member,
+        ])?;
+        Ok(())
+    }
+
+    /// Read the playback status of an MPRIS player
+    fn playback_status(&self, bus_name: &str) -> CommandResult<PlaybackStatus> {
+        let dest = format!("--dest={}", bus_name);
+        let interface = format!("string:{}", MPRIS_PLAYER_INTERFACE);
+        let reply = self.run("dbus-send", &[
+            "--session",
+            "--print-reply",
+            &dest,
+            MPRIS_PATH,
+            "org.freedesktop.DBus.Properties.Get",
+            &interface,
+            "string:PlaybackStatus",
+        ])?;
+
+        Ok(parse_playback_status(&reply))
+    }
+
+    /// Read the default sink volume percentage
+    fn volume(&self) -> CommandResult<u8> {
+        let reply = self.run("pactl", &["get-sink-volume", DEFAULT_SINK])?;
+        parse_pactl_volume(&reply)
+            .ok_or_else(|| CommandError::platform_error("Unable to parse sink volume"))
+    }
+
+    /// Read the default sink mute state
+    fn muted(&self) -> CommandResult<bool> {
+        let reply = self.run("pactl", &["get-sink-mute", DEFAULT_SINK])?;
+        Ok(reply.contains("yes"))
+    }
+
+    /// Set the default sink volume percentage
+    fn set_volume(&self, level: u8) -> CommandResult<()> {
+        let level = format!("{}%", level.min(100));
+        self.run("pactl", &["set-sink-volume", DEFAULT_SINK, &level])?;
+        Ok(())
+    }
+
+    /// Set the default sink mute state ("1", "0" or "toggle")
+    fn set_mute(&self, state: &str) -> CommandResult<()> {
+        self.run("pactl", &["set-sink-mute", DEFAULT_SINK, state])?;
+        Ok(())
+    }
+}
+
+impl MediaBackend for LinuxMediaBackend {
+    fn execute(&self, command: MediaCommand, player: Option<&str>) -> CommandResult<()> {
+        match command {
+            MediaCommand::SetVolume(level) => self.set_volume(level),
+            MediaCommand::AdjustVolume(delta) => {
+                let current = self.volume()?;
+                self.set_volume(apply_volume_delta(current, delta))
+            }
+            MediaCommand::Mute => self.set_mute("1"),
+            MediaCommand::Unmute => self.set_mute("0"),
+            MediaCommand::ToggleMute => self.set_mute("toggle"),
+            playback => {
+                let bus_name = self.resolve_player(player)?;
+                let method = match playback {
+                    MediaCommand::Play => "Play",
+                    MediaCommand::Pause => "Pause",
+                    MediaCommand::PlayPause => "PlayPause",
+                    MediaCommand::Stop => "Stop",
+                    MediaCommand::Next => "Next",
+                    MediaCommand::Previous => "Previous",
+                    _ => unreachable!("volume commands handled above"),
+                };
+                self.call_player(&bus_name, method)
+            }
+        }
+    }
+
+    fn query_state(&self, player: Option<&str>) -> CommandResult<MediaState> {
+        let bus_name = self.resolve_player(player).ok();
+        let status = bus_name
+            .as_deref()
+            .and_then(|name| self.playback_status(name).ok())
+            .unwrap_or(PlaybackStatus::Unknown);
+
+        Ok(MediaState {
+            status,
+            volume: self.volume().ok(),
+            muted: self.muted().ok(),
+            player: bus_name.map(|name| name.trim_start_matches(MPRIS_PREFIX).to_string()),
+        })
+    }
+
+    fn is_supported(&self) -> bool {
+        cfg!(target_os = "linux")
+    }
+
+    fn get_capabilities(&self) -> MediaCapabilities {
+        MediaCapabilities {
+            supports_playback: true,
+            supports_absolute_volume: true,
+            supports_mute: true,
+            supports_player_selection: true,
+            supports_state_query: true,
+        }
+    }
+}
+
+/// Extract MPRIS bus names from a `ListNames` reply
+fn parse_mpris_names(reply: &str) -> Vec<String> {
+    reply
+        .lines()
+        .filter_map(|line| {
+            let value = line.trim().strip_prefix("string \"")?.strip_suffix('"')?;
+            value.starts_with(MPRIS_PREFIX).then(|| value.to_string())
+        })
+        .collect()
+}
+
+/// Extract the playback status from a `Properties.Get` reply
+fn parse_playback_status(reply: &str) -> PlaybackStatus {
+    if reply.contains("\"Playing\"") {
+        PlaybackStatus::Playing
+    } else if reply.contains("\"Paused\"") {
+        PlaybackStatus::Paused
+    } else if reply.contains("\"Stopped\"") {
+        PlaybackStatus::Stopped
+    } else {
+        PlaybackStatus::Unknown
+    }
+}
+
+/// Extract the first channel percentage from `pactl get-sink-volume` output
+fn parse_pactl_volume(reply: &str) -> Option<u8> {
+    reply
+        .split_whitespace()
+        .find_map(|token| token.strip_suffix('%')?.parse::<u32>().ok())
+        .map(|level| level.min(100) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mpris_names() {
+        let reply = r#"method return time=1 sender=org.freedesktop.DBus
+   array [
+      string "org.freedesktop.DBus"
+      string "org.mpris.MediaPlayer2.spotify"
+      string ":1.42"
+      string "org.mpris.MediaPlayer2.vlc"
+   ]"#;
+        assert_eq!(
+            parse_mpris_names(reply),
+            vec!["org.mpris.MediaPlayer2.spotify", "org.mpris.MediaPlayer2.vlc"]
+        );
+    }
+
+    #[test]
+    fn test_parse_playback_status() {
+        assert_eq!(parse_playback_status("variant string \"Paused\""), PlaybackStatus::Paused);
+        assert_eq!(parse_playback_status(""), PlaybackStatus::Unknown);
+    }
+
+    #[test]
+    fn test_parse_pactl_volume() {
+        let reply = "Volume: front-left: 42598 /  65% / -11.23 dB,   front-right: 42598 /  65% / -11.23 dB";
+        assert_eq!(parse_pactl_volume(reply), Some(65));
+        assert_eq!(parse_pactl_volume("no volume"), None);
+    }
+}
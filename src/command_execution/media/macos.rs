@@ -0,0 +1,152 @@
+// macOS Media Backend
+//
+// Implements playback control by scripting the running media application and volume
+// control through the standard AppleScript `set volume` command.
+
+use crate::command_execution::error::{CommandError, CommandResult};
+use super::{apply_volume_delta, MediaBackend, MediaCapabilities, MediaCommand, MediaState, PlaybackStatus};
+use std::process::Command;
+
+/// Applications that understand the standard playback verbs
+const SCRIPTABLE_PLAYERS: &[&str] = &["Music", "Spotify"];
+
+/// macOS media backend using AppleScript
+pub struct MacOSMediaBackend;
+
+impl MacOSMediaBackend {
+    /// Create a new macOS media backend
+    pub fn new() -> CommandResult<Self> {
+        Ok(Self)
+    }
+
+    /// Run an AppleScript snippet and return its trimmed output
+    fn osascript(&self, script: &str) -> CommandResult<String> {
+        let output = Command::new("osascript")
+            .args(["-e", script])
+            .output()
+            .map_err(|e| CommandError::platform_error(format!("Failed to run osascript: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(CommandError::platform_error(format!(
+                "osascript failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Resolve the requested (or first running) scriptable player
+    fn resolve_player(&self, player: Option<&str>) -> CommandResult<&'static str> {
+        if let Some(name) = player {
+            return SCRIPTABLE_PLAYERS
+                .iter()
+                .copied()
+                .find(|p| p.eq_ignore_ascii_case(name))
+                .ok_or_else(|| CommandError::CommandNotFound(format!("Media player {} not supported", name)));
+        }
+
+        for candidate in SCRIPTABLE_PLAYERS {
+            let script = format!("application \"{}\" is running", candidate);
+            if self.osascript(&script)? == "true" {
+                return Ok(candidate);
+            }
+        }
+
+        Err(CommandError::CommandNotFound("No active media player".to_string()))
+    }
+
+    /// Read the output volume percentage
+    fn volume(&self) -> CommandResult<u8> {
+        self.osascript("output volume of (get volume settings)")?
+            .parse::<u8>()
+            .map_err(|_| CommandError::platform_error("Unable to parse output volume"))
+    }
+
+    /// Read the output mute state
+    fn muted(&self) -> CommandResult<bool> {
+        Ok(self.osascript("output muted of (get volume settings)")? == "true")
+    }
+}
+
+impl MediaBackend for MacOSMediaBackend {
+    fn execute(&self, command: MediaCommand, player: Option<&str>) -> CommandResult<()> {
+        let script = match command {
+            MediaCommand::SetVolume(level) => format!("set volume output volume {}", level.min(100)),
+            MediaCommand::AdjustVolume(delta) => {
+                let level = apply_volume_delta(self.volume()?, delta);
+                format!("set volume output volume {}", level)
+            }
+            MediaCommand::Mute => "set volume output muted true".to_string(),
+            MediaCommand::Unmute => "set volume output muted false".to_string(),
+            MediaCommand::ToggleMute => {
+                format!("set volume output muted {}", !self.muted()?)
+            }
+            playback => {
+                let app = self.resolve_player(player)?;
+                let verb = match playback {
+                    MediaCommand::Play => "play",
+                    MediaCommand::Pause => "pause",
+                    MediaCommand::PlayPause => "playpause",
+                    MediaCommand::Stop => "pause",
+                    MediaCommand::Next => "next track",
+                    MediaCommand::Previous => "previous track",
+                    _ => unreachable!("volume commands handled above"),
+                };
+                format!("tell application \"{}\" to {}", app, verb)
+            }
+        };
+
+        self.osascript(&script)?;
+        Ok(())
+    }
+
+    fn query_state(&self, player: Option<&str>) -> CommandResult<MediaState> {
+        let app = self.resolve_player(player).ok();
+        let status = app
+            .and_then(|app| {
+                self.osascript(&format!("tell application \"{}\" to player state as string", app))
+                    .ok()
+            })
+            .map(|state| match state.as_str() {
+                "playing" => PlaybackStatus::Playing,
+                "paused" => PlaybackStatus::Paused,
+                "stopped" => PlaybackStatus::Stopped,
+                _ => PlaybackStatus::Unknown,
+            })
+            .unwrap_or(PlaybackStatus::Unknown);
+
+        Ok(MediaState {
+            status,
+            volume: self.volume().ok(),
+            muted: self.muted().ok(),
+            player: app.map(str::to_string),
+        })
+    }
+
+    fn is_supported(&self) -> bool {
+        cfg!(target_os = "macos")
+    }
+
+    fn get_capabilities(&self) -> MediaCapabilities {
+        MediaCapabilities {
+            supports_playback: true,
+            supports_absolute_volume: true,
+            supports_mute: true,
+            supports_player_selection: true,
+            supports_state_query: true,
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "macos")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_macos_unknown_player_rejected() {
+        let backend = MacOSMediaBackend::new().unwrap();
+        assert!(backend.resolve_player(Some("Winamp")).is_err());
+    }
+}
@@ -0,0 +1,378 @@
+// Media Control for Cross-Device Playback
+//
+// This module exposes play/pause, track navigation, and volume control as a typed
+// API. Because requests are structured values rather than shell strings, media
+// control can be granted to semi-trusted peers without opening up command execution.
+
+use crate::command_execution::error::{CommandError, CommandResult};
+use crate::command_execution::types::*;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+#[cfg(target_os = "windows")]
+pub mod windows;
+#[cfg(target_os = "macos")]
+pub mod macos;
+#[cfg(target_os = "linux")]
+pub mod linux;
+
+/// Unique identifier for a media control request
+pub type MediaRequestId = Uuid;
+
+/// Media control command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaCommand {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    /// Set the output volume to an absolute percentage (0-100)
+    SetVolume(u8),
+    /// Adjust the output volume by a relative percentage
+    AdjustVolume(i8),
+    Mute,
+    Unmute,
+    ToggleMute,
+}
+
+impl MediaCommand {
+    /// Validate command parameters
+    pub fn validate(&self) -> CommandResult<()> {
+        match self {
+            MediaCommand::SetVolume(level) if *level > 100 => Err(CommandError::invalid_request(
+                format!("Volume must be between 0 and 100, got {}", level),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Risk level of the command; media control never modifies system state
+    /// beyond playback and output volume
+    pub fn risk_level(&self) -> RiskLevel {
+        RiskLevel::Low
+    }
+
+    /// Whether the command changes output volume or mute state
+    pub fn is_volume_command(&self) -> bool {
+        matches!(
+            self,
+            MediaCommand::SetVolume(_)
+                | MediaCommand::AdjustVolume(_)
+                | MediaCommand::Mute
+                | MediaCommand::Unmute
+                | MediaCommand::ToggleMute
+        )
+    }
+}
+
+/// Media control request sent by a peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaRequest {
+    pub request_id: MediaRequestId,
+    pub command: MediaCommand,
+    /// Optional player name (e.g. an MPRIS bus name suffix); `None` targets the active player
+    pub player: Option<String>,
+    pub requester: PeerId,
+    pub created_at: Timestamp,
+}
+
+impl MediaRequest {
+    /// Create a new media request targeting the active player
+    pub fn new(command: MediaCommand, requester: PeerId) -> Self {
+        Self {
+            request_id: Uuid::new_v4(),
+            command,
+            player: None,
+            requester,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Target a specific player
+    pub fn with_player(mut self, player: impl Into<String>) -> Self {
+        self.player = Some(player.into());
+        self
+    }
+}
+
+/// Result of a media control request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaResult {
+    pub request_id: MediaRequestId,
+    pub success: bool,
+    pub state: Option<MediaState>,
+    pub error: Option<String>,
+    pub completed_at: Timestamp,
+}
+
+/// Playback status reported by the platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+    Unknown,
+}
+
+/// Current media state as reported by the backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaState {
+    pub status: PlaybackStatus,
+    pub volume: Option<u8>,
+    pub muted: Option<bool>,
+    pub player: Option<String>,
+}
+
+impl Default for MediaState {
+    fn default() -> Self {
+        Self {
+            status: PlaybackStatus::Unknown,
+            volume: None,
+            muted: None,
+            player: None,
+        }
+    }
+}
+
+/// Media backend capabilities
+#[derive(Debug, Clone)]
+pub struct MediaCapabilities {
+    pub supports_playback: bool,
+    pub supports_absolute_volume: bool,
+    pub supports_mute: bool,
+    pub supports_player_selection: bool,
+    pub supports_state_query: bool,
+}
+
+/// Platform-specific media control backend trait
+pub trait MediaBackend: Send + Sync {
+    /// Execute a media command on the platform
+    fn execute(&self, command: MediaCommand, player: Option<&str>) -> CommandResult<()>;
+
+    /// Query the current media state
+    fn query_state(&self, player: Option<&str>) -> CommandResult<MediaState>;
+
+    /// Check if media control is supported on this platform
+    fn is_supported(&self) -> bool;
+
+    /// Get platform-specific capabilities
+    fn get_capabilities(&self) -> MediaCapabilities;
+}
+
+/// Media controller enforcing per-peer access on top of a platform backend
+pub struct MediaController {
+    backend: Box<dyn MediaBackend>,
+    allowed_peers: Arc<Mutex<HashSet<PeerId>>>,
+    allow_volume: bool,
+}
+
+impl MediaController {
+    /// Create a new media controller with the platform-specific backend
+    pub fn new() -> CommandResult<Self> {
+        Ok(Self::with_backend(Self::create_platform_backend()?))
+    }
+
+    /// Create a media controller with a custom backend
+    pub fn with_backend(backend: Box<dyn MediaBackend>) -> Self {
+        Self {
+            backend,
+            allowed_peers: Arc::new(Mutex::new(HashSet::new())),
+            allow_volume: true,
+        }
+    }
+
+    /// Create the appropriate platform-specific backend
+    pub(crate) fn create_platform_backend() -> CommandResult<Box<dyn MediaBackend>> {
+        #[cfg(target_os = "windows")]
+        {
+            Ok(Box::new(windows::WindowsMediaBackend::new()?))
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            Ok(Box::new(macos::MacOSMediaBackend::new()?))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            Ok(Box::new(linux::LinuxMediaBackend::new()?))
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+        {
+            Err(CommandError::PlatformError(
+                "Media control not supported on this platform".to_string()
+            ))
+        }
+    }
+
+    /// Allow a peer to issue media commands
+    pub fn allow_peer(&self, peer_id: impl Into<PeerId>) {
+        self.allowed_peers.lock().unwrap().insert(peer_id.into());
+    }
+
+    /// Revoke media control access for a peer
+    pub fn revoke_peer(&self, peer_id: &str) -> bool {
+        self.allowed_peers.lock().unwrap().remove(peer_id)
+    }
+
+    /// Check whether a peer may issue media commands
+    pub fn is_peer_allowed(&self, peer_id: &str) -> bool {
+        self.allowed_peers.lock().unwrap().contains(peer_id)
+    }
+
+    /// Enable or disable volume commands for remote peers
+    pub fn set_allow_volume(&mut self, allow: bool) {
+        self.allow_volume = allow;
+    }
+
+    /// Get backend capabilities
+    pub fn capabilities(&self) -> MediaCapabilities {
+        self.backend.get_capabilities()
+    }
+
+    /// Handle a media request from a peer
+    pub fn handle_request(&self, request: &MediaRequest) -> CommandResult<MediaResult> {
+        crate::lockdown::Lockdown::global()
+            .check(crate::lockdown::RemoteCapability::CommandExecution)
+            .map_err(|e| CommandError::authorization_denied(e.to_string()))?;
+
+        if !self.is_peer_allowed(&request.requester) {
+            return Err(CommandError::authorization_denied(format!(
+                "Peer {} is not allowed to control media",
+                request.requester
+            )));
+        }
+
+        if request.command.is_volume_command() && !self.allow_volume {
+            return Err(CommandError::permission_error("Volume control is disabled"));
+        }
+
+        request.command.validate()?;
+
+        if !self.backend.is_supported() {
+            return Err(CommandError::platform_error("Media control is not available"));
+        }
+
+        let outcome = self.backend.execute(request.command, request.player.as_deref());
+        let state = match outcome {
+            Ok(()) if self.backend.get_capabilities().supports_state_query => {
+                self.backend.query_state(request.player.as_deref()).ok()
+            }
+            _ => None,
+        };
+
+        Ok(MediaResult {
+            request_id: request.request_id,
+            success: outcome.is_ok(),
+            state,
+            error: outcome.err().map(|e| e.to_string()),
+            completed_at: Utc::now(),
+        })
+    }
+
+    /// Query the local media state
+    pub fn query_state(&self, player: Option<&str>) -> CommandResult<MediaState> {
+        self.backend.query_state(player)
+    }
+}
+
+/// Clamp a relative volume adjustment to the 0-100 range
+pub(crate) fn apply_volume_delta(current: u8, delta: i8) -> u8 {
+    (current as i16 + delta as i16).clamp(0, 100) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct RecordingBackend {
+        executed: Arc<Mutex<Vec<MediaCommand>>>,
+    }
+
+    impl MediaBackend for RecordingBackend {
+        fn execute(&self, command: MediaCommand, _player: Option<&str>) -> CommandResult<()> {
+            self.executed.lock().unwrap().push(command);
+            Ok(())
+        }
+
+        fn query_state(&self, _player: Option<&str>) -> CommandResult<MediaState> {
+            Ok(MediaState {
+                status: PlaybackStatus::Playing,
+                volume: Some(40),
+                muted: Some(false),
+                player: None,
+            })
+        }
+
+        fn is_supported(&self) -> bool {
+            true
+        }
+
+        fn get_capabilities(&self) -> MediaCapabilities {
+            MediaCapabilities {
+                supports_playback: true,
+                supports_absolute_volume: true,
+                supports_mute: true,
+                supports_player_selection: false,
+                supports_state_query: true,
+            }
+        }
+    }
+
+    fn controller() -> (MediaController, Arc<Mutex<Vec<MediaCommand>>>) {
+        let executed = Arc::new(Mutex::new(Vec::new()));
+        let backend = RecordingBackend { executed: executed.clone() };
+        (MediaController::with_backend(Box::new(backend)), executed)
+    }
+
+    #[test]
+    fn test_unknown_peer_denied() {
+        let (controller, executed) = controller();
+        let request = MediaRequest::new(MediaCommand::PlayPause, "stranger".to_string());
+
+        let result = controller.handle_request(&request);
+        assert!(matches!(result, Err(CommandError::AuthorizationDenied(_))));
+        assert!(executed.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_allowed_peer_executes() {
+        let (controller, executed) = controller();
+        controller.allow_peer("phone");
+
+        let request = MediaRequest::new(MediaCommand::Next, "phone".to_string());
+        let result = controller.handle_request(&request).unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.state.unwrap().status, PlaybackStatus::Playing);
+        assert_eq!(*executed.lock().unwrap(), vec![MediaCommand::Next]);
+    }
+
+    #[test]
+    fn test_volume_disabled() {
+        let (mut controller, _) = controller();
+        controller.allow_peer("phone");
+        controller.set_allow_volume(false);
+
+        let request = MediaRequest::new(MediaCommand::SetVolume(30), "phone".to_string());
+        assert!(matches!(
+            controller.handle_request(&request),
+            Err(CommandError::PermissionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_volume_validation() {
+        assert!(MediaCommand::SetVolume(100).validate().is_ok());
+        assert!(MediaCommand::SetVolume(101).validate().is_err());
+        assert_eq!(apply_volume_delta(95, 10), 100);
+        assert_eq!(apply_volume_delta(5, -10), 0);
+        assert_eq!(apply_volume_delta(50, -10), 40);
+    }
+}
@@ -0,0 +1,123 @@
+// Windows Media Backend
+//
+// Implements media control by synthesizing the virtual media and volume keys, which
+// every Windows media session (SMTC) and the system mixer respond to.
+
+use crate::command_execution::error::{CommandError, CommandResult};
+use super::{MediaBackend, MediaCapabilities, MediaCommand, MediaState};
+use winapi::um::winuser::{
+    keybd_event, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, VK_MEDIA_NEXT_TRACK,
+    VK_MEDIA_PLAY_PAUSE, VK_MEDIA_PREV_TRACK, VK_MEDIA_STOP, VK_VOLUME_DOWN,
+    VK_VOLUME_MUTE, VK_VOLUME_UP,
+};
+
+/// Volume percentage changed by a single volume key press
+const VOLUME_STEP_PERCENT: u8 = 2;
+
+/// Windows media backend using virtual media keys
+pub struct WindowsMediaBackend;
+
+impl WindowsMediaBackend {
+    /// Create a new Windows media backend
+    pub fn new() -> CommandResult<Self> {
+        Ok(Self)
+    }
+
+    /// Press and release a virtual key
+    fn tap_key(&self, vk: i32) {
+        unsafe {
+            keybd_event(vk as u8, 0, KEYEVENTF_EXTENDEDKEY, 0);
+            keybd_event(vk as u8, 0, KEYEVENTF_EXTENDEDKEY | KEYEVENTF_KEYUP, 0);
+        }
+    }
+
+    /// Press a virtual key repeatedly
+    fn tap_key_times(&self, vk: i32, times: u8) {
+        for _ in 0..times {
+            self.tap_key(vk);
+        }
+    }
+}
+
+impl MediaBackend for WindowsMediaBackend {
+    fn execute(&self, command: MediaCommand, player: Option<&str>) -> CommandResult<()> {
+        if player.is_some() {
+            return Err(CommandError::platform_error(
+                "Player selection is not supported on Windows",
+            ));
+        }
+
+        match command {
+            MediaCommand::PlayPause => self.tap_key(VK_MEDIA_PLAY_PAUSE),
+            // Media keys only expose a toggle, which would invert an explicit play or pause
+            MediaCommand::Play | MediaCommand::Pause => {
+                return Err(CommandError::platform_error(
+                    "Only play/pause toggling is supported on Windows",
+                ));
+            }
+            MediaCommand::Stop => self.tap_key(VK_MEDIA_STOP),
+            MediaCommand::Next => self.tap_key(VK_MEDIA_NEXT_TRACK),
+            MediaCommand::Previous => self.tap_key(VK_MEDIA_PREV_TRACK),
+            MediaCommand::SetVolume(level) => {
+                // Drive the mixer to zero, then step up to the requested level
+                self.tap_key_times(VK_VOLUME_DOWN, 100 / VOLUME_STEP_PERCENT);
+                self.tap_key_times(VK_VOLUME_UP, level.min(100) / VOLUME_STEP_PERCENT);
+            }
+            MediaCommand::AdjustVolume(0) => {}
+            MediaCommand::AdjustVolume(delta) => {
+                let steps = (delta.unsigned_abs() / VOLUME_STEP_PERCENT).max(1);
+                let vk = if delta >= 0 { VK_VOLUME_UP } else { VK_VOLUME_DOWN };
+                self.tap_key_times(vk, steps);
+            }
+            MediaCommand::ToggleMute => self.tap_key(VK_VOLUME_MUTE),
+            // The mute key only toggles, so an explicit mute or unmute could do the opposite
+            MediaCommand::Mute | MediaCommand::Unmute => {
+                return Err(CommandError::platform_error(
+                    "Only mute toggling is supported on Windows",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn query_state(&self, _player: Option<&str>) -> CommandResult<MediaState> {
+        // Media keys are fire-and-forget; state requires the WinRT session manager
+        Ok(MediaState::default())
+    }
+
+    fn is_supported(&self) -> bool {
+        cfg!(target_os = "windows")
+    }
+
+    fn get_capabilities(&self) -> MediaCapabilities {
+        MediaCapabilities {
+            supports_playback: true,
+            supports_absolute_volume: true,
+            supports_mute: true,
+            supports_player_selection: false,
+            supports_state_query: false,
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "windows")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_capabilities() {
+        let backend = WindowsMediaBackend::new().unwrap();
+        assert!(backend.is_supported());
+        assert!(!backend.get_capabilities().supports_player_selection);
+    }
+
+    #[test]
+    fn test_windows_rejects_absolute_toggles() {
+        let backend = WindowsMediaBackend::new().unwrap();
+        assert!(backend.execute(MediaCommand::Mute, None).is_err());
+        assert!(backend.execute(MediaCommand::Pause, None).is_err());
+        assert!(backend.execute(MediaCommand::AdjustVolume(0), None).is_ok());
+    }
+}
@@ -2,65 +2,114 @@
 //
 // This module provides secure remote command execution capabilities with sandboxing,
 // authorization controls, and cross-platform compatibility.
+//
+// Notifications, media control, URL handoff and locate act only on the local
+// device and are always built; remote command and script execution need the
+// `command-execution` feature.
 
+pub mod error;
+pub mod types;
+pub mod notification;
+pub mod media;
+pub mod handoff;
+pub mod locate;
+#[cfg(feature = "command-execution")]
 pub mod manager;
+#[cfg(feature = "command-execution")]
 pub mod sandbox;
+#[cfg(feature = "command-execution")]
 pub mod auth;
+#[cfg(feature = "command-execution")]
 pub mod script;
-pub mod error;
-pub mod types;
+#[cfg(feature = "command-execution")]
 pub mod platform;
+#[cfg(feature = "command-execution")]
 pub mod system_info;
-pub mod notification;
+#[cfg(feature = "command-execution")]
 pub mod template;
+#[cfg(feature = "command-execution")]
 pub mod template_sharing;
+#[cfg(feature = "command-execution")]
 pub mod scheduler;
+#[cfg(feature = "command-execution")]
 pub mod history;
+#[cfg(feature = "command-execution")]
 pub mod audit;
+#[cfg(feature = "command-execution")]
 pub mod security_integration;
+#[cfg(feature = "command-execution")]
 pub mod transport_integration;
+#[cfg(feature = "command-execution")]
 pub mod api;
 
 // Re-export main types and traits
 pub use error::{CommandError, CommandResult as CmdResult};
 pub use types::*;
+#[cfg(feature = "command-execution")]
 pub use manager::CommandManager;
+#[cfg(feature = "command-execution")]
 pub use sandbox::SandboxEngine;
+#[cfg(feature = "command-execution")]
 pub use auth::AuthorizationManager;
+#[cfg(feature = "command-execution")]
 pub use script::ScriptEngine;
+#[cfg(feature = "command-execution")]
 pub use platform::{UnifiedCommandManager, CommandTranslator, Platform};
+#[cfg(feature = "command-execution")]
 pub use system_info::SystemInfoProvider;
 pub use notification::{
     NotificationManager, NotificationBackend, NotificationCapabilities, NotificationRecord,
+    NotificationActivation, ActivationHandler, NotificationEvent, EventHandler, CloseReason,
     NotificationFormatter, NotificationBuilder, FormattedNotification, NotificationStyle,
     DeliveryService, DeliveryTracker, DeliveryInfo, DeliveryAnalytics,
+    NotificationMirror, MirrorConfig, MirrorSink, NotificationSource, CapturedNotification, CaptureEvent,
+};
+pub use media::{
+    MediaController, MediaBackend, MediaCapabilities, MediaCommand, MediaRequest,
+    MediaResult, MediaState, PlaybackStatus,
+};
+pub use handoff::{
+    HandoffController, HandoffRequest, HandoffResult, HandoffTarget, LaunchBackend, SystemLauncher,
+    DEFAULT_ALLOWED_SCHEMES,
+};
+pub use locate::{
+    AlertSound, LocateAction, LocateConfig, LocateController, LocateEvent, LocateRequest, LocateResult,
+    LocateStopReason, SystemAlertSound, MAX_LOCATE_MESSAGE_LEN,
 };
+#[cfg(feature = "command-execution")]
 pub use template::{
     TemplateManager, CommandTemplate, TemplateParameter, ParameterType,
     TemplateInstantiationRequest, ValidationResult, ValidationError, TemplateId,
 };
+#[cfg(feature = "command-execution")]
 pub use template_sharing::{
     TemplateSharingManager, TemplateShareRequest, TemplatePermissions,
     SharedTemplate, SyncStatus, TemplateUpdate,
 };
+#[cfg(feature = "command-execution")]
 pub use scheduler::{
     Scheduler, ScheduledTask, ScheduledTaskType, Schedule, ScheduleType,
     ScheduledExecutionResult, ScheduleId,
 };
+#[cfg(feature = "command-execution")]
 pub use history::{
     HistoryManager, SqliteHistoryManager, HistoryFilter,
 };
+#[cfg(feature = "command-execution")]
 pub use audit::{
     AuditLogger, SqliteAuditLogger, AuditLogEntry, AuditEventType,
     AuditSeverity, AuditFilter, create_authorization_log, create_security_event_log,
 };
+#[cfg(feature = "command-execution")]
 pub use security_integration::{
     CommandSecurityIntegration, EncryptedCommandMessage, CommandMessageType,
     CommandMessage, SecureCommandTransmission,
 };
+#[cfg(feature = "command-execution")]
 pub use transport_integration::{
     CommandTransportIntegration, CommandExecutionApi, CommandExecutionConfig,
 };
+#[cfg(feature = "command-execution")]
 pub use api::{
     CommandExecution, CommandExecutionBuilder, CommandExecutionEvent,
     CommandExecutionCallback,
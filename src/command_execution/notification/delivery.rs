@@ -4,6 +4,7 @@
 
 use crate::command_execution::error::{CommandError, CommandResult};
 use crate::command_execution::types::*;
+use super::{CloseReason, NotificationEvent};
 use chrono::Utc;
 use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
@@ -107,6 +108,10 @@ impl NotificationQueue {
 }
 
 /// Notification delivery tracker
+///
+/// Clones share the same records, so backends can report clicks and closes
+/// from their own threads.
+#[derive(Clone)]
 pub struct DeliveryTracker {
     deliveries: Arc<Mutex<HashMap<NotificationId, DeliveryInfo>>>,
     analytics: Arc<Mutex<DeliveryAnalytics>>,
@@ -122,6 +127,12 @@ pub struct DeliveryInfo {
     pub last_attempt: Option<Timestamp>,
     pub delivered_at: Option<Timestamp>,
     pub error_message: Option<String>,
+    /// When the user last clicked the notification
+    pub activated_at: Option<Timestamp>,
+    /// Action clicked last, `None` when the notification body was clicked
+    pub activated_action: Option<String>,
+    pub closed_at: Option<Timestamp>,
+    pub close_reason: Option<CloseReason>,
 }
 
 /// Delivery analytics and statistics
@@ -133,6 +144,10 @@ pub struct DeliveryAnalytics {
     pub total_cancelled: usize,
     pub average_delivery_time: Duration,
     pub retry_rate: f64,
+    /// Notifications the user clicked
+    pub total_activated: usize,
+    /// Notifications the user dismissed
+    pub total_dismissed: usize,
 }
 
 impl DeliveryTracker {
@@ -160,6 +175,10 @@ impl DeliveryTracker {
                 last_attempt: None,
                 delivered_at: None,
                 error_message: None,
+                activated_at: None,
+                activated_action: None,
+                closed_at: None,
+                close_reason: None,
             });
         }
         
@@ -226,6 +245,32 @@ impl DeliveryTracker {
         }
     }
     
+    /// Record a click or close reported by the notification backend
+    ///
+    /// Events for notifications this tracker never saw are ignored.
+    pub fn record_event(&self, event: &NotificationEvent) {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        
+        match event {
+            NotificationEvent::Activated(activation) => {
+                if let Some(info) = deliveries.get_mut(&activation.notification_id) {
+                    info.activated_at = Some(Utc::now());
+                    info.activated_action = activation.action_id.clone();
+                    self.analytics.lock().unwrap().total_activated += 1;
+                }
+            }
+            NotificationEvent::Closed { notification_id, reason } => {
+                if let Some(info) = deliveries.get_mut(notification_id) {
+                    info.closed_at = Some(Utc::now());
+                    info.close_reason = Some(*reason);
+                    if *reason == CloseReason::Dismissed {
+                        self.analytics.lock().unwrap().total_dismissed += 1;
+                    }
+                }
+            }
+        }
+    }
+    
     /// Get delivery information for a notification
     pub fn get_delivery_info(&self, notification_id: NotificationId) -> Option<DeliveryInfo> {
         let deliveries = self.deliveries.lock().unwrap();
@@ -344,11 +389,21 @@ impl DeliveryService {
         self.tracker.get_all_deliveries()
     }
     
+    /// Start tracking a delivery attempt
+    pub fn track_attempt(&self, notification_id: NotificationId) {
+        self.tracker.track_attempt(notification_id);
+    }
+    
     /// Mark a notification as delivered
     pub fn mark_delivered(&self, notification_id: NotificationId) {
         self.tracker.mark_delivered(notification_id);
     }
     
+    /// Handle to the tracker for reporting notification events
+    pub fn tracker(&self) -> DeliveryTracker {
+        self.tracker.clone()
+    }
+    
     /// Mark a notification as failed
     pub fn mark_failed(&self, notification_id: NotificationId, error: String) {
         self.tracker.mark_failed(notification_id, error);
@@ -375,6 +430,8 @@ mod tests {
             duration: None,
             actions: vec![],
             sender: "test-peer".to_string(),
+            image: None,
+            launch: None,
         }
     }
 
@@ -406,6 +463,31 @@ mod tests {
         assert!(matches!(info.unwrap().status, DeliveryStatus::Delivered));
     }
 
+    #[test]
+    fn test_delivery_tracker_records_clicks_and_dismissals() {
+        let tracker = DeliveryTracker::new();
+        let notification_id = Uuid::new_v4();
+        tracker.track_attempt(notification_id);
+        tracker.mark_delivered(notification_id);
+        
+        tracker.record_event(&NotificationEvent::Activated(super::super::NotificationActivation {
+            notification_id,
+            action_id: Some("open".to_string()),
+            launch: None,
+        }));
+        tracker.record_event(&NotificationEvent::Closed { notification_id, reason: CloseReason::Dismissed });
+        // Notifications from before this tracker existed are ignored
+        tracker.record_event(&NotificationEvent::Closed { notification_id: Uuid::new_v4(), reason: CloseReason::Dismissed });
+        
+        let info = tracker.get_delivery_info(notification_id).unwrap();
+        assert_eq!(info.activated_action.as_deref(), Some("open"));
+        assert_eq!(info.close_reason, Some(CloseReason::Dismissed));
+        
+        let analytics = tracker.get_analytics();
+        assert_eq!(analytics.total_activated, 1);
+        assert_eq!(analytics.total_dismissed, 1);
+    }
+
     #[test]
     fn test_delivery_analytics() {
         let tracker = DeliveryTracker::new();
@@ -183,6 +183,8 @@ pub struct NotificationBuilder {
     duration: Option<Duration>,
     actions: Vec<NotificationAction>,
     sender: PeerId,
+    image: Option<String>,
+    launch: Option<String>,
 }
 
 impl NotificationBuilder {
@@ -196,6 +198,8 @@ impl NotificationBuilder {
             duration: None,
             actions: vec![],
             sender,
+            image: None,
+            launch: None,
         }
     }
     
@@ -226,6 +230,18 @@ impl NotificationBuilder {
         self
     }
     
+    /// Show a picture from a local file with the notification
+    pub fn image(mut self, path: impl Into<String>) -> Self {
+        self.image = Some(path.into());
+        self
+    }
+    
+    /// Set the argument passed to the activation handler on click
+    pub fn launch(mut self, argument: impl Into<String>) -> Self {
+        self.launch = Some(argument.into());
+        self
+    }
+    
     /// Build the notification
     pub fn build(self) -> Notification {
         Notification {
@@ -237,6 +253,8 @@ impl NotificationBuilder {
             duration: self.duration,
             actions: self.actions,
             sender: self.sender,
+            image: self.image,
+            launch: self.launch,
         }
     }
 }
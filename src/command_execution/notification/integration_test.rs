@@ -145,9 +145,9 @@ mod tests {
         let capabilities = manager.get_capabilities();
         
         // Capabilities should be platform-specific
+        // Actions on Linux depend on the running notification server
         #[cfg(target_os = "linux")]
         {
-            assert!(capabilities.supports_actions);
             assert!(capabilities.supports_duration);
             assert!(capabilities.supports_priority);
         }
@@ -160,7 +160,8 @@ mod tests {
         
         #[cfg(target_os = "macos")]
         {
-            assert!(capabilities.supports_actions);
+            // Only available when running from the app bundle
+            assert_eq!(capabilities.supports_actions, manager.is_supported());
         }
     }
 }
@@ -1,14 +1,57 @@
 // Linux Notification Backend
 //
-// Implements Linux notifications using libnotify and desktop notification standards
+// Implements Linux notifications over the org.freedesktop.Notifications D-Bus
+// interface. Notifications are sent with `gdbus call` so action buttons,
+// urgency and image hints and `replaces_id` can all be used, and a
+// `gdbus monitor` process reports ActionInvoked and NotificationClosed
+// signals back to the event handler.
 
 use crate::command_execution::error::{CommandError, CommandResult};
 use crate::command_execution::types::*;
-use super::{NotificationBackend, NotificationCapabilities};
+use super::{
+    CloseReason, EventHandler, NotificationActivation, NotificationBackend, NotificationCapabilities,
+    NotificationEvent,
+};
+use super::mirror::{CaptureEvent, CapturedNotification, NotificationSource};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc;
 
-/// Linux notification backend using libnotify
+const NOTIFICATIONS_DEST: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Action key the server reports when the notification body is clicked
+const DEFAULT_ACTION: &str = "default";
+
+/// Linux notification backend using the desktop notification D-Bus interface
 pub struct LinuxNotificationBackend {
     app_name: String,
+    shown: Arc<Mutex<ShownNotifications>>,
+    event_handler: Arc<RwLock<Option<EventHandler>>>,
+    /// Capabilities the notification server reported, once it answered
+    server_capabilities: Mutex<Option<Vec<String>>>,
+    /// `gdbus monitor` process watching for server signals
+    monitor: Mutex<Option<std::process::Child>>,
+}
+
+/// Server-assigned IDs of the notifications this backend has shown
+#[derive(Default)]
+struct ShownNotifications {
+    server_ids: HashMap<NotificationId, u32>,
+    /// Server ID to our ID and the notification's launch argument
+    by_server_id: HashMap<u32, (NotificationId, Option<String>)>,
+}
+
+/// A notification server signal read from `gdbus monitor`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ServerSignal {
+    ActionInvoked { id: u32, action_key: String },
+    Closed { id: u32, reason: CloseReason },
 }
 
 impl LinuxNotificationBackend {
@@ -16,37 +59,19 @@ impl LinuxNotificationBackend {
     pub fn new() -> CommandResult<Self> {
         Ok(Self {
             app_name: "Kizuna Command Execution".to_string(),
+            shown: Arc::new(Mutex::new(ShownNotifications::default())),
+            event_handler: Arc::new(RwLock::new(None)),
+            server_capabilities: Mutex::new(None),
+            monitor: Mutex::new(None),
         })
     }
     
-    /// Format notification for Linux
-    fn format_notification(&self, notification: &Notification) -> String {
-        format!(
-            "[{}] {}: {}",
-            self.format_type(notification.notification_type),
-            notification.title,
-            notification.message
-        )
-    }
-    
-    /// Format notification type for display
-    fn format_type(&self, notification_type: NotificationType) -> &str {
-        match notification_type {
-            NotificationType::Info => "INFO",
-            NotificationType::Warning => "WARNING",
-            NotificationType::Error => "ERROR",
-            NotificationType::Success => "SUCCESS",
-        }
-    }
-    
-    /// Get urgency level for libnotify
-    fn get_urgency(&self, notification_type: NotificationType, priority: NotificationPriority) -> &str {
-        match (notification_type, priority) {
-            (NotificationType::Error, _) => "critical",
-            (_, NotificationPriority::Critical) => "critical",
-            (NotificationType::Warning, _) => "normal",
-            (_, NotificationPriority::High) => "normal",
-            _ => "low",
+    /// Urgency hint for a priority: 0 low, 1 normal, 2 critical
+    fn urgency(priority: NotificationPriority) -> u8 {
+        match priority {
+            NotificationPriority::Low => 0,
+            NotificationPriority::Normal | NotificationPriority::High => 1,
+            NotificationPriority::Critical => 2,
         }
     }
     
@@ -59,34 +84,151 @@ impl LinuxNotificationBackend {
             NotificationType::Success => "dialog-information",
         }
     }
+    
+    /// Capabilities of the running notification server, empty if none answered
+    ///
+    /// Only a successful answer is cached, so a server started later is
+    /// picked up on the next notification.
+    fn server_capabilities(&self) -> Vec<String> {
+        let mut cached = self.server_capabilities.lock().unwrap();
+        if let Some(capabilities) = cached.as_ref() {
+            return capabilities.clone();
+        }
+        
+        match gdbus_call("GetCapabilities", &[]) {
+            Ok(reply) => {
+                let capabilities = parse_string_array(&reply);
+                *cached = Some(capabilities.clone());
+                capabilities
+            }
+            Err(e) => {
+                log::debug!("Notification server unavailable: {}", e);
+                Vec::new()
+            }
+        }
+    }
+    
+    /// Start watching for ActionInvoked and NotificationClosed signals
+    fn ensure_monitor(&self) {
+        let mut monitor = self.monitor.lock().unwrap();
+        if monitor.is_some() {
+            return;
+        }
+        
+        let child = std::process::Command::new("gdbus")
+            .args(["monitor", "--session", "--dest", NOTIFICATIONS_DEST, "--object-path", NOTIFICATIONS_PATH])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                log::warn!("Failed to start gdbus monitor, notification clicks will not be reported: {}", e);
+                return;
+            }
+        };
+        let Some(stdout) = child.stdout.take() else {
+            return;
+        };
+        
+        let shown = Arc::clone(&self.shown);
+        let handler = Arc::clone(&self.event_handler);
+        std::thread::spawn(move || {
+            for line in std::io::BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Some(event) = parse_signal(&line).and_then(|signal| own_event(&shown, signal))
+                    && let Some(handler) = handler.read().unwrap().as_ref()
+                {
+                    handler(event);
+                }
+            }
+        });
+        
+        *monitor = Some(child);
+    }
+    
+    /// Arguments for a `Notify` call
+    fn notify_arguments(&self, notification: &Notification, replaces_id: u32, capabilities: &[String]) -> Vec<String> {
+        let supports = |capability: &str| capabilities.iter().any(|c| c == capability);
+        
+        let body = if supports("body-markup") {
+            escape_markup(&notification.message)
+        } else {
+            notification.message.clone()
+        };
+        
+        let mut actions = Vec::new();
+        if supports("actions") {
+            actions.push(DEFAULT_ACTION.to_string());
+            actions.push("Open".to_string());
+            for action in &notification.actions {
+                actions.push(action.id.clone());
+                actions.push(action.label.clone());
+            }
+        }
+        
+        let mut hints = vec![
+            format!("'urgency': <byte {}>", Self::urgency(notification.priority)),
+            "'desktop-entry': <'kizuna'>".to_string(),
+        ];
+        if let Some(image) = notification.image.as_deref()
+            && std::path::Path::new(image).is_absolute()
+        {
+            hints.push(format!("'image-path': <{}>", gvariant_string(image)));
+        }
+        
+        let expire_timeout = notification
+            .duration
+            .map(|duration| duration.as_millis().min(i32::MAX as u128) as i32)
+            .unwrap_or(-1);
+        
+        vec![
+            gvariant_string(&self.app_name),
+            replaces_id.to_string(),
+            gvariant_string(self.get_icon_name(notification.notification_type)),
+            gvariant_string(&notification.title),
+            gvariant_string(&body),
+            gvariant_string_array(&actions),
+            format!("{{{}}}", hints.join(", ")),
+            expire_timeout.to_string(),
+        ]
+    }
 }
 
 impl NotificationBackend for LinuxNotificationBackend {
     fn show_notification(&self, notification: &Notification) -> CommandResult<()> {
-        #[cfg(target_os = "linux")]
-        {
-            // In a production implementation, this would use:
-            // - libnotify via notify-rust crate
-            // - D-Bus notifications via org.freedesktop.Notifications
-            // - Desktop notification specification
-            
-            let formatted = self.format_notification(notification);
-            let urgency = self.get_urgency(notification.notification_type, notification.priority);
-            let icon = self.get_icon_name(notification.notification_type);
-            
-            // Placeholder: In production, this would call libnotify
-            // For now, we'll simulate success
-            eprintln!("Linux Notification [{}] [{}]: {}", urgency, icon, formatted);
-            
-            Ok(())
+        let capabilities = self.server_capabilities();
+        if capabilities.is_empty() {
+            return Err(CommandError::NotificationError("No notification server is running".to_string()));
         }
+        self.ensure_monitor();
         
-        #[cfg(not(target_os = "linux"))]
-        {
-            Err(CommandError::NotificationError(
-                "Linux notifications only supported on Linux".to_string()
-            ))
+        // Showing a notification again replaces the one already on screen
+        let replaces_id = self
+            .shown
+            .lock()
+            .unwrap()
+            .server_ids
+            .get(&notification.notification_id)
+            .copied()
+            .unwrap_or(0);
+        
+        let reply = gdbus_call("Notify", &self.notify_arguments(notification, replaces_id, &capabilities))?;
+        let server_id = parse_uint32_reply(&reply).ok_or_else(|| {
+            CommandError::NotificationError(format!("Unexpected Notify reply: {}", reply.trim()))
+        })?;
+        
+        let mut shown = self.shown.lock().unwrap();
+        if let Some(previous) = shown.server_ids.insert(notification.notification_id, server_id) {
+            shown.by_server_id.remove(&previous);
         }
+        shown
+            .by_server_id
+            .insert(server_id, (notification.notification_id, notification.launch.clone()));
+        
+        Ok(())
     }
     
     fn is_supported(&self) -> bool {
@@ -94,15 +236,394 @@ impl NotificationBackend for LinuxNotificationBackend {
     }
     
     fn get_capabilities(&self) -> NotificationCapabilities {
+        let capabilities = self.server_capabilities();
+        let actions = capabilities.iter().any(|c| c == "actions");
+        
         NotificationCapabilities {
-            supports_actions: true,
+            supports_actions: actions,
             supports_duration: true,
             supports_priority: true,
             supports_icons: true,
+            // image-path is a standard hint, but only a running server can show it
+            supports_images: !capabilities.is_empty(),
+            supports_activation: actions,
+            supports_replace: !capabilities.is_empty(),
+            // LinuxNotificationSource watches the same session bus
+            supports_mirroring: !capabilities.is_empty(),
             max_title_length: None, // No strict limit
             max_message_length: None, // No strict limit
         }
     }
+    
+    fn set_event_handler(&self, handler: EventHandler) {
+        *self.event_handler.write().unwrap() = Some(handler);
+    }
+    
+    fn close_notification(&self, notification_id: NotificationId) -> CommandResult<()> {
+        let Some(server_id) = self.shown.lock().unwrap().server_ids.get(&notification_id).copied() else {
+            return Ok(());
+        };
+        gdbus_call("CloseNotification", &[server_id.to_string()]).map(drop)
+    }
+}
+
+impl Drop for LinuxNotificationBackend {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.monitor.lock().unwrap().take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Call an org.freedesktop.Notifications method and return gdbus's output
+fn gdbus_call(method: &str, arguments: &[String]) -> CommandResult<String> {
+    let output = std::process::Command::new("gdbus")
+        .args(["call", "--session", "--dest", NOTIFICATIONS_DEST, "--object-path", NOTIFICATIONS_PATH])
+        .arg("--method")
+        .arg(format!("{}.{}", NOTIFICATIONS_DEST, method))
+        .args(arguments)
+        .output()
+        .map_err(|e| CommandError::NotificationError(format!("Failed to run gdbus: {}", e)))?;
+    
+    if !output.status.success() {
+        return Err(CommandError::NotificationError(format!(
+            "{} failed: {}",
+            method,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Translate a server signal into an event, if it is about one of our notifications
+fn own_event(shown: &Mutex<ShownNotifications>, signal: ServerSignal) -> Option<NotificationEvent> {
+    let mut shown = shown.lock().unwrap();
+    match signal {
+        ServerSignal::ActionInvoked { id, action_key } => {
+            let (notification_id, launch) = shown.by_server_id.get(&id).cloned()?;
+            Some(NotificationEvent::Activated(NotificationActivation {
+                notification_id,
+                action_id: (action_key != DEFAULT_ACTION).then_some(action_key),
+                launch,
+            }))
+        }
+        ServerSignal::Closed { id, reason } => {
+            let (notification_id, _) = shown.by_server_id.remove(&id)?;
+            shown.server_ids.remove(&notification_id);
+            Some(NotificationEvent::Closed { notification_id, reason })
+        }
+    }
+}
+
+/// Parse a `gdbus monitor` line for the two signals we care about
+fn parse_signal(line: &str) -> Option<ServerSignal> {
+    let (_, signal) = line.split_once(&format!("{}.", NOTIFICATIONS_DEST))?;
+    let (member, arguments) = signal.split_once(' ')?;
+    let arguments = arguments.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let (id, rest) = arguments.split_once(", ")?;
+    let id = id.strip_prefix("uint32 ")?.parse().ok()?;
+
+    match member {
+        "ActionInvoked" => Some(ServerSignal::ActionInvoked {
+            id,
+            action_key: parse_gvariant_string(rest)?,
+        }),
+        "NotificationClosed" => {
+            let reason = match rest.strip_prefix("uint32 ")?.parse::<u32>().ok()? {
+                1 => CloseReason::Expired,
+                2 => CloseReason::Dismissed,
+                3 => CloseReason::Closed,
+                _ => CloseReason::Unknown,
+            };
+            Some(ServerSignal::Closed { id, reason })
+        }
+        _ => None,
+    }
+}
+
+/// The number in a `(uint32 N,)` reply
+fn parse_uint32_reply(reply: &str) -> Option<u32> {
+    reply.trim().strip_prefix("(uint32 ")?.strip_suffix(",)")?.parse().ok()
+}
+
+/// The strings in an `(['a', 'b'],)` reply
+fn parse_string_array(reply: &str) -> Vec<String> {
+    let Some(inner) = reply
+        .trim()
+        .strip_prefix("([")
+        .and_then(|rest| rest.strip_suffix("],)"))
+    else {
+        return Vec::new();
+    };
+    inner.split(", ").filter_map(parse_gvariant_string).collect()
+}
+
+/// A string in GVariant text format, quoted for gdbus
+fn gvariant_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace('\n', "\\n");
+    format!("'{}'", escaped)
+}
+
+fn gvariant_string_array(values: &[String]) -> String {
+    if values.is_empty() {
+        return "@as []".to_string();
+    }
+    let items: Vec<String> = values.iter().map(|value| gvariant_string(value)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Unquote a single- or double-quoted GVariant string
+fn parse_gvariant_string(value: &str) -> Option<String> {
+    let value = value.trim();
+    let inner = value
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+        .or_else(|| value.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')))?;
+    Some(inner.replace("\\'", "'").replace("\\\"", "\"").replace("\\\\", "\\"))
+}
+
+/// Escape text for servers that render a subset of HTML in the body
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Captures desktop notifications by watching the session bus
+///
+/// Runs `dbus-monitor` on the `org.freedesktop.Notifications` interface and pairs
+/// each `Notify` call with the ID the notification server returns for it.
+pub struct LinuxNotificationSource {
+    monitor: tokio::sync::Mutex<Option<tokio::process::Child>>,
+}
+
+impl LinuxNotificationSource {
+    /// Create a capture source for the session bus
+    pub fn new() -> Self {
+        Self {
+            monitor: tokio::sync::Mutex::new(None),
+        }
+    }
+}
+
+impl Default for LinuxNotificationSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl NotificationSource for LinuxNotificationSource {
+    async fn start(&self, events: mpsc::Sender<CaptureEvent>) -> CommandResult<()> {
+        let mut monitor = self.monitor.lock().await;
+        if monitor.is_some() {
+            return Ok(());
+        }
+
+        let mut child = Command::new("dbus-monitor")
+            .arg("--session")
+            .arg("interface='org.freedesktop.Notifications'")
+            .arg("type='method_return'")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| CommandError::NotificationError(format!("Failed to start dbus-monitor: {}", e)))?;
+
+        let stdout = child.stdout.take().ok_or_else(|| {
+            CommandError::NotificationError("dbus-monitor has no output".to_string())
+        })?;
+
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            let mut parser = DbusMonitorParser::default();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Some(event) = parser.feed(&line) && events.send(event).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        *monitor = Some(child);
+        Ok(())
+    }
+
+    async fn stop(&self) -> CommandResult<()> {
+        if let Some(mut child) = self.monitor.lock().await.take() {
+            let _ = child.kill().await;
+        }
+        Ok(())
+    }
+
+    async fn dismiss(&self, source_id: &str) -> CommandResult<()> {
+        let id: u32 = source_id.parse().map_err(|_| {
+            CommandError::NotificationError(format!("Invalid notification ID: {}", source_id))
+        })?;
+
+        let status = Command::new("gdbus")
+            .args([
+                "call", "--session",
+                "--dest", "org.freedesktop.Notifications",
+                "--object-path", "/org/freedesktop/Notifications",
+                "--method", "org.freedesktop.Notifications.CloseNotification",
+            ])
+            .arg(id.to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .map_err(|e| CommandError::NotificationError(format!("Failed to run gdbus: {}", e)))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(CommandError::NotificationError(format!("CloseNotification failed for {}", id)))
+        }
+    }
+
+    fn is_supported(&self) -> bool {
+        cfg!(target_os = "linux")
+    }
+}
+
+/// A bus message being read from `dbus-monitor` output
+enum PendingMessage {
+    Notify {
+        sender: String,
+        serial: u32,
+        strings: Vec<String>,
+        desktop_entry: Option<String>,
+        urgency: Option<u8>,
+        last_key: Option<String>,
+    },
+    Reply { key: (String, u32) },
+    Closed,
+    Other,
+}
+
+/// Turns `dbus-monitor` output into capture events
+///
+/// A `Notify` call is reported once the server's reply tells us the ID it
+/// assigned, since that ID is what `NotificationClosed` and `CloseNotification`
+/// use. Calls are matched to replies by sender and serial.
+#[derive(Default)]
+pub(crate) struct DbusMonitorParser {
+    current: Option<PendingMessage>,
+    awaiting_reply: HashMap<(String, u32), CapturedNotification>,
+}
+
+impl DbusMonitorParser {
+    /// Feed one line of output, returning an event when one is complete
+    pub(crate) fn feed(&mut self, line: &str) -> Option<CaptureEvent> {
+        if !line.starts_with(' ') {
+            self.finish_current();
+            self.current = Some(Self::parse_header(line));
+            return None;
+        }
+
+        let trimmed = line.trim();
+        let top_level = line.len() - line.trim_start().len() == 3;
+        match self.current.as_mut()? {
+            PendingMessage::Notify { strings, desktop_entry, urgency, last_key, .. } => {
+                if top_level {
+                    if let Some(value) = string_value(trimmed) {
+                        strings.push(value);
+                    }
+                } else if let Some(value) = trimmed.strip_prefix("variant").map(str::trim) {
+                    match last_key.take().as_deref() {
+                        Some("desktop-entry") => *desktop_entry = string_value(value),
+                        Some("urgency") => {
+                            *urgency = value.strip_prefix("byte").and_then(|v| v.trim().parse().ok());
+                        }
+                        _ => {}
+                    }
+                } else if let Some(key) = string_value(trimmed) {
+                    *last_key = Some(key);
+                }
+                None
+            }
+            PendingMessage::Reply { key } => {
+                let key = key.clone();
+                self.current = Some(PendingMessage::Other);
+                let id = uint32_value(trimmed)?;
+                let mut captured = self.awaiting_reply.remove(&key)?;
+                captured.source_id = id.to_string();
+                Some(CaptureEvent::Posted(captured))
+            }
+            PendingMessage::Closed => {
+                self.current = Some(PendingMessage::Other);
+                let id = uint32_value(trimmed)?;
+                Some(CaptureEvent::Dismissed { source_id: id.to_string() })
+            }
+            PendingMessage::Other => None,
+        }
+    }
+
+    fn parse_header(line: &str) -> PendingMessage {
+        let field = |name: &str| {
+            line.split([' ', ';'])
+                .find_map(|part| part.strip_prefix(name)?.strip_prefix('='))
+                .map(str::to_string)
+        };
+
+        if line.starts_with("method call") && field("member").as_deref() == Some("Notify") {
+            if let (Some(sender), Some(serial)) = (field("sender"), field("serial").and_then(|s| s.parse().ok())) {
+                return PendingMessage::Notify {
+                    sender,
+                    serial,
+                    strings: Vec::new(),
+                    desktop_entry: None,
+                    urgency: None,
+                    last_key: None,
+                };
+            }
+        } else if line.starts_with("method return") {
+            if let (Some(destination), Some(serial)) = (field("destination"), field("reply_serial").and_then(|s| s.parse().ok())) {
+                return PendingMessage::Reply { key: (destination, serial) };
+            }
+        } else if line.starts_with("signal") && field("member").as_deref() == Some("NotificationClosed") {
+            return PendingMessage::Closed;
+        }
+        PendingMessage::Other
+    }
+
+    fn finish_current(&mut self) {
+        let Some(PendingMessage::Notify { sender, serial, strings, desktop_entry, urgency, .. }) = self.current.take() else {
+            return;
+        };
+        // Notify(app_name, replaces_id, app_icon, summary, body, actions, hints, timeout)
+        let [app_name, _icon, summary, body, ..] = strings.as_slice() else {
+            return;
+        };
+
+        let priority = match urgency {
+            Some(0) => NotificationPriority::Low,
+            Some(2) => NotificationPriority::Critical,
+            _ => NotificationPriority::Normal,
+        };
+        self.awaiting_reply.insert((sender, serial), CapturedNotification {
+            source_id: String::new(),
+            app_id: desktop_entry.unwrap_or_else(|| app_name.clone()),
+            app_name: app_name.clone(),
+            summary: summary.clone(),
+            body: body.clone(),
+            priority,
+        });
+    }
+}
+
+/// Value of a `string "..."` line
+fn string_value(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("string")?.trim();
+    let inner = rest.strip_prefix('"')?.strip_suffix('"')?;
+    Some(inner.to_string())
+}
+
+/// Value of a `uint32 N` line
+fn uint32_value(line: &str) -> Option<u32> {
+    line.strip_prefix("uint32")?.trim().parse().ok()
 }
 
 #[cfg(test)]
@@ -127,8 +648,133 @@ mod tests {
     fn test_linux_capabilities() {
         let backend = LinuxNotificationBackend::new().unwrap();
         let caps = backend.get_capabilities();
-        assert!(caps.supports_actions);
+        // Actions depend on the notification server, if one is running at all
+        assert_eq!(caps.supports_actions, caps.supports_activation);
         assert!(caps.supports_duration);
         assert!(caps.supports_priority);
     }
+
+    #[test]
+    fn test_notify_arguments() {
+        let backend = LinuxNotificationBackend::new().unwrap();
+        let mut notification = crate::command_execution::notification::NotificationBuilder::new(
+            "Transfer finished",
+            "report.pdf <1 MB> isn't new",
+            "peer".to_string(),
+        )
+        .priority(NotificationPriority::Critical)
+        .duration(std::time::Duration::from_secs(5))
+        .add_action("open-folder", "Open folder")
+        .build();
+        notification.image = Some("/tmp/preview.png".to_string());
+
+        let capabilities = vec!["actions".to_string(), "body-markup".to_string()];
+        let args = backend.notify_arguments(&notification, 7, &capabilities);
+        assert_eq!(args[1], "7");
+        assert_eq!(args[4], "'report.pdf &lt;1 MB&gt; isn\\'t new'");
+        assert_eq!(args[5], "['default', 'Open', 'open-folder', 'Open folder']");
+        assert!(args[6].contains("'urgency': <byte 2>"));
+        assert!(args[6].contains("'image-path': <'/tmp/preview.png'>"));
+        assert_eq!(args[7], "5000");
+
+        // Servers without actions get no buttons and plain text
+        let args = backend.notify_arguments(&notification, 0, &[]);
+        assert_eq!(args[4], "'report.pdf <1 MB> isn\\'t new'");
+        assert_eq!(args[5], "@as []");
+    }
+
+    #[test]
+    fn test_gdbus_output_parsing() {
+        assert_eq!(parse_uint32_reply("(uint32 42,)\n"), Some(42));
+        assert_eq!(
+            parse_string_array("(['actions', 'body', 'body-markup'],)\n"),
+            vec!["actions", "body", "body-markup"]
+        );
+        assert!(parse_string_array("Error: no server").is_empty());
+
+        assert_eq!(
+            parse_signal("/org/freedesktop/Notifications: org.freedesktop.Notifications.ActionInvoked (uint32 12, 'open-folder')"),
+            Some(ServerSignal::ActionInvoked { id: 12, action_key: "open-folder".to_string() })
+        );
+        assert_eq!(
+            parse_signal("/org/freedesktop/Notifications: org.freedesktop.Notifications.NotificationClosed (uint32 12, uint32 2)"),
+            Some(ServerSignal::Closed { id: 12, reason: CloseReason::Dismissed })
+        );
+        assert_eq!(
+            parse_signal("/org/freedesktop/Notifications: org.freedesktop.Notifications.ActivationToken (uint32 12, 'x')"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_signals_for_other_apps_are_ignored() {
+        let shown = Mutex::new(ShownNotifications::default());
+        let notification_id = Uuid::new_v4();
+        shown.lock().unwrap().server_ids.insert(notification_id, 12);
+        shown
+            .lock()
+            .unwrap()
+            .by_server_id
+            .insert(12, (notification_id, Some("operation/1".to_string())));
+
+        let clicked = own_event(&shown, ServerSignal::ActionInvoked { id: 12, action_key: "default".to_string() });
+        assert_eq!(
+            clicked,
+            Some(NotificationEvent::Activated(NotificationActivation {
+                notification_id,
+                action_id: None,
+                launch: Some("operation/1".to_string()),
+            }))
+        );
+        assert_eq!(own_event(&shown, ServerSignal::ActionInvoked { id: 99, action_key: "default".to_string() }), None);
+
+        let closed = own_event(&shown, ServerSignal::Closed { id: 12, reason: CloseReason::Expired });
+        assert_eq!(closed, Some(NotificationEvent::Closed { notification_id, reason: CloseReason::Expired }));
+        assert!(shown.lock().unwrap().server_ids.is_empty());
+    }
+
+    #[test]
+    fn test_dbus_monitor_parser() {
+        let output = r#"method call time=1.0 sender=:1.50 -> destination=org.freedesktop.Notifications serial=7 path=/org/freedesktop/Notifications; interface=org.freedesktop.Notifications; member=Notify
+   string "Signal"
+   uint32 0
+   string "signal-desktop"
+   string "Alice"
+   string "Are you coming?"
+   array [
+   ]
+   array [
+      dict entry(
+         string "desktop-entry"
+         variant             string "signal-desktop"
+      )
+      dict entry(
+         string "urgency"
+         variant             byte 2
+      )
+   ]
+   int32 -1
+method return time=1.1 sender=:1.20 -> destination=:1.50 serial=30 reply_serial=7
+   uint32 42
+signal time=2.0 sender=:1.20 -> destination=(null destination) serial=31 path=/org/freedesktop/Notifications; interface=org.freedesktop.Notifications; member=NotificationClosed
+   uint32 42
+   uint32 2"#;
+
+        let mut parser = DbusMonitorParser::default();
+        let events: Vec<CaptureEvent> = output.lines().filter_map(|line| parser.feed(line)).collect();
+        assert_eq!(events.len(), 2);
+
+        match &events[0] {
+            CaptureEvent::Posted(captured) => {
+                assert_eq!(captured.source_id, "42");
+                assert_eq!(captured.app_id, "signal-desktop");
+                assert_eq!(captured.app_name, "Signal");
+                assert_eq!(captured.summary, "Alice");
+                assert_eq!(captured.body, "Are you coming?");
+                assert_eq!(captured.priority, NotificationPriority::Critical);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(matches!(&events[1], CaptureEvent::Dismissed { source_id } if source_id == "42"));
+    }
 }
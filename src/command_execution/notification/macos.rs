@@ -1,102 +1,509 @@
 // macOS Notification Backend
 //
-// Implements macOS notifications using UserNotifications framework
+// Implements macOS notifications with the UserNotifications framework.
+// UNUserNotificationCenter only works for processes inside an app bundle; a
+// bare `kizuna` binary has no bundle identifier and the framework raises an
+// exception, so the backend checks first and reports itself unsupported
+// instead. Inside Kizuna.app, permission is requested when the backend is
+// created and notifications wait for the user's answer before being sent.
 
 use crate::command_execution::error::{CommandError, CommandResult};
 use crate::command_execution::types::*;
-use super::{NotificationBackend, NotificationCapabilities};
+use super::{
+    CloseReason, EventHandler, NotificationActivation, NotificationBackend, NotificationCapabilities,
+    NotificationEvent,
+};
+use block::{Block, ConcreteBlock};
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSArray, NSAutoreleasePool, NSString, NSUInteger};
+use objc::declare::ClassDecl;
+use objc::runtime::{Object, Sel, BOOL, YES};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, Once, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+#[link(name = "UserNotifications", kind = "framework")]
+unsafe extern "C" {}
+
+/// UNAuthorizationOptionBadge | UNAuthorizationOptionSound | UNAuthorizationOptionAlert
+const AUTHORIZATION_OPTIONS: NSUInteger = (1 << 0) | (1 << 1) | (1 << 2);
+
+/// Banner, list and sound, plus the pre-Big Sur alert flag, so notifications
+/// also show while Kizuna is frontmost
+const PRESENTATION_OPTIONS: NSUInteger = (1 << 1) | (1 << 2) | (1 << 3) | (1 << 4);
+
+/// UNNotificationActionOptionForeground
+const ACTION_OPTION_FOREGROUND: NSUInteger = 1 << 2;
+
+/// UNNotificationCategoryOptionCustomDismissAction, needed to hear about dismissals
+const CATEGORY_OPTION_CUSTOM_DISMISS: NSUInteger = 1 << 0;
+
+const DEFAULT_ACTION: &str = "com.apple.UNNotificationDefaultActionIdentifier";
+const DISMISS_ACTION: &str = "com.apple.UNNotificationDismissActionIdentifier";
+
+/// userInfo key holding the notification's launch argument
+const LAUNCH_KEY: &str = "kizuna.launch";
+
+/// How long the first notification waits for the user to answer the permission prompt
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long to wait for the notification center to accept a request
+const ADD_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Handler for notification responses, shared with the delegate class
+static EVENT_HANDLER: RwLock<Option<EventHandler>> = RwLock::new(None);
+
+/// Answer to the notification permission request
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AuthorizationState {
+    Pending,
+    Granted,
+    Denied(Option<String>),
+}
+
+struct Authorization {
+    state: Mutex<AuthorizationState>,
+    changed: Condvar,
+}
+
+impl Authorization {
+    fn new(state: AuthorizationState) -> Self {
+        Self {
+            state: Mutex::new(state),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn set(&self, state: AuthorizationState) {
+        *self.state.lock().unwrap() = state;
+        self.changed.notify_all();
+    }
+
+    /// The answer, waiting up to `timeout` while the prompt is still open
+    fn wait(&self, timeout: Duration) -> AuthorizationState {
+        let state = self.state.lock().unwrap();
+        let (state, _) = self
+            .changed
+            .wait_timeout_while(state, timeout, |state| *state == AuthorizationState::Pending)
+            .unwrap();
+        state.clone()
+    }
+}
 
 /// macOS notification backend using UserNotifications framework
 pub struct MacOSNotificationBackend {
-    bundle_id: String,
+    /// Bundle identifier of the running app, `None` for a bare binary
+    bundle_id: Option<String>,
+    authorization: Arc<Authorization>,
+    /// Categories registered with the notification center, one per action set
+    categories: Mutex<Vec<(String, Vec<NotificationAction>)>>,
 }
 
 impl MacOSNotificationBackend {
     /// Create a new macOS notification backend
     pub fn new() -> CommandResult<Self> {
+        let bundle_id = main_bundle_identifier();
+        let authorization = match &bundle_id {
+            Some(_) => {
+                let authorization = Arc::new(Authorization::new(AuthorizationState::Pending));
+                let center = notification_center();
+                install_delegate(center);
+                request_authorization(center, Arc::clone(&authorization));
+                authorization
+            }
+            None => {
+                log::debug!("Not running from an app bundle, macOS notifications are unavailable");
+                Arc::new(Authorization::new(AuthorizationState::Denied(None)))
+            }
+        };
+
         Ok(Self {
-            bundle_id: "com.kizuna.command-execution".to_string(),
+            bundle_id,
+            authorization,
+            categories: Mutex::new(Vec::new()),
         })
     }
-    
-    /// Format notification for macOS
-    fn format_notification(&self, notification: &Notification) -> String {
-        format!(
-            "[{}] {}: {}",
-            self.format_type(notification.notification_type),
-            notification.title,
-            notification.message
-        )
-    }
-    
-    /// Format notification type for display
-    fn format_type(&self, notification_type: NotificationType) -> &str {
-        match notification_type {
-            NotificationType::Info => "ℹ️",
-            NotificationType::Warning => "⚠️",
-            NotificationType::Error => "❌",
-            NotificationType::Success => "✅",
+
+    /// Identifier of the category carrying these actions, registering it if needed
+    fn category_for(&self, center: id, actions: &[NotificationAction]) -> String {
+        let category_id = std::iter::once("kizuna")
+            .chain(actions.iter().map(|action| action.id.as_str()))
+            .collect::<Vec<_>>()
+            .join("|");
+
+        let mut categories = self.categories.lock().unwrap();
+        if !categories.iter().any(|(id, _)| *id == category_id) {
+            categories.push((category_id.clone(), actions.to_vec()));
+            set_categories(center, &categories);
         }
+        category_id
     }
-    
-    /// Get sound name based on notification type
-    fn get_sound_name(&self, notification_type: NotificationType) -> &str {
-        match notification_type {
-            NotificationType::Info => "default",
-            NotificationType::Warning => "Basso",
-            NotificationType::Error => "Sosumi",
-            NotificationType::Success => "Glass",
+
+    /// Build and submit the notification request
+    fn add_request(&self, notification: &Notification) -> CommandResult<()> {
+        unsafe {
+            let center = notification_center();
+            let content: id = objc::msg_send![objc::class!(UNMutableNotificationContent), new];
+            let _: () = objc::msg_send![content, setTitle: ns_string(&notification.title)];
+            let _: () = objc::msg_send![content, setBody: ns_string(&notification.message)];
+
+            let category = self.category_for(center, &notification.actions);
+            let _: () = objc::msg_send![content, setCategoryIdentifier: ns_string(&category)];
+
+            if notification.notification_type != NotificationType::Info {
+                let sound: id = objc::msg_send![objc::class!(UNNotificationSound), defaultSound];
+                let _: () = objc::msg_send![content, setSound: sound];
+            }
+
+            if let Some(launch) = &notification.launch {
+                let user_info: id = objc::msg_send![
+                    objc::class!(NSDictionary),
+                    dictionaryWithObject: ns_string(launch)
+                    forKey: ns_string(LAUNCH_KEY)
+                ];
+                let _: () = objc::msg_send![content, setUserInfo: user_info];
+            }
+
+            // Interruption levels arrived in macOS 12
+            let has_levels: BOOL = objc::msg_send![content, respondsToSelector: objc::sel!(setInterruptionLevel:)];
+            if has_levels == YES {
+                let level: NSUInteger = match notification.priority {
+                    NotificationPriority::Low => 0,
+                    NotificationPriority::Normal | NotificationPriority::High => 1,
+                    NotificationPriority::Critical => 2,
+                };
+                let _: () = objc::msg_send![content, setInterruptionLevel: level];
+            }
+
+            if let Some(attachment) = notification.image.as_deref().and_then(image_attachment) {
+                let _: () = objc::msg_send![content, setAttachments: NSArray::arrayWithObjects(nil, &[attachment])];
+            }
+
+            // Reusing the notification's ID replaces one that is still on screen
+            let request: id = objc::msg_send![
+                objc::class!(UNNotificationRequest),
+                requestWithIdentifier: ns_string(&notification.notification_id.to_string())
+                content: content
+                trigger: nil
+            ];
+            let _: () = objc::msg_send![content, release];
+
+            let (tx, rx) = std::sync::mpsc::channel();
+            let completion = ConcreteBlock::new(move |error: id| {
+                let _ = tx.send(error_description(error));
+            })
+            .copy();
+            let _: () = objc::msg_send![center, addNotificationRequest: request withCompletionHandler: &*completion];
+
+            match rx.recv_timeout(ADD_REQUEST_TIMEOUT) {
+                Ok(None) => Ok(()),
+                Ok(Some(error)) => Err(CommandError::NotificationError(format!("Failed to show notification: {}", error))),
+                Err(_) => Err(CommandError::NotificationError(
+                    "Notification center did not accept the notification".to_string(),
+                )),
+            }
         }
     }
 }
 
 impl NotificationBackend for MacOSNotificationBackend {
     fn show_notification(&self, notification: &Notification) -> CommandResult<()> {
-        #[cfg(target_os = "macos")]
-        {
-            // In a production implementation, this would use:
-            // - UNUserNotificationCenter for modern macOS (10.14+)
-            // - NSUserNotificationCenter for older macOS versions
-            // - Objective-C bindings via objc crate
-            
-            let formatted = self.format_notification(notification);
-            
-            // Placeholder: In production, this would call macOS APIs
-            // For now, we'll simulate success
-            eprintln!("macOS Notification: {}", formatted);
-            
-            Ok(())
+        if self.bundle_id.is_none() {
+            return Err(CommandError::NotificationError(
+                "macOS notifications need Kizuna to run from Kizuna.app".to_string(),
+            ));
         }
-        
-        #[cfg(not(target_os = "macos"))]
-        {
-            Err(CommandError::NotificationError(
-                "macOS notifications only supported on macOS".to_string()
-            ))
+
+        match self.authorization.wait(AUTHORIZATION_TIMEOUT) {
+            AuthorizationState::Granted => {}
+            AuthorizationState::Denied(reason) => {
+                return Err(CommandError::NotificationError(match reason {
+                    Some(reason) => format!("Notification permission request failed: {}", reason),
+                    None => "Notifications for Kizuna are turned off in System Settings > Notifications".to_string(),
+                }));
+            }
+            AuthorizationState::Pending => {
+                return Err(CommandError::NotificationError(
+                    "Still waiting for permission to show notifications".to_string(),
+                ));
+            }
+        }
+
+        unsafe {
+            let pool = NSAutoreleasePool::new(nil);
+            let result = self.add_request(notification);
+            let _: () = objc::msg_send![pool, drain];
+            result
         }
     }
-    
+
     fn is_supported(&self) -> bool {
-        cfg!(target_os = "macos")
+        self.bundle_id.is_some()
     }
-    
+
     fn get_capabilities(&self) -> NotificationCapabilities {
+        let bundled = self.bundle_id.is_some();
         NotificationCapabilities {
-            supports_actions: true,
+            supports_actions: bundled,
             supports_duration: false, // macOS controls duration
-            supports_priority: false,
-            supports_icons: true,
+            supports_priority: bundled && has_interruption_levels(),
+            // Notifications always carry the app icon
+            supports_icons: false,
+            supports_images: bundled,
+            supports_activation: bundled,
+            // Requests reuse the notification ID as their identifier
+            supports_replace: bundled,
+            // There is no public API for reading other applications' notifications
+            supports_mirroring: false,
             max_title_length: Some(256),
             max_message_length: Some(2048),
         }
     }
+
+    fn set_event_handler(&self, handler: EventHandler) {
+        *EVENT_HANDLER.write().unwrap() = Some(handler);
+    }
+
+    fn close_notification(&self, notification_id: NotificationId) -> CommandResult<()> {
+        if self.bundle_id.is_none() {
+            return Ok(());
+        }
+
+        unsafe {
+            let pool = NSAutoreleasePool::new(nil);
+            let center = notification_center();
+            let identifiers = NSArray::arrayWithObjects(nil, &[ns_string(&notification_id.to_string())]);
+            let _: () = objc::msg_send![center, removeDeliveredNotificationsWithIdentifiers: identifiers];
+            let _: () = objc::msg_send![center, removePendingNotificationRequestsWithIdentifiers: identifiers];
+            let _: () = objc::msg_send![pool, drain];
+        }
+        Ok(())
+    }
+}
+
+/// Bundle identifier of the running process, if it was launched from an app bundle
+fn main_bundle_identifier() -> Option<String> {
+    unsafe {
+        let bundle: id = objc::msg_send![objc::class!(NSBundle), mainBundle];
+        if bundle == nil {
+            return None;
+        }
+        let identifier: id = objc::msg_send![bundle, bundleIdentifier];
+        rust_string(identifier)
+    }
+}
+
+fn notification_center() -> id {
+    unsafe { objc::msg_send![objc::class!(UNUserNotificationCenter), currentNotificationCenter] }
+}
+
+fn has_interruption_levels() -> bool {
+    unsafe {
+        let responds: BOOL = objc::msg_send![
+            objc::class!(UNMutableNotificationContent),
+            instancesRespondToSelector: objc::sel!(setInterruptionLevel:)
+        ];
+        responds == YES
+    }
+}
+
+/// Ask for permission to show notifications; the system only prompts the first time
+fn request_authorization(center: id, authorization: Arc<Authorization>) {
+    let completion = ConcreteBlock::new(move |granted: BOOL, error: id| {
+        authorization.set(if granted == YES {
+            AuthorizationState::Granted
+        } else {
+            AuthorizationState::Denied(error_description(error))
+        });
+    })
+    .copy();
+
+    unsafe {
+        let _: () = objc::msg_send![
+            center,
+            requestAuthorizationWithOptions: AUTHORIZATION_OPTIONS
+            completionHandler: &*completion
+        ];
+    }
+}
+
+/// Replace the registered categories with `categories`
+fn set_categories(center: id, categories: &[(String, Vec<NotificationAction>)]) {
+    unsafe {
+        let objects: Vec<id> = categories
+            .iter()
+            .map(|(category_id, actions)| {
+                let actions: Vec<id> = actions
+                    .iter()
+                    .map(|action| {
+                        objc::msg_send![
+                            objc::class!(UNNotificationAction),
+                            actionWithIdentifier: ns_string(&action.id)
+                            title: ns_string(&action.label)
+                            options: ACTION_OPTION_FOREGROUND
+                        ]
+                    })
+                    .collect();
+                objc::msg_send![
+                    objc::class!(UNNotificationCategory),
+                    categoryWithIdentifier: ns_string(category_id)
+                    actions: NSArray::arrayWithObjects(nil, &actions)
+                    intentIdentifiers: NSArray::arrayWithObjects(nil, &[])
+                    options: CATEGORY_OPTION_CUSTOM_DISMISS
+                ]
+            })
+            .collect();
+
+        let set: id = objc::msg_send![objc::class!(NSSet), setWithArray: NSArray::arrayWithObjects(nil, &objects)];
+        let _: () = objc::msg_send![center, setNotificationCategories: set];
+    }
+}
+
+/// Attachment for a local image
+///
+/// The notification center moves attachment files into its own store, so a
+/// temporary copy is attached instead of the original.
+fn image_attachment(image: &str) -> Option<id> {
+    let copy = temporary_copy(Path::new(image))?;
+    unsafe {
+        let url: id = objc::msg_send![objc::class!(NSURL), fileURLWithPath: ns_string(&copy.to_string_lossy())];
+        let mut error: id = nil;
+        let attachment: id = objc::msg_send![
+            objc::class!(UNNotificationAttachment),
+            attachmentWithIdentifier: ns_string("image")
+            URL: url
+            options: nil
+            error: &mut error
+        ];
+        if attachment == nil {
+            log::debug!("Not attaching {}: {}", image, error_description(error).unwrap_or_default());
+            let _ = std::fs::remove_file(&copy);
+            return None;
+        }
+        Some(attachment)
+    }
+}
+
+fn temporary_copy(source: &Path) -> Option<PathBuf> {
+    if !source.is_file() {
+        return None;
+    }
+    // The extension tells the notification center what kind of file it is
+    let extension = source.extension()?.to_str()?;
+    let copy = std::env::temp_dir().join(format!("kizuna-notification-{}.{}", Uuid::new_v4(), extension));
+    std::fs::copy(source, &copy).ok()?;
+    Some(copy)
+}
+
+/// Register the notification center delegate that reports clicks and dismissals
+fn install_delegate(center: id) {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| unsafe {
+        let Some(mut decl) = ClassDecl::new("KizunaNotificationDelegate", objc::class!(NSObject)) else {
+            log::warn!("Notification delegate class already exists, clicks will not be reported");
+            return;
+        };
+        decl.add_method(
+            objc::sel!(userNotificationCenter:didReceiveNotificationResponse:withCompletionHandler:),
+            did_receive_response as extern "C" fn(&Object, Sel, id, id, id),
+        );
+        decl.add_method(
+            objc::sel!(userNotificationCenter:willPresentNotification:withCompletionHandler:),
+            will_present as extern "C" fn(&Object, Sel, id, id, id),
+        );
+        let class = decl.register();
+
+        // The center holds its delegate weakly; this one lives for the whole process
+        let delegate: id = objc::msg_send![class, new];
+        let _: () = objc::msg_send![center, setDelegate: delegate];
+    });
+}
+
+extern "C" fn did_receive_response(_this: &Object, _sel: Sel, _center: id, response: id, completion: id) {
+    unsafe {
+        let action: id = objc::msg_send![response, actionIdentifier];
+        let notification: id = objc::msg_send![response, notification];
+        let request: id = objc::msg_send![notification, request];
+        let identifier: id = objc::msg_send![request, identifier];
+        let content: id = objc::msg_send![request, content];
+        let user_info: id = objc::msg_send![content, userInfo];
+        let launch: id = if user_info == nil {
+            nil
+        } else {
+            objc::msg_send![user_info, objectForKey: ns_string(LAUNCH_KEY)]
+        };
+
+        let notification_id = rust_string(identifier).and_then(|identifier| Uuid::parse_str(&identifier).ok());
+        if let Some(notification_id) = notification_id {
+            let event = response_event(notification_id, rust_string(action), rust_string(launch));
+            if let Some(handler) = EVENT_HANDLER.read().unwrap().as_ref() {
+                handler(event);
+            }
+        }
+
+        let completion = &*(completion as *const Block<(), ()>);
+        completion.call(());
+    }
+}
+
+extern "C" fn will_present(_this: &Object, _sel: Sel, _center: id, _notification: id, completion: id) {
+    unsafe {
+        let completion = &*(completion as *const Block<(NSUInteger,), ()>);
+        completion.call((PRESENTATION_OPTIONS,));
+    }
+}
+
+/// Event for a notification response with the given action identifier
+fn response_event(notification_id: NotificationId, action: Option<String>, launch: Option<String>) -> NotificationEvent {
+    match action.as_deref() {
+        Some(DISMISS_ACTION) => NotificationEvent::Closed {
+            notification_id,
+            reason: CloseReason::Dismissed,
+        },
+        Some(DEFAULT_ACTION) | None => NotificationEvent::Activated(NotificationActivation {
+            notification_id,
+            action_id: None,
+            launch,
+        }),
+        Some(_) => NotificationEvent::Activated(NotificationActivation {
+            notification_id,
+            action_id: action,
+            launch,
+        }),
+    }
+}
+
+fn ns_string(value: &str) -> id {
+    unsafe { NSString::alloc(nil).init_str(value).autorelease() }
+}
+
+fn rust_string(value: id) -> Option<String> {
+    if value == nil {
+        return None;
+    }
+    unsafe {
+        let utf8: *const c_char = objc::msg_send![value, UTF8String];
+        (!utf8.is_null()).then(|| CStr::from_ptr(utf8).to_string_lossy().into_owned())
+    }
+}
+
+fn error_description(error: id) -> Option<String> {
+    if error == nil {
+        return None;
+    }
+    unsafe {
+        let description: id = objc::msg_send![error, localizedDescription];
+        rust_string(description)
+    }
 }
 
 #[cfg(test)]
-#[cfg(target_os = "macos")]
 mod tests {
     use super::*;
-    use uuid::Uuid;
 
     #[test]
     fn test_macos_backend_creation() {
@@ -105,16 +512,48 @@ mod tests {
     }
 
     #[test]
-    fn test_macos_backend_supported() {
+    fn test_bare_binary_reports_no_capabilities() {
+        // Test binaries are not app bundles
         let backend = MacOSNotificationBackend::new().unwrap();
-        assert!(backend.is_supported());
+        let caps = backend.get_capabilities();
+        assert_eq!(backend.is_supported(), main_bundle_identifier().is_some());
+        assert_eq!(caps.supports_actions, backend.is_supported());
+        assert!(!caps.supports_duration);
+
+        if !backend.is_supported() {
+            let notification = super::super::create_notification("Title", "Message", NotificationType::Info, "peer".to_string());
+            assert!(backend.show_notification(&notification).is_err());
+        }
     }
 
     #[test]
-    fn test_macos_capabilities() {
-        let backend = MacOSNotificationBackend::new().unwrap();
-        let caps = backend.get_capabilities();
-        assert!(caps.supports_actions);
-        assert!(!caps.supports_duration);
+    fn test_response_event() {
+        let id = Uuid::new_v4();
+        let launch = Some("operation/1".to_string());
+
+        assert_eq!(
+            response_event(id, Some(DEFAULT_ACTION.to_string()), launch.clone()),
+            NotificationEvent::Activated(NotificationActivation { notification_id: id, action_id: None, launch: launch.clone() })
+        );
+        assert_eq!(
+            response_event(id, Some("open-folder".to_string()), None),
+            NotificationEvent::Activated(NotificationActivation {
+                notification_id: id,
+                action_id: Some("open-folder".to_string()),
+                launch: None,
+            })
+        );
+        assert_eq!(
+            response_event(id, Some(DISMISS_ACTION.to_string()), launch),
+            NotificationEvent::Closed { notification_id: id, reason: CloseReason::Dismissed }
+        );
+    }
+
+    #[test]
+    fn test_authorization_wait() {
+        let authorization = Authorization::new(AuthorizationState::Pending);
+        assert_eq!(authorization.wait(Duration::from_millis(10)), AuthorizationState::Pending);
+        authorization.set(AuthorizationState::Granted);
+        assert_eq!(authorization.wait(Duration::from_secs(1)), AuthorizationState::Granted);
     }
 }
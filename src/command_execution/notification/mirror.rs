@@ -0,0 +1,660 @@
+// Notification Mirroring
+//
+// Captures notifications that other applications post on this device and mirrors
+// them to selected peers through the command execution notification channel.
+// Only applications on the allowlist are mirrored, and nothing is mirrored until
+// an application is allowed. Dismissing a mirrored notification on any device
+// dismisses the original and every other copy. With presence attached, notifications
+// mirrored to a busy or streaming device are held until it is available again.
+
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::command_execution::error::{CommandError, CommandResult};
+#[cfg(feature = "command-execution")]
+use crate::command_execution::transport_integration::CommandTransportIntegration;
+use crate::command_execution::types::*;
+use crate::discovery::PresenceManager;
+use crate::transport::PeerAddress;
+use super::NotificationManager;
+
+/// A notification posted by a local application
+#[derive(Debug, Clone)]
+pub struct CapturedNotification {
+    /// Platform identifier, used to dismiss the original
+    pub source_id: String,
+    /// Stable application identifier (desktop entry, bundle ID or AUMID)
+    pub app_id: String,
+    /// Human-readable application name
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    pub priority: NotificationPriority,
+}
+
+/// Change observed by a platform capture hook
+#[derive(Debug, Clone)]
+pub enum CaptureEvent {
+    Posted(CapturedNotification),
+    Dismissed { source_id: String },
+}
+
+/// Platform hook that observes notifications posted on this device
+#[async_trait]
+pub trait NotificationSource: Send + Sync {
+    /// Begin reporting notifications to `events`
+    async fn start(&self, events: mpsc::Sender<CaptureEvent>) -> CommandResult<()>;
+
+    /// Stop reporting notifications
+    async fn stop(&self) -> CommandResult<()>;
+
+    /// Dismiss a captured notification on this device
+    async fn dismiss(&self, source_id: &str) -> CommandResult<()>;
+
+    /// Whether capture works on this platform
+    fn is_supported(&self) -> bool;
+}
+
+/// Delivery path for mirrored notifications and dismissals
+#[async_trait]
+pub trait MirrorSink: Send + Sync {
+    async fn forward(&self, notification: Notification, peer: &PeerAddress) -> CommandResult<()>;
+
+    async fn dismiss(&self, dismissal: NotificationDismissal, peer: &PeerAddress) -> CommandResult<()>;
+}
+
+#[cfg(feature = "command-execution")]
+#[async_trait]
+impl MirrorSink for CommandTransportIntegration {
+    async fn forward(&self, notification: Notification, peer: &PeerAddress) -> CommandResult<()> {
+        self.send_notification(notification, peer).await
+    }
+
+    async fn dismiss(&self, dismissal: NotificationDismissal, peer: &PeerAddress) -> CommandResult<()> {
+        self.send_notification_dismissal(dismissal, peer).await
+    }
+}
+
+/// Notification mirroring configuration
+#[derive(Debug, Clone)]
+pub struct MirrorConfig {
+    /// Application IDs or names to mirror, matched case-insensitively
+    pub allowed_apps: Vec<String>,
+    /// Mirrored notifications remembered for dismiss-sync
+    pub max_tracked: usize,
+}
+
+impl Default for MirrorConfig {
+    fn default() -> Self {
+        Self {
+            allowed_apps: Vec::new(),
+            max_tracked: 512,
+        }
+    }
+}
+
+/// Where a tracked notification came from
+#[derive(Debug, Clone)]
+enum Origin {
+    /// Captured on this device
+    Local { source_id: String },
+    /// Mirrored to us by a peer
+    Remote { peer: PeerAddress },
+}
+
+#[derive(Debug, Clone)]
+struct Tracked {
+    origin: Origin,
+    /// Peers a copy was delivered to
+    copies: HashMap<PeerId, PeerAddress>,
+    dismissed: bool,
+}
+
+#[derive(Default)]
+struct MirrorState {
+    tracked: HashMap<NotificationId, Tracked>,
+    order: VecDeque<NotificationId>,
+    by_source: HashMap<String, NotificationId>,
+}
+
+/// Mirrors allowlisted local notifications to selected peers
+pub struct NotificationMirror {
+    local_peer: PeerId,
+    source: Arc<dyn NotificationSource>,
+    sink: Arc<dyn MirrorSink>,
+    manager: Option<Arc<NotificationManager>>,
+    presence: Option<Arc<PresenceManager>>,
+    max_tracked: usize,
+    allowed_apps: RwLock<HashSet<String>>,
+    targets: RwLock<HashMap<PeerId, PeerAddress>>,
+    state: Mutex<MirrorState>,
+    /// Notifications from peers waiting for do-not-disturb to end
+    held: Mutex<VecDeque<Notification>>,
+}
+
+impl NotificationMirror {
+    /// Create a mirror fed by `source` and delivering through `sink`
+    pub fn new(
+        local_peer: PeerId,
+        source: Arc<dyn NotificationSource>,
+        sink: Arc<dyn MirrorSink>,
+        config: MirrorConfig,
+    ) -> Self {
+        let allowed_apps = config.allowed_apps.iter().map(|app| app.to_lowercase()).collect();
+        Self {
+            local_peer,
+            source,
+            sink,
+            manager: None,
+            presence: None,
+            max_tracked: config.max_tracked.max(1),
+            allowed_apps: RwLock::new(allowed_apps),
+            targets: RwLock::new(HashMap::new()),
+            state: Mutex::new(MirrorState::default()),
+            held: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Show notifications mirrored from peers through `manager`
+    pub fn with_notification_manager(mut self, manager: Arc<NotificationManager>) -> Self {
+        self.manager = Some(manager);
+        self
+    }
+
+    /// Hold notifications from peers while this device's presence is do-not-disturb
+    pub fn with_presence(mut self, presence: Arc<PresenceManager>) -> Self {
+        self.presence = Some(presence);
+        self
+    }
+
+    /// Capture hook for the current platform
+    ///
+    /// Only Linux can capture notifications; elsewhere the notification backend
+    /// reports `supports_mirroring: false` and this fails.
+    pub fn platform_source() -> CommandResult<Arc<dyn NotificationSource>> {
+        #[cfg(target_os = "linux")]
+        {
+            Ok(Arc::new(super::linux::LinuxNotificationSource::new()))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err(CommandError::NotificationError(
+                "Notification capture is not supported on this platform".to_string()
+            ))
+        }
+    }
+
+    /// Allow an application to be mirrored
+    pub fn allow_app(&self, app: &str) {
+        self.allowed_apps.write().unwrap().insert(app.to_lowercase());
+    }
+
+    /// Stop mirroring an application
+    pub fn disallow_app(&self, app: &str) -> bool {
+        self.allowed_apps.write().unwrap().remove(&app.to_lowercase())
+    }
+
+    /// Applications currently mirrored
+    pub fn allowed_apps(&self) -> Vec<String> {
+        let mut apps: Vec<String> = self.allowed_apps.read().unwrap().iter().cloned().collect();
+        apps.sort();
+        apps
+    }
+
+    /// Whether notifications from an application are mirrored
+    pub fn is_allowed(&self, app_id: &str, app_name: &str) -> bool {
+        let allowed = self.allowed_apps.read().unwrap();
+        allowed.contains(&app_id.to_lowercase()) || allowed.contains(&app_name.to_lowercase())
+    }
+
+    /// Mirror notifications to a peer
+    ///
+    /// The peer must be trusted; untrusted peers are refused when a notification
+    /// is encrypted for them.
+    pub fn add_target(&self, peer: PeerAddress) {
+        self.targets.write().unwrap().insert(peer.peer_id.clone(), peer);
+    }
+
+    /// Stop mirroring notifications to a peer
+    pub fn remove_target(&self, peer_id: &str) -> bool {
+        self.targets.write().unwrap().remove(peer_id).is_some()
+    }
+
+    /// Peers receiving mirrored notifications
+    pub fn targets(&self) -> Vec<PeerId> {
+        let mut targets: Vec<PeerId> = self.targets.read().unwrap().keys().cloned().collect();
+        targets.sort();
+        targets
+    }
+
+    /// Start the platform hook and mirror what it reports until it stops
+    pub async fn start(self: &Arc<Self>) -> CommandResult<tokio::task::JoinHandle<()>> {
+        if !self.source.is_supported() {
+            return Err(CommandError::NotificationError(
+                "Notification capture is not supported on this platform".to_string()
+            ));
+        }
+
+        let (tx, mut rx) = mpsc::channel(64);
+        self.source.start(tx).await?;
+
+        let mirror = Arc::clone(self);
+        Ok(tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                if let Err(e) = mirror.handle_capture_event(event).await {
+                    log::warn!("Failed to mirror notification: {}", e);
+                }
+            }
+        }))
+    }
+
+    /// Stop the platform hook
+    pub async fn stop(&self) -> CommandResult<()> {
+        self.source.stop().await
+    }
+
+    /// Release held notifications whenever presence changes
+    ///
+    /// Returns `None` when no presence manager is attached.
+    pub fn watch_presence(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let mut changes = self.presence.as_ref()?.subscribe();
+        let mirror = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            while changes.changed().await.is_ok() {
+                if let Err(e) = mirror.release_held().await {
+                    log::warn!("Failed to show held notifications: {}", e);
+                }
+            }
+        }))
+    }
+
+    /// Mirror or dismiss in response to a local capture event
+    ///
+    /// Returns the mirrored notification's ID when a new notification was forwarded.
+    pub async fn handle_capture_event(&self, event: CaptureEvent) -> CommandResult<Option<NotificationId>> {
+        match event {
+            CaptureEvent::Posted(captured) => self.mirror(captured).await,
+            CaptureEvent::Dismissed { source_id } => {
+                let id = self.state.lock().unwrap().by_source.get(&source_id).copied();
+                if let Some(id) = id {
+                    self.propagate_dismissal(id, None).await?;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Record and display a notification a peer mirrored to us
+    pub async fn handle_remote_notification(&self, from: PeerAddress, notification: Notification) -> CommandResult<()> {
+        let id = notification.notification_id;
+        self.track(id, Tracked {
+            origin: Origin::Remote { peer: from },
+            copies: HashMap::new(),
+            dismissed: false,
+        });
+
+        if !self.accepts(&notification) {
+            log::debug!("Holding notification {} while do-not-disturb is on", id);
+            let mut held = self.held.lock().unwrap();
+            held.push_back(notification);
+            while held.len() > self.max_tracked {
+                held.pop_front();
+            }
+            return Ok(());
+        }
+        self.show(notification).await
+    }
+
+    /// Show held notifications that presence now lets through
+    ///
+    /// Notifications dismissed on another device while held are dropped. Returns
+    /// how many were shown.
+    pub async fn release_held(&self) -> CommandResult<usize> {
+        let ready: VecDeque<Notification> = {
+            let mut held = self.held.lock().unwrap();
+            let (ready, waiting) = held.drain(..).partition(|n| self.accepts(n));
+            *held = waiting;
+            ready
+        };
+
+        let mut shown = 0;
+        for notification in ready {
+            if self.is_dismissed(&notification.notification_id) == Some(true) {
+                continue;
+            }
+            self.show(notification).await?;
+            shown += 1;
+        }
+        Ok(shown)
+    }
+
+    /// Number of notifications waiting for do-not-disturb to end
+    pub fn held_count(&self) -> usize {
+        self.held.lock().unwrap().len()
+    }
+
+    fn accepts(&self, notification: &Notification) -> bool {
+        self.presence
+            .as_ref()
+            .is_none_or(|presence| presence.accepts(notification.priority))
+    }
+
+    async fn show(&self, notification: Notification) -> CommandResult<()> {
+        if let Some(manager) = &self.manager {
+            let sender = notification.sender.clone();
+            manager.send_notification(notification, sender).await?;
+        }
+        Ok(())
+    }
+
+    /// Apply a dismissal received from a peer
+    pub async fn handle_remote_dismissal(&self, from: &PeerId, dismissal: NotificationDismissal) -> CommandResult<()> {
+        self.propagate_dismissal(dismissal.notification_id, Some(from)).await
+    }
+
+    /// Dismiss a mirrored notification because the user dismissed it on this device
+    pub async fn dismiss(&self, notification_id: NotificationId) -> CommandResult<()> {
+        self.propagate_dismissal(notification_id, None).await
+    }
+
+    /// Whether a tracked notification has been dismissed
+    pub fn is_dismissed(&self, notification_id: &NotificationId) -> Option<bool> {
+        self.state.lock().unwrap().tracked.get(notification_id).map(|t| t.dismissed)
+    }
+
+    async fn mirror(&self, captured: CapturedNotification) -> CommandResult<Option<NotificationId>> {
+        if !self.is_allowed(&captured.app_id, &captured.app_name) {
+            return Ok(None);
+        }
+        let targets: Vec<PeerAddress> = self.targets.read().unwrap().values().cloned().collect();
+        if targets.is_empty() {
+            return Ok(None);
+        }
+
+        let notification = Notification {
+            notification_id: Uuid::new_v4(),
+            title: format!("{}: {}", captured.app_name, captured.summary),
+            message: captured.body,
+            notification_type: NotificationType::Info,
+            priority: captured.priority,
+            duration: None,
+            actions: Vec::new(),
+            sender: self.local_peer.clone(),
+            image: None,
+            launch: None,
+        };
+        let id = notification.notification_id;
+
+        let mut copies = HashMap::new();
+        for peer in targets {
+            match self.sink.forward(notification.clone(), &peer).await {
+                Ok(()) => {
+                    copies.insert(peer.peer_id.clone(), peer);
+                }
+                Err(e) => log::warn!("Failed to mirror notification to {}: {}", peer.peer_id, e),
+            }
+        }
+
+        self.state.lock().unwrap().by_source.insert(captured.source_id.clone(), id);
+        self.track(id, Tracked {
+            origin: Origin::Local { source_id: captured.source_id },
+            copies,
+            dismissed: false,
+        });
+        Ok(Some(id))
+    }
+
+    fn track(&self, id: NotificationId, tracked: Tracked) {
+        let mut state = self.state.lock().unwrap();
+        state.tracked.insert(id, tracked);
+        state.order.push_back(id);
+        while state.order.len() > self.max_tracked {
+            let Some(oldest) = state.order.pop_front() else { break };
+            if let Some(Tracked { origin: Origin::Local { source_id }, .. }) = state.tracked.remove(&oldest) {
+                state.by_source.remove(&source_id);
+            }
+        }
+    }
+
+    /// Dismiss everywhere except `from`, the peer that told us about the dismissal
+    ///
+    /// The origin device fans a dismissal out to every copy; a device holding a copy
+    /// only reports back to the origin. Already-dismissed notifications are ignored,
+    /// which also stops the echo when the platform reports our own dismissal back.
+    async fn propagate_dismissal(&self, id: NotificationId, from: Option<&PeerId>) -> CommandResult<()> {
+        let tracked = {
+            let mut state = self.state.lock().unwrap();
+            let Some(tracked) = state.tracked.get_mut(&id) else {
+                return Ok(());
+            };
+            if tracked.dismissed {
+                return Ok(());
+            }
+            tracked.dismissed = true;
+            tracked.clone()
+        };
+
+        let dismissal = NotificationDismissal {
+            notification_id: id,
+            dismissed_by: self.local_peer.clone(),
+            dismissed_at: Utc::now(),
+        };
+
+        let recipients: Vec<PeerAddress> = match tracked.origin {
+            Origin::Local { source_id } => {
+                if from.is_some() && let Err(e) = self.source.dismiss(&source_id).await {
+                    log::warn!("Failed to dismiss local notification {}: {}", source_id, e);
+                }
+                tracked
+                    .copies
+                    .into_values()
+                    .filter(|peer| Some(&peer.peer_id) != from)
+                    .collect()
+            }
+            Origin::Remote { peer } => {
+                if let Some(manager) = &self.manager {
+                    manager.cancel_notification(id).await?;
+                }
+                if from.is_some() { Vec::new() } else { vec![peer] }
+            }
+        };
+
+        for peer in recipients {
+            if let Err(e) = self.sink.dismiss(dismissal.clone(), &peer).await {
+                log::warn!("Failed to sync dismissal to {}: {}", peer.peer_id, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingSink {
+        forwarded: Mutex<Vec<(PeerId, Notification)>>,
+        dismissed: Mutex<Vec<(PeerId, NotificationId)>>,
+    }
+
+    #[async_trait]
+    impl MirrorSink for RecordingSink {
+        async fn forward(&self, notification: Notification, peer: &PeerAddress) -> CommandResult<()> {
+            self.forwarded.lock().unwrap().push((peer.peer_id.clone(), notification));
+            Ok(())
+        }
+
+        async fn dismiss(&self, dismissal: NotificationDismissal, peer: &PeerAddress) -> CommandResult<()> {
+            self.dismissed.lock().unwrap().push((peer.peer_id.clone(), dismissal.notification_id));
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSource {
+        dismissed: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl NotificationSource for RecordingSource {
+        async fn start(&self, _events: mpsc::Sender<CaptureEvent>) -> CommandResult<()> {
+            Ok(())
+        }
+
+        async fn stop(&self) -> CommandResult<()> {
+            Ok(())
+        }
+
+        async fn dismiss(&self, source_id: &str) -> CommandResult<()> {
+            self.dismissed.lock().unwrap().push(source_id.to_string());
+            Ok(())
+        }
+
+        fn is_supported(&self) -> bool {
+            true
+        }
+    }
+
+    fn peer(id: &str) -> PeerAddress {
+        PeerAddress::new(id.to_string(), vec![], vec![], Default::default())
+    }
+
+    fn captured(app: &str, source_id: &str) -> CaptureEvent {
+        CaptureEvent::Posted(CapturedNotification {
+            source_id: source_id.to_string(),
+            app_id: format!("org.example.{}", app),
+            app_name: app.to_string(),
+            summary: "New message".to_string(),
+            body: "Hello".to_string(),
+            priority: NotificationPriority::Normal,
+        })
+    }
+
+    fn mirror(allowed: &[&str]) -> (NotificationMirror, Arc<RecordingSource>, Arc<RecordingSink>) {
+        let source = Arc::new(RecordingSource::default());
+        let sink = Arc::new(RecordingSink::default());
+        let config = MirrorConfig {
+            allowed_apps: allowed.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        let mirror = NotificationMirror::new("local".to_string(), source.clone(), sink.clone(), config);
+        mirror.add_target(peer("phone"));
+        mirror.add_target(peer("tablet"));
+        (mirror, source, sink)
+    }
+
+    #[tokio::test]
+    async fn test_only_allowlisted_apps_are_mirrored() {
+        let (mirror, _source, sink) = mirror(&["Signal"]);
+
+        assert!(mirror.handle_capture_event(captured("Spotify", "1")).await.unwrap().is_none());
+        let id = mirror.handle_capture_event(captured("signal", "2")).await.unwrap().unwrap();
+
+        let forwarded = sink.forwarded.lock().unwrap();
+        assert_eq!(forwarded.len(), 2);
+        assert!(forwarded.iter().all(|(_, n)| n.notification_id == id && n.title == "signal: New message"));
+    }
+
+    #[tokio::test]
+    async fn test_local_dismissal_reaches_every_copy() {
+        let (mirror, source, sink) = mirror(&["signal"]);
+        let id = mirror.handle_capture_event(captured("signal", "7")).await.unwrap().unwrap();
+
+        mirror.handle_capture_event(CaptureEvent::Dismissed { source_id: "7".to_string() }).await.unwrap();
+        assert_eq!(mirror.is_dismissed(&id), Some(true));
+        assert_eq!(sink.dismissed.lock().unwrap().len(), 2);
+        assert!(source.dismissed.lock().unwrap().is_empty(), "already gone locally");
+    }
+
+    #[tokio::test]
+    async fn test_remote_dismissal_closes_original_and_other_copies() {
+        let (mirror, source, sink) = mirror(&["signal"]);
+        let id = mirror.handle_capture_event(captured("signal", "9")).await.unwrap().unwrap();
+
+        let dismissal = NotificationDismissal {
+            notification_id: id,
+            dismissed_by: "phone".to_string(),
+            dismissed_at: Utc::now(),
+        };
+        mirror.handle_remote_dismissal(&"phone".to_string(), dismissal.clone()).await.unwrap();
+        assert_eq!(*source.dismissed.lock().unwrap(), vec!["9".to_string()]);
+        assert_eq!(*sink.dismissed.lock().unwrap(), vec![("tablet".to_string(), id)]);
+
+        // The platform echoing the close back is not propagated again
+        mirror.handle_capture_event(CaptureEvent::Dismissed { source_id: "9".to_string() }).await.unwrap();
+        mirror.handle_remote_dismissal(&"tablet".to_string(), dismissal).await.unwrap();
+        assert_eq!(sink.dismissed.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_copy_dismissal_reports_to_origin() {
+        let (mirror, _source, sink) = mirror(&[]);
+        let notification = Notification {
+            notification_id: Uuid::new_v4(),
+            title: "Mail: Invoice".to_string(),
+            message: "Due Friday".to_string(),
+            notification_type: NotificationType::Info,
+            priority: NotificationPriority::Normal,
+            duration: None,
+            actions: Vec::new(),
+            sender: "laptop".to_string(),
+            image: None,
+            launch: None,
+        };
+        let id = notification.notification_id;
+        mirror.handle_remote_notification(peer("laptop"), notification).await.unwrap();
+
+        mirror.dismiss(id).await.unwrap();
+        assert_eq!(*sink.dismissed.lock().unwrap(), vec![("laptop".to_string(), id)]);
+    }
+
+    #[tokio::test]
+    async fn test_held_while_busy() {
+        use crate::discovery::{ActivityProbe, ActivitySignals, PresenceConfig, PresenceStatus};
+
+        struct Idle;
+        impl ActivityProbe for Idle {
+            fn sample(&self) -> ActivitySignals {
+                ActivitySignals::default()
+            }
+        }
+
+        let presence = Arc::new(PresenceManager::with_probe(PresenceConfig::default(), Box::new(Idle)));
+        presence.set_manual(Some(PresenceStatus::Busy));
+        let (mirror, _source, _sink) = mirror(&[]);
+        let mirror = mirror.with_presence(presence.clone());
+
+        let remote = |title: &str, priority| Notification {
+            notification_id: Uuid::new_v4(),
+            title: title.to_string(),
+            message: String::new(),
+            notification_type: NotificationType::Info,
+            priority,
+            duration: None,
+            actions: Vec::new(),
+            sender: "laptop".to_string(),
+            image: None,
+            launch: None,
+        };
+        let first = remote("Build finished", NotificationPriority::Normal);
+        let second = remote("Chat", NotificationPriority::Low);
+        let dismissed = second.notification_id;
+        mirror.handle_remote_notification(peer("laptop"), first).await.unwrap();
+        mirror.handle_remote_notification(peer("laptop"), second).await.unwrap();
+        mirror.handle_remote_notification(peer("laptop"), remote("Alarm", NotificationPriority::Critical)).await.unwrap();
+        assert_eq!(mirror.held_count(), 2);
+
+        assert_eq!(mirror.release_held().await.unwrap(), 0);
+        mirror.dismiss(dismissed).await.unwrap();
+
+        presence.set_manual(None);
+        assert_eq!(mirror.release_held().await.unwrap(), 1);
+        assert_eq!(mirror.held_count(), 0);
+    }
+}
@@ -7,7 +7,7 @@ use crate::command_execution::error::{CommandError, CommandResult};
 use crate::command_execution::types::*;
 use chrono::Utc;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use uuid::Uuid;
 
 #[cfg(target_os = "windows")]
@@ -19,6 +19,7 @@ pub mod linux;
 
 pub mod formatter;
 pub mod delivery;
+pub mod mirror;
 
 #[cfg(test)]
 mod integration_test;
@@ -31,6 +32,12 @@ pub use delivery::{
     NotificationQueue, DeliveryTracker, DeliveryService,
     DeliveryInfo, DeliveryAnalytics,
 };
+pub use mirror::{
+    NotificationMirror, MirrorConfig, MirrorSink, NotificationSource,
+    CapturedNotification, CaptureEvent,
+};
+#[cfg(target_os = "linux")]
+pub use linux::LinuxNotificationSource;
 
 /// Platform-specific notification backend trait
 pub trait NotificationBackend: Send + Sync {
@@ -42,6 +49,57 @@ pub trait NotificationBackend: Send + Sync {
     
     /// Get platform-specific capabilities
     fn get_capabilities(&self) -> NotificationCapabilities;
+    
+    /// Register the callback run when a shown notification is clicked or closed
+    ///
+    /// Backends without `supports_activation` ignore the handler.
+    fn set_event_handler(&self, _handler: EventHandler) {}
+    
+    /// Take a shown notification off the screen
+    ///
+    /// Backends that cannot remove notifications leave them to expire.
+    fn close_notification(&self, _notification_id: NotificationId) -> CommandResult<()> {
+        Ok(())
+    }
+}
+
+/// Callback for clicked notifications
+pub type ActivationHandler = Arc<dyn Fn(NotificationActivation) + Send + Sync>;
+
+/// Callback for everything that happens to shown notifications
+pub type EventHandler = Arc<dyn Fn(NotificationEvent) + Send + Sync>;
+
+/// Something that happened to a notification after it was shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationEvent {
+    /// The notification or one of its actions was clicked
+    Activated(NotificationActivation),
+    /// The notification left the screen
+    Closed {
+        notification_id: NotificationId,
+        reason: CloseReason,
+    },
+}
+
+/// Why a notification left the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    Expired,
+    /// Dismissed by the user
+    Dismissed,
+    /// Closed through `close_notification`
+    Closed,
+    Unknown,
+}
+
+/// A notification the user clicked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationActivation {
+    pub notification_id: NotificationId,
+    /// The action button that was clicked, `None` for the notification body
+    pub action_id: Option<String>,
+    /// The notification's `launch` argument
+    pub launch: Option<String>,
 }
 
 /// Notification system capabilities
@@ -51,6 +109,14 @@ pub struct NotificationCapabilities {
     pub supports_duration: bool,
     pub supports_priority: bool,
     pub supports_icons: bool,
+    /// Shows `Notification::image`
+    pub supports_images: bool,
+    /// Reports clicks to the activation handler
+    pub supports_activation: bool,
+    /// Showing a notification with the ID of one still on screen updates it in place
+    pub supports_replace: bool,
+    /// Notifications other applications post here can be captured for `NotificationMirror`
+    pub supports_mirroring: bool,
     pub max_title_length: Option<usize>,
     pub max_message_length: Option<usize>,
 }
@@ -63,6 +129,7 @@ pub struct NotificationManager {
     notification_history: Arc<Mutex<Vec<NotificationRecord>>>,
     pending_notifications: Arc<Mutex<HashMap<NotificationId, Notification>>>,
     delivery_status: Arc<Mutex<HashMap<NotificationId, DeliveryStatus>>>,
+    activation_handler: Arc<RwLock<Option<ActivationHandler>>>,
 }
 
 /// Notification history record
@@ -111,6 +178,20 @@ impl NotificationManager {
         );
         let delivery_service = DeliveryService::new(max_retries, retry_delay);
         
+        // Clicks and closes go to the delivery tracker; clicks are also passed
+        // on to the handler registered with on_activation
+        let tracker = delivery_service.tracker();
+        let activation_handler: Arc<RwLock<Option<ActivationHandler>>> = Arc::new(RwLock::new(None));
+        let forward = Arc::clone(&activation_handler);
+        backend.set_event_handler(Arc::new(move |event: NotificationEvent| {
+            tracker.record_event(&event);
+            if let NotificationEvent::Activated(activation) = event
+                && let Some(handler) = forward.read().expect("Lock poisoned").as_ref()
+            {
+                handler(activation);
+            }
+        }));
+        
         Ok(Self {
             backend,
             formatter,
@@ -118,11 +199,12 @@ impl NotificationManager {
             notification_history: Arc::new(Mutex::new(Vec::new())),
             pending_notifications: Arc::new(Mutex::new(HashMap::new())),
             delivery_status: Arc::new(Mutex::new(HashMap::new())),
+            activation_handler,
         })
     }
     
     /// Create the appropriate platform-specific backend
-    fn create_platform_backend() -> CommandResult<Box<dyn NotificationBackend>> {
+    pub(crate) fn create_platform_backend() -> CommandResult<Box<dyn NotificationBackend>> {
         #[cfg(target_os = "windows")]
         {
             Ok(Box::new(windows::WindowsNotificationBackend::new()?))
@@ -171,6 +253,7 @@ impl NotificationManager {
         
         // Queue notification for delivery with retry support
         self.delivery_service.queue_notification(notification.clone(), target.clone());
+        self.delivery_service.track_attempt(notification_id);
         
         // Attempt immediate delivery
         let backend = &self.backend;
@@ -308,6 +391,31 @@ impl NotificationManager {
             status.insert(notification_id, DeliveryStatus::Cancelled);
         }
         
+        // Take it off the screen if it was already shown
+        if let Err(e) = self.backend.close_notification(notification_id) {
+            log::debug!("Failed to close notification {}: {}", notification_id, e);
+        }
+        
+        Ok(())
+    }
+    
+    /// Replace a notification that is already on screen, e.g. to update
+    /// the progress it reports
+    ///
+    /// Backends that cannot replace notifications show a new one.
+    pub async fn update_notification(&self, notification: Notification) -> CommandResult<()> {
+        self.formatter.validate(&notification)?;
+        self.backend.show_notification(&notification)?;
+        
+        let mut history = self.notification_history.lock().unwrap();
+        if let Some(record) = history
+            .iter_mut()
+            .rev()
+            .find(|record| record.notification.notification_id == notification.notification_id)
+        {
+            record.notification = notification;
+        }
+        
         Ok(())
     }
     
@@ -322,6 +430,14 @@ impl NotificationManager {
         self.backend.get_capabilities()
     }
     
+    /// Run `handler` when the user clicks a notification or one of its actions
+    ///
+    /// Replaces any previous handler. Does nothing on platforms without
+    /// activation support; check `get_capabilities().supports_activation`.
+    pub fn on_activation(&self, handler: impl Fn(NotificationActivation) + Send + Sync + 'static) {
+        *self.activation_handler.write().expect("Lock poisoned") = Some(Arc::new(handler));
+    }
+    
     /// Check if notifications are supported
     pub fn is_supported(&self) -> bool {
         self.backend.is_supported()
@@ -445,6 +561,8 @@ pub fn create_notification(
         duration: None,
         actions: vec![],
         sender,
+        image: None,
+        launch: None,
     }
 }
 
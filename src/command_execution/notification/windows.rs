@@ -1,97 +1,300 @@
 // Windows Notification Backend
 //
-// Implements Windows notifications using Windows Runtime APIs (WinRT)
+// Implements Windows notifications as WinRT toasts. Toasts carry up to five
+// action buttons and an optional hero image, and clicks and dismissals are
+// reported to the event handler while the process that showed the toast is
+// running.
+//
+// Kizuna is not a packaged app, so its AppUserModelID is registered under
+// HKCU\Software\Classes\AppUserModelId for Windows to accept its toasts.
 
 use crate::command_execution::error::{CommandError, CommandResult};
 use crate::command_execution::types::*;
-use super::{NotificationBackend, NotificationCapabilities};
+use super::{
+    CloseReason, EventHandler, NotificationActivation, NotificationBackend, NotificationCapabilities,
+    NotificationEvent,
+};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+use windows::core::{IInspectable, Interface, HSTRING};
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::Foundation::TypedEventHandler;
+use windows::UI::Notifications::{
+    ToastActivatedEventArgs, ToastDismissalReason, ToastDismissedEventArgs, ToastNotification,
+    ToastNotificationManager, ToastNotificationPriority,
+};
+
+/// Windows shows at most five buttons on a toast
+const MAX_TOAST_ACTIONS: usize = 5;
+
+/// Toasts with a requested duration above this stay up for the long duration (~25s)
+const SHORT_TOAST_DURATION: Duration = Duration::from_secs(7);
+
+/// Name shown as the toast's source in Action Center
+const APP_DISPLAY_NAME: &str = "Kizuna";
 
 /// Windows notification backend using WinRT APIs
 pub struct WindowsNotificationBackend {
     app_id: String,
+    event_handler: Arc<RwLock<Option<EventHandler>>>,
 }
 
 impl WindowsNotificationBackend {
     /// Create a new Windows notification backend
     pub fn new() -> CommandResult<Self> {
+        let app_id = "Kizuna.CommandExecution".to_string();
+        if let Err(e) = register_app_id(&app_id) {
+            log::warn!("Failed to register {} for toast notifications: {}", app_id, e);
+        }
+
         Ok(Self {
-            app_id: "Kizuna.CommandExecution".to_string(),
+            app_id,
+            event_handler: Arc::new(RwLock::new(None)),
         })
     }
-    
-    /// Format notification for Windows
-    fn format_notification(&self, notification: &Notification) -> String {
-        // Create a simple text notification format
-        // In a full implementation, this would use Windows.UI.Notifications
-        format!(
-            "[{}] {}: {}",
-            self.format_type(notification.notification_type),
-            notification.title,
-            notification.message
-        )
-    }
-    
-    /// Format notification type for display
-    fn format_type(&self, notification_type: NotificationType) -> &str {
-        match notification_type {
-            NotificationType::Info => "INFO",
-            NotificationType::Warning => "WARNING",
-            NotificationType::Error => "ERROR",
-            NotificationType::Success => "SUCCESS",
+
+    /// Build the toast XML for a notification
+    fn toast_xml(&self, notification: &Notification) -> String {
+        let id = notification.notification_id;
+        let launch = notification.launch.as_deref();
+        let actions = &notification.actions[..notification.actions.len().min(MAX_TOAST_ACTIONS)];
+        if actions.len() < notification.actions.len() {
+            log::debug!(
+                "Toast {} has {} actions, showing the first {}",
+                id,
+                notification.actions.len(),
+                MAX_TOAST_ACTIONS
+            );
+        }
+
+        let mut xml = format!("<toast launch=\"{}\"", escape_xml(&toast_arguments(id, None, launch)));
+        if notification.duration.is_some_and(|duration| duration > SHORT_TOAST_DURATION) {
+            xml.push_str(" duration=\"long\"");
+        }
+        // The reminder scenario keeps the toast on screen until the user acts
+        // on it, and Windows ignores it for toasts without buttons
+        if notification.priority == NotificationPriority::Critical && !actions.is_empty() {
+            xml.push_str(" scenario=\"reminder\"");
+        }
+        xml.push('>');
+
+        xml.push_str("<visual><binding template=\"ToastGeneric\">");
+        xml.push_str(&format!("<text>{}</text>", escape_xml(&notification.title)));
+        xml.push_str(&format!("<text>{}</text>", escape_xml(&notification.message)));
+        if let Some(src) = notification.image.as_deref().and_then(image_uri) {
+            xml.push_str(&format!("<image placement=\"hero\" src=\"{}\"/>", escape_xml(&src)));
+        }
+        xml.push_str("</binding></visual>");
+
+        if !actions.is_empty() {
+            xml.push_str("<actions>");
+            for action in actions {
+                xml.push_str(&format!(
+                    "<action content=\"{}\" arguments=\"{}\" activationType=\"foreground\"/>",
+                    escape_xml(&action.label),
+                    escape_xml(&toast_arguments(id, Some(&action.id), launch)),
+                ));
+            }
+            xml.push_str("</actions>");
+        }
+
+        xml.push_str("</toast>");
+        xml
+    }
+
+    /// Create the toast and hook its activation and dismissal events up to the handler
+    fn build_toast(&self, notification: &Notification) -> windows::core::Result<ToastNotification> {
+        let document = XmlDocument::new()?;
+        document.LoadXml(&HSTRING::from(self.toast_xml(notification)))?;
+
+        let toast = ToastNotification::CreateToastNotification(&document)?;
+        toast.SetTag(&HSTRING::from(notification.notification_id.simple().to_string()))?;
+        if notification.priority >= NotificationPriority::High {
+            toast.SetPriority(ToastNotificationPriority::High)?;
         }
+
+        let handler = Arc::clone(&self.event_handler);
+        toast.Activated(&TypedEventHandler::<ToastNotification, IInspectable>::new(
+            move |_, args: &Option<IInspectable>| {
+                let arguments = match args.as_ref().map(|args| args.cast::<ToastActivatedEventArgs>()) {
+                    Some(Ok(args)) => args.Arguments()?.to_string_lossy(),
+                    _ => return Ok(()),
+                };
+                let Some(activation) = parse_toast_arguments(&arguments) else {
+                    log::debug!("Ignoring toast activation with unknown arguments: {}", arguments);
+                    return Ok(());
+                };
+                if let Some(handler) = handler.read().expect("Lock poisoned").as_ref() {
+                    handler(NotificationEvent::Activated(activation));
+                }
+                Ok(())
+            },
+        ))?;
+
+        let handler = Arc::clone(&self.event_handler);
+        let notification_id = notification.notification_id;
+        toast.Dismissed(&TypedEventHandler::<ToastNotification, ToastDismissedEventArgs>::new(
+            move |_, args: &Option<ToastDismissedEventArgs>| {
+                let reason = match args.as_ref().map(|args| args.Reason()) {
+                    Some(Ok(ToastDismissalReason::UserCanceled)) => CloseReason::Dismissed,
+                    Some(Ok(ToastDismissalReason::TimedOut)) => CloseReason::Expired,
+                    Some(Ok(ToastDismissalReason::ApplicationHidden)) => CloseReason::Closed,
+                    _ => CloseReason::Unknown,
+                };
+                if let Some(handler) = handler.read().expect("Lock poisoned").as_ref() {
+                    handler(NotificationEvent::Closed { notification_id, reason });
+                }
+                Ok(())
+            },
+        ))?;
+
+        Ok(toast)
     }
 }
 
 impl NotificationBackend for WindowsNotificationBackend {
     fn show_notification(&self, notification: &Notification) -> CommandResult<()> {
-        // For now, we'll use a simple console output as a placeholder
-        // A full implementation would use Windows.UI.Notifications.ToastNotificationManager
-        
-        #[cfg(target_os = "windows")]
-        {
-            // In a production implementation, this would use:
-            // - Windows.UI.Notifications.ToastNotificationManager
-            // - Windows.Data.Xml.Dom for XML toast templates
-            // - Windows.UI.Notifications.ToastNotification
-            
-            let formatted = self.format_notification(notification);
-            
-            // Placeholder: In production, this would call WinRT APIs
-            // For now, we'll simulate success
-            eprintln!("Windows Notification: {}", formatted);
-            
-            Ok(())
-        }
-        
-        #[cfg(not(target_os = "windows"))]
-        {
-            Err(CommandError::NotificationError(
-                "Windows notifications only supported on Windows".to_string()
-            ))
-        }
+        let show = || -> windows::core::Result<()> {
+            let toast = self.build_toast(notification)?;
+            ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(&self.app_id))?.Show(&toast)
+        };
+
+        show().map_err(|e| CommandError::NotificationError(format!("Failed to show toast notification: {}", e)))
     }
-    
+
     fn is_supported(&self) -> bool {
-        cfg!(target_os = "windows")
+        true
     }
-    
+
     fn get_capabilities(&self) -> NotificationCapabilities {
         NotificationCapabilities {
             supports_actions: true,
+            // Toasts are either short (~7s) or long (~25s)
             supports_duration: true,
-            supports_priority: false,
-            supports_icons: true,
+            supports_priority: true,
+            // The app icon comes from the AppUserModelID; per-toast icons are not set
+            supports_icons: false,
+            supports_images: true,
+            supports_activation: true,
+            // Toasts with the same tag replace each other
+            supports_replace: true,
+            // Reading other applications' toasts needs a packaged listener, which is not implemented
+            supports_mirroring: false,
             max_title_length: Some(256),
             max_message_length: Some(1024),
         }
     }
+
+    fn set_event_handler(&self, handler: EventHandler) {
+        *self.event_handler.write().expect("Lock poisoned") = Some(handler);
+    }
+}
+
+/// Bring the console window this process runs in to the front
+///
+/// Used when a toast for an operation started from the terminal is clicked.
+/// Returns false when there is no console window or Windows refused the
+/// focus change.
+pub fn focus_console_window() -> bool {
+    use windows::Win32::System::Console::GetConsoleWindow;
+    use windows::Win32::UI::WindowsAndMessaging::SetForegroundWindow;
+
+    unsafe {
+        let window = GetConsoleWindow();
+        !window.is_invalid() && SetForegroundWindow(window).as_bool()
+    }
+}
+
+/// Register the AppUserModelID so Windows shows toasts from an unpackaged app
+fn register_app_id(app_id: &str) -> windows::core::Result<()> {
+    use windows::Win32::System::Registry::{RegCloseKey, RegCreateKeyW, RegSetValueExW, HKEY, HKEY_CURRENT_USER, REG_SZ};
+
+    let key_path = HSTRING::from(format!("Software\\Classes\\AppUserModelId\\{}", app_id));
+    let display_name: Vec<u8> = APP_DISPLAY_NAME
+        .encode_utf16()
+        .chain(Some(0))
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect();
+
+    unsafe {
+        let mut key = HKEY::default();
+        RegCreateKeyW(HKEY_CURRENT_USER, &key_path, &mut key).ok()?;
+        let result = RegSetValueExW(key, &HSTRING::from("DisplayName"), 0, REG_SZ, Some(&display_name)).ok();
+        let _ = RegCloseKey(key);
+        result
+    }
+}
+
+/// `file:///` URI for a local image, or `None` when it cannot be shown
+///
+/// Unpackaged apps cannot load remote images into toasts.
+fn image_uri(image: &str) -> Option<String> {
+    let path = Path::new(image);
+    if !path.is_absolute() || !path.exists() {
+        log::debug!("Not showing toast image {}: only existing local files are supported", image);
+        return None;
+    }
+    Some(format!("file:///{}", image.replace('\\', "/")))
+}
+
+/// Encode the activation arguments for the toast body or one of its buttons
+fn toast_arguments(notification_id: Uuid, action_id: Option<&str>, launch: Option<&str>) -> String {
+    let mut arguments = format!("notification={}", notification_id);
+    if let Some(action_id) = action_id {
+        arguments.push_str(&format!("&action={}", escape_argument(action_id)));
+    }
+    if let Some(launch) = launch {
+        arguments.push_str(&format!("&launch={}", escape_argument(launch)));
+    }
+    arguments
+}
+
+/// Decode the arguments Windows passes back when a toast is clicked
+fn parse_toast_arguments(arguments: &str) -> Option<NotificationActivation> {
+    let mut notification_id = None;
+    let mut action_id = None;
+    let mut launch = None;
+
+    for pair in arguments.split('&') {
+        let (key, value) = pair.split_once('=')?;
+        match key {
+            "notification" => notification_id = Uuid::parse_str(value).ok(),
+            "action" => action_id = Some(unescape_argument(value)),
+            "launch" => launch = Some(unescape_argument(value)),
+            _ => {}
+        }
+    }
+
+    Some(NotificationActivation {
+        notification_id: notification_id?,
+        action_id,
+        launch,
+    })
+}
+
+fn escape_argument(value: &str) -> String {
+    value.replace('%', "%25").replace('&', "%26").replace('=', "%3D")
+}
+
+fn unescape_argument(value: &str) -> String {
+    value.replace("%3D", "=").replace("%26", "&").replace("%25", "%")
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
 }
 
 #[cfg(test)]
-#[cfg(target_os = "windows")]
 mod tests {
     use super::*;
-    use uuid::Uuid;
+    use super::super::create_notification;
 
     #[test]
     fn test_windows_backend_creation() {
@@ -111,5 +314,42 @@ mod tests {
         let caps = backend.get_capabilities();
         assert!(caps.supports_actions);
         assert!(caps.supports_duration);
+        assert!(caps.supports_images);
+        assert!(caps.supports_activation);
+    }
+
+    #[test]
+    fn test_toast_arguments_round_trip() {
+        let id = Uuid::new_v4();
+        let arguments = toast_arguments(id, Some("re=try&now"), Some("operation/100%"));
+        let activation = parse_toast_arguments(&arguments).unwrap();
+        assert_eq!(activation.notification_id, id);
+        assert_eq!(activation.action_id.as_deref(), Some("re=try&now"));
+        assert_eq!(activation.launch.as_deref(), Some("operation/100%"));
+
+        let body = parse_toast_arguments(&toast_arguments(id, None, None)).unwrap();
+        assert_eq!(body.action_id, None);
+        assert!(parse_toast_arguments("launch=x").is_none());
+    }
+
+    #[test]
+    fn test_toast_xml_escapes_text_and_limits_actions() {
+        let backend = WindowsNotificationBackend::new().unwrap();
+        let mut notification = create_notification("Tom & Jerry", "<done>", NotificationType::Info, "peer".to_string());
+        notification.priority = NotificationPriority::Critical;
+        notification.duration = Some(Duration::from_secs(20));
+        notification.actions = (0..7)
+            .map(|i| NotificationAction { id: format!("a{}", i), label: format!("Action {}", i) })
+            .collect();
+
+        let xml = backend.toast_xml(&notification);
+        assert!(xml.contains("<text>Tom &amp; Jerry</text>"));
+        assert!(xml.contains("<text>&lt;done&gt;</text>"));
+        assert!(xml.contains("duration=\"long\""));
+        assert!(xml.contains("scenario=\"reminder\""));
+        assert_eq!(xml.matches("<action ").count(), MAX_TOAST_ACTIONS);
+
+        let document = XmlDocument::new().unwrap();
+        document.LoadXml(&HSTRING::from(xml)).unwrap();
     }
 }
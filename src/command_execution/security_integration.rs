@@ -9,10 +9,12 @@ use serde::{Deserialize, Serialize};
 
 use crate::command_execution::{
     CommandRequest, CommandResult, ScriptRequest, ScriptResult, Notification,
-    NotificationResult, SystemInfo, SystemInfoQuery,
+    NotificationDismissal, NotificationResult, SystemInfo, SystemInfoQuery, MediaRequest, MediaResult,
+    HandoffRequest, HandoffResult, LocateRequest, LocateResult,
 };
 use crate::command_execution::error::{CommandError, CommandResult as CmdResult};
 use crate::security::{Security, SessionId, PeerId as SecurityPeerId};
+use crate::security::policy::RateLimitedOperation;
 
 // Command execution uses String for PeerId
 type PeerId = String;
@@ -43,6 +45,29 @@ pub enum CommandMessageType {
     SystemInfoResponse,
     NotificationRequest,
     NotificationResult,
+    NotificationDismiss,
+    MediaRequest,
+    MediaResult,
+    HandoffRequest,
+    HandoffResult,
+    LocateRequest,
+    LocateResult,
+}
+
+impl CommandMessageType {
+    /// Whether a peer sends this to have something done on this device
+    pub fn is_request(&self) -> bool {
+        matches!(
+            self,
+            CommandMessageType::CommandRequest
+                | CommandMessageType::ScriptRequest
+                | CommandMessageType::SystemInfoQuery
+                | CommandMessageType::NotificationRequest
+                | CommandMessageType::MediaRequest
+                | CommandMessageType::HandoffRequest
+                | CommandMessageType::LocateRequest
+        )
+    }
 }
 
 /// Command message payload (before encryption)
@@ -56,6 +81,13 @@ pub enum CommandMessage {
     SystemInfoResponse(SystemInfo),
     NotificationRequest(Notification),
     NotificationResult(NotificationResult),
+    NotificationDismiss(NotificationDismissal),
+    MediaRequest(MediaRequest),
+    MediaResult(MediaResult),
+    HandoffRequest(HandoffRequest),
+    HandoffResult(HandoffResult),
+    LocateRequest(LocateRequest),
+    LocateResult(LocateResult),
 }
 
 impl CommandMessage {
@@ -70,6 +102,13 @@ impl CommandMessage {
             CommandMessage::SystemInfoResponse(_) => CommandMessageType::SystemInfoResponse,
             CommandMessage::NotificationRequest(_) => CommandMessageType::NotificationRequest,
             CommandMessage::NotificationResult(_) => CommandMessageType::NotificationResult,
+            CommandMessage::NotificationDismiss(_) => CommandMessageType::NotificationDismiss,
+            CommandMessage::MediaRequest(_) => CommandMessageType::MediaRequest,
+            CommandMessage::MediaResult(_) => CommandMessageType::MediaResult,
+            CommandMessage::HandoffRequest(_) => CommandMessageType::HandoffRequest,
+            CommandMessage::HandoffResult(_) => CommandMessageType::HandoffResult,
+            CommandMessage::LocateRequest(_) => CommandMessageType::LocateRequest,
+            CommandMessage::LocateResult(_) => CommandMessageType::LocateResult,
         }
     }
 }
@@ -155,6 +194,14 @@ impl CommandSecurityIntegration {
             ));
         }
 
+        // Requests spend the sender's command allowance before any work is done
+        if encrypted_message.message_type.is_request() {
+            self.security
+                .check_operation_rate(&security_sender, RateLimitedOperation::CommandRequest)
+                .await
+                .map_err(|e| CommandError::SecurityError(format!("Request refused: {}", e)))?;
+        }
+
         // Decrypt message
         let decrypted_data = self.security.decrypt_message(
             &encrypted_message.session_id,
@@ -163,12 +210,22 @@ impl CommandSecurityIntegration {
         .await
         .map_err(|e| CommandError::SecurityError(format!("Decryption failed: {}", e)))?;
 
-        // Deserialize message
-        let message: CommandMessage = serde_json::from_slice(&decrypted_data)
-            .map_err(|e| CommandError::SerializationError(format!("Failed to deserialize message: {}", e)))?;
+        // Deserialize message; garbage from a trusted peer counts towards a ban
+        let message: CommandMessage = match serde_json::from_slice(&decrypted_data) {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = self.security
+                    .report_malformed_message(&security_sender, "undecodable command message")
+                    .await;
+                return Err(CommandError::SerializationError(format!("Failed to deserialize message: {}", e)));
+            }
+        };
 
         // Verify message type matches
         if message.message_type() != encrypted_message.message_type {
+            let _ = self.security
+                .report_malformed_message(&security_sender, "command message type mismatch")
+                .await;
             return Err(CommandError::SecurityError(
                 "Message type mismatch - possible tampering".to_string()
             ));
@@ -11,7 +11,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::command_execution::{
     CommandRequest, CommandResult, ScriptRequest, ScriptResult, Notification,
-    NotificationResult, SystemInfo, SystemInfoQuery, PeerId,
+    NotificationDismissal, NotificationResult, SystemInfo, SystemInfoQuery, PeerId, MediaRequest, MediaResult,
+    HandoffRequest, HandoffResult, LocateRequest, LocateResult,
 };
 use crate::command_execution::error::{CommandError, CommandResult as CmdResult};
 use crate::command_execution::security_integration::{
@@ -258,18 +259,171 @@ impl CommandTransportIntegration {
         self.send_encrypted_message(message, peer_id, peer_address).await
     }
 
+    /// Tell a peer that a mirrored notification was dismissed (fire and forget)
+    pub async fn send_notification_dismissal(
+        &self,
+        dismissal: NotificationDismissal,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<()> {
+        let peer_id = &peer_address.peer_id;
+        let message = CommandMessage::NotificationDismiss(dismissal);
+        self.send_encrypted_message(message, peer_id, peer_address).await
+    }
+
+    /// Send a media control request and wait for result
+    pub async fn send_media_request(
+        &self,
+        request: MediaRequest,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<MediaResult> {
+        let request_id = request.request_id;
+        let peer_id = &peer_address.peer_id;
+
+        // Create response channel
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        {
+            let mut channels = self.response_channels.write().await;
+            channels.insert(request_id, tx);
+        }
+
+        // Send request
+        let message = CommandMessage::MediaRequest(request);
+        self.send_encrypted_message(message, peer_id, peer_address).await?;
+
+        // Wait for response with timeout
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(10), // Media commands are near-instant
+            rx.recv()
+        )
+        .await
+        .map_err(|_| CommandError::Timeout(std::time::Duration::from_secs(10)))?
+        .ok_or_else(|| CommandError::TransportError("Response channel closed".to_string()))?;
+
+        // Clean up response channel
+        {
+            let mut channels = self.response_channels.write().await;
+            channels.remove(&request_id);
+        }
+
+        // Extract media result
+        match result {
+            CommandMessage::MediaResult(media_result) => Ok(media_result),
+            _ => Err(CommandError::TransportError("Unexpected response type".to_string())),
+        }
+    }
+
+    /// Send a handoff request and wait for the peer to open the target
+    pub async fn send_handoff_request(
+        &self,
+        request: HandoffRequest,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<HandoffResult> {
+        let request_id = request.request_id;
+        let peer_id = &peer_address.peer_id;
+
+        // Create response channel
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        {
+            let mut channels = self.response_channels.write().await;
+            channels.insert(request_id, tx);
+        }
+
+        // Send request
+        let message = CommandMessage::HandoffRequest(request);
+        self.send_encrypted_message(message, peer_id, peer_address).await?;
+
+        // Wait for the reply; launching a browser or viewer is quick
+        let result = self
+            .await_reply(request_id, peer_address, &mut rx, std::time::Duration::from_secs(10))
+            .await?;
+
+        // Extract handoff result
+        match result {
+            CommandMessage::HandoffResult(handoff_result) => Ok(handoff_result),
+            _ => Err(CommandError::TransportError("Unexpected response type".to_string())),
+        }
+    }
+
+    /// Send a locate request and wait for the peer to start or stop ringing
+    pub async fn send_locate_request(
+        &self,
+        request: LocateRequest,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<LocateResult> {
+        let request_id = request.request_id;
+        let peer_id = &peer_address.peer_id;
+
+        // Create response channel
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        {
+            let mut channels = self.response_channels.write().await;
+            channels.insert(request_id, tx);
+        }
+
+        // Send request
+        let message = CommandMessage::LocateRequest(request);
+        self.send_encrypted_message(message, peer_id, peer_address).await?;
+
+        // Wait for the reply; the peer answers once ringing starts
+        let result = self
+            .await_reply(request_id, peer_address, &mut rx, std::time::Duration::from_secs(10))
+            .await?;
+
+        // Extract locate result
+        match result {
+            CommandMessage::LocateResult(locate_result) => Ok(locate_result),
+            _ => Err(CommandError::TransportError("Unexpected response type".to_string())),
+        }
+    }
+
+    /// Wait for the reply to `request_id`
+    ///
+    /// Replies normally reach the response channel through
+    /// [`handle_incoming_message`](Self::handle_incoming_message). A one-shot
+    /// caller has no receive loop running, so the peer's connection is read
+    /// here as well and anything that is not this reply is routed as usual.
+    async fn await_reply(
+        &self,
+        request_id: uuid::Uuid,
+        peer_address: &PeerAddress,
+        rx: &mut mpsc::UnboundedReceiver<CommandMessage>,
+        timeout: std::time::Duration,
+    ) -> CmdResult<CommandMessage> {
+        let handle = self.get_or_connect(peer_address).await?;
+        let wait = async {
+            loop {
+                tokio::select! {
+                    message = rx.recv() => {
+                        return message
+                            .ok_or_else(|| CommandError::TransportError("Response channel closed".to_string()));
+                    }
+                    message = self.receive_encrypted_message(&handle) => {
+                        let message = message?;
+                        if reply_id(&message) == Some(request_id) {
+                            return Ok(message);
+                        }
+                        self.handle_incoming_message(message).await?;
+                    }
+                }
+            }
+        };
+        let result = tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| CommandError::Timeout(timeout));
+
+        // Clean up response channel
+        {
+            let mut channels = self.response_channels.write().await;
+            channels.remove(&request_id);
+        }
+
+        result?
+    }
+
     /// Handle incoming message (to be called by message receiver loop)
     pub async fn handle_incoming_message(&self, message: CommandMessage) -> CmdResult<()> {
         // Route message to appropriate response channel
-        let message_id = match &message {
-            CommandMessage::CommandResult(result) => Some(result.request_id),
-            CommandMessage::ScriptResult(result) => Some(result.request_id),
-            CommandMessage::SystemInfoResponse(_) => None, // Need to extract query_id differently
-            CommandMessage::NotificationResult(result) => Some(result.notification_id),
-            _ => None,
-        };
-
-        if let Some(id) = message_id {
+        if let Some(id) = reply_id(&message) {
             let channels = self.response_channels.read().await;
             if let Some(tx) = channels.get(&id) {
                 let _ = tx.send(message);
@@ -307,6 +461,20 @@ impl CommandTransportIntegration {
     }
 }
 
+/// Request ID a reply answers, if it is a reply
+fn reply_id(message: &CommandMessage) -> Option<uuid::Uuid> {
+    match message {
+        CommandMessage::CommandResult(result) => Some(result.request_id),
+        CommandMessage::ScriptResult(result) => Some(result.request_id),
+        CommandMessage::SystemInfoResponse(_) => None, // Need to extract query_id differently
+        CommandMessage::NotificationResult(result) => Some(result.notification_id),
+        CommandMessage::MediaResult(result) => Some(result.request_id),
+        CommandMessage::HandoffResult(result) => Some(result.request_id),
+        CommandMessage::LocateResult(result) => Some(result.request_id),
+        _ => None,
+    }
+}
+
 /// Configuration for command execution API
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandExecutionConfig {
@@ -388,6 +556,33 @@ impl CommandExecutionApi {
         self.transport_integration.send_notification(notification, peer_address).await
     }
 
+    /// Send a media control command to a remote peer
+    pub async fn control_media(
+        &self,
+        request: MediaRequest,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<MediaResult> {
+        self.transport_integration.send_media_request(request, peer_address).await
+    }
+
+    /// Open a URL or file on a remote peer
+    pub async fn open_on(
+        &self,
+        request: HandoffRequest,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<HandoffResult> {
+        self.transport_integration.send_handoff_request(request, peer_address).await
+    }
+
+    /// Make a remote peer ring, or stop it ringing
+    pub async fn locate(
+        &self,
+        request: LocateRequest,
+        peer_address: &PeerAddress,
+    ) -> CmdResult<LocateResult> {
+        self.transport_integration.send_locate_request(request, peer_address).await
+    }
+
     /// Disconnect from a peer
     pub async fn disconnect(&self, peer_id: &PeerId) -> CmdResult<()> {
         self.transport_integration.disconnect_peer(peer_id).await
@@ -341,6 +341,13 @@ pub struct Notification {
     pub duration: Option<Duration>,
     pub actions: Vec<NotificationAction>,
     pub sender: PeerId,
+    /// Local path of a large picture shown with the notification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Argument handed to the activation handler when the notification is
+    /// clicked, e.g. `operation/<id>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch: Option<String>,
 }
 
 /// Notification types
@@ -377,6 +384,14 @@ pub struct NotificationResult {
     pub error: Option<String>,
 }
 
+/// Dismissal of a mirrored notification, propagated to every device showing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationDismissal {
+    pub notification_id: NotificationId,
+    pub dismissed_by: PeerId,
+    pub dismissed_at: Timestamp,
+}
+
 /// Notification delivery status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DeliveryStatus {
@@ -0,0 +1,299 @@
+//! Background process that keeps this device available to its peers
+//!
+//! `kizuna daemon` runs the long-lived subsystems: the responder answering
+//! directed discovery queries, the periodic [`Maintenance`] run that drops
+//! expired sessions and, in builds with clipboard sync, the clipboard monitor.
+//! Each runs under a [`Supervisor`], so a component that crashes is restarted
+//! with backoff instead of silently leaving the device degraded, and repeated
+//! failures raise a desktop notification. In builds with the browser server,
+//! its `/health/ready` endpoint reports every component.
+//!
+//! The daemon also accepts sessions on the port it announces, exchanging
+//! revocations with every peer that connects.
+
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+#[cfg(feature = "clipboard")]
+use std::time::Duration;
+
+use crate::command_execution::notification::NotificationManager;
+use crate::discovery::responder::{AnnounceResponder, ResponderConfig, ResponderGate};
+#[cfg(feature = "clipboard")]
+use crate::error::KizunaError;
+use crate::error::KizunaResult;
+use crate::maintenance::{Maintenance, MaintenanceConfig, Reaper};
+use crate::security::api::SecuritySystem;
+use crate::security::identity::DeviceIdentity;
+use crate::shutdown::ShutdownCoordinator;
+use crate::supervisor::{desktop_escalation, Supervisor, SupervisorConfig};
+use crate::transport::{
+    accept_connections, select_port, ConnectionProvider, InboundRouter, PortConfig, PortProtocol,
+    ProviderConfig, TcpConfig, TcpListener, TcpTransport, DEFAULT_SERVICE_PORT,
+};
+
+/// Name of the discovery responder in supervisor health reports
+pub const RESPONDER_COMPONENT: &str = "mdns responder";
+/// Name of the clipboard monitor in supervisor health reports
+#[cfg(feature = "clipboard")]
+pub const CLIPBOARD_COMPONENT: &str = "clipboard monitor";
+/// Port the browser server and its health endpoints listen on
+pub const DEFAULT_HEALTH_PORT: u16 = 41360;
+
+/// How often the clipboard monitor is checked for having stopped
+#[cfg(feature = "clipboard")]
+const CLIPBOARD_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What the daemon announces and where it listens
+#[derive(Debug, Clone)]
+pub struct DaemonConfig {
+    /// Name announced to peers
+    pub device_name: String,
+    /// Port peers connect to, tried first before the ports after it
+    pub port: u16,
+    /// Port of the browser server; `None` leaves it off
+    pub health_port: Option<u16>,
+    pub supervisor: SupervisorConfig,
+    pub maintenance: MaintenanceConfig,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            device_name: hostname::get()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|_| "Unknown".to_string()),
+            port: DEFAULT_SERVICE_PORT,
+            health_port: Some(DEFAULT_HEALTH_PORT),
+            supervisor: SupervisorConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+        }
+    }
+}
+
+/// The running daemon
+///
+/// Its components stop when the shutdown coordinator it was started with
+/// begins shutdown.
+pub struct Daemon {
+    supervisor: Arc<Supervisor>,
+    maintenance: Arc<Maintenance>,
+    port: u16,
+}
+
+impl Daemon {
+    /// Start every subsystem under supervision
+    pub async fn start(shutdown: Arc<ShutdownCoordinator>, config: DaemonConfig) -> KizunaResult<Self> {
+        let security = Arc::new(SecuritySystem::new()?);
+        let identity = Arc::new(security.get_or_create_identity().await?);
+        let peer_id = identity.derive_peer_id().to_hex();
+        // Stealth mode and announce windows silence the responder too
+        let visibility = security.get_policy().await?.effective_visibility();
+        let gate: ResponderGate = Arc::new(move || visibility.state().allows_announce());
+
+        let mut supervisor = Supervisor::new(Arc::clone(&shutdown), config.supervisor);
+        match NotificationManager::new() {
+            Ok(manager) => supervisor = supervisor.with_escalation(desktop_escalation(Arc::new(manager))),
+            Err(e) => log::warn!("Repeated component failures will only be logged: {}", e),
+        }
+        let supervisor = Arc::new(supervisor);
+
+        let (provider, port) = serve_sessions(&shutdown, &security, config.port).await?;
+        let maintenance = start_maintenance(
+            &supervisor,
+            config.maintenance,
+            vec![security as Arc<dyn Reaper>, provider as Arc<dyn Reaper>],
+        )
+        .await;
+
+        let responder = ResponderConfig::new(peer_id.clone(), config.device_name.clone(), port);
+        supervise_responder(&supervisor, responder, identity, gate);
+        #[cfg(feature = "clipboard")]
+        supervise_clipboard(&supervisor);
+        #[cfg(feature = "browser-support")]
+        if let Some(port) = config.health_port {
+            serve_browser(&shutdown, &supervisor, peer_id, config.device_name, port).await?;
+        }
+
+        Ok(Self {
+            supervisor,
+            maintenance,
+            port,
+        })
+    }
+
+    /// Supervisor running the daemon's components
+    pub fn supervisor(&self) -> &Arc<Supervisor> {
+        &self.supervisor
+    }
+
+    /// Periodic reaping of expired sessions
+    pub fn maintenance(&self) -> &Arc<Maintenance> {
+        &self.maintenance
+    }
+
+    /// Port the daemon accepts sessions on, which may differ from the
+    /// configured one when that was taken
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+/// Accept sessions on `port`, or the first free port after it, until shutdown
+///
+/// Returns the provider holding the sessions and the port actually bound.
+async fn serve_sessions(
+    shutdown: &ShutdownCoordinator,
+    security: &SecuritySystem,
+    port: u16,
+) -> KizunaResult<(Arc<ConnectionProvider>, u16)> {
+    let address = select_port(Ipv4Addr::UNSPECIFIED.into(), &PortConfig::new(port), PortProtocol::Tcp)?;
+    let listener = TcpListener::bind(address, TcpConfig::default()).await?;
+    let port = listener.local_addr()?.port();
+
+    let provider = Arc::new(ConnectionProvider::new(Arc::new(TcpTransport::new()), ProviderConfig::default()));
+    let inbound = InboundRouter::new();
+    inbound.attach(&provider);
+    security.revocation_gossip(Arc::clone(&provider)).await?.serve(&inbound);
+
+    let sessions = Arc::clone(&provider);
+    shutdown.spawn("session listener", move |token| async move {
+        let accepting = accept_connections(listener, Arc::clone(&sessions));
+        token.cancelled().await;
+        accepting.abort();
+        sessions.close_all("daemon shutting down").await;
+    });
+    Ok((provider, port))
+}
+
+/// Run `reapers` on the maintenance interval until shutdown
+async fn start_maintenance(
+    supervisor: &Supervisor,
+    config: MaintenanceConfig,
+    reapers: Vec<Arc<dyn Reaper>>,
+) -> Arc<Maintenance> {
+    let maintenance = Arc::new(Maintenance::new(config));
+    for reaper in reapers {
+        maintenance.register(reaper).await;
+    }
+    maintenance.supervise(supervisor);
+    maintenance
+}
+
+/// Answer discovery queries until shutdown
+fn supervise_responder(
+    supervisor: &Supervisor,
+    config: ResponderConfig,
+    identity: Arc<DeviceIdentity>,
+    gate: ResponderGate,
+) {
+    supervisor.supervise(RESPONDER_COMPONENT, move |token| {
+        let responder = AnnounceResponder::new(config.clone())
+            .with_identity(Arc::clone(&identity))
+            .with_gate(Arc::clone(&gate));
+        async move { Ok(responder.run(token).await?) }
+    });
+}
+
+/// Watch the clipboard until shutdown
+#[cfg(feature = "clipboard")]
+fn supervise_clipboard(supervisor: &Supervisor) {
+    use crate::clipboard::monitor::{ClipboardMonitor, UnifiedClipboardMonitor};
+
+    supervisor.supervise(CLIPBOARD_COMPONENT, |token| async move {
+        let monitor = UnifiedClipboardMonitor::new();
+        monitor.start_monitoring().await?;
+
+        // The monitor turns itself off when it loses access to the clipboard
+        let result = loop {
+            tokio::select! {
+                _ = token.cancelled() => break Ok(()),
+                _ = tokio::time::sleep(CLIPBOARD_CHECK_INTERVAL) => {
+                    if !monitor.is_monitoring() {
+                        break Err(KizunaError::other("clipboard monitor stopped"));
+                    }
+                }
+            }
+        };
+        let _ = monitor.stop_monitoring().await;
+        result
+    });
+}
+
+/// Serve the browser API, whose health endpoints report the supervisor
+#[cfg(feature = "browser-support")]
+async fn serve_browser(
+    shutdown: &ShutdownCoordinator,
+    supervisor: &Arc<Supervisor>,
+    peer_id: String,
+    device_name: String,
+    port: u16,
+) -> KizunaResult<()> {
+    use crate::browser_support::api::server::WebServer;
+    use crate::browser_support::discovery::BrowserDiscovery;
+
+    let mut server = WebServer::new(Arc::new(BrowserDiscovery::new(peer_id, device_name)))
+        .with_supervisor(Arc::clone(supervisor));
+    server.start(port).await?;
+
+    shutdown.spawn("browser server", move |token| async move {
+        token.cancelled().await;
+        if let Err(e) = server.shutdown().await {
+            log::warn!("Failed to stop the browser server: {}", e);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maintenance::MAINTENANCE_COMPONENT;
+    use crate::shutdown::ShutdownReason;
+    use crate::supervisor::ComponentState;
+
+    #[tokio::test]
+    async fn test_responder_runs_until_shutdown() {
+        let shutdown = Arc::new(ShutdownCoordinator::default());
+        let supervisor = Supervisor::new(Arc::clone(&shutdown), SupervisorConfig::default());
+        let mut config = ResponderConfig::new("kizuna-1234".to_string(), "Laptop".to_string(), 8080);
+        config.probe_port = Some(0);
+        config.mdns = false;
+
+        supervise_responder(
+            &supervisor,
+            config,
+            Arc::new(DeviceIdentity::generate().unwrap()),
+            Arc::new(|| true),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(supervisor.component(RESPONDER_COMPONENT).unwrap().state, ComponentState::Running);
+
+        assert!(shutdown.shutdown(ShutdownReason::Requested).await.is_clean());
+        let health = supervisor.component(RESPONDER_COMPONENT).unwrap();
+        assert_eq!(health.state, ComponentState::Stopped);
+        assert_eq!(health.restarts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_reaps_registered_sessions() {
+        let shutdown = Arc::new(ShutdownCoordinator::default());
+        let supervisor = Supervisor::new(Arc::clone(&shutdown), SupervisorConfig::default());
+        let provider = Arc::new(ConnectionProvider::new(Arc::new(TcpTransport::new()), ProviderConfig::default()));
+
+        let maintenance = start_maintenance(
+            &supervisor,
+            MaintenanceConfig::default(),
+            vec![provider as Arc<dyn Reaper>],
+        )
+        .await;
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(supervisor.component(MAINTENANCE_COMPONENT).unwrap().state, ComponentState::Running);
+
+        let report = maintenance.run_once().await;
+        assert!(report.is_success());
+        assert_eq!(report.reaped, vec![("transport sessions".to_string(), 0)]);
+
+        assert!(shutdown.shutdown(ShutdownReason::Requested).await.is_clean());
+        assert_eq!(supervisor.component(MAINTENANCE_COMPONENT).unwrap().state, ComponentState::Stopped);
+    }
+}
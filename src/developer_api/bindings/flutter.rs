@@ -253,6 +253,7 @@ pub mod frb_bindings {
                 enable_webrtc: self.enable_webrtc,
                 enable_websocket: self.enable_websocket,
                 connection_timeout_secs: 30,
+                ..NetworkConfig::default()
             };
             
             Ok(config)
@@ -375,6 +376,12 @@ pub mod frb_bindings {
                 KizunaEvent::Error(error) => {
                     ("error".to_string(), serde_json::to_string(&error).unwrap_or_default())
                 }
+                KizunaEvent::Clipboard(event) => {
+                    ("clipboard".to_string(), serde_json::to_string(&event).unwrap_or_default())
+                }
+                KizunaEvent::Security(event) => {
+                    ("security".to_string(), serde_json::to_string(&event).unwrap_or_default())
+                }
             };
             
             Self { event_type, data }
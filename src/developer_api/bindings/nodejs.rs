@@ -675,6 +675,22 @@ pub mod napi_bindings {
                     data,
                 }
             }
+            KizunaEvent::Clipboard(event) => {
+                let data = serde_json::to_string(&event)
+                    .unwrap_or_else(|_| "{}".to_string());
+                Event {
+                    event_type: "clipboard".to_string(),
+                    data,
+                }
+            }
+            KizunaEvent::Security(event) => {
+                let data = serde_json::to_string(&event)
+                    .unwrap_or_else(|_| "{}".to_string());
+                Event {
+                    event_type: "security".to_string(),
+                    data,
+                }
+            }
         }
     }
 }
@@ -319,6 +319,12 @@ pub mod pyo3_bindings {
                 KizunaEvent::Error(error) => {
                     ("error".to_string(), serde_json::to_string(&error).unwrap_or_default())
                 }
+                KizunaEvent::Clipboard(event) => {
+                    ("clipboard".to_string(), serde_json::to_string(&event).unwrap_or_default())
+                }
+                KizunaEvent::Security(event) => {
+                    ("security".to_string(), serde_json::to_string(&event).unwrap_or_default())
+                }
             };
             
             Self { event_type, data }
@@ -464,6 +470,22 @@ pub mod pyo3_bindings {
                 listen_port: networking_dict.get_item("listen_port")?
                     .map(|v| v.extract::<u16>())
                     .transpose()?,
+                port_range: networking_dict.get_item("port_range")?
+                    .map(|v| v.extract::<String>())
+                    .transpose()?
+                    .map(|range| range.parse::<crate::transport::PortRange>())
+                    .transpose()
+                    .map_err(|e| PyValueError::new_err(e.to_string()))?,
+                port_mapping: networking_dict.get_item("port_mapping")?
+                    .map(|v| v.extract::<bool>())
+                    .transpose()?
+                    .unwrap_or(false),
+                proxy: networking_dict.get_item("proxy")?
+                    .map(|v| v.extract::<String>())
+                    .transpose()?
+                    .map(|url| url.parse::<crate::transport::ProxyConfig>())
+                    .transpose()
+                    .map_err(|e| PyValueError::new_err(e.to_string()))?,
                 enable_ipv6: networking_dict.get_item("enable_ipv6")?
                     .map(|v| v.extract::<bool>())
                     .transpose()?
@@ -1,6 +1,8 @@
 /// Core API trait and implementation
 use super::{KizunaConfig, KizunaError, KizunaEvent};
-use super::events::{PeerId, PeerInfo, TransferId, StreamId};
+use super::events::{
+    ClipboardEvents, DiscoveryEvents, PeerId, PeerInfo, SecurityEvents, StreamId, TransferEvents, TransferId,
+};
 use async_trait::async_trait;
 use futures::Stream;
 use std::path::PathBuf;
@@ -10,6 +12,10 @@ use tokio::sync::RwLock;
 
 // Import Kizuna core systems
 use super::integration::IntegratedSystemManager;
+use crate::discovery::{DiscoveryError, ServiceId, ServiceRegistration, ServiceRegistry};
+use crate::remote_fs::{BrowseError, DirectoryListing, RemoteEntry, RemoteErrorKind};
+#[cfg(feature = "streaming")]
+use crate::streaming::recording::{RecordingDetails, RecordingMetadata, RecordingQuery};
 
 /// Main API trait for Kizuna functionality
 #[async_trait]
@@ -31,9 +37,34 @@ pub trait KizunaAPI: Send + Sync {
     /// Starts a media stream
     async fn start_stream(&self, config: StreamConfig) -> Result<StreamHandle, KizunaError>;
     
+    /// Lists local recordings matching a query, newest first
+    #[cfg(feature = "streaming")]
+    async fn list_recordings(&self, query: RecordingQuery) -> Result<Vec<RecordingMetadata>, KizunaError>;
+    
+    /// Describes a recording, given its ID or a unique prefix of it
+    #[cfg(feature = "streaming")]
+    async fn get_recording(&self, id: String) -> Result<RecordingDetails, KizunaError>;
+    
+    /// Adds and removes user tags on a recording
+    #[cfg(feature = "streaming")]
+    async fn tag_recording(&self, id: String, add: Vec<String>, remove: Vec<String>) -> Result<RecordingMetadata, KizunaError>;
+    
+    /// Replaces the notes on a recording; `None` clears them
+    #[cfg(feature = "streaming")]
+    async fn set_recording_notes(&self, id: String, notes: Option<String>) -> Result<RecordingMetadata, KizunaError>;
+    
     /// Executes a command on a peer
     async fn execute_command(&self, command: String, peer_id: PeerId) -> Result<CommandResult, KizunaError>;
     
+    /// Lists a directory in a peer's shared folders; the empty path lists its shares
+    async fn browse_remote(&self, peer_id: PeerId, path: String) -> Result<DirectoryListing, KizunaError>;
+    
+    /// Describes one entry in a peer's shared folders
+    async fn stat_remote(&self, peer_id: PeerId, path: String) -> Result<RemoteEntry, KizunaError>;
+    
+    /// Searches a peer's shared folders under `path` for names containing `query`
+    async fn search_remote(&self, peer_id: PeerId, path: String, query: String) -> Result<Vec<RemoteEntry>, KizunaError>;
+    
     /// Subscribes to events
     async fn subscribe_events(&self) -> Result<Pin<Box<dyn Stream<Item = KizunaEvent> + Send>>, KizunaError>;
     
@@ -59,7 +90,6 @@ pub struct KizunaInstance {
     config: KizunaConfig,
     runtime: super::runtime::AsyncRuntime,
     event_emitter: super::runtime::ThreadSafe<super::events::EventEmitter>,
-    event_tx: Arc<tokio::sync::broadcast::Sender<KizunaEvent>>,
     // Integrated system manager for all core systems
     system_manager: Arc<IntegratedSystemManager>,
     // Lifecycle management
@@ -87,10 +117,6 @@ impl KizunaInstance {
         let runtime = super::runtime::AsyncRuntime::with_config(runtime_config)
             .map_err(|e| KizunaError::other(format!("Failed to create runtime: {}", e)))?;
         
-        // Create event channel with larger buffer for high-throughput scenarios
-        let (event_tx, _) = tokio::sync::broadcast::channel(1000);
-        let event_tx = Arc::new(event_tx);
-        
         // Create shutdown channel
         let (shutdown_tx, _) = tokio::sync::broadcast::channel(1);
         let shutdown_tx = Arc::new(shutdown_tx);
@@ -101,8 +127,8 @@ impl KizunaInstance {
         Ok(Self {
             config,
             runtime,
-            event_emitter: super::runtime::ThreadSafe::new(super::events::EventEmitter::new()),
-            event_tx,
+            // Larger buffer for high-throughput scenarios
+            event_emitter: super::runtime::ThreadSafe::new(super::events::EventEmitter::with_capacity(1000)),
             system_manager,
             state: Arc::new(tokio::sync::RwLock::new(InstanceState::Initializing)),
             shutdown_tx,
@@ -420,6 +446,32 @@ impl KizunaAPI for KizunaInstance {
         Err(KizunaError::state("Streaming feature not enabled"))
     }
     
+    #[cfg(feature = "streaming")]
+    async fn list_recordings(&self, query: RecordingQuery) -> Result<Vec<RecordingMetadata>, KizunaError> {
+        let catalog = self.system_manager.recordings().await?;
+        catalog.search(&query).await.map_err(recording_error)
+    }
+    
+    #[cfg(feature = "streaming")]
+    async fn get_recording(&self, id: String) -> Result<RecordingDetails, KizunaError> {
+        let catalog = self.system_manager.recordings().await?;
+        catalog.details(&id).await.map_err(recording_error)
+    }
+    
+    #[cfg(feature = "streaming")]
+    async fn tag_recording(&self, id: String, add: Vec<String>, remove: Vec<String>) -> Result<RecordingMetadata, KizunaError> {
+        let catalog = self.system_manager.recordings().await?;
+        let recording = catalog.find(&id).await.map_err(recording_error)?;
+        catalog.tag(recording.session_id, &add, &remove).await.map_err(recording_error)
+    }
+    
+    #[cfg(feature = "streaming")]
+    async fn set_recording_notes(&self, id: String, notes: Option<String>) -> Result<RecordingMetadata, KizunaError> {
+        let catalog = self.system_manager.recordings().await?;
+        let recording = catalog.find(&id).await.map_err(recording_error)?;
+        catalog.set_notes(recording.session_id, notes).await.map_err(recording_error)
+    }
+    
     async fn execute_command(&self, command: String, peer_id: PeerId) -> Result<CommandResult, KizunaError> {
         // Check state
         let current_state = *self.state.read().await;
@@ -435,6 +487,28 @@ impl KizunaAPI for KizunaInstance {
         )))
     }
     
+    async fn browse_remote(&self, peer_id: PeerId, path: String) -> Result<DirectoryListing, KizunaError> {
+        let (remote_fs, peer_address) = self.remote_fs_for(&peer_id, "browse").await?;
+        remote_fs.list(&peer_address, &path).await.map_err(browse_error)
+    }
+    
+    async fn stat_remote(&self, peer_id: PeerId, path: String) -> Result<RemoteEntry, KizunaError> {
+        let (remote_fs, peer_address) = self.remote_fs_for(&peer_id, "stat").await?;
+        remote_fs.stat(&peer_address, &path).await.map_err(browse_error)
+    }
+    
+    async fn search_remote(&self, peer_id: PeerId, path: String, query: String) -> Result<Vec<RemoteEntry>, KizunaError> {
+        if query.trim().is_empty() {
+            return Err(KizunaError::parameter("query", "must not be empty"));
+        }
+        let (remote_fs, peer_address) = self.remote_fs_for(&peer_id, "search").await?;
+        let (entries, truncated) = remote_fs.search(&peer_address, &path, &query).await.map_err(browse_error)?;
+        if truncated {
+            log::debug!("Search of {} for {:?} stopped at the peer's result limit", peer_id, query);
+        }
+        Ok(entries)
+    }
+    
     async fn subscribe_events(&self) -> Result<Pin<Box<dyn Stream<Item = KizunaEvent> + Send>>, KizunaError> {
         // Check state
         let current_state = *self.state.read().await;
@@ -442,11 +516,7 @@ impl KizunaAPI for KizunaInstance {
             return Err(KizunaError::state("Cannot subscribe to events: instance is shutdown"));
         }
         
-        // Use the AsyncStreamBuilder for creating the event stream
-        let rx = self.event_tx.subscribe();
-        let stream = super::runtime::AsyncStreamBuilder::from_broadcast(rx);
-        
-        Ok(stream)
+        Ok(self.event_emitter.read().await.subscribe_all())
     }
     
     async fn shutdown(&self) -> Result<(), KizunaError> {
@@ -454,6 +524,130 @@ impl KizunaAPI for KizunaInstance {
     }
 }
 
+impl KizunaInstance {
+    /// Fails once the instance is shut down, when no more events will arrive
+    async fn ensure_subscribable(&self) -> Result<(), KizunaError> {
+        if *self.state.read().await == InstanceState::Shutdown {
+            return Err(KizunaError::state("Cannot subscribe to events: instance is shutdown"));
+        }
+        Ok(())
+    }
+    
+    /// Subscribes to peer discovery and connection events only
+    pub async fn subscribe_discovery_events(&self) -> Result<DiscoveryEvents, KizunaError> {
+        self.ensure_subscribable().await?;
+        Ok(self.event_emitter.read().await.subscribe_discovery())
+    }
+    
+    /// Subscribes to file transfer events only
+    pub async fn subscribe_transfer_events(&self) -> Result<TransferEvents, KizunaError> {
+        self.ensure_subscribable().await?;
+        Ok(self.event_emitter.read().await.subscribe_transfers())
+    }
+    
+    /// Subscribes to clipboard events only
+    pub async fn subscribe_clipboard_events(&self) -> Result<ClipboardEvents, KizunaError> {
+        self.ensure_subscribable().await?;
+        Ok(self.event_emitter.read().await.subscribe_clipboard())
+    }
+    
+    /// Subscribes to security events only
+    pub async fn subscribe_security_events(&self) -> Result<SecurityEvents, KizunaError> {
+        self.ensure_subscribable().await?;
+        Ok(self.event_emitter.read().await.subscribe_security())
+    }
+    
+    /// Advertise an application service over mDNS through Kizuna's responder
+    ///
+    /// Saves running a second mDNS stack in the same process. The service is
+    /// advertised until it is unregistered or the instance shuts down.
+    pub async fn register_mdns_service(&self, registration: ServiceRegistration) -> Result<ServiceId, KizunaError> {
+        let services = self.mdns_services("register").await?;
+        services.register(registration).map_err(|e| match &e {
+            DiscoveryError::ServiceConflict { service, .. } => KizunaError::already_exists(service.clone()),
+            DiscoveryError::InvalidServiceRecord { reason } => {
+                KizunaError::parameter("registration".to_string(), reason.clone())
+            }
+            _ => KizunaError::discovery(e.to_string()),
+        })
+    }
+    
+    /// Stop advertising a service; returns false if it was not registered
+    pub async fn unregister_mdns_service(&self, id: ServiceId) -> Result<bool, KizunaError> {
+        Ok(self.mdns_services("unregister").await?.unregister(id))
+    }
+    
+    /// Whether another host on the network claimed a registered service's
+    /// instance name, in which case it is no longer advertised
+    pub async fn mdns_service_conflicted(&self, id: ServiceId) -> Result<bool, KizunaError> {
+        Ok(self.mdns_services("check").await?.is_conflicted(id))
+    }
+    
+    /// Registry of advertised services, once the instance is ready
+    async fn mdns_services(&self, operation: &str) -> Result<ServiceRegistry, KizunaError> {
+        let current_state = *self.state.read().await;
+        if current_state != InstanceState::Ready {
+            return Err(KizunaError::state(format!("Cannot {} mDNS services: instance is in {:?} state", operation, current_state)));
+        }
+        
+        let discovery = self.system_manager.discovery().await?;
+        let services = discovery.read().await.services().clone();
+        Ok(services)
+    }
+    
+    /// Remote browsing service and address for a peer, once the instance is ready
+    async fn remote_fs_for(
+        &self,
+        peer_id: &PeerId,
+        operation: &str,
+    ) -> Result<(crate::remote_fs::RemoteFileSystem, crate::transport::PeerAddress), KizunaError> {
+        let current_state = *self.state.read().await;
+        if current_state != InstanceState::Ready {
+            return Err(KizunaError::state(format!("Cannot {} remote files: instance is in {:?} state", operation, current_state)));
+        }
+        
+        let remote_fs = self.system_manager.remote_fs().await?;
+        
+        // Streams are opened through the connection provider, which reuses the
+        // peer's existing session, so the address needs no socket addresses here
+        let peer_address = crate::transport::PeerAddress::new(
+            peer_id.to_string(),
+            vec![],
+            vec![],
+            crate::transport::TransportCapabilities::default(),
+        );
+        Ok((remote_fs, peer_address))
+    }
+}
+
+/// Convert a browse failure into an API error
+fn browse_error(error: BrowseError) -> KizunaError {
+    match &error {
+        BrowseError::PeerNotTrusted { peer_id } | BrowseError::PermissionDenied { peer_id } => {
+            KizunaError::permission_denied(format!("browse files of {}", peer_id))
+        }
+        BrowseError::Remote { kind: RemoteErrorKind::PermissionDenied, message } => {
+            KizunaError::permission_denied(message.clone())
+        }
+        BrowseError::Remote { kind: RemoteErrorKind::NotFound, message } => KizunaError::not_found(message.clone()),
+        BrowseError::Remote { kind: RemoteErrorKind::InvalidPath, message } => {
+            KizunaError::parameter("path".to_string(), message.clone())
+        }
+        BrowseError::Transport { .. } => KizunaError::network(error.to_string()),
+        _ => KizunaError::other(error.to_string()),
+    }
+}
+
+/// Convert a recordings catalog failure into an API error
+#[cfg(feature = "streaming")]
+fn recording_error(error: crate::streaming::StreamError) -> KizunaError {
+    match error {
+        crate::streaming::StreamError::SessionNotFound(id) => KizunaError::not_found(format!("recording {}", id)),
+        crate::streaming::StreamError::Configuration(message) => KizunaError::parameter("recording".to_string(), message),
+        error => KizunaError::streaming(error.to_string()),
+    }
+}
+
 /// Handle to a peer connection
 pub struct PeerConnection {
     peer_id: PeerId,
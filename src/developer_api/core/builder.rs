@@ -0,0 +1,276 @@
+/// Convenience builder for fully wired Kizuna instances
+///
+/// The builder starts from sane defaults and exposes per-subsystem toggles so
+/// that applications do not need to assemble a `KizunaConfig` by hand. All
+/// persistent state (identity, trust store, transfer sessions) is rooted in a
+/// single storage directory so the subsystems share a consistent identity.
+use super::api::KizunaInstance;
+use super::config::{IdentityConfig, KizunaConfig, TrustMode};
+use super::KizunaError;
+use std::path::{Path, PathBuf};
+
+/// Builder for `KizunaInstance`
+#[derive(Debug, Clone)]
+pub struct KizunaInstanceBuilder {
+    config: KizunaConfig,
+    storage_dir: Option<PathBuf>,
+    device_name: Option<String>,
+}
+
+impl KizunaInstanceBuilder {
+    /// Creates a builder with default configuration
+    pub fn new() -> Self {
+        Self::from_config(KizunaConfig::default())
+    }
+
+    /// Creates a builder starting from an existing configuration
+    pub fn from_config(config: KizunaConfig) -> Self {
+        Self {
+            config,
+            storage_dir: None,
+            device_name: None,
+        }
+    }
+
+    /// Enables or disables peer discovery
+    pub fn with_discovery(mut self, enabled: bool) -> Self {
+        self.config.enable_discovery = enabled;
+        self
+    }
+
+    /// Enables or disables the transport system
+    pub fn with_transport(mut self, enabled: bool) -> Self {
+        self.config.enable_transport = enabled;
+        self
+    }
+
+    /// Enables or disables file transfer
+    pub fn with_file_transfer(mut self, enabled: bool) -> Self {
+        self.config.enable_file_transfer = enabled;
+        self
+    }
+
+    /// Enables or disables media streaming
+    pub fn with_streaming(mut self, enabled: bool) -> Self {
+        self.config.enable_streaming = enabled;
+        self
+    }
+
+    /// Enables or disables clipboard sync
+    pub fn with_clipboard(mut self, enabled: bool) -> Self {
+        self.config.enable_clipboard = enabled;
+        self
+    }
+
+    /// Enables or disables remote command execution
+    pub fn with_command_execution(mut self, enabled: bool) -> Self {
+        self.config.enable_command_execution = enabled;
+        self
+    }
+
+    /// Sets the root directory for all persistent state
+    pub fn with_storage_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.storage_dir = Some(path.into());
+        self
+    }
+
+    /// Sets the device name advertised to peers
+    pub fn with_device_name(mut self, name: impl Into<String>) -> Self {
+        self.device_name = Some(name.into());
+        self
+    }
+
+    /// Sets the trust mode for incoming peers
+    pub fn with_trust_mode(mut self, mode: TrustMode) -> Self {
+        self.config.security.trust_mode = mode;
+        self
+    }
+
+    /// Sets the discovery strategies to enable
+    pub fn with_discovery_strategies<I, S>(mut self, strategies: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.discovery_strategies = strategies.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the transport protocols to enable
+    pub fn with_transport_protocols<I, S>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.transport_protocols = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the number of runtime worker threads
+    pub fn with_runtime_threads(mut self, threads: usize) -> Self {
+        self.config.runtime_threads = Some(threads);
+        self
+    }
+
+    /// Sets the connection timeout in seconds
+    pub fn with_connection_timeout(mut self, secs: u64) -> Self {
+        self.config.connection_timeout_secs = secs;
+        self.config.networking.connection_timeout_secs = secs;
+        self
+    }
+
+    /// Resolves the final configuration without creating an instance
+    pub fn build_config(&self) -> Result<KizunaConfig, Box<KizunaError>> {
+        let mut config = self.config.clone();
+
+        if let Some(dir) = &self.storage_dir {
+            apply_storage_dir(&mut config, dir);
+        }
+
+        if let Some(name) = &self.device_name {
+            let identity = config.identity.get_or_insert_with(|| IdentityConfig {
+                device_name: String::new(),
+                user_name: None,
+                identity_path: None,
+            });
+            identity.device_name = name.clone();
+        }
+
+        if config.enable_file_transfer && !config.enable_transport {
+            return Err(Box::new(KizunaError::config("File transfer requires the transport system")));
+        }
+
+        if config.enable_streaming && !config.enable_transport {
+            return Err(Box::new(KizunaError::config("Streaming requires the transport system")));
+        }
+
+        if config.enable_discovery && config.discovery_strategies.is_empty() {
+            return Err(Box::new(KizunaError::config("Discovery is enabled but no strategies are configured")));
+        }
+
+        if config.enable_transport && config.transport_protocols.is_empty() {
+            return Err(Box::new(KizunaError::config("Transport is enabled but no protocols are configured")));
+        }
+
+        config.validate().map_err(|e| Box::new(KizunaError::config(e)))?;
+
+        Ok(config)
+    }
+
+    /// Builds the instance without initializing its subsystems
+    pub fn build(self) -> Result<KizunaInstance, Box<KizunaError>> {
+        let config = self.build_config()?;
+
+        if let Some(dir) = &self.storage_dir {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                Box::new(KizunaError::config(format!("Failed to create storage directory {:?}: {}", dir, e)))
+            })?;
+        }
+
+        KizunaInstance::new(config).map_err(Box::new)
+    }
+
+    /// Builds the instance and initializes all enabled subsystems
+    pub async fn build_and_initialize(self) -> Result<KizunaInstance, Box<KizunaError>> {
+        let instance = self.build()?;
+        instance.initialize_systems().await.map_err(Box::new)?;
+        Ok(instance)
+    }
+}
+
+impl Default for KizunaInstanceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Roots every persistent path in the configuration under `dir`
+fn apply_storage_dir(config: &mut KizunaConfig, dir: &Path) {
+    config.file_transfer_session_dir = dir.join("file_transfer");
+    config.recording_dir = dir.join("recordings");
+    config.security.key_storage_path = Some(dir.join("keys"));
+
+    if let Some(identity) = config.identity.as_mut() {
+        identity.identity_path = Some(dir.join("identity"));
+    } else {
+        config.identity = Some(IdentityConfig {
+            device_name: default_device_name(),
+            user_name: None,
+            identity_path: Some(dir.join("identity")),
+        });
+    }
+}
+
+/// Derives a device name from the host name
+fn default_device_name() -> String {
+    hostname::get()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+        .unwrap_or_else(|| "kizuna-device".to_string())
+}
+
+impl KizunaInstance {
+    /// Creates a builder for a fully wired instance
+    pub fn builder() -> KizunaInstanceBuilder {
+        KizunaInstanceBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggles_apply_to_config() {
+        let config = KizunaInstance::builder()
+            .with_clipboard(true)
+            .with_streaming(false)
+            .with_command_execution(false)
+            .build_config()
+            .unwrap();
+
+        assert!(config.enable_clipboard);
+        assert!(!config.enable_streaming);
+        assert!(!config.enable_command_execution);
+    }
+
+    #[test]
+    fn test_storage_dir_roots_all_paths() {
+        let config = KizunaInstance::builder()
+            .with_storage_dir("/tmp/kizuna-test")
+            .with_device_name("laptop")
+            .build_config()
+            .unwrap();
+
+        let root = PathBuf::from("/tmp/kizuna-test");
+        assert_eq!(config.file_transfer_session_dir, root.join("file_transfer"));
+        assert_eq!(config.security.key_storage_path, Some(root.join("keys")));
+
+        let identity = config.identity.unwrap();
+        assert_eq!(identity.device_name, "laptop");
+        assert_eq!(identity.identity_path, Some(root.join("identity")));
+    }
+
+    #[test]
+    fn test_file_transfer_requires_transport() {
+        let result = KizunaInstance::builder()
+            .with_transport(false)
+            .build_config();
+        assert!(result.is_err());
+
+        let result = KizunaInstance::builder()
+            .with_transport(false)
+            .with_file_transfer(false)
+            .with_streaming(false)
+            .build_config();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_empty_strategies_rejected() {
+        let result = KizunaInstance::builder()
+            .with_discovery_strategies(Vec::<String>::new())
+            .build_config();
+        assert!(result.is_err());
+    }
+}
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::transport::{PortRange, ProxyConfig};
+
 /// Main configuration for Kizuna API
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KizunaConfig {
@@ -61,6 +63,10 @@ pub struct KizunaConfig {
     
     /// File transfer session directory
     pub file_transfer_session_dir: PathBuf,
+    
+    /// Directory holding stream recordings and their index
+    #[serde(default = "default_recording_dir")]
+    pub recording_dir: PathBuf,
 }
 
 impl Default for KizunaConfig {
@@ -98,10 +104,19 @@ impl Default for KizunaConfig {
                 path.push("file_transfer");
                 path
             },
+            recording_dir: default_recording_dir(),
         }
     }
 }
 
+/// Where recordings are kept unless configured otherwise
+fn default_recording_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("kizuna")
+        .join("recordings")
+}
+
 /// Identity configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IdentityConfig {
@@ -192,6 +207,18 @@ pub struct NetworkConfig {
     /// Listen port
     pub listen_port: Option<u16>,
     
+    /// Ports to fall back to when the listen port is taken
+    #[serde(default)]
+    pub port_range: Option<PortRange>,
+    
+    /// Ask the router to forward the listen port over UPnP or NAT-PMP
+    #[serde(default)]
+    pub port_mapping: bool,
+    
+    /// Proxy for relay, rendezvous and STUN servers
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    
     /// Enable IPv6
     pub enable_ipv6: bool,
     
@@ -212,6 +239,9 @@ impl Default for NetworkConfig {
     fn default() -> Self {
         Self {
             listen_port: None,
+            port_range: None,
+            port_mapping: false,
+            proxy: None,
             enable_ipv6: true,
             enable_quic: true,
             enable_webrtc: true,
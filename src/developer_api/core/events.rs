@@ -1,6 +1,9 @@
 /// Event system for the Developer API
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::pin::Pin;
+use futures::Stream;
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
 /// Events emitted by the Kizuna API
@@ -35,8 +38,169 @@ pub enum KizunaEvent {
     
     /// An error occurred
     Error(ErrorEvent),
+    
+    /// Clipboard content changed or was synced
+    Clipboard(ClipboardEvent),
+    
+    /// Trust or authentication changed for a peer
+    Security(SecurityEvent),
+}
+
+/// Area of the system an event comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventDomain {
+    Discovery,
+    Transfer,
+    Stream,
+    Command,
+    Clipboard,
+    Security,
+    Error,
+}
+
+impl KizunaEvent {
+    /// Domain the event belongs to
+    pub fn domain(&self) -> EventDomain {
+        match self {
+            KizunaEvent::PeerDiscovered(_)
+            | KizunaEvent::PeerConnected(_)
+            | KizunaEvent::PeerDisconnected(_) => EventDomain::Discovery,
+            KizunaEvent::TransferStarted(_)
+            | KizunaEvent::TransferProgress(_)
+            | KizunaEvent::TransferCompleted(_) => EventDomain::Transfer,
+            KizunaEvent::StreamStarted(_) | KizunaEvent::StreamEnded(_) => EventDomain::Stream,
+            KizunaEvent::CommandExecuted(_) => EventDomain::Command,
+            KizunaEvent::Clipboard(_) => EventDomain::Clipboard,
+            KizunaEvent::Security(_) => EventDomain::Security,
+            KizunaEvent::Error(_) => EventDomain::Error,
+        }
+    }
+    
+    /// The event as a discovery event, if it is one
+    pub fn as_discovery(&self) -> Option<DiscoveryEvent> {
+        match self {
+            KizunaEvent::PeerDiscovered(info) => Some(DiscoveryEvent::PeerDiscovered(info.clone())),
+            KizunaEvent::PeerConnected(peer_id) => Some(DiscoveryEvent::PeerConnected(peer_id.clone())),
+            KizunaEvent::PeerDisconnected(peer_id) => Some(DiscoveryEvent::PeerDisconnected(peer_id.clone())),
+            _ => None,
+        }
+    }
+    
+    /// The event as a transfer event, if it is one
+    pub fn as_transfer(&self) -> Option<TransferEvent> {
+        match self {
+            KizunaEvent::TransferStarted(info) => Some(TransferEvent::Started(info.clone())),
+            KizunaEvent::TransferProgress(progress) => Some(TransferEvent::Progress(progress.clone())),
+            KizunaEvent::TransferCompleted(result) => Some(TransferEvent::Completed(result.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// Peer discovery and connection events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DiscoveryEvent {
+    /// A peer was discovered
+    PeerDiscovered(PeerInfo),
+    /// A peer connection was established
+    PeerConnected(PeerId),
+    /// A peer connection was closed
+    PeerDisconnected(PeerId),
+}
+
+impl From<DiscoveryEvent> for KizunaEvent {
+    fn from(event: DiscoveryEvent) -> Self {
+        match event {
+            DiscoveryEvent::PeerDiscovered(info) => KizunaEvent::PeerDiscovered(info),
+            DiscoveryEvent::PeerConnected(peer_id) => KizunaEvent::PeerConnected(peer_id),
+            DiscoveryEvent::PeerDisconnected(peer_id) => KizunaEvent::PeerDisconnected(peer_id),
+        }
+    }
+}
+
+/// File transfer events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TransferEvent {
+    /// A file transfer started
+    Started(TransferInfo),
+    /// File transfer progress update
+    Progress(TransferProgress),
+    /// A file transfer completed
+    Completed(TransferResult),
+}
+
+impl From<TransferEvent> for KizunaEvent {
+    fn from(event: TransferEvent) -> Self {
+        match event {
+            TransferEvent::Started(info) => KizunaEvent::TransferStarted(info),
+            TransferEvent::Progress(progress) => KizunaEvent::TransferProgress(progress),
+            TransferEvent::Completed(result) => KizunaEvent::TransferCompleted(result),
+        }
+    }
+}
+
+/// Clipboard events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClipboardEvent {
+    /// Clipboard content changed, locally or from a peer
+    Changed {
+        /// Peer the content came from, `None` for a local copy
+        source: Option<PeerId>,
+        /// Kind of content, e.g. "text" or "image"
+        content_type: String,
+        /// Content size in bytes
+        size: usize,
+    },
+    /// Clipboard content reached a peer
+    Synced {
+        peer_id: PeerId,
+        /// SHA-256 of the synced content
+        content_hash: String,
+    },
+    /// Clipboard content could not be synced to a peer
+    SyncFailed { peer_id: PeerId, reason: String },
+}
+
+impl From<ClipboardEvent> for KizunaEvent {
+    fn from(event: ClipboardEvent) -> Self {
+        KizunaEvent::Clipboard(event)
+    }
+}
+
+/// Peer trust and authentication events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SecurityEvent {
+    /// A peer asked to pair
+    PairingRequested(PeerId),
+    /// A peer was added to the trust list
+    PeerTrusted(PeerId),
+    /// A peer was removed from the trust list
+    PeerUntrusted(PeerId),
+    /// A peer failed to authenticate
+    AuthenticationFailed { peer_id: PeerId, reason: String },
+}
+
+impl From<SecurityEvent> for KizunaEvent {
+    fn from(event: SecurityEvent) -> Self {
+        KizunaEvent::Security(event)
+    }
 }
 
+/// Stream of every event
+pub type AllEvents = Pin<Box<dyn Stream<Item = KizunaEvent> + Send>>;
+
+/// Stream of discovery events only
+pub type DiscoveryEvents = Pin<Box<dyn Stream<Item = DiscoveryEvent> + Send>>;
+
+/// Stream of transfer events only
+pub type TransferEvents = Pin<Box<dyn Stream<Item = TransferEvent> + Send>>;
+
+/// Stream of clipboard events only
+pub type ClipboardEvents = Pin<Box<dyn Stream<Item = ClipboardEvent> + Send>>;
+
+/// Stream of security events only
+pub type SecurityEvents = Pin<Box<dyn Stream<Item = SecurityEvent> + Send>>;
+
 /// Peer identifier
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PeerId(pub String);
@@ -269,9 +433,16 @@ pub trait EventListener: Send + Sync {
 }
 
 /// Event emitter for publishing events
+///
+/// Each domain has its own channel, so a subscriber to transfer events is
+/// only woken for transfers rather than for every event in the system.
 pub struct EventEmitter {
     listeners: Vec<Box<dyn EventListener>>,
-    event_tx: Arc<tokio::sync::broadcast::Sender<KizunaEvent>>,
+    event_tx: Arc<broadcast::Sender<KizunaEvent>>,
+    discovery_tx: broadcast::Sender<DiscoveryEvent>,
+    transfer_tx: broadcast::Sender<TransferEvent>,
+    clipboard_tx: broadcast::Sender<ClipboardEvent>,
+    security_tx: broadcast::Sender<SecurityEvent>,
 }
 
 use std::sync::Arc;
@@ -279,10 +450,19 @@ use std::sync::Arc;
 impl EventEmitter {
     /// Creates a new event emitter
     pub fn new() -> Self {
-        let (event_tx, _) = tokio::sync::broadcast::channel(100);
+        Self::with_capacity(100)
+    }
+    
+    /// Creates an event emitter buffering `capacity` events per subscriber
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (event_tx, _) = broadcast::channel(capacity);
         Self {
             listeners: Vec::new(),
             event_tx: Arc::new(event_tx),
+            discovery_tx: broadcast::channel(capacity).0,
+            transfer_tx: broadcast::channel(capacity).0,
+            clipboard_tx: broadcast::channel(capacity).0,
+            security_tx: broadcast::channel(capacity).0,
         }
     }
     
@@ -293,6 +473,16 @@ impl EventEmitter {
     
     /// Emits an event to all listeners
     pub async fn emit(&self, event: KizunaEvent) {
+        // Route to the domain stream, skipping the copy when nobody listens
+        match &event {
+            KizunaEvent::Clipboard(clipboard) => Self::route(&self.clipboard_tx, || Some(clipboard.clone())),
+            KizunaEvent::Security(security) => Self::route(&self.security_tx, || Some(security.clone())),
+            _ => {
+                Self::route(&self.discovery_tx, || event.as_discovery());
+                Self::route(&self.transfer_tx, || event.as_transfer());
+            }
+        }
+        
         // Emit to broadcast channel
         let _ = self.event_tx.send(event.clone());
         
@@ -302,6 +492,13 @@ impl EventEmitter {
         }
     }
     
+    /// Sends a domain event if anyone subscribed to that domain
+    fn route<T>(tx: &broadcast::Sender<T>, event: impl FnOnce() -> Option<T>) {
+        if tx.receiver_count() > 0 && let Some(event) = event() {
+            let _ = tx.send(event);
+        }
+    }
+    
     /// Subscribes to events as a stream
     pub fn subscribe(&self) -> impl futures::Stream<Item = KizunaEvent> + Send {
         let tx = Arc::clone(&self.event_tx);
@@ -313,6 +510,31 @@ impl EventEmitter {
             }
         }
     }
+    
+    /// Subscribes to every event, for consumers that handle them all
+    pub fn subscribe_all(&self) -> AllEvents {
+        super::runtime::AsyncStreamBuilder::from_broadcast(self.event_tx.subscribe())
+    }
+    
+    /// Subscribes to peer discovery and connection events
+    pub fn subscribe_discovery(&self) -> DiscoveryEvents {
+        super::runtime::AsyncStreamBuilder::from_broadcast(self.discovery_tx.subscribe())
+    }
+    
+    /// Subscribes to file transfer events
+    pub fn subscribe_transfers(&self) -> TransferEvents {
+        super::runtime::AsyncStreamBuilder::from_broadcast(self.transfer_tx.subscribe())
+    }
+    
+    /// Subscribes to clipboard events
+    pub fn subscribe_clipboard(&self) -> ClipboardEvents {
+        super::runtime::AsyncStreamBuilder::from_broadcast(self.clipboard_tx.subscribe())
+    }
+    
+    /// Subscribes to security events
+    pub fn subscribe_security(&self) -> SecurityEvents {
+        super::runtime::AsyncStreamBuilder::from_broadcast(self.security_tx.subscribe())
+    }
 }
 
 impl Default for EventEmitter {
@@ -320,3 +542,32 @@ impl Default for EventEmitter {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_domain_streams_only_see_their_events() {
+        let emitter = EventEmitter::new();
+        let mut transfers = emitter.subscribe_transfers();
+        let mut security = emitter.subscribe_security();
+        let mut all = emitter.subscribe_all();
+
+        emitter.emit(KizunaEvent::PeerConnected(PeerId::from("peer"))).await;
+        emitter.emit(SecurityEvent::PeerTrusted(PeerId::from("peer")).into()).await;
+        emitter.emit(KizunaEvent::TransferProgress(TransferProgress {
+            id: TransferId::new(),
+            bytes_transferred: 5,
+            total_bytes: 10,
+            speed_bps: 1,
+        })).await;
+
+        assert!(matches!(transfers.next().await, Some(TransferEvent::Progress(p)) if p.bytes_transferred == 5));
+        assert!(matches!(security.next().await, Some(SecurityEvent::PeerTrusted(_))));
+        assert_eq!(all.next().await.unwrap().domain(), EventDomain::Discovery);
+        assert_eq!(all.next().await.unwrap().domain(), EventDomain::Security);
+        assert_eq!(all.next().await.unwrap().domain(), EventDomain::Transfer);
+    }
+}
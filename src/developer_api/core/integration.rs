@@ -16,12 +16,18 @@ use tokio::sync::RwLock;
 // Import all core Kizuna systems
 use crate::discovery::api::{KizunaDiscovery, DiscoveryConfig, DiscoveryEvent};
 use crate::transport::api::{KizunaTransport, KizunaTransportConfig, ConnectionHandle, ConnectionEvent};
+use crate::transport::{PortConfig, PortMappingConfig};
 use crate::security::api::{SecuritySystem, SecuritySystemConfig};
 use crate::file_transfer::api::{FileTransferSystem, TransferStats};
 #[cfg(feature = "streaming")]
 use crate::streaming::api::{StreamingApi, Streaming, StreamEvent};
-use crate::clipboard::{ClipboardSystem, ClipboardContent};
+#[cfg(feature = "streaming")]
+use crate::streaming::recording::RecordingCatalog;
+#[cfg(feature = "clipboard")]
+use crate::clipboard::ClipboardSystem;
+#[cfg(feature = "command-execution")]
 use crate::command_execution::{CommandManager, CommandRequest, CommandResult as CmdResult, UnifiedCommandManager};
+use crate::remote_fs::RemoteFileSystem;
 use crate::developer_api::plugins::SystemHookRegistry;
 
 /// Integrated system manager that coordinates all Kizuna subsystems
@@ -42,12 +48,21 @@ pub struct IntegratedSystemManager {
     #[cfg(feature = "streaming")]
     streaming: Arc<RwLock<Option<StreamingApi>>>,
     
+    /// Catalog of local stream recordings
+    #[cfg(feature = "streaming")]
+    recordings: Arc<RwLock<Option<RecordingCatalog>>>,
+    
     /// Clipboard system
+    #[cfg(feature = "clipboard")]
     clipboard: Arc<RwLock<Option<ClipboardSystem>>>,
     
     /// Command execution manager
+    #[cfg(feature = "command-execution")]
     command_manager: Arc<RwLock<Option<UnifiedCommandManager>>>,
     
+    /// Remote share browsing, attached once a connection provider exists
+    remote_fs: Arc<RwLock<Option<RemoteFileSystem>>>,
+    
     /// Plugin hook registry
     hook_registry: Arc<RwLock<SystemHookRegistry>>,
     
@@ -68,8 +83,13 @@ impl IntegratedSystemManager {
             file_transfer: Arc::new(RwLock::new(None)),
             #[cfg(feature = "streaming")]
             streaming: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "streaming")]
+            recordings: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "clipboard")]
             clipboard: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "command-execution")]
             command_manager: Arc::new(RwLock::new(None)),
+            remote_fs: Arc::new(RwLock::new(None)),
             hook_registry: Arc::new(RwLock::new(SystemHookRegistry::new())),
             config,
             initialized: Arc::new(RwLock::new(false)),
@@ -100,6 +120,7 @@ impl IntegratedSystemManager {
                 auto_select: true,
                 default_timeout: Duration::from_secs(5),
                 enabled_strategies: self.config.discovery_strategies.clone(),
+                proxy: self.config.networking.proxy.clone(),
                 ..Default::default()
             };
             
@@ -112,11 +133,23 @@ impl IntegratedSystemManager {
         
         // Initialize transport system
         if self.config.enable_transport {
-            let transport_config = KizunaTransportConfig {
+            let mut transport_config = KizunaTransportConfig {
                 connection_timeout: Duration::from_secs(self.config.connection_timeout_secs),
                 enabled_protocols: self.config.transport_protocols.clone(),
+                port_mapping: self.config.networking.port_mapping.then(PortMappingConfig::default),
+                proxy: self.config.networking.proxy.clone(),
                 ..Default::default()
             };
+            let networking = &self.config.networking;
+            if networking.listen_port.is_some() || networking.port_range.is_some() {
+                let mut port = PortConfig::new(networking.listen_port.unwrap_or(0));
+                if let Some(range) = networking.port_range {
+                    port.port = networking.listen_port.unwrap_or(range.start);
+                    port.range = Some(range);
+                }
+                transport_config.listen_ports.tcp = port;
+                transport_config.listen_ports.quic = port;
+            }
             
             let transport = KizunaTransport::with_config(transport_config).await
                 .map_err(|e| KizunaError::transport(format!("Failed to initialize transport: {}", e)))?;
@@ -146,6 +179,10 @@ impl IntegratedSystemManager {
         if self.config.enable_streaming {
             let streaming = StreamingApi::new();
             *self.streaming.write().await = Some(streaming);
+            
+            let recordings = RecordingCatalog::open(self.config.recording_dir.clone())
+                .map_err(|e| KizunaError::streaming(format!("Failed to open recordings: {}", e)))?;
+            *self.recordings.write().await = Some(recordings);
         }
         
         // Initialize clipboard system
@@ -156,6 +193,7 @@ impl IntegratedSystemManager {
         // }
         
         // Initialize command execution manager
+        #[cfg(feature = "command-execution")]
         if self.config.enable_command_execution {
             let command_manager = UnifiedCommandManager::new()
                 .map_err(|e| KizunaError::state(format!("Failed to create command manager: {}", e)))?;
@@ -224,7 +262,16 @@ impl IntegratedSystemManager {
             .ok_or_else(|| KizunaError::state("Streaming system not initialized"))
     }
     
+    /// Get the recordings catalog
+    #[cfg(feature = "streaming")]
+    pub async fn recordings(&self) -> Result<RecordingCatalog, KizunaError> {
+        self.recordings.read().await
+            .clone()
+            .ok_or_else(|| KizunaError::state("Streaming system not initialized"))
+    }
+    
     /// Get clipboard system
+    #[cfg(feature = "clipboard")]
     pub async fn clipboard(&self) -> Result<Arc<ClipboardSystem>, KizunaError> {
         let clipboard_guard = self.clipboard.read().await;
         clipboard_guard.as_ref()
@@ -233,6 +280,7 @@ impl IntegratedSystemManager {
     }
     
     /// Get command execution manager
+    #[cfg(feature = "command-execution")]
     pub async fn command_manager(&self) -> Result<Arc<UnifiedCommandManager>, KizunaError> {
         let cmd_guard = self.command_manager.read().await;
         cmd_guard.as_ref()
@@ -240,6 +288,21 @@ impl IntegratedSystemManager {
             .ok_or_else(|| KizunaError::state("Command execution system not initialized"))
     }
     
+    /// Attach the remote browsing service
+    ///
+    /// Browsing needs the shared connection provider and trust store, so it is
+    /// set up by the host rather than during [`initialize`](Self::initialize).
+    pub async fn set_remote_fs(&self, remote_fs: RemoteFileSystem) {
+        *self.remote_fs.write().await = Some(remote_fs);
+    }
+    
+    /// Get the remote browsing service
+    pub async fn remote_fs(&self) -> Result<RemoteFileSystem, KizunaError> {
+        self.remote_fs.read().await
+            .clone()
+            .ok_or_else(|| KizunaError::state("Remote browsing not initialized"))
+    }
+    
     /// Get the plugin hook registry
     pub fn hook_registry(&self) -> Arc<RwLock<SystemHookRegistry>> {
         Arc::clone(&self.hook_registry)
@@ -292,6 +355,7 @@ impl IntegratedSystemManager {
     }
     
     /// Register a clipboard hook
+    #[cfg(feature = "clipboard")]
     pub async fn register_clipboard_hook(
         &self,
         hook: Arc<dyn crate::developer_api::plugins::ClipboardHook>,
@@ -315,13 +379,18 @@ impl IntegratedSystemManager {
         
         // Shutdown in reverse order of initialization
         
+        // Detach remote browsing
+        self.remote_fs.write().await.take();
+        
         // Shutdown command execution
+        #[cfg(feature = "command-execution")]
         if let Some(cmd_manager) = self.command_manager.write().await.take() {
             // Command manager doesn't have explicit shutdown
             drop(cmd_manager);
         }
         
         // Shutdown clipboard
+        #[cfg(feature = "clipboard")]
         if let Some(clipboard) = self.clipboard.write().await.take() {
             // Clipboard doesn't have explicit shutdown
             drop(clipboard);
@@ -333,6 +402,8 @@ impl IntegratedSystemManager {
             // Streaming doesn't have explicit shutdown
             drop(streaming);
         }
+        #[cfg(feature = "streaming")]
+        self.recordings.write().await.take();
         
         // Shutdown file transfer
         if let Some(file_transfer) = self.file_transfer.write().await.take() {
@@ -410,6 +481,7 @@ impl Clone for StreamingApi {
     }
 }
 
+#[cfg(feature = "clipboard")]
 impl Clone for ClipboardSystem {
     fn clone(&self) -> Self {
         // ClipboardSystem clone not fully implemented - use Arc instead
@@ -417,6 +489,7 @@ impl Clone for ClipboardSystem {
     }
 }
 
+#[cfg(feature = "command-execution")]
 impl Clone for UnifiedCommandManager {
     fn clone(&self) -> Self {
         // Create a new command manager
@@ -518,6 +591,7 @@ impl IntegratedOperations {
             capture_audio: false,
             monitor_index: None,
             quality: crate::streaming::StreamQuality::default(),
+            privacy: crate::streaming::PrivacyMaskConfig::default(),
         };
         
         let session = streaming.start_screen_stream(config).await
@@ -535,6 +609,7 @@ impl IntegratedOperations {
     }
     
     /// Execute a command on a remote peer
+    #[cfg(feature = "command-execution")]
     pub async fn execute_remote_command(
         &self,
         peer_id: String,
@@ -565,6 +640,7 @@ impl IntegratedOperations {
     }
     
     /// Share clipboard content with a peer
+    #[cfg(feature = "clipboard")]
     pub async fn share_clipboard(
         &self,
         peer_id: String,
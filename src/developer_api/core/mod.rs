@@ -1,5 +1,6 @@
 /// Core API module providing the foundational Rust API
 pub mod api;
+pub mod builder;
 pub mod config;
 pub mod error;
 pub mod events;
@@ -18,9 +19,13 @@ mod integration_test;
 
 // Re-export core types
 pub use api::{KizunaAPI, KizunaInstance};
+pub use builder::KizunaInstanceBuilder;
 pub use config::KizunaConfig;
 pub use error::KizunaError;
-pub use events::KizunaEvent;
+pub use events::{
+    ClipboardEvent, ClipboardEvents, DiscoveryEvent, DiscoveryEvents, EventDomain, KizunaEvent, SecurityEvent,
+    SecurityEvents, TransferEvent, TransferEvents,
+};
 pub use runtime::AsyncRuntime;
 pub use versioning::{ApiVersion, CompatibilityManager, CompatibilityCheck, CompatibilityLevel};
 pub use deprecation::{DeprecationManager, DeprecationInfo, DeprecationStatus, MigrationGuide, MigrationStep};
@@ -7,7 +7,7 @@ pub mod tools;
 pub mod docs;
 
 // Re-export core types for convenience
-pub use core::{KizunaAPI, KizunaInstance, KizunaConfig, KizunaError, KizunaEvent};
+pub use core::{KizunaAPI, KizunaInstance, KizunaInstanceBuilder, KizunaConfig, KizunaError, KizunaEvent};
 pub use plugins::{Plugin, PluginContext, PluginManager};
 pub use docs::{DocGenerator, CodeExample, ExampleManager, VersionManager};
 
@@ -12,10 +12,12 @@ pub use sandbox::{PluginSandbox, ResourceLimits, PluginPermissions};
 pub use loader::PluginLoader;
 pub use system_hooks::{
     DiscoveryHook, TransportHook, SecurityHook, FileTransferHook,
-    ClipboardHook, CommandExecutionHook,
-    SystemHookRegistry, SecurityEvent,
+    CommandExecutionHook, SystemHookRegistry, SecurityEvent,
 };
 
+#[cfg(feature = "clipboard")]
+pub use system_hooks::ClipboardHook;
+
 #[cfg(feature = "streaming")]
 pub use system_hooks::StreamingHook;
 
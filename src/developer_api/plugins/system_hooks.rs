@@ -15,6 +15,7 @@ use crate::transport::{PeerId, Connection};
 use crate::file_transfer::types::{TransferManifest, TransferSession};
 #[cfg(feature = "streaming")]
 use crate::streaming::{StreamSession, StreamConfig};
+#[cfg(feature = "clipboard")]
 use crate::clipboard::ClipboardContent;
 use crate::command_execution::{CommandRequest, CommandResult};
 
@@ -215,6 +216,7 @@ pub trait StreamingHook: Send + Sync {
 }
 
 /// Clipboard plugin hook for custom clipboard handling
+#[cfg(feature = "clipboard")]
 #[async_trait]
 pub trait ClipboardHook: Send + Sync {
     /// Called when clipboard content changes
@@ -283,6 +285,7 @@ pub struct SystemHookRegistry {
     file_transfer_hooks: Vec<Arc<dyn FileTransferHook>>,
     #[cfg(feature = "streaming")]
     streaming_hooks: Vec<Arc<dyn StreamingHook>>,
+    #[cfg(feature = "clipboard")]
     clipboard_hooks: Vec<Arc<dyn ClipboardHook>>,
     command_execution_hooks: Vec<Arc<dyn CommandExecutionHook>>,
 }
@@ -297,6 +300,7 @@ impl SystemHookRegistry {
             file_transfer_hooks: Vec::new(),
             #[cfg(feature = "streaming")]
             streaming_hooks: Vec::new(),
+            #[cfg(feature = "clipboard")]
             clipboard_hooks: Vec::new(),
             command_execution_hooks: Vec::new(),
         }
@@ -329,6 +333,7 @@ impl SystemHookRegistry {
     }
     
     /// Registers a clipboard hook
+    #[cfg(feature = "clipboard")]
     pub fn register_clipboard_hook(&mut self, hook: Arc<dyn ClipboardHook>) {
         self.clipboard_hooks.push(hook);
     }
@@ -365,6 +370,7 @@ impl SystemHookRegistry {
     }
     
     /// Gets all clipboard hooks
+    #[cfg(feature = "clipboard")]
     pub fn clipboard_hooks(&self) -> &[Arc<dyn ClipboardHook>] {
         &self.clipboard_hooks
     }
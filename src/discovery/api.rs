@@ -1,6 +1,12 @@
 use crate::discovery::{Discovery, DiscoveryManager, ServiceRecord, DiscoveryError};
+use crate::discovery::manager::StrategyStats;
+use crate::discovery::responder::{AnnounceResponder, ResponderConfig};
+use crate::discovery::services::ServiceRegistry;
+use crate::security::policy::{AnnounceState, DiscoveryVisibility, SecurityPolicy};
+use crate::transport::ProxyConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
@@ -20,6 +26,19 @@ pub struct DiscoveryConfig {
     pub peer_cache_ttl: Duration,
     /// Maximum number of concurrent discovery operations
     pub max_concurrent_discoveries: usize,
+    /// Run all strategies at once, each under its own timeout, instead of
+    /// picking one
+    #[serde(default = "default_concurrent_discovery")]
+    pub concurrent_discovery: bool,
+    /// Stealth mode and scheduled announce windows
+    #[serde(default)]
+    pub visibility: DiscoveryVisibility,
+    /// Rendezvous servers (`host:port`) used when multicast is unreachable
+    #[serde(default)]
+    pub rendezvous_servers: Vec<String>,
+    /// Proxy for rendezvous servers; `None` uses the proxy environment variables
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
 }
 
 impl Default for DiscoveryConfig {
@@ -36,16 +55,25 @@ impl Default for DiscoveryConfig {
             ],
             peer_cache_ttl: Duration::from_secs(300), // 5 minutes
             max_concurrent_discoveries: 10,
+            concurrent_discovery: true,
+            visibility: DiscoveryVisibility::default(),
+            rendezvous_servers: Vec::new(),
+            proxy: None,
         }
     }
 }
 
+fn default_concurrent_discovery() -> bool {
+    true
+}
+
 /// Strategy-specific configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StrategyConfig {
     /// Strategy priority (higher = preferred)
     pub priority: u8,
-    /// Strategy-specific timeout override
+    /// Strategy-specific timeout override; the strategy is given up on after
+    /// this long without holding up the others
     pub timeout: Option<Duration>,
     /// Strategy-specific parameters
     pub parameters: HashMap<String, String>,
@@ -80,6 +108,7 @@ pub struct KizunaDiscovery {
     config: DiscoveryConfig,
     event_sender: Option<mpsc::UnboundedSender<DiscoveryEvent>>,
     cancellation_token: CancellationToken,
+    services: ServiceRegistry,
 }
 
 impl KizunaDiscovery {
@@ -91,15 +120,14 @@ impl KizunaDiscovery {
     /// Create a new discovery instance with custom configuration
     pub fn with_config(config: DiscoveryConfig) -> Self {
         let mut manager = DiscoveryManager::new();
-        
-        // Configure auto-selection
-        manager.set_auto_select(config.auto_select);
+        apply_config(&mut manager, &config);
         
         Self {
             manager,
             config,
             event_sender: None,
             cancellation_token: CancellationToken::new(),
+            services: ServiceRegistry::new(),
         }
     }
 
@@ -141,6 +169,15 @@ impl KizunaDiscovery {
             }
         }
 
+        // Relay infrastructure only takes over when local discovery cannot work
+        if !self.config.rendezvous_servers.is_empty() {
+            let mut strategy = crate::discovery::strategies::rendezvous::RendezvousDiscovery::new(
+                self.config.rendezvous_servers.clone(),
+            );
+            strategy.set_proxy(self.config.proxy.clone().or_else(ProxyConfig::from_env));
+            self.manager.add_infrastructure_strategy(Box::new(strategy));
+        }
+
         Ok(())
     }
 
@@ -155,14 +192,89 @@ impl KizunaDiscovery {
     }
 
     /// Discover peers once with optional timeout
+    ///
+    /// After `start_discovery`, each peer is also sent as a `PeerDiscovered`
+    /// event as soon as a strategy finds it, before slower strategies finish.
     pub async fn discover_once(&self, timeout: Option<Duration>) -> Result<Vec<ServiceRecord>, DiscoveryError> {
         let timeout = timeout.unwrap_or(self.config.default_timeout);
-        self.manager.discover_peers(timeout).await
+        let Some(events) = &self.event_sender else {
+            return self.manager.discover_peers(timeout).await;
+        };
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let forward = async {
+            while let Some(peer) = receiver.recv().await {
+                let _ = events.send(DiscoveryEvent::PeerDiscovered(peer));
+            }
+        };
+        let (result, ()) = tokio::join!(self.manager.discover_peers_streaming(timeout, sender), forward);
+        if let Err(e) = &result {
+            let _ = events.send(DiscoveryEvent::Error(e.to_string()));
+        }
+        result
+    }
+
+    /// Success, failure and timing statistics for each strategy
+    pub async fn strategy_stats(&self) -> HashMap<String, StrategyStats> {
+        self.manager.get_strategy_stats().await
     }
 
     /// Announce this peer's presence
+    ///
+    /// In stealth mode or outside the announce windows this withdraws any
+    /// running announcement instead; browsing keeps working either way.
     pub async fn announce(&self) -> Result<(), DiscoveryError> {
-        self.manager.announce_presence().await
+        if self.announce_state().allows_announce() {
+            self.manager.announce_presence().await
+        } else {
+            self.manager.stop_announce().await
+        }
+    }
+
+    /// Check whether this peer may announce itself right now
+    pub fn announce_state(&self) -> AnnounceState {
+        self.config.visibility.state()
+    }
+
+    /// Keep announcements in step with the announce schedule until shutdown
+    pub async fn announce_on_schedule(&self) -> Result<(), DiscoveryError> {
+        let mut announcing = None;
+        loop {
+            let allowed = self.announce_state().allows_announce();
+            if announcing != Some(allowed) {
+                self.announce().await?;
+                announcing = Some(allowed);
+            }
+
+            tokio::select! {
+                _ = self.cancellation_token.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(Duration::from_secs(30)) => {}
+            }
+        }
+    }
+
+    /// Answer directed discovery queries until shutdown
+    ///
+    /// Runs next to the periodic announcements so peers probing for this
+    /// device get an answer straight away. Queries are ignored while stealth
+    /// mode or the announce windows forbid announcing. Services registered
+    /// through [`services`](Self::services) are answered for as well.
+    pub async fn respond(&self, config: ResponderConfig) -> Result<(), DiscoveryError> {
+        let visibility = self.config.visibility.clone();
+        let responder = AnnounceResponder::new(config)
+            .with_gate(Arc::new(move || visibility.state().allows_announce()))
+            .with_services(self.services.clone());
+        responder.run(self.cancellation_token.child_token()).await
+    }
+
+    /// Application services advertised over mDNS next to this device
+    pub fn services(&self) -> &ServiceRegistry {
+        &self.services
+    }
+
+    /// Apply the visibility settings from a security policy
+    pub fn apply_security_policy(&mut self, policy: &SecurityPolicy) {
+        self.config.visibility = policy.effective_visibility();
     }
 
     /// Stop announcing and clean up resources
@@ -194,7 +306,7 @@ impl KizunaDiscovery {
     /// Update configuration
     pub fn update_config(&mut self, config: DiscoveryConfig) {
         self.config = config;
-        self.manager.set_auto_select(self.config.auto_select);
+        apply_config(&mut self.manager, &self.config);
     }
 
     /// Get current configuration
@@ -205,11 +317,24 @@ impl KizunaDiscovery {
     /// Shutdown the discovery system
     pub async fn shutdown(&mut self) -> Result<(), DiscoveryError> {
         self.cancellation_token.cancel();
+        self.services.clear();
         self.stop_announce().await?;
         Ok(())
     }
 }
 
+/// Push the strategy selection and timeout settings down to the manager
+fn apply_config(manager: &mut DiscoveryManager, config: &DiscoveryConfig) {
+    manager.set_auto_select(config.auto_select);
+    manager.set_concurrent_discovery(config.concurrent_discovery);
+    manager.set_max_concurrent_strategies(config.max_concurrent_discoveries);
+    for (name, strategy_config) in &config.strategy_configs {
+        if let Some(timeout) = strategy_config.timeout {
+            manager.set_strategy_timeout(name, timeout);
+        }
+    }
+}
+
 impl Default for KizunaDiscovery {
     fn default() -> Self {
         Self::new()
@@ -259,6 +384,30 @@ impl DiscoveryBuilder {
         self
     }
 
+    /// Run strategies concurrently, each under its own timeout
+    pub fn concurrent(mut self, enabled: bool) -> Self {
+        self.config.concurrent_discovery = enabled;
+        self
+    }
+
+    /// Give up on one strategy after `timeout` without holding up the others
+    pub fn strategy_timeout(mut self, strategy: String, timeout: Duration) -> Self {
+        self.config.strategy_configs.entry(strategy).or_default().timeout = Some(timeout);
+        self
+    }
+
+    /// Set stealth mode and announce windows
+    pub fn visibility(mut self, visibility: DiscoveryVisibility) -> Self {
+        self.config.visibility = visibility;
+        self
+    }
+
+    /// Fall back to these rendezvous servers when multicast is unreachable
+    pub fn rendezvous_servers(mut self, servers: Vec<String>) -> Self {
+        self.config.rendezvous_servers = servers;
+        self
+    }
+
     /// Add strategy-specific configuration
     pub fn strategy_config(mut self, strategy: String, config: StrategyConfig) -> Self {
         self.config.strategy_configs.insert(strategy, config);
@@ -302,6 +451,18 @@ mod tests {
         assert_eq!(discovery.config.enabled_strategies.len(), 2);
     }
 
+    #[test]
+    fn test_strategy_timeouts_reach_manager() {
+        let discovery = DiscoveryBuilder::new()
+            .timeout(Duration::from_secs(5))
+            .strategy_timeout("bluetooth".to_string(), Duration::from_secs(2))
+            .build();
+
+        assert!(discovery.config.concurrent_discovery);
+        assert_eq!(discovery.manager.strategy_timeout("bluetooth", Duration::from_secs(5)), Duration::from_secs(2));
+        assert_eq!(discovery.manager.strategy_timeout("mdns", Duration::from_secs(5)), Duration::from_secs(5));
+    }
+
     #[tokio::test]
     async fn test_discovery_initialization() {
         let mut discovery = KizunaDiscovery::new();
@@ -1,4 +1,5 @@
-use crate::discovery::{KizunaDiscovery, DiscoveryBuilder, DiscoveryEvent};
+use crate::discovery::{KizunaDiscovery, DiscoveryBuilder, DiscoveryEvent, ResponderConfig};
+use crate::security::policy::{AnnounceState, DiscoveryVisibility};
 use std::time::Duration;
 use tokio::time::timeout;
 
@@ -122,19 +123,28 @@ impl DiscoveryCli {
     }
 
     /// Announce this peer's presence
+    ///
+    /// With `respond`, directed discovery queries are also answered as they
+    /// arrive instead of waiting for the next announcement.
     pub async fn announce(
         peer_name: Option<String>,
-        _port: Option<u16>,
+        port: Option<u16>,
         strategies: Option<Vec<String>>,
         duration_secs: Option<u64>,
+        visibility: DiscoveryVisibility,
+        respond: bool,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut discovery = if let Some(strategies) = strategies {
-            DiscoveryBuilder::new()
-                .strategies(strategies)
-                .build()
-        } else {
-            KizunaDiscovery::new()
+        let builder = match strategies {
+            Some(strategies) => DiscoveryBuilder::new().strategies(strategies),
+            None => DiscoveryBuilder::new(),
         };
+        let mut discovery = builder.visibility(visibility.clone()).build();
+
+        let state = discovery.announce_state();
+        if state == AnnounceState::Stealth {
+            println!("Stealth mode is enabled: this device browses for peers but never announces itself");
+            return Ok(());
+        }
 
         discovery.initialize().await?;
 
@@ -147,15 +157,47 @@ impl DiscoveryCli {
 
         println!("Announcing presence as '{}' on available strategies...", peer_name);
         println!("Available strategies: {:?}", discovery.get_available_strategies());
+        if !visibility.announce_windows.is_empty() {
+            let windows: Vec<String> = visibility.announce_windows.iter().map(|w| w.to_string()).collect();
+            println!("Announce windows: {}", windows.join(", "));
+            println!("Current state: {}", state);
+        }
 
-        discovery.announce().await?;
+        if respond {
+            println!("Answering discovery queries as they arrive");
+        }
 
-        if let Some(duration) = duration_secs {
-            println!("Announcing for {} seconds...", duration);
-            tokio::time::sleep(Duration::from_secs(duration)).await;
-        } else {
-            println!("Announcing indefinitely... (Press Ctrl+C to stop)");
-            tokio::signal::ctrl_c().await?;
+        {
+            // Follows the announce windows until the duration or Ctrl+C ends it
+            let schedule = discovery.announce_on_schedule();
+            tokio::pin!(schedule);
+
+            let responder = async {
+                if !respond {
+                    return std::future::pending().await;
+                }
+                let peer_id = format!("kizuna-{}", &uuid::Uuid::new_v4().to_string()[..8]);
+                discovery
+                    .respond(ResponderConfig::new(peer_id, peer_name.clone(), port.unwrap_or(41337)))
+                    .await
+            };
+            tokio::pin!(responder);
+
+            if let Some(duration) = duration_secs {
+                println!("Announcing for {} seconds...", duration);
+                tokio::select! {
+                    result = &mut schedule => result?,
+                    result = &mut responder => result?,
+                    _ = tokio::time::sleep(Duration::from_secs(duration)) => {}
+                }
+            } else {
+                println!("Announcing indefinitely... (Press Ctrl+C to stop)");
+                tokio::select! {
+                    result = &mut schedule => result?,
+                    result = &mut responder => result?,
+                    result = tokio::signal::ctrl_c() => result?,
+                }
+            }
         }
 
         println!("Stopping announcement...");
@@ -1,4 +1,5 @@
 use crate::discovery::api::{DiscoveryConfig, StrategyConfig as ApiStrategyConfig};
+use crate::transport::proxy::{split_host_port, ProxyConfig};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -32,12 +33,18 @@ pub struct GlobalDiscoveryConfig {
     #[serde(default = "default_enabled_strategies")]
     pub enabled_strategies: Vec<String>,
     /// Enable concurrent discovery across multiple strategies
-    #[serde(default)]
+    #[serde(default = "default_true")]
     pub concurrent_discovery: bool,
     /// Device name for announcements
     pub device_name: Option<String>,
     /// Default port for services
     pub default_port: Option<u16>,
+    /// Rendezvous servers (`host:port`) used when multicast is unreachable
+    #[serde(default)]
+    pub rendezvous_servers: Vec<String>,
+    /// Proxy for rendezvous servers
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -128,9 +135,11 @@ impl Default for DiscoveryConfigFile {
                 peer_cache_ttl_secs: 300,
                 max_concurrent_discoveries: 10,
                 enabled_strategies: default_enabled_strategies(),
-                concurrent_discovery: false,
+                concurrent_discovery: true,
                 device_name: None,
                 default_port: Some(8080),
+                rendezvous_servers: Vec::new(),
+                proxy: None,
             },
             strategies,
         }
@@ -188,6 +197,10 @@ impl DiscoveryConfigFile {
             enabled_strategies: self.discovery.enabled_strategies.clone(),
             peer_cache_ttl: Duration::from_secs(self.discovery.peer_cache_ttl_secs),
             max_concurrent_discoveries: self.discovery.max_concurrent_discoveries,
+            concurrent_discovery: self.discovery.concurrent_discovery,
+            visibility: Default::default(),
+            rendezvous_servers: self.discovery.rendezvous_servers.clone(),
+            proxy: self.discovery.proxy.clone(),
         }
     }
 
@@ -212,9 +225,11 @@ impl DiscoveryConfigFile {
                 peer_cache_ttl_secs: config.peer_cache_ttl.as_secs(),
                 max_concurrent_discoveries: config.max_concurrent_discoveries,
                 enabled_strategies: config.enabled_strategies.clone(),
-                concurrent_discovery: false, // This would need to be tracked separately
+                concurrent_discovery: config.concurrent_discovery,
                 device_name: None,
                 default_port: None,
+                rendezvous_servers: config.rendezvous_servers.clone(),
+                proxy: config.proxy.clone(),
             },
             strategies,
         }
@@ -249,6 +264,19 @@ impl DiscoveryConfigFile {
             errors.push("At least one strategy must be enabled".to_string());
         }
 
+        for server in &self.discovery.rendezvous_servers {
+            let valid = server
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+            if !valid {
+                errors.push(format!("Rendezvous server '{}' must be in host:port form", server));
+            }
+        }
+
+        if let Some(proxy) = &self.discovery.proxy && split_host_port(&proxy.address).is_none() {
+            errors.push(format!("Proxy address '{}' must be in host:port form", proxy.address));
+        }
+
         // Validate strategy configurations
         for (name, strategy) in &self.strategies {
             if strategy.enabled && !self.discovery.enabled_strategies.contains(name) {
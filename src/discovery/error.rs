@@ -13,6 +13,9 @@ pub enum DiscoveryError {
     
     #[error("Invalid service record: {reason}")]
     InvalidServiceRecord { reason: String },
+
+    #[error("Service {service} conflicts: {reason}")]
+    ServiceConflict { service: String, reason: String },
     
     #[error("Bluetooth error: {0}")]
     Bluetooth(String),
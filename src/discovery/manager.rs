@@ -1,9 +1,15 @@
 use crate::discovery::{Discovery, DiscoveryError, ServiceRecord};
 use crate::discovery::error::{ErrorContext, ErrorSeverity};
-use std::collections::HashMap;
+use crate::discovery::security_integration::verify_record_identity;
+use crate::error::KizunaError;
+use crate::error_aggregator::{Disposition, ErrorAggregator};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 
 #[derive(Debug, Clone)]
 pub struct StrategyConfig {
@@ -115,6 +121,8 @@ pub struct StrategyUsage {
     pub is_recently_failing: bool,
     pub network_latency: Option<Duration>,
     pub availability_score: f64,
+    pub last_response_time: Option<Duration>,
+    pub timeout_count: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -497,6 +505,10 @@ pub struct StrategyStats {
     pub recent_failures: u32,
     pub consecutive_failures: u32,
     pub last_performance_test: Option<SystemTime>,
+    /// How long the most recent attempt took, whatever its outcome
+    pub last_response_time: Option<Duration>,
+    /// Attempts cut off by the strategy's time limit
+    pub timeout_count: u64,
 }
 
 impl StrategyStats {
@@ -514,6 +526,8 @@ impl StrategyStats {
             recent_failures: 0,
             consecutive_failures: 0,
             last_performance_test: None,
+            last_response_time: None,
+            timeout_count: 0,
         }
     }
 
@@ -567,6 +581,43 @@ impl StrategyStats {
     }
 }
 
+/// Check whether the host has a route for multicast or broadcast traffic
+///
+/// Connecting a UDP socket only resolves the route, so nothing is sent.
+fn multicast_reachable() -> bool {
+    let Ok(socket) = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) else {
+        return false;
+    };
+    if socket.connect((Ipv4Addr::new(224, 0, 0, 251), 5353)).is_ok() {
+        return true;
+    }
+    socket.set_broadcast(true).is_ok() && socket.connect((Ipv4Addr::BROADCAST, 41338)).is_ok()
+}
+
+/// Forwards peers to a listener as strategies report them, once per peer
+struct PeerStream {
+    sender: mpsc::UnboundedSender<ServiceRecord>,
+    sent: HashSet<String>,
+}
+
+impl PeerStream {
+    fn new(sender: mpsc::UnboundedSender<ServiceRecord>) -> Self {
+        Self {
+            sender,
+            sent: HashSet::new(),
+        }
+    }
+
+    fn emit(&mut self, peers: &[ServiceRecord]) {
+        for peer in peers {
+            if self.sent.insert(peer.identity_key().to_string()) {
+                // The listener going away does not stop discovery
+                let _ = self.sender.send(peer.clone());
+            }
+        }
+    }
+}
+
 pub struct DiscoveryManager {
     strategies: Vec<Box<dyn Discovery>>,
     auto_select: bool,
@@ -577,6 +628,8 @@ pub struct DiscoveryManager {
     concurrent_discovery: bool,
     max_concurrent_strategies: usize,
     performance_test_timeout: Duration,
+    /// Per-strategy time limits overriding the discovery timeout
+    strategy_timeouts: HashMap<String, Duration>,
     network_condition_cache: Arc<RwLock<Option<NetworkCondition>>>,
     last_network_check: Arc<RwLock<Option<SystemTime>>>,
     fallback_enabled: bool,
@@ -584,8 +637,27 @@ pub struct DiscoveryManager {
     retry_config: RetryConfig,
     error_recovery_config: ErrorRecoveryConfig,
     error_history: Arc<RwLock<Vec<(SystemTime, DiscoveryError, ErrorContext)>>>,
+    error_aggregator: Arc<ErrorAggregator>,
     circuit_breakers: Arc<RwLock<HashMap<String, CircuitBreakerState>>>,
     performance_monitor: Arc<RwLock<PerformanceMonitor>>,
+    /// Rendezvous/relay strategies used only when multicast is unusable
+    infrastructure_strategies: Vec<Box<dyn Discovery>>,
+    infrastructure_fallback: Arc<RwLock<InfrastructureFallback>>,
+    announcing: Arc<AtomicBool>,
+}
+
+/// Consecutive empty local rounds before switching to infrastructure discovery
+const MULTICAST_FAILURE_ROUNDS: u32 = 3;
+
+/// Tracks whether local discovery has failed badly enough to use infrastructure
+#[derive(Debug, Clone, Default)]
+pub struct InfrastructureFallback {
+    /// Discovery currently goes through rendezvous/relay servers
+    pub active: bool,
+    /// Local discovery rounds in a row that found nobody
+    pub empty_local_rounds: u32,
+    /// When the fallback was last switched on
+    pub activated_at: Option<SystemTime>,
 }
 
 #[derive(Debug, Clone)]
@@ -633,6 +705,7 @@ impl DiscoveryManager {
             concurrent_discovery: false,
             max_concurrent_strategies: 3,
             performance_test_timeout: Duration::from_secs(10),
+            strategy_timeouts: HashMap::new(),
             network_condition_cache: Arc::new(RwLock::new(None)),
             last_network_check: Arc::new(RwLock::new(None)),
             fallback_enabled: true,
@@ -640,8 +713,12 @@ impl DiscoveryManager {
             retry_config: RetryConfig::default(),
             error_recovery_config: ErrorRecoveryConfig::default(),
             error_history: Arc::new(RwLock::new(Vec::new())),
+            error_aggregator: Arc::new(ErrorAggregator::default()),
             circuit_breakers: Arc::new(RwLock::new(HashMap::new())),
             performance_monitor: Arc::new(RwLock::new(PerformanceMonitor::new())),
+            infrastructure_strategies: Vec::new(),
+            infrastructure_fallback: Arc::new(RwLock::new(InfrastructureFallback::default())),
+            announcing: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -668,6 +745,12 @@ impl DiscoveryManager {
         stats_guard.insert(strategy_name.clone(), StrategyStats::new(strategy_name));
     }
 
+    /// Add a rendezvous/relay strategy that is only used once local
+    /// multicast and broadcast discovery turn out to be unreachable
+    pub fn add_infrastructure_strategy(&mut self, strategy: Box<dyn Discovery>) {
+        self.infrastructure_strategies.push(strategy);
+    }
+
     pub fn set_auto_select(&mut self, enabled: bool) {
         self.auto_select = enabled;
     }
@@ -688,6 +771,19 @@ impl DiscoveryManager {
         self.performance_test_timeout = timeout;
     }
 
+    /// Give a strategy its own time limit instead of the discovery timeout
+    ///
+    /// Useful for strategies such as Bluetooth that are slow to answer, so
+    /// they cannot hold up the others.
+    pub fn set_strategy_timeout(&mut self, strategy_name: &str, timeout: Duration) {
+        self.strategy_timeouts.insert(strategy_name.to_string(), timeout);
+    }
+
+    /// Time limit for one strategy when discovery was given `timeout`
+    pub fn strategy_timeout(&self, strategy_name: &str, timeout: Duration) -> Duration {
+        self.strategy_timeouts.get(strategy_name).copied().unwrap_or(timeout)
+    }
+
     pub fn set_fallback_enabled(&mut self, enabled: bool) {
         self.fallback_enabled = enabled;
     }
@@ -700,16 +796,86 @@ impl DiscoveryManager {
         self.retry_config = config;
     }
 
+    /// Share an error aggregator, e.g. the daemon's, to deduplicate error output
+    pub fn set_error_aggregator(&mut self, aggregator: Arc<ErrorAggregator>) {
+        self.error_aggregator = aggregator;
+    }
+
+    /// Aggregator that deduplicates and rate-limits discovery errors
+    pub fn error_aggregator(&self) -> Arc<ErrorAggregator> {
+        Arc::clone(&self.error_aggregator)
+    }
+
     pub fn set_error_recovery_config(&mut self, config: ErrorRecoveryConfig) {
         self.error_recovery_config = config;
     }
 
     pub async fn discover_peers(&self, timeout: Duration) -> Result<Vec<ServiceRecord>, DiscoveryError> {
+        self.discover_peers_with(timeout, None).await
+    }
+
+    /// Discover peers, sending each one to `sender` as soon as a strategy finds it
+    ///
+    /// In concurrent mode fast strategies report their peers while slower
+    /// ones are still running. Every peer is sent once; the merged list is
+    /// returned when all strategies have finished or timed out.
+    pub async fn discover_peers_streaming(
+        &self,
+        timeout: Duration,
+        sender: mpsc::UnboundedSender<ServiceRecord>,
+    ) -> Result<Vec<ServiceRecord>, DiscoveryError> {
+        let mut stream = PeerStream::new(sender);
+        self.discover_peers_with(timeout, Some(&mut stream)).await
+    }
+
+    async fn discover_peers_with(
+        &self,
+        timeout: Duration,
+        mut stream: Option<&mut PeerStream>,
+    ) -> Result<Vec<ServiceRecord>, DiscoveryError> {
         // Clean up expired peers before discovery
         self.cleanup_expired_peers().await;
 
+        let local = self.discover_local(timeout, stream.as_deref_mut()).await;
+        if let (Some(stream), Ok(peers)) = (stream.as_deref_mut(), &local) {
+            stream.emit(peers);
+        }
+        if self.infrastructure_strategies.is_empty() {
+            return local;
+        }
+
+        let found_local = matches!(&local, Ok(peers) if !peers.is_empty());
+        if !self.update_infrastructure_fallback(found_local).await {
+            return local;
+        }
+
+        match self.discover_with_infrastructure(timeout).await {
+            Ok(remote) => {
+                if let Some(stream) = stream {
+                    stream.emit(&remote);
+                }
+                let mut peers: HashMap<String, ServiceRecord> = HashMap::new();
+                for peer in local.unwrap_or_default().into_iter().chain(remote) {
+                    match peers.get_mut(peer.identity_key()) {
+                        Some(existing) => existing.merge(peer),
+                        None => {
+                            peers.insert(peer.identity_key().to_string(), peer);
+                        }
+                    }
+                }
+                Ok(peers.into_values().collect())
+            }
+            Err(e) => local.or(Err(e)),
+        }
+    }
+
+    async fn discover_local(
+        &self,
+        timeout: Duration,
+        stream: Option<&mut PeerStream>,
+    ) -> Result<Vec<ServiceRecord>, DiscoveryError> {
         if self.concurrent_discovery {
-            self.discover_concurrent(timeout).await
+            self.discover_concurrent(timeout, stream).await
         } else if self.auto_select {
             self.discover_with_auto_select(timeout).await
         } else if let Some(strategy_name) = &self.active_strategy {
@@ -730,8 +896,15 @@ impl DiscoveryManager {
 
     pub async fn announce_presence(&self) -> Result<(), DiscoveryError> {
         let mut errors = Vec::new();
+        self.announcing.store(true, Ordering::SeqCst);
         
-        for strategy in &self.strategies {
+        let infrastructure: &[Box<dyn Discovery>] = if self.infrastructure_fallback.read().await.active {
+            &self.infrastructure_strategies
+        } else {
+            &[]
+        };
+        
+        for strategy in self.strategies.iter().chain(infrastructure) {
             if strategy.is_available() {
                 if let Err(e) = strategy.announce().await {
                     errors.push(format!("{}: {}", strategy.strategy_name(), e));
@@ -751,8 +924,9 @@ impl DiscoveryManager {
 
     pub async fn stop_announce(&self) -> Result<(), DiscoveryError> {
         let mut errors = Vec::new();
+        self.announcing.store(false, Ordering::SeqCst);
         
-        for strategy in &self.strategies {
+        for strategy in self.strategies.iter().chain(&self.infrastructure_strategies) {
             if let Err(e) = strategy.stop_announce().await {
                 errors.push(format!("{}: {}", strategy.strategy_name(), e));
             }
@@ -914,7 +1088,11 @@ impl DiscoveryManager {
         Ok(peers)
     }
 
-    async fn discover_concurrent(&self, timeout: Duration) -> Result<Vec<ServiceRecord>, DiscoveryError> {
+    async fn discover_concurrent(
+        &self,
+        timeout: Duration,
+        mut stream: Option<&mut PeerStream>,
+    ) -> Result<Vec<ServiceRecord>, DiscoveryError> {
         let available_strategies: Vec<_> = self.strategies
             .iter()
             .filter(|s| s.is_available())
@@ -927,43 +1105,42 @@ impl DiscoveryManager {
             });
         }
 
-        let mut tasks = Vec::new();
-        
-        for strategy in available_strategies {
-            let strategy_ref = strategy.as_ref();
-            let task = self.discover_with_single_strategy(strategy_ref, timeout);
-            tasks.push(task);
+        {
+            let mut monitor = self.performance_monitor.write().await;
+            monitor.record_concurrent_discovery();
         }
 
-        // Wait for all strategies to complete
-        let results = futures::future::join_all(tasks).await;
-        
-        let mut all_peers = Vec::new();
+        // Each strategy runs under its own time limit, so a hanging one only
+        // loses its own results; the rest are handled as they finish
+        let mut pending: FuturesUnordered<_> = available_strategies
+            .into_iter()
+            .map(|strategy| self.discover_with_single_strategy(strategy.as_ref(), timeout))
+            .collect();
+
+        let mut unique_peers: HashMap<String, ServiceRecord> = HashMap::new();
         let mut had_success = false;
 
-        for result in results {
-            match result {
-                Ok(peers) => {
-                    all_peers.extend(peers);
-                    had_success = true;
-                }
-                Err(_) => {
-                    // Continue with other results
-                }
+        while let Some(result) = pending.next().await {
+            let Ok(peers) = result else {
+                // Continue with other results
+                continue;
+            };
+            had_success = true;
+            if let Some(stream) = stream.as_deref_mut() {
+                stream.emit(&peers);
             }
-        }
 
-        if had_success {
-            // Deduplicate peers by peer_id and merge records
-            let mut unique_peers: HashMap<String, ServiceRecord> = HashMap::new();
-            for peer in all_peers {
-                if let Some(existing) = unique_peers.get_mut(&peer.peer_id) {
+            // Deduplicate peers by identity and merge records
+            for peer in peers {
+                if let Some(existing) = unique_peers.get_mut(peer.identity_key()) {
                     existing.merge(peer);
                 } else {
-                    unique_peers.insert(peer.peer_id.clone(), peer);
+                    unique_peers.insert(peer.identity_key().to_string(), peer);
                 }
             }
+        }
 
+        if had_success {
             let final_peers: Vec<ServiceRecord> = unique_peers.into_values().collect();
             self.update_peer_cache(&final_peers).await;
             Ok(final_peers)
@@ -985,7 +1162,18 @@ impl DiscoveryManager {
             });
         }
 
-        let result = self.discover_with_retry(strategy, timeout).await;
+        // The limit covers retries too, so a strategy that keeps hanging
+        // cannot stretch discovery past its own budget
+        let limit = self.strategy_timeout(&strategy_name, timeout);
+        let started = Instant::now();
+        let result = match tokio::time::timeout(limit, self.discover_with_retry(strategy, limit)).await {
+            Ok(result) => result,
+            Err(_) => {
+                let error = DiscoveryError::Timeout { timeout: limit };
+                self.record_strategy_timeout(&strategy_name, &error, started.elapsed()).await;
+                Err(error)
+            }
+        };
         
         // Update circuit breaker state
         match &result {
@@ -993,7 +1181,13 @@ impl DiscoveryManager {
             Err(_) => self.record_strategy_failure(&strategy_name).await,
         }
         
-        result
+        // Decide once, here, which identities can be trusted when records are merged
+        result.map(|mut peers| {
+            for peer in &mut peers {
+                verify_record_identity(peer);
+            }
+            peers
+        })
     }
 
     async fn discover_with_retry(&self, strategy: &dyn Discovery, timeout: Duration) -> Result<Vec<ServiceRecord>, DiscoveryError> {
@@ -1077,6 +1271,19 @@ impl DiscoveryManager {
         }))
     }
 
+    async fn record_strategy_timeout(&self, strategy_name: &str, error: &DiscoveryError, elapsed: Duration) {
+        self.update_strategy_stats(strategy_name, &Err(error.clone()), elapsed, 0).await;
+        {
+            let mut monitor = self.performance_monitor.write().await;
+            monitor.record_discovery_failure(strategy_name, elapsed);
+        }
+
+        let context = ErrorContext::new(format!("discover_{}", strategy_name))
+            .with_strategy(strategy_name.to_string())
+            .with_severity(self.classify_error_severity(error));
+        self.log_error(error, &context).await;
+    }
+
     fn calculate_retry_delay(&self, attempt: u32) -> Duration {
         let base_delay = self.retry_config.base_delay.as_millis() as f64;
         let multiplier = self.retry_config.backoff_multiplier;
@@ -1137,6 +1344,7 @@ impl DiscoveryManager {
     async fn update_strategy_stats(&self, strategy_name: &str, result: &Result<Vec<ServiceRecord>, DiscoveryError>, elapsed: Duration, peer_count: usize) {
         let mut stats = self.strategy_stats.write().await;
         if let Some(stat) = stats.get_mut(strategy_name) {
+            stat.last_response_time = Some(elapsed);
             match result {
                 Ok(_) => {
                     stat.success_count += 1;
@@ -1160,7 +1368,10 @@ impl DiscoveryManager {
                     // Update availability score (exponential moving average)
                     stat.availability_score = stat.availability_score * 0.9 + 0.1;
                 }
-                Err(_) => {
+                Err(error) => {
+                    if matches!(error, DiscoveryError::Timeout { .. }) {
+                        stat.timeout_count += 1;
+                    }
                     stat.failure_count += 1;
                     stat.last_failure = Some(SystemTime::now());
                     stat.consecutive_failures += 1;
@@ -1373,6 +1584,82 @@ impl DiscoveryManager {
         }))
     }
 
+    /// Record the outcome of a local round and decide whether to use infrastructure
+    async fn update_infrastructure_fallback(&self, found_local: bool) -> bool {
+        let (active, changed) = {
+            let mut fallback = self.infrastructure_fallback.write().await;
+            let was_active = fallback.active;
+
+            if found_local {
+                fallback.empty_local_rounds = 0;
+                fallback.active = false;
+            } else {
+                fallback.empty_local_rounds += 1;
+                if !fallback.active
+                    && (fallback.empty_local_rounds >= MULTICAST_FAILURE_ROUNDS || !multicast_reachable())
+                {
+                    fallback.active = true;
+                    fallback.activated_at = Some(SystemTime::now());
+                }
+            }
+
+            (fallback.active, fallback.active != was_active)
+        };
+
+        if changed {
+            if active {
+                println!("[INFO] No multicast or broadcast reachability, discovering through rendezvous servers");
+            } else {
+                println!("[INFO] Local discovery recovered, leaving rendezvous servers");
+            }
+
+            // Registrations follow the announce state of the local strategies
+            for strategy in &self.infrastructure_strategies {
+                let result = if active && self.announcing.load(Ordering::SeqCst) {
+                    strategy.announce().await
+                } else if !active {
+                    strategy.stop_announce().await
+                } else {
+                    Ok(())
+                };
+                if let Err(e) = result {
+                    eprintln!("[WARN] Discovery: {} - {}", strategy.strategy_name(), e);
+                }
+            }
+        }
+
+        active
+    }
+
+    async fn discover_with_infrastructure(&self, timeout: Duration) -> Result<Vec<ServiceRecord>, DiscoveryError> {
+        let mut peers = Vec::new();
+        let mut last_error = None;
+
+        for strategy in self.infrastructure_strategies.iter().filter(|s| s.is_available()) {
+            // Refresh registrations so servers do not expire them
+            if self.announcing.load(Ordering::SeqCst) {
+                let _ = strategy.announce().await;
+            }
+
+            match self.discover_with_single_strategy(strategy.as_ref(), timeout).await {
+                Ok(found) => peers.extend(found),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        if peers.is_empty() && let Some(e) = last_error {
+            return Err(e);
+        }
+
+        self.update_peer_cache(&peers).await;
+        Ok(peers)
+    }
+
+    /// Current infrastructure fallback state
+    pub async fn infrastructure_fallback(&self) -> InfrastructureFallback {
+        self.infrastructure_fallback.read().await.clone()
+    }
+
     async fn mark_strategy_failure(&self, strategy_name: &str) {
         let mut stats = self.strategy_stats.write().await;
         if let Some(stat) = stats.get_mut(strategy_name) {
@@ -1393,12 +1680,12 @@ impl DiscoveryManager {
         let mut cache = self.discovered_peers.write().await;
         
         for peer in peers {
-            if let Some(existing) = cache.get_mut(&peer.peer_id) {
+            if let Some(existing) = cache.get_mut(peer.identity_key()) {
                 // Merge with existing record
                 existing.merge(peer.clone());
             } else {
                 // Add new peer
-                cache.insert(peer.peer_id.clone(), peer.clone());
+                cache.insert(peer.identity_key().to_string(), peer.clone());
             }
         }
     }
@@ -1457,10 +1744,14 @@ impl DiscoveryManager {
         best_strategy
     }
 
-    /// Get a specific peer by ID
+    /// Get a specific peer by identity or strategy peer_id
     pub async fn get_peer(&self, peer_id: &str) -> Option<ServiceRecord> {
         let peers = self.discovered_peers.read().await;
-        peers.get(peer_id).filter(|p| !p.is_expired(self.peer_ttl)).cloned()
+        peers
+            .get(peer_id)
+            .or_else(|| peers.values().find(|p| p.peer_id == peer_id))
+            .filter(|p| !p.is_expired(self.peer_ttl))
+            .cloned()
     }
 
     /// Get peers discovered by a specific strategy
@@ -1590,6 +1881,17 @@ impl DiscoveryManager {
             }
         }
 
+        // Repeats of the same transient error are held back for the periodic
+        // summary; critical errors are always shown
+        if !matches!(context.severity, ErrorSeverity::Critical) {
+            let report = KizunaError::from(error.clone()).report();
+            if self.error_aggregator.admit(&report) != Disposition::Reported {
+                log::debug!("Discovery: {} - {} (attempt {}/{})",
+                    context.operation, error, context.retry_count + 1, context.max_retries);
+                return;
+            }
+        }
+
         // Log based on severity
         match context.severity {
             ErrorSeverity::Low => {
@@ -1981,6 +2283,7 @@ impl DiscoveryManager {
             concurrent_discovery: self.concurrent_discovery,
             max_concurrent_strategies: self.max_concurrent_strategies,
             performance_test_timeout: self.performance_test_timeout,
+            strategy_timeouts: self.strategy_timeouts.clone(),
             network_condition_cache: Arc::clone(&self.network_condition_cache),
             last_network_check: Arc::clone(&self.last_network_check),
             fallback_enabled: self.fallback_enabled,
@@ -1988,8 +2291,12 @@ impl DiscoveryManager {
             retry_config: self.retry_config.clone(),
             error_recovery_config: self.error_recovery_config.clone(),
             error_history: Arc::clone(&self.error_history),
+            error_aggregator: Arc::clone(&self.error_aggregator),
             circuit_breakers: Arc::clone(&self.circuit_breakers),
             performance_monitor: Arc::clone(&self.performance_monitor),
+            infrastructure_strategies: Vec::new(),
+            infrastructure_fallback: Arc::clone(&self.infrastructure_fallback),
+            announcing: Arc::clone(&self.announcing),
         }
     }
 
@@ -2025,11 +2332,16 @@ impl DiscoveryManager {
             registered_count += 1;
         }
 
-        // Register Bluetooth LE strategy
-        let bluetooth_strategy = BluetoothDiscovery::new();
-        if bluetooth_strategy.is_available() {
-            self.add_strategy_async(Box::new(bluetooth_strategy)).await;
-            registered_count += 1;
+        // Register Bluetooth LE strategy. Probing the adapter can block for
+        // a long time on SBCs, so the constrained profile leaves it out
+        if crate::platform::ResourceProfile::active().hardware_probing {
+            let bluetooth_strategy = BluetoothDiscovery::new();
+            if bluetooth_strategy.is_available() {
+                self.add_strategy_async(Box::new(bluetooth_strategy)).await;
+                registered_count += 1;
+            }
+        } else {
+            log::info!("Skipping Bluetooth discovery because hardware probing is disabled");
         }
 
         if registered_count == 0 {
@@ -2213,7 +2525,7 @@ impl DiscoveryManager {
         let mut peer_sources: HashMap<String, Vec<String>> = HashMap::new();
 
         for peer in peers {
-            let peer_id = peer.peer_id.clone();
+            let peer_id = peer.identity_key().to_string();
             
             // Track which strategies discovered this peer
             peer_sources.entry(peer_id.clone())
@@ -2237,8 +2549,8 @@ impl DiscoveryManager {
         // Sort by discovery confidence (peers found by multiple strategies first)
         let mut final_peers: Vec<ServiceRecord> = unique_peers.into_values().collect();
         final_peers.sort_by(|a, b| {
-            let a_sources = peer_sources.get(&a.peer_id).map(|s| s.len()).unwrap_or(0);
-            let b_sources = peer_sources.get(&b.peer_id).map(|s| s.len()).unwrap_or(0);
+            let a_sources = peer_sources.get(a.identity_key()).map(|s| s.len()).unwrap_or(0);
+            let b_sources = peer_sources.get(b.identity_key()).map(|s| s.len()).unwrap_or(0);
             b_sources.cmp(&a_sources) // More sources = higher confidence
         });
 
@@ -2266,6 +2578,8 @@ impl DiscoveryManager {
                 is_recently_failing: stat.is_recently_failing(),
                 network_latency: stat.network_latency,
                 availability_score: stat.availability_score,
+                last_response_time: stat.last_response_time,
+                timeout_count: stat.timeout_count,
             };
             
             total_discoveries += stat.success_count + stat.failure_count;
@@ -2306,6 +2620,7 @@ mod tests {
         priority: u8,
         should_fail: bool,
         peers_to_return: Vec<ServiceRecord>,
+        delay: Option<Duration>,
     }
 
     impl MockDiscovery {
@@ -2316,6 +2631,7 @@ mod tests {
                 priority,
                 should_fail: false,
                 peers_to_return: Vec::new(),
+                delay: None,
             }
         }
 
@@ -2328,11 +2644,19 @@ mod tests {
             self.should_fail = true;
             self
         }
+
+        fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = Some(delay);
+            self
+        }
     }
 
     #[async_trait::async_trait]
     impl Discovery for MockDiscovery {
         async fn discover(&self, _timeout: Duration) -> Result<Vec<ServiceRecord>, DiscoveryError> {
+            if let Some(delay) = self.delay {
+                tokio::time::sleep(delay).await;
+            }
             if self.should_fail {
                 Err(DiscoveryError::Network("Mock failure".to_string()))
             } else {
@@ -2473,6 +2797,35 @@ mod tests {
         assert_eq!(peers.len(), 2); // Should get peers from both strategies
     }
 
+    #[tokio::test]
+    async fn test_hanging_strategy_times_out_alone() {
+        let mut manager = DiscoveryManager::new();
+        manager.set_concurrent_discovery(true);
+        manager.set_strategy_timeout("bluetooth", Duration::from_millis(50));
+
+        let fast = MockDiscovery::new("mdns", true, 80)
+            .with_peers(vec![ServiceRecord::new("peer-1".to_string(), "Device 1".to_string(), 8080)]);
+        let hanging = MockDiscovery::new("bluetooth", true, 40)
+            .with_peers(vec![ServiceRecord::new("peer-2".to_string(), "Device 2".to_string(), 8080)])
+            .with_delay(Duration::from_secs(60));
+        manager.add_strategy_async(Box::new(fast)).await;
+        manager.add_strategy_async(Box::new(hanging)).await;
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let started = Instant::now();
+        let peers = manager.discover_peers_streaming(Duration::from_secs(5), sender).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(peers.len(), 1);
+        assert_eq!(receiver.recv().await.unwrap().peer_id, "peer-1");
+        assert!(receiver.recv().await.is_none());
+
+        let stats = manager.get_strategy_stats().await;
+        assert_eq!(stats["bluetooth"].timeout_count, 1);
+        assert!(stats["bluetooth"].last_response_time.unwrap() >= Duration::from_millis(50));
+        assert_eq!(stats["mdns"].timeout_count, 0);
+        assert!(stats["mdns"].last_response_time.is_some());
+    }
+
     #[tokio::test]
     async fn test_peer_deduplication_in_concurrent_mode() {
         let mut manager = DiscoveryManager::new();
@@ -2769,6 +3122,36 @@ mod tests {
         assert!(merged_peer.discovery_method.contains("multi")); // Should show multiple sources
     }
 
+    #[tokio::test]
+    async fn test_peers_merged_by_verified_identity() {
+        use crate::discovery::security_integration::attach_identity;
+        use crate::security::identity::DeviceIdentity;
+
+        let mut manager = DiscoveryManager::new();
+        manager.set_concurrent_discovery(true);
+        let identity = DeviceIdentity::generate().unwrap();
+
+        let mut mdns_peer = ServiceRecord::new("kizuna-1a2b".to_string(), "Laptop".to_string(), 8080);
+        mdns_peer.add_address(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 100)), 8080));
+        attach_identity(&mut mdns_peer.capabilities, &identity, mdns_peer.port, &mdns_peer.addresses).unwrap();
+
+        // Claims the same identity over UDP without a proof, from another address
+        let mut udp_peer = ServiceRecord::new("kizuna-3c4d".to_string(), "Unverified".to_string(), 8080);
+        udp_peer.add_address(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)), 8080));
+        udp_peer.add_capability("identity".to_string(), identity.derive_peer_id().to_hex());
+
+        manager.add_strategy_async(Box::new(MockDiscovery::new("mdns", true, 50).with_peers(vec![mdns_peer]))).await;
+        manager.add_strategy_async(Box::new(MockDiscovery::new("udp", true, 60).with_peers(vec![udp_peer]))).await;
+
+        let peers = manager.discover_peers(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(peers.len(), 1);
+        assert!(peers[0].identity_verified);
+        assert_eq!(peers[0].name, "Laptop");
+        assert_eq!(peers[0].addresses, vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 100)), 8080)]);
+
+        assert!(manager.get_peer("kizuna-1a2b").await.is_some());
+    }
+
     #[tokio::test]
     async fn test_strategy_performance_testing() {
         let mut manager = DiscoveryManager::new();
@@ -3188,4 +3571,26 @@ mod tests {
         assert_eq!(metrics_after.total_attempts, 0);
         assert_eq!(metrics_after.successful_attempts, 0);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_infrastructure_fallback_after_empty_local_rounds() {
+        let mut manager = DiscoveryManager::new();
+
+        let mut remote = ServiceRecord::new("peer-remote".to_string(), "Remote".to_string(), 41337);
+        remote.set_discovery_method("rendezvous".to_string());
+
+        manager.add_strategy_async(Box::new(MockDiscovery::new("local", true, 80))).await;
+        manager.add_infrastructure_strategy(Box::new(
+            MockDiscovery::new("rendezvous", true, 10).with_peers(vec![remote]),
+        ));
+
+        let mut peers = Vec::new();
+        for _ in 0..MULTICAST_FAILURE_ROUNDS {
+            peers = manager.discover_peers(Duration::from_secs(1)).await.unwrap();
+        }
+
+        assert!(manager.infrastructure_fallback().await.active);
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].discovery_method, "rendezvous");
+    }
+}
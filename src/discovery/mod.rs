@@ -9,6 +9,9 @@ pub mod api;
 pub mod cli;
 pub mod config;
 pub mod security_integration;
+pub mod presence;
+pub mod responder;
+pub mod services;
 
 // Re-export legacy modules for backward compatibility
 pub mod udp {
@@ -25,8 +28,15 @@ pub use api::{KizunaDiscovery, DiscoveryConfig, DiscoveryBuilder, DiscoveryEvent
 pub use cli::DiscoveryCli;
 pub use config::{DiscoveryConfigFile, ConfigManager};
 pub use security_integration::{
-    DiscoverySecurityHooks, IdentityProof, SecureServiceRecord
+    attach_identity, verify_record_identity, DiscoverySecurityHooks, IdentityProof,
+    SecureServiceRecord, IDENTITY_CAPABILITY, IDENTITY_PROOF_CAPABILITY,
 };
+pub use presence::{
+    ActivityProbe, ActivitySignals, Presence, PresenceConfig, PresenceManager, PresenceStatus,
+    SystemActivityProbe, LOW_POWER_CAPABILITY, PRESENCE_CAPABILITY,
+};
+pub use responder::{AnnounceResponder, ResponderConfig, ResponderGate};
+pub use services::{ServiceId, ServiceRegistration, ServiceRegistry, KIZUNA_SERVICE_TYPE};
 
 // Keep the legacy Peer struct for backward compatibility
 #[derive(Debug, Clone)]
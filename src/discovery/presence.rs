@@ -0,0 +1,483 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::command_execution::NotificationPriority;
+use crate::discovery::{DiscoveryError, ServiceRecord};
+
+/// Capability key carrying the device's status in discovery records
+pub const PRESENCE_CAPABILITY: &str = "presence";
+
+/// Capability key set when the device is saving battery
+pub const LOW_POWER_CAPABILITY: &str = "low_power";
+
+/// Status a device shows to its peers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceStatus {
+    Online,
+    Away,
+    Busy,
+    Streaming,
+}
+
+impl PresenceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PresenceStatus::Online => "online",
+            PresenceStatus::Away => "away",
+            PresenceStatus::Busy => "busy",
+            PresenceStatus::Streaming => "streaming",
+        }
+    }
+
+    /// Whether a notification of `priority` should be shown right away
+    ///
+    /// Busy and streaming devices are in do-not-disturb and only let high
+    /// priority notifications through.
+    pub fn accepts(&self, priority: NotificationPriority) -> bool {
+        match self {
+            PresenceStatus::Online | PresenceStatus::Away => true,
+            PresenceStatus::Busy | PresenceStatus::Streaming => priority >= NotificationPriority::High,
+        }
+    }
+}
+
+impl fmt::Display for PresenceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PresenceStatus {
+    type Err = DiscoveryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "online" => Ok(PresenceStatus::Online),
+            "away" | "idle" => Ok(PresenceStatus::Away),
+            "busy" | "dnd" => Ok(PresenceStatus::Busy),
+            "streaming" => Ok(PresenceStatus::Streaming),
+            other => Err(DiscoveryError::Parse(format!("Unknown presence status '{}'", other))),
+        }
+    }
+}
+
+/// A device's published presence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Presence {
+    pub status: PresenceStatus,
+    /// The device is in battery saver mode
+    pub low_power: bool,
+}
+
+impl Presence {
+    pub fn online() -> Self {
+        Self {
+            status: PresenceStatus::Online,
+            low_power: false,
+        }
+    }
+
+    /// Presence a peer advertised, if it shares one
+    pub fn from_record(record: &ServiceRecord) -> Option<Self> {
+        let status = record.get_capability(PRESENCE_CAPABILITY)?.parse().ok()?;
+        Some(Self {
+            status,
+            low_power: record
+                .get_capability(LOW_POWER_CAPABILITY)
+                .is_some_and(|value| value == "true"),
+        })
+    }
+
+    /// Derive presence from activity signals
+    ///
+    /// Streaming wins over a meeting, which wins over being idle for
+    /// `away_after`.
+    pub fn derive(signals: &ActivitySignals, away_after: Duration) -> Self {
+        let status = if signals.streaming {
+            PresenceStatus::Streaming
+        } else if signals.in_meeting {
+            PresenceStatus::Busy
+        } else if signals.idle_for.is_some_and(|idle| idle >= away_after) {
+            PresenceStatus::Away
+        } else {
+            PresenceStatus::Online
+        };
+        Self {
+            status,
+            low_power: signals.battery_saver,
+        }
+    }
+}
+
+impl Default for Presence {
+    fn default() -> Self {
+        Self::online()
+    }
+}
+
+/// What the device is doing, as far as presence is concerned
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActivitySignals {
+    /// Time since the last keyboard or pointer input, when known
+    pub idle_for: Option<Duration>,
+    /// A call or meeting is using the microphone or camera
+    pub in_meeting: bool,
+    /// A screen or camera stream is running
+    pub streaming: bool,
+    /// The OS is in battery saver or low power mode
+    pub battery_saver: bool,
+}
+
+/// Platform-specific source of activity signals
+pub trait ActivityProbe: Send + Sync {
+    /// Take a fresh sample; signals the platform cannot report are left unset
+    fn sample(&self) -> ActivitySignals;
+}
+
+/// Activity probe using the desktop's idle and power tools
+pub struct SystemActivityProbe;
+
+impl SystemActivityProbe {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn run(program: &str, args: &[&str]) -> Option<String> {
+        let output = std::process::Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn idle_for() -> Option<Duration> {
+        let millis = Self::run("xprintidle", &[])?.trim().parse().ok()?;
+        Some(Duration::from_millis(millis))
+    }
+
+    #[cfg(target_os = "macos")]
+    fn idle_for() -> Option<Duration> {
+        let output = Self::run("ioreg", &["-c", "IOHIDSystem", "-d", "4"])?;
+        let nanos = output
+            .lines()
+            .find(|line| line.contains("\"HIDIdleTime\""))?
+            .rsplit('=')
+            .next()?
+            .trim()
+            .parse()
+            .ok()?;
+        Some(Duration::from_nanos(nanos))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    fn idle_for() -> Option<Duration> {
+        None
+    }
+
+    #[cfg(target_os = "linux")]
+    fn battery_saver() -> bool {
+        Self::run("powerprofilesctl", &["get"]).is_some_and(|profile| profile.trim() == "power-saver")
+    }
+
+    #[cfg(target_os = "macos")]
+    fn battery_saver() -> bool {
+        Self::run("pmset", &["-g"]).is_some_and(|settings| {
+            settings.lines().any(|line| {
+                let mut fields = line.split_whitespace();
+                fields.next() == Some("lowpowermode") && fields.next() == Some("1")
+            })
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    fn battery_saver() -> bool {
+        false
+    }
+}
+
+impl Default for SystemActivityProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActivityProbe for SystemActivityProbe {
+    fn sample(&self) -> ActivitySignals {
+        ActivitySignals {
+            idle_for: Self::idle_for(),
+            battery_saver: Self::battery_saver(),
+            ..ActivitySignals::default()
+        }
+    }
+}
+
+/// Presence settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresenceConfig {
+    /// Publish status to peers; when off, peers see no status at all
+    pub share_presence: bool,
+    /// Idle time after which the device shows as away
+    pub away_after: Duration,
+    /// How often activity is sampled
+    pub refresh_interval: Duration,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            share_presence: true,
+            away_after: Duration::from_secs(5 * 60),
+            refresh_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+struct PresenceState {
+    manual: Option<PresenceStatus>,
+    in_meeting: bool,
+    streaming: bool,
+    share: bool,
+}
+
+/// Tracks this device's presence and publishes it in discovery records
+///
+/// Status is derived from the activity probe plus meeting and streaming flags
+/// set by the subsystems that know about them, unless the user picked a
+/// status by hand.
+pub struct PresenceManager {
+    probe: Box<dyn ActivityProbe>,
+    away_after: Duration,
+    refresh_interval: Duration,
+    state: Mutex<PresenceState>,
+    current: watch::Sender<Presence>,
+}
+
+impl PresenceManager {
+    /// Create a presence manager using the platform's activity probe
+    pub fn new(config: PresenceConfig) -> Self {
+        Self::with_probe(config, Box::new(SystemActivityProbe::new()))
+    }
+
+    /// Create a presence manager with a custom activity probe
+    pub fn with_probe(config: PresenceConfig, probe: Box<dyn ActivityProbe>) -> Self {
+        let (current, _) = watch::channel(Presence::online());
+        Self {
+            probe,
+            away_after: config.away_after,
+            refresh_interval: config.refresh_interval,
+            state: Mutex::new(PresenceState {
+                manual: None,
+                in_meeting: false,
+                streaming: false,
+                share: config.share_presence,
+            }),
+            current,
+        }
+    }
+
+    /// Current presence
+    pub fn current(&self) -> Presence {
+        *self.current.borrow()
+    }
+
+    /// Presence as peers should see it, or `None` when sharing is off
+    pub fn advertised(&self) -> Option<Presence> {
+        self.is_sharing().then(|| self.current())
+    }
+
+    /// Watch for presence changes
+    pub fn subscribe(&self) -> watch::Receiver<Presence> {
+        self.current.subscribe()
+    }
+
+    /// Turn publishing presence to peers on or off
+    pub fn set_sharing(&self, share: bool) {
+        self.state.lock().unwrap().share = share;
+    }
+
+    pub fn is_sharing(&self) -> bool {
+        self.state.lock().unwrap().share
+    }
+
+    /// Pin a status chosen by the user, or return to automatic status with `None`
+    pub fn set_manual(&self, status: Option<PresenceStatus>) -> Presence {
+        self.state.lock().unwrap().manual = status;
+        self.refresh()
+    }
+
+    /// Report whether a call or meeting is in progress
+    pub fn set_in_meeting(&self, in_meeting: bool) -> Presence {
+        self.state.lock().unwrap().in_meeting = in_meeting;
+        self.refresh()
+    }
+
+    /// Report whether a stream is running
+    pub fn set_streaming(&self, streaming: bool) -> Presence {
+        self.state.lock().unwrap().streaming = streaming;
+        self.refresh()
+    }
+
+    /// Sample activity and update the current presence
+    pub fn refresh(&self) -> Presence {
+        let mut signals = self.probe.sample();
+        let manual = {
+            let state = self.state.lock().unwrap();
+            signals.in_meeting |= state.in_meeting;
+            signals.streaming |= state.streaming;
+            state.manual
+        };
+
+        let mut presence = Presence::derive(&signals, self.away_after);
+        if let Some(status) = manual {
+            presence.status = status;
+        }
+        self.current.send_if_modified(|current| {
+            let changed = *current != presence;
+            *current = presence;
+            changed
+        });
+        presence
+    }
+
+    /// Refresh presence every `refresh_interval` until the manager is dropped
+    pub fn spawn_refresh(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let manager = Arc::downgrade(self);
+        let interval = self.refresh_interval;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                manager.refresh();
+            }
+        })
+    }
+
+    /// Write the advertised presence into a record about to be announced
+    pub fn apply_to(&self, record: &mut ServiceRecord) {
+        match self.advertised() {
+            Some(presence) => {
+                record.add_capability(PRESENCE_CAPABILITY.to_string(), presence.status.to_string());
+                if presence.low_power {
+                    record.add_capability(LOW_POWER_CAPABILITY.to_string(), "true".to_string());
+                } else {
+                    record.remove_capability(LOW_POWER_CAPABILITY);
+                }
+            }
+            None => {
+                record.remove_capability(PRESENCE_CAPABILITY);
+                record.remove_capability(LOW_POWER_CAPABILITY);
+            }
+        }
+    }
+
+    /// Whether a notification of `priority` should be shown now
+    pub fn accepts(&self, priority: NotificationPriority) -> bool {
+        self.current().status.accepts(priority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedProbe(Mutex<ActivitySignals>);
+
+    impl ActivityProbe for FixedProbe {
+        fn sample(&self) -> ActivitySignals {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    #[test]
+    fn test_derive_precedence() {
+        let away_after = Duration::from_secs(60);
+        let mut signals = ActivitySignals {
+            idle_for: Some(Duration::from_secs(120)),
+            ..ActivitySignals::default()
+        };
+        assert_eq!(Presence::derive(&signals, away_after).status, PresenceStatus::Away);
+
+        signals.in_meeting = true;
+        assert_eq!(Presence::derive(&signals, away_after).status, PresenceStatus::Busy);
+
+        signals.streaming = true;
+        signals.battery_saver = true;
+        let presence = Presence::derive(&signals, away_after);
+        assert_eq!(presence.status, PresenceStatus::Streaming);
+        assert!(presence.low_power);
+
+        let active = ActivitySignals {
+            idle_for: Some(Duration::from_secs(5)),
+            ..ActivitySignals::default()
+        };
+        assert_eq!(Presence::derive(&active, away_after), Presence::online());
+    }
+
+    #[test]
+    fn test_manual_status_and_flags() {
+        let probe = FixedProbe(Mutex::new(ActivitySignals::default()));
+        let manager = PresenceManager::with_probe(PresenceConfig::default(), Box::new(probe));
+        let mut changes = manager.subscribe();
+
+        assert_eq!(manager.set_streaming(true).status, PresenceStatus::Streaming);
+        assert!(changes.has_changed().unwrap());
+        changes.mark_unchanged();
+
+        assert_eq!(manager.set_manual(Some(PresenceStatus::Online)).status, PresenceStatus::Online);
+        assert_eq!(manager.set_manual(None).status, PresenceStatus::Streaming);
+        manager.refresh();
+        assert!(changes.has_changed().unwrap());
+
+        assert!(!manager.accepts(NotificationPriority::Normal));
+        assert!(manager.accepts(NotificationPriority::Critical));
+        assert_eq!(manager.set_streaming(false).status, PresenceStatus::Online);
+        assert!(manager.accepts(NotificationPriority::Low));
+    }
+
+    #[test]
+    fn test_record_round_trip_and_privacy() {
+        let probe = FixedProbe(Mutex::new(ActivitySignals {
+            battery_saver: true,
+            ..ActivitySignals::default()
+        }));
+        let manager = PresenceManager::with_probe(PresenceConfig::default(), Box::new(probe));
+        manager.set_in_meeting(true);
+
+        let mut record = ServiceRecord::new("laptop".to_string(), "Laptop".to_string(), 4000);
+        manager.apply_to(&mut record);
+        assert_eq!(
+            Presence::from_record(&record),
+            Some(Presence {
+                status: PresenceStatus::Busy,
+                low_power: true,
+            })
+        );
+
+        manager.set_sharing(false);
+        manager.apply_to(&mut record);
+        assert_eq!(Presence::from_record(&record), None);
+        assert!(!record.has_capability(LOW_POWER_CAPABILITY));
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!("Busy".parse::<PresenceStatus>().unwrap(), PresenceStatus::Busy);
+        assert_eq!("idle".parse::<PresenceStatus>().unwrap(), PresenceStatus::Away);
+        assert!("asleep".parse::<PresenceStatus>().is_err());
+    }
+}
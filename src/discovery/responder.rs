@@ -0,0 +1,701 @@
+//! Announce responder
+//!
+//! Periodic announcements only reach peers that happen to be listening when
+//! they go out, so on a large network a new device can wait a whole interval
+//! before it is found. The responder keeps sockets open and answers directed
+//! discovery queries as soon as they arrive:
+//!
+//! - UDP `DISCOVER_KIZUNA` probes get a `KIZUNA_PEER` reply sent straight back
+//!   to the prober's listening port.
+//! - mDNS queries for `_kizuna._tcp.local` (PTR), this device's instance
+//!   (SRV/TXT) or host name (A) get the full record set. Queries that ask for a
+//!   unicast response, and legacy one-shot queries from ports other than 5353,
+//!   are answered directly to the sender; the rest go to the multicast group.
+//! - The same goes for services applications registered in a
+//!   [`ServiceRegistry`]: PTR queries for their type and SRV/TXT queries for
+//!   their instance are answered with this device's host record.
+//!
+//! Given the device identity, every answer carries its PeerId and a fresh
+//! proof bound to the address it is answered from, so peers can merge what
+//! they learn over mDNS and UDP into one record.
+//!
+//! Sockets are bound with `SO_REUSEADDR` so the responder can run next to the
+//! UDP strategy and the system's own mDNS daemon.
+
+use crate::discovery::security_integration::attach_identity;
+use crate::discovery::service_record::version_capabilities;
+use crate::discovery::services::{ServiceRegistration, ServiceRegistry};
+use crate::discovery::strategies::udp::UdpDiscovery;
+use crate::discovery::DiscoveryError;
+use crate::security::identity::DeviceIdentity;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio_util::sync::CancellationToken;
+
+const KIZUNA_SERVICE: &str = "_kizuna._tcp.local";
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+/// Port the UDP strategy sends probes to
+pub const DEFAULT_PROBE_PORT: u16 = 41337;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Top bit of the class: unicast-response in questions, cache-flush in answers
+const CLASS_TOP_BIT: u16 = 0x8000;
+const RECORD_TTL: u32 = 120;
+
+/// Decides whether queries should be answered right now
+pub type ResponderGate = Arc<dyn Fn() -> bool + Send + Sync>;
+
+/// What the responder advertises and which queries it answers
+#[derive(Debug, Clone)]
+pub struct ResponderConfig {
+    pub peer_id: String,
+    pub device_name: String,
+    /// Port peers connect to
+    pub port: u16,
+    pub capabilities: HashMap<String, String>,
+    /// Port to answer UDP probes on; `None` turns UDP answering off
+    pub probe_port: Option<u16>,
+    /// Answer mDNS queries
+    pub mdns: bool,
+}
+
+impl ResponderConfig {
+    pub fn new(peer_id: String, device_name: String, port: u16) -> Self {
+        let mut capabilities = HashMap::new();
+        capabilities.insert("version".to_string(), "0.1.0".to_string());
+        capabilities.extend(version_capabilities());
+
+        Self {
+            peer_id,
+            device_name,
+            port,
+            capabilities,
+            probe_port: Some(DEFAULT_PROBE_PORT),
+            mdns: true,
+        }
+    }
+}
+
+/// Answers directed discovery queries until cancelled
+pub struct AnnounceResponder {
+    config: ResponderConfig,
+    gate: Option<ResponderGate>,
+    identity: Option<Arc<DeviceIdentity>>,
+    services: Option<ServiceRegistry>,
+    answered: AtomicU64,
+}
+
+impl AnnounceResponder {
+    pub fn new(config: ResponderConfig) -> Self {
+        Self {
+            config,
+            gate: None,
+            identity: None,
+            services: None,
+            answered: AtomicU64::new(0),
+        }
+    }
+
+    /// Only answer while `gate` returns true, e.g. inside announce windows
+    pub fn with_gate(mut self, gate: ResponderGate) -> Self {
+        self.gate = Some(gate);
+        self
+    }
+
+    /// Prove this device's identity in every answer
+    pub fn with_identity(mut self, identity: Arc<DeviceIdentity>) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Also answer for the services in `services`
+    pub fn with_services(mut self, services: ServiceRegistry) -> Self {
+        self.services = Some(services);
+        self
+    }
+
+    /// Capabilities to advertise, with a fresh identity proof when configured
+    ///
+    /// The proof is bound to `addresses`, the addresses the receiver will
+    /// record for this answer.
+    fn capabilities(&self, addresses: &[SocketAddr]) -> HashMap<String, String> {
+        let mut capabilities = self.config.capabilities.clone();
+        if let Some(identity) = &self.identity
+            && let Err(e) = attach_identity(&mut capabilities, identity, self.config.port, addresses)
+        {
+            log::debug!("Answering without identity proof: {}", e);
+        }
+        capabilities
+    }
+
+    /// Number of queries answered so far
+    pub fn answered(&self) -> u64 {
+        self.answered.load(Ordering::Relaxed)
+    }
+
+    /// Answer queries until `token` is cancelled
+    ///
+    /// Fails only if neither protocol could open its socket.
+    pub async fn run(&self, token: CancellationToken) -> Result<(), DiscoveryError> {
+        let udp = self
+            .config
+            .probe_port
+            .map(|port| bind_reusable(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port)));
+        let mdns = self.config.mdns.then(|| {
+            let socket = bind_reusable(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, MDNS_PORT))?;
+            socket.join_multicast_v4(MDNS_GROUP, Ipv4Addr::UNSPECIFIED)?;
+            socket.set_multicast_loop_v4(true)?;
+            Ok::<_, std::io::Error>(socket)
+        });
+
+        let udp = open_or_log(udp, "UDP probe");
+        let mdns = open_or_log(mdns, "mDNS");
+        if udp.is_none() && mdns.is_none() {
+            return Err(DiscoveryError::Network(
+                "Announce responder could not open any socket".to_string(),
+            ));
+        }
+
+        tokio::select! {
+            _ = self.serve_udp(udp) => {}
+            _ = self.serve_mdns(mdns) => {}
+            _ = token.cancelled() => {}
+        }
+        Ok(())
+    }
+
+    async fn serve_udp(&self, socket: Option<UdpSocket>) {
+        let Some(socket) = socket else {
+            return std::future::pending().await;
+        };
+        let mut buf = [0u8; 2048];
+        loop {
+            let (n, src) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    log::debug!("Announce responder UDP receive failed: {}", e);
+                    continue;
+                }
+            };
+            if !self.allowed() {
+                continue;
+            }
+            let message = String::from_utf8_lossy(&buf[..n]);
+            if let Some((reply, target)) = self.answer_probe(&message, src) {
+                match socket.send_to(reply.as_bytes(), target).await {
+                    Ok(_) => {
+                        self.answered.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => log::debug!("Announce responder failed to answer {}: {}", target, e),
+                }
+            }
+        }
+    }
+
+    async fn serve_mdns(&self, socket: Option<UdpSocket>) {
+        let Some(socket) = socket else {
+            return std::future::pending().await;
+        };
+        let mut buf = [0u8; 9000];
+        loop {
+            let (n, src) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    log::debug!("Announce responder mDNS receive failed: {}", e);
+                    continue;
+                }
+            };
+            // Conflicts are watched for even while not answering
+            if let Some(services) = &self.services
+                && let Some(answers) = parse_srv_answers(&buf[..n])
+            {
+                services.note_answers(&answers, &self.host_name());
+                continue;
+            }
+            if !self.allowed() {
+                continue;
+            }
+            let Some(query) = parse_query(&buf[..n]) else {
+                continue;
+            };
+            let Some((response, unicast)) = self.answer_query(&query, src, local_ipv4()) else {
+                continue;
+            };
+
+            let target = if unicast {
+                src
+            } else {
+                SocketAddr::V4(SocketAddrV4::new(MDNS_GROUP, MDNS_PORT))
+            };
+            match socket.send_to(&response, target).await {
+                Ok(_) => {
+                    self.answered.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => log::debug!("Announce responder failed to answer {}: {}", target, e),
+            }
+        }
+    }
+
+    fn allowed(&self) -> bool {
+        self.gate.as_ref().is_none_or(|gate| gate())
+    }
+
+    /// Reply to a UDP probe and where to send it
+    ///
+    /// Probes carry the port the prober listens on; replies go there rather
+    /// than to the ephemeral port the probe was sent from.
+    fn answer_probe(&self, message: &str, src: SocketAddr) -> Option<(String, SocketAddr)> {
+        let mut parts = message.split('|');
+        if parts.next()? != "DISCOVER_KIZUNA" {
+            return None;
+        }
+        let peer_id = parts.next()?;
+        if peer_id == self.config.peer_id {
+            return None;
+        }
+        let port = parts.nth(1).and_then(|port| port.parse().ok()).unwrap_or(src.port());
+
+        // Name the address explicitly so the prober records what the proof covers
+        let addresses: Vec<SocketAddr> = local_ip_toward(src)
+            .map(|ip| SocketAddr::new(ip, self.config.port))
+            .into_iter()
+            .collect();
+        let mut reply = UdpDiscovery::peer_response(
+            &self.config.peer_id,
+            &self.config.device_name,
+            self.config.port,
+            &addresses,
+        );
+        let mut capabilities: Vec<_> = self.capabilities(&addresses).into_iter().collect();
+        capabilities.sort();
+        for (key, value) in capabilities {
+            reply.push_str(&format!(",{}={}", key, value));
+        }
+        Some((reply, SocketAddr::new(src.ip(), port)))
+    }
+
+    /// Host name the SRV records point at
+    fn host_name(&self) -> String {
+        format!("{}.local", self.config.peer_id)
+    }
+
+    /// Response to an mDNS query, and whether to send it straight to the sender
+    fn answer_query(&self, query: &Query, src: SocketAddr, address: Option<Ipv4Addr>) -> Option<(Vec<u8>, bool)> {
+        let instance = format!("{}.{}", self.config.peer_id.to_lowercase(), KIZUNA_SERVICE);
+        let host = self.host_name().to_lowercase();
+        let services = self.services.as_ref().map(ServiceRegistry::active).unwrap_or_default();
+
+        let mut asked: Vec<&Question> = Vec::new();
+        let mut kizuna_asked = false;
+        let mut services_asked = vec![false; services.len()];
+        for question in &query.questions {
+            let kizuna = match question.name.as_str() {
+                name if name == KIZUNA_SERVICE => matches!(question.qtype, TYPE_PTR | TYPE_ANY),
+                name if name == instance => matches!(question.qtype, TYPE_SRV | TYPE_TXT | TYPE_ANY),
+                name if name == host => matches!(question.qtype, TYPE_A | TYPE_ANY),
+                _ => false,
+            };
+            let mut matched = kizuna;
+            for (service, service_asked) in services.iter().zip(services_asked.iter_mut()) {
+                if service_answers(service, question) {
+                    *service_asked = true;
+                    matched = true;
+                }
+            }
+            kizuna_asked |= kizuna;
+            if matched {
+                asked.push(question);
+            }
+        }
+        if asked.is_empty() {
+            return None;
+        }
+
+        // One-shot resolvers not on port 5353 expect a classic DNS answer
+        // echoing their ID and questions
+        let legacy = src.port() != MDNS_PORT;
+        let unicast = legacy || asked.iter().any(|q| q.unicast);
+
+        let host = self.host_name();
+        let mut records = Vec::new();
+        if kizuna_asked {
+            let instance = format!("{}.{}", self.config.peer_id, KIZUNA_SERVICE);
+            records.push((KIZUNA_SERVICE.to_string(), TYPE_PTR, name_bytes(&instance)));
+            records.push((instance.clone(), TYPE_SRV, srv_data(self.config.port, &host)));
+            records.push((instance, TYPE_TXT, self.txt_record(address)));
+        }
+        for (service, _) in services.iter().zip(&services_asked).filter(|(_, asked)| **asked) {
+            let instance = service.instance_fqdn();
+            records.push((service.service_name(), TYPE_PTR, name_bytes(&instance)));
+            records.push((instance.clone(), TYPE_SRV, srv_data(service.port, &host)));
+            records.push((instance, TYPE_TXT, txt_data(service.txt_entries())));
+        }
+        if let Some(address) = address {
+            records.push((host, TYPE_A, address.octets().to_vec()));
+        }
+
+        let mut packet = Vec::with_capacity(512);
+        packet.extend_from_slice(&(if legacy { query.id } else { 0 }).to_be_bytes());
+        packet.extend_from_slice(&0x8400u16.to_be_bytes()); // response, authoritative
+        packet.extend_from_slice(&(if legacy { asked.len() as u16 } else { 0 }).to_be_bytes());
+        packet.extend_from_slice(&(records.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&0u16.to_be_bytes());
+        packet.extend_from_slice(&0u16.to_be_bytes());
+        if legacy {
+            for question in &asked {
+                write_name(&mut packet, &question.name);
+                packet.extend_from_slice(&question.qtype.to_be_bytes());
+                packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+            }
+        }
+        for (name, rtype, data) in records {
+            write_name(&mut packet, &name);
+            packet.extend_from_slice(&rtype.to_be_bytes());
+            // Legacy resolvers do not understand the cache-flush bit
+            let class = if legacy || rtype == TYPE_PTR { CLASS_IN } else { CLASS_IN | CLASS_TOP_BIT };
+            packet.extend_from_slice(&class.to_be_bytes());
+            packet.extend_from_slice(&RECORD_TTL.to_be_bytes());
+            packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            packet.extend_from_slice(&data);
+        }
+
+        Some((packet, unicast))
+    }
+
+    /// TXT entries in the format the mDNS strategy parses
+    ///
+    /// `address` is the one sent in the A record, which is what receivers
+    /// record alongside the SRV port.
+    fn txt_record(&self, address: Option<Ipv4Addr>) -> Vec<u8> {
+        let mut entries = vec![
+            format!("peer_id={}", self.config.peer_id),
+            format!("name={}", self.config.device_name),
+        ];
+        let addresses: Vec<SocketAddr> = address
+            .map(|ip| SocketAddr::new(IpAddr::V4(ip), self.config.port))
+            .into_iter()
+            .collect();
+        let mut capabilities: Vec<_> = self.capabilities(&addresses).into_iter().collect();
+        capabilities.sort();
+        entries.extend(capabilities.into_iter().map(|(key, value)| format!("{}={}", key, value)));
+        txt_data(entries)
+    }
+}
+
+/// Whether `question` asks for a record of a registered service
+fn service_answers(service: &ServiceRegistration, question: &Question) -> bool {
+    if question.name == service.service_name().to_lowercase() {
+        matches!(question.qtype, TYPE_PTR | TYPE_ANY)
+    } else if question.name == service.instance_fqdn().to_lowercase() {
+        matches!(question.qtype, TYPE_SRV | TYPE_TXT | TYPE_ANY)
+    } else {
+        false
+    }
+}
+
+fn srv_data(port: u16, host: &str) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&0u16.to_be_bytes()); // priority
+    data.extend_from_slice(&0u16.to_be_bytes()); // weight
+    data.extend_from_slice(&port.to_be_bytes());
+    write_name(&mut data, host);
+    data
+}
+
+/// TXT record data; a record without entries holds one empty string
+fn txt_data(entries: impl IntoIterator<Item = String>) -> Vec<u8> {
+    let mut data = Vec::new();
+    for entry in entries {
+        let entry = &entry.as_bytes()[..entry.len().min(255)];
+        data.push(entry.len() as u8);
+        data.extend_from_slice(entry);
+    }
+    if data.is_empty() {
+        data.push(0);
+    }
+    data
+}
+
+/// Bind a UDP socket that other processes and strategies can share
+pub(crate) fn bind_reusable(addr: SocketAddrV4) -> std::io::Result<UdpSocket> {
+    let socket = socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::DGRAM, Some(socket2::Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_broadcast(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::V4(addr).into())?;
+    UdpSocket::from_std(socket.into())
+}
+
+fn open_or_log(socket: Option<std::io::Result<UdpSocket>>, protocol: &str) -> Option<UdpSocket> {
+    match socket? {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            log::warn!("Announce responder is not answering {} queries: {}", protocol, e);
+            None
+        }
+    }
+}
+
+/// Address of the interface that carries multicast traffic
+///
+/// Connecting a UDP socket only selects a route; nothing is sent.
+/// Local address the OS would use to reach `target`
+fn local_ip_toward(target: SocketAddr) -> Option<IpAddr> {
+    let bind: SocketAddr = match target {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = std::net::UdpSocket::bind(bind).ok()?;
+    socket.connect(target).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified()).then_some(ip)
+}
+
+fn local_ipv4() -> Option<Ipv4Addr> {
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect((MDNS_GROUP, MDNS_PORT)).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Question {
+    /// Lowercased, without the trailing dot
+    name: String,
+    qtype: u16,
+    /// The querier asked for a unicast response
+    unicast: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Query {
+    id: u16,
+    questions: Vec<Question>,
+}
+
+fn parse_query(packet: &[u8]) -> Option<Query> {
+    let id = read_u16(packet, 0)?;
+    let flags = read_u16(packet, 2)?;
+    if flags & 0x8000 != 0 {
+        return None; // a response, not a query
+    }
+    let count = read_u16(packet, 4)?;
+
+    let mut questions = Vec::with_capacity(count as usize);
+    let mut pos = 12;
+    for _ in 0..count {
+        let (name, next) = read_name(packet, pos)?;
+        let qtype = read_u16(packet, next)?;
+        let qclass = read_u16(packet, next + 2)?;
+        questions.push(Question {
+            name,
+            qtype,
+            unicast: qclass & CLASS_TOP_BIT != 0,
+        });
+        pos = next + 4;
+    }
+    Some(Query { id, questions })
+}
+
+/// SRV records in an mDNS response, as (instance, target host) pairs
+///
+/// Returns `None` for queries. Names come back lowercased.
+fn parse_srv_answers(packet: &[u8]) -> Option<Vec<(String, String)>> {
+    let flags = read_u16(packet, 2)?;
+    if flags & 0x8000 == 0 {
+        return None;
+    }
+    let questions = read_u16(packet, 4)?;
+    let records = [6, 8, 10]
+        .iter()
+        .map(|&offset| read_u16(packet, offset).map(usize::from))
+        .sum::<Option<usize>>()?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        let (_, next) = read_name(packet, pos)?;
+        pos = next + 4;
+    }
+
+    let mut answers = Vec::new();
+    for _ in 0..records {
+        let (name, next) = read_name(packet, pos)?;
+        let rtype = read_u16(packet, next)?;
+        let length = read_u16(packet, next + 8)? as usize;
+        let data = next + 10;
+        if rtype == TYPE_SRV {
+            let (target, _) = read_name(packet, data + 6)?;
+            answers.push((name, target));
+        }
+        pos = data + length;
+    }
+    Some(answers)
+}
+
+/// Read a possibly compressed name; returns it with the position after it
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    for _ in 0..32 {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some((labels.join(".").to_lowercase(), end.unwrap_or(pos + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let pointer = (read_u16(packet, pos)? & 0x3FFF) as usize;
+            end.get_or_insert(pos + 2);
+            pos = pointer;
+            continue;
+        }
+        let label = packet.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len;
+    }
+    None // pointer loop
+}
+
+fn read_u16(packet: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]))
+}
+
+fn write_name(buf: &mut Vec<u8>, name: &str) {
+    for label in name.trim_end_matches('.').split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label);
+    }
+    buf.push(0);
+}
+
+fn name_bytes(name: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_name(&mut buf, name);
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn responder() -> AnnounceResponder {
+        AnnounceResponder::new(ResponderConfig::new("kizuna-1234".to_string(), "Laptop".to_string(), 8080))
+    }
+
+    fn query(name: &str, qtype: u16, class: u16) -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        write_name(&mut packet, name);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&class.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_answers_udp_probe_on_listening_port() {
+        let responder = responder();
+        let src: SocketAddr = "192.168.1.20:53012".parse().unwrap();
+
+        let (reply, target) = responder.answer_probe("DISCOVER_KIZUNA|kizuna-abcd|Phone|41337", src).unwrap();
+        assert!(reply.starts_with("KIZUNA_PEER|kizuna-1234|Laptop|8080|"));
+        assert_eq!(target, "192.168.1.20:41337".parse().unwrap());
+
+        // Its own probes and other traffic are ignored
+        assert!(responder.answer_probe("DISCOVER_KIZUNA|kizuna-1234|Laptop|41337", src).is_none());
+        assert!(responder.answer_probe("KIZUNA_PEER|kizuna-abcd|Phone|41337", src).is_none());
+
+        // With an identity the reply proves it
+        let identity = Arc::new(DeviceIdentity::generate().unwrap());
+        let peer_id = identity.derive_peer_id().to_hex();
+        let responder = responder.with_identity(identity);
+        let (reply, _) = responder.answer_probe("DISCOVER_KIZUNA|kizuna-abcd|Phone|41337", src).unwrap();
+        assert!(reply.contains(&format!(",identity={}", peer_id)));
+        assert!(reply.contains(",identity_proof="));
+    }
+
+    #[test]
+    fn test_answers_mdns_queries() {
+        let responder = responder();
+        let mdns_src: SocketAddr = "192.168.1.20:5353".parse().unwrap();
+        let address = Some(Ipv4Addr::new(192, 168, 1, 10));
+
+        let ptr = parse_query(&query("_kizuna._tcp.local", TYPE_PTR, CLASS_IN)).unwrap();
+        let (response, unicast) = responder.answer_query(&ptr, mdns_src, address).unwrap();
+        assert!(!unicast);
+        assert_eq!(read_u16(&response, 0), Some(0));
+        assert_eq!(read_u16(&response, 6), Some(4));
+        let (name, _) = read_name(&response, 12).unwrap();
+        assert_eq!(name, "_kizuna._tcp.local");
+
+        // Unicast-response bit and legacy ports get a direct answer
+        let qu = parse_query(&query("kizuna-1234._kizuna._tcp.local", TYPE_SRV, CLASS_IN | CLASS_TOP_BIT)).unwrap();
+        assert!(qu.questions[0].unicast);
+        assert!(responder.answer_query(&qu, mdns_src, address).unwrap().1);
+        let legacy_src: SocketAddr = "192.168.1.20:60000".parse().unwrap();
+        let (response, unicast) = responder.answer_query(&ptr, legacy_src, None).unwrap();
+        assert!(unicast);
+        assert_eq!(read_u16(&response, 0), Some(0x1234));
+        assert_eq!(read_u16(&response, 4), Some(1));
+        assert_eq!(read_u16(&response, 6), Some(3));
+
+        // Other services are left to their owners
+        let other = parse_query(&query("_http._tcp.local", TYPE_PTR, CLASS_IN)).unwrap();
+        assert!(responder.answer_query(&other, mdns_src, address).is_none());
+    }
+
+    #[test]
+    fn test_answers_registered_services() {
+        let services = ServiceRegistry::new();
+        let web = services
+            .register(ServiceRegistration::new("_http._tcp", "Web UI", 8081).with_txt("path", "/admin"))
+            .unwrap();
+        let responder = responder().with_services(services.clone());
+        let mdns_src: SocketAddr = "192.168.1.20:5353".parse().unwrap();
+        let address = Some(Ipv4Addr::new(192, 168, 1, 10));
+
+        let ptr = parse_query(&query("_http._tcp.local", TYPE_PTR, CLASS_IN)).unwrap();
+        let (response, _) = responder.answer_query(&ptr, mdns_src, address).unwrap();
+        assert_eq!(read_u16(&response, 6), Some(4));
+        let (name, _) = read_name(&response, 12).unwrap();
+        assert_eq!(name, "_http._tcp.local");
+
+        // Our own answer looping back is not a conflict; the SRV points at us
+        let answers = parse_srv_answers(&response).unwrap();
+        assert_eq!(answers, vec![("web ui._http._tcp.local".to_string(), "kizuna-1234.local".to_string())]);
+        assert!(services.note_answers(&answers, &responder.host_name()).is_empty());
+        assert!(parse_srv_answers(&query("_http._tcp.local", TYPE_PTR, CLASS_IN)).is_none());
+
+        // Another host claiming the name takes the service off the air
+        let foreign = vec![("web ui._http._tcp.local".to_string(), "nas.local".to_string())];
+        assert_eq!(services.note_answers(&foreign, &responder.host_name()), vec![web]);
+        assert!(responder.answer_query(&ptr, mdns_src, address).is_none());
+    }
+
+    #[test]
+    fn test_gate_and_compressed_names() {
+        let responder = responder().with_gate(Arc::new(|| false));
+        assert!(!responder.allowed());
+
+        // "_kizuna._tcp.local" followed by a pointer back to "_tcp.local"
+        let mut packet = query("_kizuna._tcp.local", TYPE_PTR, CLASS_IN);
+        packet[5] = 2;
+        packet.extend_from_slice(&[3, b'f', b'o', b'o', 0xC0, 20]);
+        packet.extend_from_slice(&TYPE_SRV.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+        let parsed = parse_query(&packet).unwrap();
+        assert_eq!(parsed.questions[1].name, "foo._tcp.local");
+
+        // Pointer loops are rejected
+        assert!(read_name(&[0xC0, 0], 0).is_none());
+    }
+}
@@ -1,29 +1,47 @@
 use async_trait::async_trait;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::security::{Security, SecurityResult};
+use crate::security::clock::{self, timestamp_expired, DEFAULT_CLOCK_SKEW_TOLERANCE_SECS};
 use crate::security::identity::{PeerId, DeviceIdentity};
 use crate::security::trust::TrustManager;
-use crate::security::policy::{PolicyEngine, SecurityEvent, SecurityEventType};
+use crate::security::policy::{AnnounceState, PolicyEngine, SecurityEvent, SecurityEventType};
 use crate::discovery::{ServiceRecord, DiscoveryError};
 
+/// Capability carrying the announcing device's PeerId, in hex
+pub const IDENTITY_CAPABILITY: &str = "identity";
+/// Capability carrying an [`IdentityProof`] for [`IDENTITY_CAPABILITY`]
+pub const IDENTITY_PROOF_CAPABILITY: &str = "identity_proof";
+/// Seconds an [`IdentityProof`] stays valid
+pub const IDENTITY_PROOF_LIFETIME_SECS: u64 = 300;
+
 /// Identity proof for secure peer announcement
+///
+/// The proof signs the endpoint it was issued for, so a captured proof cannot
+/// be replayed to advertise other addresses under the same identity.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct IdentityProof {
     /// Peer ID of the announcing device
     pub peer_id: PeerId,
     /// Timestamp of the announcement
     pub timestamp: u64,
-    /// Signature of (peer_id + timestamp) using device private key
+    /// Port the announcing device advertised
+    #[serde(default)]
+    pub port: u16,
+    /// Addresses the announcing device advertised, sorted
+    #[serde(default)]
+    pub addresses: Vec<SocketAddr>,
+    /// Signature of (peer_id + timestamp + port + addresses) using device private key
     pub signature: Vec<u8>,
     /// Public key for verification
     pub public_key: Vec<u8>,
 }
 
 impl IdentityProof {
-    /// Create a new identity proof
-    pub fn new(identity: &DeviceIdentity) -> SecurityResult<Self> {
+    /// Create a new identity proof for the endpoint `port` and `addresses`
+    pub fn new(identity: &DeviceIdentity, port: u16, addresses: &[SocketAddr]) -> SecurityResult<Self> {
         use std::time::{SystemTime, UNIX_EPOCH};
         
         let peer_id = identity.derive_peer_id();
@@ -33,32 +51,43 @@ impl IdentityProof {
                 format!("System time error: {}", e)
             ))?
             .as_secs();
+        let mut addresses = addresses.to_vec();
+        addresses.sort();
+        addresses.dedup();
         
-        // Create message to sign: peer_id + timestamp
-        let mut message = Vec::new();
-        message.extend_from_slice(peer_id.fingerprint());
-        message.extend_from_slice(&timestamp.to_le_bytes());
-        
-        // Sign the message
-        let signature = identity.sign(&message);
-        let public_key = identity.public_key().as_bytes().to_vec();
-        
-        Ok(Self {
+        let mut proof = Self {
             peer_id,
             timestamp,
-            signature: signature.to_vec(),
-            public_key,
-        })
+            port,
+            addresses,
+            signature: Vec::new(),
+            public_key: identity.public_key().as_bytes().to_vec(),
+        };
+        proof.signature = identity.sign(&proof.signed_message()).to_vec();
+        Ok(proof)
+    }
+    
+    /// Bytes covered by the signature
+    fn signed_message(&self) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(self.peer_id.fingerprint());
+        message.extend_from_slice(&self.timestamp.to_le_bytes());
+        message.extend_from_slice(&self.port.to_le_bytes());
+        message.extend_from_slice(encode_addresses(&self.addresses).as_bytes());
+        message
+    }
+    
+    /// Whether `record` advertises only the endpoint this proof was issued for
+    pub fn covers(&self, record: &ServiceRecord) -> bool {
+        record.port == self.port
+            && record.addresses.iter().all(|addr| self.addresses.contains(addr))
     }
     
     /// Verify the identity proof
     pub fn verify(&self) -> SecurityResult<bool> {
         use ed25519_dalek::{Signature, VerifyingKey, Verifier};
         
-        // Reconstruct the message
-        let mut message = Vec::new();
-        message.extend_from_slice(self.peer_id.fingerprint());
-        message.extend_from_slice(&self.timestamp.to_le_bytes());
+        let message = self.signed_message();
         
         // Parse public key
         let public_key_bytes: [u8; 32] = self.public_key
@@ -91,16 +120,18 @@ impl IdentityProof {
     }
     
     /// Check if the proof has expired (older than 5 minutes)
+    ///
+    /// Allows the default clock skew between the announcing peer and us.
     pub fn is_expired(&self) -> bool {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-        
-        // Proof expires after 5 minutes
-        now - self.timestamp > 300
+        self.is_expired_with_tolerance(DEFAULT_CLOCK_SKEW_TOLERANCE_SECS)
+    }
+    
+    /// Check if the proof has expired, allowing `tolerance_secs` of clock skew
+    ///
+    /// Proofs dated further than the tolerance in the future are treated as
+    /// expired as well.
+    pub fn is_expired_with_tolerance(&self, tolerance_secs: u64) -> bool {
+        timestamp_expired(self.timestamp, IDENTITY_PROOF_LIFETIME_SECS, tolerance_secs, clock::unix_secs())
     }
     
     /// Verify that the peer ID matches the public key
@@ -122,6 +153,94 @@ impl IdentityProof {
         let derived_peer_id = PeerId::from_public_key(&verifying_key);
         Ok(derived_peer_id == self.peer_id)
     }
+
+    /// Encode as a capability value:
+    /// `<timestamp>.<public key>.<signature>.<port>.<addresses>`
+    ///
+    /// The peer ID is carried separately in [`IDENTITY_CAPABILITY`]. The
+    /// addresses are hex encoded, so the value fits in a single mDNS TXT
+    /// entry and contains none of the UDP payload separators.
+    pub fn to_capability(&self) -> String {
+        format!(
+            "{}.{}.{}.{}.{}",
+            self.timestamp,
+            hex::encode(&self.public_key),
+            hex::encode(&self.signature),
+            self.port,
+            hex::encode(encode_addresses(&self.addresses)),
+        )
+    }
+
+    /// Decode a capability value produced by [`IdentityProof::to_capability`]
+    pub fn from_capability(peer_id: PeerId, value: &str) -> Option<Self> {
+        let mut parts = value.split('.');
+        let timestamp = parts.next()?.parse().ok()?;
+        let public_key = hex::decode(parts.next()?).ok()?;
+        let signature = hex::decode(parts.next()?).ok()?;
+        let port = parts.next()?.parse().ok()?;
+        let addresses = String::from_utf8(hex::decode(parts.next()?).ok()?).ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        let addresses = addresses
+            .split_whitespace()
+            .map(|addr| addr.parse().ok())
+            .collect::<Option<Vec<SocketAddr>>>()?;
+
+        Some(Self {
+            peer_id,
+            timestamp,
+            port,
+            addresses,
+            signature,
+            public_key,
+        })
+    }
+}
+
+/// Canonical form of the signed addresses
+fn encode_addresses(addresses: &[SocketAddr]) -> String {
+    addresses.iter().map(SocketAddr::to_string).collect::<Vec<_>>().join(" ")
+}
+
+/// Advertise `identity` in a set of discovery capabilities
+///
+/// Adds the PeerId and a fresh proof bound to `port` and `addresses`, which
+/// must be exactly what receivers will record for the announcement. Proofs
+/// expire, so this should be called again for each announcement rather than
+/// once at startup.
+pub fn attach_identity(
+    capabilities: &mut std::collections::HashMap<String, String>,
+    identity: &DeviceIdentity,
+    port: u16,
+    addresses: &[SocketAddr],
+) -> SecurityResult<()> {
+    let proof = IdentityProof::new(identity, port, addresses)?;
+    capabilities.insert(IDENTITY_CAPABILITY.to_string(), proof.peer_id.to_hex());
+    capabilities.insert(IDENTITY_PROOF_CAPABILITY.to_string(), proof.to_capability());
+    Ok(())
+}
+
+/// Check the identity a discovered record claims and mark it verified
+///
+/// A record is verified when its proof is signed by the key its PeerId is
+/// derived from, has not expired and covers the record's port and addresses.
+/// Anything else, including a verified flag set by whoever produced the
+/// record, is cleared.
+pub fn verify_record_identity(record: &mut ServiceRecord) -> bool {
+    let proof = record
+        .get_capability(IDENTITY_CAPABILITY)
+        .and_then(|id| PeerId::from_hex(id).ok())
+        .zip(record.get_capability(IDENTITY_PROOF_CAPABILITY))
+        .and_then(|(peer_id, value)| IdentityProof::from_capability(peer_id, value));
+
+    record.identity_verified = proof.is_some_and(|proof| {
+        !proof.is_expired()
+            && proof.covers(record)
+            && proof.verify_peer_id().unwrap_or(false)
+            && proof.verify().unwrap_or(false)
+    });
+    record.identity_verified
 }
 
 /// Enhanced service record with identity proof
@@ -144,6 +263,11 @@ impl SecureServiceRecord {
     
     /// Verify the identity proof
     pub fn verify(&self) -> SecurityResult<bool> {
+        self.verify_with_tolerance(DEFAULT_CLOCK_SKEW_TOLERANCE_SECS)
+    }
+    
+    /// Verify the identity proof, allowing `tolerance_secs` of clock skew
+    pub fn verify_with_tolerance(&self, tolerance_secs: u64) -> SecurityResult<bool> {
         // Verify the signature
         if !self.identity_proof.verify()? {
             return Ok(false);
@@ -159,8 +283,13 @@ impl SecureServiceRecord {
             return Ok(false);
         }
         
+        // The proof must have been issued for the advertised endpoint
+        if !self.identity_proof.covers(&self.service_record) {
+            return Ok(false);
+        }
+        
         // Check if proof has expired
-        if self.identity_proof.is_expired() {
+        if self.identity_proof.is_expired_with_tolerance(tolerance_secs) {
             return Ok(false);
         }
         
@@ -195,10 +324,10 @@ impl DiscoverySecurityHooks {
         }
     }
     
-    /// Create identity proof for announcement
-    pub async fn create_identity_proof(&self) -> SecurityResult<IdentityProof> {
+    /// Create identity proof for announcing `port` and `addresses`
+    pub async fn create_identity_proof(&self, port: u16, addresses: &[SocketAddr]) -> SecurityResult<IdentityProof> {
         let identity = self.security.get_device_identity().await?;
-        IdentityProof::new(&identity)
+        IdentityProof::new(&identity, port, addresses)
     }
     
     /// Verify peer identity during discovery
@@ -230,7 +359,7 @@ impl DiscoverySecurityHooks {
         }
         
         // Check if proof has expired
-        if identity_proof.is_expired() {
+        if identity_proof.is_expired_with_tolerance(self.skew_tolerance().await) {
             return Ok(false);
         }
         
@@ -284,12 +413,20 @@ impl DiscoverySecurityHooks {
         Ok(true)
     }
     
+    /// Check whether stealth mode or the announce schedule allows announcing now
+    pub async fn announce_state(&self) -> SecurityResult<AnnounceState> {
+        let policy = self.policy_engine.get_policy().await?;
+        Ok(policy.effective_visibility().state())
+    }
+    
     /// Create secure announcement with identity proof
     pub async fn create_secure_announcement(
         &self,
         service_record: ServiceRecord,
     ) -> SecurityResult<SecureServiceRecord> {
-        let identity_proof = self.create_identity_proof().await?;
+        let identity_proof = self
+            .create_identity_proof(service_record.port, &service_record.addresses)
+            .await?;
         Ok(SecureServiceRecord::new(service_record, identity_proof))
     }
     
@@ -299,7 +436,7 @@ impl DiscoverySecurityHooks {
         secure_record: &SecureServiceRecord,
     ) -> SecurityResult<bool> {
         // Verify the record structure
-        if !secure_record.verify()? {
+        if !secure_record.verify_with_tolerance(self.skew_tolerance().await)? {
             return Ok(false);
         }
         
@@ -318,13 +455,23 @@ impl DiscoverySecurityHooks {
     
     /// Clear expired proofs from cache
     pub async fn cleanup_expired_proofs(&self) -> usize {
+        let tolerance = self.skew_tolerance().await;
         let mut verified = self.verified_peers.write().await;
         let initial_count = verified.len();
         
-        verified.retain(|_, proof| !proof.is_expired());
+        verified.retain(|_, proof| !proof.is_expired_with_tolerance(tolerance));
         
         initial_count - verified.len()
     }
+    
+    /// Clock skew allowed on identity proofs by the security policy
+    async fn skew_tolerance(&self) -> u64 {
+        self.policy_engine
+            .get_policy()
+            .await
+            .map(|policy| policy.clock_skew.tolerance_secs)
+            .unwrap_or(DEFAULT_CLOCK_SKEW_TOLERANCE_SECS)
+    }
 }
 
 /// Convert DiscoveryError to SecurityResult
@@ -345,7 +492,7 @@ mod tests {
         let identity = DeviceIdentity::generate().unwrap();
         
         // Create identity proof
-        let proof = IdentityProof::new(&identity).unwrap();
+        let proof = IdentityProof::new(&identity, 8080, &[]).unwrap();
         
         // Verify the proof
         assert!(proof.verify().unwrap());
@@ -363,7 +510,7 @@ mod tests {
         let identity = DeviceIdentity::generate().unwrap();
         
         // Create identity proof
-        let mut proof = IdentityProof::new(&identity).unwrap();
+        let mut proof = IdentityProof::new(&identity, 8080, &[]).unwrap();
         
         // Tamper with the signature
         proof.signature[0] ^= 0xFF;
@@ -372,6 +519,22 @@ mod tests {
         assert!(!proof.verify().unwrap());
     }
     
+    #[test]
+    fn test_identity_proof_expiry_allows_clock_skew() {
+        let identity = DeviceIdentity::generate().unwrap();
+        let mut proof = IdentityProof::new(&identity, 8080, &[]).unwrap();
+        let issued = proof.timestamp;
+        
+        // Announcer's clock is six minutes behind ours
+        proof.timestamp = issued - 360;
+        assert!(!proof.is_expired_with_tolerance(120));
+        assert!(proof.is_expired_with_tolerance(0));
+        
+        // A proof from far in the future is not valid for longer
+        proof.timestamp = issued + 600;
+        assert!(proof.is_expired());
+    }
+    
     #[test]
     fn test_secure_service_record_verification() {
         use std::net::{IpAddr, Ipv4Addr, SocketAddr};
@@ -390,12 +553,46 @@ mod tests {
         service_record.add_capability("clipboard".to_string(), "enabled".to_string());
         
         // Create identity proof
-        let identity_proof = IdentityProof::new(&identity).unwrap();
+        let identity_proof = IdentityProof::new(&identity, service_record.port, &service_record.addresses).unwrap();
         
         // Create secure service record
-        let secure_record = SecureServiceRecord::new(service_record, identity_proof);
+        let mut secure_record = SecureServiceRecord::new(service_record, identity_proof);
         
         // Verify the secure record
         assert!(secure_record.verify().unwrap());
+        
+        // The proof does not vouch for an address it was not issued for
+        secure_record.service_record.add_address(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 9)), 8080));
+        assert!(!secure_record.verify().unwrap());
+    }
+
+    #[test]
+    fn test_record_identity_verification() {
+        let identity = DeviceIdentity::generate().unwrap();
+        let mut record = ServiceRecord::new("kizuna-1a2b".to_string(), "Laptop".to_string(), 8080);
+        record.add_address("192.168.1.10:8080".parse().unwrap());
+        attach_identity(&mut record.capabilities, &identity, record.port, &record.addresses).unwrap();
+
+        assert!(verify_record_identity(&mut record));
+        assert_eq!(record.identity(), Some(identity.derive_peer_id().to_hex().as_str()));
+
+        // Replaying the proof with another address or port fails
+        let mut replayed = record.clone();
+        replayed.addresses = vec!["203.0.113.7:8080".parse().unwrap()];
+        assert!(!verify_record_identity(&mut replayed));
+        let mut replayed = record.clone();
+        replayed.port = 9090;
+        assert!(!verify_record_identity(&mut replayed));
+
+        // Claiming another device's identity with our own proof fails
+        let other = DeviceIdentity::generate().unwrap();
+        record.add_capability(IDENTITY_CAPABILITY.to_string(), other.derive_peer_id().to_hex());
+        assert!(!verify_record_identity(&mut record));
+        assert!(!record.identity_verified);
+
+        // So does a record that only says it is verified
+        let mut claimed = ServiceRecord::new("kizuna-3c4d".to_string(), "Phone".to_string(), 8080);
+        claimed.identity_verified = true;
+        assert!(!verify_record_identity(&mut claimed));
     }
 }
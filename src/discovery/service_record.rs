@@ -3,6 +3,7 @@ use std::net::SocketAddr;
 use std::time::{Duration, SystemTime};
 use std::fmt;
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
+use crate::transport::wire::{WIRE_VERSION_MAJOR, WIRE_VERSION_MINOR};
 
 // Helper functions for SystemTime serialization
 fn serialize_system_time<S>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
@@ -24,6 +25,26 @@ where
     Ok(UNIX_EPOCH + Duration::from_secs(secs))
 }
 
+/// Capability carrying the Kizuna release a peer runs
+///
+/// The older `version` capability describes each discovery protocol, not the
+/// software, so it cannot be used to detect version skew.
+pub const SOFTWARE_VERSION_CAPABILITY: &str = "kizuna_version";
+
+/// Capability carrying the wire protocol version a peer speaks, as "major.minor"
+pub const PROTOCOL_VERSION_CAPABILITY: &str = "kizuna_protocol";
+
+/// Capability entries advertising this build's release and wire protocol
+pub fn version_capabilities() -> [(String, String); 2] {
+    [
+        (SOFTWARE_VERSION_CAPABILITY.to_string(), env!("CARGO_PKG_VERSION").to_string()),
+        (
+            PROTOCOL_VERSION_CAPABILITY.to_string(),
+            format!("{}.{}", WIRE_VERSION_MAJOR, WIRE_VERSION_MINOR),
+        ),
+    ]
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ServiceRecord {
     pub peer_id: String,
@@ -34,6 +55,10 @@ pub struct ServiceRecord {
     pub capabilities: HashMap<String, String>,
     #[serde(serialize_with = "serialize_system_time", deserialize_with = "deserialize_system_time")]
     pub last_seen: SystemTime,
+    /// Whether the identity capability was backed by a valid proof; recomputed
+    /// by `verify_record_identity` for every discovered record
+    #[serde(default)]
+    pub identity_verified: bool,
 }
 
 impl ServiceRecord {
@@ -46,6 +71,7 @@ impl ServiceRecord {
             discovery_method: String::new(),
             capabilities: HashMap::new(),
             last_seen: SystemTime::now(),
+            identity_verified: false,
         }
     }
 
@@ -59,6 +85,17 @@ impl ServiceRecord {
         self.capabilities.insert(key, value);
     }
 
+    /// Kizuna release the peer advertises, if any
+    pub fn software_version(&self) -> Option<&str> {
+        self.capabilities.get(SOFTWARE_VERSION_CAPABILITY).map(String::as_str)
+    }
+
+    /// Wire protocol version the peer advertises, if any
+    pub fn protocol_version(&self) -> Option<(u8, u8)> {
+        let (major, minor) = self.capabilities.get(PROTOCOL_VERSION_CAPABILITY)?.split_once('.')?;
+        Some((major.parse().ok()?, minor.parse().ok()?))
+    }
+
     pub fn is_expired(&self, timeout: Duration) -> bool {
         self.last_seen.elapsed().unwrap_or(Duration::MAX) > timeout
     }
@@ -113,17 +150,49 @@ impl ServiceRecord {
         self.capabilities.clear();
     }
 
-    /// Check if this record represents the same peer (by peer_id)
+    /// Cryptographic PeerId the announcing device claims, if it sent one
+    pub fn identity(&self) -> Option<&str> {
+        self.get_capability(crate::discovery::security_integration::IDENTITY_CAPABILITY)
+            .map(String::as_str)
+    }
+
+    /// Key to deduplicate records by: the claimed identity, else the peer_id
+    ///
+    /// Strategies generate their own peer_ids, so the same device found via
+    /// mDNS and UDP only shares its identity.
+    pub fn identity_key(&self) -> &str {
+        self.identity().unwrap_or(&self.peer_id)
+    }
+
+    /// Check if this record represents the same peer
+    ///
+    /// Records that both carry an identity are compared by it, so two devices
+    /// cannot be merged just because a strategy reused a peer_id.
     pub fn is_same_peer(&self, other: &ServiceRecord) -> bool {
-        self.peer_id == other.peer_id
+        match (self.identity(), other.identity()) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.peer_id == other.peer_id,
+        }
     }
 
     /// Merge another service record into this one (useful for combining discovery results)
-    pub fn merge(&mut self, other: ServiceRecord) {
+    ///
+    /// When only one record has a verified identity, it is kept as it is and
+    /// the other is dropped: an unverified record claiming the same identity
+    /// may come from anyone, so none of its addresses or capabilities are
+    /// trusted.
+    pub fn merge(&mut self, mut other: ServiceRecord) {
         if !self.is_same_peer(&other) {
             return; // Only merge records from the same peer
         }
 
+        if other.identity_verified && !self.identity_verified {
+            std::mem::swap(self, &mut other);
+        }
+        if self.identity_verified && !other.identity_verified {
+            return;
+        }
+
         // Update name if the other record has a more descriptive name
         if self.name == "Unknown" && other.name != "Unknown" {
             self.name = other.name;
@@ -394,6 +463,41 @@ mod tests {
         assert_eq!(record1.name, original_name);
     }
 
+    #[test]
+    fn test_merge_by_identity() {
+        let mut mdns = ServiceRecord::new("kizuna-1a2b".to_string(), "Laptop".to_string(), 8080);
+        mdns.add_address(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 100)), 8080));
+        mdns.add_capability("identity".to_string(), "ab12".to_string());
+        mdns.add_capability("version".to_string(), "0.1.0".to_string());
+        mdns.identity_verified = true;
+
+        let mut udp = ServiceRecord::new("kizuna-3c4d".to_string(), "Spoofed".to_string(), 8080);
+        udp.add_address(SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 8080));
+        udp.add_capability("identity".to_string(), "ab12".to_string());
+        udp.add_capability("version".to_string(), "9.9.9".to_string());
+        udp.add_capability("protocol".to_string(), "udp".to_string());
+        assert!(udp.is_same_peer(&mdns));
+        assert_eq!(udp.identity_key(), "ab12");
+
+        // The verified record wins whichever side it is merged from, and the
+        // unverified claim adds none of its addresses or capabilities
+        let expected = mdns.clone();
+        udp.merge(mdns);
+        assert_eq!(udp, expected);
+
+        let mut verified = expected.clone();
+        let mut spoofed = ServiceRecord::new("kizuna-3c4d".to_string(), "Spoofed".to_string(), 8080);
+        spoofed.add_address(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)), 8080));
+        spoofed.add_capability("identity".to_string(), "ab12".to_string());
+        verified.merge(spoofed);
+        assert_eq!(verified, expected);
+
+        // A shared peer_id does not merge records claiming different identities
+        let mut other = ServiceRecord::new("kizuna-1a2b".to_string(), "Other".to_string(), 8080);
+        other.add_capability("identity".to_string(), "cd34".to_string());
+        assert!(!other.is_same_peer(&udp));
+    }
+
     #[test]
     fn test_network_string_serialization() {
         let mut record = ServiceRecord::new("peer-123".to_string(), "Test Device".to_string(), 8080);
@@ -424,6 +528,18 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_protocol_version_capability() {
+        let mut record = ServiceRecord::new("peer-123".to_string(), "Test Device".to_string(), 8080);
+        assert_eq!(record.protocol_version(), None);
+
+        record.capabilities.extend(version_capabilities());
+        assert_eq!(record.protocol_version(), Some((WIRE_VERSION_MAJOR, WIRE_VERSION_MINOR)));
+
+        record.add_capability(PROTOCOL_VERSION_CAPABILITY.to_string(), "two".to_string());
+        assert_eq!(record.protocol_version(), None);
+    }
+
     #[test]
     fn test_peer_conversion() {
         let peer = crate::discovery::Peer {
@@ -0,0 +1,326 @@
+//! Third-party mDNS services
+//!
+//! Applications embedding Kizuna can advertise their own DNS-SD services,
+//! such as a web UI under `_http._tcp`, through the announce responder
+//! instead of running a second mDNS stack next to it. A
+//! [`ServiceRegistration`] names a service type, an instance and a port on
+//! this device plus TXT entries; the responder answers PTR queries for the
+//! type and SRV/TXT queries for the instance, pointing at the same host
+//! record Kizuna advertises itself with.
+//!
+//! Conflicts are caught twice. Registering an instance name that is already
+//! registered here, or Kizuna's own service type, fails straight away. While
+//! answering, an SRV record for a registered instance that points at another
+//! host means someone else on the network owns the name: the registration is
+//! marked conflicted and no longer answered, so the application can register
+//! again under a new name.
+//!
+//! The registry belongs to [`KizunaDiscovery`](super::KizunaDiscovery) and is
+//! cleared when discovery shuts down.
+
+use crate::discovery::DiscoveryError;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// Service type Kizuna advertises itself under
+pub const KIZUNA_SERVICE_TYPE: &str = "_kizuna._tcp";
+
+/// Longest service name between the underscore and the protocol (RFC 6335)
+const MAX_SERVICE_NAME_LEN: usize = 15;
+
+/// Longest instance name, one DNS label
+const MAX_INSTANCE_NAME_LEN: usize = 63;
+
+/// Largest TXT record, so answers still fit in one packet
+const MAX_TXT_LEN: usize = 1300;
+
+/// An application service to advertise over mDNS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceRegistration {
+    /// Service type such as `_http._tcp`, without `.local`
+    pub service_type: String,
+    /// Instance name shown in service browsers
+    pub instance_name: String,
+    /// Port the service listens on on this device
+    pub port: u16,
+    pub txt: BTreeMap<String, String>,
+}
+
+impl ServiceRegistration {
+    pub fn new(service_type: impl Into<String>, instance_name: impl Into<String>, port: u16) -> Self {
+        Self {
+            service_type: service_type.into(),
+            instance_name: instance_name.into(),
+            port,
+            txt: BTreeMap::new(),
+        }
+    }
+
+    /// Add a TXT entry
+    pub fn with_txt(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.txt.insert(key.into(), value.into());
+        self
+    }
+
+    /// Check the registration can be advertised as given
+    pub fn validate(&self) -> Result<(), DiscoveryError> {
+        let invalid = |reason: String| Err(DiscoveryError::InvalidServiceRecord { reason });
+
+        let Some((name, protocol)) = self.service_type.split_once('.') else {
+            return invalid(format!("Service type {} is not of the form _name._tcp", self.service_type));
+        };
+        let name = name.strip_prefix('_').unwrap_or_default();
+        if !matches!(protocol, "_tcp" | "_udp")
+            || name.is_empty()
+            || name.len() > MAX_SERVICE_NAME_LEN
+            || name.starts_with('-')
+            || name.ends_with('-')
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return invalid(format!("Service type {} is not of the form _name._tcp", self.service_type));
+        }
+        if self.service_type.eq_ignore_ascii_case(KIZUNA_SERVICE_TYPE) {
+            return Err(DiscoveryError::ServiceConflict {
+                service: self.service_type.clone(),
+                reason: "reserved for Kizuna itself".to_string(),
+            });
+        }
+
+        if self.instance_name.is_empty()
+            || self.instance_name.len() > MAX_INSTANCE_NAME_LEN
+            || self.instance_name.contains('.')
+            || self.instance_name.chars().any(char::is_control)
+        {
+            return invalid(format!(
+                "Instance name {:?} must be 1 to {} bytes without dots",
+                self.instance_name, MAX_INSTANCE_NAME_LEN
+            ));
+        }
+        if self.port == 0 {
+            return invalid("Service port must not be 0".to_string());
+        }
+
+        let mut total = 0;
+        for entry in self.txt_entries() {
+            let key = entry.split('=').next().unwrap_or_default();
+            if key.is_empty() || !key.bytes().all(|b| (0x20..0x7F).contains(&b)) {
+                return invalid(format!("TXT key {:?} must be printable ASCII", key));
+            }
+            if entry.len() > 255 {
+                return invalid(format!("TXT entry for {} is longer than 255 bytes", key));
+            }
+            total += entry.len() + 1;
+        }
+        if total > MAX_TXT_LEN {
+            return invalid(format!("TXT record is {} bytes, more than {}", total, MAX_TXT_LEN));
+        }
+        Ok(())
+    }
+
+    /// Name browsers query for, e.g. `_http._tcp.local`
+    pub fn service_name(&self) -> String {
+        format!("{}.local", self.service_type)
+    }
+
+    /// Full name of this instance, e.g. `Web UI._http._tcp.local`
+    pub fn instance_fqdn(&self) -> String {
+        format!("{}.{}.local", self.instance_name, self.service_type)
+    }
+
+    /// TXT entries as `key=value` strings
+    pub(crate) fn txt_entries(&self) -> Vec<String> {
+        self.txt.iter().map(|(key, value)| format!("{}={}", key, value)).collect()
+    }
+}
+
+/// Handle for a registered service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ServiceId(u64);
+
+#[derive(Debug)]
+struct RegisteredService {
+    registration: ServiceRegistration,
+    /// Another host answers for the same instance name
+    conflicted: bool,
+}
+
+#[derive(Debug, Default)]
+struct RegistryState {
+    next_id: u64,
+    services: BTreeMap<ServiceId, RegisteredService>,
+}
+
+/// Services the announce responder advertises besides Kizuna's own
+///
+/// Clones share the same registrations, so a running responder picks up
+/// changes straight away.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceRegistry {
+    state: Arc<RwLock<RegistryState>>,
+}
+
+impl ServiceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start advertising a service
+    pub fn register(&self, registration: ServiceRegistration) -> Result<ServiceId, DiscoveryError> {
+        registration.validate()?;
+
+        let mut state = self.state.write().unwrap();
+        let fqdn = registration.instance_fqdn();
+        if state
+            .services
+            .values()
+            .any(|service| service.registration.instance_fqdn().to_lowercase() == fqdn.to_lowercase())
+        {
+            return Err(DiscoveryError::ServiceConflict {
+                service: fqdn,
+                reason: "already registered on this device".to_string(),
+            });
+        }
+
+        state.next_id += 1;
+        let id = ServiceId(state.next_id);
+        log::info!("Advertising {} on port {} over mDNS", fqdn, registration.port);
+        state.services.insert(
+            id,
+            RegisteredService {
+                registration,
+                conflicted: false,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Stop advertising a service; returns false if it was not registered
+    pub fn unregister(&self, id: ServiceId) -> bool {
+        self.state.write().unwrap().services.remove(&id).is_some()
+    }
+
+    /// Stop advertising every service
+    pub fn clear(&self) {
+        self.state.write().unwrap().services.clear();
+    }
+
+    pub fn get(&self, id: ServiceId) -> Option<ServiceRegistration> {
+        self.state
+            .read()
+            .unwrap()
+            .services
+            .get(&id)
+            .map(|service| service.registration.clone())
+    }
+
+    /// Whether another host claimed the service's instance name
+    pub fn is_conflicted(&self, id: ServiceId) -> bool {
+        self.state
+            .read()
+            .unwrap()
+            .services
+            .get(&id)
+            .is_some_and(|service| service.conflicted)
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().services.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Registrations that are still answered
+    pub(crate) fn active(&self) -> Vec<ServiceRegistration> {
+        self.state
+            .read()
+            .unwrap()
+            .services
+            .values()
+            .filter(|service| !service.conflicted)
+            .map(|service| service.registration.clone())
+            .collect()
+    }
+
+    /// Mark services another host answers for
+    ///
+    /// `answers` are SRV records seen on the network as (instance, target
+    /// host) pairs with lowercased names; records pointing at `own_host` are
+    /// this device's own answers coming back. Returns the services newly
+    /// marked conflicted.
+    pub(crate) fn note_answers(&self, answers: &[(String, String)], own_host: &str) -> Vec<ServiceId> {
+        let own_host = own_host.to_lowercase();
+        let mut state = self.state.write().unwrap();
+        let mut conflicted = Vec::new();
+        for (id, service) in state.services.iter_mut().filter(|(_, service)| !service.conflicted) {
+            let fqdn = service.registration.instance_fqdn();
+            let lowercase = fqdn.to_lowercase();
+            let claimed = answers
+                .iter()
+                .any(|(name, target)| *name == lowercase && *target != own_host);
+            if claimed {
+                log::warn!("Another host on the network advertises {}; no longer answering for it", fqdn);
+                service.conflicted = true;
+                conflicted.push(*id);
+            }
+        }
+        conflicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registration_validation() {
+        let web = ServiceRegistration::new("_http._tcp", "Web UI", 8080).with_txt("path", "/");
+        assert!(web.validate().is_ok());
+        assert_eq!(web.instance_fqdn(), "Web UI._http._tcp.local");
+
+        for bad in [
+            ServiceRegistration::new("http._tcp", "Web UI", 8080),
+            ServiceRegistration::new("_http._sctp", "Web UI", 8080),
+            ServiceRegistration::new("_a-very-long-service._tcp", "Web UI", 8080),
+            ServiceRegistration::new("_http._tcp", "web.ui", 8080),
+            ServiceRegistration::new("_http._tcp", "Web UI", 0),
+            ServiceRegistration::new("_http._tcp", "Web UI", 8080).with_txt("", "x"),
+            ServiceRegistration::new("_http._tcp", "Web UI", 8080).with_txt("blob", "x".repeat(300)),
+        ] {
+            assert!(matches!(bad.validate(), Err(DiscoveryError::InvalidServiceRecord { .. })), "{:?}", bad);
+        }
+        assert!(matches!(
+            ServiceRegistration::new("_Kizuna._tcp", "Mine", 8080).validate(),
+            Err(DiscoveryError::ServiceConflict { .. })
+        ));
+    }
+
+    #[test]
+    fn test_local_and_network_conflicts() {
+        let registry = ServiceRegistry::new();
+        let web = registry
+            .register(ServiceRegistration::new("_http._tcp", "Web UI", 8080))
+            .unwrap();
+        let err = registry
+            .register(ServiceRegistration::new("_http._tcp", "web ui", 9090))
+            .unwrap_err();
+        assert!(matches!(err, DiscoveryError::ServiceConflict { .. }));
+        let ssh = registry.register(ServiceRegistration::new("_ssh._tcp", "Web UI", 22)).unwrap();
+
+        // Our own answers looping back are not a conflict
+        let echoed = vec![("web ui._http._tcp.local".to_string(), "kizuna-1234.local".to_string())];
+        assert!(registry.note_answers(&echoed, "kizuna-1234.local").is_empty());
+
+        let foreign = vec![("web ui._http._tcp.local".to_string(), "printer.local".to_string())];
+        assert_eq!(registry.note_answers(&foreign, "kizuna-1234.local"), vec![web]);
+        assert!(registry.is_conflicted(web));
+        assert!(!registry.is_conflicted(ssh));
+        assert_eq!(registry.active().len(), 1);
+
+        assert!(registry.unregister(web));
+        assert!(!registry.unregister(web));
+        registry.clear();
+        assert!(registry.is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::discovery::service_record::version_capabilities;
 use crate::discovery::{Discovery, DiscoveryError, ServiceRecord};
 use async_trait::async_trait;
 use btleplug::api::{Central, Manager as _, Peripheral, ScanFilter};
@@ -35,7 +36,7 @@ impl BluetoothDiscovery {
             device_name: "Kizuna Device".to_string(),
             port: 41337,
             service_uuid: Uuid::parse_str("6ba7b810-9dad-11d1-80b4-00c04fd430c8").unwrap(),
-            capabilities: HashMap::new(),
+            capabilities: HashMap::from(version_capabilities()),
             manager: Arc::new(RwLock::new(None)),
             adapter: Arc::new(RwLock::new(None)),
             is_advertising: Arc::new(RwLock::new(false)),
@@ -45,6 +46,7 @@ impl BluetoothDiscovery {
     pub fn with_config(peer_id: String, device_name: String, port: u16) -> Self {
         let mut capabilities = HashMap::new();
         capabilities.insert("version".to_string(), "0.1.0".to_string());
+        capabilities.extend(version_capabilities());
         
         Self {
             peer_id,
@@ -1,3 +1,4 @@
+use crate::discovery::service_record::version_capabilities;
 use crate::discovery::{Discovery, DiscoveryError, ServiceRecord};
 use async_trait::async_trait;
 use std::collections::HashMap;
@@ -26,7 +27,7 @@ impl MdnsDiscovery {
             device_name: "Kizuna Device".to_string(),
             port: 41337,
             version: "0.1.0".to_string(),
-            capabilities: HashMap::new(),
+            capabilities: HashMap::from(version_capabilities()),
             is_announcing: Arc::new(RwLock::new(false)),
         }
     }
@@ -34,6 +35,7 @@ impl MdnsDiscovery {
     pub fn with_config(peer_id: String, device_name: String, port: u16) -> Self {
         let mut capabilities = HashMap::new();
         capabilities.insert("version".to_string(), "0.1.0".to_string());
+        capabilities.extend(version_capabilities());
         
         Self {
             peer_id,
@@ -292,16 +294,9 @@ impl Discovery for MdnsDiscovery {
             ));
         }
 
-        // For now, we'll mark as announcing but not actually implement the responder
-        // since the mdns crate doesn't seem to have a simple responder API
-        // This would need a more sophisticated implementation or different crate
+        // The mdns crate only browses; queries for this device are answered
+        // by the announce responder (see discovery::responder)
         *is_announcing = true;
-
-        // TODO: Implement actual mDNS service announcement
-        // This would require either:
-        // 1. Using a different mDNS crate that supports service announcement
-        // 2. Implementing our own mDNS responder
-        // 3. Using system-level mDNS services (like Avahi on Linux)
         
         Ok(())
     }
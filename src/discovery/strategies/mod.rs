@@ -2,3 +2,4 @@ pub mod mdns;
 pub mod udp;
 pub mod tcp;
 pub mod bluetooth;
+pub mod rendezvous;
@@ -0,0 +1,306 @@
+use crate::discovery::service_record::version_capabilities;
+use crate::discovery::{Discovery, DiscoveryError, ServiceRecord};
+use crate::transport::proxy::{self, ProxyConfig};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::RwLock;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const PROTOCOL_VERSION: &str = "1.0.0";
+const KIZUNA_REGISTER: &str = "KIZUNA_REGISTER";
+const KIZUNA_UNREGISTER: &str = "KIZUNA_UNREGISTER";
+const KIZUNA_LOOKUP: &str = "KIZUNA_LOOKUP";
+const KIZUNA_PEER_RECORD: &str = "KIZUNA_PEER_RECORD";
+const KIZUNA_OK: &str = "KIZUNA_OK";
+const KIZUNA_END: &str = "KIZUNA_END";
+
+/// Capability naming the rendezvous server a peer was learned from
+pub const RENDEZVOUS_SERVER_CAPABILITY: &str = "rendezvous_server";
+
+/// Discovery through rendezvous servers on relay infrastructure
+///
+/// Used when the local network blocks multicast and broadcast, e.g. guest
+/// Wi-Fi with client isolation. Peers register a service record with each
+/// configured server and look up the records other peers registered there.
+pub struct RendezvousDiscovery {
+    peer_id: String,
+    device_name: String,
+    port: u16,
+    servers: Vec<String>,
+    capabilities: HashMap<String, String>,
+    registration_ttl: Duration,
+    registered: Arc<RwLock<Vec<String>>>,
+    proxy: Option<ProxyConfig>,
+}
+
+impl RendezvousDiscovery {
+    pub fn new(servers: Vec<String>) -> Self {
+        Self::with_config(
+            format!("kizuna-{}", &uuid::Uuid::new_v4().to_string()[..8]),
+            "Kizuna Device".to_string(),
+            41337,
+            servers,
+        )
+    }
+
+    pub fn with_config(peer_id: String, device_name: String, port: u16, servers: Vec<String>) -> Self {
+        let mut capabilities = HashMap::new();
+        capabilities.insert("version".to_string(), PROTOCOL_VERSION.to_string());
+        capabilities.insert("protocol".to_string(), "rendezvous".to_string());
+        capabilities.extend(version_capabilities());
+
+        Self {
+            peer_id,
+            device_name,
+            port,
+            servers,
+            capabilities,
+            registration_ttl: Duration::from_secs(300),
+            registered: Arc::new(RwLock::new(Vec::new())),
+            proxy: None,
+        }
+    }
+
+    /// Set how long servers keep our registration without a refresh
+    pub fn set_registration_ttl(&mut self, ttl: Duration) {
+        self.registration_ttl = ttl;
+    }
+
+    /// Reach rendezvous servers through a proxy
+    pub fn set_proxy(&mut self, proxy: Option<ProxyConfig>) {
+        self.proxy = proxy;
+    }
+
+    /// Configured rendezvous servers
+    pub fn servers(&self) -> &[String] {
+        &self.servers
+    }
+
+    fn own_record(&self) -> ServiceRecord {
+        let mut record = ServiceRecord::new(self.peer_id.clone(), self.device_name.clone(), self.port);
+        record.set_discovery_method(self.strategy_name().to_string());
+        for (key, value) in &self.capabilities {
+            record.add_capability(key.clone(), value.clone());
+        }
+        record
+    }
+
+    /// Send one request line and collect response lines up to a terminator
+    async fn request(&self, server: &str, line: String, terminator: &str) -> Result<Vec<String>, DiscoveryError> {
+        let exchange = async {
+            let (host, port) = proxy::split_host_port(server)
+                .ok_or_else(|| DiscoveryError::Network(format!("{}: expected host:port", server)))?;
+            let stream = proxy::connect_tcp(self.proxy.as_ref(), host, port)
+                .await
+                .map_err(|e| DiscoveryError::Network(format!("{}: {}", server, e)))?;
+            let (reader, mut writer) = stream.into_split();
+
+            writer
+                .write_all(format!("{}\n", line).as_bytes())
+                .await
+                .map_err(|e| DiscoveryError::Network(e.to_string()))?;
+            writer.flush().await.map_err(|e| DiscoveryError::Network(e.to_string()))?;
+
+            let mut lines = BufReader::new(reader).lines();
+            let mut response = Vec::new();
+            while let Some(line) = lines.next_line().await.map_err(|e| DiscoveryError::Network(e.to_string()))? {
+                let line = line.trim().to_string();
+                if line == terminator {
+                    return Ok(response);
+                }
+                response.push(line);
+            }
+
+            Err(DiscoveryError::ProtocolError {
+                strategy: "rendezvous".to_string(),
+                message: format!("{} closed the connection before {}", server, terminator),
+            })
+        };
+
+        tokio::time::timeout(REQUEST_TIMEOUT, exchange)
+            .await
+            .map_err(|_| DiscoveryError::Timeout { timeout: REQUEST_TIMEOUT })?
+    }
+
+    /// Look up the peers registered with one server
+    async fn lookup(&self, server: &str) -> Result<Vec<ServiceRecord>, DiscoveryError> {
+        let request = format!("{}|{}|{}", KIZUNA_LOOKUP, PROTOCOL_VERSION, self.peer_id);
+        let lines = self.request(server, request, KIZUNA_END).await?;
+
+        let mut peers = Vec::new();
+        for line in lines {
+            let Some((KIZUNA_PEER_RECORD, record)) = line.split_once('|') else {
+                continue;
+            };
+            let Ok(mut record) = ServiceRecord::from_network_string(record) else {
+                continue;
+            };
+            if record.peer_id == self.peer_id {
+                continue;
+            }
+
+            record.set_discovery_method(self.strategy_name().to_string());
+            record.add_capability(RENDEZVOUS_SERVER_CAPABILITY.to_string(), server.to_string());
+            record.update_last_seen();
+            peers.push(record);
+        }
+
+        Ok(peers)
+    }
+
+    async fn register(&self, server: &str) -> Result<(), DiscoveryError> {
+        let request = format!(
+            "{}|{}|{}|{}",
+            KIZUNA_REGISTER,
+            PROTOCOL_VERSION,
+            self.registration_ttl.as_secs(),
+            self.own_record().to_network_string()
+        );
+        self.request(server, request, KIZUNA_OK).await.map(|_| ())
+    }
+
+    async fn unregister(&self, server: &str) -> Result<(), DiscoveryError> {
+        let request = format!("{}|{}|{}", KIZUNA_UNREGISTER, PROTOCOL_VERSION, self.peer_id);
+        self.request(server, request, KIZUNA_OK).await.map(|_| ())
+    }
+}
+
+#[async_trait]
+impl Discovery for RendezvousDiscovery {
+    async fn discover(&self, timeout: Duration) -> Result<Vec<ServiceRecord>, DiscoveryError> {
+        let lookups = self.servers.iter().map(|server| self.lookup(server));
+        let results = tokio::time::timeout(timeout, futures::future::join_all(lookups))
+            .await
+            .map_err(|_| DiscoveryError::Timeout { timeout })?;
+
+        let mut peers: HashMap<String, ServiceRecord> = HashMap::new();
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(records) => {
+                    for record in records {
+                        match peers.get_mut(&record.peer_id) {
+                            Some(existing) => existing.merge(record),
+                            None => {
+                                peers.insert(record.peer_id.clone(), record);
+                            }
+                        }
+                    }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+
+        // Only fail when no server answered at all
+        if peers.is_empty() && !errors.is_empty() && errors.len() == self.servers.len() {
+            return Err(DiscoveryError::MultipleErrors { errors });
+        }
+
+        Ok(peers.into_values().collect())
+    }
+
+    async fn announce(&self) -> Result<(), DiscoveryError> {
+        let mut registered = Vec::new();
+        let mut errors = Vec::new();
+
+        for server in &self.servers {
+            match self.register(server).await {
+                Ok(()) => registered.push(server.clone()),
+                Err(e) => errors.push(format!("{}: {}", server, e)),
+            }
+        }
+
+        let none_registered = registered.is_empty();
+        *self.registered.write().await = registered;
+
+        if none_registered && !errors.is_empty() {
+            return Err(DiscoveryError::Network(format!(
+                "Could not register with any rendezvous server: {}",
+                errors.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn stop_announce(&self) -> Result<(), DiscoveryError> {
+        let servers = std::mem::take(&mut *self.registered.write().await);
+        for server in servers {
+            // Registrations expire on their own if the server is unreachable
+            let _ = self.unregister(&server).await;
+        }
+        Ok(())
+    }
+
+    fn strategy_name(&self) -> &'static str {
+        "rendezvous"
+    }
+
+    fn is_available(&self) -> bool {
+        !self.servers.is_empty()
+    }
+
+    fn priority(&self) -> u8 {
+        // Last resort after every local strategy
+        10
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Rendezvous server stub that answers a single lookup
+    async fn spawn_server(records: Vec<ServiceRecord>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let request = lines.next_line().await.unwrap().unwrap();
+            assert!(request.starts_with(KIZUNA_LOOKUP));
+
+            for record in records {
+                let line = format!("{}|{}\n", KIZUNA_PEER_RECORD, record.to_network_string());
+                writer.write_all(line.as_bytes()).await.unwrap();
+            }
+            writer.write_all(format!("{}\n", KIZUNA_END).as_bytes()).await.unwrap();
+        });
+
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_discover_marks_rendezvous_provenance() {
+        let mut remote = ServiceRecord::new("peer-remote".to_string(), "Laptop".to_string(), 41337);
+        remote.add_address("203.0.113.7:41337".parse().unwrap());
+        let own = ServiceRecord::new("peer-self".to_string(), "Self".to_string(), 41337);
+        let server = spawn_server(vec![remote, own]).await;
+
+        let discovery = RendezvousDiscovery::with_config(
+            "peer-self".to_string(),
+            "Self".to_string(),
+            41337,
+            vec![server.clone()],
+        );
+        let peers = discovery.discover(Duration::from_secs(2)).await.unwrap();
+
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].peer_id, "peer-remote");
+        assert_eq!(peers[0].discovery_method, "rendezvous");
+        assert_eq!(peers[0].capabilities.get(RENDEZVOUS_SERVER_CAPABILITY), Some(&server));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_servers_fail() {
+        let discovery = RendezvousDiscovery::new(vec!["127.0.0.1:1".to_string()]);
+        assert!(discovery.discover(Duration::from_secs(2)).await.is_err());
+        assert!(!RendezvousDiscovery::new(Vec::new()).is_available());
+    }
+}
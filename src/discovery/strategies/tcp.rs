@@ -1,3 +1,4 @@
+use crate::discovery::service_record::version_capabilities;
 use crate::discovery::{Discovery, DiscoveryError, ServiceRecord};
 use async_trait::async_trait;
 use std::collections::HashMap;
@@ -28,6 +29,7 @@ impl TcpDiscovery {
         let mut capabilities = HashMap::new();
         capabilities.insert("version".to_string(), "1.0.0".to_string());
         capabilities.insert("protocol".to_string(), "tcp".to_string());
+        capabilities.extend(version_capabilities());
         
         Self {
             peer_id: format!("kizuna-{}", uuid::Uuid::new_v4().to_string()[..8].to_string()),
@@ -43,6 +45,7 @@ impl TcpDiscovery {
         let mut capabilities = HashMap::new();
         capabilities.insert("version".to_string(), "1.0.0".to_string());
         capabilities.insert("protocol".to_string(), "tcp".to_string());
+        capabilities.extend(version_capabilities());
         
         Self {
             peer_id,
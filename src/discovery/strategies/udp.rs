@@ -1,6 +1,8 @@
+use crate::discovery::responder::bind_reusable;
+use crate::discovery::service_record::version_capabilities;
 use crate::discovery::{Discovery, DiscoveryError, ServiceRecord};
 use async_trait::async_trait;
-use std::net::SocketAddr;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::net::UdpSocket;
@@ -95,8 +97,8 @@ impl UdpDiscovery {
     }
 
     async fn listen_for_responses(&self, timeout: Duration) -> Result<Vec<ServiceRecord>, DiscoveryError> {
-        let bind_addr = format!("0.0.0.0:{}", self.port);
-        let socket = UdpSocket::bind(&bind_addr).await
+        // Shared with the announce responder, which listens on the same port
+        let socket = bind_reusable(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, self.port))
             .map_err(|e| DiscoveryError::Network(e.to_string()))?;
 
         let mut peers = Vec::new();
@@ -236,14 +238,22 @@ impl UdpDiscovery {
         Some(record)
     }
 
+    /// Reply to a discovery request describing this peer
+    pub(crate) fn peer_response(peer_id: &str, device_name: &str, port: u16, addresses: &[SocketAddr]) -> String {
+        let versions = version_capabilities().map(|(key, value)| format!("{}={}", key, value)).join(",");
+        let addresses = addresses.iter().map(SocketAddr::to_string).collect::<Vec<_>>().join(",");
+        format!(
+            "KIZUNA_PEER|{}|{}|{}|{}|version=0.1.0,protocol=udp,{}",
+            peer_id, device_name, port, addresses, versions
+        )
+    }
+
     /// Send a peer response message
     async fn send_peer_response(peer_id: String, device_name: String, port: u16, target_addr: SocketAddr) -> Result<(), DiscoveryError> {
         let socket = UdpSocket::bind("0.0.0.0:0").await
             .map_err(|e| DiscoveryError::Network(e.to_string()))?;
 
-        // Create response message with our peer information
-        let response = format!("KIZUNA_PEER|{}|{}|{}||version=0.1.0,protocol=udp", 
-            peer_id, device_name, port);
+        let response = Self::peer_response(&peer_id, &device_name, port, &[]);
 
         socket.send_to(response.as_bytes(), target_addr).await
             .map_err(|e| DiscoveryError::Network(e.to_string()))?;
@@ -0,0 +1,730 @@
+//! Connectivity diagnostics
+//!
+//! [`Doctor`] runs an end-to-end check of everything Kizuna needs to reach
+//! peers: local ports and firewall rules, multicast for mDNS, the outbound
+//! proxy, STUN and relay reachability, clock skew, the OS keyring and
+//! optionally a specific peer.
+//! Each check produces a [`CheckResult`] with a status and, when something is
+//! wrong, a concrete suggestion for fixing it.
+//!
+//! Checks never abort the run; a failing check is reported and the rest
+//! continue, so one blocked service does not hide other problems.
+
+use crate::security::clock::CLOCK_SKEW_WARN_SECS;
+use crate::transport::proxy::{self, ProxyConfig};
+use crate::transport::{resolve_stun_servers, NatTraversal, NatType, TransportError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, UdpSocket as StdUdpSocket};
+use std::process::Command;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::{TcpStream, UdpSocket};
+
+/// Default Kizuna discovery and transport port
+pub const KIZUNA_PORT: u16 = 41337;
+
+const MDNS_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(224, 0, 0, 251), 5353);
+/// Seconds between the NTP epoch (1900) and the Unix epoch
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+const PEER_PING_COUNT: usize = 5;
+const KEYRING_PROBE_SERVICE: &str = "kizuna.doctor";
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Skipped,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "ok",
+            CheckStatus::Skipped => "skip",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        }
+    }
+}
+
+/// Result of one diagnostic check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or failure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl CheckResult {
+    fn new(name: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            suggestion: None,
+        }
+    }
+
+    fn suggest(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+/// Results of a full diagnostic run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoctorReport {
+    pub checks: Vec<CheckResult>,
+    /// When the run finished, in seconds since the Unix epoch
+    pub completed_at: u64,
+}
+
+impl DoctorReport {
+    /// Checks that warned or failed
+    pub fn problems(&self) -> Vec<&CheckResult> {
+        self.checks.iter().filter(|c| c.status >= CheckStatus::Warn).collect()
+    }
+
+    /// Worst status of any check
+    pub fn overall(&self) -> CheckStatus {
+        self.checks.iter().map(|c| c.status).max().unwrap_or(CheckStatus::Pass)
+    }
+}
+
+/// Transport protocol of a local port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortProtocol {
+    Tcp,
+    Udp,
+}
+
+impl fmt::Display for PortProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortProtocol::Tcp => write!(f, "tcp"),
+            PortProtocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+/// A local port Kizuna needs to listen on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRequirement {
+    pub port: u16,
+    pub protocol: PortProtocol,
+    pub purpose: String,
+    /// Whether another process normally holds this port too, e.g. the
+    /// system mDNS responder on 5353
+    pub shared: bool,
+}
+
+impl PortRequirement {
+    fn new(port: u16, protocol: PortProtocol, purpose: &str, shared: bool) -> Self {
+        Self {
+            port,
+            protocol,
+            purpose: purpose.to_string(),
+            shared,
+        }
+    }
+}
+
+/// Peer to ping at the end of the run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerTarget {
+    /// Name the user gave
+    pub name: String,
+    /// Addresses to try; empty if the peer could not be found
+    pub addresses: Vec<SocketAddr>,
+}
+
+/// What the doctor checks
+#[derive(Debug, Clone)]
+pub struct DoctorConfig {
+    pub ports: Vec<PortRequirement>,
+    /// STUN servers as `stun:host:port` URLs
+    pub stun_servers: Vec<String>,
+    /// Relay and rendezvous servers as `host:port` or URLs
+    pub relay_servers: Vec<String>,
+    /// NTP server used for the clock skew check
+    pub ntp_server: String,
+    /// Largest clock difference that still lets pairing codes verify
+    pub max_clock_skew: Duration,
+    /// Timeout for each network probe
+    pub probe_timeout: Duration,
+    /// Proxy relay, rendezvous and STUN traffic goes through
+    pub proxy: Option<ProxyConfig>,
+    pub peer: Option<PeerTarget>,
+}
+
+impl Default for DoctorConfig {
+    fn default() -> Self {
+        Self {
+            ports: vec![
+                PortRequirement::new(KIZUNA_PORT, PortProtocol::Tcp, "transport and TCP discovery", false),
+                PortRequirement::new(KIZUNA_PORT, PortProtocol::Udp, "UDP broadcast discovery", false),
+                PortRequirement::new(5353, PortProtocol::Udp, "mDNS discovery", true),
+            ],
+            stun_servers: vec![
+                "stun:stun.l.google.com:19302".to_string(),
+                "stun:stun1.l.google.com:19302".to_string(),
+            ],
+            relay_servers: Vec::new(),
+            ntp_server: "pool.ntp.org:123".to_string(),
+            max_clock_skew: Duration::from_secs(CLOCK_SKEW_WARN_SECS),
+            probe_timeout: Duration::from_secs(3),
+            proxy: None,
+            peer: None,
+        }
+    }
+}
+
+/// Runs the connectivity checks
+pub struct Doctor {
+    config: DoctorConfig,
+}
+
+impl Doctor {
+    pub fn new(config: DoctorConfig) -> Self {
+        Self { config }
+    }
+
+    /// Run every check and collect the results
+    pub async fn run(&self) -> DoctorReport {
+        let mut checks = self.check_ports();
+        checks.extend(self.check_firewall());
+
+        let (multicast, proxy, stun, relays, clock) = tokio::join!(
+            self.check_multicast(),
+            self.check_proxy(),
+            self.check_stun(),
+            self.check_relays(),
+            self.check_clock(),
+        );
+        checks.push(multicast);
+        checks.extend(proxy);
+        checks.push(stun);
+        checks.push(relays);
+        checks.push(clock);
+        checks.push(self.check_keyring().await);
+
+        if let Some(peer) = &self.config.peer {
+            checks.push(self.check_peer(peer).await);
+        }
+
+        DoctorReport {
+            checks,
+            completed_at: unix_now().as_secs(),
+        }
+    }
+
+    /// Make sure the ports Kizuna listens on can be bound
+    fn check_ports(&self) -> Vec<CheckResult> {
+        self.config
+            .ports
+            .iter()
+            .map(|req| {
+                let name = format!("Port {}/{}", req.port, req.protocol);
+                let bound = match req.protocol {
+                    PortProtocol::Tcp => TcpListener::bind((Ipv4Addr::UNSPECIFIED, req.port)).map(drop),
+                    PortProtocol::Udp => StdUdpSocket::bind((Ipv4Addr::UNSPECIFIED, req.port)).map(drop),
+                };
+
+                match bound {
+                    Ok(()) => CheckResult::new(&name, CheckStatus::Pass, format!("Available for {}", req.purpose)),
+                    Err(e) if e.kind() == ErrorKind::AddrInUse && req.shared => CheckResult::new(
+                        &name,
+                        CheckStatus::Pass,
+                        format!("Shared with the system responder for {}", req.purpose),
+                    ),
+                    Err(e) if e.kind() == ErrorKind::AddrInUse => CheckResult::new(
+                        &name,
+                        CheckStatus::Warn,
+                        format!("Already in use; needed for {}", req.purpose),
+                    )
+                    .suggest(format!(
+                        "If Kizuna is not already running, stop the process using {} port {}",
+                        req.protocol.to_string().to_uppercase(),
+                        req.port
+                    )),
+                    Err(e) => CheckResult::new(&name, CheckStatus::Fail, format!("Cannot bind: {}", e))
+                        .suggest("Check that no security software blocks Kizuna from listening"),
+                }
+            })
+            .collect()
+    }
+
+    /// Look for an active host firewall without rules for Kizuna
+    ///
+    /// Only ufw and firewalld on Linux can be inspected; elsewhere the check
+    /// is left out rather than guessed.
+    fn check_firewall(&self) -> Option<CheckResult> {
+        const NAME: &str = "Firewall";
+
+        if !cfg!(target_os = "linux") {
+            return None;
+        }
+
+        let (tool, missing, rule): (&str, Vec<&PortRequirement>, fn(&PortRequirement) -> String) =
+            if let Some(status) = run_command("ufw", &["status"]) {
+                if !status.contains("Status: active") {
+                    return Some(CheckResult::new(NAME, CheckStatus::Pass, "ufw is inactive"));
+                }
+                let missing = self
+                    .config
+                    .ports
+                    .iter()
+                    .filter(|req| !status.lines().any(|line| line.starts_with(&req.port.to_string())))
+                    .collect();
+                ("ufw", missing, |req| format!("sudo ufw allow {}/{}", req.port, req.protocol))
+            } else if let Some(ports) = run_command("firewall-cmd", &["--list-ports"]) {
+                let missing = self
+                    .config
+                    .ports
+                    .iter()
+                    .filter(|req| !ports.split_whitespace().any(|p| p == format!("{}/{}", req.port, req.protocol)))
+                    .collect();
+                ("firewalld", missing, |req| {
+                    format!("sudo firewall-cmd --permanent --add-port={}/{}", req.port, req.protocol)
+                })
+            } else {
+                return None;
+            };
+
+        if missing.is_empty() {
+            return Some(CheckResult::new(NAME, CheckStatus::Pass, format!("{} allows the Kizuna ports", tool)));
+        }
+
+        let ports: Vec<String> = missing.iter().map(|req| format!("{}/{}", req.port, req.protocol)).collect();
+        let rules: Vec<String> = missing.iter().map(|req| rule(req)).collect();
+        Some(
+            CheckResult::new(
+                NAME,
+                CheckStatus::Warn,
+                format!("{} is active with no rule for {}", tool, ports.join(", ")),
+            )
+            .suggest(format!("Allow them with: {}", rules.join("; "))),
+        )
+    }
+
+    /// Send an mDNS query and wait for any responder to answer
+    async fn check_multicast(&self) -> CheckResult {
+        const NAME: &str = "Multicast (mDNS)";
+
+        let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await {
+            Ok(socket) => socket,
+            Err(e) => return CheckResult::new(NAME, CheckStatus::Fail, format!("Cannot open a UDP socket: {}", e)),
+        };
+
+        if let Err(e) = socket.send_to(&build_mdns_query(), MDNS_ADDR).await {
+            return CheckResult::new(NAME, CheckStatus::Fail, format!("Cannot send multicast: {}", e))
+                .suggest("This network has no multicast route; enable the UDP or TCP discovery strategy or use a rendezvous server");
+        }
+
+        let mut responders = Vec::new();
+        let mut buf = [0u8; 1500];
+        let deadline = tokio::time::Instant::now() + self.config.probe_timeout;
+        while let Ok(Ok((len, from))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            // Only count responses (QR bit set)
+            if len >= 12 && buf[2] & 0x80 != 0 && !responders.contains(&from.ip()) {
+                responders.push(from.ip());
+            }
+        }
+
+        if responders.is_empty() {
+            CheckResult::new(NAME, CheckStatus::Warn, "No mDNS responder answered a service query")
+                .suggest("mDNS may be blocked: enable the UDP discovery strategy or open UDP port 5353")
+        } else {
+            CheckResult::new(
+                NAME,
+                CheckStatus::Pass,
+                format!("{} device(s) answered on the local network", responders.len()),
+            )
+        }
+    }
+
+    /// Classify the NAT through the configured STUN servers
+    async fn check_stun(&self) -> CheckResult {
+        const NAME: &str = "STUN";
+
+        if self.config.stun_servers.is_empty() {
+            return CheckResult::new(NAME, CheckStatus::Skipped, "No STUN servers configured");
+        }
+
+        let servers = resolve_stun_servers(&self.config.stun_servers).await;
+        if servers.is_empty() {
+            return CheckResult::new(NAME, CheckStatus::Fail, "Could not resolve any STUN server")
+                .suggest("Check DNS and internet access, or configure STUN servers by IP address");
+        }
+
+        let nat_traversal = NatTraversal::new(servers).with_proxy(self.config.proxy.clone());
+        let report = match nat_traversal.detect_nat().await {
+            Ok(report) => report,
+            Err(e) => return CheckResult::new(NAME, CheckStatus::Fail, e.to_string()),
+        };
+
+        let public = report
+            .mapped_address
+            .map(|addr| format!(", public address {}", addr))
+            .unwrap_or_default();
+        let via = match &self.config.proxy {
+            Some(proxy) if proxy.supports_udp() => format!(" (through proxy {})", proxy.address),
+            Some(_) => " (direct; HTTP proxies cannot carry UDP)".to_string(),
+            None => String::new(),
+        };
+        let detail = format!("NAT type {}{}{}", report.nat_type.description(), public, via);
+        match report.nat_type {
+            NatType::Unknown => CheckResult::new(NAME, CheckStatus::Fail, "No STUN server answered")
+                .suggest("Outbound UDP appears blocked; allow UDP to the STUN servers or configure a relay server"),
+            NatType::Symmetric => CheckResult::new(NAME, CheckStatus::Warn, detail)
+                .suggest(report.recommendation()),
+            _ => CheckResult::new(NAME, CheckStatus::Pass, detail),
+        }
+    }
+
+    /// Connect to every configured relay or rendezvous server
+    async fn check_relays(&self) -> CheckResult {
+        const NAME: &str = "Relay servers";
+
+        if self.config.relay_servers.is_empty() {
+            return CheckResult::new(NAME, CheckStatus::Skipped, "No relay or rendezvous servers configured");
+        }
+
+        let probes = self.config.relay_servers.iter().map(|server| async move {
+            let reachable = match server_address(server) {
+                Some(addr) => self.connect_server(&addr).await.is_ok(),
+                None => false,
+            };
+            (server, reachable)
+        });
+        let results = futures::future::join_all(probes).await;
+
+        let unreachable: Vec<&str> = results.iter().filter(|(_, ok)| !ok).map(|(s, _)| s.as_str()).collect();
+        let total = results.len();
+        match unreachable.len() {
+            0 => CheckResult::new(NAME, CheckStatus::Pass, format!("All {} reachable", total)),
+            n if n == total => CheckResult::new(NAME, CheckStatus::Fail, "None reachable").suggest(
+                if self.config.proxy.is_some() {
+                    "Check the server addresses and that the proxy allows connections to them"
+                } else {
+                    "Check the server addresses and that outbound TCP to them is allowed, or configure a proxy"
+                },
+            ),
+            _ => CheckResult::new(
+                NAME,
+                CheckStatus::Warn,
+                format!("Unreachable: {}", unreachable.join(", ")),
+            )
+            .suggest("Remove or fix the unreachable servers"),
+        }
+    }
+
+    /// Authenticate with the proxy and tunnel through it to a configured server
+    ///
+    /// Left out when no proxy is configured.
+    async fn check_proxy(&self) -> Option<CheckResult> {
+        const NAME: &str = "Proxy";
+
+        let proxy = self.config.proxy.as_ref()?;
+        let Some((host, port)) = proxy::split_host_port(&proxy.address) else {
+            return Some(
+                CheckResult::new(NAME, CheckStatus::Fail, format!("Invalid proxy address {}", proxy.address))
+                    .suggest("Give the proxy as host:port, e.g. socks5://proxy.example.com:1080"),
+            );
+        };
+
+        // Reaching the proxy at all comes first; the tunnel errors are ambiguous without it
+        let reachable = tokio::time::timeout(self.config.probe_timeout, TcpStream::connect((host, port))).await;
+        if !reachable.is_ok_and(|r| r.is_ok()) {
+            return Some(
+                CheckResult::new(NAME, CheckStatus::Fail, format!("Cannot reach {}", proxy))
+                    .suggest("Check the proxy host and port, e.g. against the system or browser proxy settings"),
+            );
+        }
+
+        let Some(target) = self.config.relay_servers.iter().find_map(|server| server_address(server)) else {
+            return Some(CheckResult::new(
+                NAME,
+                CheckStatus::Pass,
+                format!("{} reachable; no relay or rendezvous server to tunnel to", proxy),
+            ));
+        };
+
+        let result = match self.connect_server(&target).await {
+            Ok(()) if proxy.supports_udp() && !self.proxy_relays_udp(proxy).await => CheckResult::new(
+                NAME,
+                CheckStatus::Warn,
+                format!("{} tunnels to {} but refuses UDP, so STUN goes out directly", proxy, target),
+            )
+            .suggest("Allow SOCKS5 UDP ASSOCIATE on the proxy, or rely on relay servers for connectivity"),
+            Ok(()) => CheckResult::new(NAME, CheckStatus::Pass, format!("{} tunnels to {}", proxy, target)),
+            Err(TransportError::AuthenticationFailed { reason }) => {
+                CheckResult::new(NAME, CheckStatus::Fail, format!("Authentication failed: {}", reason))
+                    .suggest("Check the proxy username and password")
+            }
+            Err(e) => CheckResult::new(NAME, CheckStatus::Fail, format!("Cannot tunnel to {}: {}", target, e))
+                .suggest("Ask the network administrator to allow connections to the relay servers through the proxy"),
+        };
+        Some(result)
+    }
+
+    async fn proxy_relays_udp(&self, proxy: &ProxyConfig) -> bool {
+        tokio::time::timeout(self.config.probe_timeout, proxy.udp_associate())
+            .await
+            .is_ok_and(|r| r.is_ok())
+    }
+
+    /// Open a TCP connection to a server, through the proxy unless it is bypassed
+    async fn connect_server(&self, address: &str) -> Result<(), TransportError> {
+        let (host, port) = proxy::split_host_port(address).ok_or(TransportError::InvalidPeerAddress)?;
+        tokio::time::timeout(self.config.probe_timeout, proxy::connect_tcp(self.config.proxy.as_ref(), host, port))
+            .await
+            .map_err(|_| TransportError::ConnectionTimeout {
+                timeout: self.config.probe_timeout,
+            })?
+            .map(drop)
+    }
+
+    /// Compare the system clock with an NTP server
+    async fn check_clock(&self) -> CheckResult {
+        const NAME: &str = "Clock";
+
+        let offset = match self.query_ntp_offset().await {
+            Some(offset) => offset,
+            None => {
+                return CheckResult::new(NAME, CheckStatus::Skipped, format!("No answer from {}", self.config.ntp_server))
+            }
+        };
+
+        let skew = Duration::from_secs_f64(offset.abs());
+        let direction = if offset > 0.0 { "behind" } else { "ahead" };
+        let detail = format!("{:.1}s {} {}", skew.as_secs_f64(), direction, self.config.ntp_server);
+        if skew > self.config.max_clock_skew {
+            CheckResult::new(NAME, CheckStatus::Fail, detail)
+                .suggest("Enable automatic time sync; pairing codes and certificates fail with a skewed clock")
+        } else {
+            CheckResult::new(NAME, CheckStatus::Pass, detail)
+        }
+    }
+
+    /// Seconds the server clock is ahead of ours
+    async fn query_ntp_offset(&self) -> Option<f64> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await.ok()?;
+        socket.connect(self.config.ntp_server.as_str()).await.ok()?;
+
+        let mut request = [0u8; 48];
+        request[0] = 0x1B; // LI 0, version 3, client mode
+        let sent = unix_now().as_secs_f64();
+        socket.send(&request).await.ok()?;
+
+        let mut response = [0u8; 48];
+        let len = tokio::time::timeout(self.config.probe_timeout, socket.recv(&mut response))
+            .await
+            .ok()?
+            .ok()?;
+        let received = unix_now().as_secs_f64();
+
+        let server_time = parse_ntp_transmit_time(&response[..len])?;
+        Some(server_time - (sent + received) / 2.0)
+    }
+
+    /// Store, read back and remove a probe secret in the OS keyring
+    async fn check_keyring(&self) -> CheckResult {
+        const NAME: &str = "Keyring";
+
+        let probe = tokio::task::spawn_blocking(|| -> Result<(), keyring::Error> {
+            let entry = keyring::Entry::new(KEYRING_PROBE_SERVICE, &whoami::username())?;
+            entry.set_password("probe")?;
+            let read_back = entry.get_password();
+            entry.delete_password()?;
+            read_back.map(drop)
+        })
+        .await;
+
+        match probe {
+            Ok(Ok(())) => CheckResult::new(NAME, CheckStatus::Pass, "Secrets can be stored"),
+            Ok(Err(e)) => {
+                let suggestion = if cfg!(target_os = "linux") {
+                    "Install and unlock a Secret Service provider such as GNOME Keyring or KWallet"
+                } else {
+                    "Unlock the system keychain and allow Kizuna to access it"
+                };
+                CheckResult::new(NAME, CheckStatus::Fail, format!("Keyring unavailable: {}", e)).suggest(suggestion)
+            }
+            Err(e) => CheckResult::new(NAME, CheckStatus::Fail, format!("Keyring probe failed: {}", e)),
+        }
+    }
+
+    /// Measure connection round trips to a peer
+    async fn check_peer(&self, peer: &PeerTarget) -> CheckResult {
+        let name = format!("Peer {}", peer.name);
+
+        if peer.addresses.is_empty() {
+            return CheckResult::new(&name, CheckStatus::Fail, "Not found by discovery")
+                .suggest("Make sure the peer is running Kizuna and announcing, or give its address as host:port");
+        }
+
+        let mut rtts = Vec::new();
+        let mut reached = None;
+        for _ in 0..PEER_PING_COUNT {
+            for addr in &peer.addresses {
+                let started = Instant::now();
+                if let Ok(Ok(_)) = tokio::time::timeout(self.config.probe_timeout, TcpStream::connect(*addr)).await {
+                    rtts.push(started.elapsed());
+                    reached = Some(*addr);
+                    break;
+                }
+            }
+        }
+
+        let Some(addr) = reached else {
+            return CheckResult::new(&name, CheckStatus::Fail, "No address answered")
+                .suggest("The peer may be behind a firewall or on another network; open TCP port 41337 there or configure a relay");
+        };
+
+        let average = rtts.iter().sum::<Duration>() / rtts.len() as u32;
+        let detail = format!(
+            "{} replied {}/{} times, average {:.1} ms",
+            addr,
+            rtts.len(),
+            PEER_PING_COUNT,
+            average.as_secs_f64() * 1000.0
+        );
+        if rtts.len() < PEER_PING_COUNT {
+            CheckResult::new(&name, CheckStatus::Warn, detail).suggest("Connections are dropping; check Wi-Fi signal or network load")
+        } else {
+            CheckResult::new(&name, CheckStatus::Pass, detail)
+        }
+    }
+}
+
+/// DNS-SD service enumeration query asking for unicast replies
+fn build_mdns_query() -> Vec<u8> {
+    let mut packet = vec![0u8; 12];
+    packet[5] = 1; // one question
+    for label in ["_services", "_dns-sd", "_udp", "local"] {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&12u16.to_be_bytes()); // PTR
+    packet.extend_from_slice(&0x8001u16.to_be_bytes()); // IN, unicast response
+    packet
+}
+
+/// Unix time from the transmit timestamp of an NTP response
+fn parse_ntp_transmit_time(packet: &[u8]) -> Option<f64> {
+    let timestamp = packet.get(40..48)?;
+    let seconds = u64::from(u32::from_be_bytes(timestamp[0..4].try_into().ok()?));
+    let fraction = f64::from(u32::from_be_bytes(timestamp[4..8].try_into().ok()?)) / f64::from(u32::MAX);
+    if seconds < NTP_UNIX_OFFSET {
+        return None;
+    }
+    Some((seconds - NTP_UNIX_OFFSET) as f64 + fraction)
+}
+
+/// `host:port` of a server given as an address or a URL
+fn server_address(server: &str) -> Option<String> {
+    if !server.contains("://") {
+        return Some(server.to_string());
+    }
+    let url = url::Url::parse(server).ok()?;
+    Some(format!("{}:{}", url.host_str()?, url.port_or_known_default()?))
+}
+
+fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ntp_transmit_time() {
+        let mut packet = [0u8; 48];
+        // 2024-01-01T00:00:00Z plus half a second
+        packet[40..44].copy_from_slice(&((1_704_067_200 + NTP_UNIX_OFFSET) as u32).to_be_bytes());
+        packet[44..48].copy_from_slice(&(u32::MAX / 2).to_be_bytes());
+
+        let time = parse_ntp_transmit_time(&packet).unwrap();
+        assert!((time - 1_704_067_200.5).abs() < 0.001);
+        assert!(parse_ntp_transmit_time(&packet[..40]).is_none());
+    }
+
+    #[test]
+    fn test_mdns_query_encoding() {
+        let query = build_mdns_query();
+        assert_eq!(&query[4..6], &[0, 1]);
+        assert_eq!(query[12], 9);
+        assert_eq!(&query[13..22], b"_services");
+        assert_eq!(&query[query.len() - 4..], &[0, 12, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn test_server_address() {
+        assert_eq!(server_address("relay.example.com:9000").as_deref(), Some("relay.example.com:9000"));
+        assert_eq!(server_address("wss://relay.example.com").as_deref(), Some("relay.example.com:443"));
+        assert_eq!(server_address("ws://relay.example.com:8080/path").as_deref(), Some("relay.example.com:8080"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_peer_fails_with_suggestion() {
+        let doctor = Doctor::new(DoctorConfig::default());
+        let result = doctor
+            .check_peer(&PeerTarget {
+                name: "laptop".to_string(),
+                addresses: Vec::new(),
+            })
+            .await;
+
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(result.suggestion.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_proxy_fails_with_suggestion() {
+        let doctor = Doctor::new(DoctorConfig::default());
+        assert!(doctor.check_proxy().await.is_none());
+
+        let doctor = Doctor::new(DoctorConfig {
+            proxy: Some("socks5://127.0.0.1:1".parse().unwrap()),
+            relay_servers: vec!["relay.example.com:443".to_string()],
+            ..DoctorConfig::default()
+        });
+        let result = doctor.check_proxy().await.unwrap();
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(result.suggestion.is_some());
+    }
+
+    #[test]
+    fn test_report_overall_status() {
+        let report = DoctorReport {
+            checks: vec![
+                CheckResult::new("a", CheckStatus::Pass, ""),
+                CheckResult::new("b", CheckStatus::Skipped, ""),
+                CheckResult::new("c", CheckStatus::Warn, "").suggest("fix it"),
+            ],
+            completed_at: 0,
+        };
+
+        assert_eq!(report.overall(), CheckStatus::Warn);
+        assert_eq!(report.problems().len(), 1);
+    }
+}
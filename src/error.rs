@@ -0,0 +1,790 @@
+//! Crate-wide error taxonomy
+//!
+//! Every subsystem keeps its own error enum. [`KizunaError`] wraps all of them so
+//! code that spans subsystems (the CLI, the daemon, language bindings) can propagate
+//! any failure with `?` and still classify it: each error maps to a stable
+//! [`ErrorCode`], a retryability verdict, a process exit code and a JSON report.
+//!
+//! Error codes are part of the public contract. Scripts match on
+//! `"<subsystem>.<code>"` strings such as `transport.timeout`, so existing codes must
+//! never be renamed; new failure modes get new codes.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(feature = "browser-support")]
+use crate::browser_support::BrowserSupportError;
+use crate::cli::CLIError;
+#[cfg(feature = "clipboard")]
+use crate::clipboard::ClipboardError;
+use crate::command_execution::CommandError;
+use crate::discovery::DiscoveryError;
+use crate::file_transfer::FileTransferError;
+use crate::messaging::MessagingError;
+use crate::platform::PlatformError;
+use crate::remote_fs::{BrowseError, RemoteErrorKind};
+use crate::security::error::{
+    AuthenticationError, EncryptionError, IdentityError, PolicyError, SecurityError, TrustError,
+};
+#[cfg(feature = "streaming")]
+use crate::streaming::StreamError;
+use crate::transport::{TransportError, WireError};
+
+/// Result type for operations that can fail in any subsystem
+pub type KizunaResult<T> = std::result::Result<T, KizunaError>;
+
+/// Stable, subsystem-independent classification of a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The caller supplied an invalid argument or request
+    InvalidInput,
+    /// A peer, file, session or other resource does not exist
+    NotFound,
+    /// The resource already exists or the operation is already running
+    AlreadyExists,
+    /// The operation is not allowed by policy, trust or OS permissions
+    PermissionDenied,
+    /// The peer's identity could not be established
+    Unauthenticated,
+    /// The operation did not complete in time
+    Timeout,
+    /// A peer, network path or service could not be reached
+    Unavailable,
+    /// A size, rate or capacity limit was hit
+    ResourceExhausted,
+    /// The user or a shutdown cancelled the operation
+    Cancelled,
+    /// The remote side sent something this build cannot understand
+    Protocol,
+    /// Data failed an integrity or authenticity check
+    Integrity,
+    /// Local file system or device I/O failed
+    Io,
+    /// Configuration is missing or invalid
+    Configuration,
+    /// The platform or peer does not support the operation
+    Unsupported,
+    /// A bug or unexpected internal state
+    Internal,
+}
+
+impl ErrorCode {
+    /// Stable identifier used in JSON output and logs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidInput => "invalid_input",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::AlreadyExists => "already_exists",
+            ErrorCode::PermissionDenied => "permission_denied",
+            ErrorCode::Unauthenticated => "unauthenticated",
+            ErrorCode::Timeout => "timeout",
+            ErrorCode::Unavailable => "unavailable",
+            ErrorCode::ResourceExhausted => "resource_exhausted",
+            ErrorCode::Cancelled => "cancelled",
+            ErrorCode::Protocol => "protocol",
+            ErrorCode::Integrity => "integrity",
+            ErrorCode::Io => "io",
+            ErrorCode::Configuration => "configuration",
+            ErrorCode::Unsupported => "unsupported",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    /// Whether failures of this class are usually worth retrying unchanged
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::Timeout | ErrorCode::Unavailable | ErrorCode::ResourceExhausted
+        )
+    }
+
+    /// Process exit status for the CLI, following the BSD `sysexits.h` conventions
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorCode::InvalidInput => 64,      // EX_USAGE
+            ErrorCode::NotFound => 66,          // EX_NOINPUT
+            ErrorCode::AlreadyExists => 73,     // EX_CANTCREAT
+            ErrorCode::PermissionDenied => 77,  // EX_NOPERM
+            ErrorCode::Unauthenticated => 77,   // EX_NOPERM
+            ErrorCode::Timeout => 75,           // EX_TEMPFAIL
+            ErrorCode::Unavailable => 69,       // EX_UNAVAILABLE
+            ErrorCode::ResourceExhausted => 75, // EX_TEMPFAIL
+            ErrorCode::Cancelled => 130,        // terminated by Ctrl-C
+            ErrorCode::Protocol => 76,          // EX_PROTOCOL
+            ErrorCode::Integrity => 65,         // EX_DATAERR
+            ErrorCode::Io => 74,                // EX_IOERR
+            ErrorCode::Configuration => 78,     // EX_CONFIG
+            ErrorCode::Unsupported => 69,       // EX_UNAVAILABLE
+            ErrorCode::Internal => 70,          // EX_SOFTWARE
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Any error produced by Kizuna
+#[derive(Debug, Error)]
+pub enum KizunaError {
+    #[error(transparent)]
+    Transport(#[from] TransportError),
+
+    #[error(transparent)]
+    Wire(#[from] WireError),
+
+    #[error(transparent)]
+    Discovery(#[from] DiscoveryError),
+
+    #[cfg(feature = "clipboard")]
+    #[error(transparent)]
+    Clipboard(#[from] ClipboardError),
+
+    #[error(transparent)]
+    FileTransfer(#[from] FileTransferError),
+
+    #[error(transparent)]
+    Security(#[from] SecurityError),
+
+    #[error(transparent)]
+    Command(#[from] CommandError),
+
+    #[error(transparent)]
+    Messaging(#[from] MessagingError),
+
+    #[error(transparent)]
+    Browse(#[from] BrowseError),
+
+    #[error(transparent)]
+    Platform(#[from] PlatformError),
+
+    #[cfg(feature = "browser-support")]
+    #[error(transparent)]
+    BrowserSupport(#[from] BrowserSupportError),
+
+    #[cfg(feature = "streaming")]
+    #[error(transparent)]
+    Streaming(#[from] StreamError),
+
+    #[error(transparent)]
+    Cli(#[from] CLIError),
+
+    #[error(transparent)]
+    Api(#[from] crate::developer_api::KizunaError),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+// Security sub-errors convert through `SecurityError` so they keep the security subsystem
+macro_rules! via_security {
+    ($($error:ty),* $(,)?) => {
+        $(
+            impl From<$error> for KizunaError {
+                fn from(error: $error) -> Self {
+                    KizunaError::Security(SecurityError::from(error))
+                }
+            }
+        )*
+    };
+}
+
+via_security!(IdentityError, TrustError, EncryptionError, PolicyError, AuthenticationError);
+
+impl KizunaError {
+    /// Create an invalid input error
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        KizunaError::InvalidInput(message.into())
+    }
+
+    /// Create an uncategorized error
+    pub fn other(message: impl Into<String>) -> Self {
+        KizunaError::Other(message.into())
+    }
+
+    /// Subsystem the error originated in
+    pub fn subsystem(&self) -> &'static str {
+        match self {
+            KizunaError::Transport(_) | KizunaError::Wire(_) => "transport",
+            KizunaError::Discovery(_) => "discovery",
+            #[cfg(feature = "clipboard")]
+            KizunaError::Clipboard(_) => "clipboard",
+            KizunaError::FileTransfer(_) => "file_transfer",
+            KizunaError::Security(_) => "security",
+            KizunaError::Command(_) => "command",
+            KizunaError::Messaging(_) => "messaging",
+            KizunaError::Browse(_) => "remote_fs",
+            KizunaError::Platform(_) => "platform",
+            #[cfg(feature = "browser-support")]
+            KizunaError::BrowserSupport(_) => "browser",
+            #[cfg(feature = "streaming")]
+            KizunaError::Streaming(_) => "streaming",
+            KizunaError::Cli(_) => "cli",
+            KizunaError::Api(_) => "api",
+            KizunaError::Io(_)
+            | KizunaError::Serialization(_)
+            | KizunaError::InvalidInput(_)
+            | KizunaError::Other(_) => "core",
+        }
+    }
+
+    /// Subsystem-independent classification
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            KizunaError::Transport(e) => transport_code(e),
+            KizunaError::Wire(e) => match e {
+                WireError::PayloadTooLarge { .. } => ErrorCode::ResourceExhausted,
+                WireError::UnsupportedVersion { .. } => ErrorCode::Unsupported,
+                _ => ErrorCode::Protocol,
+            },
+            KizunaError::Discovery(e) => discovery_code(e),
+            #[cfg(feature = "clipboard")]
+            KizunaError::Clipboard(e) => clipboard_code(e),
+            KizunaError::FileTransfer(e) => file_transfer_code(e),
+            KizunaError::Security(e) => security_code(e),
+            KizunaError::Command(e) => command_code(e),
+            KizunaError::Messaging(e) => messaging_code(e),
+            KizunaError::Browse(e) => browse_code(e),
+            KizunaError::Platform(e) => match e {
+                PlatformError::FeatureUnavailable(_) | PlatformError::UnsupportedPlatform(_) => {
+                    ErrorCode::Unsupported
+                }
+                PlatformError::ConfigurationError(_) => ErrorCode::Configuration,
+                PlatformError::IoError(_) => ErrorCode::Io,
+                _ => ErrorCode::Internal,
+            },
+            #[cfg(feature = "browser-support")]
+            KizunaError::BrowserSupport(e) => browser_code(e),
+            #[cfg(feature = "streaming")]
+            KizunaError::Streaming(e) => streaming_code(e),
+            KizunaError::Cli(e) => cli_code(e),
+            KizunaError::Api(e) => api_code(e.kind()),
+            KizunaError::Io(e) => io_code(e),
+            KizunaError::Serialization(_) => ErrorCode::Protocol,
+            KizunaError::InvalidInput(_) => ErrorCode::InvalidInput,
+            KizunaError::Other(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// Stable `"<subsystem>.<code>"` identifier, e.g. `transport.timeout`
+    pub fn error_code(&self) -> String {
+        format!("{}.{}", self.subsystem(), self.code())
+    }
+
+    /// Whether retrying the same operation may succeed
+    ///
+    /// Subsystems that already classify their own errors are consulted first, so a
+    /// retry decision here matches what the subsystem would do internally.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            KizunaError::Transport(e) => e.is_recoverable(),
+            #[cfg(feature = "clipboard")]
+            KizunaError::Clipboard(e) => e.is_recoverable(),
+            KizunaError::FileTransfer(e) => e.is_recoverable(),
+            KizunaError::Api(e) => e.is_retryable(),
+            other => other.code().is_transient(),
+        }
+    }
+
+    /// Process exit status the CLI should use for this error
+    pub fn exit_code(&self) -> i32 {
+        self.code().exit_code()
+    }
+
+    /// Machine-readable description for `--format json` output
+    pub fn report(&self) -> ErrorReport {
+        ErrorReport {
+            code: self.error_code(),
+            subsystem: self.subsystem().to_string(),
+            category: self.code(),
+            message: self.to_string(),
+            retryable: self.is_retryable(),
+            exit_code: self.exit_code(),
+        }
+    }
+}
+
+/// JSON representation of an error for CLI and API consumers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    /// Stable `"<subsystem>.<code>"` identifier
+    pub code: String,
+    /// Subsystem the error originated in
+    pub subsystem: String,
+    /// Subsystem-independent classification
+    pub category: ErrorCode,
+    /// Human-readable message
+    pub message: String,
+    /// Whether retrying may succeed
+    pub retryable: bool,
+    /// Exit status used by the CLI
+    pub exit_code: i32,
+}
+
+impl ErrorReport {
+    /// Render as a JSON object
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "error": self })
+    }
+}
+
+impl From<&KizunaError> for ErrorReport {
+    fn from(error: &KizunaError) -> Self {
+        error.report()
+    }
+}
+
+impl From<KizunaError> for CLIError {
+    fn from(error: KizunaError) -> Self {
+        match error {
+            KizunaError::Cli(e) => e,
+            KizunaError::Io(e) => CLIError::IOError(e),
+            // Keep the classes scripts branch on, so the exit code survives the round trip
+            other => {
+                let message = format!("[{}] {}", other.error_code(), other);
+                match other.code() {
+                    ErrorCode::PermissionDenied | ErrorCode::Unauthenticated => {
+                        CLIError::PermissionDenied(message)
+                    }
+                    ErrorCode::NotFound => CLIError::NotFound(message),
+                    ErrorCode::Timeout => CLIError::Timeout(message),
+                    _ => CLIError::IntegrationError(message),
+                }
+            }
+        }
+    }
+}
+
+impl CLIError {
+    /// Process exit status for this error, see [`ErrorCode::exit_code`]
+    pub fn exit_code(&self) -> i32 {
+        cli_code(self).exit_code()
+    }
+}
+
+fn io_code(error: &std::io::Error) -> ErrorCode {
+    use std::io::ErrorKind;
+    match error.kind() {
+        ErrorKind::NotFound => ErrorCode::NotFound,
+        ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+        ErrorKind::AlreadyExists => ErrorCode::AlreadyExists,
+        ErrorKind::TimedOut => ErrorCode::Timeout,
+        ErrorKind::InvalidInput => ErrorCode::InvalidInput,
+        ErrorKind::InvalidData => ErrorCode::Integrity,
+        ErrorKind::Interrupted => ErrorCode::Cancelled,
+        ErrorKind::ConnectionRefused
+        | ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::NotConnected
+        | ErrorKind::AddrNotAvailable
+        | ErrorKind::BrokenPipe => ErrorCode::Unavailable,
+        ErrorKind::Unsupported => ErrorCode::Unsupported,
+        ErrorKind::OutOfMemory => ErrorCode::ResourceExhausted,
+        _ => ErrorCode::Io,
+    }
+}
+
+fn transport_code(error: &TransportError) -> ErrorCode {
+    match error {
+        TransportError::ConnectionFailed { .. }
+        | TransportError::NatTraversalFailed { .. }
+        | TransportError::RelayFailed { .. }
+        | TransportError::NetworkUnreachable { .. }
+        | TransportError::TransportNotAvailable
+        | TransportError::ShutdownInProgress => ErrorCode::Unavailable,
+        TransportError::NegotiationTimeout | TransportError::ConnectionTimeout { .. } => {
+            ErrorCode::Timeout
+        }
+        TransportError::UnsupportedProtocol { .. } | TransportError::IncompatiblePeer { .. } => {
+            ErrorCode::Unsupported
+        }
+        TransportError::Io(e) => io_code(e),
+        TransportError::Serialization(_) | TransportError::ProtocolVersionMismatch { .. } => {
+            ErrorCode::Protocol
+        }
+        TransportError::InvalidPeerAddress | TransportError::InvalidRoute { .. } => {
+            ErrorCode::InvalidInput
+        }
+        TransportError::ConnectionExists => ErrorCode::AlreadyExists,
+        TransportError::ConnectionNotFound => ErrorCode::NotFound,
+        TransportError::ResourceLimitExceeded { .. }
+        | TransportError::BandwidthLimitExceeded { .. }
+        | TransportError::ConnectionPoolExhausted { .. } => ErrorCode::ResourceExhausted,
+        TransportError::AuthenticationFailed { .. }
+        | TransportError::CertificateValidationFailed { .. } => ErrorCode::Unauthenticated,
+        TransportError::SecurityError { .. } => ErrorCode::PermissionDenied,
+        TransportError::ConfigurationError { .. } | TransportError::Configuration(_) => {
+            ErrorCode::Configuration
+        }
+        TransportError::Quic(_) | TransportError::WebRTC(_) | TransportError::WebSocket(_) => {
+            ErrorCode::Unavailable
+        }
+    }
+}
+
+fn discovery_code(error: &DiscoveryError) -> ErrorCode {
+    match error {
+        DiscoveryError::Network(_)
+        | DiscoveryError::Bluetooth(_)
+        | DiscoveryError::ServiceUnavailable { .. }
+        | DiscoveryError::TransientError { .. } => ErrorCode::Unavailable,
+        DiscoveryError::StrategyUnavailable { .. } => ErrorCode::Unsupported,
+        DiscoveryError::Timeout { .. } => ErrorCode::Timeout,
+        DiscoveryError::InvalidServiceRecord { .. }
+        | DiscoveryError::Parse(_)
+        | DiscoveryError::ProtocolError { .. } => ErrorCode::Protocol,
+        DiscoveryError::Configuration(_) => ErrorCode::Configuration,
+        DiscoveryError::ServiceConflict { .. } => ErrorCode::AlreadyExists,
+        DiscoveryError::PermissionDenied { .. } => ErrorCode::PermissionDenied,
+        DiscoveryError::ResourceExhausted { .. } | DiscoveryError::RateLimitExceeded { .. } => {
+            ErrorCode::ResourceExhausted
+        }
+        DiscoveryError::AuthenticationFailed { .. } => ErrorCode::Unauthenticated,
+        DiscoveryError::MultipleErrors { errors } => errors
+            .first()
+            .map(discovery_code)
+            .unwrap_or(ErrorCode::Internal),
+        DiscoveryError::FatalError { .. }
+        | DiscoveryError::InitializationFailed { .. }
+        | DiscoveryError::ShutdownError { .. } => ErrorCode::Internal,
+    }
+}
+
+#[cfg(feature = "clipboard")]
+fn clipboard_code(error: &ClipboardError) -> ErrorCode {
+    match error {
+        ClipboardError::PlatformError { .. } => ErrorCode::Unavailable,
+        ClipboardError::PermissionError { .. } | ClipboardError::PrivacyError { .. } => {
+            ErrorCode::PermissionDenied
+        }
+        ClipboardError::ContentError { .. } => ErrorCode::InvalidInput,
+        ClipboardError::SyncError { .. } => ErrorCode::Unavailable,
+        ClipboardError::SizeError { .. } => ErrorCode::ResourceExhausted,
+        ClipboardError::FormatError { .. } => ErrorCode::Unsupported,
+        ClipboardError::IoError { .. } | ClipboardError::DatabaseError { .. } => ErrorCode::Io,
+        ClipboardError::SerializationError { .. } => ErrorCode::Protocol,
+        ClipboardError::TimeoutError { .. } => ErrorCode::Timeout,
+        ClipboardError::ConfigError { .. } => ErrorCode::Configuration,
+        ClipboardError::InternalError { .. } => ErrorCode::Internal,
+    }
+}
+
+fn file_transfer_code(error: &FileTransferError) -> ErrorCode {
+    match error {
+        FileTransferError::ScanError { source, .. }
+        | FileTransferError::ChecksumError { source, .. }
+        | FileTransferError::IoError { source, .. } => io_code(source),
+        FileTransferError::InvalidManifest { .. }
+        | FileTransferError::InvalidResumeToken { .. }
+        | FileTransferError::InvalidQueueOperation { .. }
+        | FileTransferError::InvalidNegotiation { .. }
+        | FileTransferError::InvalidPath { .. }
+        | FileTransferError::UnsafeFileName { .. } => ErrorCode::InvalidInput,
+        FileTransferError::FileExists { .. } => ErrorCode::AlreadyExists,
+        FileTransferError::ManifestVerificationFailed { .. }
+        | FileTransferError::ChecksumMismatch { .. }
+        | FileTransferError::ChunkVerificationFailed { .. }
+        | FileTransferError::CorruptionDetected { .. }
+        | FileTransferError::IntegrityError(_) => ErrorCode::Integrity,
+        FileTransferError::TransportNegotiationFailed { .. }
+        | FileTransferError::NetworkError { .. }
+        | FileTransferError::TransportError(_) => ErrorCode::Unavailable,
+        FileTransferError::UnsupportedTransport { .. }
+        | FileTransferError::UnsupportedOperation { .. } => ErrorCode::Unsupported,
+        FileTransferError::InsufficientDiskSpace { .. } => ErrorCode::ResourceExhausted,
+        FileTransferError::PermissionDenied { .. }
+        | FileTransferError::PeerNotTrusted { .. }
+        | FileTransferError::MalwareDetected { .. } => ErrorCode::PermissionDenied,
+        FileTransferError::ScanFailed { .. } => ErrorCode::Unavailable,
+        FileTransferError::SecurityError(_)
+        | FileTransferError::EncryptionFailed { .. }
+        | FileTransferError::DecryptionFailed { .. } => ErrorCode::Integrity,
+        FileTransferError::AuthenticationFailed { .. } => ErrorCode::Unauthenticated,
+        FileTransferError::ResumeTokenExpired | FileTransferError::ResumeError { .. } => {
+            ErrorCode::InvalidInput
+        }
+        FileTransferError::SessionNotFound { .. } | FileTransferError::QueueItemNotFound { .. } => {
+            ErrorCode::NotFound
+        }
+        FileTransferError::TransferInProgress { .. } => ErrorCode::AlreadyExists,
+        FileTransferError::TransferCancelled => ErrorCode::Cancelled,
+        FileTransferError::TransferTimeout => ErrorCode::Timeout,
+        FileTransferError::CompressionError(_) | FileTransferError::DecompressionError(_) => {
+            ErrorCode::Integrity
+        }
+        FileTransferError::InvalidConfiguration { .. } => ErrorCode::Configuration,
+        FileTransferError::InternalError(_) | FileTransferError::Other(_) => ErrorCode::Internal,
+    }
+}
+
+fn security_code(error: &SecurityError) -> ErrorCode {
+    match error {
+        SecurityError::Identity(e) => match e {
+            IdentityError::InvalidPeerId(_) => ErrorCode::InvalidInput,
+            IdentityError::Corrupted(_) => ErrorCode::Integrity,
+            _ => ErrorCode::Io,
+        },
+        SecurityError::Trust(e) => match e {
+            TrustError::PeerNotFound(_) => ErrorCode::NotFound,
+            TrustError::PairingExpired => ErrorCode::Timeout,
+            TrustError::InvalidPairingCode => ErrorCode::InvalidInput,
+            TrustError::DatabaseError(_) => ErrorCode::Io,
+            _ => ErrorCode::PermissionDenied,
+        },
+        SecurityError::Encryption(e) => match e {
+            EncryptionError::SessionNotFound(_) => ErrorCode::NotFound,
+            EncryptionError::SessionExpired(_) => ErrorCode::Unauthenticated,
+            EncryptionError::AuthenticationFailed => ErrorCode::Integrity,
+            EncryptionError::ReplayedNonce => ErrorCode::Integrity,
+            _ => ErrorCode::Internal,
+        },
+        SecurityError::Policy(e) => match e {
+            PolicyError::RateLimitExceeded => ErrorCode::ResourceExhausted,
+            _ => ErrorCode::PermissionDenied,
+        },
+        SecurityError::Authentication(_) => ErrorCode::Unauthenticated,
+        SecurityError::PolicyViolation(_) => ErrorCode::PermissionDenied,
+        SecurityError::Generic(_) | SecurityError::Other(_) => ErrorCode::Internal,
+    }
+}
+
+fn command_code(error: &CommandError) -> ErrorCode {
+    match error {
+        CommandError::AuthorizationDenied(_)
+        | CommandError::PermissionError(_)
+        | CommandError::SecurityError(_) => ErrorCode::PermissionDenied,
+        CommandError::AuthorizationTimeout | CommandError::Timeout(_) => ErrorCode::Timeout,
+        CommandError::ResourceLimitExceeded(_) => ErrorCode::ResourceExhausted,
+        CommandError::IoError(e) => io_code(e),
+        CommandError::SerializationError(_) => ErrorCode::Protocol,
+        CommandError::TransportError(_) => ErrorCode::Unavailable,
+        CommandError::InvalidRequest(_) | CommandError::ValidationError(_) => {
+            ErrorCode::InvalidInput
+        }
+        CommandError::CommandNotFound(_) | CommandError::TemplateNotFound(_) => {
+            ErrorCode::NotFound
+        }
+        CommandError::PlatformError(_) => ErrorCode::Unsupported,
+        CommandError::StorageError(_) => ErrorCode::Io,
+        CommandError::SandboxError(_)
+        | CommandError::ExecutionError(_)
+        | CommandError::ScriptError(_)
+        | CommandError::SystemInfoError(_)
+        | CommandError::NotificationError(_)
+        | CommandError::ScheduleError(_)
+        | CommandError::Internal(_) => ErrorCode::Internal,
+    }
+}
+
+fn messaging_code(error: &MessagingError) -> ErrorCode {
+    match error {
+        MessagingError::PeerNotTrusted { .. } => ErrorCode::PermissionDenied,
+        MessagingError::InvalidPeer { .. }
+        | MessagingError::EmptyMessage
+        | MessagingError::NoRecipients
+        | MessagingError::MessageTooLarge { .. }
+        | MessagingError::InvalidStream { .. } => ErrorCode::InvalidInput,
+        MessagingError::UnknownMessage(_) => ErrorCode::NotFound,
+        MessagingError::Trust(_) => ErrorCode::Internal,
+        MessagingError::Encryption { .. } | MessagingError::HistoryCorrupt(_) => ErrorCode::Integrity,
+        MessagingError::Transport { source, .. } => transport_code(source),
+        MessagingError::Io { source, .. } => io_code(source),
+        MessagingError::Serialization(_) => ErrorCode::Protocol,
+    }
+}
+
+fn browse_code(error: &BrowseError) -> ErrorCode {
+    match error {
+        BrowseError::PeerNotTrusted { .. }
+        | BrowseError::PermissionDenied { .. }
+        | BrowseError::ReadOnlyShare(_)
+        | BrowseError::OutsideShare(_) => ErrorCode::PermissionDenied,
+        BrowseError::InvalidPeer { .. }
+        | BrowseError::InvalidPath { .. }
+        | BrowseError::NotADirectory(_)
+        | BrowseError::InvalidStream { .. } => ErrorCode::InvalidInput,
+        BrowseError::InvalidShare { .. } => ErrorCode::Configuration,
+        BrowseError::UnknownShare(_) | BrowseError::NotFound(_) => ErrorCode::NotFound,
+        BrowseError::Remote { kind, .. } => match kind {
+            RemoteErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            RemoteErrorKind::NotFound => ErrorCode::NotFound,
+            RemoteErrorKind::InvalidPath => ErrorCode::InvalidInput,
+            RemoteErrorKind::Internal => ErrorCode::Internal,
+        },
+        BrowseError::UnexpectedResponse(_) | BrowseError::Serialization(_) => ErrorCode::Protocol,
+        BrowseError::Trust(_) => ErrorCode::Internal,
+        BrowseError::Transfer(_) | BrowseError::Mount(_) => ErrorCode::Unavailable,
+        BrowseError::Transport { source, .. } => transport_code(source),
+        BrowseError::Io { source, .. } => io_code(source),
+    }
+}
+
+#[cfg(feature = "browser-support")]
+fn browser_code(error: &BrowserSupportError) -> ErrorCode {
+    match error {
+        BrowserSupportError::WebRTCError { .. } | BrowserSupportError::NetworkError { .. } => {
+            ErrorCode::Unavailable
+        }
+        BrowserSupportError::BrowserCompatibilityError { .. } => ErrorCode::Unsupported,
+        BrowserSupportError::SessionNotFound(_) => ErrorCode::NotFound,
+        BrowserSupportError::AuthenticationFailed(_)
+        | BrowserSupportError::CertificateValidationFailed(_) => ErrorCode::Unauthenticated,
+        BrowserSupportError::PermissionDenied(_)
+        | BrowserSupportError::SecurityError { .. }
+        | BrowserSupportError::SecurityPolicyViolation(_)
+        | BrowserSupportError::HTTPSRequired(_) => ErrorCode::PermissionDenied,
+        BrowserSupportError::EncryptionFailed(_) | BrowserSupportError::DecryptionFailed(_) => {
+            ErrorCode::Integrity
+        }
+        BrowserSupportError::ConfigurationError { .. } => ErrorCode::Configuration,
+        _ => ErrorCode::Internal,
+    }
+}
+
+#[cfg(feature = "streaming")]
+fn streaming_code(error: &StreamError) -> ErrorCode {
+    match error {
+        StreamError::Permission(_) => ErrorCode::PermissionDenied,
+        StreamError::Network(_) => ErrorCode::Unavailable,
+        StreamError::Resource(_) => ErrorCode::ResourceExhausted,
+        StreamError::SessionNotFound(_) | StreamError::DeviceNotFound(_) => ErrorCode::NotFound,
+        StreamError::Configuration(_) => ErrorCode::Configuration,
+        StreamError::Unsupported(_) | StreamError::HardwareAcceleration(_) => {
+            ErrorCode::Unsupported
+        }
+        StreamError::InvalidState(_) => ErrorCode::InvalidInput,
+        StreamError::Timeout(_) => ErrorCode::Timeout,
+        StreamError::Io(e) => io_code(e),
+        _ => ErrorCode::Internal,
+    }
+}
+
+fn cli_code(error: &CLIError) -> ErrorCode {
+    match error {
+        CLIError::ParseError(_)
+        | CLIError::InvalidCommand(_)
+        | CLIError::MissingArgument(_)
+        | CLIError::InvalidArgumentValue { .. }
+        | CLIError::InputRequired(_) => ErrorCode::InvalidInput,
+        CLIError::ConfigError(_) => ErrorCode::Configuration,
+        CLIError::IOError(e) => io_code(e),
+        CLIError::Cancelled => ErrorCode::Cancelled,
+        CLIError::PermissionDenied(_) => ErrorCode::PermissionDenied,
+        CLIError::NotFound(_) => ErrorCode::NotFound,
+        CLIError::Timeout(_) => ErrorCode::Timeout,
+        CLIError::IntegrationError(_) => ErrorCode::Unavailable,
+        CLIError::TUIError(_)
+        | CLIError::ExecutionError(_)
+        | CLIError::FormatError(_)
+        | CLIError::Other(_) => ErrorCode::Internal,
+    }
+}
+
+fn api_code(kind: &crate::developer_api::core::error::ErrorKind) -> ErrorCode {
+    use crate::developer_api::core::error::ErrorKind;
+    match kind {
+        ErrorKind::DiscoveryError { .. }
+        | ErrorKind::ConnectionError { .. }
+        | ErrorKind::NetworkError { .. } => ErrorCode::Unavailable,
+        ErrorKind::TransferError { .. } | ErrorKind::PluginError { .. } => ErrorCode::Internal,
+        ErrorKind::ConfigError { .. } => ErrorCode::Configuration,
+        ErrorKind::StateError { .. } | ErrorKind::ParameterError { .. } => ErrorCode::InvalidInput,
+        ErrorKind::SecurityError { .. } | ErrorKind::PermissionDenied { .. } => {
+            ErrorCode::PermissionDenied
+        }
+        ErrorKind::IoError { .. } => ErrorCode::Io,
+        ErrorKind::SerializationError { .. } => ErrorCode::Protocol,
+        ErrorKind::TimeoutError { .. } => ErrorCode::Timeout,
+        ErrorKind::ResourceExhausted { .. } => ErrorCode::ResourceExhausted,
+        ErrorKind::NotFound { .. } => ErrorCode::NotFound,
+        ErrorKind::AlreadyExists { .. } => ErrorCode::AlreadyExists,
+        ErrorKind::Cancelled { .. } => ErrorCode::Cancelled,
+        ErrorKind::Other { .. } => ErrorCode::Internal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn fails_with_transport() -> KizunaResult<()> {
+        Err(TransportError::ConnectionTimeout { timeout: Duration::from_secs(5) })?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_question_mark_converts_subsystem_errors() {
+        let error = fails_with_transport().unwrap_err();
+        assert_eq!(error.error_code(), "transport.timeout");
+        assert!(error.is_retryable());
+        assert_eq!(error.exit_code(), 75);
+    }
+
+    #[test]
+    fn test_security_sub_errors_keep_subsystem() {
+        let error = KizunaError::from(TrustError::NotTrusted("peer-1".to_string()));
+        assert_eq!(error.subsystem(), "security");
+        assert_eq!(error.code(), ErrorCode::PermissionDenied);
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn test_file_transfer_io_uses_io_kind() {
+        let error = KizunaError::from(FileTransferError::IoError {
+            path: "missing.txt".into(),
+            source: std::io::Error::new(std::io::ErrorKind::NotFound, "gone"),
+        });
+        assert_eq!(error.error_code(), "file_transfer.not_found");
+    }
+
+    #[test]
+    fn test_report_json() {
+        let error = KizunaError::invalid_input("port must be non-zero");
+        let json = error.report().to_json();
+        assert_eq!(json["error"]["code"], "core.invalid_input");
+        assert_eq!(json["error"]["category"], "invalid_input");
+        assert_eq!(json["error"]["retryable"], false);
+        assert_eq!(json["error"]["exit_code"], 64);
+    }
+
+    #[test]
+    fn test_cli_error_round_trip() {
+        let cli = CLIError::from(KizunaError::from(CLIError::Cancelled));
+        assert!(matches!(cli, CLIError::Cancelled));
+
+        #[cfg(feature = "clipboard")]
+        {
+            let cli = CLIError::from(KizunaError::from(ClipboardError::size(10, 5)));
+            assert!(cli.to_string().contains("clipboard.resource_exhausted"));
+        }
+
+        let cli = CLIError::from(KizunaError::from(TrustError::NotTrusted("peer-1".to_string())));
+        assert!(matches!(cli, CLIError::PermissionDenied(_)));
+        assert_eq!(cli.exit_code(), 77);
+    }
+
+    #[test]
+    fn test_cli_exit_codes_by_failure_class() {
+        assert_eq!(CLIError::MissingArgument("peer".to_string()).exit_code(), 64);
+        assert_eq!(CLIError::input_required("confirm wipe").exit_code(), 64);
+        assert_eq!(CLIError::not_found("recording abc").exit_code(), 66);
+        assert_eq!(CLIError::transfer("peer unreachable").exit_code(), 69);
+        assert_eq!(CLIError::timeout("no answer from laptop").exit_code(), 75);
+        assert_eq!(CLIError::permission_denied("peer is not trusted").exit_code(), 77);
+        assert_eq!(CLIError::config("bad theme").exit_code(), 78);
+        assert_eq!(CLIError::Cancelled.exit_code(), 130);
+    }
+}
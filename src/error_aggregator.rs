@@ -0,0 +1,315 @@
+//! Deduplicated, rate-limited error reporting
+//!
+//! A flapping network produces the same transient error many times a second,
+//! which buries everything else in the logs. Errors passed to an
+//! [`ErrorAggregator`] are always logged in full at debug level, but only
+//! surfaced to the user (at warn level) when:
+//!
+//! - the same error, by `"<subsystem>.<code>"` and message, has not already been
+//!   surfaced since the last summary, and
+//! - fewer than [`AggregatorConfig::per_category_limit`] errors of the same
+//!   [`ErrorCode`] were surfaced in the current [`AggregatorConfig::window`].
+//!
+//! Everything held back is counted, and every
+//! [`AggregatorConfig::summary_interval`] a single [`ErrorSummary`] line reports
+//! how many errors were suppressed and which ones. The daemon logs summaries from
+//! [`ErrorAggregator::run`]; the TUI pulls them into its log panel.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::{ErrorCode, ErrorReport, KizunaError};
+
+/// Most distinct errors named in a summary line
+const SUMMARY_ENTRIES: usize = 3;
+
+/// Deduplication and rate limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregatorConfig {
+    /// Period over which the per-category limit applies
+    pub window: Duration,
+    /// Errors of one category surfaced per window
+    pub per_category_limit: u32,
+    /// How often suppressed errors are summarized
+    pub summary_interval: Duration,
+}
+
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            per_category_limit: 5,
+            summary_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// What happened to a recorded error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposition {
+    /// Shown to the user
+    Reported,
+    /// Already shown since the last summary
+    Duplicate,
+    /// Its category hit the rate limit
+    RateLimited,
+}
+
+/// Errors held back since the previous summary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorSummary {
+    /// Time covered by the summary
+    pub period: Duration,
+    /// Number of errors suppressed
+    pub suppressed: u64,
+    /// Suppressed errors, most frequent first
+    pub entries: Vec<SummaryEntry>,
+}
+
+/// One distinct suppressed error
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SummaryEntry {
+    /// Stable `"<subsystem>.<code>"` identifier
+    pub code: String,
+    pub message: String,
+    pub count: u64,
+}
+
+impl std::fmt::Display for ErrorSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Suppressed {} repeated error{} in the last {}s:",
+            self.suppressed,
+            if self.suppressed == 1 { "" } else { "s" },
+            self.period.as_secs()
+        )?;
+        for (i, entry) in self.entries.iter().take(SUMMARY_ENTRIES).enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{}{} x{} ({})", separator, entry.code, entry.count, entry.message)?;
+        }
+        if self.entries.len() > SUMMARY_ENTRIES {
+            write!(f, " and {} more", self.entries.len() - SUMMARY_ENTRIES)?;
+        }
+        Ok(())
+    }
+}
+
+struct AggregatorState {
+    window_start: Instant,
+    surfaced_in_window: HashMap<ErrorCode, u32>,
+    period_start: Instant,
+    /// Errors surfaced since the last summary, by key
+    surfaced: HashSet<String>,
+    /// Errors suppressed since the last summary, by key
+    suppressed: HashMap<String, SummaryEntry>,
+}
+
+/// Deduplicates and rate-limits errors before they reach the user
+pub struct ErrorAggregator {
+    config: AggregatorConfig,
+    state: StdMutex<AggregatorState>,
+}
+
+impl ErrorAggregator {
+    pub fn new(config: AggregatorConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            config,
+            state: StdMutex::new(AggregatorState {
+                window_start: now,
+                surfaced_in_window: HashMap::new(),
+                period_start: now,
+                surfaced: HashSet::new(),
+                suppressed: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Deduplication and rate limits in use
+    pub fn config(&self) -> &AggregatorConfig {
+        &self.config
+    }
+
+    /// Record an error, logging it at warn level unless it is suppressed
+    ///
+    /// `context` says what was being done, e.g. "mDNS announcement".
+    pub fn record(&self, context: &str, error: &KizunaError) -> Disposition {
+        self.record_report(context, &error.report())
+    }
+
+    /// Record an already classified error
+    pub fn record_report(&self, context: &str, report: &ErrorReport) -> Disposition {
+        log::debug!("{}: {} [{}]", context, report.message, report.code);
+
+        let disposition = self.admit(report);
+        if disposition == Disposition::Reported {
+            log::warn!("{}: {}", context, report.message);
+        }
+        disposition
+    }
+
+    /// Classify an error without logging it, for callers with their own output
+    pub fn admit(&self, report: &ErrorReport) -> Disposition {
+        self.classify(report, Instant::now())
+    }
+
+    /// Summary of suppressed errors, if the summary interval has passed
+    ///
+    /// Returns `None` when nothing was suppressed. Starts a new period either
+    /// way once the interval has passed, so a persistent error is surfaced again.
+    pub fn summary_if_due(&self) -> Option<ErrorSummary> {
+        self.summary_at(Instant::now(), false)
+    }
+
+    /// Summary of errors suppressed so far, starting a new period
+    pub fn take_summary(&self) -> Option<ErrorSummary> {
+        self.summary_at(Instant::now(), true)
+    }
+
+    /// Log a summary every summary interval until `token` is cancelled
+    pub async fn run(&self, token: CancellationToken) {
+        let mut interval = tokio::time::interval(self.config.summary_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Some(summary) = self.summary_if_due() {
+                        log::warn!("{}", summary);
+                    }
+                }
+                _ = token.cancelled() => break,
+            }
+        }
+        if let Some(summary) = self.take_summary() {
+            log::warn!("{}", summary);
+        }
+    }
+
+    fn classify(&self, report: &ErrorReport, now: Instant) -> Disposition {
+        let mut state = self.state.lock().unwrap();
+        if now.duration_since(state.window_start) >= self.config.window {
+            state.window_start = now;
+            state.surfaced_in_window.clear();
+        }
+
+        let key = format!("{}\u{0}{}", report.code, report.message);
+        let disposition = if state.surfaced.contains(&key) {
+            Disposition::Duplicate
+        } else if state.surfaced_in_window.get(&report.category).copied().unwrap_or(0)
+            >= self.config.per_category_limit
+        {
+            Disposition::RateLimited
+        } else {
+            Disposition::Reported
+        };
+
+        if disposition == Disposition::Reported {
+            state.surfaced.insert(key);
+            *state.surfaced_in_window.entry(report.category).or_insert(0) += 1;
+        } else {
+            state
+                .suppressed
+                .entry(key)
+                .or_insert_with(|| SummaryEntry {
+                    code: report.code.clone(),
+                    message: report.message.clone(),
+                    count: 0,
+                })
+                .count += 1;
+        }
+        disposition
+    }
+
+    fn summary_at(&self, now: Instant, force: bool) -> Option<ErrorSummary> {
+        let mut state = self.state.lock().unwrap();
+        let period = now.duration_since(state.period_start);
+        if !force && period < self.config.summary_interval {
+            return None;
+        }
+
+        state.period_start = now;
+        state.surfaced.clear();
+        let mut entries: Vec<SummaryEntry> = state.suppressed.drain().map(|(_, entry)| entry).collect();
+        if entries.is_empty() {
+            return None;
+        }
+        entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.code.cmp(&b.code)));
+
+        Some(ErrorSummary {
+            period,
+            suppressed: entries.iter().map(|entry| entry.count).sum(),
+            entries,
+        })
+    }
+}
+
+impl Default for ErrorAggregator {
+    fn default() -> Self {
+        Self::new(AggregatorConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::TransportError;
+
+    fn timeout() -> ErrorReport {
+        KizunaError::from(TransportError::ConnectionTimeout { timeout: Duration::from_secs(5) }).report()
+    }
+
+    fn unavailable(peer: &str) -> ErrorReport {
+        KizunaError::from(TransportError::ConnectionFailed { reason: format!("{} unreachable", peer) }).report()
+    }
+
+    #[test]
+    fn test_repeats_are_summarized() {
+        let aggregator = ErrorAggregator::new(AggregatorConfig {
+            per_category_limit: 2,
+            ..AggregatorConfig::default()
+        });
+        let now = Instant::now();
+
+        assert_eq!(aggregator.classify(&timeout(), now), Disposition::Reported);
+        for _ in 0..9 {
+            assert_eq!(aggregator.classify(&timeout(), now), Disposition::Duplicate);
+        }
+        // Timeouts do not count against the unavailable limit
+        assert_eq!(aggregator.classify(&unavailable("a"), now), Disposition::Reported);
+        assert_eq!(aggregator.classify(&unavailable("b"), now), Disposition::Reported);
+        assert_eq!(aggregator.classify(&unavailable("c"), now), Disposition::RateLimited);
+
+        assert!(aggregator.summary_at(now + Duration::from_secs(1), false).is_none());
+        let summary = aggregator.summary_at(now + Duration::from_secs(60), false).unwrap();
+        assert_eq!(summary.entries[0].code, timeout().code);
+        assert_eq!(summary.entries[0].count, 9);
+        assert_eq!(summary.suppressed, 10);
+        assert!(summary.to_string().starts_with("Suppressed 10 repeated errors in the last 60s: transport.timeout x9"));
+
+        // A new period surfaces a persistent error again
+        assert_eq!(aggregator.classify(&timeout(), now + Duration::from_secs(60)), Disposition::Reported);
+        assert!(aggregator.take_summary().is_none());
+    }
+
+    #[test]
+    fn test_category_limit_resets_each_window() {
+        let aggregator = ErrorAggregator::new(AggregatorConfig {
+            window: Duration::from_secs(10),
+            per_category_limit: 1,
+            summary_interval: Duration::from_secs(600),
+        });
+        let now = Instant::now();
+
+        assert_eq!(aggregator.classify(&unavailable("a"), now), Disposition::Reported);
+        assert_eq!(aggregator.classify(&unavailable("b"), now), Disposition::RateLimited);
+        assert_eq!(aggregator.classify(&unavailable("c"), now + Duration::from_secs(10)), Disposition::Reported);
+
+        let summary = aggregator.take_summary().unwrap();
+        assert_eq!(summary.suppressed, 1);
+        assert_eq!(summary.entries[0].message, unavailable("b").message);
+    }
+}
@@ -11,15 +11,26 @@ use crate::file_transfer::{
     progress::{ProgressTracker, ProgressCallback, EventCallback, TransferEvent},
     notification::{NotificationManager, NotificationCallback, TransferStatus, FileStatus, FileTransferState},
     incoming::{IncomingTransferManager, IncomingTransferRequest, TransferRequestDetails},
-    session::SessionManager,
+    negotiation::TransferTerms,
+    dry_run::TransferPlan,
+    chunk::ChunkEngineImpl,
+    fanout::{FanOutSender, FanOutTarget, SwarmPlan},
+    manifest::ManifestBuilderImpl,
+    pipe::{self, PipeEntry, PipeReceiver, PipeSender, PipeSummary},
+    session::{SessionManager, SessionRecovery},
     transport::TransportNegotiatorImpl,
     TransportNegotiator,
     FileTransfer, TransferManager,
 };
+use crate::error::KizunaResult;
 use crate::security::Security;
+use crate::shutdown::{ShutdownHook, ShutdownReason, ShutdownStage};
+use crate::transport::{ConnectionProvider, MuxStream};
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
 
 /// Unified file transfer system
 pub struct FileTransferSystem {
@@ -39,6 +50,10 @@ pub struct FileTransferSystem {
     incoming_manager: Arc<IncomingTransferManager>,
     /// Global bandwidth limit
     bandwidth_limit: Arc<tokio::sync::RwLock<Option<u64>>>,
+    /// Bandwidth limits for single peers, applied within the global limit
+    peer_bandwidth_limits: Arc<tokio::sync::RwLock<HashMap<PeerId, u64>>>,
+    /// Peers whose transfers are compressed, or not, against the default
+    peer_compression: Arc<tokio::sync::RwLock<HashMap<PeerId, bool>>>,
 }
 
 impl FileTransferSystem {
@@ -47,9 +62,9 @@ impl FileTransferSystem {
         security_system: Arc<dyn Security>,
         session_persistence_dir: PathBuf,
     ) -> Self {
+        let session_manager = Arc::new(SessionManager::new(session_persistence_dir, security_system.storage()));
         let security = Arc::new(FileTransferSecurity::new(security_system));
         let transport = Arc::new(FileTransferTransport::new());
-        let session_manager = Arc::new(SessionManager::new(session_persistence_dir));
         let transport_negotiator = Arc::new(TransportNegotiatorImpl::new());
         let progress_tracker = Arc::new(ProgressTracker::new());
         let notification_manager = Arc::new(NotificationManager::new());
@@ -64,9 +79,61 @@ impl FileTransferSystem {
             notification_manager,
             incoming_manager,
             bandwidth_limit: Arc::new(tokio::sync::RwLock::new(None)),
+            peer_bandwidth_limits: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            peer_compression: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
         }
     }
 
+    /// Cap transfers to one peer, or remove its cap with `None`
+    ///
+    /// The global limit still applies; the lower of the two wins.
+    pub async fn set_peer_bandwidth_limit(&self, peer_id: PeerId, limit: Option<u64>) {
+        let mut limits = self.peer_bandwidth_limits.write().await;
+        match limit {
+            Some(limit) => {
+                limits.insert(peer_id, limit);
+            }
+            None => {
+                limits.remove(&peer_id);
+            }
+        }
+    }
+
+    /// Turn compression on or off for transfers to one peer, or go back to
+    /// the default of compressing with `None`
+    pub async fn set_peer_compression(&self, peer_id: PeerId, compression: Option<bool>) {
+        let mut peers = self.peer_compression.write().await;
+        match compression {
+            Some(compression) => {
+                peers.insert(peer_id, compression);
+            }
+            None => {
+                peers.remove(&peer_id);
+            }
+        }
+    }
+
+    /// Whether transfers to a peer are compressed
+    pub async fn compression_for(&self, peer_id: &PeerId) -> bool {
+        self.peer_compression.read().await.get(peer_id).copied().unwrap_or(true)
+    }
+
+    /// Bandwidth limit for transfers to a peer
+    pub async fn bandwidth_limit_for(&self, peer_id: &PeerId) -> Option<u64> {
+        let global = *self.bandwidth_limit.read().await;
+        let peer = self.peer_bandwidth_limits.read().await.get(peer_id).copied();
+        match (global, peer) {
+            (Some(global), Some(peer)) => Some(global.min(peer)),
+            (global, peer) => global.or(peer),
+        }
+    }
+
+    /// Route transfer streams through a shared connection provider
+    pub fn with_connection_provider(mut self, provider: Arc<ConnectionProvider>) -> Self {
+        self.transport = Arc::new(FileTransferTransport::with_provider(provider));
+        self
+    }
+
     /// Get transport integration for adding connections
     pub fn transport(&self) -> &Arc<FileTransferTransport> {
         &self.transport
@@ -90,6 +157,11 @@ impl FileTransferSystem {
         Ok(())
     }
 
+    /// Transfers paused or failed while recovering from an unclean shutdown
+    pub async fn recovered_sessions(&self) -> SessionRecovery {
+        self.session_manager.last_recovery().await
+    }
+
     /// Register a progress callback
     pub async fn on_progress(&self, callback: ProgressCallback) {
         self.progress_tracker.register_progress_callback(callback).await;
@@ -183,6 +255,166 @@ impl FileTransferSystem {
         self.start_transfer(manifest, peer_id).await
     }
 
+    /// Send piped data of unknown length to a peer, such as stdin
+    ///
+    /// The receiver saves it as `name`, or "stdin" without one. Data moves in
+    /// the background as it is read, once the session exists; the session
+    /// completes when `reader` reaches its end.
+    pub async fn send_pipe<R>(
+        &self,
+        mut reader: R,
+        name: Option<&str>,
+        peer_id: PeerId,
+    ) -> Result<TransferSession>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let manifest = pipe::pipe_manifest("local-peer".to_string(), name)?;
+        let pipe = manifest.pipe.clone().expect("pipe manifest has a pipe entry");
+
+        let session = self.start_transfer(manifest, peer_id.clone()).await?;
+        let mut stream = match self.transport.create_chunk_stream(&peer_id, session.transport).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = self.progress_tracker.fail_session(session.session_id, e.to_string()).await;
+                return Err(e);
+            }
+        };
+
+        let sender = PipeSender::new(
+            ChunkEngineImpl::new().with_hash_algorithm(session.manifest.hash_algorithm),
+            Arc::clone(&self.progress_tracker),
+        );
+        let session_id = session.session_id;
+        tokio::spawn(async move {
+            match sender.send(&mut reader, &pipe, session_id, stream.as_mut()).await {
+                Ok(summary) => log::info!("Piped {} bytes to {}", summary.bytes, peer_id),
+                Err(e) => log::warn!("Piped send to {} stopped: {}", peer_id, e),
+            }
+        });
+
+        Ok(session)
+    }
+
+    /// Write piped data a peer sends on `stream` to `writer` as it arrives
+    ///
+    /// The receiving side of [`send_pipe`](Self::send_pipe); `stream` is an
+    /// inbound stream on the file transfer channel. Returns once the sender's
+    /// end marker arrives.
+    pub async fn receive_pipe<W>(&self, stream: MuxStream, writer: &mut W) -> Result<(PipeEntry, PipeSummary)>
+    where
+        W: AsyncWrite + Unpin + Send,
+    {
+        let mut stream = FileTransferTransport::mux_chunk_stream(stream);
+        PipeReceiver::new(ChunkEngineImpl::new())
+            .receive_next(stream.as_mut(), writer)
+            .await
+    }
+
+    /// Work out what sending `paths` to a peer would do, without sending
+    ///
+    /// Builds the real manifest, checks the peer is trusted and negotiates
+    /// and benchmarks the transport, but creates no session. A single
+    /// directory is planned as a recursive folder send.
+    pub async fn plan_send(&self, paths: Vec<PathBuf>, peer_id: PeerId) -> Result<TransferPlan> {
+        let manifest = Self::build_send_manifest(paths).await?;
+
+        self.security.verify_peer_trust(&peer_id).await?;
+
+        let protocol = self
+            .transport_negotiator
+            .negotiate_transport(peer_id.clone(), manifest.total_size)
+            .await?;
+        // Without a measurement the plan still lists files and conflicts
+        let metrics = match self.transport_negotiator.benchmark_transport(protocol, peer_id.clone()).await {
+            Ok(metrics) => Some(metrics),
+            Err(e) => {
+                log::debug!("Could not benchmark {} to {}: {}", protocol.as_str(), peer_id, e);
+                None
+            }
+        };
+        let bandwidth_limit = self.bandwidth_limit_for(&peer_id).await;
+
+        Ok(TransferPlan::new(peer_id, manifest, protocol, metrics.as_ref(), bandwidth_limit))
+    }
+
+    /// Send the same files to several peers, reading each chunk once
+    ///
+    /// Each peer gets its own session, so progress and failures are reported
+    /// per peer. With `swarm`, peers on the local network share chunks with
+    /// each other rather than each taking the full upload. Returns every
+    /// peer with its session, or the reason it could not be started; data
+    /// moves in the background once the sessions exist.
+    pub async fn send_files_to(
+        &self,
+        paths: Vec<PathBuf>,
+        peers: Vec<PeerId>,
+        swarm: bool,
+    ) -> Result<Vec<(PeerId, Result<TransferSession>)>> {
+        let mut manifest = Self::build_send_manifest(paths).await?;
+        if swarm {
+            let mut lan_peers = Vec::new();
+            for peer in &peers {
+                if self.transport.is_lan_peer(peer).await {
+                    lan_peers.push(peer.clone());
+                }
+            }
+            manifest.swarm = SwarmPlan::new(lan_peers, Chunk::DEFAULT_SIZE);
+        }
+
+        let mut started = Vec::new();
+        let mut targets = Vec::new();
+        for peer_id in peers {
+            let session = match self.start_transfer(manifest.clone(), peer_id.clone()).await {
+                Ok(session) => session,
+                Err(e) => {
+                    started.push((peer_id, Err(e)));
+                    continue;
+                }
+            };
+
+            match self.transport.create_chunk_stream(&peer_id, session.transport).await {
+                Ok(stream) => targets.push(FanOutTarget {
+                    peer_id: peer_id.clone(),
+                    session_id: session.session_id,
+                    stream,
+                }),
+                Err(e) => {
+                    let _ = self.progress_tracker.fail_session(session.session_id, e.to_string()).await;
+                }
+            }
+            started.push((peer_id, Ok(session)));
+        }
+
+        if !targets.is_empty() {
+            let sender = FanOutSender::new(
+                ChunkEngineImpl::new().with_hash_algorithm(manifest.hash_algorithm),
+                Arc::clone(&self.progress_tracker),
+            );
+            tokio::spawn(async move {
+                match sender.send(&manifest, targets).await {
+                    Ok(outcomes) => {
+                        let failed = outcomes.iter().filter(|o| !o.succeeded()).count();
+                        log::info!("Fan-out send finished: {} of {} peers failed", failed, outcomes.len());
+                    }
+                    Err(e) => log::warn!("Fan-out send stopped: {}", e),
+                }
+            });
+        }
+
+        Ok(started)
+    }
+
+    /// Build the manifest for a send; a single directory is sent recursively
+    async fn build_send_manifest(paths: Vec<PathBuf>) -> Result<TransferManifest> {
+        let builder = ManifestBuilderImpl::new("local-peer".to_string());
+        match paths.as_slice() {
+            [path] if path.is_dir() => builder.build_folder_manifest(path.clone(), true).await,
+            [path] => builder.build_file_manifest(path.clone()).await,
+            _ => builder.build_multi_file_manifest(paths).await,
+        }
+    }
+
     /// Build manifest for a single file
     async fn build_file_manifest(&self, _file_path: PathBuf) -> Result<TransferManifest> {
         // TODO: Implement actual manifest building
@@ -248,6 +480,14 @@ impl FileTransferSystem {
             .await
     }
 
+    /// Stop the file transfer system gracefully
+    ///
+    /// Running transfers are paused and every live session is written to disk so
+    /// the transfers can be resumed after restart. Returns the number paused.
+    pub async fn shutdown(&self) -> Result<usize> {
+        self.session_manager.suspend_all().await
+    }
+
     // Incoming transfer management methods
 
     /// Handle an incoming transfer request
@@ -302,7 +542,78 @@ impl FileTransferSystem {
             .await?;
 
         // Start the transfer session
-        let session = self.start_transfer(manifest, "incoming".to_string()).await?;
+        self.start_incoming_transfer(request_id, manifest).await
+    }
+
+    /// Accept only some of the files in an incoming transfer request
+    pub async fn accept_incoming_files(
+        &self,
+        request_id: TransferId,
+        files: Vec<PathBuf>,
+        download_location: PathBuf,
+    ) -> Result<TransferSession> {
+        let manifest = self.incoming_manager
+            .accept_files(request_id, files, download_location)
+            .await?;
+
+        self.start_incoming_transfer(request_id, manifest).await
+    }
+
+    /// Counter-propose different terms for an incoming transfer request
+    pub async fn counter_incoming_transfer(
+        &self,
+        request_id: TransferId,
+        terms: TransferTerms,
+    ) -> Result<()> {
+        self.incoming_manager.counter_request(request_id, terms).await
+    }
+
+    /// The sender took our counter-proposal; start the transfer on the
+    /// agreed terms
+    pub async fn incoming_counter_accepted(
+        &self,
+        request_id: TransferId,
+        download_location: PathBuf,
+    ) -> Result<TransferSession> {
+        let manifest = self.incoming_manager
+            .counter_accepted(request_id, download_location)
+            .await?;
+
+        self.start_incoming_transfer(request_id, manifest).await
+    }
+
+    /// The sender answered our counter-proposal with a revised offer
+    pub async fn incoming_offer_revised(
+        &self,
+        request_id: TransferId,
+        terms: TransferTerms,
+    ) -> Result<()> {
+        self.incoming_manager.offer_revised(request_id, terms).await
+    }
+
+    /// The sender turned down our counter-proposal
+    pub async fn incoming_counter_declined(
+        &self,
+        request_id: TransferId,
+        reason: Option<String>,
+    ) -> Result<()> {
+        self.incoming_manager.counter_declined(request_id, reason).await
+    }
+
+    /// Start the session for an accepted request and record how it was
+    /// negotiated
+    async fn start_incoming_transfer(
+        &self,
+        request_id: TransferId,
+        manifest: TransferManifest,
+    ) -> Result<TransferSession> {
+        let mut session = self.start_transfer(manifest, "incoming".to_string()).await?;
+
+        let negotiation = self.incoming_manager.get_request(request_id).await?.negotiation;
+        self.session_manager
+            .record_negotiation(session.session_id, negotiation.clone())
+            .await?;
+        session.negotiation = Some(negotiation);
 
         Ok(session)
     }
@@ -329,6 +640,23 @@ impl FileTransferSystem {
     }
 }
 
+#[async_trait]
+impl ShutdownHook for FileTransferSystem {
+    fn name(&self) -> &str {
+        "file transfer"
+    }
+
+    fn stage(&self) -> ShutdownStage {
+        ShutdownStage::Flush
+    }
+
+    async fn shutdown(&self, _reason: &ShutdownReason) -> KizunaResult<()> {
+        let paused = FileTransferSystem::shutdown(self).await?;
+        log::info!("Paused {} running transfers for resume after restart", paused);
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl FileTransfer for FileTransferSystem {
     async fn start_transfer(
@@ -346,10 +674,22 @@ impl FileTransfer for FileTransferSystem {
             .await?;
 
         // Create transfer session
-        let session = self
+        let mut session = self
             .session_manager
             .create_session(manifest.clone(), peer_id.clone(), protocol)
             .await?;
+        if let Some(limit) = self.bandwidth_limit_for(&peer_id).await {
+            self.session_manager
+                .set_bandwidth_limit(session.session_id, Some(limit))
+                .await?;
+            session.bandwidth_limit = Some(limit);
+        }
+        if !self.compression_for(&peer_id).await {
+            self.session_manager
+                .set_compression(session.session_id, false)
+                .await?;
+            session.compression = false;
+        }
 
         // Start progress tracking
         self.progress_tracker
@@ -547,6 +887,10 @@ mod tests {
         async fn add_trusted_peer(&self, _peer_id: SecurityPeerId, _nickname: String) -> SecurityResult<()> {
             Ok(())
         }
+
+        fn storage(&self) -> Option<crate::security::EncryptedStorage> {
+            None
+        }
     }
 
     async fn create_test_system() -> (FileTransferSystem, TempDir) {
@@ -598,6 +942,23 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_peer_bandwidth_limit_applies_within_global_limit() {
+        let (system, _temp_dir) = create_test_system().await;
+        system.set_peer_bandwidth_limit("nas".to_string(), Some(500_000)).await;
+
+        let session = system
+            .start_transfer(TransferManifest::new("test-sender".to_string()), "nas".to_string())
+            .await
+            .unwrap();
+        assert_eq!(session.bandwidth_limit, Some(500_000));
+        assert_eq!(system.bandwidth_limit_for(&"phone".to_string()).await, None);
+
+        system.set_bandwidth_limit(Some(200_000)).await.unwrap();
+        assert_eq!(system.bandwidth_limit_for(&"nas".to_string()).await, Some(200_000));
+        assert_eq!(system.bandwidth_limit_for(&"phone".to_string()).await, Some(200_000));
+    }
+
     #[tokio::test]
     async fn test_get_transfer_progress() {
         let (system, _temp_dir) = create_test_system().await;
@@ -4,18 +4,100 @@
 
 use crate::file_transfer::{
     error::{FileTransferError, Result},
+    fidelity,
+    hashing::{self, ContentHasher},
+    sanitize::{PathSanitizer, SanitizedPath, ORIGINAL_NAME_XATTR},
     types::*,
     ChunkEngine, ChunkStream,
 };
 use async_trait::async_trait;
-use sha2::{Digest, Sha256};
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Suffix of files still being received
+pub const PARTIAL_FILE_SUFFIX: &str = ".kizuna-partial";
+
+/// How hard reassembly works to make received files survive a crash
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    /// Leave flushing to the OS; fastest, but a crash can lose recent files
+    Never,
+    /// Sync file contents before the rename
+    File,
+    /// Sync file contents and the directory entry created by the rename
+    #[default]
+    Full,
+}
+
+/// Location of one chunk within a file, known before the chunk is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSpec {
+    pub chunk_id: ChunkId,
+    pub offset: u64,
+    pub size: usize,
+}
+
+/// Lazily yields the chunk layout of a file
+///
+/// Lets a sender walk a large file one chunk at a time instead of loading
+/// every chunk up front the way `create_chunks` does.
+#[derive(Debug, Clone)]
+pub struct ChunkPlan {
+    file_size: u64,
+    chunk_size: usize,
+    next_chunk: ChunkId,
+}
+
+impl ChunkPlan {
+    pub fn new(file_size: u64, chunk_size: usize) -> Self {
+        Self {
+            file_size,
+            chunk_size: chunk_size.max(1),
+            next_chunk: 0,
+        }
+    }
+
+    /// Total number of chunks in the file
+    pub fn chunk_count(&self) -> u64 {
+        self.file_size.div_ceil(self.chunk_size as u64)
+    }
+}
+
+impl Iterator for ChunkPlan {
+    type Item = ChunkSpec;
+
+    fn next(&mut self) -> Option<ChunkSpec> {
+        let offset = self.next_chunk * self.chunk_size as u64;
+        if offset >= self.file_size {
+            return None;
+        }
+
+        let spec = ChunkSpec {
+            chunk_id: self.next_chunk,
+            offset,
+            size: (self.file_size - offset).min(self.chunk_size as u64) as usize,
+        };
+        self.next_chunk += 1;
+        Some(spec)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.chunk_count() - self.next_chunk.min(self.chunk_count())) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for ChunkPlan {}
 
 /// Chunk engine implementation for file streaming
 pub struct ChunkEngineImpl {
     chunk_size: usize,
+    hash_algorithm: HashAlgorithm,
+    sparse_output: bool,
+    fsync_policy: FsyncPolicy,
 }
 
 impl ChunkEngineImpl {
@@ -23,22 +105,149 @@ impl ChunkEngineImpl {
     pub fn new() -> Self {
         Self {
             chunk_size: Chunk::DEFAULT_SIZE,
+            hash_algorithm: HashAlgorithm::default(),
+            sparse_output: false,
+            fsync_policy: FsyncPolicy::default(),
         }
     }
 
     /// Create a new chunk engine with custom chunk size
     pub fn with_chunk_size(chunk_size: usize) -> Self {
-        Self { chunk_size }
+        Self {
+            chunk_size,
+            hash_algorithm: HashAlgorithm::default(),
+            sparse_output: false,
+            fsync_policy: FsyncPolicy::default(),
+        }
+    }
+
+    /// Use the given checksum algorithm, normally the transfer manifest's
+    pub fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
     }
 
-    /// Calculate SHA-256 checksum for data
-    fn calculate_checksum(data: &[u8]) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-        hasher.update(data);
-        let result = hasher.finalize();
-        let mut checksum = [0u8; 32];
-        checksum.copy_from_slice(&result);
-        checksum
+    /// Leave all-zero chunks as holes when reassembling, recreating the
+    /// layout of sparse files on filesystems that support them
+    pub fn with_sparse_output(mut self, sparse_output: bool) -> Self {
+        self.sparse_output = sparse_output;
+        self
+    }
+
+    /// How reassembled files are synced to disk
+    pub fn with_fsync_policy(mut self, fsync_policy: FsyncPolicy) -> Self {
+        self.fsync_policy = fsync_policy;
+        self
+    }
+
+    /// Remove partial files left in `dir` by interrupted receives
+    pub async fn remove_partial_files(dir: &Path) -> Result<usize> {
+        let io_error = |e| FileTransferError::IoError {
+            path: dir.to_path_buf(),
+            source: e,
+        };
+
+        let mut entries = tokio::fs::read_dir(dir).await.map_err(io_error)?;
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+            if entry.file_name().to_string_lossy().ends_with(PARTIAL_FILE_SUFFIX)
+                && tokio::fs::remove_file(entry.path()).await.is_ok()
+            {
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Largest chunk this engine creates
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Checksum algorithm this engine creates and verifies chunks with
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Chunk layout for a file of the given size, computed on demand
+    pub fn plan_chunks(&self, file_size: u64) -> ChunkPlan {
+        ChunkPlan::new(file_size, self.chunk_size)
+    }
+
+    /// Reassemble a received file under `root` at the sanitized form of
+    /// the sender's path
+    ///
+    /// When the name had to change, the sender's path is kept in an
+    /// extended attribute where the filesystem supports one, and is always
+    /// returned so it can be recorded with the transfer.
+    pub async fn reassemble_received(
+        &self,
+        chunks: Vec<Chunk>,
+        root: &Path,
+        original: &Path,
+        sanitizer: &PathSanitizer,
+    ) -> Result<SanitizedPath> {
+        let destination = sanitizer.resolve(root, original)?;
+        self.reassemble_file(chunks, destination.path.clone()).await?;
+
+        if destination.sanitized || destination.renamed {
+            let _ = xattr::set(
+                &destination.path,
+                ORIGINAL_NAME_XATTR,
+                original.to_string_lossy().as_bytes(),
+            );
+        }
+
+        Ok(destination)
+    }
+
+    /// Recreate a received symlink under `root` at the sanitized form of
+    /// the sender's path
+    ///
+    /// The link's own path goes through the same sanitizer as received
+    /// files, and its target is checked against `root` before it is created.
+    pub fn restore_received_symlink(
+        &self,
+        entry: &SymlinkEntry,
+        root: &Path,
+        sanitizer: &PathSanitizer,
+    ) -> Result<SanitizedPath> {
+        let destination = sanitizer.resolve(root, &entry.path)?;
+        fidelity::restore_symlink(root, &destination.path, entry)?;
+        Ok(destination)
+    }
+
+    /// Read and checksum a single planned chunk
+    pub async fn read_chunk(&self, file_path: &Path, spec: ChunkSpec) -> Result<Chunk> {
+        let io_error = |e| FileTransferError::IoError {
+            path: file_path.to_path_buf(),
+            source: e,
+        };
+
+        let mut file = File::open(file_path).await.map_err(io_error)?;
+        file.seek(std::io::SeekFrom::Start(spec.offset)).await.map_err(io_error)?;
+
+        let mut data = vec![0u8; spec.size];
+        file.read_exact(&mut data).await.map_err(io_error)?;
+
+        let algorithm = self.hash_algorithm;
+        let (data, checksum) = tokio::task::spawn_blocking(move || {
+            let checksum = algorithm.digest(&data);
+            (data, checksum)
+        })
+        .await
+        .map_err(|e| FileTransferError::InternalError(format!("Chunk hashing failed: {}", e)))?;
+
+        Ok(Chunk {
+            chunk_id: spec.chunk_id,
+            file_path: file_path.to_path_buf(),
+            offset: spec.offset,
+            size: spec.size,
+            data,
+            checksum,
+            compressed: false,
+        })
     }
 }
 
@@ -51,7 +260,7 @@ impl Default for ChunkEngineImpl {
 #[async_trait]
 impl ChunkEngine for ChunkEngineImpl {
     /// Create chunks from a file
-    /// Reads file in 64KB segments and creates chunk metadata with checksums
+    /// Reads file in 64KB segments, then checksums the chunks in parallel
     async fn create_chunks(&self, file_path: PathBuf) -> Result<Vec<Chunk>> {
         // Open the file
         let mut file = File::open(&file_path).await.map_err(|e| {
@@ -91,17 +300,14 @@ impl ChunkEngine for ChunkEngineImpl {
             // Truncate buffer to actual bytes read
             buffer.truncate(bytes_read);
 
-            // Calculate checksum for this chunk
-            let checksum = Self::calculate_checksum(&buffer);
-
-            // Create chunk with metadata
+            // Create chunk with metadata; checksums are filled in below
             let chunk = Chunk {
                 chunk_id,
                 file_path: file_path.clone(),
                 offset,
                 size: bytes_read,
                 data: buffer,
-                checksum,
+                checksum: [0u8; 32],
                 compressed: false,
             };
 
@@ -116,12 +322,26 @@ impl ChunkEngine for ChunkEngineImpl {
             }
         }
 
-        Ok(chunks)
+        // Hash chunks across cores off the async runtime
+        let algorithm = self.hash_algorithm;
+        tokio::task::spawn_blocking(move || {
+            let data: Vec<&[u8]> = chunks.iter().map(|chunk| chunk.data.as_slice()).collect();
+            let checksums = hashing::hash_chunks(algorithm, &data);
+            for (chunk, checksum) in chunks.iter_mut().zip(checksums) {
+                chunk.checksum = checksum;
+            }
+            chunks
+        })
+        .await
+        .map_err(|e| FileTransferError::InternalError(format!("Chunk hashing failed: {}", e)))
     }
 
     /// Stream a chunk over the connection
     /// Sends chunk metadata followed by chunk data with flow control
     async fn stream_chunk(&self, chunk: Chunk, stream: &mut dyn ChunkStream) -> Result<()> {
+        // Hold the chunk back while the transport is above its high watermark
+        stream.ready().await?;
+
         // Serialize chunk metadata (without data) for transmission
         let metadata = ChunkMetadata {
             chunk_id: chunk.chunk_id,
@@ -230,7 +450,7 @@ impl ChunkEngine for ChunkEngineImpl {
 
     /// Verify chunk integrity by recalculating checksum
     async fn verify_chunk(&self, chunk: &Chunk) -> Result<bool> {
-        let calculated_checksum = Self::calculate_checksum(&chunk.data);
+        let calculated_checksum = self.hash_algorithm.digest(&chunk.data);
         Ok(calculated_checksum == chunk.checksum)
     }
 
@@ -276,10 +496,42 @@ impl ChunkEngine for ChunkEngineImpl {
             })?;
         }
 
-        // Create output file
-        let mut output_file = File::create(&output_path).await.map_err(|e| {
+        // Write under a temporary name and only rename once verified, so a
+        // crash never leaves a truncated file under the final name
+        let partial_path = partial_path_for(&output_path);
+        if let Err(e) = self.write_partial(&chunks, &partial_path, &output_path).await {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return Err(e);
+        }
+
+        if let Err(e) = tokio::fs::rename(&partial_path, &output_path).await {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return Err(FileTransferError::IoError {
+                path: output_path,
+                source: e,
+            });
+        }
+
+        // The rename is only durable once the directory entry is synced
+        if self.fsync_policy == FsyncPolicy::Full && let Some(parent) = output_path.parent() {
+            sync_directory(parent).await.map_err(|e| FileTransferError::IoError {
+                path: parent.to_path_buf(),
+                source: e,
+            })?;
+        }
+
+        Ok(())
+    }
+
+}
+
+impl ChunkEngineImpl {
+    /// Write verified chunks to `partial_path` and check the whole file
+    async fn write_partial(&self, chunks: &[Chunk], partial_path: &Path, output_path: &Path) -> Result<()> {
+        // Create the partial file
+        let mut output_file = File::create(partial_path).await.map_err(|e| {
             FileTransferError::IoError {
-                path: output_path.clone(),
+                path: partial_path.to_path_buf(),
                 source: e,
             }
         })?;
@@ -287,7 +539,7 @@ impl ChunkEngine for ChunkEngineImpl {
         // Write chunks to file in order
         let mut expected_offset = 0u64;
 
-        for chunk in &chunks {
+        for chunk in chunks {
             // Verify chunk offset matches expected position
             if chunk.offset != expected_offset {
                 return Err(FileTransferError::InternalError(format!(
@@ -303,90 +555,113 @@ impl ChunkEngine for ChunkEngineImpl {
                 });
             }
 
-            // Write chunk data to file
-            output_file.write_all(&chunk.data).await.map_err(|e| {
+            // Write chunk data to file, skipping over zero runs for sparse output
+            if self.sparse_output && chunk.data.iter().all(|&byte| byte == 0) {
+                output_file
+                    .seek(std::io::SeekFrom::Current(chunk.size as i64))
+                    .await
+                    .map_err(|e| FileTransferError::IoError {
+                        path: partial_path.to_path_buf(),
+                        source: e,
+                    })?;
+            } else {
+                output_file.write_all(&chunk.data).await.map_err(|e| {
+                    FileTransferError::IoError {
+                        path: partial_path.to_path_buf(),
+                        source: e,
+                    }
+                })?;
+            }
+
+            expected_offset += chunk.size as u64;
+        }
+
+        // A trailing hole only exists once the length is set explicitly
+        if self.sparse_output {
+            output_file.set_len(expected_offset).await.map_err(|e| {
                 FileTransferError::IoError {
-                    path: output_path.clone(),
+                    path: partial_path.to_path_buf(),
                     source: e,
                 }
             })?;
-
-            expected_offset += chunk.size as u64;
         }
 
-        // Flush and sync to ensure all data is written
+        // Flush, and sync unless the policy trades durability for speed
         output_file.flush().await.map_err(|e| {
             FileTransferError::IoError {
-                path: output_path.clone(),
+                path: partial_path.to_path_buf(),
                 source: e,
             }
         })?;
 
-        output_file.sync_all().await.map_err(|e| {
-            FileTransferError::IoError {
-                path: output_path.clone(),
-                source: e,
-            }
-        })?;
+        if self.fsync_policy != FsyncPolicy::Never {
+            output_file.sync_all().await.map_err(|e| {
+                FileTransferError::IoError {
+                    path: partial_path.to_path_buf(),
+                    source: e,
+                }
+            })?;
+        }
 
         // Close the file
         drop(output_file);
 
         // Verify final file integrity by calculating checksum
-        let final_checksum = self.calculate_file_checksum(&output_path).await?;
+        let final_checksum = self.calculate_file_checksum(partial_path).await?;
 
         // Calculate expected checksum from all chunk data
-        let mut hasher = Sha256::new();
-        for chunk in &chunks {
+        let mut hasher = ContentHasher::new(self.hash_algorithm);
+        for chunk in chunks {
             hasher.update(&chunk.data);
         }
         let expected_checksum = hasher.finalize();
-        let mut expected_checksum_array = [0u8; 32];
-        expected_checksum_array.copy_from_slice(&expected_checksum);
 
         // Verify checksums match
-        if final_checksum != expected_checksum_array {
+        if final_checksum != expected_checksum {
             return Err(FileTransferError::ChecksumMismatch {
-                path: output_path,
+                path: output_path.to_path_buf(),
             });
         }
 
         Ok(())
     }
 
-}
-
-impl ChunkEngineImpl {
     /// Calculate checksum for an entire file (private helper method)
-    async fn calculate_file_checksum(&self, file_path: &PathBuf) -> Result<[u8; 32]> {
-        let mut file = File::open(file_path).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: file_path.clone(),
+    async fn calculate_file_checksum(&self, file_path: &Path) -> Result<[u8; 32]> {
+        hashing::hash_file(file_path, self.hash_algorithm)
+            .await
+            .map_err(|e| FileTransferError::IoError {
+                path: file_path.to_path_buf(),
                 source: e,
-            }
-        })?;
-
-        let mut hasher = Sha256::new();
-        let mut buffer = vec![0u8; self.chunk_size];
-
-        loop {
-            let bytes_read = file.read(&mut buffer).await.map_err(|e| {
-                FileTransferError::IoError {
-                    path: file_path.clone(),
-                    source: e,
-                }
-            })?;
+            })
+    }
+}
 
-            if bytes_read == 0 {
-                break;
-            }
+/// Hidden temporary name next to `output_path`, unique per receive
+fn partial_path_for(output_path: &Path) -> PathBuf {
+    let name = output_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    output_path.with_file_name(format!(
+        ".{}.{}{}",
+        name,
+        uuid::Uuid::new_v4().simple(),
+        PARTIAL_FILE_SUFFIX
+    ))
+}
 
-            hasher.update(&buffer[..bytes_read]);
-        }
+/// Sync a directory so entries created or renamed in it are durable
+async fn sync_directory(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        File::open(dir).await?.sync_all().await
+    }
 
-        let result = hasher.finalize();
-        let mut checksum = [0u8; 32];
-        checksum.copy_from_slice(&result);
-        Ok(checksum)
+    // Windows cannot open directories as files; NTFS journals the rename
+    #[cfg(not(unix))]
+    {
+        let _ = dir;
+        Ok(())
     }
 }
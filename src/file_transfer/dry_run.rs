@@ -0,0 +1,226 @@
+// Transfer Dry Run Module
+//
+// A dry run goes through a send up to the point where data would move: the
+// manifest is built, the peer's trust is checked and a transport negotiated
+// and measured. The resulting plan lists what would be sent, how long it
+// should take, and which files would clash or be renamed on the receiver.
+// No session is created and nothing is written on either side.
+//
+// The receiver's platform and download directory are not known before the
+// transfer starts, so conflicts are reported against the strictest naming
+// rules (Windows, case-insensitive) rather than the peer's actual files.
+
+use crate::file_transfer::{
+    sanitize::{PathSanitizer, SanitizeStrategy},
+    types::*,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// What a send would do, worked out without transferring anything
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferPlan {
+    pub peer_id: PeerId,
+    pub manifest: TransferManifest,
+    /// Transport the transfer would use
+    pub protocol: TransportProtocol,
+    /// Expected transfer rate in bytes per second, when it could be measured
+    pub throughput_bps: Option<u64>,
+    /// Expected time to send the whole manifest
+    pub estimated_duration: Option<Duration>,
+    /// Files that would clash or be renamed on the receiver
+    pub conflicts: Vec<TransferConflict>,
+}
+
+impl TransferPlan {
+    /// Work out timing and conflicts for a negotiated transfer
+    ///
+    /// The estimate uses the slowest of the link, this device's hashing
+    /// rate and the bandwidth limit, plus one round trip per file.
+    pub fn new(
+        peer_id: PeerId,
+        manifest: TransferManifest,
+        protocol: TransportProtocol,
+        metrics: Option<&PerformanceMetrics>,
+        bandwidth_limit: Option<u64>,
+    ) -> Self {
+        let throughput_bps = metrics
+            .map(|metrics| {
+                [Some(metrics.throughput_bps), Some(metrics.hashing_throughput_bps), bandwidth_limit]
+                    .into_iter()
+                    .flatten()
+                    .filter(|&rate| rate > 0)
+                    .min()
+                    .unwrap_or(0)
+            })
+            .or(bandwidth_limit)
+            .filter(|&rate| rate > 0);
+
+        let estimated_duration = throughput_bps.map(|rate| {
+            let latency = metrics.map_or(0, |metrics| metrics.latency_ms);
+            Duration::from_secs_f64(manifest.total_size as f64 / rate as f64)
+                + Duration::from_millis(latency * manifest.file_count as u64)
+        });
+
+        let conflicts = find_conflicts(&manifest);
+
+        Self {
+            peer_id,
+            manifest,
+            protocol,
+            throughput_bps,
+            estimated_duration,
+            conflicts,
+        }
+    }
+}
+
+/// A file that would not arrive under the name it was sent with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferConflict {
+    /// Path as listed in the manifest
+    pub path: PathBuf,
+    pub kind: ConflictKind,
+}
+
+/// Why a file would not arrive as sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Another file in the transfer has the same destination; names that
+    /// differ only in case clash on Windows and macOS
+    Duplicate { other: PathBuf },
+    /// The name is not valid on Windows and would be renamed there
+    Renamed { to: PathBuf },
+    /// The name cannot be used at all
+    Unusable { reason: String },
+}
+
+/// Where a manifest entry lands relative to the receiver's download directory
+///
+/// Individually sent files are listed by their full local path but arrive
+/// under their file name; folder entries keep their relative path.
+pub fn destination(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.file_name().map(PathBuf::from).unwrap_or_else(|| path.to_path_buf())
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Files in a manifest that would clash or be renamed on the receiver
+pub fn find_conflicts(manifest: &TransferManifest) -> Vec<TransferConflict> {
+    let windows = PathSanitizer::new()
+        .with_strategy(SanitizeStrategy::Replace('_'))
+        .with_target(PlatformFamily::Windows);
+    let mut conflicts = Vec::new();
+    let mut seen: HashMap<String, &Path> = HashMap::new();
+
+    for entry in &manifest.files {
+        let destination = destination(&entry.path);
+
+        let key = destination.to_string_lossy().to_lowercase();
+        if let Some(other) = seen.get(&key) {
+            conflicts.push(TransferConflict {
+                path: entry.path.clone(),
+                kind: ConflictKind::Duplicate { other: other.to_path_buf() },
+            });
+            continue;
+        }
+        seen.insert(key, &entry.path);
+
+        let mut renamed = PathBuf::new();
+        let mut changed = false;
+        for component in destination.iter() {
+            let name = component.to_string_lossy();
+            match windows.sanitize_component(&name) {
+                Ok(clean) => {
+                    changed |= clean != name;
+                    renamed.push(clean);
+                }
+                Err(e) => {
+                    conflicts.push(TransferConflict {
+                        path: entry.path.clone(),
+                        kind: ConflictKind::Unusable { reason: e.to_string() },
+                    });
+                    changed = false;
+                    break;
+                }
+            }
+        }
+        if changed {
+            conflicts.push(TransferConflict {
+                path: entry.path.clone(),
+                kind: ConflictKind::Renamed { to: renamed },
+            });
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(paths: &[&str]) -> TransferManifest {
+        let mut manifest = TransferManifest::new("local-peer".to_string());
+        for path in paths {
+            manifest.files.push(FileEntry {
+                path: PathBuf::from(path),
+                size: 4 * 1024 * 1024,
+                checksum: [0; 32],
+                permissions: FilePermissions::default(),
+                modified_at: 0,
+                chunk_count: 4,
+                extended: None,
+            });
+        }
+        manifest.file_count = paths.len();
+        manifest.total_size = 4 * 1024 * 1024 * paths.len() as u64;
+        manifest
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let conflicts = find_conflicts(&manifest(&[
+            "/home/a/report.pdf",
+            "/home/b/Report.PDF",
+            "photos/notes: v2.txt",
+            "photos/ok.jpg",
+        ]));
+
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts[0].kind,
+            ConflictKind::Duplicate { other: PathBuf::from("/home/a/report.pdf") }
+        );
+        assert_eq!(
+            conflicts[1].kind,
+            ConflictKind::Renamed { to: PathBuf::from("photos").join("notes_ v2.txt") }
+        );
+    }
+
+    #[test]
+    fn test_estimate_uses_slowest_limit() {
+        let metrics = PerformanceMetrics {
+            latency_ms: 10,
+            throughput_bps: 4 * 1024 * 1024,
+            packet_loss: 0.0,
+            jitter_ms: 0,
+            hashing_throughput_bps: 100 * 1024 * 1024,
+        };
+        let files = manifest(&["a.bin", "b.bin"]);
+
+        let plan = TransferPlan::new("laptop".to_string(), files.clone(), TransportProtocol::Quic, Some(&metrics), None);
+        assert_eq!(plan.throughput_bps, Some(4 * 1024 * 1024));
+        assert_eq!(plan.estimated_duration, Some(Duration::from_millis(2020)));
+
+        let limited = Some(1024 * 1024);
+        let plan = TransferPlan::new("laptop".to_string(), files, TransportProtocol::Quic, Some(&metrics), limited);
+        assert_eq!(plan.throughput_bps, limited);
+        assert!(plan.conflicts.is_empty());
+    }
+}
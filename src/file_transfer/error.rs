@@ -48,6 +48,12 @@ pub enum FileTransferError {
     #[error("Invalid path: {path}")]
     InvalidPath { path: PathBuf },
 
+    #[error("Unsafe file name {name:?}: {reason}")]
+    UnsafeFileName { name: String, reason: String },
+
+    #[error("File already exists: {path}")]
+    FileExists { path: PathBuf },
+
     // Integrity errors
     #[error("Checksum mismatch for: {path}")]
     ChecksumMismatch { path: PathBuf },
@@ -71,6 +77,12 @@ pub enum FileTransferError {
     #[error("Peer not trusted: {peer_id}")]
     PeerNotTrusted { peer_id: String },
 
+    #[error("Malware detected in {path}: {signature}")]
+    MalwareDetected { path: PathBuf, signature: String },
+
+    #[error("Scan of {path} failed: {reason}")]
+    ScanFailed { path: PathBuf, reason: String },
+
     #[error("Encryption failed: {reason}")]
     EncryptionFailed { reason: String },
 
@@ -100,6 +112,9 @@ pub enum FileTransferError {
     #[error("Transfer timeout")]
     TransferTimeout,
 
+    #[error("Invalid transfer negotiation: {reason}")]
+    InvalidNegotiation { reason: String },
+
     // Queue errors
     #[error("Queue item not found: {queue_id}")]
     QueueItemNotFound { queue_id: String },
@@ -0,0 +1,379 @@
+// Fan-Out Module
+//
+// Sends one set of files to several peers at once. Each chunk is read from disk
+// and checksummed once, then written to every target concurrently, so another
+// receiver costs upload bandwidth but no extra disk reads or hashing.
+//
+// In swarm mode, receivers on the sender's LAN share the upload: each chunk is
+// sent to only one of them (its seeder, assigned round-robin) and the others
+// fetch it from that seeder. The `SwarmPlan` travels in the manifest so every
+// receiver derives the same assignment. Receivers outside the LAN always get
+// every chunk from the sender, and so do LAN receivers for chunks whose seeder
+// has dropped out.
+
+use crate::file_transfer::{
+    chunk::{ChunkEngineImpl, ChunkSpec},
+    error::Result,
+    progress::ProgressTracker,
+    types::*,
+    ChunkEngine, ChunkStream,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// How chunks are shared between receivers on the same LAN
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwarmPlan {
+    /// LAN receivers, in the order chunks are assigned to them
+    pub seeders: Vec<PeerId>,
+    /// Chunk size the files are split with
+    pub chunk_size: usize,
+}
+
+/// A chunk a swarm receiver fetches from another receiver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwarmFetch {
+    pub file_path: PathBuf,
+    pub spec: ChunkSpec,
+    /// Receiver the sender gave the chunk to
+    pub from: PeerId,
+}
+
+impl SwarmPlan {
+    /// Plan a swarm among `lan_peers`, or None when fewer than two can share
+    pub fn new(lan_peers: Vec<PeerId>, chunk_size: usize) -> Option<Self> {
+        let mut seeders: Vec<PeerId> = Vec::new();
+        for peer in lan_peers {
+            if !seeders.contains(&peer) {
+                seeders.push(peer);
+            }
+        }
+
+        (seeders.len() >= 2).then_some(Self { seeders, chunk_size })
+    }
+
+    /// Whether `peer` takes part in the swarm
+    pub fn contains(&self, peer: &PeerId) -> bool {
+        self.seeders.contains(peer)
+    }
+
+    /// Receiver the sender gives chunk number `sequence` to
+    ///
+    /// Chunks are numbered across the whole transfer, file by file in
+    /// manifest order.
+    pub fn seeder(&self, sequence: u64) -> &PeerId {
+        &self.seeders[(sequence % self.seeders.len() as u64) as usize]
+    }
+
+    /// Chunks of `manifest` that `peer` must fetch from other receivers
+    pub fn chunks_to_fetch(&self, manifest: &TransferManifest, peer: &PeerId) -> Vec<SwarmFetch> {
+        let mut fetches = Vec::new();
+        if !self.contains(peer) {
+            return fetches;
+        }
+
+        let mut sequence = 0u64;
+        for file in &manifest.files {
+            for spec in ChunkEngineImpl::with_chunk_size(self.chunk_size).plan_chunks(file.size) {
+                let seeder = self.seeder(sequence);
+                if seeder != peer {
+                    fetches.push(SwarmFetch {
+                        file_path: file.path.clone(),
+                        spec,
+                        from: seeder.clone(),
+                    });
+                }
+                sequence += 1;
+            }
+        }
+        fetches
+    }
+}
+
+/// One receiver of a fan-out send
+pub struct FanOutTarget {
+    pub peer_id: PeerId,
+    /// Session the target's progress is reported under
+    pub session_id: SessionId,
+    pub stream: Box<dyn ChunkStream>,
+}
+
+/// How a fan-out send went for one receiver
+#[derive(Debug, Clone)]
+pub struct TargetOutcome {
+    pub peer_id: PeerId,
+    pub session_id: SessionId,
+    /// Bytes the sender wrote to this receiver itself
+    pub bytes_sent: u64,
+    /// Why the receiver dropped out, if it did
+    pub error: Option<String>,
+}
+
+impl TargetOutcome {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Receiver state while a fan-out send runs
+struct ActiveTarget {
+    target: FanOutTarget,
+    bytes_sent: u64,
+    /// Bytes this receiver has, directly or through its swarm
+    bytes_delivered: u64,
+    error: Option<String>,
+}
+
+/// Sends the same files to several receivers, reading each chunk once
+pub struct FanOutSender {
+    engine: ChunkEngineImpl,
+    progress: Arc<ProgressTracker>,
+}
+
+impl FanOutSender {
+    pub fn new(engine: ChunkEngineImpl, progress: Arc<ProgressTracker>) -> Self {
+        Self { engine, progress }
+    }
+
+    /// Send every file in `manifest` to all targets
+    ///
+    /// A receiver that fails is dropped and its session failed; the rest
+    /// carry on. Swarm receivers are credited with chunks handed to their
+    /// seeder, so their progress shows what the swarm as a whole holds. Only
+    /// a local read error stops the send for everyone.
+    pub async fn send(&self, manifest: &TransferManifest, targets: Vec<FanOutTarget>) -> Result<Vec<TargetOutcome>> {
+        let swarm = manifest.swarm.as_ref();
+        let mut targets: Vec<ActiveTarget> = targets
+            .into_iter()
+            .map(|target| ActiveTarget {
+                target,
+                bytes_sent: 0,
+                bytes_delivered: 0,
+                error: None,
+            })
+            .collect();
+
+        let mut sequence = 0u64;
+        for file in &manifest.files {
+            for spec in self.engine.plan_chunks(file.size) {
+                if targets.iter().all(|t| t.error.is_some()) {
+                    return Ok(Self::outcomes(targets));
+                }
+
+                let chunk = match self.engine.read_chunk(&file.path, spec).await {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        for target in targets.iter().filter(|t| t.error.is_none()) {
+                            let _ = self.progress.fail_session(target.target.session_id, e.to_string()).await;
+                        }
+                        return Err(e);
+                    }
+                };
+
+                // Swarm members other than the seeder get the chunk from it;
+                // if the seeder drops out they are sent it directly instead
+                let mut given: HashSet<PeerId> = HashSet::new();
+                loop {
+                    let live: HashSet<PeerId> = targets
+                        .iter()
+                        .filter(|t| t.error.is_none())
+                        .map(|t| t.target.peer_id.clone())
+                        .collect();
+                    let seeder = swarm.map(|plan| plan.seeder(sequence)).filter(|seeder| live.contains(*seeder));
+                    let fetches_from_seeder = |peer: &PeerId| {
+                        seeder.is_some_and(|seeder| seeder != peer && swarm.is_some_and(|plan| plan.contains(peer)))
+                    };
+
+                    let sends: Vec<_> = targets
+                        .iter_mut()
+                        .filter(|t| {
+                            t.error.is_none()
+                                && !given.contains(&t.target.peer_id)
+                                && !fetches_from_seeder(&t.target.peer_id)
+                        })
+                        .map(|t| {
+                            let chunk = chunk.clone();
+                            async move {
+                                let result = self.engine.stream_chunk(chunk, t.target.stream.as_mut()).await;
+                                (t, result)
+                            }
+                        })
+                        .collect();
+                    if sends.is_empty() {
+                        break;
+                    }
+
+                    for (target, result) in futures::future::join_all(sends).await {
+                        match result {
+                            Ok(()) => {
+                                target.bytes_sent += spec.size as u64;
+                                given.insert(target.target.peer_id.clone());
+                            }
+                            Err(e) => {
+                                log::warn!("Fan-out to {} failed: {}", target.target.peer_id, e);
+                                let _ = self.progress.fail_session(target.target.session_id, e.to_string()).await;
+                                target.error = Some(e.to_string());
+                            }
+                        }
+                    }
+                }
+
+                for target in targets.iter_mut().filter(|t| t.error.is_none()) {
+                    target.bytes_delivered += spec.size as u64;
+                    let _ = self
+                        .progress
+                        .update_progress(target.target.session_id, target.bytes_delivered)
+                        .await;
+                }
+                sequence += 1;
+            }
+
+            for target in targets.iter().filter(|t| t.error.is_none()) {
+                let _ = self.progress.file_completed(target.target.session_id, file.path.clone()).await;
+            }
+        }
+
+        for target in targets.iter().filter(|t| t.error.is_none()) {
+            let _ = self.progress.complete_session(target.target.session_id).await;
+        }
+        Ok(Self::outcomes(targets))
+    }
+
+    fn outcomes(targets: Vec<ActiveTarget>) -> Vec<TargetOutcome> {
+        targets
+            .into_iter()
+            .map(|t| TargetOutcome {
+                peer_id: t.target.peer_id,
+                session_id: t.target.session_id,
+                bytes_sent: t.bytes_sent,
+                error: t.error,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_transfer::error::FileTransferError;
+    use crate::file_transfer::manifest::ManifestBuilderImpl;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// Stream that records what is sent and can replay it
+    #[derive(Clone, Default)]
+    struct MemoryStream {
+        data: Arc<Mutex<Vec<u8>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl ChunkStream for MemoryStream {
+        async fn send(&mut self, data: &[u8]) -> Result<()> {
+            if self.fail {
+                return Err(FileTransferError::NetworkError {
+                    reason: "connection reset".to_string(),
+                });
+            }
+            self.data.lock().unwrap().extend_from_slice(data);
+            Ok(())
+        }
+
+        async fn receive(&mut self, buffer: &mut [u8]) -> Result<usize> {
+            let mut data = self.data.lock().unwrap();
+            let n = buffer.len().min(data.len());
+            buffer[..n].copy_from_slice(&data[..n]);
+            data.drain(..n);
+            Ok(n)
+        }
+
+        async fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn target(peer: &str, stream: &MemoryStream) -> FanOutTarget {
+        FanOutTarget {
+            peer_id: peer.to_string(),
+            session_id: uuid::Uuid::new_v4(),
+            stream: Box::new(stream.clone()),
+        }
+    }
+
+    async fn received_chunks(engine: &ChunkEngineImpl, stream: &MemoryStream) -> Vec<ChunkId> {
+        let mut reader = stream.clone();
+        let mut ids = Vec::new();
+        while !stream.data.lock().unwrap().is_empty() {
+            ids.push(engine.receive_chunk(&mut reader).await.unwrap().chunk_id);
+        }
+        ids
+    }
+
+    async fn manifest_for(dir: &TempDir, size: usize) -> TransferManifest {
+        let path = dir.path().join("video.mkv");
+        std::fs::write(&path, vec![7u8; size]).unwrap();
+        ManifestBuilderImpl::new("sender".to_string())
+            .build_file_manifest(path)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_every_target_gets_every_chunk() {
+        let dir = TempDir::new().unwrap();
+        let manifest = manifest_for(&dir, Chunk::DEFAULT_SIZE * 3 + 10).await;
+        let engine = ChunkEngineImpl::new().with_hash_algorithm(manifest.hash_algorithm);
+
+        let (laptop, phone) = (MemoryStream::default(), MemoryStream::default());
+        let broken = MemoryStream { fail: true, ..Default::default() };
+        let sender = FanOutSender::new(
+            ChunkEngineImpl::new().with_hash_algorithm(manifest.hash_algorithm),
+            Arc::new(ProgressTracker::new()),
+        );
+        let outcomes = sender
+            .send(&manifest, vec![target("laptop", &laptop), target("phone", &phone), target("nas", &broken)])
+            .await
+            .unwrap();
+
+        // A failing receiver does not hold back the others
+        assert!(outcomes[0].succeeded() && outcomes[1].succeeded());
+        assert!(!outcomes[2].succeeded());
+        assert_eq!(outcomes[0].bytes_sent, manifest.total_size);
+        assert_eq!(received_chunks(&engine, &laptop).await, vec![0, 1, 2, 3]);
+        assert_eq!(received_chunks(&engine, &phone).await, vec![0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_swarm_splits_lan_upload() {
+        let dir = TempDir::new().unwrap();
+        let mut manifest = manifest_for(&dir, Chunk::DEFAULT_SIZE * 4).await;
+        let plan = SwarmPlan::new(vec!["laptop".to_string(), "phone".to_string()], Chunk::DEFAULT_SIZE).unwrap();
+        assert!(SwarmPlan::new(vec!["laptop".to_string(), "laptop".to_string()], Chunk::DEFAULT_SIZE).is_none());
+
+        // Each swarm member fetches exactly the chunks the other was given
+        let phone_fetches = plan.chunks_to_fetch(&manifest, &"phone".to_string());
+        assert_eq!(phone_fetches.iter().map(|f| f.spec.chunk_id).collect::<Vec<_>>(), vec![0, 2]);
+        assert!(phone_fetches.iter().all(|f| f.from == "laptop"));
+        assert!(plan.chunks_to_fetch(&manifest, &"nas".to_string()).is_empty());
+        manifest.swarm = Some(plan);
+
+        let engine = ChunkEngineImpl::new().with_hash_algorithm(manifest.hash_algorithm);
+        let (laptop, phone, nas) = (MemoryStream::default(), MemoryStream::default(), MemoryStream::default());
+        let sender = FanOutSender::new(
+            ChunkEngineImpl::new().with_hash_algorithm(manifest.hash_algorithm),
+            Arc::new(ProgressTracker::new()),
+        );
+        let outcomes = sender
+            .send(&manifest, vec![target("laptop", &laptop), target("phone", &phone), target("nas", &nas)])
+            .await
+            .unwrap();
+
+        assert!(outcomes.iter().all(TargetOutcome::succeeded));
+        assert_eq!(received_chunks(&engine, &laptop).await, vec![0, 2]);
+        assert_eq!(received_chunks(&engine, &phone).await, vec![1, 3]);
+        assert_eq!(received_chunks(&engine, &nas).await, vec![0, 1, 2, 3]);
+    }
+}
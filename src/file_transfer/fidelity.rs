@@ -0,0 +1,422 @@
+// Metadata Fidelity Module
+//
+// Captures and restores filesystem metadata that plain file contents lose:
+// symlinks, POSIX permissions, modification times, extended attributes and
+// sparse-file holes. What survives a transfer depends on both platforms, so
+// the requested preservation is negotiated against a compatibility matrix.
+
+use crate::file_transfer::{
+    error::{FileTransferError, Result},
+    types::*,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Kind of metadata a transfer can preserve
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataKind {
+    Symlinks,
+    Permissions,
+    Mtimes,
+    Xattrs,
+    SparseFiles,
+}
+
+impl MetadataKind {
+    pub const ALL: [MetadataKind; 5] = [
+        MetadataKind::Symlinks,
+        MetadataKind::Permissions,
+        MetadataKind::Mtimes,
+        MetadataKind::Xattrs,
+        MetadataKind::SparseFiles,
+    ];
+
+    fn requested(self, preservation: &MetadataPreservation) -> bool {
+        match self {
+            Self::Symlinks => preservation.symlinks,
+            Self::Permissions => preservation.permissions,
+            Self::Mtimes => preservation.mtimes,
+            Self::Xattrs => preservation.xattrs,
+            Self::SparseFiles => preservation.sparse,
+        }
+    }
+
+    fn disable(self, preservation: &mut MetadataPreservation) {
+        match self {
+            Self::Symlinks => preservation.symlinks = false,
+            Self::Permissions => preservation.permissions = false,
+            Self::Mtimes => preservation.mtimes = false,
+            Self::Xattrs => preservation.xattrs = false,
+            Self::SparseFiles => preservation.sparse = false,
+        }
+    }
+}
+
+/// Whether a platform can store a kind of metadata, and why not if it can't
+fn platform_support(platform: PlatformFamily, kind: MetadataKind) -> std::result::Result<(), &'static str> {
+    use MetadataKind::*;
+    use PlatformFamily::*;
+
+    match (platform, kind) {
+        (Linux | MacOs, _) => Ok(()),
+        (Windows, Mtimes) => Ok(()),
+        (Windows, Symlinks) => Err("Windows needs Developer Mode or admin rights to create symlinks"),
+        (Windows, Permissions) => Err("Windows has no POSIX mode bits; only read-only is kept"),
+        (Windows, Xattrs) => Err("Windows alternate data streams are not mapped to extended attributes"),
+        (Windows, SparseFiles) => Err("NTFS files must be flagged sparse explicitly; holes are written as zeros"),
+        (Other, Mtimes) => Ok(()),
+        (Other, _) => Err("not supported on this platform"),
+    }
+}
+
+/// One cell of the sender/receiver compatibility matrix
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompatibilityEntry {
+    pub kind: MetadataKind,
+    pub preserved: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// What survives a transfer between two platforms
+pub fn compatibility_matrix(sender: PlatformFamily, receiver: PlatformFamily) -> Vec<CompatibilityEntry> {
+    MetadataKind::ALL
+        .iter()
+        .map(|&kind| {
+            let reason = platform_support(sender, kind)
+                .map_err(|why| format!("sender: {}", why))
+                .and_then(|_| platform_support(receiver, kind).map_err(|why| format!("receiver: {}", why)))
+                .err();
+            CompatibilityEntry {
+                kind,
+                preserved: reason.is_none(),
+                reason,
+            }
+        })
+        .collect()
+}
+
+/// Drop requested metadata that cannot round-trip between the platforms
+///
+/// Returns the effective preservation plus the matrix rows for every kind
+/// that was requested but had to be dropped.
+pub fn negotiate(
+    requested: MetadataPreservation,
+    sender: PlatformFamily,
+    receiver: PlatformFamily,
+) -> (MetadataPreservation, Vec<CompatibilityEntry>) {
+    let mut effective = requested;
+    let mut dropped = Vec::new();
+
+    for entry in compatibility_matrix(sender, receiver) {
+        if entry.kind.requested(&requested) && !entry.preserved {
+            entry.kind.disable(&mut effective);
+            dropped.push(entry);
+        }
+    }
+
+    (effective, dropped)
+}
+
+/// Extended metadata for a file on the sending side
+pub fn capture_extended(path: &Path, preservation: &MetadataPreservation) -> Option<ExtendedMetadata> {
+    if !preservation.xattrs && !preservation.sparse {
+        return None;
+    }
+
+    let mut extended = ExtendedMetadata::default();
+    if preservation.xattrs {
+        extended.xattrs = read_xattrs(path);
+    }
+    if preservation.sparse {
+        extended.sparse = std::fs::metadata(path).map(|m| is_sparse(&m)).unwrap_or(false);
+    }
+
+    Some(extended)
+}
+
+/// Extended attributes with UTF-8 names; unreadable ones are skipped
+fn read_xattrs(path: &Path) -> Vec<ExtendedAttribute> {
+    if !xattr::SUPPORTED_PLATFORM {
+        return Vec::new();
+    }
+
+    let Ok(names) = xattr::list(path) else {
+        return Vec::new();
+    };
+
+    names
+        .filter_map(|name| {
+            let value = xattr::get(path, &name).ok()??;
+            Some(ExtendedAttribute {
+                name: name.into_string().ok()?,
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Whether fewer blocks are allocated than the length needs
+pub fn is_sparse(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.is_file() && metadata.blocks() * 512 < metadata.len()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+/// Manifest entry for a symlink, recorded as a link rather than followed
+pub fn read_symlink(path: &Path) -> std::io::Result<SymlinkEntry> {
+    Ok(SymlinkEntry {
+        path: path.to_path_buf(),
+        target: std::fs::read_link(path)?,
+        target_is_dir: std::fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false),
+    })
+}
+
+/// Recreate a symlink at `link_path` inside the receiver's `root`
+///
+/// Targets that are absolute or climb out of `root` are refused so a
+/// malicious sender cannot point received links at arbitrary files.
+/// `link_path` is trusted as given; links from a manifest should go through
+/// [`ChunkEngineImpl::restore_received_symlink`](crate::file_transfer::chunk::ChunkEngineImpl::restore_received_symlink),
+/// which sanitizes it first.
+pub fn restore_symlink(root: &Path, link_path: &Path, entry: &SymlinkEntry) -> Result<()> {
+    let link_dir = link_path.parent().unwrap_or(root);
+    let relative_dir = link_dir.strip_prefix(root).unwrap_or(Path::new(""));
+    if escapes_root(relative_dir, &entry.target) {
+        return Err(FileTransferError::PermissionDenied {
+            path: link_path.to_path_buf(),
+        });
+    }
+
+    let io_error = |e| FileTransferError::IoError {
+        path: link_path.to_path_buf(),
+        source: e,
+    };
+
+    std::fs::create_dir_all(link_dir).map_err(io_error)?;
+
+    #[cfg(unix)]
+    {
+        let _ = entry.target_is_dir;
+        std::os::unix::fs::symlink(&entry.target, link_path).map_err(io_error)
+    }
+
+    #[cfg(windows)]
+    {
+        if entry.target_is_dir {
+            std::os::windows::fs::symlink_dir(&entry.target, link_path).map_err(io_error)
+        } else {
+            std::os::windows::fs::symlink_file(&entry.target, link_path).map_err(io_error)
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        Err(io_error(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "symlinks are not supported on this platform",
+        )))
+    }
+}
+
+/// Whether following `target` from `dir` (relative to the root) leaves the root
+fn escapes_root(dir: &Path, target: &Path) -> bool {
+    let mut depth = dir.components().filter(|c| matches!(c, Component::Normal(_))).count();
+
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if depth == 0 {
+                    return true;
+                }
+                depth -= 1;
+            }
+            Component::RootDir | Component::Prefix(_) => return true,
+        }
+    }
+
+    false
+}
+
+/// Restore captured metadata onto a received file
+///
+/// Failures to restore individual attributes are not fatal; they are
+/// returned as warnings so the transfer still completes.
+pub fn apply_file_metadata(
+    path: &Path,
+    entry: &FileEntry,
+    preservation: &MetadataPreservation,
+) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+
+    if preservation.xattrs && let Some(extended) = &entry.extended {
+        for attr in &extended.xattrs {
+            if let Err(e) = xattr::set(path, &attr.name, &attr.value) {
+                warnings.push(format!("extended attribute {}: {}", attr.name, e));
+            }
+        }
+    }
+
+    // Set the time before permissions, which may make the file read-only
+    if preservation.mtimes {
+        let modified = UNIX_EPOCH + Duration::from_secs(entry.modified_at);
+        let result = std::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(modified));
+        if let Err(e) = result {
+            warnings.push(format!("modification time: {}", e));
+        }
+    }
+
+    if preservation.permissions {
+        let io_error = |e| FileTransferError::IoError {
+            path: path.to_path_buf(),
+            source: e,
+        };
+        let mut permissions = std::fs::metadata(path).map_err(io_error)?.permissions();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // Never restore setuid, setgid or sticky bits from a remote peer
+            permissions.set_mode(entry.permissions.mode & 0o777);
+        }
+
+        #[cfg(not(unix))]
+        {
+            permissions.set_readonly(entry.permissions.readonly);
+        }
+
+        std::fs::set_permissions(path, permissions).map_err(io_error)?;
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_negotiate_drops_unsupported_metadata() {
+        let (same, dropped) = negotiate(MetadataPreservation::all(), PlatformFamily::Linux, PlatformFamily::MacOs);
+        assert_eq!(same, MetadataPreservation::all());
+        assert!(dropped.is_empty());
+
+        let (effective, dropped) =
+            negotiate(MetadataPreservation::all(), PlatformFamily::Linux, PlatformFamily::Windows);
+        assert!(effective.mtimes);
+        assert!(!effective.permissions && !effective.xattrs && !effective.symlinks && !effective.sparse);
+        assert_eq!(dropped.len(), 4);
+        assert!(dropped.iter().all(|entry| entry.reason.as_deref().unwrap().starts_with("receiver")));
+    }
+
+    #[test]
+    fn test_symlink_targets_cannot_escape_root() {
+        assert!(!escapes_root(Path::new("docs"), Path::new("../readme.md")));
+        assert!(!escapes_root(Path::new(""), Path::new("./a/b")));
+        assert!(escapes_root(Path::new("docs"), Path::new("../../etc/passwd")));
+        assert!(escapes_root(Path::new(""), Path::new("/etc/passwd")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_file_metadata() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("script.sh");
+        std::fs::write(&path, b"#!/bin/sh\n").unwrap();
+
+        let entry = FileEntry {
+            path: path.clone(),
+            size: 10,
+            checksum: [0u8; 32],
+            permissions: FilePermissions {
+                readonly: false,
+                executable: true,
+                mode: 0o4755,
+            },
+            modified_at: 1_600_000_000,
+            chunk_count: 1,
+            extended: None,
+        };
+
+        let warnings = apply_file_metadata(&path, &entry, &MetadataPreservation::all()).unwrap();
+        assert!(warnings.is_empty());
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o755);
+        assert_eq!(
+            metadata.modified().unwrap(),
+            UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+        );
+
+        let link = temp_dir.path().join("bin").join("run");
+        let symlink = SymlinkEntry {
+            path: PathBuf::from("bin/run"),
+            target: PathBuf::from("../script.sh"),
+            target_is_dir: false,
+        };
+        restore_symlink(temp_dir.path(), &link, &symlink).unwrap();
+        assert_eq!(std::fs::read_link(&link).unwrap(), PathBuf::from("../script.sh"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_received_symlinks_go_through_the_sanitizer() {
+        use crate::file_transfer::{chunk::ChunkEngineImpl, sanitize::PathSanitizer};
+
+        let temp_dir = TempDir::new().unwrap();
+        let engine = ChunkEngineImpl::new();
+        let sanitizer = PathSanitizer::new();
+
+        let link = SymlinkEntry {
+            path: PathBuf::from("docs/latest"),
+            target: PathBuf::from("v2/readme.md"),
+            target_is_dir: false,
+        };
+        let restored = engine
+            .restore_received_symlink(&link, temp_dir.path(), &sanitizer)
+            .unwrap();
+        assert_eq!(restored.path, temp_dir.path().join("docs").join("latest"));
+        assert_eq!(std::fs::read_link(&restored.path).unwrap(), PathBuf::from("v2/readme.md"));
+
+        // A link path that climbs out of the root is refused before anything
+        // is created, as is a target that does
+        let climbing = SymlinkEntry {
+            path: PathBuf::from("../outside"),
+            ..link.clone()
+        };
+        assert!(matches!(
+            engine.restore_received_symlink(&climbing, temp_dir.path(), &sanitizer),
+            Err(FileTransferError::UnsafeFileName { .. })
+        ));
+        assert!(!temp_dir.path().parent().unwrap().join("outside").exists());
+
+        let escaping = SymlinkEntry {
+            path: PathBuf::from("passwd"),
+            target: PathBuf::from("../../etc/passwd"),
+            target_is_dir: false,
+        };
+        assert!(matches!(
+            engine.restore_received_symlink(&escaping, temp_dir.path(), &sanitizer),
+            Err(FileTransferError::PermissionDenied { .. })
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+// Content Hashing Module
+//
+// Computes manifest, file and chunk checksums with the algorithm a transfer
+// negotiated. BLAKE3 picks the widest SIMD instruction set the CPU offers at
+// runtime and hashes large buffers across the rayon thread pool; SHA-256 is
+// kept for peers that only understand the original manifest format.
+//
+// aarch64 always gets BLAKE3's NEON path. 32-bit ARMv7 boards only get it
+// with the `neon` feature, since NEON is optional on that architecture. The
+// constrained resource profile hashes on a single thread with a small read
+// buffer so hashing does not starve the rest of the daemon on an SBC.
+
+use crate::file_transfer::types::HashAlgorithm;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Buffers at least this large are hashed on multiple threads
+pub const PARALLEL_HASH_THRESHOLD: usize = 128 * 1024;
+
+/// Read size used when hashing whole files, capped by the resource profile
+const FILE_READ_SIZE: usize = 1024 * 1024;
+
+/// Amount of data hashed when measuring local hashing throughput
+const THROUGHPUT_SAMPLE_SIZE: usize = 16 * 1024 * 1024;
+
+impl HashAlgorithm {
+    /// Hash a single buffer
+    pub fn digest(self, data: &[u8]) -> [u8; 32] {
+        let mut hasher = ContentHasher::new(self);
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+/// Incremental hasher for either supported algorithm
+pub enum ContentHasher {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(Sha256),
+}
+
+impl ContentHasher {
+    /// Create a hasher for the given algorithm
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+        }
+    }
+
+    /// Feed more data into the hash
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Blake3(hasher)
+                if data.len() >= PARALLEL_HASH_THRESHOLD
+                    && crate::platform::ResourceProfile::active().hash_threads > 1 =>
+            {
+                hasher.update_rayon(data);
+            }
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Self::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Finish hashing and return the 32-byte digest
+    pub fn finalize(self) -> [u8; 32] {
+        match self {
+            Self::Blake3(hasher) => *hasher.finalize().as_bytes(),
+            Self::Sha256(hasher) => hasher.finalize().into(),
+        }
+    }
+}
+
+/// Hash many independent buffers, spreading them across CPU cores
+///
+/// Used for chunk checksums, where each chunk is hashed on its own. This
+/// parallelizes SHA-256 as well, which cannot split a single buffer.
+pub fn hash_chunks(algorithm: HashAlgorithm, chunks: &[&[u8]]) -> Vec<[u8; 32]> {
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(crate::platform::ResourceProfile::active().hash_threads)
+        .min(chunks.len());
+    let total: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+
+    if workers <= 1 || total < PARALLEL_HASH_THRESHOLD {
+        return chunks.iter().map(|chunk| algorithm.digest(chunk)).collect();
+    }
+
+    let per_worker = chunks.len().div_ceil(workers);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .chunks(per_worker)
+            .map(|batch| {
+                scope.spawn(move || {
+                    batch.iter().map(|chunk| algorithm.digest(chunk)).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("hashing thread panicked"))
+            .collect()
+    })
+}
+
+/// Hash a file's contents without blocking the async runtime
+pub async fn hash_file(path: &Path, algorithm: HashAlgorithm) -> std::io::Result<[u8; 32]> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(&path)?;
+        let mut hasher = ContentHasher::new(algorithm);
+        let read_size = FILE_READ_SIZE.min(crate::platform::ResourceProfile::active().io_buffer_size);
+        let mut buffer = vec![0u8; read_size];
+
+        loop {
+            let bytes_read = file.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..bytes_read]);
+        }
+
+        Ok(hasher.finalize())
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Local hashing throughput in bytes per second
+///
+/// Measured once per algorithm on first use and cached for the life of the
+/// process, so it can be reported alongside transport benchmarks.
+pub fn hashing_throughput(algorithm: HashAlgorithm) -> u64 {
+    static BLAKE3: OnceLock<u64> = OnceLock::new();
+    static SHA256: OnceLock<u64> = OnceLock::new();
+
+    let cell = match algorithm {
+        HashAlgorithm::Blake3 => &BLAKE3,
+        HashAlgorithm::Sha256 => &SHA256,
+    };
+    *cell.get_or_init(|| measure_throughput(algorithm, THROUGHPUT_SAMPLE_SIZE))
+}
+
+/// Hash `sample_size` bytes and return the observed bytes per second
+fn measure_throughput(algorithm: HashAlgorithm, sample_size: usize) -> u64 {
+    let sample = vec![0xA5u8; sample_size];
+    let start = Instant::now();
+    std::hint::black_box(algorithm.digest(&sample));
+    let elapsed = start.elapsed().as_secs_f64();
+
+    if elapsed > 0.0 {
+        (sample_size as f64 / elapsed) as u64
+    } else {
+        u64::MAX
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            hex::encode(HashAlgorithm::Sha256.digest(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(HashAlgorithm::Blake3.digest(b"abc"), HashAlgorithm::Sha256.digest(b"abc"));
+    }
+
+    #[test]
+    fn test_parallel_hashing_matches_sequential() {
+        let data: Vec<u8> = (0..PARALLEL_HASH_THRESHOLD * 4).map(|i| (i % 251) as u8).collect();
+
+        for algorithm in [HashAlgorithm::Blake3, HashAlgorithm::Sha256] {
+            let mut incremental = ContentHasher::new(algorithm);
+            incremental.update(&data[..1000]);
+            incremental.update(&data[1000..]);
+            assert_eq!(incremental.finalize(), algorithm.digest(&data));
+
+            let chunks: Vec<&[u8]> = data.chunks(64 * 1024).collect();
+            let expected: Vec<_> = chunks.iter().map(|chunk| algorithm.digest(chunk)).collect();
+            assert_eq!(hash_chunks(algorithm, &chunks), expected);
+        }
+    }
+
+    #[test]
+    fn test_measure_throughput_is_positive() {
+        assert!(measure_throughput(HashAlgorithm::Blake3, 1024 * 1024) > 0);
+    }
+}
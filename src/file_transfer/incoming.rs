@@ -4,6 +4,8 @@
 
 use crate::file_transfer::{
     error::{FileTransferError, Result},
+    negotiation::{Negotiation, NegotiationState, OfferResponse, TransferOffer, TransferTerms},
+    preview::{self, FilePreview},
     types::*,
 };
 use serde::{Deserialize, Serialize};
@@ -25,12 +27,16 @@ pub struct IncomingTransferRequest {
     pub received_at: Timestamp,
     /// Request state
     pub state: IncomingRequestState,
+    /// Offer and answers exchanged with the sender
+    pub negotiation: Negotiation,
 }
 
 /// State of an incoming transfer request
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IncomingRequestState {
     Pending,
+    /// Counter-proposed; waiting for the sender to answer
+    Countered,
     Accepted,
     Rejected,
     Expired,
@@ -78,12 +84,40 @@ impl IncomingTransferManager {
         sender_id: PeerId,
         manifest: TransferManifest,
     ) -> Result<IncomingTransferRequest> {
+        self.receive_offer(sender_id, manifest, TransferTerms::default()).await
+    }
+
+    /// Receive an incoming transfer request with the terms the sender proposes
+    pub async fn receive_offer(
+        &self,
+        sender_id: PeerId,
+        mut manifest: TransferManifest,
+        terms: TransferTerms,
+    ) -> Result<IncomingTransferRequest> {
+        // Piped data is saved under its name, so that has to be safe
+        if let Some(pipe) = &manifest.pipe {
+            pipe.validate()?;
+            if !manifest.files.is_empty() {
+                return Err(FileTransferError::InvalidManifest {
+                    reason: "Piped transfer also lists files".to_string(),
+                });
+            }
+        }
+
+        // Previews come from the sender unchecked; only show well-formed ones
+        let dropped = preview::sanitize_previews(&mut manifest);
+        if dropped > 0 {
+            log::debug!("Dropped {} previews from transfer offer by {}", dropped, sender_id);
+        }
+
+        let negotiation = Negotiation::new(TransferOffer::from_manifest(&manifest, terms))?;
         let request = IncomingTransferRequest {
             request_id: manifest.transfer_id,
             sender_id,
             manifest,
             received_at: current_timestamp(),
             state: IncomingRequestState::Pending,
+            negotiation,
         };
 
         // Add to pending requests
@@ -120,23 +154,112 @@ impl IncomingTransferManager {
         &self,
         request_id: TransferId,
         download_location: PathBuf,
+    ) -> Result<TransferManifest> {
+        self.settle_request(request_id, download_location, |negotiation| {
+            negotiation.respond(OfferResponse::AcceptAll).map(|_| ())
+        })
+        .await
+    }
+
+    /// Accept only some of the offered files
+    ///
+    /// Returns the manifest narrowed to those files.
+    pub async fn accept_files(
+        &self,
+        request_id: TransferId,
+        files: Vec<PathBuf>,
+        download_location: PathBuf,
+    ) -> Result<TransferManifest> {
+        self.settle_request(request_id, download_location, |negotiation| {
+            negotiation.respond(OfferResponse::AcceptSubset { files }).map(|_| ())
+        })
+        .await
+    }
+
+    /// Propose different terms back to the sender
+    pub async fn counter_request(&self, request_id: TransferId, terms: TransferTerms) -> Result<()> {
+        self.update_request(request_id, |req| {
+            req.negotiation.respond(OfferResponse::Counter { terms })?;
+            req.state = IncomingRequestState::Countered;
+            Ok(())
+        })
+        .await
+    }
+
+    /// The sender took our counter-proposal
+    ///
+    /// Returns the manifest narrowed to the agreed files.
+    pub async fn counter_accepted(
+        &self,
+        request_id: TransferId,
+        download_location: PathBuf,
+    ) -> Result<TransferManifest> {
+        self.settle_request(request_id, download_location, |negotiation| {
+            negotiation.accept_counter().map(|_| ())
+        })
+        .await
+    }
+
+    /// The sender answered our counter-proposal with a revised offer, which
+    /// is pending again
+    pub async fn offer_revised(&self, request_id: TransferId, terms: TransferTerms) -> Result<()> {
+        self.update_request(request_id, |req| {
+            req.negotiation.revise(terms)?;
+            req.state = IncomingRequestState::Pending;
+            Ok(())
+        })
+        .await
+    }
+
+    /// The sender turned down our counter-proposal
+    pub async fn counter_declined(&self, request_id: TransferId, reason: Option<String>) -> Result<()> {
+        self.update_request(request_id, |req| {
+            req.negotiation.decline_counter(reason)?;
+            req.state = IncomingRequestState::Rejected;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Settle the negotiation and mark the request accepted once the agreed
+    /// files fit in `download_location`
+    async fn settle_request(
+        &self,
+        request_id: TransferId,
+        download_location: PathBuf,
+        settle: impl FnOnce(&mut Negotiation) -> Result<()>,
     ) -> Result<TransferManifest> {
         // Validate download location
         self.validate_download_location(&download_location).await?;
 
-        // Check available disk space
         let request = self.get_request(request_id).await?;
-        self.check_disk_space(&download_location, request.manifest.total_size).await?;
+        let mut negotiation = request.negotiation;
+        settle(&mut negotiation)?;
+        let manifest = negotiation.apply_to(&request.manifest)?;
 
-        // Update request state
-        let mut pending = self.pending_requests.write().await;
-        if let Some(req) = pending.iter_mut().find(|r| r.request_id == request_id) {
+        // Check available disk space for the agreed files only
+        self.check_disk_space(&download_location, manifest.total_size).await?;
+
+        self.update_request(request_id, |req| {
             req.state = IncomingRequestState::Accepted;
-            Ok(req.manifest.clone())
-        } else {
-            Err(FileTransferError::InternalError(
+            req.negotiation = negotiation;
+            Ok(())
+        })
+        .await?;
+        Ok(manifest)
+    }
+
+    async fn update_request(
+        &self,
+        request_id: TransferId,
+        update: impl FnOnce(&mut IncomingTransferRequest) -> Result<()>,
+    ) -> Result<()> {
+        let mut pending = self.pending_requests.write().await;
+        match pending.iter_mut().find(|r| r.request_id == request_id) {
+            Some(req) => update(req),
+            None => Err(FileTransferError::InternalError(
                 format!("Incoming request not found: {}", request_id)
-            ))
+            )),
         }
     }
 
@@ -148,6 +271,9 @@ impl IncomingTransferManager {
     ) -> Result<()> {
         let mut pending = self.pending_requests.write().await;
         if let Some(req) = pending.iter_mut().find(|r| r.request_id == request_id) {
+            if req.negotiation.state() == NegotiationState::Offered {
+                req.negotiation.respond(OfferResponse::Reject { reason })?;
+            }
             req.state = IncomingRequestState::Rejected;
             Ok(())
         } else {
@@ -177,7 +303,7 @@ impl IncomingTransferManager {
         
         let mut removed_count = 0;
         pending.retain(|req| {
-            let is_expired = req.state == IncomingRequestState::Pending
+            let is_expired = matches!(req.state, IncomingRequestState::Pending | IncomingRequestState::Countered)
                 && (current_time - req.received_at) > self.request_timeout;
             
             if is_expired {
@@ -290,6 +416,8 @@ impl IncomingTransferManager {
                 .iter()
                 .map(|f| f.path.clone())
                 .collect(),
+            previews: request.manifest.previews,
+            proposed_terms: request.negotiation.proposed_terms().clone(),
             received_at: request.received_at,
         })
     }
@@ -309,6 +437,12 @@ pub struct TransferRequestDetails {
     pub file_count: usize,
     pub total_size: u64,
     pub file_names: Vec<PathBuf>,
+    /// File icons and thumbnails to help decide whether to accept
+    #[serde(default)]
+    pub previews: Vec<FilePreview>,
+    /// Destination, compression and file selection currently proposed
+    #[serde(default)]
+    pub proposed_terms: TransferTerms,
     pub received_at: Timestamp,
 }
 
@@ -487,4 +621,37 @@ mod tests {
         let pending = manager.get_pending_requests().await.unwrap();
         assert_eq!(pending.len(), 3);
     }
+
+    #[tokio::test]
+    async fn test_counter_proposal_then_accepted() {
+        let manager = IncomingTransferManager::new();
+        let temp_dir = TempDir::new().unwrap();
+        let request = manager
+            .receive_offer(
+                "test-peer".to_string(),
+                create_test_manifest(),
+                TransferTerms { compression: true, ..Default::default() },
+            )
+            .await
+            .unwrap();
+
+        let terms = TransferTerms {
+            destination: Some(PathBuf::from("From test-peer")),
+            ..Default::default()
+        };
+        manager.counter_request(request.request_id, terms.clone()).await.unwrap();
+        assert_eq!(
+            manager.get_request(request.request_id).await.unwrap().state,
+            IncomingRequestState::Countered
+        );
+        assert!(manager.get_pending_requests().await.unwrap().is_empty());
+
+        manager
+            .counter_accepted(request.request_id, temp_dir.path().join("download"))
+            .await
+            .unwrap();
+        let updated_request = manager.get_request(request.request_id).await.unwrap();
+        assert_eq!(updated_request.state, IncomingRequestState::Accepted);
+        assert_eq!(updated_request.negotiation.agreed, Some(terms));
+    }
 }
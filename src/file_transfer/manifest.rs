@@ -4,9 +4,12 @@
 
 use crate::file_transfer::{
     error::{FileTransferError, Result},
+    fidelity,
+    hashing::{self, ContentHasher},
+    manifest_stream::ManifestStreamConfig,
+    preview,
     types::*,
 };
-use sha2::{Digest, Sha256};
 use std::fs::{self, Metadata};
 use std::path::{Path, PathBuf};
 use tokio::fs as tokio_fs;
@@ -129,7 +132,7 @@ impl FileScanner {
     }
 
     /// Extract file permissions from metadata
-    fn extract_permissions(metadata: &Metadata) -> FilePermissions {
+    pub(crate) fn extract_permissions(metadata: &Metadata) -> FilePermissions {
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
@@ -151,7 +154,7 @@ impl FileScanner {
     }
 
     /// Extract modified time from metadata
-    fn extract_modified_time(metadata: &Metadata) -> Timestamp {
+    pub(crate) fn extract_modified_time(metadata: &Metadata) -> Timestamp {
         metadata
             .modified()
             .ok()
@@ -161,7 +164,7 @@ impl FileScanner {
     }
 
     /// Extract created time from metadata
-    fn extract_created_time(metadata: &Metadata) -> Timestamp {
+    pub(crate) fn extract_created_time(metadata: &Metadata) -> Timestamp {
         metadata
             .created()
             .ok()
@@ -174,11 +177,69 @@ impl FileScanner {
 /// Manifest builder implementation
 pub struct ManifestBuilderImpl {
     sender_id: PeerId,
+    hash_algorithm: HashAlgorithm,
+    preservation: MetadataPreservation,
+    previews: bool,
 }
 
 impl ManifestBuilderImpl {
     pub fn new(sender_id: PeerId) -> Self {
-        Self { sender_id }
+        Self {
+            sender_id,
+            hash_algorithm: HashAlgorithm::default(),
+            preservation: MetadataPreservation::default(),
+            previews: false,
+        }
+    }
+
+    /// Include file icons and image thumbnails for the receiver's prompt
+    pub fn with_previews(mut self, previews: bool) -> Self {
+        self.previews = previews;
+        self
+    }
+
+    /// Capture symlinks, xattrs and sparseness, and ask the receiver to
+    /// restore the selected metadata
+    pub fn with_metadata_preservation(mut self, preservation: MetadataPreservation) -> Self {
+        self.preservation = preservation;
+        self
+    }
+
+    /// Metadata this builder captures
+    pub fn metadata_preservation(&self) -> MetadataPreservation {
+        self.preservation
+    }
+
+    /// Hash with the given algorithm, e.g. SHA-256 for a peer without BLAKE3
+    pub fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+
+    /// Peer ID recorded as the sender of built manifests
+    pub fn sender_id(&self) -> &PeerId {
+        &self.sender_id
+    }
+
+    /// Algorithm used for file and manifest checksums
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Attach previews to a built manifest, if enabled
+    fn finish_manifest(&self, mut manifest: TransferManifest) -> TransferManifest {
+        if self.previews {
+            preview::attach_previews(&mut manifest);
+        }
+        manifest
+    }
+
+    /// Empty manifest stamped with this builder's sender and algorithm
+    fn new_manifest(&self) -> TransferManifest {
+        let mut manifest = TransferManifest::new(self.sender_id.clone());
+        manifest.hash_algorithm = self.hash_algorithm;
+        manifest.preservation = self.preservation;
+        manifest
     }
 }
 
@@ -186,43 +247,19 @@ impl ManifestBuilderImpl {
 pub struct ChecksumCalculator;
 
 impl ChecksumCalculator {
-    /// Calculate SHA-256 checksum for a file
-    pub async fn calculate_file_checksum(path: &Path) -> Result<[u8; 32]> {
-        let mut file = tokio_fs::File::open(path)
+    /// Calculate a file's checksum with the given algorithm
+    pub async fn calculate_file_checksum(path: &Path, algorithm: HashAlgorithm) -> Result<[u8; 32]> {
+        hashing::hash_file(path, algorithm)
             .await
             .map_err(|e| FileTransferError::ChecksumError {
                 path: path.to_path_buf(),
                 source: e,
-            })?;
-
-        let mut hasher = Sha256::new();
-        let mut buffer = vec![0u8; 64 * 1024]; // 64KB buffer
-
-        loop {
-            let bytes_read = file
-                .read(&mut buffer)
-                .await
-                .map_err(|e| FileTransferError::ChecksumError {
-                    path: path.to_path_buf(),
-                    source: e,
-                })?;
-
-            if bytes_read == 0 {
-                break;
-            }
-
-            hasher.update(&buffer[..bytes_read]);
-        }
-
-        let result = hasher.finalize();
-        let mut checksum = [0u8; 32];
-        checksum.copy_from_slice(&result);
-        Ok(checksum)
+            })
     }
 
-    /// Calculate SHA-256 checksum for manifest data
+    /// Calculate manifest checksum with the manifest's own algorithm
     pub fn calculate_manifest_checksum(manifest: &TransferManifest) -> Result<[u8; 32]> {
-        let mut hasher = Sha256::new();
+        let mut hasher = ContentHasher::new(manifest.hash_algorithm);
 
         // Hash transfer ID
         hasher.update(manifest.transfer_id.as_bytes());
@@ -246,10 +283,14 @@ impl ChecksumCalculator {
             hasher.update(dir.path.to_string_lossy().as_bytes());
         }
 
-        let result = hasher.finalize();
-        let mut checksum = [0u8; 32];
-        checksum.copy_from_slice(&result);
-        Ok(checksum)
+        // Hash symlink entries; absent for manifests without them, so older
+        // manifests keep the same checksum
+        for link in &manifest.symlinks {
+            hasher.update(link.path.to_string_lossy().as_bytes());
+            hasher.update(link.target.to_string_lossy().as_bytes());
+        }
+
+        Ok(hasher.finalize())
     }
 }
 
@@ -348,7 +389,7 @@ impl ManifestBuilderImpl {
         }
 
         // Calculate checksum
-        let checksum = ChecksumCalculator::calculate_file_checksum(&path).await?;
+        let checksum = ChecksumCalculator::calculate_file_checksum(&path, self.hash_algorithm).await?;
 
         // Calculate chunk count
         let chunk_count = ((scanned_file.size + Chunk::DEFAULT_SIZE as u64 - 1) 
@@ -362,6 +403,7 @@ impl ManifestBuilderImpl {
             permissions: scanned_file.permissions,
             modified_at: scanned_file.modified_at,
             chunk_count,
+            extended: fidelity::capture_extended(&path, &self.preservation),
         };
 
         // Report progress
@@ -370,7 +412,7 @@ impl ManifestBuilderImpl {
         }
 
         // Create manifest
-        let mut manifest = TransferManifest::new(self.sender_id.clone());
+        let mut manifest = self.new_manifest();
         manifest.files.push(file_entry);
         manifest.file_count = 1;
         manifest.total_size = scanned_file.size;
@@ -378,7 +420,7 @@ impl ManifestBuilderImpl {
         // Calculate manifest checksum
         manifest.checksum = ChecksumCalculator::calculate_manifest_checksum(&manifest)?;
 
-        Ok(manifest)
+        Ok(self.finish_manifest(manifest))
     }
 
     /// Build manifest for multiple files
@@ -399,7 +441,7 @@ impl ManifestBuilderImpl {
         }
 
         let total_files = paths.len();
-        let mut manifest = TransferManifest::new(self.sender_id.clone());
+        let mut manifest = self.new_manifest();
         let mut processed = 0;
 
         for path in paths {
@@ -420,7 +462,7 @@ impl ManifestBuilderImpl {
             let scanned_file = FileScanner::scan_file(&path)?;
 
             // Calculate checksum
-            let checksum = ChecksumCalculator::calculate_file_checksum(&path).await?;
+            let checksum = ChecksumCalculator::calculate_file_checksum(&path, self.hash_algorithm).await?;
 
             // Calculate chunk count
             let chunk_count = ((scanned_file.size + Chunk::DEFAULT_SIZE as u64 - 1) 
@@ -434,6 +476,7 @@ impl ManifestBuilderImpl {
                 permissions: scanned_file.permissions,
                 modified_at: scanned_file.modified_at,
                 chunk_count,
+                extended: fidelity::capture_extended(&path, &self.preservation),
             };
 
             manifest.files.push(file_entry);
@@ -452,7 +495,7 @@ impl ManifestBuilderImpl {
         // Calculate manifest checksum
         manifest.checksum = ChecksumCalculator::calculate_manifest_checksum(&manifest)?;
 
-        Ok(manifest)
+        Ok(self.finish_manifest(manifest))
     }
 
     /// Build manifest for a folder (recursive)
@@ -471,71 +514,13 @@ impl ManifestBuilderImpl {
         recursive: bool,
         progress_callback: Option<ManifestProgressCallback>,
     ) -> Result<TransferManifest> {
-        // Validate path exists and is a directory
-        if !path.exists() {
-            return Err(FileTransferError::InvalidPath {
-                path: path.clone(),
-            });
-        }
-
-        if !path.is_dir() {
-            return Err(FileTransferError::InvalidManifest {
-                reason: format!("{} is not a directory", path.display()),
-            });
-        }
-
-        // Scan directory
-        let (scanned_files, scanned_directories) = FileScanner::scan_directory(&path, recursive)?;
-
-        let total_files = scanned_files.len();
-        let mut manifest = TransferManifest::new(self.sender_id.clone());
-        let mut processed = 0;
-
-        // Add directory entries
-        for scanned_dir in scanned_directories {
-            manifest.directories.push(DirectoryEntry {
-                path: scanned_dir.path,
-                permissions: scanned_dir.permissions,
-                created_at: scanned_dir.created_at,
-            });
-        }
-
-        // Process files
-        for scanned_file in scanned_files {
-            // Calculate checksum
-            let checksum = ChecksumCalculator::calculate_file_checksum(&scanned_file.path).await?;
-
-            // Calculate chunk count
-            let chunk_count = ((scanned_file.size + Chunk::DEFAULT_SIZE as u64 - 1) 
-                / Chunk::DEFAULT_SIZE as u64) as usize;
-
-            // Create file entry
-            let file_entry = FileEntry {
-                path: scanned_file.path,
-                size: scanned_file.size,
-                checksum,
-                permissions: scanned_file.permissions,
-                modified_at: scanned_file.modified_at,
-                chunk_count,
-            };
-
-            manifest.files.push(file_entry);
-            manifest.total_size += scanned_file.size;
-
-            processed += 1;
-
-            // Report progress
-            if let Some(ref callback) = progress_callback {
-                callback(processed, total_files);
-            }
-        }
-
-        manifest.file_count = manifest.files.len();
-
-        // Calculate manifest checksum
-        manifest.checksum = ChecksumCalculator::calculate_manifest_checksum(&manifest)?;
-
-        Ok(manifest)
+        // Stream the directory so enumeration, hashing and assembly overlap
+        // instead of holding a full scan in memory before hashing starts
+        let manifest = self
+            .stream_folder_manifest(path, recursive, ManifestStreamConfig::default())?
+            .collect_manifest(progress_callback)
+            .await?;
+        Ok(self.finish_manifest(manifest))
     }
 
     /// Verify manifest integrity
@@ -0,0 +1,449 @@
+// Manifest Streaming Module
+//
+// Builds folder manifests incrementally. Enumeration runs on a blocking thread
+// and feeds a bounded pipeline that hashes a few files at a time, so entries
+// reach the caller while the walk is still going and memory stays bounded by
+// the channel capacities rather than the size of the tree.
+
+use crate::file_transfer::{
+    error::{FileTransferError, Result},
+    fidelity,
+    manifest::{ChecksumCalculator, FileScanner, ManifestBuilderImpl, ManifestProgressCallback},
+    types::*,
+};
+use futures::StreamExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use walkdir::WalkDir;
+
+/// Tuning for streamed manifest creation
+#[derive(Debug, Clone)]
+pub struct ManifestStreamConfig {
+    /// Entries buffered between pipeline stages; bounds memory use
+    pub channel_capacity: usize,
+    /// Files hashed concurrently
+    pub hash_concurrency: usize,
+}
+
+impl Default for ManifestStreamConfig {
+    fn default() -> Self {
+        Self {
+            channel_capacity: 1024,
+            hash_concurrency: 4,
+        }
+    }
+}
+
+/// Totals sent once enumeration and hashing have finished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestSummary {
+    pub transfer_id: TransferId,
+    pub file_count: usize,
+    pub directory_count: usize,
+    pub symlink_count: usize,
+    pub total_size: u64,
+}
+
+/// One step of a streamed manifest
+#[derive(Debug, Clone)]
+pub enum ManifestEvent {
+    /// A directory was found
+    Directory(DirectoryEntry),
+    /// A file was found and hashed; it can be transferred right away
+    File(FileEntry),
+    /// A symlink was found and is sent as a link
+    Symlink(SymlinkEntry),
+    /// Enumeration finished; no more events follow
+    Complete(ManifestSummary),
+}
+
+/// Entry found by the directory walk, before hashing
+enum ScannedEntry {
+    Directory(DirectoryEntry),
+    File(FileEntry),
+    Symlink(SymlinkEntry),
+}
+
+/// Manifest being built in the background
+///
+/// Dropping the stream stops enumeration and hashing.
+pub struct ManifestStream {
+    transfer_id: TransferId,
+    sender_id: PeerId,
+    hash_algorithm: HashAlgorithm,
+    preservation: MetadataPreservation,
+    events: mpsc::Receiver<Result<ManifestEvent>>,
+    files_discovered: Arc<AtomicUsize>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl ManifestStream {
+    /// Transfer ID the finished manifest will carry
+    pub fn transfer_id(&self) -> TransferId {
+        self.transfer_id
+    }
+
+    /// Algorithm file checksums are computed with
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Files enumerated so far, hashed or not
+    pub fn files_discovered(&self) -> usize {
+        self.files_discovered.load(Ordering::Relaxed)
+    }
+
+    /// Wait for the next entry; `None` once the stream has ended
+    pub async fn next_event(&mut self) -> Option<Result<ManifestEvent>> {
+        self.events.recv().await
+    }
+
+    /// Drain the stream into a complete manifest
+    ///
+    /// Progress reports files hashed against files discovered so far; the
+    /// second number keeps growing until enumeration finishes.
+    pub async fn collect_manifest(
+        mut self,
+        progress_callback: Option<ManifestProgressCallback>,
+    ) -> Result<TransferManifest> {
+        let mut manifest = TransferManifest::new(self.sender_id.clone());
+        manifest.transfer_id = self.transfer_id;
+        manifest.hash_algorithm = self.hash_algorithm;
+        manifest.preservation = self.preservation;
+
+        while let Some(event) = self.next_event().await {
+            match event? {
+                ManifestEvent::Directory(entry) => manifest.directories.push(entry),
+                ManifestEvent::Symlink(entry) => manifest.symlinks.push(entry),
+                ManifestEvent::File(entry) => {
+                    manifest.total_size += entry.size;
+                    manifest.files.push(entry);
+
+                    if let Some(ref callback) = progress_callback {
+                        callback(manifest.files.len(), self.files_discovered());
+                    }
+                }
+                ManifestEvent::Complete(_) => {
+                    manifest.file_count = manifest.files.len();
+                    manifest.checksum = ChecksumCalculator::calculate_manifest_checksum(&manifest)?;
+                    return Ok(manifest);
+                }
+            }
+        }
+
+        Err(FileTransferError::InternalError(
+            "Manifest stream ended before enumeration finished".to_string(),
+        ))
+    }
+}
+
+impl Drop for ManifestStream {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl ManifestBuilderImpl {
+    /// Start building a folder manifest in the background
+    ///
+    /// Entries are delivered as they are enumerated and hashed, in walk order,
+    /// so transfers can begin before large trees are fully scanned.
+    pub fn stream_folder_manifest(
+        &self,
+        path: PathBuf,
+        recursive: bool,
+        config: ManifestStreamConfig,
+    ) -> Result<ManifestStream> {
+        if !path.exists() {
+            return Err(FileTransferError::InvalidPath { path });
+        }
+
+        if !path.is_dir() {
+            return Err(FileTransferError::InvalidManifest {
+                reason: format!("{} is not a directory", path.display()),
+            });
+        }
+
+        let capacity = config.channel_capacity.max(1);
+        let (scan_tx, scan_rx) = mpsc::channel(capacity);
+        let (event_tx, event_rx) = mpsc::channel(capacity);
+        let files_discovered = Arc::new(AtomicUsize::new(0));
+        let algorithm = self.hash_algorithm();
+        let preservation = self.metadata_preservation();
+        let transfer_id = uuid::Uuid::new_v4();
+
+        let walker = {
+            let files_discovered = Arc::clone(&files_discovered);
+            tokio::task::spawn_blocking(move || {
+                enumerate(&path, recursive, &preservation, &scan_tx, &files_discovered);
+            })
+        };
+
+        let hasher = tokio::spawn(hash_entries(
+            scan_rx,
+            event_tx,
+            algorithm,
+            transfer_id,
+            config.hash_concurrency.max(1),
+        ));
+
+        Ok(ManifestStream {
+            transfer_id,
+            sender_id: self.sender_id().clone(),
+            hash_algorithm: algorithm,
+            preservation,
+            events: event_rx,
+            files_discovered,
+            tasks: vec![walker, hasher],
+        })
+    }
+}
+
+/// Walk the tree, handing entries to the hashing stage
+///
+/// `blocking_send` applies back-pressure, so the walk pauses whenever
+/// hashing falls `channel_capacity` entries behind.
+fn enumerate(
+    root: &Path,
+    recursive: bool,
+    preservation: &MetadataPreservation,
+    scan_tx: &mpsc::Sender<Result<ScannedEntry>>,
+    files_discovered: &AtomicUsize,
+) {
+    let walker = if recursive {
+        WalkDir::new(root).follow_links(false)
+    } else {
+        WalkDir::new(root).max_depth(1).follow_links(false)
+    };
+
+    for entry in walker {
+        let scanned = entry
+            .map_err(|e| FileTransferError::ScanError {
+                path: root.to_path_buf(),
+                source: std::io::Error::other(e),
+            })
+            .and_then(|entry| {
+                if entry.path() == root {
+                    return Ok(None);
+                }
+                let metadata = entry.metadata().map_err(|e| FileTransferError::ScanError {
+                    path: entry.path().to_path_buf(),
+                    source: std::io::Error::other(e),
+                })?;
+                Ok(scanned_entry(entry.into_path(), &metadata, preservation))
+            });
+
+        let scanned = match scanned {
+            Ok(Some(scanned)) => scanned,
+            // Root and special files (devices, sockets) are skipped
+            Ok(None) => continue,
+            Err(e) => {
+                let _ = scan_tx.blocking_send(Err(e));
+                return;
+            }
+        };
+
+        if matches!(scanned, ScannedEntry::File(_)) {
+            files_discovered.fetch_add(1, Ordering::Relaxed);
+        }
+        if scan_tx.blocking_send(Ok(scanned)).is_err() {
+            // Stream was dropped
+            return;
+        }
+    }
+}
+
+/// Manifest entry for a walked path, with the file checksum still unset
+fn scanned_entry(
+    path: PathBuf,
+    metadata: &std::fs::Metadata,
+    preservation: &MetadataPreservation,
+) -> Option<ScannedEntry> {
+    if metadata.file_type().is_symlink() {
+        // Links are only sent when asked for; otherwise they are skipped,
+        // since the walk does not follow them
+        if !preservation.symlinks {
+            return None;
+        }
+        return fidelity::read_symlink(&path).ok().map(ScannedEntry::Symlink);
+    }
+
+    if metadata.is_dir() {
+        Some(ScannedEntry::Directory(DirectoryEntry {
+            path,
+            permissions: FileScanner::extract_permissions(metadata),
+            created_at: FileScanner::extract_created_time(metadata),
+        }))
+    } else if metadata.is_file() {
+        let size = metadata.len();
+        let extended = fidelity::capture_extended(&path, preservation);
+        Some(ScannedEntry::File(FileEntry {
+            path,
+            size,
+            checksum: [0u8; 32],
+            permissions: FileScanner::extract_permissions(metadata),
+            modified_at: FileScanner::extract_modified_time(metadata),
+            chunk_count: size.div_ceil(Chunk::DEFAULT_SIZE as u64) as usize,
+            extended,
+        }))
+    } else {
+        None
+    }
+}
+
+/// Hash files with bounded concurrency and forward entries in walk order
+async fn hash_entries(
+    scan_rx: mpsc::Receiver<Result<ScannedEntry>>,
+    event_tx: mpsc::Sender<Result<ManifestEvent>>,
+    algorithm: HashAlgorithm,
+    transfer_id: TransferId,
+    hash_concurrency: usize,
+) {
+    let scanned = futures::stream::unfold(scan_rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    });
+    let hashed = scanned
+        .map(|item| hash_entry(item, algorithm))
+        .buffered(hash_concurrency);
+    tokio::pin!(hashed);
+
+    let mut summary = ManifestSummary {
+        transfer_id,
+        file_count: 0,
+        directory_count: 0,
+        symlink_count: 0,
+        total_size: 0,
+    };
+
+    while let Some(event) = hashed.next().await {
+        match &event {
+            Ok(ManifestEvent::Directory(_)) => summary.directory_count += 1,
+            Ok(ManifestEvent::Symlink(_)) => summary.symlink_count += 1,
+            Ok(ManifestEvent::File(entry)) => {
+                summary.file_count += 1;
+                summary.total_size += entry.size;
+            }
+            Ok(ManifestEvent::Complete(_)) => {}
+            Err(_) => {
+                let _ = event_tx.send(event).await;
+                return;
+            }
+        }
+        if event_tx.send(event).await.is_err() {
+            return;
+        }
+    }
+
+    let _ = event_tx.send(Ok(ManifestEvent::Complete(summary))).await;
+}
+
+/// Fill in a scanned file's checksum
+async fn hash_entry(item: Result<ScannedEntry>, algorithm: HashAlgorithm) -> Result<ManifestEvent> {
+    match item? {
+        ScannedEntry::Directory(entry) => Ok(ManifestEvent::Directory(entry)),
+        ScannedEntry::Symlink(entry) => Ok(ManifestEvent::Symlink(entry)),
+        ScannedEntry::File(mut entry) => {
+            entry.checksum = ChecksumCalculator::calculate_file_checksum(&entry.path, algorithm).await?;
+            Ok(ManifestEvent::File(entry))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_transfer::manifest::ManifestValidator;
+    use tempfile::TempDir;
+
+    fn create_tree(files: usize) -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        for i in 0..files {
+            let dir = if i % 2 == 0 { temp_dir.path() } else { nested.as_path() };
+            std::fs::write(dir.join(format!("file-{}.txt", i)), vec![i as u8; 1000 + i]).unwrap();
+        }
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn test_streamed_manifest_matches_entries() {
+        let temp_dir = create_tree(40);
+        let builder = ManifestBuilderImpl::new("sender".to_string());
+        let config = ManifestStreamConfig {
+            channel_capacity: 4,
+            hash_concurrency: 3,
+        };
+
+        let mut stream = builder
+            .stream_folder_manifest(temp_dir.path().to_path_buf(), true, config)
+            .unwrap();
+        let transfer_id = stream.transfer_id();
+
+        let mut files = 0;
+        let mut summary = None;
+        while let Some(event) = stream.next_event().await {
+            match event.unwrap() {
+                ManifestEvent::File(entry) => {
+                    let expected = ChecksumCalculator::calculate_file_checksum(&entry.path, stream.hash_algorithm())
+                        .await
+                        .unwrap();
+                    assert_eq!(entry.checksum, expected);
+                    files += 1;
+                }
+                ManifestEvent::Directory(_) | ManifestEvent::Symlink(_) => {}
+                ManifestEvent::Complete(done) => summary = Some(done),
+            }
+        }
+
+        let summary = summary.expect("stream should complete");
+        assert_eq!(files, 40);
+        assert_eq!(summary.file_count, 40);
+        assert_eq!(summary.directory_count, 1);
+        assert_eq!(summary.transfer_id, transfer_id);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_sent_as_links_when_preserved() {
+        let temp_dir = create_tree(2);
+        std::os::unix::fs::symlink("file-0.txt", temp_dir.path().join("link.txt")).unwrap();
+
+        let plain = ManifestBuilderImpl::new("sender".to_string())
+            .build_folder_manifest(temp_dir.path().to_path_buf(), true)
+            .await
+            .unwrap();
+        assert!(plain.symlinks.is_empty());
+
+        let preserving = ManifestBuilderImpl::new("sender".to_string())
+            .with_metadata_preservation(MetadataPreservation::all())
+            .build_folder_manifest(temp_dir.path().to_path_buf(), true)
+            .await
+            .unwrap();
+        assert_eq!(preserving.file_count, 2);
+        assert_eq!(preserving.symlinks.len(), 1);
+        assert_eq!(preserving.symlinks[0].target, PathBuf::from("file-0.txt"));
+        assert!(preserving.files.iter().all(|file| file.extended.is_some()));
+        assert!(ManifestValidator::validate(&preserving).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_collected_manifest_validates() {
+        let temp_dir = create_tree(10);
+        let builder = ManifestBuilderImpl::new("sender".to_string());
+
+        let manifest = builder
+            .build_folder_manifest(temp_dir.path().to_path_buf(), true)
+            .await
+            .unwrap();
+
+        assert_eq!(manifest.file_count, 10);
+        assert_eq!(manifest.directories.len(), 1);
+        assert!(ManifestValidator::validate(&manifest).unwrap());
+    }
+}
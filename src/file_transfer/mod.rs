@@ -5,7 +5,12 @@
 // transport negotiation.
 
 pub mod manifest;
+pub mod manifest_stream;
 pub mod chunk;
+pub mod hashing;
+pub mod fidelity;
+pub mod sanitize;
+pub mod scan;
 pub mod queue;
 pub mod transport;
 pub mod error;
@@ -22,10 +27,25 @@ pub mod progress;
 pub mod api;
 pub mod notification;
 pub mod incoming;
+pub mod dry_run;
+pub mod fanout;
+pub mod preview;
+pub mod negotiation;
+pub mod pipe;
 
 pub use error::{FileTransferError, Result};
 pub use types::*;
+pub use chunk::{ChunkEngineImpl, ChunkPlan, ChunkSpec, FsyncPolicy};
+pub use manifest::ManifestBuilderImpl;
+pub use sanitize::{CollisionPolicy, PathSanitizer, SanitizeStrategy, SanitizedPath};
+pub use scan::{ClamdAddress, ClamdScanner, CommandScanner, PostReceiveHook, Quarantine, ScanHistory, ScanPolicy, ScanVerdict};
+pub use manifest_stream::{ManifestEvent, ManifestStream, ManifestStreamConfig, ManifestSummary};
 pub use api::{FileTransferSystem, TransferStats};
+pub use dry_run::{ConflictKind, TransferConflict, TransferPlan};
+pub use fanout::{FanOutSender, FanOutTarget, SwarmFetch, SwarmPlan, TargetOutcome};
+pub use preview::{FileKind, FilePreview, Thumbnail};
+pub use pipe::{PipeEntry, PipeReceiver, PipeSender, PipeSummary};
+pub use negotiation::{Negotiation, NegotiationParty, NegotiationState, OfferResponse, TransferOffer, TransferTerms};
 pub use progress::{ProgressTracker, ProgressCallback, EventCallback, TransferEvent};
 pub use notification::{NotificationManager, NotificationCallback, TransferNotification, TransferStatus, FileStatus, FileTransferState};
 pub use incoming::{IncomingTransferManager, IncomingTransferRequest, IncomingRequestState, TransferResponse, TransferRequestDetails};
@@ -155,6 +175,19 @@ pub trait ChunkStream: Send + Sync {
 
     /// Flush the stream
     async fn flush(&mut self) -> Result<()>;
+
+    /// Wait until the stream can accept more data without excessive buffering
+    ///
+    /// Chunk producers await this before each chunk so that a slow network pauses
+    /// production instead of growing the send buffer.
+    async fn ready(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Bytes sent but not yet handed to the network
+    fn buffered_bytes(&self) -> usize {
+        0
+    }
 }
 
 /// Transport negotiator selects optimal transport protocol for file transfers
@@ -0,0 +1,446 @@
+// Transfer Offer Negotiation
+//
+// Before any data moves the sender offers a summary of the manifest along with
+// the terms it proposes: which files, where they go under the receiver's
+// download directory and whether chunks are compressed. The receiver accepts
+// everything, accepts some of the files, counter-proposes different terms or
+// rejects. A counter-proposal goes back to the sender, who accepts it, revises
+// the offer or declines. Every step is recorded in a [`Negotiation`], which is
+// kept with the transfer session so it is clear afterwards what was agreed.
+
+use crate::file_transfer::{
+    error::{FileTransferError, Result},
+    manifest::ChecksumCalculator,
+    types::*,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Offers and counter-proposals allowed before the negotiation is abandoned
+pub const MAX_NEGOTIATION_ROUNDS: usize = 8;
+
+/// Terms a transfer runs under
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferTerms {
+    /// Files to send; `None` for every file in the manifest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<PathBuf>>,
+    /// Folder under the receiver's download directory; `None` for the
+    /// download directory itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<PathBuf>,
+    /// Compress chunks on the wire
+    #[serde(default)]
+    pub compression: bool,
+}
+
+impl TransferTerms {
+    /// Whether `path` is sent under these terms
+    pub fn includes(&self, path: &Path) -> bool {
+        self.files
+            .as_ref()
+            .is_none_or(|files| files.iter().any(|f| f == path))
+    }
+}
+
+/// One file in an offer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OfferedFile {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Manifest summary the sender proposes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferOffer {
+    pub transfer_id: TransferId,
+    pub sender_id: PeerId,
+    pub files: Vec<OfferedFile>,
+    pub total_size: u64,
+    pub terms: TransferTerms,
+}
+
+impl TransferOffer {
+    /// Summarise a manifest for offering under `terms`
+    pub fn from_manifest(manifest: &TransferManifest, terms: TransferTerms) -> Self {
+        Self {
+            transfer_id: manifest.transfer_id,
+            sender_id: manifest.sender_id.clone(),
+            files: manifest
+                .files
+                .iter()
+                .map(|file| OfferedFile {
+                    path: file.path.clone(),
+                    size: file.size,
+                })
+                .collect(),
+            total_size: manifest.total_size,
+            terms,
+        }
+    }
+
+    /// Bytes sent under `terms`
+    pub fn size_under(&self, terms: &TransferTerms) -> u64 {
+        self.files
+            .iter()
+            .filter(|file| terms.includes(&file.path))
+            .map(|file| file.size)
+            .sum()
+    }
+}
+
+/// Receiver's answer to an offer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OfferResponse {
+    AcceptAll,
+    /// Accept only these files, on otherwise unchanged terms
+    AcceptSubset { files: Vec<PathBuf> },
+    /// Propose different terms back to the sender
+    Counter { terms: TransferTerms },
+    Reject {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+}
+
+/// Side of the transfer that took a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NegotiationParty {
+    Sender,
+    Receiver,
+}
+
+/// One step of a negotiation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NegotiationStep {
+    /// The sender offered, or revised its offer after a counter-proposal
+    Offered { terms: TransferTerms },
+    /// The receiver answered the latest offer
+    Responded { response: OfferResponse },
+    /// The sender took the receiver's counter-proposal
+    CounterAccepted,
+    /// The sender turned down the counter-proposal, ending the negotiation
+    CounterDeclined {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+}
+
+/// A recorded step
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegotiationRound {
+    pub party: NegotiationParty,
+    pub step: NegotiationStep,
+    pub at: Timestamp,
+}
+
+/// Where a negotiation stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NegotiationState {
+    /// Waiting for the receiver to answer an offer
+    Offered,
+    /// Waiting for the sender to answer a counter-proposal
+    Countered,
+    Agreed,
+    Rejected,
+}
+
+/// Offer, answers and outcome of one transfer's negotiation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Negotiation {
+    pub offer: TransferOffer,
+    pub rounds: Vec<NegotiationRound>,
+    /// Terms both sides agreed on, once they have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agreed: Option<TransferTerms>,
+}
+
+impl Negotiation {
+    /// Start a negotiation with the sender's offer
+    pub fn new(offer: TransferOffer) -> Result<Self> {
+        validate_terms(&offer, &offer.terms)?;
+        let terms = offer.terms.clone();
+        let mut negotiation = Self {
+            offer,
+            rounds: Vec::new(),
+            agreed: None,
+        };
+        negotiation.record(NegotiationParty::Sender, NegotiationStep::Offered { terms });
+        Ok(negotiation)
+    }
+
+    pub fn state(&self) -> NegotiationState {
+        if self.agreed.is_some() {
+            return NegotiationState::Agreed;
+        }
+        match self.rounds.last().map(|round| &round.step) {
+            Some(NegotiationStep::Responded { response: OfferResponse::Counter { .. } }) => {
+                NegotiationState::Countered
+            }
+            Some(NegotiationStep::Responded { response: OfferResponse::Reject { .. } })
+            | Some(NegotiationStep::CounterDeclined { .. }) => NegotiationState::Rejected,
+            _ => NegotiationState::Offered,
+        }
+    }
+
+    /// Terms currently on the table: the latest offer or counter-proposal
+    pub fn proposed_terms(&self) -> &TransferTerms {
+        self.rounds
+            .iter()
+            .rev()
+            .find_map(|round| match &round.step {
+                NegotiationStep::Offered { terms }
+                | NegotiationStep::Responded { response: OfferResponse::Counter { terms } } => Some(terms),
+                _ => None,
+            })
+            .unwrap_or(&self.offer.terms)
+    }
+
+    /// Record the receiver's answer to the latest offer
+    ///
+    /// Returns the agreed terms when the answer settles the negotiation.
+    pub fn respond(&mut self, response: OfferResponse) -> Result<Option<&TransferTerms>> {
+        self.expect_state(NegotiationState::Offered, "answer an offer")?;
+
+        let offered = self.proposed_terms().clone();
+        let agreed = match &response {
+            OfferResponse::AcceptAll => Some(offered),
+            OfferResponse::AcceptSubset { files } => {
+                let terms = TransferTerms {
+                    files: Some(files.clone()),
+                    ..offered
+                };
+                validate_terms(&self.offer, &terms)?;
+                Some(terms)
+            }
+            OfferResponse::Counter { terms } => {
+                validate_terms(&self.offer, terms)?;
+                self.check_round_limit()?;
+                None
+            }
+            OfferResponse::Reject { .. } => None,
+        };
+
+        self.record(NegotiationParty::Receiver, NegotiationStep::Responded { response });
+        self.agreed = agreed;
+        Ok(self.agreed.as_ref())
+    }
+
+    /// The sender takes the receiver's counter-proposal
+    pub fn accept_counter(&mut self) -> Result<&TransferTerms> {
+        self.expect_state(NegotiationState::Countered, "accept a counter-proposal")?;
+        let terms = self.proposed_terms().clone();
+        self.record(NegotiationParty::Sender, NegotiationStep::CounterAccepted);
+        Ok(self.agreed.insert(terms))
+    }
+
+    /// The sender answers a counter-proposal with a revised offer
+    pub fn revise(&mut self, terms: TransferTerms) -> Result<()> {
+        self.expect_state(NegotiationState::Countered, "revise the offer")?;
+        validate_terms(&self.offer, &terms)?;
+        self.check_round_limit()?;
+        self.record(NegotiationParty::Sender, NegotiationStep::Offered { terms });
+        Ok(())
+    }
+
+    /// The sender turns down a counter-proposal
+    pub fn decline_counter(&mut self, reason: Option<String>) -> Result<()> {
+        self.expect_state(NegotiationState::Countered, "decline a counter-proposal")?;
+        self.record(NegotiationParty::Sender, NegotiationStep::CounterDeclined { reason });
+        Ok(())
+    }
+
+    /// Narrow `manifest` to the agreed files
+    ///
+    /// File count, total size, previews and checksum are recomputed so the
+    /// result validates like any other manifest.
+    pub fn apply_to(&self, manifest: &TransferManifest) -> Result<TransferManifest> {
+        let terms = self.agreed.as_ref().ok_or_else(|| FileTransferError::InvalidNegotiation {
+            reason: "No terms have been agreed yet".to_string(),
+        })?;
+        if manifest.transfer_id != self.offer.transfer_id {
+            return Err(FileTransferError::InvalidNegotiation {
+                reason: format!(
+                    "Negotiation is for transfer {}, not {}",
+                    self.offer.transfer_id, manifest.transfer_id
+                ),
+            });
+        }
+        if terms.files.is_none() {
+            return Ok(manifest.clone());
+        }
+
+        let mut narrowed = manifest.clone();
+        narrowed.files.retain(|file| terms.includes(&file.path));
+        narrowed.symlinks.retain(|link| terms.includes(&link.path));
+        narrowed.previews.retain(|preview| terms.includes(&preview.path));
+        narrowed.file_count = narrowed.files.len();
+        narrowed.total_size = narrowed.files.iter().map(|file| file.size).sum();
+        narrowed.checksum = ChecksumCalculator::calculate_manifest_checksum(&narrowed)?;
+        Ok(narrowed)
+    }
+
+    fn record(&mut self, party: NegotiationParty, step: NegotiationStep) {
+        self.rounds.push(NegotiationRound {
+            party,
+            step,
+            at: current_timestamp(),
+        });
+    }
+
+    fn expect_state(&self, expected: NegotiationState, action: &str) -> Result<()> {
+        let state = self.state();
+        if state != expected {
+            return Err(FileTransferError::InvalidNegotiation {
+                reason: format!("Cannot {} while the negotiation is {:?}", action, state),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_round_limit(&self) -> Result<()> {
+        let proposals = self
+            .rounds
+            .iter()
+            .filter(|round| {
+                matches!(
+                    round.step,
+                    NegotiationStep::Offered { .. }
+                        | NegotiationStep::Responded { response: OfferResponse::Counter { .. } }
+                )
+            })
+            .count();
+        if proposals >= MAX_NEGOTIATION_ROUNDS {
+            return Err(FileTransferError::InvalidNegotiation {
+                reason: format!("Gave up after {} proposals", proposals),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Check that terms only name offered files and a destination that stays
+/// inside the download directory
+fn validate_terms(offer: &TransferOffer, terms: &TransferTerms) -> Result<()> {
+    let invalid = |reason: String| Err(FileTransferError::InvalidNegotiation { reason });
+
+    if let Some(files) = &terms.files {
+        if files.is_empty() {
+            return invalid("No files selected".to_string());
+        }
+        let offered: HashSet<&Path> = offer.files.iter().map(|file| file.path.as_path()).collect();
+        if let Some(unknown) = files.iter().find(|path| !offered.contains(path.as_path())) {
+            return invalid(format!("{} was not offered", unknown.display()));
+        }
+    }
+
+    if let Some(destination) = &terms.destination {
+        let contained = destination.components().next().is_some()
+            && destination
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !contained {
+            return invalid(format!(
+                "Destination {} is not a folder inside the download directory",
+                destination.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, size: u64) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            size,
+            checksum: [0u8; 32],
+            permissions: FilePermissions::default(),
+            modified_at: 0,
+            chunk_count: 0,
+            extended: None,
+        }
+    }
+
+    fn manifest() -> TransferManifest {
+        let mut manifest = TransferManifest::new("sender".to_string());
+        manifest.files = vec![entry("a.jpg", 100), entry("b.jpg", 200), entry("c.mov", 4000)];
+        manifest.file_count = 3;
+        manifest.total_size = 4300;
+        manifest
+    }
+
+    #[test]
+    fn test_accept_subset_narrows_manifest() {
+        let manifest = manifest();
+        let offer = TransferOffer::from_manifest(&manifest, TransferTerms::default());
+        let mut negotiation = Negotiation::new(offer).unwrap();
+
+        let unknown = OfferResponse::AcceptSubset { files: vec![PathBuf::from("../secret")] };
+        assert!(negotiation.respond(unknown).is_err());
+
+        let files = vec![PathBuf::from("a.jpg"), PathBuf::from("b.jpg")];
+        let agreed = negotiation.respond(OfferResponse::AcceptSubset { files }).unwrap().cloned().unwrap();
+        assert_eq!(negotiation.state(), NegotiationState::Agreed);
+        assert_eq!(negotiation.offer.size_under(&agreed), 300);
+
+        let narrowed = negotiation.apply_to(&manifest).unwrap();
+        assert_eq!(narrowed.file_count, 2);
+        assert_eq!(narrowed.total_size, 300);
+        assert!(negotiation.respond(OfferResponse::AcceptAll).is_err());
+    }
+
+    #[test]
+    fn test_counter_proposal_round_trip() {
+        let offer = TransferOffer::from_manifest(
+            &manifest(),
+            TransferTerms { compression: true, ..Default::default() },
+        );
+        let mut negotiation = Negotiation::new(offer).unwrap();
+
+        let escaping = TransferTerms { destination: Some(PathBuf::from("/etc")), ..Default::default() };
+        assert!(negotiation.respond(OfferResponse::Counter { terms: escaping }).is_err());
+
+        let counter = TransferTerms {
+            destination: Some(PathBuf::from("Photos/2024")),
+            compression: false,
+            ..Default::default()
+        };
+        assert_eq!(negotiation.respond(OfferResponse::Counter { terms: counter.clone() }).unwrap(), None);
+        assert_eq!(negotiation.state(), NegotiationState::Countered);
+        assert!(negotiation.accept_counter().is_ok());
+        assert_eq!(negotiation.agreed, Some(counter));
+
+        let parties: Vec<_> = negotiation.rounds.iter().map(|round| round.party).collect();
+        assert_eq!(
+            parties,
+            vec![NegotiationParty::Sender, NegotiationParty::Receiver, NegotiationParty::Sender]
+        );
+    }
+
+    #[test]
+    fn test_declined_counter_ends_negotiation() {
+        let mut negotiation =
+            Negotiation::new(TransferOffer::from_manifest(&manifest(), TransferTerms::default())).unwrap();
+        assert!(negotiation.accept_counter().is_err());
+
+        let counter = TransferTerms { compression: true, ..Default::default() };
+        negotiation.respond(OfferResponse::Counter { terms: counter }).unwrap();
+        negotiation.revise(TransferTerms::default()).unwrap();
+        assert_eq!(negotiation.state(), NegotiationState::Offered);
+
+        let counter = TransferTerms { compression: true, ..Default::default() };
+        negotiation.respond(OfferResponse::Counter { terms: counter }).unwrap();
+        negotiation.decline_counter(Some("Sender is on battery".to_string())).unwrap();
+        assert_eq!(negotiation.state(), NegotiationState::Rejected);
+        assert!(negotiation.apply_to(&manifest()).is_err());
+    }
+}
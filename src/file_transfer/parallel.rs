@@ -468,6 +468,7 @@ mod tests {
             permissions: FilePermissions::default(),
             modified_at: current_timestamp(),
             chunk_count: (size / 65536) as usize + 1,
+            extended: None,
         }
     }
 
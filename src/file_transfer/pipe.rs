@@ -0,0 +1,356 @@
+// Pipe Transfers
+//
+// `kizuna send -` sends whatever arrives on stdin and `kizuna receive
+// --to-stdout` writes what it receives to stdout, so transfers fit into shell
+// pipelines and database dump workflows. The length is unknown until the pipe
+// closes, so instead of file entries the manifest carries a single
+// [`PipeEntry`], and the data goes through the chunk engine as it is read with
+// no temporary file on either side. An empty chunk at the final offset ends
+// the stream, so a sender that dies halfway shows up as a failed transfer
+// rather than a short file.
+
+use crate::file_transfer::{
+    chunk::ChunkEngineImpl,
+    error::{FileTransferError, Result},
+    hashing::ContentHasher,
+    manifest::ChecksumCalculator,
+    progress::ProgressTracker,
+    types::*,
+    ChunkEngine, ChunkStream,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// File argument that stands for stdin when sending
+pub const STDIO_PATH: &str = "-";
+
+/// Name piped data is offered under when the sender gives none
+pub const DEFAULT_PIPE_NAME: &str = "stdin";
+
+/// Whether a path given on the command line means stdin or stdout
+pub fn is_stdio(path: &Path) -> bool {
+    path == Path::new(STDIO_PATH)
+}
+
+/// Data of unknown length sent from a pipe
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PipeEntry {
+    /// Name the receiver saves the data under, a single path component
+    pub name: PathBuf,
+}
+
+impl PipeEntry {
+    /// Check the name is a plain file name, as it comes from the sender
+    pub fn validate(&self) -> Result<()> {
+        let mut components = self.name.components();
+        if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+            return Err(FileTransferError::UnsafeFileName {
+                name: self.name.display().to_string(),
+                reason: "piped data needs a plain file name".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// What went through a pipe once it closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipeSummary {
+    pub bytes: u64,
+    pub chunks: u64,
+    /// Checksum of all the data, with the manifest's hash algorithm
+    pub checksum: [u8; 32],
+}
+
+/// Manifest for sending piped data as `name`
+pub fn pipe_manifest(sender_id: PeerId, name: Option<&str>) -> Result<TransferManifest> {
+    let pipe = PipeEntry {
+        name: PathBuf::from(name.unwrap_or(DEFAULT_PIPE_NAME)),
+    };
+    pipe.validate()?;
+
+    let mut manifest = TransferManifest::new(sender_id);
+    manifest.pipe = Some(pipe);
+    manifest.checksum = ChecksumCalculator::calculate_manifest_checksum(&manifest)?;
+    Ok(manifest)
+}
+
+/// Sends piped data to one peer as it is read
+pub struct PipeSender {
+    engine: ChunkEngineImpl,
+    progress: Arc<ProgressTracker>,
+}
+
+impl PipeSender {
+    pub fn new(engine: ChunkEngineImpl, progress: Arc<ProgressTracker>) -> Self {
+        Self { engine, progress }
+    }
+
+    /// Read `reader` to the end, sending each chunk as soon as it is full
+    ///
+    /// The session is completed once the end marker is sent, or failed if
+    /// reading or sending fails.
+    pub async fn send<R: AsyncRead + Unpin + Send>(
+        &self,
+        reader: &mut R,
+        pipe: &PipeEntry,
+        session_id: SessionId,
+        stream: &mut dyn ChunkStream,
+    ) -> Result<PipeSummary> {
+        match self.send_chunks(reader, pipe, session_id, stream).await {
+            Ok(summary) => {
+                let _ = self.progress.complete_session(session_id).await;
+                Ok(summary)
+            }
+            Err(e) => {
+                let _ = self.progress.fail_session(session_id, e.to_string()).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn send_chunks<R: AsyncRead + Unpin + Send>(
+        &self,
+        reader: &mut R,
+        pipe: &PipeEntry,
+        session_id: SessionId,
+        stream: &mut dyn ChunkStream,
+    ) -> Result<PipeSummary> {
+        let algorithm = self.engine.hash_algorithm();
+        let mut hasher = ContentHasher::new(algorithm);
+        let mut offset = 0u64;
+        let mut chunk_id = 0u64;
+
+        loop {
+            let data = read_up_to(reader, self.engine.chunk_size(), &pipe.name).await?;
+            let size = data.len();
+            hasher.update(&data);
+
+            let chunk = Chunk {
+                chunk_id,
+                file_path: pipe.name.clone(),
+                offset,
+                size,
+                checksum: algorithm.digest(&data),
+                data,
+                compressed: false,
+            };
+            self.engine.stream_chunk(chunk, stream).await?;
+
+            // The empty chunk is the end marker
+            if size == 0 {
+                break;
+            }
+            offset += size as u64;
+            chunk_id += 1;
+            let _ = self.progress.update_progress(session_id, offset).await;
+        }
+
+        Ok(PipeSummary {
+            bytes: offset,
+            chunks: chunk_id,
+            checksum: hasher.finalize(),
+        })
+    }
+}
+
+/// Receives piped data and writes it out as it arrives
+pub struct PipeReceiver {
+    engine: ChunkEngineImpl,
+}
+
+impl PipeReceiver {
+    pub fn new(engine: ChunkEngineImpl) -> Self {
+        Self { engine }
+    }
+
+    /// Write the chunks of `pipe` to `writer` until the end marker
+    ///
+    /// Every chunk is verified before it is written and must follow on from
+    /// the previous one; a connection that closes before the end marker is
+    /// an error even though everything up to it has been written.
+    pub async fn receive<W: AsyncWrite + Unpin + Send>(
+        &self,
+        stream: &mut dyn ChunkStream,
+        pipe: &PipeEntry,
+        writer: &mut W,
+    ) -> Result<PipeSummary> {
+        self.write_chunks(stream, pipe, None, writer).await
+    }
+
+    /// Write the next pipe on `stream` to `writer`, whatever it is called
+    ///
+    /// For receivers that never saw the manifest: the name is taken from the
+    /// first chunk and checked like a manifest's would be.
+    pub async fn receive_next<W: AsyncWrite + Unpin + Send>(
+        &self,
+        stream: &mut dyn ChunkStream,
+        writer: &mut W,
+    ) -> Result<(PipeEntry, PipeSummary)> {
+        let first = self.engine.receive_chunk(stream).await?;
+        let pipe = PipeEntry {
+            name: first.file_path.clone(),
+        };
+        pipe.validate()?;
+
+        let summary = self.write_chunks(stream, &pipe, Some(first), writer).await?;
+        Ok((pipe, summary))
+    }
+
+    async fn write_chunks<W: AsyncWrite + Unpin + Send>(
+        &self,
+        stream: &mut dyn ChunkStream,
+        pipe: &PipeEntry,
+        mut first: Option<Chunk>,
+        writer: &mut W,
+    ) -> Result<PipeSummary> {
+        let write_error = |e| FileTransferError::IoError {
+            path: PathBuf::from(STDIO_PATH),
+            source: e,
+        };
+        let mut hasher = ContentHasher::new(self.engine.hash_algorithm());
+        let mut offset = 0u64;
+        let mut chunk_id = 0u64;
+
+        loop {
+            let chunk = match first.take() {
+                Some(chunk) => chunk,
+                None => self.engine.receive_chunk(stream).await?,
+            };
+            if chunk.file_path != pipe.name || chunk.chunk_id != chunk_id || chunk.offset != offset {
+                return Err(FileTransferError::CorruptionDetected {
+                    reason: format!(
+                        "Expected chunk {} of {} at offset {}, got chunk {} of {} at {}",
+                        chunk_id,
+                        pipe.name.display(),
+                        offset,
+                        chunk.chunk_id,
+                        chunk.file_path.display(),
+                        chunk.offset
+                    ),
+                });
+            }
+            if chunk.size == 0 {
+                break;
+            }
+
+            writer.write_all(&chunk.data).await.map_err(write_error)?;
+            hasher.update(&chunk.data);
+            offset += chunk.size as u64;
+            chunk_id += 1;
+        }
+        writer.flush().await.map_err(write_error)?;
+
+        Ok(PipeSummary {
+            bytes: offset,
+            chunks: chunk_id,
+            checksum: hasher.finalize(),
+        })
+    }
+}
+
+/// Read until `limit` bytes or the end of input, whichever comes first
+///
+/// Pipes hand over data in small pieces; gathering a full chunk keeps the
+/// per-chunk overhead the same as for files.
+async fn read_up_to<R: AsyncRead + Unpin>(reader: &mut R, limit: usize, name: &Path) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(limit);
+    (&mut *reader)
+        .take(limit as u64)
+        .read_to_end(&mut data)
+        .await
+        .map_err(|e| FileTransferError::IoError {
+            path: name.to_path_buf(),
+            source: e,
+        })?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Stream that records what is sent and can replay it
+    #[derive(Clone, Default)]
+    struct MemoryStream {
+        data: Arc<Mutex<Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl ChunkStream for MemoryStream {
+        async fn send(&mut self, data: &[u8]) -> Result<()> {
+            self.data.lock().unwrap().extend_from_slice(data);
+            Ok(())
+        }
+
+        async fn receive(&mut self, buffer: &mut [u8]) -> Result<usize> {
+            let mut data = self.data.lock().unwrap();
+            let n = buffer.len().min(data.len());
+            buffer[..n].copy_from_slice(&data[..n]);
+            data.drain(..n);
+            Ok(n)
+        }
+
+        async fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipe_round_trip_without_known_length() {
+        let manifest = pipe_manifest("sender".to_string(), Some("backup.tar")).unwrap();
+        let pipe = manifest.pipe.clone().unwrap();
+        let input: Vec<u8> = (0..Chunk::DEFAULT_SIZE * 2 + 123).map(|i| (i % 251) as u8).collect();
+
+        let stream = MemoryStream::default();
+        let sender = PipeSender::new(ChunkEngineImpl::new(), Arc::new(ProgressTracker::new()));
+        let sent = sender
+            .send(&mut input.as_slice(), &pipe, uuid::Uuid::new_v4(), &mut stream.clone())
+            .await
+            .unwrap();
+        assert_eq!(sent.bytes, input.len() as u64);
+        assert_eq!(sent.chunks, 3);
+
+        let mut output = Vec::new();
+        let received = PipeReceiver::new(ChunkEngineImpl::new())
+            .receive(&mut stream.clone(), &pipe, &mut output)
+            .await
+            .unwrap();
+        assert_eq!(output, input);
+        assert_eq!(received, sent);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_pipe_is_an_error() {
+        let pipe = PipeEntry { name: PathBuf::from("dump.sql") };
+        let stream = MemoryStream::default();
+        let engine = ChunkEngineImpl::new();
+        let chunk = Chunk {
+            chunk_id: 0,
+            file_path: pipe.name.clone(),
+            offset: 0,
+            size: 4,
+            data: b"BEGI".to_vec(),
+            checksum: engine.hash_algorithm().digest(b"BEGI"),
+            compressed: false,
+        };
+        engine.stream_chunk(chunk, &mut stream.clone()).await.unwrap();
+
+        let mut output = Vec::new();
+        let result = PipeReceiver::new(ChunkEngineImpl::new())
+            .receive(&mut stream.clone(), &pipe, &mut output)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(output, b"BEGI");
+
+        assert!(pipe_manifest("sender".to_string(), Some("../etc/passwd")).is_err());
+        assert_eq!(
+            pipe_manifest("sender".to_string(), None).unwrap().pipe.unwrap().name,
+            PathBuf::from(DEFAULT_PIPE_NAME)
+        );
+    }
+}
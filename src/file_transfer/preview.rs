@@ -0,0 +1,329 @@
+// Transfer offer previews
+//
+// A manifest can carry a small preview for its files so the receiving user
+// can decide whether to accept before anything is sent: the kind of file for
+// an icon, its MIME type and, for images, a thumbnail. Previews are made on
+// the sender and are advisory; they are not covered by the manifest checksum,
+// so the receiver checks their size and format before showing them and drops
+// the ones that do not pass.
+
+use crate::file_transfer::error::{FileTransferError, Result};
+use crate::file_transfer::types::TransferManifest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Longest side of a thumbnail in pixels
+pub const MAX_THUMBNAIL_DIMENSION: u32 = 128;
+
+/// Largest encoded thumbnail
+pub const MAX_THUMBNAIL_BYTES: usize = 16 * 1024;
+
+/// Files in one offer that get a thumbnail; the rest only get an icon
+pub const MAX_THUMBNAILS_PER_OFFER: usize = 8;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_SIGNATURE: &[u8] = &[0xFF, 0xD8, 0xFF];
+
+/// Broad kind of a file, for picking an icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    Image,
+    Video,
+    Audio,
+    Document,
+    Archive,
+    Text,
+    Executable,
+    Other,
+}
+
+impl FileKind {
+    /// Guess the kind from the file extension
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "tif" | "tiff" | "heic" | "svg" => FileKind::Image,
+            "mp4" | "mkv" | "mov" | "avi" | "webm" | "m4v" => FileKind::Video,
+            "mp3" | "flac" | "ogg" | "opus" | "wav" | "m4a" | "aac" => FileKind::Audio,
+            "pdf" | "doc" | "docx" | "odt" | "xls" | "xlsx" | "ods" | "ppt" | "pptx" | "odp" | "epub" => {
+                FileKind::Document
+            }
+            "zip" | "tar" | "gz" | "tgz" | "xz" | "bz2" | "zst" | "7z" | "rar" => FileKind::Archive,
+            "txt" | "md" | "csv" | "json" | "toml" | "yaml" | "yml" | "xml" | "log" | "rs" | "py" | "js" | "ts"
+            | "c" | "h" | "cpp" | "go" | "sh" => FileKind::Text,
+            "exe" | "msi" | "dmg" | "pkg" | "deb" | "rpm" | "apk" | "appimage" | "bat" | "ps1" => {
+                FileKind::Executable
+            }
+            _ => FileKind::Other,
+        }
+    }
+
+    /// Freedesktop icon name for the kind
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            FileKind::Image => "image-x-generic",
+            FileKind::Video => "video-x-generic",
+            FileKind::Audio => "audio-x-generic",
+            FileKind::Document => "x-office-document",
+            FileKind::Archive => "package-x-generic",
+            FileKind::Text => "text-x-generic",
+            FileKind::Executable => "application-x-executable",
+            FileKind::Other => "text-x-generic-template",
+        }
+    }
+}
+
+/// Small image shown with an offer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    /// "image/png" or "image/jpeg"
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Thumbnail {
+    /// Check a thumbnail from a peer before showing it
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| Err(FileTransferError::InvalidManifest { reason });
+
+        if self.data.len() > MAX_THUMBNAIL_BYTES {
+            return invalid(format!(
+                "Thumbnail is {} bytes, more than {}",
+                self.data.len(),
+                MAX_THUMBNAIL_BYTES
+            ));
+        }
+        if self.width == 0
+            || self.height == 0
+            || self.width > MAX_THUMBNAIL_DIMENSION
+            || self.height > MAX_THUMBNAIL_DIMENSION
+        {
+            return invalid(format!("Thumbnail is {}x{}", self.width, self.height));
+        }
+
+        let signature = match self.mime_type.as_str() {
+            "image/png" => PNG_SIGNATURE,
+            "image/jpeg" => JPEG_SIGNATURE,
+            other => return invalid(format!("Thumbnail type {} is not allowed", other)),
+        };
+        if !self.data.starts_with(signature) {
+            return invalid(format!("Thumbnail data is not {}", self.mime_type));
+        }
+        Ok(())
+    }
+}
+
+/// Preview of one file in an offer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilePreview {
+    /// Path of the file entry in the manifest
+    pub path: PathBuf,
+    pub kind: FileKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<Thumbnail>,
+}
+
+impl FilePreview {
+    /// Preview a file on disk, with a thumbnail if asked for and possible
+    pub fn for_file(path: &Path, with_thumbnail: bool) -> Self {
+        let kind = FileKind::from_path(path);
+        let thumbnail = (with_thumbnail && kind == FileKind::Image)
+            .then(|| image_thumbnail(path))
+            .flatten();
+
+        Self {
+            path: path.to_path_buf(),
+            kind,
+            mime_type: mime_type(path).map(str::to_string),
+            thumbnail,
+        }
+    }
+}
+
+/// Attach previews for every file in a manifest that is being offered
+///
+/// Only the first [`MAX_THUMBNAILS_PER_OFFER`] images get a thumbnail, to
+/// keep the offer small.
+pub fn attach_previews(manifest: &mut TransferManifest) {
+    let mut thumbnails = 0;
+    manifest.previews = manifest
+        .files
+        .iter()
+        .map(|file| {
+            let preview = FilePreview::for_file(&file.path, thumbnails < MAX_THUMBNAILS_PER_OFFER);
+            if preview.thumbnail.is_some() {
+                thumbnails += 1;
+            }
+            preview
+        })
+        .collect();
+}
+
+/// Drop previews from a received offer that should not be shown
+///
+/// Removes previews for paths the manifest does not contain, thumbnails that
+/// fail [`Thumbnail::validate`] and thumbnails beyond the per-offer limit.
+/// Returns how many previews or thumbnails were dropped.
+pub fn sanitize_previews(manifest: &mut TransferManifest) -> usize {
+    let paths: HashSet<&Path> = manifest.files.iter().map(|file| file.path.as_path()).collect();
+    let before = manifest.previews.len();
+    let mut previews = std::mem::take(&mut manifest.previews);
+    previews.retain(|preview| paths.contains(preview.path.as_path()));
+    previews.truncate(manifest.files.len());
+    let mut dropped = before - previews.len();
+
+    let mut thumbnails = 0;
+    for preview in &mut previews {
+        let keep = match &preview.thumbnail {
+            Some(thumbnail) => thumbnails < MAX_THUMBNAILS_PER_OFFER && thumbnail.validate().is_ok(),
+            None => continue,
+        };
+        if keep {
+            thumbnails += 1;
+        } else {
+            preview.thumbnail = None;
+            dropped += 1;
+        }
+    }
+
+    manifest.previews = previews;
+    dropped
+}
+
+fn mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        "ogg" | "opus" => "audio/ogg",
+        "wav" => "audio/wav",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "json" => "application/json",
+        _ => return None,
+    })
+}
+
+/// Decode an image file and shrink it to a thumbnail
+#[cfg(feature = "transfer-previews")]
+fn image_thumbnail(path: &Path) -> Option<Thumbnail> {
+    use std::io::Cursor;
+
+    /// Images larger than this are not decoded
+    const MAX_THUMBNAIL_SOURCE_BYTES: u64 = 32 * 1024 * 1024;
+
+    if std::fs::metadata(path).ok()?.len() > MAX_THUMBNAIL_SOURCE_BYTES {
+        return None;
+    }
+    let image = image::open(path)
+        .map_err(|e| log::debug!("No thumbnail for {}: {}", path.display(), e))
+        .ok()?
+        .thumbnail(MAX_THUMBNAIL_DIMENSION, MAX_THUMBNAIL_DIMENSION);
+
+    let mut data = Cursor::new(Vec::new());
+    let (mime_type, result) = if image.color().has_alpha() {
+        ("image/png", image.write_to(&mut data, image::ImageOutputFormat::Png))
+    } else {
+        let rgb = image::DynamicImage::ImageRgb8(image.to_rgb8());
+        ("image/jpeg", rgb.write_to(&mut data, image::ImageOutputFormat::Jpeg(70)))
+    };
+    result.ok()?;
+
+    let thumbnail = Thumbnail {
+        width: image.width(),
+        height: image.height(),
+        mime_type: mime_type.to_string(),
+        data: data.into_inner(),
+    };
+    thumbnail.validate().ok()?;
+    Some(thumbnail)
+}
+
+/// Thumbnails need the `transfer-previews` feature; without it offers only
+/// carry the file kind
+#[cfg(not(feature = "transfer-previews"))]
+fn image_thumbnail(_path: &Path) -> Option<Thumbnail> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_transfer::types::{FileEntry, FilePermissions};
+
+    fn entry(path: &str) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            size: 0,
+            checksum: [0u8; 32],
+            permissions: FilePermissions::default(),
+            modified_at: 0,
+            chunk_count: 0,
+            extended: None,
+        }
+    }
+
+    fn png_thumbnail(len: usize) -> Thumbnail {
+        let mut data = PNG_SIGNATURE.to_vec();
+        data.resize(len, 0);
+        Thumbnail {
+            width: 64,
+            height: 48,
+            mime_type: "image/png".to_string(),
+            data,
+        }
+    }
+
+    #[test]
+    fn test_file_kind_from_extension() {
+        assert_eq!(FileKind::from_path(Path::new("holiday/IMG_0001.JPG")), FileKind::Image);
+        assert_eq!(FileKind::from_path(Path::new("setup.exe")), FileKind::Executable);
+        assert_eq!(FileKind::from_path(Path::new("notes")), FileKind::Other);
+
+        let preview = FilePreview::for_file(Path::new("report.pdf"), true);
+        assert_eq!(preview.kind, FileKind::Document);
+        assert_eq!(preview.mime_type.as_deref(), Some("application/pdf"));
+        assert!(preview.thumbnail.is_none());
+    }
+
+    #[test]
+    fn test_sanitize_drops_unknown_paths_and_bad_thumbnails() {
+        let mut manifest = TransferManifest::new("sender".to_string());
+        manifest.files = vec![entry("a.png"), entry("b.png")];
+
+        let mut spoofed = png_thumbnail(100);
+        spoofed.mime_type = "image/svg+xml".to_string();
+        manifest.previews = vec![
+            FilePreview { thumbnail: Some(png_thumbnail(100)), ..FilePreview::for_file(Path::new("a.png"), false) },
+            FilePreview { thumbnail: Some(spoofed), ..FilePreview::for_file(Path::new("b.png"), false) },
+            FilePreview::for_file(Path::new("../../etc/passwd"), false),
+        ];
+
+        assert_eq!(sanitize_previews(&mut manifest), 2);
+        assert_eq!(manifest.previews.len(), 2);
+        assert!(manifest.previews[0].thumbnail.is_some());
+        assert!(manifest.previews[1].thumbnail.is_none());
+        assert!(png_thumbnail(MAX_THUMBNAIL_BYTES + 1).validate().is_err());
+    }
+}
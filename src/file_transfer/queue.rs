@@ -4,14 +4,18 @@
 
 use crate::file_transfer::{
     error::{FileTransferError, Result},
+    manifest::FileScanner,
     types::*,
 };
+use crate::journal::Journal;
+use crate::metered::TrafficPolicy;
+use crate::security::storage::EncryptedStorage;
 use serde::{Deserialize, Serialize};
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -40,14 +44,21 @@ impl Ord for PriorityQueueItem {
         // Higher priority comes first
         match self.item.priority.cmp(&other.item.priority) {
             std::cmp::Ordering::Equal => {
-                // If priorities are equal, earlier created_at comes first (FIFO)
-                other.item.created_at.cmp(&self.item.created_at)
+                // If priorities are equal, earlier position comes first (FIFO)
+                (other.item.position, other.item.created_at)
+                    .cmp(&(self.item.position, self.item.created_at))
             }
             ordering => ordering,
         }
     }
 }
 
+/// Journal name for the transfer queue within the persistence directory
+const QUEUE_JOURNAL: &str = "transfer_queue";
+
+/// Journal entry kind for a queue item
+const JOURNAL_KIND_QUEUE_ITEM: &str = "queue_item";
+
 /// Queue manager implementation
 pub struct QueueManagerImpl {
     /// Priority queue for pending transfers
@@ -56,24 +67,44 @@ pub struct QueueManagerImpl {
     pub items: Arc<RwLock<HashMap<QueueId, QueueItem>>>,
     /// Queue persistence directory
     persistence_dir: PathBuf,
+    /// Write-ahead journal of queue state
+    journal: Journal,
     /// Maximum concurrent transfers
     max_concurrent: usize,
+    /// Maximum concurrent transfers with a single peer, if capped
+    max_per_peer: Option<usize>,
     /// Currently active transfer count
     active_count: Arc<RwLock<usize>>,
+    /// Active transfer count per peer, used to share slots fairly
+    active_per_peer: Arc<RwLock<HashMap<PeerId, usize>>>,
+    /// Position given to the next enqueued item
+    next_position: AtomicU64,
 }
 
 impl QueueManagerImpl {
     /// Create a new queue manager with persistence directory
-    pub fn new(persistence_dir: PathBuf, max_concurrent: usize) -> Self {
+    ///
+    /// The queue journal is encrypted at rest with `storage`.
+    pub fn new(persistence_dir: PathBuf, max_concurrent: usize, storage: Option<EncryptedStorage>) -> Self {
         Self {
             queue: Arc::new(RwLock::new(BinaryHeap::new())),
             items: Arc::new(RwLock::new(HashMap::new())),
+            journal: Journal::new(persistence_dir.clone(), QUEUE_JOURNAL, storage),
             persistence_dir,
             max_concurrent,
+            max_per_peer: None,
             active_count: Arc::new(RwLock::new(0)),
+            active_per_peer: Arc::new(RwLock::new(HashMap::new())),
+            next_position: AtomicU64::new(1),
         }
     }
 
+    /// Cap how many transfers a single peer may have running at once
+    pub fn with_max_per_peer(mut self, max_per_peer: usize) -> Self {
+        self.max_per_peer = Some(max_per_peer.max(1));
+        self
+    }
+
     /// Initialize queue manager and load persisted queue items
     pub async fn initialize(&self) -> Result<()> {
         fs::create_dir_all(&self.persistence_dir)
@@ -103,6 +134,8 @@ impl QueueManagerImpl {
             estimated_start: None,
             state: QueueState::Pending,
             created_at,
+            position: self.next_position.fetch_add(1, Ordering::Relaxed),
+            failure: None,
         };
 
         let mut queue = self.queue.write().await;
@@ -201,6 +234,31 @@ impl QueueManagerImpl {
         Ok(())
     }
 
+    /// Move an item within its priority level, keeping the order across restarts
+    pub async fn set_queue_item_position(&self, queue_id: QueueId, position: u64) -> Result<()> {
+        let mut items = self.items.write().await;
+        let item = items
+            .get_mut(&queue_id)
+            .ok_or_else(|| FileTransferError::QueueItemNotFound {
+                queue_id: queue_id.to_string(),
+            })?;
+        item.position = position;
+        let updated = item.clone();
+        drop(items);
+
+        let mut queue = self.queue.write().await;
+        let all_items: Vec<PriorityQueueItem> = queue.drain().collect();
+        for mut pq_item in all_items {
+            if pq_item.item.queue_id == queue_id {
+                pq_item.item.position = position;
+            }
+            queue.push(pq_item);
+        }
+        drop(queue);
+
+        self.persist_queue_item(&updated).await
+    }
+
     /// Get all queue items
     pub async fn get_all_queue_items(&self) -> Result<Vec<QueueItem>> {
         let items = self.items.read().await;
@@ -235,7 +293,7 @@ impl QueueManagerImpl {
 
         pending.sort_by(|a, b| {
             match b.priority.cmp(&a.priority) {
-                std::cmp::Ordering::Equal => a.created_at.cmp(&b.created_at),
+                std::cmp::Ordering::Equal => (a.position, a.created_at).cmp(&(b.position, b.created_at)),
                 ordering => ordering,
             }
         });
@@ -243,37 +301,67 @@ impl QueueManagerImpl {
         Ok(pending)
     }
 
-    /// Get next item to process from queue
-    pub async fn get_next_item(&self) -> Result<Option<QueueItem>> {
-        let mut queue = self.queue.write().await;
-        let items = self.items.read().await;
+    /// Get pending items in the order they should start
+    ///
+    /// Within a priority level, peers with fewer running transfers go first
+    /// so one busy peer cannot take every slot. Peers at their per-peer cap
+    /// are left out until one of their transfers finishes.
+    pub async fn get_schedulable_items(&self) -> Result<Vec<QueueItem>> {
+        let pending = self.get_pending_items().await?;
+        let active_per_peer = self.active_per_peer.read().await;
+        let running = |item: &QueueItem| {
+            active_per_peer
+                .get(&item.transfer_request.peer_id)
+                .copied()
+                .unwrap_or(0)
+        };
 
-        while let Some(pq_item) = queue.pop() {
-            if let Some(item) = items.get(&pq_item.item.queue_id) {
-                if item.state == QueueState::Pending {
-                    queue.push(pq_item.clone());
-                    return Ok(Some(item.clone()));
-                }
-            }
-        }
+        let mut schedulable: Vec<QueueItem> = pending
+            .into_iter()
+            .filter(|item| self.max_per_peer.is_none_or(|max| running(item) < max))
+            .collect();
+
+        // Stable sort keeps FIFO order among equally loaded peers
+        schedulable.sort_by(|a, b| {
+            b.priority
+                .cmp(&a.priority)
+                .then_with(|| running(a).cmp(&running(b)))
+        });
+
+        Ok(schedulable)
+    }
 
-        Ok(None)
+    /// Get next item to process from queue
+    pub async fn get_next_item(&self) -> Result<Option<QueueItem>> {
+        Ok(self.get_schedulable_items().await?.into_iter().next())
     }
 
     /// Mark item as scheduled
     pub async fn mark_item_scheduled(&self, queue_id: QueueId) -> Result<()> {
+        let peer_id = self.get_queue_item(queue_id).await?.transfer_request.peer_id;
         self.update_queue_item_state(queue_id, QueueState::Scheduled)
             .await?;
         let mut active_count = self.active_count.write().await;
         *active_count += 1;
+        *self.active_per_peer.write().await.entry(peer_id).or_insert(0) += 1;
         Ok(())
     }
 
     /// Mark item as completed
     pub async fn mark_item_completed(&self, queue_id: QueueId) -> Result<()> {
+        let peer_id = self.get_queue_item(queue_id).await.ok().map(|item| item.transfer_request.peer_id);
         self.remove_from_queue(queue_id).await?;
         let mut active_count = self.active_count.write().await;
         *active_count = active_count.saturating_sub(1);
+        if let Some(peer_id) = peer_id {
+            let mut active_per_peer = self.active_per_peer.write().await;
+            if let Some(count) = active_per_peer.get_mut(&peer_id) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    active_per_peer.remove(&peer_id);
+                }
+            }
+        }
         self.delete_persisted_queue_item(queue_id).await?;
         let mut items = self.items.write().await;
         items.remove(&queue_id);
@@ -290,6 +378,16 @@ impl QueueManagerImpl {
         self.max_concurrent
     }
 
+    /// Get the per-peer cap on concurrent transfers, if any
+    pub fn get_max_per_peer(&self) -> Option<usize> {
+        self.max_per_peer
+    }
+
+    /// Get the number of running transfers with a peer
+    pub async fn get_active_count_for_peer(&self, peer_id: &PeerId) -> usize {
+        self.active_per_peer.read().await.get(peer_id).copied().unwrap_or(0)
+    }
+
     /// Check if queue has capacity
     pub async fn has_capacity(&self) -> bool {
         let active_count = self.get_active_count().await;
@@ -322,39 +420,76 @@ impl QueueManagerImpl {
         Ok(())
     }
 
-    /// Persist queue item to disk
+    /// Record queue item state in the journal
     pub async fn persist_queue_item(&self, item: &QueueItem) -> Result<()> {
-        let item_file = self.get_queue_item_file_path(item.queue_id);
-        let item_json = serde_json::to_vec_pretty(item).map_err(|e| {
-            FileTransferError::InternalError(format!("Failed to serialize queue item: {}", e))
-        })?;
+        self.journal
+            .put(&item.queue_id.to_string(), JOURNAL_KIND_QUEUE_ITEM, item)
+            .await
+            .map_err(|e| self.journal_error(e))
+    }
 
-        let mut file = fs::File::create(&item_file).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: item_file.clone(),
-                source: e,
-            }
-        })?;
+    /// Replay the journal and requeue items interrupted by a crash
+    async fn load_persisted_queue(&self) -> Result<()> {
+        let recovered = self.journal.recover().await.map_err(|e| self.journal_error(e))?;
 
-        file.write_all(&item_json).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: item_file.clone(),
-                source: e,
+        let mut loaded = Vec::new();
+        for entry in recovered.entries {
+            match entry.decode::<QueueItem>() {
+                Ok(item) => loaded.push(item),
+                Err(e) => eprintln!("Failed to decode journaled queue item {}: {}", entry.key, e),
             }
-        })?;
+        }
+        loaded.extend(self.import_legacy_queue().await?);
 
-        file.flush().await.map_err(|e| {
-            FileTransferError::IoError {
-                path: item_file.clone(),
-                source: e,
+        let last_position = loaded.iter().map(|item| item.position).max().unwrap_or(0);
+        self.next_position.fetch_max(last_position + 1, Ordering::Relaxed);
+
+        let mut queue = self.queue.write().await;
+        let mut items = self.items.write().await;
+        for mut item in loaded {
+            // Scheduled items were running when the process died and no transfer
+            // survives a restart, so they go back to waiting for a slot
+            if item.state == QueueState::Scheduled {
+                item.state = QueueState::Pending;
+                item.estimated_start = None;
+                self.persist_queue_item(&item).await?;
+            }
+            // Files may have been edited or deleted while the daemon was down
+            if matches!(item.state, QueueState::Pending | QueueState::Paused)
+                && let Some(failure) = Self::check_sources(&item.transfer_request.manifest).await
+            {
+                item.state = QueueState::Failed;
+                item.failure = Some(failure);
+                item.estimated_start = None;
+                self.persist_queue_item(&item).await?;
             }
-        })?;
+            if item.state == QueueState::Pending {
+                queue.push(PriorityQueueItem { item: item.clone() });
+            }
+            items.insert(item.queue_id, item);
+        }
 
         Ok(())
     }
 
-    /// Load persisted queue items
-    async fn load_persisted_queue(&self) -> Result<()> {
+    /// Check that a manifest's files are still as they were when it was queued
+    async fn check_sources(manifest: &TransferManifest) -> Option<QueueFailure> {
+        for file in &manifest.files {
+            let metadata = match fs::metadata(&file.path).await {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => return Some(QueueFailure::SourceMissing { path: file.path.clone() }),
+            };
+            if metadata.len() != file.size
+                || FileScanner::extract_modified_time(&metadata) != file.modified_at
+            {
+                return Some(QueueFailure::SourceChanged { path: file.path.clone() });
+            }
+        }
+        None
+    }
+
+    /// Move items written as one JSON file each by earlier versions into the journal
+    async fn import_legacy_queue(&self) -> Result<Vec<QueueItem>> {
         let mut entries = fs::read_dir(&self.persistence_dir)
             .await
             .map_err(|e| FileTransferError::IoError {
@@ -362,9 +497,7 @@ impl QueueManagerImpl {
                 source: e,
             })?;
 
-        let mut queue = self.queue.write().await;
-        let mut items = self.items.write().await;
-
+        let mut imported = Vec::new();
         while let Some(entry) = entries.next_entry().await.map_err(|e| {
             FileTransferError::IoError {
                 path: self.persistence_dir.clone(),
@@ -372,70 +505,49 @@ impl QueueManagerImpl {
             }
         })? {
             let path = entry.path();
-
-            if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            let is_queue_file = path.extension().and_then(|s| s.to_str()) == Some("json")
+                && path
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|name| name.starts_with("queue_"));
+            if !is_queue_file {
                 continue;
             }
 
-            match self.load_queue_item_from_file(&path).await {
-                Ok(item) => {
-                    if item.state == QueueState::Pending {
-                        queue.push(PriorityQueueItem { item: item.clone() });
-                    }
-                    items.insert(item.queue_id, item);
-                }
+            let contents = match fs::read(&path).await {
+                Ok(contents) => contents,
                 Err(e) => {
-                    eprintln!("Failed to load queue item from {:?}: {}", path, e);
+                    eprintln!("Failed to read legacy queue item {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            match serde_json::from_slice::<QueueItem>(&contents) {
+                Ok(item) => {
+                    self.persist_queue_item(&item).await?;
+                    fs::remove_file(&path).await.ok();
+                    imported.push(item);
                 }
+                Err(e) => eprintln!("Failed to load legacy queue item {:?}: {}", path, e),
             }
         }
 
-        Ok(())
-    }
-
-    /// Load queue item from file
-    async fn load_queue_item_from_file(&self, path: &PathBuf) -> Result<QueueItem> {
-        let mut file = fs::File::open(path).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: path.clone(),
-                source: e,
-            }
-        })?;
-
-        let mut contents = Vec::new();
-        file.read_to_end(&mut contents).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: path.clone(),
-                source: e,
-            }
-        })?;
-
-        let item: QueueItem = serde_json::from_slice(&contents).map_err(|e| {
-            FileTransferError::InternalError(format!("Failed to deserialize queue item: {}", e))
-        })?;
-
-        Ok(item)
+        Ok(imported)
     }
 
-    /// Delete persisted queue item
+    /// Remove a queue item from the journal
     pub async fn delete_persisted_queue_item(&self, queue_id: QueueId) -> Result<()> {
-        let item_file = self.get_queue_item_file_path(queue_id);
-
-        if item_file.exists() {
-            fs::remove_file(&item_file).await.map_err(|e| {
-                FileTransferError::IoError {
-                    path: item_file,
-                    source: e,
-                }
-            })?;
-        }
-
+        self.journal
+            .remove(&queue_id.to_string())
+            .await
+            .map_err(|e| self.journal_error(e))?;
         Ok(())
     }
 
-    /// Get file path for queue item
-    fn get_queue_item_file_path(&self, queue_id: QueueId) -> PathBuf {
-        self.persistence_dir.join(format!("queue_{}.json", queue_id))
+    fn journal_error(&self, source: std::io::Error) -> FileTransferError {
+        FileTransferError::IoError {
+            path: self.journal.log_path(),
+            source,
+        }
     }
 
     /// Cleanup old items
@@ -474,6 +586,10 @@ pub struct QueueScheduler {
     connection_slots: Arc<RwLock<usize>>,
     total_bandwidth: Arc<RwLock<Option<u64>>>,
     bandwidth_per_transfer: Arc<RwLock<HashMap<QueueId, u64>>>,
+    /// Bulk transfers wait while the connection is metered
+    traffic_policy: Arc<RwLock<TrafficPolicy>>,
+    /// Items the user chose to send despite a metered connection
+    metered_overrides: Arc<RwLock<HashSet<QueueId>>>,
 }
 
 impl QueueScheduler {
@@ -483,9 +599,28 @@ impl QueueScheduler {
             connection_slots: Arc::new(RwLock::new(connection_slots)),
             total_bandwidth: Arc::new(RwLock::new(None)),
             bandwidth_per_transfer: Arc::new(RwLock::new(HashMap::new())),
+            traffic_policy: Arc::new(RwLock::new(TrafficPolicy::unrestricted(None))),
+            metered_overrides: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 
+    /// Apply a new traffic policy; held items start once the connection is unmetered
+    pub async fn set_traffic_policy(&self, policy: TrafficPolicy) {
+        *self.traffic_policy.write().await = policy;
+    }
+
+    /// Let an item run on a metered connection after the user confirmed it
+    pub async fn allow_on_metered(&self, queue_id: QueueId) {
+        self.metered_overrides.write().await.insert(queue_id);
+    }
+
+    /// Whether the traffic policy holds an item back
+    async fn is_held_back(&self, item: &QueueItem) -> bool {
+        let policy = self.traffic_policy.read().await;
+        policy.is_bulk(item.transfer_request.manifest.total_size)
+            && !self.metered_overrides.read().await.contains(&item.queue_id)
+    }
+
     pub async fn set_total_bandwidth(&self, bandwidth: Option<u64>) {
         let mut total_bandwidth = self.total_bandwidth.write().await;
         *total_bandwidth = bandwidth;
@@ -507,7 +642,17 @@ impl QueueScheduler {
             return Ok(None);
         }
 
-        let next_item = self.queue_manager.get_next_item().await?;
+        let mut next_item = self.queue_manager.get_next_item().await?;
+        if let Some(item) = &next_item && self.is_held_back(item).await {
+            // Fall through to the first pending item small enough to send
+            next_item = None;
+            for pending in self.queue_manager.get_schedulable_items().await? {
+                if !self.is_held_back(&pending).await {
+                    next_item = Some(pending);
+                    break;
+                }
+            }
+        }
 
         if let Some(item) = next_item {
             self.allocate_resources(&item).await?;
@@ -538,6 +683,7 @@ impl QueueScheduler {
     }
 
     pub async fn deallocate_resources(&self, queue_id: QueueId) -> Result<()> {
+        self.metered_overrides.write().await.remove(&queue_id);
         let mut bandwidth_allocations = self.bandwidth_per_transfer.write().await;
         bandwidth_allocations.remove(&queue_id);
         drop(bandwidth_allocations);
@@ -679,9 +825,21 @@ impl QueueOperations {
             });
         }
 
+        // Reuse the existing positions so other items keep their relative order
+        let mut positions: Vec<u64> = pending_items.iter().map(|i| i.position).collect();
+        positions.sort_unstable();
+
         let item_to_move = pending_items.remove(current_position);
         pending_items.insert(new_position, item_to_move);
 
+        for (pending_item, position) in pending_items.iter().zip(positions) {
+            if pending_item.position != position {
+                self.queue_manager
+                    .set_queue_item_position(pending_item.queue_id, position)
+                    .await?;
+            }
+        }
+
         let base_priority = Priority::Normal as i32;
         for (index, pending_item) in pending_items.iter().enumerate() {
             let priority_adjustment = (pending_items.len() - index) as i32;
@@ -802,11 +960,18 @@ impl QueueOperations {
             .cloned()
             .collect();
 
+        let failed_items: Vec<QueueItem> = all_items
+            .iter()
+            .filter(|item| item.state == QueueState::Failed)
+            .cloned()
+            .collect();
+
         Ok(QueueStatus {
             pending_items,
             scheduled_items,
             paused_items,
             cancelled_items,
+            failed_items,
             statistics,
         })
     }
@@ -904,6 +1069,7 @@ pub struct QueueStatus {
     pub scheduled_items: Vec<QueueItem>,
     pub paused_items: Vec<QueueItem>,
     pub cancelled_items: Vec<QueueItem>,
+    pub failed_items: Vec<QueueItem>,
     pub statistics: QueueStatistics,
 }
 
@@ -913,3 +1079,89 @@ pub struct QueueItemStatus {
     pub position_in_queue: Option<usize>,
     pub bandwidth_allocation: Option<u64>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(peer_id: &str) -> TransferRequest {
+        TransferRequest {
+            manifest: TransferManifest::new("local".to_string()),
+            peer_id: peer_id.to_string(),
+            transport_preference: None,
+            bandwidth_limit: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_busy_peer_cannot_take_every_slot() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = Arc::new(QueueManagerImpl::new(dir.path().to_path_buf(), 4, None).with_max_per_peer(2));
+        let scheduler = QueueScheduler::new(manager.clone(), 4);
+
+        for _ in 0..4 {
+            manager.enqueue_transfer(request("greedy"), Priority::Normal).await.unwrap();
+        }
+        manager.enqueue_transfer(request("quiet"), Priority::Normal).await.unwrap();
+
+        let first = scheduler.schedule_next_transfer().await.unwrap().unwrap();
+        let second = scheduler.schedule_next_transfer().await.unwrap().unwrap();
+        assert_ne!(first.transfer_request.peer_id, second.transfer_request.peer_id);
+
+        let third = scheduler.schedule_next_transfer().await.unwrap().unwrap();
+        assert_eq!(third.transfer_request.peer_id, "greedy");
+        assert_eq!(manager.get_active_count_for_peer(&"greedy".to_string()).await, 2);
+        // The greedy peer is at its cap even though global slots remain
+        assert!(scheduler.schedule_next_transfer().await.unwrap().is_none());
+
+        manager.mark_item_completed(third.queue_id).await.unwrap();
+        let next = scheduler.schedule_next_transfer().await.unwrap().unwrap();
+        assert_eq!(next.transfer_request.peer_id, "greedy");
+    }
+
+    #[tokio::test]
+    async fn test_restart_restores_order_and_fails_missing_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("report.pdf");
+        std::fs::write(&source, b"report").unwrap();
+
+        let mut with_file = request("peer");
+        let metadata = std::fs::metadata(&source).unwrap();
+        with_file.manifest.files.push(FileEntry {
+            path: source.clone(),
+            size: metadata.len(),
+            checksum: [0; 32],
+            permissions: FilePermissions::default(),
+            modified_at: FileScanner::extract_modified_time(&metadata),
+            chunk_count: 1,
+            extended: None,
+        });
+
+        let journal_dir = dir.path().join("queue");
+        let manager = QueueManagerImpl::new(journal_dir.clone(), 2, None);
+        manager.initialize().await.unwrap();
+        let first = manager.enqueue_transfer(request("peer"), Priority::Normal).await.unwrap();
+        let second = manager.enqueue_transfer(request("peer"), Priority::Normal).await.unwrap();
+        let urgent = manager.enqueue_transfer(request("peer"), Priority::High).await.unwrap();
+        let paused = manager.enqueue_transfer(request("peer"), Priority::Low).await.unwrap();
+        let stale = manager.enqueue_transfer(with_file, Priority::Normal).await.unwrap();
+        manager.update_queue_item_state(paused, QueueState::Paused).await.unwrap();
+        manager.remove_from_queue(paused).await.unwrap();
+        drop(manager);
+
+        std::fs::remove_file(&source).unwrap();
+
+        let restored = QueueManagerImpl::new(journal_dir, 2, None);
+        restored.initialize().await.unwrap();
+        let order: Vec<QueueId> = restored.get_pending_items().await.unwrap().iter().map(|i| i.queue_id).collect();
+        assert_eq!(order, vec![urgent, first, second]);
+        assert_eq!(restored.get_queue_item(paused).await.unwrap().state, QueueState::Paused);
+
+        let failed = restored.get_queue_item(stale).await.unwrap();
+        assert_eq!(failed.state, QueueState::Failed);
+        assert_eq!(failed.failure, Some(QueueFailure::SourceMissing { path: source }));
+
+        let next = restored.enqueue_transfer(request("peer"), Priority::Normal).await.unwrap();
+        assert!(restored.get_queue_item(next).await.unwrap().position > restored.get_queue_item(stale).await.unwrap().position);
+    }
+}
@@ -525,7 +525,7 @@ mod tests {
         let session_dir = temp_dir.path().join("sessions");
         let resume_dir = temp_dir.path().join("resume");
 
-        let session_manager = SessionManager::new(session_dir);
+        let session_manager = SessionManager::new(session_dir, None);
         session_manager.initialize().await.unwrap();
 
         let resume_manager = ResumeManager::new(resume_dir);
@@ -545,6 +545,7 @@ mod tests {
             permissions: FilePermissions::default(),
             modified_at: current_timestamp(),
             chunk_count: 16, // 1MB / 64KB
+            extended: None,
         });
         manifest
     }
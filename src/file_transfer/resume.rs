@@ -6,12 +6,13 @@ use crate::file_transfer::{
     error::{FileTransferError, Result},
     types::*,
 };
+use crate::journal::atomic_write;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::AsyncReadExt;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -220,22 +221,8 @@ impl ResumeManager {
             FileTransferError::InternalError(format!("Failed to serialize resume token: {}", e))
         })?;
 
-        // Write to file
-        let mut file = fs::File::create(&token_file).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: token_file.clone(),
-                source: e,
-            }
-        })?;
-
-        file.write_all(&token_json).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: token_file.clone(),
-                source: e,
-            }
-        })?;
-
-        file.flush().await.map_err(|e| {
+        // Replace atomically so a crash never leaves a truncated token
+        atomic_write(&token_file, &token_json).await.map_err(|e| {
             FileTransferError::IoError {
                 path: token_file.clone(),
                 source: e,
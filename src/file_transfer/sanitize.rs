@@ -0,0 +1,326 @@
+// Path Sanitization Module
+//
+// Maps manifest paths from the sender onto safe paths under the receiver's
+// download directory. Components that are invalid on the receiving platform
+// (e.g. `:` or `?` on Windows, reserved device names) are rewritten with a
+// configurable strategy, traversal outside the download directory is refused,
+// and name collisions are resolved before anything is written.
+
+use crate::file_transfer::{
+    error::{FileTransferError, Result},
+    types::PlatformFamily,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Characters Windows does not allow in file names
+const WINDOWS_INVALID_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names Windows reserves regardless of extension
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Extended attribute holding the sender's path when a file was renamed
+pub const ORIGINAL_NAME_XATTR: &str = "user.kizuna.original_name";
+
+/// Upper bound on numbered alternatives tried for a colliding name
+const MAX_COLLISION_SUFFIX: u32 = 10_000;
+
+/// How to handle a path component that is invalid on the receiver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "strategy", content = "with")]
+pub enum SanitizeStrategy {
+    /// Encode offending characters as `%XX`, keeping names distinguishable
+    PercentEncode,
+    /// Substitute offending characters with the given character
+    Replace(char),
+    /// Refuse the file
+    Reject,
+}
+
+impl Default for SanitizeStrategy {
+    fn default() -> Self {
+        Self::Replace('_')
+    }
+}
+
+/// What to do when the sanitized path already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionPolicy {
+    /// Append ` (1)`, ` (2)`, ... before the extension
+    #[default]
+    Rename,
+    /// Replace the existing file
+    Overwrite,
+    /// Refuse the file
+    Reject,
+}
+
+/// Where a received file ends up, and the name the sender gave it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SanitizedPath {
+    pub path: PathBuf,
+    pub original: PathBuf,
+    /// Name was rewritten to be valid on this platform
+    pub sanitized: bool,
+    /// Name was changed to avoid an existing file
+    pub renamed: bool,
+}
+
+/// Receiver-side path sanitizer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathSanitizer {
+    pub strategy: SanitizeStrategy,
+    pub collisions: CollisionPolicy,
+    /// Platform whose naming rules apply; usually the receiver's own, but a
+    /// portable target can be chosen for removable or shared drives
+    pub target: PlatformFamily,
+}
+
+impl Default for PathSanitizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathSanitizer {
+    /// Sanitizer for this platform with the default strategy and policy
+    pub fn new() -> Self {
+        Self {
+            strategy: SanitizeStrategy::default(),
+            collisions: CollisionPolicy::default(),
+            target: PlatformFamily::current(),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: SanitizeStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_collision_policy(mut self, collisions: CollisionPolicy) -> Self {
+        self.collisions = collisions;
+        self
+    }
+
+    pub fn with_target(mut self, target: PlatformFamily) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Safe destination for a manifest path under `root`
+    ///
+    /// `original` is the file's path relative to the transfer.
+    pub fn resolve(&self, root: &Path, original: &Path) -> Result<SanitizedPath> {
+        let mut relative = PathBuf::new();
+        let mut sanitized = false;
+
+        for component in original.components() {
+            match component {
+                Component::Normal(name) => {
+                    let name = name.to_string_lossy();
+                    let clean = self.sanitize_component(&name)?;
+                    sanitized |= clean != name;
+                    relative.push(clean);
+                }
+                // Senders list paths relative to the transfer; anything that
+                // anchors or climbs the tree would escape the download root
+                Component::RootDir | Component::Prefix(_) | Component::ParentDir => {
+                    return Err(FileTransferError::UnsafeFileName {
+                        name: original.to_string_lossy().into_owned(),
+                        reason: "path leaves the download directory".to_string(),
+                    });
+                }
+                Component::CurDir => {}
+            }
+        }
+
+        if relative.as_os_str().is_empty() {
+            return Err(FileTransferError::UnsafeFileName {
+                name: original.to_string_lossy().into_owned(),
+                reason: "path has no file name".to_string(),
+            });
+        }
+
+        let (path, renamed) = self.resolve_collision(root.join(relative))?;
+
+        Ok(SanitizedPath {
+            path,
+            original: original.to_path_buf(),
+            sanitized,
+            renamed,
+        })
+    }
+
+    /// Apply the strategy to one path component
+    pub fn sanitize_component(&self, name: &str) -> Result<String> {
+        let mut clean = String::with_capacity(name.len());
+        let mut chars = name.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            // Windows silently strips trailing dots and spaces
+            let trailing = chars.peek().is_none() && self.is_windows() && (c == '.' || c == ' ');
+            if self.is_invalid_char(c) || trailing {
+                self.substitute(name, c, &mut clean)?;
+            } else {
+                clean.push(c);
+            }
+        }
+
+        if self.is_windows() && is_reserved_name(&clean) {
+            // Rewrite the last character of the stem so "CON.txt" stays readable
+            let stem_len = clean.split('.').next().unwrap_or("").len();
+            let (stem, rest) = clean.split_at(stem_len);
+            let mut stem = stem.to_string();
+            let last = stem.pop().unwrap_or('_');
+            let mut rewritten = stem;
+            self.substitute(name, last, &mut rewritten)?;
+            rewritten.push_str(rest);
+            clean = rewritten;
+        }
+
+        if clean == "." || clean == ".." || clean.is_empty() {
+            return Err(FileTransferError::UnsafeFileName {
+                name: name.to_string(),
+                reason: "not a usable file name".to_string(),
+            });
+        }
+
+        Ok(clean)
+    }
+
+    fn is_windows(&self) -> bool {
+        self.target == PlatformFamily::Windows
+    }
+
+    fn is_invalid_char(&self, c: char) -> bool {
+        if c == '/' || c == '\0' {
+            return true;
+        }
+        self.is_windows() && (c.is_control() || WINDOWS_INVALID_CHARS.contains(&c))
+    }
+
+    fn substitute(&self, name: &str, c: char, out: &mut String) -> Result<()> {
+        match self.strategy {
+            SanitizeStrategy::PercentEncode => {
+                let mut buf = [0u8; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    out.push_str(&format!("%{:02X}", byte));
+                }
+            }
+            SanitizeStrategy::Replace(replacement) => out.push(replacement),
+            SanitizeStrategy::Reject => {
+                return Err(FileTransferError::UnsafeFileName {
+                    name: name.to_string(),
+                    reason: format!("{:?} is not allowed on {:?}", c, self.target),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the collision policy to a candidate path
+    fn resolve_collision(&self, candidate: PathBuf) -> Result<(PathBuf, bool)> {
+        if !candidate.exists() {
+            return Ok((candidate, false));
+        }
+
+        match self.collisions {
+            CollisionPolicy::Overwrite => Ok((candidate, false)),
+            CollisionPolicy::Reject => Err(FileTransferError::FileExists { path: candidate }),
+            CollisionPolicy::Rename => {
+                let stem = candidate
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let extension = candidate.extension().map(|e| e.to_string_lossy().into_owned());
+
+                for n in 1..=MAX_COLLISION_SUFFIX {
+                    let name = match &extension {
+                        Some(ext) => format!("{} ({}).{}", stem, n, ext),
+                        None => format!("{} ({})", stem, n),
+                    };
+                    let alternative = candidate.with_file_name(name);
+                    if !alternative.exists() {
+                        return Ok((alternative, true));
+                    }
+                }
+
+                Err(FileTransferError::FileExists { path: candidate })
+            }
+        }
+    }
+}
+
+/// Whether a name is a Windows device name, with or without extension
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or("").trim_end();
+    WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn windows(strategy: SanitizeStrategy) -> PathSanitizer {
+        PathSanitizer::new()
+            .with_strategy(strategy)
+            .with_target(PlatformFamily::Windows)
+    }
+
+    #[test]
+    fn test_windows_strategies() {
+        let replace = windows(SanitizeStrategy::Replace('_'));
+        assert_eq!(replace.sanitize_component("notes: v2?.txt").unwrap(), "notes_ v2_.txt");
+        assert_eq!(replace.sanitize_component("trailing. ").unwrap(), "trailing._");
+        assert_eq!(replace.sanitize_component("con.txt").unwrap(), "co_.txt");
+
+        let encode = windows(SanitizeStrategy::PercentEncode);
+        assert_eq!(encode.sanitize_component("a:b").unwrap(), "a%3Ab");
+        assert_eq!(encode.sanitize_component("AUX").unwrap(), "AU%58");
+
+        let reject = windows(SanitizeStrategy::Reject);
+        assert!(matches!(
+            reject.sanitize_component("a|b"),
+            Err(FileTransferError::UnsafeFileName { .. })
+        ));
+        assert_eq!(reject.sanitize_component("fine.txt").unwrap(), "fine.txt");
+
+        // The same names are valid on Linux
+        let linux = PathSanitizer::new().with_target(PlatformFamily::Linux);
+        assert_eq!(linux.sanitize_component("notes: v2?.txt").unwrap(), "notes: v2?.txt");
+    }
+
+    #[test]
+    fn test_resolve_refuses_traversal_and_renames_collisions() {
+        let temp_dir = TempDir::new().unwrap();
+        let sanitizer = windows(SanitizeStrategy::Replace('_'));
+
+        assert!(sanitizer.resolve(temp_dir.path(), Path::new("../escape.txt")).is_err());
+        assert!(sanitizer.resolve(temp_dir.path(), Path::new("/etc/passwd")).is_err());
+
+        let first = sanitizer.resolve(temp_dir.path(), Path::new("docs/a:b.txt")).unwrap();
+        assert_eq!(first.path, temp_dir.path().join("docs").join("a_b.txt"));
+        assert_eq!(first.original, PathBuf::from("docs/a:b.txt"));
+        assert!(first.sanitized && !first.renamed);
+
+        std::fs::create_dir_all(first.path.parent().unwrap()).unwrap();
+        std::fs::write(&first.path, b"taken").unwrap();
+
+        let second = sanitizer.resolve(temp_dir.path(), Path::new("docs/a?b.txt")).unwrap();
+        assert_eq!(second.path, temp_dir.path().join("docs").join("a_b (1).txt"));
+        assert!(second.renamed);
+
+        let strict = sanitizer.clone().with_collision_policy(CollisionPolicy::Reject);
+        assert!(matches!(
+            strict.resolve(temp_dir.path(), Path::new("docs/a_b.txt")),
+            Err(FileTransferError::FileExists { .. })
+        ));
+    }
+}
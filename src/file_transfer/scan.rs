@@ -0,0 +1,671 @@
+// Post-Receive Scan Module
+//
+// Received files are written into a quarantine directory and only moved to
+// their destination once every post-receive hook has inspected them. Hooks
+// can hand the file to a clamd daemon or to any command-line scanner, and
+// each verdict is appended to the transfer history so detections can be
+// audited after the transfer is gone.
+
+use crate::file_transfer::{
+    error::{FileTransferError, Result},
+    types::{current_timestamp, Timestamp, TransferId},
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// File name of the scan log inside the transfer history directory
+pub const SCAN_HISTORY_FILE: &str = "scan_history.jsonl";
+
+/// Placeholder replaced with the quarantined file path in scanner arguments
+pub const PATH_PLACEHOLDER: &str = "{path}";
+
+/// Size of each INSTREAM chunk sent to clamd
+const CLAMD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Default time a single scanner may take before the scan counts as failed
+const DEFAULT_SCAN_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Result of one hook inspecting one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "verdict")]
+pub enum ScanVerdict {
+    Clean,
+    Infected { signature: String },
+    /// The scanner could not give an answer
+    Failed { reason: String },
+}
+
+/// Check run against a received file before it leaves quarantine
+#[async_trait]
+pub trait PostReceiveHook: Send + Sync {
+    /// Name recorded in the transfer history
+    fn name(&self) -> &str;
+
+    /// Inspect the quarantined file
+    async fn inspect(&self, path: &Path) -> ScanVerdict;
+}
+
+/// Where a clamd daemon listens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClamdAddress {
+    /// Local socket, e.g. `/run/clamav/clamd.ctl`
+    Unix(PathBuf),
+    /// `host:port`, usually `127.0.0.1:3310`
+    Tcp(String),
+}
+
+/// Scans files by streaming them to clamd with the INSTREAM command
+///
+/// Streaming means clamd does not need read access to the quarantine
+/// directory, which typically belongs to a different user.
+#[derive(Debug, Clone)]
+pub struct ClamdScanner {
+    address: ClamdAddress,
+    timeout: Duration,
+}
+
+impl ClamdScanner {
+    pub fn new(address: ClamdAddress) -> Self {
+        Self {
+            address,
+            timeout: DEFAULT_SCAN_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Connect to clamd and return its raw reply for the file
+    async fn scan(&self, path: &Path) -> std::io::Result<String> {
+        match &self.address {
+            #[cfg(unix)]
+            ClamdAddress::Unix(socket) => {
+                let stream = tokio::net::UnixStream::connect(socket).await?;
+                instream(stream, path).await
+            }
+            #[cfg(not(unix))]
+            ClamdAddress::Unix(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "unix sockets are not available on this platform",
+            )),
+            ClamdAddress::Tcp(address) => {
+                let stream = tokio::net::TcpStream::connect(address).await?;
+                instream(stream, path).await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl PostReceiveHook for ClamdScanner {
+    fn name(&self) -> &str {
+        "clamd"
+    }
+
+    async fn inspect(&self, path: &Path) -> ScanVerdict {
+        match tokio::time::timeout(self.timeout, self.scan(path)).await {
+            Ok(Ok(reply)) => parse_clamd_reply(&reply),
+            Ok(Err(e)) => ScanVerdict::Failed {
+                reason: format!("clamd: {}", e),
+            },
+            Err(_) => ScanVerdict::Failed {
+                reason: format!("clamd did not answer within {:?}", self.timeout),
+            },
+        }
+    }
+}
+
+/// Send a file with `zINSTREAM` and read the null-terminated reply
+async fn instream<S>(mut stream: S, path: &Path) -> std::io::Result<String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut file = tokio::fs::File::open(path).await?;
+    stream.write_all(b"zINSTREAM\0").await?;
+
+    let mut buffer = vec![0u8; CLAMD_CHUNK_SIZE];
+    loop {
+        let bytes_read = file.read(&mut buffer).await?;
+        if bytes_read == 0 {
+            break;
+        }
+        stream.write_all(&(bytes_read as u32).to_be_bytes()).await?;
+        stream.write_all(&buffer[..bytes_read]).await?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await?;
+    stream.flush().await?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
+
+/// Interpret replies such as `stream: OK` or `stream: Eicar-Signature FOUND`
+fn parse_clamd_reply(reply: &str) -> ScanVerdict {
+    let reply = reply.trim_end_matches('\0').trim();
+    let body = reply.strip_prefix("stream:").map(str::trim).unwrap_or(reply);
+
+    if body == "OK" {
+        ScanVerdict::Clean
+    } else if let Some(signature) = body.strip_suffix(" FOUND") {
+        ScanVerdict::Infected {
+            signature: signature.trim().to_string(),
+        }
+    } else {
+        ScanVerdict::Failed {
+            reason: format!("clamd: {}", body),
+        }
+    }
+}
+
+/// Scans files by running an external program
+///
+/// Exit status 0 means clean and any of `infected_exit_codes` means a
+/// detection; every other status is treated as a scanner failure. The
+/// defaults match `clamscan`.
+#[derive(Debug, Clone)]
+pub struct CommandScanner {
+    pub program: PathBuf,
+    /// Arguments, with [`PATH_PLACEHOLDER`] replaced by the file path
+    pub args: Vec<String>,
+    pub infected_exit_codes: Vec<i32>,
+    pub timeout: Duration,
+}
+
+impl CommandScanner {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            args: vec![PATH_PLACEHOLDER.to_string()],
+            infected_exit_codes: vec![1],
+            timeout: DEFAULT_SCAN_TIMEOUT,
+        }
+    }
+
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_infected_exit_codes(mut self, codes: Vec<i32>) -> Self {
+        self.infected_exit_codes = codes;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[async_trait]
+impl PostReceiveHook for CommandScanner {
+    fn name(&self) -> &str {
+        self.program
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("command")
+    }
+
+    async fn inspect(&self, path: &Path) -> ScanVerdict {
+        let file = path.to_string_lossy();
+        let mut command = tokio::process::Command::new(&self.program);
+        command
+            .args(self.args.iter().map(|arg| arg.replace(PATH_PLACEHOLDER, &file)))
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true);
+
+        let output = match tokio::time::timeout(self.timeout, command.output()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                return ScanVerdict::Failed {
+                    reason: format!("failed to run {}: {}", self.program.display(), e),
+                };
+            }
+            Err(_) => {
+                return ScanVerdict::Failed {
+                    reason: format!("{} did not finish within {:?}", self.name(), self.timeout),
+                };
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let last_line = stdout.lines().rev().find(|line| !line.trim().is_empty());
+
+        match output.status.code() {
+            Some(0) => ScanVerdict::Clean,
+            Some(code) if self.infected_exit_codes.contains(&code) => ScanVerdict::Infected {
+                signature: last_line
+                    .map(|line| line.trim().to_string())
+                    .unwrap_or_else(|| format!("detected by {}", self.name())),
+            },
+            code => ScanVerdict::Failed {
+                reason: format!(
+                    "{} exited with {}",
+                    self.name(),
+                    code.map_or_else(|| "a signal".to_string(), |c| format!("status {}", c))
+                ),
+            },
+        }
+    }
+}
+
+/// What to do with a file a hook flagged as infected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectionAction {
+    /// Delete the file and fail the transfer
+    #[default]
+    Reject,
+    /// Leave the file in quarantine for manual review
+    Hold,
+}
+
+/// What to do when a scanner could not give a verdict
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanErrorAction {
+    /// Release the file as if it were clean
+    Release,
+    /// Leave the file in quarantine for manual review
+    #[default]
+    Hold,
+    /// Delete the file and fail the transfer
+    Reject,
+}
+
+/// How scan verdicts decide a file's fate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanPolicy {
+    pub on_detection: DetectionAction,
+    pub on_error: ScanErrorAction,
+}
+
+/// Where a quarantined file ended up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseOutcome {
+    /// Moved to its destination
+    Released,
+    /// Still in the quarantine directory
+    Held,
+    /// Deleted
+    Rejected,
+}
+
+/// One hook's verdict on one file, as kept in the transfer history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanRecord {
+    pub transfer_id: TransferId,
+    /// Destination the file was received for
+    pub file: PathBuf,
+    pub hook: String,
+    #[serde(flatten)]
+    pub verdict: ScanVerdict,
+    pub outcome: ReleaseOutcome,
+    pub scanned_at: Timestamp,
+}
+
+/// Outcome of running the hooks on a quarantined file
+#[derive(Debug, Clone)]
+pub struct ScanReport {
+    pub outcome: ReleaseOutcome,
+    /// Current location of the file; `None` once rejected
+    pub path: Option<PathBuf>,
+    pub records: Vec<ScanRecord>,
+}
+
+/// Append-only log of scan verdicts, one JSON record per line
+#[derive(Debug, Clone)]
+pub struct ScanHistory {
+    path: PathBuf,
+}
+
+impl ScanHistory {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Log in the transfer history directory, which the retention sweep
+    /// already ages out with the rest of the transfer records
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("kizuna").join("sessions").join(SCAN_HISTORY_FILE))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append records to the log
+    pub async fn append(&self, records: &[ScanRecord]) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = String::new();
+        for record in records {
+            let line = serde_json::to_string(record)
+                .map_err(|e| FileTransferError::InternalError(format!("scan record: {}", e)))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| io_error(parent, e))?;
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| io_error(&self.path, e))?;
+        file.write_all(lines.as_bytes())
+            .await
+            .map_err(|e| io_error(&self.path, e))?;
+        file.flush().await.map_err(|e| io_error(&self.path, e))
+    }
+
+    /// All records, skipping lines that fail to parse
+    pub async fn load(&self) -> Result<Vec<ScanRecord>> {
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(&self.path, e)),
+        };
+
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Records for a single transfer
+    pub async fn for_transfer(&self, transfer_id: TransferId) -> Result<Vec<ScanRecord>> {
+        Ok(self
+            .load()
+            .await?
+            .into_iter()
+            .filter(|record| record.transfer_id == transfer_id)
+            .collect())
+    }
+}
+
+/// Holds received files until the post-receive hooks have passed them
+#[derive(Clone)]
+pub struct Quarantine {
+    dir: PathBuf,
+    hooks: Vec<Arc<dyn PostReceiveHook>>,
+    policy: ScanPolicy,
+    history: Option<ScanHistory>,
+}
+
+impl Quarantine {
+    /// Quarantine in `dir`; keep it on the same filesystem as the download
+    /// directory so releasing a file is a rename rather than a copy
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            hooks: Vec::new(),
+            policy: ScanPolicy::default(),
+            history: None,
+        }
+    }
+
+    pub fn with_hook(mut self, hook: Arc<dyn PostReceiveHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: ScanPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_history(mut self, history: ScanHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn policy(&self) -> ScanPolicy {
+        self.policy
+    }
+
+    /// Where a file should be received before it is scanned
+    pub fn quarantine_path(&self, transfer_id: TransferId, file_name: &str) -> PathBuf {
+        self.dir.join(transfer_id.to_string()).join(file_name)
+    }
+
+    /// Run every hook on a quarantined file and release, hold or delete it
+    ///
+    /// Returns `MalwareDetected` or `ScanFailed` when the policy rejects the
+    /// file; verdicts are recorded in the history either way.
+    pub async fn release(
+        &self,
+        transfer_id: TransferId,
+        quarantined: &Path,
+        destination: &Path,
+    ) -> Result<ScanReport> {
+        let mut verdicts = Vec::with_capacity(self.hooks.len());
+        for hook in &self.hooks {
+            verdicts.push((hook.name().to_string(), hook.inspect(quarantined).await));
+        }
+
+        let detection = verdicts.iter().find_map(|(_, verdict)| match verdict {
+            ScanVerdict::Infected { signature } => Some(signature.clone()),
+            _ => None,
+        });
+        let failure = verdicts.iter().find_map(|(hook, verdict)| match verdict {
+            ScanVerdict::Failed { reason } => Some(format!("{}: {}", hook, reason)),
+            _ => None,
+        });
+
+        let outcome = match (&detection, &failure) {
+            (Some(_), _) => match self.policy.on_detection {
+                DetectionAction::Reject => ReleaseOutcome::Rejected,
+                DetectionAction::Hold => ReleaseOutcome::Held,
+            },
+            (None, Some(_)) => match self.policy.on_error {
+                ScanErrorAction::Release => ReleaseOutcome::Released,
+                ScanErrorAction::Hold => ReleaseOutcome::Held,
+                ScanErrorAction::Reject => ReleaseOutcome::Rejected,
+            },
+            (None, None) => ReleaseOutcome::Released,
+        };
+
+        let path = match outcome {
+            ReleaseOutcome::Released => {
+                move_file(quarantined, destination).await?;
+                Some(destination.to_path_buf())
+            }
+            ReleaseOutcome::Held => Some(quarantined.to_path_buf()),
+            ReleaseOutcome::Rejected => {
+                tokio::fs::remove_file(quarantined)
+                    .await
+                    .map_err(|e| io_error(quarantined, e))?;
+                None
+            }
+        };
+
+        let scanned_at = current_timestamp();
+        let records: Vec<ScanRecord> = verdicts
+            .into_iter()
+            .map(|(hook, verdict)| ScanRecord {
+                transfer_id,
+                file: destination.to_path_buf(),
+                hook,
+                verdict,
+                outcome,
+                scanned_at,
+            })
+            .collect();
+
+        if let Some(history) = &self.history {
+            history.append(&records).await?;
+        }
+
+        if outcome == ReleaseOutcome::Rejected {
+            return Err(match (detection, failure) {
+                (Some(signature), _) => FileTransferError::MalwareDetected {
+                    path: destination.to_path_buf(),
+                    signature,
+                },
+                (None, reason) => FileTransferError::ScanFailed {
+                    path: destination.to_path_buf(),
+                    reason: reason.unwrap_or_default(),
+                },
+            });
+        }
+
+        Ok(ScanReport {
+            outcome,
+            path,
+            records,
+        })
+    }
+}
+
+/// Move a file, copying when the quarantine is on another filesystem
+async fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| io_error(parent, e))?;
+    }
+
+    if tokio::fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+
+    tokio::fs::copy(from, to).await.map_err(|e| io_error(to, e))?;
+    tokio::fs::remove_file(from).await.map_err(|e| io_error(from, e))
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> FileTransferError {
+    FileTransferError::IoError {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_parse_clamd_reply() {
+        assert_eq!(parse_clamd_reply("stream: OK\0"), ScanVerdict::Clean);
+        assert_eq!(
+            parse_clamd_reply("stream: Eicar-Test-Signature FOUND\0"),
+            ScanVerdict::Infected {
+                signature: "Eicar-Test-Signature".to_string()
+            }
+        );
+        assert!(matches!(
+            parse_clamd_reply("INSTREAM size limit exceeded. ERROR\0"),
+            ScanVerdict::Failed { .. }
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_clamd_instream() {
+        let temp_dir = TempDir::new().unwrap();
+        let socket = temp_dir.path().join("clamd.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut command = [0u8; 10];
+            stream.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+
+            let mut received = Vec::new();
+            loop {
+                let mut len = [0u8; 4];
+                stream.read_exact(&mut len).await.unwrap();
+                let len = u32::from_be_bytes(len) as usize;
+                if len == 0 {
+                    break;
+                }
+                let mut chunk = vec![0u8; len];
+                stream.read_exact(&mut chunk).await.unwrap();
+                received.extend_from_slice(&chunk);
+            }
+            stream.write_all(b"stream: Test.Sig FOUND\0").await.unwrap();
+            received
+        });
+
+        let file = temp_dir.path().join("payload.bin");
+        let data = vec![7u8; CLAMD_CHUNK_SIZE + 100];
+        std::fs::write(&file, &data).unwrap();
+
+        let scanner = ClamdScanner::new(ClamdAddress::Unix(socket));
+        assert_eq!(
+            scanner.inspect(&file).await,
+            ScanVerdict::Infected {
+                signature: "Test.Sig".to_string()
+            }
+        );
+        assert_eq!(server.await.unwrap(), data);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_quarantine_release_and_reject() {
+        let temp_dir = TempDir::new().unwrap();
+        let history = ScanHistory::new(temp_dir.path().join(SCAN_HISTORY_FILE));
+        let scanner = CommandScanner::new("sh").with_args([
+            "-c",
+            "if grep -q EICAR \"$1\"; then echo \"$1: Eicar FOUND\"; exit 1; fi",
+            "scan",
+            PATH_PLACEHOLDER,
+        ]);
+        let quarantine = Quarantine::new(temp_dir.path().join("quarantine"))
+            .with_hook(Arc::new(scanner))
+            .with_history(history.clone());
+        let transfer_id = Uuid::new_v4();
+
+        let clean = quarantine.quarantine_path(transfer_id, "clean.txt");
+        std::fs::create_dir_all(clean.parent().unwrap()).unwrap();
+        std::fs::write(&clean, b"hello").unwrap();
+        let destination = temp_dir.path().join("downloads").join("clean.txt");
+
+        let report = quarantine.release(transfer_id, &clean, &destination).await.unwrap();
+        assert_eq!(report.outcome, ReleaseOutcome::Released);
+        assert!(destination.exists() && !clean.exists());
+
+        let infected = quarantine.quarantine_path(transfer_id, "bad.txt");
+        std::fs::write(&infected, b"X5O EICAR test").unwrap();
+        let bad_destination = temp_dir.path().join("downloads").join("bad.txt");
+
+        let result = quarantine.release(transfer_id, &infected, &bad_destination).await;
+        assert!(matches!(result, Err(FileTransferError::MalwareDetected { .. })));
+        assert!(!infected.exists() && !bad_destination.exists());
+
+        let records = history.for_transfer(transfer_id).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].verdict, ScanVerdict::Clean);
+        assert_eq!(records[1].outcome, ReleaseOutcome::Rejected);
+        assert!(matches!(&records[1].verdict, ScanVerdict::Infected { signature } if signature.ends_with("Eicar FOUND")));
+    }
+}
@@ -160,12 +160,9 @@ impl FileTransferSecurity {
     }
 
     /// Verify chunk integrity after decryption
-    pub async fn verify_chunk_integrity(&self, chunk: &Chunk) -> Result<()> {
+    pub async fn verify_chunk_integrity(&self, chunk: &Chunk, algorithm: HashAlgorithm) -> Result<()> {
         // Calculate checksum of chunk data
-        use sha2::{Sha256, Digest};
-        let mut hasher = Sha256::new();
-        hasher.update(&chunk.data);
-        let calculated_checksum: [u8; 32] = hasher.finalize().into();
+        let calculated_checksum = algorithm.digest(&chunk.data);
 
         // Compare with stored checksum
         if calculated_checksum != chunk.checksum {
@@ -218,7 +215,9 @@ impl SecureTransferSession {
     /// Receive and decrypt chunk
     pub async fn receive_encrypted_chunk(&self, encrypted_data: &[u8]) -> Result<Chunk> {
         let chunk = self.security.decrypt_chunk(&self.security_session_id, encrypted_data).await?;
-        self.security.verify_chunk_integrity(&chunk).await?;
+        self.security
+            .verify_chunk_integrity(&chunk, self.session.manifest.hash_algorithm)
+            .await?;
         Ok(chunk)
     }
 
@@ -298,6 +297,10 @@ mod tests {
         async fn add_trusted_peer(&self, _peer_id: SecurityPeerId, _nickname: String) -> SecurityResult<()> {
             Ok(())
         }
+
+        fn storage(&self) -> Option<crate::security::EncryptedStorage> {
+            None
+        }
     }
 
     fn create_test_security() -> Arc<dyn Security> {
@@ -463,7 +466,7 @@ mod tests {
             compressed: false,
         };
 
-        let result = ft_security.verify_chunk_integrity(&chunk).await;
+        let result = ft_security.verify_chunk_integrity(&chunk, HashAlgorithm::Sha256).await;
         assert!(result.is_ok());
     }
 
@@ -482,7 +485,7 @@ mod tests {
             compressed: false,
         };
 
-        let result = ft_security.verify_chunk_integrity(&chunk).await;
+        let result = ft_security.verify_chunk_integrity(&chunk, HashAlgorithm::Sha256).await;
         assert!(result.is_err());
     }
 
@@ -4,17 +4,36 @@
 
 use crate::file_transfer::{
     error::{FileTransferError, Result},
+    negotiation::Negotiation,
     types::*,
 };
+use crate::journal::Journal;
+use crate::security::storage::EncryptedStorage;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+/// Journal name for transfer sessions within the persistence directory
+const SESSION_JOURNAL: &str = "transfer_sessions";
+
+/// Journal entry kind for a transfer session
+const JOURNAL_KIND_SESSION: &str = "transfer_session";
+
+/// What startup recovery did with sessions interrupted by a crash
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionRecovery {
+    /// Transfers that were running and are now paused, ready to resume
+    pub paused: Vec<SessionId>,
+    /// Transfers that could not be resumed and were marked failed
+    pub failed: Vec<SessionId>,
+    /// Journal records dropped because they were torn by the crash
+    pub discarded_records: usize,
+}
+
 /// Session manager handles transfer session lifecycle and state management
 #[derive(Clone)]
 pub struct SessionManager {
@@ -22,18 +41,26 @@ pub struct SessionManager {
     sessions: Arc<RwLock<HashMap<SessionId, TransferSession>>>,
     /// Session persistence directory
     persistence_dir: PathBuf,
+    /// Write-ahead journal of session state
+    journal: Arc<Journal>,
+    /// Outcome of the last startup recovery
+    recovery: Arc<RwLock<SessionRecovery>>,
 }
 
 impl SessionManager {
     /// Create a new session manager with persistence directory
-    pub fn new(persistence_dir: PathBuf) -> Self {
+    ///
+    /// The session journal is encrypted at rest with `storage`.
+    pub fn new(persistence_dir: PathBuf, storage: Option<EncryptedStorage>) -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            journal: Arc::new(Journal::new(persistence_dir.clone(), SESSION_JOURNAL, storage)),
             persistence_dir,
+            recovery: Arc::new(RwLock::new(SessionRecovery::default())),
         }
     }
 
-    /// Initialize session manager and load persisted sessions
+    /// Initialize session manager and recover persisted sessions
     pub async fn initialize(&self) -> Result<()> {
         // Create persistence directory if it doesn't exist
         fs::create_dir_all(&self.persistence_dir)
@@ -43,12 +70,17 @@ impl SessionManager {
                 source: e,
             })?;
 
-        // Load persisted sessions
-        self.load_persisted_sessions().await?;
+        // Replay the journal, settling anything interrupted by a crash
+        self.recover_sessions().await?;
 
         Ok(())
     }
 
+    /// Sessions paused or failed by the last startup recovery
+    pub async fn last_recovery(&self) -> SessionRecovery {
+        self.recovery.read().await.clone()
+    }
+
     /// Create a new transfer session
     pub async fn create_session(
         &self,
@@ -131,6 +163,68 @@ impl SessionManager {
         }
     }
 
+    /// Set the bandwidth limit for a session
+    pub async fn set_bandwidth_limit(
+        &self,
+        session_id: SessionId,
+        limit: Option<u64>,
+    ) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+
+        if let Some(session) = sessions.get_mut(&session_id) {
+            session.bandwidth_limit = limit;
+
+            // Persist updated session
+            self.persist_session(session).await?;
+
+            Ok(())
+        } else {
+            Err(FileTransferError::SessionNotFound {
+                session_id: session_id.to_string(),
+            })
+        }
+    }
+
+    /// Record how the transfer's terms were negotiated
+    pub async fn record_negotiation(
+        &self,
+        session_id: SessionId,
+        negotiation: Negotiation,
+    ) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+
+        if let Some(session) = sessions.get_mut(&session_id) {
+            session.negotiation = Some(negotiation);
+
+            // Persist updated session
+            self.persist_session(session).await?;
+
+            Ok(())
+        } else {
+            Err(FileTransferError::SessionNotFound {
+                session_id: session_id.to_string(),
+            })
+        }
+    }
+
+    /// Set whether a session compresses chunks
+    pub async fn set_compression(&self, session_id: SessionId, compression: bool) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+
+        if let Some(session) = sessions.get_mut(&session_id) {
+            session.compression = compression;
+
+            // Persist updated session
+            self.persist_session(session).await?;
+
+            Ok(())
+        } else {
+            Err(FileTransferError::SessionNotFound {
+                session_id: session_id.to_string(),
+            })
+        }
+    }
+
     /// Set resume token for a session
     pub async fn set_resume_token(
         &self,
@@ -169,6 +263,35 @@ impl SessionManager {
             .collect())
     }
 
+    /// Pause every running transfer and write all live sessions to disk
+    ///
+    /// Used on shutdown so interrupted transfers are found paused, with their last
+    /// recorded progress, when the session manager is next initialized. Returns the
+    /// number of transfers paused.
+    pub async fn suspend_all(&self) -> Result<usize> {
+        let mut sessions = self.sessions.write().await;
+        let mut paused = 0;
+
+        for session in sessions.values_mut() {
+            match session.state {
+                TransferState::Transferring => {
+                    session.state = TransferState::Paused;
+                    paused += 1;
+                }
+                TransferState::Pending | TransferState::Negotiating | TransferState::Paused => {}
+                _ => continue,
+            }
+            self.persist_session(session).await?;
+        }
+
+        Ok(paused)
+    }
+
+    /// Get every known session, whatever its state
+    pub async fn get_all_sessions(&self) -> Vec<TransferSession> {
+        self.sessions.read().await.values().cloned().collect()
+    }
+
     /// Get all sessions in a specific state
     pub async fn get_sessions_by_state(&self, state: TransferState) -> Result<Vec<TransferSession>> {
         let sessions = self.sessions.read().await;
@@ -273,52 +396,67 @@ impl SessionManager {
         }
     }
 
-    /// Persist session to disk
+    /// Record session state in the journal
     async fn persist_session(&self, session: &TransferSession) -> Result<()> {
-        let session_file = self.get_session_file_path(session.session_id);
-        
-        // Serialize session to JSON
-        let session_json = serde_json::to_vec_pretty(session).map_err(|e| {
-            FileTransferError::InternalError(format!("Failed to serialize session: {}", e))
-        })?;
-        
-        // Write to file
-        let mut file = fs::File::create(&session_file).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: session_file.clone(),
-                source: e,
-            }
-        })?;
-        
-        file.write_all(&session_json).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: session_file.clone(),
-                source: e,
+        self.journal
+            .put(&session.session_id.to_string(), JOURNAL_KIND_SESSION, session)
+            .await
+            .map_err(|e| self.journal_error(e))
+    }
+
+    /// Replay the journal and settle sessions interrupted by a crash
+    async fn recover_sessions(&self) -> Result<()> {
+        let recovered = self.journal.recover().await.map_err(|e| self.journal_error(e))?;
+
+        let mut loaded = Vec::new();
+        for entry in recovered.entries {
+            match entry.decode::<TransferSession>() {
+                Ok(session) => loaded.push(session),
+                Err(e) => eprintln!("Failed to decode journaled session {}: {}", entry.key, e),
             }
-        })?;
-        
-        file.flush().await.map_err(|e| {
-            FileTransferError::IoError {
-                path: session_file.clone(),
-                source: e,
+        }
+        loaded.extend(self.import_legacy_sessions().await?);
+
+        let mut report = SessionRecovery {
+            discarded_records: recovered.discarded_records,
+            ..Default::default()
+        };
+        let mut sessions = self.sessions.write().await;
+        for mut session in loaded {
+            // Nothing survives a restart mid-transfer except what was journaled, so
+            // a running transfer is paused for resume from its last recorded
+            // progress, and a negotiation has to be redone from scratch
+            let recovered_state = match session.state {
+                TransferState::Transferring => Some(TransferState::Paused),
+                TransferState::Negotiating => Some(TransferState::Failed),
+                _ => None,
+            };
+            if let Some(state) = recovered_state {
+                session.state = state;
+                self.persist_session(&session).await?;
+                match state {
+                    TransferState::Paused => report.paused.push(session.session_id),
+                    _ => report.failed.push(session.session_id),
+                }
             }
-        })?;
-        
+            sessions.insert(session.session_id, session);
+        }
+        drop(sessions);
+
+        *self.recovery.write().await = report;
         Ok(())
     }
 
-    /// Load persisted sessions from disk
-    async fn load_persisted_sessions(&self) -> Result<()> {
-        // Read all session files from persistence directory
+    /// Move sessions written as one JSON file each by earlier versions into the journal
+    async fn import_legacy_sessions(&self) -> Result<Vec<TransferSession>> {
         let mut entries = fs::read_dir(&self.persistence_dir)
             .await
             .map_err(|e| FileTransferError::IoError {
                 path: self.persistence_dir.clone(),
                 source: e,
             })?;
-        
-        let mut sessions = self.sessions.write().await;
-        
+
+        let mut imported = Vec::new();
         while let Some(entry) = entries.next_entry().await.map_err(|e| {
             FileTransferError::IoError {
                 path: self.persistence_dir.clone(),
@@ -326,70 +464,49 @@ impl SessionManager {
             }
         })? {
             let path = entry.path();
-            
-            // Only process .json files
-            if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            let is_session_file = path.extension().and_then(|s| s.to_str()) == Some("json")
+                && path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|stem| Uuid::parse_str(stem).is_ok());
+            if !is_session_file {
                 continue;
             }
-            
-            // Load and deserialize session
-            match self.load_session_from_file(&path).await {
-                Ok(session) => {
-                    sessions.insert(session.session_id, session);
-                }
+
+            let contents = match fs::read(&path).await {
+                Ok(contents) => contents,
                 Err(e) => {
-                    // Log error but continue loading other sessions
-                    eprintln!("Failed to load session from {:?}: {}", path, e);
+                    eprintln!("Failed to read legacy session {:?}: {}", path, e);
+                    continue;
                 }
+            };
+            match serde_json::from_slice::<TransferSession>(&contents) {
+                Ok(session) => {
+                    self.persist_session(&session).await?;
+                    fs::remove_file(&path).await.ok();
+                    imported.push(session);
+                }
+                Err(e) => eprintln!("Failed to load legacy session {:?}: {}", path, e),
             }
         }
-        
-        Ok(())
-    }
 
-    /// Load a single session from file
-    async fn load_session_from_file(&self, path: &PathBuf) -> Result<TransferSession> {
-        let mut file = fs::File::open(path).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: path.clone(),
-                source: e,
-            }
-        })?;
-        
-        let mut contents = Vec::new();
-        file.read_to_end(&mut contents).await.map_err(|e| {
-            FileTransferError::IoError {
-                path: path.clone(),
-                source: e,
-            }
-        })?;
-        
-        let session: TransferSession = serde_json::from_slice(&contents).map_err(|e| {
-            FileTransferError::InternalError(format!("Failed to deserialize session: {}", e))
-        })?;
-        
-        Ok(session)
+        Ok(imported)
     }
 
-    /// Delete persisted session file
+    /// Remove a session from the journal
     async fn delete_persisted_session(&self, session_id: SessionId) -> Result<()> {
-        let session_file = self.get_session_file_path(session_id);
-        
-        if session_file.exists() {
-            fs::remove_file(&session_file).await.map_err(|e| {
-                FileTransferError::IoError {
-                    path: session_file,
-                    source: e,
-                }
-            })?;
-        }
-        
+        self.journal
+            .remove(&session_id.to_string())
+            .await
+            .map_err(|e| self.journal_error(e))?;
         Ok(())
     }
 
-    /// Get file path for persisted session
-    fn get_session_file_path(&self, session_id: SessionId) -> PathBuf {
-        self.persistence_dir.join(format!("{}.json", session_id))
+    fn journal_error(&self, source: std::io::Error) -> FileTransferError {
+        FileTransferError::IoError {
+            path: self.journal.log_path(),
+            source,
+        }
     }
 }
 
@@ -400,7 +517,7 @@ mod tests {
 
     async fn create_test_session_manager() -> (SessionManager, TempDir) {
         let temp_dir = TempDir::new().unwrap();
-        let manager = SessionManager::new(temp_dir.path().to_path_buf());
+        let manager = SessionManager::new(temp_dir.path().to_path_buf(), None);
         manager.initialize().await.unwrap();
         (manager, temp_dir)
     }
@@ -481,7 +598,7 @@ mod tests {
         let persistence_dir = temp_dir.path().to_path_buf();
         
         let session_id = {
-            let manager = SessionManager::new(persistence_dir.clone());
+            let manager = SessionManager::new(persistence_dir.clone(), None);
             manager.initialize().await.unwrap();
             
             let manifest = create_test_manifest();
@@ -494,13 +611,84 @@ mod tests {
         };
         
         // Create new manager and verify session was loaded
-        let manager = SessionManager::new(persistence_dir);
+        let manager = SessionManager::new(persistence_dir, None);
         manager.initialize().await.unwrap();
         
         let loaded_session = manager.get_session(session_id).await.unwrap();
         assert_eq!(loaded_session.session_id, session_id);
     }
 
+    #[tokio::test]
+    async fn test_suspend_all_pauses_running_transfers() {
+        let temp_dir = TempDir::new().unwrap();
+        let persistence_dir = temp_dir.path().to_path_buf();
+
+        let session_id = {
+            let manager = SessionManager::new(persistence_dir.clone(), None);
+            manager.initialize().await.unwrap();
+
+            let session = manager
+                .create_session(create_test_manifest(), "peer1".to_string(), TransportProtocol::Tcp)
+                .await
+                .unwrap();
+            manager
+                .update_session_state(session.session_id, TransferState::Negotiating)
+                .await
+                .unwrap();
+            manager
+                .update_session_state(session.session_id, TransferState::Transferring)
+                .await
+                .unwrap();
+
+            assert_eq!(manager.suspend_all().await.unwrap(), 1);
+            session.session_id
+        };
+
+        let manager = SessionManager::new(persistence_dir, None);
+        manager.initialize().await.unwrap();
+        let loaded = manager.get_session(session_id).await.unwrap();
+        assert_eq!(loaded.state, TransferState::Paused);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_after_crash() {
+        let temp_dir = TempDir::new().unwrap();
+        let persistence_dir = temp_dir.path().to_path_buf();
+
+        let (running, negotiating) = {
+            let manager = SessionManager::new(persistence_dir.clone(), None);
+            manager.initialize().await.unwrap();
+
+            let running = manager
+                .create_session(create_test_manifest(), "peer1".to_string(), TransportProtocol::Tcp)
+                .await
+                .unwrap();
+            let negotiating = manager
+                .create_session(create_test_manifest(), "peer2".to_string(), TransportProtocol::Tcp)
+                .await
+                .unwrap();
+            for state in [TransferState::Negotiating, TransferState::Transferring] {
+                manager.update_session_state(running.session_id, state).await.unwrap();
+            }
+            manager
+                .update_session_state(negotiating.session_id, TransferState::Negotiating)
+                .await
+                .unwrap();
+
+            // Dropped without shutdown, as in a crash
+            (running.session_id, negotiating.session_id)
+        };
+
+        let manager = SessionManager::new(persistence_dir, None);
+        manager.initialize().await.unwrap();
+
+        assert_eq!(manager.get_session(running).await.unwrap().state, TransferState::Paused);
+        assert_eq!(manager.get_session(negotiating).await.unwrap().state, TransferState::Failed);
+        let recovery = manager.last_recovery().await;
+        assert_eq!(recovery.paused, vec![running]);
+        assert_eq!(recovery.failed, vec![negotiating]);
+    }
+
     #[tokio::test]
     async fn test_get_active_sessions() {
         let (manager, _temp_dir) = create_test_session_manager().await;
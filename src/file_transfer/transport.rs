@@ -4,6 +4,7 @@
 
 use crate::file_transfer::{
     error::{FileTransferError, Result},
+    hashing,
     types::*,
     TransportNegotiator,
 };
@@ -277,6 +278,14 @@ impl TransportNegotiatorImpl {
         // Simulate benchmark with timeout
         tokio::time::timeout(self.benchmark_timeout, async {
             tokio::time::sleep(Duration::from_millis(100)).await;
+
+            // Checksumming every chunk bounds throughput on fast links, so
+            // report how quickly this device can hash alongside the link
+            let hashing_throughput_bps = tokio::task::spawn_blocking(|| {
+                hashing::hashing_throughput(HashAlgorithm::default())
+            })
+            .await
+            .unwrap_or(0);
             
             // Return simulated metrics based on protocol characteristics
             Ok(match protocol {
@@ -285,18 +294,21 @@ impl TransportNegotiatorImpl {
                     throughput_bps: 100_000_000, // 100 Mbps
                     packet_loss: 0.01,
                     jitter_ms: 5,
+                    hashing_throughput_bps,
                 },
                 TransportProtocol::Tcp => PerformanceMetrics {
                     latency_ms: 25,
                     throughput_bps: 95_000_000, // 95 Mbps
                     packet_loss: 0.005,
                     jitter_ms: 3,
+                    hashing_throughput_bps,
                 },
                 TransportProtocol::WebRtc => PerformanceMetrics {
                     latency_ms: 30,
                     throughput_bps: 80_000_000, // 80 Mbps
                     packet_loss: 0.02,
                     jitter_ms: 8,
+                    hashing_throughput_bps,
                 },
             })
         })
@@ -683,6 +695,7 @@ mod tests {
             throughput_bps: 100_000_000,
             packet_loss: 0.01,
             jitter_ms: 5,
+            hashing_throughput_bps: 0,
         };
 
         // Cache metrics
@@ -714,6 +727,7 @@ mod tests {
                 throughput_bps: 90_000_000, // 90 Mbps
                 packet_loss: 0.01,
                 jitter_ms: 5,
+                hashing_throughput_bps: 0,
             },
         );
         metrics.insert(
@@ -723,6 +737,7 @@ mod tests {
                 throughput_bps: 100_000_000, // 100 Mbps
                 packet_loss: 0.005,
                 jitter_ms: 3,
+                hashing_throughput_bps: 0,
             },
         );
 
@@ -750,6 +765,7 @@ mod tests {
                 throughput_bps: 50_000_000, // 50 Mbps (only 50% of TCP)
                 packet_loss: 0.05,
                 jitter_ms: 15,
+                hashing_throughput_bps: 0,
             },
         );
         metrics.insert(
@@ -759,6 +775,7 @@ mod tests {
                 throughput_bps: 100_000_000, // 100 Mbps
                 packet_loss: 0.005,
                 jitter_ms: 3,
+                hashing_throughput_bps: 0,
             },
         );
 
@@ -848,6 +865,7 @@ mod tests {
             throughput_bps: 100_000_000,
             packet_loss: 0.01,
             jitter_ms: 5,
+            hashing_throughput_bps: 0,
         };
         negotiator.cache_performance_metrics(&peer_id, TransportProtocol::Quic, baseline_metrics).await;
 
@@ -9,11 +9,12 @@ use crate::file_transfer::{
     ChunkStream,
 };
 use crate::transport::{
-    Connection, PeerAddress, PeerId as TransportPeerId,
-    TransportCapabilities as TransportCaps,
+    Connection, ConnectionProvider, MuxStream, PeerAddress,
+    ProviderStats, TransportCapabilities as TransportCaps, CHANNEL_FILE_TRANSFER,
 };
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -21,6 +22,8 @@ use tokio::sync::RwLock;
 pub struct FileTransferTransport {
     /// Connection pool for reusing connections across transfers
     connection_pool: Arc<RwLock<HashMap<PeerId, Arc<RwLock<Box<dyn Connection>>>>>>,
+    /// Shared provider for multiplexed peer sessions
+    provider: Option<Arc<ConnectionProvider>>,
     /// Protocol-specific configurations
     protocol_configs: Arc<RwLock<HashMap<TransportProtocol, ProtocolConfig>>>,
 }
@@ -85,22 +88,44 @@ impl ProtocolConfig {
             keep_alive_interval: Some(30),
         }
     }
+
+    /// Cap the local buffer and in-flight chunk count at the resource profile
+    pub fn limited_to(mut self, profile: &crate::platform::ResourceProfile) -> Self {
+        self.buffer_size = self.buffer_size.min(profile.io_buffer_size);
+        self.max_concurrent_chunks = self.max_concurrent_chunks.min(profile.chunks_in_flight);
+        self
+    }
 }
 
 impl FileTransferTransport {
     /// Create a new transport integration
     pub fn new() -> Self {
+        let profile = crate::platform::ResourceProfile::active();
         let mut protocol_configs = HashMap::new();
-        protocol_configs.insert(TransportProtocol::Quic, ProtocolConfig::quic());
-        protocol_configs.insert(TransportProtocol::Tcp, ProtocolConfig::tcp());
-        protocol_configs.insert(TransportProtocol::WebRtc, ProtocolConfig::webrtc());
+        protocol_configs.insert(TransportProtocol::Quic, ProtocolConfig::quic().limited_to(profile));
+        protocol_configs.insert(TransportProtocol::Tcp, ProtocolConfig::tcp().limited_to(profile));
+        protocol_configs.insert(TransportProtocol::WebRtc, ProtocolConfig::webrtc().limited_to(profile));
 
         Self {
             connection_pool: Arc::new(RwLock::new(HashMap::new())),
+            provider: None,
             protocol_configs: Arc::new(RwLock::new(protocol_configs)),
         }
     }
 
+    /// Create a transport integration that opens streams over a shared provider
+    pub fn with_provider(provider: Arc<ConnectionProvider>) -> Self {
+        Self {
+            provider: Some(provider),
+            ..Self::new()
+        }
+    }
+
+    /// Shared connection provider, if one is configured
+    pub fn provider(&self) -> Option<&Arc<ConnectionProvider>> {
+        self.provider.as_ref()
+    }
+
     /// Add a connection to the pool
     pub async fn add_connection(&self, peer_id: PeerId, connection: Box<dyn Connection>) {
         let mut pool = self.connection_pool.write().await;
@@ -125,6 +150,19 @@ impl FileTransferTransport {
         })
     }
 
+    /// Whether the pooled connection to a peer runs over the local network
+    ///
+    /// Peers reached only through the shared provider are treated as remote,
+    /// since their session does not expose its address.
+    pub async fn is_lan_peer(&self, peer_id: &PeerId) -> bool {
+        let connection = match self.connection_pool.read().await.get(peer_id) {
+            Some(connection) => Arc::clone(connection),
+            None => return false,
+        };
+        let remote = connection.read().await.info().remote_addr.ip();
+        is_lan_address(remote)
+    }
+
     /// Release a connection back to the pool
     pub async fn release_connection(&self, peer_id: &PeerId) {
         // Connection remains in pool for reuse
@@ -185,12 +223,49 @@ impl FileTransferTransport {
         peer_id: &PeerId,
         protocol: TransportProtocol,
     ) -> Result<Box<dyn ChunkStream>> {
+        // Prefer a stream on an already pooled session
+        if let Some(session) = self.provider.as_ref().and_then(|p| p.existing_session(peer_id)) {
+            let stream = session.open_stream(CHANNEL_FILE_TRANSFER).map_err(|e| FileTransferError::NetworkError {
+                reason: format!("Failed to open transfer stream: {}", e),
+            })?;
+            return Ok(Box::new(MuxChunkStream::new(stream)));
+        }
+
         let connection = self.get_connection(peer_id).await?;
         let config = self.get_protocol_config(protocol).await;
         
         Ok(Box::new(TransportChunkStream::new(Arc::clone(&connection), config)))
     }
 
+    /// Open a chunk stream to a peer through the shared provider, dialing if needed
+    pub async fn open_chunk_stream(&self, peer_address: &PeerAddress) -> Result<Box<dyn ChunkStream>> {
+        let provider = self.provider.as_ref().ok_or_else(|| FileTransferError::NetworkError {
+            reason: "No connection provider configured".to_string(),
+        })?;
+
+        let stream = provider
+            .open_stream(peer_address, CHANNEL_FILE_TRANSFER)
+            .await
+            .map_err(|e| FileTransferError::NetworkError {
+                reason: format!("Failed to open transfer stream to {}: {}", peer_address.peer_id, e),
+            })?;
+
+        Ok(Box::new(MuxChunkStream::new(stream)))
+    }
+
+    /// Chunk stream over a file transfer stream that is already open
+    ///
+    /// Used for inbound streams, which the embedding app accepts on the
+    /// file transfer channel and hands over.
+    pub fn mux_chunk_stream(stream: MuxStream) -> Box<dyn ChunkStream> {
+        Box::new(MuxChunkStream::new(stream))
+    }
+
+    /// Statistics of the shared provider, if one is configured
+    pub fn provider_stats(&self) -> Option<ProviderStats> {
+        self.provider.as_ref().map(|p| p.stats())
+    }
+
     /// Get connection pool statistics
     pub async fn get_pool_stats(&self) -> ConnectionPoolStats {
         let pool = self.connection_pool.read().await;
@@ -266,6 +341,88 @@ impl ChunkStream for TransportChunkStream {
                 reason: format!("Failed to flush: {}", e),
             })
     }
+
+    async fn ready(&mut self) -> Result<()> {
+        let mut conn = self.connection.write().await;
+        conn.ready()
+            .await
+            .map_err(|e| FileTransferError::NetworkError {
+                reason: format!("Connection not ready: {}", e),
+            })
+    }
+
+    fn buffered_bytes(&self) -> usize {
+        self.connection
+            .try_read()
+            .map(|conn| conn.buffered_bytes())
+            .unwrap_or(0)
+    }
+}
+
+/// Chunk stream over a multiplexed session stream
+struct MuxChunkStream {
+    stream: MuxStream,
+    pending: Vec<u8>,
+}
+
+impl MuxChunkStream {
+    fn new(stream: MuxStream) -> Self {
+        Self {
+            stream,
+            pending: Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ChunkStream for MuxChunkStream {
+    async fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.stream
+            .send(data)
+            .await
+            .map_err(|e| FileTransferError::NetworkError {
+                reason: format!("Failed to send data: {}", e),
+            })
+    }
+
+    async fn receive(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        if self.pending.is_empty() {
+            match self.stream.recv().await {
+                Some(data) => self.pending = data,
+                None => return Ok(0),
+            }
+        }
+
+        let n = buffer.len().min(self.pending.len());
+        buffer[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        // Frames are handed to the session as they are sent
+        Ok(())
+    }
+
+    async fn ready(&mut self) -> Result<()> {
+        self.stream
+            .ready()
+            .await
+            .map_err(|e| FileTransferError::NetworkError {
+                reason: format!("Stream not ready: {}", e),
+            })
+    }
+}
+
+/// Whether an address belongs to the local network
+fn is_lan_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_link_local() || ip.is_loopback(),
+        // Unique local (fc00::/7) and link-local (fe80::/10) addresses
+        IpAddr::V6(ip) => {
+            ip.is_loopback() || (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80
+        }
+    }
 }
 
 /// Transport protocol mapper
@@ -425,12 +582,23 @@ mod tests {
     #[tokio::test]
     async fn test_get_protocol_config() {
         let transport = FileTransferTransport::new();
+        let profile = crate::platform::ResourceProfile::active();
         
         let quic_config = transport.get_protocol_config(TransportProtocol::Quic).await;
-        assert_eq!(quic_config.buffer_size, 128 * 1024);
+        assert_eq!(quic_config.buffer_size, (128 * 1024).min(profile.io_buffer_size));
         
         let tcp_config = transport.get_protocol_config(TransportProtocol::Tcp).await;
-        assert_eq!(tcp_config.buffer_size, 64 * 1024);
+        assert_eq!(tcp_config.buffer_size, (64 * 1024).min(profile.io_buffer_size));
+    }
+
+    #[test]
+    fn test_protocol_config_limited_to_constrained_profile() {
+        let config = ProtocolConfig::quic().limited_to(&crate::platform::ResourceProfile::constrained());
+        assert_eq!(config.buffer_size, 64 * 1024);
+        assert_eq!(config.max_concurrent_chunks, 2);
+
+        let config = ProtocolConfig::webrtc().limited_to(&crate::platform::ResourceProfile::constrained());
+        assert_eq!(config.buffer_size, 16 * 1024);
     }
 
     #[tokio::test]
@@ -476,6 +644,39 @@ mod tests {
         assert!(!ft_caps.supports_webrtc);
     }
 
+    #[tokio::test]
+    async fn test_chunk_stream_over_provider() {
+        use crate::testing::{MemoryTransport, VirtualNetwork};
+        use crate::transport::{ProviderConfig, Transport};
+
+        let network = VirtualNetwork::new();
+        let server = Arc::new(MemoryTransport::new("server", network.clone()));
+        let client = Arc::new(MemoryTransport::new("client", network.clone()));
+        let addr = network.allocate_address(41000);
+        server.listen(&addr).await.unwrap();
+        let peer = PeerAddress::new("server".to_string(), vec![addr], vec![], server.capabilities());
+
+        let provider = Arc::new(ConnectionProvider::new(client, ProviderConfig::default()));
+        let transport = FileTransferTransport::with_provider(Arc::clone(&provider));
+        let mut stream = transport.open_chunk_stream(&peer).await.unwrap();
+        stream.send(b"chunk").await.unwrap();
+
+        // Later transfers to the same peer reuse the pooled session
+        let _second = transport
+            .create_chunk_stream(&"server".to_string(), TransportProtocol::Tcp)
+            .await
+            .unwrap();
+        let stats = transport.provider_stats().unwrap();
+        assert_eq!(stats.dialed, 1);
+        assert_eq!(stats.open_streams, 2);
+
+        let remote = ConnectionProvider::new(Arc::clone(&server) as Arc<dyn crate::transport::PeerDialer>, ProviderConfig::default());
+        let session = remote.adopt(Box::new(server.accept().await.unwrap())).await.unwrap();
+        let inbound = session.accept_stream().await.unwrap();
+        assert_eq!(inbound.channel(), CHANNEL_FILE_TRANSFER);
+        assert_eq!(inbound.recv().await.unwrap(), b"chunk");
+    }
+
     #[tokio::test]
     async fn test_chunk_stream_send_receive() {
         let connection = Arc::new(RwLock::new(Box::new(MockConnection { connected: true }) as Box<dyn Connection>));
@@ -1,5 +1,9 @@
 // Core File Transfer Data Structures
 
+use crate::file_transfer::fanout::SwarmPlan;
+use crate::file_transfer::preview::FilePreview;
+use crate::file_transfer::negotiation::Negotiation;
+use crate::file_transfer::pipe::PipeEntry;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -21,6 +25,26 @@ pub fn current_timestamp() -> Timestamp {
         .as_secs()
 }
 
+/// Checksum algorithm used for a transfer's manifest, files and chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// BLAKE3, SIMD-accelerated and parallel across cores
+    #[default]
+    Blake3,
+    /// SHA-256, understood by every Kizuna version
+    Sha256,
+}
+
+impl HashAlgorithm {
+    /// Algorithm assumed for manifests that do not name one
+    ///
+    /// Peers predating algorithm selection always hashed with SHA-256.
+    pub fn legacy() -> Self {
+        Self::Sha256
+    }
+}
+
 /// Transfer manifest containing metadata for files to be transferred
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransferManifest {
@@ -31,7 +55,27 @@ pub struct TransferManifest {
     pub file_count: usize,
     pub files: Vec<FileEntry>,
     pub directories: Vec<DirectoryEntry>,
-    pub checksum: [u8; 32], // SHA-256 of entire manifest
+    #[serde(default = "HashAlgorithm::legacy")]
+    pub hash_algorithm: HashAlgorithm,
+    /// Symbolic links sent as links rather than as the files they point to
+    #[serde(default)]
+    pub symlinks: Vec<SymlinkEntry>,
+    /// Metadata the sender captured and asks the receiver to restore
+    #[serde(default)]
+    pub preservation: MetadataPreservation,
+    /// Platform the manifest was built on, for the compatibility matrix
+    #[serde(default)]
+    pub sender_platform: Option<PlatformFamily>,
+    /// Chunk sharing between LAN receivers of a fan-out send
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swarm: Option<SwarmPlan>,
+    /// Icons and thumbnails shown with the offer; not covered by the checksum
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previews: Vec<FilePreview>,
+    /// Piped data of unknown length, sent instead of files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipe: Option<PipeEntry>,
+    pub checksum: [u8; 32], // Hash of entire manifest using hash_algorithm
 }
 
 impl TransferManifest {
@@ -44,6 +88,13 @@ impl TransferManifest {
             file_count: 0,
             files: Vec::new(),
             directories: Vec::new(),
+            hash_algorithm: HashAlgorithm::default(),
+            symlinks: Vec::new(),
+            preservation: MetadataPreservation::default(),
+            sender_platform: Some(PlatformFamily::current()),
+            swarm: None,
+            previews: Vec::new(),
+            pipe: None,
             checksum: [0u8; 32],
         }
     }
@@ -54,10 +105,90 @@ impl TransferManifest {
 pub struct FileEntry {
     pub path: PathBuf,
     pub size: u64,
-    pub checksum: [u8; 32], // SHA-256
+    pub checksum: [u8; 32], // Hashed with the manifest's hash_algorithm
     pub permissions: FilePermissions,
     pub modified_at: Timestamp,
     pub chunk_count: usize,
+    /// Extended attributes and sparseness, when metadata preservation is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extended: Option<ExtendedMetadata>,
+}
+
+/// Filesystem metadata beyond permissions and modification time
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtendedMetadata {
+    pub xattrs: Vec<ExtendedAttribute>,
+    /// File has holes on the sender; the receiver recreates them
+    pub sparse: bool,
+}
+
+/// Single extended attribute
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtendedAttribute {
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
+/// Symbolic link entry in transfer manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymlinkEntry {
+    pub path: PathBuf,
+    pub target: PathBuf,
+    /// Whether the target is a directory, which Windows needs to know
+    pub target_is_dir: bool,
+}
+
+/// Which filesystem metadata a transfer carries across
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataPreservation {
+    pub symlinks: bool,
+    pub permissions: bool,
+    pub mtimes: bool,
+    pub xattrs: bool,
+    pub sparse: bool,
+}
+
+impl MetadataPreservation {
+    /// Preserve everything the platforms on both ends support
+    pub fn all() -> Self {
+        Self {
+            symlinks: true,
+            permissions: true,
+            mtimes: true,
+            xattrs: true,
+            sparse: true,
+        }
+    }
+
+    /// Whether any metadata beyond file contents is preserved
+    pub fn any(&self) -> bool {
+        self.symlinks || self.permissions || self.mtimes || self.xattrs || self.sparse
+    }
+}
+
+/// Operating system family, as far as metadata support is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlatformFamily {
+    Linux,
+    MacOs,
+    Windows,
+    Other,
+}
+
+impl PlatformFamily {
+    /// Platform this build runs on
+    pub fn current() -> Self {
+        if cfg!(target_os = "linux") || cfg!(target_os = "android") {
+            Self::Linux
+        } else if cfg!(target_os = "macos") || cfg!(target_os = "ios") {
+            Self::MacOs
+        } else if cfg!(windows) {
+            Self::Windows
+        } else {
+            Self::Other
+        }
+    }
 }
 
 /// Directory entry in transfer manifest
@@ -73,10 +204,22 @@ pub struct DirectoryEntry {
 pub struct FilePermissions {
     pub readonly: bool,
     pub executable: bool,
+    // Senders without POSIX modes omit this; fall back to a regular file mode
     #[cfg(unix)]
+    #[serde(default = "default_file_mode")]
     pub mode: u32,
 }
 
+#[cfg(unix)]
+fn default_file_mode() -> u32 {
+    0o644
+}
+
+// Sessions saved before compression was a setting were compressed
+fn default_compression() -> bool {
+    true
+}
+
 impl Default for FilePermissions {
     fn default() -> Self {
         Self {
@@ -98,9 +241,15 @@ pub struct TransferSession {
     pub state: TransferState,
     pub progress: TransferProgress,
     pub bandwidth_limit: Option<u64>,
+    /// Compress chunks on the wire
+    #[serde(default = "default_compression")]
+    pub compression: bool,
     pub parallel_streams: usize,
     pub resume_token: Option<ResumeToken>,
     pub created_at: Timestamp,
+    /// How the sender and receiver settled on the transfer's terms
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub negotiation: Option<Negotiation>,
 }
 
 impl TransferSession {
@@ -113,9 +262,11 @@ impl TransferSession {
             state: TransferState::Pending,
             progress: TransferProgress::default(),
             bandwidth_limit: None,
+            compression: true,
             parallel_streams: 1,
             resume_token: None,
             created_at: current_timestamp(),
+            negotiation: None,
         }
     }
 }
@@ -223,7 +374,7 @@ pub struct Chunk {
     pub offset: u64,
     pub size: usize,
     pub data: Vec<u8>,
-    pub checksum: [u8; 32], // SHA-256
+    pub checksum: [u8; 32], // Hashed with the transfer's hash_algorithm
     pub compressed: bool,
 }
 
@@ -289,6 +440,9 @@ pub struct PerformanceMetrics {
     pub throughput_bps: u64,
     pub packet_loss: f64,
     pub jitter_ms: u64,
+    /// Local checksum throughput, which caps transfer speed on fast links
+    #[serde(default)]
+    pub hashing_throughput_bps: u64,
 }
 
 /// Transfer request for queue management
@@ -309,6 +463,12 @@ pub struct QueueItem {
     pub estimated_start: Option<Timestamp>,
     pub state: QueueState,
     pub created_at: Timestamp,
+    /// Order among items of equal priority, preserved across restarts
+    #[serde(default)]
+    pub position: u64,
+    /// Why the item can no longer be sent, when its state is `Failed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<QueueFailure>,
 }
 
 /// Priority levels for queue items
@@ -327,4 +487,23 @@ pub enum QueueState {
     Scheduled,
     Paused,
     Cancelled,
+    Failed,
+}
+
+/// Reason a queued transfer failed before it could start
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueueFailure {
+    /// A source file was deleted or moved while the transfer waited
+    SourceMissing { path: PathBuf },
+    /// A source file was modified after it was queued
+    SourceChanged { path: PathBuf },
+}
+
+impl std::fmt::Display for QueueFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueFailure::SourceMissing { path } => write!(f, "source file {} no longer exists", path.display()),
+            QueueFailure::SourceChanged { path } => write!(f, "source file {} changed since it was queued", path.display()),
+        }
+    }
 }
@@ -0,0 +1,578 @@
+//! Port and socket forwarding between trusted peers
+//!
+//! A minimal `ssh -L`: connections to a local TCP port or unix socket are
+//! carried over the `forward` channel of a peer's encrypted session and
+//! handed to a service on that peer. Every connection gets its own stream,
+//! which starts with a [`ForwardRequest`] naming the target and answered with
+//! a [`ForwardResponse`], and after that carries plain bytes both ways.
+//!
+//! The remote side decides what may be reached. Only trusted peers are
+//! served, nothing is forwarded while the device is locked down, and the
+//! target has to be on the [`ForwardConfig`] allowlist of loopback ports and
+//! unix sockets. The allowlist is empty by default, so each service has to be
+//! opened up explicitly.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::lockdown::{Lockdown, RemoteCapability};
+use crate::security::identity::PeerId;
+use crate::security::trust::TrustManager;
+use crate::transport::{ConnectionProvider, MuxStream, PeerAddress, CHANNEL_FORWARD};
+
+/// Largest request or response on a forward stream
+const MAX_FORWARD_MESSAGE_LEN: usize = 4096;
+
+/// Bytes read from a local connection at a time
+const FORWARD_BUFFER_LEN: usize = 16 * 1024;
+
+/// How long the remote side waits for the target service to accept
+const TARGET_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Service on the remote device that connections are forwarded to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ForwardTarget {
+    /// TCP port on the remote device's loopback interface
+    Tcp { port: u16 },
+    /// Unix socket on the remote device
+    Unix { path: PathBuf },
+}
+
+impl fmt::Display for ForwardTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForwardTarget::Tcp { port } => write!(f, "localhost:{}", port),
+            ForwardTarget::Unix { path } => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Forwarding settings, part of the user configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForwardConfig {
+    /// Loopback TCP ports trusted peers may forward to
+    pub allowed_ports: Vec<u16>,
+    /// Unix sockets trusted peers may forward to, matched exactly
+    pub allowed_sockets: Vec<PathBuf>,
+}
+
+impl ForwardConfig {
+    /// Whether peers may forward to `target`
+    pub fn allows(&self, target: &ForwardTarget) -> bool {
+        match target {
+            ForwardTarget::Tcp { port } => self.allowed_ports.contains(port),
+            ForwardTarget::Unix { path } => self.allowed_sockets.iter().any(|allowed| allowed == path),
+        }
+    }
+}
+
+/// First message on a forward stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForwardRequest {
+    pub target: ForwardTarget,
+}
+
+/// What the remote side answers a [`ForwardRequest`] with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ForwardResponse {
+    /// The target accepted the connection; bytes follow
+    Connected,
+    Refused { reason: String },
+}
+
+/// Why a connection could not be forwarded
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ForwardError {
+    #[error("Peer {0} is not trusted")]
+    PeerNotTrusted(String),
+    #[error("Peer is unreachable: {0}")]
+    Unreachable(String),
+    #[error("Peer refused to forward: {0}")]
+    Refused(String),
+    #[error("Cannot listen on {address}: {reason}")]
+    Listen { address: String, reason: String },
+    #[error("Forwarding failed: {0}")]
+    Failed(String),
+}
+
+/// A local listener forwarding to a peer
+///
+/// Dropping the handle stops accepting connections; connections already
+/// forwarded run until either end closes them.
+pub struct ForwardHandle {
+    local_address: String,
+    peer_id: String,
+    target: ForwardTarget,
+    socket_path: Option<PathBuf>,
+    task: JoinHandle<()>,
+}
+
+impl ForwardHandle {
+    /// Address the listener is bound to, with the actual port if 0 was asked for
+    pub fn local_address(&self) -> &str {
+        &self.local_address
+    }
+
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    pub fn target(&self) -> &ForwardTarget {
+        &self.target
+    }
+
+    /// Stop accepting connections
+    pub fn stop(self) {}
+}
+
+impl Drop for ForwardHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+        if let Some(path) = &self.socket_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Local connection or target service carried over a forward stream
+trait Duplex: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Duplex for T {}
+
+/// Forwards local connections to peers and serves their forwards
+#[derive(Clone)]
+pub struct PortForwarder {
+    provider: Arc<ConnectionProvider>,
+    trust: Arc<dyn TrustManager>,
+    config: Arc<RwLock<ForwardConfig>>,
+}
+
+impl PortForwarder {
+    pub fn new(provider: Arc<ConnectionProvider>, trust: Arc<dyn TrustManager>, config: ForwardConfig) -> Self {
+        Self {
+            provider,
+            trust,
+            config: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    pub async fn config(&self) -> ForwardConfig {
+        self.config.read().await.clone()
+    }
+
+    /// Replace the allowlist; forwards already running are not affected
+    pub async fn set_config(&self, config: ForwardConfig) {
+        *self.config.write().await = config;
+    }
+
+    /// Open one forwarded connection to `target` on `peer`
+    ///
+    /// The returned stream carries the connection's bytes once the peer has
+    /// connected to the target.
+    pub async fn connect(&self, peer: &PeerAddress, target: &ForwardTarget) -> Result<MuxStream, ForwardError> {
+        self.ensure_trusted(&peer.peer_id).await?;
+
+        let request = serde_json::to_vec(&ForwardRequest { target: target.clone() })
+            .map_err(|e| ForwardError::Failed(e.to_string()))?;
+        let stream = self
+            .provider
+            .open_stream(peer, CHANNEL_FORWARD)
+            .await
+            .map_err(|e| ForwardError::Unreachable(e.to_string()))?;
+        stream
+            .send_message(&request)
+            .await
+            .map_err(|e| ForwardError::Unreachable(e.to_string()))?;
+
+        let reply = match stream.recv_message(MAX_FORWARD_MESSAGE_LEN).await {
+            Ok(Some(reply)) => reply,
+            Ok(None) => return Err(ForwardError::Unreachable("Peer closed the forward stream".to_string())),
+            Err(e) => return Err(ForwardError::Unreachable(e.to_string())),
+        };
+        match serde_json::from_slice::<ForwardResponse>(&reply) {
+            Ok(ForwardResponse::Connected) => Ok(stream),
+            Ok(ForwardResponse::Refused { reason }) => Err(ForwardError::Refused(reason)),
+            Err(e) => {
+                stream.reset("Malformed forward reply");
+                Err(ForwardError::Failed(format!("Malformed reply: {}", e)))
+            }
+        }
+    }
+
+    /// Listen on a local TCP address and forward each connection to `target` on `peer`
+    pub async fn forward_tcp(
+        &self,
+        local: SocketAddr,
+        peer: PeerAddress,
+        target: ForwardTarget,
+    ) -> Result<ForwardHandle, ForwardError> {
+        self.ensure_trusted(&peer.peer_id).await?;
+        let listener = TcpListener::bind(local).await.map_err(|e| ForwardError::Listen {
+            address: local.to_string(),
+            reason: e.to_string(),
+        })?;
+        let local_address = listener.local_addr().unwrap_or(local).to_string();
+
+        let forwarder = self.clone();
+        let (task_peer, task_target, task_address) = (peer.clone(), target.clone(), local_address.clone());
+        let task = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((connection, from)) => {
+                        log::debug!("Forwarding {} to {} on {}", from, task_target, task_peer.peer_id);
+                        forwarder.spawn_forward(Box::new(connection), task_peer.clone(), task_target.clone());
+                    }
+                    Err(e) => {
+                        log::warn!("Forward listener on {} failed: {}", task_address, e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        log::info!("Forwarding {} to {} on {}", local_address, target, peer.peer_id);
+        Ok(ForwardHandle {
+            local_address,
+            peer_id: peer.peer_id,
+            target,
+            socket_path: None,
+            task,
+        })
+    }
+
+    /// Listen on a local unix socket and forward each connection to `target` on `peer`
+    ///
+    /// The socket file is created here and removed when the handle is dropped.
+    #[cfg(unix)]
+    pub async fn forward_unix(
+        &self,
+        local: &std::path::Path,
+        peer: PeerAddress,
+        target: ForwardTarget,
+    ) -> Result<ForwardHandle, ForwardError> {
+        self.ensure_trusted(&peer.peer_id).await?;
+        let listener = tokio::net::UnixListener::bind(local).map_err(|e| ForwardError::Listen {
+            address: local.display().to_string(),
+            reason: e.to_string(),
+        })?;
+        let local_address = local.display().to_string();
+
+        let forwarder = self.clone();
+        let (task_peer, task_target, task_address) = (peer.clone(), target.clone(), local_address.clone());
+        let task = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((connection, _)) => {
+                        log::debug!("Forwarding {} to {} on {}", task_address, task_target, task_peer.peer_id);
+                        forwarder.spawn_forward(Box::new(connection), task_peer.clone(), task_target.clone());
+                    }
+                    Err(e) => {
+                        log::warn!("Forward listener on {} failed: {}", task_address, e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        log::info!("Forwarding {} to {} on {}", local_address, target, peer.peer_id);
+        Ok(ForwardHandle {
+            local_address,
+            peer_id: peer.peer_id,
+            target,
+            socket_path: Some(local.to_path_buf()),
+            task,
+        })
+    }
+
+    /// Serve a forward request arriving on a stream
+    pub fn attach_stream(&self, peer_id: &str, stream: MuxStream) -> Result<(), ForwardError> {
+        if stream.channel() != CHANNEL_FORWARD {
+            return Err(ForwardError::Failed(format!(
+                "Stream on channel {} is not a forward stream",
+                stream.channel()
+            )));
+        }
+
+        let forwarder = self.clone();
+        let peer_id = peer_id.to_string();
+        tokio::spawn(async move { forwarder.serve(&peer_id, stream).await });
+        Ok(())
+    }
+
+    /// Decide whether `peer_id` may reach `target`, with the reason if not
+    pub async fn authorize(&self, peer_id: &str, target: &ForwardTarget) -> Result<(), String> {
+        if let Err(locked) = Lockdown::global().check(RemoteCapability::PortForwarding) {
+            return Err(locked.to_string());
+        }
+        if self.ensure_trusted(peer_id).await.is_err() {
+            return Err("This device does not trust you".to_string());
+        }
+        if !self.config.read().await.allows(target) {
+            return Err(format!("{} is not open for forwarding", target));
+        }
+        Ok(())
+    }
+
+    async fn serve(&self, peer_id: &str, stream: MuxStream) {
+        let request = match stream.recv_message(MAX_FORWARD_MESSAGE_LEN).await {
+            Ok(Some(bytes)) => serde_json::from_slice::<ForwardRequest>(&bytes).ok(),
+            _ => None,
+        };
+        let Some(request) = request else {
+            log::debug!("Malformed forward request from {}", peer_id);
+            stream.reset("Malformed forward request");
+            return;
+        };
+
+        let connection = match self.authorize(peer_id, &request.target).await {
+            Ok(()) => connect_target(&request.target).await,
+            Err(reason) => Err(reason),
+        };
+        let connection = match connection {
+            Ok(connection) => connection,
+            Err(reason) => {
+                log::info!("Refused forward from {} to {}: {}", peer_id, request.target, reason);
+                if let Ok(bytes) = serde_json::to_vec(&ForwardResponse::Refused { reason }) {
+                    let _ = stream.send_message(&bytes).await;
+                }
+                let _ = stream.close();
+                return;
+            }
+        };
+
+        let Ok(bytes) = serde_json::to_vec(&ForwardResponse::Connected) else {
+            return;
+        };
+        if stream.send_message(&bytes).await.is_err() {
+            return;
+        }
+        log::debug!("Forwarding from {} to {}", peer_id, request.target);
+        if let Err(e) = pipe(connection, &stream).await {
+            log::debug!("Forward from {} to {} ended: {}", peer_id, request.target, e);
+        }
+    }
+
+    /// Forward one accepted local connection in the background
+    fn spawn_forward(&self, connection: Box<dyn Duplex>, peer: PeerAddress, target: ForwardTarget) {
+        let forwarder = self.clone();
+        tokio::spawn(async move {
+            match forwarder.connect(&peer, &target).await {
+                Ok(stream) => {
+                    if let Err(e) = pipe(connection, &stream).await {
+                        log::debug!("Forward to {} on {} ended: {}", target, peer.peer_id, e);
+                    }
+                }
+                Err(e) => log::warn!("Cannot forward to {} on {}: {}", target, peer.peer_id, e),
+            }
+        });
+    }
+
+    async fn ensure_trusted(&self, peer_id: &str) -> Result<(), ForwardError> {
+        let not_trusted = || ForwardError::PeerNotTrusted(peer_id.to_string());
+        let peer = PeerId::from_string(peer_id).map_err(|_| not_trusted())?;
+        if !self.trust.is_trusted(&peer).await.unwrap_or(false) {
+            return Err(not_trusted());
+        }
+        Ok(())
+    }
+}
+
+/// Connect to a forward target on this device
+async fn connect_target(target: &ForwardTarget) -> Result<Box<dyn Duplex>, String> {
+    let connect = async {
+        match target {
+            ForwardTarget::Tcp { port } => TcpStream::connect((Ipv4Addr::LOCALHOST, *port))
+                .await
+                .map(|stream| Box::new(stream) as Box<dyn Duplex>),
+            #[cfg(unix)]
+            ForwardTarget::Unix { path } => tokio::net::UnixStream::connect(path)
+                .await
+                .map(|stream| Box::new(stream) as Box<dyn Duplex>),
+            #[cfg(not(unix))]
+            ForwardTarget::Unix { .. } => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "unix sockets are not supported on this platform",
+            )),
+        }
+    };
+
+    match tokio::time::timeout(TARGET_CONNECT_TIMEOUT, connect).await {
+        Ok(Ok(connection)) => Ok(connection),
+        Ok(Err(e)) => Err(format!("Cannot connect to {}: {}", target, e)),
+        Err(_) => Err(format!("{} did not accept within {:?}", target, TARGET_CONNECT_TIMEOUT)),
+    }
+}
+
+/// Copy bytes between a connection and a forward stream until both sides close
+///
+/// Returns the bytes sent and received. Either side closing only ends its
+/// direction, so half-closed protocols keep working; an error resets the
+/// stream.
+async fn pipe<C: AsyncRead + AsyncWrite>(connection: C, stream: &MuxStream) -> std::io::Result<(u64, u64)> {
+    let (mut reader, mut writer) = tokio::io::split(connection);
+
+    let outbound = async {
+        let mut buffer = vec![0u8; FORWARD_BUFFER_LEN];
+        let mut sent = 0u64;
+        loop {
+            let n = reader.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+            stream
+                .send(&buffer[..n])
+                .await
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::BrokenPipe, e.to_string()))?;
+            sent += n as u64;
+        }
+        let _ = stream.close();
+        Ok::<_, std::io::Error>(sent)
+    };
+    let inbound = async {
+        let mut received = 0u64;
+        while let Some(data) = stream.recv().await {
+            writer.write_all(&data).await?;
+            received += data.len() as u64;
+        }
+        writer.shutdown().await?;
+        Ok::<_, std::io::Error>(received)
+    };
+
+    let result = tokio::try_join!(outbound, inbound);
+    if result.is_err() {
+        stream.reset("Forwarded connection failed");
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::identity::DeviceIdentity;
+    use crate::security::trust::TrustManagerImpl;
+    use crate::testing::transport::MEMORY_PROTOCOL;
+    use crate::testing::{MemoryTransport, VirtualNetwork};
+    use crate::transport::{PeerDialer, ProviderConfig, Transport};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_allowlist_matches_exact_targets() {
+        let config = ForwardConfig {
+            allowed_ports: vec![5432],
+            allowed_sockets: vec![PathBuf::from("/run/app.sock")],
+        };
+        assert!(config.allows(&ForwardTarget::Tcp { port: 5432 }));
+        assert!(!config.allows(&ForwardTarget::Tcp { port: 22 }));
+        assert!(config.allows(&ForwardTarget::Unix { path: PathBuf::from("/run/app.sock") }));
+        assert!(!config.allows(&ForwardTarget::Unix { path: PathBuf::from("/run/../run/app.sock") }));
+        assert!(!ForwardConfig::default().allows(&ForwardTarget::Tcp { port: 5432 }));
+    }
+
+    #[tokio::test]
+    async fn test_forward_tcp_to_allowed_port() {
+        let alice_identity = DeviceIdentity::generate().unwrap();
+        let bob_identity = DeviceIdentity::generate().unwrap();
+        let alice_id = alice_identity.derive_peer_id().to_hex();
+        let bob_id = bob_identity.derive_peer_id().to_hex();
+
+        let network = VirtualNetwork::new();
+        let alice_transport = Arc::new(MemoryTransport::new(alice_id.clone(), network.clone()));
+        let bob_transport = Arc::new(MemoryTransport::new(bob_id.clone(), network.clone()));
+        let addr = network.allocate_address(43200);
+        bob_transport.listen(&addr).await.unwrap();
+        let bob_addr = PeerAddress::new(
+            bob_id.clone(),
+            vec![addr],
+            vec![MEMORY_PROTOCOL.to_string()],
+            bob_transport.capabilities(),
+        );
+
+        let (alice_dir, bob_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let alice_trust = Arc::new(TrustManagerImpl::new(alice_dir.path().join("trust.db")).unwrap());
+        let bob_trust = Arc::new(TrustManagerImpl::new(bob_dir.path().join("trust.db")).unwrap());
+        alice_trust
+            .add_trusted_peer(bob_identity.derive_peer_id(), "bob".to_string())
+            .await
+            .unwrap();
+        bob_trust
+            .add_trusted_peer(alice_identity.derive_peer_id(), "alice".to_string())
+            .await
+            .unwrap();
+
+        // Echo service on Bob's loopback
+        let service = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let service_port = service.local_addr().unwrap().port();
+        let echo = tokio::spawn(async move {
+            while let Ok((mut connection, _)) = service.accept().await {
+                tokio::spawn(async move {
+                    let (mut reader, mut writer) = connection.split();
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+
+        let alice = PortForwarder::new(
+            Arc::new(ConnectionProvider::new(alice_transport, ProviderConfig::default())),
+            alice_trust,
+            ForwardConfig::default(),
+        );
+        let bob_provider = Arc::new(ConnectionProvider::new(
+            Arc::clone(&bob_transport) as Arc<dyn PeerDialer>,
+            ProviderConfig::default(),
+        ));
+        let bob = PortForwarder::new(
+            Arc::clone(&bob_provider),
+            bob_trust,
+            ForwardConfig {
+                allowed_ports: vec![service_port],
+                ..Default::default()
+            },
+        );
+
+        let serve = {
+            let alice_id = alice_id.clone();
+            tokio::spawn(async move {
+                let session = bob_provider.adopt(Box::new(bob_transport.accept().await.unwrap())).await.unwrap();
+                while let Some(stream) = session.accept_stream().await {
+                    bob.attach_stream(&alice_id, stream).unwrap();
+                }
+            })
+        };
+
+        let handle = alice
+            .forward_tcp(
+                (Ipv4Addr::LOCALHOST, 0).into(),
+                bob_addr.clone(),
+                ForwardTarget::Tcp { port: service_port },
+            )
+            .await
+            .unwrap();
+        let mut connection = TcpStream::connect(handle.local_address()).await.unwrap();
+        connection.write_all(b"hello over kizuna").await.unwrap();
+        let mut echoed = [0u8; 17];
+        connection.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"hello over kizuna");
+
+        // Ports outside Bob's allowlist are refused
+        let err = alice
+            .connect(&bob_addr, &ForwardTarget::Tcp { port: service_port.wrapping_add(1) })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ForwardError::Refused(_)));
+
+        handle.stop();
+        serve.abort();
+        echo.abort();
+    }
+}
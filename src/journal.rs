@@ -0,0 +1,554 @@
+//! Crash-safe state journaling
+//!
+//! Long-running operations (file transfers, the transfer queue, stream recordings)
+//! record their state in a [`Journal`] so a crash or power loss cannot lose or
+//! corrupt it. Every change is appended to a write-ahead log and synced before it
+//! is applied in memory; each record carries a checksum, so a record torn by a crash
+//! mid-write is detected on replay and discarded along with anything after it.
+//!
+//! The log is compacted into a snapshot once it grows past
+//! [`JournalConfig::compact_after`] records. Snapshots are replaced with
+//! [`atomic_write`] (write to a temporary file, sync, rename), so a reader always
+//! sees either the previous snapshot or the new one, never a partial file.
+//!
+//! On startup the owner calls [`Journal::recover`] to get back every operation that
+//! was still in flight, then decides per operation whether it can be resumed or has
+//! to be marked failed.
+//!
+//! Journals are given the shared [`EncryptedStorage`] when created, and then the
+//! snapshot and each log record are encrypted at rest. A journal written in
+//! plaintext is still replayed, and the compaction that follows recovery rewrites
+//! it encrypted. After that, plaintext in the journal is no longer accepted.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
+
+use crate::security::storage::EncryptedStorage;
+
+/// Default number of log records written between compactions
+pub const DEFAULT_COMPACT_AFTER: usize = 1024;
+
+/// Replace the contents of `path` so that readers see either the old or new data
+///
+/// The data is written to a temporary file in the same directory, synced, and
+/// renamed over `path`; the directory is then synced so the rename itself survives
+/// a crash.
+pub async fn atomic_write(path: &Path, data: &[u8]) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = fs::File::create(&tmp_path).await?;
+    file.write_all(data).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    if let Err(e) = fs::rename(&tmp_path, path).await {
+        let _ = fs::remove_file(&tmp_path).await;
+        return Err(e);
+    }
+    sync_parent(path).await
+}
+
+#[cfg(unix)]
+async fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(parent) => fs::File::open(parent).await?.sync_all().await,
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn sync_parent(_path: &Path) -> io::Result<()> {
+    // Directories cannot be opened for syncing on this platform; the rename is
+    // still atomic, only its durability across power loss is weaker
+    Ok(())
+}
+
+/// State of one in-flight operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unique key of the operation, e.g. a session ID
+    pub key: String,
+    /// Kind of operation, used by owners sharing a journal
+    pub kind: String,
+    /// Serialized operation state
+    pub state: serde_json::Value,
+    /// Unix timestamp of the last update
+    pub updated_at: u64,
+}
+
+impl JournalEntry {
+    /// Deserialize the recorded state
+    pub fn decode<T: DeserializeOwned>(&self) -> io::Result<T> {
+        serde_json::from_value(self.state.clone()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// A single write-ahead log record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalRecord {
+    Put { entry: JournalEntry },
+    Remove { key: String },
+}
+
+/// Journal tuning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalConfig {
+    /// Log records written before the log is folded into a new snapshot
+    pub compact_after: usize,
+    /// Sync the log to disk after every record
+    ///
+    /// Disabling this trades durability of the last few updates for throughput;
+    /// torn records are still detected either way.
+    pub sync_writes: bool,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            compact_after: DEFAULT_COMPACT_AFTER,
+            sync_writes: true,
+        }
+    }
+}
+
+/// What [`Journal::recover`] found on disk
+#[derive(Debug, Clone, Default)]
+pub struct JournalRecovery {
+    /// Operations that were in flight, in no particular order
+    pub entries: Vec<JournalEntry>,
+    /// Log records dropped because they were torn or corrupt
+    pub discarded_records: usize,
+}
+
+#[derive(Debug, Default)]
+struct JournalState {
+    entries: HashMap<String, JournalEntry>,
+    log: Option<fs::File>,
+    records_since_compaction: usize,
+}
+
+/// Write-ahead journal of in-flight operation state
+///
+/// Creating a journal does not touch the disk; [`recover`](Self::recover) loads it
+/// and must run before the first update, or is run implicitly by it.
+#[derive(Debug)]
+pub struct Journal {
+    dir: PathBuf,
+    name: String,
+    config: JournalConfig,
+    storage: Option<EncryptedStorage>,
+    state: Mutex<JournalState>,
+}
+
+impl Journal {
+    /// Journal stored as `<name>.snapshot` and `<name>.wal` in `dir`
+    ///
+    /// `storage` encrypts the snapshot and log records at rest; `None` only when
+    /// encryption at rest is turned off.
+    pub fn new(dir: impl Into<PathBuf>, name: &str, storage: Option<EncryptedStorage>) -> Self {
+        Self::with_config(dir, name, JournalConfig::default(), storage)
+    }
+
+    /// Journal with explicit tuning
+    pub fn with_config(
+        dir: impl Into<PathBuf>,
+        name: &str,
+        config: JournalConfig,
+        storage: Option<EncryptedStorage>,
+    ) -> Self {
+        Self {
+            dir: dir.into(),
+            name: name.to_string(),
+            config,
+            storage,
+            state: Mutex::new(JournalState::default()),
+        }
+    }
+
+    /// Path of the write-ahead log
+    pub fn log_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.wal", self.name))
+    }
+
+    /// Path of the compacted snapshot
+    pub fn snapshot_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.snapshot", self.name))
+    }
+
+    /// Load the snapshot, replay the log and return the operations in flight
+    ///
+    /// The recovered state is compacted into a fresh snapshot, so torn records are
+    /// gone from disk once this returns.
+    pub async fn recover(&self) -> io::Result<JournalRecovery> {
+        let mut state = self.state.lock().await;
+        let discarded_records = self.load(&mut state).await?;
+        Ok(JournalRecovery {
+            entries: state.entries.values().cloned().collect(),
+            discarded_records,
+        })
+    }
+
+    /// Record the current state of an operation
+    pub async fn put<T: Serialize>(&self, key: &str, kind: &str, state: &T) -> io::Result<()> {
+        let entry = JournalEntry {
+            key: key.to_string(),
+            kind: kind.to_string(),
+            state: serde_json::to_value(state).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            updated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        };
+
+        let mut journal = self.state.lock().await;
+        self.append(&mut journal, &JournalRecord::Put { entry: entry.clone() }).await?;
+        journal.entries.insert(entry.key.clone(), entry);
+        self.maybe_compact(&mut journal).await
+    }
+
+    /// Forget a finished operation
+    ///
+    /// Returns whether the key was present.
+    pub async fn remove(&self, key: &str) -> io::Result<bool> {
+        let mut journal = self.state.lock().await;
+        if journal.log.is_none() {
+            self.load(&mut journal).await?;
+        }
+        if !journal.entries.contains_key(key) {
+            return Ok(false);
+        }
+
+        self.append(&mut journal, &JournalRecord::Remove { key: key.to_string() }).await?;
+        journal.entries.remove(key);
+        self.maybe_compact(&mut journal).await?;
+        Ok(true)
+    }
+
+    /// Current entry for a key
+    pub async fn get(&self, key: &str) -> Option<JournalEntry> {
+        self.state.lock().await.entries.get(key).cloned()
+    }
+
+    /// All entries of the given kind
+    pub async fn entries_of(&self, kind: &str) -> Vec<JournalEntry> {
+        self.state
+            .lock()
+            .await
+            .entries
+            .values()
+            .filter(|entry| entry.kind == kind)
+            .cloned()
+            .collect()
+    }
+
+    /// Number of operations in flight
+    pub async fn len(&self) -> usize {
+        self.state.lock().await.entries.len()
+    }
+
+    /// Whether no operations are in flight
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Fold the log into a new snapshot and truncate it
+    pub async fn compact(&self) -> io::Result<()> {
+        let mut journal = self.state.lock().await;
+        if journal.log.is_none() {
+            self.load(&mut journal).await?;
+        }
+        self.write_snapshot(&mut journal).await
+    }
+
+    async fn load(&self, journal: &mut JournalState) -> io::Result<usize> {
+        fs::create_dir_all(&self.dir).await?;
+        journal.entries.clear();
+        // Plaintext is only accepted until the first sealed snapshot is written
+        let migrating = !EncryptedStorage::is_marked_sealed(&self.snapshot_path()).await;
+
+        match fs::read(self.snapshot_path()).await {
+            Ok(bytes) => {
+                let bytes = self.open_snapshot(bytes, migrating)?;
+                let entries: Vec<JournalEntry> =
+                    serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                journal
+                    .entries
+                    .extend(entries.into_iter().map(|entry| (entry.key.clone(), entry)));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut discarded = 0;
+        match fs::File::open(self.log_path()).await {
+            Ok(file) => {
+                let mut lines = BufReader::new(file).lines();
+                let mut torn = false;
+                while let Some(line) = lines.next_line().await.or_else(|e| {
+                    // Invalid UTF-8 can only come from a torn write
+                    if e.kind() == io::ErrorKind::InvalidData { Ok(None) } else { Err(e) }
+                })? {
+                    if torn {
+                        discarded += 1;
+                        continue;
+                    }
+                    match decode_record(&line, self.storage.as_ref(), &self.storage_context(), migrating) {
+                        Some(JournalRecord::Put { entry }) => {
+                            journal.entries.insert(entry.key.clone(), entry);
+                        }
+                        Some(JournalRecord::Remove { key }) => {
+                            journal.entries.remove(&key);
+                        }
+                        None => {
+                            torn = true;
+                            discarded += 1;
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        if discarded > 0 {
+            log::warn!("Discarded {} torn records from journal {:?}", discarded, self.log_path());
+        }
+        self.write_snapshot(journal).await?;
+        Ok(discarded)
+    }
+
+    async fn write_snapshot(&self, journal: &mut JournalState) -> io::Result<()> {
+        let entries: Vec<&JournalEntry> = journal.entries.values().collect();
+        let mut bytes = serde_json::to_vec(&entries).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(storage) = &self.storage {
+            bytes = storage.seal(&bytes, &self.storage_context()).map_err(io::Error::other)?;
+        }
+        atomic_write(&self.snapshot_path(), &bytes).await?;
+        EncryptedStorage::mark_sealed(&self.snapshot_path(), self.storage.is_some()).await?;
+
+        // Records already in the snapshot are replayed harmlessly if a crash hits
+        // before the truncation below lands
+        let log = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(self.log_path())
+            .await?;
+        log.sync_all().await?;
+        journal.log = Some(log);
+        journal.records_since_compaction = 0;
+        Ok(())
+    }
+
+    async fn append(&self, journal: &mut JournalState, record: &JournalRecord) -> io::Result<()> {
+        if journal.log.is_none() {
+            self.load(journal).await?;
+        }
+        let line = encode_record(record, self.storage.as_ref(), &self.storage_context())?;
+        let log = journal.log.as_mut().expect("journal log opened by load");
+        log.write_all(line.as_bytes()).await?;
+        if self.config.sync_writes {
+            log.sync_data().await?;
+        } else {
+            log.flush().await?;
+        }
+        journal.records_since_compaction += 1;
+        Ok(())
+    }
+
+    fn storage_context(&self) -> String {
+        format!("journal:{}", self.name)
+    }
+
+    fn open_snapshot(&self, bytes: Vec<u8>, migrating: bool) -> io::Result<Vec<u8>> {
+        match &self.storage {
+            Some(storage) if migrating => storage
+                .open_unmigrated(&bytes, &self.storage_context())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Some(storage) => storage
+                .open(&bytes, &self.storage_context())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            None if EncryptedStorage::is_sealed(&bytes) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "journal snapshot is encrypted but no storage key was given",
+            )),
+            None => Ok(bytes),
+        }
+    }
+
+    async fn maybe_compact(&self, journal: &mut JournalState) -> io::Result<()> {
+        if journal.records_since_compaction >= self.config.compact_after {
+            self.write_snapshot(journal).await?;
+        }
+        Ok(())
+    }
+}
+
+/// `<crc32 hex> <json>\n`, with the JSON sealed when storage is encrypted
+fn encode_record(record: &JournalRecord, storage: Option<&EncryptedStorage>, context: &str) -> io::Result<String> {
+    let mut json = serde_json::to_string(record).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if let Some(storage) = storage {
+        json = storage.seal_text(&json, context).map_err(io::Error::other)?;
+    }
+    Ok(format!("{:08x} {}\n", crc32(json.as_bytes()), json))
+}
+
+/// Plaintext records are only accepted while `migrating`; later they are
+/// treated like torn ones
+fn decode_record(
+    line: &str,
+    storage: Option<&EncryptedStorage>,
+    context: &str,
+    migrating: bool,
+) -> Option<JournalRecord> {
+    let (checksum, json) = line.split_once(' ')?;
+    let checksum = u32::from_str_radix(checksum, 16).ok()?;
+    if checksum != crc32(json.as_bytes()) {
+        return None;
+    }
+    match storage {
+        Some(storage) if migrating => serde_json::from_str(&storage.open_text_unmigrated(json, context).ok()?).ok(),
+        Some(storage) => serde_json::from_str(&storage.open_text(json, context).ok()?).ok(),
+        None => serde_json::from_str(json).ok(),
+    }
+}
+
+/// CRC-32 (IEEE 802.3)
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Progress {
+        bytes: u64,
+    }
+
+    #[test]
+    fn test_crc32_reference_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[tokio::test]
+    async fn test_replay_after_restart() {
+        let dir = TempDir::new().unwrap();
+        {
+            let journal = Journal::new(dir.path(), "ops", None);
+            journal.put("a", "transfer", &Progress { bytes: 10 }).await.unwrap();
+            journal.put("a", "transfer", &Progress { bytes: 20 }).await.unwrap();
+            journal.put("b", "queue", &Progress { bytes: 1 }).await.unwrap();
+            assert!(journal.remove("b").await.unwrap());
+        }
+
+        let journal = Journal::new(dir.path(), "ops", None);
+        let recovery = journal.recover().await.unwrap();
+        assert_eq!(recovery.discarded_records, 0);
+        assert_eq!(recovery.entries.len(), 1);
+        assert_eq!(recovery.entries[0].decode::<Progress>().unwrap(), Progress { bytes: 20 });
+    }
+
+    #[tokio::test]
+    async fn test_torn_tail_discarded() {
+        let dir = TempDir::new().unwrap();
+        {
+            let journal = Journal::new(dir.path(), "ops", None);
+            journal.put("a", "transfer", &Progress { bytes: 10 }).await.unwrap();
+            journal.put("a", "transfer", &Progress { bytes: 20 }).await.unwrap();
+        }
+
+        // Simulate a crash halfway through writing the last record
+        let log_path = dir.path().join("ops.wal");
+        let log = std::fs::read(&log_path).unwrap();
+        std::fs::write(&log_path, &log[..log.len() - 8]).unwrap();
+
+        let journal = Journal::new(dir.path(), "ops", None);
+        let recovery = journal.recover().await.unwrap();
+        assert_eq!(recovery.discarded_records, 1);
+        assert_eq!(recovery.entries[0].decode::<Progress>().unwrap(), Progress { bytes: 10 });
+
+        // Recovery rewrote the journal, so the torn record is gone for good
+        let recovery = Journal::new(dir.path(), "ops", None).recover().await.unwrap();
+        assert_eq!(recovery.discarded_records, 0);
+    }
+
+    #[tokio::test]
+    async fn test_compaction_truncates_log() {
+        let dir = TempDir::new().unwrap();
+        let config = JournalConfig {
+            compact_after: 4,
+            sync_writes: false,
+        };
+        let journal = Journal::with_config(dir.path(), "ops", config, None);
+        for bytes in 0..10 {
+            journal.put("a", "transfer", &Progress { bytes }).await.unwrap();
+        }
+        assert!(std::fs::metadata(journal.log_path()).unwrap().len() < 300);
+
+        let recovery = Journal::new(dir.path(), "ops", None).recover().await.unwrap();
+        assert_eq!(recovery.entries[0].decode::<Progress>().unwrap(), Progress { bytes: 9 });
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_journal_encrypted_on_recovery() {
+        use crate::security::storage::StorageKey;
+
+        let dir = TempDir::new().unwrap();
+        let storage = EncryptedStorage::new(StorageKey::from_bytes([3; 32]));
+        {
+            let journal = Journal::new(dir.path(), "ops", None);
+            journal.put("a", "transfer", &Progress { bytes: 10 }).await.unwrap();
+        }
+
+        let journal = Journal::new(dir.path(), "ops", Some(storage.clone()));
+        let recovery = journal.recover().await.unwrap();
+        assert_eq!(recovery.entries[0].decode::<Progress>().unwrap(), Progress { bytes: 10 });
+        assert!(EncryptedStorage::is_sealed(&std::fs::read(journal.snapshot_path()).unwrap()));
+
+        journal.put("a", "transfer", &Progress { bytes: 20 }).await.unwrap();
+        let log = std::fs::read_to_string(journal.log_path()).unwrap();
+        assert!(!log.contains("transfer"));
+
+        let recovery = Journal::new(dir.path(), "ops", Some(storage.clone())).recover().await.unwrap();
+        assert_eq!(recovery.entries[0].decode::<Progress>().unwrap(), Progress { bytes: 20 });
+        assert!(Journal::new(dir.path(), "ops", None).recover().await.is_err());
+
+        // Once sealed, a plaintext snapshot planted in its place is refused
+        std::fs::write(journal.snapshot_path(), b"[]").unwrap();
+        assert!(Journal::new(dir.path(), "ops", Some(storage)).recover().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_atomic_write_replaces_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.json");
+        atomic_write(&path, b"old").await.unwrap();
+        atomic_write(&path, b"new").await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        assert!(!dir.path().join("state.json.tmp").exists());
+    }
+}
@@ -1,6 +1,10 @@
+pub mod error;
+pub mod error_aggregator;
 pub mod discovery;
 pub mod transport;
+#[cfg(feature = "browser-support")]
 pub mod browser_support;
+#[cfg(feature = "clipboard")]
 pub mod clipboard;
 pub mod security;
 pub mod file_transfer;
@@ -10,22 +14,80 @@ pub mod streaming;
 pub mod cli;
 pub mod command_execution;
 pub mod platform;
+pub mod testing;
+pub mod shutdown;
+pub mod supervisor;
+pub mod daemon;
+pub mod doctor;
+pub mod forwarding;
+pub mod journal;
+pub mod liveness;
+pub mod lockdown;
+pub mod maintenance;
+pub mod metered;
+pub mod messaging;
+pub mod remote_fs;
+pub mod retention;
+pub mod util;
 
 pub use discovery::*;
 pub use transport::*;
+#[cfg(feature = "browser-support")]
 pub use browser_support::*;
+#[cfg(feature = "clipboard")]
 pub use clipboard::*;
 pub use security::*;
 pub use file_transfer::*;
-pub use developer_api::{KizunaAPI, KizunaInstance, KizunaConfig, KizunaError, KizunaEvent};
+pub use error::{KizunaError, KizunaResult, ErrorCode, ErrorReport};
+pub use error_aggregator::{AggregatorConfig, Disposition, ErrorAggregator, ErrorSummary};
+pub use doctor::{
+    CheckResult, CheckStatus, Doctor, DoctorConfig, DoctorReport, PeerTarget, PortProtocol, PortRequirement,
+};
+pub use forwarding::{
+    ForwardConfig, ForwardError, ForwardHandle, ForwardRequest, ForwardResponse, ForwardTarget, PortForwarder,
+};
+pub use journal::{Journal, JournalConfig, JournalEntry, JournalRecovery};
+pub use liveness::{LoadHints, Liveness, PingError, PingReply, PingRequest, PingResponse, PingResult};
+pub use lockdown::{
+    LockedDown, Lockdown, LockdownConfig, LockdownState, LockdownTrigger, RemoteCapability,
+};
+pub use metered::{
+    ConnectionCost, Initiator, MeteredDetector, MeteredSource, TrafficDecision, TrafficMonitor,
+    TrafficOperation, TrafficPolicy, TrafficPolicyConfig,
+};
+pub use messaging::{
+    BroadcastMessage, BroadcastReport, ChatMessage, MessageStatus, MessagingConfig, MessagingError, MessagingEvent, MessagingResult,
+    MessagingSystem,
+};
+pub use remote_fs::{
+    BrowseConfig, BrowseError, BrowseResult, DirectoryListing, FetchHandler, MountConfig, MountDriver,
+    MountSession, RemoteEntry, RemoteFileSystem, RemoteMount, RemoteSpec, ShareAccess, ShareConfig,
+    ShareOperation, ShareRoot,
+};
+#[cfg(all(unix, feature = "fuse"))]
+pub use remote_fs::FuseDriver;
+#[cfg(all(windows, feature = "projfs"))]
+pub use remote_fs::ProjFsDriver;
+pub use retention::{
+    DataCategory, RemoteWipe, RemoteWipeError, RemoteWipeRequest, RetentionConfig, RetentionEngine,
+    RetentionReport, RetentionTarget,
+};
+pub use shutdown::{
+    ShutdownCoordinator, ShutdownConfig, ShutdownHook, ShutdownReason, ShutdownReport, ShutdownStage,
+};
+pub use supervisor::{
+    ComponentHealth, ComponentState, EscalationCallback, HealthReport, Supervisor, SupervisorConfig,
+};
+pub use developer_api::{KizunaAPI, KizunaInstance, KizunaInstanceBuilder, KizunaConfig, KizunaEvent};
 pub use cli::{CLIConfig, CLIError, CLIResult};
 
 // Command execution exports (avoid glob to prevent ambiguous re-exports)
 pub use command_execution::{
-    CommandManager, SandboxEngine, AuthorizationManager, ScriptEngine,
     CommandError, CommandRequest, CommandResult as CmdExecutionResult,
     ScriptRequest, ScriptResult, SystemInfo, Notification,
 };
+#[cfg(feature = "command-execution")]
+pub use command_execution::{CommandManager, SandboxEngine, AuthorizationManager, ScriptEngine};
 
 // Platform exports
 pub use platform::{
@@ -35,4 +97,4 @@ pub use platform::{
 };
 
 /// Common result type for Kizuna operations
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, KizunaError>;
\ No newline at end of file
@@ -0,0 +1,451 @@
+//! Authenticated application-level ping
+//!
+//! A transport-level connect only shows that something is listening. A
+//! [`PingRequest`] travels over the `ping` channel of a peer's session and is
+//! answered by the Kizuna process itself with a [`PingReply`] carrying its
+//! software and protocol version and a few [`LoadHints`].
+//!
+//! Both directions are signed with the device identity. The responder only
+//! answers peers it trusts, and the reply signs the requester's nonce, so a
+//! reply cannot be replayed or forged by anything between the two devices.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::security::identity::{DeviceIdentity, PeerId};
+use crate::security::trust::{TrustManager, TrustManagerImpl};
+use crate::transport::handshake::ProtocolHello;
+use crate::transport::{ConnectionProvider, MuxStream, PeerAddress, CHANNEL_PING};
+
+/// Largest ping message accepted in either direction
+const MAX_PING_MESSAGE_LEN: usize = 8192;
+
+/// Length of the random nonce a reply has to sign
+const NONCE_LEN: usize = 16;
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Check an Ed25519 signature and that the key belongs to `signer`
+fn verify_signature(signer: &PeerId, key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    let Ok(key_bytes) = <[u8; 32]>::try_from(key) else {
+        return false;
+    };
+    let Ok(signature_bytes) = <[u8; 64]>::try_from(signature) else {
+        return false;
+    };
+    let Ok(key) = VerifyingKey::from_bytes(&key_bytes) else {
+        return false;
+    };
+    PeerId::from_public_key(&key) == *signer && key.verify(message, &Signature::from_bytes(&signature_bytes)).is_ok()
+}
+
+/// How busy the answering device is
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoadHints {
+    /// Peer sessions currently open
+    pub active_sessions: usize,
+    /// Streams open across those sessions
+    pub open_streams: usize,
+    /// One-minute load average, where the platform reports one
+    pub load_average: Option<f64>,
+    /// Logical CPUs available to the process
+    pub cpu_count: usize,
+}
+
+impl LoadHints {
+    /// Sample this device's load
+    pub fn collect(provider: &ConnectionProvider) -> Self {
+        let stats = provider.stats();
+        let load = sysinfo::System::load_average();
+        Self {
+            active_sessions: stats.active_sessions,
+            open_streams: stats.open_streams,
+            // Platforms without a load average report zero
+            load_average: (load.one > 0.0).then_some(load.one),
+            cpu_count: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        }
+    }
+
+    /// Whether the device has more runnable work than CPUs
+    pub fn is_busy(&self) -> bool {
+        self.load_average
+            .is_some_and(|load| load > self.cpu_count.max(1) as f64)
+    }
+}
+
+/// Signed ping from the device asking
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PingRequest {
+    pub issuer: PeerId,
+    /// Public key of the issuer, for verification
+    pub issuer_key: Vec<u8>,
+    /// Random value the reply has to sign
+    pub nonce: Vec<u8>,
+    /// Unix time the ping was sent
+    pub issued_at: u64,
+    pub signature: Vec<u8>,
+}
+
+impl PingRequest {
+    /// Pings older than this are refused, so a captured one cannot be replayed later
+    pub const MAX_AGE_SECS: u64 = 300;
+
+    pub fn new(identity: &DeviceIdentity) -> Self {
+        let mut request = Self {
+            issuer: identity.derive_peer_id(),
+            issuer_key: identity.public_key().as_bytes().to_vec(),
+            nonce: rand::random::<[u8; NONCE_LEN]>().to_vec(),
+            issued_at: unix_now(),
+            signature: Vec::new(),
+        };
+        request.signature = identity.sign(&request.signed_bytes()).to_vec();
+        request
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(b"kizuna-ping-v1");
+        message.extend_from_slice(self.issuer.fingerprint());
+        message.extend_from_slice(&self.nonce);
+        message.extend_from_slice(&self.issued_at.to_le_bytes());
+        message
+    }
+
+    /// Check the signature and that the key belongs to the issuer
+    pub fn verify(&self) -> bool {
+        verify_signature(&self.issuer, &self.issuer_key, &self.signed_bytes(), &self.signature)
+    }
+}
+
+/// Signed answer to a ping
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PingReply {
+    pub responder: PeerId,
+    /// Public key of the responder, for verification
+    pub responder_key: Vec<u8>,
+    /// Nonce from the request being answered
+    pub nonce: Vec<u8>,
+    /// Software version, e.g. "kizuna/0.1.0"
+    pub software_version: String,
+    pub protocol_major: u8,
+    pub protocol_minor: u8,
+    pub load: LoadHints,
+    pub signature: Vec<u8>,
+}
+
+impl PingReply {
+    pub fn new(identity: &DeviceIdentity, request: &PingRequest, load: LoadHints) -> Self {
+        let hello = ProtocolHello::local();
+        let mut reply = Self {
+            responder: identity.derive_peer_id(),
+            responder_key: identity.public_key().as_bytes().to_vec(),
+            nonce: request.nonce.clone(),
+            software_version: hello.software_version,
+            protocol_major: hello.protocol_major,
+            protocol_minor: hello.protocol_minor,
+            load,
+            signature: Vec::new(),
+        };
+        reply.signature = identity.sign(&reply.signed_bytes()).to_vec();
+        reply
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(b"kizuna-pong-v1");
+        message.extend_from_slice(self.responder.fingerprint());
+        message.extend_from_slice(&self.nonce);
+        message.push(self.protocol_major);
+        message.push(self.protocol_minor);
+        message.extend_from_slice(&(self.software_version.len() as u32).to_le_bytes());
+        message.extend_from_slice(self.software_version.as_bytes());
+        message.extend_from_slice(&serde_json::to_vec(&self.load).unwrap_or_default());
+        message
+    }
+
+    /// Check the signature and that the key belongs to the responder
+    pub fn verify(&self) -> bool {
+        verify_signature(&self.responder, &self.responder_key, &self.signed_bytes(), &self.signature)
+    }
+
+    /// Protocol version as "major.minor"
+    pub fn protocol_version(&self) -> String {
+        format!("{}.{}", self.protocol_major, self.protocol_minor)
+    }
+}
+
+/// What a responder sends back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PingResponse {
+    Pong(PingReply),
+    Refused { reason: String },
+}
+
+/// Why a ping did not produce a verified reply
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PingError {
+    #[error("Peer is unreachable: {0}")]
+    Unreachable(String),
+    #[error("No reply within {0:?}")]
+    Timeout(Duration),
+    #[error("Peer refused the ping: {0}")]
+    Refused(String),
+    #[error("Reply failed verification: {0}")]
+    InvalidReply(String),
+    #[error("Ping failed: {0}")]
+    Failed(String),
+}
+
+impl PingError {
+    /// Whether the peer's Kizuna process answered at all
+    pub fn peer_responded(&self) -> bool {
+        matches!(self, PingError::Refused(_) | PingError::InvalidReply(_))
+    }
+}
+
+/// Outcome of a successful ping
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PingResult {
+    /// Full peer ID of the device that answered
+    pub peer_id: String,
+    /// Time from sending the ping to receiving the reply
+    pub rtt_ms: f64,
+    pub software_version: String,
+    pub protocol_version: String,
+    pub load: LoadHints,
+    /// Whether the responder is in this device's trust database
+    pub trusted: bool,
+}
+
+/// Sends and answers authenticated pings
+#[derive(Clone)]
+pub struct Liveness {
+    provider: Arc<ConnectionProvider>,
+    trust: Arc<TrustManagerImpl>,
+    identity: Arc<DeviceIdentity>,
+}
+
+impl Liveness {
+    pub fn new(provider: Arc<ConnectionProvider>, trust: Arc<TrustManagerImpl>, identity: Arc<DeviceIdentity>) -> Self {
+        Self { provider, trust, identity }
+    }
+
+    /// Ping a peer and verify its reply
+    ///
+    /// If `peer.peer_id` is a full peer ID the reply must be signed by that
+    /// device; otherwise whichever device answered is reported.
+    pub async fn ping(&self, peer: &PeerAddress, timeout: Duration) -> Result<PingResult, PingError> {
+        let request = PingRequest::new(&self.identity);
+        let bytes = serde_json::to_vec(&request).map_err(|e| PingError::Failed(e.to_string()))?;
+
+        let exchange = async {
+            let stream = self
+                .provider
+                .open_stream(peer, CHANNEL_PING)
+                .await
+                .map_err(|e| PingError::Unreachable(e.to_string()))?;
+
+            // Only the request and reply are timed, not setting up the session
+            let started = Instant::now();
+            let reply = match stream.send_message(&bytes).await {
+                Ok(()) => stream.recv_message(MAX_PING_MESSAGE_LEN).await,
+                Err(e) => Err(e),
+            };
+            let rtt = started.elapsed();
+            let _ = stream.close();
+
+            match reply {
+                Ok(Some(reply)) => Ok((reply, rtt)),
+                Ok(None) => Err(PingError::Unreachable("Peer closed the ping stream".to_string())),
+                Err(e) => Err(PingError::Unreachable(e.to_string())),
+            }
+        };
+        let (reply, rtt) = tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| PingError::Timeout(timeout))??;
+
+        let reply = match serde_json::from_slice::<PingResponse>(&reply) {
+            Ok(PingResponse::Pong(reply)) => reply,
+            Ok(PingResponse::Refused { reason }) => return Err(PingError::Refused(reason)),
+            Err(e) => return Err(PingError::InvalidReply(format!("Malformed reply: {}", e))),
+        };
+        if reply.nonce != request.nonce {
+            return Err(PingError::InvalidReply("Reply is for another ping".to_string()));
+        }
+        if !reply.verify() {
+            return Err(PingError::InvalidReply("Signature is invalid".to_string()));
+        }
+        if let Ok(expected) = PeerId::from_string(&peer.peer_id) && expected != reply.responder {
+            return Err(PingError::InvalidReply(format!(
+                "Answered by {} instead of {}",
+                reply.responder, expected
+            )));
+        }
+
+        let trusted = self.trust.is_trusted(&reply.responder).await.unwrap_or(false);
+        Ok(PingResult {
+            peer_id: reply.responder.to_string(),
+            rtt_ms: rtt.as_secs_f64() * 1000.0,
+            protocol_version: reply.protocol_version(),
+            software_version: reply.software_version,
+            load: reply.load,
+            trusted,
+        })
+    }
+
+    /// Decide how to answer a ping that arrived from `peer_id`
+    pub async fn answer(&self, peer_id: &str, request: &PingRequest) -> PingResponse {
+        let refuse = |reason: &str| PingResponse::Refused { reason: reason.to_string() };
+
+        if !request.verify() {
+            return refuse("Ping signature is invalid");
+        }
+        if request.issuer.to_string() != peer_id {
+            return refuse("Ping was relayed for another device");
+        }
+        if unix_now().abs_diff(request.issued_at) > PingRequest::MAX_AGE_SECS {
+            return refuse("Ping is too old or from the future; check the clocks");
+        }
+        if !self.trust.is_trusted(&request.issuer).await.unwrap_or(false) {
+            return refuse("This device does not trust you");
+        }
+
+        PingResponse::Pong(PingReply::new(&self.identity, request, LoadHints::collect(&self.provider)))
+    }
+
+    /// Answer a ping arriving on a stream
+    pub fn attach_stream(&self, peer_id: &str, stream: MuxStream) -> Result<(), PingError> {
+        if stream.channel() != CHANNEL_PING {
+            return Err(PingError::Failed(format!(
+                "Stream on channel {} is not a ping stream",
+                stream.channel()
+            )));
+        }
+
+        let liveness = self.clone();
+        let peer_id = peer_id.to_string();
+        tokio::spawn(async move {
+            let request = match stream.recv_message(MAX_PING_MESSAGE_LEN).await {
+                Ok(Some(bytes)) => serde_json::from_slice::<PingRequest>(&bytes).ok(),
+                _ => None,
+            };
+            let Some(request) = request else {
+                log::debug!("Malformed ping from {}", peer_id);
+                let _ = stream.close();
+                return;
+            };
+
+            let response = liveness.answer(&peer_id, &request).await;
+            if let PingResponse::Refused { reason } = &response {
+                log::info!("Refused ping from {}: {}", peer_id, reason);
+            }
+            if let Ok(bytes) = serde_json::to_vec(&response) {
+                let _ = stream.send_message(&bytes).await;
+            }
+            let _ = stream.close();
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::transport::MEMORY_PROTOCOL;
+    use crate::testing::{MemoryTransport, VirtualNetwork};
+    use crate::transport::{PeerDialer, ProviderConfig, Transport};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ping_signatures() {
+        let alice = DeviceIdentity::generate().unwrap();
+        let bob = DeviceIdentity::generate().unwrap();
+
+        let request = PingRequest::new(&alice);
+        assert!(request.verify());
+        let mut forged = request.clone();
+        forged.issuer = bob.derive_peer_id();
+        assert!(!forged.verify());
+
+        let reply = PingReply::new(&bob, &request, LoadHints::default());
+        assert!(reply.verify());
+        let mut tampered = reply.clone();
+        tampered.software_version = "kizuna/9.9.9".to_string();
+        assert!(!tampered.verify());
+    }
+
+    #[tokio::test]
+    async fn test_ping_trusted_and_untrusted_peers() {
+        let alice_identity = Arc::new(DeviceIdentity::generate().unwrap());
+        let bob_identity = Arc::new(DeviceIdentity::generate().unwrap());
+        let alice_id = alice_identity.derive_peer_id().to_hex();
+        let bob_id = bob_identity.derive_peer_id().to_hex();
+
+        let network = VirtualNetwork::new();
+        let alice_transport = Arc::new(MemoryTransport::new(alice_id.clone(), network.clone()));
+        let bob_transport = Arc::new(MemoryTransport::new(bob_id.clone(), network.clone()));
+        let addr = network.allocate_address(43100);
+        bob_transport.listen(&addr).await.unwrap();
+        let bob_addr = PeerAddress::new(
+            bob_id.clone(),
+            vec![addr],
+            vec![MEMORY_PROTOCOL.to_string()],
+            bob_transport.capabilities(),
+        );
+
+        let (alice_dir, bob_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let alice_trust = Arc::new(TrustManagerImpl::new(alice_dir.path().join("trust.db")).unwrap());
+        let bob_trust = Arc::new(TrustManagerImpl::new(bob_dir.path().join("trust.db")).unwrap());
+        alice_trust
+            .add_trusted_peer(bob_identity.derive_peer_id(), "bob".to_string())
+            .await
+            .unwrap();
+
+        let alice = Liveness::new(
+            Arc::new(ConnectionProvider::new(alice_transport, ProviderConfig::default())),
+            alice_trust,
+            alice_identity,
+        );
+        let bob_provider = Arc::new(ConnectionProvider::new(
+            Arc::clone(&bob_transport) as Arc<dyn PeerDialer>,
+            ProviderConfig::default(),
+        ));
+        let bob = Liveness::new(Arc::clone(&bob_provider), Arc::clone(&bob_trust), bob_identity);
+
+        let serve = {
+            let alice_id = alice_id.clone();
+            tokio::spawn(async move {
+                let session = bob_provider.adopt(Box::new(bob_transport.accept().await.unwrap())).await.unwrap();
+                while let Some(stream) = session.accept_stream().await {
+                    bob.attach_stream(&alice_id, stream).unwrap();
+                }
+            })
+        };
+
+        // Bob has not paired with Alice yet
+        let err = alice.ping(&bob_addr, Duration::from_secs(5)).await.unwrap_err();
+        assert!(matches!(err, PingError::Refused(_)));
+        assert!(err.peer_responded());
+
+        bob_trust
+            .add_trusted_peer(PeerId::from_string(&alice_id).unwrap(), "alice".to_string())
+            .await
+            .unwrap();
+        let result = alice.ping(&bob_addr, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(result.peer_id, bob_id);
+        assert!(result.trusted);
+        assert_eq!(result.software_version, ProtocolHello::local().software_version);
+        assert!(result.load.active_sessions >= 1);
+
+        serve.abort();
+    }
+}
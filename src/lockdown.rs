@@ -0,0 +1,288 @@
+//! Lockdown of inbound remote access
+//!
+//! A panic switch for when something looks wrong. While the [`Lockdown`] is
+//! engaged, peers cannot write to the clipboard, run commands or open URLs
+//! and files on this device, watch and control its screen, or forward
+//! connections to its local services. Outbound features keep working, so
+//! the user can still see what is going on.
+//!
+//! The switch is usually thrown with a global hotkey and only lifted from
+//! the TUI or the API; pressing the hotkey again keeps it engaged. Stream
+//! viewers and control grants admitted before the lockdown stay revoked
+//! after it is lifted, so they have to ask again.
+//!
+//! The state is process-wide ([`Lockdown::global`]) so every subsystem
+//! checks the same switch.
+
+use crate::platform::{DefaultPlatformManager, Hotkey, PlatformError, PlatformManager, PlatformResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+use tokio::sync::watch;
+
+/// Hotkey that engages the lockdown unless configured otherwise
+pub const DEFAULT_LOCKDOWN_HOTKEY: &str = "Ctrl+Alt+Shift+K";
+
+static GLOBAL_LOCKDOWN: OnceLock<Lockdown> = OnceLock::new();
+
+/// Something peers can do to this device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteCapability {
+    ClipboardWrite,
+    CommandExecution,
+    /// Opening URLs and files handed off by a peer
+    Handoff,
+    StreamViewing,
+    RemoteControl,
+    /// Connections forwarded to local ports and sockets
+    PortForwarding,
+}
+
+impl RemoteCapability {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            RemoteCapability::ClipboardWrite => "clipboard writes",
+            RemoteCapability::CommandExecution => "command execution",
+            RemoteCapability::Handoff => "URL and file handoff",
+            RemoteCapability::StreamViewing => "stream viewing",
+            RemoteCapability::RemoteControl => "remote control",
+            RemoteCapability::PortForwarding => "port forwarding",
+        }
+    }
+}
+
+/// What engaged the lockdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockdownTrigger {
+    Hotkey,
+    /// The TUI, CLI or API
+    User,
+}
+
+/// Current lockdown state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockdownState {
+    pub engaged: bool,
+    /// When the lockdown was last engaged, even if it has been lifted since
+    pub engaged_at: Option<SystemTime>,
+    pub trigger: Option<LockdownTrigger>,
+}
+
+impl LockdownState {
+    /// Whether something admitted at `admitted_at` may still act
+    ///
+    /// Nothing is admitted while engaged, and admissions from before the
+    /// latest lockdown stay revoked after it is lifted.
+    pub fn admits(&self, admitted_at: SystemTime) -> bool {
+        !self.engaged && self.engaged_at.is_none_or(|engaged_at| admitted_at > engaged_at)
+    }
+
+    /// One-line summary for status output
+    pub fn describe(&self) -> String {
+        match (self.engaged, self.engaged_at) {
+            (true, Some(engaged_at)) => format!(
+                "Remote access locked down since {}{}",
+                chrono::DateTime::<chrono::Local>::from(engaged_at).format("%H:%M:%S"),
+                if self.trigger == Some(LockdownTrigger::Hotkey) { " (hotkey)" } else { "" }
+            ),
+            (true, None) => "Remote access locked down".to_string(),
+            (false, _) => "Remote access allowed".to_string(),
+        }
+    }
+}
+
+/// A remote capability refused because of the lockdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockedDown(pub RemoteCapability);
+
+impl fmt::Display for LockedDown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Remote {} is blocked while this device is locked down", self.0.describe())
+    }
+}
+
+impl std::error::Error for LockedDown {}
+
+/// Lockdown settings, part of the user configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockdownConfig {
+    /// Register the global hotkey
+    pub hotkey_enabled: bool,
+    /// Key combination, e.g. "Ctrl+Alt+Shift+K"
+    pub hotkey: String,
+}
+
+impl Default for LockdownConfig {
+    fn default() -> Self {
+        Self {
+            hotkey_enabled: true,
+            hotkey: DEFAULT_LOCKDOWN_HOTKEY.to_string(),
+        }
+    }
+}
+
+/// The lockdown switch
+pub struct Lockdown {
+    state: watch::Sender<LockdownState>,
+}
+
+impl Lockdown {
+    pub fn new() -> Self {
+        let (state, _) = watch::channel(LockdownState::default());
+        Self { state }
+    }
+
+    /// The switch every subsystem checks
+    pub fn global() -> &'static Lockdown {
+        GLOBAL_LOCKDOWN.get_or_init(Lockdown::new)
+    }
+
+    pub fn state(&self) -> LockdownState {
+        *self.state.borrow()
+    }
+
+    pub fn is_engaged(&self) -> bool {
+        self.state.borrow().engaged
+    }
+
+    /// Watch for the lockdown being engaged or lifted
+    pub fn subscribe(&self) -> watch::Receiver<LockdownState> {
+        self.state.subscribe()
+    }
+
+    /// Refuse all inbound remote access; returns false if already engaged
+    pub fn engage(&self, trigger: LockdownTrigger) -> bool {
+        self.state.send_if_modified(|state| {
+            if state.engaged {
+                return false;
+            }
+            log::warn!("Lockdown engaged; refusing all inbound remote access");
+            *state = LockdownState {
+                engaged: true,
+                engaged_at: Some(SystemTime::now()),
+                trigger: Some(trigger),
+            };
+            true
+        })
+    }
+
+    /// Allow inbound remote access again; returns false if not engaged
+    pub fn release(&self) -> bool {
+        self.state.send_if_modified(|state| {
+            if !state.engaged {
+                return false;
+            }
+            log::info!("Lockdown lifted");
+            state.engaged = false;
+            true
+        })
+    }
+
+    /// Engage if lifted, lift if engaged; returns whether it is now engaged
+    pub fn toggle(&self, trigger: LockdownTrigger) -> bool {
+        if !self.release() {
+            self.engage(trigger);
+        }
+        self.is_engaged()
+    }
+
+    /// Refuse `capability` while engaged
+    pub fn check(&self, capability: RemoteCapability) -> Result<(), LockedDown> {
+        if self.is_engaged() {
+            return Err(LockedDown(capability));
+        }
+        Ok(())
+    }
+
+    /// Refuse `capability` for something admitted before the latest lockdown
+    pub fn check_admitted(&self, capability: RemoteCapability, admitted_at: SystemTime) -> Result<(), LockedDown> {
+        if !self.state().admits(admitted_at) {
+            return Err(LockedDown(capability));
+        }
+        Ok(())
+    }
+
+    /// Register the configured hotkey through the platform adapter
+    ///
+    /// Returns the hotkey that was registered, or `None` if the hotkey is
+    /// turned off in the config.
+    pub fn arm_hotkey(&'static self, config: &LockdownConfig) -> PlatformResult<Option<Hotkey>> {
+        if !config.hotkey_enabled {
+            return Ok(None);
+        }
+
+        let hotkey: Hotkey = config.hotkey.parse()?;
+        let hotkeys = DefaultPlatformManager::new()?
+            .get_platform_adapter()?
+            .global_hotkeys()
+            .ok_or_else(|| {
+                PlatformError::FeatureUnavailable(format!(
+                    "Global hotkeys; bind {} to the lockdown in your desktop settings instead",
+                    hotkey
+                ))
+            })?;
+        hotkeys.register(&hotkey, Arc::new(move || {
+            self.engage(LockdownTrigger::Hotkey);
+        }))?;
+
+        log::info!("Press {} to lock down remote access", hotkey);
+        Ok(Some(hotkey))
+    }
+}
+
+impl Default for Lockdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lockdown_refuses_until_released() {
+        let lockdown = Lockdown::new();
+        let mut changes = lockdown.subscribe();
+        assert!(lockdown.check(RemoteCapability::ClipboardWrite).is_ok());
+
+        assert!(lockdown.engage(LockdownTrigger::Hotkey));
+        assert!(!lockdown.engage(LockdownTrigger::Hotkey));
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(
+            lockdown.check(RemoteCapability::CommandExecution),
+            Err(LockedDown(RemoteCapability::CommandExecution))
+        );
+        assert!(lockdown.state().describe().ends_with("(hotkey)"));
+
+        assert!(lockdown.release());
+        assert!(lockdown.check(RemoteCapability::CommandExecution).is_ok());
+        assert!(lockdown.toggle(LockdownTrigger::User));
+        assert!(!lockdown.toggle(LockdownTrigger::User));
+    }
+
+    #[test]
+    fn test_earlier_admissions_stay_revoked() {
+        let lockdown = Lockdown::new();
+        let before = SystemTime::now() - Duration::from_secs(1);
+
+        lockdown.engage(LockdownTrigger::User);
+        lockdown.release();
+
+        assert!(lockdown.check_admitted(RemoteCapability::StreamViewing, before).is_err());
+        let after = SystemTime::now() + Duration::from_millis(1);
+        assert!(lockdown.check_admitted(RemoteCapability::StreamViewing, after).is_ok());
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: LockdownConfig = serde_json::from_str("{}").unwrap();
+        assert!(config.hotkey_enabled);
+        assert!(config.hotkey.parse::<Hotkey>().is_ok());
+    }
+}
@@ -1,5 +1,7 @@
 use anyhow::Result;
+use clap::error::ErrorKind;
 use std::env;
+use std::sync::Arc;
 use std::time::Duration;
 
 // Use the library's discovery module instead of re-declaring it
@@ -8,10 +10,35 @@ use kizuna::discovery::{
     DiscoveryConfigFile, discovery_selector,
     strategies::{udp::UdpDiscovery, mdns::MdnsDiscovery},
 };
+use kizuna::cli::parser::{ClapCommandParser, CommandExecutor};
+use kizuna::cli::pipeline::PipelineOutput;
+use kizuna::cli::types::{CommandOutput, OutputFormat};
+use kizuna::daemon::{Daemon, DaemonConfig};
+use kizuna::security::policy::DiscoveryVisibility;
+use kizuna::shutdown::ShutdownCoordinator;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
+
+    // Ctrl-C or SIGTERM runs registered shutdown hooks within the grace period
+    // instead of killing the process outright; a second signal forces exit
+    let coordinator = Arc::new(ShutdownCoordinator::default());
+    coordinator.listen_for_signals();
+
+    tokio::select! {
+        result = run(&args, &coordinator) => result,
+        report = coordinator.run() => {
+            if !report.is_clean() {
+                eprintln!("Shutdown incomplete after {:?}: {:?}", report.elapsed, report);
+            }
+            std::process::exit(report.exit_code());
+        }
+    }
+}
+
+/// Dispatch a command line
+async fn run(args: &[String], coordinator: &Arc<ShutdownCoordinator>) -> Result<()> {
     let command = args.get(1).map(|s| s.as_str()).unwrap_or("help");
 
     match command {
@@ -37,8 +64,19 @@ async fn main() -> Result<()> {
             let strategies = parse_arg(&args, "--strategies")
                 .map(|s| s.split(',').map(|s| s.trim().to_string()).collect());
             let duration = parse_arg(&args, "--duration").and_then(|s| s.parse().ok());
+            let visibility = DiscoveryVisibility {
+                stealth: args.contains(&"--stealth".to_string()),
+                announce_windows: parse_all_args(&args, "--window")
+                    .into_iter()
+                    .map(|w| w.parse())
+                    .collect::<Result<_, String>>()
+                    .map_err(|e| anyhow::anyhow!("{}", e))?,
+                ..Default::default()
+            };
+            
+            let respond = args.contains(&"--respond".to_string());
             
-            DiscoveryCli::announce(name, port, strategies, duration).await.map_err(|e| anyhow::anyhow!("{}", e))?;
+            DiscoveryCli::announce(name, port, strategies, duration, visibility, respond).await.map_err(|e| anyhow::anyhow!("{}", e))?;
         }
         "test-strategy" => {
             let strategy = args.get(2).ok_or_else(|| anyhow::anyhow!("Strategy name required"))?.to_string();
@@ -87,6 +125,33 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        "daemon" => {
+            let mut config = DaemonConfig::default();
+            if let Some(name) = parse_arg(args, "--name") {
+                config.device_name = name.to_string();
+            }
+            if let Some(port) = parse_arg(args, "--port").and_then(|s| s.parse().ok()) {
+                config.port = port;
+            }
+            if let Some(port) = parse_arg(args, "--health-port").and_then(|s| s.parse().ok()) {
+                config.health_port = Some(port);
+            }
+            if args.contains(&"--no-health".to_string()) {
+                config.health_port = None;
+            }
+
+            let name = config.device_name.clone();
+            let daemon = Daemon::start(Arc::clone(coordinator), config)
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            println!(
+                "Kizuna daemon running as '{}' on port {}; press Ctrl+C to stop",
+                name,
+                daemon.port()
+            );
+            // Shutdown exits the process once its hooks and tasks are done
+            std::future::pending::<()>().await;
+        }
         "help" | "--help" | "-h" => {
             print_help();
         }
@@ -130,19 +195,50 @@ async fn main() -> Result<()> {
                 Err(e) => eprintln!("mDNS browse failed: {}", e),
             }
         }
-        "auto" | _ => {
-            // Default behavior - show help for unknown commands
-            if command != "auto" {
-                println!("Unknown command: {}", command);
-                println!();
-            }
+        "auto" => {
             print_help();
         }
+
+        // Everything else goes through the full command-line interface
+        _ => {
+            let exit_code = run_cli(args).await;
+            if exit_code != 0 {
+                std::process::exit(exit_code);
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Parse, validate and run a command with the CLI, returning the exit code
+async fn run_cli(args: &[String]) -> i32 {
+    // Let clap print --help and --version itself, they are not errors
+    let parser = ClapCommandParser::new();
+    if let Err(e) = parser.command().clone().try_get_matches_from(args)
+        && matches!(e.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayVersion)
+    {
+        e.exit();
+    }
+
+    let result = match CommandExecutor::new().execute_from_args(args.to_vec()).await {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{}", e);
+            return e.exit_code();
+        }
+    };
+
+    // Handlers end their text with a newline already
+    if let CommandOutput::Text(text) = &result.output {
+        print!("{}", text);
+    } else if let Err(e) = PipelineOutput::to_stdout(OutputFormat::JSON).write_output(&result.output) {
+        eprintln!("{}", e);
+        return e.exit_code();
+    }
+    result.exit_code
+}
+
 /// Parse command line argument value
 fn parse_arg<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
     args.iter()
@@ -151,6 +247,14 @@ fn parse_arg<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
         .map(|s| s.as_str())
 }
 
+/// Collect every value given for a repeatable flag
+fn parse_all_args<'a>(args: &'a [String], flag: &str) -> Vec<&'a str> {
+    args.windows(2)
+        .filter(|pair| pair[0] == flag)
+        .map(|pair| pair[1].as_str())
+        .collect()
+}
+
 /// Print help information
 fn print_help() {
     println!("Kizuna Discovery System");
@@ -167,8 +271,12 @@ fn print_help() {
     println!("    benchmark               Benchmark all available strategies");
     println!("    stats                   Show discovery statistics");
     println!("    config <SUBCOMMAND>     Configuration management");
+    println!("    daemon                  Answer peers and watch the clipboard in the background");
     println!("    help                    Show this help message");
     println!();
+    println!("    Any other command, such as send, msg, get, ping or wipe, is run by the");
+    println!("    full command-line interface; see kizuna <COMMAND> --help.");
+    println!();
     println!("DISCOVERY OPTIONS:");
     println!("    --timeout SECS          Discovery timeout in seconds (default: 5)");
     println!("    --strategies LIST       Comma-separated list of strategies");
@@ -178,6 +286,16 @@ fn print_help() {
     println!("    --name NAME             Device name for announcements");
     println!("    --port PORT             Service port number");
     println!("    --duration SECS         Announce for specified seconds");
+    println!("    --respond               Answer discovery queries as soon as they arrive");
+    println!("    --stealth               Browse only, never announce this device");
+    println!("    --window [DAYS ]HH:MM-HH:MM");
+    println!("                            Only announce inside this window (repeatable)");
+    println!();
+    println!("DAEMON OPTIONS:");
+    println!("    --name NAME             Device name for announcements");
+    println!("    --port PORT             Port peers connect to (default: 41340)");
+    println!("    --health-port PORT      Port of the browser server and health checks (default: 41360)");
+    println!("    --no-health             Do not start the browser server");
     println!();
     println!("CONFIG SUBCOMMANDS:");
     println!("    init                    Create default configuration file");
@@ -194,6 +312,8 @@ fn print_help() {
     println!("EXAMPLES:");
     println!("    kizuna discover --strategies mdns,udp --timeout 10 --verbose");
     println!("    kizuna announce --name \"My Device\" --port 8080 --duration 60");
+    println!("    kizuna announce --window \"mon,tue,wed,thu,fri 09:00-17:00\"");
+    println!("    kizuna announce --name \"My Device\" --respond");
     println!("    kizuna test-strategy mdns --verbose");
     println!("    kizuna benchmark --iterations 5 --timeout 3");
     println!("    kizuna config init");
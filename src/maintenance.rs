@@ -0,0 +1,263 @@
+//! Periodic reaping of expired state
+//!
+//! Several subsystems keep state that outlives its use: pooled transport
+//! sessions whose peer vanished, encryption and pairing sessions past their
+//! timeout, idle relay sessions. Each knows how to drop its own expired
+//! entries but nothing calls it on a schedule. [`Maintenance`] runs every
+//! registered [`Reaper`] on one interval, as a supervised daemon component, so
+//! a reaper that fails is reported without stopping the others.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::error::KizunaResult;
+use crate::security::api::SecuritySystem;
+use crate::supervisor::Supervisor;
+use crate::transport::manager::ConnectionManager;
+use crate::transport::relay::RelayManager;
+use crate::transport::ConnectionProvider;
+
+/// Name of the maintenance component in supervisor health reports
+pub const MAINTENANCE_COMPONENT: &str = "maintenance";
+
+/// Something holding state that expires
+#[async_trait]
+pub trait Reaper: Send + Sync {
+    /// Name shown in reports
+    fn name(&self) -> &str;
+
+    /// Drop whatever has expired, returning how many items went
+    async fn reap(&self) -> KizunaResult<usize>;
+}
+
+/// How often reapers run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// Seconds between runs
+    pub interval_secs: u64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self { interval_secs: 60 }
+    }
+}
+
+/// Outcome of one run across all reapers
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    /// Items dropped per reaper
+    pub reaped: Vec<(String, usize)>,
+    /// Reapers that failed, with the error
+    pub errors: Vec<(String, String)>,
+}
+
+impl MaintenanceReport {
+    pub fn total_reaped(&self) -> usize {
+        self.reaped.iter().map(|(_, n)| n).sum()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Runs the registered reapers periodically
+pub struct Maintenance {
+    config: MaintenanceConfig,
+    reapers: RwLock<Vec<Arc<dyn Reaper>>>,
+    last_report: StdMutex<Option<MaintenanceReport>>,
+}
+
+impl Maintenance {
+    pub fn new(config: MaintenanceConfig) -> Self {
+        Self {
+            config,
+            reapers: RwLock::new(Vec::new()),
+            last_report: StdMutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &MaintenanceConfig {
+        &self.config
+    }
+
+    pub async fn register(&self, reaper: Arc<dyn Reaper>) {
+        self.reapers.write().await.push(reaper);
+    }
+
+    /// Run every reaper once
+    pub async fn run_once(&self) -> MaintenanceReport {
+        let mut report = MaintenanceReport::default();
+
+        for reaper in self.reapers.read().await.iter() {
+            match reaper.reap().await {
+                Ok(reaped) => report.reaped.push((reaper.name().to_string(), reaped)),
+                Err(e) => {
+                    log::warn!("Reaping {} failed: {}", reaper.name(), e);
+                    report.errors.push((reaper.name().to_string(), e.to_string()));
+                }
+            }
+        }
+
+        if report.total_reaped() > 0 {
+            log::debug!("Maintenance reaped {} expired items", report.total_reaped());
+        }
+        *self.last_report.lock().unwrap() = Some(report.clone());
+        report
+    }
+
+    /// Outcome of the latest run, if there has been one
+    pub fn last_report(&self) -> Option<MaintenanceReport> {
+        self.last_report.lock().unwrap().clone()
+    }
+
+    /// Run the reapers every `interval_secs` as a supervised component
+    ///
+    /// The component stops at shutdown.
+    pub fn supervise(self: &Arc<Self>, supervisor: &Supervisor) {
+        let maintenance = Arc::clone(self);
+        supervisor.supervise(MAINTENANCE_COMPONENT, move |token| {
+            let maintenance = Arc::clone(&maintenance);
+            async move {
+                let interval = Duration::from_secs(maintenance.config.interval_secs.max(1));
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => return Ok(()),
+                        _ = tokio::time::sleep(interval) => {
+                            maintenance.run_once().await;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl std::fmt::Debug for Maintenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Maintenance")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Reaper for ConnectionProvider {
+    fn name(&self) -> &str {
+        "transport sessions"
+    }
+
+    async fn reap(&self) -> KizunaResult<usize> {
+        Ok(self.evict_idle())
+    }
+}
+
+#[async_trait]
+impl Reaper for SecuritySystem {
+    fn name(&self) -> &str {
+        "security sessions"
+    }
+
+    async fn reap(&self) -> KizunaResult<usize> {
+        Ok(self.reap_expired().await?)
+    }
+}
+
+#[async_trait]
+impl Reaper for RelayManager {
+    fn name(&self) -> &str {
+        "relay sessions"
+    }
+
+    async fn reap(&self) -> KizunaResult<usize> {
+        Ok(self.cleanup_idle_sessions().await?)
+    }
+}
+
+#[async_trait]
+impl Reaper for ConnectionManager {
+    fn name(&self) -> &str {
+        "connections"
+    }
+
+    async fn reap(&self) -> KizunaResult<usize> {
+        Ok(self.cleanup_connections().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::KizunaError;
+    use crate::shutdown::{ShutdownCoordinator, ShutdownReason};
+    use crate::supervisor::{ComponentState, SupervisorConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counting {
+        runs: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Reaper for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn reap(&self) -> KizunaResult<usize> {
+            Ok(self.runs.fetch_add(1, Ordering::SeqCst) + 1)
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl Reaper for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        async fn reap(&self) -> KizunaResult<usize> {
+            Err(KizunaError::other("store unavailable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_reaper_does_not_stop_others() {
+        let maintenance = Maintenance::new(MaintenanceConfig::default());
+        maintenance.register(Arc::new(Failing)).await;
+        maintenance.register(Arc::new(Counting { runs: AtomicUsize::new(0) })).await;
+
+        let report = maintenance.run_once().await;
+        assert_eq!(report.reaped, vec![("counting".to_string(), 1)]);
+        assert_eq!(report.errors.len(), 1);
+        assert!(!report.is_success());
+        assert_eq!(maintenance.last_report().unwrap().total_reaped(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_supervised_until_shutdown() {
+        let shutdown = Arc::new(ShutdownCoordinator::default());
+        let supervisor = Supervisor::new(Arc::clone(&shutdown), SupervisorConfig::default());
+        let maintenance = Arc::new(Maintenance::new(MaintenanceConfig { interval_secs: 1 }));
+        let reaper = Arc::new(Counting { runs: AtomicUsize::new(0) });
+        maintenance.register(Arc::clone(&reaper) as Arc<dyn Reaper>).await;
+
+        maintenance.supervise(&supervisor);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while reaper.runs.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        shutdown.shutdown(ShutdownReason::Requested).await;
+        let health = supervisor.component(MAINTENANCE_COMPONENT).unwrap();
+        assert_eq!(health.state, ComponentState::Stopped);
+    }
+}
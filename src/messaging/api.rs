@@ -0,0 +1,764 @@
+//! Messaging system API
+//!
+//! [`MessagingSystem`] ties the history, the peer's security session and the
+//! shared connection provider together. It is cheap to clone; clones share
+//! state, and the reader task spawned for each peer stream holds one.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use uuid::Uuid;
+
+use crate::messaging::broadcast::{
+    BroadcastContent, BroadcastMessage, BroadcastReport, MAX_BROADCAST_LEN, MAX_TRACKED_BROADCASTS,
+};
+use crate::messaging::history::MessageHistory;
+use crate::messaging::protocol::{MessageFrame, MAX_FRAME_LEN};
+use crate::messaging::{
+    now_millis, ChatMessage, Direction, MessageId, MessageStatus, MessagingError, MessagingEvent,
+    MessagingResult, PeerId, ReceiptKind,
+};
+use crate::security::encryption::{EncryptionEngine, SessionId};
+use crate::security::storage::EncryptedStorage;
+use crate::security::trust::TrustManager;
+use crate::security::PeerId as SecurityPeerId;
+use crate::transport::{ConnectionProvider, MuxStream, PeerAddress, CHANNEL_MESSAGES};
+
+/// Configuration for the messaging system
+#[derive(Debug, Clone)]
+pub struct MessagingConfig {
+    /// Directory holding the encrypted history and its key
+    pub storage_dir: PathBuf,
+    /// Longest accepted message text in bytes
+    pub max_text_len: usize,
+    /// Messages kept per conversation
+    pub history_limit: usize,
+    /// Tell peers when their messages have been read
+    pub send_read_receipts: bool,
+}
+
+impl Default for MessagingConfig {
+    fn default() -> Self {
+        let storage_dir = dirs::data_local_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("kizuna")
+            .join("messages");
+        Self {
+            storage_dir,
+            max_text_len: 16 * 1024,
+            history_limit: 10_000,
+            send_read_receipts: true,
+        }
+    }
+}
+
+struct MessagingInner {
+    config: MessagingConfig,
+    provider: Arc<ConnectionProvider>,
+    encryption: Arc<dyn EncryptionEngine>,
+    trust: Arc<dyn TrustManager>,
+    history: MessageHistory,
+    /// Security sessions by peer ID
+    sessions: RwLock<HashMap<PeerId, SessionId>>,
+    /// Messaging streams by peer ID
+    streams: RwLock<HashMap<PeerId, Arc<MuxStream>>>,
+    /// Delivery reports for broadcasts we sent
+    broadcasts: RwLock<HashMap<MessageId, BroadcastReport>>,
+    events: broadcast::Sender<MessagingEvent>,
+}
+
+/// Encrypted text messaging between trusted peers
+#[derive(Clone)]
+pub struct MessagingSystem {
+    inner: Arc<MessagingInner>,
+}
+
+impl MessagingSystem {
+    /// Create the messaging system, opening the local history sealed with `storage`
+    pub async fn new(
+        config: MessagingConfig,
+        provider: Arc<ConnectionProvider>,
+        encryption: Arc<dyn EncryptionEngine>,
+        trust: Arc<dyn TrustManager>,
+        storage: EncryptedStorage,
+    ) -> MessagingResult<Self> {
+        let history = MessageHistory::open(&config.storage_dir, storage, config.history_limit).await?;
+        let (events, _) = broadcast::channel(256);
+        Ok(Self {
+            inner: Arc::new(MessagingInner {
+                config,
+                provider,
+                encryption,
+                trust,
+                history,
+                sessions: RwLock::new(HashMap::new()),
+                streams: RwLock::new(HashMap::new()),
+                broadcasts: RwLock::new(HashMap::new()),
+                events,
+            }),
+        })
+    }
+
+    /// Configuration in use
+    pub fn config(&self) -> &MessagingConfig {
+        &self.inner.config
+    }
+
+    /// Local message history
+    pub fn history(&self) -> &MessageHistory {
+        &self.inner.history
+    }
+
+    /// Subscribe to incoming messages and status changes
+    pub fn subscribe(&self) -> broadcast::Receiver<MessagingEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// Send a text message to a trusted peer
+    ///
+    /// The message is stored before anything goes on the wire. If the peer cannot
+    /// be reached it is returned as `Queued` and sent by a later [`flush`](Self::flush).
+    pub async fn send(&self, peer: &PeerAddress, text: impl Into<String>) -> MessagingResult<ChatMessage> {
+        let text = text.into();
+        if text.trim().is_empty() {
+            return Err(MessagingError::EmptyMessage);
+        }
+        if text.len() > self.inner.config.max_text_len {
+            return Err(MessagingError::MessageTooLarge {
+                len: text.len(),
+                limit: self.inner.config.max_text_len,
+            });
+        }
+        self.inner.ensure_trusted(&peer.peer_id).await?;
+
+        let message = ChatMessage::outgoing(peer.peer_id.clone(), text);
+        self.inner.history.insert(message.clone()).await?;
+
+        match self.inner.deliver(peer, &message).await {
+            Ok(updated) => Ok(updated.unwrap_or(message)),
+            Err(e) if e.is_transient() => {
+                log::debug!("Queued message {} for offline peer {}: {}", message.id, peer.peer_id, e);
+                Ok(message)
+            }
+            Err(e) => {
+                self.inner.set_status(&message.id, MessageStatus::Failed).await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Send everything waiting for a peer: queued messages, then deferred read receipts
+    ///
+    /// Call this when the peer becomes reachable. Stops at the first transport
+    /// failure, leaving the rest queued. Returns the number of messages sent.
+    pub async fn flush(&self, peer: &PeerAddress) -> MessagingResult<usize> {
+        let queued = self.inner.history.queued(&peer.peer_id).await;
+        if !queued.is_empty() {
+            self.inner.ensure_trusted(&peer.peer_id).await?;
+        }
+
+        let mut sent = 0;
+        for message in queued {
+            match self.inner.deliver(peer, &message).await {
+                Ok(_) => sent += 1,
+                Err(e) if e.is_transient() => return Err(e),
+                Err(e) => {
+                    log::warn!("Message {} to {} failed permanently: {}", message.id, peer.peer_id, e);
+                    self.inner.set_status(&message.id, MessageStatus::Failed).await?;
+                }
+            }
+        }
+
+        if self.inner.config.send_read_receipts {
+            let pending: Vec<MessageId> = self
+                .inner
+                .history
+                .conversation(&peer.peer_id)
+                .await
+                .into_iter()
+                .filter(|m| {
+                    m.direction == Direction::Incoming
+                        && m.status == MessageStatus::Read
+                        && m.receipt_sent != Some(ReceiptKind::Read)
+                })
+                .map(|m| m.id)
+                .collect();
+            if !pending.is_empty() {
+                let stream = self.inner.stream_for(peer).await?;
+                self.inner.send_receipt(&stream, pending, ReceiptKind::Read).await?;
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Mark every message from a peer as read, notifying the peer if possible
+    ///
+    /// A receipt that cannot be sent now goes out with the next [`flush`](Self::flush).
+    /// Returns the number of messages marked.
+    pub async fn mark_read(&self, peer: &PeerAddress) -> MessagingResult<usize> {
+        let mut ids = Vec::new();
+        for message in self.inner.history.unread(&peer.peer_id).await {
+            if self.inner.history.advance(&message.id, MessageStatus::Read).await?.is_some() {
+                ids.push(message.id);
+            }
+        }
+
+        if self.inner.config.send_read_receipts && !ids.is_empty() {
+            let result = match self.inner.stream_for(peer).await {
+                Ok(stream) => self.inner.send_receipt(&stream, ids.clone(), ReceiptKind::Read).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                log::debug!("Deferring read receipts to {}: {}", peer.peer_id, e);
+            }
+        }
+        Ok(ids.len())
+    }
+
+    /// Send a small message to several trusted peers at once, e.g. a device group
+    ///
+    /// Copies go out concurrently. In the returned report each recipient is
+    /// `Sent` or `Failed`; untrusted or unreachable recipients fail without
+    /// holding up the others and nothing is queued for them. Recipients move
+    /// to `Delivered` as their receipts arrive, see
+    /// [`wait_for_broadcast`](Self::wait_for_broadcast).
+    pub async fn broadcast(
+        &self,
+        recipients: &[PeerAddress],
+        topic: impl Into<String>,
+        body: impl Into<String>,
+    ) -> MessagingResult<BroadcastReport> {
+        let content = BroadcastContent {
+            topic: topic.into(),
+            body: body.into(),
+        };
+        let len = content.topic.len() + content.body.len();
+        if len > MAX_BROADCAST_LEN {
+            return Err(MessagingError::MessageTooLarge {
+                len,
+                limit: MAX_BROADCAST_LEN,
+            });
+        }
+
+        let mut unique: Vec<&PeerAddress> = Vec::new();
+        for peer in recipients {
+            if !unique.iter().any(|p| p.peer_id == peer.peer_id) {
+                unique.push(peer);
+            }
+        }
+        if unique.is_empty() {
+            return Err(MessagingError::NoRecipients);
+        }
+
+        let id = Uuid::new_v4();
+        let sent_at = now_millis();
+        let report = BroadcastReport::new(
+            id,
+            content.topic.clone(),
+            sent_at,
+            unique.iter().map(|peer| peer.peer_id.clone()),
+        );
+        self.inner.track_broadcast(report).await;
+
+        let sends = unique
+            .iter()
+            .map(|peer| self.inner.deliver_broadcast(peer, id, sent_at, &content));
+        for (peer, result) in unique.iter().zip(futures::future::join_all(sends).await) {
+            match result {
+                Ok(()) => self.inner.set_broadcast_status(&id, &peer.peer_id, MessageStatus::Sent, None).await,
+                Err(e) => {
+                    log::debug!("Broadcast {} to {} failed: {}", id, peer.peer_id, e);
+                    self.inner
+                        .set_broadcast_status(&id, &peer.peer_id, MessageStatus::Failed, Some(e.to_string()))
+                        .await
+                }
+            }
+        }
+
+        self.broadcast_report(&id).await.ok_or(MessagingError::UnknownMessage(id))
+    }
+
+    /// Current delivery report for a broadcast we sent
+    pub async fn broadcast_report(&self, id: &MessageId) -> Option<BroadcastReport> {
+        self.inner.broadcasts.read().await.get(id).cloned()
+    }
+
+    /// Wait until every recipient of a broadcast acknowledged it or failed
+    ///
+    /// Returns the report as it stands when `timeout` runs out; recipients
+    /// still `Sent` then have not confirmed delivery.
+    pub async fn wait_for_broadcast(&self, id: &MessageId, timeout: Duration) -> Option<BroadcastReport> {
+        let mut events = self.subscribe();
+        let settled = async {
+            loop {
+                let report = self.broadcast_report(id).await?;
+                if report.is_settled() {
+                    return Some(report);
+                }
+                if let Err(broadcast::error::RecvError::Closed) = events.recv().await {
+                    return Some(report);
+                }
+            }
+        };
+        match tokio::time::timeout(timeout, settled).await {
+            Ok(report) => report,
+            Err(_) => self.broadcast_report(id).await,
+        }
+    }
+
+    /// Serve a messaging stream opened by a remote peer
+    pub async fn attach_stream(&self, peer_id: PeerId, stream: MuxStream) -> MessagingResult<()> {
+        if stream.channel() != CHANNEL_MESSAGES {
+            return Err(MessagingError::InvalidStream {
+                channel: stream.channel().to_string(),
+            });
+        }
+        let stream = Arc::new(stream);
+        self.inner.streams.write().await.insert(peer_id.clone(), Arc::clone(&stream));
+        self.inner.spawn_reader(peer_id, stream);
+        Ok(())
+    }
+
+    /// All messages exchanged with a peer, oldest first
+    pub async fn conversation(&self, peer_id: &str) -> Vec<ChatMessage> {
+        self.inner.history.conversation(peer_id).await
+    }
+
+    /// Number of unread messages from a peer
+    pub async fn unread_count(&self, peer_id: &str) -> usize {
+        self.inner.history.unread(peer_id).await.len()
+    }
+
+    /// Close the messaging stream to a peer
+    pub async fn disconnect(&self, peer_id: &str) {
+        if let Some(stream) = self.inner.streams.write().await.remove(peer_id) {
+            let _ = stream.close();
+        }
+    }
+}
+
+impl MessagingInner {
+    fn security_peer_id(peer_id: &str) -> MessagingResult<SecurityPeerId> {
+        SecurityPeerId::from_string(peer_id).map_err(|e| MessagingError::InvalidPeer {
+            peer_id: peer_id.to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    async fn ensure_trusted(&self, peer_id: &str) -> MessagingResult<()> {
+        let security_peer_id = Self::security_peer_id(peer_id)?;
+        let trusted = self
+            .trust
+            .is_trusted(&security_peer_id)
+            .await
+            .map_err(|e| MessagingError::Trust(e.to_string()))?;
+        if !trusted {
+            return Err(MessagingError::PeerNotTrusted {
+                peer_id: peer_id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn session_for(&self, peer_id: &str) -> MessagingResult<SessionId> {
+        if let Some(session_id) = self.sessions.read().await.get(peer_id) {
+            return Ok(session_id.clone());
+        }
+
+        let security_peer_id = Self::security_peer_id(peer_id)?;
+        let session_id = self
+            .encryption
+            .establish_session(&security_peer_id)
+            .await
+            .map_err(|e| MessagingError::encryption("establish_session", e))?;
+        let mut sessions = self.sessions.write().await;
+        Ok(sessions.entry(peer_id.to_string()).or_insert(session_id).clone())
+    }
+
+    /// Get the open messaging stream to a peer, opening one if needed
+    async fn stream_for(self: &Arc<Self>, peer: &PeerAddress) -> MessagingResult<Arc<MuxStream>> {
+        if let Some(stream) = self.streams.read().await.get(&peer.peer_id) && stream.is_open() {
+            return Ok(Arc::clone(stream));
+        }
+
+        let stream = self
+            .provider
+            .open_stream(peer, CHANNEL_MESSAGES)
+            .await
+            .map(Arc::new)
+            .map_err(|e| MessagingError::transport("open_stream", e))?;
+        self.streams.write().await.insert(peer.peer_id.clone(), Arc::clone(&stream));
+        self.spawn_reader(peer.peer_id.clone(), Arc::clone(&stream));
+        Ok(stream)
+    }
+
+    /// Encrypt and send one message, marking it sent
+    async fn deliver(self: &Arc<Self>, peer: &PeerAddress, message: &ChatMessage) -> MessagingResult<Option<ChatMessage>> {
+        let session_id = self.session_for(&peer.peer_id).await?;
+        let ciphertext = self
+            .encryption
+            .encrypt_message(&session_id, message.text.as_bytes())
+            .await
+            .map_err(|e| MessagingError::encryption("encrypt_message", e))?;
+        let frame = MessageFrame::Text {
+            id: message.id,
+            sent_at: message.sent_at,
+            ciphertext,
+        };
+
+        let stream = self.stream_for(peer).await?;
+        if let Err(e) = stream.send_message(&frame.encode()?).await {
+            self.streams.write().await.remove(&peer.peer_id);
+            return Err(MessagingError::transport("send_message", e));
+        }
+        self.set_status(&message.id, MessageStatus::Sent).await
+    }
+
+    /// Encrypt and send one recipient's copy of a broadcast
+    async fn deliver_broadcast(
+        self: &Arc<Self>,
+        peer: &PeerAddress,
+        id: MessageId,
+        sent_at: u64,
+        content: &BroadcastContent,
+    ) -> MessagingResult<()> {
+        self.ensure_trusted(&peer.peer_id).await?;
+        let session_id = self.session_for(&peer.peer_id).await?;
+        let ciphertext = self
+            .encryption
+            .encrypt_message(&session_id, &serde_json::to_vec(content)?)
+            .await
+            .map_err(|e| MessagingError::encryption("encrypt_message", e))?;
+        let frame = MessageFrame::Broadcast { id, sent_at, ciphertext };
+
+        let stream = self.stream_for(peer).await?;
+        if let Err(e) = stream.send_message(&frame.encode()?).await {
+            self.streams.write().await.remove(&peer.peer_id);
+            return Err(MessagingError::transport("send_message", e));
+        }
+        Ok(())
+    }
+
+    /// Start a delivery report, dropping the oldest once too many are kept
+    async fn track_broadcast(&self, report: BroadcastReport) {
+        let mut broadcasts = self.broadcasts.write().await;
+        while broadcasts.len() >= MAX_TRACKED_BROADCASTS {
+            let Some(oldest) = broadcasts.values().min_by_key(|report| report.sent_at).map(|report| report.id) else {
+                break;
+            };
+            broadcasts.remove(&oldest);
+        }
+        broadcasts.insert(report.id, report);
+    }
+
+    /// Move a broadcast recipient forward and publish the change
+    async fn set_broadcast_status(&self, id: &MessageId, peer_id: &str, status: MessageStatus, error: Option<String>) {
+        let changed = self
+            .broadcasts
+            .write()
+            .await
+            .get_mut(id)
+            .is_some_and(|report| report.update(peer_id, status, error));
+        if changed {
+            let _ = self.events.send(MessagingEvent::BroadcastStatusChanged {
+                id: *id,
+                peer_id: peer_id.to_string(),
+                status,
+            });
+        }
+    }
+
+    async fn send_receipt(&self, stream: &MuxStream, ids: Vec<MessageId>, kind: ReceiptKind) -> MessagingResult<()> {
+        let frame = MessageFrame::Receipt { ids: ids.clone(), kind };
+        stream
+            .send_message(&frame.encode()?)
+            .await
+            .map_err(|e| MessagingError::transport("send_receipt", e))?;
+
+        for id in ids {
+            self.history
+                .modify(&id, |message| {
+                    if message.receipt_sent == Some(ReceiptKind::Read) {
+                        return false;
+                    }
+                    message.receipt_sent = Some(kind);
+                    true
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Advance a message's status and publish the change
+    async fn set_status(&self, id: &MessageId, status: MessageStatus) -> MessagingResult<Option<ChatMessage>> {
+        let updated = self.history.advance(id, status).await?;
+        if let Some(message) = &updated {
+            let _ = self.events.send(MessagingEvent::StatusChanged {
+                id: message.id,
+                peer_id: message.peer_id.clone(),
+                status: message.status,
+            });
+        }
+        Ok(updated)
+    }
+
+    fn spawn_reader(self: &Arc<Self>, peer_id: PeerId, stream: Arc<MuxStream>) {
+        let inner = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                let bytes = match stream.recv_message(MAX_FRAME_LEN).await {
+                    Ok(Some(bytes)) => bytes,
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::warn!("Messaging stream from {} failed: {}", peer_id, e);
+                        break;
+                    }
+                };
+                if let Err(e) = inner.handle_frame(&peer_id, &stream, &bytes).await {
+                    log::warn!("Dropping message frame from {}: {}", peer_id, e);
+                }
+            }
+
+            let mut streams = inner.streams.write().await;
+            if streams.get(&peer_id).is_some_and(|s| Arc::ptr_eq(s, &stream)) {
+                streams.remove(&peer_id);
+            }
+        });
+    }
+
+    async fn handle_frame(&self, peer_id: &str, stream: &MuxStream, bytes: &[u8]) -> MessagingResult<()> {
+        match MessageFrame::decode(bytes)? {
+            MessageFrame::Text { id, sent_at, ciphertext } => {
+                self.ensure_trusted(peer_id).await?;
+                let session_id = self.session_for(peer_id).await?;
+                let plaintext = self
+                    .encryption
+                    .decrypt_message(&session_id, &ciphertext)
+                    .await
+                    .map_err(|e| MessagingError::encryption("decrypt_message", e))?;
+                let text = String::from_utf8(plaintext)
+                    .map_err(|e| MessagingError::encryption("decrypt_message", e))?;
+
+                let message = ChatMessage::incoming(id, peer_id, text, sent_at);
+                // A resend after a lost receipt is acknowledged again but not stored twice
+                if self.history.insert(message.clone()).await? {
+                    let _ = self.events.send(MessagingEvent::Received(message));
+                }
+                self.send_receipt(stream, vec![id], ReceiptKind::Delivered).await
+            }
+            MessageFrame::Broadcast { id, sent_at, ciphertext } => {
+                self.ensure_trusted(peer_id).await?;
+                let session_id = self.session_for(peer_id).await?;
+                let plaintext = self
+                    .encryption
+                    .decrypt_message(&session_id, &ciphertext)
+                    .await
+                    .map_err(|e| MessagingError::encryption("decrypt_message", e))?;
+                let content: BroadcastContent = serde_json::from_slice(&plaintext)?;
+
+                let _ = self.events.send(MessagingEvent::BroadcastReceived(BroadcastMessage {
+                    id,
+                    sender: peer_id.to_string(),
+                    topic: content.topic,
+                    body: content.body,
+                    sent_at,
+                }));
+                // Broadcasts are not in the history, so the receipt is not recorded
+                let receipt = MessageFrame::Receipt {
+                    ids: vec![id],
+                    kind: ReceiptKind::Delivered,
+                };
+                stream
+                    .send_message(&receipt.encode()?)
+                    .await
+                    .map_err(|e| MessagingError::transport("send_receipt", e))
+            }
+            MessageFrame::Receipt { ids, kind } => {
+                for id in ids {
+                    if self.broadcasts.read().await.contains_key(&id) {
+                        self.set_broadcast_status(&id, peer_id, kind.into(), None).await;
+                        continue;
+                    }
+                    match self.history.get(&id).await {
+                        Some(message) if message.peer_id == peer_id && message.direction == Direction::Outgoing => {
+                            self.set_status(&id, kind.into()).await?;
+                        }
+                        _ => log::debug!("Ignoring receipt from {} for unknown message {}", peer_id, id),
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::trust::TrustManagerImpl;
+    use crate::testing::transport::MEMORY_PROTOCOL;
+    use crate::security::storage::StorageKey;
+    use crate::testing::{MemoryEncryptionEngine, MemoryTransport, VirtualNetwork};
+    use crate::transport::{PeerDialer, ProviderConfig, Transport};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn peer_hex(byte: u8) -> String {
+        SecurityPeerId::from_fingerprint([byte; 32]).to_hex()
+    }
+
+    async fn system(dir: &TempDir, local: &str, remote: &str, dialer: Arc<dyn PeerDialer>) -> MessagingSystem {
+        let trust = TrustManagerImpl::new(dir.path().join("trust.db")).unwrap();
+        trust
+            .add_trusted_peer(SecurityPeerId::from_string(remote).unwrap(), "peer".to_string())
+            .await
+            .unwrap();
+        let config = MessagingConfig {
+            storage_dir: dir.path().join("messages"),
+            ..Default::default()
+        };
+        MessagingSystem::new(
+            config,
+            Arc::new(ConnectionProvider::new(dialer, ProviderConfig::default())),
+            Arc::new(MemoryEncryptionEngine::new(SecurityPeerId::from_string(local).unwrap())),
+            Arc::new(trust),
+            EncryptedStorage::new(StorageKey::from_bytes([4; 32])),
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn wait_for_status(system: &MessagingSystem, id: &MessageId, status: MessageStatus) {
+        for _ in 0..100 {
+            if system.history().get(id).await.map(|m| m.status) == Some(status) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("message {} never reached {}", id, status);
+    }
+
+    #[tokio::test]
+    async fn test_message_delivered_and_read() {
+        let (alice_id, bob_id) = (peer_hex(1), peer_hex(2));
+        let network = VirtualNetwork::new();
+        let alice_transport = Arc::new(MemoryTransport::new(alice_id.clone(), network.clone()));
+        let bob_transport = Arc::new(MemoryTransport::new(bob_id.clone(), network.clone()));
+        let addr = network.allocate_address(42000);
+        bob_transport.listen(&addr).await.unwrap();
+        let bob_addr = PeerAddress::new(
+            bob_id.clone(),
+            vec![addr],
+            vec![MEMORY_PROTOCOL.to_string()],
+            bob_transport.capabilities(),
+        );
+
+        let (alice_dir, bob_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let alice = system(&alice_dir, &alice_id, &bob_id, alice_transport).await;
+        let bob = system(&bob_dir, &bob_id, &alice_id, Arc::clone(&bob_transport) as Arc<dyn PeerDialer>).await;
+        let mut bob_events = bob.subscribe();
+
+        let sent = alice.send(&bob_addr, "hello bob").await.unwrap();
+        assert_eq!(sent.status, MessageStatus::Sent);
+
+        let session = bob.inner.provider.adopt(Box::new(bob_transport.accept().await.unwrap())).await.unwrap();
+        bob.attach_stream(alice_id.clone(), session.accept_stream().await.unwrap()).await.unwrap();
+
+        match bob_events.recv().await.unwrap() {
+            MessagingEvent::Received(message) => assert_eq!(message.text, "hello bob"),
+            other => panic!("Expected received message, got {:?}", other),
+        }
+        wait_for_status(&alice, &sent.id, MessageStatus::Delivered).await;
+
+        let alice_addr = PeerAddress::new(alice_id.clone(), vec![], vec![], Default::default());
+        assert_eq!(bob.unread_count(&alice_id).await, 1);
+        assert_eq!(bob.mark_read(&alice_addr).await.unwrap(), 1);
+        wait_for_status(&alice, &sent.id, MessageStatus::Read).await;
+    }
+
+    #[tokio::test]
+    async fn test_offline_peer_queues_message() {
+        let (alice_id, bob_id) = (peer_hex(3), peer_hex(4));
+        let network = VirtualNetwork::new();
+        let alice_transport = Arc::new(MemoryTransport::new(alice_id.clone(), network.clone()));
+        let bob_addr = PeerAddress::new(
+            bob_id.clone(),
+            vec![network.allocate_address(42001)],
+            vec![MEMORY_PROTOCOL.to_string()],
+            alice_transport.capabilities(),
+        );
+
+        let dir = TempDir::new().unwrap();
+        let alice = system(&dir, &alice_id, &bob_id, alice_transport).await;
+        let queued = alice.send(&bob_addr, "are you there?").await.unwrap();
+        assert_eq!(queued.status, MessageStatus::Queued);
+        assert!(alice.flush(&bob_addr).await.unwrap_err().is_transient());
+        assert_eq!(alice.history().queued(&bob_id).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_peer_rejected() {
+        let network = VirtualNetwork::new();
+        let dir = TempDir::new().unwrap();
+        let transport = Arc::new(MemoryTransport::new(peer_hex(5), network));
+        let alice = system(&dir, &peer_hex(5), &peer_hex(6), transport).await;
+
+        let stranger = PeerAddress::new(peer_hex(7), vec![], vec![], Default::default());
+        let err = alice.send(&stranger, "hi").await.unwrap_err();
+        assert!(matches!(err, MessagingError::PeerNotTrusted { .. }));
+        assert!(alice.history().is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_reports_each_recipient() {
+        let (alice_id, bob_id) = (peer_hex(8), peer_hex(9));
+        let network = VirtualNetwork::new();
+        let alice_transport = Arc::new(MemoryTransport::new(alice_id.clone(), network.clone()));
+        let bob_transport = Arc::new(MemoryTransport::new(bob_id.clone(), network.clone()));
+        let addr = network.allocate_address(42002);
+        bob_transport.listen(&addr).await.unwrap();
+        let bob_addr = PeerAddress::new(
+            bob_id.clone(),
+            vec![addr],
+            vec![MEMORY_PROTOCOL.to_string()],
+            bob_transport.capabilities(),
+        );
+        let stranger = PeerAddress::new(peer_hex(10), vec![], vec![], Default::default());
+
+        let (alice_dir, bob_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let alice = system(&alice_dir, &alice_id, &bob_id, alice_transport).await;
+        let bob = system(&bob_dir, &bob_id, &alice_id, Arc::clone(&bob_transport) as Arc<dyn PeerDialer>).await;
+        let mut bob_events = bob.subscribe();
+
+        assert!(matches!(
+            alice.broadcast(&[], "backup", "done").await,
+            Err(MessagingError::NoRecipients)
+        ));
+        let report = alice
+            .broadcast(&[bob_addr.clone(), stranger.clone(), bob_addr], "backup", "finished")
+            .await
+            .unwrap();
+        assert_eq!(report.recipients.len(), 2);
+        assert_eq!(report.recipients[&bob_id].status, MessageStatus::Sent);
+        assert_eq!(report.failed(), vec![&stranger.peer_id]);
+
+        let session = bob.inner.provider.adopt(Box::new(bob_transport.accept().await.unwrap())).await.unwrap();
+        bob.attach_stream(alice_id.clone(), session.accept_stream().await.unwrap()).await.unwrap();
+        match bob_events.recv().await.unwrap() {
+            MessagingEvent::BroadcastReceived(message) => {
+                assert_eq!((message.sender.as_str(), message.topic.as_str()), (alice_id.as_str(), "backup"));
+                assert_eq!(message.id, report.id);
+            }
+            other => panic!("Expected broadcast, got {:?}", other),
+        }
+
+        let settled = alice.wait_for_broadcast(&report.id, Duration::from_secs(1)).await.unwrap();
+        assert!(settled.is_settled());
+        assert!(!settled.is_complete());
+        assert_eq!(settled.delivered(), vec![&bob_id]);
+        assert!(alice.history().is_empty().await);
+        assert!(bob.history().is_empty().await);
+    }
+}
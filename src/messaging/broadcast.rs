@@ -0,0 +1,153 @@
+//! One-to-many broadcasts
+//!
+//! A broadcast is a small message such as "backup finished", a clipboard push
+//! or a notification, sent to every member of a device group in one call.
+//! Each recipient gets its own copy on the `messages` channel, encrypted with
+//! that pair's security session, so it is authenticated the same way a chat
+//! message is. Broadcasts are not kept in the chat history and are not queued
+//! for peers that are offline.
+//!
+//! Recipients acknowledge a broadcast with a `Delivered` receipt. The sender
+//! keeps a [`BroadcastReport`] per broadcast with the state of every recipient,
+//! which moves from `Sent` to `Delivered` as receipts come in, or ends at
+//! `Failed` with the reason.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::messaging::{MessageId, MessageStatus, PeerId};
+
+/// Largest topic and body together, in bytes
+pub const MAX_BROADCAST_LEN: usize = 8 * 1024;
+
+/// Reports kept for broadcasts this device sent; the oldest go first
+pub const MAX_TRACKED_BROADCASTS: usize = 256;
+
+/// A broadcast received from a peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BroadcastMessage {
+    pub id: MessageId,
+    pub sender: PeerId,
+    /// What the broadcast is about, e.g. "backup"
+    pub topic: String,
+    pub body: String,
+    /// Sender's clock when it was sent, in milliseconds since the epoch
+    pub sent_at: u64,
+}
+
+/// Encrypted part of a broadcast frame
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct BroadcastContent {
+    pub topic: String,
+    pub body: String,
+}
+
+/// Where one recipient's copy of a broadcast is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipientStatus {
+    pub status: MessageStatus,
+    /// Why the copy could not be sent
+    pub error: Option<String>,
+}
+
+/// Delivery state of a broadcast across all of its recipients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BroadcastReport {
+    pub id: MessageId,
+    pub topic: String,
+    pub sent_at: u64,
+    pub recipients: BTreeMap<PeerId, RecipientStatus>,
+}
+
+impl BroadcastReport {
+    /// Report with every recipient still queued
+    pub fn new(id: MessageId, topic: String, sent_at: u64, recipients: impl IntoIterator<Item = PeerId>) -> Self {
+        let queued = RecipientStatus {
+            status: MessageStatus::Queued,
+            error: None,
+        };
+        Self {
+            id,
+            topic,
+            sent_at,
+            recipients: recipients.into_iter().map(|peer_id| (peer_id, queued.clone())).collect(),
+        }
+    }
+
+    /// Recipients that acknowledged the broadcast
+    pub fn delivered(&self) -> Vec<&PeerId> {
+        self.with(|status| status >= MessageStatus::Delivered && status != MessageStatus::Failed)
+    }
+
+    /// Recipients the broadcast could not be sent to
+    pub fn failed(&self) -> Vec<&PeerId> {
+        self.with(|status| status == MessageStatus::Failed)
+    }
+
+    /// Recipients that were sent a copy but have not acknowledged it yet
+    pub fn pending(&self) -> Vec<&PeerId> {
+        self.with(|status| matches!(status, MessageStatus::Queued | MessageStatus::Sent))
+    }
+
+    /// Whether every recipient has acknowledged the broadcast
+    pub fn is_complete(&self) -> bool {
+        self.delivered().len() == self.recipients.len()
+    }
+
+    /// Whether nothing more is expected: every recipient acknowledged or failed
+    pub fn is_settled(&self) -> bool {
+        self.pending().is_empty()
+    }
+
+    /// Move a recipient to `status`; returns whether it changed
+    ///
+    /// Statuses only move forward, so a receipt that overtakes the send
+    /// result is kept.
+    pub(crate) fn update(&mut self, peer_id: &str, status: MessageStatus, error: Option<String>) -> bool {
+        let Some(recipient) = self.recipients.get_mut(peer_id) else {
+            return false;
+        };
+        if !recipient.status.can_advance_to(status) {
+            return false;
+        }
+        recipient.status = status;
+        recipient.error = error;
+        true
+    }
+
+    fn with(&self, matches: impl Fn(MessageStatus) -> bool) -> Vec<&PeerId> {
+        self.recipients
+            .iter()
+            .filter(|(_, recipient)| matches(recipient.status))
+            .map(|(peer_id, _)| peer_id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_report_aggregates_recipients() {
+        let peers = ["a", "b", "c"].map(String::from);
+        let mut report = BroadcastReport::new(Uuid::new_v4(), "backup".to_string(), 0, peers.clone());
+        assert_eq!(report.pending().len(), 3);
+
+        assert!(report.update("a", MessageStatus::Sent, None));
+        assert!(report.update("b", MessageStatus::Failed, Some("unreachable".to_string())));
+        // The receipt overtook the send result
+        assert!(report.update("c", MessageStatus::Delivered, None));
+        assert!(!report.update("c", MessageStatus::Sent, None));
+        assert!(!report.update("stranger", MessageStatus::Delivered, None));
+        assert!(!report.is_settled());
+
+        assert!(report.update("a", MessageStatus::Delivered, None));
+        assert!(report.is_settled());
+        assert!(!report.is_complete());
+        assert_eq!(report.delivered(), vec![&peers[0], &peers[2]]);
+        assert_eq!(report.failed(), vec![&peers[1]]);
+        assert_eq!(report.recipients["b"].error.as_deref(), Some("unreachable"));
+    }
+}
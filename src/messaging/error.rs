@@ -0,0 +1,105 @@
+//! Error types for peer-to-peer messaging
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::messaging::MessageId;
+use crate::transport::TransportError;
+
+/// Result type for messaging operations
+pub type MessagingResult<T> = Result<T, MessagingError>;
+
+/// Errors produced by the messaging subsystem
+#[derive(Error, Debug)]
+pub enum MessagingError {
+    /// The peer is not on the trust list
+    #[error("Peer {peer_id} is not trusted for messaging")]
+    PeerNotTrusted { peer_id: String },
+
+    /// The peer ID cannot be mapped to a security identity
+    #[error("Invalid peer ID {peer_id}: {reason}")]
+    InvalidPeer { peer_id: String, reason: String },
+
+    /// The message has no text
+    #[error("Message text is empty")]
+    EmptyMessage,
+
+    /// The message text exceeds the configured limit
+    #[error("Message is {len} bytes, limit is {limit}")]
+    MessageTooLarge { len: usize, limit: usize },
+
+    /// A broadcast was sent to nobody
+    #[error("Broadcast has no recipients")]
+    NoRecipients,
+
+    /// A stream on another channel was handed to the messaging subsystem
+    #[error("Stream channel {channel} is not a messaging stream")]
+    InvalidStream { channel: String },
+
+    /// No message with this ID is in the history
+    #[error("Unknown message {0}")]
+    UnknownMessage(MessageId),
+
+    /// The trust store could not be queried
+    #[error("Trust lookup failed: {0}")]
+    Trust(String),
+
+    /// Encrypting or decrypting a message body failed
+    #[error("Encryption failed during {operation}: {reason}")]
+    Encryption { operation: String, reason: String },
+
+    /// The peer could not be reached
+    #[error("Transport failed during {operation}: {source}")]
+    Transport {
+        operation: String,
+        #[source]
+        source: TransportError,
+    },
+
+    /// The local history file is unreadable or was sealed with another key
+    #[error("Message history is corrupt: {0}")]
+    HistoryCorrupt(String),
+
+    /// Local storage I/O failed
+    #[error("I/O error at {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A frame or history record could not be encoded or decoded
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+impl MessagingError {
+    /// Create a transport error for an operation
+    pub fn transport(operation: impl Into<String>, source: TransportError) -> Self {
+        MessagingError::Transport {
+            operation: operation.into(),
+            source,
+        }
+    }
+
+    /// Create an encryption error for an operation
+    pub fn encryption(operation: impl Into<String>, reason: impl std::fmt::Display) -> Self {
+        MessagingError::Encryption {
+            operation: operation.into(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Create an I/O error for a path
+    pub fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        MessagingError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Whether the message should stay queued and be retried when the peer reappears
+    pub fn is_transient(&self) -> bool {
+        matches!(self, MessagingError::Transport { .. })
+    }
+}
@@ -0,0 +1,400 @@
+//! Encrypted local message history
+//!
+//! Messages are kept in memory and mirrored to an append-only file of sealed
+//! records. Each record holds the full current state of one message sealed with
+//! the device's [`EncryptedStorage`], so a status change appends a newer copy and
+//! replay keeps the last one. The file is rewritten without superseded copies
+//! once they outnumber the live messages.
+//!
+//! The storage key never sits next to the history. The history file on its own
+//! — in a backup or a synced folder — reveals nothing but message counts and sizes.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::journal::atomic_write;
+use crate::messaging::{
+    ChatMessage, Direction, MessageId, MessageStatus, MessagingError, MessagingResult, PeerId,
+};
+use crate::security::storage::EncryptedStorage;
+
+const HISTORY_FILE: &str = "messages.history";
+/// Context label binding sealed records to the message history
+const HISTORY_CONTEXT: &str = "messaging:history";
+const LEN_PREFIX: usize = 4;
+/// Superseded records tolerated before the file is rewritten
+const COMPACT_SLACK: usize = 64;
+
+struct HistoryState {
+    messages: HashMap<MessageId, ChatMessage>,
+    /// Records in the file, including superseded copies
+    records: usize,
+    file: Option<tokio::fs::File>,
+}
+
+/// Encrypted, persistent store of all conversations
+pub struct MessageHistory {
+    path: PathBuf,
+    storage: EncryptedStorage,
+    max_per_peer: usize,
+    state: Mutex<HistoryState>,
+}
+
+impl MessageHistory {
+    /// Open the history in `dir` sealed with `storage`, creating the file on first use
+    ///
+    /// Only the newest `max_per_peer` messages of each conversation are kept;
+    /// queued messages are never dropped.
+    pub async fn open(
+        dir: impl AsRef<Path>,
+        storage: EncryptedStorage,
+        max_per_peer: usize,
+    ) -> MessagingResult<Self> {
+        let dir = dir.as_ref();
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| MessagingError::io(dir, e))?;
+
+        let history = Self {
+            path: dir.join(HISTORY_FILE),
+            storage,
+            max_per_peer: max_per_peer.max(1),
+            state: Mutex::new(HistoryState {
+                messages: HashMap::new(),
+                records: 0,
+                file: None,
+            }),
+        };
+        history.load().await?;
+        Ok(history)
+    }
+
+    /// Path of the sealed history file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Store a new message, returning `false` if one with the same ID exists
+    pub async fn insert(&self, message: ChatMessage) -> MessagingResult<bool> {
+        let mut state = self.state.lock().await;
+        if state.messages.contains_key(&message.id) {
+            return Ok(false);
+        }
+
+        self.append(&mut state, &message).await?;
+        let peer_id = message.peer_id.clone();
+        state.messages.insert(message.id, message);
+        self.trim(&mut state, &peer_id);
+        self.maybe_compact(&mut state).await?;
+        Ok(true)
+    }
+
+    /// Apply `update` to a message and persist it if `update` returns `true`
+    ///
+    /// Returns the message after the update, or `None` if it was unchanged.
+    pub async fn modify<F>(&self, id: &MessageId, update: F) -> MessagingResult<Option<ChatMessage>>
+    where
+        F: FnOnce(&mut ChatMessage) -> bool,
+    {
+        let mut state = self.state.lock().await;
+        let mut message = state
+            .messages
+            .get(id)
+            .cloned()
+            .ok_or(MessagingError::UnknownMessage(*id))?;
+        if !update(&mut message) {
+            return Ok(None);
+        }
+
+        self.append(&mut state, &message).await?;
+        state.messages.insert(*id, message.clone());
+        self.maybe_compact(&mut state).await?;
+        Ok(Some(message))
+    }
+
+    /// Advance a message's status, returning the message if it changed
+    pub async fn advance(&self, id: &MessageId, status: MessageStatus) -> MessagingResult<Option<ChatMessage>> {
+        self.modify(id, |message| message.advance(status)).await
+    }
+
+    /// Look up a message
+    pub async fn get(&self, id: &MessageId) -> Option<ChatMessage> {
+        self.state.lock().await.messages.get(id).cloned()
+    }
+
+    /// All messages exchanged with a peer, oldest first
+    pub async fn conversation(&self, peer_id: &str) -> Vec<ChatMessage> {
+        self.select(|m| m.peer_id == peer_id).await
+    }
+
+    /// Outgoing messages to a peer that have not been sent yet, oldest first
+    pub async fn queued(&self, peer_id: &str) -> Vec<ChatMessage> {
+        self.select(|m| {
+            m.peer_id == peer_id && m.direction == Direction::Outgoing && m.status == MessageStatus::Queued
+        })
+        .await
+    }
+
+    /// Incoming messages from a peer the local user has not read, oldest first
+    pub async fn unread(&self, peer_id: &str) -> Vec<ChatMessage> {
+        self.select(|m| m.peer_id == peer_id && m.is_unread()).await
+    }
+
+    /// Peers with at least one stored message
+    pub async fn peers(&self) -> Vec<PeerId> {
+        let state = self.state.lock().await;
+        let mut peers: Vec<PeerId> = state.messages.values().map(|m| m.peer_id.clone()).collect();
+        peers.sort();
+        peers.dedup();
+        peers
+    }
+
+    /// Number of stored messages
+    pub async fn len(&self) -> usize {
+        self.state.lock().await.messages.len()
+    }
+
+    /// Whether the history is empty
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Rewrite the file with only the current copy of each message
+    pub async fn compact(&self) -> MessagingResult<()> {
+        let mut state = self.state.lock().await;
+        self.rewrite(&mut state).await
+    }
+
+    async fn select<F>(&self, filter: F) -> Vec<ChatMessage>
+    where
+        F: Fn(&ChatMessage) -> bool,
+    {
+        let state = self.state.lock().await;
+        let mut selected: Vec<ChatMessage> = state.messages.values().filter(|m| filter(m)).cloned().collect();
+        selected.sort_by(|a, b| a.sent_at.cmp(&b.sent_at).then(a.id.cmp(&b.id)));
+        selected
+    }
+
+    async fn load(&self) -> MessagingResult<()> {
+        let data = match tokio::fs::read(&self.path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(MessagingError::io(&self.path, e)),
+        };
+
+        let mut state = self.state.lock().await;
+        let mut offset = 0;
+        let mut discarded = false;
+        while offset < data.len() {
+            match self.open_record(&data[offset..]) {
+                Some((message, consumed)) => {
+                    state.messages.insert(message.id, message);
+                    state.records += 1;
+                    offset += consumed;
+                }
+                None if state.records == 0 && record_is_complete(&data[offset..]) => {
+                    // The first record failing authentication means the wrong key, not a torn write
+                    return Err(MessagingError::HistoryCorrupt(format!(
+                        "{} cannot be opened with this device's storage key",
+                        self.path.display()
+                    )));
+                }
+                None => {
+                    log::warn!(
+                        "Discarding {} unreadable bytes at the end of {}",
+                        data.len() - offset,
+                        self.path.display()
+                    );
+                    discarded = true;
+                    break;
+                }
+            }
+        }
+
+        let peers: Vec<PeerId> = state.messages.values().map(|m| m.peer_id.clone()).collect();
+        for peer_id in peers {
+            self.trim(&mut state, &peer_id);
+        }
+        if discarded || state.records > state.messages.len() + COMPACT_SLACK {
+            self.rewrite(&mut state).await?;
+        }
+        Ok(())
+    }
+
+    /// Drop the oldest non-queued messages of a conversation over the limit
+    fn trim(&self, state: &mut HistoryState, peer_id: &str) {
+        let total = state.messages.values().filter(|m| m.peer_id == peer_id).count();
+        if total <= self.max_per_peer {
+            return;
+        }
+
+        let mut droppable: Vec<(u64, MessageId)> = state
+            .messages
+            .values()
+            .filter(|m| m.peer_id == peer_id && m.status != MessageStatus::Queued)
+            .map(|m| (m.sent_at, m.id))
+            .collect();
+        droppable.sort();
+        for (_, id) in droppable.into_iter().take(total - self.max_per_peer) {
+            state.messages.remove(&id);
+        }
+    }
+
+    async fn maybe_compact(&self, state: &mut HistoryState) -> MessagingResult<()> {
+        if state.records > 2 * state.messages.len() + COMPACT_SLACK {
+            self.rewrite(state).await?;
+        }
+        Ok(())
+    }
+
+    async fn append(&self, state: &mut HistoryState, message: &ChatMessage) -> MessagingResult<()> {
+        let record = self.seal_record(message)?;
+        if state.file.is_none() {
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await
+                .map_err(|e| MessagingError::io(&self.path, e))?;
+            state.file = Some(file);
+        }
+
+        let file = state.file.as_mut().expect("history file opened above");
+        file.write_all(&record)
+            .await
+            .map_err(|e| MessagingError::io(&self.path, e))?;
+        file.sync_data()
+            .await
+            .map_err(|e| MessagingError::io(&self.path, e))?;
+        state.records += 1;
+        Ok(())
+    }
+
+    async fn rewrite(&self, state: &mut HistoryState) -> MessagingResult<()> {
+        let mut data = Vec::new();
+        for message in state.messages.values() {
+            data.extend_from_slice(&self.seal_record(message)?);
+        }
+
+        // Close the append handle first so it does not keep writing to the replaced inode
+        state.file = None;
+        atomic_write(&self.path, &data)
+            .await
+            .map_err(|e| MessagingError::io(&self.path, e))?;
+        state.records = state.messages.len();
+        Ok(())
+    }
+
+    fn seal_record(&self, message: &ChatMessage) -> MessagingResult<Vec<u8>> {
+        let plaintext = serde_json::to_vec(message)?;
+        let sealed = self
+            .storage
+            .seal(&plaintext, HISTORY_CONTEXT)
+            .map_err(|e| MessagingError::encryption("seal_history", e))?;
+
+        let mut record = Vec::with_capacity(LEN_PREFIX + sealed.len());
+        record.extend_from_slice(&(sealed.len() as u32).to_le_bytes());
+        record.extend_from_slice(&sealed);
+        Ok(record)
+    }
+
+    /// Decode the record at the start of `data`, returning it and its encoded length
+    fn open_record(&self, data: &[u8]) -> Option<(ChatMessage, usize)> {
+        let prefix: [u8; LEN_PREFIX] = data.get(..LEN_PREFIX)?.try_into().ok()?;
+        let body_len = u32::from_le_bytes(prefix) as usize;
+        let body = data.get(LEN_PREFIX..LEN_PREFIX + body_len)?;
+        let plaintext = self.storage.open(body, HISTORY_CONTEXT).ok()?;
+        let message = serde_json::from_slice(&plaintext).ok()?;
+        Some((message, LEN_PREFIX + body_len))
+    }
+}
+
+/// Whether `data` starts with a record whose full length is present
+fn record_is_complete(data: &[u8]) -> bool {
+    let Some(prefix) = data.get(..LEN_PREFIX) else {
+        return false;
+    };
+    let body_len = u32::from_le_bytes(prefix.try_into().expect("prefix is four bytes")) as usize;
+    data.len() >= LEN_PREFIX + body_len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::storage::StorageKey;
+    use tempfile::TempDir;
+
+    fn storage(key: u8) -> EncryptedStorage {
+        EncryptedStorage::new(StorageKey::from_bytes([key; 32]))
+    }
+
+    #[tokio::test]
+    async fn test_history_survives_reopen() {
+        let dir = TempDir::new().unwrap();
+        let message = ChatMessage::outgoing("peer", "hello there");
+        {
+            let history = MessageHistory::open(dir.path(), storage(1), 100).await.unwrap();
+            assert!(history.insert(message.clone()).await.unwrap());
+            assert!(!history.insert(message.clone()).await.unwrap());
+            history.advance(&message.id, MessageStatus::Delivered).await.unwrap();
+        }
+
+        let history = MessageHistory::open(dir.path(), storage(1), 100).await.unwrap();
+        let stored = history.get(&message.id).await.unwrap();
+        assert_eq!(stored.text, "hello there");
+        assert_eq!(stored.status, MessageStatus::Delivered);
+    }
+
+    #[tokio::test]
+    async fn test_history_is_encrypted_at_rest() {
+        let dir = TempDir::new().unwrap();
+        let history = MessageHistory::open(dir.path(), storage(1), 100).await.unwrap();
+        history.insert(ChatMessage::outgoing("peer", "secret plans")).await.unwrap();
+
+        let raw = std::fs::read(history.path()).unwrap();
+        assert!(!raw.windows(6).any(|w| w == b"secret"));
+
+        let err = MessageHistory::open(dir.path(), storage(7), 100).await.err().unwrap();
+        assert!(matches!(err, MessagingError::HistoryCorrupt(_)));
+    }
+
+    #[tokio::test]
+    async fn test_torn_tail_is_discarded() {
+        let dir = TempDir::new().unwrap();
+        let (first, second) = (ChatMessage::outgoing("peer", "one"), ChatMessage::outgoing("peer", "two"));
+        let path = {
+            let history = MessageHistory::open(dir.path(), storage(1), 100).await.unwrap();
+            history.insert(first.clone()).await.unwrap();
+            history.insert(second.clone()).await.unwrap();
+            history.path().to_path_buf()
+        };
+
+        let len = std::fs::metadata(&path).unwrap().len();
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(len - 3).unwrap();
+
+        let history = MessageHistory::open(dir.path(), storage(1), 100).await.unwrap();
+        assert!(history.get(&first.id).await.is_some());
+        assert!(history.get(&second.id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_trim_keeps_queued_messages() {
+        let dir = TempDir::new().unwrap();
+        let history = MessageHistory::open(dir.path(), storage(1), 2).await.unwrap();
+        let queued = ChatMessage::outgoing("peer", "pending");
+        history.insert(queued.clone()).await.unwrap();
+        for text in ["a", "b", "c"] {
+            let mut message = ChatMessage::outgoing("peer", text);
+            message.advance(MessageStatus::Sent);
+            history.insert(message).await.unwrap();
+        }
+
+        let conversation = history.conversation("peer").await;
+        assert_eq!(conversation.len(), 2);
+        assert!(conversation.iter().any(|m| m.id == queued.id));
+        assert_eq!(history.queued("peer").await.len(), 1);
+    }
+}
@@ -0,0 +1,223 @@
+//! Peer-to-peer text messaging
+//!
+//! Short text messages between trusted peers, carried on the `messages` channel
+//! of each peer's multiplexed session. Message bodies are encrypted with the
+//! peer's security session before they leave the device, and the local history
+//! is sealed at rest with a device-local key.
+//!
+//! Outgoing messages move through `Queued` → `Sent` → `Delivered` → `Read` as the
+//! peer acknowledges them. A message to a peer that cannot be reached stays
+//! `Queued` in the history and goes out when [`MessagingSystem::flush`] is called
+//! for that peer, typically when discovery sees it again.
+//!
+//! [`MessagingSystem::broadcast`] sends one small message to a whole device
+//! group; see [`broadcast`] for how its delivery is tracked.
+
+pub mod error;
+pub mod protocol;
+pub mod history;
+pub mod api;
+pub mod broadcast;
+
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+pub use error::{MessagingError, MessagingResult};
+pub use protocol::MessageFrame;
+pub use history::MessageHistory;
+pub use api::{MessagingConfig, MessagingSystem};
+pub use broadcast::{BroadcastMessage, BroadcastReport, RecipientStatus};
+
+/// Unique identifier for a message, chosen by the sender
+pub type MessageId = Uuid;
+
+/// Unique identifier for peers
+pub type PeerId = String;
+
+/// Delivery state of a message
+///
+/// Statuses only move forward; a late `Delivered` receipt never downgrades a
+/// message that is already `Read`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageStatus {
+    /// Stored locally, waiting for the peer to become reachable
+    Queued,
+    /// Written to the peer's session
+    Sent,
+    /// The peer acknowledged receipt
+    Delivered,
+    /// The peer (or, for incoming messages, the local user) read it
+    Read,
+    /// Will not be delivered
+    Failed,
+}
+
+impl MessageStatus {
+    /// Whether a message in this state may move to `next`
+    pub fn can_advance_to(self, next: MessageStatus) -> bool {
+        match (self, next) {
+            (MessageStatus::Queued, MessageStatus::Failed) => true,
+            (MessageStatus::Failed, _) | (_, MessageStatus::Failed) => false,
+            _ => next > self,
+        }
+    }
+}
+
+impl std::fmt::Display for MessageStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            MessageStatus::Queued => "queued",
+            MessageStatus::Sent => "sent",
+            MessageStatus::Delivered => "delivered",
+            MessageStatus::Read => "read",
+            MessageStatus::Failed => "failed",
+        };
+        f.write_str(label)
+    }
+}
+
+/// Which side wrote a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Outgoing,
+    Incoming,
+}
+
+/// Acknowledgement a recipient sends back to the author
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptKind {
+    Delivered,
+    Read,
+}
+
+impl From<ReceiptKind> for MessageStatus {
+    fn from(kind: ReceiptKind) -> Self {
+        match kind {
+            ReceiptKind::Delivered => MessageStatus::Delivered,
+            ReceiptKind::Read => MessageStatus::Read,
+        }
+    }
+}
+
+/// A text message in a conversation with one peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// Message ID, shared by both sides
+    pub id: MessageId,
+    /// The other side of the conversation
+    pub peer_id: PeerId,
+    /// Whether we wrote or received the message
+    pub direction: Direction,
+    /// Message text
+    pub text: String,
+    /// Author's clock when the message was written, in milliseconds since the epoch
+    pub sent_at: u64,
+    /// Local clock at the last status change, in milliseconds since the epoch
+    pub updated_at: u64,
+    /// Delivery state
+    pub status: MessageStatus,
+    /// For incoming messages, the last receipt sent back to the author
+    #[serde(default)]
+    pub receipt_sent: Option<ReceiptKind>,
+}
+
+impl ChatMessage {
+    /// Create a new queued outgoing message
+    pub fn outgoing(peer_id: impl Into<PeerId>, text: impl Into<String>) -> Self {
+        let now = now_millis();
+        Self {
+            id: Uuid::new_v4(),
+            peer_id: peer_id.into(),
+            direction: Direction::Outgoing,
+            text: text.into(),
+            sent_at: now,
+            updated_at: now,
+            status: MessageStatus::Queued,
+            receipt_sent: None,
+        }
+    }
+
+    /// Create a message received from a peer
+    pub fn incoming(id: MessageId, peer_id: impl Into<PeerId>, text: impl Into<String>, sent_at: u64) -> Self {
+        Self {
+            id,
+            peer_id: peer_id.into(),
+            direction: Direction::Incoming,
+            text: text.into(),
+            sent_at,
+            updated_at: now_millis(),
+            status: MessageStatus::Delivered,
+            receipt_sent: None,
+        }
+    }
+
+    /// Move to `status` if that is a forward transition, returning whether it changed
+    pub fn advance(&mut self, status: MessageStatus) -> bool {
+        if !self.status.can_advance_to(status) {
+            return false;
+        }
+        self.status = status;
+        self.updated_at = now_millis();
+        true
+    }
+
+    /// Whether this is an incoming message the local user has not read yet
+    pub fn is_unread(&self) -> bool {
+        self.direction == Direction::Incoming && self.status < MessageStatus::Read
+    }
+}
+
+/// Events published by the messaging subsystem
+#[derive(Debug, Clone)]
+pub enum MessagingEvent {
+    /// A new message arrived from a peer
+    Received(ChatMessage),
+    /// An outgoing message changed state
+    StatusChanged {
+        id: MessageId,
+        peer_id: PeerId,
+        status: MessageStatus,
+    },
+    /// A broadcast arrived from a peer
+    BroadcastReceived(BroadcastMessage),
+    /// A recipient of one of our broadcasts changed state
+    BroadcastStatusChanged {
+        id: MessageId,
+        peer_id: PeerId,
+        status: MessageStatus,
+    },
+}
+
+/// Current time in milliseconds since the Unix epoch
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_only_moves_forward() {
+        let mut message = ChatMessage::outgoing("peer", "hi");
+        assert!(message.advance(MessageStatus::Sent));
+        assert!(message.advance(MessageStatus::Read));
+        assert!(!message.advance(MessageStatus::Delivered));
+        assert!(!message.advance(MessageStatus::Failed));
+        assert_eq!(message.status, MessageStatus::Read);
+    }
+
+    #[test]
+    fn test_only_queued_messages_fail() {
+        assert!(MessageStatus::Queued.can_advance_to(MessageStatus::Failed));
+        assert!(!MessageStatus::Sent.can_advance_to(MessageStatus::Failed));
+        assert!(!MessageStatus::Failed.can_advance_to(MessageStatus::Sent));
+    }
+}
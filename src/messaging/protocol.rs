@@ -0,0 +1,49 @@
+//! Wire format for the messaging channel
+//!
+//! Each frame is a JSON object sent with `MuxStream::send_message`. Only the
+//! message text is encrypted; IDs and timestamps travel in the clear so that
+//! receipts can be matched without a decryption round trip.
+
+use serde::{Deserialize, Serialize};
+
+use crate::messaging::{MessageId, MessagingResult, ReceiptKind};
+
+/// Largest frame accepted from a peer
+pub const MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// A frame on the `messages` channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessageFrame {
+    /// A new message
+    Text {
+        id: MessageId,
+        sent_at: u64,
+        /// Message text encrypted with the pair's security session
+        ciphertext: Vec<u8>,
+    },
+    /// A broadcast sent to several peers at once
+    Broadcast {
+        id: MessageId,
+        sent_at: u64,
+        /// Topic and body encrypted with the pair's security session
+        ciphertext: Vec<u8>,
+    },
+    /// Acknowledgement for one or more messages or broadcasts
+    Receipt {
+        ids: Vec<MessageId>,
+        kind: ReceiptKind,
+    },
+}
+
+impl MessageFrame {
+    /// Serialize for sending
+    pub fn encode(&self) -> MessagingResult<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Parse a received frame
+    pub fn decode(bytes: &[u8]) -> MessagingResult<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
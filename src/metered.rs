@@ -0,0 +1,444 @@
+//! Metered connection detection and traffic policy
+//!
+//! A [`TrafficMonitor`] works out whether the current connection costs money
+//! per byte, either from the operating system (NetworkManager on Linux,
+//! connection cost on Windows), from hotspot heuristics, or from a manual flag
+//! when neither is available. While metered, it publishes a restrictive
+//! [`TrafficPolicy`]: bulk transfers wait, images are not synced from the
+//! clipboard automatically and streams are capped to a lower bitrate.
+//!
+//! Background work is simply held back. Operations the user starts explicitly
+//! get [`TrafficDecision::Confirm`] instead, so the caller can ask whether to
+//! spend the data anyway.
+
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Where a metered verdict came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeteredSource {
+    /// The operating system reports the connection cost
+    Platform,
+    /// The network looks like a phone hotspot
+    Hotspot,
+    /// Set by the user
+    Manual,
+}
+
+/// Whether the current connection is metered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionCost {
+    pub metered: bool,
+    pub source: MeteredSource,
+}
+
+/// Detects metered connections
+pub trait MeteredDetector: Send + Sync {
+    /// `None` when the platform cannot tell
+    fn detect(&self) -> Option<ConnectionCost>;
+}
+
+/// Traffic settings, part of the user configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrafficPolicyConfig {
+    /// Force the connection to be treated as metered or not; unset to detect
+    pub metered: Option<bool>,
+    /// Hold back large transfers while metered
+    pub pause_bulk_transfers: bool,
+    /// Transfers at least this large are bulk
+    pub bulk_transfer_bytes: u64,
+    /// Stop syncing copied images automatically while metered
+    pub pause_image_clipboard_sync: bool,
+    /// Highest stream bitrate while metered, in bits per second
+    pub metered_stream_bitrate: Option<u32>,
+    /// Seconds between connection checks
+    pub check_interval_secs: u64,
+}
+
+impl Default for TrafficPolicyConfig {
+    fn default() -> Self {
+        Self {
+            metered: None,
+            pause_bulk_transfers: true,
+            bulk_transfer_bytes: 10 * 1024 * 1024,
+            pause_image_clipboard_sync: true,
+            metered_stream_bitrate: Some(1_000_000),
+            check_interval_secs: 60,
+        }
+    }
+}
+
+/// Restrictions in force for the current connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficPolicy {
+    /// The connection verdict the policy was built from, if known
+    pub connection: Option<ConnectionCost>,
+    /// Smallest transfer held back; `None` lets every transfer run
+    pub bulk_transfer_bytes: Option<u64>,
+    pub image_clipboard_sync: bool,
+    pub max_stream_bitrate: Option<u32>,
+}
+
+/// Something that uses the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrafficOperation {
+    Transfer { bytes: u64 },
+    ImageClipboardSync,
+    Stream { bitrate: u32 },
+}
+
+/// Who started an operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Initiator {
+    /// Queued, synced or retried without the user asking just now
+    Background,
+    /// The user explicitly asked for it
+    User,
+}
+
+/// What to do with an operation under the current policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrafficDecision {
+    Allow,
+    /// Go ahead at no more than this bitrate
+    Cap { bitrate: u32 },
+    /// Ask the user whether to go ahead
+    Confirm { reason: String },
+    /// Wait until the connection is no longer metered
+    Defer { reason: String },
+}
+
+impl TrafficPolicy {
+    /// No restrictions
+    pub fn unrestricted(connection: Option<ConnectionCost>) -> Self {
+        Self {
+            connection,
+            bulk_transfer_bytes: None,
+            image_clipboard_sync: true,
+            max_stream_bitrate: None,
+        }
+    }
+
+    /// Policy for a connection under the given settings
+    pub fn for_connection(config: &TrafficPolicyConfig, connection: Option<ConnectionCost>) -> Self {
+        if !connection.is_some_and(|cost| cost.metered) {
+            return Self::unrestricted(connection);
+        }
+
+        Self {
+            connection,
+            bulk_transfer_bytes: config.pause_bulk_transfers.then_some(config.bulk_transfer_bytes),
+            image_clipboard_sync: !config.pause_image_clipboard_sync,
+            max_stream_bitrate: config.metered_stream_bitrate,
+        }
+    }
+
+    pub fn is_metered(&self) -> bool {
+        self.connection.is_some_and(|cost| cost.metered)
+    }
+
+    /// Whether a transfer of this size is held back
+    pub fn is_bulk(&self, bytes: u64) -> bool {
+        self.bulk_transfer_bytes.is_some_and(|threshold| bytes >= threshold)
+    }
+
+    /// Decide what to do with an operation
+    pub fn check(&self, operation: TrafficOperation, initiator: Initiator) -> TrafficDecision {
+        let reason = match operation {
+            TrafficOperation::Transfer { bytes } if self.is_bulk(bytes) => {
+                format!("Sending {} over a metered connection", format_bytes(bytes))
+            }
+            TrafficOperation::ImageClipboardSync if !self.image_clipboard_sync => {
+                "Syncing a copied image over a metered connection".to_string()
+            }
+            TrafficOperation::Stream { bitrate } => {
+                return match self.max_stream_bitrate {
+                    Some(cap) if bitrate > cap => TrafficDecision::Cap { bitrate: cap },
+                    _ => TrafficDecision::Allow,
+                };
+            }
+            _ => return TrafficDecision::Allow,
+        };
+
+        match initiator {
+            Initiator::User => TrafficDecision::Confirm { reason },
+            Initiator::Background => TrafficDecision::Defer { reason },
+        }
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MIB: u64 = 1024 * 1024;
+    if bytes >= 1024 * MIB {
+        format!("{:.1} GiB", bytes as f64 / (1024 * MIB) as f64)
+    } else {
+        format!("{:.1} MiB", bytes as f64 / MIB as f64)
+    }
+}
+
+/// Detects metered connections with the tools each platform ships
+pub struct SystemMeteredDetector;
+
+impl SystemMeteredDetector {
+    /// Run a helper program and return its stdout
+    #[cfg_attr(not(any(target_os = "linux", target_os = "windows", target_os = "macos")), allow(dead_code))]
+    fn run(program: &str, args: &[&str]) -> Option<String> {
+        let output = Command::new(program).args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Parse NetworkManager's global `Metered` property as printed by busctl
+    pub fn parse_network_manager(output: &str) -> Option<ConnectionCost> {
+        // NMMetered: 0 unknown, 1 yes, 2 no, 3 guess-yes, 4 guess-no
+        let value = output.trim().strip_prefix("u ")?.trim().parse::<u32>().ok()?;
+        match value {
+            1 => Some(ConnectionCost { metered: true, source: MeteredSource::Platform }),
+            // Guesses come from hotspot hints such as Android's DHCP vendor option
+            3 => Some(ConnectionCost { metered: true, source: MeteredSource::Hotspot }),
+            2 | 4 => Some(ConnectionCost { metered: false, source: MeteredSource::Platform }),
+            _ => None,
+        }
+    }
+
+    /// Parse "<NetworkCostType> <Roaming> <OverDataLimit>" from the Windows connection profile
+    pub fn parse_windows_cost(output: &str) -> Option<ConnectionCost> {
+        let mut fields = output.split_whitespace();
+        let cost_type = fields.next()?;
+        let roaming = fields.next().is_some_and(|f| f.eq_ignore_ascii_case("true"));
+        let over_limit = fields.next().is_some_and(|f| f.eq_ignore_ascii_case("true"));
+
+        let metered = match cost_type {
+            "Unrestricted" => roaming || over_limit,
+            "Fixed" | "Variable" => true,
+            _ => return None,
+        };
+        Some(ConnectionCost { metered, source: MeteredSource::Platform })
+    }
+
+    /// Parse `route -n get default` on macOS, spotting iPhone Personal Hotspot
+    pub fn parse_macos_route(output: &str) -> Option<ConnectionCost> {
+        let gateway = output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("gateway:"))?
+            .trim()
+            .parse::<std::net::Ipv4Addr>()
+            .ok()?;
+
+        // Personal Hotspot always hands out 172.20.10.0/28
+        let octets = gateway.octets();
+        let hotspot = octets[..3] == [172, 20, 10] && octets[3] < 16;
+        hotspot.then_some(ConnectionCost { metered: true, source: MeteredSource::Hotspot })
+    }
+}
+
+impl MeteredDetector for SystemMeteredDetector {
+    fn detect(&self) -> Option<ConnectionCost> {
+        #[cfg(target_os = "linux")]
+        {
+            let output = Self::run(
+                "busctl",
+                &[
+                    "get-property",
+                    "org.freedesktop.NetworkManager",
+                    "/org/freedesktop/NetworkManager",
+                    "org.freedesktop.NetworkManager",
+                    "Metered",
+                ],
+            )?;
+            Self::parse_network_manager(&output)
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            const SCRIPT: &str = "$p = [Windows.Networking.Connectivity.NetworkInformation, Windows.Networking.Connectivity, ContentType=WindowsRuntime]::GetInternetConnectionProfile(); \
+                 if ($p) { $c = $p.GetConnectionCost(); \"$($c.NetworkCostType) $($c.Roaming) $($c.OverDataLimit)\" }";
+            let output = Self::run("powershell", &["-NoProfile", "-NonInteractive", "-Command", SCRIPT])?;
+            Self::parse_windows_cost(&output)
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let output = Self::run("route", &["-n", "get", "default"])?;
+            Self::parse_macos_route(&output)
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+        {
+            None
+        }
+    }
+}
+
+/// Tracks the connection and publishes the traffic policy
+pub struct TrafficMonitor {
+    detector: Arc<dyn MeteredDetector>,
+    config: RwLock<TrafficPolicyConfig>,
+    policy: watch::Sender<TrafficPolicy>,
+}
+
+impl TrafficMonitor {
+    /// Create a monitor using the platform detector
+    pub fn new(config: TrafficPolicyConfig) -> Self {
+        Self::with_detector(config, Arc::new(SystemMeteredDetector))
+    }
+
+    /// Create a monitor with a custom detector
+    pub fn with_detector(config: TrafficPolicyConfig, detector: Arc<dyn MeteredDetector>) -> Self {
+        let (policy, _) = watch::channel(TrafficPolicy::unrestricted(None));
+        Self {
+            detector,
+            config: RwLock::new(config),
+            policy,
+        }
+    }
+
+    /// Policy currently in force
+    pub fn policy(&self) -> TrafficPolicy {
+        *self.policy.borrow()
+    }
+
+    /// Watch for policy changes
+    pub fn subscribe(&self) -> watch::Receiver<TrafficPolicy> {
+        self.policy.subscribe()
+    }
+
+    /// Force the connection to be treated as metered or not; `None` detects again
+    pub async fn set_manual(&self, metered: Option<bool>) -> TrafficPolicy {
+        self.config.write().expect("Lock poisoned").metered = metered;
+        self.refresh().await
+    }
+
+    /// Replace the settings and re-evaluate
+    pub async fn set_config(&self, config: TrafficPolicyConfig) -> TrafficPolicy {
+        *self.config.write().expect("Lock poisoned") = config;
+        self.refresh().await
+    }
+
+    /// Check the connection now and publish the resulting policy
+    pub async fn refresh(&self) -> TrafficPolicy {
+        let config = self.config.read().expect("Lock poisoned").clone();
+
+        let connection = match config.metered {
+            Some(metered) => Some(ConnectionCost { metered, source: MeteredSource::Manual }),
+            None => {
+                let detector = Arc::clone(&self.detector);
+                tokio::task::spawn_blocking(move || detector.detect()).await.unwrap_or(None)
+            }
+        };
+
+        let policy = TrafficPolicy::for_connection(&config, connection);
+        self.policy.send_if_modified(|current| {
+            if *current == policy {
+                return false;
+            }
+            log::info!(
+                "Connection is {}; traffic policy updated",
+                if policy.is_metered() { "metered" } else { "not metered" }
+            );
+            *current = policy;
+            true
+        });
+        policy
+    }
+
+    /// Re-check the connection periodically until the monitor is dropped
+    pub fn spawn(self: &Arc<Self>) -> JoinHandle<()> {
+        let weak = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                let Some(monitor) = weak.upgrade() else { break };
+                monitor.refresh().await;
+                let interval = monitor.config.read().expect("Lock poisoned").check_interval_secs.max(1);
+                drop(monitor);
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedDetector(Option<ConnectionCost>);
+
+    impl MeteredDetector for FixedDetector {
+        fn detect(&self) -> Option<ConnectionCost> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_platform_output_parsing() {
+        assert_eq!(
+            SystemMeteredDetector::parse_network_manager("u 3\n"),
+            Some(ConnectionCost { metered: true, source: MeteredSource::Hotspot })
+        );
+        assert_eq!(SystemMeteredDetector::parse_network_manager("u 4").map(|c| c.metered), Some(false));
+        assert_eq!(SystemMeteredDetector::parse_network_manager("u 0"), None);
+
+        assert_eq!(SystemMeteredDetector::parse_windows_cost("Variable False False").map(|c| c.metered), Some(true));
+        assert_eq!(SystemMeteredDetector::parse_windows_cost("Unrestricted True False").map(|c| c.metered), Some(true));
+        assert_eq!(SystemMeteredDetector::parse_windows_cost("Unrestricted False False").map(|c| c.metered), Some(false));
+        assert_eq!(SystemMeteredDetector::parse_windows_cost("Unknown False False"), None);
+
+        let route = "   route to: default\ndestination: default\n    gateway: 172.20.10.1\n  interface: en0\n";
+        assert_eq!(
+            SystemMeteredDetector::parse_macos_route(route).map(|c| c.source),
+            Some(MeteredSource::Hotspot)
+        );
+        assert_eq!(SystemMeteredDetector::parse_macos_route("gateway: 192.168.1.1"), None);
+    }
+
+    #[test]
+    fn test_metered_policy_defers_background_and_confirms_user_operations() {
+        let config = TrafficPolicyConfig::default();
+        let metered = Some(ConnectionCost { metered: true, source: MeteredSource::Platform });
+        let policy = TrafficPolicy::for_connection(&config, metered);
+
+        let big = TrafficOperation::Transfer { bytes: 50 * 1024 * 1024 };
+        let small = TrafficOperation::Transfer { bytes: 4096 };
+        assert!(matches!(policy.check(big, Initiator::Background), TrafficDecision::Defer { .. }));
+        assert!(matches!(policy.check(big, Initiator::User), TrafficDecision::Confirm { .. }));
+        assert_eq!(policy.check(small, Initiator::Background), TrafficDecision::Allow);
+        assert!(matches!(
+            policy.check(TrafficOperation::ImageClipboardSync, Initiator::Background),
+            TrafficDecision::Defer { .. }
+        ));
+        assert_eq!(
+            policy.check(TrafficOperation::Stream { bitrate: 6_000_000 }, Initiator::User),
+            TrafficDecision::Cap { bitrate: 1_000_000 }
+        );
+
+        let unmetered = TrafficPolicy::for_connection(&config, None);
+        assert_eq!(unmetered.check(big, Initiator::Background), TrafficDecision::Allow);
+    }
+
+    #[tokio::test]
+    async fn test_manual_flag_overrides_detection() {
+        let detector = Arc::new(FixedDetector(Some(ConnectionCost {
+            metered: false,
+            source: MeteredSource::Platform,
+        })));
+        let monitor = TrafficMonitor::with_detector(TrafficPolicyConfig::default(), detector);
+        let mut changes = monitor.subscribe();
+
+        assert!(!monitor.refresh().await.is_metered());
+
+        let policy = monitor.set_manual(Some(true)).await;
+        assert_eq!(policy.connection.map(|c| c.source), Some(MeteredSource::Manual));
+        assert!(changes.has_changed().unwrap());
+        assert!(!changes.borrow_and_update().image_clipboard_sync);
+
+        assert!(!monitor.set_manual(None).await.is_metered());
+    }
+}
@@ -190,6 +190,11 @@ impl PlatformAdapter for GenericAdapter {
     fn platform_name(&self) -> &str {
         &self.name
     }
+
+    fn global_hotkeys(&self) -> Option<Arc<dyn crate::platform::GlobalHotkeys>> {
+        // No desktop to take hotkeys from
+        None
+    }
 }
 
 #[cfg(test)]
@@ -80,6 +80,18 @@ fn detect_runtime_info() -> RuntimeInfo {
 fn detect_hardware_info() -> HardwareInfo {
     use sysinfo::System;
     
+    // A full sysinfo refresh walks every process and disk, which takes
+    // seconds on an SBC, so constrained devices only read /proc/meminfo
+    if !crate::platform::ResourceProfile::active().hardware_probing {
+        return HardwareInfo {
+            cpu_count: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            total_memory_mb: get_total_memory_mb(),
+            has_gpu: false,
+            has_hardware_crypto: detect_hardware_crypto(),
+            has_simd: detect_simd_support(),
+        };
+    }
+    
     let mut sys = System::new_all();
     sys.refresh_all();
     
@@ -142,7 +154,7 @@ fn detect_os() -> OperatingSystem {
 }
 
 /// Detect CPU architecture
-fn detect_architecture() -> Architecture {
+pub(crate) fn detect_architecture() -> Architecture {
     #[cfg(target_arch = "x86_64")]
     {
         Architecture::X86_64
@@ -306,8 +318,11 @@ pub fn detect_capabilities(os: &OperatingSystem, arch: &Architecture) -> Platfor
 
 /// Discover capabilities at runtime
 fn discover_runtime_capabilities(capabilities: &mut PlatformCapabilities, os: &OperatingSystem) {
+    // GPU and codec probes can hang on SBC display drivers
+    let probe_hardware = crate::platform::ResourceProfile::active().hardware_probing;
+    
     // Check for GPU availability
-    if detect_gpu_availability() {
+    if probe_hardware && detect_gpu_availability() {
         capabilities.hardware_acceleration.insert(crate::platform::HardwareFeature::GPU);
     }
     
@@ -318,7 +333,7 @@ fn discover_runtime_capabilities(capabilities: &mut PlatformCapabilities, os: &O
     }
     
     // Check for video codec support
-    if detect_video_codec_support() {
+    if probe_hardware && detect_video_codec_support() {
         capabilities.hardware_acceleration.insert(crate::platform::HardwareFeature::VideoCodec);
     }
     
@@ -550,7 +565,7 @@ fn detect_display_server() -> Option<String> {
 }
 
 /// Get total system memory in MB
-fn get_total_memory_mb() -> u64 {
+pub(crate) fn get_total_memory_mb() -> u64 {
     #[cfg(target_os = "linux")]
     {
         use std::fs;
@@ -617,7 +632,11 @@ fn detect_hardware_crypto() -> bool {
 
 /// Detect SIMD support
 fn detect_simd_support() -> bool {
-    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    #[cfg(any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "arm", target_feature = "neon")
+    ))]
     {
         return true;
     }
@@ -0,0 +1,138 @@
+// X11 global hotkeys
+//
+// Each hotkey is grabbed on the root window from its own display connection
+// and thread. The grab is repeated with Caps Lock and Num Lock held, since
+// X11 matches modifier masks exactly.
+
+use super::{GlobalHotkeys, Hotkey, HotkeyKey};
+use crate::platform::{PlatformError, PlatformResult};
+use std::ffi::CString;
+use std::os::raw::{c_int, c_uint};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use x11::xlib;
+
+/// Set by the error handler when another client already owns a grab
+static GRAB_FAILED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn on_grab_error(_display: *mut xlib::Display, _event: *mut xlib::XErrorEvent) -> c_int {
+    GRAB_FAILED.store(true, Ordering::SeqCst);
+    0
+}
+
+/// Global hotkeys grabbed from the X server
+pub struct X11Hotkeys;
+
+impl X11Hotkeys {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Whether an X server (or XWayland) is reachable
+    pub fn is_available() -> bool {
+        std::env::var_os("DISPLAY").is_some()
+    }
+
+    fn modifier_mask(hotkey: &Hotkey) -> c_uint {
+        let mut mask = 0;
+        if hotkey.ctrl {
+            mask |= xlib::ControlMask;
+        }
+        if hotkey.alt {
+            mask |= xlib::Mod1Mask;
+        }
+        if hotkey.shift {
+            mask |= xlib::ShiftMask;
+        }
+        if hotkey.meta {
+            mask |= xlib::Mod4Mask;
+        }
+        mask
+    }
+
+    fn keysym_name(key: HotkeyKey) -> String {
+        match key {
+            HotkeyKey::Char(c) => c.to_string(),
+            HotkeyKey::Function(n) => format!("F{}", n),
+            HotkeyKey::Escape => "Escape".to_string(),
+            HotkeyKey::Pause => "Pause".to_string(),
+            HotkeyKey::Space => "space".to_string(),
+        }
+    }
+}
+
+impl Default for X11Hotkeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GlobalHotkeys for X11Hotkeys {
+    fn register(&self, hotkey: &Hotkey, on_trigger: Arc<dyn Fn() + Send + Sync>) -> PlatformResult<()> {
+        let mask = Self::modifier_mask(hotkey);
+        let keysym_name = CString::new(Self::keysym_name(hotkey.key))
+            .map_err(|e| PlatformError::ConfigurationError(e.to_string()))?;
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<(), String>>();
+
+        std::thread::Builder::new()
+            .name("kizuna-hotkey".into())
+            .spawn(move || unsafe {
+                let display = xlib::XOpenDisplay(ptr::null());
+                if display.is_null() {
+                    let _ = ready_tx.send(Err("cannot open the X display".to_string()));
+                    return;
+                }
+
+                let keysym = xlib::XStringToKeysym(keysym_name.as_ptr());
+                let keycode = xlib::XKeysymToKeycode(display, keysym);
+                if keysym == 0 || keycode == 0 {
+                    let _ = ready_tx.send(Err("the key is not on this keyboard".to_string()));
+                    xlib::XCloseDisplay(display);
+                    return;
+                }
+
+                let root = xlib::XDefaultRootWindow(display);
+                GRAB_FAILED.store(false, Ordering::SeqCst);
+                let previous = xlib::XSetErrorHandler(Some(on_grab_error));
+                for locks in [0, xlib::LockMask, xlib::Mod2Mask, xlib::LockMask | xlib::Mod2Mask] {
+                    xlib::XGrabKey(
+                        display,
+                        c_int::from(keycode),
+                        mask | locks,
+                        root,
+                        xlib::True,
+                        xlib::GrabModeAsync,
+                        xlib::GrabModeAsync,
+                    );
+                }
+                xlib::XSync(display, xlib::False);
+                xlib::XSetErrorHandler(previous);
+
+                if GRAB_FAILED.load(Ordering::SeqCst) {
+                    let _ = ready_tx.send(Err("another program may own it".to_string()));
+                    xlib::XCloseDisplay(display);
+                    return;
+                }
+                let _ = ready_tx.send(Ok(()));
+
+                let mut event: xlib::XEvent = std::mem::zeroed();
+                loop {
+                    xlib::XNextEvent(display, &mut event);
+                    if event.get_type() == xlib::KeyPress {
+                        on_trigger();
+                    }
+                }
+            })
+            .map_err(|e| PlatformError::SystemError(format!("Failed to start hotkey thread: {}", e)))?;
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(reason)) => Err(PlatformError::IntegrationError(format!(
+                "Could not grab the {} hotkey: {}",
+                hotkey, reason
+            ))),
+            Err(_) => Err(PlatformError::IntegrationError(format!("Could not grab the {} hotkey", hotkey))),
+        }
+    }
+}
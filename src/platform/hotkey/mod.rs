@@ -0,0 +1,152 @@
+// Global hotkeys
+//
+// System-wide key combinations that reach Kizuna whichever application has
+// focus, such as the lockdown hotkey. Windows registers them with
+// RegisterHotKey and X11 desktops grab them on the root window. Wayland has
+// no global grabs, so there the compositor has to bind the key itself.
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(all(target_os = "linux", feature = "hotkeys"))]
+mod linux;
+
+use crate::platform::{PlatformError, PlatformResult};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Key pressed together with the modifiers of a [`Hotkey`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HotkeyKey {
+    /// An uppercase letter or a digit
+    Char(char),
+    /// F1 to F24
+    Function(u8),
+    Escape,
+    Pause,
+    Space,
+}
+
+impl fmt::Display for HotkeyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HotkeyKey::Char(c) => write!(f, "{}", c),
+            HotkeyKey::Function(n) => write!(f, "F{}", n),
+            HotkeyKey::Escape => write!(f, "Esc"),
+            HotkeyKey::Pause => write!(f, "Pause"),
+            HotkeyKey::Space => write!(f, "Space"),
+        }
+    }
+}
+
+/// A key combination such as "Ctrl+Alt+Shift+K"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// The Windows, Command or Super key
+    pub meta: bool,
+    pub key: HotkeyKey,
+}
+
+impl FromStr for Hotkey {
+    type Err = PlatformError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| PlatformError::ConfigurationError(format!("Invalid hotkey '{}': {}", s, reason));
+
+        let parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let (key, modifiers) = parts.split_last().ok_or_else(|| invalid("no key"))?;
+
+        let (mut ctrl, mut alt, mut shift, mut meta) = (false, false, false, false);
+        for modifier in modifiers {
+            let flag = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => &mut ctrl,
+                "alt" | "option" => &mut alt,
+                "shift" => &mut shift,
+                "super" | "win" | "cmd" | "meta" => &mut meta,
+                _ => return Err(invalid(&format!("unknown modifier '{}'", modifier))),
+            };
+            *flag = true;
+        }
+        // A bare key would be swallowed in every application
+        if !(ctrl || alt || shift || meta) {
+            return Err(invalid("needs at least one modifier"));
+        }
+
+        let lower = key.to_ascii_lowercase();
+        let key = match lower.as_str() {
+            "esc" | "escape" => HotkeyKey::Escape,
+            "pause" => HotkeyKey::Pause,
+            "space" => HotkeyKey::Space,
+            _ if key.len() == 1 && key.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                HotkeyKey::Char(key.to_ascii_uppercase().chars().next().unwrap_or_default())
+            }
+            _ => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=24) => HotkeyKey::Function(n),
+                _ => return Err(invalid(&format!("unknown key '{}'", key))),
+            },
+        };
+
+        Ok(Self { ctrl, alt, shift, meta, key })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [(self.ctrl, "Ctrl"), (self.alt, "Alt"), (self.shift, "Shift"), (self.meta, "Super")];
+        for (_, name) in modifiers.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// Registers system-wide hotkeys
+pub trait GlobalHotkeys: Send + Sync {
+    /// Call `on_trigger` whenever `hotkey` is pressed, in any application
+    fn register(&self, hotkey: &Hotkey, on_trigger: Arc<dyn Fn() + Send + Sync>) -> PlatformResult<()>;
+}
+
+/// Hotkey support built into this platform, if any
+pub fn platform_hotkeys() -> Option<Arc<dyn GlobalHotkeys>> {
+    #[cfg(target_os = "windows")]
+    {
+        Some(Arc::new(windows::WindowsHotkeys::new()))
+    }
+
+    #[cfg(all(target_os = "linux", feature = "hotkeys"))]
+    {
+        linux::X11Hotkeys::is_available().then(|| Arc::new(linux::X11Hotkeys::new()) as Arc<dyn GlobalHotkeys>)
+    }
+
+    #[cfg(not(any(target_os = "windows", all(target_os = "linux", feature = "hotkeys"))))]
+    {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotkey_parsing() {
+        let hotkey: Hotkey = "ctrl+alt+shift+k".parse().unwrap();
+        assert!(hotkey.ctrl && hotkey.alt && hotkey.shift && !hotkey.meta);
+        assert_eq!(hotkey.key, HotkeyKey::Char('K'));
+        assert_eq!(hotkey.to_string(), "Ctrl+Alt+Shift+K");
+
+        let hotkey: Hotkey = "Super + F12".parse().unwrap();
+        assert_eq!(hotkey.key, HotkeyKey::Function(12));
+        assert_eq!(hotkey.to_string(), "Super+F12");
+        assert_eq!("Ctrl+Alt+Esc".parse::<Hotkey>().unwrap().key, HotkeyKey::Escape);
+
+        assert!("K".parse::<Hotkey>().is_err());
+        assert!("Ctrl+".parse::<Hotkey>().is_err());
+        assert!("Hyper+K".parse::<Hotkey>().is_err());
+        assert!("Ctrl+F25".parse::<Hotkey>().is_err());
+        assert!("Ctrl+Tab".parse::<Hotkey>().is_err());
+    }
+}
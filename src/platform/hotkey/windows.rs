@@ -0,0 +1,96 @@
+// Windows global hotkeys
+//
+// Each hotkey is registered with RegisterHotKey on its own thread, which
+// owns the registration and pumps messages for WM_HOTKEY.
+
+use super::{GlobalHotkeys, Hotkey, HotkeyKey};
+use crate::platform::{PlatformError, PlatformResult};
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{mpsc, Arc};
+use winapi::um::winuser::{
+    GetMessageW, RegisterHotKey, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN, MSG, VK_ESCAPE, VK_F1,
+    VK_PAUSE, VK_SPACE, WM_HOTKEY,
+};
+
+/// Identifiers passed to RegisterHotKey
+static NEXT_HOTKEY_ID: AtomicI32 = AtomicI32::new(0x4b60);
+
+/// Global hotkeys registered with RegisterHotKey
+pub struct WindowsHotkeys;
+
+impl WindowsHotkeys {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn modifiers(hotkey: &Hotkey) -> u32 {
+        let mut modifiers = MOD_NOREPEAT;
+        if hotkey.ctrl {
+            modifiers |= MOD_CONTROL;
+        }
+        if hotkey.alt {
+            modifiers |= MOD_ALT;
+        }
+        if hotkey.shift {
+            modifiers |= MOD_SHIFT;
+        }
+        if hotkey.meta {
+            modifiers |= MOD_WIN;
+        }
+        modifiers as u32
+    }
+
+    fn virtual_key(key: HotkeyKey) -> u32 {
+        match key {
+            // Virtual-key codes of letters and digits are their ASCII codes
+            HotkeyKey::Char(c) => c as u32,
+            HotkeyKey::Function(n) => (VK_F1 + i32::from(n) - 1) as u32,
+            HotkeyKey::Escape => VK_ESCAPE as u32,
+            HotkeyKey::Pause => VK_PAUSE as u32,
+            HotkeyKey::Space => VK_SPACE as u32,
+        }
+    }
+}
+
+impl Default for WindowsHotkeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GlobalHotkeys for WindowsHotkeys {
+    fn register(&self, hotkey: &Hotkey, on_trigger: Arc<dyn Fn() + Send + Sync>) -> PlatformResult<()> {
+        let id = NEXT_HOTKEY_ID.fetch_add(1, Ordering::SeqCst);
+        let modifiers = Self::modifiers(hotkey);
+        let virtual_key = Self::virtual_key(hotkey.key);
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("kizuna-hotkey".into())
+            .spawn(move || {
+                let registered = unsafe { RegisterHotKey(ptr::null_mut(), id, modifiers, virtual_key) };
+                let _ = ready_tx.send(registered != 0);
+                if registered == 0 {
+                    return;
+                }
+
+                let mut msg: MSG = unsafe { mem::zeroed() };
+                while unsafe { GetMessageW(&mut msg, ptr::null_mut(), 0, 0) } > 0 {
+                    if msg.message == WM_HOTKEY && msg.wParam as i32 == id {
+                        on_trigger();
+                    }
+                }
+            })
+            .map_err(|e| PlatformError::SystemError(format!("Failed to start hotkey thread: {}", e)))?;
+
+        match ready_rx.recv() {
+            Ok(true) => Ok(()),
+            _ => Err(PlatformError::IntegrationError(format!(
+                "Could not register the {} hotkey; another program may own it",
+                hotkey
+            ))),
+        }
+    }
+}
@@ -15,6 +15,8 @@ pub mod metrics;
 pub mod build_system;
 pub mod deployment;
 pub mod feature_parity;
+pub mod profile;
+pub mod hotkey;
 
 // Platform-specific implementations
 #[cfg(target_os = "linux")]
@@ -51,6 +53,8 @@ pub use build_system::{
 };
 pub use deployment::*;
 pub use feature_parity::*;
+pub use profile::*;
+pub use hotkey::{GlobalHotkeys, Hotkey, HotkeyKey};
 
 use thiserror::Error;
 
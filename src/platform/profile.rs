@@ -0,0 +1,255 @@
+// Resource profiles for constrained devices
+//
+// Single-board computers and routers have a fraction of the memory of a
+// desktop and some of their drivers hang when probed. The active profile
+// scales local buffers, caches and connection limits to the device and
+// turns off the slow hardware probes. Wire-level sizes (transfer chunks,
+// mux windows, frame limits) are not part of the profile because both
+// peers have to agree on them.
+
+use crate::platform::Architecture;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Devices at or below this much memory always get the constrained profile
+pub const CONSTRAINED_MEMORY_MB: u64 = 1024;
+
+/// ARM boards at or below this much memory get the constrained profile
+pub const CONSTRAINED_SBC_MEMORY_MB: u64 = 2048;
+
+static ACTIVE_PROFILE: OnceLock<ResourceProfile> = OnceLock::new();
+
+/// Which profile the user asked for in the `[resources]` config section
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileSelection {
+    /// Pick from the detected hardware
+    #[default]
+    Auto,
+    Standard,
+    Constrained,
+}
+
+/// The profile that ended up being applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileKind {
+    Standard,
+    Constrained,
+}
+
+impl ProfileKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProfileKind::Standard => "standard",
+            ProfileKind::Constrained => "constrained",
+        }
+    }
+}
+
+/// `[resources]` config section
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceConfig {
+    pub profile: ProfileSelection,
+    /// Override the remote mount read chunk size in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<usize>,
+    /// Override the total number of open connections
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<usize>,
+    /// Force GPU, codec and Bluetooth probing on or off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hardware_probing: Option<bool>,
+}
+
+/// Cheap facts about the device, read without touching any drivers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceTraits {
+    pub architecture: Architecture,
+    pub total_memory_mb: u64,
+    pub cpu_count: usize,
+    /// Board name from the device tree, e.g. "Raspberry Pi 4 Model B Rev 1.4"
+    pub board_model: Option<String>,
+    pub openwrt: bool,
+}
+
+impl DeviceTraits {
+    /// Read the device traits from procfs and the device tree
+    pub fn detect() -> Self {
+        Self {
+            architecture: crate::platform::detection::detect_architecture(),
+            total_memory_mb: crate::platform::detection::get_total_memory_mb(),
+            cpu_count: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            board_model: std::fs::read_to_string("/proc/device-tree/model")
+                .ok()
+                .map(|model| model.trim_end_matches('\0').trim().to_string())
+                .filter(|model| !model.is_empty()),
+            openwrt: std::path::Path::new("/etc/openwrt_release").exists(),
+        }
+    }
+
+    /// Whether the device should run with the constrained profile
+    pub fn is_constrained(&self) -> bool {
+        if self.openwrt || self.total_memory_mb <= CONSTRAINED_MEMORY_MB {
+            return true;
+        }
+
+        let arm = matches!(self.architecture, Architecture::ARM32 | Architecture::ARM64);
+        arm && self.board_model.is_some() && self.total_memory_mb <= CONSTRAINED_SBC_MEMORY_MB
+    }
+}
+
+/// Local resource limits scaled to the device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceProfile {
+    pub kind: ProfileKind,
+    /// Read chunk size for remote mounts
+    pub chunk_size: usize,
+    /// Remote mount block cache size
+    pub cache_bytes: usize,
+    /// Buffer used when reading files for hashing and sending
+    pub io_buffer_size: usize,
+    /// Chunks a single transfer keeps in flight
+    pub chunks_in_flight: usize,
+    pub max_concurrent_connections: usize,
+    pub max_connections_per_peer: usize,
+    /// Worker threads used for hashing
+    pub hash_threads: usize,
+    /// Probe GPU, video codecs and Bluetooth adapters
+    pub hardware_probing: bool,
+}
+
+impl ResourceProfile {
+    /// Limits for desktops, laptops and servers
+    pub fn standard() -> Self {
+        Self {
+            kind: ProfileKind::Standard,
+            chunk_size: 128 * 1024,
+            cache_bytes: 64 * 1024 * 1024,
+            io_buffer_size: 1024 * 1024,
+            chunks_in_flight: 8,
+            max_concurrent_connections: 100,
+            max_connections_per_peer: 5,
+            hash_threads: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            hardware_probing: true,
+        }
+    }
+
+    /// Limits for single-board computers and routers
+    pub fn constrained() -> Self {
+        Self {
+            kind: ProfileKind::Constrained,
+            chunk_size: 32 * 1024,
+            cache_bytes: 4 * 1024 * 1024,
+            io_buffer_size: 64 * 1024,
+            chunks_in_flight: 2,
+            max_concurrent_connections: 16,
+            max_connections_per_peer: 2,
+            hash_threads: 1,
+            hardware_probing: false,
+        }
+    }
+
+    /// Pick a profile for the device and apply the config overrides
+    pub fn resolve(config: &ResourceConfig, device: &DeviceTraits) -> Self {
+        let mut profile = match config.profile {
+            ProfileSelection::Standard => Self::standard(),
+            ProfileSelection::Constrained => Self::constrained(),
+            ProfileSelection::Auto if device.is_constrained() => Self::constrained(),
+            ProfileSelection::Auto => Self::standard(),
+        };
+
+        if let Some(chunk_size) = config.chunk_size {
+            profile.chunk_size = chunk_size;
+        }
+        if let Some(max_connections) = config.max_connections {
+            profile.max_concurrent_connections = max_connections;
+            profile.max_connections_per_peer = profile.max_connections_per_peer.min(max_connections);
+        }
+        if let Some(hardware_probing) = config.hardware_probing {
+            profile.hardware_probing = hardware_probing;
+        }
+
+        profile
+    }
+
+    /// Make this the process-wide profile
+    ///
+    /// Returns false if a profile was already installed or read, since
+    /// subsystems that started earlier would not pick up the change.
+    pub fn install(self) -> bool {
+        let kind = self.kind;
+        let installed = ACTIVE_PROFILE.set(self).is_ok();
+        if installed {
+            log::info!("Using the {} resource profile", kind.as_str());
+        }
+        installed
+    }
+
+    /// The process-wide profile, detected from the device if none was installed
+    pub fn active() -> &'static ResourceProfile {
+        ACTIVE_PROFILE.get_or_init(|| Self::resolve(&ResourceConfig::default(), &DeviceTraits::detect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(architecture: Architecture, total_memory_mb: u64, board_model: Option<&str>) -> DeviceTraits {
+        DeviceTraits {
+            architecture,
+            total_memory_mb,
+            cpu_count: 4,
+            board_model: board_model.map(str::to_string),
+            openwrt: false,
+        }
+    }
+
+    #[test]
+    fn test_auto_detects_constrained_devices() {
+        let pi = device(Architecture::ARM64, 1900, Some("Raspberry Pi 4 Model B Rev 1.4"));
+        assert!(pi.is_constrained());
+
+        let small_vm = device(Architecture::X86_64, 512, None);
+        assert!(small_vm.is_constrained());
+
+        let arm_server = device(Architecture::ARM64, 1900, None);
+        assert!(!arm_server.is_constrained());
+
+        let router = DeviceTraits { openwrt: true, ..device(Architecture::ARM32, 4096, None) };
+        assert!(router.is_constrained());
+
+        let desktop = device(Architecture::X86_64, 16384, None);
+        let profile = ResourceProfile::resolve(&ResourceConfig::default(), &desktop);
+        assert_eq!(profile.kind, ProfileKind::Standard);
+    }
+
+    #[test]
+    fn test_config_overrides_apply_on_top_of_profile() {
+        let desktop = device(Architecture::X86_64, 16384, None);
+        let config = ResourceConfig {
+            profile: ProfileSelection::Constrained,
+            chunk_size: Some(16 * 1024),
+            max_connections: Some(1),
+            hardware_probing: Some(true),
+        };
+
+        let profile = ResourceProfile::resolve(&config, &desktop);
+        assert_eq!(profile.kind, ProfileKind::Constrained);
+        assert_eq!(profile.chunk_size, 16 * 1024);
+        assert_eq!(profile.max_concurrent_connections, 1);
+        assert_eq!(profile.max_connections_per_peer, 1);
+        assert!(profile.hardware_probing);
+        assert_eq!(profile.hash_threads, 1);
+    }
+
+    #[test]
+    fn test_config_deserializes_with_defaults() {
+        let config: ResourceConfig = serde_json::from_str(r#"{"profile": "constrained", "chunk_size": 8192}"#).unwrap();
+        assert_eq!(config.profile, ProfileSelection::Constrained);
+        assert_eq!(config.chunk_size, Some(8192));
+        assert_eq!(config.max_connections, None);
+    }
+}
@@ -56,6 +56,11 @@ pub trait PlatformAdapter: Send + Sync {
     fn get_optimizations(&self) -> Vec<String> {
         Vec::new()
     }
+
+    /// System-wide hotkey registration, if the platform has it
+    fn global_hotkeys(&self) -> Option<std::sync::Arc<dyn crate::platform::GlobalHotkeys>> {
+        crate::platform::hotkey::platform_hotkeys()
+    }
 }
 
 /// Build system trait for cross-compilation
@@ -0,0 +1,549 @@
+//! Remote filesystem API
+//!
+//! [`RemoteFileSystem`] is both sides of the browse channel: it answers
+//! requests from trusted peers against the local share roots, and it sends
+//! requests to browse a peer's shares. It is cheap to clone; clones share state.
+
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::file_transfer::FileTransferSystem;
+use crate::remote_fs::protocol::{BrowseRequest, BrowseResponse, RemoteErrorKind, MAX_FRAME_LEN, MAX_READ_LEN};
+use crate::remote_fs::{
+    BrowseError, BrowseResult, DirectoryListing, MountConfig, MountDriver, MountSession, PeerId,
+    RemoteEntry, RemoteMount, ShareConfig, ShareOperation, ShareRoot, ShareRoots,
+};
+use crate::security::trust::TrustManager;
+use crate::security::PeerId as SecurityPeerId;
+use crate::transport::{ConnectionProvider, MuxStream, PeerAddress, CHANNEL_BROWSE};
+
+/// Starts sending a shared file to the peer that fetched it
+#[async_trait]
+pub trait FetchHandler: Send + Sync {
+    /// Begin the transfer, returning its ID
+    async fn send_file(&self, peer_id: &str, path: PathBuf) -> Result<Uuid, String>;
+}
+
+#[async_trait]
+impl FetchHandler for FileTransferSystem {
+    async fn send_file(&self, peer_id: &str, path: PathBuf) -> Result<Uuid, String> {
+        FileTransferSystem::send_file(self, path, peer_id.to_string())
+            .await
+            .map(|session| session.session_id)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Configuration for remote browsing
+#[derive(Debug, Clone)]
+pub struct BrowseConfig {
+    /// Folders this device exports, and to whom
+    pub shares: ShareConfig,
+    /// Most entries returned for one directory
+    pub max_entries: usize,
+    /// Most matches returned for one search
+    pub max_search_results: usize,
+    /// Deepest directory level a search descends to
+    pub max_search_depth: usize,
+}
+
+impl Default for BrowseConfig {
+    fn default() -> Self {
+        Self {
+            shares: ShareConfig::default(),
+            max_entries: 1000,
+            max_search_results: 200,
+            max_search_depth: 16,
+        }
+    }
+}
+
+struct RemoteFsInner {
+    config: BrowseConfig,
+    provider: Arc<ConnectionProvider>,
+    trust: Arc<dyn TrustManager>,
+    roots: RwLock<ShareRoots>,
+    fetch: RwLock<Option<Arc<dyn FetchHandler>>>,
+}
+
+/// Browse peers' shared folders and serve our own
+#[derive(Clone)]
+pub struct RemoteFileSystem {
+    inner: Arc<RemoteFsInner>,
+}
+
+impl RemoteFileSystem {
+    /// Create the browse service with the configured share roots
+    pub fn new(
+        config: BrowseConfig,
+        provider: Arc<ConnectionProvider>,
+        trust: Arc<dyn TrustManager>,
+    ) -> BrowseResult<Self> {
+        let roots = ShareRoots::from_config(&config.shares)?;
+        Ok(Self {
+            inner: Arc::new(RemoteFsInner {
+                config,
+                provider,
+                trust,
+                roots: RwLock::new(roots),
+                fetch: RwLock::new(None),
+            }),
+        })
+    }
+
+    /// Configuration in use
+    pub fn config(&self) -> &BrowseConfig {
+        &self.inner.config
+    }
+
+    /// Folders currently shared
+    pub async fn shares(&self) -> Vec<ShareRoot> {
+        self.inner.roots.read().await.roots().to_vec()
+    }
+
+    /// Start sharing a folder
+    pub async fn add_share(&self, root: ShareRoot) -> BrowseResult<()> {
+        self.inner.roots.write().await.add(root)
+    }
+
+    /// Stop sharing a folder
+    pub async fn remove_share(&self, name: &str) -> bool {
+        self.inner.roots.write().await.remove(name)
+    }
+
+    /// Define or replace a group of peers that shares can be exported to
+    pub async fn set_group(&self, name: impl Into<String>, members: Vec<PeerId>) {
+        self.inner.roots.write().await.set_group(name, members);
+    }
+
+    /// Let peers fetch files, sending them through `handler`
+    ///
+    /// Until a handler is set, fetch requests are refused.
+    pub async fn set_fetch_handler(&self, handler: Arc<dyn FetchHandler>) {
+        *self.inner.fetch.write().await = Some(handler);
+    }
+
+    /// Check a peer's access to a share-relative path, returning the local path
+    ///
+    /// This is the single access check for shares; anything that reads or
+    /// writes shared files on a peer's behalf should go through it.
+    pub async fn authorize(&self, peer_id: &str, path: &str, operation: ShareOperation) -> BrowseResult<PathBuf> {
+        self.inner.ensure_permitted(peer_id).await?;
+        self.inner.roots.read().await.authorize(peer_id, path, operation).await
+    }
+
+    /// List a directory on a peer; the empty path lists its shares
+    pub async fn list(&self, peer: &PeerAddress, path: &str) -> BrowseResult<DirectoryListing> {
+        let request = BrowseRequest::List { path: path.to_string() };
+        match self.inner.request(peer, &request).await? {
+            BrowseResponse::Listing { listing } => Ok(listing),
+            _ => Err(BrowseError::UnexpectedResponse(request.operation().to_string())),
+        }
+    }
+
+    /// Describe one entry on a peer
+    pub async fn stat(&self, peer: &PeerAddress, path: &str) -> BrowseResult<RemoteEntry> {
+        let request = BrowseRequest::Stat { path: path.to_string() };
+        match self.inner.request(peer, &request).await? {
+            BrowseResponse::Entry { entry } => Ok(entry),
+            _ => Err(BrowseError::UnexpectedResponse(request.operation().to_string())),
+        }
+    }
+
+    /// Search a peer's shares under `path` for names containing `query`
+    ///
+    /// Returns the matches and whether the peer stopped at its result limit.
+    pub async fn search(&self, peer: &PeerAddress, path: &str, query: &str) -> BrowseResult<(Vec<RemoteEntry>, bool)> {
+        let request = BrowseRequest::Search {
+            path: path.to_string(),
+            query: query.to_string(),
+            limit: self.inner.config.max_search_results,
+        };
+        match self.inner.request(peer, &request).await? {
+            BrowseResponse::SearchResults { entries, truncated } => Ok((entries, truncated)),
+            _ => Err(BrowseError::UnexpectedResponse(request.operation().to_string())),
+        }
+    }
+
+    /// Ask a peer to send one of its shared files
+    ///
+    /// The file arrives through the normal incoming transfer flow. Returns the
+    /// file's entry and the transfer ID the peer assigned.
+    pub async fn fetch(&self, peer: &PeerAddress, path: &str) -> BrowseResult<(RemoteEntry, Uuid)> {
+        let request = BrowseRequest::Fetch { path: path.to_string() };
+        match self.inner.request(peer, &request).await? {
+            BrowseResponse::TransferStarted { entry, transfer_id } => Ok((entry, transfer_id)),
+            _ => Err(BrowseError::UnexpectedResponse(request.operation().to_string())),
+        }
+    }
+
+    /// Read part of a file on a peer without transferring all of it
+    ///
+    /// The peer returns at most [`MAX_READ_LEN`] bytes and nothing past the end
+    /// of the file, along with the file's current entry.
+    pub async fn read(&self, peer: &PeerAddress, path: &str, offset: u64, length: usize) -> BrowseResult<(RemoteEntry, Vec<u8>)> {
+        let request = BrowseRequest::Read {
+            path: path.to_string(),
+            offset,
+            length,
+        };
+        match self.inner.request(peer, &request).await? {
+            BrowseResponse::Chunk { entry, data, .. } => Ok((entry, data)),
+            _ => Err(BrowseError::UnexpectedResponse(request.operation().to_string())),
+        }
+    }
+
+    /// Mount a peer's share at a local folder through a platform driver
+    ///
+    /// The share is checked first, so a share the peer does not export to us
+    /// fails here rather than on first access.
+    pub async fn mount(
+        &self,
+        peer: &PeerAddress,
+        share: &str,
+        mountpoint: &Path,
+        driver: &dyn MountDriver,
+        config: MountConfig,
+    ) -> BrowseResult<Box<dyn MountSession>> {
+        let root = self.stat(peer, share).await?;
+        if !root.is_dir() {
+            return Err(BrowseError::NotADirectory(root.path));
+        }
+        let mount = RemoteMount::new(self.clone(), peer.clone(), root.path, config);
+        let session = driver.mount(mount, mountpoint).await?;
+        log::info!(
+            "Mounted {} from {} at {} with {}",
+            share,
+            peer.peer_id,
+            mountpoint.display(),
+            driver.name()
+        );
+        Ok(session)
+    }
+
+    /// Answer a browse stream opened by a remote peer
+    pub async fn attach_stream(&self, peer_id: PeerId, stream: MuxStream) -> BrowseResult<()> {
+        if stream.channel() != CHANNEL_BROWSE {
+            return Err(BrowseError::InvalidStream {
+                channel: stream.channel().to_string(),
+            });
+        }
+
+        let inner = Arc::clone(&self.inner);
+        tokio::spawn(async move {
+            if let Err(e) = inner.serve(&peer_id, &stream).await {
+                log::warn!("Browse request from {} failed: {}", peer_id, e);
+            }
+            let _ = stream.close();
+        });
+        Ok(())
+    }
+}
+
+impl RemoteFsInner {
+    fn security_peer_id(peer_id: &str) -> BrowseResult<SecurityPeerId> {
+        SecurityPeerId::from_string(peer_id).map_err(|e| BrowseError::InvalidPeer {
+            peer_id: peer_id.to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    async fn ensure_trusted(&self, peer_id: &str) -> BrowseResult<()> {
+        let trusted = self
+            .trust
+            .is_trusted(&Self::security_peer_id(peer_id)?)
+            .await
+            .map_err(|e| BrowseError::Trust(e.to_string()))?;
+        if !trusted {
+            return Err(BrowseError::PeerNotTrusted {
+                peer_id: peer_id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// A peer may browse if it is trusted and allowed to transfer files
+    async fn ensure_permitted(&self, peer_id: &str) -> BrowseResult<()> {
+        let entry = self
+            .trust
+            .get_trust_entry(&Self::security_peer_id(peer_id)?)
+            .await
+            .map_err(|e| BrowseError::Trust(e.to_string()))?
+            .ok_or_else(|| BrowseError::PeerNotTrusted {
+                peer_id: peer_id.to_string(),
+            })?;
+        if !entry.permissions.file_transfer {
+            return Err(BrowseError::PermissionDenied {
+                peer_id: peer_id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Send one request on a fresh stream and wait for the answer
+    async fn request(&self, peer: &PeerAddress, request: &BrowseRequest) -> BrowseResult<BrowseResponse> {
+        self.ensure_trusted(&peer.peer_id).await?;
+
+        let stream = self
+            .provider
+            .open_stream(peer, CHANNEL_BROWSE)
+            .await
+            .map_err(|e| BrowseError::transport("open_stream", e))?;
+        let result = async {
+            stream
+                .send_message(&request.encode()?)
+                .await
+                .map_err(|e| BrowseError::transport(request.operation(), e))?;
+            stream
+                .recv_message(MAX_FRAME_LEN)
+                .await
+                .map_err(|e| BrowseError::transport(request.operation(), e))?
+                .ok_or_else(|| BrowseError::UnexpectedResponse(request.operation().to_string()))
+        }
+        .await;
+        let _ = stream.close();
+
+        match BrowseResponse::decode(&result?)? {
+            BrowseResponse::Error { kind, message } => Err(BrowseError::Remote { kind, message }),
+            response => Ok(response),
+        }
+    }
+
+    /// Answer the single request on a stream
+    async fn serve(&self, peer_id: &str, stream: &MuxStream) -> BrowseResult<()> {
+        let Some(bytes) = stream
+            .recv_message(MAX_FRAME_LEN)
+            .await
+            .map_err(|e| BrowseError::transport("recv_request", e))?
+        else {
+            return Ok(());
+        };
+
+        let response = match self.handle(peer_id, &bytes).await {
+            Ok(response) => response,
+            Err(e) => {
+                log::debug!("Refusing browse request from {}: {}", peer_id, e);
+                let kind = e.remote_kind();
+                // Internal failures can carry local paths, so the peer only learns that it failed
+                let message = match kind {
+                    RemoteErrorKind::Internal => "Request failed on the peer".to_string(),
+                    _ => e.to_string(),
+                };
+                BrowseResponse::Error { kind, message }
+            }
+        };
+        stream
+            .send_message(&response.encode()?)
+            .await
+            .map_err(|e| BrowseError::transport("send_response", e))
+    }
+
+    async fn handle(&self, peer_id: &str, bytes: &[u8]) -> BrowseResult<BrowseResponse> {
+        self.ensure_permitted(peer_id).await?;
+        let all_roots = self.roots.read().await;
+        let roots = all_roots.visible_to(peer_id);
+
+        match BrowseRequest::decode(bytes)? {
+            BrowseRequest::List { path } => Ok(BrowseResponse::Listing {
+                listing: roots.list(&path, self.config.max_entries).await?,
+            }),
+            BrowseRequest::Stat { path } => Ok(BrowseResponse::Entry {
+                entry: roots.stat(&path).await?,
+            }),
+            BrowseRequest::Search { path, query, limit } => {
+                let limit = limit.min(self.config.max_search_results);
+                let (entries, truncated) = roots
+                    .search(&path, &query, limit, self.config.max_search_depth)
+                    .await?;
+                Ok(BrowseResponse::SearchResults { entries, truncated })
+            }
+            BrowseRequest::Fetch { path } => {
+                let local = all_roots.authorize(peer_id, &path, ShareOperation::Read).await?;
+                let entry = roots.stat(&path).await?;
+                if entry.is_dir() {
+                    return Err(BrowseError::invalid_path(path, "directories cannot be fetched"));
+                }
+                let handler = self
+                    .fetch
+                    .read()
+                    .await
+                    .clone()
+                    .ok_or_else(|| BrowseError::Transfer("fetching is not enabled".to_string()))?;
+                let transfer_id = handler.send_file(peer_id, local).await.map_err(BrowseError::Transfer)?;
+                log::info!("Sending {} to {} for a fetch", entry.path, peer_id);
+                Ok(BrowseResponse::TransferStarted { entry, transfer_id })
+            }
+            BrowseRequest::Read { path, offset, length } => {
+                let (entry, data) = roots.read_range(&path, offset, length.min(MAX_READ_LEN)).await?;
+                Ok(BrowseResponse::Chunk { entry, offset, data })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote_fs::mount::ROOT_INODE;
+    use crate::remote_fs::RemoteErrorKind;
+    use crate::security::trust::{ServicePermissions, TrustManagerImpl};
+    use crate::testing::transport::MEMORY_PROTOCOL;
+    use crate::testing::{MemoryTransport, VirtualNetwork};
+    use crate::transport::{PeerDialer, ProviderConfig, Transport};
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct RecordingFetch(std::sync::Mutex<Vec<(String, PathBuf)>>);
+
+    #[async_trait]
+    impl FetchHandler for RecordingFetch {
+        async fn send_file(&self, peer_id: &str, path: PathBuf) -> Result<Uuid, String> {
+            self.0.lock().unwrap().push((peer_id.to_string(), path));
+            Ok(Uuid::new_v4())
+        }
+    }
+
+    fn peer_hex(byte: u8) -> String {
+        SecurityPeerId::from_fingerprint([byte; 32]).to_hex()
+    }
+
+    async fn system(dir: &TempDir, remote: &str, dialer: Arc<dyn PeerDialer>, roots: Vec<ShareRoot>) -> (RemoteFileSystem, Arc<TrustManagerImpl>) {
+        let trust = Arc::new(TrustManagerImpl::new(dir.path().join("trust.db")).unwrap());
+        trust
+            .add_trusted_peer(SecurityPeerId::from_string(remote).unwrap(), "peer".to_string())
+            .await
+            .unwrap();
+        let config = BrowseConfig {
+            shares: ShareConfig {
+                shares: roots,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let system = RemoteFileSystem::new(
+            config,
+            Arc::new(ConnectionProvider::new(dialer, ProviderConfig::default())),
+            Arc::clone(&trust) as Arc<dyn TrustManager>,
+        )
+        .unwrap();
+        (system, trust)
+    }
+
+    #[tokio::test]
+    async fn test_browse_peer_shares() {
+        let (alice_id, bob_id) = (peer_hex(1), peer_hex(2));
+        let network = VirtualNetwork::new();
+        let alice_transport = Arc::new(MemoryTransport::new(alice_id.clone(), network.clone()));
+        let bob_transport = Arc::new(MemoryTransport::new(bob_id.clone(), network.clone()));
+        let addr = network.allocate_address(43000);
+        bob_transport.listen(&addr).await.unwrap();
+        let bob_addr = PeerAddress::new(
+            bob_id.clone(),
+            vec![addr],
+            vec![MEMORY_PROTOCOL.to_string()],
+            bob_transport.capabilities(),
+        );
+
+        let (alice_dir, bob_dir) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+        let shared = bob_dir.path().join("shared");
+        std::fs::create_dir_all(shared.join("music")).unwrap();
+        std::fs::write(shared.join("music/song.mp3"), b"la la").unwrap();
+
+        let (alice, _) = system(&alice_dir, &bob_id, alice_transport, Vec::new()).await;
+        let (bob, bob_trust) = system(
+            &bob_dir,
+            &alice_id,
+            Arc::clone(&bob_transport) as Arc<dyn PeerDialer>,
+            vec![
+                ShareRoot::new("shared", &shared).with_peer(alice_id.clone()),
+                ShareRoot::new("private", &shared).with_peer(peer_hex(9)),
+            ],
+        )
+        .await;
+        let sent = Arc::new(RecordingFetch::default());
+        bob.set_fetch_handler(Arc::clone(&sent) as Arc<dyn FetchHandler>).await;
+
+        let serve = {
+            let bob = bob.clone();
+            let bob_transport = Arc::clone(&bob_transport);
+            let alice_id = alice_id.clone();
+            tokio::spawn(async move {
+                let session = bob.inner.provider.adopt(Box::new(bob_transport.accept().await.unwrap())).await.unwrap();
+                while let Some(stream) = session.accept_stream().await {
+                    bob.attach_stream(alice_id.clone(), stream).await.unwrap();
+                }
+            })
+        };
+
+        let shares = alice.list(&bob_addr, "").await.unwrap();
+        assert_eq!(shares.entries.len(), 1);
+        assert!(shares.entries[0].is_dir());
+
+        let listing = alice.list(&bob_addr, "shared/music").await.unwrap();
+        assert_eq!(listing.entries[0].path, "shared/music/song.mp3");
+        assert_eq!(alice.stat(&bob_addr, "shared/music/song.mp3").await.unwrap().size, 5);
+
+        let (found, _) = alice.search(&bob_addr, "", "SONG").await.unwrap();
+        assert_eq!(found.len(), 1);
+
+        let (entry, _) = alice.fetch(&bob_addr, "shared/music/song.mp3").await.unwrap();
+        assert_eq!(entry.name, "song.mp3");
+        assert_eq!(sent.0.lock().unwrap()[0], (alice_id.clone(), shared.join("music/song.mp3").canonicalize().unwrap()));
+        let err = alice.fetch(&bob_addr, "shared/music").await.unwrap_err();
+        assert!(matches!(err, BrowseError::Remote { kind: RemoteErrorKind::InvalidPath, .. }));
+
+        let mount = RemoteMount::new(
+            alice.clone(),
+            bob_addr.clone(),
+            "shared",
+            MountConfig {
+                chunk_size: 2,
+                ..Default::default()
+            },
+        );
+        let names: Vec<String> = mount.readdir(ROOT_INODE).await.unwrap().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["music".to_string()]);
+        let music = mount.lookup(ROOT_INODE, "music").await.unwrap();
+        let song = mount.lookup(music.inode, "song.mp3").await.unwrap();
+        assert_eq!(song.size, 5);
+        assert_eq!(mount.read(song.inode, 1, 10).await.unwrap(), b"a la");
+        assert_eq!(mount.read(song.inode, 0, 2).await.unwrap(), b"la");
+
+        // Shares exported to other peers stay invisible
+        let err = alice.list(&bob_addr, "private").await.unwrap_err();
+        assert!(matches!(err, BrowseError::Remote { kind: RemoteErrorKind::NotFound, .. }));
+        let err = alice.fetch(&bob_addr, "private/music/song.mp3").await.unwrap_err();
+        assert!(matches!(err, BrowseError::Remote { kind: RemoteErrorKind::NotFound, .. }));
+
+        let err = alice.stat(&bob_addr, "shared/../../etc").await.unwrap_err();
+        assert!(matches!(err, BrowseError::Remote { kind: RemoteErrorKind::InvalidPath, .. }));
+
+        let permissions = ServicePermissions {
+            file_transfer: false,
+            ..Default::default()
+        };
+        bob_trust
+            .update_permissions(&SecurityPeerId::from_string(&alice_id).unwrap(), permissions)
+            .await
+            .unwrap();
+        let err = alice.list(&bob_addr, "shared").await.unwrap_err();
+        assert!(matches!(err, BrowseError::Remote { kind: RemoteErrorKind::PermissionDenied, .. }));
+
+        serve.abort();
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_peer_not_browsed() {
+        let network = VirtualNetwork::new();
+        let dir = TempDir::new().unwrap();
+        let transport = Arc::new(MemoryTransport::new(peer_hex(3), network));
+        let (system, _) = system(&dir, &peer_hex(4), transport, Vec::new()).await;
+
+        let stranger = PeerAddress::new(peer_hex(5), vec![], vec![], Default::default());
+        let err = system.list(&stranger, "").await.unwrap_err();
+        assert!(matches!(err, BrowseError::PeerNotTrusted { .. }));
+    }
+}
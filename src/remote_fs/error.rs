@@ -0,0 +1,141 @@
+//! Error types for remote filesystem browsing
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::remote_fs::protocol::RemoteErrorKind;
+use crate::transport::TransportError;
+
+/// Result type for browse operations
+pub type BrowseResult<T> = Result<T, BrowseError>;
+
+/// Errors produced while browsing shares
+#[derive(Error, Debug)]
+pub enum BrowseError {
+    /// The peer is not on the trust list
+    #[error("Peer {peer_id} is not trusted")]
+    PeerNotTrusted { peer_id: String },
+
+    /// The peer is trusted but may not browse files
+    #[error("Peer {peer_id} is not permitted to browse files")]
+    PermissionDenied { peer_id: String },
+
+    /// The peer ID cannot be mapped to a security identity
+    #[error("Invalid peer ID {peer_id}: {reason}")]
+    InvalidPeer { peer_id: String, reason: String },
+
+    /// A configured share root is unusable
+    #[error("Invalid share {name}: {reason}")]
+    InvalidShare { name: String, reason: String },
+
+    /// No share with this name is configured
+    #[error("Unknown share {0}")]
+    UnknownShare(String),
+
+    /// A requested path is malformed
+    #[error("Invalid path {path}: {reason}")]
+    InvalidPath { path: String, reason: String },
+
+    /// A write was requested on a read-only share
+    #[error("Share {0} is read-only")]
+    ReadOnlyShare(String),
+
+    /// A requested path resolves outside its share root
+    #[error("Path {0} is outside the share")]
+    OutsideShare(String),
+
+    /// Nothing exists at a requested path
+    #[error("No such file or directory: {0}")]
+    NotFound(String),
+
+    /// A directory operation was requested on a file
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+
+    /// The peer refused or failed a request
+    #[error("Peer rejected request ({kind:?}): {message}")]
+    Remote { kind: RemoteErrorKind, message: String },
+
+    /// A stream on another channel was handed to the browse service
+    #[error("Stream channel {channel} is not a browse stream")]
+    InvalidStream { channel: String },
+
+    /// A share could not be mounted or unmounted
+    #[error("Mount failed: {0}")]
+    Mount(String),
+
+    /// A fetched file could not be handed to file transfer
+    #[error("Could not start transfer: {0}")]
+    Transfer(String),
+
+    /// The peer answered with a response that does not match the request
+    #[error("Unexpected response to {0}")]
+    UnexpectedResponse(String),
+
+    /// The trust store could not be queried
+    #[error("Trust lookup failed: {0}")]
+    Trust(String),
+
+    /// The peer could not be reached
+    #[error("Transport failed during {operation}: {source}")]
+    Transport {
+        operation: String,
+        #[source]
+        source: TransportError,
+    },
+
+    /// Reading a shared folder failed
+    #[error("I/O error at {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A request or response could not be encoded or decoded
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+impl BrowseError {
+    /// Create a transport error for an operation
+    pub fn transport(operation: impl Into<String>, source: TransportError) -> Self {
+        BrowseError::Transport {
+            operation: operation.into(),
+            source,
+        }
+    }
+
+    /// Create an I/O error for a path
+    pub fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        BrowseError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// Create an invalid path error
+    pub fn invalid_path(path: impl Into<String>, reason: impl Into<String>) -> Self {
+        BrowseError::InvalidPath {
+            path: path.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// How this error is reported to the requesting peer
+    ///
+    /// Local details such as absolute paths are not included in the kind, and
+    /// internal failures are sent without their display text.
+    pub fn remote_kind(&self) -> RemoteErrorKind {
+        match self {
+            BrowseError::PeerNotTrusted { .. }
+            | BrowseError::PermissionDenied { .. }
+            | BrowseError::ReadOnlyShare(_)
+            | BrowseError::OutsideShare(_) => RemoteErrorKind::PermissionDenied,
+            BrowseError::UnknownShare(_) | BrowseError::NotFound(_) => RemoteErrorKind::NotFound,
+            BrowseError::InvalidPath { .. } | BrowseError::NotADirectory(_) => RemoteErrorKind::InvalidPath,
+            BrowseError::Remote { kind, .. } => *kind,
+            _ => RemoteErrorKind::Internal,
+        }
+    }
+}
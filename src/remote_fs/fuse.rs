@@ -0,0 +1,261 @@
+//! FUSE driver for mounted shares
+//!
+//! [`FuseDriver`] serves a [`RemoteMount`] through the kernel's FUSE interface
+//! on Linux and macOS. Kernel requests arrive on fuser's session threads, which
+//! block on the tokio runtime that started the mount while the peer answers.
+//! Files and folders belong to the owner of the mountpoint and are read-only.
+//!
+//! On Linux the mount goes through `fusermount`, so no FUSE headers are needed
+//! to build; macOS needs macFUSE.
+
+use async_trait::async_trait;
+use fuser::{
+    BackgroundSession, Config, Errno, FileAttr, FileHandle, FileType, Filesystem, Generation, INodeNo, LockOwner,
+    MountOption, OpenFlags, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry, Request,
+};
+use std::ffi::OsStr;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::runtime::Handle;
+
+use crate::remote_fs::protocol::RemoteErrorKind;
+use crate::remote_fs::{BrowseError, BrowseResult, EntryKind, MountAttr, MountDriver, MountSession, RemoteMount};
+
+/// Session threads answering the kernel; fuser runs more than one only on Linux
+const SESSION_THREADS: usize = if cfg!(target_os = "linux") { 4 } else { 1 };
+
+/// Block size reported to `stat`
+const BLOCK_SIZE: u32 = 4096;
+
+/// Mounts shares through FUSE
+#[derive(Debug, Clone, Default)]
+pub struct FuseDriver;
+
+impl FuseDriver {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl MountDriver for FuseDriver {
+    fn name(&self) -> &str {
+        "fuse"
+    }
+
+    async fn mount(&self, mount: RemoteMount, mountpoint: &Path) -> BrowseResult<Box<dyn MountSession>> {
+        let metadata = std::fs::metadata(mountpoint).map_err(|e| BrowseError::io(mountpoint, e))?;
+        if !metadata.is_dir() {
+            return Err(BrowseError::Mount(format!("{} is not a folder", mountpoint.display())));
+        }
+
+        let mut config = Config::default();
+        config.mount_options = vec![
+            MountOption::RO,
+            MountOption::NoSuid,
+            MountOption::NoDev,
+            MountOption::NoAtime,
+            MountOption::FSName(format!("kizuna:{}", mount.peer().peer_id)),
+            MountOption::Subtype("kizuna".to_string()),
+        ];
+        config.n_threads = Some(SESSION_THREADS);
+        let filesystem = FuseFilesystem {
+            ttl: mount.metadata_ttl(),
+            mount,
+            runtime: Handle::current(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+        };
+
+        // Mounting waits on fusermount, so keep it off the runtime's workers
+        let path = mountpoint.to_path_buf();
+        let session = tokio::task::spawn_blocking(move || fuser::spawn_mount(filesystem, &path, &config))
+            .await
+            .map_err(|e| BrowseError::Mount(e.to_string()))?
+            .map_err(|e| BrowseError::Mount(format!("{}: {}", mountpoint.display(), e)))?;
+
+        Ok(Box::new(FuseSession {
+            mountpoint: mountpoint.to_path_buf(),
+            session,
+        }))
+    }
+}
+
+/// A share mounted through FUSE
+struct FuseSession {
+    mountpoint: PathBuf,
+    session: BackgroundSession,
+}
+
+#[async_trait]
+impl MountSession for FuseSession {
+    fn mountpoint(&self) -> &Path {
+        &self.mountpoint
+    }
+
+    async fn unmount(self: Box<Self>) -> BrowseResult<()> {
+        let FuseSession { mountpoint, session } = *self;
+        tokio::task::spawn_blocking(move || session.umount_and_join())
+            .await
+            .map_err(|e| BrowseError::Mount(e.to_string()))?
+            .map_err(|e| BrowseError::Mount(format!("{}: {}", mountpoint.display(), e)))
+    }
+}
+
+/// Kernel callbacks bridged onto a [`RemoteMount`]
+struct FuseFilesystem {
+    mount: RemoteMount,
+    runtime: Handle,
+    ttl: Duration,
+    uid: u32,
+    gid: u32,
+}
+
+impl FuseFilesystem {
+    fn file_attr(&self, attr: &MountAttr) -> FileAttr {
+        let modified = attr
+            .modified
+            .map(|millis| UNIX_EPOCH + Duration::from_millis(millis))
+            .unwrap_or(UNIX_EPOCH);
+        let (kind, perm, nlink) = match attr.kind {
+            EntryKind::Directory => (FileType::Directory, 0o555, 2),
+            EntryKind::File => (FileType::RegularFile, 0o444, 1),
+        };
+
+        FileAttr {
+            ino: INodeNo(attr.inode),
+            size: attr.size,
+            blocks: attr.size.div_ceil(512),
+            atime: modified,
+            mtime: modified,
+            ctime: modified,
+            crtime: modified,
+            kind,
+            perm,
+            nlink,
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: BLOCK_SIZE,
+            flags: 0,
+        }
+    }
+}
+
+impl Filesystem for FuseFilesystem {
+    fn lookup(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+        // Shares only hold UTF-8 names
+        let Some(name) = name.to_str() else {
+            reply.error(Errno::ENOENT);
+            return;
+        };
+        match self.runtime.block_on(self.mount.lookup(parent.0, name)) {
+            Ok(attr) => reply.entry(&self.ttl, &self.file_attr(&attr), Generation(0)),
+            Err(e) => reply.error(errno("lookup", &e)),
+        }
+    }
+
+    fn getattr(&self, _req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
+        match self.runtime.block_on(self.mount.getattr(ino.0)) {
+            Ok(attr) => reply.attr(&self.ttl, &self.file_attr(&attr)),
+            Err(e) => reply.error(errno("getattr", &e)),
+        }
+    }
+
+    fn read(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        _fh: FileHandle,
+        offset: u64,
+        size: u32,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        reply: ReplyData,
+    ) {
+        match self.runtime.block_on(self.mount.read(ino.0, offset, size as usize)) {
+            Ok(data) => reply.data(&data),
+            Err(e) => reply.error(errno("read", &e)),
+        }
+    }
+
+    fn readdir(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, offset: u64, mut reply: ReplyDirectory) {
+        let entries = match self.runtime.block_on(self.mount.readdir(ino.0)) {
+            Ok(entries) => entries,
+            Err(e) => {
+                reply.error(errno("readdir", &e));
+                return;
+            }
+        };
+
+        // The kernel resolves `..` itself, so its inode here is only cosmetic
+        let dots = [
+            (ino.0, FileType::Directory, ".".to_string()),
+            (ino.0, FileType::Directory, "..".to_string()),
+        ];
+        let listing = dots.into_iter().chain(entries.into_iter().map(|(name, attr)| {
+            let kind = match attr.kind {
+                EntryKind::Directory => FileType::Directory,
+                EntryKind::File => FileType::RegularFile,
+            };
+            (attr.inode, kind, name)
+        }));
+        for (index, (inode, kind, name)) in listing.enumerate().skip(offset as usize) {
+            // Offsets name the entry after this one
+            if reply.add(INodeNo(inode), index as u64 + 1, kind, &name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
+
+/// The errno a failed operation is reported with
+fn errno(operation: &str, error: &BrowseError) -> Errno {
+    let errno = match error {
+        BrowseError::NotFound(_) | BrowseError::UnknownShare(_) => Errno::ENOENT,
+        BrowseError::NotADirectory(_) => Errno::ENOTDIR,
+        BrowseError::InvalidPath { .. } => Errno::EINVAL,
+        BrowseError::ReadOnlyShare(_) => Errno::EROFS,
+        BrowseError::PeerNotTrusted { .. } | BrowseError::PermissionDenied { .. } | BrowseError::OutsideShare(_) => {
+            Errno::EACCES
+        }
+        BrowseError::Remote { kind, .. } => match kind {
+            RemoteErrorKind::NotFound => Errno::ENOENT,
+            RemoteErrorKind::PermissionDenied => Errno::EACCES,
+            RemoteErrorKind::InvalidPath => Errno::EINVAL,
+            RemoteErrorKind::Internal => Errno::EIO,
+        },
+        _ => Errno::EIO,
+    };
+    if errno == Errno::EIO {
+        log::warn!("FUSE {} failed: {}", operation, error);
+    }
+    errno
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_browse_errors_map_to_errno() {
+        assert_eq!(errno("lookup", &BrowseError::NotFound("a".into())), Errno::ENOENT);
+        assert_eq!(
+            errno("readdir", &BrowseError::NotADirectory("a".into())),
+            Errno::ENOTDIR
+        );
+        assert_eq!(
+            errno(
+                "read",
+                &BrowseError::Remote {
+                    kind: RemoteErrorKind::PermissionDenied,
+                    message: "no".into(),
+                }
+            ),
+            Errno::EACCES
+        );
+        assert_eq!(errno("read", &BrowseError::Mount("gone".into())), Errno::EIO);
+    }
+}
@@ -0,0 +1,271 @@
+//! Remote filesystem browsing
+//!
+//! Lets a trusted peer look through the folders this device shares before it
+//! asks for a file: list a directory, stat an entry, or search by name. Requests
+//! travel on the `browse` channel of the peer's multiplexed session.
+//!
+//! Paths are share-relative and `/`-separated: the first component names a share
+//! root and the rest is resolved inside it, so `photos/2024/beach.jpg` is
+//! `beach.jpg` under the `2024` folder of the share called `photos`. The empty
+//! path lists the shares themselves. Nothing outside a share root is reachable,
+//! including through symlinks, and a peer needs the file transfer permission on
+//! its trust entry to browse at all.
+//!
+//! Each share is exported read-only or read-write to a list of peers and named
+//! groups. A peer that is not a member does not see the share at all. The checks
+//! live in [`ShareRoots`] so browsing, fetching and anything else that touches a
+//! share go through the same policy.
+//!
+//! A share can also be read in place: [`RemoteFileSystem::read`] returns a byte
+//! range of a file, and [`RemoteMount`] builds a cached, mountable view of a
+//! share on top of it. `FuseDriver` (feature `fuse`) and `ProjFsDriver`
+//! (feature `projfs`, Windows) mount that view as a local folder.
+
+pub mod error;
+pub mod protocol;
+pub mod roots;
+pub mod api;
+pub mod mount;
+#[cfg(all(unix, feature = "fuse"))]
+pub mod fuse;
+#[cfg(all(windows, feature = "projfs"))]
+pub mod projfs;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub use error::{BrowseError, BrowseResult};
+pub use protocol::{BrowseRequest, BrowseResponse, RemoteErrorKind};
+pub use roots::ShareRoots;
+pub use api::{BrowseConfig, FetchHandler, RemoteFileSystem};
+pub use mount::{MountAttr, MountConfig, MountDriver, MountSession, RemoteMount};
+#[cfg(all(unix, feature = "fuse"))]
+pub use fuse::FuseDriver;
+#[cfg(all(windows, feature = "projfs"))]
+pub use projfs::ProjFsDriver;
+
+/// Unique identifier for peers
+pub type PeerId = String;
+
+/// What members of a share may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShareAccess {
+    #[default]
+    ReadOnly,
+    ReadWrite,
+}
+
+/// An operation checked against a share's access
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShareOperation {
+    /// Browse, stat, search or fetch
+    Read,
+    /// Create or replace files
+    Write,
+}
+
+/// A local folder exported to peers under a name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareRoot {
+    /// Name peers use as the first path component
+    pub name: String,
+    /// Folder on this device
+    pub path: PathBuf,
+    #[serde(default)]
+    pub access: ShareAccess,
+    /// Peer IDs the share is exported to
+    #[serde(default)]
+    pub peers: Vec<PeerId>,
+    /// Groups the share is exported to
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl ShareRoot {
+    /// A read-only share exported to nobody yet
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+            access: ShareAccess::ReadOnly,
+            peers: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    /// Let members create and replace files
+    pub fn read_write(mut self) -> Self {
+        self.access = ShareAccess::ReadWrite;
+        self
+    }
+
+    /// Export to a peer
+    pub fn with_peer(mut self, peer_id: impl Into<PeerId>) -> Self {
+        self.peers.push(peer_id.into());
+        self
+    }
+
+    /// Export to every member of a group
+    pub fn with_group(mut self, group: impl Into<String>) -> Self {
+        self.groups.push(group.into());
+        self
+    }
+
+    /// Whether the share allows an operation at all
+    pub fn permits(&self, operation: ShareOperation) -> bool {
+        operation == ShareOperation::Read || self.access == ShareAccess::ReadWrite
+    }
+}
+
+/// Shares this device exports and the groups they can be exported to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareConfig {
+    #[serde(default)]
+    pub shares: Vec<ShareRoot>,
+    /// Group name to member peer IDs
+    #[serde(default)]
+    pub groups: HashMap<String, Vec<PeerId>>,
+}
+
+/// A `peer:share/path` reference to a file in a peer's shares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSpec {
+    /// Peer name or ID
+    pub peer: String,
+    /// Share-relative path, starting with the share name
+    pub path: String,
+}
+
+impl RemoteSpec {
+    /// Parse `peer:share/path`
+    pub fn parse(spec: &str) -> BrowseResult<Self> {
+        let (peer, path) = spec
+            .split_once(':')
+            .ok_or_else(|| BrowseError::invalid_path(spec, "expected PEER:SHARE/PATH"))?;
+        if peer.trim().is_empty() {
+            return Err(BrowseError::invalid_path(spec, "peer name is empty"));
+        }
+        let parts = roots::split_path(path)?;
+        if parts.is_empty() {
+            return Err(BrowseError::invalid_path(spec, "share name is missing"));
+        }
+        Ok(Self {
+            peer: peer.trim().to_string(),
+            path: parts.join("/"),
+        })
+    }
+
+    /// Name of the share the path is in
+    pub fn share(&self) -> &str {
+        self.path.split('/').next().unwrap_or_default()
+    }
+
+    /// Final path component
+    pub fn file_name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or_default()
+    }
+}
+
+impl FromStr for RemoteSpec {
+    type Err = BrowseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for RemoteSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.peer, self.path)
+    }
+}
+
+/// Kind of a remote entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    File,
+    Directory,
+}
+
+/// A file or directory in a peer's shares
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteEntry {
+    /// Final path component
+    pub name: String,
+    /// Share-relative path, usable in further requests
+    pub path: String,
+    pub kind: EntryKind,
+    /// Size in bytes, zero for directories
+    pub size: u64,
+    /// Last modification in milliseconds since the epoch, if the platform reports it
+    pub modified: Option<u64>,
+}
+
+impl RemoteEntry {
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Directory
+    }
+}
+
+/// One page of a remote directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryListing {
+    /// Share-relative path that was listed
+    pub path: String,
+    /// Directories first, then files, each sorted by name
+    pub entries: Vec<RemoteEntry>,
+    /// More entries exist than the peer was willing to return
+    pub truncated: bool,
+}
+
+/// Join a share-relative path and a child name
+pub fn join_path(parent: &str, name: &str) -> String {
+    let parent = parent.trim_matches('/');
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// Parent of a share-relative path, `None` at the list of shares
+pub fn parent_path(path: &str) -> Option<String> {
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        return None;
+    }
+    Some(path.rsplit_once('/').map(|(parent, _)| parent.to_string()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_helpers() {
+        assert_eq!(join_path("", "docs"), "docs");
+        assert_eq!(join_path("docs/", "a.txt"), "docs/a.txt");
+        assert_eq!(parent_path("docs/reports/a.txt").as_deref(), Some("docs/reports"));
+        assert_eq!(parent_path("docs").as_deref(), Some(""));
+        assert_eq!(parent_path(""), None);
+    }
+
+    #[test]
+    fn test_parse_remote_spec() {
+        let spec = RemoteSpec::parse("laptop:projects/report.pdf").unwrap();
+        assert_eq!(spec.peer, "laptop");
+        assert_eq!(spec.share(), "projects");
+        assert_eq!(spec.file_name(), "report.pdf");
+        assert_eq!(spec.to_string(), "laptop:projects/report.pdf");
+
+        assert!(RemoteSpec::parse("projects/report.pdf").is_err());
+        assert!(RemoteSpec::parse(":projects/report.pdf").is_err());
+        assert!(RemoteSpec::parse("laptop:").is_err());
+        assert!(RemoteSpec::parse("laptop:projects/../etc/passwd").is_err());
+    }
+}
@@ -0,0 +1,471 @@
+//! Mounting a peer's share as a local folder
+//!
+//! [`RemoteMount`] is the filesystem a mount driver serves. It hands out inode
+//! numbers for share-relative paths, answers lookups and directory reads from a
+//! short-lived metadata cache, and turns reads into fixed-size chunk requests on
+//! the browse channel. Chunks are kept in a bounded cache tagged with the file's
+//! modification time, so a file that changes on the peer is read again.
+//!
+//! The platform side sits behind [`MountDriver`]: a FUSE driver on Linux and
+//! macOS, or a Projected File System provider on Windows, calls into
+//! [`RemoteMount`] from its callbacks. The drivers live in the `fuse` and
+//! `projfs` modules behind the features of the same names, since each links its
+//! platform's filesystem library. Mounts are read-only.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::remote_fs::protocol::MAX_READ_LEN;
+use crate::remote_fs::{join_path, BrowseError, BrowseResult, EntryKind, RemoteEntry, RemoteFileSystem};
+use crate::transport::PeerAddress;
+
+/// Inode number of a mounted path
+pub type Inode = u64;
+
+/// Inode of the mounted share's top folder
+pub const ROOT_INODE: Inode = 1;
+
+/// Tuning for a mount
+#[derive(Debug, Clone)]
+pub struct MountConfig {
+    /// Bytes requested from the peer per read, capped at the protocol limit
+    pub chunk_size: usize,
+    /// Most file data kept in memory
+    pub cache_bytes: usize,
+    /// How long entries and listings are used before asking the peer again
+    pub metadata_ttl: Duration,
+}
+
+impl Default for MountConfig {
+    fn default() -> Self {
+        Self::for_profile(crate::platform::ResourceProfile::active())
+    }
+}
+
+impl MountConfig {
+    /// Read chunk and cache sizes scaled to a resource profile
+    pub fn for_profile(profile: &crate::platform::ResourceProfile) -> Self {
+        Self {
+            chunk_size: profile.chunk_size,
+            cache_bytes: profile.cache_bytes,
+            metadata_ttl: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Attributes of a mounted file or folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountAttr {
+    pub inode: Inode,
+    pub kind: EntryKind,
+    /// Size in bytes, zero for folders
+    pub size: u64,
+    /// Last modification in milliseconds since the epoch
+    pub modified: Option<u64>,
+}
+
+/// A platform filesystem bridge that serves a [`RemoteMount`] at a local path
+#[async_trait]
+pub trait MountDriver: Send + Sync {
+    /// Name for logs, such as `fuse` or `projfs`
+    fn name(&self) -> &str;
+
+    /// Start serving `mount` at `mountpoint`
+    async fn mount(&self, mount: RemoteMount, mountpoint: &Path) -> BrowseResult<Box<dyn MountSession>>;
+}
+
+/// A live mount created by a [`MountDriver`]
+#[async_trait]
+pub trait MountSession: Send + Sync {
+    /// Local folder the share is mounted at
+    fn mountpoint(&self) -> &Path;
+
+    /// Detach the mount; files still open on it fail afterwards
+    async fn unmount(self: Box<Self>) -> BrowseResult<()>;
+}
+
+struct Cached<T> {
+    value: T,
+    fetched: Instant,
+}
+
+impl<T: Clone> Cached<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value,
+            fetched: Instant::now(),
+        }
+    }
+
+    fn fresh(&self, ttl: Duration) -> Option<T> {
+        (self.fetched.elapsed() < ttl).then(|| self.value.clone())
+    }
+}
+
+struct CachedChunk {
+    data: Vec<u8>,
+    modified: Option<u64>,
+    last_used: u64,
+}
+
+/// File data cache with a byte budget, evicting the least recently used chunk
+struct ChunkCache {
+    chunks: HashMap<(Inode, u64), CachedChunk>,
+    bytes: usize,
+    budget: usize,
+    clock: u64,
+}
+
+impl ChunkCache {
+    fn new(budget: usize) -> Self {
+        Self {
+            chunks: HashMap::new(),
+            bytes: 0,
+            budget,
+            clock: 0,
+        }
+    }
+
+    /// A cached chunk, if it was read from the same version of the file
+    fn get(&mut self, inode: Inode, index: u64, modified: Option<u64>) -> Option<Vec<u8>> {
+        self.clock += 1;
+        let chunk = self.chunks.get_mut(&(inode, index))?;
+        if chunk.modified != modified {
+            return None;
+        }
+        chunk.last_used = self.clock;
+        Some(chunk.data.clone())
+    }
+
+    fn insert(&mut self, inode: Inode, index: u64, modified: Option<u64>, data: Vec<u8>) {
+        if data.len() > self.budget {
+            return;
+        }
+        self.clock += 1;
+        self.bytes += data.len();
+        let chunk = CachedChunk {
+            data,
+            modified,
+            last_used: self.clock,
+        };
+        if let Some(old) = self.chunks.insert((inode, index), chunk) {
+            self.bytes -= old.data.len();
+        }
+
+        while self.bytes > self.budget {
+            let Some(oldest) = self
+                .chunks
+                .iter()
+                .min_by_key(|(_, chunk)| chunk.last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            if let Some(evicted) = self.chunks.remove(&oldest) {
+                self.bytes -= evicted.data.len();
+            }
+        }
+    }
+
+    fn invalidate(&mut self, inode: Inode) {
+        let bytes = &mut self.bytes;
+        self.chunks.retain(|(chunk_inode, _), chunk| {
+            if *chunk_inode == inode {
+                *bytes -= chunk.data.len();
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn clear(&mut self) {
+        self.chunks.clear();
+        self.bytes = 0;
+    }
+}
+
+struct MountState {
+    paths: HashMap<Inode, String>,
+    inodes: HashMap<String, Inode>,
+    next_inode: Inode,
+    entries: HashMap<Inode, Cached<RemoteEntry>>,
+    listings: HashMap<Inode, Cached<Vec<RemoteEntry>>>,
+    chunks: ChunkCache,
+}
+
+impl MountState {
+    /// Inode for a path, allocating one the first time the path is seen
+    fn intern(&mut self, path: &str) -> Inode {
+        if let Some(inode) = self.inodes.get(path) {
+            return *inode;
+        }
+        let inode = self.next_inode;
+        self.next_inode += 1;
+        self.paths.insert(inode, path.to_string());
+        self.inodes.insert(path.to_string(), inode);
+        inode
+    }
+}
+
+struct MountInner {
+    fs: RemoteFileSystem,
+    peer: PeerAddress,
+    share: String,
+    config: MountConfig,
+    state: Mutex<MountState>,
+}
+
+/// A peer's share seen as an inode-based filesystem
+///
+/// Cheap to clone; clones share the inode table and caches, so a driver can
+/// hand one to each callback thread.
+#[derive(Clone)]
+pub struct RemoteMount {
+    inner: Arc<MountInner>,
+}
+
+impl RemoteMount {
+    /// Mount the share called `share` on `peer`
+    pub fn new(fs: RemoteFileSystem, peer: PeerAddress, share: impl Into<String>, config: MountConfig) -> Self {
+        let share = share.into();
+        let mut state = MountState {
+            paths: HashMap::new(),
+            inodes: HashMap::new(),
+            next_inode: ROOT_INODE,
+            entries: HashMap::new(),
+            listings: HashMap::new(),
+            chunks: ChunkCache::new(config.cache_bytes),
+        };
+        state.intern(&share);
+
+        Self {
+            inner: Arc::new(MountInner {
+                fs,
+                peer,
+                share,
+                config,
+                state: Mutex::new(state),
+            }),
+        }
+    }
+
+    /// Peer the share belongs to
+    pub fn peer(&self) -> &PeerAddress {
+        &self.inner.peer
+    }
+
+    /// Name of the mounted share
+    pub fn share(&self) -> &str {
+        &self.inner.share
+    }
+
+    /// How long entries and listings are trusted before the peer is asked again
+    pub fn metadata_ttl(&self) -> Duration {
+        self.inner.config.metadata_ttl
+    }
+
+    /// Share-relative path of an inode
+    pub fn path(&self, inode: Inode) -> BrowseResult<String> {
+        self.state()
+            .paths
+            .get(&inode)
+            .cloned()
+            .ok_or_else(|| BrowseError::NotFound(format!("inode {}", inode)))
+    }
+
+    /// Attributes of an inode
+    pub async fn getattr(&self, inode: Inode) -> BrowseResult<MountAttr> {
+        let path = self.path(inode)?;
+        let entry = self.entry(inode, &path).await?;
+        Ok(Self::attr(inode, &entry))
+    }
+
+    /// Find `name` in the folder `parent`
+    pub async fn lookup(&self, parent: Inode, name: &str) -> BrowseResult<MountAttr> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(BrowseError::invalid_path(name, "not a single path component"));
+        }
+        let path = join_path(&self.path(parent)?, name);
+
+        // After a directory read, lookups of its children need no round trip
+        let cached = {
+            let mut state = self.state();
+            let ttl = self.inner.config.metadata_ttl;
+            state
+                .listings
+                .get(&parent)
+                .and_then(|listing| listing.fresh(ttl))
+                .map(|entries| entries.into_iter().find(|entry| entry.name == name))
+                .map(|found| found.map(|entry| (state.intern(&entry.path), entry)))
+        };
+        match cached {
+            Some(Some((inode, entry))) => Ok(Self::attr(inode, &entry)),
+            Some(None) => Err(BrowseError::NotFound(path)),
+            None => {
+                let entry = self.inner.fs.stat(&self.inner.peer, &path).await?;
+                let inode = self.remember(entry.clone());
+                Ok(Self::attr(inode, &entry))
+            }
+        }
+    }
+
+    /// Entries of a folder
+    pub async fn readdir(&self, inode: Inode) -> BrowseResult<Vec<(String, MountAttr)>> {
+        let path = self.path(inode)?;
+        let ttl = self.inner.config.metadata_ttl;
+        let cached = self.state().listings.get(&inode).and_then(|listing| listing.fresh(ttl));
+        let entries = match cached {
+            Some(entries) => entries,
+            None => {
+                let listing = self.inner.fs.list(&self.inner.peer, &path).await?;
+                if listing.truncated {
+                    log::warn!(
+                        "Listing of {} on {} was truncated by the peer",
+                        path,
+                        self.inner.peer.peer_id
+                    );
+                }
+                self.state().listings.insert(inode, Cached::new(listing.entries.clone()));
+                listing.entries
+            }
+        };
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| {
+                let inode = self.remember(entry.clone());
+                (entry.name.clone(), Self::attr(inode, &entry))
+            })
+            .collect())
+    }
+
+    /// Read up to `size` bytes of a file starting at `offset`
+    ///
+    /// Fetches the chunks covering the range that are not cached. Returns fewer
+    /// bytes at the end of the file.
+    pub async fn read(&self, inode: Inode, offset: u64, size: usize) -> BrowseResult<Vec<u8>> {
+        let path = self.path(inode)?;
+        let mut entry = self.entry(inode, &path).await?;
+        if entry.is_dir() {
+            return Err(BrowseError::invalid_path(path, "is a folder"));
+        }
+
+        let chunk_size = self.chunk_size() as u64;
+        let end = offset.saturating_add(size as u64).min(entry.size);
+        let mut data = Vec::with_capacity(end.saturating_sub(offset) as usize);
+        let mut position = offset;
+        while position < end {
+            let index = position / chunk_size;
+            let chunk = self.chunk(inode, &path, index, &mut entry).await?;
+            let start = (position - index * chunk_size) as usize;
+            if start >= chunk.len() {
+                // The file shrank on the peer
+                break;
+            }
+            let take = ((end - position) as usize).min(chunk.len() - start);
+            data.extend_from_slice(&chunk[start..start + take]);
+            position += take as u64;
+        }
+        Ok(data)
+    }
+
+    /// Drop all cached entries, listings and file data
+    pub fn invalidate(&self) {
+        let mut state = self.state();
+        state.entries.clear();
+        state.listings.clear();
+        state.chunks.clear();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MountState> {
+        self.inner.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.inner.config.chunk_size.clamp(1, MAX_READ_LEN)
+    }
+
+    fn attr(inode: Inode, entry: &RemoteEntry) -> MountAttr {
+        MountAttr {
+            inode,
+            kind: entry.kind,
+            size: entry.size,
+            modified: entry.modified,
+        }
+    }
+
+    /// Cache an entry and return its inode
+    fn remember(&self, entry: RemoteEntry) -> Inode {
+        let mut state = self.state();
+        let inode = state.intern(&entry.path);
+        state.entries.insert(inode, Cached::new(entry));
+        inode
+    }
+
+    async fn entry(&self, inode: Inode, path: &str) -> BrowseResult<RemoteEntry> {
+        let ttl = self.inner.config.metadata_ttl;
+        if let Some(entry) = self.state().entries.get(&inode).and_then(|entry| entry.fresh(ttl)) {
+            return Ok(entry);
+        }
+        let entry = self.inner.fs.stat(&self.inner.peer, path).await?;
+        self.state().entries.insert(inode, Cached::new(entry.clone()));
+        Ok(entry)
+    }
+
+    /// One chunk of a file, from the cache or the peer
+    ///
+    /// When the peer reports a different version of the file than `entry`, the
+    /// file's cached chunks are dropped and `entry` is updated.
+    async fn chunk(&self, inode: Inode, path: &str, index: u64, entry: &mut RemoteEntry) -> BrowseResult<Vec<u8>> {
+        if let Some(data) = self.state().chunks.get(inode, index, entry.modified) {
+            return Ok(data);
+        }
+
+        let chunk_size = self.chunk_size();
+        let (current, data) = self
+            .inner
+            .fs
+            .read(&self.inner.peer, path, index * chunk_size as u64, chunk_size)
+            .await?;
+        {
+            let mut state = self.state();
+            if current.modified != entry.modified || current.size != entry.size {
+                state.chunks.invalidate(inode);
+            }
+            state.chunks.insert(inode, index, current.modified, data.clone());
+            state.entries.insert(inode, Cached::new(current.clone()));
+        }
+        *entry = current;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_cache_evicts_least_recently_used() {
+        let mut cache = ChunkCache::new(10);
+        cache.insert(2, 0, Some(1), vec![0; 4]);
+        cache.insert(2, 1, Some(1), vec![1; 4]);
+        assert!(cache.get(2, 0, Some(1)).is_some());
+
+        cache.insert(3, 0, None, vec![2; 4]);
+        assert_eq!(cache.bytes, 8);
+        assert!(cache.get(2, 1, Some(1)).is_none());
+        assert_eq!(cache.get(2, 0, Some(1)), Some(vec![0; 4]));
+
+        // A newer version of the file misses
+        assert!(cache.get(2, 0, Some(2)).is_none());
+
+        cache.invalidate(2);
+        assert_eq!(cache.bytes, 4);
+        cache.insert(4, 0, None, vec![0; 11]);
+        assert_eq!(cache.bytes, 4);
+    }
+}
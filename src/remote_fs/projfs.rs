@@ -0,0 +1,493 @@
+//! Projected File System driver for mounted shares
+//!
+//! [`ProjFsDriver`] serves a [`RemoteMount`] with the Windows Projected File
+//! System. The mountpoint becomes a virtualization root: Windows asks for a
+//! folder's entries when it is first listed and for a file's contents when it
+//! is first read, and keeps what it was given in the folder as placeholders.
+//! Callbacks run on ProjFS worker threads and block on the tokio runtime that
+//! started the mount while the peer answers.
+//!
+//! The mountpoint has to be an empty folder, and it is emptied again on
+//! unmount, so nothing read from one mount is served stale by the next.
+//! Deleting, renaming or editing projected files is refused; files created
+//! under the root by local programs stay local.
+//!
+//! Needs the "Windows Projected File System" optional feature, which provides
+//! `ProjectedFSLib.dll`.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Handle;
+use uuid::Uuid;
+use windows::core::{GUID, HRESULT, HSTRING, PCWSTR};
+use windows::Win32::Foundation::{
+    BOOLEAN, ERROR_ACCESS_DENIED, ERROR_DIRECTORY, ERROR_FILE_NOT_FOUND, ERROR_INSUFFICIENT_BUFFER,
+    ERROR_INTERNAL_ERROR, ERROR_INVALID_PARAMETER, ERROR_UNEXP_NET_ERR, ERROR_WRITE_PROTECT, E_OUTOFMEMORY, S_OK,
+    WIN32_ERROR,
+};
+use windows::Win32::Storage::ProjectedFileSystem::{
+    PrjAllocateAlignedBuffer, PrjFileNameCompare, PrjFileNameMatch, PrjFillDirEntryBuffer, PrjFreeAlignedBuffer,
+    PrjMarkDirectoryAsPlaceholder, PrjStartVirtualizing, PrjStopVirtualizing, PrjWriteFileData,
+    PrjWritePlaceholderInfo, PRJ_CALLBACKS, PRJ_CALLBACK_DATA, PRJ_CB_DATA_FLAG_ENUM_RESTART_SCAN,
+    PRJ_DIR_ENTRY_BUFFER_HANDLE, PRJ_FILE_BASIC_INFO, PRJ_NAMESPACE_VIRTUALIZATION_CONTEXT, PRJ_NOTIFICATION,
+    PRJ_NOTIFICATION_MAPPING, PRJ_NOTIFICATION_PARAMETERS, PRJ_NOTIFY_FILE_PRE_CONVERT_TO_FULL, PRJ_NOTIFY_PRE_DELETE,
+    PRJ_NOTIFY_PRE_RENAME, PRJ_NOTIFY_PRE_SET_HARDLINK, PRJ_PLACEHOLDER_INFO, PRJ_STARTVIRTUALIZING_OPTIONS,
+};
+
+use crate::remote_fs::mount::{Inode, ROOT_INODE};
+use crate::remote_fs::protocol::RemoteErrorKind;
+use crate::remote_fs::{BrowseError, BrowseResult, EntryKind, MountAttr, MountDriver, MountSession, RemoteMount};
+
+/// Most file data handed to ProjFS in one write
+const WRITE_BLOCK: u64 = 1024 * 1024;
+
+/// Milliseconds between 1601-01-01, where `FILETIME` counts from, and the Unix epoch
+const FILETIME_EPOCH_OFFSET_MS: u64 = 11_644_473_600_000;
+
+const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+
+/// Mounts shares through the Projected File System
+#[derive(Debug, Clone, Default)]
+pub struct ProjFsDriver;
+
+impl ProjFsDriver {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl MountDriver for ProjFsDriver {
+    fn name(&self) -> &str {
+        "projfs"
+    }
+
+    async fn mount(&self, mount: RemoteMount, mountpoint: &Path) -> BrowseResult<Box<dyn MountSession>> {
+        let mut contents = std::fs::read_dir(mountpoint).map_err(|e| BrowseError::io(mountpoint, e))?;
+        if contents.next().is_some() {
+            return Err(BrowseError::Mount(format!(
+                "{} is not an empty folder",
+                mountpoint.display()
+            )));
+        }
+
+        let provider = Arc::new(Provider {
+            mount,
+            runtime: Handle::current(),
+            enumerations: Mutex::new(HashMap::new()),
+        });
+        let root = HSTRING::from(mountpoint.as_os_str());
+        let instance = GUID::from_u128(Uuid::new_v4().as_u128());
+        let callbacks = PRJ_CALLBACKS {
+            StartDirectoryEnumerationCallback: Some(start_enumeration),
+            EndDirectoryEnumerationCallback: Some(end_enumeration),
+            GetDirectoryEnumerationCallback: Some(get_enumeration),
+            GetPlaceholderInfoCallback: Some(get_placeholder_info),
+            GetFileDataCallback: Some(get_file_data),
+            NotificationCallback: Some(notification),
+            ..Default::default()
+        };
+        // The empty notification root covers the whole mount
+        let mut mappings = [PRJ_NOTIFICATION_MAPPING {
+            NotificationBitMask: PRJ_NOTIFY_PRE_DELETE
+                | PRJ_NOTIFY_PRE_RENAME
+                | PRJ_NOTIFY_PRE_SET_HARDLINK
+                | PRJ_NOTIFY_FILE_PRE_CONVERT_TO_FULL,
+            NotificationRoot: windows::core::w!(""),
+        }];
+        let options = PRJ_STARTVIRTUALIZING_OPTIONS {
+            NotificationMappings: mappings.as_mut_ptr(),
+            NotificationMappingsCount: mappings.len() as u32,
+            ..Default::default()
+        };
+
+        // SAFETY: every pointer handed over outlives the call, and the provider
+        // passed as instance context is kept alive by the session until
+        // virtualization has stopped.
+        let context = unsafe {
+            PrjMarkDirectoryAsPlaceholder(&root, PCWSTR::null(), None, &instance)
+                .and_then(|()| {
+                    PrjStartVirtualizing(
+                        &root,
+                        &callbacks,
+                        Some(Arc::as_ptr(&provider) as *const c_void),
+                        Some(&options),
+                    )
+                })
+                .map_err(|e| BrowseError::Mount(format!("{}: {}", mountpoint.display(), e)))?
+        };
+
+        Ok(Box::new(ProjFsSession {
+            mountpoint: mountpoint.to_path_buf(),
+            context: VirtualizationContext(context),
+            provider,
+        }))
+    }
+}
+
+/// Handle of a running virtualization instance
+struct VirtualizationContext(PRJ_NAMESPACE_VIRTUALIZATION_CONTEXT);
+
+// SAFETY: the handle is an opaque token that ProjFS accepts from any thread
+unsafe impl Send for VirtualizationContext {}
+unsafe impl Sync for VirtualizationContext {}
+
+impl Drop for VirtualizationContext {
+    /// Stop serving the root; callbacks in flight have returned when this does
+    fn drop(&mut self) {
+        // SAFETY: the context came from PrjStartVirtualizing and is stopped once
+        unsafe { PrjStopVirtualizing(self.0) }
+    }
+}
+
+/// A share mounted through the Projected File System
+///
+/// Dropping it stops virtualization before the provider goes away.
+struct ProjFsSession {
+    mountpoint: PathBuf,
+    context: VirtualizationContext,
+    provider: Arc<Provider>,
+}
+
+#[async_trait]
+impl MountSession for ProjFsSession {
+    fn mountpoint(&self) -> &Path {
+        &self.mountpoint
+    }
+
+    async fn unmount(self: Box<Self>) -> BrowseResult<()> {
+        let ProjFsSession {
+            mountpoint,
+            context,
+            provider,
+        } = *self;
+        tokio::task::spawn_blocking(move || {
+            drop(context);
+            drop(provider);
+
+            // Placeholders and hydrated files would show stale data next time
+            std::fs::remove_dir_all(&mountpoint)
+                .and_then(|()| std::fs::create_dir(&mountpoint))
+                .map_err(|e| BrowseError::io(&mountpoint, e))
+        })
+        .await
+        .map_err(|e| BrowseError::Mount(e.to_string()))?
+    }
+}
+
+/// A directory listing handed out across enumeration callbacks
+struct Enumeration {
+    /// Entries in ProjFS collation order
+    entries: Vec<(String, MountAttr)>,
+    next: usize,
+    /// Search expression the enumeration was started with
+    filter: Option<HSTRING>,
+}
+
+/// ProjFS callbacks bridged onto a [`RemoteMount`]
+struct Provider {
+    mount: RemoteMount,
+    runtime: Handle,
+    enumerations: Mutex<HashMap<u128, Enumeration>>,
+}
+
+impl Provider {
+    /// Inode, attributes and share spelling of a root-relative Windows path
+    ///
+    /// Windows names are case-insensitive, so a component that does not match
+    /// exactly is looked up in its folder's listing.
+    fn resolve(&self, path: &str) -> BrowseResult<(Inode, MountAttr, String)> {
+        self.runtime.block_on(async {
+            let mut attr = self.mount.getattr(ROOT_INODE).await?;
+            let mut names = Vec::new();
+            for component in path.split('\\').filter(|component| !component.is_empty()) {
+                let parent = attr.inode;
+                let (name, found) = match self.mount.lookup(parent, component).await {
+                    Ok(found) => (component.to_string(), found),
+                    Err(BrowseError::NotFound(missing)) => self
+                        .mount
+                        .readdir(parent)
+                        .await?
+                        .into_iter()
+                        .find(|(name, _)| compare(name, component) == 0)
+                        .ok_or(BrowseError::NotFound(missing))?,
+                    Err(e) => return Err(e),
+                };
+                names.push(name);
+                attr = found;
+            }
+            Ok((attr.inode, attr, names.join("\\")))
+        })
+    }
+
+    fn start_enumeration(&self, id: u128, path: &str) -> BrowseResult<()> {
+        let (inode, _, _) = self.resolve(path)?;
+        let mut entries = self.runtime.block_on(self.mount.readdir(inode))?;
+        entries.sort_by(|(a, _), (b, _)| compare(a, b).cmp(&0));
+        self.enumerations().insert(
+            id,
+            Enumeration {
+                entries,
+                next: 0,
+                filter: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Fill `buffer` with the next entries of an enumeration
+    ///
+    /// # Safety
+    ///
+    /// `search` must be null or a valid string and `buffer` the handle passed
+    /// to the enumeration callback.
+    unsafe fn fill_enumeration(
+        &self,
+        id: u128,
+        restart: bool,
+        search: PCWSTR,
+        buffer: PRJ_DIR_ENTRY_BUFFER_HANDLE,
+    ) -> HRESULT {
+        let mut enumerations = self.enumerations();
+        let Some(enumeration) = enumerations.get_mut(&id) else {
+            return HRESULT::from_win32(ERROR_INVALID_PARAMETER.0);
+        };
+        if restart {
+            enumeration.next = 0;
+            enumeration.filter = None;
+        }
+        // The search expression only comes with the first call of a scan
+        if enumeration.filter.is_none() && !search.is_null() {
+            enumeration.filter = unsafe { search.to_hstring() }.ok();
+        }
+
+        let mut filled = false;
+        while let Some((name, attr)) = enumeration.entries.get(enumeration.next) {
+            let name = HSTRING::from(name.as_str());
+            let matches = match &enumeration.filter {
+                Some(filter) if !filter.is_empty() => unsafe { PrjFileNameMatch(&name, filter) }.as_bool(),
+                _ => true,
+            };
+            if matches {
+                let info = basic_info(attr);
+                if let Err(e) = unsafe { PrjFillDirEntryBuffer(&name, Some(&info), buffer) } {
+                    if e.code() == HRESULT::from_win32(ERROR_INSUFFICIENT_BUFFER.0) && filled {
+                        break;
+                    }
+                    return e.code();
+                }
+                filled = true;
+            }
+            enumeration.next += 1;
+        }
+        S_OK
+    }
+
+    /// Create the placeholder for a path that Windows asked about
+    ///
+    /// # Safety
+    ///
+    /// `context` must be the context passed to the callback.
+    unsafe fn write_placeholder(&self, context: PRJ_NAMESPACE_VIRTUALIZATION_CONTEXT, path: &str) -> HRESULT {
+        let (_, attr, path) = match self.resolve(path) {
+            Ok(found) => found,
+            Err(e) => return hresult("placeholder", &e),
+        };
+        let info = PRJ_PLACEHOLDER_INFO {
+            FileBasicInfo: basic_info(&attr),
+            ..Default::default()
+        };
+        let written = unsafe {
+            PrjWritePlaceholderInfo(
+                context,
+                &HSTRING::from(path.as_str()),
+                &info,
+                std::mem::size_of::<PRJ_PLACEHOLDER_INFO>() as u32,
+            )
+        };
+        written.map(|()| S_OK).unwrap_or_else(|e| e.code())
+    }
+
+    /// Hand ProjFS the bytes of a file from `offset`, `length` bytes long
+    ///
+    /// # Safety
+    ///
+    /// `context` and `stream` must be the ones passed to the callback.
+    unsafe fn write_file_data(
+        &self,
+        context: PRJ_NAMESPACE_VIRTUALIZATION_CONTEXT,
+        stream: &GUID,
+        path: &str,
+        offset: u64,
+        length: u32,
+    ) -> HRESULT {
+        let inode = match self.resolve(path) {
+            Ok((inode, _, _)) => inode,
+            Err(e) => return hresult("read", &e),
+        };
+
+        let end = offset + length as u64;
+        let mut position = offset;
+        while position < end {
+            let want = (end - position).min(WRITE_BLOCK) as usize;
+            let data = match self.runtime.block_on(self.mount.read(inode, position, want)) {
+                Ok(data) if !data.is_empty() => data,
+                Ok(_) => return hresult("read", &BrowseError::NotFound(path.to_string())),
+                Err(e) => return hresult("read", &e),
+            };
+
+            // ProjFS wants writes from a buffer it aligned itself
+            let buffer = unsafe { PrjAllocateAlignedBuffer(context, data.len()) };
+            if buffer.is_null() {
+                return E_OUTOFMEMORY;
+            }
+            let written = unsafe {
+                std::ptr::copy_nonoverlapping(data.as_ptr(), buffer as *mut u8, data.len());
+                let written = PrjWriteFileData(context, stream, buffer, position, data.len() as u32);
+                PrjFreeAlignedBuffer(buffer);
+                written
+            };
+            if let Err(e) = written {
+                return e.code();
+            }
+            position += data.len() as u64;
+        }
+        S_OK
+    }
+
+    fn enumerations(&self) -> std::sync::MutexGuard<'_, HashMap<u128, Enumeration>> {
+        self.enumerations
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The provider and root-relative path a callback is about
+///
+/// # Safety
+///
+/// `data` must be the callback data ProjFS passed in, whose instance context is
+/// a live [`Provider`].
+unsafe fn callback<'a>(data: *const PRJ_CALLBACK_DATA) -> (&'a Provider, &'a PRJ_CALLBACK_DATA, String) {
+    let data = unsafe { &*data };
+    let provider = unsafe { &*(data.InstanceContext as *const Provider) };
+    let path = if data.FilePathName.is_null() {
+        String::new()
+    } else {
+        unsafe { data.FilePathName.to_string() }.unwrap_or_default()
+    };
+    (provider, data, path)
+}
+
+unsafe extern "system" fn start_enumeration(data: *const PRJ_CALLBACK_DATA, id: *const GUID) -> HRESULT {
+    let (provider, _, path) = unsafe { callback(data) };
+    let id = unsafe { (*id).to_u128() };
+    match provider.start_enumeration(id, &path) {
+        Ok(()) => S_OK,
+        Err(e) => hresult("list", &e),
+    }
+}
+
+unsafe extern "system" fn end_enumeration(data: *const PRJ_CALLBACK_DATA, id: *const GUID) -> HRESULT {
+    let (provider, _, _) = unsafe { callback(data) };
+    provider.enumerations().remove(&unsafe { (*id).to_u128() });
+    S_OK
+}
+
+unsafe extern "system" fn get_enumeration(
+    data: *const PRJ_CALLBACK_DATA,
+    id: *const GUID,
+    search: PCWSTR,
+    buffer: PRJ_DIR_ENTRY_BUFFER_HANDLE,
+) -> HRESULT {
+    let (provider, data, _) = unsafe { callback(data) };
+    let restart = (data.Flags.0 & PRJ_CB_DATA_FLAG_ENUM_RESTART_SCAN.0) != 0;
+    unsafe { provider.fill_enumeration((*id).to_u128(), restart, search, buffer) }
+}
+
+unsafe extern "system" fn get_placeholder_info(data: *const PRJ_CALLBACK_DATA) -> HRESULT {
+    let (provider, data, path) = unsafe { callback(data) };
+    unsafe { provider.write_placeholder(data.NamespaceVirtualizationContext, &path) }
+}
+
+unsafe extern "system" fn get_file_data(data: *const PRJ_CALLBACK_DATA, offset: u64, length: u32) -> HRESULT {
+    let (provider, data, path) = unsafe { callback(data) };
+    unsafe {
+        provider.write_file_data(
+            data.NamespaceVirtualizationContext,
+            &data.DataStreamId,
+            &path,
+            offset,
+            length,
+        )
+    }
+}
+
+/// Refuses every change it is asked about; only pre-operation notifications are registered
+unsafe extern "system" fn notification(
+    _data: *const PRJ_CALLBACK_DATA,
+    _is_directory: BOOLEAN,
+    _notification: PRJ_NOTIFICATION,
+    _destination: PCWSTR,
+    _parameters: *mut PRJ_NOTIFICATION_PARAMETERS,
+) -> HRESULT {
+    HRESULT::from_win32(ERROR_ACCESS_DENIED.0)
+}
+
+/// Order of two names in a ProjFS listing, as `PrjFileNameCompare` reports it
+fn compare(a: &str, b: &str) -> i32 {
+    // SAFETY: both strings are valid and null-terminated for the call
+    unsafe { PrjFileNameCompare(&HSTRING::from(a), &HSTRING::from(b)) }
+}
+
+fn basic_info(attr: &MountAttr) -> PRJ_FILE_BASIC_INFO {
+    // FILETIME counts 100 ns intervals
+    let time = attr
+        .modified
+        .map(|millis| ((millis + FILETIME_EPOCH_OFFSET_MS) * 10_000) as i64)
+        .unwrap_or(0);
+    let is_dir = attr.kind == EntryKind::Directory;
+
+    PRJ_FILE_BASIC_INFO {
+        IsDirectory: BOOLEAN(is_dir as u8),
+        FileSize: if is_dir { 0 } else { attr.size as i64 },
+        CreationTime: time,
+        LastAccessTime: time,
+        LastWriteTime: time,
+        ChangeTime: time,
+        FileAttributes: if is_dir {
+            FILE_ATTRIBUTE_DIRECTORY | FILE_ATTRIBUTE_READONLY
+        } else {
+            FILE_ATTRIBUTE_READONLY
+        },
+    }
+}
+
+/// The result code a failed callback reports
+fn hresult(operation: &str, error: &BrowseError) -> HRESULT {
+    let code: WIN32_ERROR = match error {
+        BrowseError::NotFound(_) | BrowseError::UnknownShare(_) => ERROR_FILE_NOT_FOUND,
+        BrowseError::NotADirectory(_) => ERROR_DIRECTORY,
+        BrowseError::InvalidPath { .. } => ERROR_INVALID_PARAMETER,
+        BrowseError::ReadOnlyShare(_) => ERROR_WRITE_PROTECT,
+        BrowseError::PeerNotTrusted { .. } | BrowseError::PermissionDenied { .. } | BrowseError::OutsideShare(_) => {
+            ERROR_ACCESS_DENIED
+        }
+        BrowseError::Remote { kind, .. } => match kind {
+            RemoteErrorKind::NotFound => ERROR_FILE_NOT_FOUND,
+            RemoteErrorKind::PermissionDenied => ERROR_ACCESS_DENIED,
+            RemoteErrorKind::InvalidPath => ERROR_INVALID_PARAMETER,
+            RemoteErrorKind::Internal => ERROR_INTERNAL_ERROR,
+        },
+        BrowseError::Transport { .. } => ERROR_UNEXP_NET_ERR,
+        _ => ERROR_INTERNAL_ERROR,
+    };
+    if code == ERROR_INTERNAL_ERROR || code == ERROR_UNEXP_NET_ERR {
+        log::warn!("ProjFS {} failed: {}", operation, error);
+    }
+    HRESULT::from_win32(code.0)
+}
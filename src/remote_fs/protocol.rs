@@ -0,0 +1,90 @@
+//! Wire format for the browse channel
+//!
+//! Each request is sent on its own stream and answered with exactly one
+//! response, both as JSON with `MuxStream::send_message`.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::remote_fs::{BrowseResult, DirectoryListing, RemoteEntry};
+
+/// Largest frame accepted on the browse channel
+pub const MAX_FRAME_LEN: usize = 4 * 1024 * 1024;
+
+/// Largest chunk returned for one read, small enough to fit a frame once encoded
+pub const MAX_READ_LEN: usize = 512 * 1024;
+
+/// A request from the browsing peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BrowseRequest {
+    /// List a directory; the empty path lists the shares
+    List { path: String },
+    /// Describe one entry
+    Stat { path: String },
+    /// Find entries under `path` whose name contains `query`, ignoring case
+    Search { path: String, query: String, limit: usize },
+    /// Ask the peer to send a file with file transfer
+    Fetch { path: String },
+    /// Read part of a file in place
+    Read { path: String, offset: u64, length: usize },
+}
+
+impl BrowseRequest {
+    /// Short name for logs and errors
+    pub fn operation(&self) -> &'static str {
+        match self {
+            BrowseRequest::List { .. } => "list",
+            BrowseRequest::Stat { .. } => "stat",
+            BrowseRequest::Search { .. } => "search",
+            BrowseRequest::Fetch { .. } => "fetch",
+            BrowseRequest::Read { .. } => "read",
+        }
+    }
+
+    /// Serialize for sending
+    pub fn encode(&self) -> BrowseResult<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Parse a received request
+    pub fn decode(bytes: &[u8]) -> BrowseResult<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Why the serving peer refused a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteErrorKind {
+    PermissionDenied,
+    NotFound,
+    InvalidPath,
+    Internal,
+}
+
+/// The serving peer's answer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BrowseResponse {
+    Listing { listing: DirectoryListing },
+    Entry { entry: RemoteEntry },
+    SearchResults { entries: Vec<RemoteEntry>, truncated: bool },
+    /// The peer started sending the file
+    TransferStarted { entry: RemoteEntry, transfer_id: Uuid },
+    /// Part of a file; `entry` describes the file as it was read
+    Chunk { entry: RemoteEntry, offset: u64, data: Vec<u8> },
+    Error { kind: RemoteErrorKind, message: String },
+}
+
+impl BrowseResponse {
+    /// Serialize for sending
+    pub fn encode(&self) -> BrowseResult<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Parse a received response
+    pub fn decode(bytes: &[u8]) -> BrowseResult<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
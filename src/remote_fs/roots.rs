@@ -0,0 +1,540 @@
+//! Share roots and path resolution
+//!
+//! Every request path is split into components, checked for `..` and separator
+//! tricks, joined onto its share root and canonicalized. The canonical result
+//! must still lie under the canonical root, which also catches symlinks that
+//! point out of the share.
+//!
+//! Access is decided here too: a share is visible only to the peers it is
+//! exported to, directly or through a group, and writes need a read-write share.
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::remote_fs::{
+    join_path, BrowseError, BrowseResult, DirectoryListing, EntryKind, PeerId, RemoteEntry,
+    ShareConfig, ShareOperation, ShareRoot,
+};
+
+/// The set of folders this device shares, and who they are shared with
+#[derive(Debug, Clone, Default)]
+pub struct ShareRoots {
+    roots: Vec<ShareRoot>,
+    groups: HashMap<String, Vec<PeerId>>,
+}
+
+/// A request path resolved inside a share
+struct Resolved {
+    /// Canonical share root
+    root: PathBuf,
+    /// Canonical target, under `root`
+    target: PathBuf,
+    /// Normalized share-relative path
+    relative: String,
+}
+
+impl ShareRoots {
+    /// Validate and collect share roots
+    pub fn new(roots: Vec<ShareRoot>) -> BrowseResult<Self> {
+        let mut shares = Self::default();
+        for root in roots {
+            shares.add(root)?;
+        }
+        Ok(shares)
+    }
+
+    /// Build from a share configuration, checking that every group it names exists
+    pub fn from_config(config: &ShareConfig) -> BrowseResult<Self> {
+        let mut shares = Self {
+            roots: Vec::new(),
+            groups: config.groups.clone(),
+        };
+        for root in &config.shares {
+            shares.add(root.clone())?;
+        }
+        Ok(shares)
+    }
+
+    /// Define or replace a group
+    pub fn set_group(&mut self, name: impl Into<String>, members: Vec<PeerId>) {
+        self.groups.insert(name.into(), members);
+    }
+
+    /// Members of a group
+    pub fn group(&self, name: &str) -> Option<&[PeerId]> {
+        self.groups.get(name).map(Vec::as_slice)
+    }
+
+    /// Add a share root
+    pub fn add(&mut self, root: ShareRoot) -> BrowseResult<()> {
+        let invalid = |reason: &str| BrowseError::InvalidShare {
+            name: root.name.clone(),
+            reason: reason.to_string(),
+        };
+        if root.name.is_empty() || root.name == "." || root.name == ".." {
+            return Err(invalid("name must not be empty or a relative reference"));
+        }
+        if root.name.contains(['/', '\\', '\0']) {
+            return Err(invalid("name must not contain path separators"));
+        }
+        if !root.path.is_absolute() {
+            return Err(invalid("path must be absolute"));
+        }
+        if self.get(&root.name).is_some() {
+            return Err(invalid("a share with this name already exists"));
+        }
+        if let Some(group) = root.groups.iter().find(|group| !self.groups.contains_key(*group)) {
+            return Err(invalid(&format!("group {} is not defined", group)));
+        }
+        self.roots.push(root);
+        Ok(())
+    }
+
+    /// Remove a share root by name
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.roots.len();
+        self.roots.retain(|root| root.name != name);
+        self.roots.len() != before
+    }
+
+    /// Share root with this name
+    pub fn get(&self, name: &str) -> Option<&ShareRoot> {
+        self.roots.iter().find(|root| root.name == name)
+    }
+
+    /// All share roots
+    pub fn roots(&self) -> &[ShareRoot] {
+        &self.roots
+    }
+
+    /// Whether a share is exported to a peer, directly or through a group
+    pub fn is_member(&self, root: &ShareRoot, peer_id: &str) -> bool {
+        root.peers.iter().any(|peer| peer.eq_ignore_ascii_case(peer_id))
+            || root.groups.iter().any(|group| {
+                self.groups
+                    .get(group)
+                    .is_some_and(|members| members.iter().any(|peer| peer.eq_ignore_ascii_case(peer_id)))
+            })
+    }
+
+    /// The shares a peer may see
+    ///
+    /// Requests from a peer are answered against this view, so shares it is not
+    /// a member of look exactly like shares that do not exist.
+    pub fn visible_to(&self, peer_id: &str) -> ShareRoots {
+        ShareRoots {
+            roots: self
+                .roots
+                .iter()
+                .filter(|root| self.is_member(root, peer_id))
+                .cloned()
+                .collect(),
+            groups: self.groups.clone(),
+        }
+    }
+
+    /// Check that a peer may perform `operation` on a path, returning the local path
+    ///
+    /// For writes the file itself need not exist yet, but its parent directory must.
+    pub async fn authorize(&self, peer_id: &str, path: &str, operation: ShareOperation) -> BrowseResult<PathBuf> {
+        let view = self.visible_to(peer_id);
+        let parts = split_path(path)?;
+        let share = parts
+            .first()
+            .ok_or_else(|| BrowseError::invalid_path(path, "path does not name a share"))?;
+        let root = view
+            .get(share)
+            .ok_or_else(|| BrowseError::UnknownShare(share.to_string()))?;
+        if !root.permits(operation) {
+            return Err(BrowseError::ReadOnlyShare(root.name.clone()));
+        }
+
+        match operation {
+            ShareOperation::Read => view.local_path(path).await,
+            ShareOperation::Write => {
+                let Some((file_name, parent)) = parts.split_last().filter(|(_, parent)| !parent.is_empty()) else {
+                    return Err(BrowseError::invalid_path(path, "path does not name a file"));
+                };
+                let resolved = view.resolve(&parent.join("/")).await?;
+                if !metadata(&resolved.target).await?.is_dir() {
+                    return Err(BrowseError::NotADirectory(resolved.relative));
+                }
+                let target = resolved.target.join(file_name);
+                if tokio::fs::symlink_metadata(&target).await.is_ok_and(|m| m.is_dir() || m.file_type().is_symlink()) {
+                    return Err(BrowseError::invalid_path(path, "target is a directory or link"));
+                }
+                Ok(target)
+            }
+        }
+    }
+
+    /// Local file a share-relative path points to
+    pub async fn local_path(&self, path: &str) -> BrowseResult<PathBuf> {
+        Ok(self.resolve(path).await?.target)
+    }
+
+    /// List a directory, or the shares themselves for the empty path
+    pub async fn list(&self, path: &str, max_entries: usize) -> BrowseResult<DirectoryListing> {
+        if split_path(path)?.is_empty() {
+            return Ok(self.list_shares(max_entries).await);
+        }
+
+        let resolved = self.resolve(path).await?;
+        let metadata = metadata(&resolved.target).await?;
+        if !metadata.is_dir() {
+            return Err(BrowseError::NotADirectory(resolved.relative));
+        }
+
+        let mut read_dir = tokio::fs::read_dir(&resolved.target)
+            .await
+            .map_err(|e| BrowseError::io(&resolved.target, e))?;
+        let mut entries = Vec::new();
+        while let Some(dir_entry) = read_dir
+            .next_entry()
+            .await
+            .map_err(|e| BrowseError::io(&resolved.target, e))?
+        {
+            let Some(name) = dir_entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let entry_path = dir_entry.path();
+            let is_symlink = dir_entry.file_type().await.map(|t| t.is_symlink()).unwrap_or(true);
+            if is_symlink {
+                // Only links that stay inside the share are shown
+                match tokio::fs::canonicalize(&entry_path).await {
+                    Ok(target) if target.starts_with(&resolved.root) => {}
+                    _ => continue,
+                }
+            }
+            // Dangling or unreadable entries are left out rather than failing the listing
+            if let Ok(metadata) = tokio::fs::metadata(&entry_path).await {
+                entries.push(entry_for(join_path(&resolved.relative, &name), name, &metadata));
+            }
+        }
+
+        Ok(into_listing(resolved.relative, entries, max_entries))
+    }
+
+    /// Describe one entry
+    pub async fn stat(&self, path: &str) -> BrowseResult<RemoteEntry> {
+        let resolved = self.resolve(path).await?;
+        let metadata = metadata(&resolved.target).await?;
+        let name = resolved.relative.rsplit('/').next().unwrap_or_default().to_string();
+        Ok(entry_for(resolved.relative, name, &metadata))
+    }
+
+    /// Read up to `length` bytes of a file starting at `offset`
+    ///
+    /// Returns the file's entry alongside the data so callers can tell whether
+    /// it changed since their last read. Reads past the end return no data.
+    pub async fn read_range(&self, path: &str, offset: u64, length: usize) -> BrowseResult<(RemoteEntry, Vec<u8>)> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let resolved = self.resolve(path).await?;
+        let metadata = metadata(&resolved.target).await?;
+        if metadata.is_dir() {
+            return Err(BrowseError::invalid_path(resolved.relative, "not a file"));
+        }
+
+        let mut file = tokio::fs::File::open(&resolved.target)
+            .await
+            .map_err(|e| BrowseError::io(&resolved.target, e))?;
+        let available = metadata.len().saturating_sub(offset).min(length as u64);
+        let mut data = Vec::with_capacity(available as usize);
+        if available > 0 {
+            file.seek(std::io::SeekFrom::Start(offset))
+                .await
+                .map_err(|e| BrowseError::io(&resolved.target, e))?;
+            file.take(available)
+                .read_to_end(&mut data)
+                .await
+                .map_err(|e| BrowseError::io(&resolved.target, e))?;
+        }
+
+        let name = resolved.relative.rsplit('/').next().unwrap_or_default().to_string();
+        Ok((entry_for(resolved.relative, name, &metadata), data))
+    }
+
+    /// Find entries under `path` whose name contains `query`, ignoring case
+    ///
+    /// Symlinks are not followed. Returns the matches and whether the search
+    /// stopped at `limit`.
+    pub async fn search(
+        &self,
+        path: &str,
+        query: &str,
+        limit: usize,
+        max_depth: usize,
+    ) -> BrowseResult<(Vec<RemoteEntry>, bool)> {
+        if query.trim().is_empty() {
+            return Err(BrowseError::invalid_path(query, "search query is empty"));
+        }
+
+        let mut bases = Vec::new();
+        if split_path(path)?.is_empty() {
+            for root in &self.roots {
+                if let Ok(canonical) = tokio::fs::canonicalize(&root.path).await {
+                    bases.push((canonical, root.name.clone()));
+                }
+            }
+        } else {
+            let resolved = self.resolve(path).await?;
+            if !metadata(&resolved.target).await?.is_dir() {
+                return Err(BrowseError::NotADirectory(resolved.relative));
+            }
+            bases.push((resolved.target, resolved.relative));
+        }
+
+        let query = query.to_lowercase();
+        tokio::task::spawn_blocking(move || walk(bases, &query, limit, max_depth))
+            .await
+            .map_err(|e| BrowseError::io(PathBuf::new(), std::io::Error::other(e)))
+    }
+
+    async fn list_shares(&self, max_entries: usize) -> DirectoryListing {
+        let mut entries = Vec::new();
+        for root in &self.roots {
+            match tokio::fs::metadata(&root.path).await {
+                Ok(metadata) if metadata.is_dir() => {
+                    entries.push(entry_for(root.name.clone(), root.name.clone(), &metadata));
+                }
+                _ => log::debug!("Share {} at {} is unavailable", root.name, root.path.display()),
+            }
+        }
+        into_listing(String::new(), entries, max_entries)
+    }
+
+    async fn resolve(&self, path: &str) -> BrowseResult<Resolved> {
+        let parts = split_path(path)?;
+        let Some((share, rest)) = parts.split_first() else {
+            return Err(BrowseError::invalid_path(path, "path does not name a share"));
+        };
+        let relative = parts.join("/");
+
+        let share_root = self
+            .get(share)
+            .ok_or_else(|| BrowseError::UnknownShare(share.to_string()))?;
+        let root = match tokio::fs::canonicalize(&share_root.path).await {
+            Ok(root) => root,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(BrowseError::NotFound(share.to_string())),
+            Err(e) => return Err(BrowseError::io(&share_root.path, e)),
+        };
+
+        let joined = rest.iter().fold(root.clone(), |acc, part| acc.join(part));
+        let target = match tokio::fs::canonicalize(&joined).await {
+            Ok(target) => target,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(BrowseError::NotFound(relative)),
+            Err(e) => return Err(BrowseError::io(joined, e)),
+        };
+        if !target.starts_with(&root) {
+            return Err(BrowseError::OutsideShare(relative));
+        }
+
+        Ok(Resolved { root, target, relative })
+    }
+}
+
+/// Split a share-relative path into components, rejecting anything that could step outside
+pub(crate) fn split_path(path: &str) -> BrowseResult<Vec<&str>> {
+    let mut parts = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => return Err(BrowseError::invalid_path(path, "parent references are not allowed")),
+            part if part.contains(['\\', '\0', ':']) => {
+                return Err(BrowseError::invalid_path(path, "component contains a reserved character"));
+            }
+            part => parts.push(part),
+        }
+    }
+    Ok(parts)
+}
+
+async fn metadata(path: &Path) -> BrowseResult<Metadata> {
+    tokio::fs::metadata(path).await.map_err(|e| BrowseError::io(path, e))
+}
+
+fn entry_for(path: String, name: String, metadata: &Metadata) -> RemoteEntry {
+    let kind = if metadata.is_dir() { EntryKind::Directory } else { EntryKind::File };
+    RemoteEntry {
+        name,
+        path,
+        kind,
+        size: if metadata.is_dir() { 0 } else { metadata.len() },
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64),
+    }
+}
+
+fn into_listing(path: String, mut entries: Vec<RemoteEntry>, max_entries: usize) -> DirectoryListing {
+    entries.sort_by(|a, b| {
+        b.is_dir()
+            .cmp(&a.is_dir())
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    let truncated = entries.len() > max_entries;
+    entries.truncate(max_entries);
+    DirectoryListing { path, entries, truncated }
+}
+
+/// Breadth-first name search, run on a blocking thread
+fn walk(bases: Vec<(PathBuf, String)>, query: &str, limit: usize, max_depth: usize) -> (Vec<RemoteEntry>, bool) {
+    let mut queue: std::collections::VecDeque<(PathBuf, String, usize)> =
+        bases.into_iter().map(|(dir, relative)| (dir, relative, 0)).collect();
+    let mut results = Vec::new();
+
+    while let Some((dir, relative, depth)) = queue.pop_front() {
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for dir_entry in read_dir.flatten() {
+            let Ok(file_type) = dir_entry.file_type() else {
+                continue;
+            };
+            if file_type.is_symlink() {
+                continue;
+            }
+            let Some(name) = dir_entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let entry_relative = join_path(&relative, &name);
+
+            if name.to_lowercase().contains(query) {
+                if results.len() == limit {
+                    return (results, true);
+                }
+                if let Ok(metadata) = dir_entry.metadata() {
+                    results.push(entry_for(entry_relative.clone(), name, &metadata));
+                }
+            }
+            if file_type.is_dir() && depth + 1 < max_depth {
+                queue.push_back((dir_entry.path(), entry_relative, depth + 1));
+            }
+        }
+    }
+    (results, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn shares(dir: &TempDir) -> ShareRoots {
+        let docs = dir.path().join("docs");
+        std::fs::create_dir_all(docs.join("reports/2024")).unwrap();
+        std::fs::write(docs.join("readme.txt"), b"hello").unwrap();
+        std::fs::write(docs.join("reports/2024/Q1-report.pdf"), b"pdf").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), b"secret").unwrap();
+        ShareRoots::new(vec![ShareRoot::new("docs", docs)]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_list_and_stat() {
+        let dir = TempDir::new().unwrap();
+        let shares = shares(&dir);
+
+        let top = shares.list("", 100).await.unwrap();
+        assert_eq!(top.entries.len(), 1);
+        assert_eq!(top.entries[0].path, "docs");
+
+        let listing = shares.list("docs", 100).await.unwrap();
+        let names: Vec<&str> = listing.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["reports", "readme.txt"]);
+
+        let entry = shares.stat("docs/readme.txt").await.unwrap();
+        assert_eq!(entry.kind, EntryKind::File);
+        assert_eq!(entry.size, 5);
+        assert!(matches!(shares.list("docs/readme.txt", 100).await, Err(BrowseError::NotADirectory(_))));
+    }
+
+    #[tokio::test]
+    async fn test_read_range() {
+        let dir = TempDir::new().unwrap();
+        let roots = shares(&dir);
+
+        let (entry, data) = roots.read_range("docs/readme.txt", 1, 3).await.unwrap();
+        assert_eq!(entry.size, 5);
+        assert_eq!(data, b"ell");
+        assert_eq!(roots.read_range("docs/readme.txt", 3, 100).await.unwrap().1, b"lo");
+        assert!(roots.read_range("docs/readme.txt", 10, 4).await.unwrap().1.is_empty());
+        assert!(roots.read_range("docs/reports", 0, 4).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_paths_cannot_escape_share() {
+        let dir = TempDir::new().unwrap();
+        let shares = shares(&dir);
+
+        assert!(matches!(shares.stat("docs/../secret.txt").await, Err(BrowseError::InvalidPath { .. })));
+        assert!(matches!(shares.stat("other/readme.txt").await, Err(BrowseError::UnknownShare(_))));
+        assert!(matches!(shares.stat("docs/missing").await, Err(BrowseError::NotFound(_))));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path().join("secret.txt"), dir.path().join("docs/link")).unwrap();
+            assert!(matches!(shares.stat("docs/link").await, Err(BrowseError::OutsideShare(_))));
+            let listing = shares.list("docs", 100).await.unwrap();
+            assert!(listing.entries.iter().all(|e| e.name != "link"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shares_visible_only_to_members() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("work")).unwrap();
+        std::fs::create_dir_all(dir.path().join("family")).unwrap();
+        std::fs::write(dir.path().join("work/plan.txt"), b"plan").unwrap();
+
+        let mut config = ShareConfig::default();
+        config.groups.insert("home".to_string(), vec!["phone".to_string()]);
+        config.shares.push(ShareRoot::new("work", dir.path().join("work")).with_peer("laptop"));
+        config
+            .shares
+            .push(ShareRoot::new("family", dir.path().join("family")).read_write().with_group("home"));
+        let shares = ShareRoots::from_config(&config).unwrap();
+
+        let names = |peer: &str| -> Vec<String> {
+            shares.visible_to(peer).roots().iter().map(|r| r.name.clone()).collect()
+        };
+        assert_eq!(names("laptop"), vec!["work"]);
+        assert_eq!(names("phone"), vec!["family"]);
+        assert!(names("stranger").is_empty());
+
+        assert!(shares.authorize("laptop", "work/plan.txt", ShareOperation::Read).await.is_ok());
+        assert!(matches!(
+            shares.authorize("phone", "work/plan.txt", ShareOperation::Read).await,
+            Err(BrowseError::UnknownShare(_))
+        ));
+        assert!(matches!(
+            shares.authorize("laptop", "work/new.txt", ShareOperation::Write).await,
+            Err(BrowseError::ReadOnlyShare(_))
+        ));
+        let target = shares.authorize("phone", "family/new.txt", ShareOperation::Write).await.unwrap();
+        assert!(target.ends_with("family/new.txt"));
+
+        config.shares.push(ShareRoot::new("misc", dir.path()).with_group("nobody"));
+        assert!(matches!(ShareRoots::from_config(&config), Err(BrowseError::InvalidShare { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_search_by_name() {
+        let dir = TempDir::new().unwrap();
+        let shares = shares(&dir);
+
+        let (found, truncated) = shares.search("", "REPORT", 10, 8).await.unwrap();
+        let paths: Vec<&str> = found.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["docs/reports", "docs/reports/2024/Q1-report.pdf"]);
+        assert!(!truncated);
+
+        let (found, truncated) = shares.search("docs", "report", 1, 8).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(truncated);
+    }
+}
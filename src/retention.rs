@@ -0,0 +1,789 @@
+//! Data retention and secure wipe
+//!
+//! A [`RetentionEngine`] periodically purges clipboard history, transfer
+//! history, audit logs and recordings older than the ages set in
+//! [`RetentionConfig`]. Each store is registered as a [`RetentionTarget`], so the
+//! engine does not need to know how a store keeps its data.
+//!
+//! [`RetentionEngine::wipe_all`] is the panic button: it securely deletes every
+//! registered store, including the device identity and trust database, whatever
+//! their age. It can also be triggered by another of the user's devices with a
+//! signed [`RemoteWipeRequest`], if remote wipe was enabled beforehand.
+//!
+//! Secure deletion overwrites file contents before unlinking them. On SSDs and
+//! copy-on-write filesystems the old blocks may survive; combined with
+//! encryption at rest this still leaves nothing readable once the key is gone.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::security::identity::{DeviceIdentity, IdentityStore, PeerId};
+use crate::security::trust::TrustManagerImpl;
+use crate::transport::{ConnectionProvider, MuxStream, PeerAddress, CHANNEL_WIPE};
+
+/// Largest remote wipe message accepted
+const MAX_WIPE_MESSAGE_LEN: usize = 4096;
+
+/// Kind of data a target holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataCategory {
+    ClipboardHistory,
+    TransferHistory,
+    AuditLogs,
+    Recordings,
+    Messages,
+    CommandHistory,
+    Identity,
+    TrustDatabase,
+    Configuration,
+}
+
+/// How long each kind of data is kept
+///
+/// `None` keeps data until it is deleted by hand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub clipboard_history_days: Option<u32>,
+    pub transfer_history_days: Option<u32>,
+    pub audit_log_days: Option<u32>,
+    pub recording_days: Option<u32>,
+    /// Seconds between purges
+    pub sweep_interval_secs: u64,
+    /// Accept signed wipe requests from the user's other devices
+    pub allow_remote_wipe: bool,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            clipboard_history_days: Some(30),
+            transfer_history_days: Some(90),
+            audit_log_days: Some(90),
+            recording_days: None,
+            sweep_interval_secs: 3600,
+            allow_remote_wipe: false,
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// Maximum age for a category, if it is purged at all
+    pub fn max_age(&self, category: DataCategory) -> Option<Duration> {
+        let days = match category {
+            DataCategory::ClipboardHistory => self.clipboard_history_days,
+            DataCategory::TransferHistory => self.transfer_history_days,
+            DataCategory::AuditLogs => self.audit_log_days,
+            DataCategory::Recordings => self.recording_days,
+            _ => None,
+        }?;
+        Some(Duration::from_secs(u64::from(days) * 24 * 60 * 60))
+    }
+}
+
+/// A store whose old data can be purged and which can be wiped
+#[async_trait]
+pub trait RetentionTarget: Send + Sync {
+    /// Name shown in reports
+    fn name(&self) -> &str;
+
+    fn category(&self) -> DataCategory;
+
+    /// Delete data created before `cutoff`, returning how many items went
+    async fn purge_older_than(&self, cutoff: SystemTime) -> io::Result<usize>;
+
+    /// Securely delete everything
+    async fn wipe(&self) -> io::Result<()>;
+}
+
+/// Overwrite a file with zeros, sync it and remove it
+pub async fn secure_delete(path: &Path) -> io::Result<()> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || secure_delete_blocking(&path))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Securely delete every file under a directory, then the directory itself
+pub async fn secure_delete_dir(dir: &Path) -> io::Result<()> {
+    let dir = dir.to_path_buf();
+    tokio::task::spawn_blocking(move || secure_delete_dir_blocking(&dir))
+        .await
+        .map_err(io::Error::other)?
+}
+
+fn secure_delete_blocking(path: &Path) -> io::Result<()> {
+    let len = match std::fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+    let zeros = [0u8; 64 * 1024];
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::remove_file(path)
+}
+
+fn secure_delete_dir_blocking(dir: &Path) -> io::Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        // Symlinks are unlinked without following them out of the directory
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            secure_delete_dir_blocking(&entry.path())?;
+        } else if file_type.is_symlink() {
+            std::fs::remove_file(entry.path())?;
+        } else {
+            secure_delete_blocking(&entry.path())?;
+        }
+    }
+    std::fs::remove_dir(dir)
+}
+
+fn purge_dir_blocking(dir: &Path, cutoff: SystemTime) -> io::Result<usize> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut purged = 0;
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            purged += purge_dir_blocking(&entry.path(), cutoff)?;
+        } else if file_type.is_file() && entry.metadata()?.modified()? < cutoff {
+            secure_delete_blocking(&entry.path())?;
+            purged += 1;
+        }
+    }
+    Ok(purged)
+}
+
+/// Files in a directory, aged by modification time (recordings, transfer state)
+pub struct DirectoryTarget {
+    name: String,
+    category: DataCategory,
+    dir: PathBuf,
+}
+
+impl DirectoryTarget {
+    pub fn new(name: impl Into<String>, category: DataCategory, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            category,
+            dir: dir.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl RetentionTarget for DirectoryTarget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn category(&self) -> DataCategory {
+        self.category
+    }
+
+    async fn purge_older_than(&self, cutoff: SystemTime) -> io::Result<usize> {
+        let dir = self.dir.clone();
+        tokio::task::spawn_blocking(move || purge_dir_blocking(&dir, cutoff))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    async fn wipe(&self) -> io::Result<()> {
+        secure_delete_dir(&self.dir).await
+    }
+}
+
+/// A JSON lines log, aged by a timestamp field in each line (audit logs)
+pub struct JsonLinesTarget {
+    name: String,
+    category: DataCategory,
+    path: PathBuf,
+    /// JSON pointer to the Unix timestamp, e.g. "/event/timestamp"
+    timestamp_pointer: String,
+}
+
+impl JsonLinesTarget {
+    pub fn new(
+        name: impl Into<String>,
+        category: DataCategory,
+        path: impl Into<PathBuf>,
+        timestamp_pointer: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            category,
+            path: path.into(),
+            timestamp_pointer: timestamp_pointer.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl RetentionTarget for JsonLinesTarget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn category(&self) -> DataCategory {
+        self.category
+    }
+
+    async fn purge_older_than(&self, cutoff: SystemTime) -> io::Result<usize> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let cutoff = cutoff.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+        let mut kept = String::with_capacity(content.len());
+        let mut purged = 0;
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let timestamp = serde_json::from_str::<serde_json::Value>(line)
+                .ok()
+                .and_then(|value| value.pointer(&self.timestamp_pointer).and_then(|t| t.as_u64()));
+            // Lines without a readable timestamp are kept rather than guessed at
+            if timestamp.is_some_and(|t| t < cutoff) {
+                purged += 1;
+            } else {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+        }
+
+        if purged > 0 {
+            crate::journal::atomic_write(&self.path, kept.as_bytes()).await?;
+        }
+        Ok(purged)
+    }
+
+    async fn wipe(&self) -> io::Result<()> {
+        secure_delete(&self.path).await
+    }
+}
+
+/// A single file that is kept until wiped (trust database, command history)
+pub struct FileTarget {
+    name: String,
+    category: DataCategory,
+    path: PathBuf,
+}
+
+impl FileTarget {
+    pub fn new(name: impl Into<String>, category: DataCategory, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            category,
+            path: path.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl RetentionTarget for FileTarget {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn category(&self) -> DataCategory {
+        self.category
+    }
+
+    async fn purge_older_than(&self, _cutoff: SystemTime) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    async fn wipe(&self) -> io::Result<()> {
+        secure_delete(&self.path).await
+    }
+}
+
+/// The device identity held in the OS keyring
+pub struct IdentityTarget {
+    store: IdentityStore,
+}
+
+impl IdentityTarget {
+    pub fn new(store: IdentityStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl RetentionTarget for IdentityTarget {
+    fn name(&self) -> &str {
+        "device identity"
+    }
+
+    fn category(&self) -> DataCategory {
+        DataCategory::Identity
+    }
+
+    async fn purge_older_than(&self, _cutoff: SystemTime) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    async fn wipe(&self) -> io::Result<()> {
+        if !self.store.has_identity() {
+            return Ok(());
+        }
+        self.store.delete_identity().map_err(io::Error::other)
+    }
+}
+
+/// Outcome of a purge or wipe across all targets
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionReport {
+    /// Items removed per target
+    pub purged: Vec<(String, usize)>,
+    /// Targets that failed, with the error
+    pub errors: Vec<(String, String)>,
+}
+
+impl RetentionReport {
+    pub fn total_purged(&self) -> usize {
+        self.purged.iter().map(|(_, n)| n).sum()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Applies the retention policy to registered targets
+pub struct RetentionEngine {
+    config: RwLock<RetentionConfig>,
+    targets: RwLock<Vec<Arc<dyn RetentionTarget>>>,
+}
+
+impl RetentionEngine {
+    pub fn new(config: RetentionConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            targets: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Engine with Kizuna's default stores registered
+    ///
+    /// Covers the device identity, trust database, messages, recordings,
+    /// snapshots, transfer sessions and command history in their default locations.
+    pub async fn with_default_targets(config: RetentionConfig) -> Self {
+        let engine = Self::new(config);
+        engine.register(Arc::new(IdentityTarget::new(IdentityStore::default()))).await;
+        if let Some(data_dir) = dirs::data_local_dir() {
+            let kizuna = data_dir.join("kizuna");
+            engine
+                .register(Arc::new(FileTarget::new("trust database", DataCategory::TrustDatabase, kizuna.join("trust.db"))))
+                .await;
+            engine
+                .register(Arc::new(DirectoryTarget::new("messages", DataCategory::Messages, kizuna.join("messages"))))
+                .await;
+            engine
+                .register(Arc::new(DirectoryTarget::new("recordings", DataCategory::Recordings, kizuna.join("recordings"))))
+                .await;
+            engine
+                .register(Arc::new(DirectoryTarget::new("snapshots", DataCategory::Recordings, kizuna.join("snapshots"))))
+                .await;
+            engine
+                .register(Arc::new(DirectoryTarget::new(
+                    "transfer sessions",
+                    DataCategory::TransferHistory,
+                    kizuna.join("sessions"),
+                )))
+                .await;
+        }
+        if let Some(config_dir) = dirs::config_dir() {
+            engine
+                .register(Arc::new(FileTarget::new(
+                    "command history",
+                    DataCategory::CommandHistory,
+                    config_dir.join("kizuna").join("history"),
+                )))
+                .await;
+        }
+        engine
+    }
+
+    pub async fn register(&self, target: Arc<dyn RetentionTarget>) {
+        self.targets.write().await.push(target);
+    }
+
+    pub async fn config(&self) -> RetentionConfig {
+        self.config.read().await.clone()
+    }
+
+    pub async fn set_config(&self, config: RetentionConfig) {
+        *self.config.write().await = config;
+    }
+
+    /// Purge everything older than its category's maximum age
+    pub async fn sweep(&self) -> RetentionReport {
+        let config = self.config().await;
+        let now = SystemTime::now();
+        let mut report = RetentionReport::default();
+
+        for target in self.targets.read().await.iter() {
+            let Some(max_age) = config.max_age(target.category()) else {
+                continue;
+            };
+            let cutoff = now.checked_sub(max_age).unwrap_or(UNIX_EPOCH);
+            match target.purge_older_than(cutoff).await {
+                Ok(purged) => report.purged.push((target.name().to_string(), purged)),
+                Err(e) => {
+                    log::warn!("Retention purge of {} failed: {}", target.name(), e);
+                    report.errors.push((target.name().to_string(), e.to_string()));
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Run [`RetentionEngine::sweep`] every `sweep_interval_secs`
+    pub fn spawn(self: &Arc<Self>) -> JoinHandle<()> {
+        let engine = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                let report = engine.sweep().await;
+                if report.total_purged() > 0 {
+                    log::info!("Retention purged {} items", report.total_purged());
+                }
+                let interval = engine.config().await.sweep_interval_secs.max(60);
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        })
+    }
+
+    /// Securely delete every registered store
+    ///
+    /// Keeps going past failures so as much as possible is gone; check the
+    /// report for anything left behind.
+    pub async fn wipe_all(&self) -> RetentionReport {
+        let mut report = RetentionReport::default();
+
+        for target in self.targets.read().await.iter() {
+            match target.wipe().await {
+                Ok(()) => report.purged.push((target.name().to_string(), 1)),
+                Err(e) => {
+                    log::error!("Wipe of {} failed: {}", target.name(), e);
+                    report.errors.push((target.name().to_string(), e.to_string()));
+                }
+            }
+        }
+
+        report
+    }
+}
+
+/// Signed instruction from one of the user's devices to wipe another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteWipeRequest {
+    /// Device to wipe
+    pub target: PeerId,
+    /// Device asking for the wipe
+    pub issuer: PeerId,
+    /// Public key of the issuer, for verification
+    pub issuer_key: Vec<u8>,
+    /// Unix time the request was made
+    pub issued_at: u64,
+    pub signature: Vec<u8>,
+}
+
+impl RemoteWipeRequest {
+    /// Requests older than this are refused, so a captured one cannot be replayed later
+    pub const MAX_AGE_SECS: u64 = 600;
+
+    pub fn new(identity: &DeviceIdentity, target: PeerId) -> Self {
+        let issued_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut request = Self {
+            target,
+            issuer: identity.derive_peer_id(),
+            issuer_key: identity.public_key().as_bytes().to_vec(),
+            issued_at,
+            signature: Vec::new(),
+        };
+        request.signature = identity.sign(&request.signed_bytes()).to_vec();
+        request
+    }
+
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(b"kizuna-remote-wipe-v1");
+        message.extend_from_slice(self.target.fingerprint());
+        message.extend_from_slice(self.issuer.fingerprint());
+        message.extend_from_slice(&self.issued_at.to_le_bytes());
+        message
+    }
+
+    /// Check the signature and that the key belongs to the issuer
+    pub fn verify(&self) -> bool {
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+        let Ok(key_bytes) = <[u8; 32]>::try_from(self.issuer_key.as_slice()) else {
+            return false;
+        };
+        let Ok(signature_bytes) = <[u8; 64]>::try_from(self.signature.as_slice()) else {
+            return false;
+        };
+        let Ok(key) = VerifyingKey::from_bytes(&key_bytes) else {
+            return false;
+        };
+        PeerId::from_public_key(&key) == self.issuer
+            && key.verify(&self.signed_bytes(), &Signature::from_bytes(&signature_bytes)).is_ok()
+    }
+}
+
+/// Why a remote wipe request was refused
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RemoteWipeError {
+    #[error("Remote wipe is disabled on this device")]
+    Disabled,
+    #[error("Wipe request signature is invalid")]
+    InvalidSignature,
+    #[error("Wipe request is for another device")]
+    WrongTarget,
+    #[error("Wipe request is too old or from the future")]
+    Expired,
+    #[error("Wipe request issuer {0} is not one of this user's devices")]
+    UntrustedIssuer(String),
+    #[error("Remote wipe failed: {0}")]
+    Failed(String),
+}
+
+/// Sends and answers remote wipe requests
+#[derive(Clone)]
+pub struct RemoteWipe {
+    provider: Arc<ConnectionProvider>,
+    trust: Arc<TrustManagerImpl>,
+    engine: Arc<RetentionEngine>,
+    local: PeerId,
+}
+
+impl RemoteWipe {
+    pub fn new(
+        provider: Arc<ConnectionProvider>,
+        trust: Arc<TrustManagerImpl>,
+        engine: Arc<RetentionEngine>,
+        local: PeerId,
+    ) -> Self {
+        Self { provider, trust, engine, local }
+    }
+
+    /// Ask another of the user's devices to wipe itself
+    pub async fn request_wipe(&self, identity: &DeviceIdentity, peer: &PeerAddress) -> Result<(), RemoteWipeError> {
+        let target = PeerId::from_string(&peer.peer_id).map_err(|e| RemoteWipeError::Failed(e.to_string()))?;
+        let request = RemoteWipeRequest::new(identity, target);
+        let bytes = serde_json::to_vec(&request).map_err(|e| RemoteWipeError::Failed(e.to_string()))?;
+
+        let stream = self
+            .provider
+            .open_stream(peer, CHANNEL_WIPE)
+            .await
+            .map_err(|e| RemoteWipeError::Failed(e.to_string()))?;
+        let result = stream
+            .send_message(&bytes)
+            .await
+            .map_err(|e| RemoteWipeError::Failed(e.to_string()));
+        let _ = stream.close();
+        result
+    }
+
+    /// Check a request against local policy
+    pub async fn authorize(&self, request: &RemoteWipeRequest) -> Result<(), RemoteWipeError> {
+        if !self.engine.config().await.allow_remote_wipe {
+            return Err(RemoteWipeError::Disabled);
+        }
+        if !request.verify() {
+            return Err(RemoteWipeError::InvalidSignature);
+        }
+        if request.target != self.local {
+            return Err(RemoteWipeError::WrongTarget);
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        if now.abs_diff(request.issued_at) > RemoteWipeRequest::MAX_AGE_SECS {
+            return Err(RemoteWipeError::Expired);
+        }
+
+        let database = self.trust.trust_database();
+        let in_group = database
+            .is_in_device_group(&request.issuer)
+            .map_err(|e| RemoteWipeError::Failed(e.to_string()))?;
+        let revoked = database
+            .get_revocation(&request.issuer)
+            .map_err(|e| RemoteWipeError::Failed(e.to_string()))?
+            .is_some();
+        if !in_group || revoked {
+            return Err(RemoteWipeError::UntrustedIssuer(request.issuer.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Answer a wipe request arriving on a stream
+    pub fn attach_stream(&self, peer_id: &str, stream: MuxStream) -> Result<(), RemoteWipeError> {
+        if stream.channel() != CHANNEL_WIPE {
+            return Err(RemoteWipeError::Failed(format!(
+                "Stream on channel {} is not a wipe stream",
+                stream.channel()
+            )));
+        }
+
+        let wipe = self.clone();
+        let peer_id = peer_id.to_string();
+        tokio::spawn(async move {
+            let request = match stream.recv_message(MAX_WIPE_MESSAGE_LEN).await {
+                Ok(Some(bytes)) => serde_json::from_slice::<RemoteWipeRequest>(&bytes).ok(),
+                _ => None,
+            };
+            let _ = stream.close();
+
+            let Some(request) = request else {
+                log::warn!("Malformed wipe request from {}", peer_id);
+                return;
+            };
+            if request.issuer.to_string() != peer_id {
+                log::warn!("Wipe request relayed by {} for issuer {} refused", peer_id, request.issuer);
+                return;
+            }
+            match wipe.authorize(&request).await {
+                Ok(()) => {
+                    log::warn!("Wiping this device at the request of {}", request.issuer);
+                    let report = wipe.engine.wipe_all().await;
+                    if !report.is_success() {
+                        log::error!("Remote wipe left {} stores behind", report.errors.len());
+                    }
+                }
+                Err(e) => log::warn!("Refused wipe request from {}: {}", peer_id, e),
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn set_age(path: &Path, age: Duration) {
+        let file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sweep_purges_only_old_files() {
+        let dir = TempDir::new().unwrap();
+        let old = dir.path().join("old.mp4");
+        let new = dir.path().join("new.mp4");
+        std::fs::write(&old, b"old").unwrap();
+        std::fs::write(&new, b"new").unwrap();
+        set_age(&old, Duration::from_secs(10 * 24 * 3600));
+
+        let engine = RetentionEngine::new(RetentionConfig {
+            recording_days: Some(7),
+            ..Default::default()
+        });
+        engine
+            .register(Arc::new(DirectoryTarget::new("recordings", DataCategory::Recordings, dir.path())))
+            .await;
+
+        let report = engine.sweep().await;
+        assert_eq!(report.total_purged(), 1);
+        assert!(!old.exists());
+        assert!(new.exists());
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_lines_purged_by_timestamp() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("audit.log");
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        std::fs::write(
+            &path,
+            format!(
+                "{{\"event\":{{\"timestamp\":{}}}}}\n{{\"event\":{{\"timestamp\":{}}}}}\nnot json\n",
+                now - 100 * 24 * 3600,
+                now
+            ),
+        )
+        .unwrap();
+
+        let target = JsonLinesTarget::new("audit", DataCategory::AuditLogs, &path, "/event/timestamp");
+        let cutoff = SystemTime::now() - Duration::from_secs(90 * 24 * 3600);
+        assert_eq!(target.purge_older_than(cutoff).await.unwrap(), 1);
+
+        let kept = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(kept.lines().count(), 2);
+        assert!(kept.contains(&now.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_wipe_all_removes_everything() {
+        let dir = TempDir::new().unwrap();
+        let messages = dir.path().join("messages");
+        std::fs::create_dir_all(messages.join("peer")).unwrap();
+        std::fs::write(messages.join("peer").join("log.json"), b"hello").unwrap();
+        let trust = dir.path().join("trust.db");
+        std::fs::write(&trust, b"trust").unwrap();
+
+        let engine = RetentionEngine::new(RetentionConfig::default());
+        engine
+            .register(Arc::new(DirectoryTarget::new("messages", DataCategory::Messages, &messages)))
+            .await;
+        engine
+            .register(Arc::new(FileTarget::new("trust database", DataCategory::TrustDatabase, &trust)))
+            .await;
+        engine
+            .register(Arc::new(FileTarget::new("missing", DataCategory::CommandHistory, dir.path().join("none"))))
+            .await;
+
+        let report = engine.wipe_all().await;
+        assert!(report.is_success());
+        assert!(!messages.exists());
+        assert!(!trust.exists());
+    }
+
+    #[test]
+    fn test_remote_wipe_request_signature() {
+        let identity = DeviceIdentity::generate().unwrap();
+        let request = RemoteWipeRequest::new(&identity, PeerId::from_fingerprint([5; 32]));
+        assert!(request.verify());
+
+        let mut retargeted = request;
+        retargeted.target = PeerId::from_fingerprint([6; 32]);
+        assert!(!retargeted.verify());
+    }
+}
@@ -4,15 +4,21 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use crate::security::{Security, SecurityResult, SecurityError};
+use crate::security::error::EncryptionError;
 use crate::security::identity::{
     DeviceIdentity, PeerId, DisposableIdentity, IdentityStore, DisposableIdentityManager,
 };
 use crate::security::encryption::{EncryptionEngine, EncryptionEngineImpl, SessionId};
 use crate::security::trust::{
     TrustManager, TrustManagerImpl, TrustEntry, PairingCode, ServicePermissions, TrustLevel,
+    RevocationGossip, RevocationRecord, TotpSalt,
 };
+use crate::security::storage::{EncryptedStorage, StorageKey, StorageKeySource};
+use crate::transport::ConnectionProvider;
+use crate::liveness::Liveness;
 use crate::security::policy::{
     PolicyEngine, PolicyEngineImpl, SecurityPolicy, ConnectionType, SecurityEvent, InviteCode,
+    RateLimitedOperation,
 };
 
 /// Unified security system implementation
@@ -27,6 +33,8 @@ pub struct SecuritySystem {
     trust_manager: Arc<TrustManagerImpl>,
     /// Policy engine
     policy_engine: Arc<PolicyEngineImpl>,
+    /// Encryption at rest for persistent state (None = plaintext)
+    storage: Option<EncryptedStorage>,
 }
 
 impl SecuritySystem {
@@ -70,7 +78,19 @@ impl SecuritySystem {
             })?;
         }
         
-        let trust_manager = Arc::new(TrustManagerImpl::new(trust_db_path)?);
+        let storage = if config.encrypt_at_rest {
+            let key = match &config.storage_key_source {
+                StorageKeySource::Identity => StorageKey::from_identity(&identity_store.get_or_create_identity()?),
+                StorageKeySource::Keyring { service_name } => {
+                    StorageKey::from_keyring(service_name, &whoami::username())?
+                }
+            };
+            Some(EncryptedStorage::new(key))
+        } else {
+            None
+        };
+        
+        let trust_manager = Arc::new(TrustManagerImpl::with_storage(trust_db_path, storage.clone())?);
         
         // Initialize policy engine
         let policy_engine = Arc::new(PolicyEngineImpl::with_policy(config.security_policy));
@@ -81,9 +101,17 @@ impl SecuritySystem {
             encryption_engine,
             trust_manager,
             policy_engine,
+            storage,
         })
     }
     
+    /// Encryption at rest for other stores (clipboard history, journals, config)
+    ///
+    /// None when encryption at rest is disabled.
+    pub fn storage(&self) -> Option<EncryptedStorage> {
+        self.storage.clone()
+    }
+    
     /// Get the encryption engine
     pub fn encryption_engine(&self) -> Arc<EncryptionEngineImpl> {
         Arc::clone(&self.encryption_engine)
@@ -156,17 +184,85 @@ impl SecuritySystem {
         peer_id: &PeerId,
         nickname: String,
     ) -> SecurityResult<bool> {
+        self.policy_engine.check_operation_rate(peer_id, RateLimitedOperation::Pairing).await?;
         let verified = self.trust_manager.verify_pairing_code(code, peer_id).await?;
         
         if verified {
             // Add peer to trust list with Verified trust level
             let entry = TrustEntry::new(peer_id.clone(), nickname, TrustLevel::Verified);
             self.trust_manager.trust_database().add_peer(entry)?;
+        } else {
+            self.policy_engine.report_failed_pairing(peer_id)?;
+        }
+        
+        Ok(verified)
+    }
+    
+    /// Start rolling-code pairing with a secret typed on both devices
+    ///
+    /// The returned salt has to reach the other device, which joins with it.
+    pub fn start_totp_pairing(&self, secret: &str) -> SecurityResult<TotpSalt> {
+        self.trust_manager.pairing_service().start_totp_pairing(secret)
+    }
+    
+    /// Join rolling-code pairing another device started, with its salt
+    pub fn join_totp_pairing(&self, secret: &str, salt: &TotpSalt) -> SecurityResult<()> {
+        self.trust_manager.pairing_service().join_totp_pairing(secret, salt)
+    }
+    
+    /// The current rolling pairing code
+    pub fn totp_pairing_code(&self) -> SecurityResult<String> {
+        self.trust_manager.pairing_service().current_totp_code()
+    }
+    
+    /// Verify a rolling pairing code and add peer to trust list
+    pub async fn verify_totp_and_trust_peer(
+        &self,
+        code: &str,
+        peer_id: &PeerId,
+        nickname: String,
+    ) -> SecurityResult<bool> {
+        self.policy_engine.check_operation_rate(peer_id, RateLimitedOperation::Pairing).await?;
+        let verified = self.trust_manager.pairing_service().verify_totp_code(code, peer_id)?;
+        
+        if verified {
+            let entry = TrustEntry::new(peer_id.clone(), nickname, TrustLevel::Verified);
+            self.trust_manager.trust_database().add_peer(entry)?;
+        } else {
+            self.policy_engine.report_failed_pairing(peer_id)?;
         }
         
         Ok(verified)
     }
     
+    /// Revoke a peer here and sign a revocation for the rest of the device group
+    pub async fn revoke_peer(&self, peer_id: &PeerId, reason: Option<String>) -> SecurityResult<RevocationRecord> {
+        let identity = self.get_or_create_identity().await?;
+        self.trust_manager.revoke_peer(&identity, peer_id, reason).await
+    }
+    
+    /// Honor revocations issued by another of the user's devices
+    pub fn add_to_device_group(&self, peer_id: &PeerId) -> SecurityResult<()> {
+        self.trust_manager.add_to_device_group(peer_id)
+    }
+    
+    /// Keep trusting a peer even if another device revokes it
+    pub fn set_revocation_override(&self, peer_id: &PeerId, keep_trusted: bool) -> SecurityResult<()> {
+        self.trust_manager.set_revocation_override(peer_id, keep_trusted)
+    }
+    
+    /// Gossip service that spreads revocations to trusted peers on contact
+    pub async fn revocation_gossip(&self, provider: Arc<ConnectionProvider>) -> SecurityResult<RevocationGossip> {
+        let local = self.get_or_create_identity().await?.derive_peer_id();
+        Ok(RevocationGossip::new(provider, Arc::clone(&self.trust_manager), local))
+    }
+    
+    /// Liveness service that sends and answers authenticated pings
+    pub async fn liveness(&self, provider: Arc<ConnectionProvider>) -> SecurityResult<Liveness> {
+        let identity = self.get_or_create_identity().await?;
+        Ok(Liveness::new(provider, Arc::clone(&self.trust_manager), Arc::new(identity)))
+    }
+    
     /// Update permissions for a peer
     pub async fn update_peer_permissions(
         &self,
@@ -230,11 +326,27 @@ impl SecuritySystem {
         self.policy_engine.get_audit_log(limit).await
     }
     
+    /// Record an event in the audit log
+    pub async fn log_event(&self, event: SecurityEvent) -> SecurityResult<()> {
+        self.policy_engine.log_event(event).await
+    }
+    
     /// Cleanup expired sessions
     pub async fn cleanup_expired_sessions(&self) -> SecurityResult<usize> {
         self.encryption_engine.cleanup_expired_sessions().await
     }
     
+    /// Drop expired encryption and pairing sessions, and stale rate limit
+    /// and attack detection records
+    ///
+    /// Returns the number of encryption sessions dropped.
+    pub async fn reap_expired(&self) -> SecurityResult<usize> {
+        let removed = self.encryption_engine.cleanup_expired_sessions().await?;
+        self.trust_manager.cleanup_expired_sessions()?;
+        self.policy_engine.cleanup()?;
+        Ok(removed)
+    }
+    
     /// Get session count
     pub async fn session_count(&self) -> usize {
         self.encryption_engine.session_count().await
@@ -267,7 +379,15 @@ impl Security for SecuritySystem {
     }
     
     async fn decrypt_message(&self, session_id: &SessionId, data: &[u8]) -> SecurityResult<Vec<u8>> {
-        self.encryption_engine.decrypt_message(session_id, data).await
+        let result = self.encryption_engine.decrypt_message(session_id, data).await;
+        
+        if let Err(SecurityError::Encryption(EncryptionError::ReplayedNonce)) = &result
+            && let Some(peer_id) = self.encryption_engine.session_peer(session_id).await
+        {
+            self.policy_engine.report_replayed_nonce(&peer_id)?;
+        }
+        
+        result
     }
     
     async fn is_trusted(&self, peer_id: &PeerId) -> SecurityResult<bool> {
@@ -277,6 +397,19 @@ impl Security for SecuritySystem {
     async fn add_trusted_peer(&self, peer_id: PeerId, nickname: String) -> SecurityResult<()> {
         self.trust_manager.add_trusted_peer(peer_id, nickname).await
     }
+    
+    fn storage(&self) -> Option<EncryptedStorage> {
+        self.storage.clone()
+    }
+    
+    async fn check_operation_rate(&self, peer_id: &PeerId, operation: RateLimitedOperation) -> SecurityResult<()> {
+        self.policy_engine.check_operation_rate(peer_id, operation).await
+    }
+    
+    async fn report_malformed_message(&self, peer_id: &PeerId, detail: &str) -> SecurityResult<()> {
+        self.policy_engine.report_malformed_message(peer_id, detail)?;
+        Ok(())
+    }
 }
 
 /// Configuration for the security system
@@ -294,6 +427,10 @@ pub struct SecuritySystemConfig {
     pub disposable_identity_lifetime: Duration,
     /// Security policy
     pub security_policy: SecurityPolicy,
+    /// Encrypt persistent state at rest
+    pub encrypt_at_rest: bool,
+    /// Where the at-rest encryption key comes from
+    pub storage_key_source: StorageKeySource,
 }
 
 impl Default for SecuritySystemConfig {
@@ -305,6 +442,8 @@ impl Default for SecuritySystemConfig {
             key_rotation_interval: Duration::from_secs(900), // 15 minutes
             disposable_identity_lifetime: Duration::from_secs(86400), // 24 hours
             security_policy: SecurityPolicy::default(),
+            encrypt_at_rest: true,
+            storage_key_source: StorageKeySource::default(),
         }
     }
 }
@@ -358,6 +497,18 @@ impl SecuritySystemBuilder {
         self
     }
     
+    /// Enable or disable encryption of persistent state at rest
+    pub fn encrypt_at_rest(mut self, enabled: bool) -> Self {
+        self.config.encrypt_at_rest = enabled;
+        self
+    }
+    
+    /// Set where the at-rest encryption key comes from
+    pub fn storage_key_source(mut self, source: StorageKeySource) -> Self {
+        self.config.storage_key_source = source;
+        self
+    }
+    
     /// Build the security system
     pub fn build(self) -> SecurityResult<SecuritySystem> {
         SecuritySystem::with_config(self.config)
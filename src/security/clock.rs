@@ -0,0 +1,133 @@
+//! Wall-clock timestamps exchanged with peers
+//!
+//! Identity proofs and protocol hellos carry the sender's wall-clock time,
+//! which is only comparable with ours if both clocks agree. Expiry checks on
+//! such timestamps allow a configurable amount of skew either way, and the
+//! skew measured during the handshake is reported so the user can fix time
+//! sync before it breaks anything. Local deadlines use `Instant` instead and
+//! are unaffected by clock changes.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Skew tolerated in expiry checks unless configured otherwise
+pub const DEFAULT_CLOCK_SKEW_TOLERANCE_SECS: u64 = 120;
+
+/// Skew above which the user is told to fix time sync
+///
+/// Rolling pairing codes are accepted one 30 second step either side, so
+/// larger skew is the first thing to break.
+pub const CLOCK_SKEW_WARN_SECS: u64 = 30;
+
+/// Clock skew settings, part of the security policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClockSkewConfig {
+    /// Seconds a peer's timestamp may be off either way before it is
+    /// treated as expired or from the future
+    pub tolerance_secs: u64,
+}
+
+impl Default for ClockSkewConfig {
+    fn default() -> Self {
+        Self {
+            tolerance_secs: DEFAULT_CLOCK_SKEW_TOLERANCE_SECS,
+        }
+    }
+}
+
+/// How far a peer's clock is from ours
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockSkew {
+    /// Milliseconds the peer is ahead; negative when it is behind
+    pub offset_ms: i64,
+}
+
+impl ClockSkew {
+    /// Skew between two Unix timestamps in milliseconds taken at about the
+    /// same moment
+    pub fn between(local_ms: u64, remote_ms: u64) -> Self {
+        Self {
+            offset_ms: (remote_ms as i128 - local_ms as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        }
+    }
+
+    pub fn magnitude(&self) -> Duration {
+        Duration::from_millis(self.offset_ms.unsigned_abs())
+    }
+
+    /// Whether the skew is large enough to warn about
+    pub fn is_significant(&self) -> bool {
+        self.magnitude() > Duration::from_secs(CLOCK_SKEW_WARN_SECS)
+    }
+}
+
+impl fmt::Display for ClockSkew {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.magnitude().as_secs();
+        let direction = if self.offset_ms > 0 { "ahead of" } else { "behind" };
+        if secs >= 60 {
+            write!(f, "{}m {}s {} this device", secs / 60, secs % 60, direction)
+        } else {
+            write!(f, "{:.1}s {} this device", self.magnitude().as_secs_f64(), direction)
+        }
+    }
+}
+
+/// Current Unix time in seconds
+pub fn unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Current Unix time in milliseconds
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Whether a peer's timestamp is outside its lifetime as seen from `now`
+///
+/// `tolerance_secs` of skew is allowed either way: a timestamp only counts
+/// as expired once `lifetime_secs + tolerance_secs` have passed, and one
+/// further than `tolerance_secs` in the future is rejected too, since it
+/// would otherwise stay valid for longer than its lifetime.
+pub fn timestamp_expired(issued_at: u64, lifetime_secs: u64, tolerance_secs: u64, now: u64) -> bool {
+    issued_at > now.saturating_add(tolerance_secs)
+        || now > issued_at.saturating_add(lifetime_secs).saturating_add(tolerance_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_tolerates_skew_both_ways() {
+        let now = 1_000_000;
+        // Peer 90s behind, proof just issued
+        assert!(!timestamp_expired(now - 90, 60, 120, now));
+        // Peer 90s ahead
+        assert!(!timestamp_expired(now + 90, 60, 120, now));
+        // Too far in the future
+        assert!(timestamp_expired(now + 121, 60, 120, now));
+        // Lifetime and tolerance both used up
+        assert!(timestamp_expired(now - 181, 60, 120, now));
+        assert!(timestamp_expired(now - 61, 60, 0, now));
+    }
+
+    #[test]
+    fn test_skew_description() {
+        let skew = ClockSkew::between(10_000, 10_000 + 252_000);
+        assert!(skew.is_significant());
+        assert_eq!(skew.to_string(), "4m 12s ahead of this device");
+
+        let skew = ClockSkew::between(10_000, 7_500);
+        assert!(!skew.is_significant());
+        assert_eq!(skew.to_string(), "2.5s behind this device");
+    }
+}
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 use zeroize::{Zeroize, ZeroizeOnDrop};
@@ -77,19 +77,18 @@ pub struct SecuritySession {
     send_nonce_counter: u64,
     /// Nonce counter for receiving (prevents replay)
     recv_nonce_counter: u64,
-    /// Timestamp when session was created
-    created_at: u64,
-    /// Timestamp of last key rotation
-    last_rotation: u64,
+    /// When the session was created, on the monotonic clock
+    #[zeroize(skip)]
+    created_at: Instant,
+    /// When the keys were last rotated, on the monotonic clock
+    #[zeroize(skip)]
+    last_rotation: Instant,
 }
 
 impl SecuritySession {
     /// Create a new security session from a shared secret
     fn new(peer_id: PeerId, shared_secret: [u8; 32]) -> SecurityResult<Self> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| EncryptionError::KeyExchangeFailed(format!("System time error: {}", e)))?
-            .as_secs();
+        let now = Instant::now();
         
         // Derive separate send and receive keys using HKDF
         let (send_key, recv_key) = Self::derive_session_keys(&shared_secret)?;
@@ -156,7 +155,7 @@ impl SecuritySession {
         let is_valid = !ConstantTime::less_than_u64(counter, self.recv_nonce_counter + 1);
         
         if !is_valid {
-            return Err(EncryptionError::AuthenticationFailed.into());
+            return Err(EncryptionError::ReplayedNonce.into());
         }
         
         self.recv_nonce_counter = counter;
@@ -165,22 +164,12 @@ impl SecuritySession {
     
     /// Check if session has expired
     pub fn is_expired(&self, timeout: Duration) -> bool {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-        
-        now - self.created_at > timeout.as_secs()
+        self.created_at.elapsed() > timeout
     }
     
     /// Check if keys need rotation
     pub fn needs_rotation(&self, rotation_interval: Duration) -> bool {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-        
-        now - self.last_rotation > rotation_interval.as_secs()
+        self.last_rotation.elapsed() > rotation_interval
     }
     
     /// Rotate session keys for forward secrecy
@@ -208,7 +197,7 @@ impl SecuritySession {
         self.shared_secret = SecureKey::new(new_shared_secret);
         self.send_key = SecureKey::new(send_key);
         self.recv_key = SecureKey::new(recv_key);
-        self.last_rotation = now;
+        self.last_rotation = Instant::now();
         
         // Reset nonce counters after rotation
         self.send_nonce_counter = 0;
@@ -281,6 +270,11 @@ impl EncryptionEngineImpl {
         )
     }
     
+    /// Peer on the other end of a session
+    pub async fn session_peer(&self, session_id: &SessionId) -> Option<PeerId> {
+        self.sessions.read().await.get(session_id).map(|session| session.peer_id().clone())
+    }
+    
     /// Establish a session with a peer using key exchange
     /// 
     /// This performs X25519 ECDH key exchange and derives session keys
@@ -104,6 +104,9 @@ pub enum EncryptionError {
     #[error("Invalid nonce or authentication tag")]
     AuthenticationFailed,
     
+    #[error("Message nonce was already used")]
+    ReplayedNonce,
+    
     #[error("Key rotation failed: {0}")]
     KeyRotationFailed(String),
 }
@@ -128,6 +131,9 @@ pub enum PolicyError {
     
     #[error("Suspicious activity detected: {0}")]
     SuspiciousActivity(String),
+    
+    #[error("Address blocked: {0}")]
+    AddressBlocked(String),
 }
 
 /// Authentication errors
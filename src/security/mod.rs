@@ -6,6 +6,8 @@ pub mod error;
 pub mod api;
 pub mod secure_memory;
 pub mod constant_time;
+pub mod clock;
+pub mod storage;
 
 pub use error::{SecurityError, SecurityResult};
 pub use api::{SecuritySystem, SecuritySystemConfig, SecuritySystemBuilder};
@@ -13,6 +15,7 @@ pub use identity::{DeviceIdentity, PeerId, DisposableIdentity};
 pub use encryption::SessionId;
 pub use trust::TrustManager;
 pub use policy::{PolicyEngine, SecurityEvent, SecurityEventType};
+pub use storage::{EncryptedStorage, StorageKey, StorageKeySource};
 
 use async_trait::async_trait;
 
@@ -39,4 +42,29 @@ pub trait Security: Send + Sync {
     
     /// Add a trusted peer
     async fn add_trusted_peer(&self, peer_id: PeerId, nickname: String) -> SecurityResult<()>;
+    
+    /// Encryption at rest for the stores kept on behalf of this device
+    ///
+    /// `None` when encryption at rest is turned off.
+    fn storage(&self) -> Option<EncryptedStorage>;
+    
+    /// Spend one token of a peer's allowance for an operation
+    ///
+    /// Implementations without rate limiting allow everything.
+    async fn check_operation_rate(
+        &self,
+        peer_id: &PeerId,
+        operation: policy::RateLimitedOperation,
+    ) -> SecurityResult<()> {
+        let _ = (peer_id, operation);
+        Ok(())
+    }
+    
+    /// Report a message from a peer that decrypted but did not parse
+    ///
+    /// Implementations without attack detection ignore it.
+    async fn report_malformed_message(&self, peer_id: &PeerId, detail: &str) -> SecurityResult<()> {
+        let _ = (peer_id, detail);
+        Ok(())
+    }
 }
@@ -1,8 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
 use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 use crate::security::error::{SecurityResult, PolicyError};
 use crate::security::identity::PeerId;
+use super::RateLimitedOperation;
+
+/// Inbound connection attempts per address, as (timestamp, local port) pairs
+type ProbeLog = HashMap<IpAddr, Vec<(u64, u16)>>;
 
 /// Pattern of suspicious activity
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -17,26 +22,111 @@ pub enum SuspiciousPattern {
     UnusualTiming,
     /// Multiple connections from same peer
     MultipleConnections,
+    /// Rate limits hit over and over
+    RateLimitAbuse,
+    /// Connection attempts from one address across many ports
+    PortScan,
+    /// Messages that do not parse as the protocol
+    MalformedMessages,
+    /// Encrypted messages with nonces seen before
+    ReplayedNonces,
+}
+
+impl SuspiciousPattern {
+    /// Short description for alerts and audit entries
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SuspiciousPattern::RapidConnections => "rapid connection attempts",
+            SuspiciousPattern::FailedPairings => "repeated wrong pairing codes",
+            SuspiciousPattern::BlockedPeerAttempt => "connection attempts while blocked",
+            SuspiciousPattern::UnusualTiming => "connection attempts at machine-regular intervals",
+            SuspiciousPattern::MultipleConnections => "too many simultaneous connections",
+            SuspiciousPattern::RateLimitAbuse => "repeatedly exceeded rate limits",
+            SuspiciousPattern::PortScan => "connection attempts across many ports",
+            SuspiciousPattern::MalformedMessages => "malformed protocol messages",
+            SuspiciousPattern::ReplayedNonces => "replayed encrypted messages",
+        }
+    }
+}
+
+/// Raised when a peer or address is banned for suspicious activity
+#[derive(Clone, Debug)]
+pub struct AttackAlert {
+    /// Peer that was banned, if it identified itself
+    pub peer_id: Option<PeerId>,
+    /// Address that was banned, for attacks before any handshake
+    pub address: Option<IpAddr>,
+    /// What gave it away
+    pub patterns: Vec<SuspiciousPattern>,
+    /// How long the ban lasts
+    pub banned_for_secs: u64,
+}
+
+impl AttackAlert {
+    /// One-line summary for notifications
+    pub fn describe(&self) -> String {
+        let source = match (&self.peer_id, &self.address) {
+            (Some(peer_id), _) => format!("Peer {}", peer_id),
+            (None, Some(address)) => format!("Address {}", address),
+            (None, None) => "Unknown source".to_string(),
+        };
+        let reasons: Vec<&str> = self.patterns.iter().map(SuspiciousPattern::describe).collect();
+        format!(
+            "{} blocked for {} minutes: {}",
+            source,
+            self.banned_for_secs.div_ceil(60),
+            reasons.join(", ")
+        )
+    }
 }
 
 /// Activity record for a peer
 #[derive(Clone, Debug)]
 struct ActivityRecord {
     connection_attempts: Vec<u64>,
-    failed_pairings: u32,
+    failed_pairings: Vec<u64>,
     last_blocked_attempt: Option<u64>,
     active_connections: u32,
+    rate_limit_hits: Vec<(u64, RateLimitedOperation)>,
+    malformed_messages: Vec<u64>,
+    replayed_nonces: Vec<u64>,
 }
 
 impl ActivityRecord {
     fn new() -> Self {
         Self {
             connection_attempts: Vec::new(),
-            failed_pairings: 0,
+            failed_pairings: Vec::new(),
             last_blocked_attempt: None,
             active_connections: 0,
+            rate_limit_hits: Vec::new(),
+            malformed_messages: Vec::new(),
+            replayed_nonces: Vec::new(),
         }
     }
+    
+    /// Drop events older than the detection window
+    fn prune(&mut self, window_start: u64) {
+        self.connection_attempts.retain(|&timestamp| timestamp > window_start);
+        self.failed_pairings.retain(|&timestamp| timestamp > window_start);
+        self.rate_limit_hits.retain(|(timestamp, _)| *timestamp > window_start);
+        self.malformed_messages.retain(|&timestamp| timestamp > window_start);
+        self.replayed_nonces.retain(|&timestamp| timestamp > window_start);
+    }
+    
+    fn is_idle(&self) -> bool {
+        self.connection_attempts.is_empty()
+            && self.failed_pairings.is_empty()
+            && self.rate_limit_hits.is_empty()
+            && self.malformed_messages.is_empty()
+            && self.replayed_nonces.is_empty()
+            && self.active_connections == 0
+    }
+}
+
+/// Count timestamps inside the detection window
+fn count_since(timestamps: &[u64], window_start: u64) -> u32 {
+    timestamps.iter().filter(|&&timestamp| timestamp > window_start).count() as u32
 }
 
 /// Configuration for attack detection
@@ -50,6 +140,16 @@ pub struct AttackDetectorConfig {
     pub detection_window_secs: u64,
     /// Maximum simultaneous connections per peer
     pub max_simultaneous_connections: u32,
+    /// Rate limit hits per window before a peer counts as abusive
+    pub rate_limit_abuse_threshold: u32,
+    /// Distinct local ports one address may try per window
+    pub port_scan_threshold: u32,
+    /// Malformed messages per window before a peer counts as hostile
+    pub malformed_message_threshold: u32,
+    /// Replayed nonces per window before a peer counts as hostile
+    pub replayed_nonce_threshold: u32,
+    /// How long bans last (seconds)
+    pub ban_duration_secs: u64,
 }
 
 impl Default for AttackDetectorConfig {
@@ -59,6 +159,13 @@ impl Default for AttackDetectorConfig {
             failed_pairing_threshold: 3,
             detection_window_secs: 60,
             max_simultaneous_connections: 3,
+            rate_limit_abuse_threshold: 5,
+            // Kizuna listens on a handful of ports; a scanner tries many more
+            port_scan_threshold: 8,
+            malformed_message_threshold: 5,
+            // A reordered packet can look like a replay once, not repeatedly
+            replayed_nonce_threshold: 3,
+            ban_duration_secs: 3600,
         }
     }
 }
@@ -71,6 +178,10 @@ pub struct AttackDetector {
     activity: Arc<RwLock<HashMap<PeerId, ActivityRecord>>>,
     /// Blocked peers
     blocked_peers: Arc<RwLock<HashMap<PeerId, u64>>>,
+    /// Inbound connection attempts per address, with the local port tried
+    probes: Arc<RwLock<ProbeLog>>,
+    /// Banned addresses with unban timestamp
+    banned_addresses: Arc<RwLock<HashMap<IpAddr, u64>>>,
 }
 
 impl AttackDetector {
@@ -85,6 +196,8 @@ impl AttackDetector {
             config: Arc::new(RwLock::new(config)),
             activity: Arc::new(RwLock::new(HashMap::new())),
             blocked_peers: Arc::new(RwLock::new(HashMap::new())),
+            probes: Arc::new(RwLock::new(HashMap::new())),
+            banned_addresses: Arc::new(RwLock::new(HashMap::new())),
         }
     }
     
@@ -109,10 +222,70 @@ impl AttackDetector {
     
     /// Record a failed pairing attempt
     pub fn record_failed_pairing(&self, peer_id: &PeerId) -> SecurityResult<()> {
+        let now = Self::now();
+        let mut activity = self.activity.write().unwrap();
+        
+        let record = activity.entry(peer_id.clone()).or_insert_with(ActivityRecord::new);
+        record.failed_pairings.push(now);
+        
+        Ok(())
+    }
+    
+    /// Record a message from a peer that did not parse
+    pub fn record_malformed_message(&self, peer_id: &PeerId) -> SecurityResult<()> {
+        let now = Self::now();
+        let mut activity = self.activity.write().unwrap();
+        
+        let record = activity.entry(peer_id.clone()).or_insert_with(ActivityRecord::new);
+        record.malformed_messages.push(now);
+        
+        Ok(())
+    }
+    
+    /// Record an encrypted message whose nonce was already used
+    pub fn record_replayed_nonce(&self, peer_id: &PeerId) -> SecurityResult<()> {
+        let now = Self::now();
+        let mut activity = self.activity.write().unwrap();
+        
+        let record = activity.entry(peer_id.clone()).or_insert_with(ActivityRecord::new);
+        record.replayed_nonces.push(now);
+        
+        Ok(())
+    }
+    
+    /// Record an inbound connection before the peer identifies itself
+    pub fn record_connection_probe(&self, address: IpAddr, local_port: u16) -> SecurityResult<()> {
+        let now = Self::now();
+        let mut probes = self.probes.write().unwrap();
+        probes.entry(address).or_default().push((now, local_port));
+        Ok(())
+    }
+    
+    /// Check whether an address has tried too many of our ports
+    pub fn detect_port_scan(&self, address: IpAddr) -> SecurityResult<bool> {
+        let config = self.config.read().unwrap();
+        let window_start = Self::now().saturating_sub(config.detection_window_secs);
+        let probes = self.probes.read().unwrap();
+        
+        let ports: HashSet<u16> = probes.get(&address)
+            .map(|probes| {
+                probes.iter()
+                    .filter(|(timestamp, _)| *timestamp > window_start)
+                    .map(|(_, port)| *port)
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        Ok(ports.len() as u32 >= config.port_scan_threshold)
+    }
+    
+    /// Record a request refused by an operation rate limit
+    pub fn record_rate_limit_exceeded(&self, peer_id: &PeerId, operation: RateLimitedOperation) -> SecurityResult<()> {
+        let now = Self::now();
         let mut activity = self.activity.write().unwrap();
         
         let record = activity.entry(peer_id.clone()).or_insert_with(ActivityRecord::new);
-        record.failed_pairings += 1;
+        record.rate_limit_hits.push((now, operation));
         
         Ok(())
     }
@@ -167,15 +340,39 @@ impl AttackDetector {
             }
             
             // Check for failed pairings
-            if record.failed_pairings >= config.failed_pairing_threshold {
+            if count_since(&record.failed_pairings, window_start) >= config.failed_pairing_threshold {
                 patterns.push(SuspiciousPattern::FailedPairings);
             }
             
+            // Check for garbage and replayed messages
+            if count_since(&record.malformed_messages, window_start) >= config.malformed_message_threshold {
+                patterns.push(SuspiciousPattern::MalformedMessages);
+            }
+            if count_since(&record.replayed_nonces, window_start) >= config.replayed_nonce_threshold {
+                patterns.push(SuspiciousPattern::ReplayedNonces);
+            }
+            
             // Check for multiple simultaneous connections
             if record.active_connections > config.max_simultaneous_connections {
                 patterns.push(SuspiciousPattern::MultipleConnections);
             }
             
+            // Check for repeatedly exhausted rate limits; pairing limits are
+            // tight enough that hitting them at all points to code guessing
+            let recent_hits: Vec<RateLimitedOperation> = record.rate_limit_hits.iter()
+                .filter(|(timestamp, _)| *timestamp > window_start)
+                .map(|(_, operation)| *operation)
+                .collect();
+            let pairing_hits = recent_hits.iter()
+                .filter(|&&operation| operation == RateLimitedOperation::Pairing)
+                .count() as u32;
+            
+            if recent_hits.len() as u32 >= config.rate_limit_abuse_threshold
+                || pairing_hits >= config.failed_pairing_threshold
+            {
+                patterns.push(SuspiciousPattern::RateLimitAbuse);
+            }
+            
             // Check for unusual timing patterns (connections at very regular intervals)
             if record.connection_attempts.len() >= 5 {
                 let recent: Vec<u64> = record.connection_attempts.iter()
@@ -238,7 +435,10 @@ impl AttackDetector {
             match pattern {
                 SuspiciousPattern::RapidConnections |
                 SuspiciousPattern::FailedPairings |
-                SuspiciousPattern::BlockedPeerAttempt => {
+                SuspiciousPattern::BlockedPeerAttempt |
+                SuspiciousPattern::RateLimitAbuse |
+                SuspiciousPattern::MalformedMessages |
+                SuspiciousPattern::ReplayedNonces => {
                     return Ok(true);
                 }
                 _ => {}
@@ -284,6 +484,38 @@ impl AttackDetector {
         false
     }
     
+    /// Ban an address, refusing its connections before any handshake
+    pub fn ban_address(&self, address: IpAddr, duration_secs: u64) -> SecurityResult<()> {
+        let unban_time = Self::now() + duration_secs;
+        self.banned_addresses.write().unwrap().insert(address, unban_time);
+        Ok(())
+    }
+    
+    /// Lift an address ban
+    pub fn unban_address(&self, address: IpAddr) -> SecurityResult<()> {
+        self.banned_addresses.write().unwrap().remove(&address);
+        self.probes.write().unwrap().remove(&address);
+        Ok(())
+    }
+    
+    /// Check if an address is banned
+    pub fn is_address_banned(&self, address: IpAddr) -> bool {
+        let banned = self.banned_addresses.read().unwrap();
+        banned.get(&address).is_some_and(|&unban_time| Self::now() < unban_time)
+    }
+    
+    /// Number of peers blocked right now
+    pub fn blocked_peer_count(&self) -> usize {
+        let now = Self::now();
+        self.blocked_peers.read().unwrap().values().filter(|&&unblock_time| now < unblock_time).count()
+    }
+    
+    /// Number of addresses banned right now
+    pub fn banned_address_count(&self) -> usize {
+        let now = Self::now();
+        self.banned_addresses.read().unwrap().values().filter(|&&unban_time| now < unban_time).count()
+    }
+    
     /// Reset activity for a peer
     pub fn reset_peer_activity(&self, peer_id: &PeerId) -> SecurityResult<()> {
         let mut activity = self.activity.write().unwrap();
@@ -301,20 +533,24 @@ impl AttackDetector {
         let now = Self::now();
         let window_start = now - config.detection_window_secs;
         
-        // Cleanup old connection attempts
+        // Cleanup old connection attempts and other events
         let mut activity = self.activity.write().unwrap();
         for record in activity.values_mut() {
-            record.connection_attempts.retain(|&timestamp| timestamp > window_start);
+            record.prune(window_start);
         }
-        activity.retain(|_, record| {
-            !record.connection_attempts.is_empty() || 
-            record.failed_pairings > 0 || 
-            record.active_connections > 0
-        });
+        activity.retain(|_, record| !record.is_idle());
+        
+        let mut probes = self.probes.write().unwrap();
+        for address_probes in probes.values_mut() {
+            address_probes.retain(|(timestamp, _)| *timestamp > window_start);
+        }
+        probes.retain(|_, address_probes| !address_probes.is_empty());
         
         // Cleanup expired blocks
         let mut blocked_peers = self.blocked_peers.write().unwrap();
         blocked_peers.retain(|_, &mut unblock_time| now < unblock_time);
+        let mut banned_addresses = self.banned_addresses.write().unwrap();
+        banned_addresses.retain(|_, &mut unban_time| now < unban_time);
         
         Ok(())
     }
@@ -334,13 +570,20 @@ impl AttackDetector {
             
             Some(format!(
                 "Recent attempts: {}, Failed pairings: {}, Active connections: {}",
-                recent_attempts, record.failed_pairings, record.active_connections
+                recent_attempts,
+                count_since(&record.failed_pairings, window_start),
+                record.active_connections
             ))
         } else {
             None
         }
     }
     
+    /// Get current configuration
+    pub fn get_config(&self) -> AttackDetectorConfig {
+        self.config.read().unwrap().clone()
+    }
+    
     /// Update configuration
     pub fn update_config(&self, config: AttackDetectorConfig) -> SecurityResult<()> {
         let mut current_config = self.config.write().unwrap();
@@ -463,6 +706,70 @@ mod tests {
         assert!(summary.unwrap().contains("Recent attempts: 1"));
     }
     
+    #[test]
+    fn test_rate_limit_abuse_detection() {
+        let detector = AttackDetector::new();
+        let peer_id = PeerId::from_string("test_peer").unwrap();
+        
+        for _ in 0..4 {
+            detector.record_rate_limit_exceeded(&peer_id, RateLimitedOperation::ClipboardPush).unwrap();
+        }
+        assert!(!detector.should_block(&peer_id).unwrap());
+        
+        detector.record_rate_limit_exceeded(&peer_id, RateLimitedOperation::CommandRequest).unwrap();
+        let patterns = detector.detect_suspicious_patterns(&peer_id).unwrap();
+        assert!(patterns.contains(&SuspiciousPattern::RateLimitAbuse));
+        assert!(detector.should_block(&peer_id).unwrap());
+    }
+    
+    #[test]
+    fn test_port_scan_detection() {
+        let detector = AttackDetector::new();
+        let scanner: IpAddr = "192.0.2.7".parse().unwrap();
+        let neighbour: IpAddr = "192.0.2.8".parse().unwrap();
+        
+        // Reconnecting to the same port is not a scan
+        for _ in 0..20 {
+            detector.record_connection_probe(neighbour, 41337).unwrap();
+        }
+        assert!(!detector.detect_port_scan(neighbour).unwrap());
+        
+        for port in 8000..8008 {
+            detector.record_connection_probe(scanner, port).unwrap();
+        }
+        assert!(detector.detect_port_scan(scanner).unwrap());
+        
+        detector.ban_address(scanner, 60).unwrap();
+        assert!(detector.is_address_banned(scanner));
+        assert!(!detector.is_address_banned(neighbour));
+        
+        detector.unban_address(scanner).unwrap();
+        assert!(!detector.is_address_banned(scanner));
+        assert!(!detector.detect_port_scan(scanner).unwrap());
+    }
+    
+    #[test]
+    fn test_malformed_and_replayed_messages_detection() {
+        let detector = AttackDetector::new();
+        let peer_id = PeerId::from_string("test_peer").unwrap();
+        
+        // A single out-of-order packet is tolerated
+        detector.record_replayed_nonce(&peer_id).unwrap();
+        assert!(!detector.should_block(&peer_id).unwrap());
+        
+        for _ in 0..2 {
+            detector.record_replayed_nonce(&peer_id).unwrap();
+        }
+        for _ in 0..5 {
+            detector.record_malformed_message(&peer_id).unwrap();
+        }
+        
+        let patterns = detector.detect_suspicious_patterns(&peer_id).unwrap();
+        assert!(patterns.contains(&SuspiciousPattern::ReplayedNonces));
+        assert!(patterns.contains(&SuspiciousPattern::MalformedMessages));
+        assert!(detector.should_block(&peer_id).unwrap());
+    }
+    
     #[test]
     fn test_cleanup() {
         let config = AttackDetectorConfig {
@@ -470,6 +777,8 @@ mod tests {
             failed_pairing_threshold: 3,
             detection_window_secs: 1, // 1 second window
             max_simultaneous_connections: 3,
+            rate_limit_abuse_threshold: 5,
+            ..AttackDetectorConfig::default()
         };
         
         let detector = AttackDetector::with_config(config);
@@ -130,6 +130,9 @@ impl SecurityAuditor {
             SecurityEventType::RateLimitExceeded => Severity::Critical,
             SecurityEventType::SuspiciousActivity => Severity::Critical,
             SecurityEventType::PolicyViolation => Severity::Warning,
+            SecurityEventType::ClipboardSyncSent => Severity::Info,
+            SecurityEventType::ClipboardSyncReceived => Severity::Info,
+            SecurityEventType::ClipboardSyncFailed => Severity::Warning,
         }
     }
     
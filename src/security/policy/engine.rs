@@ -1,13 +1,27 @@
+use std::net::IpAddr;
 use std::sync::{Arc, RwLock};
 use async_trait::async_trait;
+use tokio::sync::broadcast;
 use crate::security::error::{SecurityResult, PolicyError};
 use crate::security::identity::PeerId;
 use super::{
     SecurityPolicy, ConnectionType, SecurityEvent, SecurityEventType,
     PolicyEngine, PrivateModeController, InviteCode, RateLimiter, SecurityAuditor,
-    NetworkPolicyEnforcer, AttackDetector,
+    NetworkPolicyEnforcer, AttackDetector, OperationRateLimiter, RateLimitedOperation,
+    AttackAlert, SuspiciousPattern, IpFilter, IpEntryPoint, SecurityStats,
 };
 
+/// Alerts buffered for slow subscribers
+const ALERT_CHANNEL_CAPACITY: usize = 32;
+
+/// Pattern names for audit entries
+fn pattern_names(patterns: &[SuspiciousPattern]) -> String {
+    patterns.iter()
+        .map(|p| format!("{:?}", p))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Implementation of the security policy engine
 pub struct PolicyEngineImpl {
     /// Current security policy
@@ -18,10 +32,16 @@ pub struct PolicyEngineImpl {
     network_policy: Arc<NetworkPolicyEnforcer>,
     /// Rate limiter for connection attempts
     rate_limiter: Arc<RateLimiter>,
+    /// Token buckets per peer and operation
+    operation_limiter: Arc<OperationRateLimiter>,
     /// Attack detector for suspicious patterns
     attack_detector: Arc<AttackDetector>,
+    /// Address filter for connections from the internet
+    ip_filter: Arc<IpFilter>,
     /// Security auditor for event logging
     auditor: Arc<SecurityAuditor>,
+    /// Bans raised by attack detection
+    alerts: broadcast::Sender<AttackAlert>,
 }
 
 impl PolicyEngineImpl {
@@ -32,8 +52,11 @@ impl PolicyEngineImpl {
             private_mode: Arc::new(PrivateModeController::new()),
             network_policy: Arc::new(NetworkPolicyEnforcer::new()),
             rate_limiter: Arc::new(RateLimiter::new()),
+            operation_limiter: Arc::new(OperationRateLimiter::new()),
             attack_detector: Arc::new(AttackDetector::new()),
+            ip_filter: Arc::new(IpFilter::new()),
             auditor: Arc::new(SecurityAuditor::new()),
+            alerts: broadcast::channel(ALERT_CHANNEL_CAPACITY).0,
         }
     }
     
@@ -50,6 +73,12 @@ impl PolicyEngineImpl {
             let _ = engine.network_policy.enable_local_only();
         }
         
+        let _ = engine.operation_limiter.update_limits(policy.rate_limits.clone());
+        
+        if let Err(e) = engine.ip_filter.update_config(policy.ip_filter.clone()) {
+            log::warn!("Address filter not applied: {}", e);
+        }
+        
         *engine.policy.write().unwrap() = policy;
         
         engine
@@ -66,43 +95,201 @@ impl PolicyEngineImpl {
         // Record the connection attempt
         self.attack_detector.record_connection_attempt(peer_id)?;
         
-        // Check for suspicious patterns
+        if let Some(patterns) = self.respond_to_activity(peer_id)? {
+            return Err(PolicyError::SuspiciousActivity(
+                format!("Blocked due to suspicious patterns: {}", pattern_names(&patterns))
+            ).into());
+        }
+        
+        Ok(false)
+    }
+    
+    /// Audit a peer's suspicious patterns and ban it when they are serious
+    ///
+    /// Returns the patterns that got the peer banned. The user is alerted
+    /// when a ban starts, not every time it is extended.
+    fn respond_to_activity(&self, peer_id: &PeerId) -> SecurityResult<Option<Vec<SuspiciousPattern>>> {
         let patterns = self.attack_detector.detect_suspicious_patterns(peer_id)?;
+        if patterns.is_empty() {
+            return Ok(None);
+        }
         
-        if !patterns.is_empty() {
-            let pattern_names: Vec<String> = patterns.iter()
-                .map(|p| format!("{:?}", p))
-                .collect();
-            
-            let event = SecurityEvent::new(
-                SecurityEventType::SuspiciousActivity,
-                Some(peer_id.clone()),
-                format!("Suspicious patterns detected: {}", pattern_names.join(", ")),
-            );
-            self.auditor.log_event(event)?;
-            
-            // Check if we should block
-            if self.attack_detector.should_block(peer_id)? {
-                // Block for 1 hour
-                self.attack_detector.block_peer(peer_id, 3600)?;
-                
-                return Err(PolicyError::SuspiciousActivity(
-                    format!("Blocked due to suspicious patterns: {}", pattern_names.join(", "))
-                ).into());
-            }
+        let event = SecurityEvent::new(
+            SecurityEventType::SuspiciousActivity,
+            Some(peer_id.clone()),
+            format!("Suspicious patterns detected: {}", pattern_names(&patterns)),
+        );
+        self.auditor.log_event(event)?;
+        
+        if !self.attack_detector.should_block(peer_id)? {
+            return Ok(None);
         }
         
-        Ok(false)
+        let already_blocked = self.attack_detector.is_blocked(peer_id);
+        let ban_duration_secs = self.attack_detector.get_config().ban_duration_secs;
+        self.attack_detector.block_peer(peer_id, ban_duration_secs)?;
+        
+        if !already_blocked {
+            self.raise_alert(AttackAlert {
+                peer_id: Some(peer_id.clone()),
+                address: None,
+                patterns: patterns.clone(),
+                banned_for_secs: ban_duration_secs,
+            });
+        }
+        
+        Ok(Some(patterns))
+    }
+    
+    /// Tell subscribers, and through them the user, about a new ban
+    fn raise_alert(&self, alert: AttackAlert) {
+        log::warn!("{}", alert.describe());
+        // Nobody listening is fine; the audit log still has the details
+        let _ = self.alerts.send(alert);
+    }
+    
+    /// Refuse an operation that ran out of tokens, audit it and block
+    /// peers that keep hitting their limits
+    fn enforce_operation_rate(&self, peer_id: &PeerId, operation: RateLimitedOperation) -> SecurityResult<()> {
+        if self.attack_detector.is_blocked(peer_id) {
+            return Err(PolicyError::SuspiciousActivity(
+                format!("Peer is blocked; refused {}", operation)
+            ).into());
+        }
+        
+        let Err(e) = self.operation_limiter.check(peer_id, operation) else {
+            return Ok(());
+        };
+        
+        let event = SecurityEvent::new(
+            SecurityEventType::RateLimitExceeded,
+            Some(peer_id.clone()),
+            format!("Rate limit exceeded for {}", operation),
+        );
+        self.auditor.log_event(event)?;
+        
+        self.attack_detector.record_rate_limit_exceeded(peer_id, operation)?;
+        self.respond_to_activity(peer_id)?;
+        
+        Err(e)
+    }
+    
+    /// Record a wrong pairing code; returns whether the peer is now banned
+    pub fn report_failed_pairing(&self, peer_id: &PeerId) -> SecurityResult<bool> {
+        self.attack_detector.record_failed_pairing(peer_id)?;
+        
+        let event = SecurityEvent::new(
+            SecurityEventType::PairingFailure,
+            Some(peer_id.clone()),
+            "Wrong pairing code".to_string(),
+        );
+        self.auditor.log_event(event)?;
+        
+        Ok(self.respond_to_activity(peer_id)?.is_some())
+    }
+    
+    /// Record a message from a peer that did not parse; returns whether the
+    /// peer is now banned
+    pub fn report_malformed_message(&self, peer_id: &PeerId, detail: &str) -> SecurityResult<bool> {
+        self.attack_detector.record_malformed_message(peer_id)?;
+        
+        let event = SecurityEvent::new(
+            SecurityEventType::PolicyViolation,
+            Some(peer_id.clone()),
+            format!("Malformed message: {}", detail),
+        );
+        self.auditor.log_event(event)?;
+        
+        Ok(self.respond_to_activity(peer_id)?.is_some())
+    }
+    
+    /// Record a message whose nonce was already used; returns whether the
+    /// peer is now banned
+    pub fn report_replayed_nonce(&self, peer_id: &PeerId) -> SecurityResult<bool> {
+        self.attack_detector.record_replayed_nonce(peer_id)?;
+        
+        let event = SecurityEvent::new(
+            SecurityEventType::PolicyViolation,
+            Some(peer_id.clone()),
+            "Replayed message nonce".to_string(),
+        );
+        self.auditor.log_event(event)?;
+        
+        Ok(self.respond_to_activity(peer_id)?.is_some())
+    }
+    
+    /// Screen an inbound connection before the peer identifies itself
+    ///
+    /// Fails for banned addresses, and bans addresses that try too many of
+    /// our ports.
+    pub fn screen_connection_probe(&self, address: IpAddr, local_port: u16) -> SecurityResult<()> {
+        if self.attack_detector.is_address_banned(address) {
+            return Err(PolicyError::SuspiciousActivity(
+                format!("Address {} is banned", address)
+            ).into());
+        }
+        
+        self.attack_detector.record_connection_probe(address, local_port)?;
+        if !self.attack_detector.detect_port_scan(address)? {
+            return Ok(());
+        }
+        
+        let ban_duration_secs = self.attack_detector.get_config().ban_duration_secs;
+        self.attack_detector.ban_address(address, ban_duration_secs)?;
+        
+        let event = SecurityEvent::new(
+            SecurityEventType::SuspiciousActivity,
+            None,
+            format!("Port scan from {}", address),
+        );
+        self.auditor.log_event(event)?;
+        
+        self.raise_alert(AttackAlert {
+            peer_id: None,
+            address: Some(address),
+            patterns: vec![SuspiciousPattern::PortScan],
+            banned_for_secs: ban_duration_secs,
+        });
+        
+        Err(PolicyError::SuspiciousActivity(format!("Port scan from {}", address)).into())
+    }
+    
+    /// Subscribe to bans raised by attack detection
+    pub fn subscribe_alerts(&self) -> broadcast::Receiver<AttackAlert> {
+        self.alerts.subscribe()
     }
     
     /// Perform periodic cleanup tasks
     pub fn cleanup(&self) -> SecurityResult<()> {
         self.rate_limiter.cleanup()?;
+        self.operation_limiter.cleanup()?;
         self.private_mode.cleanup_expired_codes()?;
         self.attack_detector.cleanup()?;
+        
+        // Pick up blocklist files refreshed on disk, e.g. by a cron job
+        if let Err(e) = self.ip_filter.reload_if_changed() {
+            log::warn!("Failed to reload address blocklists: {}", e);
+        }
         Ok(())
     }
     
+    /// Refuse a connection from the internet whose address the filter blocks
+    ///
+    /// Refusals are counted in the security stats rather than audited, since
+    /// blocked ranges can be noisy.
+    pub fn check_address(&self, address: IpAddr, entry_point: IpEntryPoint) -> SecurityResult<()> {
+        self.ip_filter.check(address, entry_point)
+    }
+    
+    /// Counters of refused connections and blocked peers
+    pub fn security_stats(&self) -> SecurityStats {
+        SecurityStats {
+            ip_filter: self.ip_filter.stats(),
+            blocked_peers: self.attack_detector.blocked_peer_count(),
+            banned_addresses: self.attack_detector.banned_address_count(),
+        }
+    }
+    
     /// Get the private mode controller
     pub fn private_mode_controller(&self) -> Arc<PrivateModeController> {
         Arc::clone(&self.private_mode)
@@ -113,6 +300,11 @@ impl PolicyEngineImpl {
         Arc::clone(&self.rate_limiter)
     }
     
+    /// Get the per-operation rate limiter
+    pub fn operation_limiter(&self) -> Arc<OperationRateLimiter> {
+        Arc::clone(&self.operation_limiter)
+    }
+    
     /// Get the security auditor
     pub fn auditor(&self) -> Arc<SecurityAuditor> {
         Arc::clone(&self.auditor)
@@ -127,6 +319,11 @@ impl PolicyEngineImpl {
     pub fn attack_detector(&self) -> Arc<AttackDetector> {
         Arc::clone(&self.attack_detector)
     }
+    
+    /// Get the address filter, for listeners that check it themselves
+    pub fn ip_filter(&self) -> Arc<IpFilter> {
+        Arc::clone(&self.ip_filter)
+    }
 }
 
 impl Default for PolicyEngineImpl {
@@ -164,6 +361,9 @@ impl PolicyEngine for PolicyEngineImpl {
         // Check for suspicious activity
         self.detect_suspicious_activity(peer_id)?;
         
+        // Connection attempts also count against the connection bucket
+        self.enforce_operation_rate(peer_id, RateLimitedOperation::Connection)?;
+        
         // Check local-only mode restrictions
         self.check_local_only_mode(&connection_type)?;
         
@@ -195,6 +395,9 @@ impl PolicyEngine for PolicyEngineImpl {
     }
     
     async fn update_policy(&self, new_policy: SecurityPolicy) -> SecurityResult<()> {
+        // Reload blocklists first so a missing file rejects the whole update
+        self.ip_filter.update_config(new_policy.ip_filter.clone())?;
+        
         let mut policy = self.policy.write().unwrap();
         
         // Update private mode if changed
@@ -206,6 +409,10 @@ impl PolicyEngine for PolicyEngineImpl {
             }
         }
         
+        if new_policy.rate_limits != policy.rate_limits {
+            self.operation_limiter.update_limits(new_policy.rate_limits.clone())?;
+        }
+        
         *policy = new_policy;
         Ok(())
     }
@@ -218,6 +425,14 @@ impl PolicyEngine for PolicyEngineImpl {
         self.rate_limiter.check_rate_limit(peer_id)
     }
     
+    async fn check_operation_rate(&self, peer_id: &PeerId, operation: RateLimitedOperation) -> SecurityResult<()> {
+        self.enforce_operation_rate(peer_id, operation)
+    }
+    
+    async fn screen_connection_probe(&self, address: IpAddr, local_port: u16) -> SecurityResult<()> {
+        PolicyEngineImpl::screen_connection_probe(self, address, local_port)
+    }
+    
     async fn enable_private_mode(&self) -> SecurityResult<()> {
         self.private_mode.enable()?;
         
@@ -336,6 +551,91 @@ mod tests {
         assert!(result.is_err());
     }
     
+    #[tokio::test]
+    async fn test_operation_rate_limit_blocks_abusive_peer() {
+        let engine = PolicyEngineImpl::new();
+        let peer_id = PeerId::from_string("test_peer").unwrap();
+        
+        // Three pairing attempts fit the default bucket
+        for _ in 0..3 {
+            engine.check_operation_rate(&peer_id, RateLimitedOperation::Pairing).await.unwrap();
+        }
+        
+        // Each refusal is audited; enough of them block the peer
+        for _ in 0..3 {
+            assert!(engine.check_operation_rate(&peer_id, RateLimitedOperation::Pairing).await.is_err());
+        }
+        assert!(engine.attack_detector.is_blocked(&peer_id));
+        assert!(engine.check_operation_rate(&peer_id, RateLimitedOperation::ClipboardPush).await.is_err());
+        
+        let log = engine.get_audit_log(10).await.unwrap();
+        assert!(log.iter().any(|e| matches!(e.event_type, SecurityEventType::RateLimitExceeded)));
+        assert!(log.iter().any(|e| matches!(e.event_type, SecurityEventType::SuspiciousActivity)));
+    }
+    
+    #[tokio::test]
+    async fn test_ip_filter_follows_policy_updates() {
+        let engine = PolicyEngineImpl::new();
+        let address: IpAddr = "203.0.113.8".parse().unwrap();
+        assert!(engine.check_address(address, IpEntryPoint::Relay).is_ok());
+        
+        let mut policy = engine.get_policy().await.unwrap();
+        policy.ip_filter.deny = vec!["203.0.113.0/24".parse().unwrap()];
+        engine.update_policy(policy).await.unwrap();
+        
+        assert!(engine.check_address(address, IpEntryPoint::Relay).is_err());
+        assert_eq!(engine.security_stats().ip_filter.denied, 1);
+        
+        // A policy pointing at a missing blocklist is refused as a whole
+        let mut policy = engine.get_policy().await.unwrap();
+        policy.ip_filter.deny.clear();
+        policy.ip_filter.blocklists = vec!["/nonexistent/kizuna-blocklist".into()];
+        assert!(engine.update_policy(policy).await.is_err());
+        assert!(engine.check_address(address, IpEntryPoint::Relay).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_port_scan_bans_address_and_alerts() {
+        let engine = PolicyEngineImpl::new();
+        let mut alerts = engine.subscribe_alerts();
+        let scanner: IpAddr = "198.51.100.4".parse().unwrap();
+        
+        let results: Vec<bool> = (9000..9008)
+            .map(|port| PolicyEngineImpl::screen_connection_probe(&engine, scanner, port).is_ok())
+            .collect();
+        assert!(results[..7].iter().all(|&ok| ok));
+        assert!(!results[7]);
+        
+        // Banned addresses are refused on any port
+        assert!(PolicyEngineImpl::screen_connection_probe(&engine, scanner, 9000).is_err());
+        
+        let alert = alerts.try_recv().unwrap();
+        assert_eq!(alert.address, Some(scanner));
+        assert_eq!(alert.patterns, vec![SuspiciousPattern::PortScan]);
+        assert!(alerts.try_recv().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_failed_pairings_ban_peer_once() {
+        let engine = PolicyEngineImpl::new();
+        let mut alerts = engine.subscribe_alerts();
+        let peer_id = PeerId::from_string("test_peer").unwrap();
+        
+        assert!(!engine.report_failed_pairing(&peer_id).unwrap());
+        assert!(!engine.report_failed_pairing(&peer_id).unwrap());
+        assert!(engine.report_failed_pairing(&peer_id).unwrap());
+        assert!(engine.report_failed_pairing(&peer_id).unwrap());
+        
+        // Only the first ban is announced
+        let alert = alerts.try_recv().unwrap();
+        assert_eq!(alert.peer_id, Some(peer_id.clone()));
+        assert!(alert.patterns.contains(&SuspiciousPattern::FailedPairings));
+        assert!(alerts.try_recv().is_err());
+        
+        let result = engine.is_connection_allowed(&peer_id, ConnectionType::LocalNetwork).await;
+        assert!(result.is_err());
+    }
+    
     #[tokio::test]
     async fn test_invite_code_generation() {
         let engine = PolicyEngineImpl::new();
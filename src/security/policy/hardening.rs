@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::security::trust::{TrustEntry, TrustLevel};
+use super::SecurityPolicy;
+
+/// How much a failed check weakens the device
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingSeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl FindingSeverity {
+    /// Points taken off the score when a check with this severity fails
+    pub fn weight(self) -> u32 {
+        match self {
+            Self::Low => 3,
+            Self::Medium => 8,
+            Self::High => 15,
+            Self::Critical => 30,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// Result of one hardening check
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HardeningFinding {
+    /// Stable identifier, e.g. "pairing-required"
+    pub check: String,
+    /// What was checked
+    pub title: String,
+    /// False when an input the check needs could not be read
+    pub evaluated: bool,
+    pub passed: bool,
+    pub severity: FindingSeverity,
+    /// What was found, or why the check was not evaluated
+    pub detail: String,
+    /// How to fix a failed check
+    pub remediation: Option<String>,
+}
+
+/// Scored outcome of a hardening check run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HardeningReport {
+    pub findings: Vec<HardeningFinding>,
+    /// 0 to 100, higher is better
+    pub score: u8,
+}
+
+impl HardeningReport {
+    fn new(findings: Vec<HardeningFinding>) -> Self {
+        let penalty: u32 = findings
+            .iter()
+            .filter(|f| f.evaluated && !f.passed)
+            .map(|f| f.severity.weight())
+            .sum();
+        let score = 100u32.saturating_sub(penalty) as u8;
+        Self { findings, score }
+    }
+
+    /// Letter grade for the score
+    ///
+    /// A device is not graded A while a high-severity check could not be
+    /// evaluated, since the issue it looks for may be present.
+    pub fn grade(&self) -> char {
+        let grade = match self.score {
+            90..=100 => 'A',
+            75..=89 => 'B',
+            60..=74 => 'C',
+            40..=59 => 'D',
+            _ => 'F',
+        };
+        let unchecked = self.not_evaluated().iter().any(|f| f.severity >= FindingSeverity::High);
+        if grade == 'A' && unchecked { 'B' } else { grade }
+    }
+
+    /// Failed checks, most severe first
+    pub fn failures(&self) -> Vec<&HardeningFinding> {
+        let mut failed: Vec<_> = self.findings.iter().filter(|f| f.evaluated && !f.passed).collect();
+        failed.sort_by_key(|f| std::cmp::Reverse(f.severity));
+        failed
+    }
+
+    /// Checks that passed
+    pub fn passed(&self) -> Vec<&HardeningFinding> {
+        self.findings.iter().filter(|f| f.evaluated && f.passed).collect()
+    }
+
+    /// Checks whose inputs could not be read, most severe first
+    pub fn not_evaluated(&self) -> Vec<&HardeningFinding> {
+        let mut skipped: Vec<_> = self.findings.iter().filter(|f| !f.evaluated).collect();
+        skipped.sort_by_key(|f| std::cmp::Reverse(f.severity));
+        skipped
+    }
+}
+
+/// How command execution is configured
+#[derive(Clone, Debug)]
+pub struct CommandExecutionPosture {
+    /// Whether commands from peers need authorization
+    pub require_authorization: bool,
+    /// Whether commands above low risk can be approved without a prompt
+    pub auto_approves_above_low_risk: bool,
+}
+
+/// One input to the hardening checks
+#[derive(Clone, Debug, Default)]
+pub enum HardeningInput<T> {
+    /// The subsystem is not part of this build; its checks do not apply
+    #[default]
+    NotApplicable,
+    /// The input could not be read; its checks are reported as not evaluated
+    Unavailable(String),
+    Known(T),
+}
+
+impl<T> HardeningInput<T> {
+    /// The value, or why it is missing; None when the checks do not apply
+    fn applicable(&self) -> Option<Result<&T, &str>> {
+        match self {
+            Self::NotApplicable => None,
+            Self::Unavailable(reason) => Some(Err(reason)),
+            Self::Known(value) => Some(Ok(value)),
+        }
+    }
+}
+
+/// Everything the hardening checks look at
+#[derive(Clone, Debug, Default)]
+pub struct HardeningInputs {
+    pub policy: HardeningInput<SecurityPolicy>,
+    /// Whether persistent state is encrypted at rest
+    pub encrypt_at_rest: HardeningInput<bool>,
+    pub trusted_peers: HardeningInput<Vec<TrustEntry>>,
+    pub command_execution: HardeningInput<CommandExecutionPosture>,
+    /// Whether the browser server requires HTTPS
+    pub browser_https_enforced: HardeningInput<bool>,
+    /// Config files, databases and keys that must not be readable by other users
+    pub sensitive_files: Vec<PathBuf>,
+    /// Protocol version this build speaks
+    pub local_protocol: (u8, u8),
+    /// Protocol versions recently seen from peers; the check is not
+    /// evaluated when there are none
+    pub peer_protocols: Vec<(u8, u8)>,
+}
+
+/// Longest key rotation interval considered safe
+const MAX_KEY_ROTATION: Duration = Duration::from_secs(3600);
+
+/// Run every applicable check and score the result
+pub fn run_hardening_checks(inputs: &HardeningInputs) -> HardeningReport {
+    let mut findings = Vec::new();
+
+    if let Some(policy) = inputs.policy.applicable() {
+        findings.push(check(
+            "pairing-required",
+            "Pairing required for new peers",
+            policy.map(|p| p.require_pairing),
+            FindingSeverity::High,
+            "Unpaired peers can connect",
+            "Set require_pairing in the security policy",
+        ));
+        findings.push(check(
+            "private-mode",
+            "Private mode",
+            policy.map(|p| p.private_mode),
+            FindingSeverity::Low,
+            "This device is visible to everyone on the network",
+            "Enable private mode so only invited peers can discover this device",
+        ));
+        findings.push(check(
+            "key-rotation",
+            "Session key rotation",
+            policy.map(|p| p.key_rotation_interval <= MAX_KEY_ROTATION),
+            FindingSeverity::Low,
+            &policy
+                .map(|p| format!("Keys rotate every {}s", p.key_rotation_interval.as_secs()))
+                .unwrap_or_default(),
+            "Rotate session keys at least hourly",
+        ));
+    }
+
+    if let Some(encrypted) = inputs.encrypt_at_rest.applicable() {
+        findings.push(check(
+            "encryption-at-rest",
+            "Persistent state encrypted at rest",
+            encrypted.copied(),
+            FindingSeverity::Medium,
+            "Clipboard history, trust database and journals are stored in plaintext",
+            "Enable encrypt_at_rest in the security configuration",
+        ));
+    }
+
+    if let Some(commands) = inputs.command_execution.applicable() {
+        findings.push(check(
+            "command-authorization",
+            "Remote commands require authorization",
+            commands.map(|c| c.require_authorization),
+            FindingSeverity::Critical,
+            "Any connected peer can run commands without approval",
+            "Turn require_authorization back on for command execution",
+        ));
+        findings.push(check(
+            "command-auto-approve",
+            "Only low-risk commands are auto-approved",
+            commands.map(|c| !c.auto_approves_above_low_risk),
+            FindingSeverity::Medium,
+            "Medium or high risk commands run without a prompt",
+            "Lower max_auto_approve_risk to low",
+        ));
+    }
+
+    if let Some(peers) = inputs.trusted_peers.applicable() {
+        let unverified_with_commands: Result<Vec<&str>, &str> = peers.map(|peers| {
+            peers
+                .iter()
+                .filter(|entry| entry.permissions.commands && entry.trust_level == TrustLevel::Allowlisted)
+                .map(|entry| entry.nickname.as_str())
+                .collect()
+        });
+        findings.push(check(
+            "unverified-command-peers",
+            "Command execution limited to verified peers",
+            unverified_with_commands.as_ref().map(|names| names.is_empty()).map_err(|e| *e),
+            FindingSeverity::High,
+            &format!(
+                "Unverified peers may run commands: {}",
+                unverified_with_commands.as_deref().unwrap_or_default().join(", ")
+            ),
+            "Pair these peers or revoke their command permission",
+        ));
+    }
+
+    if let Some(https) = inputs.browser_https_enforced.applicable() {
+        findings.push(check(
+            "browser-https",
+            "Browser server requires HTTPS",
+            https.copied(),
+            FindingSeverity::High,
+            "Browser clients can connect over plain HTTP",
+            "Run the browser server with HTTPS enforcement",
+        ));
+    }
+
+    let exposed = exposed_files(&inputs.sensitive_files);
+    findings.push(check(
+        "file-permissions",
+        "Sensitive files private to this user",
+        Ok(exposed.is_empty()),
+        FindingSeverity::Medium,
+        &format!("Readable by other users: {}", exposed.join(", ")),
+        "Run chmod 600 on these files",
+    ));
+
+    let newest = inputs.peer_protocols.iter().copied().max();
+    findings.push(check(
+        "protocol-version",
+        "Protocol version up to date",
+        newest
+            .map(|newest| newest <= inputs.local_protocol)
+            .ok_or("No peers advertised a protocol version to compare with"),
+        FindingSeverity::Medium,
+        &format!(
+            "This device speaks {}.{} but peers use {}",
+            inputs.local_protocol.0,
+            inputs.local_protocol.1,
+            newest.map(|(major, minor)| format!("{}.{}", major, minor)).unwrap_or_default()
+        ),
+        "Update Kizuna to get the latest protocol fixes",
+    ));
+
+    HardeningReport::new(findings)
+}
+
+/// Finding for one check; `outcome` is whether it passed, or why it could
+/// not be evaluated
+fn check(
+    id: &str,
+    title: &str,
+    outcome: Result<bool, &str>,
+    severity: FindingSeverity,
+    failure: &str,
+    remediation: &str,
+) -> HardeningFinding {
+    let (evaluated, passed, detail) = match outcome {
+        Ok(true) => (true, true, "OK".to_string()),
+        Ok(false) => (true, false, failure.to_string()),
+        Err(reason) => (false, false, reason.to_string()),
+    };
+    HardeningFinding {
+        check: id.to_string(),
+        title: title.to_string(),
+        evaluated,
+        passed,
+        severity,
+        detail,
+        remediation: (evaluated && !passed).then(|| remediation.to_string()),
+    }
+}
+
+#[cfg(unix)]
+fn exposed_files(paths: &[PathBuf]) -> Vec<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    paths
+        .iter()
+        .filter(|path| {
+            std::fs::metadata(path)
+                .map(|meta| meta.permissions().mode() & 0o077 != 0)
+                .unwrap_or(false)
+        })
+        .map(|path| path.display().to_string())
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn exposed_files(_paths: &[PathBuf]) -> Vec<String> {
+    // Access is governed by ACLs here, which inherit from the user profile
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::identity::PeerId;
+
+    #[test]
+    fn test_default_posture_scores_well() {
+        let inputs = HardeningInputs {
+            policy: HardeningInput::Known(SecurityPolicy::default()),
+            encrypt_at_rest: HardeningInput::Known(true),
+            trusted_peers: HardeningInput::Known(Vec::new()),
+            local_protocol: (1, 0),
+            peer_protocols: vec![(1, 0)],
+            ..Default::default()
+        };
+        let report = run_hardening_checks(&inputs);
+
+        // Only private mode, which is off by default, should fail
+        let failures = report.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].check, "private-mode");
+        assert_eq!(report.grade(), 'A');
+    }
+
+    #[test]
+    fn test_risky_posture_flagged() {
+        let mut peer = TrustEntry::new(PeerId::from_fingerprint([4; 32]), "kiosk".to_string(), TrustLevel::Allowlisted);
+        peer.permissions.commands = true;
+        let inputs = HardeningInputs {
+            command_execution: HardeningInput::Known(CommandExecutionPosture {
+                require_authorization: false,
+                auto_approves_above_low_risk: true,
+            }),
+            trusted_peers: HardeningInput::Known(vec![peer]),
+            browser_https_enforced: HardeningInput::Known(false),
+            local_protocol: (1, 0),
+            peer_protocols: vec![(1, 2)],
+            ..Default::default()
+        };
+        let report = run_hardening_checks(&inputs);
+
+        let failures = report.failures();
+        assert_eq!(failures[0].check, "command-authorization");
+        assert!(failures.iter().any(|f| f.check == "unverified-command-peers" && f.detail.contains("kiosk")));
+        assert!(failures.iter().any(|f| f.check == "protocol-version"));
+        assert!(failures.iter().all(|f| f.remediation.is_some()));
+        assert_eq!(report.grade(), 'F');
+    }
+
+    #[test]
+    fn test_unreadable_inputs_not_evaluated() {
+        let reason = "Security system unavailable".to_string();
+        let inputs = HardeningInputs {
+            policy: HardeningInput::Unavailable(reason.clone()),
+            encrypt_at_rest: HardeningInput::Unavailable(reason.clone()),
+            trusted_peers: HardeningInput::Unavailable(reason),
+            local_protocol: (1, 0),
+            ..Default::default()
+        };
+        let report = run_hardening_checks(&inputs);
+
+        // Nothing failed, but the pairing check could not run
+        assert!(report.failures().is_empty());
+        assert_eq!(report.score, 100);
+        let skipped = report.not_evaluated();
+        assert_eq!(skipped[0].severity, FindingSeverity::High);
+        assert!(skipped.iter().any(|f| f.check == "pairing-required"));
+        assert!(skipped.iter().any(|f| f.check == "protocol-version"));
+        assert!(skipped.iter().all(|f| f.remediation.is_none()));
+        assert_eq!(report.grade(), 'B');
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_world_readable_file_flagged() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, b"").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(exposed_files(std::slice::from_ref(&path)).len(), 1);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(exposed_files(&[path]).is_empty());
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::security::error::{SecurityResult, PolicyError};
+
+/// An address range in CIDR notation, e.g. `203.0.113.0/24` or `2001:db8::/32`
+///
+/// A bare address is a range of one. IPv4-mapped IPv6 addresses are matched
+/// as the IPv4 address they carry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    /// Create the range of `prefix_len` leading bits of `address`
+    pub fn new(address: IpAddr, prefix_len: u8) -> Result<Self, String> {
+        let address = address.to_canonical();
+        if prefix_len > max_prefix_len(address) {
+            return Err(format!("prefix /{} is too long for {}", prefix_len, address));
+        }
+        Ok(Self {
+            network: mask(address, prefix_len),
+            prefix_len,
+        })
+    }
+
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Check whether an address falls in the range
+    pub fn contains(&self, address: IpAddr) -> bool {
+        let address = address.to_canonical();
+        address.is_ipv4() == self.network.is_ipv4() && mask(address, self.prefix_len) == self.network
+    }
+}
+
+fn max_prefix_len(address: IpAddr) -> u8 {
+    if address.is_ipv4() { 32 } else { 128 }
+}
+
+/// Clear all but the leading `prefix_len` bits
+fn mask(address: IpAddr, prefix_len: u8) -> IpAddr {
+    let host_bits = u32::from(max_prefix_len(address) - prefix_len);
+    match address {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(host_bits).unwrap_or(0);
+            IpAddr::V4((u32::from(v4) & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(host_bits).unwrap_or(0);
+            IpAddr::V6((u128::from(v6) & mask).into())
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None),
+        };
+
+        let address: IpAddr = address
+            .parse()
+            .map_err(|_| format!("invalid address '{}'", address))?;
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse()
+                .map_err(|_| format!("invalid prefix length '{}'", prefix_len))?,
+            None => max_prefix_len(address.to_canonical()),
+        };
+        Self::new(address, prefix_len)
+    }
+}
+
+impl TryFrom<String> for IpRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<IpRange> for String {
+    fn from(range: IpRange) -> Self {
+        range.to_string()
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prefix_len == max_prefix_len(self.network) {
+            write!(f, "{}", self.network)
+        } else {
+            write!(f, "{}/{}", self.network, self.prefix_len)
+        }
+    }
+}
+
+/// Globally reachable listener an address filter is applied on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpEntryPoint {
+    /// The relay service, which also hosts rendezvous
+    Relay,
+    /// The browser API server
+    BrowserServer,
+    /// A drop box served on its own listener
+    DropBox,
+}
+
+impl fmt::Display for IpEntryPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpEntryPoint::Relay => write!(f, "relay"),
+            IpEntryPoint::BrowserServer => write!(f, "browser server"),
+            IpEntryPoint::DropBox => write!(f, "drop box"),
+        }
+    }
+}
+
+/// Why an address was refused
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpBlockReason {
+    /// On the deny list
+    Denied,
+    /// An allow list is set and the address is not on it
+    NotAllowed,
+    /// In one of the blocklist files
+    Blocklisted,
+}
+
+impl fmt::Display for IpBlockReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpBlockReason::Denied => write!(f, "on the deny list"),
+            IpBlockReason::NotAllowed => write!(f, "not on the allow list"),
+            IpBlockReason::Blocklisted => write!(f, "on a blocklist"),
+        }
+    }
+}
+
+/// Address filter for connections from the internet
+///
+/// Addresses on the deny list are always refused. Addresses on the allow
+/// list are admitted even if a blocklist covers them, and once the allow
+/// list has entries nothing else is admitted. The filter is empty, and
+/// admits everyone, by default.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpFilterConfig {
+    /// Addresses and ranges that are always refused
+    pub deny: Vec<IpRange>,
+    /// Addresses and ranges admitted despite the blocklists; when set,
+    /// the only ones admitted
+    pub allow: Vec<IpRange>,
+    /// Files listing one range per line, such as per-country lists;
+    /// `#` and `;` start comments
+    pub blocklists: Vec<PathBuf>,
+}
+
+impl IpFilterConfig {
+    pub fn is_empty(&self) -> bool {
+        self.deny.is_empty() && self.allow.is_empty() && self.blocklists.is_empty()
+    }
+}
+
+/// Blocked attempts counted by an [`IpFilter`]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IpFilterStats {
+    pub denied: u64,
+    pub not_allowed: u64,
+    pub blocklisted: u64,
+    /// Blocked attempts per listener
+    pub by_entry_point: HashMap<IpEntryPoint, u64>,
+    /// Ranges loaded from the blocklist files
+    pub blocklist_ranges: usize,
+    /// When the blocklist files were last loaded
+    pub loaded_at: Option<u64>,
+    pub last_blocked_at: Option<u64>,
+}
+
+impl IpFilterStats {
+    pub fn blocked_total(&self) -> u64 {
+        self.denied + self.not_allowed + self.blocklisted
+    }
+}
+
+/// Rules compiled from a config
+#[derive(Debug, Default)]
+struct IpFilterRules {
+    config: IpFilterConfig,
+    blocklist: Vec<IpRange>,
+    /// Modification times of the blocklist files when they were loaded
+    loaded_versions: Vec<Option<SystemTime>>,
+}
+
+/// Applies allow, deny and blocklist rules to incoming addresses
+#[derive(Debug)]
+pub struct IpFilter {
+    rules: Arc<RwLock<IpFilterRules>>,
+    stats: Arc<RwLock<IpFilterStats>>,
+}
+
+impl IpFilter {
+    /// Create a filter that admits everyone
+    pub fn new() -> Self {
+        Self {
+            rules: Arc::new(RwLock::new(IpFilterRules::default())),
+            stats: Arc::new(RwLock::new(IpFilterStats::default())),
+        }
+    }
+
+    /// Create a filter and load its blocklist files
+    pub fn with_config(config: IpFilterConfig) -> SecurityResult<Self> {
+        let filter = Self::new();
+        filter.update_config(config)?;
+        Ok(filter)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    /// Switch to a new config while running
+    ///
+    /// The blocklist files are loaded before anything changes, so a missing
+    /// file leaves the current rules in place.
+    pub fn update_config(&self, config: IpFilterConfig) -> SecurityResult<()> {
+        let mut blocklist = Vec::new();
+        let mut loaded_versions = Vec::with_capacity(config.blocklists.len());
+        for path in &config.blocklists {
+            loaded_versions.push(modified_at(path));
+            blocklist.extend(load_blocklist(path)?);
+        }
+
+        {
+            let mut stats = self.stats.write().unwrap();
+            stats.blocklist_ranges = blocklist.len();
+            stats.loaded_at = Some(Self::now());
+        }
+        *self.rules.write().unwrap() = IpFilterRules {
+            config,
+            blocklist,
+            loaded_versions,
+        };
+        Ok(())
+    }
+
+    /// Load the blocklist files again
+    pub fn reload(&self) -> SecurityResult<()> {
+        self.update_config(self.get_config())
+    }
+
+    /// Load the blocklist files again if any of them changed on disk
+    pub fn reload_if_changed(&self) -> SecurityResult<bool> {
+        let changed = {
+            let rules = self.rules.read().unwrap();
+            rules
+                .config
+                .blocklists
+                .iter()
+                .zip(&rules.loaded_versions)
+                .any(|(path, loaded)| modified_at(path) != *loaded)
+        };
+        if changed {
+            self.reload()?;
+        }
+        Ok(changed)
+    }
+
+    /// Get the current config
+    pub fn get_config(&self) -> IpFilterConfig {
+        self.rules.read().unwrap().config.clone()
+    }
+
+    /// Check an address against the rules without counting it
+    pub fn evaluate(&self, address: IpAddr) -> Option<IpBlockReason> {
+        let rules = self.rules.read().unwrap();
+        let config = &rules.config;
+
+        if config.deny.iter().any(|range| range.contains(address)) {
+            return Some(IpBlockReason::Denied);
+        }
+        if config.allow.iter().any(|range| range.contains(address)) {
+            return None;
+        }
+        if rules.blocklist.iter().any(|range| range.contains(address)) {
+            return Some(IpBlockReason::Blocklisted);
+        }
+        if !config.allow.is_empty() {
+            return Some(IpBlockReason::NotAllowed);
+        }
+        None
+    }
+
+    /// Refuse an address arriving on `entry_point` if the rules block it,
+    /// counting the attempt
+    pub fn check(&self, address: IpAddr, entry_point: IpEntryPoint) -> SecurityResult<()> {
+        let Some(reason) = self.evaluate(address) else {
+            return Ok(());
+        };
+
+        {
+            let mut stats = self.stats.write().unwrap();
+            match reason {
+                IpBlockReason::Denied => stats.denied += 1,
+                IpBlockReason::NotAllowed => stats.not_allowed += 1,
+                IpBlockReason::Blocklisted => stats.blocklisted += 1,
+            }
+            *stats.by_entry_point.entry(entry_point).or_insert(0) += 1;
+            stats.last_blocked_at = Some(Self::now());
+        }
+
+        Err(PolicyError::AddressBlocked(format!("{} is {}", address, reason)).into())
+    }
+
+    /// Get the blocked attempt counters
+    pub fn stats(&self) -> IpFilterStats {
+        self.stats.read().unwrap().clone()
+    }
+}
+
+impl Default for IpFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn modified_at(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Read the ranges in a blocklist file, skipping lines that are not ranges
+fn load_blocklist(path: &PathBuf) -> SecurityResult<Vec<IpRange>> {
+    let contents = std::fs::read_to_string(path)?;
+
+    let mut ranges = Vec::new();
+    let mut skipped = 0;
+    for line in contents.lines() {
+        let entry = line.split(['#', ';']).next().unwrap_or_default().trim();
+        if entry.is_empty() {
+            continue;
+        }
+        match entry.parse() {
+            Ok(range) => ranges.push(range),
+            Err(_) => skipped += 1,
+        }
+    }
+
+    if skipped > 0 {
+        log::warn!("Skipped {} unreadable lines in blocklist {}", skipped, path.display());
+    }
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ip_range_parsing_and_matching() {
+        let range: IpRange = "203.0.113.77/24".parse().unwrap();
+        assert_eq!(range.to_string(), "203.0.113.0/24");
+        assert!(range.contains(ip("203.0.113.1")));
+        assert!(range.contains(ip("::ffff:203.0.113.9")));
+        assert!(!range.contains(ip("203.0.114.1")));
+
+        let v6: IpRange = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(ip("2001:db8:1::5")));
+        assert!(!v6.contains(ip("2001:db9::1")));
+        assert!(!v6.contains(ip("203.0.113.1")));
+
+        let single: IpRange = "198.51.100.4".parse().unwrap();
+        assert_eq!(single.prefix_len(), 32);
+        assert_eq!(single.to_string(), "198.51.100.4");
+        assert!(single.contains(ip("198.51.100.4")));
+        assert!(!single.contains(ip("198.51.100.5")));
+
+        let everything: IpRange = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains(ip("8.8.8.8")));
+
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("10.0.0/8".parse::<IpRange>().is_err());
+        assert!("10.0.0.0/x".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn test_rules_and_counters() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let blocklist = dir.path().join("xx.zone");
+        std::fs::write(&blocklist, "# country list\n192.0.2.0/24 ; SBL1\n\nnot a range\n").unwrap();
+
+        let config: IpFilterConfig = serde_json::from_value(serde_json::json!({
+            "deny": ["198.51.100.0/24"],
+            "blocklists": [blocklist],
+        }))
+        .unwrap();
+        let filter = IpFilter::with_config(config).unwrap();
+
+        assert!(filter.check(ip("203.0.113.1"), IpEntryPoint::Relay).is_ok());
+        assert!(filter.check(ip("198.51.100.9"), IpEntryPoint::Relay).is_err());
+        assert!(filter.check(ip("192.0.2.1"), IpEntryPoint::BrowserServer).is_err());
+        assert_eq!(filter.evaluate(ip("192.0.2.1")), Some(IpBlockReason::Blocklisted));
+
+        // Allow entries punch holes in blocklists and shut everything else out
+        let mut config = filter.get_config();
+        config.allow = vec!["192.0.2.7".parse().unwrap()];
+        filter.update_config(config).unwrap();
+        assert_eq!(filter.evaluate(ip("192.0.2.7")), None);
+        assert_eq!(filter.evaluate(ip("203.0.113.1")), Some(IpBlockReason::NotAllowed));
+
+        let stats = filter.stats();
+        assert_eq!(stats.denied, 1);
+        assert_eq!(stats.blocklisted, 1);
+        assert_eq!(stats.blocked_total(), 2);
+        assert_eq!(stats.by_entry_point.get(&IpEntryPoint::Relay), Some(&1));
+        assert_eq!(stats.blocklist_ranges, 1);
+    }
+
+    #[test]
+    fn test_missing_blocklist_keeps_current_rules() {
+        let filter = IpFilter::new();
+        filter.update_config(IpFilterConfig {
+            deny: vec!["198.51.100.0/24".parse().unwrap()],
+            ..IpFilterConfig::default()
+        }).unwrap();
+
+        let result = filter.update_config(IpFilterConfig {
+            blocklists: vec![PathBuf::from("/nonexistent/kizuna-blocklist")],
+            ..IpFilterConfig::default()
+        });
+        assert!(result.is_err());
+        assert_eq!(filter.evaluate(ip("198.51.100.1")), Some(IpBlockReason::Denied));
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let blocklist = dir.path().join("list.txt");
+        std::fs::write(&blocklist, "192.0.2.0/24\n").unwrap();
+
+        let filter = IpFilter::with_config(IpFilterConfig {
+            blocklists: vec![blocklist.clone()],
+            ..IpFilterConfig::default()
+        }).unwrap();
+        assert!(!filter.reload_if_changed().unwrap());
+
+        std::fs::write(&blocklist, "192.0.2.0/24\n203.0.113.0/24\n").unwrap();
+        let file = std::fs::File::options().write(true).open(&blocklist).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(60)).unwrap();
+
+        assert!(filter.reload_if_changed().unwrap());
+        assert_eq!(filter.evaluate(ip("203.0.113.1")), Some(IpBlockReason::Blocklisted));
+    }
+}
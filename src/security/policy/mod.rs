@@ -4,17 +4,29 @@ mod rate_limiter;
 mod audit;
 mod network_policy;
 mod attack_detector;
+mod hardening;
+mod visibility;
+mod ip_filter;
 
 pub use engine::PolicyEngineImpl;
 pub use private_mode::{PrivateModeController, InviteCode};
-pub use rate_limiter::RateLimiter;
-pub use audit::{SecurityAuditor, AuditLog};
+pub use rate_limiter::{
+    OperationLimits, OperationRateLimiter, RateLimitConfig, RateLimitedOperation, RateLimiter, TokenBucketConfig,
+};
+pub use audit::{SecurityAuditor, AuditLog, AuditLogEntry, Severity};
 pub use network_policy::{NetworkPolicyEnforcer, NetworkMode};
-pub use attack_detector::{AttackDetector, SuspiciousPattern, AttackDetectorConfig};
+pub use attack_detector::{AttackAlert, AttackDetector, SuspiciousPattern, AttackDetectorConfig};
+pub use hardening::{
+    run_hardening_checks, CommandExecutionPosture, FindingSeverity, HardeningFinding, HardeningInput,
+    HardeningInputs, HardeningReport,
+};
+pub use visibility::{AnnounceState, AnnounceWindow, DiscoveryVisibility};
+pub use ip_filter::{IpBlockReason, IpEntryPoint, IpFilter, IpFilterConfig, IpFilterStats, IpRange};
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use crate::security::clock::ClockSkewConfig;
 use crate::security::error::SecurityResult;
 use crate::security::identity::PeerId;
 
@@ -27,6 +39,30 @@ pub struct SecurityPolicy {
     pub auto_accept_trusted: bool,
     pub session_timeout: Duration,
     pub key_rotation_interval: Duration,
+    /// Stealth mode and scheduled announce windows
+    #[serde(default)]
+    pub discovery_visibility: DiscoveryVisibility,
+    /// Token buckets for pairing, connections, commands and clipboard pushes
+    #[serde(default)]
+    pub rate_limits: OperationLimits,
+    /// Address allow, deny and blocklists for relay and browser server
+    /// connections from the internet
+    #[serde(default)]
+    pub ip_filter: IpFilterConfig,
+    /// Clock skew allowed when checking timestamps from peers
+    #[serde(default)]
+    pub clock_skew: ClockSkewConfig,
+}
+
+impl SecurityPolicy {
+    /// Discovery visibility with private mode taken into account
+    pub fn effective_visibility(&self) -> DiscoveryVisibility {
+        let mut visibility = self.discovery_visibility.clone();
+        if self.private_mode && visibility.stealth_in_private_mode {
+            visibility.stealth = true;
+        }
+        visibility
+    }
 }
 
 impl Default for SecurityPolicy {
@@ -38,10 +74,25 @@ impl Default for SecurityPolicy {
             auto_accept_trusted: true,
             session_timeout: Duration::from_secs(3600), // 1 hour
             key_rotation_interval: Duration::from_secs(300), // 5 minutes
+            discovery_visibility: DiscoveryVisibility::default(),
+            rate_limits: OperationLimits::default(),
+            ip_filter: IpFilterConfig::default(),
+            clock_skew: ClockSkewConfig::default(),
         }
     }
 }
 
+/// Counters of connections and peers refused by the policy engine
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SecurityStats {
+    /// Attempts refused by the address filter
+    pub ip_filter: IpFilterStats,
+    /// Peers blocked by attack detection right now
+    pub blocked_peers: usize,
+    /// Addresses banned by attack detection right now
+    pub banned_addresses: usize,
+}
+
 /// Connection type for policy enforcement
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ConnectionType {
@@ -88,6 +139,9 @@ pub enum SecurityEventType {
     RateLimitExceeded,
     SuspiciousActivity,
     PolicyViolation,
+    ClipboardSyncSent,
+    ClipboardSyncReceived,
+    ClipboardSyncFailed,
 }
 
 /// Security policy engine trait
@@ -112,6 +166,16 @@ pub trait PolicyEngine: Send + Sync {
     /// Check rate limiting for a peer
     async fn check_rate_limit(&self, peer_id: &PeerId) -> SecurityResult<bool>;
     
+    /// Spend one token of a peer's allowance for an operation
+    ///
+    /// Refusals are audited and fed to attack detection, which may block
+    /// the peer.
+    async fn check_operation_rate(&self, peer_id: &PeerId, operation: RateLimitedOperation) -> SecurityResult<()>;
+    
+    /// Screen an inbound connection from `address` to our `local_port`
+    /// before the peer identifies itself
+    async fn screen_connection_probe(&self, address: std::net::IpAddr, local_port: u16) -> SecurityResult<()>;
+    
     /// Enable private mode
     async fn enable_private_mode(&self) -> SecurityResult<()>;
     
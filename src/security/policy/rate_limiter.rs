@@ -1,6 +1,8 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::{Arc, RwLock};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
 use crate::security::error::{SecurityResult, PolicyError};
 use crate::security::identity::PeerId;
 
@@ -247,6 +249,211 @@ impl Default for RateLimiter {
     }
 }
 
+/// Peer operation with its own rate limit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitedOperation {
+    /// Pairing code and rolling code attempts
+    Pairing,
+    /// Connection attempts
+    Connection,
+    /// Command, script, media, handoff and other remote requests
+    CommandRequest,
+    /// Clipboard content pushed by a peer
+    ClipboardPush,
+}
+
+impl RateLimitedOperation {
+    pub const ALL: [RateLimitedOperation; 4] = [
+        RateLimitedOperation::Pairing,
+        RateLimitedOperation::Connection,
+        RateLimitedOperation::CommandRequest,
+        RateLimitedOperation::ClipboardPush,
+    ];
+}
+
+impl fmt::Display for RateLimitedOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RateLimitedOperation::Pairing => "pairing",
+            RateLimitedOperation::Connection => "connection",
+            RateLimitedOperation::CommandRequest => "command request",
+            RateLimitedOperation::ClipboardPush => "clipboard push",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Size and refill rate of a token bucket
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenBucketConfig {
+    /// Operations allowed in a burst
+    pub capacity: u32,
+    /// Tokens added back per minute
+    pub refill_per_minute: u32,
+}
+
+impl TokenBucketConfig {
+    pub fn new(capacity: u32, refill_per_minute: u32) -> Self {
+        Self { capacity, refill_per_minute }
+    }
+}
+
+/// Token bucket sizes for each rate-limited operation
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OperationLimits {
+    pub pairing: TokenBucketConfig,
+    pub connection: TokenBucketConfig,
+    pub command_request: TokenBucketConfig,
+    pub clipboard_push: TokenBucketConfig,
+}
+
+impl OperationLimits {
+    /// Bucket for one operation
+    pub fn for_operation(&self, operation: RateLimitedOperation) -> TokenBucketConfig {
+        match operation {
+            RateLimitedOperation::Pairing => self.pairing,
+            RateLimitedOperation::Connection => self.connection,
+            RateLimitedOperation::CommandRequest => self.command_request,
+            RateLimitedOperation::ClipboardPush => self.clipboard_push,
+        }
+    }
+}
+
+impl Default for OperationLimits {
+    fn default() -> Self {
+        Self {
+            // A six-digit code must not be guessable by brute force
+            pairing: TokenBucketConfig::new(3, 1),
+            connection: TokenBucketConfig::new(10, 30),
+            command_request: TokenBucketConfig::new(10, 30),
+            clipboard_push: TokenBucketConfig::new(30, 120),
+        }
+    }
+}
+
+/// Tokens left for one peer and operation
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn full(config: TokenBucketConfig) -> Self {
+        Self {
+            tokens: f64::from(config.capacity),
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, config: TokenBucketConfig, now: Instant) {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        let added = elapsed * f64::from(config.refill_per_minute) / 60.0;
+        self.tokens = (self.tokens + added).min(f64::from(config.capacity));
+        self.refilled_at = now;
+    }
+
+    fn is_full(&self, config: TokenBucketConfig) -> bool {
+        self.tokens >= f64::from(config.capacity)
+    }
+}
+
+/// Token bucket rate limiter per (peer, operation)
+///
+/// Unlike [`RateLimiter`], which blocks a peer outright after a burst of
+/// connection attempts, this only refuses the operation that ran out of
+/// tokens; blocking is left to the attack detector.
+pub struct OperationRateLimiter {
+    /// Bucket sizes
+    limits: Arc<RwLock<OperationLimits>>,
+    /// Buckets per peer and operation
+    buckets: Arc<RwLock<HashMap<(PeerId, RateLimitedOperation), TokenBucket>>>,
+}
+
+impl OperationRateLimiter {
+    /// Create a new limiter with default limits
+    pub fn new() -> Self {
+        Self::with_limits(OperationLimits::default())
+    }
+
+    /// Create a new limiter with custom limits
+    pub fn with_limits(limits: OperationLimits) -> Self {
+        Self {
+            limits: Arc::new(RwLock::new(limits)),
+            buckets: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Spend a token for `operation`, failing if the peer has none left
+    pub fn check(&self, peer_id: &PeerId, operation: RateLimitedOperation) -> SecurityResult<()> {
+        let config = self.limits.read().unwrap().for_operation(operation);
+        let mut buckets = self.buckets.write().unwrap();
+        let bucket = buckets
+            .entry((peer_id.clone(), operation))
+            .or_insert_with(|| TokenBucket::full(config));
+
+        bucket.refill(config, Instant::now());
+        if bucket.tokens < 1.0 {
+            return Err(PolicyError::RateLimitExceeded.into());
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+
+    /// Whole tokens a peer has left for `operation`
+    pub fn remaining(&self, peer_id: &PeerId, operation: RateLimitedOperation) -> u32 {
+        let config = self.limits.read().unwrap().for_operation(operation);
+        let buckets = self.buckets.read().unwrap();
+        match buckets.get(&(peer_id.clone(), operation)) {
+            Some(bucket) => {
+                let mut bucket = bucket.clone();
+                bucket.refill(config, Instant::now());
+                bucket.tokens as u32
+            }
+            None => config.capacity,
+        }
+    }
+
+    /// Give a peer full buckets again
+    pub fn reset_peer(&self, peer_id: &PeerId) -> SecurityResult<()> {
+        let mut buckets = self.buckets.write().unwrap();
+        buckets.retain(|(peer, _), _| peer != peer_id);
+        Ok(())
+    }
+
+    /// Drop buckets that have refilled completely
+    pub fn cleanup(&self) -> SecurityResult<()> {
+        let limits = self.limits.read().unwrap();
+        let now = Instant::now();
+        let mut buckets = self.buckets.write().unwrap();
+        buckets.retain(|(_, operation), bucket| {
+            let config = limits.for_operation(*operation);
+            bucket.refill(config, now);
+            !bucket.is_full(config)
+        });
+        Ok(())
+    }
+
+    /// Update bucket sizes; existing buckets keep their tokens
+    pub fn update_limits(&self, limits: OperationLimits) -> SecurityResult<()> {
+        *self.limits.write().unwrap() = limits;
+        Ok(())
+    }
+
+    /// Get current bucket sizes
+    pub fn get_limits(&self) -> OperationLimits {
+        self.limits.read().unwrap().clone()
+    }
+}
+
+impl Default for OperationRateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -347,4 +554,46 @@ mod tests {
         limiter.reset_peer(&peer_id).unwrap();
         assert_eq!(limiter.get_attempt_count(&peer_id), 0);
     }
+    
+    #[test]
+    fn test_operation_buckets_are_separate() {
+        let limiter = OperationRateLimiter::new();
+        let peer_id = PeerId::from_string("test_peer").unwrap();
+        let other_peer = PeerId::from_string("other_peer").unwrap();
+        
+        // Default pairing bucket holds 3 attempts
+        for _ in 0..3 {
+            assert!(limiter.check(&peer_id, RateLimitedOperation::Pairing).is_ok());
+        }
+        assert!(limiter.check(&peer_id, RateLimitedOperation::Pairing).is_err());
+        assert_eq!(limiter.remaining(&peer_id, RateLimitedOperation::Pairing), 0);
+        
+        // Other operations and other peers are unaffected
+        assert!(limiter.check(&peer_id, RateLimitedOperation::ClipboardPush).is_ok());
+        assert!(limiter.check(&other_peer, RateLimitedOperation::Pairing).is_ok());
+        
+        limiter.reset_peer(&peer_id).unwrap();
+        assert_eq!(limiter.remaining(&peer_id, RateLimitedOperation::Pairing), 3);
+    }
+    
+    #[test]
+    fn test_operation_bucket_refills() {
+        let limits = OperationLimits {
+            command_request: TokenBucketConfig::new(1, 6000), // 100 per second
+            ..OperationLimits::default()
+        };
+        let limiter = OperationRateLimiter::with_limits(limits);
+        let peer_id = PeerId::from_string("test_peer").unwrap();
+        
+        assert!(limiter.check(&peer_id, RateLimitedOperation::CommandRequest).is_ok());
+        assert!(limiter.check(&peer_id, RateLimitedOperation::CommandRequest).is_err());
+        
+        thread::sleep(Duration::from_millis(50));
+        assert!(limiter.check(&peer_id, RateLimitedOperation::CommandRequest).is_ok());
+        
+        // Full buckets are dropped on cleanup
+        thread::sleep(Duration::from_millis(50));
+        limiter.cleanup().unwrap();
+        assert!(limiter.buckets.read().unwrap().is_empty());
+    }
 }
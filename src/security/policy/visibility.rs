@@ -0,0 +1,232 @@
+use std::fmt;
+use std::str::FromStr;
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// A daily time range during which this device announces itself
+///
+/// Written as `HH:MM-HH:MM`, optionally prefixed with a comma-separated
+/// list of days, e.g. `mon,tue,wed,thu,fri 09:00-17:30`. A window whose end
+/// is before its start runs past midnight into the next day.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AnnounceWindow {
+    /// Days the window opens on; empty means every day
+    pub days: Vec<Weekday>,
+    /// Local time the window opens
+    pub start: NaiveTime,
+    /// Local time the window closes
+    pub end: NaiveTime,
+}
+
+impl AnnounceWindow {
+    /// Create a window open every day between `start` and `end`
+    pub fn daily(start: NaiveTime, end: NaiveTime) -> Self {
+        Self { days: Vec::new(), start, end }
+    }
+
+    fn opens_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    fn is_overnight(&self) -> bool {
+        self.end <= self.start
+    }
+
+    /// Check whether the window is open at a local time
+    pub fn contains(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let today = now.weekday();
+        if self.is_overnight() {
+            // Open from `start` today, or until `end` if it opened yesterday
+            (self.opens_on(today) && time >= self.start) || (self.opens_on(today.pred()) && time < self.end)
+        } else {
+            self.opens_on(today) && time >= self.start && time < self.end
+        }
+    }
+
+    /// Next time after `now` that the window opens
+    pub fn next_open(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        (0..=7)
+            .map(|offset| now.date() + ChronoDuration::days(offset))
+            .filter(|date| self.opens_on(date.weekday()))
+            .map(|date| date.and_time(self.start))
+            .find(|open| *open > now)
+    }
+}
+
+impl FromStr for AnnounceWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (days, range) = match s.rsplit_once(char::is_whitespace) {
+            Some((days, range)) => (Some(days.trim()), range),
+            None => (None, s),
+        };
+
+        let days = match days {
+            Some(days) => days
+                .split(',')
+                .map(|day| {
+                    day.trim()
+                        .parse::<Weekday>()
+                        .map_err(|_| format!("Unknown day '{}' in announce window", day.trim()))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| format!("Announce window '{}' must look like HH:MM-HH:MM", s))?;
+        let parse_time = |value: &str| {
+            NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .map_err(|_| format!("Invalid time '{}' in announce window", value.trim()))
+        };
+
+        Ok(Self {
+            days,
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        })
+    }
+}
+
+impl TryFrom<String> for AnnounceWindow {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<AnnounceWindow> for String {
+    fn from(window: AnnounceWindow) -> Self {
+        window.to_string()
+    }
+}
+
+impl fmt::Display for AnnounceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.days.is_empty() {
+            let days: Vec<String> = self.days.iter().map(|day| day.to_string().to_lowercase()).collect();
+            write!(f, "{} ", days.join(","))?;
+        }
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+/// Controls whether this device announces itself during discovery
+///
+/// Browsing for other peers is never affected; only outgoing announcements
+/// are suppressed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryVisibility {
+    /// Never announce, only browse
+    pub stealth: bool,
+    /// Switch to stealth whenever private mode is enabled
+    pub stealth_in_private_mode: bool,
+    /// Only announce inside these windows; empty means always
+    pub announce_windows: Vec<AnnounceWindow>,
+}
+
+impl DiscoveryVisibility {
+    /// Announcement state at a local time
+    pub fn state_at(&self, now: NaiveDateTime) -> AnnounceState {
+        if self.stealth {
+            return AnnounceState::Stealth;
+        }
+
+        if self.announce_windows.is_empty() || self.announce_windows.iter().any(|w| w.contains(now)) {
+            return AnnounceState::Visible;
+        }
+
+        AnnounceState::OutsideWindow {
+            next_window: self.announce_windows.iter().filter_map(|w| w.next_open(now)).min(),
+        }
+    }
+
+    /// Announcement state right now
+    pub fn state(&self) -> AnnounceState {
+        self.state_at(Local::now().naive_local())
+    }
+}
+
+/// Whether announcements are currently allowed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnnounceState {
+    /// Announcing normally
+    Visible,
+    /// Stealth mode: browsing only
+    Stealth,
+    /// Outside every configured announce window
+    OutsideWindow { next_window: Option<NaiveDateTime> },
+}
+
+impl AnnounceState {
+    /// Check if this device should announce itself
+    pub fn allows_announce(&self) -> bool {
+        matches!(self, AnnounceState::Visible)
+    }
+}
+
+impl fmt::Display for AnnounceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnnounceState::Visible => write!(f, "announcing"),
+            AnnounceState::Stealth => write!(f, "stealth mode, browsing only"),
+            AnnounceState::OutsideWindow { next_window: Some(next) } => {
+                write!(f, "outside announce window, next opens {}", next.format("%a %H:%M"))
+            }
+            AnnounceState::OutsideWindow { next_window: None } => write!(f, "outside announce window"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2024-01-01 was a Monday
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_window() {
+        let window: AnnounceWindow = "mon,fri 09:00-17:30".parse().unwrap();
+        assert_eq!(window.days, vec![Weekday::Mon, Weekday::Fri]);
+        assert_eq!(window.to_string(), "mon,fri 09:00-17:30");
+
+        assert!("09:00".parse::<AnnounceWindow>().is_err());
+        assert!("someday 09:00-10:00".parse::<AnnounceWindow>().is_err());
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        let window: AnnounceWindow = "fri 22:00-06:00".parse().unwrap();
+        assert!(window.contains(at(5, 23, 0)));
+        assert!(window.contains(at(6, 5, 59)));
+        assert!(!window.contains(at(6, 6, 0)));
+        assert!(!window.contains(at(4, 23, 0)));
+    }
+
+    #[test]
+    fn test_visibility_state() {
+        let mut visibility = DiscoveryVisibility::default();
+        assert_eq!(visibility.state_at(at(1, 3, 0)), AnnounceState::Visible);
+
+        visibility.announce_windows.push("09:00-17:00".parse().unwrap());
+        assert!(visibility.state_at(at(1, 10, 0)).allows_announce());
+        assert_eq!(
+            visibility.state_at(at(1, 18, 0)),
+            AnnounceState::OutsideWindow { next_window: Some(at(2, 9, 0)) }
+        );
+
+        visibility.stealth = true;
+        assert_eq!(visibility.state_at(at(1, 10, 0)), AnnounceState::Stealth);
+    }
+}
@@ -0,0 +1,411 @@
+//! Encryption at rest for persistent state
+//!
+//! Clipboard history, the trust database, transfer journals and config files
+//! seal their contents with a [`StorageKey`] before writing to disk. Stores
+//! written before encryption was enabled are still readable: plaintext is
+//! recognized by the missing header and rewritten sealed on first access.
+//!
+//! That migration happens once. A migrated store is marked sealed, with a
+//! marker file next to a file store or a row in a SQLite store, and from then
+//! on plaintext in it is refused rather than trusted, since it can only have
+//! been written by someone without the key.
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng as AeadOsRng, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::security::error::{EncryptionError, IdentityError, SecurityError, SecurityResult};
+use crate::security::identity::DeviceIdentity;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header identifying sealed data, followed by the nonce and ciphertext
+const SEALED_MAGIC: &[u8] = b"KZS1";
+
+/// Prefix of sealed values stored in text columns
+const SEALED_TEXT_PREFIX: &str = "kzs1:";
+
+/// Appended to a file's name for the marker saying it has been sealed
+const SEALED_MARKER_SUFFIX: &str = ".sealed";
+
+/// Table listing the sealed stores in a SQLite database
+const SEALED_STORES_TABLE: &str = "sealed_stores";
+
+/// Domain separation for deriving the storage key from the device identity
+const STORAGE_KEY_CONTEXT: &[u8] = b"kizuna-storage-key-v1";
+
+const NONCE_LEN: usize = 12;
+
+/// Key used to seal persistent state
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct StorageKey {
+    key: [u8; 32],
+}
+
+impl StorageKey {
+    /// Use raw key bytes
+    pub fn from_bytes(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    /// Derive the key from the device identity
+    ///
+    /// The key survives reinstalls as long as the identity does, and needs
+    /// nothing else stored.
+    pub fn from_identity(identity: &DeviceIdentity) -> Self {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(STORAGE_KEY_CONTEXT)
+            .expect("HMAC accepts keys of any length");
+        let secret = Zeroizing::new(identity.private_key().to_bytes());
+        mac.update(secret.as_ref());
+
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&mac.finalize().into_bytes());
+        Self { key }
+    }
+
+    /// Load a random key from the OS keyring, creating it on first use
+    pub fn from_keyring(service_name: &str, username: &str) -> SecurityResult<Self> {
+        let entry = keyring::Entry::new(service_name, username)
+            .map_err(|e| IdentityError::KeystoreError(format!("Failed to create keyring entry: {}", e)))?;
+
+        if let Ok(stored) = entry.get_password() {
+            let stored = Zeroizing::new(stored);
+            let bytes = Zeroizing::new(
+                hex::decode(stored.as_str())
+                    .map_err(|e| IdentityError::Corrupted(format!("Invalid storage key: {}", e)))?,
+            );
+            let key: [u8; 32] = bytes
+                .as_slice()
+                .try_into()
+                .map_err(|_| IdentityError::Corrupted("Invalid storage key length".to_string()))?;
+            return Ok(Self { key });
+        }
+
+        let mut key = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut key);
+        let encoded = Zeroizing::new(hex::encode(key));
+        entry.set_password(&encoded)
+            .map_err(|e| IdentityError::SaveFailed(format!("Failed to save storage key: {}", e)))?;
+
+        Ok(Self { key })
+    }
+}
+
+/// Where the storage key comes from
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum StorageKeySource {
+    /// Derived from the device identity
+    #[default]
+    Identity,
+    /// Random key held in the OS keyring under this service name
+    Keyring { service_name: String },
+}
+
+/// Seals and opens persistent state with a [`StorageKey`]
+///
+/// Every value is bound to a context label such as the store name, so sealed
+/// data copied from one store into another fails to open. Cheap to clone.
+#[derive(Clone)]
+pub struct EncryptedStorage {
+    key: Arc<StorageKey>,
+}
+
+// The key never appears in logs or debug output
+impl std::fmt::Debug for EncryptedStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedStorage").finish_non_exhaustive()
+    }
+}
+
+impl EncryptedStorage {
+    pub fn new(key: StorageKey) -> Self {
+        Self { key: Arc::new(key) }
+    }
+
+    /// Whether data was produced by [`EncryptedStorage::seal`]
+    pub fn is_sealed(data: &[u8]) -> bool {
+        data.starts_with(SEALED_MAGIC)
+    }
+
+    /// Whether a text value was produced by [`EncryptedStorage::seal_text`]
+    pub fn is_sealed_text(value: &str) -> bool {
+        value.starts_with(SEALED_TEXT_PREFIX)
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new_from_slice(&self.key.key).expect("storage key is 32 bytes")
+    }
+
+    /// Encrypt data for storage
+    pub fn seal(&self, plaintext: &[u8], context: &str) -> SecurityResult<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut AeadOsRng);
+        let ciphertext = self.cipher()
+            .encrypt(&nonce, Payload { msg: plaintext, aad: context.as_bytes() })
+            .map_err(|e| EncryptionError::EncryptionFailed(format!("Sealing {} failed: {}", context, e)))?;
+
+        let mut sealed = Vec::with_capacity(SEALED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(SEALED_MAGIC);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt sealed data; plaintext is refused
+    pub fn open(&self, data: &[u8], context: &str) -> SecurityResult<Vec<u8>> {
+        if !Self::is_sealed(data) {
+            return Err(EncryptionError::DecryptionFailed(format!("{} is not encrypted", context)).into());
+        }
+
+        let body = &data[SEALED_MAGIC.len()..];
+        if body.len() < NONCE_LEN {
+            return Err(EncryptionError::DecryptionFailed(format!("Sealed {} is truncated", context)).into());
+        }
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().expect("split at the nonce length");
+        self.cipher()
+            .decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad: context.as_bytes() })
+            .map_err(|_| EncryptionError::AuthenticationFailed.into())
+    }
+
+    /// Decrypt sealed data, passing plaintext from before encryption through unchanged
+    ///
+    /// Only for a store that has not been marked sealed yet, while migrating it.
+    pub fn open_unmigrated(&self, data: &[u8], context: &str) -> SecurityResult<Vec<u8>> {
+        if !Self::is_sealed(data) {
+            return Ok(data.to_vec());
+        }
+        self.open(data, context)
+    }
+
+    /// Encrypt a value for a text column
+    pub fn seal_text(&self, plaintext: &str, context: &str) -> SecurityResult<String> {
+        let sealed = self.seal(plaintext.as_bytes(), context)?;
+        Ok(format!("{}{}", SEALED_TEXT_PREFIX, hex::encode(&sealed[SEALED_MAGIC.len()..])))
+    }
+
+    /// Decrypt a text column value; plaintext is refused
+    pub fn open_text(&self, value: &str, context: &str) -> SecurityResult<String> {
+        let Some(encoded) = value.strip_prefix(SEALED_TEXT_PREFIX) else {
+            return Err(EncryptionError::DecryptionFailed(format!("{} is not encrypted", context)).into());
+        };
+
+        let mut sealed = SEALED_MAGIC.to_vec();
+        sealed.extend(
+            hex::decode(encoded)
+                .map_err(|e| EncryptionError::DecryptionFailed(format!("Sealed {} is corrupt: {}", context, e)))?,
+        );
+        String::from_utf8(self.open(&sealed, context)?)
+            .map_err(|_| EncryptionError::DecryptionFailed(format!("Sealed {} is not UTF-8", context)).into())
+    }
+
+    /// Decrypt a text column value, passing plaintext through unchanged
+    ///
+    /// Only for a store that has not been marked sealed yet, while migrating it.
+    pub fn open_text_unmigrated(&self, value: &str, context: &str) -> SecurityResult<String> {
+        if !Self::is_sealed_text(value) {
+            return Ok(value.to_string());
+        }
+        self.open_text(value, context)
+    }
+
+    /// Marker file recording that the file store at `path` has been sealed
+    pub fn sealed_marker(path: &Path) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(SEALED_MARKER_SUFFIX);
+        path.with_file_name(name)
+    }
+
+    /// Whether the file store at `path` has been sealed, so plaintext in it
+    /// must be refused
+    pub async fn is_marked_sealed(path: &Path) -> bool {
+        tokio::fs::try_exists(Self::sealed_marker(path)).await.unwrap_or(false)
+    }
+
+    /// Record whether the file store at `path` is sealed
+    ///
+    /// A store written in plaintext again is unmarked, so enabling encryption
+    /// later migrates it once more.
+    pub async fn mark_sealed(path: &Path, sealed: bool) -> std::io::Result<()> {
+        let marker = Self::sealed_marker(path);
+        if sealed {
+            if !tokio::fs::try_exists(&marker).await? {
+                tokio::fs::write(&marker, b"").await?;
+            }
+            return Ok(());
+        }
+        match tokio::fs::remove_file(&marker).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `store` in a SQLite database has been sealed
+    pub fn is_db_sealed(conn: &rusqlite::Connection, store: &str) -> rusqlite::Result<bool> {
+        Self::create_sealed_stores(conn)?;
+        conn.query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE store = ?1)", SEALED_STORES_TABLE),
+            [store],
+            |row| row.get(0),
+        )
+    }
+
+    /// Record whether `store` in a SQLite database is sealed
+    pub fn mark_db_sealed(conn: &rusqlite::Connection, store: &str, sealed: bool) -> rusqlite::Result<()> {
+        Self::create_sealed_stores(conn)?;
+        let sql = if sealed {
+            format!("INSERT OR IGNORE INTO {} (store) VALUES (?1)", SEALED_STORES_TABLE)
+        } else {
+            format!("DELETE FROM {} WHERE store = ?1", SEALED_STORES_TABLE)
+        };
+        conn.execute(&sql, [store]).map(|_| ())
+    }
+
+    fn create_sealed_stores(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+        conn.execute(
+            &format!("CREATE TABLE IF NOT EXISTS {} (store TEXT PRIMARY KEY)", SEALED_STORES_TABLE),
+            [],
+        )
+        .map(|_| ())
+    }
+
+    /// Read a file, migrating it to sealed form if it is still plaintext
+    ///
+    /// Returns `None` if the file does not exist. Plaintext in a file that
+    /// was already sealed is refused.
+    pub async fn read_file(&self, path: &Path, context: &str) -> SecurityResult<Option<Vec<u8>>> {
+        let data = match tokio::fs::read(path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        if !Self::is_sealed(&data) {
+            if Self::is_marked_sealed(path).await {
+                return Err(EncryptionError::DecryptionFailed(format!(
+                    "{:?} was encrypted but now holds plaintext",
+                    path
+                ))
+                .into());
+            }
+            log::info!("Encrypting plaintext store {:?}", path);
+            self.write_file(path, &data, context).await?;
+            return Ok(Some(data));
+        }
+        self.open(&data, context).map(Some)
+    }
+
+    /// Seal data and atomically replace the file with it
+    pub async fn write_file(&self, path: &Path, data: &[u8], context: &str) -> SecurityResult<()> {
+        let sealed = self.seal(data, context)?;
+        crate::journal::atomic_write(path, &sealed).await?;
+        Self::mark_sealed(path, true).await.map_err(SecurityError::from)
+    }
+
+    /// Encrypt a plaintext file in place; returns whether anything was migrated
+    pub async fn migrate_file(&self, path: &Path, context: &str) -> SecurityResult<bool> {
+        let data = match tokio::fs::read(path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        if Self::is_sealed(&data) {
+            return Ok(false);
+        }
+        if Self::is_marked_sealed(path).await {
+            return Err(EncryptionError::DecryptionFailed(format!(
+                "{:?} was encrypted but now holds plaintext",
+                path
+            ))
+            .into());
+        }
+
+        self.write_file(path, &data, context).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn storage() -> EncryptedStorage {
+        EncryptedStorage::new(StorageKey::from_bytes([42; 32]))
+    }
+
+    #[test]
+    fn test_seal_round_trip_and_context_binding() {
+        let storage = storage();
+        let sealed = storage.seal(b"secret clipboard", "clipboard").unwrap();
+
+        assert!(EncryptedStorage::is_sealed(&sealed));
+        assert_eq!(storage.open(&sealed, "clipboard").unwrap(), b"secret clipboard");
+        assert!(storage.open(&sealed, "journal").is_err());
+
+        let other = EncryptedStorage::new(StorageKey::from_bytes([7; 32]));
+        assert!(other.open(&sealed, "clipboard").is_err());
+    }
+
+    #[test]
+    fn test_plaintext_only_passes_through_while_migrating() {
+        let storage = storage();
+        let sealed = storage.seal_text("Laptop", "trust").unwrap();
+
+        assert!(EncryptedStorage::is_sealed_text(&sealed));
+        assert_eq!(storage.open_text(&sealed, "trust").unwrap(), "Laptop");
+        assert_eq!(storage.open_text_unmigrated("Old laptop", "trust").unwrap(), "Old laptop");
+        assert!(storage.open_text("Old laptop", "trust").is_err());
+        assert!(storage.open(b"plain", "clipboard").is_err());
+        assert_eq!(storage.open_unmigrated(b"plain", "clipboard").unwrap(), b"plain");
+    }
+
+    #[test]
+    fn test_db_sealed_marker() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        assert!(!EncryptedStorage::is_db_sealed(&conn, "trust").unwrap());
+        EncryptedStorage::mark_db_sealed(&conn, "trust", true).unwrap();
+        EncryptedStorage::mark_db_sealed(&conn, "trust", true).unwrap();
+        assert!(EncryptedStorage::is_db_sealed(&conn, "trust").unwrap());
+        assert!(!EncryptedStorage::is_db_sealed(&conn, "clipboard").unwrap());
+        EncryptedStorage::mark_db_sealed(&conn, "trust", false).unwrap();
+        assert!(!EncryptedStorage::is_db_sealed(&conn, "trust").unwrap());
+    }
+
+    #[test]
+    fn test_identity_key_is_stable() {
+        let identity = DeviceIdentity::generate().unwrap();
+        let a = EncryptedStorage::new(StorageKey::from_identity(&identity));
+        let b = EncryptedStorage::new(StorageKey::from_identity(&identity));
+
+        let sealed = a.seal(b"config", "config").unwrap();
+        assert_eq!(b.open(&sealed, "config").unwrap(), b"config");
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_file_migrated_on_read() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, b"name = \"desk\"").unwrap();
+
+        let storage = storage();
+        let data = storage.read_file(&path, "config").await.unwrap().unwrap();
+        assert_eq!(data, b"name = \"desk\"");
+
+        let on_disk = std::fs::read(&path).unwrap();
+        assert!(EncryptedStorage::is_sealed(&on_disk));
+        assert_eq!(storage.read_file(&path, "config").await.unwrap().unwrap(), b"name = \"desk\"");
+        assert!(!storage.migrate_file(&path, "config").await.unwrap());
+
+        // Once sealed, plaintext written over the file is not trusted
+        std::fs::write(&path, b"name = \"evil\"").unwrap();
+        assert!(storage.read_file(&path, "config").await.is_err());
+        assert!(storage.migrate_file(&path, "config").await.is_err());
+    }
+}
@@ -3,27 +3,102 @@ use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use crate::security::error::{SecurityResult, TrustError};
 use crate::security::identity::PeerId;
+use crate::security::storage::EncryptedStorage;
 use super::{TrustEntry, TrustLevel, ServicePermissions};
+use super::revocation::RevocationRecord;
+
+/// Context labels binding sealed columns to this database
+const NICKNAME_CONTEXT: &str = "trust:nickname";
+const REVOCATION_CONTEXT: &str = "trust:revocation";
+
+/// Name the database is marked sealed under
+const TRUST_STORE: &str = "trust";
 
 /// Trust database for managing trusted peers
 pub struct TrustDatabase {
     conn: Arc<Mutex<Connection>>,
+    storage: Option<EncryptedStorage>,
 }
 
 impl TrustDatabase {
     /// Create a new trust database
     pub fn new(db_path: PathBuf) -> SecurityResult<Self> {
+        Self::with_storage(db_path, None)
+    }
+    
+    /// Create a trust database that encrypts nicknames and revocations at rest
+    ///
+    /// Plaintext values written before encryption was enabled are sealed on
+    /// the first encrypted open; plaintext found after that is refused.
+    pub fn with_storage(db_path: PathBuf, storage: Option<EncryptedStorage>) -> SecurityResult<Self> {
         let conn = Connection::open(db_path)
             .map_err(|e| TrustError::DatabaseError(format!("Failed to open database: {}", e)))?;
         
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
+            storage,
         };
         
         db.initialize_schema()?;
+        db.migrate_plaintext()?;
         Ok(db)
     }
     
+    /// Seal plaintext nicknames and revocation records, once
+    fn migrate_plaintext(&self) -> SecurityResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let marker_error = |e: rusqlite::Error| TrustError::DatabaseError(format!("Failed to access sealed marker: {}", e));
+        let Some(storage) = &self.storage else {
+            // New values are plaintext, so the next encrypted open migrates again
+            EncryptedStorage::mark_db_sealed(&conn, TRUST_STORE, false).map_err(marker_error)?;
+            return Ok(());
+        };
+        if EncryptedStorage::is_db_sealed(&conn, TRUST_STORE).map_err(marker_error)? {
+            return Ok(());
+        }
+        
+        for (table, key, column, context) in [
+            ("trust_entries", "peer_id", "nickname", NICKNAME_CONTEXT),
+            ("revocations", "revoked_peer", "record", REVOCATION_CONTEXT),
+        ] {
+            let rows: Vec<(String, String)> = {
+                let mut stmt = conn.prepare(&format!("SELECT {}, {} FROM {}", key, column, table))
+                    .map_err(|e| TrustError::DatabaseError(format!("Failed to prepare statement: {}", e)))?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                    .map_err(|e| TrustError::DatabaseError(format!("Failed to query {}: {}", table, e)))?;
+                rows.collect::<Result<_, _>>()
+                    .map_err(|e| TrustError::DatabaseError(format!("Failed to read {}: {}", table, e)))?
+            };
+            
+            for (id, value) in rows.into_iter().filter(|(_, value)| !EncryptedStorage::is_sealed_text(value)) {
+                conn.execute(
+                    &format!("UPDATE {} SET {} = ?1 WHERE {} = ?2", table, column, key),
+                    params![storage.seal_text(&value, context)?, id],
+                ).map_err(|e| TrustError::DatabaseError(format!("Failed to encrypt {}: {}", table, e)))?;
+            }
+        }
+        
+        EncryptedStorage::mark_db_sealed(&conn, TRUST_STORE, true).map_err(marker_error)?;
+        Ok(())
+    }
+    
+    fn seal(&self, value: &str, context: &str) -> SecurityResult<String> {
+        match &self.storage {
+            Some(storage) => storage.seal_text(value, context),
+            None => Ok(value.to_string()),
+        }
+    }
+    
+    fn open(&self, value: String, context: &str) -> SecurityResult<String> {
+        match &self.storage {
+            Some(storage) => storage.open_text(&value, context),
+            None if EncryptedStorage::is_sealed_text(&value) => Err(TrustError::DatabaseError(
+                "Trust database is encrypted but no storage key was given".to_string(),
+            ).into()),
+            None => Ok(value),
+        }
+    }
+    
     /// Initialize database schema
     fn initialize_schema(&self) -> SecurityResult<()> {
         let conn = self.conn.lock().unwrap();
@@ -43,6 +118,26 @@ impl TrustDatabase {
             [],
         ).map_err(|e| TrustError::DatabaseError(format!("Failed to create table: {}", e)))?;
         
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS device_group (
+                peer_id TEXT PRIMARY KEY
+            );
+            CREATE TABLE IF NOT EXISTS revocations (
+                revoked_peer TEXT PRIMARY KEY,
+                issuer TEXT NOT NULL,
+                issued_at INTEGER NOT NULL,
+                record TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS revocation_overrides (
+                peer_id TEXT PRIMARY KEY
+            );
+            CREATE TABLE IF NOT EXISTS revocations_sent (
+                peer_id TEXT NOT NULL,
+                revoked_peer TEXT NOT NULL,
+                PRIMARY KEY (peer_id, revoked_peer)
+            );"
+        ).map_err(|e| TrustError::DatabaseError(format!("Failed to create revocation tables: {}", e)))?;
+        
         Ok(())
     }
     
@@ -51,6 +146,7 @@ impl TrustDatabase {
         let conn = self.conn.lock().unwrap();
         
         let peer_id_str = entry.peer_id.to_string();
+        let nickname = self.seal(&entry.nickname, NICKNAME_CONTEXT)?;
         let trust_level_str = match entry.trust_level {
             TrustLevel::Verified => "Verified",
             TrustLevel::Trusted => "Trusted",
@@ -64,7 +160,7 @@ impl TrustDatabase {
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
             params![
                 peer_id_str,
-                entry.nickname,
+                nickname,
                 entry.first_seen,
                 entry.last_seen,
                 trust_level_str,
@@ -127,7 +223,10 @@ impl TrustDatabase {
         ).optional()
         .map_err(|e| TrustError::DatabaseError(format!("Failed to get peer: {}", e)))?;
         
-        Ok(result)
+        result.map(|mut entry| {
+            entry.nickname = self.open(entry.nickname, NICKNAME_CONTEXT)?;
+            Ok(entry)
+        }).transpose()
     }
     
     /// Check if a peer is trusted
@@ -175,7 +274,9 @@ impl TrustDatabase {
         
         let mut result = Vec::new();
         for entry in entries {
-            result.push(entry.map_err(|e| TrustError::DatabaseError(format!("Failed to parse entry: {}", e)))?);
+            let mut entry = entry.map_err(|e| TrustError::DatabaseError(format!("Failed to parse entry: {}", e)))?;
+            entry.nickname = self.open(entry.nickname, NICKNAME_CONTEXT)?;
+            result.push(entry);
         }
         
         Ok(result)
@@ -239,4 +340,169 @@ impl TrustDatabase {
         
         Ok(())
     }
+    
+    /// Add one of the user's own devices, whose revocations are honored
+    pub fn add_to_device_group(&self, peer_id: &PeerId) -> SecurityResult<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT OR IGNORE INTO device_group (peer_id) VALUES (?1)",
+            params![peer_id.to_string()],
+        ).map_err(|e| TrustError::DatabaseError(format!("Failed to add to device group: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    /// Remove a device from the device group
+    pub fn remove_from_device_group(&self, peer_id: &PeerId) -> SecurityResult<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "DELETE FROM device_group WHERE peer_id = ?1",
+            params![peer_id.to_string()],
+        ).map_err(|e| TrustError::DatabaseError(format!("Failed to remove from device group: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    /// Check if a device is in the device group
+    pub fn is_in_device_group(&self, peer_id: &PeerId) -> SecurityResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        
+        let found = conn.query_row(
+            "SELECT 1 FROM device_group WHERE peer_id = ?1",
+            params![peer_id.to_string()],
+            |_| Ok(()),
+        ).optional()
+        .map_err(|e| TrustError::DatabaseError(format!("Failed to query device group: {}", e)))?;
+        
+        Ok(found.is_some())
+    }
+    
+    /// Get all devices in the device group
+    pub fn get_device_group(&self) -> SecurityResult<Vec<PeerId>> {
+        let conn = self.conn.lock().unwrap();
+        
+        let mut stmt = conn.prepare("SELECT peer_id FROM device_group")
+            .map_err(|e| TrustError::DatabaseError(format!("Failed to prepare statement: {}", e)))?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| TrustError::DatabaseError(format!("Failed to query device group: {}", e)))?;
+        
+        let mut result = Vec::new();
+        for row in rows {
+            let peer_id_str = row.map_err(|e| TrustError::DatabaseError(format!("Failed to parse entry: {}", e)))?;
+            result.push(PeerId::from_string(&peer_id_str)?);
+        }
+        
+        Ok(result)
+    }
+    
+    /// Store a revocation record; returns false if the peer was already revoked
+    pub fn add_revocation(&self, record: &RevocationRecord) -> SecurityResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        
+        let json = self.seal(&serde_json::to_string(record)?, REVOCATION_CONTEXT)?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO revocations (revoked_peer, issuer, issued_at, record)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![record.revoked.to_string(), record.issuer.to_string(), record.issued_at, json],
+        ).map_err(|e| TrustError::DatabaseError(format!("Failed to add revocation: {}", e)))?;
+        
+        Ok(inserted > 0)
+    }
+    
+    /// Get the revocation record for a peer
+    pub fn get_revocation(&self, peer_id: &PeerId) -> SecurityResult<Option<RevocationRecord>> {
+        let conn = self.conn.lock().unwrap();
+        
+        let json = conn.query_row(
+            "SELECT record FROM revocations WHERE revoked_peer = ?1",
+            params![peer_id.to_string()],
+            |row| row.get::<_, String>(0),
+        ).optional()
+        .map_err(|e| TrustError::DatabaseError(format!("Failed to query revocation: {}", e)))?;
+        
+        match json {
+            Some(json) => Ok(Some(serde_json::from_str(&self.open(json, REVOCATION_CONTEXT)?)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Get all revocation records
+    pub fn get_all_revocations(&self) -> SecurityResult<Vec<RevocationRecord>> {
+        self.query_revocations("SELECT record FROM revocations ORDER BY issued_at", params![])
+    }
+    
+    /// Get revocation records not yet sent to a peer, excluding the peer's own
+    pub fn get_unsent_revocations(&self, peer_id: &PeerId) -> SecurityResult<Vec<RevocationRecord>> {
+        let peer_id_str = peer_id.to_string();
+        self.query_revocations(
+            "SELECT record FROM revocations
+             WHERE revoked_peer != ?1
+               AND revoked_peer NOT IN (SELECT revoked_peer FROM revocations_sent WHERE peer_id = ?1)
+             ORDER BY issued_at",
+            params![peer_id_str],
+        )
+    }
+    
+    /// Record that revocations were delivered to a peer
+    pub fn mark_revocations_sent(&self, peer_id: &PeerId, revoked: &[PeerId]) -> SecurityResult<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        let peer_id_str = peer_id.to_string();
+        for revoked_peer in revoked {
+            conn.execute(
+                "INSERT OR IGNORE INTO revocations_sent (peer_id, revoked_peer) VALUES (?1, ?2)",
+                params![peer_id_str, revoked_peer.to_string()],
+            ).map_err(|e| TrustError::DatabaseError(format!("Failed to record sent revocation: {}", e)))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Keep trusting a peer even if it is revoked by another device
+    pub fn set_revocation_override(&self, peer_id: &PeerId, keep_trusted: bool) -> SecurityResult<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        let sql = if keep_trusted {
+            "INSERT OR IGNORE INTO revocation_overrides (peer_id) VALUES (?1)"
+        } else {
+            "DELETE FROM revocation_overrides WHERE peer_id = ?1"
+        };
+        conn.execute(sql, params![peer_id.to_string()])
+            .map_err(|e| TrustError::DatabaseError(format!("Failed to update revocation override: {}", e)))?;
+        
+        Ok(())
+    }
+    
+    /// Check if revocations of a peer are overridden locally
+    pub fn has_revocation_override(&self, peer_id: &PeerId) -> SecurityResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        
+        let found = conn.query_row(
+            "SELECT 1 FROM revocation_overrides WHERE peer_id = ?1",
+            params![peer_id.to_string()],
+            |_| Ok(()),
+        ).optional()
+        .map_err(|e| TrustError::DatabaseError(format!("Failed to query revocation override: {}", e)))?;
+        
+        Ok(found.is_some())
+    }
+    
+    fn query_revocations(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> SecurityResult<Vec<RevocationRecord>> {
+        let conn = self.conn.lock().unwrap();
+        
+        let mut stmt = conn.prepare(sql)
+            .map_err(|e| TrustError::DatabaseError(format!("Failed to prepare statement: {}", e)))?;
+        let rows = stmt.query_map(params, |row| row.get::<_, String>(0))
+            .map_err(|e| TrustError::DatabaseError(format!("Failed to query revocations: {}", e)))?;
+        
+        let mut result = Vec::new();
+        for row in rows {
+            let json = row.map_err(|e| TrustError::DatabaseError(format!("Failed to parse revocation: {}", e)))?;
+            result.push(serde_json::from_str(&self.open(json, REVOCATION_CONTEXT)?)?);
+        }
+        
+        Ok(result)
+    }
 }
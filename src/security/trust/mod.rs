@@ -1,16 +1,24 @@
 mod database;
 mod pairing;
 mod allowlist;
+mod totp;
+mod revocation;
 
 pub use database::TrustDatabase;
 pub use pairing::PairingService;
+pub use totp::{TotpConfig, TotpSalt, TotpSecret, TOTP_SALT_LEN};
+pub use revocation::{
+    RevocationBatch, RevocationGossip, RevocationOutcome, RevocationRecord, MAX_REVOCATION_BATCH_LEN,
+};
 pub use allowlist::AllowlistManager;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use crate::security::error::SecurityResult;
-use crate::security::identity::PeerId;
+use crate::security::error::AuthenticationError;
+use crate::security::identity::{DeviceIdentity, PeerId};
+use crate::security::storage::EncryptedStorage;
 
 /// Trust level for a peer
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -77,6 +85,8 @@ impl TrustEntry {
 pub struct PairingCode {
     code: String,
     created_at: u64,
+    /// Monotonic creation time, so expiry survives wall-clock changes
+    issued: Instant,
 }
 
 impl PairingCode {
@@ -86,20 +96,20 @@ impl PairingCode {
             .unwrap()
             .as_secs();
         
-        Self { code, created_at }
+        Self { code, created_at, issued: Instant::now() }
     }
     
     pub fn code(&self) -> &str {
         &self.code
     }
     
+    /// Unix time the code was generated, for display
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+    
     pub fn is_expired(&self, timeout_secs: u64) -> bool {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        
-        now - self.created_at > timeout_secs
+        self.issued.elapsed() > Duration::from_secs(timeout_secs)
     }
 }
 
@@ -147,8 +157,13 @@ pub struct TrustManagerImpl {
 impl TrustManagerImpl {
     /// Create a new trust manager
     pub fn new(db_path: std::path::PathBuf) -> SecurityResult<Self> {
+        Self::with_storage(db_path, None)
+    }
+    
+    /// Create a trust manager whose database is encrypted at rest
+    pub fn with_storage(db_path: std::path::PathBuf, storage: Option<EncryptedStorage>) -> SecurityResult<Self> {
         Ok(Self {
-            database: TrustDatabase::new(db_path)?,
+            database: TrustDatabase::with_storage(db_path, storage)?,
             pairing_service: PairingService::new(),
             allowlist_manager: AllowlistManager::new(),
         })
@@ -163,6 +178,96 @@ impl TrustManagerImpl {
     pub fn cleanup_expired_sessions(&self) -> SecurityResult<()> {
         self.pairing_service.cleanup_expired_sessions()
     }
+    
+    /// Get reference to the pairing service
+    pub fn pairing_service(&self) -> &PairingService {
+        &self.pairing_service
+    }
+    
+    /// Mark another of the user's devices as able to revoke peers for this one
+    pub fn add_to_device_group(&self, peer_id: &PeerId) -> SecurityResult<()> {
+        self.database.add_to_device_group(peer_id)
+    }
+    
+    /// Stop honoring revocations issued by a device
+    pub fn remove_from_device_group(&self, peer_id: &PeerId) -> SecurityResult<()> {
+        self.database.remove_from_device_group(peer_id)
+    }
+    
+    /// Get the user's other devices
+    pub fn get_device_group(&self) -> SecurityResult<Vec<PeerId>> {
+        self.database.get_device_group()
+    }
+    
+    /// Keep trusting a peer locally even if another device revokes it
+    pub fn set_revocation_override(&self, peer_id: &PeerId, keep_trusted: bool) -> SecurityResult<()> {
+        self.database.set_revocation_override(peer_id, keep_trusted)
+    }
+    
+    /// Get the revocation recorded for a peer, if any
+    pub fn revocation_for(&self, peer_id: &PeerId) -> SecurityResult<Option<RevocationRecord>> {
+        self.database.get_revocation(peer_id)
+    }
+    
+    /// Revoke a peer on this device and record a signed revocation for gossip
+    pub async fn revoke_peer(
+        &self,
+        identity: &DeviceIdentity,
+        peer_id: &PeerId,
+        reason: Option<String>,
+    ) -> SecurityResult<RevocationRecord> {
+        let record = match self.database.get_revocation(peer_id)? {
+            Some(existing) => existing,
+            None => {
+                let record = RevocationRecord::new(identity, peer_id.clone(), reason)?;
+                self.database.add_revocation(&record)?;
+                record
+            }
+        };
+        
+        self.database.set_revocation_override(peer_id, false)?;
+        self.database.remove_from_device_group(peer_id)?;
+        if self.database.is_trusted(peer_id)? {
+            self.remove_trusted_peer(peer_id).await?;
+        }
+        
+        Ok(record)
+    }
+    
+    /// Apply a revocation received from another device
+    ///
+    /// Only revocations signed by a device in the group are honored. A local
+    /// override keeps the peer trusted but still records the revocation.
+    pub async fn apply_revocation(&self, record: &RevocationRecord, local: &PeerId) -> SecurityResult<RevocationOutcome> {
+        if !record.verify()? {
+            return Err(AuthenticationError::InvalidSignature.into());
+        }
+        if &record.revoked == local {
+            return Ok(RevocationOutcome::SelfRevocation);
+        }
+        if !self.database.is_in_device_group(&record.issuer)?
+            || self.database.get_revocation(&record.issuer)?.is_some()
+        {
+            return Ok(RevocationOutcome::UntrustedIssuer);
+        }
+        if !self.database.add_revocation(record)? {
+            return Ok(RevocationOutcome::Duplicate);
+        }
+        
+        if self.database.has_revocation_override(&record.revoked)? {
+            log::warn!(
+                "Peer {} was revoked by {} but is kept trusted by a local override",
+                record.revoked, record.issuer
+            );
+            return Ok(RevocationOutcome::Flagged);
+        }
+        
+        self.database.remove_from_device_group(&record.revoked)?;
+        if self.database.is_trusted(&record.revoked)? {
+            self.remove_trusted_peer(&record.revoked).await?;
+        }
+        Ok(RevocationOutcome::Applied)
+    }
 }
 
 #[async_trait]
@@ -1,10 +1,13 @@
 use rand::Rng;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use crate::security::error::{SecurityResult, AuthenticationError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::security::error::{SecurityResult, AuthenticationError, TrustError};
 use crate::security::identity::PeerId;
 use crate::security::constant_time::ConstantTime;
+use crate::security::policy::{RateLimitConfig, RateLimiter};
 use super::PairingCode;
+use super::totp::{TotpConfig, TotpSalt, TotpSecret};
 
 /// Pairing session information
 struct PairingSession {
@@ -12,19 +15,27 @@ struct PairingSession {
     peer_id: Option<PeerId>,
 }
 
+/// Rolling-code pairing in progress
+struct TotpSession {
+    secret: TotpSecret,
+    started_at: Instant,
+    failures: u32,
+    last_step: Option<u64>,
+}
+
 /// Service for managing pairing codes and verification
 pub struct PairingService {
     sessions: Arc<Mutex<HashMap<String, PairingSession>>>,
     timeout_secs: u64,
+    totp: Arc<Mutex<Option<TotpSession>>>,
+    totp_config: TotpConfig,
+    totp_limiter: RateLimiter,
 }
 
 impl PairingService {
     /// Create a new pairing service
     pub fn new() -> Self {
-        Self {
-            sessions: Arc::new(Mutex::new(HashMap::new())),
-            timeout_secs: 60, // 60 second timeout as per requirements
-        }
+        Self::with_timeout(60) // 60 second timeout as per requirements
     }
     
     /// Create a new pairing service with custom timeout
@@ -32,9 +43,22 @@ impl PairingService {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
             timeout_secs,
+            totp: Arc::new(Mutex::new(None)),
+            totp_config: TotpConfig::default(),
+            totp_limiter: RateLimiter::with_config(RateLimitConfig {
+                max_attempts: 3,
+                window_secs: 30,
+                ..RateLimitConfig::default()
+            }),
         }
     }
     
+    /// Use custom settings for rolling-code pairing
+    pub fn with_totp_config(mut self, config: TotpConfig) -> Self {
+        self.totp_config = config;
+        self
+    }
+    
     /// Generate a 6-digit pairing code
     pub fn generate_pairing_code(&self) -> SecurityResult<PairingCode> {
         let mut rng = rand::thread_rng();
@@ -135,6 +159,107 @@ impl PairingService {
             false
         }
     }
+    
+    /// Start rolling-code pairing with a secret typed on both devices
+    ///
+    /// For devices that cannot show or scan a QR code or compare emoji, such as a
+    /// headless server reached over SSH. Both sides derive the same 6-digit code
+    /// from the secret and the returned salt, which the other device joins with
+    /// through [`join_totp_pairing`](Self::join_totp_pairing). The code changes
+    /// every `step_secs`. Replaces any rolling-code pairing already in progress.
+    pub fn start_totp_pairing(&self, secret: &str) -> SecurityResult<TotpSalt> {
+        let salt = TotpSalt::generate();
+        self.join_totp_pairing(secret, &salt)?;
+        Ok(salt)
+    }
+    
+    /// Join rolling-code pairing the other device started, with its salt
+    pub fn join_totp_pairing(&self, secret: &str, salt: &TotpSalt) -> SecurityResult<()> {
+        let secret = TotpSecret::from_passphrase(secret, salt, self.totp_config.min_secret_len)?;
+        *self.totp.lock().unwrap() = Some(TotpSession {
+            secret,
+            started_at: Instant::now(),
+            failures: 0,
+            last_step: None,
+        });
+        Ok(())
+    }
+    
+    /// The rolling code to type on, or send to, the other device
+    pub fn current_totp_code(&self) -> SecurityResult<String> {
+        let mut totp = self.totp.lock().unwrap();
+        let now = Self::now();
+        match totp.as_ref() {
+            Some(session) if !self.totp_expired(session) => {
+                Ok(session.secret.code_at(now, self.totp_config.step_secs))
+            }
+            Some(_) => {
+                *totp = None;
+                Err(TrustError::PairingExpired.into())
+            }
+            None => Err(TrustError::PairingFailed("No rolling-code pairing in progress".to_string()).into()),
+        }
+    }
+    
+    /// Verify a rolling code from a peer
+    ///
+    /// Attempts are rate limited per peer, and the pairing is abandoned after
+    /// `max_failures` wrong codes from anyone, so the secret has to be entered
+    /// again. A code is accepted once; the pairing ends when it succeeds.
+    pub fn verify_totp_code(&self, code: &str, peer_id: &PeerId) -> SecurityResult<bool> {
+        self.totp_limiter.check_rate_limit(peer_id)?;
+        
+        let mut totp = self.totp.lock().unwrap();
+        let now = Self::now();
+        let Some(session) = totp.as_mut() else {
+            return Ok(false);
+        };
+        if self.totp_expired(session) {
+            *totp = None;
+            return Ok(false);
+        }
+        
+        let matched = session
+            .secret
+            .verify(code, now, self.totp_config.step_secs, self.totp_config.skew_steps)
+            .filter(|step| session.last_step.is_none_or(|last| *step > last));
+        if let Some(step) = matched {
+            session.last_step = Some(step);
+            *totp = None;
+            return Ok(true);
+        }
+        
+        session.failures += 1;
+        if session.failures >= self.totp_config.max_failures {
+            *totp = None;
+            return Err(TrustError::PairingFailed(
+                "Too many wrong codes; start pairing again".to_string()
+            ).into());
+        }
+        Ok(false)
+    }
+    
+    /// Abandon rolling-code pairing
+    pub fn cancel_totp_pairing(&self) {
+        *self.totp.lock().unwrap() = None;
+    }
+    
+    /// Whether rolling-code pairing is in progress
+    pub fn is_totp_pairing_active(&self) -> bool {
+        let totp = self.totp.lock().unwrap();
+        totp.as_ref().is_some_and(|session| !self.totp_expired(session))
+    }
+    
+    fn totp_expired(&self, session: &TotpSession) -> bool {
+        session.started_at.elapsed() > Duration::from_secs(self.totp_config.session_timeout_secs)
+    }
+    
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
 }
 
 impl Default for PairingService {
@@ -204,4 +329,46 @@ mod tests {
         
         assert_eq!(service.active_sessions_count(), 0);
     }
+    
+    #[test]
+    fn test_totp_pairing() {
+        let server = PairingService::new();
+        let client = PairingService::new();
+        let salt = server.start_totp_pairing("blue-otter-7421").unwrap();
+        client.join_totp_pairing("BLUE OTTER 7421", &salt).unwrap();
+        
+        // Without the server's salt the same secret gives different codes
+        let stranger = PairingService::new();
+        stranger.start_totp_pairing("blue-otter-7421").unwrap();
+        assert_ne!(stranger.current_totp_code().unwrap(), client.current_totp_code().unwrap());
+        
+        let peer_id = PeerId::from_fingerprint([1; 32]);
+        let code = client.current_totp_code().unwrap();
+        assert!(server.verify_totp_code(&code, &peer_id).unwrap());
+        
+        // The pairing ends once a code is accepted
+        assert!(!server.is_totp_pairing_active());
+        assert!(!server.verify_totp_code(&code, &peer_id).unwrap());
+    }
+    
+    #[test]
+    fn test_totp_rate_limited_and_abandoned() {
+        let service = PairingService::new().with_totp_config(TotpConfig {
+            max_failures: 4,
+            ..TotpConfig::default()
+        });
+        service.start_totp_pairing("blue-otter-7421").unwrap();
+        let wrong = if service.current_totp_code().unwrap() == "000000" { "111111" } else { "000000" };
+        
+        let attacker = PeerId::from_fingerprint([2; 32]);
+        for _ in 0..3 {
+            assert!(!service.verify_totp_code(wrong, &attacker).unwrap());
+        }
+        assert!(service.verify_totp_code(wrong, &attacker).is_err(), "per-peer limit");
+        
+        // Another peer ID still counts toward the total
+        let other = PeerId::from_fingerprint([3; 32]);
+        assert!(service.verify_totp_code(wrong, &other).is_err());
+        assert!(!service.is_totp_pairing_active());
+    }
 }
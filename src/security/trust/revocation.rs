@@ -0,0 +1,444 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use crate::security::error::{AuthenticationError, IdentityError, SecurityError, SecurityResult};
+use crate::security::identity::{DeviceIdentity, PeerId};
+use crate::transport::{
+    ConnectionProvider, InboundRouter, Multiplexer, MuxRole, MuxStream, PeerAddress, PeerId as TransportPeerId,
+    SessionObserver, StreamHandler, CHANNEL_REVOCATIONS,
+};
+use super::{TrustManager, TrustManagerImpl};
+
+/// Largest revocation batch accepted from a peer
+pub const MAX_REVOCATION_BATCH_LEN: usize = 256 * 1024;
+
+/// Signed statement that a device should no longer be trusted
+///
+/// Issued by one device in the user's group and honored by the others when
+/// they receive it during gossip.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationRecord {
+    /// Device being revoked
+    pub revoked: PeerId,
+    /// Device that issued the revocation
+    pub issuer: PeerId,
+    /// Public key of the issuer, for verification
+    pub issuer_key: Vec<u8>,
+    /// Unix time the revocation was issued
+    pub issued_at: u64,
+    /// Optional reason shown to the user
+    pub reason: Option<String>,
+    /// Signature of the record by the issuer
+    pub signature: Vec<u8>,
+}
+
+impl RevocationRecord {
+    /// Create and sign a revocation of `revoked`
+    pub fn new(identity: &DeviceIdentity, revoked: PeerId, reason: Option<String>) -> SecurityResult<Self> {
+        let issued_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| IdentityError::GenerationFailed(format!("System time error: {}", e)))?
+            .as_secs();
+
+        let mut record = Self {
+            revoked,
+            issuer: identity.derive_peer_id(),
+            issuer_key: identity.public_key().as_bytes().to_vec(),
+            issued_at,
+            reason,
+            signature: Vec::new(),
+        };
+        record.signature = identity.sign(&record.signed_bytes()).to_vec();
+
+        Ok(record)
+    }
+
+    /// Bytes covered by the signature
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(b"kizuna-revocation-v1");
+        message.extend_from_slice(self.revoked.fingerprint());
+        message.extend_from_slice(self.issuer.fingerprint());
+        message.extend_from_slice(&self.issued_at.to_le_bytes());
+        if let Some(reason) = &self.reason {
+            message.extend_from_slice(reason.as_bytes());
+        }
+        message
+    }
+
+    /// Verify the signature and that the key belongs to the issuer
+    pub fn verify(&self) -> SecurityResult<bool> {
+        use ed25519_dalek::{Signature, VerifyingKey, Verifier};
+
+        let key_bytes: [u8; 32] = self.issuer_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| IdentityError::InvalidPeerId("Invalid public key length".to_string()))?;
+        let verifying_key = VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|e| IdentityError::InvalidPeerId(format!("Invalid public key: {}", e)))?;
+
+        if PeerId::from_public_key(&verifying_key) != self.issuer {
+            return Ok(false);
+        }
+
+        let signature_bytes: [u8; 64] = self.signature
+            .as_slice()
+            .try_into()
+            .map_err(|_| IdentityError::InvalidPeerId("Invalid signature length".to_string()))?;
+        let signature = Signature::from_bytes(&signature_bytes);
+
+        Ok(verifying_key.verify(&self.signed_bytes(), &signature).is_ok())
+    }
+}
+
+/// What happened when a received revocation was applied
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RevocationOutcome {
+    /// Trust in the revoked device was removed
+    Applied,
+    /// Recorded, but the device stays trusted because of a local override
+    Flagged,
+    /// The revocation was already known
+    Duplicate,
+    /// The issuer is not one of the user's devices, so it was ignored
+    UntrustedIssuer,
+    /// The revocation targets this device, so it was ignored
+    SelfRevocation,
+}
+
+/// Revocations exchanged in one gossip round
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RevocationBatch {
+    pub records: Vec<RevocationRecord>,
+}
+
+impl RevocationBatch {
+    pub fn encode(&self) -> SecurityResult<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn decode(bytes: &[u8]) -> SecurityResult<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// Propagates revocations to trusted peers on contact
+///
+/// Each side sends the revocations the other has not seen yet and applies
+/// what it receives, so a revocation spreads through the device group as
+/// devices meet.
+#[derive(Clone)]
+pub struct RevocationGossip {
+    provider: Arc<ConnectionProvider>,
+    trust: Arc<TrustManagerImpl>,
+    local: PeerId,
+    /// Rounds started on sessions we dialed, awaited by `finish`
+    rounds: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl RevocationGossip {
+    pub fn new(provider: Arc<ConnectionProvider>, trust: Arc<TrustManagerImpl>, local: PeerId) -> Self {
+        Self {
+            provider,
+            trust,
+            local,
+            rounds: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Gossip on every session the provider establishes from now on
+    ///
+    /// Rounds peers start arrive through `inbound`; on sessions we dial, we
+    /// start one ourselves.
+    pub fn serve(&self, inbound: &InboundRouter) {
+        inbound.register(CHANNEL_REVOCATIONS, Arc::new(self.clone()));
+        self.provider.observe_sessions(Arc::new(self.clone()));
+    }
+
+    /// Wait up to `timeout` for the rounds started on dialed sessions
+    ///
+    /// Short-lived processes call this before closing their sessions so the
+    /// rounds are not cut off.
+    pub async fn finish(&self, timeout: Duration) {
+        let rounds = std::mem::take(&mut *self.rounds.lock().unwrap());
+        let _ = tokio::time::timeout(timeout, async {
+            for round in rounds {
+                let _ = round.await;
+            }
+        })
+        .await;
+    }
+
+    /// Exchange revocations with a trusted peer
+    pub async fn sync(&self, peer: &PeerAddress) -> SecurityResult<Vec<RevocationOutcome>> {
+        self.ensure_trusted(&peer.peer_id).await?;
+        let session = self.provider
+            .session(peer)
+            .await
+            .map_err(|e| SecurityError::Generic(format!("Failed to connect for revocations: {}", e)))?;
+
+        self.sync_session(&peer.peer_id, &session).await
+    }
+
+    /// Exchange revocations over a session that is already established
+    async fn sync_session(&self, peer_id: &str, session: &Multiplexer) -> SecurityResult<Vec<RevocationOutcome>> {
+        let peer_id = self.ensure_trusted(peer_id).await?;
+
+        let stream = session
+            .open_stream(CHANNEL_REVOCATIONS)
+            .map_err(|e| SecurityError::Generic(format!("Failed to open revocation stream: {}", e)))?;
+        let result = self.exchange(&peer_id, &stream, true).await;
+        let _ = stream.close();
+
+        result
+    }
+
+    /// Answer a gossip round started by a peer
+    pub async fn attach_stream(&self, peer_id: &str, stream: MuxStream) -> SecurityResult<()> {
+        if stream.channel() != CHANNEL_REVOCATIONS {
+            return Err(SecurityError::Generic(format!(
+                "Stream on channel {} is not a revocation stream",
+                stream.channel()
+            )));
+        }
+        let peer_id = self.ensure_trusted(peer_id).await?;
+
+        let gossip = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = gossip.exchange(&peer_id, &stream, false).await {
+                log::warn!("Revocation gossip with {} failed: {}", peer_id, e);
+            }
+            let _ = stream.close();
+        });
+        Ok(())
+    }
+
+    async fn ensure_trusted(&self, peer_id: &str) -> SecurityResult<PeerId> {
+        let peer_id = PeerId::from_string(peer_id)?;
+        if !self.trust.is_trusted(&peer_id).await? {
+            return Err(AuthenticationError::Failed(format!(
+                "Peer {} is not trusted for revocation gossip",
+                peer_id
+            )).into());
+        }
+        Ok(peer_id)
+    }
+
+    /// Send our batch and apply theirs; the initiator sends first
+    async fn exchange(
+        &self,
+        peer_id: &PeerId,
+        stream: &MuxStream,
+        initiator: bool,
+    ) -> SecurityResult<Vec<RevocationOutcome>> {
+        let database = self.trust.trust_database();
+        let outgoing = RevocationBatch {
+            records: database.get_unsent_revocations(peer_id)?,
+        };
+
+        if initiator {
+            self.send(stream, &outgoing).await?;
+        }
+        let incoming = stream
+            .recv_message(MAX_REVOCATION_BATCH_LEN)
+            .await
+            .map_err(|e| SecurityError::Generic(format!("Failed to receive revocations: {}", e)))?
+            .map(|bytes| RevocationBatch::decode(&bytes))
+            .transpose()?
+            .unwrap_or_default();
+        if !initiator {
+            self.send(stream, &outgoing).await?;
+        }
+
+        let sent: Vec<PeerId> = outgoing.records.iter().map(|r| r.revoked.clone()).collect();
+        database.mark_revocations_sent(peer_id, &sent)?;
+
+        let mut outcomes = Vec::with_capacity(incoming.records.len());
+        for record in incoming.records {
+            match self.trust.apply_revocation(&record, &self.local).await {
+                Ok(outcome) => {
+                    // The peer already has it, so there is no need to send it back
+                    database.mark_revocations_sent(peer_id, std::slice::from_ref(&record.revoked))?;
+                    outcomes.push(outcome);
+                }
+                Err(e) => log::warn!("Ignoring revocation from {}: {}", peer_id, e),
+            }
+        }
+
+        Ok(outcomes)
+    }
+
+    async fn send(&self, stream: &MuxStream, batch: &RevocationBatch) -> SecurityResult<()> {
+        stream
+            .send_message(&batch.encode()?)
+            .await
+            .map_err(|e| SecurityError::Generic(format!("Failed to send revocations: {}", e)))
+    }
+}
+
+#[async_trait]
+impl StreamHandler for RevocationGossip {
+    async fn handle_stream(&self, peer_id: TransportPeerId, stream: MuxStream) -> Result<(), String> {
+        self.attach_stream(&peer_id, stream).await.map_err(|e| e.to_string())
+    }
+}
+
+impl SessionObserver for RevocationGossip {
+    fn session_established(&self, peer_id: &TransportPeerId, session: &Arc<Multiplexer>, role: MuxRole) {
+        // The dialing side starts the round, so each contact runs one
+        if role != MuxRole::Initiator {
+            return;
+        }
+
+        let gossip = self.clone();
+        let peer_id = peer_id.clone();
+        let session = Arc::clone(session);
+        let round = tokio::spawn(async move {
+            match gossip.sync_session(&peer_id, &session).await {
+                Ok(outcomes) => log::debug!("Revocation gossip with {}: {:?}", peer_id, outcomes),
+                Err(e) => log::debug!("No revocation gossip with {}: {}", peer_id, e),
+            }
+        });
+
+        let mut rounds = self.rounds.lock().unwrap();
+        rounds.retain(|round| !round.is_finished());
+        rounds.push(round);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::trust::TrustManager;
+    use crate::testing::transport::MEMORY_PROTOCOL;
+    use crate::testing::{MemoryTransport, VirtualNetwork};
+    use crate::transport::{PeerDialer, ProviderConfig, Transport};
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, TrustManagerImpl) {
+        let dir = TempDir::new().unwrap();
+        let trust = TrustManagerImpl::new(dir.path().join("trust.db")).unwrap();
+        (dir, trust)
+    }
+
+    #[test]
+    fn test_record_signature() {
+        let identity = DeviceIdentity::generate().unwrap();
+        let revoked = PeerId::from_fingerprint([7; 32]);
+
+        let record = RevocationRecord::new(&identity, revoked.clone(), Some("stolen".to_string())).unwrap();
+        assert!(record.verify().unwrap());
+
+        let mut tampered = record.clone();
+        tampered.revoked = PeerId::from_fingerprint([8; 32]);
+        assert!(!tampered.verify().unwrap());
+
+        let mut forged = record;
+        forged.issuer = PeerId::from_fingerprint([9; 32]);
+        assert!(!forged.verify().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_apply_from_device_group() {
+        let (_dir, trust) = setup();
+        let local = PeerId::from_fingerprint([1; 32]);
+        let sibling = DeviceIdentity::generate().unwrap();
+        let stranger = DeviceIdentity::generate().unwrap();
+        let laptop = PeerId::from_fingerprint([2; 32]);
+        trust.add_trusted_peer(laptop.clone(), "laptop".to_string()).await.unwrap();
+
+        let record = RevocationRecord::new(&stranger, laptop.clone(), None).unwrap();
+        assert_eq!(trust.apply_revocation(&record, &local).await.unwrap(), RevocationOutcome::UntrustedIssuer);
+        assert!(trust.is_trusted(&laptop).await.unwrap());
+
+        trust.add_to_device_group(&sibling.derive_peer_id()).unwrap();
+        let record = RevocationRecord::new(&sibling, laptop.clone(), None).unwrap();
+        assert_eq!(trust.apply_revocation(&record, &local).await.unwrap(), RevocationOutcome::Applied);
+        assert!(!trust.is_trusted(&laptop).await.unwrap());
+        assert_eq!(trust.apply_revocation(&record, &local).await.unwrap(), RevocationOutcome::Duplicate);
+
+        let record = RevocationRecord::new(&sibling, local.clone(), None).unwrap();
+        assert_eq!(trust.apply_revocation(&record, &local).await.unwrap(), RevocationOutcome::SelfRevocation);
+    }
+
+    #[tokio::test]
+    async fn test_local_override_keeps_trust() {
+        let (_dir, trust) = setup();
+        let local = PeerId::from_fingerprint([1; 32]);
+        let sibling = DeviceIdentity::generate().unwrap();
+        let phone = PeerId::from_fingerprint([3; 32]);
+        trust.add_to_device_group(&sibling.derive_peer_id()).unwrap();
+        trust.add_trusted_peer(phone.clone(), "phone".to_string()).await.unwrap();
+        trust.set_revocation_override(&phone, true).unwrap();
+
+        let record = RevocationRecord::new(&sibling, phone.clone(), None).unwrap();
+        assert_eq!(trust.apply_revocation(&record, &local).await.unwrap(), RevocationOutcome::Flagged);
+        assert!(trust.is_trusted(&phone).await.unwrap());
+        assert!(trust.revocation_for(&phone).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_dialed_sessions_gossip_revocations() {
+        let alice_identity = DeviceIdentity::generate().unwrap();
+        let alice = alice_identity.derive_peer_id();
+        let bob = DeviceIdentity::generate().unwrap().derive_peer_id();
+        let laptop = PeerId::from_fingerprint([4; 32]);
+
+        let network = VirtualNetwork::new();
+        let alice_transport = Arc::new(MemoryTransport::new(alice.to_hex(), network.clone()));
+        let bob_transport = Arc::new(MemoryTransport::new(bob.to_hex(), network.clone()));
+        let addr = network.allocate_address(43200);
+        bob_transport.listen(&addr).await.unwrap();
+        let bob_addr = PeerAddress::new(
+            bob.to_hex(),
+            vec![addr],
+            vec![MEMORY_PROTOCOL.to_string()],
+            bob_transport.capabilities(),
+        );
+
+        // Alice revoked the laptop; Bob still trusts it but honors Alice
+        let ((_alice_dir, alice_trust), (_bob_dir, bob_trust)) = (setup(), setup());
+        let (alice_trust, bob_trust) = (Arc::new(alice_trust), Arc::new(bob_trust));
+        alice_trust.add_trusted_peer(bob.clone(), "bob".to_string()).await.unwrap();
+        alice_trust.revoke_peer(&alice_identity, &laptop, None).await.unwrap();
+        bob_trust.add_trusted_peer(alice.clone(), "alice".to_string()).await.unwrap();
+        bob_trust.add_to_device_group(&alice).unwrap();
+        bob_trust.add_trusted_peer(laptop.clone(), "laptop".to_string()).await.unwrap();
+
+        let alice_provider = Arc::new(ConnectionProvider::new(alice_transport, ProviderConfig::default()));
+        let alice_inbound = InboundRouter::new();
+        alice_inbound.attach(&alice_provider);
+        let alice_gossip = RevocationGossip::new(Arc::clone(&alice_provider), alice_trust, alice);
+        alice_gossip.serve(&alice_inbound);
+
+        let bob_provider = Arc::new(ConnectionProvider::new(
+            Arc::clone(&bob_transport) as Arc<dyn PeerDialer>,
+            ProviderConfig::default(),
+        ));
+        let bob_inbound = InboundRouter::new();
+        bob_inbound.attach(&bob_provider);
+        RevocationGossip::new(Arc::clone(&bob_provider), Arc::clone(&bob_trust), bob).serve(&bob_inbound);
+        let accepting = tokio::spawn({
+            let bob_provider = Arc::clone(&bob_provider);
+            async move {
+                bob_provider.adopt(Box::new(bob_transport.accept().await.unwrap())).await.unwrap();
+            }
+        });
+
+        // Connecting is enough; nobody asks for a sync
+        alice_provider.session(&bob_addr).await.unwrap();
+        accepting.await.unwrap();
+        alice_gossip.finish(Duration::from_secs(5)).await;
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while bob_trust.is_trusted(&laptop).await.unwrap() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(bob_trust.revocation_for(&laptop).unwrap().is_some());
+    }
+}
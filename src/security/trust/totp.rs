@@ -0,0 +1,219 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::fmt;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+use crate::security::constant_time::ConstantTime;
+use crate::security::error::{SecurityResult, TrustError};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Domain separation for deriving the code key from a typed secret
+const TOTP_KEY_CONTEXT: &[u8] = b"kizuna-totp-pairing-v1";
+
+/// Argon2id cost of deriving the code key, in KiB of memory and passes
+///
+/// Both devices must use the same cost, so it is fixed rather than configured.
+const KDF_MEMORY_KIB: u32 = 19 * 1024;
+const KDF_ITERATIONS: u32 = 2;
+
+/// Bytes in a pairing salt
+pub const TOTP_SALT_LEN: usize = 16;
+
+/// Random salt for one rolling-code pairing
+///
+/// The device that starts the pairing generates it and the other device joins
+/// with it, so a code an eavesdropper sees can only be used to guess the secret
+/// of that one pairing, at the cost of an Argon2 derivation per guess.
+/// Displayed and entered as hex.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TotpSalt([u8; TOTP_SALT_LEN]);
+
+impl TotpSalt {
+    pub fn generate() -> Self {
+        let mut salt = [0u8; TOTP_SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self(salt)
+    }
+
+    pub fn from_bytes(bytes: [u8; TOTP_SALT_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; TOTP_SALT_LEN] {
+        &self.0
+    }
+
+    /// Parse a salt typed from the other device, ignoring spaces and dashes
+    pub fn from_hex(text: &str) -> SecurityResult<Self> {
+        let digits: String = text.chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+        let bytes = hex::decode(digits)
+            .ok()
+            .and_then(|bytes| <[u8; TOTP_SALT_LEN]>::try_from(bytes).ok())
+            .ok_or_else(|| {
+                TrustError::PairingFailed(format!("Pairing salt must be {} hex digits", TOTP_SALT_LEN * 2))
+            })?;
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Display for TotpSalt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+/// Settings for rolling-code pairing
+#[derive(Clone, Debug)]
+pub struct TotpConfig {
+    /// Seconds each code is valid for
+    pub step_secs: u64,
+    /// Codes from this many steps before or after the current one are accepted,
+    /// to allow for clock drift and typing time
+    pub skew_steps: u64,
+    /// Seconds a rolling-code pairing stays open
+    pub session_timeout_secs: u64,
+    /// Wrong codes accepted in total before the pairing is abandoned
+    pub max_failures: u32,
+    /// Shortest secret accepted, after spaces and dashes are removed
+    pub min_secret_len: usize,
+}
+
+impl Default for TotpConfig {
+    fn default() -> Self {
+        Self {
+            step_secs: 30,
+            skew_steps: 1,
+            session_timeout_secs: 300,
+            max_failures: 10,
+            min_secret_len: 8,
+        }
+    }
+}
+
+/// Key for 6-digit rolling codes, derived from a secret typed on both devices
+///
+/// The key is derived with Argon2id over the secret and the pairing's
+/// [`TotpSalt`]. Codes follow RFC 6238 with HMAC-SHA256.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct TotpSecret {
+    key: [u8; 32],
+}
+
+impl TotpSecret {
+    /// Derive the key from a typed secret and the pairing's salt
+    ///
+    /// Case, spaces and dashes are ignored so the secret can be typed in groups.
+    pub fn from_passphrase(secret: &str, salt: &TotpSalt, min_len: usize) -> SecurityResult<Self> {
+        let mut normalized: String = secret
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .flat_map(char::to_lowercase)
+            .collect();
+        if normalized.chars().count() < min_len {
+            normalized.zeroize();
+            return Err(TrustError::PairingFailed(format!(
+                "Pairing secret must be at least {} characters",
+                min_len
+            ))
+            .into());
+        }
+
+        let params = Params::new(KDF_MEMORY_KIB, KDF_ITERATIONS, 1, Some(32))
+            .map_err(|e| TrustError::PairingFailed(format!("Invalid key derivation settings: {}", e)))?;
+        let kdf = Argon2::new_with_secret(TOTP_KEY_CONTEXT, Algorithm::Argon2id, Version::V0x13, params)
+            .map_err(|e| TrustError::PairingFailed(format!("Invalid key derivation settings: {}", e)))?;
+
+        let mut key = [0u8; 32];
+        let derived = kdf.hash_password_into(normalized.as_bytes(), salt.as_bytes(), &mut key);
+        normalized.zeroize();
+        derived.map_err(|e| TrustError::PairingFailed(format!("Failed to derive pairing key: {}", e)))?;
+        Ok(Self { key })
+    }
+
+    /// Code for a time step
+    pub fn code_for_step(&self, step: u64) -> String {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&self.key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(&step.to_be_bytes());
+        let digest = mac.finalize().into_bytes();
+
+        // Dynamic truncation from RFC 4226
+        let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            digest[offset] & 0x7f,
+            digest[offset + 1],
+            digest[offset + 2],
+            digest[offset + 3],
+        ]);
+        format!("{:06}", binary % 1_000_000)
+    }
+
+    /// Code valid at a Unix time
+    pub fn code_at(&self, unix_secs: u64, step_secs: u64) -> String {
+        self.code_for_step(unix_secs / step_secs.max(1))
+    }
+
+    /// Step a code belongs to, if it is valid within `skew_steps` of `unix_secs`
+    ///
+    /// Every candidate is compared so the time taken does not reveal which one matched.
+    pub fn verify(&self, code: &str, unix_secs: u64, step_secs: u64, skew_steps: u64) -> Option<u64> {
+        let current = unix_secs / step_secs.max(1);
+        let mut matched = None;
+        for step in current.saturating_sub(skew_steps)..=current.saturating_add(skew_steps) {
+            if ConstantTime::compare(self.code_for_step(step).as_bytes(), code.trim().as_bytes()) {
+                matched = Some(step);
+            }
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_secret_same_codes() {
+        let salt = TotpSalt::generate();
+        let a = TotpSecret::from_passphrase("Correct-Horse 42", &salt, 8).unwrap();
+        let b = TotpSecret::from_passphrase("correcthorse42", &salt, 8).unwrap();
+        let other = TotpSecret::from_passphrase("correcthorse43", &salt, 8).unwrap();
+
+        let code = a.code_at(1_700_000_000, 30);
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(code, b.code_at(1_700_000_000, 30));
+        assert_ne!(code, other.code_at(1_700_000_000, 30));
+    }
+
+    #[test]
+    fn test_salt_separates_pairings() {
+        let salt = TotpSalt::from_bytes([7; TOTP_SALT_LEN]);
+        let a = TotpSecret::from_passphrase("correcthorse42", &salt, 8).unwrap();
+        let b = TotpSecret::from_passphrase("correcthorse42", &TotpSalt::from_bytes([8; TOTP_SALT_LEN]), 8).unwrap();
+        assert_ne!(a.code_at(1_700_000_000, 30), b.code_at(1_700_000_000, 30));
+
+        let typed = TotpSalt::from_hex(&format!(" {} ", salt)).unwrap();
+        assert_eq!(typed, salt);
+        assert!(TotpSalt::from_hex("0707").is_err());
+        assert!(TotpSalt::from_hex("not hex").is_err());
+    }
+
+    #[test]
+    fn test_verify_allows_skew() {
+        let secret = TotpSecret::from_passphrase("headless-server", &TotpSalt::generate(), 8).unwrap();
+        let now = 1_700_000_000;
+        let previous = secret.code_at(now - 30, 30);
+
+        assert_eq!(secret.verify(&previous, now, 30, 1), Some(now / 30 - 1));
+        assert_eq!(secret.verify(&previous, now + 60, 30, 1), None);
+        assert_eq!(secret.verify("12345", now, 30, 1), None);
+    }
+
+    #[test]
+    fn test_short_secret_rejected() {
+        assert!(TotpSecret::from_passphrase("ab - cd", &TotpSalt::generate(), 8).is_err());
+    }
+}
@@ -0,0 +1,535 @@
+//! Graceful shutdown coordination
+//!
+//! A [`ShutdownCoordinator`] turns Ctrl-C, SIGTERM or an explicit request into an
+//! orderly stop instead of killing the process mid-transfer:
+//!
+//! 1. a cancellation token is broadcast to every task spawned through the
+//!    coordinator (or holding one of its tokens), so loops stop taking new work;
+//! 2. registered [`ShutdownHook`]s run stage by stage: subsystems first stop
+//!    accepting work, then flush state (transfer sessions, clipboard history), then
+//!    say goodbye to peers and close their sessions;
+//! 3. tracked tasks are given whatever is left of the grace period to finish, and
+//!    are aborted once it runs out.
+//!
+//! The whole sequence is bounded by [`ShutdownConfig::grace_period`]. A second
+//! signal during shutdown skips the remaining work so a stuck hook can never keep
+//! the process alive.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::KizunaResult;
+
+/// Default bound on the whole shutdown sequence
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Default bound on a single hook
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why the process is shutting down
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShutdownReason {
+    /// An OS signal such as SIGINT or SIGTERM
+    Signal(String),
+    /// Requested by the application or a user command
+    Requested,
+    /// A fatal error elsewhere in the process
+    Fatal(String),
+}
+
+impl std::fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShutdownReason::Signal(signal) => write!(f, "received {}", signal),
+            ShutdownReason::Requested => write!(f, "shutdown requested"),
+            ShutdownReason::Fatal(error) => write!(f, "fatal error: {}", error),
+        }
+    }
+}
+
+/// Phase of the shutdown sequence a hook belongs to
+///
+/// Stages run in declaration order; hooks within a stage run concurrently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ShutdownStage {
+    /// Stop accepting new work: listeners, monitors, announcements
+    Quiesce,
+    /// Persist in-flight state: transfer sessions, queues, history
+    Flush,
+    /// Notify peers and close their sessions
+    Disconnect,
+}
+
+/// Work a subsystem performs when the process shuts down
+#[async_trait]
+pub trait ShutdownHook: Send + Sync {
+    /// Name used in the shutdown report
+    fn name(&self) -> &str;
+
+    /// Stage in which the hook runs
+    fn stage(&self) -> ShutdownStage;
+
+    /// Perform the shutdown work
+    ///
+    /// The coordinator abandons the hook once its timeout or the grace period runs
+    /// out, so implementations should write state before doing anything slow.
+    async fn shutdown(&self, reason: &ShutdownReason) -> KizunaResult<()>;
+}
+
+type HookFuture = Pin<Box<dyn Future<Output = KizunaResult<()>> + Send>>;
+
+/// Hook built from a closure by [`ShutdownCoordinator::on_shutdown`]
+struct FnHook<F> {
+    name: String,
+    stage: ShutdownStage,
+    run: F,
+}
+
+#[async_trait]
+impl<F> ShutdownHook for FnHook<F>
+where
+    F: Fn(ShutdownReason) -> HookFuture + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn stage(&self) -> ShutdownStage {
+        self.stage
+    }
+
+    async fn shutdown(&self, reason: &ShutdownReason) -> KizunaResult<()> {
+        (self.run)(reason.clone()).await
+    }
+}
+
+/// Shutdown timing limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShutdownConfig {
+    /// Time allowed for hooks and tracked tasks together before forced exit
+    pub grace_period: Duration,
+    /// Time allowed for any single hook
+    pub hook_timeout: Duration,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            grace_period: DEFAULT_GRACE_PERIOD,
+            hook_timeout: DEFAULT_HOOK_TIMEOUT,
+        }
+    }
+}
+
+/// What happened to one hook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HookOutcome {
+    /// Finished successfully
+    Completed,
+    /// Returned an error
+    Failed(String),
+    /// Abandoned after its timeout or the grace period ran out
+    TimedOut,
+    /// Not started because shutdown was forced
+    Skipped,
+}
+
+/// Result of running one hook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookReport {
+    pub name: String,
+    pub stage: ShutdownStage,
+    pub outcome: HookOutcome,
+    pub elapsed: Duration,
+}
+
+/// Summary of a completed shutdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShutdownReport {
+    pub reason: ShutdownReason,
+    pub hooks: Vec<HookReport>,
+    /// Tracked tasks still running when the grace period ended
+    pub aborted_tasks: Vec<String>,
+    /// Whether a second signal cut the sequence short
+    pub forced: bool,
+    pub elapsed: Duration,
+}
+
+impl ShutdownReport {
+    /// Whether every hook completed and every task finished on its own
+    pub fn is_clean(&self) -> bool {
+        !self.forced
+            && self.aborted_tasks.is_empty()
+            && self.hooks.iter().all(|hook| hook.outcome == HookOutcome::Completed)
+    }
+
+    /// Process exit code: 0 for a clean shutdown, 1 otherwise
+    pub fn exit_code(&self) -> i32 {
+        if self.is_clean() { 0 } else { 1 }
+    }
+}
+
+/// Broadcasts shutdown to tasks and runs subsystem hooks within a grace period
+///
+/// Share it behind an `Arc`; all methods take `&self`.
+pub struct ShutdownCoordinator {
+    config: ShutdownConfig,
+    token: CancellationToken,
+    forced: CancellationToken,
+    reason: StdMutex<Option<ShutdownReason>>,
+    hooks: StdMutex<Vec<Arc<dyn ShutdownHook>>>,
+    tasks: StdMutex<Vec<(String, JoinHandle<()>)>>,
+}
+
+impl ShutdownCoordinator {
+    /// Create a coordinator with the given limits
+    pub fn new(config: ShutdownConfig) -> Self {
+        Self {
+            config,
+            token: CancellationToken::new(),
+            forced: CancellationToken::new(),
+            reason: StdMutex::new(None),
+            hooks: StdMutex::new(Vec::new()),
+            tasks: StdMutex::new(Vec::new()),
+        }
+    }
+
+    /// Shutdown timing limits
+    pub fn config(&self) -> &ShutdownConfig {
+        &self.config
+    }
+
+    /// Token cancelled when shutdown begins
+    ///
+    /// Each call returns a child token, so a task can cancel its own token without
+    /// affecting the rest of the process.
+    pub fn token(&self) -> CancellationToken {
+        self.token.child_token()
+    }
+
+    /// Whether shutdown has begun
+    pub fn is_shutting_down(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Reason shutdown began, if it has
+    pub fn reason(&self) -> Option<ShutdownReason> {
+        self.reason.lock().unwrap().clone()
+    }
+
+    /// Register a hook to run during shutdown
+    pub fn register(&self, hook: Arc<dyn ShutdownHook>) {
+        self.hooks.lock().unwrap().push(hook);
+    }
+
+    /// Register a closure as a shutdown hook
+    pub fn on_shutdown<F, Fut>(&self, name: &str, stage: ShutdownStage, run: F)
+    where
+        F: Fn(ShutdownReason) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = KizunaResult<()>> + Send + 'static,
+    {
+        self.register(Arc::new(FnHook {
+            name: name.to_string(),
+            stage,
+            run: move |reason| -> HookFuture { Box::pin(run(reason)) },
+        }));
+    }
+
+    /// Spawn a task that shutdown waits for
+    ///
+    /// The task is handed a token cancelled when shutdown begins and should return
+    /// promptly once it fires; it is aborted if still running when the grace period
+    /// ends.
+    pub fn spawn<F, Fut>(&self, name: &str, task: F) -> CancellationToken
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let token = self.token();
+        let handle = tokio::spawn(task(token.clone()));
+
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|(_, handle)| !handle.is_finished());
+        tasks.push((name.to_string(), handle));
+        token
+    }
+
+    /// Begin shutdown
+    ///
+    /// Returns `false` if shutdown had already begun; the first reason is kept.
+    pub fn trigger(&self, reason: ShutdownReason) -> bool {
+        {
+            let mut current = self.reason.lock().unwrap();
+            if current.is_some() {
+                return false;
+            }
+            *current = Some(reason);
+        }
+        self.token.cancel();
+        true
+    }
+
+    /// Abandon the remaining shutdown work and abort tracked tasks immediately
+    pub fn force(&self) {
+        self.trigger(ShutdownReason::Requested);
+        self.forced.cancel();
+    }
+
+    /// Wait until shutdown begins
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+
+    /// Spawn a task that triggers shutdown on SIGINT or SIGTERM
+    ///
+    /// A second signal forces shutdown.
+    pub fn listen_for_signals(self: &Arc<Self>) -> JoinHandle<()> {
+        let coordinator = Arc::clone(self);
+        tokio::spawn(async move {
+            let Some(signal) = next_signal().await else {
+                return;
+            };
+            log::info!("Received {}, shutting down", signal);
+            coordinator.trigger(ShutdownReason::Signal(signal.to_string()));
+
+            if next_signal().await.is_some() {
+                log::warn!("Received second signal, forcing shutdown");
+                coordinator.force();
+            }
+        })
+    }
+
+    /// Begin shutdown and run it to completion
+    pub async fn shutdown(&self, reason: ShutdownReason) -> ShutdownReport {
+        self.trigger(reason);
+        self.run().await
+    }
+
+    /// Wait for shutdown to begin, then run hooks and drain tracked tasks
+    pub async fn run(&self) -> ShutdownReport {
+        self.cancelled().await;
+        let started = Instant::now();
+        let deadline = tokio::time::Instant::now() + self.config.grace_period;
+        let reason = self.reason().unwrap_or(ShutdownReason::Requested);
+
+        let mut hooks = self.hooks.lock().unwrap().clone();
+        hooks.sort_by_key(|hook| hook.stage());
+
+        let mut reports = Vec::with_capacity(hooks.len());
+        let mut remaining = hooks.as_slice();
+        while let Some(first) = remaining.first() {
+            let stage = first.stage();
+            let count = remaining.iter().take_while(|hook| hook.stage() == stage).count();
+            let (current, rest) = remaining.split_at(count);
+            remaining = rest;
+
+            let stage_reports =
+                futures::future::join_all(current.iter().map(|hook| self.run_hook(hook.as_ref(), &reason, deadline)))
+                    .await;
+            reports.extend(stage_reports);
+        }
+
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        let mut aborted_tasks = Vec::new();
+        for (name, mut handle) in tasks {
+            let finished = tokio::select! {
+                _ = &mut handle => true,
+                _ = tokio::time::sleep_until(deadline) => false,
+                _ = self.forced.cancelled() => false,
+            };
+            if !finished {
+                handle.abort();
+                aborted_tasks.push(name);
+            }
+        }
+
+        let report = ShutdownReport {
+            reason,
+            hooks: reports,
+            aborted_tasks,
+            forced: self.forced.is_cancelled(),
+            elapsed: started.elapsed(),
+        };
+        for hook in report.hooks.iter().filter(|hook| hook.outcome != HookOutcome::Completed) {
+            log::warn!("Shutdown hook {} did not complete: {:?}", hook.name, hook.outcome);
+        }
+        if !report.aborted_tasks.is_empty() {
+            log::warn!("Aborted tasks still running after grace period: {:?}", report.aborted_tasks);
+        }
+        report
+    }
+
+    async fn run_hook(
+        &self,
+        hook: &dyn ShutdownHook,
+        reason: &ShutdownReason,
+        deadline: tokio::time::Instant,
+    ) -> HookReport {
+        let started = Instant::now();
+        let hook_deadline = deadline.min(tokio::time::Instant::now() + self.config.hook_timeout);
+
+        let outcome = if self.forced.is_cancelled() {
+            HookOutcome::Skipped
+        } else {
+            tokio::select! {
+                result = hook.shutdown(reason) => match result {
+                    Ok(()) => HookOutcome::Completed,
+                    Err(e) => HookOutcome::Failed(e.to_string()),
+                },
+                _ = tokio::time::sleep_until(hook_deadline) => HookOutcome::TimedOut,
+                _ = self.forced.cancelled() => HookOutcome::TimedOut,
+            }
+        };
+
+        HookReport {
+            name: hook.name().to_string(),
+            stage: hook.stage(),
+            outcome,
+            elapsed: started.elapsed(),
+        }
+    }
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self::new(ShutdownConfig::default())
+    }
+}
+
+impl std::fmt::Debug for ShutdownCoordinator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownCoordinator")
+            .field("config", &self.config)
+            .field("reason", &self.reason())
+            .field("hooks", &self.hooks.lock().unwrap().len())
+            .field("tasks", &self.tasks.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// Wait for the next termination signal and return its name
+///
+/// Returns `None` if signal handlers cannot be installed.
+#[cfg(unix)]
+async fn next_signal() -> Option<&'static str> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut interrupt = signal(SignalKind::interrupt()).ok()?;
+    let mut terminate = signal(SignalKind::terminate()).ok()?;
+    tokio::select! {
+        _ = interrupt.recv() => Some("SIGINT"),
+        _ = terminate.recv() => Some("SIGTERM"),
+    }
+}
+
+#[cfg(not(unix))]
+async fn next_signal() -> Option<&'static str> {
+    tokio::signal::ctrl_c().await.ok().map(|_| "Ctrl-C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::KizunaError;
+
+    fn coordinator(grace_period: Duration, hook_timeout: Duration) -> ShutdownCoordinator {
+        ShutdownCoordinator::new(ShutdownConfig { grace_period, hook_timeout })
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_in_stage_order() {
+        let coordinator = coordinator(Duration::from_secs(1), Duration::from_secs(1));
+        let order = Arc::new(StdMutex::new(Vec::new()));
+
+        for (name, stage) in [
+            ("sessions", ShutdownStage::Disconnect),
+            ("transfers", ShutdownStage::Flush),
+            ("listener", ShutdownStage::Quiesce),
+        ] {
+            let order = Arc::clone(&order);
+            coordinator.on_shutdown(name, stage, move |_| {
+                let order = Arc::clone(&order);
+                async move {
+                    order.lock().unwrap().push(name);
+                    Ok(())
+                }
+            });
+        }
+
+        let report = coordinator.shutdown(ShutdownReason::Requested).await;
+        assert!(report.is_clean());
+        assert_eq!(*order.lock().unwrap(), vec!["listener", "transfers", "sessions"]);
+    }
+
+    #[tokio::test]
+    async fn test_slow_and_failing_hooks_reported() {
+        let coordinator = coordinator(Duration::from_secs(1), Duration::from_millis(20));
+        coordinator.on_shutdown("stuck", ShutdownStage::Flush, |_| async {
+            std::future::pending::<()>().await;
+            Ok(())
+        });
+        coordinator.on_shutdown("broken", ShutdownStage::Flush, |_| async {
+            Err(KizunaError::other("disk full"))
+        });
+
+        let report = coordinator.shutdown(ShutdownReason::Signal("SIGTERM".to_string())).await;
+        assert!(!report.is_clean());
+        assert_eq!(report.exit_code(), 1);
+        assert_eq!(report.hooks[0].outcome, HookOutcome::TimedOut);
+        assert!(matches!(report.hooks[1].outcome, HookOutcome::Failed(_)));
+        assert!(report.elapsed < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_tasks_cancelled_and_stragglers_aborted() {
+        let coordinator = coordinator(Duration::from_millis(50), Duration::from_millis(50));
+
+        coordinator.spawn("cooperative", |token| async move {
+            token.cancelled().await;
+        });
+        coordinator.spawn("stubborn", |_| async move {
+            std::future::pending::<()>().await;
+        });
+
+        let report = coordinator.shutdown(ShutdownReason::Requested).await;
+        assert_eq!(report.aborted_tasks, vec!["stubborn".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_first_reason_wins() {
+        let coordinator = ShutdownCoordinator::default();
+        let token = coordinator.token();
+
+        assert!(coordinator.trigger(ShutdownReason::Signal("SIGINT".to_string())));
+        assert!(!coordinator.trigger(ShutdownReason::Requested));
+        assert!(token.is_cancelled());
+        assert_eq!(coordinator.reason(), Some(ShutdownReason::Signal("SIGINT".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_force_skips_remaining_work() {
+        let coordinator = Arc::new(coordinator(Duration::from_secs(30), Duration::from_secs(30)));
+        coordinator.on_shutdown("stuck", ShutdownStage::Flush, |_| async {
+            std::future::pending::<()>().await;
+            Ok(())
+        });
+
+        let forcer = Arc::clone(&coordinator);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            forcer.force();
+        });
+
+        let report = tokio::time::timeout(Duration::from_secs(1), coordinator.shutdown(ShutdownReason::Requested))
+            .await
+            .unwrap();
+        assert!(report.forced);
+        assert_eq!(report.hooks[0].outcome, HookOutcome::TimedOut);
+    }
+}
@@ -7,19 +7,22 @@
 
 use async_trait::async_trait;
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::{RwLock, mpsc, watch};
 use uuid::Uuid;
 
 use super::{
     StreamError, StreamResult,
-    StreamSession, StreamConfig, ScreenConfig, StreamQuality,
+    StreamSession, StreamConfig, ScreenConfig, ScreenConfigUpdate, StreamQuality,
+    ScreenCaptureControl,
     SessionId, ViewerId, PeerId,
     CameraDevice, CaptureConfig, CaptureCapabilities,
     VideoStream, StreamConnection, StreamStats,
     ViewerPermissions, ViewerStatus,
     RecordingSession, RecordingConfig, RecordingFile, RecordingStatus,
-    StreamState, StreamType,
+    StreamState, StreamType, VideoFrame,
 };
+use super::network::{StreamNotice, StreamOffer};
+use super::snapshot::preview_thumbnail;
 
 /// Stream event types for event-driven API
 /// 
@@ -103,6 +106,23 @@ pub enum StreamEvent {
         file_path: String,
     },
     
+    /// Monitor, region or cursor capture of a screen share changed
+    ScreenConfigChanged {
+        session_id: SessionId,
+        config: ScreenConfig,
+    },
+    
+    /// A viewer or the application asked for an immediate keyframe
+    KeyframeRequested {
+        session_id: SessionId,
+    },
+    
+    /// Notice to forward to every viewer of the session, e.g. a paused card
+    ViewerNotice {
+        session_id: SessionId,
+        notice: StreamNotice,
+    },
+    
     /// Network condition changed
     NetworkConditionChanged {
         session_id: SessionId,
@@ -190,6 +210,17 @@ pub trait Streaming: Send + Sync {
     /// Enable or disable automatic quality adaptation
     async fn set_auto_quality(&self, session_id: SessionId, enabled: bool) -> StreamResult<()>;
     
+    /// Ask the encoder for an immediate keyframe, e.g. after packet loss
+    async fn request_keyframe(&self, session_id: SessionId) -> StreamResult<()>;
+    
+    /// Switch monitors, move the region or toggle the cursor of an active
+    /// screen share without restarting it
+    async fn update_screen_config(
+        &self,
+        session_id: SessionId,
+        update: ScreenConfigUpdate,
+    ) -> StreamResult<ScreenConfig>;
+    
     // === Device Management ===
     
     /// List all available camera devices
@@ -250,6 +281,9 @@ pub trait Streaming: Send + Sync {
     async fn unregister_event_handler(&self, handler: Arc<dyn StreamEventHandler>) -> StreamResult<()>;
 }
 
+/// Default number of streams that may run at once
+pub const DEFAULT_MAX_CONCURRENT_STREAMS: usize = 4;
+
 /// Concrete implementation of the unified streaming API
 /// 
 /// This implementation integrates all streaming components (capture, encoding,
@@ -260,9 +294,18 @@ pub struct StreamingApi {
     /// Active stream sessions
     sessions: Arc<RwLock<std::collections::HashMap<SessionId, StreamSession>>>,
     
+    /// Live capture controls for screen sessions
+    screen_controls: Arc<RwLock<std::collections::HashMap<SessionId, ScreenCaptureControl>>>,
+    
+    /// Paused flag followed by each session's frame loop
+    pause_signals: Arc<RwLock<std::collections::HashMap<SessionId, watch::Sender<bool>>>>,
+    
     /// Event handlers
     event_handlers: Arc<RwLock<Vec<Arc<dyn StreamEventHandler>>>>,
     
+    /// Maximum number of sessions running at once
+    max_concurrent_streams: usize,
+    
     /// Event channel for internal event distribution
     event_tx: mpsc::UnboundedSender<StreamEvent>,
     event_rx: Arc<RwLock<mpsc::UnboundedReceiver<StreamEvent>>>,
@@ -275,7 +318,10 @@ impl StreamingApi {
         
         let api = Self {
             sessions: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            screen_controls: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            pause_signals: Arc::new(RwLock::new(std::collections::HashMap::new())),
             event_handlers: Arc::new(RwLock::new(Vec::new())),
+            max_concurrent_streams: DEFAULT_MAX_CONCURRENT_STREAMS,
             event_tx,
             event_rx: Arc::new(RwLock::new(event_rx)),
         };
@@ -286,6 +332,24 @@ impl StreamingApi {
         api
     }
     
+    /// Set how many streams may run at once
+    pub fn with_max_concurrent_streams(mut self, max: usize) -> Self {
+        self.max_concurrent_streams = max.max(1);
+        self
+    }
+    
+    /// Refuse a new session once the concurrent stream limit is reached
+    async fn ensure_stream_capacity(&self) -> StreamResult<()> {
+        let running = self.sessions.read().await.len();
+        if running >= self.max_concurrent_streams {
+            return Err(StreamError::resource(format!(
+                "Maximum concurrent streams ({}) reached",
+                self.max_concurrent_streams
+            )));
+        }
+        Ok(())
+    }
+    
     /// Start the event processor task
     fn start_event_processor(&self) {
         let event_rx = Arc::clone(&self.event_rx);
@@ -310,6 +374,81 @@ impl StreamingApi {
         });
     }
     
+    /// Follow configuration changes of a screen session in its capture loop
+    pub async fn subscribe_screen_config(&self, session_id: SessionId) -> Option<watch::Receiver<ScreenConfig>> {
+        self.screen_controls
+            .read()
+            .await
+            .get(&session_id)
+            .map(|control| control.subscribe())
+    }
+    
+    /// Follow the paused flag of a session in its frame loop
+    ///
+    /// While the flag is `true` the loop should stop sending frames but keep
+    /// its capture, encoder and viewer connections, so resuming is immediate.
+    pub async fn subscribe_paused(&self, session_id: SessionId) -> Option<watch::Receiver<bool>> {
+        self.pause_signals
+            .read()
+            .await
+            .get(&session_id)
+            .map(|signal| signal.subscribe())
+    }
+    
+    /// Pause a session, showing viewers `message` instead of the default paused card
+    pub async fn pause_stream_with_message(
+        &self,
+        session_id: SessionId,
+        message: Option<String>,
+    ) -> StreamResult<()> {
+        let state = self.get_stream(session_id).await?.state;
+        if state != StreamState::Active {
+            return Err(StreamError::invalid_state(format!("Cannot pause a {:?} stream", state)));
+        }
+        
+        self.set_paused(session_id, true).await;
+        self.update_session_state(session_id, StreamState::Paused).await?;
+        self.emit_event(StreamEvent::ViewerNotice {
+            session_id,
+            notice: StreamNotice::Paused { session_id, message },
+        }).await;
+        Ok(())
+    }
+    
+    /// Offer inviting a peer to watch a session
+    ///
+    /// `frame` is the current picture, if the caller has one; it goes with
+    /// the offer as a small snapshot. Send the notice to the invited peer.
+    pub async fn offer_stream(
+        &self,
+        session_id: SessionId,
+        frame: Option<&VideoFrame>,
+    ) -> StreamResult<StreamNotice> {
+        let session = self.get_stream(session_id).await?;
+        let snapshot = frame.and_then(|frame| preview_thumbnail(frame).ok());
+        Ok(StreamNotice::Offered {
+            session_id,
+            offer: StreamOffer {
+                stream_type: session.stream_type,
+                resolution: session.quality.resolution,
+                snapshot,
+            },
+        })
+    }
+    
+    /// Register a session's paused flag
+    async fn track_pause(&self, session_id: SessionId) {
+        let (signal, _) = watch::channel(false);
+        self.pause_signals.write().await.insert(session_id, signal);
+    }
+    
+    /// Flip a session's paused flag for its frame loop
+    async fn set_paused(&self, session_id: SessionId, paused: bool) {
+        if let Some(signal) = self.pause_signals.read().await.get(&session_id) {
+            signal.send_replace(paused);
+        }
+    }
+    
     /// Emit an event to all registered handlers
     async fn emit_event(&self, event: StreamEvent) {
         let _ = self.event_tx.send(event);
@@ -351,6 +490,8 @@ impl Default for StreamingApi {
 #[async_trait]
 impl Streaming for StreamingApi {
     async fn start_camera_stream(&self, config: StreamConfig) -> StreamResult<StreamSession> {
+        self.ensure_stream_capacity().await?;
+        
         // Create new session
         let session_id = Uuid::new_v4();
         let session = StreamSession {
@@ -371,6 +512,7 @@ impl Streaming for StreamingApi {
         
         // Store session
         self.sessions.write().await.insert(session_id, session.clone());
+        self.track_pause(session_id).await;
         
         // Emit event
         self.emit_event(StreamEvent::SessionStarted {
@@ -385,6 +527,8 @@ impl Streaming for StreamingApi {
     }
     
     async fn start_screen_stream(&self, config: ScreenConfig) -> StreamResult<StreamSession> {
+        self.ensure_stream_capacity().await?;
+        
         // Create new session
         let session_id = Uuid::new_v4();
         let session = StreamSession {
@@ -400,6 +544,11 @@ impl Streaming for StreamingApi {
         
         // Store session
         self.sessions.write().await.insert(session_id, session.clone());
+        self.screen_controls
+            .write()
+            .await
+            .insert(session_id, ScreenCaptureControl::new(config));
+        self.track_pause(session_id).await;
         
         // Emit event
         self.emit_event(StreamEvent::SessionStarted {
@@ -419,6 +568,8 @@ impl Streaming for StreamingApi {
         
         // Remove session
         self.sessions.write().await.remove(&session_id);
+        self.screen_controls.write().await.remove(&session_id);
+        self.pause_signals.write().await.remove(&session_id);
         
         // Emit event
         self.emit_event(StreamEvent::SessionStopped {
@@ -430,11 +581,26 @@ impl Streaming for StreamingApi {
     }
     
     async fn pause_stream(&self, session_id: SessionId) -> StreamResult<()> {
-        self.update_session_state(session_id, StreamState::Paused).await
+        self.pause_stream_with_message(session_id, None).await
     }
     
     async fn resume_stream(&self, session_id: SessionId) -> StreamResult<()> {
-        self.update_session_state(session_id, StreamState::Active).await
+        let state = self.get_stream(session_id).await?.state;
+        if state != StreamState::Paused {
+            return Err(StreamError::invalid_state(format!("Cannot resume a {:?} stream", state)));
+        }
+        
+        self.set_paused(session_id, false).await;
+        self.update_session_state(session_id, StreamState::Active).await?;
+        self.emit_event(StreamEvent::ViewerNotice {
+            session_id,
+            notice: StreamNotice::Resumed { session_id },
+        }).await;
+        
+        // The encoder kept its state, but viewers need a full picture to
+        // replace the paused card
+        self.emit_event(StreamEvent::KeyframeRequested { session_id }).await;
+        Ok(())
     }
     
     async fn get_stream(&self, session_id: SessionId) -> StreamResult<StreamSession> {
@@ -486,6 +652,78 @@ impl Streaming for StreamingApi {
         Ok(())
     }
     
+    async fn update_screen_config(
+        &self,
+        session_id: SessionId,
+        update: ScreenConfigUpdate,
+    ) -> StreamResult<ScreenConfig> {
+        let (change, config) = {
+            let mut controls = self.screen_controls.write().await;
+            let control = controls
+                .get_mut(&session_id)
+                .ok_or_else(|| StreamError::invalid_state("Not an active screen share"))?;
+            let change = control.update(update)?;
+            (change, control.config().clone())
+        };
+        
+        if change.is_empty() {
+            return Ok(config);
+        }
+        
+        let old_quality = {
+            let mut sessions = self.sessions.write().await;
+            let session = sessions
+                .get_mut(&session_id)
+                .ok_or_else(|| StreamError::session_not_found(session_id))?;
+            session.source = super::StreamSource::Screen(config.region);
+            
+            let old_quality = session.quality.clone();
+            if change.resolution_changed {
+                session.quality.resolution = super::Resolution {
+                    width: config.region.width,
+                    height: config.region.height,
+                };
+            }
+            old_quality
+        };
+        
+        self.emit_event(StreamEvent::ScreenConfigChanged {
+            session_id,
+            config: config.clone(),
+        }).await;
+        
+        // Viewers keep their stream; a new frame size only needs the encoder
+        // to restart from a keyframe
+        if change.resolution_changed {
+            let mut new_quality = old_quality.clone();
+            new_quality.resolution = super::Resolution {
+                width: config.region.width,
+                height: config.region.height,
+            };
+            self.emit_event(StreamEvent::QualityChanged {
+                session_id,
+                old_quality,
+                new_quality,
+                reason: QualityChangeReason::Automatic,
+            }).await;
+        }
+        if change.monitor_changed || change.resolution_changed {
+            self.emit_event(StreamEvent::KeyframeRequested { session_id }).await;
+        }
+        
+        Ok(config)
+    }
+    
+    async fn request_keyframe(&self, session_id: SessionId) -> StreamResult<()> {
+        if !self.sessions.read().await.contains_key(&session_id) {
+            return Err(StreamError::session_not_found(session_id));
+        }
+        
+        // The session's encoder loop forces the keyframe
+        self.emit_event(StreamEvent::KeyframeRequested { session_id }).await;
+        Ok(())
+    }
+    
     async fn list_cameras(&self) -> StreamResult<Vec<CameraDevice>> {
         // Implementation would use platform-specific capture engine
         Ok(vec![])
@@ -697,6 +935,7 @@ mod tests {
             capture_audio: false,
             monitor_index: None,
             quality: StreamQuality::default(),
+            privacy: super::super::PrivacyMaskConfig::default(),
         };
         
         let session = api.start_screen_stream(config).await.unwrap();
@@ -735,6 +974,48 @@ mod tests {
         assert_eq!(session.state, StreamState::Active);
     }
     
+    #[tokio::test]
+    async fn test_pause_keeps_viewers_and_notifies() {
+        let api = StreamingApi::new();
+        let handler = Arc::new(TestEventHandler::new());
+        api.register_event_handler(handler.clone()).await.unwrap();
+        
+        let session = api.start_camera_stream(StreamConfig::default()).await.unwrap();
+        let session_id = session.session_id;
+        let viewer_id = api.add_viewer(session_id, "peer123".to_string(), ViewerPermissions::default())
+            .await
+            .unwrap();
+        let paused = api.subscribe_paused(session_id).await.unwrap();
+        
+        api.pause_stream_with_message(session_id, Some("Back soon".to_string())).await.unwrap();
+        assert!(*paused.borrow());
+        assert!(api.pause_stream(session_id).await.is_err());
+        assert_eq!(api.get_stream(session_id).await.unwrap().viewers, vec![viewer_id]);
+        
+        api.resume_stream(session_id).await.unwrap();
+        assert!(!*paused.borrow());
+        assert!(api.resume_stream(session_id).await.is_err());
+        
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let events = handler.get_events().await;
+        let card = events.iter().find_map(|e| match e {
+            StreamEvent::ViewerNotice { notice, .. } => notice.card_text().map(str::to_string),
+            _ => None,
+        });
+        assert_eq!(card.as_deref(), Some("Back soon"));
+        assert!(events.iter().any(|e| matches!(e, StreamEvent::KeyframeRequested { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_stream_limit() {
+        let api = StreamingApi::new().with_max_concurrent_streams(1);
+        let session = api.start_camera_stream(StreamConfig::default()).await.unwrap();
+        assert!(api.start_camera_stream(StreamConfig::default()).await.is_err());
+        
+        api.stop_stream(session.session_id).await.unwrap();
+        assert!(api.start_camera_stream(StreamConfig::default()).await.is_ok());
+    }
+    
     #[tokio::test]
     async fn test_adjust_quality() {
         let api = StreamingApi::new();
@@ -4,6 +4,7 @@
 // with platform-specific implementations.
 
 pub mod platform;
+pub mod privacy;
 pub mod screen;
 
 use async_trait::async_trait;
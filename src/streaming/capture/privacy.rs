@@ -0,0 +1,422 @@
+// Privacy masking for screen shares
+//
+// Blacks out, pixelates or blurs user-defined rectangles and the windows of
+// chosen applications in every captured frame, before the frame reaches the
+// encoder, so password managers and chat apps never leave the machine.
+// Masking fails closed: if window positions cannot be determined the whole
+// frame is blacked out rather than sent unmasked.
+//
+// Requirements: 3.1, 10.1
+
+use std::sync::Arc;
+
+use crate::streaming::{
+    MaskStyle, MaskTarget, PixelFormat, PrivacyMaskConfig, ScreenRegion, StreamError, StreamResult,
+    VideoFrame,
+};
+
+/// Smallest blur radius applied; weaker blurs leave text readable
+pub const MIN_BLUR_RADIUS: u32 = 8;
+
+/// Smallest pixelation block applied
+pub const MIN_PIXELATE_BLOCK: u32 = 8;
+
+/// Finds on-screen windows of an application
+///
+/// Implemented per platform on top of the window system (e.g.
+/// `EnumWindows` on Windows, `CGWindowListCopyWindowInfo` on macOS).
+pub trait WindowLocator: Send + Sync {
+    /// Bounds of every visible window whose application name matches `app`
+    /// case-insensitively, in the coordinates of the captured monitor
+    fn find_windows(&self, monitor_index: Option<u32>, app: &str) -> StreamResult<Vec<ScreenRegion>>;
+}
+
+/// Outcome of masking one frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MaskReport {
+    /// Rectangles that overlapped the frame and were masked
+    pub regions_masked: usize,
+    /// Window positions were unknown, so the whole frame was blacked out
+    pub fail_closed: bool,
+}
+
+/// Applies a session's privacy masks to captured frames
+#[derive(Clone)]
+pub struct PrivacyMasker {
+    config: PrivacyMaskConfig,
+    locator: Option<Arc<dyn WindowLocator>>,
+}
+
+impl PrivacyMasker {
+    /// Create a masker for a session's mask configuration
+    pub fn new(config: PrivacyMaskConfig) -> Self {
+        Self { config, locator: None }
+    }
+
+    /// Locate application windows with this locator
+    ///
+    /// Without one, application masks black out the whole frame.
+    pub fn with_locator(mut self, locator: Arc<dyn WindowLocator>) -> Self {
+        self.locator = Some(locator);
+        self
+    }
+
+    /// Replace the mask configuration, e.g. after a live update
+    pub fn set_config(&mut self, config: PrivacyMaskConfig) {
+        self.config = config;
+    }
+
+    /// Current mask configuration
+    pub fn config(&self) -> &PrivacyMaskConfig {
+        &self.config
+    }
+
+    /// Mask a frame captured from `region` of monitor `monitor_index`
+    ///
+    /// Mask rectangles are translated from monitor coordinates into the
+    /// frame, so masks stay put when the capture region moves.
+    pub fn apply(
+        &self,
+        frame: &mut VideoFrame,
+        monitor_index: Option<u32>,
+        region: &ScreenRegion,
+    ) -> StreamResult<MaskReport> {
+        if !self.config.is_enabled() {
+            return Ok(MaskReport::default());
+        }
+
+        let planes = planes(frame)?;
+        let mut report = MaskReport::default();
+
+        for mask in &self.config.masks {
+            let bounds = match &mask.target {
+                MaskTarget::Region(bounds) => vec![*bounds],
+                MaskTarget::Application { name } => match self.locate(monitor_index, name) {
+                    Ok(bounds) => bounds,
+                    Err(e) => {
+                        log::warn!("Could not locate {} windows, blanking frame: {}", name, e);
+                        let full = FrameRect { x: 0, y: 0, width: frame.width, height: frame.height };
+                        for plane in &planes {
+                            plane.apply(&mut frame.data, full, MaskStyle::BlackOut);
+                        }
+                        return Ok(MaskReport { regions_masked: 1, fail_closed: true });
+                    }
+                },
+            };
+
+            for bounds in bounds {
+                let Some(rect) = to_frame_rect(&bounds, region, frame.width, frame.height) else {
+                    continue;
+                };
+                for plane in &planes {
+                    plane.apply(&mut frame.data, rect, mask.style);
+                }
+                report.regions_masked += 1;
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn locate(&self, monitor_index: Option<u32>, app: &str) -> StreamResult<Vec<ScreenRegion>> {
+        self.locator
+            .as_ref()
+            .ok_or_else(|| StreamError::unsupported("No window locator on this platform"))?
+            .find_windows(monitor_index, app)
+    }
+}
+
+/// Rectangle in frame pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameRect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// Translate monitor coordinates into the frame and clip to it
+///
+/// The frame may be scaled relative to the capture region.
+fn to_frame_rect(bounds: &ScreenRegion, region: &ScreenRegion, width: u32, height: u32) -> Option<FrameRect> {
+    if region.width == 0 || region.height == 0 {
+        return None;
+    }
+
+    let left = bounds.x.max(region.x);
+    let top = bounds.y.max(region.y);
+    let right = (bounds.x + bounds.width).min(region.x + region.width);
+    let bottom = (bounds.y + bounds.height).min(region.y + region.height);
+    if left >= right || top >= bottom {
+        return None;
+    }
+
+    // Round outwards so scaling never uncovers an edge of the window
+    let scale = |pos: u32, origin: u32, from: u32, to: u32, up: bool| {
+        let scaled = (pos - origin) as u64 * to as u64;
+        let value = if up { scaled.div_ceil(from as u64) } else { scaled / from as u64 };
+        value.min(to as u64) as u32
+    };
+    let x0 = scale(left, region.x, region.width, width, false);
+    let y0 = scale(top, region.y, region.height, height, false);
+    let x1 = scale(right, region.x, region.width, width, true);
+    let y1 = scale(bottom, region.y, region.height, height, true);
+
+    Some(FrameRect { x: x0, y: y0, width: x1 - x0, height: y1 - y0 })
+}
+
+/// One plane of a raw frame
+#[derive(Debug, Clone, Copy)]
+struct Plane {
+    offset: usize,
+    width: u32,
+    height: u32,
+    /// Bytes per pixel
+    bpp: usize,
+    /// Horizontal and vertical subsampling relative to the frame
+    subsample: u32,
+    /// Value of black for each byte of a pixel
+    black: [u8; 4],
+}
+
+/// Describe the planes of a frame, checking its buffer is large enough
+fn planes(frame: &VideoFrame) -> StreamResult<Vec<Plane>> {
+    let (w, h) = (frame.width, frame.height);
+    let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+    let luma = w as usize * h as usize;
+    let chroma = cw as usize * ch as usize;
+
+    let planes = match frame.format {
+        PixelFormat::RGB24 => vec![Plane { offset: 0, width: w, height: h, bpp: 3, subsample: 1, black: [0, 0, 0, 0] }],
+        PixelFormat::RGBA32 => vec![Plane { offset: 0, width: w, height: h, bpp: 4, subsample: 1, black: [0, 0, 0, 255] }],
+        PixelFormat::YUV420 => vec![
+            Plane { offset: 0, width: w, height: h, bpp: 1, subsample: 1, black: [16, 0, 0, 0] },
+            Plane { offset: luma, width: cw, height: ch, bpp: 1, subsample: 2, black: [128, 0, 0, 0] },
+            Plane { offset: luma + chroma, width: cw, height: ch, bpp: 1, subsample: 2, black: [128, 0, 0, 0] },
+        ],
+        PixelFormat::NV12 => vec![
+            Plane { offset: 0, width: w, height: h, bpp: 1, subsample: 1, black: [16, 0, 0, 0] },
+            Plane { offset: luma, width: cw, height: ch, bpp: 2, subsample: 2, black: [128, 128, 0, 0] },
+        ],
+        PixelFormat::MJPEG => {
+            return Err(StreamError::unsupported(
+                "Privacy masks need raw frames; capture uncompressed video",
+            ));
+        }
+    };
+
+    let required = planes
+        .iter()
+        .map(|p| p.offset + p.width as usize * p.height as usize * p.bpp)
+        .max()
+        .unwrap_or(0);
+    if frame.data.len() < required {
+        return Err(StreamError::capture(format!(
+            "Frame buffer too small: {} bytes for {}x{} {:?}",
+            frame.data.len(),
+            w,
+            h,
+            frame.format
+        )));
+    }
+
+    Ok(planes)
+}
+
+impl Plane {
+    fn apply(&self, data: &mut [u8], rect: FrameRect, style: MaskStyle) {
+        // Scale to the plane, rounding outwards
+        let x0 = rect.x / self.subsample;
+        let y0 = rect.y / self.subsample;
+        let x1 = (rect.x + rect.width).div_ceil(self.subsample).min(self.width);
+        let y1 = (rect.y + rect.height).div_ceil(self.subsample).min(self.height);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+
+        match style {
+            MaskStyle::BlackOut => self.fill(data, x0, y0, x1, y1, &self.black),
+            MaskStyle::Pixelate { block_size } => {
+                let block = (block_size.max(MIN_PIXELATE_BLOCK) / self.subsample).max(1);
+                self.pixelate(data, x0, y0, x1, y1, block);
+            }
+            MaskStyle::Blur { radius } => {
+                let radius = (radius.max(MIN_BLUR_RADIUS) / self.subsample).max(1);
+                self.blur(data, x0, y0, x1, y1, radius);
+            }
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        self.offset + (y as usize * self.width as usize + x as usize) * self.bpp
+    }
+
+    fn fill(&self, data: &mut [u8], x0: u32, y0: u32, x1: u32, y1: u32, value: &[u8; 4]) {
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let i = self.index(x, y);
+                data[i..i + self.bpp].copy_from_slice(&value[..self.bpp]);
+            }
+        }
+    }
+
+    fn pixelate(&self, data: &mut [u8], x0: u32, y0: u32, x1: u32, y1: u32, block: u32) {
+        for by in (y0..y1).step_by(block as usize) {
+            for bx in (x0..x1).step_by(block as usize) {
+                let (ex, ey) = ((bx + block).min(x1), (by + block).min(y1));
+
+                let mut sums = [0u64; 4];
+                for y in by..ey {
+                    for x in bx..ex {
+                        let i = self.index(x, y);
+                        for (c, sum) in sums.iter_mut().enumerate().take(self.bpp) {
+                            *sum += data[i + c] as u64;
+                        }
+                    }
+                }
+
+                let count = ((ex - bx) * (ey - by)) as u64;
+                let mut average = [0u8; 4];
+                for c in 0..self.bpp {
+                    average[c] = (sums[c] / count) as u8;
+                }
+                self.fill(data, bx, by, ex, ey, &average);
+            }
+        }
+    }
+
+    /// Separable box blur over the rectangle, sampling only inside it so no
+    /// masked content is mixed with its surroundings
+    fn blur(&self, data: &mut [u8], x0: u32, y0: u32, x1: u32, y1: u32, radius: u32) {
+        let (w, h) = ((x1 - x0) as usize, (y1 - y0) as usize);
+        let r = radius as usize;
+        let mut buffer = vec![0u8; w * h * self.bpp];
+        let mut line = vec![0u8; w.max(h) * self.bpp];
+
+        for y in 0..h {
+            for x in 0..w {
+                let i = self.index(x0 + x as u32, y0 + y as u32);
+                let o = (y * w + x) * self.bpp;
+                buffer[o..o + self.bpp].copy_from_slice(&data[i..i + self.bpp]);
+            }
+        }
+
+        // Horizontal pass
+        for y in 0..h {
+            for x in 0..w {
+                let (from, to) = (x.saturating_sub(r), (x + r + 1).min(w));
+                for c in 0..self.bpp {
+                    let sum: u32 = (from..to).map(|k| buffer[(y * w + k) * self.bpp + c] as u32).sum();
+                    line[x * self.bpp + c] = (sum / (to - from) as u32) as u8;
+                }
+            }
+            buffer[y * w * self.bpp..(y + 1) * w * self.bpp].copy_from_slice(&line[..w * self.bpp]);
+        }
+
+        // Vertical pass
+        for x in 0..w {
+            for y in 0..h {
+                let (from, to) = (y.saturating_sub(r), (y + r + 1).min(h));
+                for c in 0..self.bpp {
+                    let sum: u32 = (from..to).map(|k| buffer[(k * w + x) * self.bpp + c] as u32).sum();
+                    line[y * self.bpp + c] = (sum / (to - from) as u32) as u8;
+                }
+            }
+            for y in 0..h {
+                let o = (y * w + x) * self.bpp;
+                buffer[o..o + self.bpp].copy_from_slice(&line[y * self.bpp..(y + 1) * self.bpp]);
+            }
+        }
+
+        for y in 0..h {
+            for x in 0..w {
+                let i = self.index(x0 + x as u32, y0 + y as u32);
+                let o = (y * w + x) * self.bpp;
+                data[i..i + self.bpp].copy_from_slice(&buffer[o..o + self.bpp]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn frame(format: PixelFormat, width: u32, height: u32, len: usize) -> VideoFrame {
+        VideoFrame {
+            data: (0..len).map(|i| (i % 251) as u8 + 1).collect(),
+            width,
+            height,
+            format,
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    struct FixedLocator(Vec<ScreenRegion>);
+
+    impl WindowLocator for FixedLocator {
+        fn find_windows(&self, _monitor_index: Option<u32>, app: &str) -> StreamResult<Vec<ScreenRegion>> {
+            Ok(if app.eq_ignore_ascii_case("signal") { self.0.clone() } else { Vec::new() })
+        }
+    }
+
+    #[test]
+    fn test_masks_regions_and_windows() {
+        // Capture region starts at (100, 100) on the monitor
+        let region = ScreenRegion { x: 100, y: 100, width: 16, height: 16 };
+        let config = PrivacyMaskConfig::default()
+            .with_mask(MaskTarget::Region(ScreenRegion { x: 90, y: 90, width: 14, height: 14 }), MaskStyle::BlackOut)
+            .with_mask(MaskTarget::Application { name: "Signal".to_string() }, MaskStyle::BlackOut)
+            .with_mask(MaskTarget::Region(ScreenRegion { x: 0, y: 0, width: 10, height: 10 }), MaskStyle::BlackOut);
+        let window = ScreenRegion { x: 112, y: 112, width: 50, height: 50 };
+        let masker = PrivacyMasker::new(config.clone()).with_locator(Arc::new(FixedLocator(vec![window])));
+
+        let mut rgb = frame(PixelFormat::RGB24, 16, 16, 16 * 16 * 3);
+        let report = masker.apply(&mut rgb, Some(0), &region).unwrap();
+        // The region outside the capture area is skipped
+        assert_eq!(report, MaskReport { regions_masked: 2, fail_closed: false });
+
+        let pixel = |f: &VideoFrame, x: usize, y: usize| f.data[(y * 16 + x) * 3];
+        assert_eq!(pixel(&rgb, 0, 0), 0);
+        assert_eq!(pixel(&rgb, 3, 3), 0);
+        assert_ne!(pixel(&rgb, 4, 4), 0);
+        assert_eq!(pixel(&rgb, 15, 15), 0);
+
+        // Planar YUV masks luma and the matching chroma samples
+        let mut yuv = frame(PixelFormat::YUV420, 16, 16, 16 * 16 + 2 * 8 * 8);
+        masker.apply(&mut yuv, Some(0), &region).unwrap();
+        assert_eq!(yuv.data[0], 16);
+        assert_eq!(yuv.data[16 * 16], 128);
+        assert_ne!(yuv.data[5 * 16 + 5], 16);
+
+        // Without a locator the frame is blanked rather than leaked
+        let mut blind = frame(PixelFormat::RGBA32, 16, 16, 16 * 16 * 4);
+        let report = PrivacyMasker::new(config).apply(&mut blind, Some(0), &region).unwrap();
+        assert!(report.fail_closed);
+        assert!(blind.data.chunks(4).all(|p| p == [0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_pixelate_and_blur_hide_detail() {
+        let region = ScreenRegion { x: 0, y: 0, width: 16, height: 16 };
+        let whole = MaskTarget::Region(region);
+
+        for style in [MaskStyle::Pixelate { block_size: 16 }, MaskStyle::Blur { radius: 1 }] {
+            let mut rgb = frame(PixelFormat::RGB24, 16, 16, 16 * 16 * 3);
+            let original = rgb.data.clone();
+            let masker = PrivacyMasker::new(PrivacyMaskConfig::default().with_mask(whole.clone(), style));
+            masker.apply(&mut rgb, None, &region).unwrap();
+            assert_ne!(rgb.data, original);
+
+            // Neighbouring pixels end up close to each other
+            let red: Vec<u8> = rgb.data.iter().step_by(3).copied().collect();
+            let spread = red.iter().max().unwrap() - red.iter().min().unwrap();
+            assert!(spread < 128, "{:?} left too much detail", style);
+        }
+
+        let mut mjpeg = frame(PixelFormat::MJPEG, 16, 16, 64);
+        let masker = PrivacyMasker::new(PrivacyMaskConfig::sensitive_apps());
+        assert!(masker.apply(&mut mjpeg, None, &region).is_err());
+    }
+}
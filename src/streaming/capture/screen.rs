@@ -1,10 +1,15 @@
 // Screen capture optimization and utilities
 //
 // Provides efficient screen region capture, change detection, cursor handling,
-// and resolution change adaptation.
+// resolution change adaptation, and live reconfiguration of an active share
+// (monitor switching, region moves and cursor toggling).
 
-use crate::streaming::{CaptureConfig, ScreenRegion, StreamError, StreamResult, Resolution};
+use crate::streaming::{
+    CaptureConfig, Resolution, ScreenConfig, ScreenConfigUpdate, ScreenRegion, StreamError,
+    StreamResult,
+};
 use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
 
 /// Screen capture optimizer for efficient frame capture
 /// 
@@ -277,6 +282,180 @@ impl ResolutionChangeDetector {
     }
 }
 
+/// A display that can be captured
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    pub index: u32,
+    pub name: String,
+    /// Position and size on the virtual desktop
+    pub bounds: ScreenRegion,
+    pub is_primary: bool,
+}
+
+/// What a live screen config change affected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScreenConfigChange {
+    pub monitor_changed: bool,
+    pub region_changed: bool,
+    pub cursor_changed: bool,
+    pub privacy_changed: bool,
+    /// Captured size changed, so the encoder must be reconfigured and
+    /// viewers need a fresh keyframe
+    pub resolution_changed: bool,
+}
+
+impl ScreenConfigChange {
+    /// Whether anything changed at all
+    pub fn is_empty(&self) -> bool {
+        !(self.monitor_changed || self.region_changed || self.cursor_changed || self.privacy_changed)
+    }
+}
+
+/// Live control over an active screen share
+///
+/// Holds the share's current configuration and publishes every change on a
+/// watch channel, so the capture loop picks up a new monitor, region,
+/// cursor or privacy mask setting on its next frame without the stream being
+/// restarted.
+///
+/// Requirements: 3.1, 3.2, 3.4
+pub struct ScreenCaptureControl {
+    config: ScreenConfig,
+    monitors: Vec<Monitor>,
+    sender: watch::Sender<ScreenConfig>,
+}
+
+impl ScreenCaptureControl {
+    /// Wrap the configuration a share was started with
+    pub fn new(config: ScreenConfig) -> Self {
+        let (sender, _) = watch::channel(config.clone());
+        Self {
+            config,
+            monitors: Vec::new(),
+            sender,
+        }
+    }
+
+    /// Monitors available for switching; regions are clamped to their bounds
+    ///
+    /// Without a monitor list, monitor indices and regions are taken as given.
+    pub fn with_monitors(mut self, monitors: Vec<Monitor>) -> Self {
+        self.monitors = monitors;
+        self
+    }
+
+    /// Replace the monitor list, e.g. after a display was plugged in
+    pub fn set_monitors(&mut self, monitors: Vec<Monitor>) {
+        self.monitors = monitors;
+    }
+
+    /// Current configuration
+    pub fn config(&self) -> &ScreenConfig {
+        &self.config
+    }
+
+    /// Receive configuration changes in the capture loop
+    pub fn subscribe(&self) -> watch::Receiver<ScreenConfig> {
+        self.sender.subscribe()
+    }
+
+    /// Apply an update to the running share
+    pub fn update(&mut self, update: ScreenConfigUpdate) -> StreamResult<ScreenConfigChange> {
+        let mut next = self.config.clone();
+
+        if let Some(index) = update.monitor_index {
+            next.monitor_index = Some(index);
+        }
+        if let Some(cursor) = update.capture_cursor {
+            next.capture_cursor = cursor;
+        }
+        if let Some(privacy) = update.privacy {
+            next.privacy = privacy;
+        }
+
+        let monitor_changed = next.monitor_index != self.config.monitor_index;
+        let requested = match update.region {
+            Some(region) => Some(region),
+            // A new monitor gets the full screen unless a region was given
+            None if monitor_changed => None,
+            None => Some(self.config.region),
+        };
+        next.region = self.fit_region(next.monitor_index, requested)?;
+
+        let change = ScreenConfigChange {
+            monitor_changed,
+            region_changed: next.region != self.config.region,
+            cursor_changed: next.capture_cursor != self.config.capture_cursor,
+            privacy_changed: next.privacy != self.config.privacy,
+            resolution_changed: next.region.width != self.config.region.width
+                || next.region.height != self.config.region.height,
+        };
+
+        if !change.is_empty() {
+            self.config = next;
+            self.sender.send_replace(self.config.clone());
+        }
+
+        Ok(change)
+    }
+
+    /// Pan the region so it keeps the cursor in view
+    ///
+    /// The region keeps its size and moves only as far as needed, staying
+    /// inside the monitor. Cursor coordinates are relative to the monitor.
+    pub fn follow_cursor(&mut self, x: u32, y: u32) -> StreamResult<ScreenConfigChange> {
+        let region = self.config.region;
+        let pan = |pos: u32, start: u32, len: u32| {
+            if pos < start {
+                pos
+            } else if pos >= start + len {
+                pos + 1 - len
+            } else {
+                start
+            }
+        };
+
+        let moved = ScreenRegion {
+            x: pan(x, region.x, region.width),
+            y: pan(y, region.y, region.height),
+            ..region
+        };
+        self.update(ScreenConfigUpdate::default().region(moved))
+    }
+
+    fn fit_region(&self, monitor_index: Option<u32>, region: Option<ScreenRegion>) -> StreamResult<ScreenRegion> {
+        let monitor = match monitor_index {
+            Some(index) if !self.monitors.is_empty() => Some(
+                self.monitors
+                    .iter()
+                    .find(|m| m.index == index)
+                    .ok_or_else(|| StreamError::device_not_found(format!("Monitor {} not found", index)))?,
+            ),
+            _ => None,
+        };
+
+        match (monitor, region) {
+            (Some(monitor), Some(region)) => {
+                let selector = RegionSelector::new(monitor.bounds.width, monitor.bounds.height);
+                // Keep the requested size where possible by sliding the
+                // region back inside the monitor before clamping
+                let width = region.width.min(monitor.bounds.width);
+                let height = region.height.min(monitor.bounds.height);
+                selector.validate_region(ScreenRegion {
+                    x: region.x.min(monitor.bounds.width - width),
+                    y: region.y.min(monitor.bounds.height - height),
+                    width,
+                    height,
+                })
+            }
+            (Some(monitor), None) => Ok(ScreenRegion { x: 0, y: 0, ..monitor.bounds }),
+            (None, Some(region)) if region.width > 0 && region.height > 0 => Ok(region),
+            (None, Some(_)) => Err(StreamError::configuration("Invalid screen region")),
+            (None, None) => Ok(self.config.region),
+        }
+    }
+}
+
 /// Screen capture configuration optimizer
 /// 
 /// Requirements: 3.4, 3.5
@@ -383,6 +562,62 @@ mod tests {
         assert!(cursor.is_cursor_in_region(&region));
     }
 
+    fn monitors() -> Vec<Monitor> {
+        vec![
+            Monitor {
+                index: 0,
+                name: "Primary".to_string(),
+                bounds: ScreenRegion { x: 0, y: 0, width: 1920, height: 1080 },
+                is_primary: true,
+            },
+            Monitor {
+                index: 1,
+                name: "Side".to_string(),
+                bounds: ScreenRegion { x: 1920, y: 0, width: 1280, height: 1024 },
+                is_primary: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_screen_capture_control_live_updates() {
+        let config = ScreenConfig {
+            region: ScreenRegion { x: 0, y: 0, width: 1920, height: 1080 },
+            capture_cursor: true,
+            capture_audio: false,
+            monitor_index: Some(0),
+            quality: crate::streaming::StreamQuality::default(),
+            privacy: crate::streaming::PrivacyMaskConfig::default(),
+        };
+        let mut control = ScreenCaptureControl::new(config).with_monitors(monitors());
+        let mut updates = control.subscribe();
+
+        // Switching monitors captures the whole new monitor
+        let change = control.update(ScreenConfigUpdate::default().monitor(1)).unwrap();
+        assert!(change.monitor_changed && change.resolution_changed);
+        assert_eq!(control.config().region, ScreenRegion { x: 0, y: 0, width: 1280, height: 1024 });
+        assert!(updates.has_changed().unwrap());
+        assert_eq!(updates.borrow_and_update().monitor_index, Some(1));
+
+        // A region hanging off the edge slides back inside
+        let change = control
+            .update(ScreenConfigUpdate::default().region(ScreenRegion { x: 1000, y: 900, width: 640, height: 480 }))
+            .unwrap();
+        assert!(change.region_changed && change.resolution_changed);
+        assert_eq!(control.config().region, ScreenRegion { x: 640, y: 544, width: 640, height: 480 });
+
+        // Toggling the cursor keeps the encoder as it is
+        let change = control.update(ScreenConfigUpdate::default().capture_cursor(false)).unwrap();
+        assert!(change.cursor_changed && !change.resolution_changed);
+
+        // Following the cursor pans without resizing
+        let change = control.follow_cursor(100, 600).unwrap();
+        assert!(change.region_changed && !change.resolution_changed);
+        assert_eq!(control.config().region, ScreenRegion { x: 100, y: 544, width: 640, height: 480 });
+
+        assert!(control.update(ScreenConfigUpdate::default().monitor(7)).is_err());
+    }
+
     #[test]
     fn test_resolution_change_detector() {
         let mut detector = ResolutionChangeDetector::new(Resolution { width: 1920, height: 1080 });
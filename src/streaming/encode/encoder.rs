@@ -9,6 +9,7 @@ use std::time::SystemTime;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
 
 use crate::streaming::{
     EncodedFrame, EncoderConfig, EncodingQuality, PixelFormat, StreamError, StreamResult,
@@ -231,16 +232,42 @@ impl EncoderBackend {
                 encoder.set_property("tune", "zerolatency");
             }
         }
+
+        // Only x264 exposes intra refresh; hardware encoders keep relying on
+        // scheduled and requested keyframes
+        if config.intra_refresh.enabled {
+            if accelerator == HardwareAccelerator::Software {
+                encoder.set_property("intra-refresh", true);
+                encoder.set_property("key-int-max", config.intra_refresh.period_frames.max(1));
+            } else {
+                log::debug!("Intra refresh is not supported by {}", accelerator.element_name());
+            }
+        }
         
         Ok(())
     }
 
     /// Encode a video frame
-    fn encode(&mut self, frame: VideoFrame, quality: EncodingQuality) -> StreamResult<EncodedFrame> {
+    fn encode(
+        &mut self,
+        frame: VideoFrame,
+        quality: EncodingQuality,
+        force_keyframe: bool,
+    ) -> StreamResult<EncodedFrame> {
         let (appsrc, appsink) = match self {
             EncoderBackend::Hardware { appsrc, appsink, .. } => (appsrc, appsink),
             EncoderBackend::Software { appsrc, appsink, .. } => (appsrc, appsink),
         };
+
+        // Ask the encoder to make the next frame a keyframe with SPS/PPS
+        if force_keyframe {
+            let event = gst_video::DownstreamForceKeyUnitEvent::builder()
+                .all_headers(true)
+                .build();
+            if !appsrc.send_event(event) {
+                log::debug!("Encoder ignored keyframe request");
+            }
+        }
         
         // Convert frame data to GStreamer buffer
         let mut buffer = gst::Buffer::from_slice(frame.data);
@@ -297,6 +324,7 @@ impl Drop for EncoderBackend {
 pub struct H264Encoder {
     backend: EncoderBackend,
     config: EncoderConfig,
+    force_keyframe: bool,
 }
 
 impl H264Encoder {
@@ -307,6 +335,7 @@ impl H264Encoder {
         Ok(Self {
             backend,
             config,
+            force_keyframe: false,
         })
     }
 
@@ -322,7 +351,16 @@ impl H264Encoder {
             return Err(StreamError::encoding("Frame dimensions don't match encoder configuration"));
         }
         
-        self.backend.encode(frame, quality)
+        let force_keyframe = std::mem::take(&mut self.force_keyframe);
+        self.backend.encode(frame, quality, force_keyframe)
+    }
+
+    /// Make the next encoded frame a keyframe
+    ///
+    /// Used to recover viewers after packet loss without waiting for the
+    /// next scheduled keyframe.
+    pub fn request_keyframe(&mut self) {
+        self.force_keyframe = true;
     }
 
     /// Get encoder configuration
@@ -127,6 +127,14 @@ impl crate::streaming::VideoCodec for VideoCodecImpl {
         let available = HardwareAccelerator::detect_available_accelerators().is_ok();
         Ok(available)
     }
+
+    async fn request_keyframe(&self) -> StreamResult<()> {
+        // A freshly created encoder starts with a keyframe anyway
+        if let Some(encoder) = self.encoder.lock().unwrap().as_mut() {
+            encoder.request_keyframe();
+        }
+        Ok(())
+    }
 }
 
 impl Default for VideoCodecImpl {
@@ -427,6 +427,7 @@ mod tests {
             framerate: 30,
             bitrate: 1_000_000,
             hardware_acceleration: true,
+            intra_refresh: crate::streaming::IntraRefreshConfig::default(),
         };
         
         let optimized = optimizer.optimize_config(config);
@@ -11,6 +11,8 @@ pub mod recording;
 pub mod error;
 pub mod types;
 pub mod security_integration;
+pub mod snapshot;
+pub mod remote_control;
 pub mod api;
 
 pub use error::{StreamError, StreamResult};
@@ -18,15 +20,26 @@ pub use types::*;
 pub use capture::screen::{
     ScreenCaptureOptimizer, RegionSelector, CursorCapture,
     ResolutionChangeDetector, CaptureConfigOptimizer,
+    ScreenCaptureControl, ScreenConfigChange, Monitor,
 };
+pub use capture::privacy::{PrivacyMasker, WindowLocator, MaskReport};
 pub use recording::{
-    RecordingEngineImpl, StreamRecorder, StorageManager, RecordingMetadata,
-    PermissionManager, RecordingPermission,
+    RecordingEngineImpl, StreamRecorder, StorageManager, RecordingMetadata, RecordingKind,
+    PermissionManager, RecordingPermission, SupportSessionRecorder, SupportRecordingConfig,
 };
 pub use security_integration::{
     StreamSecurityManager, PeerTrustInfo, SecureStreamWrapper,
     StreamAccessControl, AccessRequest, ViewerAccess,
 };
+pub use snapshot::{
+    encode_png, preview_thumbnail, FrameGrabber, SnapshotApprovalCallback, SnapshotConfig, SnapshotDelivery,
+    SnapshotGrant, SnapshotId, SnapshotRequest, SnapshotResult, SnapshotService, SnapshotSource,
+    SystemFrameGrabber,
+};
+pub use remote_control::{
+    ControlEndReason, ControlGrant, ControlIndicator, HotkeyListener, InputEvent, InputInjector,
+    NotificationIndicator, RemoteControl, RemoteControlConfig, RemoteControlEvent, KILL_SWITCH_HOTKEY,
+};
 pub use api::{
     Streaming, StreamingApi, StreamEvent, StreamEventHandler,
     StopReason, QualityChangeReason,
@@ -52,9 +65,24 @@ pub trait StreamManager: Send + Sync {
     /// Stop an active stream
     async fn stop_stream(&self, session_id: SessionId) -> StreamResult<()>;
     
+    /// Stop sending frames and show viewers a paused card, keeping the
+    /// session, its viewers and encoder state so resuming is instant
+    async fn pause_stream(&self, session_id: SessionId) -> StreamResult<()>;
+    
+    /// Resume a paused stream, starting with a keyframe
+    async fn resume_stream(&self, session_id: SessionId) -> StreamResult<()>;
+    
     /// Adjust the quality of an active stream
     async fn adjust_quality(&self, session_id: SessionId, quality: StreamQuality) -> StreamResult<()>;
     
+    /// Switch monitors, move the region or toggle the cursor of an active
+    /// screen share without restarting it
+    async fn update_screen_config(
+        &self,
+        session_id: SessionId,
+        update: ScreenConfigUpdate,
+    ) -> StreamResult<ScreenConfig>;
+    
     /// Get all active streaming sessions
     async fn get_active_streams(&self) -> StreamResult<Vec<StreamSession>>;
 }
@@ -113,6 +141,9 @@ pub trait VideoCodec: Send + Sync {
     
     /// Enable hardware acceleration if available
     async fn enable_hardware_acceleration(&self) -> StreamResult<bool>;
+
+    /// Make the next encoded frame a keyframe
+    async fn request_keyframe(&self) -> StreamResult<()>;
 }
 
 /// Network streaming interface for video transmission
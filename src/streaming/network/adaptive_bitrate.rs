@@ -11,6 +11,7 @@ use std::time::{Duration, SystemTime};
 use tokio::sync::{Mutex, RwLock};
 
 use crate::streaming::{QualityPreset, StreamError, StreamQuality, StreamResult};
+use super::buffer_manager::{BufferHealth, JitterBufferHealth};
 
 /// Adaptive bitrate controller for video streaming
 ///
@@ -24,6 +25,10 @@ pub struct AdaptiveBitrateController {
     quality_selector: Arc<RwLock<QualitySelector>>,
     congestion_controller: Arc<Mutex<CongestionController>>,
     packet_loss_recovery: Arc<Mutex<PacketLossRecovery>>,
+    /// Ceiling from the traffic policy, such as on a metered connection
+    bitrate_cap: Arc<RwLock<Option<u32>>>,
+    /// Latest health report from the viewer's jitter buffer
+    buffer_health: Arc<RwLock<Option<JitterBufferHealth>>>,
 }
 
 /// Configuration for adaptive bitrate control
@@ -197,9 +202,37 @@ impl AdaptiveBitrateController {
             quality_selector: Arc::new(RwLock::new(QualitySelector::new())),
             congestion_controller: Arc::new(Mutex::new(CongestionController::new())),
             packet_loss_recovery: Arc::new(Mutex::new(PacketLossRecovery::new())),
+            bitrate_cap: Arc::new(RwLock::new(None)),
+            buffer_health: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Limit the bitrate below the configured maximum; `None` lifts the limit
+    pub async fn set_bitrate_cap(&self, cap: Option<u32>) {
+        *self.bitrate_cap.write().await = cap;
+    }
+
+    /// Highest bitrate currently allowed
+    pub async fn effective_max_bitrate(&self) -> u32 {
+        match *self.bitrate_cap.read().await {
+            Some(cap) => cap.clamp(self.config.min_bitrate, self.config.max_bitrate),
+            None => self.config.max_bitrate,
+        }
+    }
+
+    /// Record the viewer's jitter buffer health
+    ///
+    /// A starving or late-heavy buffer lowers the recommended bitrate and
+    /// triggers a quality downgrade even when the network samples look fine.
+    pub async fn report_buffer_health(&self, health: JitterBufferHealth) {
+        *self.buffer_health.write().await = Some(health);
+    }
+
+    /// Most recent jitter buffer health report
+    pub async fn buffer_health(&self) -> Option<JitterBufferHealth> {
+        self.buffer_health.read().await.clone()
+    }
+
     /// Update network statistics and get recommended bitrate adjustment
     pub async fn update_network_stats(
         &self,
@@ -248,14 +281,16 @@ impl AdaptiveBitrateController {
         let mut selector = self.quality_selector.write().await;
         
         // Determine if quality change is needed
-        let should_change = self.should_change_quality(conditions, &selector.current_quality).await?;
+        let over_cap = selector.current_quality.bitrate > self.effective_max_bitrate().await;
+        let should_change = over_cap || self.should_change_quality(conditions, &selector.current_quality).await?;
         
         if !should_change {
             return Ok(None);
         }
 
         // Calculate target quality
-        let target_quality = self.calculate_target_quality(conditions)?;
+        let mut target_quality = self.calculate_target_quality(conditions)?;
+        target_quality.bitrate = target_quality.bitrate.min(self.effective_max_bitrate().await);
         
         // Check if we can change quality (rate limiting)
         if !selector.can_change_quality(self.config.adjustment_interval) {
@@ -263,7 +298,8 @@ impl AdaptiveBitrateController {
         }
 
         // Determine reason for change
-        let reason = self.determine_change_reason(conditions, &selector.current_quality, &target_quality);
+        let buffer_health = self.buffer_health_level().await;
+        let reason = self.determine_change_reason(conditions, &selector.current_quality, &target_quality, buffer_health);
 
         // Apply quality change
         selector.change_quality(target_quality.clone(), reason);
@@ -325,8 +361,15 @@ impl AdaptiveBitrateController {
             recommended = (recommended as f32 * latency_penalty) as u32;
         }
 
+        // Back off while the viewer's buffer is struggling
+        recommended = match self.buffer_health_level().await {
+            BufferHealth::Critical => (recommended as f32 * 0.7) as u32,
+            BufferHealth::Warning => (recommended as f32 * 0.85) as u32,
+            BufferHealth::Healthy => recommended,
+        };
+
         // Clamp to configured limits
-        recommended = recommended.max(self.config.min_bitrate).min(self.config.max_bitrate);
+        recommended = recommended.max(self.config.min_bitrate).min(self.effective_max_bitrate().await);
 
         Ok(recommended)
     }
@@ -359,9 +402,22 @@ impl AdaptiveBitrateController {
             return Ok(true);
         }
 
+        // Check whether the viewer's buffer is starving
+        if self.buffer_health_level().await == BufferHealth::Critical {
+            return Ok(true);
+        }
+
         Ok(false)
     }
 
+    async fn buffer_health_level(&self) -> BufferHealth {
+        self.buffer_health
+            .read()
+            .await
+            .as_ref()
+            .map_or(BufferHealth::Healthy, |report| report.health)
+    }
+
     fn calculate_target_quality(&self, conditions: &NetworkConditions) -> StreamResult<StreamQuality> {
         // Select quality preset based on available bandwidth
         let preset = if conditions.estimated_bandwidth >= 5_000_000 {
@@ -397,9 +453,12 @@ impl AdaptiveBitrateController {
         conditions: &NetworkConditions,
         current: &StreamQuality,
         target: &StreamQuality,
+        buffer_health: BufferHealth,
     ) -> QualityChangeReason {
         if target.bitrate > current.bitrate {
             QualityChangeReason::BandwidthIncrease
+        } else if buffer_health == BufferHealth::Critical {
+            QualityChangeReason::BufferUnderrun
         } else if conditions.packet_loss_rate > self.config.packet_loss_threshold {
             QualityChangeReason::PacketLoss
         } else if conditions.average_rtt.as_millis() > self.config.rtt_threshold_ms as u128 {
@@ -730,6 +789,41 @@ mod tests {
         assert!(bandwidth > 0);
     }
 
+    #[tokio::test]
+    async fn test_bitrate_cap_limits_recommendation() {
+        let controller = AdaptiveBitrateController::new();
+        controller.set_bitrate_cap(Some(1_000_000)).await;
+        let recommended = controller.update_network_stats(20_000_000, 20, 0.0).await.unwrap();
+        assert_eq!(recommended, Some(1_000_000));
+
+        controller.set_bitrate_cap(None).await;
+        let recommended = controller.update_network_stats(20_000_000, 20, 0.0).await.unwrap();
+        assert!(recommended.unwrap() > 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_buffer_health_lowers_recommendation() {
+        let controller = AdaptiveBitrateController::new();
+        let healthy = controller.update_network_stats(4_000_000, 20, 0.0).await.unwrap().unwrap();
+
+        controller
+            .report_buffer_health(JitterBufferHealth {
+                health: BufferHealth::Critical,
+                buffered_frames: 0,
+                buffered_duration: Duration::ZERO,
+                target_latency: Duration::from_millis(150),
+                jitter: Duration::from_millis(80),
+                late_rate: 0.2,
+                late_frames: 12,
+                discarded_frames: 10,
+                underruns: 3,
+            })
+            .await;
+
+        let starving = controller.update_network_stats(4_000_000, 20, 0.0).await.unwrap().unwrap();
+        assert!(starving < healthy);
+    }
+
     #[test]
     fn test_congestion_levels() {
         assert_eq!(CongestionLevel::None, CongestionLevel::None);
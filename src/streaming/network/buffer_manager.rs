@@ -2,13 +2,16 @@
 //
 // Implements adaptive buffering based on network jitter and latency,
 // flow control to prevent buffer overflow/underflow, and stream
-// synchronization for audio-video alignment.
+// synchronization for audio-video alignment. On the viewer side a jitter
+// buffer reorders incoming frames and schedules them for playout a
+// configurable latency behind the sender, discarding frames that arrive too
+// late to be shown.
 //
 // Requirements: 2.4, 4.4
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::{Mutex, RwLock, Semaphore};
 
 use crate::streaming::{EncodedFrame, StreamError, StreamResult};
@@ -27,6 +30,7 @@ pub struct StreamBufferManager {
     flow_controller: Arc<Mutex<FlowController>>,
     sync_manager: Arc<RwLock<SyncManager>>,
     buffer_monitor: Arc<Mutex<BufferMonitor>>,
+    jitter_buffer: Arc<Mutex<JitterBuffer>>,
 }
 
 /// Configuration for stream buffering
@@ -48,6 +52,92 @@ pub struct BufferConfig {
     pub adaptive_sizing: bool,
     /// Flow control window size
     pub flow_control_window: usize,
+    /// Receive-side jitter buffer settings
+    pub jitter: JitterBufferConfig,
+}
+
+/// Configuration for the viewer-side jitter buffer
+#[derive(Debug, Clone)]
+pub struct JitterBufferConfig {
+    /// How far behind the sender playout runs
+    pub target_latency: Duration,
+    /// Upper bound when the target adapts to measured jitter
+    pub max_latency: Duration,
+    /// Raise the target latency when measured jitter exceeds it
+    pub adaptive_latency: bool,
+    /// What to do with frames that miss their playout time
+    pub late_frame_policy: LateFramePolicy,
+    /// Frames held before the oldest is dropped
+    pub max_frames: usize,
+}
+
+/// Handling of frames that arrive after their playout time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LateFramePolicy {
+    /// Drop every late frame
+    Discard,
+    /// Drop late frames but still play late keyframes, which later frames
+    /// depend on
+    KeepKeyframes,
+    /// Play late frames as soon as possible
+    PlayLate,
+}
+
+/// What the jitter buffer did with an arriving frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameArrival {
+    /// Queued for playout at its scheduled time
+    Buffered,
+    /// Missed its playout time but queued for immediate playout
+    Late,
+    /// Dropped
+    Discarded,
+}
+
+/// Viewer-side jitter buffer
+///
+/// Orders frames by sequence number and releases each one at
+/// `first arrival + target latency + (frame timestamp - first timestamp)`,
+/// which absorbs network jitter up to the target latency.
+pub struct JitterBuffer {
+    config: JitterBufferConfig,
+    frames: BTreeMap<u64, JitterEntry>,
+    /// Local arrival time and sender timestamp of the first frame
+    base: Option<(Instant, SystemTime)>,
+    /// Arrival time and sender timestamp of the previous frame
+    last_arrival: Option<(Instant, SystemTime)>,
+    next_sequence: Option<u64>,
+    target_latency: Duration,
+    /// Interarrival jitter estimate in seconds (RFC 3550)
+    jitter: f64,
+    /// Moving average of the share of frames arriving late
+    late_rate: f32,
+    starved: bool,
+    late_frames: u64,
+    discarded_frames: u64,
+    underruns: u64,
+}
+
+/// Jitter buffer entry
+#[derive(Debug, Clone)]
+struct JitterEntry {
+    frame: EncodedFrame,
+    play_immediately: bool,
+}
+
+/// Jitter buffer health, reported to the adaptive bitrate controller
+#[derive(Debug, Clone)]
+pub struct JitterBufferHealth {
+    pub health: BufferHealth,
+    pub buffered_frames: usize,
+    pub buffered_duration: Duration,
+    pub target_latency: Duration,
+    pub jitter: Duration,
+    /// Share of recent frames that arrived late (0.0-1.0)
+    pub late_rate: f32,
+    pub late_frames: u64,
+    pub discarded_frames: u64,
+    pub underruns: u64,
 }
 
 /// Adaptive buffer for video/audio frames
@@ -180,6 +270,19 @@ impl Default for BufferConfig {
             overrun_threshold: 0.9, // 90%
             adaptive_sizing: true,
             flow_control_window: 64 * 1024, // 64KB
+            jitter: JitterBufferConfig::default(),
+        }
+    }
+}
+
+impl Default for JitterBufferConfig {
+    fn default() -> Self {
+        Self {
+            target_latency: Duration::from_millis(150),
+            max_latency: Duration::from_secs(1),
+            adaptive_latency: true,
+            late_frame_policy: LateFramePolicy::KeepKeyframes,
+            max_frames: 120,
         }
     }
 }
@@ -204,6 +307,7 @@ impl StreamBufferManager {
             flow_controller: Arc::new(Mutex::new(FlowController::new(config.flow_control_window))),
             sync_manager: Arc::new(RwLock::new(SyncManager::new())),
             buffer_monitor: Arc::new(Mutex::new(BufferMonitor::new())),
+            jitter_buffer: Arc::new(Mutex::new(JitterBuffer::new(config.jitter.clone()))),
             config,
         }
     }
@@ -328,6 +432,47 @@ impl StreamBufferManager {
         Ok(())
     }
 
+    /// Queue a frame received from the network for jittered playout
+    pub async fn receive_video_frame(
+        &self,
+        frame: EncodedFrame,
+        sequence_number: u64,
+    ) -> FrameArrival {
+        let arrival = {
+            let mut jitter = self.jitter_buffer.lock().await;
+            jitter.push(frame, sequence_number)
+        };
+
+        let mut monitor = self.buffer_monitor.lock().await;
+        match arrival {
+            FrameArrival::Discarded => monitor.record_frame_dropped(),
+            FrameArrival::Buffered | FrameArrival::Late => monitor.record_frame_buffered(),
+        }
+
+        arrival
+    }
+
+    /// Take the next received frame whose playout time has come
+    pub async fn next_playout_frame(&self) -> Option<EncodedFrame> {
+        let mut jitter = self.jitter_buffer.lock().await;
+        let underruns = jitter.underruns;
+        let frame = jitter.pop_ready();
+
+        if jitter.underruns > underruns {
+            drop(jitter);
+            let mut monitor = self.buffer_monitor.lock().await;
+            monitor.record_underrun();
+        }
+
+        frame
+    }
+
+    /// Health of the receive-side jitter buffer
+    pub async fn jitter_health(&self) -> JitterBufferHealth {
+        let jitter = self.jitter_buffer.lock().await;
+        jitter.health()
+    }
+
     /// Get buffer level (percentage full)
     pub async fn get_buffer_level(&self) -> f32 {
         let buffer = self.video_buffer.lock().await;
@@ -389,6 +534,199 @@ impl StreamBufferManager {
     }
 }
 
+impl JitterBuffer {
+    /// Create an empty jitter buffer
+    pub fn new(config: JitterBufferConfig) -> Self {
+        Self {
+            target_latency: config.target_latency,
+            config,
+            frames: BTreeMap::new(),
+            base: None,
+            last_arrival: None,
+            next_sequence: None,
+            jitter: 0.0,
+            late_rate: 0.0,
+            starved: false,
+            late_frames: 0,
+            discarded_frames: 0,
+            underruns: 0,
+        }
+    }
+
+    /// Add a frame as it arrives from the network
+    pub fn push(&mut self, frame: EncodedFrame, sequence_number: u64) -> FrameArrival {
+        self.push_at(frame, sequence_number, Instant::now())
+    }
+
+    /// Take the next frame if its playout time has come
+    pub fn pop_ready(&mut self) -> Option<EncodedFrame> {
+        self.pop_ready_at(Instant::now())
+    }
+
+    /// Current playout delay behind the sender
+    pub fn target_latency(&self) -> Duration {
+        self.target_latency
+    }
+
+    /// Number of frames waiting for playout
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether no frames are waiting
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Drop all buffered frames and restart timing from the next arrival
+    pub fn reset(&mut self) {
+        self.frames.clear();
+        self.base = None;
+        self.last_arrival = None;
+        self.next_sequence = None;
+        self.starved = false;
+    }
+
+    /// Snapshot of buffer health
+    pub fn health(&self) -> JitterBufferHealth {
+        let buffered_duration = match (self.frames.values().next(), self.frames.values().next_back()) {
+            (Some(first), Some(last)) => last
+                .frame
+                .timestamp
+                .duration_since(first.frame.timestamp)
+                .unwrap_or_default(),
+            _ => Duration::ZERO,
+        };
+
+        let health = if self.starved || self.late_rate > 0.1 {
+            BufferHealth::Critical
+        } else if self.late_rate > 0.02 || buffered_duration < self.target_latency / 2 {
+            BufferHealth::Warning
+        } else {
+            BufferHealth::Healthy
+        };
+
+        JitterBufferHealth {
+            health,
+            buffered_frames: self.frames.len(),
+            buffered_duration,
+            target_latency: self.target_latency,
+            jitter: Duration::from_secs_f64(self.jitter),
+            late_rate: self.late_rate,
+            late_frames: self.late_frames,
+            discarded_frames: self.discarded_frames,
+            underruns: self.underruns,
+        }
+    }
+
+    fn push_at(&mut self, frame: EncodedFrame, sequence_number: u64, now: Instant) -> FrameArrival {
+        if self.base.is_none() {
+            self.base = Some((now, frame.timestamp));
+        }
+        self.update_jitter(now, frame.timestamp);
+
+        let already_played = self.next_sequence.is_some_and(|next| sequence_number < next);
+        let missed_deadline = self
+            .playout_deadline(frame.timestamp)
+            .is_some_and(|deadline| deadline < now);
+        let late = already_played || missed_deadline;
+
+        self.late_rate += ((if late { 1.0 } else { 0.0 }) - self.late_rate) / 32.0;
+
+        if !late {
+            return self.insert(sequence_number, frame, false);
+        }
+
+        self.late_frames += 1;
+        let keep = match self.config.late_frame_policy {
+            LateFramePolicy::Discard => false,
+            LateFramePolicy::KeepKeyframes => frame.is_keyframe,
+            LateFramePolicy::PlayLate => true,
+        };
+
+        // A frame behind the playout position can never be shown in order
+        if !keep || already_played {
+            self.discarded_frames += 1;
+            return FrameArrival::Discarded;
+        }
+
+        self.insert(sequence_number, frame, true)
+    }
+
+    fn pop_ready_at(&mut self, now: Instant) -> Option<EncodedFrame> {
+        let Some((&sequence_number, entry)) = self.frames.iter().next() else {
+            if self.next_sequence.is_some() && !self.starved {
+                self.starved = true;
+                self.underruns += 1;
+            }
+            return None;
+        };
+
+        let due = entry.play_immediately
+            || self
+                .playout_deadline(entry.frame.timestamp)
+                .map_or(true, |deadline| deadline <= now);
+        if !due {
+            return None;
+        }
+
+        let entry = self.frames.remove(&sequence_number)?;
+        self.next_sequence = Some(sequence_number + 1);
+        self.starved = false;
+        Some(entry.frame)
+    }
+
+    fn insert(&mut self, sequence_number: u64, frame: EncodedFrame, play_immediately: bool) -> FrameArrival {
+        if self.frames.contains_key(&sequence_number) {
+            self.discarded_frames += 1;
+            return FrameArrival::Discarded;
+        }
+
+        self.frames.insert(sequence_number, JitterEntry { frame, play_immediately });
+
+        while self.frames.len() > self.config.max_frames {
+            self.frames.pop_first();
+            self.discarded_frames += 1;
+        }
+
+        if play_immediately {
+            FrameArrival::Late
+        } else {
+            FrameArrival::Buffered
+        }
+    }
+
+    /// Local time a frame with this sender timestamp should be shown
+    fn playout_deadline(&self, timestamp: SystemTime) -> Option<Instant> {
+        let (base_local, base_media) = self.base?;
+        let anchor = base_local + self.target_latency;
+        match timestamp.duration_since(base_media) {
+            Ok(offset) => Some(anchor + offset),
+            Err(e) => anchor.checked_sub(e.duration()),
+        }
+    }
+
+    fn update_jitter(&mut self, now: Instant, timestamp: SystemTime) {
+        if let Some((last_local, last_media)) = self.last_arrival {
+            let arrival_delta = now.duration_since(last_local).as_secs_f64();
+            let media_delta = match timestamp.duration_since(last_media) {
+                Ok(d) => d.as_secs_f64(),
+                Err(e) => -e.duration().as_secs_f64(),
+            };
+            let transit_change = (arrival_delta - media_delta).abs();
+            self.jitter += (transit_change - self.jitter) / 16.0;
+        }
+        self.last_arrival = Some((now, timestamp));
+
+        if self.config.adaptive_latency {
+            let wanted = Duration::from_secs_f64(self.jitter * 3.0);
+            self.target_latency = wanted
+                .max(self.config.target_latency)
+                .min(self.config.max_latency);
+        }
+    }
+}
+
 impl AdaptiveBuffer {
     fn new(capacity: usize, target_duration: Duration) -> Self {
         Self {
@@ -587,6 +925,11 @@ impl BufferMonitor {
         self.update_health();
     }
 
+    fn record_frame_dropped(&mut self) {
+        self.stats.frames_dropped += 1;
+        self.stats.last_updated = SystemTime::now();
+    }
+
     fn record_underrun(&mut self) {
         self.stats.underrun_events += 1;
         self.add_alert(BufferAlertType::Underrun, "Buffer underrun detected");
@@ -676,6 +1019,72 @@ mod tests {
         assert!(FramePriority::Normal > FramePriority::Low);
     }
 
+    fn frame_at(start: SystemTime, offset_ms: u64, is_keyframe: bool) -> EncodedFrame {
+        EncodedFrame {
+            data: vec![0; 16],
+            timestamp: start + Duration::from_millis(offset_ms),
+            is_keyframe,
+        }
+    }
+
+    #[test]
+    fn test_jitter_buffer_reorders_and_holds_until_playout() {
+        let config = JitterBufferConfig {
+            target_latency: Duration::from_millis(100),
+            adaptive_latency: false,
+            ..JitterBufferConfig::default()
+        };
+        let mut buffer = JitterBuffer::new(config);
+        let start = SystemTime::now();
+        let t0 = Instant::now();
+
+        assert_eq!(buffer.push_at(frame_at(start, 0, true), 0, t0), FrameArrival::Buffered);
+        // Frame 2 overtakes frame 1 on the network
+        assert_eq!(buffer.push_at(frame_at(start, 66, false), 2, t0 + Duration::from_millis(40)), FrameArrival::Buffered);
+        assert_eq!(buffer.push_at(frame_at(start, 33, false), 1, t0 + Duration::from_millis(50)), FrameArrival::Buffered);
+
+        assert!(buffer.pop_ready_at(t0 + Duration::from_millis(50)).is_none());
+        let first = buffer.pop_ready_at(t0 + Duration::from_millis(100)).unwrap();
+        assert!(first.is_keyframe);
+        assert!(buffer.pop_ready_at(t0 + Duration::from_millis(110)).is_none());
+        assert_eq!(buffer.pop_ready_at(t0 + Duration::from_millis(133)).unwrap().timestamp, start + Duration::from_millis(33));
+        assert_eq!(buffer.pop_ready_at(t0 + Duration::from_millis(166)).unwrap().timestamp, start + Duration::from_millis(66));
+
+        assert!(buffer.pop_ready_at(t0 + Duration::from_millis(200)).is_none());
+        let health = buffer.health();
+        assert_eq!(health.underruns, 1);
+        assert_eq!(health.health, BufferHealth::Critical);
+    }
+
+    #[test]
+    fn test_jitter_buffer_late_frame_policy() {
+        let config = JitterBufferConfig {
+            target_latency: Duration::from_millis(50),
+            adaptive_latency: false,
+            late_frame_policy: LateFramePolicy::KeepKeyframes,
+            ..JitterBufferConfig::default()
+        };
+        let mut buffer = JitterBuffer::new(config);
+        let start = SystemTime::now();
+        let t0 = Instant::now();
+
+        buffer.push_at(frame_at(start, 0, true), 0, t0);
+        // Both arrive 200ms after being sent, well past the 50ms target
+        let late = t0 + Duration::from_millis(233);
+        assert_eq!(buffer.push_at(frame_at(start, 33, false), 1, late), FrameArrival::Discarded);
+        assert_eq!(buffer.push_at(frame_at(start, 66, true), 2, late), FrameArrival::Late);
+
+        assert!(buffer.pop_ready_at(late).unwrap().is_keyframe);
+        assert_eq!(buffer.pop_ready_at(late).unwrap().timestamp, start + Duration::from_millis(66));
+
+        // Anything behind the playout position is dropped whatever the policy
+        assert_eq!(buffer.push_at(frame_at(start, 33, true), 1, late), FrameArrival::Discarded);
+
+        let health = buffer.health();
+        assert_eq!(health.late_frames, 3);
+        assert_eq!(health.discarded_frames, 2);
+    }
+
     #[test]
     fn test_buffer_config_defaults() {
         let config = BufferConfig::default();
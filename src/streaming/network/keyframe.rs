@@ -0,0 +1,311 @@
+// Keyframe request and error recovery
+//
+// Implements PLI/FIR-style feedback between viewers and the sender. When a
+// viewer loses packets or its decoder fails it asks for an immediate
+// keyframe instead of showing artifacts until the next scheduled one. The
+// sender coalesces requests from all viewers so a lossy link cannot cause a
+// keyframe storm.
+//
+// Requirements: 4.4, 4.5
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::streaming::{SessionId, StreamError, StreamResult};
+
+/// Feedback sent from a viewer to the sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KeyframeFeedback {
+    /// Some frames were lost; the picture is damaged but decoding continues
+    PictureLoss { session_id: SessionId },
+    /// The decoder cannot continue without a full intra frame
+    ///
+    /// `sequence` increases with each new request so that retransmissions of
+    /// the same request are only acted on once.
+    FullIntraRequest { session_id: SessionId, sequence: u8 },
+}
+
+impl KeyframeFeedback {
+    /// Session the feedback refers to
+    pub fn session_id(&self) -> SessionId {
+        match self {
+            KeyframeFeedback::PictureLoss { session_id }
+            | KeyframeFeedback::FullIntraRequest { session_id, .. } => *session_id,
+        }
+    }
+
+    /// Serialize for the stream control channel
+    pub fn to_bytes(&self) -> StreamResult<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| StreamError::internal(format!("Failed to encode keyframe feedback: {}", e)))
+    }
+
+    /// Parse a control message received from a viewer
+    pub fn from_bytes(data: &[u8]) -> StreamResult<Self> {
+        serde_json::from_slice(data)
+            .map_err(|e| StreamError::network(format!("Invalid keyframe feedback: {}", e)))
+    }
+}
+
+/// Viewer-side keyframe requester
+///
+/// Turns packet loss and decoder failures into feedback messages, rate
+/// limited and repeated until a keyframe actually arrives.
+#[derive(Debug, Clone)]
+pub struct KeyframeRequester {
+    session_id: SessionId,
+    /// Minimum spacing between requests
+    min_interval: Duration,
+    last_request: Option<Instant>,
+    /// Request still waiting for a keyframe
+    pending: Option<KeyframeFeedback>,
+    fir_sequence: u8,
+    requests_sent: u64,
+}
+
+impl KeyframeRequester {
+    /// Create a requester for a session
+    pub fn new(session_id: SessionId) -> Self {
+        Self {
+            session_id,
+            min_interval: Duration::from_millis(300),
+            last_request: None,
+            pending: None,
+            fir_sequence: 0,
+            requests_sent: 0,
+        }
+    }
+
+    /// Set the minimum spacing between requests
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    /// Frames were lost; returns a picture loss indication to send, if due
+    pub fn on_packet_loss(&mut self) -> Option<KeyframeFeedback> {
+        self.on_packet_loss_at(Instant::now())
+    }
+
+    /// The decoder failed; returns a full intra request to send, if due
+    pub fn on_decoder_failure(&mut self) -> Option<KeyframeFeedback> {
+        self.on_decoder_failure_at(Instant::now())
+    }
+
+    /// Repeat an unanswered request once the interval has passed
+    ///
+    /// Call periodically; feedback can itself be lost on a lossy link.
+    pub fn poll_retry(&mut self) -> Option<KeyframeFeedback> {
+        self.poll_retry_at(Instant::now())
+    }
+
+    /// A keyframe arrived, so any outstanding request is answered
+    pub fn on_keyframe_received(&mut self) {
+        self.pending = None;
+    }
+
+    /// Whether a request is waiting for a keyframe
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Number of feedback messages produced
+    pub fn requests_sent(&self) -> u64 {
+        self.requests_sent
+    }
+
+    fn on_packet_loss_at(&mut self, now: Instant) -> Option<KeyframeFeedback> {
+        // An outstanding full intra request already covers the loss
+        if self.pending.is_none() {
+            self.pending = Some(KeyframeFeedback::PictureLoss { session_id: self.session_id });
+        }
+        self.poll_retry_at(now)
+    }
+
+    fn on_decoder_failure_at(&mut self, now: Instant) -> Option<KeyframeFeedback> {
+        if !matches!(self.pending, Some(KeyframeFeedback::FullIntraRequest { .. })) {
+            self.fir_sequence = self.fir_sequence.wrapping_add(1);
+            self.pending = Some(KeyframeFeedback::FullIntraRequest {
+                session_id: self.session_id,
+                sequence: self.fir_sequence,
+            });
+        }
+        self.poll_retry_at(now)
+    }
+
+    fn poll_retry_at(&mut self, now: Instant) -> Option<KeyframeFeedback> {
+        let feedback = self.pending?;
+        if self
+            .last_request
+            .is_some_and(|last| now.duration_since(last) < self.min_interval)
+        {
+            return None;
+        }
+
+        self.last_request = Some(now);
+        self.requests_sent += 1;
+        Some(feedback)
+    }
+}
+
+/// Sender-side keyframe scheduler
+///
+/// Collects feedback from every viewer of a session and tells the encoder
+/// loop when to force a keyframe, at most once per `min_interval`.
+#[derive(Debug, Clone)]
+pub struct KeyframeScheduler {
+    min_interval: Duration,
+    last_forced: Option<Instant>,
+    pending: bool,
+    /// Last full intra request sequence seen per viewer
+    fir_sequences: HashMap<String, u8>,
+    forced_keyframes: u64,
+    coalesced_requests: u64,
+}
+
+impl KeyframeScheduler {
+    /// Create a scheduler
+    pub fn new() -> Self {
+        Self {
+            min_interval: Duration::from_millis(500),
+            last_forced: None,
+            pending: false,
+            fir_sequences: HashMap::new(),
+            forced_keyframes: 0,
+            coalesced_requests: 0,
+        }
+    }
+
+    /// Set the minimum spacing between forced keyframes
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    /// Record feedback from a viewer
+    ///
+    /// Returns `false` when the feedback was a retransmission of a request
+    /// already acted on.
+    pub fn handle_feedback(&mut self, viewer: &str, feedback: KeyframeFeedback) -> bool {
+        if let KeyframeFeedback::FullIntraRequest { sequence, .. } = feedback {
+            if self.fir_sequences.insert(viewer.to_string(), sequence) == Some(sequence) {
+                return false;
+            }
+        }
+
+        self.request();
+        true
+    }
+
+    /// Ask for a keyframe locally, e.g. when a viewer joins
+    pub fn request(&mut self) {
+        if self.pending {
+            self.coalesced_requests += 1;
+        }
+        self.pending = true;
+    }
+
+    /// Whether the next encoded frame should be a keyframe
+    ///
+    /// Call once per frame. A request made shortly after the last forced
+    /// keyframe stays pending until the interval has passed.
+    pub fn take_keyframe(&mut self) -> bool {
+        self.take_keyframe_at(Instant::now())
+    }
+
+    /// Forget a viewer that left the session
+    pub fn remove_viewer(&mut self, viewer: &str) {
+        self.fir_sequences.remove(viewer);
+    }
+
+    /// Number of keyframes forced by requests
+    pub fn forced_keyframes(&self) -> u64 {
+        self.forced_keyframes
+    }
+
+    /// Requests that were merged into an already pending keyframe
+    pub fn coalesced_requests(&self) -> u64 {
+        self.coalesced_requests
+    }
+
+    fn take_keyframe_at(&mut self, now: Instant) -> bool {
+        if !self.pending {
+            return false;
+        }
+        if self
+            .last_forced
+            .is_some_and(|last| now.duration_since(last) < self.min_interval)
+        {
+            return false;
+        }
+
+        self.pending = false;
+        self.last_forced = Some(now);
+        self.forced_keyframes += 1;
+        true
+    }
+}
+
+impl Default for KeyframeScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requester_rate_limits_and_retries() {
+        let session_id = SessionId::new_v4();
+        let mut requester = KeyframeRequester::new(session_id).with_min_interval(Duration::from_millis(100));
+        let t0 = Instant::now();
+
+        let pli = requester.on_packet_loss_at(t0).unwrap();
+        assert_eq!(pli, KeyframeFeedback::PictureLoss { session_id });
+        assert!(requester.on_packet_loss_at(t0 + Duration::from_millis(50)).is_none());
+
+        // Decoder failure upgrades the pending request
+        let fir = requester.on_decoder_failure_at(t0 + Duration::from_millis(120)).unwrap();
+        assert!(matches!(fir, KeyframeFeedback::FullIntraRequest { sequence: 1, .. }));
+
+        // Unanswered, the same request is repeated with the same sequence
+        assert_eq!(requester.poll_retry_at(t0 + Duration::from_millis(240)), Some(fir));
+
+        requester.on_keyframe_received();
+        assert!(requester.poll_retry_at(t0 + Duration::from_millis(400)).is_none());
+        assert_eq!(requester.requests_sent(), 3);
+
+        let bytes = fir.to_bytes().unwrap();
+        assert_eq!(KeyframeFeedback::from_bytes(&bytes).unwrap(), fir);
+    }
+
+    #[test]
+    fn test_scheduler_coalesces_requests() {
+        let session_id = SessionId::new_v4();
+        let mut scheduler = KeyframeScheduler::new().with_min_interval(Duration::from_millis(500));
+        let t0 = Instant::now();
+
+        assert!(!scheduler.take_keyframe_at(t0));
+
+        let fir = KeyframeFeedback::FullIntraRequest { session_id, sequence: 1 };
+        assert!(scheduler.handle_feedback("alice", fir));
+        assert!(scheduler.handle_feedback("bob", KeyframeFeedback::PictureLoss { session_id }));
+        assert!(scheduler.take_keyframe_at(t0));
+        assert!(!scheduler.take_keyframe_at(t0 + Duration::from_millis(10)));
+
+        // A retransmitted request is ignored
+        assert!(!scheduler.handle_feedback("alice", fir));
+
+        // A new request soon after waits out the interval
+        assert!(scheduler.handle_feedback("bob", KeyframeFeedback::PictureLoss { session_id }));
+        assert!(!scheduler.take_keyframe_at(t0 + Duration::from_millis(100)));
+        assert!(scheduler.take_keyframe_at(t0 + Duration::from_millis(500)));
+
+        assert_eq!(scheduler.forced_keyframes(), 2);
+        assert_eq!(scheduler.coalesced_requests(), 1);
+    }
+}
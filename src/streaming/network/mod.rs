@@ -7,6 +7,8 @@ pub mod webrtc_streamer;
 pub mod quic_streamer;
 pub mod adaptive_bitrate;
 pub mod buffer_manager;
+pub mod keyframe;
+pub mod notice;
 
 use async_trait::async_trait;
 use std::sync::Arc;
@@ -24,8 +26,11 @@ pub use adaptive_bitrate::{
 };
 pub use buffer_manager::{
     StreamBufferManager, BufferConfig, BufferStats, BufferHealth,
-    BufferAlert, BufferAlertType, FramePriority,
+    BufferAlert, BufferAlertType, FramePriority, JitterBuffer, JitterBufferConfig,
+    JitterBufferHealth, LateFramePolicy, FrameArrival,
 };
+pub use keyframe::{KeyframeFeedback, KeyframeRequester, KeyframeScheduler};
+pub use notice::{StreamNotice, StreamOffer, DEFAULT_PAUSED_MESSAGE};
 
 /// Network streamer implementation
 /// 
@@ -100,6 +105,12 @@ impl NetworkStreamerImpl {
     pub fn buffer_manager(&self) -> Arc<StreamBufferManager> {
         self.buffer_manager.clone()
     }
+
+    /// Pass the receive-side jitter buffer health to the adaptive controller
+    pub async fn report_buffer_health(&self) {
+        let health = self.buffer_manager.jitter_health().await;
+        self.adaptive_controller.report_buffer_health(health).await;
+    }
 }
 
 #[async_trait]
@@ -0,0 +1,128 @@
+// Sender-to-viewer stream notices
+//
+// Tells viewers why the picture stopped without tearing the stream down. A
+// paused share keeps its connection, viewer registration and encoder; the
+// viewer shows a card instead of the frozen last frame until the sender
+// resumes. Before anyone watches, an offer describes the stream with an
+// optional snapshot so the invited user can decide whether to join.
+
+use serde::{Deserialize, Serialize};
+
+use crate::file_transfer::preview::Thumbnail;
+use crate::streaming::{Resolution, SessionId, StreamError, StreamResult, StreamType};
+
+/// Card shown to viewers while a stream is paused, unless the sender gives one
+pub const DEFAULT_PAUSED_MESSAGE: &str = "The presenter paused sharing";
+
+/// Notice sent from the sender to its viewers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamNotice {
+    /// Frames stopped; show a paused card until the stream resumes
+    Paused {
+        session_id: SessionId,
+        message: Option<String>,
+    },
+    /// Frames continue, starting with a keyframe
+    Resumed { session_id: SessionId },
+    /// A stream the peer is invited to watch
+    Offered {
+        session_id: SessionId,
+        offer: StreamOffer,
+    },
+}
+
+/// What an invited viewer sees before accepting a stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamOffer {
+    pub stream_type: StreamType,
+    pub resolution: Resolution,
+    /// Snapshot of the current picture, checked with `Thumbnail::validate`
+    /// before it is shown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<Thumbnail>,
+}
+
+impl StreamNotice {
+    /// Session the notice refers to
+    pub fn session_id(&self) -> SessionId {
+        match self {
+            StreamNotice::Paused { session_id, .. }
+            | StreamNotice::Resumed { session_id }
+            | StreamNotice::Offered { session_id, .. } => *session_id,
+        }
+    }
+
+    /// Text for the card a viewer shows, `None` once the picture is back
+    pub fn card_text(&self) -> Option<&str> {
+        match self {
+            StreamNotice::Paused { message, .. } => Some(message.as_deref().unwrap_or(DEFAULT_PAUSED_MESSAGE)),
+            StreamNotice::Resumed { .. } | StreamNotice::Offered { .. } => None,
+        }
+    }
+
+    /// Serialize for the stream control channel
+    pub fn to_bytes(&self) -> StreamResult<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| StreamError::internal(format!("Failed to encode stream notice: {}", e)))
+    }
+
+    /// Parse a control message received from the sender
+    ///
+    /// Snapshots in offers that fail validation are dropped rather than
+    /// failing the whole notice.
+    pub fn from_bytes(data: &[u8]) -> StreamResult<Self> {
+        let mut notice: Self = serde_json::from_slice(data)
+            .map_err(|e| StreamError::network(format!("Invalid stream notice: {}", e)))?;
+        if let StreamNotice::Offered { offer, .. } = &mut notice {
+            if offer.snapshot.as_ref().is_some_and(|snapshot| snapshot.validate().is_err()) {
+                offer.snapshot = None;
+            }
+        }
+        Ok(notice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_notice_round_trip_and_card() {
+        let session_id = Uuid::new_v4();
+        let paused = StreamNotice::Paused { session_id, message: None };
+        let decoded = StreamNotice::from_bytes(&paused.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, paused);
+        assert_eq!(decoded.card_text(), Some(DEFAULT_PAUSED_MESSAGE));
+
+        let resumed = StreamNotice::Resumed { session_id };
+        assert_eq!(resumed.session_id(), session_id);
+        assert_eq!(resumed.card_text(), None);
+    }
+
+    #[test]
+    fn test_offer_drops_invalid_snapshot() {
+        let offered = StreamNotice::Offered {
+            session_id: Uuid::new_v4(),
+            offer: StreamOffer {
+                stream_type: StreamType::Screen,
+                resolution: Resolution { width: 1920, height: 1080 },
+                snapshot: Some(Thumbnail {
+                    width: 4096,
+                    height: 4096,
+                    mime_type: "image/png".to_string(),
+                    data: Vec::new(),
+                }),
+            },
+        };
+
+        match StreamNotice::from_bytes(&offered.to_bytes().unwrap()).unwrap() {
+            StreamNotice::Offered { offer, .. } => {
+                assert_eq!(offer.resolution.width, 1920);
+                assert!(offer.snapshot.is_none());
+            }
+            other => panic!("unexpected notice {:?}", other),
+        }
+    }
+}
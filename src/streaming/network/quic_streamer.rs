@@ -16,7 +16,11 @@ use crate::streaming::{
     EncodedFrame, PeerId, StreamConnection, StreamError, StreamResult, StreamStats, VideoStream,
 };
 use crate::transport::protocols::quic::{QuicTransport, QuicConfig};
-use crate::transport::{PeerAddress, Transport, TransportCapabilities};
+use super::keyframe::KeyframeFeedback;
+use super::notice::StreamNotice;
+use crate::transport::{
+    BackpressureGauge, DeliveryMode, PeerAddress, Transport, TransportCapabilities, Watermarks,
+};
 
 /// QUIC-based video streamer for low-latency streaming
 ///
@@ -29,6 +33,8 @@ pub struct QuicVideoStreamer {
     transport: Arc<QuicTransport>,
     active_streams: Arc<RwLock<HashMap<PeerId, ActiveQuicStream>>>,
     stream_multiplexer: Arc<Mutex<StreamMultiplexer>>,
+    /// Encoded frame bytes handed to `send_frame` but not yet written
+    send_backlog: BackpressureGauge,
 }
 
 /// Configuration for QUIC video streaming
@@ -46,6 +52,8 @@ pub struct QuicStreamerConfig {
     pub idle_timeout: Duration,
     /// Enable 0-RTT for faster connection establishment
     pub enable_0rtt: bool,
+    /// Send backlog thresholds at which frame production should pause
+    pub send_watermarks: Watermarks,
 }
 
 /// Active QUIC streaming session
@@ -109,6 +117,10 @@ impl Default for QuicStreamerConfig {
             frame_buffer_size: 1024 * 1024, // 1MB
             idle_timeout: Duration::from_secs(30),
             enable_0rtt: true,
+            send_watermarks: Watermarks {
+                low: 512 * 1024,
+                high: 2 * 1024 * 1024,
+            },
         }
     }
 }
@@ -131,6 +143,7 @@ impl QuicVideoStreamer {
             .map_err(|e| StreamError::network(format!("Failed to create QUIC transport: {}", e)))?;
 
         Ok(Self {
+            send_backlog: BackpressureGauge::new(config.send_watermarks),
             config,
             transport: Arc::new(transport),
             active_streams: Arc::new(RwLock::new(HashMap::new())),
@@ -197,6 +210,37 @@ impl QuicVideoStreamer {
         quality_level: QualityLevel,
     ) -> StreamResult<()> {
         let frame_size = frame.data.len();
+        self.send_backlog.add(frame_size);
+        let result = self.send_frame_inner(peer_id, frame, quality_level).await;
+        self.send_backlog.release(frame_size);
+        result
+    }
+
+    /// Wait until the send backlog has drained below the low watermark
+    ///
+    /// Encoders should await this (or skip frames while `is_congested` is true)
+    /// before producing the next frame.
+    pub async fn ready(&self) {
+        self.send_backlog.ready().await
+    }
+
+    /// Whether the send backlog is above the high watermark
+    pub fn is_congested(&self) -> bool {
+        self.send_backlog.is_congested()
+    }
+
+    /// Encoded bytes waiting to be written
+    pub fn buffered_bytes(&self) -> usize {
+        self.send_backlog.buffered()
+    }
+
+    async fn send_frame_inner(
+        &self,
+        peer_id: &PeerId,
+        frame: EncodedFrame,
+        quality_level: QualityLevel,
+    ) -> StreamResult<()> {
+        let frame_size = frame.data.len();
         
         // Convert frame to RTP packets
         let rtp_packets = self.frame_to_rtp_packets(frame)?;
@@ -317,6 +361,81 @@ impl QuicVideoStreamer {
         Ok(stats)
     }
 
+    /// Largest control message that can be sent to a peer as a datagram
+    ///
+    /// Returns `None` if there is no stream to the peer or it does not accept datagrams.
+    pub async fn max_control_datagram_size(&self, peer_id: &PeerId) -> Option<usize> {
+        let streams = self.active_streams.read().await;
+        streams.get(peer_id)?.connection.max_datagram_size()
+    }
+
+    /// Send a stream control message such as a keyframe request or quality change
+    ///
+    /// With unreliable delivery a message that fits in a QUIC datagram skips the video
+    /// streams entirely; anything else goes out on its own unidirectional stream so it
+    /// never queues behind frame data. Returns the delivery actually used.
+    pub async fn send_control(
+        &self,
+        peer_id: &PeerId,
+        message: &[u8],
+        delivery: DeliveryMode,
+    ) -> StreamResult<DeliveryMode> {
+        let connection = {
+            let streams = self.active_streams.read().await;
+            streams
+                .get(peer_id)
+                .ok_or_else(|| StreamError::network("Stream not found"))?
+                .connection
+                .clone()
+        };
+
+        if delivery.use_datagram(message.len(), connection.max_datagram_size())
+            && connection
+                .send_datagram(bytes::Bytes::copy_from_slice(message))
+                .is_ok()
+        {
+            return Ok(DeliveryMode::Unreliable);
+        }
+
+        let mut send_stream = connection
+            .open_uni()
+            .await
+            .map_err(|e| StreamError::network(format!("Failed to open control stream: {}", e)))?;
+        send_stream
+            .write_all(message)
+            .await
+            .map_err(|e| StreamError::network(format!("Failed to send control message: {}", e)))?;
+        send_stream
+            .finish()
+            .map_err(|e| StreamError::network(format!("Failed to finish control stream: {}", e)))?;
+
+        Ok(DeliveryMode::Reliable)
+    }
+
+    /// Ask the sender for a keyframe
+    ///
+    /// Feedback goes out as a datagram when possible; a lost request is
+    /// repeated by the viewer's [`KeyframeRequester`](super::keyframe::KeyframeRequester).
+    pub async fn send_keyframe_feedback(
+        &self,
+        peer_id: &PeerId,
+        feedback: &KeyframeFeedback,
+    ) -> StreamResult<()> {
+        let message = feedback.to_bytes()?;
+        self.send_control(peer_id, &message, DeliveryMode::Unreliable).await?;
+        Ok(())
+    }
+
+    /// Tell a viewer the stream paused or resumed
+    ///
+    /// Notices are sent reliably: a viewer that misses a pause would keep
+    /// showing the last frame, and one that misses a resume would keep its card.
+    pub async fn send_notice(&self, peer_id: &PeerId, notice: &StreamNotice) -> StreamResult<()> {
+        let message = notice.to_bytes()?;
+        self.send_control(peer_id, &message, DeliveryMode::Reliable).await?;
+        Ok(())
+    }
+
     /// Adjust stream quality based on network conditions
     pub async fn adjust_quality(
         &self,
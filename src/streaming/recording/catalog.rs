@@ -0,0 +1,333 @@
+// Recordings catalog
+//
+// Searchable view over the recording index kept by the StorageManager.
+// Recordings can be filtered by date, peer, duration and tags, looked up by
+// a short ID prefix, annotated with user tags and notes, and described with
+// the details a player needs.
+//
+// Requirements: 5.5
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use super::storage::{RecordingKind, RecordingMetadata, StorageManager, PEER_TAG_PREFIX};
+use crate::streaming::{SessionId, StreamError, StreamResult, VideoFormat};
+
+/// Filter for listing recordings
+///
+/// Every criterion that is set must match; an empty query matches all
+/// recordings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordingQuery {
+    /// Only recordings started at or after this time
+    pub since: Option<SystemTime>,
+    /// Only recordings started before this time
+    pub until: Option<SystemTime>,
+    /// Only recordings made with this peer
+    pub peer: Option<String>,
+    pub min_duration: Option<Duration>,
+    pub max_duration: Option<Duration>,
+    /// Only recordings carrying all of these tags
+    pub tags: Vec<String>,
+    pub kind: Option<RecordingKind>,
+    /// Return at most this many recordings, newest first
+    pub limit: Option<usize>,
+}
+
+impl RecordingQuery {
+    /// Query matching every recording
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_since(mut self, since: SystemTime) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn with_until(mut self, until: SystemTime) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    pub fn with_peer(mut self, peer: impl Into<String>) -> Self {
+        self.peer = Some(peer.into());
+        self
+    }
+
+    pub fn with_min_duration(mut self, duration: Duration) -> Self {
+        self.min_duration = Some(duration);
+        self
+    }
+
+    pub fn with_max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn with_kind(mut self, kind: RecordingKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether a recording satisfies the query, ignoring the limit
+    pub fn matches(&self, recording: &RecordingMetadata) -> bool {
+        self.since.is_none_or(|since| recording.created_at >= since)
+            && self.until.is_none_or(|until| recording.created_at < until)
+            && self
+                .peer
+                .as_deref()
+                .is_none_or(|peer| recording.peer() == Some(peer))
+            && self.min_duration.is_none_or(|min| recording.duration >= min)
+            && self.max_duration.is_none_or(|max| recording.duration <= max)
+            && self.tags.iter().all(|tag| recording.tags.contains(tag))
+            && self.kind.is_none_or(|kind| recording.kind == kind)
+    }
+}
+
+/// How a recording can be played back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackInfo {
+    pub path: PathBuf,
+    /// Whether the file is still on disk
+    pub available: bool,
+    pub mime_type: String,
+    /// Average bitrate over the whole recording, when known
+    pub average_bitrate_kbps: Option<u64>,
+}
+
+/// A recording with its playback details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingDetails {
+    pub recording: RecordingMetadata,
+    pub peer: Option<String>,
+    pub playback: PlaybackInfo,
+}
+
+/// Searchable recordings catalog
+#[derive(Clone)]
+pub struct RecordingCatalog {
+    storage: Arc<StorageManager>,
+}
+
+impl RecordingCatalog {
+    /// Create a catalog over a recording index
+    pub fn new(storage: Arc<StorageManager>) -> Self {
+        Self { storage }
+    }
+
+    /// Open the index in a recording directory
+    pub fn open(path: PathBuf) -> StreamResult<Self> {
+        Ok(Self::new(Arc::new(StorageManager::new(path)?)))
+    }
+
+    /// Underlying recording index
+    pub fn storage(&self) -> &Arc<StorageManager> {
+        &self.storage
+    }
+
+    /// Recordings matching a query, newest first
+    pub async fn search(&self, query: &RecordingQuery) -> StreamResult<Vec<RecordingMetadata>> {
+        let mut recordings: Vec<_> = self
+            .storage
+            .get_all_recordings()
+            .await?
+            .into_iter()
+            .filter(|r| query.matches(r))
+            .collect();
+
+        recordings.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        if let Some(limit) = query.limit {
+            recordings.truncate(limit);
+        }
+        Ok(recordings)
+    }
+
+    /// Look up a recording by full ID or unique ID prefix
+    pub async fn find(&self, id: &str) -> StreamResult<RecordingMetadata> {
+        let id = id.trim().to_ascii_lowercase();
+        if id.is_empty() {
+            return Err(StreamError::configuration("Recording ID must not be empty"));
+        }
+
+        let mut matches = self
+            .storage
+            .get_all_recordings()
+            .await?
+            .into_iter()
+            .filter(|r| r.session_id.to_string().starts_with(&id));
+        match (matches.next(), matches.next()) {
+            (Some(recording), None) => Ok(recording),
+            (Some(_), Some(_)) => Err(StreamError::configuration(format!(
+                "'{}' matches more than one recording; give more of the ID",
+                id
+            ))),
+            (None, _) => Err(StreamError::session_not_found(id)),
+        }
+    }
+
+    /// A recording with its playback details
+    pub async fn details(&self, id: &str) -> StreamResult<RecordingDetails> {
+        let recording = self.find(id).await?;
+        let available = tokio::fs::try_exists(&recording.file_path).await.unwrap_or(false);
+
+        Ok(Self::describe(recording, available))
+    }
+
+    /// Add and remove user tags
+    ///
+    /// Peer tags are set when the recording is made and cannot be changed.
+    pub async fn tag(&self, session_id: SessionId, add: &[String], remove: &[String]) -> StreamResult<RecordingMetadata> {
+        let add = normalize_tags(add)?;
+        let remove = normalize_tags(remove)?;
+
+        self.storage
+            .update_recording(session_id, |recording| {
+                recording.tags.retain(|tag| !remove.contains(tag));
+                for tag in add {
+                    if !recording.tags.contains(&tag) {
+                        recording.tags.push(tag);
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Replace a recording's notes; empty notes clear them
+    pub async fn set_notes(&self, session_id: SessionId, notes: Option<String>) -> StreamResult<RecordingMetadata> {
+        let notes = notes.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+
+        self.storage
+            .update_recording(session_id, |recording| recording.notes = notes)
+            .await
+    }
+
+    fn describe(recording: RecordingMetadata, available: bool) -> RecordingDetails {
+        let mime_type = match (recording.kind, recording.format) {
+            (RecordingKind::Typescript, _) | (_, None) => "text/plain",
+            (RecordingKind::Video, Some(VideoFormat::MP4)) => "video/mp4",
+            (RecordingKind::Video, Some(VideoFormat::WebM)) => "video/webm",
+            (RecordingKind::Video, Some(VideoFormat::AVI)) => "video/x-msvideo",
+            (RecordingKind::Video, Some(VideoFormat::MOV)) => "video/quicktime",
+        };
+        let secs = recording.duration.as_secs_f64();
+        let average_bitrate_kbps =
+            (recording.kind == RecordingKind::Video && secs > 0.0 && recording.file_size > 0)
+                .then(|| (recording.file_size as f64 * 8.0 / 1000.0 / secs).round() as u64);
+
+        RecordingDetails {
+            peer: recording.peer().map(str::to_string),
+            playback: PlaybackInfo {
+                path: recording.file_path.clone(),
+                available,
+                mime_type: mime_type.to_string(),
+                average_bitrate_kbps,
+            },
+            recording,
+        }
+    }
+}
+
+/// Trim tags, drop empty ones and refuse peer tags
+fn normalize_tags(tags: &[String]) -> StreamResult<Vec<String>> {
+    let mut normalized = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if tag.starts_with(PEER_TAG_PREFIX) {
+            return Err(StreamError::configuration(format!(
+                "Tag '{}' is reserved; peer tags are set when recording",
+                tag
+            )));
+        }
+        normalized.push(tag.to_string());
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(peer: &str, age_secs: u64, duration_secs: u64) -> RecordingMetadata {
+        RecordingMetadata {
+            session_id: SessionId::new_v4(),
+            file_path: PathBuf::from(format!("/tmp/{}.mp4", peer)),
+            kind: RecordingKind::Video,
+            format: Some(VideoFormat::MP4),
+            file_size: 1_000_000,
+            duration: Duration::from_secs(duration_secs),
+            created_at: SystemTime::now() - Duration::from_secs(age_secs),
+            stream_source: "screen".to_string(),
+            quality_preset: "medium".to_string(),
+            tags: vec![format!("{}{}", PEER_TAG_PREFIX, peer)],
+            notes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_filters_and_orders() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = RecordingCatalog::open(dir.path().to_path_buf()).unwrap();
+        let old = recording("alice", 3600, 600);
+        let new = recording("alice", 60, 30);
+        let other = recording("bob", 120, 900);
+        for r in [&old, &new, &other] {
+            catalog.storage().import_recording(r.clone()).await.unwrap();
+        }
+
+        let alice = catalog.search(&RecordingQuery::new().with_peer("alice")).await.unwrap();
+        assert_eq!(
+            alice.iter().map(|r| r.session_id).collect::<Vec<_>>(),
+            vec![new.session_id, old.session_id]
+        );
+
+        let long = RecordingQuery::new().with_min_duration(Duration::from_secs(300));
+        assert_eq!(catalog.search(&long).await.unwrap().len(), 2);
+
+        let recent = RecordingQuery::new()
+            .with_since(SystemTime::now() - Duration::from_secs(600))
+            .with_limit(1);
+        assert_eq!(catalog.search(&recent).await.unwrap()[0].session_id, new.session_id);
+
+        let prefix = &other.session_id.to_string()[..8];
+        let details = catalog.details(prefix).await.unwrap();
+        assert_eq!(details.peer.as_deref(), Some("bob"));
+        assert_eq!(details.playback.mime_type, "video/mp4");
+        assert!(!details.playback.available);
+        assert_eq!(details.playback.average_bitrate_kbps, Some(9));
+    }
+
+    #[tokio::test]
+    async fn test_tags_and_notes() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = RecordingCatalog::open(dir.path().to_path_buf()).unwrap();
+        let r = recording("alice", 60, 30);
+        catalog.storage().import_recording(r.clone()).await.unwrap();
+
+        let tags = vec!["demo".to_string(), " ".to_string(), "review".to_string()];
+        catalog.tag(r.session_id, &tags, &[]).await.unwrap();
+        let updated = catalog.tag(r.session_id, &[], &["review".to_string()]).await.unwrap();
+        assert_eq!(updated.tags, vec!["peer:alice".to_string(), "demo".to_string()]);
+
+        assert!(catalog.tag(r.session_id, &[], &["peer:alice".to_string()]).await.is_err());
+
+        catalog.set_notes(r.session_id, Some("  Release demo ".to_string())).await.unwrap();
+        let demo = catalog.search(&RecordingQuery::new().with_tag("demo")).await.unwrap();
+        assert_eq!(demo[0].notes.as_deref(), Some("Release demo"));
+
+        // The index survives a reload
+        let reopened = RecordingCatalog::open(dir.path().to_path_buf()).unwrap();
+        assert_eq!(reopened.find(&r.session_id.to_string()).await.unwrap().notes.as_deref(), Some("Release demo"));
+    }
+}
@@ -0,0 +1,390 @@
+// Recording export and transcoding
+//
+// Converts finished recordings to a chosen container, quality and length by
+// running the `ffmpeg` command-line tool, and can grab a thumbnail image
+// alongside the export. The recording itself is never modified.
+//
+// Requirements: 5.1, 5.5
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::streaming::recording::{RecordingKind, RecordingMetadata};
+use crate::streaming::{StreamError, StreamResult, VideoFormat};
+
+/// Highest CRF value accepted by both x264 and VP9
+const MAX_CRF: u8 = 51;
+
+/// What to produce from a recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportOptions {
+    pub format: VideoFormat,
+    /// Constant rate factor; lower is better quality. Ignored if a bitrate is set.
+    pub crf: Option<u8>,
+    /// Target video bitrate in kbps
+    pub bitrate_kbps: Option<u32>,
+    /// Drop everything before this offset
+    pub trim_start: Option<Duration>,
+    /// Drop everything after this offset
+    pub trim_end: Option<Duration>,
+    /// Also write a JPEG thumbnail
+    pub thumbnail: Option<ThumbnailOptions>,
+    /// Output file; defaults to `<recording>-export.<ext>` next to the recording
+    pub output: Option<PathBuf>,
+}
+
+/// Thumbnail to grab from the exported range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThumbnailOptions {
+    /// Offset into the export
+    pub at: Duration,
+    /// Width in pixels; height keeps the aspect ratio
+    pub width: u32,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        Self {
+            at: Duration::from_secs(1),
+            width: 320,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Export to a format with the encoder's default quality
+    pub fn new(format: VideoFormat) -> Self {
+        Self {
+            format,
+            crf: None,
+            bitrate_kbps: None,
+            trim_start: None,
+            trim_end: None,
+            thumbnail: None,
+            output: None,
+        }
+    }
+
+    pub fn with_crf(mut self, crf: u8) -> Self {
+        self.crf = Some(crf);
+        self
+    }
+
+    pub fn with_bitrate(mut self, kbps: u32) -> Self {
+        self.bitrate_kbps = Some(kbps);
+        self
+    }
+
+    pub fn with_trim(mut self, start: Option<Duration>, end: Option<Duration>) -> Self {
+        self.trim_start = start;
+        self.trim_end = end;
+        self
+    }
+
+    pub fn with_thumbnail(mut self, thumbnail: ThumbnailOptions) -> Self {
+        self.thumbnail = Some(thumbnail);
+        self
+    }
+
+    pub fn with_output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Check the options against the recording's length
+    ///
+    /// A zero duration means the length is unknown and trims are not checked
+    /// against it.
+    pub fn validate(&self, duration: Duration) -> StreamResult<()> {
+        if let Some(crf) = self.crf {
+            if crf > MAX_CRF {
+                return Err(StreamError::configuration(format!("CRF must be 0-{}, got {}", MAX_CRF, crf)));
+            }
+        }
+        if self.bitrate_kbps == Some(0) {
+            return Err(StreamError::configuration("Bitrate must be greater than zero"));
+        }
+
+        let start = self.trim_start.unwrap_or_default();
+        if let Some(end) = self.trim_end {
+            if end <= start {
+                return Err(StreamError::configuration("Trim end must be after trim start"));
+            }
+        }
+        if !duration.is_zero() && start >= duration {
+            return Err(StreamError::configuration(format!(
+                "Trim start {:?} is past the end of the recording ({:?})",
+                start, duration
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Length of the exported range, if it is bounded
+    fn trim_length(&self) -> Option<Duration> {
+        self.trim_end
+            .map(|end| end.saturating_sub(self.trim_start.unwrap_or_default()))
+    }
+}
+
+/// Files produced by an export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportResult {
+    pub output: PathBuf,
+    pub file_size: u64,
+    pub thumbnail: Option<PathBuf>,
+}
+
+/// Transcodes recordings with ffmpeg
+#[derive(Debug, Clone)]
+pub struct RecordingExporter {
+    program: PathBuf,
+    timeout: Duration,
+}
+
+impl RecordingExporter {
+    /// Use `ffmpeg` from `PATH`
+    pub fn new() -> Self {
+        Self {
+            program: PathBuf::from("ffmpeg"),
+            timeout: Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// Use a specific ffmpeg executable
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Limit how long one export may take
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Export a recording
+    pub async fn export(&self, recording: &RecordingMetadata, options: &ExportOptions) -> StreamResult<ExportResult> {
+        if recording.kind != RecordingKind::Video {
+            return Err(StreamError::unsupported("Only video recordings can be exported"));
+        }
+        if !recording.file_path.exists() {
+            return Err(StreamError::recording(format!(
+                "Recording file is missing: {}",
+                recording.file_path.display()
+            )));
+        }
+        options.validate(recording.duration)?;
+
+        let output = options
+            .output
+            .clone()
+            .unwrap_or_else(|| default_output_path(&recording.file_path, options.format));
+        if output == recording.file_path {
+            return Err(StreamError::configuration("Export would overwrite the recording"));
+        }
+
+        self.run(transcode_args(&recording.file_path, &output, options)).await?;
+
+        let thumbnail = match &options.thumbnail {
+            Some(thumbnail) => {
+                let path = output.with_extension("jpg");
+                self.run(thumbnail_args(&output, &path, thumbnail)).await?;
+                Some(path)
+            }
+            None => None,
+        };
+
+        let file_size = tokio::fs::metadata(&output).await?.len();
+        Ok(ExportResult { output, file_size, thumbnail })
+    }
+
+    async fn run(&self, args: Vec<String>) -> StreamResult<()> {
+        log::debug!("Running {} {}", self.program.display(), args.join(" "));
+
+        let child = tokio::process::Command::new(&self.program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| StreamError::recording(format!("Failed to start {}: {}", self.program.display(), e)))?;
+
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .map_err(|_| StreamError::timeout(format!("Export took longer than {:?}", self.timeout)))?
+            .map_err(|e| StreamError::recording(format!("ffmpeg failed: {}", e)))?;
+
+        if !output.status.success() {
+            // ffmpeg puts the actual error on the last line
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("no output");
+            return Err(StreamError::recording(format!("ffmpeg exited with {}: {}", output.status, reason)));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RecordingExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// File extension for a container
+pub fn format_extension(format: VideoFormat) -> &'static str {
+    match format {
+        VideoFormat::MP4 => "mp4",
+        VideoFormat::WebM => "webm",
+        VideoFormat::AVI => "avi",
+        VideoFormat::MOV => "mov",
+    }
+}
+
+/// Parse a container name such as "mp4"
+pub fn parse_format(name: &str) -> StreamResult<VideoFormat> {
+    match name.to_ascii_lowercase().as_str() {
+        "mp4" => Ok(VideoFormat::MP4),
+        "webm" => Ok(VideoFormat::WebM),
+        "avi" => Ok(VideoFormat::AVI),
+        "mov" => Ok(VideoFormat::MOV),
+        other => Err(StreamError::unsupported(format!(
+            "Unknown export format '{}' (expected mp4, webm, avi or mov)",
+            other
+        ))),
+    }
+}
+
+/// `<recording>-export.<ext>` next to the recording
+fn default_output_path(input: &Path, format: VideoFormat) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "recording".to_string());
+    input.with_file_name(format!("{}-export.{}", stem, format_extension(format)))
+}
+
+/// Seconds with millisecond precision, as ffmpeg expects
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+/// ffmpeg arguments for a transcode
+fn transcode_args(input: &Path, output: &Path, options: &ExportOptions) -> Vec<String> {
+    let mut args: Vec<String> = vec!["-y".into(), "-hide_banner".into(), "-loglevel".into(), "error".into()];
+
+    // Seeking before the input is fast and frame accurate when re-encoding
+    if let Some(start) = options.trim_start {
+        args.extend(["-ss".into(), seconds(start)]);
+    }
+    args.extend(["-i".into(), input.to_string_lossy().into_owned()]);
+    if let Some(length) = options.trim_length() {
+        args.extend(["-t".into(), seconds(length)]);
+    }
+
+    let (video, audio) = match options.format {
+        VideoFormat::WebM => ("libvpx-vp9", "libopus"),
+        VideoFormat::MP4 | VideoFormat::MOV | VideoFormat::AVI => ("libx264", "aac"),
+    };
+    args.extend(["-c:v".into(), video.into()]);
+
+    match (options.bitrate_kbps, options.crf) {
+        (Some(kbps), _) => args.extend(["-b:v".into(), format!("{}k", kbps)]),
+        (None, Some(crf)) => {
+            args.extend(["-crf".into(), crf.to_string()]);
+            // VP9 only honours CRF in constant quality mode
+            if options.format == VideoFormat::WebM {
+                args.extend(["-b:v".into(), "0".into()]);
+            }
+        }
+        (None, None) => {}
+    }
+
+    args.extend(["-c:a".into(), audio.into()]);
+    if matches!(options.format, VideoFormat::MP4 | VideoFormat::MOV) {
+        // Lets players start before the whole file has downloaded
+        args.extend(["-movflags".into(), "+faststart".into()]);
+    }
+
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// ffmpeg arguments to grab one frame as a JPEG
+fn thumbnail_args(input: &Path, output: &Path, thumbnail: &ThumbnailOptions) -> Vec<String> {
+    vec![
+        "-y".into(),
+        "-hide_banner".into(),
+        "-loglevel".into(),
+        "error".into(),
+        "-ss".into(),
+        seconds(thumbnail.at),
+        "-i".into(),
+        input.to_string_lossy().into_owned(),
+        "-frames:v".into(),
+        "1".into(),
+        "-vf".into(),
+        format!("scale={}:-2", thumbnail.width),
+        output.to_string_lossy().into_owned(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcode_arguments() {
+        let input = Path::new("/rec/session.webm");
+        let options = ExportOptions::new(parse_format("MP4").unwrap())
+            .with_crf(23)
+            .with_trim(Some(Duration::from_secs(5)), Some(Duration::from_millis(65_500)));
+        assert!(options.validate(Duration::from_secs(120)).is_ok());
+
+        let output = default_output_path(input, options.format);
+        assert_eq!(output, PathBuf::from("/rec/session-export.mp4"));
+
+        let args = transcode_args(input, &output, &options).join(" ");
+        assert_eq!(
+            args,
+            "-y -hide_banner -loglevel error -ss 5.000 -i /rec/session.webm -t 60.500 \
+             -c:v libx264 -crf 23 -c:a aac -movflags +faststart /rec/session-export.mp4"
+        );
+
+        let webm = ExportOptions::new(VideoFormat::WebM).with_crf(30);
+        let args = transcode_args(input, Path::new("out.webm"), &webm).join(" ");
+        assert!(args.contains("-c:v libvpx-vp9 -crf 30 -b:v 0 -c:a libopus"));
+
+        // An explicit bitrate wins over CRF
+        let capped = ExportOptions::new(VideoFormat::MP4).with_crf(23).with_bitrate(2500);
+        let args = transcode_args(input, Path::new("out.mp4"), &capped).join(" ");
+        assert!(args.contains("-b:v 2500k") && !args.contains("-crf"));
+    }
+
+    #[test]
+    fn test_invalid_options_are_rejected() {
+        let duration = Duration::from_secs(30);
+        assert!(ExportOptions::new(VideoFormat::MP4).with_crf(60).validate(duration).is_err());
+        assert!(ExportOptions::new(VideoFormat::MP4).with_bitrate(0).validate(duration).is_err());
+        assert!(ExportOptions::new(VideoFormat::MP4)
+            .with_trim(Some(Duration::from_secs(10)), Some(Duration::from_secs(5)))
+            .validate(duration)
+            .is_err());
+        assert!(ExportOptions::new(VideoFormat::MP4)
+            .with_trim(Some(Duration::from_secs(40)), None)
+            .validate(duration)
+            .is_err());
+        // Unknown length: only the relative checks apply
+        assert!(ExportOptions::new(VideoFormat::MP4)
+            .with_trim(Some(Duration::from_secs(40)), None)
+            .validate(Duration::ZERO)
+            .is_ok());
+        assert!(parse_format("mkv").is_err());
+    }
+}
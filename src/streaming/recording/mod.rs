@@ -8,17 +8,34 @@
 pub mod recorder;
 pub mod storage;
 pub mod permissions;
+pub mod support;
+pub mod catalog;
+#[cfg(feature = "recording-export")]
+pub mod export;
 
 pub use recorder::{StreamRecorder, RecorderImpl};
-pub use storage::{StorageManager, RecordingMetadata};
+pub use storage::{StorageManager, RecordingKind, RecordingMetadata, default_recording_dir, PEER_TAG_PREFIX};
+pub use catalog::{PlaybackInfo, RecordingCatalog, RecordingDetails, RecordingQuery};
+#[cfg(feature = "recording-export")]
+pub use export::{ExportOptions, ExportResult, RecordingExporter, ThumbnailOptions};
 pub use permissions::{PermissionManager, RecordingPermission};
+pub use support::{
+    RecordingNoticeSender, SupportRecording, SupportRecordingConfig, SupportRecordingInfo, SupportRecordingNotice,
+    SupportSessionKind, SupportSessionRecorder, TypescriptWriter,
+};
 
 use crate::streaming::{
     StreamResult, StreamError,
     RecordingSession, RecordingConfig, RecordingFile, RecordingStatus,
     VideoStream, RecordingState, SessionId,
 };
+use crate::journal::Journal;
+use crate::security::storage::EncryptedStorage;
 use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Journal name for active recordings within the storage directory
+const RECORDING_JOURNAL: &str = "recordings";
 
 /// Recording engine implementation
 /// 
@@ -34,14 +51,29 @@ pub struct RecordingEngineImpl {
 
 impl RecordingEngineImpl {
     /// Create a new recording engine
-    pub fn new(storage_path: std::path::PathBuf) -> StreamResult<Self> {
+    ///
+    /// Active recordings are journaled under `storage_path`, encrypted at rest
+    /// with `encryption`; call [`recover_interrupted`](Self::recover_interrupted)
+    /// at startup to settle any left behind by a crash.
+    pub fn new(storage_path: std::path::PathBuf, encryption: Option<EncryptedStorage>) -> StreamResult<Self> {
+        let journal = Journal::new(storage_path.clone(), RECORDING_JOURNAL, encryption);
         Ok(Self {
-            recorder: StreamRecorder::new()?,
+            recorder: StreamRecorder::new()?.with_journal(Arc::new(journal)),
             storage: StorageManager::new(storage_path)?,
             permissions: PermissionManager::new(),
         })
     }
     
+    /// Index of finished and in-progress recordings
+    pub fn storage(&self) -> &StorageManager {
+        &self.storage
+    }
+    
+    /// Recordings that were active when the process last died, now marked failed
+    pub async fn recover_interrupted(&self) -> StreamResult<Vec<RecordingSession>> {
+        self.recorder.recover_interrupted().await
+    }
+    
     /// Validate recording configuration
     fn validate_config(&self, config: &RecordingConfig) -> StreamResult<()> {
         // Check output path is valid
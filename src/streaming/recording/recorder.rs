@@ -5,6 +5,7 @@
 //
 // Requirements: 5.1, 5.2, 5.4
 
+use crate::journal::Journal;
 use crate::streaming::{
     StreamResult, StreamError,
     RecordingSession, RecordingConfig, RecordingFile, RecordingStatus,
@@ -37,16 +38,60 @@ struct ActiveRecording {
 /// Requirements: 5.1, 5.2, 5.4
 pub struct StreamRecorder {
     active_recordings: Arc<RwLock<HashMap<SessionId, ActiveRecording>>>,
+    journal: Option<Arc<Journal>>,
 }
 
+/// Journal entry kind for a recording session
+const JOURNAL_KIND_RECORDING: &str = "recording";
+
 impl StreamRecorder {
     /// Create a new stream recorder
     pub fn new() -> StreamResult<Self> {
         Ok(Self {
             active_recordings: Arc::new(RwLock::new(HashMap::new())),
+            journal: None,
         })
     }
     
+    /// Journal active recordings so a crash leaves an accurate record of them
+    pub fn with_journal(mut self, journal: Arc<Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+    
+    /// Settle recordings that were active when the process last died
+    /// 
+    /// A recording cannot continue after a restart because the encoder state is
+    /// gone, so each one is returned in the `Error` state and dropped from the
+    /// journal. Whatever was written before the crash stays at `output_path`.
+    pub async fn recover_interrupted(&self) -> StreamResult<Vec<RecordingSession>> {
+        let Some(journal) = &self.journal else {
+            return Ok(Vec::new());
+        };
+        
+        let recovery = journal.recover().await?;
+        let mut interrupted = Vec::new();
+        for entry in recovery.entries.iter().filter(|entry| entry.kind == JOURNAL_KIND_RECORDING) {
+            match entry.decode::<RecordingSession>() {
+                Ok(mut session) => {
+                    session.state = RecordingState::Error;
+                    interrupted.push(session);
+                }
+                Err(e) => log::warn!("Discarding unreadable recording journal entry {}: {}", entry.key, e),
+            }
+            journal.remove(&entry.key).await?;
+        }
+        
+        Ok(interrupted)
+    }
+    
+    async fn journal_session(&self, session: &RecordingSession) -> StreamResult<()> {
+        if let Some(journal) = &self.journal {
+            journal.put(&session.session_id.to_string(), JOURNAL_KIND_RECORDING, session).await?;
+        }
+        Ok(())
+    }
+    
     /// Start recording a video stream
     /// 
     /// Requirements: 5.1, 5.4
@@ -68,6 +113,7 @@ impl StreamRecorder {
         
         // Initialize recording file based on format
         self.initialize_recording_file(&session, &config).await?;
+        self.journal_session(&session).await?;
         
         // Store active recording
         let active = ActiveRecording {
@@ -101,6 +147,9 @@ impl StreamRecorder {
         
         // Finalize the recording file
         self.finalize_recording_file(&active).await?;
+        if let Some(journal) = &self.journal {
+            journal.remove(&session.session_id.to_string()).await?;
+        }
         
         // Calculate duration
         let duration = SystemTime::now()
@@ -142,7 +191,7 @@ impl StreamRecorder {
         active.session.state = RecordingState::Paused;
         active.paused_at = Some(SystemTime::now());
         
-        Ok(())
+        self.journal_session(&active.session).await
     }
     
     /// Resume a paused recording
@@ -171,7 +220,7 @@ impl StreamRecorder {
         active.session.state = RecordingState::Recording;
         active.paused_at = None;
         
-        Ok(())
+        self.journal_session(&active.session).await
     }
     
     /// Get the status of a recording session
@@ -5,6 +5,7 @@
 //
 // Requirements: 5.5
 
+use crate::journal::atomic_write;
 use crate::streaming::{
     StreamResult, StreamError,
     RecordingSession, RecordingFile, SessionId, VideoFormat,
@@ -24,13 +25,47 @@ use tokio::sync::RwLock;
 pub struct RecordingMetadata {
     pub session_id: SessionId,
     pub file_path: PathBuf,
-    pub format: VideoFormat,
+    #[serde(default)]
+    pub kind: RecordingKind,
+    /// Container format; `None` for terminal typescripts
+    pub format: Option<VideoFormat>,
     pub file_size: u64,
     pub duration: Duration,
     pub created_at: SystemTime,
     pub stream_source: String,
     pub quality_preset: String,
     pub tags: Vec<String>,
+    /// Free-form notes added by the user
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl RecordingMetadata {
+    /// Peer the recording was made with, from its `peer:` tag
+    pub fn peer(&self) -> Option<&str> {
+        self.tags.iter().find_map(|tag| tag.strip_prefix(PEER_TAG_PREFIX))
+    }
+}
+
+/// Tag prefix identifying the peer a recording was made with
+pub const PEER_TAG_PREFIX: &str = "peer:";
+
+/// What a recording captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RecordingKind {
+    /// Encoded video of a stream
+    #[default]
+    Video,
+    /// Terminal output in `script(1)` typescript form
+    Typescript,
+}
+
+/// Default directory for recordings
+pub fn default_recording_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("kizuna")
+        .join("recordings")
 }
 
 /// Storage configuration
@@ -148,13 +183,15 @@ impl StorageManager {
         let metadata = RecordingMetadata {
             session_id: session.session_id,
             file_path: session.output_path.clone(),
-            format: session.format,
+            kind: RecordingKind::Video,
+            format: Some(session.format),
             file_size: 0,
             duration: Duration::ZERO,
             created_at: SystemTime::now(),
             stream_source: "unknown".to_string(),
             quality_preset: "medium".to_string(),
             tags: Vec::new(),
+            notes: None,
         };
         
         self.metadata
@@ -188,6 +225,35 @@ impl StorageManager {
         Ok(())
     }
     
+    /// Register a recording written outside the recorder, such as a typescript
+    pub async fn import_recording(&self, metadata: RecordingMetadata) -> StreamResult<()> {
+        self.metadata
+            .write().await
+            .insert(metadata.session_id, metadata);
+        
+        self.save_metadata().await
+    }
+    
+    /// Record the final size and duration of an imported recording
+    pub async fn complete_recording(
+        &self,
+        session_id: SessionId,
+        file_size: u64,
+        duration: Duration,
+    ) -> StreamResult<()> {
+        {
+            let mut metadata_map = self.metadata.write().await;
+            
+            let metadata = metadata_map
+                .get_mut(&session_id)
+                .ok_or_else(|| StreamError::session_not_found(session_id))?;
+            metadata.file_size = file_size;
+            metadata.duration = duration;
+        }
+        
+        self.save_metadata().await
+    }
+    
     /// Get all recording metadata
     /// 
     /// Requirements: 5.5
@@ -336,7 +402,7 @@ impl StorageManager {
             fs::create_dir_all(parent).await?;
         }
         
-        fs::write(&self.metadata_file, json).await?;
+        atomic_write(&self.metadata_file, json.as_bytes()).await?;
         
         Ok(())
     }
@@ -351,7 +417,11 @@ impl StorageManager {
         let loaded: HashMap<SessionId, RecordingMetadata> = serde_json::from_str(&json)
             .map_err(|e| StreamError::internal(format!("Deserialization error: {}", e)))?;
         
-        *self.metadata.blocking_write() = loaded;
+        // Only called on a fresh manager, so the lock is free; unlike
+        // blocking_write this also works inside the async runtime
+        *self.metadata
+            .try_write()
+            .map_err(|_| StreamError::internal("Recording index is locked"))? = loaded;
         
         Ok(())
     }
@@ -371,6 +441,28 @@ impl StorageManager {
         Ok(())
     }
     
+    /// Change a recording's metadata and save the index
+    ///
+    /// Returns the updated metadata.
+    pub async fn update_recording<F>(&self, session_id: SessionId, update: F) -> StreamResult<RecordingMetadata>
+    where
+        F: FnOnce(&mut RecordingMetadata),
+    {
+        let updated = {
+            let mut metadata_map = self.metadata.write().await;
+            
+            let metadata = metadata_map
+                .get_mut(&session_id)
+                .ok_or_else(|| StreamError::session_not_found(session_id))?;
+            update(metadata);
+            metadata.clone()
+        };
+        
+        self.save_metadata().await?;
+        
+        Ok(updated)
+    }
+    
     /// Search recordings by tags
     /// 
     /// Requirements: 5.5
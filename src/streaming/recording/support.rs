@@ -0,0 +1,586 @@
+// Support session recording
+//
+// Optionally records remote support: video of screen sessions under remote
+// control and typescripts of remote shell sessions. Recording only starts once
+// both the local user and the remote peer have been told, recordings are
+// indexed by the StorageManager with support tags, and every start and stop is
+// written to the command execution audit log.
+
+use crate::command_execution::audit::{AuditEventType, AuditLogEntry, AuditLogger, AuditSeverity};
+use crate::command_execution::notification::NotificationBackend;
+use crate::command_execution::types::{Notification, NotificationPriority, NotificationType};
+use crate::streaming::recording::{
+    default_recording_dir, RecordingEngineImpl, RecordingKind, RecordingMetadata, PEER_TAG_PREFIX,
+};
+use crate::streaming::{
+    PeerId, RecordingConfig, RecordingEngine, RecordingSession, SessionId, StreamError, StreamResult,
+    VideoFormat, VideoStream,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+/// Tag on every support recording in the storage index
+pub const SUPPORT_RECORDING_TAG: &str = "support";
+
+/// Kind of support session being recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SupportSessionKind {
+    /// Screen share with remote input control
+    RemoteControl,
+    /// Remote shell on a pseudo-terminal
+    Shell,
+}
+
+impl SupportSessionKind {
+    fn tag(self) -> &'static str {
+        match self {
+            Self::RemoteControl => "remote-control",
+            Self::Shell => "shell",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::RemoteControl => "remote control",
+            Self::Shell => "remote shell",
+        }
+    }
+}
+
+/// Support recording settings
+#[derive(Debug, Clone)]
+pub struct SupportRecordingConfig {
+    /// Record support sessions at all
+    pub enabled: bool,
+    /// Directory for recordings, normally the one the StorageManager indexes
+    pub output_dir: PathBuf,
+    /// Container for screen recordings
+    pub video_format: VideoFormat,
+}
+
+impl Default for SupportRecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            output_dir: default_recording_dir(),
+            video_format: VideoFormat::MP4,
+        }
+    }
+}
+
+/// Tells a party that a session is or is no longer being recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupportRecordingNotice {
+    pub recording_id: SessionId,
+    pub session_kind: SupportSessionKind,
+    /// The remote peer taking part in the session
+    pub peer_id: PeerId,
+    /// True when recording starts, false when it stops
+    pub recording: bool,
+}
+
+/// Delivers recording notices to the remote peer
+#[async_trait]
+pub trait RecordingNoticeSender: Send + Sync {
+    async fn send_notice(&self, notice: &SupportRecordingNotice) -> StreamResult<()>;
+}
+
+/// Writes terminal output as a `script(1)` typescript with a timing file
+/// that `scriptreplay` can play back
+pub struct TypescriptWriter {
+    data: File,
+    timing: File,
+    last_write: Instant,
+    started: Instant,
+    bytes: u64,
+}
+
+impl TypescriptWriter {
+    /// Create the typescript and its `.timing` companion
+    pub async fn create(path: &Path) -> StreamResult<Self> {
+        let mut data = Self::open(path).await?;
+        let timing = Self::open(&Self::timing_path(path)).await?;
+
+        let header = format!("Script started on {} [kizuna support session]\n", Utc::now().to_rfc2822());
+        data.write_all(header.as_bytes()).await?;
+
+        let now = Instant::now();
+        Ok(Self {
+            data,
+            timing,
+            last_write: now,
+            started: now,
+            bytes: header.len() as u64,
+        })
+    }
+
+    /// Path of the timing file for a typescript
+    pub fn timing_path(path: &Path) -> PathBuf {
+        path.with_extension("timing")
+    }
+
+    async fn open(path: &Path) -> StreamResult<File> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        Ok(options.open(path).await?)
+    }
+
+    /// Append a chunk of terminal output
+    pub async fn write(&mut self, output: &[u8]) -> StreamResult<()> {
+        if output.is_empty() {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let delay = now.duration_since(self.last_write).as_secs_f64();
+        self.last_write = now;
+
+        self.timing
+            .write_all(format!("{:.6} {}\n", delay, output.len()).as_bytes())
+            .await?;
+        self.data.write_all(output).await?;
+        self.bytes += output.len() as u64;
+        Ok(())
+    }
+
+    /// Close the typescript, returning its size and length
+    pub async fn finish(mut self) -> StreamResult<(u64, Duration)> {
+        let footer = format!("\nScript done on {}\n", Utc::now().to_rfc2822());
+        self.data.write_all(footer.as_bytes()).await?;
+        self.bytes += footer.len() as u64;
+
+        self.data.sync_all().await?;
+        self.timing.sync_all().await?;
+        Ok((self.bytes, self.started.elapsed()))
+    }
+}
+
+enum Capture {
+    Video(RecordingSession),
+    Typescript(TypescriptWriter),
+}
+
+/// Details of a recorded support session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupportRecordingInfo {
+    pub recording_id: SessionId,
+    pub session_kind: SupportSessionKind,
+    pub peer_id: PeerId,
+    pub path: PathBuf,
+    pub started_at: SystemTime,
+}
+
+/// A support session being recorded
+pub struct SupportRecording {
+    info: SupportRecordingInfo,
+    capture: Capture,
+}
+
+impl SupportRecording {
+    pub fn info(&self) -> &SupportRecordingInfo {
+        &self.info
+    }
+
+    /// Append shell output; only valid for shell recordings
+    pub async fn write_output(&mut self, output: &[u8]) -> StreamResult<()> {
+        match &mut self.capture {
+            Capture::Typescript(writer) => writer.write(output).await,
+            Capture::Video(_) => Err(StreamError::invalid_state("Screen recordings do not take shell output")),
+        }
+    }
+}
+
+/// Records support sessions with notice to both parties and an audit trail
+pub struct SupportSessionRecorder {
+    config: SupportRecordingConfig,
+    engine: Arc<RecordingEngineImpl>,
+    audit: Arc<dyn AuditLogger>,
+    local_notifier: Option<Arc<dyn NotificationBackend>>,
+    remote_notifier: Arc<dyn RecordingNoticeSender>,
+}
+
+impl SupportSessionRecorder {
+    /// Create a recorder; `local_notifier` is required for recording to start
+    pub fn new(
+        config: SupportRecordingConfig,
+        engine: Arc<RecordingEngineImpl>,
+        audit: Arc<dyn AuditLogger>,
+        local_notifier: Option<Arc<dyn NotificationBackend>>,
+        remote_notifier: Arc<dyn RecordingNoticeSender>,
+    ) -> Self {
+        Self {
+            config,
+            engine,
+            audit,
+            local_notifier,
+            remote_notifier,
+        }
+    }
+
+    /// Whether support sessions are recorded
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Record the screen shared during a remote control session
+    pub async fn start_screen_recording(
+        &self,
+        stream: VideoStream,
+        peer_id: impl Into<PeerId>,
+    ) -> StreamResult<SupportRecording> {
+        let peer_id = peer_id.into();
+        let recording_id = Uuid::new_v4();
+        let extension = match self.config.video_format {
+            VideoFormat::WebM => "webm",
+            _ => "mp4",
+        };
+        let path = self.recording_path(recording_id, SupportSessionKind::RemoteControl, extension);
+        self.announce(recording_id, SupportSessionKind::RemoteControl, &peer_id).await?;
+
+        let config = RecordingConfig {
+            output_path: path.clone(),
+            format: self.config.video_format,
+            quality: stream.quality.clone(),
+            max_file_size: None,
+            max_duration: None,
+        };
+        let session = match self.engine.start_recording(stream, config).await {
+            Ok(session) => session,
+            Err(e) => {
+                self.withdraw(recording_id, SupportSessionKind::RemoteControl, &peer_id).await;
+                return Err(e);
+            }
+        };
+        self.engine
+            .storage()
+            .add_tags(session.session_id, Self::tags(SupportSessionKind::RemoteControl, &peer_id))
+            .await?;
+
+        let recording = SupportRecording {
+            info: SupportRecordingInfo {
+                recording_id: session.session_id,
+                session_kind: SupportSessionKind::RemoteControl,
+                peer_id,
+                path,
+                started_at: SystemTime::now(),
+            },
+            capture: Capture::Video(session),
+        };
+        self.audit_event(&recording.info, AuditEventType::SupportRecordingStarted, None).await;
+        Ok(recording)
+    }
+
+    /// Record a remote shell session as a typescript
+    pub async fn start_shell_recording(&self, peer_id: impl Into<PeerId>) -> StreamResult<SupportRecording> {
+        let peer_id = peer_id.into();
+        let recording_id = Uuid::new_v4();
+        let path = self.recording_path(recording_id, SupportSessionKind::Shell, "typescript");
+        self.announce(recording_id, SupportSessionKind::Shell, &peer_id).await?;
+
+        let writer = async {
+            tokio::fs::create_dir_all(&self.config.output_dir).await?;
+            TypescriptWriter::create(&path).await
+        }
+        .await;
+        let writer = match writer {
+            Ok(writer) => writer,
+            Err(e) => {
+                self.withdraw(recording_id, SupportSessionKind::Shell, &peer_id).await;
+                return Err(e);
+            }
+        };
+
+        self.engine
+            .storage()
+            .import_recording(RecordingMetadata {
+                session_id: recording_id,
+                file_path: path.clone(),
+                kind: RecordingKind::Typescript,
+                format: None,
+                file_size: 0,
+                duration: Duration::ZERO,
+                created_at: SystemTime::now(),
+                stream_source: format!("shell:{}", peer_id),
+                quality_preset: "n/a".to_string(),
+                tags: Self::tags(SupportSessionKind::Shell, &peer_id),
+                notes: None,
+            })
+            .await?;
+
+        let recording = SupportRecording {
+            info: SupportRecordingInfo {
+                recording_id,
+                session_kind: SupportSessionKind::Shell,
+                peer_id,
+                path,
+                started_at: SystemTime::now(),
+            },
+            capture: Capture::Typescript(writer),
+        };
+        self.audit_event(&recording.info, AuditEventType::SupportRecordingStarted, None).await;
+        Ok(recording)
+    }
+
+    /// Finish a recording, index its final size and notify both parties
+    pub async fn stop_recording(&self, recording: SupportRecording) -> StreamResult<()> {
+        let SupportRecording { info, capture } = recording;
+
+        let result = match capture {
+            Capture::Video(session) => self.engine.stop_recording(session).await.map(|file| file.file_size),
+            Capture::Typescript(writer) => match writer.finish().await {
+                Ok((size, duration)) => self
+                    .engine
+                    .storage()
+                    .complete_recording(info.recording_id, size, duration)
+                    .await
+                    .map(|_| size),
+                Err(e) => Err(e),
+            },
+        };
+
+        let outcome = match &result {
+            Ok(size) => format!("{} bytes", size),
+            Err(e) => format!("failed: {}", e),
+        };
+        self.audit_event(&info, AuditEventType::SupportRecordingStopped, Some(outcome)).await;
+        self.withdraw(info.recording_id, info.session_kind, &info.peer_id).await;
+
+        result.map(|_| ())
+    }
+
+    /// Tell both parties recording is starting; refuse if either cannot be told
+    async fn announce(&self, recording_id: SessionId, kind: SupportSessionKind, peer_id: &str) -> StreamResult<()> {
+        if !self.config.enabled {
+            return Err(StreamError::configuration("Support session recording is disabled"));
+        }
+
+        let local = self
+            .local_notifier
+            .as_ref()
+            .ok_or_else(|| StreamError::permission("Cannot record without notifying the local user"))?;
+        local
+            .show_notification(&Self::local_notice(kind, peer_id, true))
+            .map_err(|e| StreamError::permission(format!("Failed to notify the local user: {}", e)))?;
+
+        let notice = SupportRecordingNotice {
+            recording_id,
+            session_kind: kind,
+            peer_id: peer_id.to_string(),
+            recording: true,
+        };
+        if let Err(e) = self.remote_notifier.send_notice(&notice).await {
+            let _ = local.show_notification(&Self::local_notice(kind, peer_id, false));
+            return Err(StreamError::permission(format!("Failed to notify {}: {}", peer_id, e)));
+        }
+        Ok(())
+    }
+
+    /// Tell both parties recording has stopped
+    async fn withdraw(&self, recording_id: SessionId, kind: SupportSessionKind, peer_id: &str) {
+        if let Some(local) = &self.local_notifier {
+            if let Err(e) = local.show_notification(&Self::local_notice(kind, peer_id, false)) {
+                log::warn!("Failed to notify the local user that recording stopped: {}", e);
+            }
+        }
+
+        let notice = SupportRecordingNotice {
+            recording_id,
+            session_kind: kind,
+            peer_id: peer_id.to_string(),
+            recording: false,
+        };
+        if let Err(e) = self.remote_notifier.send_notice(&notice).await {
+            log::warn!("Failed to notify {} that recording stopped: {}", peer_id, e);
+        }
+    }
+
+    fn local_notice(kind: SupportSessionKind, peer_id: &str, recording: bool) -> Notification {
+        let (title, message) = if recording {
+            ("Session is being recorded", format!("This {} session with {} is being recorded.", kind.label(), peer_id))
+        } else {
+            ("Recording stopped", format!("The {} session with {} is no longer recorded.", kind.label(), peer_id))
+        };
+        Notification {
+            notification_id: Uuid::new_v4(),
+            title: title.to_string(),
+            message,
+            notification_type: NotificationType::Info,
+            priority: if recording { NotificationPriority::High } else { NotificationPriority::Normal },
+            duration: None,
+            actions: Vec::new(),
+            sender: peer_id.to_string(),
+            image: None,
+            launch: None,
+        }
+    }
+
+    async fn audit_event(&self, recording: &SupportRecordingInfo, event_type: AuditEventType, outcome: Option<String>) {
+        let mut details = format!(
+            "{} recording {} at {}",
+            recording.session_kind.label(),
+            recording.recording_id,
+            recording.path.display()
+        );
+        if let Some(outcome) = outcome {
+            details.push_str(&format!(" ({})", outcome));
+        }
+
+        let entry = AuditLogEntry {
+            log_id: Uuid::new_v4(),
+            event_type,
+            timestamp: Utc::now(),
+            peer_id: recording.peer_id.clone(),
+            request_id: Some(recording.recording_id),
+            command_preview: None,
+            risk_level: None,
+            decision: None,
+            decided_by: None,
+            details,
+            severity: AuditSeverity::Info,
+        };
+        if let Err(e) = self.audit.log_event(entry).await {
+            log::error!("Failed to audit support recording {}: {}", recording.recording_id, e);
+        }
+    }
+
+    fn recording_path(&self, recording_id: SessionId, kind: SupportSessionKind, extension: &str) -> PathBuf {
+        self.config
+            .output_dir
+            .join(format!("support-{}-{}.{}", kind.tag(), recording_id, extension))
+    }
+
+    fn tags(kind: SupportSessionKind, peer_id: &str) -> Vec<String> {
+        vec![
+            SUPPORT_RECORDING_TAG.to_string(),
+            kind.tag().to_string(),
+            format!("{}{}", PEER_TAG_PREFIX, peer_id),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_execution::audit::SqliteAuditLogger;
+    use crate::command_execution::error::CommandResult;
+    use crate::command_execution::notification::NotificationCapabilities;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingBackend(Mutex<Vec<String>>);
+
+    impl NotificationBackend for RecordingBackend {
+        fn show_notification(&self, notification: &Notification) -> CommandResult<()> {
+            self.0.lock().unwrap().push(notification.title.clone());
+            Ok(())
+        }
+
+        fn is_supported(&self) -> bool {
+            true
+        }
+
+        fn get_capabilities(&self) -> NotificationCapabilities {
+            NotificationCapabilities {
+                supports_actions: false,
+                supports_duration: false,
+                supports_priority: false,
+                supports_icons: false,
+                supports_images: false,
+                supports_activation: false,
+                supports_replace: false,
+                supports_mirroring: false,
+                max_title_length: None,
+                max_message_length: None,
+            }
+        }
+    }
+
+    struct RemoteNotices {
+        reachable: bool,
+        sent: Mutex<Vec<bool>>,
+    }
+
+    #[async_trait]
+    impl RecordingNoticeSender for RemoteNotices {
+        async fn send_notice(&self, notice: &SupportRecordingNotice) -> StreamResult<()> {
+            if !self.reachable {
+                return Err(StreamError::network("peer unreachable"));
+            }
+            self.sent.lock().unwrap().push(notice.recording);
+            Ok(())
+        }
+    }
+
+    fn setup(
+        dir: &Path,
+        reachable: bool,
+    ) -> (SupportSessionRecorder, Arc<RecordingEngineImpl>, Arc<SqliteAuditLogger>, Arc<RemoteNotices>) {
+        let engine = Arc::new(RecordingEngineImpl::new(dir.to_path_buf(), None).unwrap());
+        let audit = Arc::new(SqliteAuditLogger::new(dir.join("audit.db")).unwrap());
+        let remote = Arc::new(RemoteNotices {
+            reachable,
+            sent: Mutex::new(Vec::new()),
+        });
+        let config = SupportRecordingConfig {
+            enabled: true,
+            output_dir: dir.to_path_buf(),
+            video_format: VideoFormat::MP4,
+        };
+        let recorder = SupportSessionRecorder::new(
+            config,
+            engine.clone(),
+            audit.clone(),
+            Some(Arc::new(RecordingBackend::default())),
+            remote.clone(),
+        );
+        (recorder, engine, audit, remote)
+    }
+
+    #[tokio::test]
+    async fn test_shell_session_recorded_indexed_and_audited() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, engine, audit, remote) = setup(dir.path(), true);
+
+        let mut recording = recorder.start_shell_recording("helpdesk").await.unwrap();
+        recording.write_output(b"$ uptime\r\n").await.unwrap();
+        recording.write_output(b" 10:00 up 3 days\r\n").await.unwrap();
+        let info = recording.info().clone();
+        recorder.stop_recording(recording).await.unwrap();
+
+        let typescript = std::fs::read_to_string(&info.path).unwrap();
+        assert!(typescript.starts_with("Script started on"));
+        assert!(typescript.contains("$ uptime\r\n 10:00 up 3 days"));
+        let timing = std::fs::read_to_string(TypescriptWriter::timing_path(&info.path)).unwrap();
+        assert_eq!(timing.lines().count(), 2);
+
+        let indexed = engine.storage().get_recording(info.recording_id).await.unwrap();
+        assert_eq!(indexed.kind, RecordingKind::Typescript);
+        assert_eq!(indexed.file_size, typescript.len() as u64);
+        let tagged = engine.storage().search_by_tags(&["peer:helpdesk".to_string()]).await.unwrap();
+        assert_eq!(tagged.len(), 1);
+
+        let logs = audit.get_logs(None).await.unwrap();
+        assert_eq!(logs.len(), 2);
+        assert!(logs.iter().all(|log| log.peer_id == "helpdesk"));
+        assert_eq!(*remote.sent.lock().unwrap(), vec![true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_recording_refused_when_peer_cannot_be_notified() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, engine, audit, _) = setup(dir.path(), false);
+
+        let result = recorder.start_shell_recording("helpdesk").await;
+        assert!(matches!(result, Err(ref e) if e.to_string().contains("helpdesk")));
+        assert!(engine.storage().get_all_recordings().await.unwrap().is_empty());
+        assert!(audit.get_logs(None).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+// Linux Input Injector
+//
+// Drives the X11 pointer and keyboard through the `xdotool` utility, which
+// also works for XWayland applications.
+
+use super::{InputEvent, InputInjector, Key, MouseButton};
+use crate::streaming::{StreamError, StreamResult};
+use std::process::Command;
+
+/// Linux input injector using xdotool
+pub struct LinuxInputInjector;
+
+impl LinuxInputInjector {
+    /// Create a new Linux input injector
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Run xdotool with the given arguments
+    fn run(&self, args: &[&str]) -> StreamResult<()> {
+        let output = Command::new("xdotool")
+            .args(args)
+            .output()
+            .map_err(|e| StreamError::unsupported(format!("Failed to run xdotool: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(StreamError::internal(format!(
+                "xdotool failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn button_number(button: MouseButton) -> &'static str {
+        match button {
+            MouseButton::Left => "1",
+            MouseButton::Middle => "2",
+            MouseButton::Right => "3",
+        }
+    }
+
+    /// X keysym name for a key
+    fn keysym(key: Key) -> String {
+        match key {
+            Key::Char(c) if c.is_ascii_alphanumeric() => c.to_string(),
+            Key::Char(c) => format!("U{:04X}", c as u32),
+            Key::Enter => "Return".into(),
+            Key::Tab => "Tab".into(),
+            Key::Escape => "Escape".into(),
+            Key::Backspace => "BackSpace".into(),
+            Key::Delete => "Delete".into(),
+            Key::Space => "space".into(),
+            Key::Left => "Left".into(),
+            Key::Right => "Right".into(),
+            Key::Up => "Up".into(),
+            Key::Down => "Down".into(),
+            Key::Home => "Home".into(),
+            Key::End => "End".into(),
+            Key::PageUp => "Prior".into(),
+            Key::PageDown => "Next".into(),
+            Key::Shift => "Shift_L".into(),
+            Key::Control => "Control_L".into(),
+            Key::Alt => "Alt_L".into(),
+            Key::Meta => "Super_L".into(),
+            Key::F(n) => format!("F{}", n),
+        }
+    }
+
+    /// Click a wheel button once per notch
+    fn scroll(&self, amount: i32, negative: &str, positive: &str) -> StreamResult<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let button = if amount < 0 { negative } else { positive };
+        let repeat = amount.unsigned_abs().to_string();
+        self.run(&["click", "--repeat", &repeat, button])
+    }
+}
+
+impl Default for LinuxInputInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputInjector for LinuxInputInjector {
+    fn inject(&self, event: &InputEvent) -> StreamResult<()> {
+        match event {
+            InputEvent::MouseMove { x, y } => self.run(&["mousemove", &x.to_string(), &y.to_string()]),
+            InputEvent::MouseButton { button, pressed } => {
+                let action = if *pressed { "mousedown" } else { "mouseup" };
+                self.run(&[action, Self::button_number(*button)])
+            }
+            InputEvent::Scroll { dx, dy } => {
+                self.scroll(*dy, "4", "5")?;
+                self.scroll(*dx, "6", "7")
+            }
+            InputEvent::Key { key, pressed } => {
+                let action = if *pressed { "keydown" } else { "keyup" };
+                self.run(&[action, &Self::keysym(*key)])
+            }
+            InputEvent::Text(text) => self.run(&["type", "--delay", "0", "--", text]),
+        }
+    }
+
+    fn is_supported(&self) -> bool {
+        std::env::var_os("DISPLAY").is_some()
+            && Command::new("xdotool")
+                .arg("version")
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false)
+    }
+}
@@ -0,0 +1,138 @@
+// macOS Input Injector
+//
+// Posts synthetic Quartz events with Core Graphics. The process needs the
+// Accessibility permission, otherwise macOS silently drops the events.
+
+use super::{InputEvent, InputInjector, Key, MouseButton};
+use crate::streaming::{StreamError, StreamResult};
+use core_graphics::event::{CGEvent, CGEventTapLocation, CGEventType, CGKeyCode, CGMouseButton, ScrollEventUnit};
+use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+use core_graphics::geometry::CGPoint;
+use std::sync::Mutex;
+
+/// macOS input injector using Quartz events
+pub struct MacOSInputInjector {
+    /// Buttons held down, so moves are sent as drags
+    pressed: Mutex<Option<MouseButton>>,
+}
+
+impl MacOSInputInjector {
+    /// Create a new macOS input injector
+    pub fn new() -> Self {
+        Self {
+            pressed: Mutex::new(None),
+        }
+    }
+
+    fn source() -> StreamResult<CGEventSource> {
+        CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+            .map_err(|_| StreamError::internal("Failed to create event source"))
+    }
+
+    fn post(event: Result<CGEvent, ()>) -> StreamResult<()> {
+        event
+            .map_err(|_| StreamError::internal("Failed to create input event"))?
+            .post(CGEventTapLocation::HID);
+        Ok(())
+    }
+
+    fn cursor_position() -> StreamResult<CGPoint> {
+        CGEvent::new(Self::source()?)
+            .map(|event| event.location())
+            .map_err(|_| StreamError::internal("Failed to read cursor position"))
+    }
+
+    /// Virtual key code for keys that have one
+    fn key_code(key: Key) -> Option<CGKeyCode> {
+        Some(match key {
+            Key::Char(_) => return None,
+            Key::Enter => 36,
+            Key::Tab => 48,
+            Key::Space => 49,
+            Key::Backspace => 51,
+            Key::Escape => 53,
+            Key::Meta => 55,
+            Key::Shift => 56,
+            Key::Alt => 58,
+            Key::Control => 59,
+            Key::Delete => 117,
+            Key::Home => 115,
+            Key::End => 119,
+            Key::PageUp => 116,
+            Key::PageDown => 121,
+            Key::Left => 123,
+            Key::Right => 124,
+            Key::Down => 125,
+            Key::Up => 126,
+            Key::F(n) => {
+                const F_KEYS: [CGKeyCode; 20] = [
+                    122, 120, 99, 118, 96, 97, 98, 100, 101, 109, 103, 111, 105, 107, 113, 106, 64, 79, 80, 90,
+                ];
+                return F_KEYS.get(usize::from(n).checked_sub(1)?).copied();
+            }
+        })
+    }
+
+    /// Post a key event that carries text instead of a key code
+    fn post_text(text: &str, keydown: bool) -> StreamResult<()> {
+        let event = CGEvent::new_keyboard_event(Self::source()?, 0, keydown)
+            .map_err(|_| StreamError::internal("Failed to create input event"))?;
+        event.set_string(text);
+        event.post(CGEventTapLocation::HID);
+        Ok(())
+    }
+}
+
+impl Default for MacOSInputInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputInjector for MacOSInputInjector {
+    fn inject(&self, event: &InputEvent) -> StreamResult<()> {
+        match event {
+            InputEvent::MouseMove { x, y } => {
+                let point = CGPoint::new(f64::from(*x), f64::from(*y));
+                let (event_type, button) = match *self.pressed.lock().unwrap() {
+                    Some(MouseButton::Left) => (CGEventType::LeftMouseDragged, CGMouseButton::Left),
+                    Some(MouseButton::Right) => (CGEventType::RightMouseDragged, CGMouseButton::Right),
+                    Some(MouseButton::Middle) => (CGEventType::OtherMouseDragged, CGMouseButton::Center),
+                    None => (CGEventType::MouseMoved, CGMouseButton::Left),
+                };
+                Self::post(CGEvent::new_mouse_event(Self::source()?, event_type, point, button))
+            }
+            InputEvent::MouseButton { button, pressed } => {
+                let (event_type, cg_button) = match (button, pressed) {
+                    (MouseButton::Left, true) => (CGEventType::LeftMouseDown, CGMouseButton::Left),
+                    (MouseButton::Left, false) => (CGEventType::LeftMouseUp, CGMouseButton::Left),
+                    (MouseButton::Right, true) => (CGEventType::RightMouseDown, CGMouseButton::Right),
+                    (MouseButton::Right, false) => (CGEventType::RightMouseUp, CGMouseButton::Right),
+                    (MouseButton::Middle, true) => (CGEventType::OtherMouseDown, CGMouseButton::Center),
+                    (MouseButton::Middle, false) => (CGEventType::OtherMouseUp, CGMouseButton::Center),
+                };
+                *self.pressed.lock().unwrap() = pressed.then_some(*button);
+                let point = Self::cursor_position()?;
+                Self::post(CGEvent::new_mouse_event(Self::source()?, event_type, point, cg_button))
+            }
+            InputEvent::Scroll { dx, dy } => {
+                // Quartz scrolls content up for positive values
+                Self::post(CGEvent::new_scroll_event(Self::source()?, ScrollEventUnit::LINE, 2, -dy, -dx, 0))
+            }
+            InputEvent::Key { key: Key::Char(c), pressed } => Self::post_text(&c.to_string(), *pressed),
+            InputEvent::Key { key, pressed } => {
+                let code = Self::key_code(*key)
+                    .ok_or_else(|| StreamError::unsupported(format!("Key {:?} is not available on macOS", key)))?;
+                Self::post(CGEvent::new_keyboard_event(Self::source()?, code, *pressed))
+            }
+            InputEvent::Text(text) => {
+                Self::post_text(text, true)?;
+                Self::post_text(text, false)
+            }
+        }
+    }
+
+    fn is_supported(&self) -> bool {
+        Self::source().is_ok()
+    }
+}
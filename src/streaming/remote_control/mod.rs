@@ -0,0 +1,628 @@
+// Remote input control
+//
+// While this device shares its screen, a viewer can be allowed to drive the
+// mouse and keyboard, for example during a support session. Control is granted
+// per stream session and viewer, covers only the shared region, and ends when
+// it expires, is revoked or the stream stops. A grant is refused unless an
+// on-screen indicator is showing and the local kill-switch hotkey is armed;
+// pressing the hotkey revokes every grant at once.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::command_execution::notification::{NotificationBackend, NotificationManager};
+use crate::command_execution::types::{Notification, NotificationPriority, NotificationType};
+use crate::lockdown::{Lockdown, RemoteCapability};
+use crate::streaming::api::{StreamEvent, StreamEventHandler};
+use crate::streaming::{PeerId, ScreenRegion, SessionId, StreamError, StreamResult};
+
+#[cfg(target_os = "windows")]
+pub mod windows;
+#[cfg(target_os = "macos")]
+pub mod macos;
+#[cfg(target_os = "linux")]
+pub mod linux;
+
+/// Hotkey that revokes all remote control on this device
+pub const KILL_SWITCH_HOTKEY: &str = "Ctrl+Alt+Esc";
+
+/// Longest text typed by a single event
+pub const MAX_TEXT_INPUT_LEN: usize = 1024;
+
+/// Mouse button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// Keyboard key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Escape,
+    Backspace,
+    Delete,
+    Space,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Shift,
+    Control,
+    Alt,
+    Meta,
+    /// Function key F1 to F24
+    F(u8),
+}
+
+/// Input sent by the viewer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputEvent {
+    /// Move the pointer; coordinates are pixels relative to the shared region
+    /// when sent and absolute screen pixels when injected
+    MouseMove { x: u32, y: u32 },
+    MouseButton { button: MouseButton, pressed: bool },
+    /// Scroll by whole notches; positive dy scrolls down
+    Scroll { dx: i32, dy: i32 },
+    Key { key: Key, pressed: bool },
+    /// Type a string
+    Text(String),
+}
+
+impl InputEvent {
+    /// Whether the event comes from the keyboard
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self, Self::Key { .. } | Self::Text(_))
+    }
+
+    /// Check the event before sending or injecting it
+    pub fn validate(&self) -> StreamResult<()> {
+        match self {
+            Self::Text(text) if text.len() > MAX_TEXT_INPUT_LEN => Err(StreamError::configuration(format!(
+                "Text input exceeds {} bytes",
+                MAX_TEXT_INPUT_LEN
+            ))),
+            Self::Key { key: Key::F(n), .. } if !(1..=24).contains(n) => {
+                Err(StreamError::configuration(format!("No function key F{}", n)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Permission for a viewer to control this device during one stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlGrant {
+    pub session_id: SessionId,
+    pub viewer_id: PeerId,
+    /// Area of the screen being shared; input outside it is rejected
+    pub region: ScreenRegion,
+    pub allow_keyboard: bool,
+    pub granted_at: SystemTime,
+    pub expires_at: SystemTime,
+}
+
+impl ControlGrant {
+    fn is_expired(&self) -> bool {
+        SystemTime::now() >= self.expires_at
+    }
+}
+
+/// Why remote control ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ControlEndReason {
+    /// The local user pressed the kill-switch hotkey
+    KillSwitch,
+    /// The grant ran out
+    Expired,
+    /// The local user revoked it
+    Revoked,
+    /// The screen share stopped
+    SessionEnded,
+}
+
+/// Remote control lifecycle events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RemoteControlEvent {
+    Granted {
+        session_id: SessionId,
+        viewer_id: PeerId,
+    },
+    Ended {
+        session_id: SessionId,
+        viewer_id: PeerId,
+        reason: ControlEndReason,
+    },
+}
+
+/// Platform-specific input injection
+pub trait InputInjector: Send + Sync {
+    /// Inject one event; pointer coordinates are absolute screen pixels
+    fn inject(&self, event: &InputEvent) -> StreamResult<()>;
+
+    /// Check if input injection is supported on this platform
+    fn is_supported(&self) -> bool;
+}
+
+/// Shows the user that someone is controlling this device
+pub trait ControlIndicator: Send + Sync {
+    fn show(&self, viewer_id: &str) -> StreamResult<()>;
+
+    fn hide(&self, viewer_id: &str);
+}
+
+/// Registers the global kill-switch hotkey
+pub trait HotkeyListener: Send + Sync {
+    /// Call `on_trigger` whenever [`KILL_SWITCH_HOTKEY`] is pressed
+    fn arm(&self, on_trigger: Arc<dyn Fn() + Send + Sync>) -> StreamResult<()>;
+}
+
+/// Indicator using a desktop notification
+pub struct NotificationIndicator {
+    backend: Arc<dyn NotificationBackend>,
+}
+
+impl NotificationIndicator {
+    pub fn new(backend: Arc<dyn NotificationBackend>) -> Self {
+        Self { backend }
+    }
+
+    fn notify(&self, viewer_id: &str, title: &str, message: String, priority: NotificationPriority) -> StreamResult<()> {
+        let notification = Notification {
+            notification_id: Uuid::new_v4(),
+            title: title.to_string(),
+            message,
+            notification_type: NotificationType::Warning,
+            priority,
+            duration: None,
+            actions: Vec::new(),
+            sender: viewer_id.to_string(),
+            image: None,
+            launch: None,
+        };
+        self.backend
+            .show_notification(&notification)
+            .map_err(|e| StreamError::permission(format!("Failed to show control indicator: {}", e)))
+    }
+}
+
+impl ControlIndicator for NotificationIndicator {
+    fn show(&self, viewer_id: &str) -> StreamResult<()> {
+        self.notify(
+            viewer_id,
+            "Remote control active",
+            format!("{} can use your mouse and keyboard. Press {} to stop.", viewer_id, KILL_SWITCH_HOTKEY),
+            NotificationPriority::Critical,
+        )
+    }
+
+    fn hide(&self, viewer_id: &str) {
+        // Notifications cannot be withdrawn everywhere, so say it has ended
+        let message = format!("{} no longer controls this device.", viewer_id);
+        if let Err(e) = self.notify(viewer_id, "Remote control ended", message, NotificationPriority::Normal) {
+            log::warn!("{}", e);
+        }
+    }
+}
+
+/// Remote control limits
+#[derive(Debug, Clone)]
+pub struct RemoteControlConfig {
+    /// Longest a single grant lasts
+    pub max_grant_duration: Duration,
+    /// Refuse grants when the kill-switch hotkey cannot be armed
+    pub require_kill_switch: bool,
+}
+
+impl Default for RemoteControlConfig {
+    fn default() -> Self {
+        Self {
+            max_grant_duration: Duration::from_secs(30 * 60),
+            require_kill_switch: true,
+        }
+    }
+}
+
+/// Grants shared with the kill-switch callback
+struct ControlState {
+    grants: Mutex<HashMap<SessionId, ControlGrant>>,
+    indicator: Option<Arc<dyn ControlIndicator>>,
+    events: broadcast::Sender<RemoteControlEvent>,
+}
+
+impl ControlState {
+    fn end(&self, grant: ControlGrant, reason: ControlEndReason) {
+        if let Some(indicator) = &self.indicator {
+            indicator.hide(&grant.viewer_id);
+        }
+        log::info!("Remote control by {} ended: {:?}", grant.viewer_id, reason);
+        let _ = self.events.send(RemoteControlEvent::Ended {
+            session_id: grant.session_id,
+            viewer_id: grant.viewer_id,
+            reason,
+        });
+    }
+
+    fn revoke(&self, session_id: &SessionId, reason: ControlEndReason) -> bool {
+        let removed = self.grants.lock().unwrap().remove(session_id);
+        match removed {
+            Some(grant) => {
+                self.end(grant, reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn revoke_all(&self, reason: ControlEndReason) -> usize {
+        let removed: Vec<ControlGrant> = self.grants.lock().unwrap().drain().map(|(_, grant)| grant).collect();
+        let count = removed.len();
+        for grant in removed {
+            self.end(grant, reason);
+        }
+        count
+    }
+}
+
+/// Remote control of this device by stream viewers
+pub struct RemoteControl {
+    injector: Arc<dyn InputInjector>,
+    hotkey: Option<Arc<dyn HotkeyListener>>,
+    kill_switch_armed: AtomicBool,
+    state: Arc<ControlState>,
+    config: RemoteControlConfig,
+}
+
+impl RemoteControl {
+    /// Create a remote control using the platform's injector, notifications
+    /// and hotkey support
+    pub fn new(config: RemoteControlConfig) -> StreamResult<Self> {
+        let indicator = NotificationManager::create_platform_backend()
+            .ok()
+            .map(|backend| Arc::new(NotificationIndicator::new(Arc::from(backend))) as Arc<dyn ControlIndicator>);
+
+        Ok(Self::with_backends(
+            Self::create_platform_injector()?,
+            indicator,
+            Self::create_platform_hotkey(),
+            config,
+        ))
+    }
+
+    /// Create a remote control with custom backends
+    pub fn with_backends(
+        injector: Arc<dyn InputInjector>,
+        indicator: Option<Arc<dyn ControlIndicator>>,
+        hotkey: Option<Arc<dyn HotkeyListener>>,
+        config: RemoteControlConfig,
+    ) -> Self {
+        let (events, _) = broadcast::channel(16);
+        Self {
+            injector,
+            hotkey,
+            kill_switch_armed: AtomicBool::new(false),
+            state: Arc::new(ControlState {
+                grants: Mutex::new(HashMap::new()),
+                indicator,
+                events,
+            }),
+            config,
+        }
+    }
+
+    fn create_platform_injector() -> StreamResult<Arc<dyn InputInjector>> {
+        #[cfg(target_os = "windows")]
+        {
+            Ok(Arc::new(windows::WindowsInputInjector::new()))
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            Ok(Arc::new(macos::MacOSInputInjector::new()))
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            Ok(Arc::new(linux::LinuxInputInjector::new()))
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+        {
+            Err(StreamError::unsupported("Remote input is not supported on this platform"))
+        }
+    }
+
+    /// Built-in hotkey support; elsewhere the desktop shell supplies a listener
+    fn create_platform_hotkey() -> Option<Arc<dyn HotkeyListener>> {
+        #[cfg(target_os = "windows")]
+        {
+            Some(Arc::new(windows::WindowsHotkeyListener::new()))
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            None
+        }
+    }
+
+    /// Subscribe to grant and end events
+    pub fn subscribe(&self) -> broadcast::Receiver<RemoteControlEvent> {
+        self.state.events.subscribe()
+    }
+
+    /// Let a viewer control this device for the rest of a screen share
+    pub fn grant(
+        &self,
+        session_id: SessionId,
+        viewer_id: impl Into<PeerId>,
+        region: ScreenRegion,
+        allow_keyboard: bool,
+        duration: Duration,
+    ) -> StreamResult<ControlGrant> {
+        Lockdown::global()
+            .check(RemoteCapability::RemoteControl)
+            .map_err(|e| StreamError::permission(e.to_string()))?;
+        if !self.injector.is_supported() {
+            return Err(StreamError::unsupported("Input injection is not available on this device"));
+        }
+        let indicator = self
+            .state
+            .indicator
+            .as_ref()
+            .ok_or_else(|| StreamError::permission("Remote control needs an on-screen indicator"))?;
+        self.arm_kill_switch()?;
+
+        let viewer_id = viewer_id.into();
+        indicator.show(&viewer_id)?;
+
+        let now = SystemTime::now();
+        let grant = ControlGrant {
+            session_id,
+            viewer_id: viewer_id.clone(),
+            region,
+            allow_keyboard,
+            granted_at: now,
+            expires_at: now + duration.min(self.config.max_grant_duration),
+        };
+        let replaced = self.state.grants.lock().unwrap().insert(session_id, grant.clone());
+        if let Some(previous) = replaced.filter(|previous| previous.viewer_id != viewer_id) {
+            self.state.end(previous, ControlEndReason::Revoked);
+        }
+
+        let _ = self.state.events.send(RemoteControlEvent::Granted { session_id, viewer_id });
+        Ok(grant)
+    }
+
+    /// Take control back for one session
+    pub fn revoke(&self, session_id: &SessionId) -> bool {
+        self.state.revoke(session_id, ControlEndReason::Revoked)
+    }
+
+    /// Revoke every grant, as the kill-switch hotkey does
+    pub fn trigger_kill_switch(&self) -> usize {
+        self.state.revoke_all(ControlEndReason::KillSwitch)
+    }
+
+    /// Grants currently in effect
+    pub fn active_grants(&self) -> Vec<ControlGrant> {
+        self.state.grants.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Inject input from a viewer
+    pub fn handle_input(&self, session_id: &SessionId, viewer_id: &str, event: &InputEvent) -> StreamResult<()> {
+        event.validate()?;
+
+        let grant = self
+            .state
+            .grants
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .filter(|grant| grant.viewer_id == viewer_id)
+            .cloned()
+            .ok_or_else(|| StreamError::permission(format!("{} has no control of this device", viewer_id)))?;
+
+        if let Err(locked) = Lockdown::global().check_admitted(RemoteCapability::RemoteControl, grant.granted_at) {
+            self.state.revoke(session_id, ControlEndReason::KillSwitch);
+            return Err(StreamError::permission(locked.to_string()));
+        }
+        if grant.is_expired() {
+            self.state.revoke(session_id, ControlEndReason::Expired);
+            return Err(StreamError::permission("Remote control grant has expired"));
+        }
+        if event.is_keyboard() && !grant.allow_keyboard {
+            return Err(StreamError::permission("Keyboard control was not granted"));
+        }
+
+        let event = match *event {
+            InputEvent::MouseMove { x, y } => {
+                if x >= grant.region.width || y >= grant.region.height {
+                    return Err(StreamError::permission("Pointer is outside the shared region"));
+                }
+                InputEvent::MouseMove {
+                    x: grant.region.x + x,
+                    y: grant.region.y + y,
+                }
+            }
+            ref other => other.clone(),
+        };
+        self.injector.inject(&event)
+    }
+
+    fn arm_kill_switch(&self) -> StreamResult<()> {
+        if self.kill_switch_armed.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        match &self.hotkey {
+            Some(hotkey) => {
+                let state = Arc::clone(&self.state);
+                hotkey.arm(Arc::new(move || {
+                    let revoked = state.revoke_all(ControlEndReason::KillSwitch);
+                    log::warn!("Kill switch pressed; revoked {} remote control grants", revoked);
+                }))?;
+                self.kill_switch_armed.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+            None if self.config.require_kill_switch => Err(StreamError::unsupported(format!(
+                "Remote control needs the {} kill switch, which is not available here",
+                KILL_SWITCH_HOTKEY
+            ))),
+            None => {
+                log::warn!("Granting remote control without a kill-switch hotkey");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StreamEventHandler for RemoteControl {
+    async fn on_event(&self, event: StreamEvent) {
+        if let StreamEvent::SessionStopped { session_id, .. } = event {
+            self.state.revoke(&session_id, ControlEndReason::SessionEnded);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::api::StopReason;
+
+    #[derive(Default)]
+    struct RecordingInjector(Mutex<Vec<InputEvent>>);
+
+    impl InputInjector for RecordingInjector {
+        fn inject(&self, event: &InputEvent) -> StreamResult<()> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+
+        fn is_supported(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingIndicator(Mutex<Vec<String>>);
+
+    impl ControlIndicator for RecordingIndicator {
+        fn show(&self, viewer_id: &str) -> StreamResult<()> {
+            self.0.lock().unwrap().push(format!("show {}", viewer_id));
+            Ok(())
+        }
+
+        fn hide(&self, viewer_id: &str) {
+            self.0.lock().unwrap().push(format!("hide {}", viewer_id));
+        }
+    }
+
+    #[derive(Default)]
+    struct ManualHotkey(Mutex<Option<Arc<dyn Fn() + Send + Sync>>>);
+
+    impl HotkeyListener for ManualHotkey {
+        fn arm(&self, on_trigger: Arc<dyn Fn() + Send + Sync>) -> StreamResult<()> {
+            *self.0.lock().unwrap() = Some(on_trigger);
+            Ok(())
+        }
+    }
+
+    const REGION: ScreenRegion = ScreenRegion { x: 100, y: 50, width: 800, height: 600 };
+
+    fn setup() -> (RemoteControl, Arc<RecordingInjector>, Arc<RecordingIndicator>, Arc<ManualHotkey>) {
+        let injector = Arc::new(RecordingInjector::default());
+        let indicator = Arc::new(RecordingIndicator::default());
+        let hotkey = Arc::new(ManualHotkey::default());
+        let control = RemoteControl::with_backends(
+            injector.clone(),
+            Some(indicator.clone()),
+            Some(hotkey.clone()),
+            RemoteControlConfig::default(),
+        );
+        (control, injector, indicator, hotkey)
+    }
+
+    #[test]
+    fn test_input_requires_grant_and_stays_in_region() {
+        let (control, injector, indicator, _) = setup();
+        let session = Uuid::new_v4();
+        let click = InputEvent::MouseButton { button: MouseButton::Left, pressed: true };
+
+        assert!(control.handle_input(&session, "helpdesk", &click).is_err());
+
+        control.grant(session, "helpdesk", REGION, false, Duration::from_secs(60)).unwrap();
+        assert_eq!(indicator.0.lock().unwrap()[0], "show helpdesk");
+
+        control.handle_input(&session, "helpdesk", &InputEvent::MouseMove { x: 10, y: 20 }).unwrap();
+        control.handle_input(&session, "helpdesk", &click).unwrap();
+        assert!(control.handle_input(&session, "helpdesk", &InputEvent::MouseMove { x: 800, y: 0 }).is_err());
+        assert!(control.handle_input(&session, "helpdesk", &InputEvent::Text("hi".into())).is_err());
+        assert!(control.handle_input(&session, "someone-else", &click).is_err());
+
+        let injected = injector.0.lock().unwrap();
+        assert_eq!(injected[0], InputEvent::MouseMove { x: 110, y: 70 });
+        assert_eq!(injected.len(), 2);
+    }
+
+    #[test]
+    fn test_kill_switch_revokes_everything() {
+        let (control, _, indicator, hotkey) = setup();
+        let mut events = control.subscribe();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        control.grant(first, "helpdesk", REGION, true, Duration::from_secs(60)).unwrap();
+        control.grant(second, "admin", REGION, true, Duration::from_secs(60)).unwrap();
+
+        let trigger = hotkey.0.lock().unwrap().clone().unwrap();
+        trigger();
+
+        assert!(control.active_grants().is_empty());
+        assert!(control.handle_input(&first, "helpdesk", &InputEvent::Text("x".into())).is_err());
+        assert!(indicator.0.lock().unwrap().contains(&"hide admin".to_string()));
+
+        let mut ended = 0;
+        while let Ok(event) = events.try_recv() {
+            if let RemoteControlEvent::Ended { reason, .. } = event {
+                assert_eq!(reason, ControlEndReason::KillSwitch);
+                ended += 1;
+            }
+        }
+        assert_eq!(ended, 2);
+    }
+
+    #[tokio::test]
+    async fn test_grant_refused_without_kill_switch_and_ends_with_stream() {
+        let injector = Arc::new(RecordingInjector::default());
+        let indicator = Arc::new(RecordingIndicator::default());
+        let unarmed = RemoteControl::with_backends(
+            injector.clone(),
+            Some(indicator.clone()),
+            None,
+            RemoteControlConfig::default(),
+        );
+        assert!(unarmed.grant(Uuid::new_v4(), "helpdesk", REGION, true, Duration::from_secs(60)).is_err());
+        assert!(indicator.0.lock().unwrap().is_empty());
+
+        let (control, _, _, _) = setup();
+        let session = Uuid::new_v4();
+        control.grant(session, "helpdesk", REGION, true, Duration::from_secs(60)).unwrap();
+        control
+            .on_event(StreamEvent::SessionStopped { session_id: session, reason: StopReason::UserRequested })
+            .await;
+        assert!(control.active_grants().is_empty());
+    }
+}
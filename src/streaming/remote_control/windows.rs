@@ -0,0 +1,237 @@
+// Windows Input Injector
+//
+// Injects pointer and keyboard input with SendInput and registers the
+// kill-switch hotkey with RegisterHotKey on a dedicated message-loop thread.
+
+use super::{HotkeyListener, InputEvent, InputInjector, Key, MouseButton};
+use crate::streaming::{StreamError, StreamResult};
+use std::mem;
+use std::ptr;
+use std::sync::{mpsc, Arc};
+use winapi::shared::minwindef::{DWORD, WORD};
+use winapi::um::winuser::{
+    GetMessageW, GetSystemMetrics, RegisterHotKey, SendInput, INPUT, INPUT_KEYBOARD, INPUT_MOUSE,
+    KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOUSEEVENTF_ABSOLUTE,
+    MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN,
+    MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP,
+    MOUSEEVENTF_VIRTUALDESK, MOUSEEVENTF_WHEEL, MSG, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
+    VK_BACK, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE, VK_F1, VK_HOME, VK_LEFT, VK_LWIN,
+    VK_MENU, VK_NEXT, VK_PRIOR, VK_RETURN, VK_RIGHT, VK_SHIFT, VK_SPACE, VK_TAB, VK_UP, WHEEL_DELTA,
+    WM_HOTKEY,
+};
+
+/// Identifier passed to RegisterHotKey
+const KILL_SWITCH_HOTKEY_ID: i32 = 0x4b5a;
+
+/// Windows input injector using SendInput
+pub struct WindowsInputInjector;
+
+impl WindowsInputInjector {
+    /// Create a new Windows input injector
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn mouse_input(dx: i32, dy: i32, data: DWORD, flags: DWORD) -> INPUT {
+        unsafe {
+            let mut input: INPUT = mem::zeroed();
+            input.type_ = INPUT_MOUSE;
+            let mi = input.u.mi_mut();
+            mi.dx = dx;
+            mi.dy = dy;
+            mi.mouseData = data;
+            mi.dwFlags = flags;
+            input
+        }
+    }
+
+    fn key_input(vk: WORD, scan: WORD, flags: DWORD) -> INPUT {
+        unsafe {
+            let mut input: INPUT = mem::zeroed();
+            input.type_ = INPUT_KEYBOARD;
+            let ki = input.u.ki_mut();
+            ki.wVk = vk;
+            ki.wScan = scan;
+            ki.dwFlags = flags;
+            input
+        }
+    }
+
+    fn send(&self, inputs: &mut [INPUT]) -> StreamResult<()> {
+        let sent = unsafe { SendInput(inputs.len() as u32, inputs.as_mut_ptr(), mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            // Input into elevated windows is blocked by UIPI
+            return Err(StreamError::permission("SendInput was blocked"));
+        }
+        Ok(())
+    }
+
+    /// Virtual key code for keys that have one
+    fn virtual_key(key: Key) -> Option<i32> {
+        Some(match key {
+            Key::Char(_) => return None,
+            Key::Enter => VK_RETURN,
+            Key::Tab => VK_TAB,
+            Key::Escape => VK_ESCAPE,
+            Key::Backspace => VK_BACK,
+            Key::Delete => VK_DELETE,
+            Key::Space => VK_SPACE,
+            Key::Left => VK_LEFT,
+            Key::Right => VK_RIGHT,
+            Key::Up => VK_UP,
+            Key::Down => VK_DOWN,
+            Key::Home => VK_HOME,
+            Key::End => VK_END,
+            Key::PageUp => VK_PRIOR,
+            Key::PageDown => VK_NEXT,
+            Key::Shift => VK_SHIFT,
+            Key::Control => VK_CONTROL,
+            Key::Alt => VK_MENU,
+            Key::Meta => VK_LWIN,
+            Key::F(n) => VK_F1 + i32::from(n) - 1,
+        })
+    }
+
+    /// Unicode key events for a string, each code unit pressed and released
+    fn unicode_inputs(text: &str, pressed: Option<bool>) -> Vec<INPUT> {
+        let mut inputs = Vec::new();
+        for unit in text.encode_utf16() {
+            if pressed != Some(false) {
+                inputs.push(Self::key_input(0, unit, KEYEVENTF_UNICODE));
+            }
+            if pressed != Some(true) {
+                inputs.push(Self::key_input(0, unit, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP));
+            }
+        }
+        inputs
+    }
+}
+
+impl Default for WindowsInputInjector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputInjector for WindowsInputInjector {
+    fn inject(&self, event: &InputEvent) -> StreamResult<()> {
+        match event {
+            InputEvent::MouseMove { x, y } => {
+                // Absolute coordinates are normalised to 0..65535 across the virtual desktop
+                let (width, height) = unsafe {
+                    (GetSystemMetrics(SM_CXVIRTUALSCREEN).max(1), GetSystemMetrics(SM_CYVIRTUALSCREEN).max(1))
+                };
+                let dx = (i64::from(*x) * 65535 / i64::from(width)) as i32;
+                let dy = (i64::from(*y) * 65535 / i64::from(height)) as i32;
+                self.send(&mut [Self::mouse_input(
+                    dx,
+                    dy,
+                    0,
+                    MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK,
+                )])
+            }
+            InputEvent::MouseButton { button, pressed } => {
+                let flags = match (button, pressed) {
+                    (MouseButton::Left, true) => MOUSEEVENTF_LEFTDOWN,
+                    (MouseButton::Left, false) => MOUSEEVENTF_LEFTUP,
+                    (MouseButton::Right, true) => MOUSEEVENTF_RIGHTDOWN,
+                    (MouseButton::Right, false) => MOUSEEVENTF_RIGHTUP,
+                    (MouseButton::Middle, true) => MOUSEEVENTF_MIDDLEDOWN,
+                    (MouseButton::Middle, false) => MOUSEEVENTF_MIDDLEUP,
+                };
+                self.send(&mut [Self::mouse_input(0, 0, 0, flags)])
+            }
+            InputEvent::Scroll { dx, dy } => {
+                let mut inputs = Vec::new();
+                if *dy != 0 {
+                    // Positive wheel data scrolls up
+                    let data = (-dy * WHEEL_DELTA as i32) as DWORD;
+                    inputs.push(Self::mouse_input(0, 0, data, MOUSEEVENTF_WHEEL));
+                }
+                if *dx != 0 {
+                    let data = (dx * WHEEL_DELTA as i32) as DWORD;
+                    inputs.push(Self::mouse_input(0, 0, data, MOUSEEVENTF_HWHEEL));
+                }
+                if inputs.is_empty() {
+                    return Ok(());
+                }
+                self.send(&mut inputs)
+            }
+            InputEvent::Key { key: Key::Char(c), pressed } => {
+                self.send(&mut Self::unicode_inputs(&c.to_string(), Some(*pressed)))
+            }
+            InputEvent::Key { key, pressed } => {
+                let vk = Self::virtual_key(*key).unwrap_or_default() as WORD;
+                let flags = if *pressed { 0 } else { KEYEVENTF_KEYUP };
+                self.send(&mut [Self::key_input(vk, 0, flags)])
+            }
+            InputEvent::Text(text) => {
+                let mut inputs = Self::unicode_inputs(text, None);
+                if inputs.is_empty() {
+                    return Ok(());
+                }
+                self.send(&mut inputs)
+            }
+        }
+    }
+
+    fn is_supported(&self) -> bool {
+        true
+    }
+}
+
+/// Kill-switch hotkey registered with RegisterHotKey
+pub struct WindowsHotkeyListener;
+
+impl WindowsHotkeyListener {
+    /// Create a new Windows hotkey listener
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for WindowsHotkeyListener {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HotkeyListener for WindowsHotkeyListener {
+    fn arm(&self, on_trigger: Arc<dyn Fn() + Send + Sync>) -> StreamResult<()> {
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        // Hotkeys belong to the registering thread, which must pump its messages
+        std::thread::Builder::new()
+            .name("kizuna-kill-switch".into())
+            .spawn(move || {
+                let registered = unsafe {
+                    RegisterHotKey(
+                        ptr::null_mut(),
+                        KILL_SWITCH_HOTKEY_ID,
+                        (MOD_CONTROL | MOD_ALT | MOD_NOREPEAT) as u32,
+                        VK_ESCAPE as u32,
+                    )
+                };
+                let _ = ready_tx.send(registered != 0);
+                if registered == 0 {
+                    return;
+                }
+
+                let mut msg: MSG = unsafe { mem::zeroed() };
+                while unsafe { GetMessageW(&mut msg, ptr::null_mut(), 0, 0) } > 0 {
+                    if msg.message == WM_HOTKEY && msg.wParam as i32 == KILL_SWITCH_HOTKEY_ID {
+                        on_trigger();
+                    }
+                }
+            })
+            .map_err(|e| StreamError::internal(format!("Failed to start hotkey thread: {}", e)))?;
+
+        match ready_rx.recv() {
+            Ok(true) => Ok(()),
+            _ => Err(StreamError::permission(format!(
+                "Could not register the {} hotkey; another program may own it",
+                super::KILL_SWITCH_HOTKEY
+            ))),
+        }
+    }
+}
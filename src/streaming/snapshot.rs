@@ -0,0 +1,750 @@
+// One-shot snapshots
+//
+// A peer can ask for a single screenshot or camera frame, for example to see
+// what the user is describing during remote support. A request must pass a
+// per-peer grant for that source, a prompt on this device (unless turned off)
+// and a rate limit. A notification is always shown here before anything is
+// captured, and the request is refused if it cannot be shown. The image is
+// encoded as PNG and sent back through file transfer.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use uuid::Uuid;
+
+use crate::command_execution::notification::{NotificationBackend, NotificationManager};
+use crate::command_execution::types::{Notification, NotificationPriority, NotificationType};
+use crate::file_transfer::api::FileTransferSystem;
+use crate::file_transfer::preview::{Thumbnail, MAX_THUMBNAIL_BYTES, MAX_THUMBNAIL_DIMENSION};
+use crate::streaming::{PeerId, PixelFormat, StreamError, StreamResult, VideoFrame};
+
+/// Unique identifier for a snapshot request
+pub type SnapshotId = Uuid;
+
+/// Longest reason shown in the snapshot notification
+pub const MAX_SNAPSHOT_REASON_LEN: usize = 256;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// What to capture
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SnapshotSource {
+    /// The whole screen, or one monitor
+    Screen { monitor_index: Option<u32> },
+    /// One frame from a camera
+    Camera { device_id: String },
+}
+
+impl SnapshotSource {
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Screen { .. } => "a screenshot",
+            Self::Camera { .. } => "a camera photo",
+        }
+    }
+}
+
+/// Snapshot request sent by a peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotRequest {
+    pub request_id: SnapshotId,
+    pub requester: PeerId,
+    pub source: SnapshotSource,
+    /// Shown in the notification so the user knows why
+    pub reason: Option<String>,
+    pub requested_at: SystemTime,
+}
+
+impl SnapshotRequest {
+    pub fn new(requester: impl Into<PeerId>, source: SnapshotSource) -> Self {
+        Self {
+            request_id: Uuid::new_v4(),
+            requester: requester.into(),
+            source,
+            reason: None,
+            requested_at: SystemTime::now(),
+        }
+    }
+
+    /// Explain the request in the notification
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Check the request before sending or acting on it
+    pub fn validate(&self) -> StreamResult<()> {
+        match &self.reason {
+            Some(reason) if reason.len() > MAX_SNAPSHOT_REASON_LEN => Err(StreamError::configuration(format!(
+                "Snapshot reason exceeds {} bytes",
+                MAX_SNAPSHOT_REASON_LEN
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Sources a peer may capture
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotGrant {
+    pub screen: bool,
+    pub camera: bool,
+}
+
+impl SnapshotGrant {
+    fn allows(&self, source: &SnapshotSource) -> bool {
+        match source {
+            SnapshotSource::Screen { .. } => self.screen,
+            SnapshotSource::Camera { .. } => self.camera,
+        }
+    }
+}
+
+/// Outcome of a snapshot request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotResult {
+    pub request_id: SnapshotId,
+    /// Name of the PNG sent to the requester
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub captured_at: SystemTime,
+}
+
+/// Snapshot limits
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    /// Shortest time between two snapshots for the same peer
+    pub min_interval: Duration,
+    /// How long the notification is up before capturing
+    pub notice_delay: Duration,
+    /// Ask the user to approve every request
+    pub require_approval: bool,
+    /// Where captured images are kept until sent
+    pub output_dir: PathBuf,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(30),
+            notice_delay: Duration::from_secs(3),
+            require_approval: true,
+            output_dir: default_snapshot_dir(),
+        }
+    }
+}
+
+/// Default directory for captured snapshots
+pub fn default_snapshot_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("kizuna")
+        .join("snapshots")
+}
+
+/// Asks the user whether to allow a snapshot; return true to allow
+pub type SnapshotApprovalCallback = Arc<dyn Fn(&SnapshotRequest) -> bool + Send + Sync>;
+
+/// Captures a single image, returned as PNG
+#[async_trait]
+pub trait FrameGrabber: Send + Sync {
+    async fn grab(&self, source: &SnapshotSource) -> StreamResult<Vec<u8>>;
+}
+
+/// Sends a captured image to the requesting peer
+#[async_trait]
+pub trait SnapshotDelivery: Send + Sync {
+    async fn deliver(&self, peer: &PeerId, path: &Path) -> StreamResult<()>;
+}
+
+#[async_trait]
+impl SnapshotDelivery for FileTransferSystem {
+    async fn deliver(&self, peer: &PeerId, path: &Path) -> StreamResult<()> {
+        self.send_file(path.to_path_buf(), peer.clone())
+            .await
+            .map(|_| ())
+            .map_err(|e| StreamError::network(format!("Failed to send snapshot: {}", e)))
+    }
+}
+
+/// Frame grabber using the desktop's screenshot tools and ffmpeg
+///
+/// Screens are captured with grim on Wayland, ImageMagick's import on X11 and
+/// screencapture on macOS. Camera frames are read with ffmpeg.
+pub struct SystemFrameGrabber;
+
+impl SystemFrameGrabber {
+    pub fn new() -> Self {
+        Self
+    }
+
+    async fn run(program: &str, args: &[&str]) -> StreamResult<Vec<u8>> {
+        let output = tokio::process::Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .await
+            .map_err(|e| StreamError::unsupported(format!("{} is not available: {}", program, e)))?;
+
+        if !output.status.success() {
+            return Err(StreamError::capture(format!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn grab_screen(&self, monitor_index: Option<u32>) -> StreamResult<Vec<u8>> {
+        if monitor_index.is_some() {
+            log::debug!("Monitor selection is not supported here; capturing all monitors");
+        }
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            Self::run("grim", &["-t", "png", "-"]).await
+        } else {
+            Self::run("import", &["-window", "root", "png:-"]).await
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    async fn grab_screen(&self, monitor_index: Option<u32>) -> StreamResult<Vec<u8>> {
+        let path = std::env::temp_dir().join(format!("kizuna-snapshot-{}.png", Uuid::new_v4()));
+        let display = (monitor_index.unwrap_or(0) + 1).to_string();
+        let path_str = path.to_string_lossy().to_string();
+        let result = Self::run("screencapture", &["-x", "-t", "png", "-D", &display, &path_str]).await;
+        let png = match result {
+            Ok(_) => tokio::fs::read(&path).await.map_err(StreamError::from),
+            Err(e) => Err(e),
+        };
+        let _ = tokio::fs::remove_file(&path).await;
+        png
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    async fn grab_screen(&self, _monitor_index: Option<u32>) -> StreamResult<Vec<u8>> {
+        Err(StreamError::unsupported("Screenshots are not supported on this platform"))
+    }
+
+    async fn grab_camera(&self, device_id: &str) -> StreamResult<Vec<u8>> {
+        let (format, valid) = if cfg!(target_os = "linux") {
+            ("v4l2", device_id.starts_with("/dev/video"))
+        } else if cfg!(target_os = "macos") {
+            ("avfoundation", !device_id.is_empty() && device_id.chars().all(|c| c.is_ascii_digit()))
+        } else {
+            return Err(StreamError::unsupported("Camera snapshots are not supported on this platform"));
+        };
+        if !valid {
+            return Err(StreamError::device_not_found(device_id));
+        }
+
+        Self::run(
+            "ffmpeg",
+            &[
+                "-hide_banner", "-loglevel", "error", "-f", format, "-i", device_id,
+                "-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-",
+            ],
+        )
+        .await
+    }
+}
+
+impl Default for SystemFrameGrabber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl FrameGrabber for SystemFrameGrabber {
+    async fn grab(&self, source: &SnapshotSource) -> StreamResult<Vec<u8>> {
+        match source {
+            SnapshotSource::Screen { monitor_index } => self.grab_screen(*monitor_index).await,
+            SnapshotSource::Camera { device_id } => self.grab_camera(device_id).await,
+        }
+    }
+}
+
+/// Snapshot service enforcing grants, prompts and rate limits
+pub struct SnapshotService {
+    grabber: Arc<dyn FrameGrabber>,
+    notifications: Option<Arc<dyn NotificationBackend>>,
+    delivery: Arc<dyn SnapshotDelivery>,
+    grants: RwLock<HashMap<PeerId, SnapshotGrant>>,
+    last_capture: Mutex<HashMap<PeerId, Instant>>,
+    approval: RwLock<Option<SnapshotApprovalCallback>>,
+    config: SnapshotConfig,
+}
+
+impl SnapshotService {
+    /// Create a snapshot service using the platform's capture tools and notifications
+    pub fn new(delivery: Arc<dyn SnapshotDelivery>, config: SnapshotConfig) -> Self {
+        Self::with_backends(
+            Arc::new(SystemFrameGrabber::new()),
+            NotificationManager::create_platform_backend().ok().map(Arc::from),
+            delivery,
+            config,
+        )
+    }
+
+    /// Create a snapshot service with custom backends
+    pub fn with_backends(
+        grabber: Arc<dyn FrameGrabber>,
+        notifications: Option<Arc<dyn NotificationBackend>>,
+        delivery: Arc<dyn SnapshotDelivery>,
+        config: SnapshotConfig,
+    ) -> Self {
+        Self {
+            grabber,
+            notifications,
+            delivery,
+            grants: RwLock::new(HashMap::new()),
+            last_capture: Mutex::new(HashMap::new()),
+            approval: RwLock::new(None),
+            config,
+        }
+    }
+
+    /// Allow a peer to request snapshots from the given sources
+    pub fn grant(&self, peer_id: impl Into<PeerId>, grant: SnapshotGrant) {
+        self.grants.write().expect("Lock poisoned").insert(peer_id.into(), grant);
+    }
+
+    /// Revoke snapshot access for a peer
+    pub fn revoke(&self, peer_id: &str) -> bool {
+        self.grants.write().expect("Lock poisoned").remove(peer_id).is_some()
+    }
+
+    /// Sources a peer may capture
+    pub fn grant_for(&self, peer_id: &str) -> SnapshotGrant {
+        self.grants
+            .read()
+            .expect("Lock poisoned")
+            .get(peer_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Set the prompt shown for each request
+    pub fn set_approval_callback(&self, callback: SnapshotApprovalCallback) {
+        *self.approval.write().expect("Lock poisoned") = Some(callback);
+    }
+
+    /// Handle a snapshot request from a peer
+    pub async fn handle_request(&self, request: &SnapshotRequest) -> StreamResult<SnapshotResult> {
+        crate::lockdown::Lockdown::global()
+            .check(crate::lockdown::RemoteCapability::StreamViewing)
+            .map_err(|e| StreamError::permission(e.to_string()))?;
+        request.validate()?;
+        if !self.grant_for(&request.requester).allows(&request.source) {
+            return Err(StreamError::permission(format!(
+                "Peer {} may not take {} of this device",
+                request.requester,
+                request.source.description()
+            )));
+        }
+        self.check_rate_limit(&request.requester)?;
+
+        if self.config.require_approval {
+            let approval = self.approval.read().expect("Lock poisoned").clone();
+            match approval {
+                Some(approve) if approve(request) => {}
+                Some(_) => return Err(StreamError::permission("Snapshot declined on this device")),
+                None => {
+                    return Err(StreamError::permission(
+                        "Snapshots need approval but no prompt is available",
+                    ))
+                }
+            }
+        }
+
+        self.notify(request)?;
+        tokio::time::sleep(self.config.notice_delay).await;
+
+        let png = self.grabber.grab(&request.source).await?;
+        if !png.starts_with(PNG_SIGNATURE) {
+            return Err(StreamError::capture("Capture did not produce a PNG image"));
+        }
+
+        let file_name = format!("snapshot-{}.png", request.request_id);
+        let path = self.config.output_dir.join(&file_name);
+        tokio::fs::create_dir_all(&self.config.output_dir).await?;
+        crate::journal::atomic_write(&path, &png).await?;
+
+        self.delivery.deliver(&request.requester, &path).await?;
+        log::info!("Sent {} to {}", request.source.description(), request.requester);
+
+        Ok(SnapshotResult {
+            request_id: request.request_id,
+            file_name,
+            size_bytes: png.len() as u64,
+            captured_at: SystemTime::now(),
+        })
+    }
+
+    fn check_rate_limit(&self, peer_id: &PeerId) -> StreamResult<()> {
+        let mut last_capture = self.last_capture.lock().expect("Lock poisoned");
+        if let Some(at) = last_capture.get(peer_id) {
+            let wait = self.config.min_interval.saturating_sub(at.elapsed());
+            if !wait.is_zero() {
+                return Err(StreamError::resource(format!(
+                    "Too many snapshot requests; try again in {}s",
+                    wait.as_secs().max(1)
+                )));
+            }
+        }
+        last_capture.insert(peer_id.clone(), Instant::now());
+        Ok(())
+    }
+
+    /// Show the notification; nothing is captured if this fails
+    fn notify(&self, request: &SnapshotRequest) -> StreamResult<()> {
+        let backend = self
+            .notifications
+            .as_ref()
+            .filter(|backend| backend.is_supported())
+            .ok_or_else(|| StreamError::permission("Snapshots are refused when no notification can be shown"))?;
+
+        let mut message = format!("{} is taking {} of this device.", request.requester, request.source.description());
+        if let Some(reason) = &request.reason {
+            message.push_str(&format!(" Reason: {}", reason));
+        }
+        let notification = Notification {
+            notification_id: Uuid::new_v4(),
+            title: "Snapshot requested".to_string(),
+            message,
+            notification_type: NotificationType::Warning,
+            priority: NotificationPriority::High,
+            duration: Some(self.config.notice_delay + Duration::from_secs(5)),
+            actions: Vec::new(),
+            sender: request.requester.clone(),
+            image: None,
+            launch: None,
+        };
+
+        backend
+            .show_notification(&notification)
+            .map_err(|e| StreamError::permission(format!("Failed to show snapshot notification: {}", e)))
+    }
+}
+
+/// Encode a raw frame as PNG
+///
+/// MJPEG frames are not decoded here; grab those as PNG instead.
+pub fn encode_png(frame: &VideoFrame) -> StreamResult<Vec<u8>> {
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    if width == 0 || height == 0 {
+        return Err(StreamError::encoding("Frame has no pixels"));
+    }
+
+    let (rgb, channels, color_type) = match frame.format {
+        PixelFormat::RGB24 => (check_len(&frame.data, width * height * 3)?.to_vec(), 3, 2u8),
+        PixelFormat::RGBA32 => (check_len(&frame.data, width * height * 4)?.to_vec(), 4, 6u8),
+        PixelFormat::YUV420 => (yuv_to_rgb(&frame.data, width, height, false)?, 3, 2u8),
+        PixelFormat::NV12 => (yuv_to_rgb(&frame.data, width, height, true)?, 3, 2u8),
+        PixelFormat::MJPEG => return Err(StreamError::unsupported("MJPEG frames cannot be converted to PNG")),
+    };
+
+    // Each scanline starts with filter type 0 (none)
+    let stride = width * channels;
+    let mut raw = Vec::with_capacity((stride + 1) * height);
+    for row in rgb.chunks_exact(stride) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&frame.width.to_be_bytes());
+    header.extend_from_slice(&frame.height.to_be_bytes());
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+/// Shrink a frame to the thumbnail sent with a stream offer
+///
+/// The PNG is stored uncompressed, so the frame is scaled down to fit the
+/// thumbnail size limit as well as its dimension limit.
+pub fn preview_thumbnail(frame: &VideoFrame) -> StreamResult<Thumbnail> {
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    if width == 0 || height == 0 {
+        return Err(StreamError::encoding("Frame has no pixels"));
+    }
+
+    let rgb = match frame.format {
+        PixelFormat::RGB24 => check_len(&frame.data, width * height * 3)?.to_vec(),
+        PixelFormat::RGBA32 => check_len(&frame.data, width * height * 4)?
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect(),
+        PixelFormat::YUV420 => yuv_to_rgb(&frame.data, width, height, false)?,
+        PixelFormat::NV12 => yuv_to_rgb(&frame.data, width, height, true)?,
+        PixelFormat::MJPEG => return Err(StreamError::unsupported("MJPEG frames cannot be converted to PNG")),
+    };
+
+    // Three bytes a pixel, leaving room for the row filters and PNG chunks
+    let pixel_budget = (MAX_THUMBNAIL_BYTES - 1024) as f64 / 3.0;
+    let scale = (MAX_THUMBNAIL_DIMENSION as f64 / width.max(height) as f64)
+        .min((pixel_budget / (width * height) as f64).sqrt())
+        .min(1.0);
+    let thumb_width = ((width as f64 * scale) as usize).max(1);
+    let thumb_height = ((height as f64 * scale) as usize).max(1);
+
+    let mut data = Vec::with_capacity(thumb_width * thumb_height * 3);
+    for y in 0..thumb_height {
+        let row = y * height / thumb_height * width;
+        for x in 0..thumb_width {
+            let i = (row + x * width / thumb_width) * 3;
+            data.extend_from_slice(&rgb[i..i + 3]);
+        }
+    }
+
+    let png = encode_png(&VideoFrame {
+        data,
+        width: thumb_width as u32,
+        height: thumb_height as u32,
+        format: PixelFormat::RGB24,
+        timestamp: frame.timestamp,
+    })?;
+    Ok(Thumbnail {
+        width: thumb_width as u32,
+        height: thumb_height as u32,
+        mime_type: "image/png".to_string(),
+        data: png,
+    })
+}
+
+fn check_len(data: &[u8], expected: usize) -> StreamResult<&[u8]> {
+    if data.len() < expected {
+        return Err(StreamError::encoding(format!(
+            "Frame has {} bytes, expected {}",
+            data.len(),
+            expected
+        )));
+    }
+    Ok(&data[..expected])
+}
+
+/// Convert planar I420 or semi-planar NV12 to RGB using BT.601
+fn yuv_to_rgb(data: &[u8], width: usize, height: usize, interleaved: bool) -> StreamResult<Vec<u8>> {
+    let (chroma_w, chroma_h) = (width.div_ceil(2), height.div_ceil(2));
+    let luma_len = width * height;
+    let data = check_len(data, luma_len + 2 * chroma_w * chroma_h)?;
+    let (luma, chroma) = data.split_at(luma_len);
+
+    let mut rgb = Vec::with_capacity(luma_len * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let c = (y / 2) * chroma_w + x / 2;
+            let (u, v) = if interleaved {
+                (chroma[2 * c], chroma[2 * c + 1])
+            } else {
+                (chroma[c], chroma[chroma_w * chroma_h + c])
+            };
+
+            let luma = (luma[y * width + x] as i32 - 16).max(0) * 298;
+            let (d, e) = (u as i32 - 128, v as i32 - 128);
+            let clamp = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+            rgb.push(clamp(luma + 409 * e));
+            rgb.push(clamp(luma - 100 * d - 208 * e));
+            rgb.push(clamp(luma + 516 * d));
+        }
+    }
+    Ok(rgb)
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crate::journal::crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap data in a zlib stream of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 65_535;
+
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 11);
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(u8::from(blocks.peek().is_none()));
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65_521;
+        b = (b + a) % 65_521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_execution::error::CommandResult;
+    use crate::command_execution::notification::NotificationCapabilities;
+    use tempfile::TempDir;
+
+    struct SolidGrabber;
+
+    #[async_trait]
+    impl FrameGrabber for SolidGrabber {
+        async fn grab(&self, _source: &SnapshotSource) -> StreamResult<Vec<u8>> {
+            encode_png(&VideoFrame {
+                data: vec![200; 4 * 2 * 3],
+                width: 4,
+                height: 2,
+                format: PixelFormat::RGB24,
+                timestamp: SystemTime::now(),
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingNotifications(Arc<Mutex<Vec<String>>>);
+
+    impl NotificationBackend for RecordingNotifications {
+        fn show_notification(&self, notification: &Notification) -> CommandResult<()> {
+            self.0.lock().unwrap().push(notification.message.clone());
+            Ok(())
+        }
+
+        fn is_supported(&self) -> bool {
+            true
+        }
+
+        fn get_capabilities(&self) -> NotificationCapabilities {
+            NotificationCapabilities {
+                supports_actions: false,
+                supports_duration: true,
+                supports_priority: true,
+                supports_icons: false,
+                supports_images: false,
+                supports_activation: false,
+                supports_replace: false,
+                supports_mirroring: false,
+                max_title_length: None,
+                max_message_length: None,
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingDelivery(Mutex<Vec<(PeerId, PathBuf)>>);
+
+    #[async_trait]
+    impl SnapshotDelivery for RecordingDelivery {
+        async fn deliver(&self, peer: &PeerId, path: &Path) -> StreamResult<()> {
+            self.0.lock().unwrap().push((peer.clone(), path.to_path_buf()));
+            Ok(())
+        }
+    }
+
+    fn setup(dir: &TempDir, notifications: Option<Arc<dyn NotificationBackend>>) -> (SnapshotService, Arc<RecordingDelivery>) {
+        let delivery = Arc::new(RecordingDelivery::default());
+        let config = SnapshotConfig {
+            notice_delay: Duration::ZERO,
+            require_approval: false,
+            output_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let service = SnapshotService::with_backends(Arc::new(SolidGrabber), notifications, delivery.clone(), config);
+        (service, delivery)
+    }
+
+    #[test]
+    fn test_encode_png_structure() {
+        let frame = VideoFrame {
+            data: vec![16; 10],
+            width: 3,
+            height: 2,
+            format: PixelFormat::YUV420,
+            timestamp: SystemTime::now(),
+        };
+        let png = encode_png(&frame).unwrap();
+
+        assert!(png.starts_with(PNG_SIGNATURE));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 3);
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 2);
+        assert!(png.ends_with(&[b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]));
+
+        let short = VideoFrame { data: vec![0; 5], ..frame };
+        assert!(encode_png(&short).is_err());
+    }
+
+    #[test]
+    fn test_preview_thumbnail_fits_limits() {
+        let frame = VideoFrame {
+            data: vec![90; 1920 * 1080 * 4],
+            width: 1920,
+            height: 1080,
+            format: PixelFormat::RGBA32,
+            timestamp: SystemTime::now(),
+        };
+        let thumbnail = preview_thumbnail(&frame).unwrap();
+
+        assert!(thumbnail.validate().is_ok());
+        assert!(thumbnail.width > thumbnail.height);
+        assert_eq!(thumbnail.width as usize * 1080 / 1920, thumbnail.height as usize);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_requires_grant_and_notifies() {
+        let dir = TempDir::new().unwrap();
+        let notifications = RecordingNotifications::default();
+        let shown = notifications.0.clone();
+        let (service, delivery) = setup(&dir, Some(Arc::new(notifications)));
+
+        let request = SnapshotRequest::new("helpdesk", SnapshotSource::Screen { monitor_index: None })
+            .with_reason("Show me the error dialog");
+        assert!(service.handle_request(&request).await.is_err());
+        assert!(shown.lock().unwrap().is_empty());
+
+        service.grant("helpdesk", SnapshotGrant { screen: true, camera: false });
+        let result = service.handle_request(&request).await.unwrap();
+
+        assert!(shown.lock().unwrap()[0].contains("error dialog"));
+        let sent = delivery.0.lock().unwrap();
+        assert_eq!(sent[0].0, "helpdesk");
+        assert!(sent[0].1.ends_with(&result.file_name));
+
+        let camera = SnapshotRequest::new("helpdesk", SnapshotSource::Camera { device_id: "/dev/video0".into() });
+        assert!(service.handle_request(&camera).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_rate_limited_and_refused_without_notification() {
+        let dir = TempDir::new().unwrap();
+        let (service, _) = setup(&dir, Some(Arc::new(RecordingNotifications::default())));
+        service.grant("helpdesk", SnapshotGrant { screen: true, camera: true });
+
+        let request = SnapshotRequest::new("helpdesk", SnapshotSource::Screen { monitor_index: Some(0) });
+        service.handle_request(&request).await.unwrap();
+        assert!(service.handle_request(&request).await.is_err());
+
+        let (silent, delivery) = setup(&dir, None);
+        silent.grant("helpdesk", SnapshotGrant { screen: true, camera: true });
+        assert!(silent.handle_request(&request).await.is_err());
+        assert!(delivery.0.lock().unwrap().is_empty());
+    }
+}
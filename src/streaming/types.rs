@@ -192,7 +192,7 @@ pub enum PixelFormat {
 }
 
 /// Screen region for screen capture
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ScreenRegion {
     pub x: u32,
     pub y: u32,
@@ -228,6 +228,117 @@ pub struct ScreenConfig {
     pub capture_audio: bool,
     pub monitor_index: Option<u32>,
     pub quality: StreamQuality,
+    #[serde(default)]
+    pub privacy: PrivacyMaskConfig,
+}
+
+/// Areas of a screen share hidden before encoding
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyMaskConfig {
+    pub masks: Vec<PrivacyMask>,
+}
+
+impl PrivacyMaskConfig {
+    /// Black out common password managers and chat apps
+    pub fn sensitive_apps() -> Self {
+        let apps = [
+            "1Password", "Bitwarden", "KeePassXC", "LastPass", "Dashlane",
+            "Signal", "Telegram", "WhatsApp", "Slack", "Discord",
+        ];
+        Self {
+            masks: apps
+                .iter()
+                .map(|app| PrivacyMask {
+                    target: MaskTarget::Application { name: app.to_string() },
+                    style: MaskStyle::BlackOut,
+                })
+                .collect(),
+        }
+    }
+
+    /// Add a mask
+    pub fn with_mask(mut self, target: MaskTarget, style: MaskStyle) -> Self {
+        self.masks.push(PrivacyMask { target, style });
+        self
+    }
+
+    /// Whether any masking is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.masks.is_empty()
+    }
+}
+
+/// One masked area
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyMask {
+    pub target: MaskTarget,
+    pub style: MaskStyle,
+}
+
+/// What a privacy mask covers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaskTarget {
+    /// Fixed rectangle, in the same coordinates as the capture region
+    Region(ScreenRegion),
+    /// Every window of an application, matched case-insensitively by name
+    Application { name: String },
+}
+
+/// How a masked area is hidden
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaskStyle {
+    /// Solid black
+    BlackOut,
+    /// Coarse blocks of the average colour
+    Pixelate { block_size: u32 },
+    /// Box blur; small radii are raised so text stays unreadable
+    Blur { radius: u32 },
+}
+
+/// Changes to an active screen share
+///
+/// Unset fields keep their current value. A region given together with a
+/// monitor is relative to that monitor.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenConfigUpdate {
+    pub monitor_index: Option<u32>,
+    pub region: Option<ScreenRegion>,
+    pub capture_cursor: Option<bool>,
+    pub privacy: Option<PrivacyMaskConfig>,
+}
+
+impl ScreenConfigUpdate {
+    /// Switch to another monitor
+    pub fn monitor(mut self, index: u32) -> Self {
+        self.monitor_index = Some(index);
+        self
+    }
+
+    /// Move or resize the captured region
+    pub fn region(mut self, region: ScreenRegion) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Turn cursor capture on or off
+    pub fn capture_cursor(mut self, enabled: bool) -> Self {
+        self.capture_cursor = Some(enabled);
+        self
+    }
+
+    /// Replace the privacy masks
+    pub fn privacy(mut self, privacy: PrivacyMaskConfig) -> Self {
+        self.privacy = Some(privacy);
+        self
+    }
+
+    /// Whether the update changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.monitor_index.is_none()
+            && self.region.is_none()
+            && self.capture_cursor.is_none()
+            && self.privacy.is_none()
+    }
 }
 
 /// Capture configuration
@@ -306,6 +417,29 @@ pub struct EncoderConfig {
     pub framerate: u32,
     pub bitrate: u32,
     pub hardware_acceleration: bool,
+    #[serde(default)]
+    pub intra_refresh: IntraRefreshConfig,
+}
+
+/// Periodic intra refresh for very lossy links
+///
+/// Instead of occasional full keyframes, every frame refreshes a band of
+/// macroblocks so the whole picture is rebuilt over `period_frames` frames
+/// and no single frame spikes the bitrate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntraRefreshConfig {
+    pub enabled: bool,
+    /// Frames over which the whole picture is refreshed
+    pub period_frames: u32,
+}
+
+impl Default for IntraRefreshConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            period_frames: 60,
+        }
+    }
 }
 
 /// Video codec type
@@ -352,6 +486,12 @@ pub struct ViewerPermissions {
     pub can_record: bool,
     pub can_control_quality: bool,
     pub max_quality: QualityPreset,
+    /// Burn an identifying watermark into this viewer's stream
+    ///
+    /// Watermarked viewers get their own encoder instead of sharing the
+    /// session's stream.
+    #[serde(default)]
+    pub watermark: Option<WatermarkConfig>,
 }
 
 impl Default for ViewerPermissions {
@@ -361,10 +501,50 @@ impl Default for ViewerPermissions {
             can_record: false,
             can_control_quality: false,
             max_quality: QualityPreset::Medium,
+            watermark: None,
         }
     }
 }
 
+/// Viewer identity overlay for leak deterrence
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatermarkConfig {
+    /// Show the viewer's peer ID
+    pub show_peer_id: bool,
+    /// Show the frame's capture time in UTC
+    pub show_timestamp: bool,
+    /// Extra text, e.g. "CONFIDENTIAL"
+    pub label: Option<String>,
+    /// Blend strength, 0 (invisible) to 255 (opaque)
+    pub opacity: u8,
+    /// Glyph scale; 1 draws 5x7 pixel characters
+    pub scale: u32,
+    pub layout: WatermarkLayout,
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            show_peer_id: true,
+            show_timestamp: true,
+            label: None,
+            opacity: 80,
+            scale: 2,
+            layout: WatermarkLayout::Tiled,
+        }
+    }
+}
+
+/// Where the watermark is drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatermarkLayout {
+    /// Repeated across the whole frame so it cannot be cropped out
+    #[default]
+    Tiled,
+    /// Once, in the bottom right corner
+    BottomRight,
+}
+
 /// Viewer status information
 /// 
 /// Requirements: 6.3, 8.5
@@ -391,7 +571,7 @@ pub enum ConnectionQuality {
 }
 
 /// Recording session handle
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordingSession {
     pub session_id: SessionId,
     pub stream_session: SessionId,
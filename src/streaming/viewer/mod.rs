@@ -3,21 +3,30 @@
 // Manages multiple viewers, viewer permissions, and efficient broadcasting
 // to multiple peers simultaneously.
 
+pub mod watermark;
+
+pub use watermark::{CodecFactory, RenditionFrame, ViewerRenditions, Watermark};
+
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::lockdown::{Lockdown, RemoteCapability};
+use crate::security::constant_time::ConstantTime;
 use crate::streaming::{
     ConnectionQuality, PeerId, StreamError, StreamQuality, StreamResult, ViewerId,
-    ViewerPermissions, ViewerStatus, VideoStream,
+    ViewerPermissions, ViewerStatus, VideoStream, WatermarkConfig,
 };
 
 /// Maximum number of concurrent viewers supported
 const MAX_VIEWERS: usize = 10;
 
+/// How long a dropped viewer keeps its seat and state for resumption
+pub const DEFAULT_RECONNECT_GRACE: Duration = Duration::from_secs(30);
+
 /// Viewer manager implementation
 /// 
 /// Manages multiple viewers for broadcasting scenarios, including viewer
@@ -96,6 +105,9 @@ struct ViewerInfo {
     bytes_sent: u64,
     current_quality: StreamQuality,
     state: ViewerState,
+    /// Secret the viewer presents to resume after a network drop
+    resume_token: String,
+    disconnected_at: Option<SystemTime>,
 }
 
 impl ViewerInfo {
@@ -111,9 +123,18 @@ impl ViewerInfo {
             bytes_sent: 0,
             current_quality: StreamQuality::default(),
             state: ViewerState::Connected,
+            resume_token: Uuid::new_v4().simple().to_string(),
+            disconnected_at: None,
         }
     }
 
+    /// Check if the viewer dropped and its grace window has run out
+    fn grace_expired(&self, grace: Duration, now: SystemTime) -> bool {
+        self.disconnected_at
+            .map(|at| now.duration_since(at).unwrap_or_default() > grace)
+            .unwrap_or(false)
+    }
+
     /// Convert to ViewerStatus
     fn to_status(&self) -> ViewerStatus {
         ViewerStatus {
@@ -168,6 +189,7 @@ enum ViewerState {
 pub struct ViewerRegistry {
     viewers: Arc<RwLock<HashMap<ViewerId, ViewerInfo>>>,
     pending_requests: Arc<RwLock<HashMap<PeerId, ViewerPermissions>>>,
+    reconnect_grace: Duration,
 }
 
 impl ViewerRegistry {
@@ -176,9 +198,16 @@ impl ViewerRegistry {
         Self {
             viewers: Arc::new(RwLock::new(HashMap::new())),
             pending_requests: Arc::new(RwLock::new(HashMap::new())),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
         }
     }
 
+    /// Set how long a dropped viewer can resume without re-approval
+    pub fn with_reconnect_grace(mut self, grace: Duration) -> Self {
+        self.reconnect_grace = grace;
+        self
+    }
+
     /// Add a viewer with authentication and permissions
     /// 
     /// Requirements: 6.1, 6.4, 8.3, 8.4
@@ -187,8 +216,24 @@ impl ViewerRegistry {
         peer_id: PeerId,
         permissions: ViewerPermissions,
     ) -> StreamResult<ViewerId> {
+        let lockdown = Lockdown::global();
+        lockdown
+            .check(RemoteCapability::StreamViewing)
+            .map_err(|e| StreamError::permission(e.to_string()))?;
+
         let mut viewers = self.viewers.write().await;
 
+        // A fresh connection replaces a dropped entry the peer did not resume,
+        // and viewers revoked by a lockdown make room for new ones
+        let now = SystemTime::now();
+        let grace = self.reconnect_grace;
+        let lockdown = lockdown.state();
+        viewers.retain(|_, v| {
+            !v.grace_expired(grace, now)
+                && !(v.peer_id == peer_id && v.state == ViewerState::Disconnected)
+                && lockdown.admits(v.connected_at)
+        });
+
         // Check if viewer limit reached
         if viewers.len() >= MAX_VIEWERS {
             return Err(StreamError::viewer(format!(
@@ -242,6 +287,75 @@ impl ViewerRegistry {
         Ok(())
     }
 
+    /// Get the token a viewer presents to resume after a network drop
+    pub async fn viewer_token(&self, viewer_id: ViewerId) -> StreamResult<String> {
+        Ok(self.get_viewer(viewer_id).await?.resume_token)
+    }
+
+    /// Mark a viewer as dropped, keeping its entry for the grace window
+    /// 
+    /// Requirements: 6.3
+    pub async fn mark_disconnected(&self, viewer_id: ViewerId) -> StreamResult<()> {
+        let mut viewers = self.viewers.write().await;
+        let viewer = viewers
+            .get_mut(&viewer_id)
+            .ok_or_else(|| StreamError::viewer(format!("Viewer {} not found", viewer_id)))?;
+
+        viewer.state = ViewerState::Disconnected;
+        viewer.connection_quality = ConnectionQuality::Disconnected;
+        viewer.disconnected_at = Some(SystemTime::now());
+        Ok(())
+    }
+
+    /// Resume a dropped viewer with its token, keeping its permissions and quality
+    /// 
+    /// Requirements: 6.3, 8.3
+    pub async fn resume_viewer(&self, peer_id: &PeerId, token: &str) -> StreamResult<ViewerId> {
+        let mut viewers = self.viewers.write().await;
+        let viewer = viewers
+            .values_mut()
+            .find(|v| &v.peer_id == peer_id && ConstantTime::compare(v.resume_token.as_bytes(), token.as_bytes()))
+            .ok_or_else(|| StreamError::permission("Unknown viewer resume token"))?;
+
+        Lockdown::global()
+            .check_admitted(RemoteCapability::StreamViewing, viewer.connected_at)
+            .map_err(|e| StreamError::permission(e.to_string()))?;
+        if viewer.state != ViewerState::Disconnected {
+            return Err(StreamError::viewer(format!(
+                "Viewer {} is still connected",
+                viewer.viewer_id
+            )));
+        }
+        if viewer.grace_expired(self.reconnect_grace, SystemTime::now()) {
+            let viewer_id = viewer.viewer_id;
+            viewers.remove(&viewer_id);
+            return Err(StreamError::viewer(format!(
+                "Reconnect window for viewer {} has expired",
+                viewer_id
+            )));
+        }
+
+        viewer.state = ViewerState::Connected;
+        viewer.connection_quality = ConnectionQuality::Good;
+        viewer.disconnected_at = None;
+        Ok(viewer.viewer_id)
+    }
+
+    /// Remove dropped viewers whose grace window has run out
+    pub async fn prune_disconnected(&self) -> Vec<ViewerId> {
+        let mut viewers = self.viewers.write().await;
+        let now = SystemTime::now();
+        let expired: Vec<ViewerId> = viewers
+            .values()
+            .filter(|v| v.grace_expired(self.reconnect_grace, now))
+            .map(|v| v.viewer_id)
+            .collect();
+        for viewer_id in &expired {
+            viewers.remove(viewer_id);
+        }
+        expired
+    }
+
     /// Get viewer information
     pub async fn get_viewer(&self, viewer_id: ViewerId) -> StreamResult<ViewerInfo> {
         let viewers = self.viewers.read().await;
@@ -335,7 +449,7 @@ impl ViewerRegistry {
 
         // Check if already connected
         let viewers = self.viewers.read().await;
-        if viewers.values().any(|v| v.peer_id == peer_id) {
+        if viewers.values().any(|v| v.peer_id == peer_id && v.state != ViewerState::Disconnected) {
             return Err(StreamError::viewer(format!(
                 "Peer {} is already connected",
                 peer_id
@@ -393,6 +507,20 @@ impl ViewerRegistry {
         viewers.keys().copied().collect()
     }
 
+    /// Get IDs of viewers currently receiving the stream
+    ///
+    /// Viewers that joined before a lockdown are left out even once it is
+    /// lifted; they have to connect again.
+    pub async fn get_connected_viewer_ids(&self) -> Vec<ViewerId> {
+        let lockdown = Lockdown::global().state();
+        let viewers = self.viewers.read().await;
+        viewers
+            .values()
+            .filter(|v| v.state == ViewerState::Connected && lockdown.admits(v.connected_at))
+            .map(|v| v.viewer_id)
+            .collect()
+    }
+
     /// Check if viewer has permission
     pub async fn check_permission(
         &self,
@@ -404,6 +532,9 @@ impl ViewerRegistry {
             .get(&viewer_id)
             .ok_or_else(|| StreamError::viewer(format!("Viewer {} not found", viewer_id)))?;
 
+        if !Lockdown::global().state().admits(viewer.connected_at) {
+            return Ok(false);
+        }
         Ok(check(&viewer.permissions))
     }
 }
@@ -423,15 +554,37 @@ impl Default for ViewerRegistry {
 /// Requirements: 6.1, 6.2, 6.5
 pub struct BroadcastController {
     active_broadcasts: Arc<RwLock<HashMap<Uuid, BroadcastSession>>>,
+    /// Sessions whose frames are held back while viewers see a paused card
+    paused: Arc<RwLock<HashSet<Uuid>>>,
 }
 
 impl BroadcastController {
     pub fn new() -> Self {
         Self {
             active_broadcasts: Arc::new(RwLock::new(HashMap::new())),
+            paused: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 
+    /// Stop sending frames for a session without dropping its viewers
+    ///
+    /// Returns `false` if the session was already paused.
+    pub async fn pause_broadcast(&self, session_id: Uuid) -> bool {
+        self.paused.write().await.insert(session_id)
+    }
+
+    /// Send frames for a paused session again
+    ///
+    /// Returns `false` if the session was not paused.
+    pub async fn resume_broadcast(&self, session_id: Uuid) -> bool {
+        self.paused.write().await.remove(&session_id)
+    }
+
+    /// Check whether a session's frames are being held back
+    pub async fn is_paused(&self, session_id: Uuid) -> bool {
+        self.paused.read().await.contains(&session_id)
+    }
+
     /// Broadcast stream to all viewers
     /// 
     /// Efficiently streams to multiple viewers with optimized encoding
@@ -443,7 +596,7 @@ impl BroadcastController {
         stream: VideoStream,
         registry: &ViewerRegistry,
     ) -> StreamResult<()> {
-        let viewer_ids = registry.get_viewer_ids().await;
+        let viewer_ids = registry.get_connected_viewer_ids().await;
 
         if viewer_ids.is_empty() {
             return Err(StreamError::viewer("No viewers connected"));
@@ -469,6 +622,20 @@ impl BroadcastController {
         // Update viewer list
         session.update_viewers(viewer_ids.clone());
 
+        // Viewers stay registered while paused; they just get no frames
+        if self.paused.read().await.contains(&session_id) {
+            return Ok(());
+        }
+
+        // Watermarked viewers each need their own encoder
+        let mut dedicated = 0;
+        for viewer_id in &viewer_ids {
+            if registry.get_viewer(*viewer_id).await?.permissions.watermark.is_some() {
+                dedicated += 1;
+            }
+        }
+        session.dedicated_renditions = dedicated;
+
         // Calculate optimal encoding settings for all viewers
         let optimal_quality = self.calculate_optimal_quality(registry, &viewer_ids).await?;
 
@@ -647,6 +814,8 @@ impl BroadcastController {
             total_frames_sent: session.total_frames_sent,
             current_quality: session.current_quality.clone(),
             started_at: session.started_at,
+            dedicated_renditions: session.dedicated_renditions,
+            paused: self.paused.read().await.contains(&session_id),
         })
     }
 
@@ -657,6 +826,7 @@ impl BroadcastController {
         if broadcasts.remove(&session_id).is_none() {
             return Err(StreamError::session_not_found(session_id));
         }
+        self.paused.write().await.remove(&session_id);
 
         Ok(())
     }
@@ -682,6 +852,7 @@ struct BroadcastSession {
     current_quality: StreamQuality,
     total_frames_sent: u64,
     started_at: SystemTime,
+    dedicated_renditions: usize,
 }
 
 impl BroadcastSession {
@@ -692,6 +863,7 @@ impl BroadcastSession {
             current_quality: quality,
             total_frames_sent: 0,
             started_at: SystemTime::now(),
+            dedicated_renditions: 0,
         }
     }
 
@@ -720,6 +892,10 @@ pub struct BroadcastStats {
     pub total_frames_sent: u64,
     pub current_quality: StreamQuality,
     pub started_at: SystemTime,
+    /// Viewers encoded separately because their stream is watermarked
+    pub dedicated_renditions: usize,
+    /// Frames are held back and viewers see a paused card
+    pub paused: bool,
 }
 
 /// Viewer management controls
@@ -781,15 +957,30 @@ impl ViewerManagementControls {
 
     /// Handle viewer disconnection
     /// 
-    /// Processes viewer disconnection and cleanup.
+    /// Keeps the viewer's seat, permissions and quality for the reconnect
+    /// grace window so a network blip does not require re-approval.
     /// 
     /// Requirements: 6.3
     pub async fn handle_viewer_disconnection(&self, viewer_id: ViewerId) -> StreamResult<()> {
-        self.registry.remove_viewer(viewer_id).await?;
+        self.registry.mark_disconnected(viewer_id).await?;
         println!("Viewer {} disconnected", viewer_id);
         Ok(())
     }
 
+    /// Handle a dropped viewer coming back with its resume token
+    /// 
+    /// Requirements: 6.3, 8.3
+    pub async fn handle_viewer_reconnection(
+        &self,
+        peer_id: PeerId,
+        token: &str,
+    ) -> StreamResult<ViewerConnectionResult> {
+        match self.registry.resume_viewer(&peer_id, token).await {
+            Ok(viewer_id) => Ok(ViewerConnectionResult::Connected(viewer_id)),
+            Err(e) => Ok(ViewerConnectionResult::Rejected(e.to_string())),
+        }
+    }
+
     /// Update viewer permissions
     /// 
     /// Modifies permissions for an existing viewer.
@@ -854,6 +1045,21 @@ impl ViewerManagementControls {
         self.update_viewer_permissions(viewer_id, permissions).await
     }
 
+    /// Set or clear the watermark burned into a viewer's stream
+    /// 
+    /// Requirements: 6.4, 8.3
+    pub async fn set_watermark(
+        &self,
+        viewer_id: ViewerId,
+        watermark: Option<WatermarkConfig>,
+    ) -> StreamResult<()> {
+        let viewer = self.registry.get_viewer(viewer_id).await?;
+        let mut permissions = viewer.permissions;
+        permissions.watermark = watermark;
+
+        self.update_viewer_permissions(viewer_id, permissions).await
+    }
+
     /// Get viewer status report
     /// 
     /// Provides detailed status information for a specific viewer.
@@ -1016,3 +1222,49 @@ pub struct PendingApproval {
     pub permissions: ViewerPermissions,
     pub requested_at: SystemTime,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dropped_viewer_resumes_with_token() {
+        let registry = ViewerRegistry::new();
+        let peer_id = "peer-0123456789".to_string();
+        let permissions = ViewerPermissions {
+            can_record: true,
+            ..ViewerPermissions::default()
+        };
+
+        let viewer_id = registry.add_viewer(peer_id.clone(), permissions).await.unwrap();
+        registry.set_viewer_quality(viewer_id, StreamQuality::default()).await.unwrap();
+        let token = registry.viewer_token(viewer_id).await.unwrap();
+
+        registry.mark_disconnected(viewer_id).await.unwrap();
+        assert!(registry.has_viewer(viewer_id).await);
+        assert!(registry.get_connected_viewer_ids().await.is_empty());
+        assert!(registry.resume_viewer(&peer_id, "wrong").await.is_err());
+
+        assert_eq!(registry.resume_viewer(&peer_id, &token).await.unwrap(), viewer_id);
+        let viewer = registry.get_viewer(viewer_id).await.unwrap();
+        assert!(viewer.permissions.can_record);
+        assert_eq!(registry.get_connected_viewer_ids().await, vec![viewer_id]);
+    }
+
+    #[tokio::test]
+    async fn test_expired_grace_requires_new_approval() {
+        let registry = ViewerRegistry::new().with_reconnect_grace(Duration::ZERO);
+        let peer_id = "peer-0123456789".to_string();
+        let viewer_id = registry
+            .add_viewer(peer_id.clone(), ViewerPermissions::default())
+            .await
+            .unwrap();
+        let token = registry.viewer_token(viewer_id).await.unwrap();
+
+        registry.mark_disconnected(viewer_id).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        assert!(registry.resume_viewer(&peer_id, &token).await.is_err());
+        assert!(!registry.has_viewer(viewer_id).await);
+    }
+}
@@ -0,0 +1,448 @@
+// Viewer identity watermarking
+//
+// Burns a semi-transparent overlay with the viewer's peer ID and the capture
+// time into the frames sent to that viewer, so a leaked recording or
+// screenshot can be traced back to the session it came from. Because every
+// watermarked viewer sees different pixels, each one gets a dedicated
+// encoder; viewers without a watermark keep sharing the session's encoder.
+//
+// Requirements: 6.4, 8.3
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::streaming::{
+    EncodedFrame, EncodingQuality, PeerId, PixelFormat, StreamError, StreamResult, VideoCodec,
+    VideoFrame, ViewerId, ViewerPermissions, WatermarkConfig, WatermarkLayout,
+};
+
+/// Glyph size in pixels at scale 1
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+/// Glyph cell including spacing
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT as u32 + 1;
+
+/// Renders one viewer's watermark
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    config: WatermarkConfig,
+    peer_id: PeerId,
+}
+
+impl Watermark {
+    /// Create the watermark for a viewer
+    pub fn new(config: WatermarkConfig, peer_id: PeerId) -> Self {
+        Self { config, peer_id }
+    }
+
+    /// Watermark configuration
+    pub fn config(&self) -> &WatermarkConfig {
+        &self.config
+    }
+
+    /// Text drawn on a frame captured at `time`
+    pub fn text_at(&self, time: std::time::SystemTime) -> String {
+        let mut parts = Vec::new();
+        if let Some(label) = &self.config.label {
+            parts.push(label.clone());
+        }
+        if self.config.show_peer_id {
+            parts.push(self.peer_id.clone());
+        }
+        if self.config.show_timestamp {
+            let time: DateTime<Utc> = time.into();
+            parts.push(time.format("%Y-%m-%d %H:%M:%S UTC").to_string());
+        }
+        parts.join("  ")
+    }
+
+    /// Draw the watermark onto a raw frame
+    ///
+    /// Only luma is changed for YUV frames, so the overlay is grey. Each
+    /// glyph pixel is pushed away from the underlying brightness, keeping
+    /// the text visible on both light and dark content.
+    pub fn apply(&self, frame: &mut VideoFrame) -> StreamResult<()> {
+        let text = self.text_at(frame.timestamp);
+        if text.is_empty() || self.config.opacity == 0 {
+            return Ok(());
+        }
+
+        let (bpp, channels) = match frame.format {
+            PixelFormat::RGB24 => (3, 3),
+            // Leave alpha alone
+            PixelFormat::RGBA32 => (4, 3),
+            PixelFormat::YUV420 | PixelFormat::NV12 => (1, 1),
+            PixelFormat::MJPEG => {
+                return Err(StreamError::unsupported("Watermarks need raw frames"));
+            }
+        };
+        let required = frame.width as usize * frame.height as usize * bpp;
+        if frame.data.len() < required {
+            return Err(StreamError::capture(format!(
+                "Frame buffer too small: {} bytes for {}x{} {:?}",
+                frame.data.len(),
+                frame.width,
+                frame.height,
+                frame.format
+            )));
+        }
+
+        let scale = self.config.scale.max(1);
+        let text_width = text.chars().count() as u32 * CELL_WIDTH * scale;
+        let text_height = CELL_HEIGHT * scale;
+
+        let origins = match self.config.layout {
+            WatermarkLayout::BottomRight => {
+                let margin = CELL_HEIGHT * scale;
+                vec![(
+                    frame.width as i64 - text_width as i64 - margin as i64,
+                    frame.height as i64 - text_height as i64 - margin as i64,
+                )]
+            }
+            WatermarkLayout::Tiled => {
+                // Staggered rows so any crop still contains a full copy
+                let step_x = (text_width + text_width / 2) as i64;
+                let step_y = (text_height * 4) as i64;
+                let mut origins = Vec::new();
+                let mut row = 0;
+                let mut y = text_height as i64;
+                while y < frame.height as i64 {
+                    let mut x = if row % 2 == 0 { 0 } else { -step_x / 2 };
+                    while x < frame.width as i64 {
+                        origins.push((x, y));
+                        x += step_x;
+                    }
+                    y += step_y;
+                    row += 1;
+                }
+                origins
+            }
+        };
+
+        let opacity = self.config.opacity as u32;
+        for (ox, oy) in origins {
+            for (i, c) in text.chars().enumerate() {
+                let rows = glyph(c);
+                let gx = ox + (i as u32 * CELL_WIDTH * scale) as i64;
+                for (row, bits) in rows.iter().enumerate() {
+                    for col in 0..GLYPH_WIDTH {
+                        if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                            continue;
+                        }
+                        for dy in 0..scale {
+                            for dx in 0..scale {
+                                let x = gx + (col * scale + dx) as i64;
+                                let y = oy + (row as u32 * scale + dy) as i64;
+                                if x < 0 || y < 0 || x >= frame.width as i64 || y >= frame.height as i64 {
+                                    continue;
+                                }
+                                let offset = (y as usize * frame.width as usize + x as usize) * bpp;
+                                for value in &mut frame.data[offset..offset + channels] {
+                                    let target = if *value < 128 { 255 } else { 0 };
+                                    *value = ((*value as u32 * (255 - opacity) + target * opacity) / 255) as u8;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Creates an encoder for a viewer that needs its own rendition
+pub type CodecFactory = Arc<dyn Fn() -> StreamResult<Arc<dyn VideoCodec>> + Send + Sync>;
+
+/// A frame encoded once and sent to one or more viewers
+#[derive(Debug, Clone)]
+pub struct RenditionFrame {
+    pub viewers: Vec<ViewerId>,
+    pub frame: EncodedFrame,
+}
+
+/// Encoder of a watermarked viewer
+struct DedicatedRendition {
+    watermark: Watermark,
+    codec: Arc<dyn VideoCodec>,
+}
+
+/// Per-viewer encoding path for a session
+///
+/// Viewers without a watermark share one encoded stream. Each watermarked
+/// viewer gets a copy of the raw frame with its watermark burned in, encoded
+/// by its own encoder.
+pub struct ViewerRenditions {
+    shared: Arc<dyn VideoCodec>,
+    factory: CodecFactory,
+    dedicated: HashMap<ViewerId, DedicatedRendition>,
+}
+
+impl ViewerRenditions {
+    /// Create the renditions for a session around its shared encoder
+    pub fn new(shared: Arc<dyn VideoCodec>, factory: CodecFactory) -> Self {
+        Self {
+            shared,
+            factory,
+            dedicated: HashMap::new(),
+        }
+    }
+
+    /// Route a viewer according to its permissions
+    ///
+    /// Called when a viewer joins or its permissions change. A viewer that
+    /// gains a watermark gets a new encoder; one that loses it moves back to
+    /// the shared stream.
+    pub fn set_viewer(
+        &mut self,
+        viewer_id: ViewerId,
+        peer_id: &PeerId,
+        permissions: &ViewerPermissions,
+    ) -> StreamResult<()> {
+        let Some(config) = &permissions.watermark else {
+            self.dedicated.remove(&viewer_id);
+            return Ok(());
+        };
+
+        let watermark = Watermark::new(config.clone(), peer_id.clone());
+        match self.dedicated.get_mut(&viewer_id) {
+            Some(rendition) => rendition.watermark = watermark,
+            None => {
+                let codec = (self.factory)()?;
+                self.dedicated.insert(viewer_id, DedicatedRendition { watermark, codec });
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop a viewer's dedicated encoder, if any
+    pub fn remove_viewer(&mut self, viewer_id: ViewerId) {
+        self.dedicated.remove(&viewer_id);
+    }
+
+    /// Whether a viewer has its own encoder
+    pub fn is_dedicated(&self, viewer_id: ViewerId) -> bool {
+        self.dedicated.contains_key(&viewer_id)
+    }
+
+    /// Number of dedicated encoders
+    pub fn dedicated_count(&self) -> usize {
+        self.dedicated.len()
+    }
+
+    /// Force a keyframe on the encoder that serves a viewer
+    pub async fn request_keyframe(&self, viewer_id: ViewerId) -> StreamResult<()> {
+        match self.dedicated.get(&viewer_id) {
+            Some(rendition) => rendition.codec.request_keyframe().await,
+            None => self.shared.request_keyframe().await,
+        }
+    }
+
+    /// Encode a captured frame for every listed viewer
+    pub async fn encode(
+        &self,
+        frame: VideoFrame,
+        quality: EncodingQuality,
+        viewers: &[ViewerId],
+    ) -> StreamResult<Vec<RenditionFrame>> {
+        let mut renditions = Vec::new();
+
+        for viewer_id in viewers {
+            if let Some(rendition) = self.dedicated.get(viewer_id) {
+                let mut marked = frame.clone();
+                rendition.watermark.apply(&mut marked)?;
+                let encoded = rendition.codec.encode_frame(marked, quality.clone()).await?;
+                renditions.push(RenditionFrame {
+                    viewers: vec![*viewer_id],
+                    frame: encoded,
+                });
+            }
+        }
+
+        let shared: Vec<ViewerId> = viewers
+            .iter()
+            .filter(|id| !self.dedicated.contains_key(*id))
+            .copied()
+            .collect();
+        if !shared.is_empty() {
+            let encoded = self.shared.encode_frame(frame, quality).await?;
+            renditions.push(RenditionFrame {
+                viewers: shared,
+                frame: encoded,
+            });
+        }
+
+        Ok(renditions)
+    }
+}
+
+/// Row bitmaps for a character, leftmost pixel in bit 4
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+        'D' => [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+        ':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+        '_' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111],
+        '/' => [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000],
+        ' ' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+        // Unknown characters draw as '?'
+        _ => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use crate::streaming::{EncoderCapabilities, EncoderConfig, Resolution};
+    use std::time::{Duration, SystemTime};
+
+    fn grey_frame() -> VideoFrame {
+        VideoFrame {
+            data: vec![100; 320 * 120 * 3],
+            width: 320,
+            height: 120,
+            format: PixelFormat::RGB24,
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        }
+    }
+
+    /// Encoder that passes raw frames through unchanged
+    struct PassthroughCodec;
+
+    #[async_trait]
+    impl VideoCodec for PassthroughCodec {
+        async fn encode_frame(&self, frame: VideoFrame, _quality: EncodingQuality) -> StreamResult<EncodedFrame> {
+            Ok(EncodedFrame {
+                data: frame.data,
+                timestamp: frame.timestamp,
+                is_keyframe: false,
+            })
+        }
+
+        async fn decode_frame(&self, _data: &[u8]) -> StreamResult<VideoFrame> {
+            Err(StreamError::unsupported("decode"))
+        }
+
+        async fn configure_encoder(&self, _config: EncoderConfig) -> StreamResult<()> {
+            Ok(())
+        }
+
+        async fn get_encoder_capabilities(&self) -> StreamResult<EncoderCapabilities> {
+            Ok(EncoderCapabilities {
+                supported_codecs: Vec::new(),
+                hardware_acceleration_available: false,
+                max_resolution: Resolution { width: 320, height: 120 },
+                max_framerate: 30,
+            })
+        }
+
+        async fn enable_hardware_acceleration(&self) -> StreamResult<bool> {
+            Ok(false)
+        }
+
+        async fn request_keyframe(&self) -> StreamResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_watermark_is_drawn_and_identifies_viewer() {
+        let config = WatermarkConfig {
+            label: Some("Confidential".to_string()),
+            ..WatermarkConfig::default()
+        };
+        let watermark = Watermark::new(config, "peer-abc".to_string());
+
+        let mut frame = grey_frame();
+        assert_eq!(
+            watermark.text_at(frame.timestamp),
+            "Confidential  peer-abc  2023-11-14 22:13:20 UTC"
+        );
+        watermark.apply(&mut frame).unwrap();
+
+        // Dark content is brightened, never darkened, and stays translucent
+        let changed: Vec<u8> = frame.data.iter().copied().filter(|&v| v != 100).collect();
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|&v| v > 100 && v < 255));
+
+        let mut other = grey_frame();
+        Watermark::new(WatermarkConfig::default(), "peer-xyz".to_string())
+            .apply(&mut other)
+            .unwrap();
+        assert_ne!(frame.data, other.data);
+    }
+
+    #[tokio::test]
+    async fn test_watermarked_viewers_get_own_rendition() {
+        let factory: CodecFactory = Arc::new(|| Ok(Arc::new(PassthroughCodec) as Arc<dyn VideoCodec>));
+        let mut renditions = ViewerRenditions::new(Arc::new(PassthroughCodec), factory);
+
+        let (plain_a, plain_b, marked) = (ViewerId::new_v4(), ViewerId::new_v4(), ViewerId::new_v4());
+        let watermarked = ViewerPermissions {
+            watermark: Some(WatermarkConfig::default()),
+            ..ViewerPermissions::default()
+        };
+        renditions.set_viewer(plain_a, &"peer-a".to_string(), &ViewerPermissions::default()).unwrap();
+        renditions.set_viewer(marked, &"peer-m".to_string(), &watermarked).unwrap();
+        assert_eq!(renditions.dedicated_count(), 1);
+
+        let quality = EncodingQuality { bitrate: 1_000_000, quality_factor: 80, keyframe_interval: 60 };
+        let frames = renditions
+            .encode(grey_frame(), quality.clone(), &[plain_a, plain_b, marked])
+            .await
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+
+        let own = frames.iter().find(|r| r.viewers == vec![marked]).unwrap();
+        let shared = frames.iter().find(|r| r.viewers == vec![plain_a, plain_b]).unwrap();
+        assert_eq!(shared.frame.data, grey_frame().data);
+        assert_ne!(own.frame.data, shared.frame.data);
+
+        // Dropping the watermark moves the viewer back to the shared stream
+        renditions.set_viewer(marked, &"peer-m".to_string(), &ViewerPermissions::default()).unwrap();
+        let frames = renditions.encode(grey_frame(), quality, &[plain_a, marked]).await.unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].viewers, vec![plain_a, marked]);
+    }
+}
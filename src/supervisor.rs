@@ -0,0 +1,413 @@
+//! Supervision of long-running subsystems
+//!
+//! The daemon runs components such as the mDNS responder and the clipboard
+//! monitor as background tasks. Without supervision a component that returns an
+//! error or panics simply disappears and the daemon keeps running without it. A
+//! [`Supervisor`] runs each component in its own task and, when one fails:
+//!
+//! 1. records the failure in the component's [`ComponentHealth`];
+//! 2. restarts it after an exponential backoff, so a component failing in a loop
+//!    does not spin; the failure count resets once it has run for
+//!    [`SupervisorConfig::stable_after`];
+//! 3. after [`SupervisorConfig::escalate_after`] consecutive failures, hands the
+//!    component's health to the escalation callback, typically a desktop
+//!    notification, and gives up once [`SupervisorConfig::max_restarts`] is hit.
+//!
+//! Components are spawned through the [`ShutdownCoordinator`], so shutdown
+//! cancels them instead of looking like a crash. A component that returns `Ok`
+//! on its own is considered finished and is not restarted.
+//! [`Supervisor::health`] reports every component for health endpoints.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio_util::sync::CancellationToken;
+
+use crate::command_execution::notification::{create_notification, NotificationManager};
+use crate::command_execution::types::{NotificationPriority, NotificationType};
+use crate::error::KizunaResult;
+use crate::shutdown::ShutdownCoordinator;
+
+type ComponentFuture = Pin<Box<dyn Future<Output = KizunaResult<()>> + Send>>;
+type ComponentFactory = Arc<dyn Fn(CancellationToken) -> ComponentFuture + Send + Sync>;
+
+/// Called with a component's health when its failures are escalated
+pub type EscalationCallback = Arc<dyn Fn(&ComponentHealth) + Send + Sync>;
+
+/// Restart and escalation limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupervisorConfig {
+    /// Delay before the first restart; doubles with each consecutive failure
+    pub initial_backoff: Duration,
+    /// Longest delay between restarts
+    pub max_backoff: Duration,
+    /// Run time after which a component counts as recovered
+    pub stable_after: Duration,
+    /// Consecutive failures before the failure is escalated
+    pub escalate_after: u32,
+    /// Consecutive failures after which the component is given up on
+    pub max_restarts: Option<u32>,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            stable_after: Duration::from_secs(60),
+            escalate_after: 3,
+            max_restarts: None,
+        }
+    }
+}
+
+impl SupervisorConfig {
+    /// Delay before restarting after `failures` consecutive failures
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// What a supervised component is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentState {
+    Running,
+    /// Crashed and waiting for its backoff before restarting
+    Restarting,
+    /// Finished on its own or stopped by shutdown
+    Stopped,
+    /// Gave up after too many consecutive failures
+    Failed,
+}
+
+/// Health of one supervised component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub state: ComponentState,
+    /// Restarts since the supervisor started
+    pub restarts: u32,
+    /// Failures since the component last ran stably
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_failure_at: Option<SystemTime>,
+}
+
+impl ComponentHealth {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            state: ComponentState::Running,
+            restarts: 0,
+            consecutive_failures: 0,
+            last_error: None,
+            last_failure_at: None,
+        }
+    }
+
+    /// Whether the component is doing its job
+    pub fn is_healthy(&self) -> bool {
+        matches!(self.state, ComponentState::Running | ComponentState::Stopped)
+    }
+}
+
+/// Health of every supervised component, for health endpoints
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub components: Vec<ComponentHealth>,
+}
+
+impl HealthReport {
+    /// Whether every component is running or finished cleanly
+    pub fn is_ready(&self) -> bool {
+        self.components.iter().all(ComponentHealth::is_healthy)
+    }
+
+    /// Components that are restarting or have failed
+    pub fn unhealthy(&self) -> impl Iterator<Item = &ComponentHealth> {
+        self.components.iter().filter(|component| !component.is_healthy())
+    }
+}
+
+/// Restarts crashed subsystems and tracks their health
+///
+/// Share it behind an `Arc`; all methods take `&self`.
+pub struct Supervisor {
+    config: SupervisorConfig,
+    shutdown: Arc<ShutdownCoordinator>,
+    components: Arc<StdMutex<BTreeMap<String, ComponentHealth>>>,
+    escalation: Option<EscalationCallback>,
+}
+
+impl Supervisor {
+    /// Create a supervisor whose components stop when `shutdown` begins
+    pub fn new(shutdown: Arc<ShutdownCoordinator>, config: SupervisorConfig) -> Self {
+        Self {
+            config,
+            shutdown,
+            components: Arc::new(StdMutex::new(BTreeMap::new())),
+            escalation: None,
+        }
+    }
+
+    /// Call `escalation` when a component keeps failing
+    pub fn with_escalation(mut self, escalation: EscalationCallback) -> Self {
+        self.escalation = Some(escalation);
+        self
+    }
+
+    /// Restart and escalation limits
+    pub fn config(&self) -> &SupervisorConfig {
+        &self.config
+    }
+
+    /// Run a component under supervision
+    ///
+    /// `component` is called again for every restart. It is handed a token
+    /// cancelled at shutdown and should return promptly once it fires.
+    pub fn supervise<F, Fut>(&self, name: &str, component: F)
+    where
+        F: Fn(CancellationToken) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = KizunaResult<()>> + Send + 'static,
+    {
+        let factory: ComponentFactory = Arc::new(move |token| -> ComponentFuture { Box::pin(component(token)) });
+        self.components
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), ComponentHealth::new(name));
+
+        let name = name.to_string();
+        let config = self.config;
+        let components = Arc::clone(&self.components);
+        let escalation = self.escalation.clone();
+        self.shutdown.spawn(&format!("supervised {}", name), move |token| {
+            run_supervised(name, factory, config, components, escalation, token)
+        });
+    }
+
+    /// Current health of one component
+    pub fn component(&self, name: &str) -> Option<ComponentHealth> {
+        self.components.lock().unwrap().get(name).cloned()
+    }
+
+    /// Current health of every component, by name
+    pub fn health(&self) -> HealthReport {
+        HealthReport {
+            components: self.components.lock().unwrap().values().cloned().collect(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Supervisor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Supervisor")
+            .field("config", &self.config)
+            .field("components", &self.components.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// Escalation that shows a desktop notification
+pub fn desktop_escalation(manager: Arc<NotificationManager>) -> EscalationCallback {
+    Arc::new(move |health: &ComponentHealth| {
+        let (title, notification_type) = match health.state {
+            ComponentState::Failed => (format!("Kizuna stopped {}", health.name), NotificationType::Error),
+            _ => (format!("Kizuna {} keeps failing", health.name), NotificationType::Warning),
+        };
+        let message = format!(
+            "{} failures in a row: {}",
+            health.consecutive_failures,
+            health.last_error.as_deref().unwrap_or("unknown error")
+        );
+        let mut notification = create_notification(title, message, notification_type, "local".to_string());
+        notification.priority = NotificationPriority::High;
+
+        let manager = Arc::clone(&manager);
+        tokio::spawn(async move {
+            if let Err(e) = manager.send_notification(notification, "local".to_string()).await {
+                log::warn!("Failed to show supervisor notification: {}", e);
+            }
+        });
+    })
+}
+
+async fn run_supervised(
+    name: String,
+    factory: ComponentFactory,
+    config: SupervisorConfig,
+    components: Arc<StdMutex<BTreeMap<String, ComponentHealth>>>,
+    escalation: Option<EscalationCallback>,
+    token: CancellationToken,
+) {
+    let update = |change: &dyn Fn(&mut ComponentHealth)| -> Option<ComponentHealth> {
+        let mut components = components.lock().unwrap();
+        let health = components.get_mut(&name)?;
+        change(health);
+        Some(health.clone())
+    };
+
+    loop {
+        let started = Instant::now();
+        // A separate task turns a panic into a JoinError instead of unwinding here
+        let outcome = tokio::spawn(factory(token.clone())).await;
+
+        if token.is_cancelled() {
+            update(&|health| health.state = ComponentState::Stopped);
+            return;
+        }
+        let error = match outcome {
+            Ok(Ok(())) => {
+                log::info!("Supervised component {} finished", name);
+                update(&|health| health.state = ComponentState::Stopped);
+                return;
+            }
+            Ok(Err(e)) => e.to_string(),
+            Err(e) if e.is_panic() => format!("panicked: {}", panic_message(e.into_panic())),
+            Err(_) => "task was aborted".to_string(),
+        };
+
+        let stable = started.elapsed() >= config.stable_after;
+        let Some(health) = update(&|health| {
+            if stable {
+                health.consecutive_failures = 0;
+            }
+            health.consecutive_failures += 1;
+            health.last_error = Some(error.clone());
+            health.last_failure_at = Some(SystemTime::now());
+            let given_up = config.max_restarts.is_some_and(|max| health.consecutive_failures > max);
+            health.state = if given_up { ComponentState::Failed } else { ComponentState::Restarting };
+        }) else {
+            return;
+        };
+
+        let failures = health.consecutive_failures;
+        if health.state == ComponentState::Failed {
+            log::error!("Supervised component {} failed {} times in a row, giving up: {}", name, failures, error);
+            if let Some(escalate) = &escalation {
+                escalate(&health);
+            }
+            return;
+        }
+
+        let delay = config.backoff(failures);
+        log::warn!("Supervised component {} failed ({}), restarting in {:?}", name, error, delay);
+        if failures == config.escalate_after && let Some(escalate) = &escalation {
+            escalate(&health);
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = token.cancelled() => {
+                update(&|health| health.state = ComponentState::Stopped);
+                return;
+            }
+        }
+        update(&|health| {
+            health.restarts += 1;
+            health.state = ComponentState::Running;
+        });
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::KizunaError;
+    use crate::shutdown::ShutdownReason;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn config() -> SupervisorConfig {
+        SupervisorConfig {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            stable_after: Duration::from_secs(60),
+            escalate_after: 2,
+            max_restarts: Some(3),
+        }
+    }
+
+    async fn wait_for(supervisor: &Supervisor, name: &str, state: ComponentState) -> ComponentHealth {
+        for _ in 0..500 {
+            if let Some(health) = supervisor.component(name) {
+                if health.state == state {
+                    return health;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        panic!("{} never reached {:?}: {:?}", name, state, supervisor.component(name));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_limit() {
+        let config = config();
+        assert_eq!(config.backoff(1), Duration::from_millis(1));
+        assert_eq!(config.backoff(2), Duration::from_millis(2));
+        assert_eq!(config.backoff(3), Duration::from_millis(4));
+        assert_eq!(config.backoff(40), Duration::from_millis(4));
+    }
+
+    #[tokio::test]
+    async fn test_crashing_component_restarted_then_escalated() {
+        let shutdown = Arc::new(ShutdownCoordinator::default());
+        let escalations = Arc::new(StdMutex::new(Vec::new()));
+        let recorded = Arc::clone(&escalations);
+        let supervisor = Supervisor::new(Arc::clone(&shutdown), config()).with_escalation(Arc::new(
+            move |health: &ComponentHealth| recorded.lock().unwrap().push(health.state),
+        ));
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&attempts);
+        supervisor.supervise("mdns responder", move |_| {
+            let attempt = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt % 2 == 0 {
+                    panic!("socket closed");
+                }
+                Err(KizunaError::other("responder exited"))
+            }
+        });
+
+        let health = wait_for(&supervisor, "mdns responder", ComponentState::Failed).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+        assert_eq!(health.restarts, 3);
+        assert_eq!(health.last_error.as_deref(), Some("responder exited"));
+        assert!(!supervisor.health().is_ready());
+        assert_eq!(
+            *escalations.lock().unwrap(),
+            vec![ComponentState::Restarting, ComponentState::Failed]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_is_not_a_failure() {
+        let shutdown = Arc::new(ShutdownCoordinator::default());
+        let supervisor = Supervisor::new(Arc::clone(&shutdown), config());
+        supervisor.supervise("clipboard monitor", |token| async move {
+            token.cancelled().await;
+            Ok(())
+        });
+        assert!(supervisor.health().is_ready());
+
+        let report = shutdown.shutdown(ShutdownReason::Requested).await;
+        assert!(report.is_clean());
+        let health = supervisor.component("clipboard monitor").unwrap();
+        assert_eq!(health.state, ComponentState::Stopped);
+        assert_eq!(health.consecutive_failures, 0);
+    }
+}
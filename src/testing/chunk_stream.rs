@@ -0,0 +1,80 @@
+//! In-memory chunk stream for file transfer tests
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::file_transfer::{ChunkStream, FileTransferError, Result};
+
+/// One end of an in-memory duplex byte stream
+pub struct MemoryChunkStream {
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    pending: Vec<u8>,
+    bytes_sent: u64,
+}
+
+impl MemoryChunkStream {
+    /// Create a connected pair of chunk streams
+    pub fn pair() -> (Self, Self) {
+        let (a_tx, b_rx) = mpsc::unbounded_channel();
+        let (b_tx, a_rx) = mpsc::unbounded_channel();
+
+        (
+            Self { tx: a_tx, rx: a_rx, pending: Vec::new(), bytes_sent: 0 },
+            Self { tx: b_tx, rx: b_rx, pending: Vec::new(), bytes_sent: 0 },
+        )
+    }
+
+    /// Total bytes written to this end
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+}
+
+#[async_trait]
+impl ChunkStream for MemoryChunkStream {
+    async fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.tx.send(data.to_vec()).map_err(|_| FileTransferError::NetworkError {
+            reason: "Remote end of memory stream closed".to_string(),
+        })?;
+        self.bytes_sent += data.len() as u64;
+        Ok(())
+    }
+
+    async fn receive(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        if self.pending.is_empty() {
+            match self.rx.recv().await {
+                Some(data) => self.pending = data,
+                None => return Ok(0),
+            }
+        }
+
+        let n = buffer.len().min(self.pending.len());
+        buffer[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let (mut a, mut b) = MemoryChunkStream::pair();
+        a.send(b"chunk-data").await.unwrap();
+
+        let mut buf = vec![0u8; 64];
+        let n = b.receive(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"chunk-data");
+        assert_eq!(a.bytes_sent(), 10);
+
+        drop(a);
+        assert_eq!(b.receive(&mut buf).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,72 @@
+//! In-memory clipboard
+
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::clipboard::{Clipboard, ClipboardContent, ClipboardResult};
+
+/// Clipboard backed by process memory instead of the system clipboard
+#[derive(Default)]
+pub struct MemoryClipboard {
+    content: Mutex<Option<ClipboardContent>>,
+    history: Mutex<Vec<ClipboardContent>>,
+    monitoring: AtomicBool,
+}
+
+impl MemoryClipboard {
+    /// Create an empty clipboard
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every value ever set on this clipboard, oldest first
+    pub fn history(&self) -> Vec<ClipboardContent> {
+        self.history.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Clipboard for MemoryClipboard {
+    async fn get_content(&self) -> ClipboardResult<Option<ClipboardContent>> {
+        Ok(self.content.lock().unwrap().clone())
+    }
+
+    async fn set_content(&self, content: ClipboardContent) -> ClipboardResult<()> {
+        self.history.lock().unwrap().push(content.clone());
+        *self.content.lock().unwrap() = Some(content);
+        Ok(())
+    }
+
+    async fn start_monitoring(&self) -> ClipboardResult<()> {
+        self.monitoring.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn stop_monitoring(&self) -> ClipboardResult<()> {
+        self.monitoring.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_monitoring(&self) -> bool {
+        self.monitoring.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::TextContent;
+
+    #[tokio::test]
+    async fn test_set_and_get() {
+        let clipboard = MemoryClipboard::new();
+        assert!(clipboard.get_content().await.unwrap().is_none());
+
+        let content = ClipboardContent::Text(TextContent::new("hello".to_string()));
+        clipboard.set_content(content.clone()).await.unwrap();
+
+        assert_eq!(clipboard.get_content().await.unwrap(), Some(content));
+        assert_eq!(clipboard.history().len(), 1);
+    }
+}
@@ -0,0 +1,110 @@
+//! In-memory discovery strategy
+
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::discovery::{Discovery, DiscoveryError, ServiceRecord};
+use super::network::VirtualNetwork;
+
+/// Discovery strategy name reported by [`MemoryDiscovery`]
+pub const MEMORY_STRATEGY: &str = "memory";
+
+/// Discovery strategy that announces and browses on a [`VirtualNetwork`]
+pub struct MemoryDiscovery {
+    network: VirtualNetwork,
+    record: ServiceRecord,
+    announcing: AtomicBool,
+}
+
+impl MemoryDiscovery {
+    /// Create a discovery strategy announcing `peer_id` at `addr`
+    pub fn new(network: VirtualNetwork, peer_id: impl Into<String>, name: impl Into<String>, addr: SocketAddr) -> Self {
+        let mut record = ServiceRecord::new(peer_id.into(), name.into(), addr.port());
+        record.add_address(addr);
+        record.set_discovery_method(MEMORY_STRATEGY.to_string());
+
+        Self {
+            network,
+            record,
+            announcing: AtomicBool::new(false),
+        }
+    }
+
+    /// Add a capability to the announced record
+    pub fn with_capability(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.record.add_capability(key.into(), value.into());
+        self
+    }
+
+    /// The record this strategy announces
+    pub fn record(&self) -> &ServiceRecord {
+        &self.record
+    }
+
+    /// Whether this strategy is currently announcing
+    pub fn is_announcing(&self) -> bool {
+        self.announcing.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Discovery for MemoryDiscovery {
+    async fn discover(&self, _timeout: Duration) -> Result<Vec<ServiceRecord>, DiscoveryError> {
+        Ok(self
+            .network
+            .announcements()
+            .into_iter()
+            .filter(|record| record.peer_id != self.record.peer_id)
+            .collect())
+    }
+
+    async fn announce(&self) -> Result<(), DiscoveryError> {
+        let mut record = self.record.clone();
+        record.update_last_seen();
+        self.network.announce(record);
+        self.announcing.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn stop_announce(&self) -> Result<(), DiscoveryError> {
+        self.network.withdraw(&self.record.peer_id);
+        self.announcing.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn strategy_name(&self) -> &'static str {
+        MEMORY_STRATEGY
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn priority(&self) -> u8 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_announce_and_discover() {
+        let network = VirtualNetwork::new();
+        let a = MemoryDiscovery::new(network.clone(), "a", "Peer A", network.allocate_address(41000));
+        let b = MemoryDiscovery::new(network.clone(), "b", "Peer B", network.allocate_address(41000));
+
+        a.announce().await.unwrap();
+        b.announce().await.unwrap();
+
+        let seen = b.discover(Duration::from_millis(10)).await.unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].peer_id, "a");
+
+        a.stop_announce().await.unwrap();
+        assert!(b.discover(Duration::from_millis(10)).await.unwrap().is_empty());
+    }
+}
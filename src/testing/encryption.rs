@@ -0,0 +1,164 @@
+//! Deterministic in-memory encryption engine
+//!
+//! This engine is NOT secure. It derives a per-pair key from both peer IDs so
+//! that two engines in the same test agree on a key without a handshake, and
+//! appends an integrity tag so decrypting with the wrong session fails the same
+//! way the real engine does.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::security::encryption::{EncryptionEngine, SessionId};
+use crate::security::error::EncryptionError;
+use crate::security::{PeerId, SecurityResult};
+
+const TAG_LEN: usize = 8;
+
+struct MemorySession {
+    key: [u8; 32],
+    rotations: u32,
+}
+
+/// Encryption engine with deterministic keys for testing
+pub struct MemoryEncryptionEngine {
+    local: PeerId,
+    sessions: Mutex<HashMap<SessionId, MemorySession>>,
+}
+
+impl MemoryEncryptionEngine {
+    /// Create an engine for the local peer
+    pub fn new(local: PeerId) -> Self {
+        Self {
+            local,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Number of key rotations performed on a session
+    pub fn rotation_count(&self, session_id: &SessionId) -> Option<u32> {
+        self.sessions.lock().unwrap().get(session_id).map(|s| s.rotations)
+    }
+
+    /// Number of established sessions
+    pub fn session_count(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    fn key_for(&self, session_id: &SessionId) -> SecurityResult<[u8; 32]> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .map(|s| s.key)
+            .ok_or_else(|| EncryptionError::SessionNotFound(session_id.to_string()).into())
+    }
+}
+
+/// Derive an order-independent key for a pair of peers
+fn pair_key(a: &PeerId, b: &PeerId) -> [u8; 32] {
+    let (first, second) = if a.fingerprint() <= b.fingerprint() { (a, b) } else { (b, a) };
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = first.fingerprint()[i].rotate_left(3) ^ second.fingerprint()[i] ^ (i as u8);
+    }
+    key
+}
+
+fn keystream_xor(key: &[u8; 32], data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ key[i % key.len()] ^ (i as u8).wrapping_mul(31))
+        .collect()
+}
+
+fn tag(key: &[u8; 32], plaintext: &[u8]) -> [u8; TAG_LEN] {
+    // FNV-1a over key || plaintext
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in key.iter().chain(plaintext) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash.to_le_bytes()
+}
+
+#[async_trait]
+impl EncryptionEngine for MemoryEncryptionEngine {
+    async fn establish_session(&self, peer_id: &PeerId) -> SecurityResult<SessionId> {
+        let session_id = SessionId::new();
+        self.sessions.lock().unwrap().insert(
+            session_id.clone(),
+            MemorySession {
+                key: pair_key(&self.local, peer_id),
+                rotations: 0,
+            },
+        );
+        Ok(session_id)
+    }
+
+    async fn encrypt_message(&self, session_id: &SessionId, data: &[u8]) -> SecurityResult<Vec<u8>> {
+        let key = self.key_for(session_id)?;
+        let mut out = keystream_xor(&key, data);
+        out.extend_from_slice(&tag(&key, data));
+        Ok(out)
+    }
+
+    async fn decrypt_message(&self, session_id: &SessionId, data: &[u8]) -> SecurityResult<Vec<u8>> {
+        let key = self.key_for(session_id)?;
+        if data.len() < TAG_LEN {
+            return Err(EncryptionError::DecryptionFailed("Ciphertext too short".to_string()).into());
+        }
+
+        let (body, received_tag) = data.split_at(data.len() - TAG_LEN);
+        let plaintext = keystream_xor(&key, body);
+        if tag(&key, &plaintext) != received_tag {
+            return Err(EncryptionError::AuthenticationFailed.into());
+        }
+        Ok(plaintext)
+    }
+
+    async fn rotate_session_keys(&self, session_id: &SessionId) -> SecurityResult<()> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| EncryptionError::SessionNotFound(session_id.to_string()))?;
+        // Keys stay pair-derived so both sides keep agreeing; only the count changes
+        session.rotations += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pair_round_trip() {
+        let alice = PeerId::from_fingerprint([1u8; 32]);
+        let bob = PeerId::from_fingerprint([2u8; 32]);
+        let a = MemoryEncryptionEngine::new(alice.clone());
+        let b = MemoryEncryptionEngine::new(bob.clone());
+
+        let a_session = a.establish_session(&bob).await.unwrap();
+        let b_session = b.establish_session(&alice).await.unwrap();
+
+        let ciphertext = a.encrypt_message(&a_session, b"secret").await.unwrap();
+        assert_ne!(&ciphertext[..6], b"secret");
+        assert_eq!(b.decrypt_message(&b_session, &ciphertext).await.unwrap(), b"secret");
+    }
+
+    #[tokio::test]
+    async fn test_wrong_key_fails() {
+        let alice = PeerId::from_fingerprint([1u8; 32]);
+        let mallory = PeerId::from_fingerprint([9u8; 32]);
+        let a = MemoryEncryptionEngine::new(alice.clone());
+        let m = MemoryEncryptionEngine::new(mallory);
+
+        let a_session = a.establish_session(&PeerId::from_fingerprint([2u8; 32])).await.unwrap();
+        let m_session = m.establish_session(&alice).await.unwrap();
+
+        let ciphertext = a.encrypt_message(&a_session, b"secret").await.unwrap();
+        assert!(m.decrypt_message(&m_session, &ciphertext).await.is_err());
+        assert!(a.decrypt_message(&SessionId::new(), &ciphertext).await.is_err());
+    }
+}
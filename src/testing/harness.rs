@@ -0,0 +1,150 @@
+//! Virtual two-peer harness
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::discovery::Discovery;
+use crate::security::PeerId as SecurityPeerId;
+use crate::transport::{Connection, PeerAddress, Transport, TransportError};
+use super::{
+    MemoryChunkStream, MemoryConnection, MemoryDiscovery, MemoryEncryptionEngine, MemoryTransport,
+    VirtualNetwork,
+};
+#[cfg(feature = "clipboard")]
+use super::MemoryClipboard;
+
+/// Default port virtual peers listen on
+pub const VIRTUAL_PORT: u16 = 41000;
+
+/// A virtual peer with in-memory implementations of every core trait
+pub struct VirtualPeer {
+    pub peer_id: String,
+    pub security_id: SecurityPeerId,
+    pub addr: SocketAddr,
+    pub transport: Arc<MemoryTransport>,
+    pub discovery: Arc<MemoryDiscovery>,
+    #[cfg(feature = "clipboard")]
+    pub clipboard: Arc<MemoryClipboard>,
+    pub encryption: Arc<MemoryEncryptionEngine>,
+}
+
+impl VirtualPeer {
+    /// Create a peer on `network` and start listening
+    pub async fn spawn(network: &VirtualNetwork, name: &str) -> Result<Self, TransportError> {
+        let peer_id = name.to_string();
+        let addr = network.allocate_address(VIRTUAL_PORT);
+        let security_id = SecurityPeerId::from_fingerprint(fingerprint_for(name));
+
+        let transport = Arc::new(MemoryTransport::new(peer_id.clone(), network.clone()));
+        transport.listen(&addr).await?;
+
+        let discovery = Arc::new(MemoryDiscovery::new(network.clone(), peer_id.clone(), name, addr));
+        let _ = discovery.announce().await;
+
+        Ok(Self {
+            peer_id,
+            addr,
+            transport,
+            discovery,
+            #[cfg(feature = "clipboard")]
+            clipboard: Arc::new(MemoryClipboard::new()),
+            encryption: Arc::new(MemoryEncryptionEngine::new(security_id.clone())),
+            security_id,
+        })
+    }
+
+    /// Transport address other peers use to reach this one
+    pub fn peer_address(&self) -> PeerAddress {
+        PeerAddress::new(
+            self.peer_id.clone(),
+            vec![self.addr],
+            vec![super::transport::MEMORY_PROTOCOL.to_string()],
+            self.transport.capabilities(),
+        )
+    }
+}
+
+/// Deterministic fingerprint for a virtual peer name
+fn fingerprint_for(name: &str) -> [u8; 32] {
+    let mut fingerprint = [0u8; 32];
+    for (i, byte) in name.bytes().enumerate() {
+        fingerprint[i % 32] ^= byte.wrapping_add(i as u8);
+    }
+    fingerprint[31] ^= name.len() as u8;
+    fingerprint
+}
+
+/// Two virtual peers on a shared in-memory network
+pub struct TwoPeerHarness {
+    pub network: VirtualNetwork,
+    pub alice: VirtualPeer,
+    pub bob: VirtualPeer,
+}
+
+impl TwoPeerHarness {
+    /// Create the harness with peers named "alice" and "bob"
+    pub async fn new() -> Result<Self, TransportError> {
+        let network = VirtualNetwork::new();
+        let alice = VirtualPeer::spawn(&network, "alice").await?;
+        let bob = VirtualPeer::spawn(&network, "bob").await?;
+        Ok(Self { network, alice, bob })
+    }
+
+    /// Connect alice to bob, returning (alice's end, bob's end)
+    pub async fn connect(&self) -> Result<(Box<dyn Connection>, MemoryConnection), TransportError> {
+        let outbound = self.alice.transport.connect(&self.bob.peer_address()).await?;
+        let inbound = self.bob.transport.accept().await?;
+        Ok((outbound, inbound))
+    }
+
+    /// Create a connected pair of chunk streams (alice's end, bob's end)
+    pub fn chunk_streams(&self) -> (MemoryChunkStream, MemoryChunkStream) {
+        MemoryChunkStream::pair()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "clipboard")]
+    use crate::clipboard::{Clipboard, ClipboardContent, TextContent};
+    use crate::security::encryption::EncryptionEngine;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_peers_discover_each_other() {
+        let harness = TwoPeerHarness::new().await.unwrap();
+        let seen = harness.alice.discovery.discover(Duration::from_millis(10)).await.unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].peer_id, "bob");
+        assert_eq!(seen[0].primary_address(), Some(harness.bob.addr));
+    }
+
+    #[cfg(feature = "clipboard")]
+    #[tokio::test]
+    async fn test_encrypted_clipboard_flow() {
+        let harness = TwoPeerHarness::new().await.unwrap();
+        let (mut to_bob, mut from_alice) = harness.connect().await.unwrap();
+
+        let a_session = harness.alice.encryption.establish_session(&harness.bob.security_id).await.unwrap();
+        let b_session = harness.bob.encryption.establish_session(&harness.alice.security_id).await.unwrap();
+
+        let ciphertext = harness.alice.encryption.encrypt_message(&a_session, b"copied text").await.unwrap();
+        to_bob.write(&ciphertext).await.unwrap();
+
+        let mut buf = vec![0u8; 256];
+        let n = from_alice.read(&mut buf).await.unwrap();
+        let plaintext = harness.bob.encryption.decrypt_message(&b_session, &buf[..n]).await.unwrap();
+
+        let text = String::from_utf8(plaintext).unwrap();
+        harness.bob.clipboard
+            .set_content(ClipboardContent::Text(TextContent::new(text)))
+            .await
+            .unwrap();
+
+        match harness.bob.clipboard.get_content().await.unwrap() {
+            Some(ClipboardContent::Text(text)) => assert_eq!(text.text, "copied text"),
+            other => panic!("unexpected clipboard content: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! In-memory test doubles for Kizuna's core traits
+//!
+//! Provides deterministic, hardware-free implementations of [`Transport`],
+//! [`Discovery`], [`Clipboard`], [`ChunkStream`], [`EncryptionEngine`] and
+//! [`Security`],
+//! plus a [`TwoPeerHarness`] that wires two virtual peers onto a shared
+//! in-process network. Downstream applications can use these to exercise
+//! complete flows without sockets, multicast, or a system clipboard.
+//! The clipboard double needs the `clipboard` feature.
+//! [`Simulation`] extends this to N peers with controllable latency, loss,
+//! and partitions under a virtual clock.
+//!
+//! [`Transport`]: crate::transport::Transport
+//! [`Discovery`]: crate::discovery::Discovery
+//! [`Clipboard`]: crate::clipboard::Clipboard
+//! [`ChunkStream`]: crate::file_transfer::ChunkStream
+//! [`EncryptionEngine`]: crate::security::encryption::EncryptionEngine
+//! [`Security`]: crate::security::Security
+
+pub mod network;
+pub mod transport;
+pub mod discovery;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod chunk_stream;
+pub mod encryption;
+pub mod security;
+pub mod harness;
+pub mod simulation;
+
+pub use network::VirtualNetwork;
+pub use transport::{MemoryTransport, MemoryConnection};
+pub use discovery::MemoryDiscovery;
+#[cfg(feature = "clipboard")]
+pub use clipboard::MemoryClipboard;
+pub use chunk_stream::MemoryChunkStream;
+pub use encryption::MemoryEncryptionEngine;
+pub use security::MemorySecurity;
+pub use harness::{TwoPeerHarness, VirtualPeer};
+pub use simulation::{
+    Simulation, SimulationConfig, LinkConditions, SimMessage, SimEvent, DropReason,
+};
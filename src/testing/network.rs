@@ -0,0 +1,108 @@
+//! Shared in-process network used by the in-memory test doubles
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+use crate::discovery::ServiceRecord;
+use crate::transport::{PeerId, TransportError};
+use super::transport::MemoryConnection;
+
+/// Listener registration on the virtual network
+#[derive(Debug)]
+struct Listener {
+    peer_id: PeerId,
+    incoming: mpsc::UnboundedSender<MemoryConnection>,
+}
+
+#[derive(Debug, Default)]
+struct NetworkState {
+    listeners: HashMap<SocketAddr, Listener>,
+    announcements: HashMap<PeerId, ServiceRecord>,
+    next_host: u8,
+}
+
+/// In-process network shared by all virtual peers of a test
+///
+/// Cloning the network yields another handle to the same state.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualNetwork {
+    state: Arc<Mutex<NetworkState>>,
+}
+
+impl VirtualNetwork {
+    /// Create an empty virtual network
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate a unique loopback-style address for a virtual peer
+    pub fn allocate_address(&self, port: u16) -> SocketAddr {
+        let mut state = self.state.lock().unwrap();
+        state.next_host = state.next_host.wrapping_add(1);
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, state.next_host)), port)
+    }
+
+    /// Register a listener for incoming connections on `addr`
+    pub(crate) fn register_listener(
+        &self,
+        addr: SocketAddr,
+        peer_id: PeerId,
+        incoming: mpsc::UnboundedSender<MemoryConnection>,
+    ) -> Result<(), TransportError> {
+        let mut state = self.state.lock().unwrap();
+        if state.listeners.contains_key(&addr) {
+            return Err(TransportError::ConnectionExists);
+        }
+        state.listeners.insert(addr, Listener { peer_id, incoming });
+        Ok(())
+    }
+
+    /// Remove the listener bound to `addr`
+    pub(crate) fn unregister_listener(&self, addr: &SocketAddr) {
+        self.state.lock().unwrap().listeners.remove(addr);
+    }
+
+    /// Deliver a connection to the listener at `addr`, returning the listener's peer ID
+    pub(crate) fn deliver(
+        &self,
+        addr: &SocketAddr,
+        connection: MemoryConnection,
+    ) -> Result<PeerId, TransportError> {
+        let state = self.state.lock().unwrap();
+        let listener = state
+            .listeners
+            .get(addr)
+            .ok_or(TransportError::NetworkUnreachable { target: *addr })?;
+
+        listener.incoming.send(connection).map_err(|_| TransportError::ConnectionFailed {
+            reason: format!("Listener at {} is no longer accepting", addr),
+        })?;
+
+        Ok(listener.peer_id.clone())
+    }
+
+    /// Peer ID of the listener at `addr`, if any
+    pub(crate) fn listener_peer(&self, addr: &SocketAddr) -> Option<PeerId> {
+        self.state.lock().unwrap().listeners.get(addr).map(|l| l.peer_id.clone())
+    }
+
+    /// Publish a service record visible to every peer's discovery
+    pub(crate) fn announce(&self, record: ServiceRecord) {
+        self.state.lock().unwrap().announcements.insert(record.peer_id.clone(), record);
+    }
+
+    /// Withdraw a previously published service record
+    pub(crate) fn withdraw(&self, peer_id: &str) {
+        self.state.lock().unwrap().announcements.remove(peer_id);
+    }
+
+    /// Snapshot of all announced service records
+    pub fn announcements(&self) -> Vec<ServiceRecord> {
+        let state = self.state.lock().unwrap();
+        let mut records: Vec<_> = state.announcements.values().cloned().collect();
+        records.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        records
+    }
+}
@@ -0,0 +1,58 @@
+//! In-memory security system
+//!
+//! Trusts every peer and passes data through unencrypted. It needs no OS
+//! keyring, so code that takes a [`Security`] can be exercised anywhere.
+
+use async_trait::async_trait;
+
+use crate::security::encryption::SessionId;
+use crate::security::{DeviceIdentity, EncryptedStorage, PeerId, Security, SecurityResult};
+
+/// Security system for testing that trusts everyone
+pub struct MemorySecurity {
+    identity: DeviceIdentity,
+}
+
+impl MemorySecurity {
+    /// Create a security system with a fresh identity
+    pub fn new() -> SecurityResult<Self> {
+        Ok(Self {
+            identity: DeviceIdentity::generate()?,
+        })
+    }
+}
+
+#[async_trait]
+impl Security for MemorySecurity {
+    async fn get_device_identity(&self) -> SecurityResult<DeviceIdentity> {
+        Ok(self.identity.clone())
+    }
+
+    async fn get_peer_id(&self) -> SecurityResult<PeerId> {
+        Ok(self.identity.derive_peer_id())
+    }
+
+    async fn establish_session(&self, _peer_id: &PeerId) -> SecurityResult<SessionId> {
+        Ok(SessionId::new())
+    }
+
+    async fn encrypt_message(&self, _session_id: &SessionId, data: &[u8]) -> SecurityResult<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    async fn decrypt_message(&self, _session_id: &SessionId, data: &[u8]) -> SecurityResult<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    async fn is_trusted(&self, _peer_id: &PeerId) -> SecurityResult<bool> {
+        Ok(true)
+    }
+
+    async fn add_trusted_peer(&self, _peer_id: PeerId, _nickname: String) -> SecurityResult<()> {
+        Ok(())
+    }
+
+    fn storage(&self) -> Option<EncryptedStorage> {
+        None
+    }
+}
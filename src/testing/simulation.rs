@@ -0,0 +1,506 @@
+//! Deterministic multi-peer network simulation
+//!
+//! Runs any number of virtual peers in one process over a discrete-event
+//! network with per-link latency, jitter and loss, plus partition and churn
+//! events. Time is virtual and randomness comes from a seeded generator, so a
+//! failing scenario replays identically from its seed.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::discovery::ServiceRecord;
+use super::network::VirtualNetwork;
+
+/// Network conditions applied to messages on a link
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkConditions {
+    /// Base one-way latency
+    pub latency: Duration,
+    /// Maximum additional random delay
+    pub jitter: Duration,
+    /// Probability in [0, 1] that a message is dropped
+    pub loss: f64,
+}
+
+impl LinkConditions {
+    /// A perfect link with no delay or loss
+    pub fn perfect() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            loss: 0.0,
+        }
+    }
+
+    /// Typical LAN conditions
+    pub fn lan() -> Self {
+        Self {
+            latency: Duration::from_millis(1),
+            jitter: Duration::from_micros(500),
+            loss: 0.0,
+        }
+    }
+
+    /// Lossy wide-area conditions
+    pub fn lossy_wan() -> Self {
+        Self {
+            latency: Duration::from_millis(80),
+            jitter: Duration::from_millis(30),
+            loss: 0.05,
+        }
+    }
+}
+
+impl Default for LinkConditions {
+    fn default() -> Self {
+        Self::lan()
+    }
+}
+
+/// Simulation configuration
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// Seed for all random decisions
+    pub seed: u64,
+    /// Conditions for links without an explicit override
+    pub default_link: LinkConditions,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0x6b697a756e61,
+            default_link: LinkConditions::default(),
+        }
+    }
+}
+
+/// Message delivered to a simulated peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimMessage {
+    pub from: String,
+    pub to: String,
+    pub payload: Vec<u8>,
+    pub sent_at: Duration,
+    pub delivered_at: Duration,
+}
+
+/// Recorded simulation event, useful when diagnosing a failing seed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimEvent {
+    Sent { at: Duration, from: String, to: String, bytes: usize },
+    Delivered { at: Duration, from: String, to: String },
+    Dropped { at: Duration, from: String, to: String, reason: DropReason },
+    Partitioned { at: Duration, groups: Vec<BTreeSet<String>> },
+    Healed { at: Duration },
+    PeerOnline { at: Duration, peer: String },
+    PeerOffline { at: Duration, peer: String },
+}
+
+/// Why a message was not delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    Loss,
+    Partition,
+    Offline,
+    UnknownPeer,
+}
+
+/// Small deterministic PRNG (SplitMix64)
+#[derive(Debug, Clone)]
+struct SimRng(u64);
+
+impl SimRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform duration in [0, max]
+    fn next_duration(&mut self, max: Duration) -> Duration {
+        if max.is_zero() {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos(self.next_u64() % (max.as_nanos() as u64 + 1))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Scheduled {
+    deliver_at: Duration,
+    seq: u64,
+    from: String,
+    to: String,
+    sent_at: Duration,
+    payload: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct SimPeer {
+    online: bool,
+    inbox: VecDeque<SimMessage>,
+    record: Option<ServiceRecord>,
+}
+
+/// Discrete-event simulation of a multi-peer network
+pub struct Simulation {
+    config: SimulationConfig,
+    now: Duration,
+    rng: SimRng,
+    seq: u64,
+    peers: BTreeMap<String, SimPeer>,
+    links: HashMap<(String, String), LinkConditions>,
+    partition: Option<Vec<BTreeSet<String>>>,
+    queue: BinaryHeap<Reverse<Scheduled>>,
+    network: VirtualNetwork,
+    trace: Vec<SimEvent>,
+}
+
+impl Simulation {
+    /// Create a simulation with the given configuration
+    pub fn new(config: SimulationConfig) -> Self {
+        let rng = SimRng(config.seed);
+        Self {
+            config,
+            now: Duration::ZERO,
+            rng,
+            seq: 0,
+            peers: BTreeMap::new(),
+            links: HashMap::new(),
+            partition: None,
+            queue: BinaryHeap::new(),
+            network: VirtualNetwork::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    /// Create a simulation with default settings and the given seed
+    pub fn with_seed(seed: u64) -> Self {
+        Self::new(SimulationConfig {
+            seed,
+            ..Default::default()
+        })
+    }
+
+    /// Current virtual time
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// Underlying virtual network shared with the in-memory transports
+    pub fn network(&self) -> &VirtualNetwork {
+        &self.network
+    }
+
+    /// Add an online peer and announce it for discovery
+    pub fn add_peer(&mut self, name: impl Into<String>) {
+        let name = name.into();
+        let addr = self.network.allocate_address(super::harness::VIRTUAL_PORT);
+        let mut record = ServiceRecord::new(name.clone(), name.clone(), addr.port());
+        record.add_address(addr);
+        record.set_discovery_method("simulation".to_string());
+
+        self.network.announce(record.clone());
+        self.peers.insert(
+            name,
+            SimPeer {
+                online: true,
+                inbox: VecDeque::new(),
+                record: Some(record),
+            },
+        );
+    }
+
+    /// Names of all peers, in sorted order
+    pub fn peer_names(&self) -> Vec<String> {
+        self.peers.keys().cloned().collect()
+    }
+
+    /// Override conditions for the link between two peers (both directions)
+    pub fn set_link(&mut self, a: &str, b: &str, conditions: LinkConditions) {
+        self.links.insert((a.to_string(), b.to_string()), conditions);
+        self.links.insert((b.to_string(), a.to_string()), conditions);
+    }
+
+    /// Split the network into isolated groups; peers not listed form their own group
+    pub fn partition(&mut self, groups: &[&[&str]]) {
+        let groups: Vec<BTreeSet<String>> = groups
+            .iter()
+            .map(|group| group.iter().map(|p| p.to_string()).collect())
+            .collect();
+
+        self.trace.push(SimEvent::Partitioned {
+            at: self.now,
+            groups: groups.clone(),
+        });
+        self.partition = Some(groups);
+    }
+
+    /// Remove any partition
+    pub fn heal(&mut self) {
+        self.partition = None;
+        self.trace.push(SimEvent::Healed { at: self.now });
+    }
+
+    /// Bring a peer online or take it offline (discovery churn)
+    pub fn set_online(&mut self, name: &str, online: bool) {
+        let Some(peer) = self.peers.get_mut(name) else {
+            return;
+        };
+        if peer.online == online {
+            return;
+        }
+
+        peer.online = online;
+        if online {
+            if let Some(record) = peer.record.clone() {
+                self.network.announce(record);
+            }
+            self.trace.push(SimEvent::PeerOnline { at: self.now, peer: name.to_string() });
+        } else {
+            self.network.withdraw(name);
+            self.trace.push(SimEvent::PeerOffline { at: self.now, peer: name.to_string() });
+        }
+    }
+
+    /// Whether two peers can currently exchange messages
+    pub fn can_reach(&self, from: &str, to: &str) -> bool {
+        let online = |name: &str| self.peers.get(name).map(|p| p.online).unwrap_or(false);
+        if !online(from) || !online(to) {
+            return false;
+        }
+
+        match &self.partition {
+            None => true,
+            Some(groups) => {
+                let group_of = |name: &str| groups.iter().position(|g| g.contains(name));
+                group_of(from) == group_of(to)
+            }
+        }
+    }
+
+    /// Peers discoverable from `name` given current partitions and churn
+    pub fn visible_peers(&self, name: &str) -> Vec<ServiceRecord> {
+        self.network
+            .announcements()
+            .into_iter()
+            .filter(|record| record.peer_id != name && self.can_reach(name, &record.peer_id))
+            .collect()
+    }
+
+    /// Send a message; delivery happens when virtual time advances
+    pub fn send(&mut self, from: &str, to: &str, payload: impl Into<Vec<u8>>) {
+        let payload = payload.into();
+        self.trace.push(SimEvent::Sent {
+            at: self.now,
+            from: from.to_string(),
+            to: to.to_string(),
+            bytes: payload.len(),
+        });
+
+        if !self.peers.contains_key(to) {
+            self.drop_message(from, to, DropReason::UnknownPeer);
+            return;
+        }
+        if !self.can_reach(from, to) {
+            let reason = if self.peers.get(from).map(|p| p.online).unwrap_or(false)
+                && self.peers.get(to).map(|p| p.online).unwrap_or(false)
+            {
+                DropReason::Partition
+            } else {
+                DropReason::Offline
+            };
+            self.drop_message(from, to, reason);
+            return;
+        }
+
+        let link = self
+            .links
+            .get(&(from.to_string(), to.to_string()))
+            .copied()
+            .unwrap_or(self.config.default_link);
+
+        if link.loss > 0.0 && self.rng.next_f64() < link.loss {
+            self.drop_message(from, to, DropReason::Loss);
+            return;
+        }
+
+        let delay = link.latency + self.rng.next_duration(link.jitter);
+        self.seq += 1;
+        self.queue.push(Reverse(Scheduled {
+            deliver_at: self.now + delay,
+            seq: self.seq,
+            from: from.to_string(),
+            to: to.to_string(),
+            sent_at: self.now,
+            payload,
+        }));
+    }
+
+    fn drop_message(&mut self, from: &str, to: &str, reason: DropReason) {
+        self.trace.push(SimEvent::Dropped {
+            at: self.now,
+            from: from.to_string(),
+            to: to.to_string(),
+            reason,
+        });
+    }
+
+    /// Advance virtual time, delivering every message due by then
+    pub fn advance(&mut self, by: Duration) {
+        let target = self.now + by;
+
+        while let Some(Reverse(next)) = self.queue.peek() {
+            if next.deliver_at > target {
+                break;
+            }
+            let Reverse(message) = self.queue.pop().unwrap();
+            self.now = message.deliver_at;
+            self.deliver(message);
+        }
+
+        self.now = target;
+    }
+
+    /// Deliver all in-flight messages, advancing time as needed
+    pub fn run_until_idle(&mut self) {
+        while let Some(Reverse(message)) = self.queue.pop() {
+            self.now = self.now.max(message.deliver_at);
+            self.deliver(message);
+        }
+    }
+
+    fn deliver(&mut self, message: Scheduled) {
+        // Partitions and churn that happen while a message is in flight also drop it
+        if !self.can_reach(&message.from, &message.to) {
+            let reason = if self.peers.get(&message.to).map(|p| p.online).unwrap_or(false) {
+                DropReason::Partition
+            } else {
+                DropReason::Offline
+            };
+            self.drop_message(&message.from, &message.to, reason);
+            return;
+        }
+
+        self.trace.push(SimEvent::Delivered {
+            at: self.now,
+            from: message.from.clone(),
+            to: message.to.clone(),
+        });
+
+        if let Some(peer) = self.peers.get_mut(&message.to) {
+            peer.inbox.push_back(SimMessage {
+                from: message.from,
+                to: message.to,
+                payload: message.payload,
+                sent_at: message.sent_at,
+                delivered_at: self.now,
+            });
+        }
+    }
+
+    /// Take all delivered messages for a peer
+    pub fn drain_inbox(&mut self, name: &str) -> Vec<SimMessage> {
+        self.peers
+            .get_mut(name)
+            .map(|peer| peer.inbox.drain(..).collect())
+            .unwrap_or_default()
+    }
+
+    /// Number of messages still in flight
+    pub fn in_flight(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Full event trace
+    pub fn trace(&self) -> &[SimEvent] {
+        &self.trace
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn three_peers(seed: u64) -> Simulation {
+        let mut sim = Simulation::with_seed(seed);
+        for name in ["a", "b", "c"] {
+            sim.add_peer(name);
+        }
+        sim
+    }
+
+    #[test]
+    fn test_latency_orders_delivery() {
+        let mut sim = three_peers(1);
+        sim.set_link("a", "b", LinkConditions { latency: Duration::from_millis(50), ..LinkConditions::perfect() });
+        sim.set_link("a", "c", LinkConditions { latency: Duration::from_millis(10), ..LinkConditions::perfect() });
+
+        sim.send("a", "b", b"slow".to_vec());
+        sim.send("a", "c", b"fast".to_vec());
+
+        sim.advance(Duration::from_millis(20));
+        assert_eq!(sim.drain_inbox("c").len(), 1);
+        assert!(sim.drain_inbox("b").is_empty());
+
+        sim.advance(Duration::from_millis(40));
+        let delivered = sim.drain_inbox("b");
+        assert_eq!(delivered[0].delivered_at, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_partition_drops_in_flight_messages() {
+        let mut sim = three_peers(2);
+        sim.set_link("a", "b", LinkConditions { latency: Duration::from_millis(30), ..LinkConditions::perfect() });
+
+        sim.send("a", "b", b"in-flight".to_vec());
+        sim.partition(&[&["a"], &["b", "c"]]);
+        sim.run_until_idle();
+        assert!(sim.drain_inbox("b").is_empty());
+        assert!(sim.visible_peers("a").is_empty());
+
+        sim.heal();
+        sim.send("a", "b", b"after-heal".to_vec());
+        sim.run_until_idle();
+        assert_eq!(sim.drain_inbox("b")[0].payload, b"after-heal");
+    }
+
+    #[test]
+    fn test_churn_updates_discovery() {
+        let mut sim = three_peers(3);
+        assert_eq!(sim.visible_peers("a").len(), 2);
+
+        sim.set_online("b", false);
+        let visible: Vec<_> = sim.visible_peers("a").into_iter().map(|r| r.peer_id).collect();
+        assert_eq!(visible, vec!["c".to_string()]);
+
+        sim.set_online("b", true);
+        assert_eq!(sim.visible_peers("a").len(), 2);
+    }
+
+    #[test]
+    fn test_same_seed_is_deterministic() {
+        let run = |seed| {
+            let mut sim = three_peers(seed);
+            sim.set_link("a", "b", LinkConditions::lossy_wan());
+            for i in 0..200u32 {
+                sim.send("a", "b", i.to_le_bytes().to_vec());
+            }
+            sim.run_until_idle();
+            sim.drain_inbox("b").into_iter().map(|m| m.delivered_at).collect::<Vec<_>>()
+        };
+
+        let first = run(42);
+        assert_eq!(first, run(42));
+        assert!(first.len() < 200, "lossy link should drop some messages");
+    }
+}
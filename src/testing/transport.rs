@@ -0,0 +1,391 @@
+//! In-memory transport and connection
+
+use async_trait::async_trait;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::transport::{
+    BackpressureGauge, Connection, ConnectionInfo, DatagramChannel, PeerAddress, PeerId,
+    Transport, TransportCapabilities, TransportError, Watermarks,
+};
+use super::network::VirtualNetwork;
+
+/// Protocol name reported by in-memory connections
+pub const MEMORY_PROTOCOL: &str = "memory";
+
+/// Largest datagram accepted by in-memory connections, matching a typical QUIC path
+pub const MEMORY_DATAGRAM_SIZE: usize = 1200;
+
+/// Datagram side of an in-memory connection
+///
+/// Datagrams are never lost in memory; tests that need loss should use [`Simulation`].
+///
+/// [`Simulation`]: crate::testing::Simulation
+#[derive(Debug)]
+pub struct MemoryDatagrams {
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    rx: Mutex<mpsc::UnboundedReceiver<Vec<u8>>>,
+}
+
+#[async_trait]
+impl DatagramChannel for MemoryDatagrams {
+    fn max_datagram_size(&self) -> Option<usize> {
+        Some(MEMORY_DATAGRAM_SIZE)
+    }
+
+    async fn send_datagram(&self, data: &[u8]) -> Result<(), TransportError> {
+        if data.len() > MEMORY_DATAGRAM_SIZE {
+            return Err(TransportError::ResourceLimitExceeded {
+                resource: format!("datagram size ({} > {} bytes)", data.len(), MEMORY_DATAGRAM_SIZE),
+            });
+        }
+        self.tx.send(data.to_vec()).map_err(|_| TransportError::ConnectionFailed {
+            reason: "Remote end closed".to_string(),
+        })
+    }
+
+    async fn recv_datagram(&self) -> Result<Vec<u8>, TransportError> {
+        self.rx.lock().await.recv().await.ok_or(TransportError::ConnectionFailed {
+            reason: "Remote end closed".to_string(),
+        })
+    }
+}
+
+/// One end of an in-memory duplex connection
+///
+/// Bytes written but not yet read by the other end count as buffered, so
+/// `ready()` applies real backpressure when the reader falls behind.
+#[derive(Debug)]
+pub struct MemoryConnection {
+    info: ConnectionInfo,
+    tx: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    pending: Vec<u8>,
+    /// Data this end has written that the peer has not read
+    outbound: BackpressureGauge,
+    /// Data the peer has written that this end has not read
+    inbound: BackpressureGauge,
+    datagrams: Arc<MemoryDatagrams>,
+}
+
+impl MemoryConnection {
+    /// Create a connected pair of in-memory connections
+    ///
+    /// The first connection belongs to `local_peer` and reports `remote_peer` as its
+    /// peer, and vice versa.
+    pub fn pair(
+        local_peer: PeerId,
+        local_addr: SocketAddr,
+        remote_peer: PeerId,
+        remote_addr: SocketAddr,
+    ) -> (Self, Self) {
+        Self::pair_with_watermarks(local_peer, local_addr, remote_peer, remote_addr, Watermarks::default())
+    }
+
+    /// Create a connected pair with custom write buffer thresholds
+    pub fn pair_with_watermarks(
+        local_peer: PeerId,
+        local_addr: SocketAddr,
+        remote_peer: PeerId,
+        remote_addr: SocketAddr,
+        watermarks: Watermarks,
+    ) -> (Self, Self) {
+        let (a_tx, b_rx) = mpsc::unbounded_channel();
+        let (b_tx, a_rx) = mpsc::unbounded_channel();
+        let a_to_b = BackpressureGauge::new(watermarks);
+        let b_to_a = BackpressureGauge::new(watermarks);
+        let (a_dgram_tx, b_dgram_rx) = mpsc::unbounded_channel();
+        let (b_dgram_tx, a_dgram_rx) = mpsc::unbounded_channel();
+
+        let a = Self {
+            info: ConnectionInfo::new(remote_peer, local_addr, remote_addr, MEMORY_PROTOCOL.to_string()),
+            tx: Some(a_tx),
+            rx: a_rx,
+            pending: Vec::new(),
+            outbound: a_to_b.clone(),
+            inbound: b_to_a.clone(),
+            datagrams: Arc::new(MemoryDatagrams {
+                tx: a_dgram_tx,
+                rx: Mutex::new(a_dgram_rx),
+            }),
+        };
+        let b = Self {
+            info: ConnectionInfo::new(local_peer, remote_addr, local_addr, MEMORY_PROTOCOL.to_string()),
+            tx: Some(b_tx),
+            rx: b_rx,
+            pending: Vec::new(),
+            outbound: b_to_a,
+            inbound: a_to_b,
+            datagrams: Arc::new(MemoryDatagrams {
+                tx: b_dgram_tx,
+                rx: Mutex::new(b_dgram_rx),
+            }),
+        };
+
+        (a, b)
+    }
+}
+
+#[async_trait]
+impl Connection for MemoryConnection {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, TransportError> {
+        if self.pending.is_empty() {
+            match self.rx.recv().await {
+                Some(data) => self.pending = data,
+                None => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        self.inbound.release(n);
+        self.info.add_bytes_received(n as u64);
+        Ok(n)
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, TransportError> {
+        let tx = self.tx.as_ref().ok_or(TransportError::ConnectionNotFound)?;
+        tx.send(buf.to_vec()).map_err(|_| TransportError::ConnectionFailed {
+            reason: "Remote end closed".to_string(),
+        })?;
+        self.outbound.add(buf.len());
+        self.info.add_bytes_sent(buf.len() as u64);
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<(), TransportError> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), TransportError> {
+        self.tx = None;
+        self.rx.close();
+        Ok(())
+    }
+
+    fn info(&self) -> ConnectionInfo {
+        self.info.clone()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.tx.as_ref().map(|tx| !tx.is_closed()).unwrap_or(false)
+    }
+
+    fn buffered_bytes(&self) -> usize {
+        self.outbound.buffered()
+    }
+
+    fn watermarks(&self) -> Watermarks {
+        self.outbound.watermarks()
+    }
+
+    async fn ready(&mut self) -> Result<(), TransportError> {
+        if !self.is_connected() {
+            return Err(TransportError::ConnectionFailed {
+                reason: "Remote end closed".to_string(),
+            });
+        }
+        self.outbound.ready().await;
+        Ok(())
+    }
+
+    fn datagrams(&self) -> Option<Arc<dyn DatagramChannel>> {
+        Some(Arc::clone(&self.datagrams) as Arc<dyn DatagramChannel>)
+    }
+}
+
+/// Transport that connects virtual peers over a [`VirtualNetwork`]
+#[derive(Debug)]
+pub struct MemoryTransport {
+    peer_id: PeerId,
+    network: VirtualNetwork,
+    bound_addr: StdMutex<Option<SocketAddr>>,
+    incoming_tx: mpsc::UnboundedSender<MemoryConnection>,
+    incoming_rx: Mutex<mpsc::UnboundedReceiver<MemoryConnection>>,
+}
+
+impl MemoryTransport {
+    /// Create a transport for `peer_id` on `network`
+    pub fn new(peer_id: impl Into<PeerId>, network: VirtualNetwork) -> Self {
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        Self {
+            peer_id: peer_id.into(),
+            network,
+            bound_addr: StdMutex::new(None),
+            incoming_tx,
+            incoming_rx: Mutex::new(incoming_rx),
+        }
+    }
+
+    /// Local peer ID
+    pub fn peer_id(&self) -> &PeerId {
+        &self.peer_id
+    }
+
+    /// Address this transport is listening on, if any
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        *self.bound_addr.lock().unwrap()
+    }
+
+    /// Wait for the next incoming connection
+    pub async fn accept(&self) -> Result<MemoryConnection, TransportError> {
+        self.incoming_rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(TransportError::ShutdownInProgress)
+    }
+
+    /// Stop listening and release the bound address
+    pub fn shutdown(&self) {
+        if let Some(addr) = self.bound_addr.lock().unwrap().take() {
+            self.network.unregister_listener(&addr);
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for MemoryTransport {
+    async fn connect(&self, addr: &PeerAddress) -> Result<Box<dyn Connection>, TransportError> {
+        let local_addr = self.local_addr().unwrap_or_else(|| self.network.allocate_address(0));
+
+        let target = addr
+            .addresses
+            .iter()
+            .copied()
+            .find(|candidate| self.network.listener_peer(candidate).as_deref() == Some(addr.peer_id.as_str()))
+            .ok_or(TransportError::InvalidPeerAddress)?;
+
+        let (local, remote) = MemoryConnection::pair(
+            self.peer_id.clone(),
+            local_addr,
+            addr.peer_id.clone(),
+            target,
+        );
+        self.network.deliver(&target, remote)?;
+
+        Ok(Box::new(local))
+    }
+
+    async fn listen(&self, bind_addr: &SocketAddr) -> Result<(), TransportError> {
+        self.network
+            .register_listener(*bind_addr, self.peer_id.clone(), self.incoming_tx.clone())?;
+        *self.bound_addr.lock().unwrap() = Some(*bind_addr);
+        Ok(())
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        MEMORY_PROTOCOL
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn priority(&self) -> u8 {
+        0
+    }
+
+    fn capabilities(&self) -> TransportCapabilities {
+        TransportCapabilities {
+            reliable: true,
+            ordered: true,
+            multiplexed: false,
+            resumable: false,
+            nat_traversal: true,
+            max_message_size: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connect_and_exchange() {
+        let network = VirtualNetwork::new();
+        let server = MemoryTransport::new("server", network.clone());
+        let client = MemoryTransport::new("client", network.clone());
+
+        let addr = network.allocate_address(41000);
+        server.listen(&addr).await.unwrap();
+
+        let peer = PeerAddress::new(
+            "server".to_string(),
+            vec![addr],
+            vec![MEMORY_PROTOCOL.to_string()],
+            server.capabilities(),
+        );
+        let mut outbound = client.connect(&peer).await.unwrap();
+        let mut inbound = server.accept().await.unwrap();
+
+        outbound.write(b"hello").await.unwrap();
+        let mut buf = [0u8; 3];
+        assert_eq!(inbound.read(&mut buf).await.unwrap(), 3);
+        assert_eq!(&buf, b"hel");
+        assert_eq!(inbound.read(&mut buf).await.unwrap(), 2);
+        assert_eq!(&buf[..2], b"lo");
+        assert_eq!(inbound.info().peer_id, "client");
+
+        outbound.close().await.unwrap();
+        assert_eq!(inbound.read(&mut buf).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_ready_tracks_unread_bytes() {
+        let (mut a, mut b) = MemoryConnection::pair_with_watermarks(
+            "a".to_string(),
+            "10.0.0.1:41000".parse().unwrap(),
+            "b".to_string(),
+            "10.0.0.2:41000".parse().unwrap(),
+            Watermarks::new(2, 8).unwrap(),
+        );
+
+        a.write(&[0u8; 8]).await.unwrap();
+        assert_eq!(a.buffered_bytes(), 8);
+
+        let mut buf = [0u8; 6];
+        b.read(&mut buf).await.unwrap();
+        assert_eq!(a.buffered_bytes(), 2);
+        tokio::time::timeout(std::time::Duration::from_secs(1), a.ready())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_datagrams_bypass_stream() {
+        let (a, b) = MemoryConnection::pair(
+            "a".to_string(),
+            "10.0.0.1:41000".parse().unwrap(),
+            "b".to_string(),
+            "10.0.0.2:41000".parse().unwrap(),
+        );
+        let a_datagrams = a.datagrams().unwrap();
+        let b_datagrams = b.datagrams().unwrap();
+
+        a_datagrams.send_datagram(b"ping").await.unwrap();
+        assert_eq!(b_datagrams.recv_datagram().await.unwrap(), b"ping");
+        assert_eq!(a.buffered_bytes(), 0);
+
+        let oversized = vec![0u8; MEMORY_DATAGRAM_SIZE + 1];
+        assert!(a_datagrams.send_datagram(&oversized).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_wrong_peer_rejected() {
+        let network = VirtualNetwork::new();
+        let server = MemoryTransport::new("server", network.clone());
+        let client = MemoryTransport::new("client", network.clone());
+
+        let addr = network.allocate_address(41000);
+        server.listen(&addr).await.unwrap();
+
+        let impostor = PeerAddress::new("other".to_string(), vec![addr], vec![], server.capabilities());
+        assert!(client.connect(&impostor).await.is_err());
+    }
+}
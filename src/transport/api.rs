@@ -1,16 +1,20 @@
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
 use async_trait::async_trait;
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::{OnceCell, RwLock, mpsc};
+use tokio::task::JoinHandle;
 use serde::{Deserialize, Serialize};
 
 use crate::transport::{
-    ConnectionManager, Connection, ConnectionInfo, TransportError, PeerAddress, 
-    TransportCapabilities, PeerId, IntegratedTransportSystem, IntegratedSystemConfig,
-    SystemState, SystemHealthReport, PerformanceMonitor, ErrorHandler
+    Connection, ConnectionInfo, DatagramChannel, TransportError, PeerAddress,
+    PeerId, IntegratedTransportSystem, IntegratedSystemConfig,
+    SystemState, SystemHealthReport, NatReport, NatTraversal,
+    NetworkConditions, resolve_stun_servers, ListenPorts, PortMapper, PortMapping, PortMappingConfig,
+    PortProtocol, select_port, ProxyConfig, WebSocketConfig
 };
+use crate::transport::handshake::{perform_handshake, NegotiatedProtocol, ProtocolHello};
 
 /// Configuration for the Kizuna Transport API
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +41,18 @@ pub struct KizunaTransportConfig {
     pub nat_traversal_config: Option<NatTraversalConfig>,
     /// Relay configuration for fallback connections
     pub relay_config: Option<RelayConfig>,
+    /// Hello sent on every outgoing connection; `None` skips the protocol handshake
+    #[serde(default)]
+    pub protocol_handshake: Option<ProtocolHello>,
+    /// Ports each transport listens on, with fallbacks when they are taken
+    #[serde(default)]
+    pub listen_ports: ListenPorts,
+    /// Ask the router to forward the listening port; `None` leaves it alone
+    #[serde(default)]
+    pub port_mapping: Option<PortMappingConfig>,
+    /// Proxy for relay and STUN servers; `None` uses the proxy environment variables
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,10 +105,36 @@ impl Default for KizunaTransportConfig {
                 enable_auto_fallback: true,
                 relay_timeout: Duration::from_secs(15),
             }),
+            protocol_handshake: None,
+            listen_ports: ListenPorts::default(),
+            port_mapping: None,
+            proxy: None,
         }
     }
 }
 
+impl KizunaTransportConfig {
+    /// Configured proxy, or the one from `ALL_PROXY`/`HTTPS_PROXY` when unset
+    pub fn effective_proxy(&self) -> Option<ProxyConfig> {
+        self.proxy.clone().or_else(ProxyConfig::from_env)
+    }
+
+    /// WebSocket transport settings for the configured relay servers and proxy
+    pub fn websocket_config(&self) -> WebSocketConfig {
+        let mut config = WebSocketConfig::default();
+        if let Some(relay) = &self.relay_config {
+            config.relay_servers = relay
+                .relay_servers
+                .iter()
+                .filter_map(|server| url::Url::parse(server).ok())
+                .collect();
+            config.connect_timeout = relay.relay_timeout;
+        }
+        config.proxy = self.effective_proxy();
+        config
+    }
+}
+
 /// Connection lifecycle events
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ConnectionEvent {
@@ -196,6 +238,7 @@ pub struct ConnectionHandle {
     connection: Arc<RwLock<Box<dyn Connection>>>,
     event_sender: mpsc::UnboundedSender<ConnectionEvent>,
     quality: Arc<RwLock<ConnectionQuality>>,
+    negotiated: Option<NegotiatedProtocol>,
 }
 
 impl ConnectionHandle {
@@ -204,6 +247,11 @@ impl ConnectionHandle {
         &self.peer_id
     }
     
+    /// Protocol parameters agreed during the handshake, if one was performed
+    pub fn negotiated_protocol(&self) -> Option<&NegotiatedProtocol> {
+        self.negotiated.as_ref()
+    }
+    
     /// Read data from the connection
     pub async fn read(&self, buffer: &mut [u8]) -> Result<usize, TransportError> {
         let mut conn = self.connection.write().await;
@@ -238,6 +286,11 @@ impl ConnectionHandle {
         conn.flush().await
     }
     
+    /// Unreliable datagram channel to the peer, if the connection supports one
+    pub async fn datagrams(&self) -> Option<Arc<dyn DatagramChannel>> {
+        self.connection.read().await.datagrams()
+    }
+    
     /// Close the connection gracefully
     pub async fn close(&self) -> Result<(), TransportError> {
         let mut conn = self.connection.write().await;
@@ -280,8 +333,20 @@ pub struct KizunaTransport {
     event_receiver: Arc<RwLock<mpsc::UnboundedReceiver<ConnectionEvent>>>,
     callbacks: Arc<RwLock<Vec<Arc<dyn ConnectionCallback>>>>,
     is_listening: Arc<RwLock<bool>>,
+    nat_traversal: Arc<OnceCell<Arc<NatTraversal>>>,
+    nat_monitor: Arc<RwLock<Option<JoinHandle<()>>>>,
+    port_mapper: Arc<RwLock<Option<ActivePortMapper>>>,
 }
 
+/// Port mapper forwarding the listen port, with the task renewing its leases
+struct ActivePortMapper {
+    mapper: Arc<PortMapper>,
+    renewal: JoinHandle<()>,
+}
+
+/// How often the NAT monitor checks for a network change
+const NAT_MONITOR_INTERVAL: Duration = Duration::from_secs(30);
+
 impl KizunaTransport {
     /// Create a new Kizuna Transport instance with default configuration
     pub async fn new() -> Result<Self, TransportError> {
@@ -305,6 +370,9 @@ impl KizunaTransport {
             event_receiver: Arc::new(RwLock::new(event_receiver)),
             callbacks: Arc::new(RwLock::new(Vec::new())),
             is_listening: Arc::new(RwLock::new(false)),
+            nat_traversal: Arc::new(OnceCell::new()),
+            nat_monitor: Arc::new(RwLock::new(None)),
+            port_mapper: Arc::new(RwLock::new(None)),
         })
     }
     
@@ -330,9 +398,40 @@ impl KizunaTransport {
         // Start event processing task
         self.start_event_processing().await;
         
+        // Classify the NAT now and again whenever the network changes
+        let nat_traversal = self.nat_traversal().await;
+        *self.nat_monitor.write().await = Some(nat_traversal.spawn_nat_monitor(NAT_MONITOR_INTERVAL));
+        
+        // Stream transports and QUIC share the port, so both protocols are forwarded
+        if let Some(config) = &self.config.port_mapping
+            && bind_address.port() != 0 && !bind_address.ip().is_loopback()
+        {
+            let mapper = Arc::new(PortMapper::new(config.clone()));
+            let port = bind_address.port();
+            let renewal = mapper.clone().spawn(vec![(PortProtocol::Tcp, port), (PortProtocol::Udp, port)]);
+            *self.port_mapper.write().await = Some(ActivePortMapper { mapper, renewal });
+        }
+        
         Ok(())
     }
     
+    /// Listen on `ip`, at the configured tcp port or the first free fallback
+    ///
+    /// Returns the address actually bound.
+    pub async fn start_listening_on(&self, ip: IpAddr) -> Result<SocketAddr, TransportError> {
+        let bind_address = select_port(ip, &self.config.listen_ports.tcp, PortProtocol::Tcp)?;
+        self.start_listening(bind_address).await?;
+        Ok(bind_address)
+    }
+    
+    /// Ports the router currently forwards to this device
+    pub async fn port_mappings(&self) -> Vec<PortMapping> {
+        match self.port_mapper.read().await.as_ref() {
+            Some(active) => active.mapper.mappings().await,
+            None => Vec::new(),
+        }
+    }
+    
     /// Stop listening for incoming connections
     pub async fn stop_listening(&self) -> Result<(), TransportError> {
         {
@@ -343,6 +442,15 @@ impl KizunaTransport {
             *is_listening = false;
         }
         
+        if let Some(monitor) = self.nat_monitor.write().await.take() {
+            monitor.abort();
+        }
+        
+        if let Some(active) = self.port_mapper.write().await.take() {
+            active.renewal.abort();
+            active.mapper.unmap_all().await;
+        }
+        
         self.transport_system.stop_listening().await?;
         Ok(())
     }
@@ -357,8 +465,9 @@ impl KizunaTransport {
         });
         
         // Attempt connection through transport system
-        let connection = self.transport_system.connect_to_peer(peer_address).await?;
+        let mut connection = self.transport_system.connect_to_peer(peer_address).await?;
         let connection_info = connection.info();
+        let negotiated = self.negotiate_protocol(&mut connection, &peer_address.peer_id).await?;
         
         // Create connection handle
         let handle = ConnectionHandle {
@@ -372,6 +481,7 @@ impl KizunaTransport {
                 stability_score: 1.0,
                 quality_class: QualityClass::Good,
             })),
+            negotiated,
         };
         
         // Store connection
@@ -405,8 +515,9 @@ impl KizunaTransport {
         });
         
         // Attempt connection with specific protocol
-        let connection = self.transport_system.connect_with_protocol(peer_address, protocol).await?;
+        let mut connection = self.transport_system.connect_with_protocol(peer_address, protocol).await?;
         let connection_info = connection.info();
+        let negotiated = self.negotiate_protocol(&mut connection, &peer_address.peer_id).await?;
         
         // Create connection handle
         let handle = ConnectionHandle {
@@ -420,6 +531,7 @@ impl KizunaTransport {
                 stability_score: 1.0,
                 quality_class: QualityClass::Good,
             })),
+            negotiated,
         };
         
         // Store connection
@@ -443,6 +555,32 @@ impl KizunaTransport {
         Ok(peer_connections.last().unwrap().clone())
     }
     
+    /// Run the protocol handshake on a new connection when one is configured
+    async fn negotiate_protocol(
+        &self,
+        connection: &mut Box<dyn Connection>,
+        peer_id: &PeerId,
+    ) -> Result<Option<NegotiatedProtocol>, TransportError> {
+        let hello = match &self.config.protocol_handshake {
+            Some(hello) => hello,
+            None => return Ok(None),
+        };
+        
+        match perform_handshake(connection.as_mut(), hello, self.config.connection_timeout).await {
+            Ok(negotiated) => Ok(Some(negotiated)),
+            Err(e) => {
+                let _ = connection.close().await;
+                let _ = self.event_sender.send(ConnectionEvent::ConnectionFailed {
+                    peer_id: peer_id.clone(),
+                    protocol: connection.info().protocol,
+                    error: e.to_string(),
+                    attempt: 1,
+                });
+                Err(e)
+            }
+        }
+    }
+    
     /// Get all active connections for a peer
     pub async fn get_connections(&self, peer_id: &PeerId) -> Vec<ConnectionHandle> {
         let connections = self.active_connections.read().await;
@@ -477,6 +615,40 @@ impl KizunaTransport {
         Ok(())
     }
     
+    /// NAT traversal engine, created on first use from the configured STUN servers
+    pub async fn nat_traversal(&self) -> Arc<NatTraversal> {
+        self.nat_traversal
+            .get_or_init(|| async {
+                let stun_servers = match &self.config.nat_traversal_config {
+                    Some(config) => resolve_stun_servers(&config.stun_servers).await,
+                    None => Vec::new(),
+                };
+                Arc::new(NatTraversal::new(stun_servers).with_proxy(self.config.effective_proxy()))
+            })
+            .await
+            .clone()
+    }
+    
+    /// Report the NAT type, running STUN detection if needed
+    ///
+    /// With `refresh` set the cached classification is ignored.
+    pub async fn diagnose_nat(&self, refresh: bool) -> Result<NatReport, TransportError> {
+        let nat_traversal = self.nat_traversal().await;
+        if !refresh && let Some(report) = nat_traversal.get_cached_nat_report().await {
+            return Ok(report);
+        }
+        nat_traversal.detect_nat().await
+    }
+    
+    /// Network conditions for protocol selection, including the last NAT classification
+    pub async fn network_conditions(&self) -> NetworkConditions {
+        let conditions = NetworkConditions::default();
+        match self.nat_traversal().await.get_cached_nat_report().await {
+            Some(report) => conditions.with_nat_report(&report),
+            None => conditions,
+        }
+    }
+    
     /// Get transport system health report
     pub async fn get_health_report(&self) -> SystemHealthReport {
         self.transport_system.get_health_report().await
@@ -599,6 +771,7 @@ impl Clone for ConnectionHandle {
             connection: Arc::clone(&self.connection),
             event_sender: self.event_sender.clone(),
             quality: Arc::clone(&self.quality),
+            negotiated: self.negotiated.clone(),
         }
     }
 }
@@ -635,6 +808,12 @@ impl KizunaTransportBuilder {
         self
     }
     
+    /// Exchange a protocol hello on every new connection
+    pub fn protocol_handshake(mut self, hello: ProtocolHello) -> Self {
+        self.config.protocol_handshake = Some(hello);
+        self
+    }
+    
     /// Set keep-alive interval
     pub fn keep_alive_interval(mut self, interval: Duration) -> Self {
         self.config.keep_alive_interval = interval;
@@ -683,6 +862,24 @@ impl KizunaTransportBuilder {
         self
     }
     
+    /// Set the ports each transport listens on
+    pub fn listen_ports(mut self, ports: ListenPorts) -> Self {
+        self.config.listen_ports = ports;
+        self
+    }
+    
+    /// Ask the router to forward the listening port over UPnP or NAT-PMP
+    pub fn port_mapping(mut self, config: PortMappingConfig) -> Self {
+        self.config.port_mapping = Some(config);
+        self
+    }
+    
+    /// Reach relay and STUN servers through a proxy
+    pub fn proxy(mut self, config: ProxyConfig) -> Self {
+        self.config.proxy = Some(config);
+        self
+    }
+    
     /// Build the KizunaTransport instance
     pub async fn build(self) -> Result<KizunaTransport, TransportError> {
         KizunaTransport::with_config(self.config).await
@@ -698,6 +895,7 @@ impl Default for KizunaTransportBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transport::TransportCapabilities;
     use std::net::{IpAddr, Ipv4Addr};
 
     #[tokio::test]
@@ -0,0 +1,177 @@
+// Send-side backpressure
+//
+// Tracks bytes accepted for sending but not yet handed to the network. Producers
+// check the gauge (or await `ready()`) before generating more data, so a sender
+// faster than the network stalls at the high watermark instead of buffering without
+// bound. Once congested, the gauge reports ready again only after the backlog drains
+// to the low watermark, which avoids flapping around a single threshold.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+use crate::transport::TransportError;
+
+/// Buffering thresholds for a sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watermarks {
+    /// Backlog at which a congested sender may resume
+    pub low: usize,
+    /// Backlog at which a sender must pause
+    pub high: usize,
+}
+
+impl Watermarks {
+    /// Create watermarks, validating that `low < high`
+    pub fn new(low: usize, high: usize) -> Result<Self, TransportError> {
+        if low >= high {
+            return Err(TransportError::ConfigurationError {
+                field: "watermarks".to_string(),
+                reason: format!("low watermark {} must be below high watermark {}", low, high),
+            });
+        }
+        Ok(Self { low, high })
+    }
+}
+
+impl Default for Watermarks {
+    fn default() -> Self {
+        Self {
+            low: 256 * 1024,
+            high: 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct GaugeState {
+    buffered: AtomicUsize,
+    congested: AtomicBool,
+    drained: Notify,
+    watermarks: Watermarks,
+}
+
+/// Shared counter of buffered bytes with watermark hysteresis
+///
+/// Clones share the same counter, so the producer and the component draining the
+/// buffer can each hold one.
+#[derive(Debug, Clone)]
+pub struct BackpressureGauge {
+    state: Arc<GaugeState>,
+}
+
+impl BackpressureGauge {
+    /// Create a gauge with the given thresholds
+    pub fn new(watermarks: Watermarks) -> Self {
+        Self {
+            state: Arc::new(GaugeState {
+                buffered: AtomicUsize::new(0),
+                congested: AtomicBool::new(false),
+                drained: Notify::new(),
+                watermarks,
+            }),
+        }
+    }
+
+    /// Thresholds used by this gauge
+    pub fn watermarks(&self) -> Watermarks {
+        self.state.watermarks
+    }
+
+    /// Bytes currently buffered
+    pub fn buffered(&self) -> usize {
+        self.state.buffered.load(Ordering::SeqCst)
+    }
+
+    /// Whether producers should pause
+    pub fn is_congested(&self) -> bool {
+        self.state.congested.load(Ordering::SeqCst)
+    }
+
+    /// Record bytes entering the buffer
+    pub fn add(&self, bytes: usize) {
+        let total = self.state.buffered.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        if total >= self.state.watermarks.high {
+            self.state.congested.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Record bytes leaving the buffer
+    pub fn release(&self, bytes: usize) {
+        let previous = self
+            .state
+            .buffered
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                Some(current.saturating_sub(bytes))
+            })
+            .unwrap_or(0);
+        let total = previous.saturating_sub(bytes);
+
+        if total <= self.state.watermarks.low && self.state.congested.swap(false, Ordering::SeqCst) {
+            self.state.drained.notify_waiters();
+        }
+    }
+
+    /// Wait until the sender is allowed to produce more data
+    pub async fn ready(&self) {
+        loop {
+            let drained = self.state.drained.notified();
+            if !self.is_congested() {
+                return;
+            }
+            drained.await;
+        }
+    }
+}
+
+impl Default for BackpressureGauge {
+    fn default() -> Self {
+        Self::new(Watermarks::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_watermarks_validated() {
+        assert!(Watermarks::new(10, 10).is_err());
+        assert!(Watermarks::new(10, 20).is_ok());
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let gauge = BackpressureGauge::new(Watermarks::new(10, 100).unwrap());
+
+        gauge.add(99);
+        assert!(!gauge.is_congested());
+        gauge.add(1);
+        assert!(gauge.is_congested());
+
+        // Dropping below high is not enough to resume
+        gauge.release(50);
+        assert!(gauge.is_congested());
+        gauge.release(40);
+        assert!(!gauge.is_congested());
+        assert_eq!(gauge.buffered(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_ready_waits_for_drain() {
+        let gauge = BackpressureGauge::new(Watermarks::new(0, 8).unwrap());
+        gauge.add(8);
+
+        let waiter = {
+            let gauge = gauge.clone();
+            tokio::spawn(async move { gauge.ready().await })
+        };
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        gauge.release(8);
+        tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+    }
+}
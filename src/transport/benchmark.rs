@@ -0,0 +1,484 @@
+// Link benchmarking between two peers
+//
+// `run_benchmark` measures one connection. A series of echo pings gives the
+// round-trip time and jitter (the mean difference between consecutive round
+// trips, as in RFC 3550), then data frames are streamed for the configured
+// duration and the peer acknowledges the byte count, which gives sustained
+// throughput. The peer answers with `serve_benchmark`.
+//
+// Messages travel in `MessageKind::Benchmark` wire frames whose payload
+// starts with an op byte:
+//
+//   op 1 ping | seq: u32
+//   op 2 pong | seq: u32
+//   op 3 data | filler bytes
+//   op 4 end
+//   op 5 ack  | bytes_received: u64
+//
+// Results are kept per peer in a `BenchmarkStore` and turned into scores
+// the protocol negotiator adds to its own, so the protocol that measured
+// best with a peer is preferred next time.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::transport::wire::{encode_frame, parse_frame, MessageKind, WireError};
+use crate::transport::{Connection, PeerId, TransportError};
+
+const OP_PING: u8 = 1;
+const OP_PONG: u8 = 2;
+const OP_DATA: u8 = 3;
+const OP_END: u8 = 4;
+const OP_ACK: u8 = 5;
+
+/// Largest data frame a responder accepts
+pub const MAX_BENCHMARK_PAYLOAD: usize = 4 * 1024 * 1024;
+
+/// Score bonus for the highest measured throughput
+const THROUGHPUT_WEIGHT: f32 = 15.0;
+/// Score bonus for the lowest measured round-trip time
+const LATENCY_WEIGHT: f32 = 10.0;
+
+/// Benchmark parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkConfig {
+    /// How long to stream data for the throughput test
+    pub duration: Duration,
+    /// Size of each data frame
+    pub payload_size: usize,
+    /// Number of echo pings for the latency test
+    pub ping_count: u32,
+    /// How long to wait for any single reply
+    pub reply_timeout: Duration,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(10),
+            payload_size: 64 * 1024,
+            ping_count: 20,
+            reply_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Measurements for one protocol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolBenchmark {
+    pub protocol: String,
+    /// Mean round-trip time in milliseconds
+    pub rtt_ms: f64,
+    pub min_rtt_ms: f64,
+    pub max_rtt_ms: f64,
+    /// Mean difference between consecutive round trips in milliseconds
+    pub jitter_ms: f64,
+    /// Fraction of pings that got no reply
+    pub ping_loss: f64,
+    /// Sustained throughput in bytes per second
+    pub throughput_bytes_per_sec: f64,
+    /// Bytes the peer acknowledged during the throughput test
+    pub bytes_transferred: u64,
+    /// When the measurement finished, in seconds since the Unix epoch
+    pub measured_at: u64,
+}
+
+impl ProtocolBenchmark {
+    /// Throughput in megabits per second
+    pub fn throughput_mbps(&self) -> f64 {
+        self.throughput_bytes_per_sec * 8.0 / 1_000_000.0
+    }
+}
+
+/// A protocol that could not be benchmarked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchmarkFailure {
+    pub protocol: String,
+    pub error: String,
+}
+
+/// Benchmark of every protocol tried with a peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub peer_id: PeerId,
+    pub config: BenchmarkConfig,
+    pub results: Vec<ProtocolBenchmark>,
+    pub failures: Vec<BenchmarkFailure>,
+}
+
+impl BenchmarkReport {
+    pub fn new(peer_id: PeerId, config: BenchmarkConfig) -> Self {
+        Self {
+            peer_id,
+            config,
+            results: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    /// Protocol with the highest score, if any was measured
+    pub fn best_protocol(&self) -> Option<&str> {
+        let scores = protocol_scores(&self.results);
+        self.results
+            .iter()
+            .max_by(|a, b| scores[&a.protocol].total_cmp(&scores[&b.protocol]))
+            .map(|result| result.protocol.as_str())
+    }
+}
+
+/// Negotiation score bonus for each measured protocol
+///
+/// The fastest protocol gets the full throughput weight and the one with the
+/// lowest round-trip time the full latency weight; the others get a share
+/// proportional to how close they came.
+pub fn protocol_scores(results: &[ProtocolBenchmark]) -> HashMap<String, f32> {
+    let max_throughput = results.iter().map(|r| r.throughput_bytes_per_sec).fold(0.0, f64::max);
+    let min_rtt = results
+        .iter()
+        .map(|r| r.rtt_ms)
+        .filter(|rtt| *rtt > 0.0)
+        .fold(f64::INFINITY, f64::min);
+
+    results
+        .iter()
+        .map(|result| {
+            let throughput = if max_throughput > 0.0 {
+                result.throughput_bytes_per_sec / max_throughput
+            } else {
+                0.0
+            };
+            let latency = if result.rtt_ms > 0.0 && min_rtt.is_finite() {
+                min_rtt / result.rtt_ms
+            } else {
+                0.0
+            };
+            let score = throughput as f32 * THROUGHPUT_WEIGHT + latency as f32 * LATENCY_WEIGHT;
+            (result.protocol.clone(), score)
+        })
+        .collect()
+}
+
+/// Measure latency, jitter and throughput over an established connection
+pub async fn run_benchmark(
+    connection: &mut dyn Connection,
+    protocol: &str,
+    config: &BenchmarkConfig,
+) -> Result<ProtocolBenchmark, TransportError> {
+    let mut reader = FrameReader::new();
+
+    // Latency: one ping at a time, so queueing does not inflate the numbers
+    let mut rtts = Vec::new();
+    for seq in 0..config.ping_count {
+        let started = Instant::now();
+        send(connection, OP_PING, &seq.to_be_bytes()).await?;
+
+        let reply = tokio::time::timeout(config.reply_timeout, async {
+            loop {
+                let (op, body) = reader.next(connection).await?;
+                // Late pongs for earlier pings are skipped
+                if op == OP_PONG && body.get(..4) == Some(&seq.to_be_bytes()[..]) {
+                    return Ok::<_, TransportError>(());
+                }
+            }
+        })
+        .await;
+        // A ping without a reply in time counts as lost
+        if let Ok(result) = reply {
+            result?;
+            rtts.push(started.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+
+    if rtts.is_empty() {
+        return Err(TransportError::ConnectionTimeout {
+            timeout: config.reply_timeout,
+        });
+    }
+
+    // Throughput: stream for the configured duration, then wait for the ack
+    let filler = vec![0xA5u8; config.payload_size];
+    let started = Instant::now();
+    while started.elapsed() < config.duration {
+        send(connection, OP_DATA, &filler).await?;
+    }
+    send(connection, OP_END, &[]).await?;
+
+    let bytes_transferred = tokio::time::timeout(config.reply_timeout, async {
+        loop {
+            let (op, body) = reader.next(connection).await?;
+            if op == OP_ACK {
+                let bytes: [u8; 8] = body
+                    .get(..8)
+                    .and_then(|b| b.try_into().ok())
+                    .ok_or_else(|| TransportError::Serialization("Truncated benchmark ack".to_string()))?;
+                return Ok::<_, TransportError>(u64::from_be_bytes(bytes));
+            }
+        }
+    })
+    .await
+    .map_err(|_| TransportError::ConnectionTimeout {
+        timeout: config.reply_timeout,
+    })??;
+    let elapsed = started.elapsed().as_secs_f64();
+
+    Ok(summarize(protocol, &rtts, config.ping_count, bytes_transferred, elapsed))
+}
+
+/// Answer a peer's benchmark until it closes the connection
+///
+/// Returns the number of data bytes received.
+pub async fn serve_benchmark(connection: &mut dyn Connection) -> Result<u64, TransportError> {
+    let mut reader = FrameReader::new();
+    let mut total = 0u64;
+    let mut since_end = 0u64;
+
+    loop {
+        let (op, body) = match reader.next(connection).await {
+            Ok(frame) => frame,
+            Err(TransportError::ConnectionFailed { .. }) => return Ok(total),
+            Err(e) => return Err(e),
+        };
+
+        match op {
+            OP_PING => send(connection, OP_PONG, &body).await?,
+            OP_DATA => since_end += body.len() as u64,
+            OP_END => {
+                send(connection, OP_ACK, &since_end.to_be_bytes()).await?;
+                total += since_end;
+                since_end = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn summarize(protocol: &str, rtts: &[f64], ping_count: u32, bytes: u64, elapsed_secs: f64) -> ProtocolBenchmark {
+    let rtt_ms = rtts.iter().sum::<f64>() / rtts.len() as f64;
+    let jitter_ms = if rtts.len() > 1 {
+        rtts.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum::<f64>() / (rtts.len() - 1) as f64
+    } else {
+        0.0
+    };
+
+    ProtocolBenchmark {
+        protocol: protocol.to_string(),
+        rtt_ms,
+        min_rtt_ms: rtts.iter().copied().fold(f64::INFINITY, f64::min),
+        max_rtt_ms: rtts.iter().copied().fold(0.0, f64::max),
+        jitter_ms,
+        ping_loss: 1.0 - rtts.len() as f64 / f64::from(ping_count.max(1)),
+        throughput_bytes_per_sec: if elapsed_secs > 0.0 { bytes as f64 / elapsed_secs } else { 0.0 },
+        bytes_transferred: bytes,
+        measured_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+    }
+}
+
+async fn send(connection: &mut dyn Connection, op: u8, body: &[u8]) -> Result<(), TransportError> {
+    let mut payload = Vec::with_capacity(1 + body.len());
+    payload.push(op);
+    payload.extend_from_slice(body);
+    let frame = encode_frame(MessageKind::Benchmark, 0, &payload)
+        .map_err(|e| TransportError::Serialization(e.to_string()))?;
+
+    let mut written = 0;
+    while written < frame.len() {
+        written += connection.write(&frame[written..]).await?;
+    }
+    connection.flush().await
+}
+
+/// Buffers reads until a whole benchmark frame is available
+struct FrameReader {
+    buffer: Vec<u8>,
+    chunk: Vec<u8>,
+}
+
+impl FrameReader {
+    fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            chunk: vec![0u8; 64 * 1024],
+        }
+    }
+
+    async fn next(&mut self, connection: &mut dyn Connection) -> Result<(u8, Vec<u8>), TransportError> {
+        loop {
+            match parse_frame(&self.buffer, MAX_BENCHMARK_PAYLOAD + 1) {
+                Ok((frame, consumed)) => {
+                    let message = match (frame.kind, frame.payload.split_first()) {
+                        (MessageKind::Benchmark, Some((op, body))) => Some((*op, body.to_vec())),
+                        _ => None,
+                    };
+                    self.buffer.drain(..consumed);
+                    // Frames of other kinds are not ours to answer
+                    if let Some(message) = message {
+                        return Ok(message);
+                    }
+                    continue;
+                }
+                Err(WireError::Incomplete { .. }) => {}
+                Err(e) => return Err(TransportError::Serialization(e.to_string())),
+            }
+
+            let n = connection.read(&mut self.chunk).await?;
+            if n == 0 {
+                return Err(TransportError::ConnectionFailed {
+                    reason: "Connection closed during benchmark".to_string(),
+                });
+            }
+            self.buffer.extend_from_slice(&self.chunk[..n]);
+        }
+    }
+}
+
+/// Benchmark results kept between runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BenchmarkStore {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    peers: HashMap<PeerId, Vec<ProtocolBenchmark>>,
+}
+
+impl BenchmarkStore {
+    /// Default location under the user's data directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("kizuna").join("benchmarks.json"))
+    }
+
+    /// Load the store from a file, starting empty if it does not exist
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TransportError> {
+        let path = path.as_ref();
+        let mut store: Self = match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| TransportError::Serialization(e.to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        store.path = Some(path.to_path_buf());
+        Ok(store)
+    }
+
+    /// Load the store from its default location
+    pub fn open_default() -> Result<Self, TransportError> {
+        let path = Self::default_path()
+            .ok_or_else(|| TransportError::Configuration("No data directory for benchmark results".to_string()))?;
+        Self::load(path)
+    }
+
+    /// Keep the latest result for each protocol measured in a report
+    pub fn record(&mut self, report: &BenchmarkReport) {
+        let results = self.peers.entry(report.peer_id.clone()).or_default();
+        for result in &report.results {
+            results.retain(|existing| existing.protocol != result.protocol);
+            results.push(result.clone());
+        }
+    }
+
+    /// Latest results for a peer
+    pub fn results_for(&self, peer_id: &str) -> &[ProtocolBenchmark] {
+        self.peers.get(peer_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Negotiation score bonus per protocol for every peer
+    pub fn scores(&self) -> HashMap<PeerId, HashMap<String, f32>> {
+        self.peers
+            .iter()
+            .map(|(peer_id, results)| (peer_id.clone(), protocol_scores(results)))
+            .collect()
+    }
+
+    /// Write the store back to the file it was loaded from
+    pub fn save(&self) -> Result<(), TransportError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_vec_pretty(self).map_err(|e| TransportError::Serialization(e.to_string()))?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MemoryConnection;
+
+    fn result(protocol: &str, rtt_ms: f64, throughput: f64) -> ProtocolBenchmark {
+        ProtocolBenchmark {
+            protocol: protocol.to_string(),
+            rtt_ms,
+            min_rtt_ms: rtt_ms,
+            max_rtt_ms: rtt_ms,
+            jitter_ms: 0.0,
+            ping_loss: 0.0,
+            throughput_bytes_per_sec: throughput,
+            bytes_transferred: 0,
+            measured_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_over_connection() {
+        let (mut a, mut b) = MemoryConnection::pair(
+            "a".to_string(),
+            "10.0.0.1:41000".parse().unwrap(),
+            "b".to_string(),
+            "10.0.0.2:41000".parse().unwrap(),
+        );
+        let responder = tokio::spawn(async move { serve_benchmark(&mut b).await });
+
+        let config = BenchmarkConfig {
+            duration: Duration::from_millis(50),
+            payload_size: 1024,
+            ping_count: 5,
+            reply_timeout: Duration::from_secs(1),
+        };
+        let result = run_benchmark(&mut a, "tcp", &config).await.unwrap();
+        a.close().await.unwrap();
+
+        assert_eq!(result.protocol, "tcp");
+        assert_eq!(result.ping_loss, 0.0);
+        assert!(result.bytes_transferred >= 1024);
+        assert!(result.throughput_bytes_per_sec > 0.0);
+        assert_eq!(responder.await.unwrap().unwrap(), result.bytes_transferred);
+    }
+
+    #[test]
+    fn test_jitter_is_mean_consecutive_difference() {
+        let summary = summarize("quic", &[10.0, 14.0, 12.0], 4, 1000, 2.0);
+        assert_eq!(summary.rtt_ms, 12.0);
+        assert_eq!(summary.jitter_ms, 3.0);
+        assert_eq!(summary.ping_loss, 0.25);
+        assert_eq!(summary.throughput_bytes_per_sec, 500.0);
+    }
+
+    #[test]
+    fn test_scores_prefer_fast_protocol() {
+        let scores = protocol_scores(&[result("tcp", 20.0, 1_000_000.0), result("quic", 10.0, 4_000_000.0)]);
+        assert_eq!(scores["quic"], THROUGHPUT_WEIGHT + LATENCY_WEIGHT);
+        assert!(scores["tcp"] < scores["quic"]);
+    }
+
+    #[test]
+    fn test_store_keeps_latest_per_protocol() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("benchmarks.json");
+
+        let mut store = BenchmarkStore::load(&path).unwrap();
+        let mut report = BenchmarkReport::new("peer-1".to_string(), BenchmarkConfig::default());
+        report.results.push(result("tcp", 20.0, 1.0));
+        store.record(&report);
+        report.results[0].rtt_ms = 5.0;
+        store.record(&report);
+        store.save().unwrap();
+
+        let reloaded = BenchmarkStore::load(&path).unwrap();
+        assert_eq!(reloaded.results_for("peer-1").len(), 1);
+        assert_eq!(reloaded.results_for("peer-1")[0].rtt_ms, 5.0);
+        assert!(reloaded.results_for("peer-2").is_empty());
+    }
+}
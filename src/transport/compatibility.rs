@@ -0,0 +1,249 @@
+// Software version skew between peers
+//
+// Peers report their software version in the protocol hello. The wire
+// protocol's major version decides whether two builds can talk at all; the
+// compatibility table decides which features they can use together. For each
+// feature it records the first release that implements it and any releases
+// known to implement it incompatibly. A feature is only enabled on a
+// connection when the peer's release passes both checks, so using it against
+// an older peer fails up front with an error naming the release to update to,
+// instead of as a decode error halfway through an operation.
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::transport::handshake::CapabilityFlags;
+
+/// Prefix of the software version sent in the protocol hello
+pub const SOFTWARE_PREFIX: &str = "kizuna/";
+
+/// Parse a software version as reported by a peer, e.g. `kizuna/0.2.1`
+pub fn parse_software_version(software: &str) -> Option<Version> {
+    let version = software.trim();
+    let version = version.strip_prefix(SOFTWARE_PREFIX).unwrap_or(version);
+    Version::parse(version.trim_start_matches('v')).ok()
+}
+
+/// Version of this build
+pub fn local_version() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).expect("crate version is valid semver")
+}
+
+/// First release implementing a feature
+#[derive(Debug, Clone)]
+pub struct FeatureRequirement {
+    pub capability: CapabilityFlags,
+    pub introduced: Version,
+}
+
+/// Releases whose implementation of a feature does not work with current ones
+#[derive(Debug, Clone)]
+pub struct KnownIncompatibility {
+    pub capability: CapabilityFlags,
+    pub versions: VersionReq,
+    pub reason: &'static str,
+}
+
+/// How far apart two peers' releases are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkewLevel {
+    /// Same release
+    None,
+    /// Only the patch version differs
+    Patch,
+    /// Different minor version, or any difference before 1.0
+    Minor,
+    /// Different major version
+    Major,
+    /// The peer's version could not be parsed
+    Unknown,
+}
+
+impl fmt::Display for SkewLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SkewLevel::None => "none",
+            SkewLevel::Patch => "patch",
+            SkewLevel::Minor => "minor",
+            SkewLevel::Major => "major",
+            SkewLevel::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Result of comparing a peer's release with this one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionSkew {
+    pub local: Version,
+    pub remote: Option<Version>,
+    pub level: SkewLevel,
+    /// Features the peer's release cannot be used for
+    pub disabled: CapabilityFlags,
+    /// Messages to show the user, one per problem
+    pub warnings: Vec<String>,
+}
+
+impl VersionSkew {
+    /// Whether the user should hear about the difference
+    pub fn is_significant(&self) -> bool {
+        self.level > SkewLevel::Patch || !self.disabled.is_empty()
+    }
+}
+
+/// Which releases can use which features together
+#[derive(Debug, Clone, Default)]
+pub struct CompatibilityTable {
+    requirements: Vec<FeatureRequirement>,
+    incompatibilities: Vec<KnownIncompatibility>,
+}
+
+impl CompatibilityTable {
+    /// Table with no entries; every feature works with every release
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Table for this build
+    ///
+    /// Add an entry here whenever a feature is introduced or its wire format
+    /// changes incompatibly.
+    pub fn standard() -> Self {
+        Self::new()
+            .with_requirement(CapabilityFlags::MULTIPLEXING, "0.1.0")
+            .with_requirement(CapabilityFlags::COMPRESSION, "0.1.0")
+            .with_requirement(CapabilityFlags::TRANSFER_RESUME, "0.1.0")
+            .with_requirement(CapabilityFlags::CLIPBOARD_SYNC, "0.1.0")
+            .with_requirement(CapabilityFlags::FILE_TRANSFER, "0.1.0")
+            .with_requirement(CapabilityFlags::STREAMING, "0.1.0")
+            .with_requirement(CapabilityFlags::COMMAND_EXECUTION, "0.1.0")
+    }
+
+    /// Record the first release implementing a feature
+    pub fn with_requirement(mut self, capability: CapabilityFlags, introduced: &str) -> Self {
+        let introduced = Version::parse(introduced).expect("compatibility table versions are valid semver");
+        self.requirements.retain(|r| r.capability != capability);
+        self.requirements.push(FeatureRequirement { capability, introduced });
+        self
+    }
+
+    /// Record releases whose implementation of a feature is incompatible
+    pub fn with_incompatibility(mut self, capability: CapabilityFlags, versions: &str, reason: &'static str) -> Self {
+        let versions = VersionReq::parse(versions).expect("compatibility table ranges are valid semver requirements");
+        self.incompatibilities.push(KnownIncompatibility { capability, versions, reason });
+        self
+    }
+
+    /// First release implementing a feature, if the table knows it
+    pub fn introduced_in(&self, capability: CapabilityFlags) -> Option<&Version> {
+        self.requirements
+            .iter()
+            .find(|r| r.capability == capability)
+            .map(|r| &r.introduced)
+    }
+
+    /// Compare a peer's reported software version with this build's
+    pub fn assess(&self, local: &Version, remote_software: &str) -> VersionSkew {
+        let Some(remote) = parse_software_version(remote_software) else {
+            return VersionSkew {
+                local: local.clone(),
+                remote: None,
+                level: SkewLevel::Unknown,
+                disabled: CapabilityFlags::NONE,
+                warnings: vec![format!(
+                    "Peer reports an unrecognised version '{}'; some features may not work",
+                    remote_software
+                )],
+            };
+        };
+
+        let level = skew_level(local, &remote);
+        let mut disabled = CapabilityFlags::NONE;
+        let mut warnings = Vec::new();
+        if level >= SkewLevel::Minor {
+            let older = if remote < *local { "the peer" } else { "this device" };
+            warnings.push(format!(
+                "Peer runs kizuna {} and this device runs {}; update {} to avoid problems",
+                remote, local, older
+            ));
+        }
+
+        // Only a peer's own release limits what it can do; a newer peer is
+        // expected to keep working with what this build implements
+        for requirement in &self.requirements {
+            if remote < requirement.introduced {
+                disabled = disabled | requirement.capability;
+                warnings.push(format!(
+                    "{} is unavailable: the peer runs kizuna {} and it needs {} or later",
+                    requirement.capability, remote, requirement.introduced
+                ));
+            }
+        }
+        for incompatibility in &self.incompatibilities {
+            if incompatibility.versions.matches(&remote) && !disabled.contains(incompatibility.capability) {
+                disabled = disabled | incompatibility.capability;
+                warnings.push(format!(
+                    "{} is unavailable with kizuna {}: {}",
+                    incompatibility.capability, remote, incompatibility.reason
+                ));
+            }
+        }
+
+        VersionSkew {
+            local: local.clone(),
+            remote: Some(remote),
+            level,
+            disabled,
+            warnings,
+        }
+    }
+}
+
+fn skew_level(local: &Version, remote: &Version) -> SkewLevel {
+    if local.major != remote.major {
+        SkewLevel::Major
+    } else if local.minor != remote.minor {
+        SkewLevel::Minor
+    } else if local.patch != remote.patch || local.pre != remote.pre {
+        // Before 1.0 every release may change behaviour
+        if local.major == 0 { SkewLevel::Minor } else { SkewLevel::Patch }
+    } else {
+        SkewLevel::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_peer_loses_newer_features() {
+        let table = CompatibilityTable::new()
+            .with_requirement(CapabilityFlags::FILE_TRANSFER, "1.0.0")
+            .with_requirement(CapabilityFlags::STREAMING, "1.3.0")
+            .with_incompatibility(CapabilityFlags::CLIPBOARD_SYNC, ">=1.1.0, <1.2.0", "clipboard frames changed in 1.2");
+        let local = Version::new(1, 4, 0);
+
+        let skew = table.assess(&local, "kizuna/1.1.5");
+        assert_eq!(skew.level, SkewLevel::Minor);
+        assert!(skew.disabled.contains(CapabilityFlags::STREAMING));
+        assert!(skew.disabled.contains(CapabilityFlags::CLIPBOARD_SYNC));
+        assert!(!skew.disabled.contains(CapabilityFlags::FILE_TRANSFER));
+        assert!(skew.warnings.iter().any(|w| w.contains("needs 1.3.0 or later")));
+        assert!(skew.warnings[0].contains("update the peer"));
+
+        let skew = table.assess(&local, "kizuna/1.4.2");
+        assert_eq!(skew.level, SkewLevel::Patch);
+        assert!(!skew.is_significant());
+    }
+
+    #[test]
+    fn test_unparseable_version_is_reported() {
+        let skew = CompatibilityTable::standard().assess(&local_version(), "someclient");
+        assert_eq!(skew.level, SkewLevel::Unknown);
+        assert!(skew.disabled.is_empty());
+        assert_eq!(parse_software_version("kizuna/v0.3.1"), Some(Version::new(0, 3, 1)));
+    }
+}
@@ -1,9 +1,12 @@
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
 use super::{PeerId, TransportError};
+use super::backpressure::Watermarks;
+use super::datagram::DatagramChannel;
 
 /// Trait for transport connections providing unified interface
 #[async_trait]
@@ -25,6 +28,29 @@ pub trait Connection: Send + Sync + std::fmt::Debug {
     
     /// Check if connection is still active
     fn is_connected(&self) -> bool;
+    
+    /// Bytes accepted by `write` but not yet handed to the network
+    fn buffered_bytes(&self) -> usize {
+        0
+    }
+    
+    /// Buffering thresholds applied to writes on this connection
+    fn watermarks(&self) -> Watermarks {
+        Watermarks::default()
+    }
+    
+    /// Wait until the write buffer has drained below the low watermark
+    ///
+    /// Producers of large payloads should await this before generating more data.
+    /// Connections that write straight to the socket are always ready.
+    async fn ready(&mut self) -> Result<(), TransportError> {
+        Ok(())
+    }
+    
+    /// Unreliable datagram channel to the same peer, if the protocol supports one
+    fn datagrams(&self) -> Option<Arc<dyn DatagramChannel>> {
+        None
+    }
 }
 
 /// Metadata and statistics about an active connection
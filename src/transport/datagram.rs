@@ -0,0 +1,66 @@
+// Unreliable datagram delivery
+//
+// Reliable ordered streams retransmit lost data and hold back everything queued behind
+// it, which is the wrong trade-off for small, latency-sensitive messages such as
+// clipboard change events or stream control signals where a late message is worth less
+// than a missing one. Connections that can carry unreliable, unordered datagrams (QUIC
+// DATAGRAM frames) expose them through a `DatagramChannel`, and subsystems pick a
+// `DeliveryMode` per message, falling back to their reliable stream when datagrams are
+// unavailable or the payload is too large.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::transport::TransportError;
+
+/// How a message should be delivered to a peer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryMode {
+    /// Reliable and ordered over a stream
+    #[default]
+    Reliable,
+    /// Best-effort datagram; may be dropped, duplicated or reordered
+    Unreliable,
+}
+
+impl DeliveryMode {
+    /// Whether a payload of `len` bytes should go out as a datagram
+    ///
+    /// `max_datagram_size` is the limit reported by the connection, or `None` when
+    /// datagrams are unavailable; anything that does not fit takes the reliable path.
+    pub fn use_datagram(&self, len: usize, max_datagram_size: Option<usize>) -> bool {
+        *self == DeliveryMode::Unreliable && max_datagram_size.is_some_and(|max| len <= max)
+    }
+}
+
+/// Unreliable, unordered message channel alongside a connection's byte stream
+///
+/// Each datagram is delivered whole or not at all. Handles are shared, so one task can
+/// receive while others send without borrowing the owning connection.
+#[async_trait]
+pub trait DatagramChannel: Send + Sync + std::fmt::Debug {
+    /// Largest payload currently accepted by `send_datagram`
+    ///
+    /// Returns `None` if the peer has not enabled datagram support. The limit can change
+    /// over the life of a connection as the path MTU is discovered.
+    fn max_datagram_size(&self) -> Option<usize>;
+
+    /// Send one datagram
+    async fn send_datagram(&self, data: &[u8]) -> Result<(), TransportError>;
+
+    /// Wait for the next datagram from the peer
+    async fn recv_datagram(&self) -> Result<Vec<u8>, TransportError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_use_datagram() {
+        assert!(!DeliveryMode::Reliable.use_datagram(10, Some(1200)));
+        assert!(DeliveryMode::Unreliable.use_datagram(1200, Some(1200)));
+        assert!(!DeliveryMode::Unreliable.use_datagram(1201, Some(1200)));
+        assert!(!DeliveryMode::Unreliable.use_datagram(10, None));
+    }
+}
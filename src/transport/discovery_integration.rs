@@ -33,6 +33,20 @@ pub struct TransportDiscoveryConfig {
     pub max_retry_attempts: u32,
     /// Delay between retry attempts
     pub retry_delay: Duration,
+    /// Look a peer up again through discovery when its cached address stops answering
+    #[serde(default = "default_re_resolve_stale_addresses")]
+    pub re_resolve_stale_addresses: bool,
+    /// How long the re-resolution lookup may take
+    #[serde(default = "default_re_resolve_timeout")]
+    pub re_resolve_timeout: Duration,
+}
+
+fn default_re_resolve_stale_addresses() -> bool {
+    true
+}
+
+fn default_re_resolve_timeout() -> Duration {
+    Duration::from_secs(5)
 }
 
 impl Default for TransportDiscoveryConfig {
@@ -59,6 +73,8 @@ impl Default for TransportDiscoveryConfig {
             retry_failed_connections: true,
             max_retry_attempts: 3,
             retry_delay: Duration::from_secs(5),
+            re_resolve_stale_addresses: default_re_resolve_stale_addresses(),
+            re_resolve_timeout: default_re_resolve_timeout(),
         }
     }
 }
@@ -97,6 +113,12 @@ pub enum TransportDiscoveryEvent {
         local_capabilities: TransportCapabilities,
         remote_capabilities: TransportCapabilities,
     },
+    /// Cached addresses stopped answering and discovery found the peer elsewhere
+    PeerReResolved {
+        peer_id: PeerId,
+        previous_addresses: Vec<SocketAddr>,
+        addresses: Vec<SocketAddr>,
+    },
     /// Peer lost (no longer discoverable)
     PeerLost {
         peer_id: PeerId,
@@ -230,7 +252,31 @@ impl TransportDiscoveryBridge {
             self.service_record_to_peer_address(service_record)
         };
         
-        let handle = self.transport.connect_to_peer(&peer_address).await?;
+        let handle = match self.transport.connect_to_peer(&peer_address).await {
+            Ok(handle) => handle,
+            Err(e) if self.config.re_resolve_stale_addresses && e.suggests_stale_address() => {
+                log::info!(
+                    "Connecting to {} at {:?} failed ({}), re-resolving through discovery",
+                    peer_id, peer_address.addresses, e
+                );
+                let fresh = self.re_resolve_peer(peer_id, &peer_address.addresses).await
+                    .map_err(|reason| TransportError::ConnectionFailed {
+                        reason: format!(
+                            "{} at {:?}: {}; re-resolution failed: {}",
+                            peer_id, peer_address.addresses, e, reason
+                        ),
+                    })?;
+                
+                self.transport.connect_to_peer(&fresh).await
+                    .map_err(|retry_error| TransportError::ConnectionFailed {
+                        reason: format!(
+                            "{} at {:?}: {}; re-resolved to {:?} but retry failed: {}",
+                            peer_id, peer_address.addresses, e, fresh.addresses, retry_error
+                        ),
+                    })?
+            }
+            Err(e) => return Err(e),
+        };
         
         // Store connection
         {
@@ -243,6 +289,41 @@ impl TransportDiscoveryBridge {
         Ok(handle)
     }
     
+    /// Run discovery again for a peer whose cached addresses stopped answering
+    ///
+    /// Updates the cached record when the peer shows up at a new address and
+    /// returns the address to retry, or why no retry is worthwhile.
+    async fn re_resolve_peer(&self, peer_id: &PeerId, stale: &[SocketAddr]) -> Result<PeerAddress, String> {
+        let peers = self.discovery.read().await
+            .discover_peers(self.config.re_resolve_timeout).await
+            .map_err(|e| format!("discovery error: {}", e))?;
+        
+        let service_record = peers.into_iter()
+            .find(|record| &record.peer_id == peer_id)
+            .ok_or_else(|| "peer was not found on the network".to_string())?;
+        
+        if service_record.addresses.is_empty()
+            || service_record.addresses.iter().all(|addr| stale.contains(addr))
+        {
+            return Err("discovery still reports the same address".to_string());
+        }
+        
+        log::debug!(
+            "Re-resolved {} from {:?} to {:?}",
+            peer_id, stale, service_record.addresses
+        );
+        let fresh = self.service_record_to_peer_address(&service_record);
+        self.discovered_peers.write().await.insert(peer_id.clone(), service_record);
+        
+        let _ = self.event_sender.send(TransportDiscoveryEvent::PeerReResolved {
+            peer_id: peer_id.clone(),
+            previous_addresses: stale.to_vec(),
+            addresses: fresh.addresses.clone(),
+        });
+        
+        Ok(fresh)
+    }
+    
     /// Get all discovered peers
     pub async fn get_discovered_peers(&self) -> Vec<ServiceRecord> {
         let peers = self.discovered_peers.read().await;
@@ -741,4 +822,24 @@ mod tests {
         assert_eq!(stats.connection_success_rate, 0.0);
         assert!(stats.auto_connect_enabled);
     }
+
+    #[test]
+    fn test_refused_connections_trigger_re_resolution() {
+        let refused = TransportError::Io(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        assert!(refused.suggests_stale_address());
+        assert!(!TransportError::InvalidPeerAddress.suggests_stale_address());
+        
+        let config: TransportDiscoveryConfig = serde_json::from_value(serde_json::json!({
+            "auto_connect": true,
+            "max_auto_connections": 3,
+            "auto_connect_timeout": { "secs": 30, "nanos": 0 },
+            "advertised_protocols": [],
+            "advertised_capabilities": TransportCapabilities::default(),
+            "enable_capability_exchange": true,
+            "retry_failed_connections": true,
+            "max_retry_attempts": 3,
+            "retry_delay": { "secs": 5, "nanos": 0 },
+        })).unwrap();
+        assert!(config.re_resolve_stale_addresses);
+    }
 }
\ No newline at end of file
@@ -73,6 +73,9 @@ pub enum TransportError {
     #[error("Protocol version mismatch: local={local}, remote={remote}")]
     ProtocolVersionMismatch { local: String, remote: String },
     
+    #[error("Incompatible peer {peer}: {reason}")]
+    IncompatiblePeer { peer: String, reason: String },
+    
     #[error("Network unreachable: {target}")]
     NetworkUnreachable { target: SocketAddr },
     
@@ -113,6 +116,28 @@ impl TransportError {
         }
     }
 
+    /// Check if this error suggests the peer moved to another address
+    ///
+    /// A refused or unreachable connection to a cached address is what a
+    /// peer whose DHCP lease changed looks like, so these are worth a fresh
+    /// discovery lookup before giving up.
+    pub fn suggests_stale_address(&self) -> bool {
+        match self {
+            TransportError::ConnectionFailed { .. } => true,
+            TransportError::ConnectionTimeout { .. } => true,
+            TransportError::NetworkUnreachable { .. } => true,
+            TransportError::Io(io_err) => matches!(
+                io_err.kind(),
+                std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::HostUnreachable
+                    | std::io::ErrorKind::NetworkUnreachable
+                    | std::io::ErrorKind::AddrNotAvailable
+            ),
+            _ => false,
+        }
+    }
+
     /// Get error severity level
     pub fn severity(&self) -> ErrorSeverity {
         match self {
@@ -127,6 +152,7 @@ impl TransportError {
             TransportError::AuthenticationFailed { .. } => ErrorSeverity::Error,
             TransportError::CertificateValidationFailed { .. } => ErrorSeverity::Error,
             TransportError::ProtocolVersionMismatch { .. } => ErrorSeverity::Error,
+            TransportError::IncompatiblePeer { .. } => ErrorSeverity::Error,
             TransportError::ConfigurationError { .. } => ErrorSeverity::Error,
             TransportError::SecurityError { .. } => ErrorSeverity::Critical,
             TransportError::ShutdownInProgress => ErrorSeverity::Info,
@@ -182,7 +208,8 @@ impl TransportError {
             
             TransportError::UnsupportedProtocol { .. } |
             TransportError::NegotiationTimeout |
-            TransportError::ProtocolVersionMismatch { .. } => ErrorCategory::Protocol,
+            TransportError::ProtocolVersionMismatch { .. } |
+            TransportError::IncompatiblePeer { .. } => ErrorCategory::Protocol,
             
             TransportError::NatTraversalFailed { .. } |
             TransportError::RelayFailed { .. } => ErrorCategory::Network,
@@ -421,6 +448,7 @@ impl Clone for TransportError {
             TransportError::BandwidthLimitExceeded { current_bps, limit_bps } => TransportError::BandwidthLimitExceeded { current_bps: *current_bps, limit_bps: *limit_bps },
             TransportError::ConnectionPoolExhausted { active, max } => TransportError::ConnectionPoolExhausted { active: *active, max: *max },
             TransportError::ProtocolVersionMismatch { local, remote } => TransportError::ProtocolVersionMismatch { local: local.clone(), remote: remote.clone() },
+            TransportError::IncompatiblePeer { peer, reason } => TransportError::IncompatiblePeer { peer: peer.clone(), reason: reason.clone() },
             TransportError::NetworkUnreachable { target } => TransportError::NetworkUnreachable { target: *target },
             TransportError::ShutdownInProgress => TransportError::ShutdownInProgress,
             TransportError::ConfigurationError { field, reason } => TransportError::ConfigurationError { field: field.clone(), reason: reason.clone() },
@@ -0,0 +1,513 @@
+// Protocol version and capability negotiation
+//
+// Immediately after a connection is established both peers send a `ProtocolHello`
+// frame and read the other side's. The exchange is symmetric, so the same code runs
+// on the dialing and accepting side. Peers with a different major protocol version,
+// or lacking a capability the local side requires, are refused with a descriptive
+// error; optional features are enabled only when both sides advertise them and
+// the peer's release is not ruled out for them by the compatibility table.
+// Each hello also carries the sender's wall-clock time, so both sides learn how
+// far apart their clocks are and can warn before time-based checks fail.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+use crate::security::clock::{self, ClockSkew};
+use crate::transport::compatibility::{local_version, CompatibilityTable};
+use crate::transport::wire::{
+    parse_frame, write_string, MessageKind, WireError, WireMessage, WireReader, WireVersion,
+    WIRE_VERSION_MAJOR, WIRE_VERSION_MINOR,
+};
+use crate::transport::{Connection, TransportError};
+
+/// Maximum size of a handshake frame
+const MAX_HANDSHAKE_LEN: usize = 4096;
+
+/// Optional protocol features a peer may support
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct CapabilityFlags(u64);
+
+impl CapabilityFlags {
+    pub const NONE: CapabilityFlags = CapabilityFlags(0);
+    pub const MULTIPLEXING: CapabilityFlags = CapabilityFlags(1 << 0);
+    pub const COMPRESSION: CapabilityFlags = CapabilityFlags(1 << 1);
+    pub const TRANSFER_RESUME: CapabilityFlags = CapabilityFlags(1 << 2);
+    pub const DATAGRAMS: CapabilityFlags = CapabilityFlags(1 << 3);
+    pub const CLIPBOARD_SYNC: CapabilityFlags = CapabilityFlags(1 << 4);
+    pub const FILE_TRANSFER: CapabilityFlags = CapabilityFlags(1 << 5);
+    pub const STREAMING: CapabilityFlags = CapabilityFlags(1 << 6);
+    pub const COMMAND_EXECUTION: CapabilityFlags = CapabilityFlags(1 << 7);
+
+    const NAMES: &'static [(CapabilityFlags, &'static str)] = &[
+        (Self::MULTIPLEXING, "multiplexing"),
+        (Self::COMPRESSION, "compression"),
+        (Self::TRANSFER_RESUME, "transfer-resume"),
+        (Self::DATAGRAMS, "datagrams"),
+        (Self::CLIPBOARD_SYNC, "clipboard-sync"),
+        (Self::FILE_TRANSFER, "file-transfer"),
+        (Self::STREAMING, "streaming"),
+        (Self::COMMAND_EXECUTION, "command-execution"),
+    ];
+
+    /// Create flags from raw bits; unknown bits from newer peers are preserved
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Raw bit representation
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    /// Whether every flag in `other` is set
+    pub const fn contains(&self, other: CapabilityFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether no flags are set
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Flags set in both
+    pub const fn intersection(&self, other: CapabilityFlags) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Flags set in either
+    pub const fn union(&self, other: CapabilityFlags) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Flags set in `self` but not in `other`
+    pub const fn difference(&self, other: CapabilityFlags) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Capabilities supported by this build
+    pub fn local() -> Self {
+        Self::MULTIPLEXING
+            .union(Self::COMPRESSION)
+            .union(Self::TRANSFER_RESUME)
+            .union(Self::CLIPBOARD_SYNC)
+            .union(Self::FILE_TRANSFER)
+            .union(Self::STREAMING)
+            .union(Self::COMMAND_EXECUTION)
+    }
+}
+
+impl std::ops::BitOr for CapabilityFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl fmt::Display for CapabilityFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<String> = Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name.to_string())
+            .collect();
+
+        let known = Self::NAMES.iter().fold(0, |acc, (flag, _)| acc | flag.0);
+        if self.0 & !known != 0 {
+            names.push(format!("unknown(0x{:x})", self.0 & !known));
+        }
+
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(","))
+        }
+    }
+}
+
+/// Hello message exchanged at connection time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolHello {
+    pub protocol_major: u8,
+    pub protocol_minor: u8,
+    /// Capabilities this peer supports
+    pub capabilities: CapabilityFlags,
+    /// Capabilities this peer refuses to operate without
+    pub required: CapabilityFlags,
+    /// Human-readable software version, e.g. "kizuna/0.1.0"
+    pub software_version: String,
+    /// Sender's Unix time in milliseconds; absent from older peers
+    #[serde(default)]
+    pub timestamp_ms: Option<u64>,
+}
+
+impl ProtocolHello {
+    /// Hello describing this build
+    pub fn local() -> Self {
+        Self {
+            protocol_major: WIRE_VERSION_MAJOR,
+            protocol_minor: WIRE_VERSION_MINOR,
+            capabilities: CapabilityFlags::local(),
+            required: CapabilityFlags::NONE,
+            software_version: format!("kizuna/{}", env!("CARGO_PKG_VERSION")),
+            timestamp_ms: Some(clock::unix_millis()),
+        }
+    }
+
+    /// Require a capability from the remote peer
+    pub fn require(mut self, capability: CapabilityFlags) -> Self {
+        self.capabilities = self.capabilities | capability;
+        self.required = self.required | capability;
+        self
+    }
+}
+
+impl Default for ProtocolHello {
+    fn default() -> Self {
+        Self::local()
+    }
+}
+
+impl WireMessage for ProtocolHello {
+    const KIND: MessageKind = MessageKind::Handshake;
+
+    fn encode_payload(&self, out: &mut Vec<u8>) -> Result<(), WireError> {
+        out.push(self.protocol_major);
+        out.push(self.protocol_minor);
+        out.extend_from_slice(&self.capabilities.bits().to_be_bytes());
+        out.extend_from_slice(&self.required.bits().to_be_bytes());
+        write_string(out, &self.software_version);
+        if let Some(timestamp_ms) = self.timestamp_ms {
+            out.extend_from_slice(&timestamp_ms.to_be_bytes());
+        }
+        Ok(())
+    }
+
+    fn decode_payload(payload: &[u8], _version: WireVersion) -> Result<Self, WireError> {
+        let mut reader = WireReader::new(payload);
+        Ok(Self {
+            protocol_major: reader.u8()?,
+            protocol_minor: reader.u8()?,
+            capabilities: CapabilityFlags::from_bits(reader.u64()?),
+            required: CapabilityFlags::from_bits(reader.u64()?),
+            software_version: reader.string(256)?,
+            timestamp_ms: if reader.remaining() >= 8 { Some(reader.u64()?) } else { None },
+        })
+    }
+}
+
+/// Outcome of a successful negotiation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegotiatedProtocol {
+    /// Protocol version both sides will speak
+    pub version_major: u8,
+    pub version_minor: u8,
+    /// Optional features enabled for this connection
+    pub capabilities: CapabilityFlags,
+    /// Software version reported by the remote peer
+    pub remote_software: String,
+    /// Version and clock skew problems to show the user
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// How far the peer's clock is from ours, if it sent its time
+    #[serde(default)]
+    pub clock_skew: Option<ClockSkew>,
+}
+
+impl NegotiatedProtocol {
+    /// Whether a feature may be used on this connection
+    pub fn supports(&self, capability: CapabilityFlags) -> bool {
+        self.capabilities.contains(capability)
+    }
+
+    /// Refuse an operation the peer cannot take part in
+    ///
+    /// The error names the release the peer needs, when known, so the user
+    /// can fix the skew instead of guessing.
+    pub fn ensure(&self, capability: CapabilityFlags) -> Result<(), TransportError> {
+        if self.supports(capability) {
+            return Ok(());
+        }
+
+        let reason = match CompatibilityTable::standard().introduced_in(capability) {
+            Some(introduced) => format!(
+                "{} is not available with {}; the peer needs kizuna {} or later",
+                capability, self.remote_software, introduced
+            ),
+            None => format!("{} is not available with {}", capability, self.remote_software),
+        };
+        Err(TransportError::IncompatiblePeer {
+            peer: self.remote_software.clone(),
+            reason,
+        })
+    }
+}
+
+/// Negotiate protocol parameters from both hellos
+pub fn negotiate(local: &ProtocolHello, remote: &ProtocolHello) -> Result<NegotiatedProtocol, TransportError> {
+    if local.protocol_major != remote.protocol_major {
+        return Err(TransportError::ProtocolVersionMismatch {
+            local: format!("{}.{}", local.protocol_major, local.protocol_minor),
+            remote: format!(
+                "{}.{} ({})",
+                remote.protocol_major, remote.protocol_minor, remote.software_version
+            ),
+        });
+    }
+
+    let missing_remote = local.required.difference(remote.capabilities);
+    if !missing_remote.is_empty() {
+        return Err(TransportError::ConnectionFailed {
+            reason: format!(
+                "Peer {} lacks required capabilities: {}",
+                remote.software_version, missing_remote
+            ),
+        });
+    }
+
+    let missing_local = remote.required.difference(local.capabilities);
+    if !missing_local.is_empty() {
+        return Err(TransportError::ConnectionFailed {
+            reason: format!(
+                "Peer {} requires capabilities this build lacks: {}",
+                remote.software_version, missing_local
+            ),
+        });
+    }
+
+    let skew = CompatibilityTable::standard().assess(&local_version(), &remote.software_version);
+    let unusable_required = local.required.intersection(skew.disabled);
+    if !unusable_required.is_empty() {
+        return Err(TransportError::IncompatiblePeer {
+            peer: remote.software_version.clone(),
+            reason: skew.warnings.join("; "),
+        });
+    }
+    if skew.is_significant() {
+        for warning in &skew.warnings {
+            log::warn!("{}", warning);
+        }
+    }
+    let mut warnings = if skew.is_significant() { skew.warnings } else { Vec::new() };
+
+    let clock_skew = local
+        .timestamp_ms
+        .zip(remote.timestamp_ms)
+        .map(|(local_ms, remote_ms)| ClockSkew::between(local_ms, remote_ms));
+    if let Some(clock_skew) = clock_skew.filter(ClockSkew::is_significant) {
+        let warning = format!(
+            "The clock of {} is {}; pairing and identity checks may fail until time sync is fixed",
+            remote.software_version, clock_skew
+        );
+        log::warn!("{}", warning);
+        warnings.push(warning);
+    }
+
+    Ok(NegotiatedProtocol {
+        version_major: local.protocol_major,
+        version_minor: local.protocol_minor.min(remote.protocol_minor),
+        capabilities: local
+            .capabilities
+            .intersection(remote.capabilities)
+            .difference(skew.disabled),
+        remote_software: remote.software_version.clone(),
+        warnings,
+        clock_skew,
+    })
+}
+
+/// Run the hello exchange over a freshly established connection
+pub async fn perform_handshake(
+    connection: &mut dyn Connection,
+    local: &ProtocolHello,
+    timeout: Duration,
+) -> Result<NegotiatedProtocol, TransportError> {
+    // Both hellos are sent at about the same moment, so the difference
+    // between their timestamps is the clock skew, give or take the latency
+    let local = &ProtocolHello {
+        timestamp_ms: Some(clock::unix_millis()),
+        ..local.clone()
+    };
+    let frame = local
+        .to_frame()
+        .map_err(|e| TransportError::Serialization(e.to_string()))?;
+
+    tokio::time::timeout(timeout, async {
+        connection.write(&frame).await?;
+        connection.flush().await?;
+
+        let remote = read_hello(connection).await?;
+        negotiate(local, &remote)
+    })
+    .await
+    .map_err(|_| TransportError::NegotiationTimeout)?
+}
+
+/// Read exactly one hello frame from the connection
+///
+/// Never reads past the end of the frame: the peer may send multiplexer
+/// frames right behind its hello, and those belong to the session driver.
+async fn read_hello(connection: &mut dyn Connection) -> Result<ProtocolHello, TransportError> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 512];
+
+    loop {
+        let needed = match parse_frame(&buffer, MAX_HANDSHAKE_LEN) {
+            Ok((frame, _)) => {
+                return ProtocolHello::from_frame(&frame).map_err(|e| match e {
+                    WireError::UnexpectedKind { actual, .. } => TransportError::ConnectionFailed {
+                        reason: format!("Peer did not start with a protocol handshake (got {:?})", actual),
+                    },
+                    other => TransportError::Serialization(other.to_string()),
+                });
+            }
+            Err(WireError::Incomplete { needed }) => needed,
+            Err(WireError::UnsupportedVersion { major, minor }) => {
+                return Err(TransportError::ProtocolVersionMismatch {
+                    local: format!("{}.{}", WIRE_VERSION_MAJOR, WIRE_VERSION_MINOR),
+                    remote: format!("{}.{}", major, minor),
+                });
+            }
+            Err(other) => return Err(TransportError::Serialization(other.to_string())),
+        };
+
+        let n = connection.read(&mut chunk[..needed.min(512)]).await?;
+        if n == 0 {
+            return Err(TransportError::ConnectionFailed {
+                reason: "Connection closed during protocol handshake".to_string(),
+            });
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MemoryConnection;
+
+    fn pair() -> (MemoryConnection, MemoryConnection) {
+        let a = "10.0.0.1:41000".parse().unwrap();
+        let b = "10.0.0.2:41000".parse().unwrap();
+        MemoryConnection::pair("a".to_string(), a, "b".to_string(), b)
+    }
+
+    #[test]
+    fn test_negotiate_intersects_capabilities() {
+        let local = ProtocolHello::local();
+        let mut remote = ProtocolHello::local();
+        remote.capabilities = CapabilityFlags::MULTIPLEXING | CapabilityFlags::DATAGRAMS;
+        remote.protocol_minor = WIRE_VERSION_MINOR + 3;
+
+        let negotiated = negotiate(&local, &remote).unwrap();
+        assert!(negotiated.supports(CapabilityFlags::MULTIPLEXING));
+        assert!(!negotiated.supports(CapabilityFlags::DATAGRAMS));
+        assert_eq!(negotiated.version_minor, WIRE_VERSION_MINOR);
+    }
+
+    #[test]
+    fn test_major_mismatch_refused() {
+        let local = ProtocolHello::local();
+        let mut remote = ProtocolHello::local();
+        remote.protocol_major += 1;
+
+        assert!(matches!(
+            negotiate(&local, &remote),
+            Err(TransportError::ProtocolVersionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_required_capability_refused() {
+        let local = ProtocolHello::local().require(CapabilityFlags::DATAGRAMS);
+        let remote = ProtocolHello::local();
+        let err = negotiate(&local, &remote).unwrap_err();
+        assert!(err.to_string().contains("datagrams"));
+    }
+
+    #[test]
+    fn test_old_release_loses_capability() {
+        let local = ProtocolHello::local();
+        let mut remote = ProtocolHello::local();
+        remote.software_version = "kizuna/0.0.9".to_string();
+
+        let negotiated = negotiate(&local, &remote).unwrap();
+        assert!(!negotiated.supports(CapabilityFlags::STREAMING));
+        assert!(!negotiated.warnings.is_empty());
+        let err = negotiated.ensure(CapabilityFlags::STREAMING).unwrap_err();
+        assert!(matches!(err, TransportError::IncompatiblePeer { .. }));
+        assert!(err.to_string().contains("needs kizuna 0.1.0 or later"));
+
+        let local = local.require(CapabilityFlags::FILE_TRANSFER);
+        assert!(matches!(
+            negotiate(&local, &remote),
+            Err(TransportError::IncompatiblePeer { .. })
+        ));
+    }
+
+    #[test]
+    fn test_clock_skew_warned() {
+        let local = ProtocolHello::local();
+        let mut remote = ProtocolHello::local();
+        remote.timestamp_ms = local.timestamp_ms.map(|ms| ms + 5 * 60_000);
+
+        let negotiated = negotiate(&local, &remote).unwrap();
+        assert_eq!(negotiated.clock_skew.unwrap().offset_ms, 5 * 60_000);
+        assert!(negotiated.warnings.iter().any(|w| w.contains("5m 0s ahead")));
+
+        // Older peers do not send their time
+        remote.timestamp_ms = None;
+        let encoded = remote.to_frame().unwrap();
+        let (frame, _) = parse_frame(&encoded, MAX_HANDSHAKE_LEN).unwrap();
+        assert_eq!(ProtocolHello::from_frame(&frame).unwrap(), remote);
+        assert_eq!(negotiate(&local, &remote).unwrap().clock_skew, None);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_over_connection() {
+        let (mut a, mut b) = pair();
+        let hello = ProtocolHello::local();
+        let hello_b = hello.clone();
+
+        let remote = tokio::spawn(async move {
+            perform_handshake(&mut b, &hello_b, Duration::from_secs(1)).await
+        });
+        let local = perform_handshake(&mut a, &hello, Duration::from_secs(1)).await.unwrap();
+
+        assert_eq!(local.capabilities, CapabilityFlags::local());
+        let mut remote = remote.await.unwrap().unwrap();
+        assert!(remote.clock_skew.unwrap().magnitude() < Duration::from_secs(1));
+        remote.clock_skew = local.clock_skew;
+        assert_eq!(remote, local);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_leaves_following_frames_unread() {
+        let (mut a, mut b) = pair();
+        let hello = ProtocolHello::local();
+        let follow_up = crate::transport::wire::encode_frame(MessageKind::RelayMessage, 0, b"after hello").unwrap();
+        let mut burst = hello.to_frame().unwrap();
+        burst.extend_from_slice(&follow_up);
+        b.write(&burst).await.unwrap();
+
+        perform_handshake(&mut a, &hello, Duration::from_secs(1)).await.unwrap();
+
+        let mut rest = vec![0u8; follow_up.len()];
+        let mut read = 0;
+        while read < rest.len() {
+            read += a.read(&mut rest[read..]).await.unwrap();
+        }
+        assert_eq!(rest, follow_up);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_rejects_non_handshake_frame() {
+        let (mut a, mut b) = pair();
+        let garbage = crate::transport::wire::encode_frame(MessageKind::RelayMessage, 0, b"{}").unwrap();
+        b.write(&garbage).await.unwrap();
+
+        let result = perform_handshake(&mut a, &ProtocolHello::local(), Duration::from_secs(1)).await;
+        assert!(matches!(result, Err(TransportError::ConnectionFailed { .. })));
+    }
+}
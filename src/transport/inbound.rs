@@ -0,0 +1,161 @@
+// Inbound stream dispatch
+//
+// Peers open one stream per exchange on a shared session, tagged with the
+// channel of the subsystem they want. An InboundRouter serves every session a
+// ConnectionProvider establishes, dialed or adopted: it accepts the streams the
+// peer opens and hands each to the handler registered for its channel. Streams
+// on channels nothing handles are reset.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::transport::mux::{Multiplexer, MuxRole, MuxStream};
+use crate::transport::protocols::tcp::TcpListener;
+use crate::transport::provider::{ConnectionProvider, SessionObserver};
+use crate::transport::{PeerId, TransportError};
+
+/// Pause before accepting again after the listener refused a connection
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Serves the streams peers open on one channel
+#[async_trait]
+pub trait StreamHandler: Send + Sync {
+    /// Take over a stream `peer_id` opened
+    ///
+    /// Exchanges that take a while should be spawned, since the session's
+    /// next stream is not accepted until this returns.
+    async fn handle_stream(&self, peer_id: PeerId, stream: MuxStream) -> Result<(), String>;
+}
+
+/// Hands inbound streams to the handler for their channel
+#[derive(Clone, Default)]
+pub struct InboundRouter {
+    handlers: Arc<RwLock<HashMap<String, Arc<dyn StreamHandler>>>>,
+}
+
+impl InboundRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send streams on `channel` to `handler`, replacing any earlier handler
+    pub fn register(&self, channel: &str, handler: Arc<dyn StreamHandler>) {
+        self.handlers.write().unwrap().insert(channel.to_string(), handler);
+    }
+
+    /// Serve every session `provider` establishes from now on
+    pub fn attach(&self, provider: &ConnectionProvider) {
+        provider.observe_sessions(Arc::new(self.clone()));
+    }
+
+    /// Accept the streams the peer opens on `session` until it ends
+    pub fn serve(&self, peer_id: PeerId, session: Arc<Multiplexer>) -> JoinHandle<()> {
+        let router = self.clone();
+        tokio::spawn(async move {
+            while let Some(stream) = session.accept_stream().await {
+                router.dispatch(peer_id.clone(), stream).await;
+            }
+        })
+    }
+
+    /// Hand one stream to the handler for its channel
+    pub async fn dispatch(&self, peer_id: PeerId, stream: MuxStream) {
+        let handler = self.handlers.read().unwrap().get(stream.channel()).cloned();
+        let Some(handler) = handler else {
+            log::debug!("No handler for {} stream from {}", stream.channel(), peer_id);
+            stream.reset("channel not served");
+            return;
+        };
+
+        let channel = stream.channel().to_string();
+        if let Err(e) = handler.handle_stream(peer_id.clone(), stream).await {
+            log::warn!("{} stream from {} failed: {}", channel, peer_id, e);
+        }
+    }
+}
+
+impl SessionObserver for InboundRouter {
+    fn session_established(&self, peer_id: &PeerId, session: &Arc<Multiplexer>, _role: MuxRole) {
+        self.serve(peer_id.clone(), Arc::clone(session));
+    }
+}
+
+/// Accept connections on `listener` and register each with `provider`
+///
+/// Runs until the listener shuts down. Handshakes run concurrently, so a
+/// slow peer does not hold up the next connection.
+pub fn accept_connections(listener: TcpListener, provider: Arc<ConnectionProvider>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while !listener.is_shutting_down() {
+            let (connection, remote_addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    if !matches!(e, TransportError::ResourceLimitExceeded { .. }) {
+                        log::warn!("Failed to accept connection: {}", e);
+                    }
+                    tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+
+            let provider = Arc::clone(&provider);
+            tokio::spawn(async move {
+                if let Err(e) = provider.adopt(Box::new(connection)).await {
+                    log::warn!("Failed to set up session with {}: {}", remote_addr, e);
+                }
+            });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MemoryConnection, MemoryTransport, VirtualNetwork};
+    use crate::transport::{MuxConfig, ProviderConfig, CHANNEL_PING, CHANNEL_REVOCATIONS};
+    use tokio::sync::mpsc;
+
+    /// Forwards each stream's first message
+    struct Recorder(mpsc::UnboundedSender<(PeerId, Vec<u8>)>);
+
+    #[async_trait]
+    impl StreamHandler for Recorder {
+        async fn handle_stream(&self, peer_id: PeerId, stream: MuxStream) -> Result<(), String> {
+            let message = stream.recv().await.ok_or("stream closed early")?;
+            self.0.send((peer_id, message)).map_err(|e| e.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_adopted_sessions_are_served_by_channel() {
+        let (a, b) = MemoryConnection::pair(
+            "alice".to_string(),
+            "10.0.0.1:41000".parse().unwrap(),
+            "bob".to_string(),
+            "10.0.0.2:41000".parse().unwrap(),
+        );
+        let provider = ConnectionProvider::new(
+            Arc::new(MemoryTransport::new("bob", VirtualNetwork::new())),
+            ProviderConfig::default(),
+        );
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let router = InboundRouter::new();
+        router.register(CHANNEL_PING, Arc::new(Recorder(tx)));
+        router.attach(&provider);
+
+        provider.adopt(Box::new(b)).await.unwrap();
+        let alice = Multiplexer::new(Box::new(a), MuxRole::Initiator, MuxConfig::default());
+
+        // Reset as soon as it arrives, which may beat this send
+        let unserved = alice.open_stream(CHANNEL_REVOCATIONS).unwrap();
+        let _ = unserved.send(b"ignored").await;
+        let served = alice.open_stream(CHANNEL_PING).unwrap();
+        served.send(b"hello").await.unwrap();
+
+        assert_eq!(rx.recv().await.unwrap(), ("alice".to_string(), b"hello".to_vec()));
+        assert!(unserved.recv().await.is_none());
+    }
+}
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -7,7 +7,9 @@ use async_trait::async_trait;
 use futures::future;
 
 use super::{
-    Connection, ConnectionInfo, PeerAddress, PeerId, TransportCapabilities, TransportError,
+    protocol_scores, run_benchmark, BenchmarkConfig, BenchmarkFailure, BenchmarkReport, Connection,
+    ConnectionInfo, ListenPorts, NatReport, NatType, PeerAddress, PeerId, PortProtocol, ProtocolBenchmark,
+    TransportCapabilities, TransportError, select_port,
 };
 
 /// Trait for transport protocol implementations
@@ -74,6 +76,8 @@ pub struct ProtocolNegotiation {
     pub negotiation_start_time: Option<Instant>,
     pub retry_count: u32,
     pub max_retries: u32,
+    /// Score bonuses from benchmarks against this peer, keyed by protocol
+    pub measured_scores: HashMap<String, f32>,
 }
 
 impl ProtocolNegotiation {
@@ -88,6 +92,7 @@ impl ProtocolNegotiation {
             negotiation_start_time: None,
             retry_count: 0,
             max_retries: 3,
+            measured_scores: HashMap::new(),
         }
     }
 
@@ -101,6 +106,11 @@ impl ProtocolNegotiation {
         self
     }
 
+    pub fn with_measured_scores(mut self, scores: HashMap<String, f32>) -> Self {
+        self.measured_scores = scores;
+        self
+    }
+
     pub fn start_negotiation(&mut self) {
         self.negotiation_start_time = Some(Instant::now());
         self.retry_count = 0;
@@ -148,6 +158,11 @@ impl ProtocolNegotiation {
             return self.select_protocol_for_conditions(conditions);
         }
 
+        // Without conditions, a benchmarked protocol beats the static order
+        if !self.measured_scores.is_empty() {
+            return self.select_protocol_for_conditions(&NetworkConditions::default());
+        }
+
         // Return the highest priority common protocol
         self.fallback_protocols.first().cloned()
     }
@@ -221,6 +236,17 @@ impl ProtocolNegotiation {
             }
         }
 
+        // Hole punching rarely works through symmetric NATs, so prefer
+        // protocols that can go through a relay
+        if conditions.direct_connection_unlikely() {
+            match protocol {
+                "websocket" => score += 25.0,
+                "webrtc" => score += 5.0, // TURN can still relay it
+                "tcp" | "quic" => score -= 20.0,
+                _ => {}
+            }
+        }
+
         // Adjust for battery constraints
         if conditions.battery_constrained {
             match protocol {
@@ -232,6 +258,9 @@ impl ProtocolNegotiation {
             }
         }
 
+        // What was actually measured with this peer outweighs the heuristics
+        score += self.measured_scores.get(protocol).copied().unwrap_or(0.0);
+
         score.max(0.0)
     }
 
@@ -465,6 +494,22 @@ pub struct NetworkConditions {
     pub nat_traversal_needed: bool,
     pub mobile_network: bool,
     pub battery_constrained: bool,
+    /// NAT type from the last STUN classification, if one ran
+    pub nat_type: Option<NatType>,
+}
+
+impl NetworkConditions {
+    /// Apply a NAT classification to the conditions
+    pub fn with_nat_report(mut self, report: &NatReport) -> Self {
+        self.nat_type = Some(report.nat_type);
+        self.nat_traversal_needed = report.nat_type != NatType::Open;
+        self
+    }
+
+    /// Whether direct peer-to-peer connections are unlikely to get through
+    pub fn direct_connection_unlikely(&self) -> bool {
+        matches!(self.nat_type, Some(NatType::Symmetric))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -497,6 +542,7 @@ impl Default for NetworkConditions {
             nat_traversal_needed: false,
             mobile_network: false,
             battery_constrained: false,
+            nat_type: None,
         }
     }
 }
@@ -577,21 +623,27 @@ pub struct ConnectionManager {
     idle_timeout: Duration,
     cleanup_interval: Duration,
     protocol_preferences: HashMap<String, u8>,
+    benchmark_scores: Arc<RwLock<HashMap<PeerId, HashMap<String, f32>>>>,
 }
 
 impl ConnectionManager {
     /// Create a new connection manager
     pub fn new() -> Self {
+        let profile = crate::platform::ResourceProfile::active();
         Self {
             transports: Vec::new(),
             active_connections: Arc::new(RwLock::new(HashMap::new())),
-            connection_pool: Arc::new(RwLock::new(ConnectionPool::new(5, Duration::from_secs(300)))),
-            max_concurrent_connections: 100,
-            max_connections_per_peer: 5,
+            connection_pool: Arc::new(RwLock::new(ConnectionPool::new(
+                profile.max_connections_per_peer,
+                Duration::from_secs(300),
+            ))),
+            max_concurrent_connections: profile.max_concurrent_connections,
+            max_connections_per_peer: profile.max_connections_per_peer,
             connection_timeout: Duration::from_secs(30),
             idle_timeout: Duration::from_secs(300),
             cleanup_interval: Duration::from_secs(60),
             protocol_preferences: HashMap::new(),
+            benchmark_scores: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -797,7 +849,8 @@ impl ConnectionManager {
         conditions: Option<&NetworkConditions>,
     ) -> Result<ProtocolNegotiationResult, TransportError> {
         let local_protocols: Vec<String> = self.get_available_protocols();
-        let mut negotiation = ProtocolNegotiation::new(&local_protocols);
+        let mut negotiation = ProtocolNegotiation::new(&local_protocols)
+            .with_measured_scores(self.benchmark_scores_for(&peer.address.peer_id).await);
         
         if let Some(conditions) = conditions {
             negotiation = negotiation.with_network_conditions(conditions.clone());
@@ -852,6 +905,67 @@ impl ConnectionManager {
         }
     }
 
+    /// Record benchmark results so later negotiations with the peer favour
+    /// the protocol that measured best
+    pub async fn record_benchmarks(&self, peer_id: &PeerId, results: &[ProtocolBenchmark]) {
+        let scores = protocol_scores(results);
+        let mut benchmark_scores = self.benchmark_scores.write().await;
+        if scores.is_empty() {
+            benchmark_scores.remove(peer_id);
+        } else {
+            benchmark_scores.insert(peer_id.clone(), scores);
+        }
+    }
+
+    /// Benchmark every transport this side and the peer have in common
+    ///
+    /// Each protocol gets its own connection, closed when its run finishes.
+    /// The results are recorded for later negotiations with the peer.
+    pub async fn benchmark_peer(&self, peer: &PeerAddress, config: &BenchmarkConfig) -> BenchmarkReport {
+        let mut report = BenchmarkReport::new(peer.peer_id.clone(), config.clone());
+
+        for protocol in self.get_available_protocols() {
+            if !peer.transport_hints.contains(&protocol) {
+                continue;
+            }
+            let Some(transport) = self.get_transport(&protocol) else {
+                continue;
+            };
+
+            let result = match tokio::time::timeout(self.connection_timeout, transport.connect(peer)).await {
+                Ok(Ok(mut connection)) => {
+                    let result = run_benchmark(connection.as_mut(), &protocol, config).await;
+                    let _ = connection.close().await;
+                    result
+                }
+                Ok(Err(e)) => Err(e),
+                Err(_) => Err(TransportError::ConnectionTimeout {
+                    timeout: self.connection_timeout,
+                }),
+            };
+
+            match result {
+                Ok(measured) => report.results.push(measured),
+                Err(e) => report.failures.push(BenchmarkFailure {
+                    protocol,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        self.record_benchmarks(&peer.peer_id, &report.results).await;
+        report
+    }
+
+    /// Load previously stored benchmark scores, e.g. from a `BenchmarkStore`
+    pub async fn load_benchmark_scores(&self, scores: HashMap<PeerId, HashMap<String, f32>>) {
+        *self.benchmark_scores.write().await = scores;
+    }
+
+    async fn benchmark_scores_for(&self, peer_id: &PeerId) -> HashMap<String, f32> {
+        self.benchmark_scores.read().await.get(peer_id).cloned().unwrap_or_default()
+    }
+
     /// Quick connectivity test for a transport (lightweight check)
     async fn can_transport_connect(&self, transport: &dyn Transport, peer_addr: &PeerAddress) -> bool {
         // For now, just check if the transport is available and the peer supports it
@@ -887,7 +1001,8 @@ impl ConnectionManager {
             local_protocols = prioritized;
         }
 
-        let mut negotiation = ProtocolNegotiation::new(&local_protocols);
+        let mut negotiation = ProtocolNegotiation::new(&local_protocols)
+            .with_measured_scores(self.benchmark_scores_for(&peer.address.peer_id).await);
         
         if let Some(conditions) = conditions {
             negotiation = negotiation.with_network_conditions(conditions.clone());
@@ -1005,6 +1120,9 @@ impl ConnectionManager {
         if conditions.nat_traversal_needed && caps.nat_traversal {
             score += 30.0;
         }
+        if conditions.direct_connection_unlikely() && transport.protocol_name() == "websocket" {
+            score += 20.0; // Relay-capable when hole punching will not work
+        }
 
         score
     }
@@ -1117,8 +1235,11 @@ impl ConnectionManager {
         }
     }
 
-    /// Clean up idle and disconnected connections
-    pub async fn cleanup_connections(&self) {
+    /// Clean up idle and disconnected connections, returning how many
+    /// active connections were closed
+    pub async fn cleanup_connections(&self) -> usize {
+        let mut closed = 0;
+        
         // Clean up active connections
         {
             let mut active = self.active_connections.write().await;
@@ -1141,6 +1262,7 @@ impl ConnectionManager {
                 }
                 
                 // Remove disconnected connections in reverse order
+                closed += indices_to_remove.len();
                 for &i in indices_to_remove.iter().rev() {
                     connections.remove(i);
                 }
@@ -1161,6 +1283,8 @@ impl ConnectionManager {
             let mut pool = self.connection_pool.write().await;
             pool.cleanup_idle_connections().await;
         }
+        
+        closed
     }
 
     /// Start automatic cleanup task (requires Arc<ConnectionManager>)
@@ -1227,18 +1351,18 @@ impl ConnectionManager {
 
     /// Register all available transport protocols with default configurations
     pub async fn register_all_transports(&mut self) -> Result<(), TransportError> {
-        use crate::transport::protocols::{
-            tcp::TcpTransport,
-            quic::QuicTransport,
-            webrtc::WebRtcTransport,
-            websocket::WebSocketTransport,
-        };
+        use crate::transport::protocols::{tcp::TcpTransport, websocket::WebSocketTransport};
+        #[cfg(feature = "transport-quic")]
+        use crate::transport::protocols::quic::QuicTransport;
+        #[cfg(feature = "transport-webrtc")]
+        use crate::transport::protocols::webrtc::WebRtcTransport;
 
         // Register TCP transport
         let tcp_transport = TcpTransport::new();
         self.add_transport(Box::new(tcp_transport));
 
         // Register QUIC transport
+        #[cfg(feature = "transport-quic")]
         match QuicTransport::new() {
             Ok(quic_transport) => {
                 self.add_transport(Box::new(quic_transport));
@@ -1250,6 +1374,7 @@ impl ConnectionManager {
         }
 
         // Register WebRTC transport
+        #[cfg(feature = "transport-webrtc")]
         match WebRtcTransport::new() {
             Ok(webrtc_transport) => {
                 self.add_transport(Box::new(webrtc_transport));
@@ -1271,16 +1396,15 @@ impl ConnectionManager {
     pub async fn register_transports_with_config(
         &mut self,
         tcp_config: Option<crate::transport::protocols::tcp::TcpConfig>,
-        quic_config: Option<crate::transport::protocols::quic::QuicConfig>,
-        webrtc_config: Option<crate::transport::protocols::webrtc::WebRtcConfig>,
+        #[cfg(feature = "transport-quic")] quic_config: Option<crate::transport::protocols::quic::QuicConfig>,
+        #[cfg(feature = "transport-webrtc")] webrtc_config: Option<crate::transport::protocols::webrtc::WebRtcConfig>,
         websocket_config: Option<crate::transport::protocols::websocket::WebSocketConfig>,
     ) -> Result<(), TransportError> {
-        use crate::transport::protocols::{
-            tcp::TcpTransport,
-            quic::QuicTransport,
-            webrtc::WebRtcTransport,
-            websocket::WebSocketTransport,
-        };
+        use crate::transport::protocols::{tcp::TcpTransport, websocket::WebSocketTransport};
+        #[cfg(feature = "transport-quic")]
+        use crate::transport::protocols::quic::QuicTransport;
+        #[cfg(feature = "transport-webrtc")]
+        use crate::transport::protocols::webrtc::WebRtcTransport;
 
         // Register TCP transport
         if let Some(config) = tcp_config {
@@ -1289,6 +1413,7 @@ impl ConnectionManager {
         }
 
         // Register QUIC transport
+        #[cfg(feature = "transport-quic")]
         if let Some(config) = quic_config {
             match QuicTransport::with_config(config) {
                 Ok(quic_transport) => {
@@ -1301,6 +1426,7 @@ impl ConnectionManager {
         }
 
         // Register WebRTC transport
+        #[cfg(feature = "transport-webrtc")]
         if let Some(config) = webrtc_config {
             match WebRtcTransport::with_config(config) {
                 Ok(webrtc_transport) => {
@@ -1526,6 +1652,7 @@ impl ConnectionManager {
             nat_traversal_needed: false,
             mobile_network: false,
             battery_constrained: false,
+            nat_type: None,
         };
 
         match self.negotiate_protocol_with_conditions(&peer_info, &conditions).await {
@@ -1717,6 +1844,36 @@ impl ConnectionManager {
         
         Ok(())
     }
+
+    /// Start each available transport on its own configured port
+    ///
+    /// Taken ports fall back as configured in `ports`. Returns the address
+    /// each transport listens on, so it can be advertised and mapped.
+    pub async fn start_listening_on(&self, ip: IpAddr, ports: &ListenPorts) -> Result<Vec<(String, SocketAddr)>, TransportError> {
+        let mut listening = Vec::new();
+        
+        for transport in self.transports.iter().filter(|t| t.is_available()) {
+            let protocol = transport.protocol_name();
+            let config = ports.for_transport(protocol);
+            let bind_addr = match select_port(ip, &config, PortProtocol::for_transport(protocol)) {
+                Ok(addr) => addr,
+                Err(e) => {
+                    eprintln!("No port for {} listener: {}", protocol, e);
+                    continue;
+                }
+            };
+            match transport.listen(&bind_addr).await {
+                Ok(()) => listening.push((protocol.to_string(), bind_addr)),
+                Err(e) => eprintln!("Failed to start listener for {}: {}", protocol, e),
+            }
+        }
+        
+        if listening.is_empty() {
+            return Err(TransportError::TransportNotAvailable);
+        }
+        
+        Ok(listening)
+    }
 }
 
 impl Default for ConnectionManager {
@@ -1734,8 +1891,9 @@ mod tests {
     #[tokio::test]
     async fn test_connection_manager_creation() {
         let manager = ConnectionManager::new();
-        assert_eq!(manager.max_concurrent_connections, 100);
-        assert_eq!(manager.max_connections_per_peer, 5);
+        let profile = crate::platform::ResourceProfile::active();
+        assert_eq!(manager.max_concurrent_connections, profile.max_concurrent_connections);
+        assert_eq!(manager.max_connections_per_peer, profile.max_connections_per_peer);
         
         let stats = manager.get_connection_stats().await;
         assert_eq!(stats.total_active_connections, 0);
@@ -1830,6 +1988,7 @@ mod tests {
             nat_traversal_needed: false,
             mobile_network: false,
             battery_constrained: false,
+            nat_type: None,
         };
 
         let result = manager.negotiate_protocol_with_conditions(&peer_info, &conditions).await;
@@ -1863,7 +2022,10 @@ mod tests {
         
         assert_eq!(stats.total_active_connections, 0);
         assert_eq!(stats.pooled_connections, 0);
-        assert_eq!(stats.max_concurrent_connections, 100);
+        assert_eq!(
+            stats.max_concurrent_connections,
+            crate::platform::ResourceProfile::active().max_concurrent_connections
+        );
         assert!(stats.protocol_usage.is_empty());
         assert!(stats.connections_by_state.is_empty());
     }
@@ -1964,6 +2126,51 @@ mod tests {
         assert!(!conditions.battery_constrained);
     }
 
+    #[test]
+    fn test_symmetric_nat_prefers_relay() {
+        let mut negotiation = ProtocolNegotiation::new(&["tcp".to_string(), "quic".to_string(), "websocket".to_string()]);
+        negotiation.add_peer_capabilities(&["tcp".to_string(), "quic".to_string(), "websocket".to_string()]);
+
+        let report = NatReport {
+            nat_type: NatType::Symmetric,
+            local_address: None,
+            mapped_address: None,
+            stun_server: None,
+            mapping_tested: true,
+            filtering_tested: false,
+            detected_at: 0,
+        };
+        let conditions = NetworkConditions::default().with_nat_report(&report);
+        assert!(conditions.nat_traversal_needed);
+
+        let negotiation = negotiation.with_network_conditions(conditions);
+        assert_eq!(negotiation.select_best_protocol(), Some("websocket".to_string()));
+    }
+
+    #[test]
+    fn test_measured_scores_override_static_order() {
+        let protocols = ["quic".to_string(), "tcp".to_string()];
+        let mut negotiation = ProtocolNegotiation::new(&protocols);
+        negotiation.add_peer_capabilities(&protocols);
+        assert_eq!(negotiation.select_best_protocol(), Some("quic".to_string()));
+
+        let measured = |protocol: &str, rtt_ms: f64, throughput: f64| ProtocolBenchmark {
+            protocol: protocol.to_string(),
+            rtt_ms,
+            min_rtt_ms: rtt_ms,
+            max_rtt_ms: rtt_ms,
+            jitter_ms: 0.0,
+            ping_loss: 0.0,
+            throughput_bytes_per_sec: throughput,
+            bytes_transferred: 0,
+            measured_at: 0,
+        };
+        let scores = protocol_scores(&[measured("quic", 40.0, 1_000_000.0), measured("tcp", 5.0, 10_000_000.0)]);
+
+        let negotiation = negotiation.with_measured_scores(scores);
+        assert_eq!(negotiation.select_best_protocol(), Some("tcp".to_string()));
+    }
+
     #[tokio::test]
     async fn test_connection_manager_config_defaults() {
         let config = ConnectionManagerConfig::default();
@@ -2019,6 +2226,7 @@ mod tests {
             nat_traversal_needed: false,
             mobile_network: false,
             battery_constrained: false,
+            nat_type: None,
         };
 
         let result = manager.negotiate_protocol_enhanced(&peer_info, Some(&conditions)).await;
@@ -2095,6 +2303,17 @@ impl Default for ConnectionManagerConfig {
     }
 }
 
+impl ConnectionManagerConfig {
+    /// Default settings with the connection limits of a resource profile
+    pub fn for_profile(profile: &crate::platform::ResourceProfile) -> Self {
+        Self {
+            max_concurrent_connections: profile.max_concurrent_connections,
+            max_connections_per_peer: profile.max_connections_per_peer,
+            ..Self::default()
+        }
+    }
+}
+
 /// Statistics about connection manager state
 #[derive(Debug, Clone)]
 pub struct ConnectionStats {
@@ -15,6 +15,18 @@ pub mod routing;
 pub mod api;
 pub mod discovery_integration;
 pub mod security_integration;
+pub mod wire;
+pub mod handshake;
+pub mod compatibility;
+pub mod mux;
+pub mod provider;
+pub mod inbound;
+pub mod backpressure;
+pub mod datagram;
+pub mod benchmark;
+pub mod ports;
+pub mod port_mapping;
+pub mod proxy;
 
 #[cfg(doc)]
 pub mod examples;
@@ -28,6 +40,7 @@ pub use manager::{
     ConcurrentConnectionResult, DetailedConnectionStats, AvailableTransport
 };
 pub use connection::{Connection, ConnectionInfo};
+pub use datagram::{DatagramChannel, DeliveryMode};
 pub use error::{TransportError, ErrorSeverity, RetryStrategy, ErrorCategory, ErrorContext, ContextualError};
 pub use error_handler::{ErrorHandler, ErrorHandlerConfig, ErrorStats, CircuitBreaker, CircuitBreakerState, ErrorHandlerHealth};
 pub use logging::{TransportLogger, LoggingConfig, LogLevel, LogCategory, LogEntry, ConnectionEvent as LogConnectionEvent, SecurityEvent as LogSecurityEvent};
@@ -40,9 +53,11 @@ pub use integrated_system::{
     IntegratedTransportSystem, IntegratedSystemConfig, SystemState, SystemHealthReport,
     SystemRecommendation, SystemStatus
 };
-pub use nat_traversal::{NatTraversal, NatType, NatTraversalConfig, HolePunchMessage, HolePunchMessageType, HolePunchPayload};
+pub use nat_traversal::{NatTraversal, NatType, NatReport, NatTraversalConfig, resolve_stun_servers, HolePunchMessage, HolePunchMessageType, HolePunchPayload};
 pub use protocols::tcp::{TcpTransport, TcpConnection, TcpListener, TcpConfig, TcpServer, TcpServerStats};
+#[cfg(feature = "transport-quic")]
 pub use protocols::quic::{QuicTransport, QuicConnection, QuicConfig, QuicConnectionStats, CongestionControl};
+#[cfg(feature = "transport-webrtc")]
 pub use protocols::webrtc::{WebRtcTransport, WebRtcConnection, WebRtcConfig, IceServerConfig, SignalingHandler, SignalingMessage, DefaultSignalingHandler};
 pub use protocols::websocket::{
     WebSocketTransport, WebSocketConnection, WebSocketListener, WebSocketConfig, 
@@ -72,6 +87,45 @@ pub use discovery_integration::{
 pub use security_integration::{
     TransportSecurityHooks, SecureConnection
 };
+pub use wire::{
+    WireError, WireMessage, WireVersion, WireReader, Frame as WireFrame, MessageKind,
+    encode_frame, parse_frame,
+};
+
+pub use handshake::{
+    CapabilityFlags, ProtocolHello, NegotiatedProtocol, negotiate, perform_handshake,
+};
+
+pub use compatibility::{
+    CompatibilityTable, SkewLevel, VersionSkew, parse_software_version,
+};
+
+pub use mux::{
+    Multiplexer, MuxStream, MuxConfig, MuxRole, CHANNEL_CONTROL, CHANNEL_CLIPBOARD,
+    CHANNEL_FILE_TRANSFER, CHANNEL_NOTIFICATIONS, CHANNEL_MESSAGES, CHANNEL_BROWSE,
+    CHANNEL_REVOCATIONS, CHANNEL_WIPE, CHANNEL_PING, CHANNEL_FORWARD,
+};
+
+pub use backpressure::{BackpressureGauge, Watermarks};
+
+pub use benchmark::{
+    BenchmarkConfig, BenchmarkFailure, BenchmarkReport, BenchmarkStore, ProtocolBenchmark,
+    protocol_scores, run_benchmark, serve_benchmark, MAX_BENCHMARK_PAYLOAD,
+};
+
+pub use provider::{
+    ConnectionProvider, ProviderConfig, ProviderStats, PeerDialer, SessionObserver,
+};
+
+pub use inbound::{InboundRouter, StreamHandler, accept_connections};
+
+pub use ports::{
+    ListenPorts, PortConfig, PortProtocol, PortRange, select_port, DEFAULT_SERVICE_PORT,
+    DEFAULT_WEBSOCKET_PORT,
+};
+
+pub use port_mapping::{MappingMethod, PortMapper, PortMapping, PortMappingConfig};
+pub use proxy::{ProxyConfig, ProxyKind, Socks5UdpSocket};
 
 /// Unique identifier for a peer in the network
 pub type PeerId = String;
@@ -0,0 +1,1154 @@
+// Stream multiplexing
+//
+// Runs many logical streams over a single `Connection` so clipboard sync, control
+// messages, file transfer and notifications can share one session per peer instead
+// of each paying for its own connection setup and NAT mapping.
+//
+// Mux frames travel inside `MessageKind::StreamFrame` wire frames:
+//
+//   stream_id: u32 BE | op: u8 | body
+//
+// Streams opened by the dialing side use odd IDs and streams opened by the accepting
+// side use even IDs, so both ends can open streams without coordination.
+//
+// Flow control is per stream. A new stream starts with no send credit; the opening
+// side grants its receive window with a window update right after the open, the
+// accepting side as soon as it registers the stream, and each receiver grants more
+// credit as the application consumes data. A sender that runs out of credit waits,
+// so one slow consumer cannot stall the other streams or force the session to buffer
+// without bound. A peer that sends past its credit has the stream reset.
+//
+// When the connection offers a datagram channel, a session can also carry small
+// unreliable messages outside any stream. Each datagram is a `MessageKind::Datagram`
+// wire frame holding the channel label and payload, and is routed to whoever
+// subscribed to that channel; datagrams for channels nobody listens to are dropped.
+//
+// A side that is shutting down sends a goodbye (a go-away frame on the reserved stream
+// ID 0) before closing the connection, so the peer can tell a deliberate departure from
+// a network failure and fail its streams with the stated reason instead of retrying.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex, Notify};
+use tokio::task::JoinHandle;
+
+use crate::transport::wire::{
+    parse_frame, write_string, Frame, MessageKind, WireError, WireMessage, WireReader,
+    WireVersion, FRAME_HEADER_LEN,
+};
+use crate::transport::backpressure::{BackpressureGauge, Watermarks};
+use crate::transport::datagram::DatagramChannel;
+use crate::transport::{Connection, PeerId, TransportError};
+
+/// Channel label for control messages
+pub const CHANNEL_CONTROL: &str = "control";
+/// Channel label for clipboard sync
+pub const CHANNEL_CLIPBOARD: &str = "clipboard";
+/// Channel label for file transfer
+pub const CHANNEL_FILE_TRANSFER: &str = "file-transfer";
+/// Channel label for notifications
+pub const CHANNEL_NOTIFICATIONS: &str = "notifications";
+/// Channel label for peer-to-peer text messages
+pub const CHANNEL_MESSAGES: &str = "messages";
+/// Channel label for browsing a peer's shared folders
+pub const CHANNEL_BROWSE: &str = "browse";
+/// Channel label for propagating device revocations
+pub const CHANNEL_REVOCATIONS: &str = "revocations";
+/// Channel label for remote wipe requests between a user's devices
+pub const CHANNEL_WIPE: &str = "wipe";
+/// Channel label for authenticated liveness pings
+pub const CHANNEL_PING: &str = "ping";
+/// Channel label for connections forwarded to a peer's local services
+pub const CHANNEL_FORWARD: &str = "forward";
+
+/// Default per-stream receive window
+pub const DEFAULT_STREAM_WINDOW: u32 = 256 * 1024;
+
+/// Default maximum data carried by a single mux frame
+pub const DEFAULT_MAX_FRAME_DATA: usize = 32 * 1024;
+
+/// Maximum length of a channel label or reset reason
+const MAX_LABEL_LEN: usize = 256;
+
+/// Received datagrams buffered per channel before newer ones are dropped
+const DATAGRAM_QUEUE_LEN: usize = 64;
+
+/// Configuration for a multiplexed session
+#[derive(Debug, Clone)]
+pub struct MuxConfig {
+    /// Bytes a peer may send on a stream before waiting for a window update
+    pub initial_window: u32,
+    /// Maximum data carried by a single frame
+    pub max_frame_data: usize,
+    /// Maximum concurrently open streams, counting both directions
+    pub max_streams: usize,
+    /// Thresholds for data queued to the connection across all streams
+    pub watermarks: Watermarks,
+}
+
+impl Default for MuxConfig {
+    fn default() -> Self {
+        Self {
+            initial_window: DEFAULT_STREAM_WINDOW,
+            max_frame_data: DEFAULT_MAX_FRAME_DATA,
+            max_streams: 256,
+            watermarks: Watermarks::default(),
+        }
+    }
+}
+
+/// Which side of the underlying connection this session runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MuxRole {
+    /// The side that dialed the connection; opens odd stream IDs
+    Initiator,
+    /// The side that accepted the connection; opens even stream IDs
+    Responder,
+}
+
+impl MuxRole {
+    fn first_stream_id(&self) -> u32 {
+        match self {
+            MuxRole::Initiator => 1,
+            MuxRole::Responder => 2,
+        }
+    }
+
+    fn owns(&self, stream_id: u32) -> bool {
+        stream_id % 2 == self.first_stream_id() % 2
+    }
+}
+
+/// Operation carried by a mux frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MuxOp {
+    Open { channel: String },
+    Data(Vec<u8>),
+    WindowUpdate(u32),
+    Close,
+    Reset(String),
+    /// Session-level goodbye; only valid on stream ID 0
+    GoAway(String),
+}
+
+/// A single mux frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MuxFrame {
+    pub stream_id: u32,
+    pub op: MuxOp,
+}
+
+impl WireMessage for MuxFrame {
+    const KIND: MessageKind = MessageKind::StreamFrame;
+
+    fn encode_payload(&self, out: &mut Vec<u8>) -> Result<(), WireError> {
+        out.extend_from_slice(&self.stream_id.to_be_bytes());
+        match &self.op {
+            MuxOp::Open { channel } => {
+                out.push(0);
+                write_string(out, channel);
+            }
+            MuxOp::Data(data) => {
+                out.push(1);
+                out.extend_from_slice(data);
+            }
+            MuxOp::WindowUpdate(increment) => {
+                out.push(2);
+                out.extend_from_slice(&increment.to_be_bytes());
+            }
+            MuxOp::Close => out.push(3),
+            MuxOp::Reset(reason) => {
+                out.push(4);
+                write_string(out, reason);
+            }
+            MuxOp::GoAway(reason) => {
+                out.push(5);
+                write_string(out, reason);
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_payload(payload: &[u8], _version: WireVersion) -> Result<Self, WireError> {
+        let mut reader = WireReader::new(payload);
+        let stream_id = reader.u32()?;
+        let op = match reader.u8()? {
+            0 => MuxOp::Open { channel: reader.string(MAX_LABEL_LEN)? },
+            1 => MuxOp::Data(reader.bytes(reader.remaining())?.to_vec()),
+            2 => MuxOp::WindowUpdate(reader.u32()?),
+            3 => MuxOp::Close,
+            4 => MuxOp::Reset(reader.string(MAX_LABEL_LEN)?),
+            5 => MuxOp::GoAway(reader.string(MAX_LABEL_LEN)?),
+            other => return Err(WireError::Malformed(format!("Unknown mux op {}", other))),
+        };
+
+        let session_level = matches!(op, MuxOp::GoAway(_));
+        if (stream_id == 0) != session_level {
+            return Err(WireError::Malformed("Stream ID 0 is reserved for session frames".to_string()));
+        }
+
+        Ok(Self { stream_id, op })
+    }
+}
+
+/// An unreliable message addressed to a channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MuxDatagram {
+    pub channel: String,
+    pub data: Vec<u8>,
+}
+
+impl MuxDatagram {
+    /// Bytes a datagram adds around its payload
+    fn overhead(channel: &str) -> usize {
+        FRAME_HEADER_LEN + 4 + channel.len()
+    }
+}
+
+impl WireMessage for MuxDatagram {
+    const KIND: MessageKind = MessageKind::Datagram;
+
+    fn encode_payload(&self, out: &mut Vec<u8>) -> Result<(), WireError> {
+        write_string(out, &self.channel);
+        out.extend_from_slice(&self.data);
+        Ok(())
+    }
+
+    fn decode_payload(payload: &[u8], _version: WireVersion) -> Result<Self, WireError> {
+        let mut reader = WireReader::new(payload);
+        let channel = reader.string(MAX_LABEL_LEN)?;
+        let data = reader.bytes(reader.remaining())?.to_vec();
+        Ok(Self { channel, data })
+    }
+}
+
+/// Flow control and lifecycle state of one stream
+#[derive(Debug, Default)]
+struct StreamState {
+    send_credit: u64,
+    recv_remaining: u64,
+    local_closed: bool,
+    remote_closed: bool,
+    reset: Option<String>,
+}
+
+#[derive(Debug)]
+struct StreamShared {
+    state: StdMutex<StreamState>,
+    notify: Notify,
+}
+
+#[derive(Debug)]
+struct StreamEntry {
+    shared: Arc<StreamShared>,
+    incoming: Option<mpsc::UnboundedSender<Vec<u8>>>,
+}
+
+/// Messages from session handles to the driver task
+#[derive(Debug)]
+enum DriverCommand {
+    Frame(MuxFrame),
+    Shutdown,
+}
+
+#[derive(Debug)]
+struct MuxInner {
+    role: MuxRole,
+    config: MuxConfig,
+    next_stream_id: AtomicU32,
+    streams: StdMutex<HashMap<u32, StreamEntry>>,
+    commands: mpsc::UnboundedSender<DriverCommand>,
+    closed: AtomicBool,
+    /// Stream data queued for the driver but not yet written
+    queued: BackpressureGauge,
+    /// Unreliable channel of the underlying connection, if it has one
+    datagrams: Option<Arc<dyn DatagramChannel>>,
+    /// Subscribers for incoming datagrams by channel label
+    datagram_routes: StdMutex<HashMap<String, mpsc::Sender<Vec<u8>>>>,
+    datagram_task: StdMutex<Option<JoinHandle<()>>>,
+    /// Reason given by the peer in its goodbye, if it sent one
+    remote_goodbye: StdMutex<Option<String>>,
+    /// When anything last arrived from the peer
+    last_received: StdMutex<Instant>,
+}
+
+impl MuxInner {
+    fn send_frame(&self, stream_id: u32, op: MuxOp) -> Result<(), TransportError> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(TransportError::ShutdownInProgress);
+        }
+        let data_len = match &op {
+            MuxOp::Data(data) => data.len(),
+            _ => 0,
+        };
+        self.commands
+            .send(DriverCommand::Frame(MuxFrame { stream_id, op }))
+            .map_err(|_| TransportError::ShutdownInProgress)?;
+        self.queued.add(data_len);
+        Ok(())
+    }
+
+    fn register(&self, stream_id: u32, send_credit: u64) -> Result<(Arc<StreamShared>, mpsc::UnboundedReceiver<Vec<u8>>), TransportError> {
+        let mut streams = self.streams.lock().unwrap();
+        if streams.len() >= self.config.max_streams {
+            return Err(TransportError::ResourceLimitExceeded {
+                resource: format!("multiplexed streams (max {})", self.config.max_streams),
+            });
+        }
+        if streams.contains_key(&stream_id) {
+            return Err(TransportError::ConnectionExists);
+        }
+
+        let shared = Arc::new(StreamShared {
+            state: StdMutex::new(StreamState {
+                send_credit,
+                recv_remaining: self.config.initial_window as u64,
+                ..Default::default()
+            }),
+            notify: Notify::new(),
+        });
+        let (tx, rx) = mpsc::unbounded_channel();
+        streams.insert(stream_id, StreamEntry {
+            shared: Arc::clone(&shared),
+            incoming: Some(tx),
+        });
+
+        Ok((shared, rx))
+    }
+
+    /// Drop a stream from the table once both directions are finished
+    fn remove_if_finished(&self, stream_id: u32) {
+        let mut streams = self.streams.lock().unwrap();
+        let finished = streams
+            .get(&stream_id)
+            .map(|entry| {
+                let state = entry.shared.state.lock().unwrap();
+                state.reset.is_some() || (state.local_closed && state.remote_closed)
+            })
+            .unwrap_or(false);
+        if finished {
+            streams.remove(&stream_id);
+        }
+    }
+
+    fn reset_stream(&self, stream_id: u32, reason: &str) {
+        if let Some(entry) = self.streams.lock().unwrap().remove(&stream_id) {
+            entry.shared.state.lock().unwrap().reset = Some(reason.to_string());
+            entry.shared.notify.notify_waiters();
+        }
+        let _ = self.send_frame(stream_id, MuxOp::Reset(reason.to_string()));
+    }
+
+    /// Apply a frame received from the remote peer
+    fn handle_frame(self: &Arc<Self>, frame: MuxFrame, accept_tx: &mpsc::UnboundedSender<MuxStream>) {
+        let stream_id = frame.stream_id;
+        match frame.op {
+            MuxOp::Open { channel } => {
+                if self.role.owns(stream_id) {
+                    let _ = self.send_frame(stream_id, MuxOp::Reset("Stream ID parity violation".to_string()));
+                    return;
+                }
+                match self.register(stream_id, 0) {
+                    Ok((shared, incoming)) => {
+                        let stream = MuxStream::new(stream_id, channel, shared, Arc::clone(self), incoming);
+                        let _ = self.send_frame(stream_id, MuxOp::WindowUpdate(self.config.initial_window));
+                        if accept_tx.send(stream).is_err() {
+                            self.reset_stream(stream_id, "Session is not accepting streams");
+                        }
+                    }
+                    Err(e) => {
+                        let _ = self.send_frame(stream_id, MuxOp::Reset(e.to_string()));
+                    }
+                }
+            }
+            MuxOp::Data(data) => {
+                let violated = {
+                    let streams = self.streams.lock().unwrap();
+                    let Some(entry) = streams.get(&stream_id) else {
+                        return;
+                    };
+                    let mut state = entry.shared.state.lock().unwrap();
+                    if data.len() as u64 > state.recv_remaining {
+                        true
+                    } else {
+                        state.recv_remaining -= data.len() as u64;
+                        if let Some(tx) = &entry.incoming {
+                            let _ = tx.send(data);
+                        }
+                        false
+                    }
+                };
+                if violated {
+                    self.reset_stream(stream_id, "Flow control window exceeded");
+                }
+            }
+            MuxOp::WindowUpdate(increment) => {
+                if let Some(entry) = self.streams.lock().unwrap().get(&stream_id) {
+                    entry.shared.state.lock().unwrap().send_credit += increment as u64;
+                    entry.shared.notify.notify_waiters();
+                }
+            }
+            MuxOp::Close => {
+                if let Some(entry) = self.streams.lock().unwrap().get_mut(&stream_id) {
+                    entry.incoming = None;
+                    entry.shared.state.lock().unwrap().remote_closed = true;
+                }
+                self.remove_if_finished(stream_id);
+            }
+            MuxOp::Reset(reason) => {
+                if let Some(entry) = self.streams.lock().unwrap().remove(&stream_id) {
+                    entry.shared.state.lock().unwrap().reset = Some(reason);
+                    entry.shared.notify.notify_waiters();
+                }
+            }
+            MuxOp::GoAway(reason) => {
+                *self.remote_goodbye.lock().unwrap() = Some(reason);
+                let _ = self.commands.send(DriverCommand::Shutdown);
+            }
+        }
+    }
+
+    /// Hand an incoming datagram to the subscriber for its channel
+    fn route_datagram(&self, bytes: &[u8]) {
+        let datagram = match parse_frame(bytes, bytes.len()) {
+            Ok((frame, _)) if frame.kind == MessageKind::Datagram => MuxDatagram::from_frame(&frame),
+            _ => return,
+        };
+        let Ok(datagram) = datagram else {
+            return;
+        };
+
+        let mut routes = self.datagram_routes.lock().unwrap();
+        if let Some(tx) = routes.get(&datagram.channel)
+            && let Err(mpsc::error::TrySendError::Closed(_)) = tx.try_send(datagram.data)
+        {
+            routes.remove(&datagram.channel);
+        }
+    }
+
+    /// Tear down every stream after the session ends
+    fn shutdown(&self, reason: &str) {
+        self.closed.store(true, Ordering::SeqCst);
+        // Queued data will never be written; wake any sender waiting on backpressure
+        self.queued.release(usize::MAX);
+        if let Some(task) = self.datagram_task.lock().unwrap().take() {
+            task.abort();
+        }
+        self.datagram_routes.lock().unwrap().clear();
+        let streams: Vec<StreamEntry> = self.streams.lock().unwrap().drain().map(|(_, e)| e).collect();
+        for entry in streams {
+            let mut state = entry.shared.state.lock().unwrap();
+            if state.reset.is_none() {
+                state.reset = Some(reason.to_string());
+            }
+            drop(state);
+            entry.shared.notify.notify_waiters();
+        }
+    }
+}
+
+/// A multiplexed session over one connection
+#[derive(Debug)]
+pub struct Multiplexer {
+    peer_id: PeerId,
+    inner: Arc<MuxInner>,
+    accept_rx: Mutex<mpsc::UnboundedReceiver<MuxStream>>,
+}
+
+impl Multiplexer {
+    /// Start a session over `connection`
+    ///
+    /// The connection is owned by a background task for the lifetime of the session;
+    /// this must be called from within a Tokio runtime.
+    pub fn new(connection: Box<dyn Connection>, role: MuxRole, config: MuxConfig) -> Self {
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        let (accept_tx, accept_rx) = mpsc::unbounded_channel();
+        let peer_id = connection.info().peer_id;
+        let queued = BackpressureGauge::new(config.watermarks);
+        let datagrams = connection.datagrams();
+
+        let inner = Arc::new(MuxInner {
+            role,
+            next_stream_id: AtomicU32::new(role.first_stream_id()),
+            config,
+            streams: StdMutex::new(HashMap::new()),
+            commands: commands_tx,
+            closed: AtomicBool::new(false),
+            queued,
+            datagrams: datagrams.clone(),
+            datagram_routes: StdMutex::new(HashMap::new()),
+            datagram_task: StdMutex::new(None),
+            remote_goodbye: StdMutex::new(None),
+            last_received: StdMutex::new(Instant::now()),
+        });
+
+        if let Some(channel) = datagrams {
+            let task = tokio::spawn(run_datagram_reader(Arc::clone(&inner), channel));
+            *inner.datagram_task.lock().unwrap() = Some(task);
+        }
+        tokio::spawn(run_driver(Arc::clone(&inner), connection, commands_rx, accept_tx));
+
+        Self {
+            peer_id,
+            inner,
+            accept_rx: Mutex::new(accept_rx),
+        }
+    }
+
+    /// Peer at the other end of the session
+    pub fn peer_id(&self) -> &PeerId {
+        &self.peer_id
+    }
+
+    /// Open a new stream labelled with `channel`
+    pub fn open_stream(&self, channel: &str) -> Result<MuxStream, TransportError> {
+        if channel.len() > MAX_LABEL_LEN {
+            return Err(TransportError::Configuration(format!(
+                "Channel label exceeds {} bytes",
+                MAX_LABEL_LEN
+            )));
+        }
+
+        let stream_id = self.inner.next_stream_id.fetch_add(2, Ordering::SeqCst);
+        let (shared, incoming) = self.inner.register(stream_id, 0)?;
+        let opened = self
+            .inner
+            .send_frame(stream_id, MuxOp::Open { channel: channel.to_string() })
+            .and_then(|()| self.inner.send_frame(stream_id, MuxOp::WindowUpdate(self.inner.config.initial_window)));
+        if let Err(e) = opened {
+            self.inner.streams.lock().unwrap().remove(&stream_id);
+            return Err(e);
+        }
+
+        Ok(MuxStream::new(stream_id, channel.to_string(), shared, Arc::clone(&self.inner), incoming))
+    }
+
+    /// Wait for the next stream opened by the remote peer
+    ///
+    /// Returns `None` once the session has ended.
+    pub async fn accept_stream(&self) -> Option<MuxStream> {
+        self.accept_rx.lock().await.recv().await
+    }
+
+    /// Largest payload [`send_datagram`](Self::send_datagram) accepts on `channel`
+    ///
+    /// Returns `None` if the connection cannot carry datagrams.
+    pub fn max_datagram_payload(&self, channel: &str) -> Option<usize> {
+        let max = self.inner.datagrams.as_ref()?.max_datagram_size()?;
+        max.checked_sub(MuxDatagram::overhead(channel))
+    }
+
+    /// Send an unreliable message to the subscriber of `channel` on the peer
+    ///
+    /// Delivery is best effort: the datagram may be lost, and is silently dropped by
+    /// the peer if nothing is subscribed to `channel`.
+    pub async fn send_datagram(&self, channel: &str, data: &[u8]) -> Result<(), TransportError> {
+        if self.is_closed() {
+            return Err(TransportError::ShutdownInProgress);
+        }
+        let datagrams = self.inner.datagrams.as_ref().ok_or_else(|| TransportError::UnsupportedProtocol {
+            protocol: "datagrams".to_string(),
+        })?;
+        if channel.len() > MAX_LABEL_LEN {
+            return Err(TransportError::Configuration(format!(
+                "Channel label exceeds {} bytes",
+                MAX_LABEL_LEN
+            )));
+        }
+
+        let max = self.max_datagram_payload(channel).unwrap_or(0);
+        if data.len() > max {
+            return Err(TransportError::ResourceLimitExceeded {
+                resource: format!("datagram payload ({} > {} bytes)", data.len(), max),
+            });
+        }
+
+        let frame = MuxDatagram {
+            channel: channel.to_string(),
+            data: data.to_vec(),
+        }
+        .to_frame()
+        .map_err(|e| TransportError::Serialization(e.to_string()))?;
+        datagrams.send_datagram(&frame).await
+    }
+
+    /// Receive datagrams sent to `channel`
+    ///
+    /// Replaces any earlier subscriber for the channel. Returns `None` if the
+    /// connection cannot carry datagrams.
+    pub fn subscribe_datagrams(&self, channel: &str) -> Option<mpsc::Receiver<Vec<u8>>> {
+        self.inner.datagrams.as_ref()?;
+        let (tx, rx) = mpsc::channel(DATAGRAM_QUEUE_LEN);
+        self.inner.datagram_routes.lock().unwrap().insert(channel.to_string(), tx);
+        Some(rx)
+    }
+
+    /// Number of streams currently open
+    pub fn stream_count(&self) -> usize {
+        self.inner.streams.lock().unwrap().len()
+    }
+
+    /// Stream data queued for the connection but not yet written
+    pub fn buffered_bytes(&self) -> usize {
+        self.inner.queued.buffered()
+    }
+
+    /// Time since anything arrived from the peer
+    ///
+    /// A session that has heard nothing for long, open streams or not, is
+    /// most likely talking to a peer that went away without closing it.
+    pub fn idle_time(&self) -> Duration {
+        self.inner.last_received.lock().unwrap().elapsed()
+    }
+
+    /// Whether the session has ended
+    pub fn is_closed(&self) -> bool {
+        self.inner.closed.load(Ordering::SeqCst)
+    }
+
+    /// Reason the peer gave when it ended the session, if it said goodbye
+    pub fn remote_goodbye(&self) -> Option<String> {
+        self.inner.remote_goodbye.lock().unwrap().clone()
+    }
+
+    /// Tell the peer why the session is ending, then close it
+    ///
+    /// Frames already queued, including stream data, are written before the goodbye.
+    pub fn goodbye(&self, reason: &str) -> Result<(), TransportError> {
+        let mut reason = reason.to_string();
+        if reason.len() > MAX_LABEL_LEN {
+            let mut end = MAX_LABEL_LEN;
+            while !reason.is_char_boundary(end) {
+                end -= 1;
+            }
+            reason.truncate(end);
+        }
+        let result = self.inner.send_frame(0, MuxOp::GoAway(reason));
+        self.close();
+        result
+    }
+
+    /// End the session and close the underlying connection
+    pub fn close(&self) {
+        let _ = self.inner.commands.send(DriverCommand::Shutdown);
+    }
+}
+
+impl Drop for Multiplexer {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Maximum size of a message accepted by [`MuxStream::recv_message`] by default
+pub const DEFAULT_MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// Receive side of a stream
+#[derive(Debug)]
+struct RecvHalf {
+    incoming: mpsc::UnboundedReceiver<Vec<u8>>,
+    pending: Vec<u8>,
+    unacked: u32,
+}
+
+/// One logical stream within a [`Multiplexer`] session
+///
+/// All methods take `&self`, so a stream can be shared behind an `Arc` between a
+/// task that sends and a task that receives.
+#[derive(Debug)]
+pub struct MuxStream {
+    stream_id: u32,
+    channel: String,
+    shared: Arc<StreamShared>,
+    inner: Arc<MuxInner>,
+    recv: Mutex<RecvHalf>,
+    send_lock: Mutex<()>,
+}
+
+impl MuxStream {
+    fn new(
+        stream_id: u32,
+        channel: String,
+        shared: Arc<StreamShared>,
+        inner: Arc<MuxInner>,
+        incoming: mpsc::UnboundedReceiver<Vec<u8>>,
+    ) -> Self {
+        Self {
+            stream_id,
+            channel,
+            shared,
+            inner,
+            recv: Mutex::new(RecvHalf {
+                incoming,
+                pending: Vec::new(),
+                unacked: 0,
+            }),
+            send_lock: Mutex::new(()),
+        }
+    }
+
+    /// Stream ID within the session
+    pub fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    /// Channel label given when the stream was opened
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Bytes that may be sent right now without waiting for the peer
+    pub fn send_credit(&self) -> u64 {
+        self.shared.state.lock().unwrap().send_credit
+    }
+
+    /// Whether data can still be sent on this stream
+    pub fn is_open(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.reset.is_none() && !state.local_closed && !self.inner.closed.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the session's write queue has drained below its low watermark
+    pub async fn ready(&self) -> Result<(), TransportError> {
+        self.inner.queued.ready().await;
+        if self.inner.closed.load(Ordering::SeqCst) {
+            return Err(TransportError::ShutdownInProgress);
+        }
+        Ok(())
+    }
+
+    /// Send data, waiting for flow control credit and write queue space as needed
+    pub async fn send(&self, data: &[u8]) -> Result<(), TransportError> {
+        let _guard = self.send_lock.lock().await;
+        self.send_unlocked(data).await
+    }
+
+    /// Send a length-prefixed message that `recv_message` returns whole
+    pub async fn send_message(&self, message: &[u8]) -> Result<(), TransportError> {
+        let len = u32::try_from(message.len()).map_err(|_| TransportError::ResourceLimitExceeded {
+            resource: format!("message of {} bytes", message.len()),
+        })?;
+
+        let _guard = self.send_lock.lock().await;
+        self.send_unlocked(&len.to_be_bytes()).await?;
+        self.send_unlocked(message).await
+    }
+
+    /// Receive the next chunk of data
+    ///
+    /// Returns `None` once the peer has closed the stream and all data was read.
+    pub async fn recv(&self) -> Option<Vec<u8>> {
+        let mut half = self.recv.lock().await;
+        if !half.pending.is_empty() {
+            return Some(std::mem::take(&mut half.pending));
+        }
+        self.next_chunk(&mut half).await
+    }
+
+    /// Receive the next message sent with `send_message`
+    ///
+    /// Returns `Ok(None)` if the peer closed the stream between messages.
+    pub async fn recv_message(&self, max_len: usize) -> Result<Option<Vec<u8>>, TransportError> {
+        let mut half = self.recv.lock().await;
+
+        let mut expected: Option<usize> = None;
+        loop {
+            if expected.is_none() && half.pending.len() >= 4 {
+                let len = u32::from_be_bytes([half.pending[0], half.pending[1], half.pending[2], half.pending[3]]) as usize;
+                if len > max_len {
+                    drop(half);
+                    self.reset("Message exceeds size limit");
+                    return Err(TransportError::ResourceLimitExceeded {
+                        resource: format!("message of {} bytes (max {})", len, max_len),
+                    });
+                }
+                half.pending.drain(..4);
+                expected = Some(len);
+            }
+
+            if let Some(len) = expected && half.pending.len() >= len {
+                let rest = half.pending.split_off(len);
+                return Ok(Some(std::mem::replace(&mut half.pending, rest)));
+            }
+
+            match self.next_chunk(&mut half).await {
+                Some(chunk) => half.pending.extend_from_slice(&chunk),
+                None if half.pending.is_empty() && expected.is_none() => return Ok(None),
+                None => {
+                    return Err(TransportError::ConnectionFailed {
+                        reason: format!("Stream {} closed mid-message", self.stream_id),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Close the sending half of the stream
+    pub fn close(&self) -> Result<(), TransportError> {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            if state.local_closed || state.reset.is_some() {
+                return Ok(());
+            }
+            state.local_closed = true;
+        }
+        self.shared.notify.notify_waiters();
+        let result = self.inner.send_frame(self.stream_id, MuxOp::Close);
+        self.inner.remove_if_finished(self.stream_id);
+        result
+    }
+
+    /// Abort the stream in both directions
+    pub fn reset(&self, reason: &str) {
+        self.inner.reset_stream(self.stream_id, reason);
+    }
+
+    async fn send_unlocked(&self, data: &[u8]) -> Result<(), TransportError> {
+        let mut offset = 0;
+        while offset < data.len() {
+            let want = (data.len() - offset).min(self.inner.config.max_frame_data);
+            self.ready().await?;
+            let granted = self.reserve_credit(want).await?;
+            self.inner
+                .send_frame(self.stream_id, MuxOp::Data(data[offset..offset + granted].to_vec()))?;
+            offset += granted;
+        }
+        Ok(())
+    }
+
+    async fn next_chunk(&self, half: &mut RecvHalf) -> Option<Vec<u8>> {
+        let data = half.incoming.recv().await?;
+        half.unacked = half.unacked.saturating_add(data.len() as u32);
+        if half.unacked >= self.inner.config.initial_window / 2 {
+            let increment = std::mem::take(&mut half.unacked);
+            self.shared.state.lock().unwrap().recv_remaining += increment as u64;
+            let _ = self.inner.send_frame(self.stream_id, MuxOp::WindowUpdate(increment));
+        }
+        Some(data)
+    }
+
+    async fn reserve_credit(&self, want: usize) -> Result<usize, TransportError> {
+        loop {
+            let notified = self.shared.notify.notified();
+            {
+                let mut state = self.shared.state.lock().unwrap();
+                if let Some(reason) = &state.reset {
+                    return Err(TransportError::ConnectionFailed {
+                        reason: format!("Stream {} reset: {}", self.stream_id, reason),
+                    });
+                }
+                if state.local_closed {
+                    return Err(TransportError::ConnectionFailed {
+                        reason: format!("Stream {} is closed", self.stream_id),
+                    });
+                }
+                if state.send_credit > 0 {
+                    let granted = (want as u64).min(state.send_credit);
+                    state.send_credit -= granted;
+                    return Ok(granted as usize);
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for MuxStream {
+    fn drop(&mut self) {
+        // Nobody is left to read, so a peer still sending is told to stop; data
+        // already queued on either side is still delivered
+        let remote_open = {
+            let state = self.shared.state.lock().unwrap();
+            state.reset.is_none() && !state.remote_closed
+        };
+        if remote_open {
+            self.reset("Stream dropped");
+        } else {
+            let _ = self.close();
+        }
+    }
+}
+
+/// Background task owning the connection
+async fn run_driver(
+    inner: Arc<MuxInner>,
+    mut connection: Box<dyn Connection>,
+    mut commands: mpsc::UnboundedReceiver<DriverCommand>,
+    accept_tx: mpsc::UnboundedSender<MuxStream>,
+) {
+    let mut buffer = Vec::new();
+    let mut chunk = vec![0u8; 16 * 1024];
+    let max_payload = inner.config.max_frame_data + MAX_LABEL_LEN + 16;
+
+    let result: Result<(), TransportError> = loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(DriverCommand::Frame(frame)) => {
+                    let result = write_frame(connection.as_mut(), &frame).await;
+                    if let MuxOp::Data(data) = &frame.op {
+                        inner.queued.release(data.len());
+                    }
+                    if let Err(e) = result {
+                        break Err(e);
+                    }
+                }
+                Some(DriverCommand::Shutdown) | None => break Ok(()),
+            },
+            read = connection.read(&mut chunk) => match read {
+                Ok(0) => break Ok(()),
+                Ok(n) => {
+                    *inner.last_received.lock().unwrap() = Instant::now();
+                    buffer.extend_from_slice(&chunk[..n]);
+                    if let Err(e) = process_buffer(&inner, &mut buffer, max_payload, &accept_tx) {
+                        break Err(e);
+                    }
+                }
+                Err(e) => break Err(e),
+            },
+        }
+    };
+
+    let goodbye = inner.remote_goodbye.lock().unwrap().clone();
+    let reason = match (&result, goodbye) {
+        (_, Some(goodbye)) => format!("Peer closed session: {}", goodbye),
+        (Ok(()), None) => "Session closed".to_string(),
+        (Err(e), None) => format!("Session failed: {}", e),
+    };
+    inner.shutdown(&reason);
+    let _ = connection.close().await;
+}
+
+/// Background task delivering incoming datagrams until the connection ends
+async fn run_datagram_reader(inner: Arc<MuxInner>, channel: Arc<dyn DatagramChannel>) {
+    while let Ok(bytes) = channel.recv_datagram().await {
+        *inner.last_received.lock().unwrap() = Instant::now();
+        inner.route_datagram(&bytes);
+    }
+}
+
+async fn write_frame(connection: &mut dyn Connection, frame: &MuxFrame) -> Result<(), TransportError> {
+    let bytes = frame
+        .to_frame()
+        .map_err(|e| TransportError::Serialization(e.to_string()))?;
+
+    let mut written = 0;
+    while written < bytes.len() {
+        let n = connection.write(&bytes[written..]).await?;
+        if n == 0 {
+            return Err(TransportError::ConnectionFailed {
+                reason: "Connection closed while writing".to_string(),
+            });
+        }
+        written += n;
+    }
+    connection.flush().await
+}
+
+/// Decode every complete frame in `buffer`; frames of other message kinds are skipped
+fn process_buffer(
+    inner: &Arc<MuxInner>,
+    buffer: &mut Vec<u8>,
+    max_payload: usize,
+    accept_tx: &mpsc::UnboundedSender<MuxStream>,
+) -> Result<(), TransportError> {
+    loop {
+        let (decoded, consumed) = match parse_frame(buffer, max_payload) {
+            Ok((frame, consumed)) => (decode_mux_frame(&frame), consumed),
+            Err(WireError::Incomplete { .. }) => return Ok(()),
+            Err(e) => return Err(TransportError::Serialization(e.to_string())),
+        };
+        buffer.drain(..consumed);
+
+        match decoded {
+            Ok(Some(frame)) => inner.handle_frame(frame, accept_tx),
+            Ok(None) => {}
+            Err(e) => return Err(TransportError::Serialization(e.to_string())),
+        }
+    }
+}
+
+fn decode_mux_frame(frame: &Frame<'_>) -> Result<Option<MuxFrame>, WireError> {
+    if frame.kind != MessageKind::StreamFrame {
+        return Ok(None);
+    }
+    MuxFrame::from_frame(frame).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MemoryConnection;
+    use std::time::Duration;
+
+    fn session_pair(config: MuxConfig) -> (Multiplexer, Multiplexer) {
+        let (a, b) = MemoryConnection::pair(
+            "a".to_string(),
+            "10.0.0.1:41000".parse().unwrap(),
+            "b".to_string(),
+            "10.0.0.2:41000".parse().unwrap(),
+        );
+        (
+            Multiplexer::new(Box::new(a), MuxRole::Initiator, config.clone()),
+            Multiplexer::new(Box::new(b), MuxRole::Responder, config),
+        )
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let ops = vec![
+            MuxOp::Open { channel: CHANNEL_CLIPBOARD.to_string() },
+            MuxOp::Data(b"payload".to_vec()),
+            MuxOp::WindowUpdate(4096),
+            MuxOp::Close,
+            MuxOp::Reset("bye".to_string()),
+        ];
+        for op in ops {
+            let frame = MuxFrame { stream_id: 7, op };
+            let encoded = frame.to_frame().unwrap();
+            let (parsed, _) = parse_frame(&encoded, 1024).unwrap();
+            assert_eq!(MuxFrame::from_frame(&parsed).unwrap(), frame);
+        }
+
+        let goodbye = MuxFrame { stream_id: 0, op: MuxOp::GoAway("shutdown".to_string()) };
+        let encoded = goodbye.to_frame().unwrap();
+        let (parsed, _) = parse_frame(&encoded, 1024).unwrap();
+        assert_eq!(MuxFrame::from_frame(&parsed).unwrap(), goodbye);
+
+        let misplaced = MuxFrame { stream_id: 7, op: MuxOp::GoAway("shutdown".to_string()) };
+        let encoded = misplaced.to_frame().unwrap();
+        let (parsed, _) = parse_frame(&encoded, 1024).unwrap();
+        assert!(MuxFrame::from_frame(&parsed).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_goodbye_reaches_peer() {
+        let (client, server) = session_pair(MuxConfig::default());
+
+        let stream = client.open_stream(CHANNEL_FILE_TRANSFER).unwrap();
+        let accepted = server.accept_stream().await.unwrap();
+        stream.send(b"last chunk").await.unwrap();
+        client.goodbye("Shutting down").unwrap();
+
+        assert_eq!(accepted.recv().await.unwrap(), b"last chunk");
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !server.is_closed() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(server.remote_goodbye().as_deref(), Some("Shutting down"));
+    }
+
+    #[tokio::test]
+    async fn test_streams_share_connection() {
+        let (client, server) = session_pair(MuxConfig::default());
+
+        let clipboard = client.open_stream(CHANNEL_CLIPBOARD).unwrap();
+        let control = client.open_stream(CHANNEL_CONTROL).unwrap();
+        assert_eq!(clipboard.stream_id() % 2, 1);
+        assert_ne!(clipboard.stream_id(), control.stream_id());
+
+        clipboard.send(b"copied text").await.unwrap();
+        control.send(b"ping").await.unwrap();
+
+        let first = server.accept_stream().await.unwrap();
+        let second = server.accept_stream().await.unwrap();
+        assert_eq!(first.channel(), CHANNEL_CLIPBOARD);
+        assert_eq!(second.channel(), CHANNEL_CONTROL);
+        assert_eq!(first.recv().await.unwrap(), b"copied text");
+        assert_eq!(second.recv().await.unwrap(), b"ping");
+
+        second.send(b"pong").await.unwrap();
+        assert_eq!(control.recv().await.unwrap(), b"pong");
+
+        clipboard.close().unwrap();
+        assert!(first.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sender_waits_for_window() {
+        let config = MuxConfig {
+            initial_window: 8,
+            max_frame_data: 4,
+            ..Default::default()
+        };
+        let (client, server) = session_pair(config);
+
+        let stream = client.open_stream(CHANNEL_FILE_TRANSFER).unwrap();
+        let sender = tokio::spawn(async move {
+            stream.send(&[7u8; 32]).await.unwrap();
+            stream
+        });
+
+        let inbound = server.accept_stream().await.unwrap();
+        let mut received = Vec::new();
+        while received.len() < 32 {
+            received.extend(inbound.recv().await.unwrap());
+        }
+        assert_eq!(received, vec![7u8; 32]);
+
+        tokio::time::timeout(Duration::from_secs(1), sender).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_messages_survive_frame_splitting() {
+        let config = MuxConfig {
+            max_frame_data: 3,
+            ..Default::default()
+        };
+        let (client, server) = session_pair(config);
+
+        let stream = client.open_stream(CHANNEL_CONTROL).unwrap();
+        stream.send_message(b"first message").await.unwrap();
+        stream.send_message(b"").await.unwrap();
+        stream.send_message(b"second").await.unwrap();
+        stream.close().unwrap();
+
+        let inbound = server.accept_stream().await.unwrap();
+        assert_eq!(inbound.recv_message(64).await.unwrap().unwrap(), b"first message");
+        assert_eq!(inbound.recv_message(64).await.unwrap().unwrap(), b"");
+        assert_eq!(inbound.recv_message(64).await.unwrap().unwrap(), b"second");
+        assert!(inbound.recv_message(64).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_datagrams_routed_by_channel() {
+        let (a, b) = session_pair(MuxConfig::default());
+        let mut clipboard = b.subscribe_datagrams(CHANNEL_CLIPBOARD).unwrap();
+        let mut control = b.subscribe_datagrams(CHANNEL_CONTROL).unwrap();
+
+        a.send_datagram(CHANNEL_NOTIFICATIONS, b"dropped").await.unwrap();
+        a.send_datagram(CHANNEL_CONTROL, b"pause").await.unwrap();
+        a.send_datagram(CHANNEL_CLIPBOARD, b"changed").await.unwrap();
+
+        assert_eq!(clipboard.recv().await.unwrap(), b"changed");
+        assert_eq!(control.recv().await.unwrap(), b"pause");
+
+        let max = a.max_datagram_payload(CHANNEL_CLIPBOARD).unwrap();
+        assert!(a.send_datagram(CHANNEL_CLIPBOARD, &vec![0u8; max + 1]).await.is_err());
+        a.send_datagram(CHANNEL_CLIPBOARD, &vec![0u8; max]).await.unwrap();
+        assert_eq!(clipboard.recv().await.unwrap().len(), max);
+    }
+
+    #[tokio::test]
+    async fn test_session_close_fails_streams() {
+        let (client, server) = session_pair(MuxConfig::default());
+        let stream = client.open_stream(CHANNEL_NOTIFICATIONS).unwrap();
+        let _inbound = server.accept_stream().await.unwrap();
+
+        server.close();
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !client.is_closed() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+
+        assert!(stream.send(b"late").await.is_err());
+        assert!(client.open_stream(CHANNEL_CONTROL).is_err());
+    }
+}
@@ -10,6 +10,18 @@ use uuid::Uuid;
 use rand::Rng;
 
 use crate::transport::{TransportError, PeerId, PeerAddress};
+use crate::transport::proxy::{ProxyConfig, Socks5UdpSocket};
+
+/// STUN magic cookie (RFC 5389)
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+const STUN_BINDING_REQUEST: u16 = 0x0001;
+const STUN_BINDING_SUCCESS: u16 = 0x0101;
+const STUN_ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const STUN_ATTR_CHANGE_REQUEST: u16 = 0x0003;
+const STUN_ATTR_CHANGED_ADDRESS: u16 = 0x0005;
+const STUN_ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+const STUN_ATTR_OTHER_ADDRESS: u16 = 0x802C;
+const STUN_RETRANSMIT_INTERVAL: Duration = Duration::from_millis(500);
 
 /// NAT traversal coordinator for establishing direct peer connections
 #[derive(Debug)]
@@ -22,10 +34,12 @@ pub struct NatTraversal {
     external_addresses: Arc<RwLock<Vec<SocketAddr>>>,
     /// Active hole punching sessions
     active_sessions: Arc<RwLock<HashMap<String, HolePunchSession>>>,
-    /// NAT type cache
-    nat_type_cache: Arc<RwLock<Option<(NatType, SystemTime)>>>,
+    /// Last NAT classification and when it was made
+    nat_type_cache: Arc<RwLock<Option<(NatReport, SystemTime)>>>,
     /// Configuration parameters
     config: NatTraversalConfig,
+    /// SOCKS5 proxy STUN requests are relayed through
+    proxy: Option<ProxyConfig>,
 }
 
 /// Configuration for NAT traversal behavior
@@ -99,6 +113,78 @@ impl NatType {
             NatType::Unknown => HolePunchDifficulty::Unknown,
         }
     }
+
+    /// Human-readable name
+    pub fn description(&self) -> &'static str {
+        match self {
+            NatType::Open => "open (no NAT)",
+            NatType::FullCone => "full cone",
+            NatType::RestrictedCone => "restricted cone",
+            NatType::PortRestrictedCone => "port-restricted cone",
+            NatType::Symmetric => "symmetric",
+            NatType::Unknown => "unknown",
+        }
+    }
+}
+
+/// Result of a NAT classification run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NatReport {
+    /// Detected NAT type
+    pub nat_type: NatType,
+    /// Local address used for the STUN requests
+    pub local_address: Option<SocketAddr>,
+    /// Public address the NAT mapped us to
+    pub mapped_address: Option<SocketAddr>,
+    /// STUN server that answered the first test
+    pub stun_server: Option<SocketAddr>,
+    /// Whether a second server address was available to test the mapping
+    pub mapping_tested: bool,
+    /// Whether the server could answer from another address to test filtering
+    pub filtering_tested: bool,
+    /// When the classification ran, in seconds since the Unix epoch
+    pub detected_at: u64,
+}
+
+impl NatReport {
+    fn unknown(stun_server: Option<SocketAddr>) -> Self {
+        Self {
+            nat_type: NatType::Unknown,
+            local_address: None,
+            mapped_address: None,
+            stun_server,
+            mapping_tested: false,
+            filtering_tested: false,
+            detected_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        }
+    }
+
+    /// Whether peers can likely connect to us directly, possibly after hole punching
+    pub fn direct_connection_feasible(&self) -> bool {
+        self.nat_type.supports_hole_punching()
+    }
+
+    /// Short advice on what the NAT type means for connections
+    pub fn recommendation(&self) -> &'static str {
+        match self.nat_type {
+            NatType::Open => "Directly reachable; peers can connect without NAT traversal",
+            NatType::FullCone | NatType::RestrictedCone => "Direct connections work after UDP hole punching",
+            NatType::PortRestrictedCone => {
+                "Direct connections usually work with hole punching when the peer is not behind a symmetric NAT"
+            }
+            NatType::Symmetric => "Direct connections are unlikely; configure a relay server",
+            NatType::Unknown => "STUN gave no answer; UDP may be blocked, so expect to need a relay",
+        }
+    }
+}
+
+/// Parsed STUN binding response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StunResponse {
+    /// Our address as the server saw it
+    mapped: SocketAddr,
+    /// Alternate server address for RFC 5780 tests, if supported
+    other_address: Option<SocketAddr>,
 }
 
 /// Difficulty levels for hole punching
@@ -176,6 +262,7 @@ impl NatTraversal {
             active_sessions: Arc::new(RwLock::new(HashMap::new())),
             nat_type_cache: Arc::new(RwLock::new(None)),
             config: NatTraversalConfig::default(),
+            proxy: None,
         }
     }
 
@@ -188,7 +275,21 @@ impl NatTraversal {
             active_sessions: Arc::new(RwLock::new(HashMap::new())),
             nat_type_cache: Arc::new(RwLock::new(None)),
             config,
+            proxy: None,
+        }
+    }
+
+    /// Send STUN requests through a proxy
+    ///
+    /// Only SOCKS5 proxies can relay UDP; with an HTTP proxy STUN stays
+    /// direct. Through a proxy, the mapped address and NAT type describe the
+    /// proxy's side of the network rather than this device's.
+    pub fn with_proxy(mut self, proxy: Option<ProxyConfig>) -> Self {
+        if let Some(proxy) = proxy.as_ref().filter(|proxy| !proxy.supports_udp()) {
+            log::info!("{} proxy {} cannot carry UDP, STUN requests go out directly", proxy.kind, proxy.address);
         }
+        self.proxy = proxy.filter(ProxyConfig::supports_udp);
+        self
     }
 
     /// Discover local network interface addresses
@@ -230,117 +331,162 @@ impl NatTraversal {
         // Check cache first
         {
             let cache = self.nat_type_cache.read().await;
-            if let Some((nat_type, cached_at)) = &*cache {
-                if cached_at.elapsed().unwrap_or(Duration::MAX) < self.config.nat_type_cache_duration {
-                    return Ok(*nat_type);
-                }
+            if let Some((report, cached_at)) = &*cache
+                && cached_at.elapsed().unwrap_or(Duration::MAX) < self.config.nat_type_cache_duration
+            {
+                return Ok(report.nat_type);
             }
         }
 
-        let nat_type = self.perform_nat_detection().await?;
+        Ok(self.detect_nat().await?.nat_type)
+    }
+
+    /// Classify the NAT now, bypassing and then refreshing the cache
+    pub async fn detect_nat(&self) -> Result<NatReport, TransportError> {
+        let report = self.perform_nat_detection().await?;
 
         // Update cache
         {
             let mut cache = self.nat_type_cache.write().await;
-            *cache = Some((nat_type, SystemTime::now()));
+            *cache = Some((report.clone(), SystemTime::now()));
+        }
+
+        if let Some(mapped) = report.mapped_address {
+            let mut external_addresses = self.external_addresses.write().await;
+            if !external_addresses.contains(&mapped) {
+                external_addresses.push(mapped);
+            }
         }
 
-        Ok(nat_type)
+        Ok(report)
     }
 
     /// Perform actual NAT type detection using STUN
-    async fn perform_nat_detection(&self) -> Result<NatType, TransportError> {
-        if self.stun_servers.is_empty() {
-            return Ok(NatType::Unknown);
+    ///
+    /// Follows the RFC 5780 tests: the mapped address seen by two different
+    /// server addresses tells cone NATs from symmetric ones, and asking the
+    /// server to answer from another IP or port probes the filtering. Servers
+    /// without RFC 5780 support cannot answer from another address, so the
+    /// filtering is then assumed to be port-restricted.
+    async fn perform_nat_detection(&self) -> Result<NatReport, TransportError> {
+        let Some(&server) = self.stun_servers.first() else {
+            return Ok(NatReport::unknown(None));
+        };
+
+        let socket = self.bind_stun_socket().await?;
+        let local_port = socket.local_addr().map_err(|e| TransportError::NatTraversalFailed {
+            method: format!("Failed to get local address: {}", e),
+        })?.port();
+        let local_address = outbound_ip(server).map(|ip| SocketAddr::new(ip, local_port));
+
+        // Test I: mapped address as seen by the primary server
+        let Some(first) = self.stun_transaction(&socket, server, false, false).await? else {
+            // No answer at all: UDP is blocked or the server is unreachable
+            let mut report = NatReport::unknown(Some(server));
+            report.local_address = local_address;
+            return Ok(report);
+        };
+
+        let mut report = NatReport {
+            nat_type: NatType::Unknown,
+            local_address,
+            mapped_address: Some(first.mapped),
+            stun_server: Some(server),
+            mapping_tested: false,
+            filtering_tested: false,
+            detected_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        };
+
+        if Some(first.mapped) == local_address {
+            report.nat_type = NatType::Open;
+            return Ok(report);
         }
 
-        // Create a UDP socket for STUN requests
-        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| TransportError::NatTraversalFailed {
-            method: format!("Failed to bind UDP socket for STUN: {}", e),
-        })?;
+        // Mapping behaviour: does another server address see the same mapping?
+        let second_server = first.other_address.or_else(|| self.stun_servers.get(1).copied());
+        if let Some(second_server) = second_server
+            && let Some(second) = self.stun_transaction(&socket, second_server, false, false).await?
+        {
+            report.mapping_tested = true;
+            if second.mapped != first.mapped {
+                report.nat_type = NatType::Symmetric;
+                return Ok(report);
+            }
+        }
 
-        let local_addr = socket.local_addr().map_err(|e| TransportError::NatTraversalFailed {
-            method: format!("Failed to get local address: {}", e),
-        })?;
+        // Filtering behaviour needs a server that can answer from elsewhere
+        if first.other_address.is_none() {
+            report.nat_type = NatType::PortRestrictedCone;
+            return Ok(report);
+        }
+        report.filtering_tested = true;
 
-        // Test 1: Basic STUN request to get external address
-        let external_addr1 = self.stun_request(&socket, &self.stun_servers[0]).await?;
-        
-        // Test 2: STUN request to different server
-        let external_addr2 = if self.stun_servers.len() > 1 {
-            self.stun_request(&socket, &self.stun_servers[1]).await?
+        report.nat_type = if self.stun_transaction(&socket, server, true, true).await?.is_some() {
+            NatType::FullCone
+        } else if self.stun_transaction(&socket, server, false, true).await?.is_some() {
+            NatType::RestrictedCone
         } else {
-            external_addr1
+            NatType::PortRestrictedCone
         };
 
-        // Analyze results to determine NAT type
-        if local_addr.ip() == external_addr1.ip() {
-            // No NAT detected
-            Ok(NatType::Open)
-        } else if external_addr1 == external_addr2 {
-            // Same external address from different servers
-            // Need more tests to distinguish between cone types
-            self.determine_cone_type(&socket, external_addr1).await
-        } else {
-            // Different external addresses = Symmetric NAT
-            Ok(NatType::Symmetric)
-        }
+        Ok(report)
     }
 
-    /// Determine the specific type of cone NAT
-    async fn determine_cone_type(&self, _socket: &UdpSocket, _external_addr: SocketAddr) -> Result<NatType, TransportError> {
-        // For now, assume restricted cone NAT as it's most common
-        // A full implementation would require more sophisticated STUN testing
-        Ok(NatType::RestrictedCone)
-    }
+    /// Socket for STUN requests, relayed by the proxy when one is configured
+    ///
+    /// Falls back to a direct socket when the proxy refuses UDP ASSOCIATE,
+    /// which many SOCKS5 proxies do.
+    async fn bind_stun_socket(&self) -> Result<StunSocket, TransportError> {
+        if let Some(proxy) = &self.proxy {
+            match proxy.udp_associate().await {
+                Ok(socket) => return Ok(StunSocket::Proxied(socket)),
+                Err(e) => log::debug!("Proxy {} cannot relay STUN, going direct: {}", proxy.address, e),
+            }
+        }
 
-    /// Perform a STUN request to discover external address
-    async fn stun_request(&self, socket: &UdpSocket, stun_server: &SocketAddr) -> Result<SocketAddr, TransportError> {
-        // Simplified STUN implementation - in a real scenario you'd use a proper STUN client
-        // For now, we'll simulate the external address discovery
-        
-        // Create a simple STUN binding request packet
-        let mut request = vec![0u8; 20]; // Minimal STUN header
-        request[0] = 0x00; // Message Type: Binding Request (0x0001)
-        request[1] = 0x01;
-        request[2] = 0x00; // Message Length: 0
-        request[3] = 0x00;
-        // Transaction ID (12 bytes) - using zeros for simplicity
-        
-        // Send STUN request
-        socket.send_to(&request, stun_server).map_err(|e| TransportError::NatTraversalFailed {
-            method: format!("Failed to send STUN request: {}", e),
-        })?;
+        TokioUdpSocket::bind("0.0.0.0:0").await.map(StunSocket::Direct).map_err(|e| {
+            TransportError::NatTraversalFailed {
+                method: format!("Failed to bind UDP socket for STUN: {}", e),
+            }
+        })
+    }
 
-        // Receive response with timeout
+    /// Send a STUN binding request and wait for the matching response
+    ///
+    /// Returns `None` when no response arrives within the STUN timeout, which
+    /// is an expected outcome of the filtering tests.
+    async fn stun_transaction(
+        &self,
+        socket: &StunSocket,
+        stun_server: SocketAddr,
+        change_ip: bool,
+        change_port: bool,
+    ) -> Result<Option<StunResponse>, TransportError> {
+        let mut transaction_id = [0u8; 12];
+        rand::thread_rng().fill(&mut transaction_id);
+        let request = build_binding_request(&transaction_id, change_ip, change_port);
+
+        let deadline = tokio::time::Instant::now() + self.config.stun_timeout;
         let mut buf = [0u8; 1024];
-        socket.set_read_timeout(Some(self.config.stun_timeout)).map_err(|e| TransportError::NatTraversalFailed {
-            method: format!("Failed to set socket timeout: {}", e),
-        })?;
 
-        let (len, _) = socket.recv_from(&mut buf).map_err(|e| TransportError::NatTraversalFailed {
-            method: format!("Failed to receive STUN response: {}", e),
-        })?;
-
-        if len < 20 {
-            return Err(TransportError::NatTraversalFailed {
-                method: "Invalid STUN response length".to_string(),
-            });
+        // UDP is lossy, so retransmit until the deadline
+        while tokio::time::Instant::now() < deadline {
+            socket.send_to(&request, stun_server).await.map_err(|e| TransportError::NatTraversalFailed {
+                method: format!("Failed to send STUN request: {}", e),
+            })?;
+
+            let retransmit_at = (tokio::time::Instant::now() + STUN_RETRANSMIT_INTERVAL).min(deadline);
+            while let Ok(received) = tokio::time::timeout_at(retransmit_at, socket.recv_from(&mut buf)).await {
+                let (len, _) = received.map_err(|e| TransportError::NatTraversalFailed {
+                    method: format!("Failed to receive STUN response: {}", e),
+                })?;
+                if let Some(response) = parse_binding_response(&buf[..len], &transaction_id) {
+                    return Ok(Some(response));
+                }
+            }
         }
 
-        // For now, return a simulated external address
-        // In a real implementation, you would parse the XOR-MAPPED-ADDRESS attribute
-        let local_addr = socket.local_addr().map_err(|e| TransportError::NatTraversalFailed {
-            method: format!("Failed to get local address: {}", e),
-        })?;
-        
-        // Simulate external address by using a different IP but same port
-        let external_ip = match stun_server.ip() {
-            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)), // TEST-NET-3
-            IpAddr::V6(_) => IpAddr::V6("2001:db8::1".parse().unwrap()), // Documentation prefix
-        };
-        
-        Ok(SocketAddr::new(external_ip, local_addr.port()))
+        Ok(None)
     }
 
     /// Discover external addresses using STUN
@@ -371,11 +517,34 @@ impl NatTraversal {
 
     /// Discover external address via a specific STUN server
     async fn discover_external_address_via_stun(&self, stun_server: &SocketAddr) -> Result<SocketAddr, TransportError> {
-        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| TransportError::NatTraversalFailed {
-            method: format!("Failed to bind UDP socket: {}", e),
-        })?;
+        let socket = self.bind_stun_socket().await?;
+
+        self.stun_transaction(&socket, *stun_server, false, false)
+            .await?
+            .map(|response| response.mapped)
+            .ok_or_else(|| TransportError::NatTraversalFailed {
+                method: format!("No STUN response from {}", stun_server),
+            })
+    }
 
-        self.stun_request(&socket, stun_server).await
+    /// Re-classify the NAT on startup and whenever the network changes
+    ///
+    /// A change is noticed when the local address used to reach the first
+    /// STUN server differs from the last check, e.g. after switching Wi-Fi.
+    pub fn spawn_nat_monitor(self: Arc<Self>, check_interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut last_route: Option<Option<IpAddr>> = None;
+            loop {
+                let route = self.stun_servers.first().and_then(|server| outbound_ip(*server));
+                if last_route != Some(route) {
+                    if let Err(e) = self.detect_nat().await {
+                        eprintln!("NAT type detection failed: {}", e);
+                    }
+                    last_route = Some(route);
+                }
+                sleep(check_interval).await;
+            }
+        })
     }
 
     /// Get cached local candidates
@@ -391,7 +560,13 @@ impl NatTraversal {
     /// Get cached NAT type
     pub async fn get_cached_nat_type(&self) -> Option<NatType> {
         let cache = self.nat_type_cache.read().await;
-        cache.as_ref().map(|(nat_type, _)| *nat_type)
+        cache.as_ref().map(|(report, _)| report.nat_type)
+    }
+
+    /// Get the last NAT classification
+    pub async fn get_cached_nat_report(&self) -> Option<NatReport> {
+        let cache = self.nat_type_cache.read().await;
+        cache.as_ref().map(|(report, _)| report.clone())
     }
 
     /// Initiate hole punching with a peer
@@ -913,6 +1088,166 @@ pub enum FallbackStrategy {
     GiveUp,
 }
 
+/// Build a STUN binding request, optionally with a CHANGE-REQUEST attribute
+fn build_binding_request(transaction_id: &[u8; 12], change_ip: bool, change_port: bool) -> Vec<u8> {
+    let mut request = Vec::with_capacity(28);
+    let with_change = change_ip || change_port;
+    request.extend_from_slice(&STUN_BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&(if with_change { 8u16 } else { 0 }).to_be_bytes());
+    request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(transaction_id);
+
+    if with_change {
+        let flags: u32 = (if change_ip { 0x04 } else { 0 }) | (if change_port { 0x02 } else { 0 });
+        request.extend_from_slice(&STUN_ATTR_CHANGE_REQUEST.to_be_bytes());
+        request.extend_from_slice(&4u16.to_be_bytes());
+        request.extend_from_slice(&flags.to_be_bytes());
+    }
+
+    request
+}
+
+/// Parse a STUN binding success response for the given transaction
+fn parse_binding_response(packet: &[u8], transaction_id: &[u8; 12]) -> Option<StunResponse> {
+    if packet.len() < 20
+        || u16::from_be_bytes([packet[0], packet[1]]) != STUN_BINDING_SUCCESS
+        || &packet[8..20] != transaction_id
+    {
+        return None;
+    }
+    let magic_cookie = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+    let body_len = usize::from(u16::from_be_bytes([packet[2], packet[3]]));
+    let body = packet.get(20..20 + body_len)?;
+
+    let mut mapped = None;
+    let mut xor_mapped = None;
+    let mut other_address = None;
+
+    let mut offset = 0;
+    while offset + 4 <= body.len() {
+        let attr_type = u16::from_be_bytes([body[offset], body[offset + 1]]);
+        let attr_len = usize::from(u16::from_be_bytes([body[offset + 2], body[offset + 3]]));
+        let value = body.get(offset + 4..offset + 4 + attr_len)?;
+
+        match attr_type {
+            STUN_ATTR_MAPPED_ADDRESS => mapped = parse_stun_address(value, None),
+            STUN_ATTR_XOR_MAPPED_ADDRESS if magic_cookie == STUN_MAGIC_COOKIE => {
+                xor_mapped = parse_stun_address(value, Some(transaction_id))
+            }
+            STUN_ATTR_OTHER_ADDRESS | STUN_ATTR_CHANGED_ADDRESS => {
+                other_address = other_address.or(parse_stun_address(value, None))
+            }
+            _ => {}
+        }
+
+        // Attributes are padded to a multiple of four bytes
+        offset += 4 + attr_len.div_ceil(4) * 4;
+    }
+
+    Some(StunResponse {
+        mapped: xor_mapped.or(mapped)?,
+        other_address,
+    })
+}
+
+/// Decode a (XOR-)MAPPED-ADDRESS style attribute value
+fn parse_stun_address(value: &[u8], xor_transaction_id: Option<&[u8; 12]>) -> Option<SocketAddr> {
+    if value.len() < 4 {
+        return None;
+    }
+    let cookie = STUN_MAGIC_COOKIE.to_be_bytes();
+    let mut port = u16::from_be_bytes([value[2], value[3]]);
+    if xor_transaction_id.is_some() {
+        port ^= (STUN_MAGIC_COOKIE >> 16) as u16;
+    }
+
+    let ip = match value[1] {
+        0x01 => {
+            let mut octets: [u8; 4] = value.get(4..8)?.try_into().ok()?;
+            if xor_transaction_id.is_some() {
+                for (octet, key) in octets.iter_mut().zip(cookie) {
+                    *octet ^= key;
+                }
+            }
+            IpAddr::from(octets)
+        }
+        0x02 => {
+            let mut octets: [u8; 16] = value.get(4..20)?.try_into().ok()?;
+            if let Some(transaction_id) = xor_transaction_id {
+                let key = cookie.iter().chain(transaction_id.iter());
+                for (octet, key) in octets.iter_mut().zip(key) {
+                    *octet ^= key;
+                }
+            }
+            IpAddr::from(octets)
+        }
+        _ => return None,
+    };
+
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Socket STUN requests are sent on
+enum StunSocket {
+    Direct(TokioUdpSocket),
+    /// Relayed through a SOCKS5 proxy
+    Proxied(Socks5UdpSocket),
+}
+
+impl StunSocket {
+    async fn send_to(&self, buf: &[u8], target: SocketAddr) -> std::io::Result<usize> {
+        match self {
+            StunSocket::Direct(socket) => socket.send_to(buf, target).await,
+            StunSocket::Proxied(socket) => socket.send_to(buf, target).await,
+        }
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        match self {
+            StunSocket::Direct(socket) => socket.recv_from(buf).await,
+            StunSocket::Proxied(socket) => socket.recv_from(buf).await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        match self {
+            StunSocket::Direct(socket) => socket.local_addr(),
+            StunSocket::Proxied(socket) => socket.local_addr(),
+        }
+    }
+}
+
+/// Local IP the routing table would use to reach `target`
+///
+/// Connecting a UDP socket only selects a route; no packet is sent.
+pub(crate) fn outbound_ip(target: SocketAddr) -> Option<IpAddr> {
+    let bind_addr: SocketAddr = match target {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(bind_addr).ok()?;
+    socket.connect(target).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+/// Resolve `stun:host:port` URLs to socket addresses, skipping failures
+pub async fn resolve_stun_servers(urls: &[String]) -> Vec<SocketAddr> {
+    let mut servers = Vec::new();
+    for url in urls {
+        let host = url.strip_prefix("stun:").unwrap_or(url);
+        match tokio::net::lookup_host(host).await {
+            Ok(addrs) => {
+                // One address per server keeps the mapping tests meaningful
+                if let Some(addr) = addrs.into_iter().find(|addr| addr.is_ipv4()) {
+                    servers.push(addr);
+                }
+            }
+            Err(e) => eprintln!("Failed to resolve STUN server {}: {}", url, e),
+        }
+    }
+    servers
+}
+
 // Add local_ip_address as a simple implementation since it's not in dependencies
 mod local_ip_address {
     use std::net::IpAddr;
@@ -1087,6 +1422,72 @@ mod tests {
         assert_eq!(status, None);
     }
 
+    #[test]
+    fn test_stun_binding_request_encoding() {
+        let transaction_id = [7u8; 12];
+        let request = build_binding_request(&transaction_id, false, false);
+        assert_eq!(request.len(), 20);
+        assert_eq!(&request[0..2], &[0x00, 0x01]);
+        assert_eq!(&request[4..8], &STUN_MAGIC_COOKIE.to_be_bytes());
+
+        let change = build_binding_request(&transaction_id, true, true);
+        assert_eq!(change.len(), 28);
+        assert_eq!(&change[2..4], &[0x00, 0x08]);
+        assert_eq!(&change[24..28], &[0, 0, 0, 0x06]);
+    }
+
+    #[test]
+    fn test_stun_response_parsing() {
+        let transaction_id = [3u8; 12];
+        let mapped = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 20)), 40000);
+        let other = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)), 3479);
+
+        let mut body = Vec::new();
+        // XOR-MAPPED-ADDRESS
+        let cookie = STUN_MAGIC_COOKIE.to_be_bytes();
+        body.extend_from_slice(&STUN_ATTR_XOR_MAPPED_ADDRESS.to_be_bytes());
+        body.extend_from_slice(&8u16.to_be_bytes());
+        body.extend_from_slice(&[0, 0x01]);
+        body.extend_from_slice(&(40000u16 ^ (STUN_MAGIC_COOKIE >> 16) as u16).to_be_bytes());
+        body.extend(mapped_octets(mapped).iter().zip(cookie).map(|(a, b)| a ^ b));
+        // OTHER-ADDRESS
+        body.extend_from_slice(&STUN_ATTR_OTHER_ADDRESS.to_be_bytes());
+        body.extend_from_slice(&8u16.to_be_bytes());
+        body.extend_from_slice(&[0, 0x01]);
+        body.extend_from_slice(&3479u16.to_be_bytes());
+        body.extend_from_slice(&mapped_octets(other));
+
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&STUN_BINDING_SUCCESS.to_be_bytes());
+        packet.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&cookie);
+        packet.extend_from_slice(&transaction_id);
+        packet.extend_from_slice(&body);
+
+        let response = parse_binding_response(&packet, &transaction_id).unwrap();
+        assert_eq!(response.mapped, mapped);
+        assert_eq!(response.other_address, Some(other));
+
+        // A response to another transaction is ignored
+        assert!(parse_binding_response(&packet, &[4u8; 12]).is_none());
+    }
+
+    fn mapped_octets(addr: SocketAddr) -> [u8; 4] {
+        match addr.ip() {
+            IpAddr::V4(ip) => ip.octets(),
+            IpAddr::V6(_) => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nat_detection_without_servers() {
+        let nat_traversal = NatTraversal::new(vec![]);
+        let report = nat_traversal.detect_nat().await.unwrap();
+        assert_eq!(report.nat_type, NatType::Unknown);
+        assert!(!report.direct_connection_feasible());
+        assert_eq!(nat_traversal.get_cached_nat_report().await, Some(report));
+    }
+
     #[test]
     fn test_fallback_strategy() {
         assert_eq!(FallbackStrategy::UseRelay, FallbackStrategy::UseRelay);
@@ -0,0 +1,777 @@
+// Router port mapping
+//
+// Behind a home router, peers outside the LAN cannot reach a listening port
+// unless the router forwards it. The mapper asks the router to, first over
+// NAT-PMP (RFC 6886), a single UDP exchange with the default gateway, and then
+// over UPnP IGD: an SSDP search for the gateway's WANIPConnection or
+// WANPPPConnection service followed by an AddPortMapping SOAP call.
+//
+// Mappings are leased. The background task renews each one at half its lease and
+// keeps retrying mappings that could not be renewed; `unmap_all` removes them
+// from the router when the transport stops listening.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::RwLock;
+use tokio::time::{sleep, timeout};
+
+use crate::transport::nat_traversal::outbound_ip;
+use crate::transport::ports::PortProtocol;
+use crate::transport::TransportError;
+
+const NAT_PMP_PORT: u16 = 5351;
+/// First NAT-PMP retransmission delay; doubled on each retry (RFC 6886 3.1)
+const NAT_PMP_RETRANSMIT: Duration = Duration::from_millis(250);
+const SSDP_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250)), 1900);
+const IGD_SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const WAN_SERVICES: [&str; 2] = ["WANIPConnection", "WANPPPConnection"];
+/// UPnP error: the external port is mapped to another client
+const UPNP_CONFLICT: u16 = 718;
+/// UPnP error: the router only accepts mappings without a lease
+const UPNP_PERMANENT_ONLY: u16 = 725;
+/// External ports tried when the one asked for is taken on the router
+const EXTERNAL_PORT_ATTEMPTS: u16 = 4;
+/// Delay before retrying mappings that failed to renew
+const RENEW_RETRY: Duration = Duration::from_secs(60);
+/// Largest HTTP response read from the router
+const MAX_HTTP_RESPONSE: u64 = 256 * 1024;
+
+/// Whether and how to ask the router to forward listening ports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortMappingConfig {
+    /// Try NAT-PMP
+    pub nat_pmp: bool,
+    /// Try UPnP IGD
+    pub upnp: bool,
+    /// Lease requested for each mapping
+    pub lease: Duration,
+    /// Time allowed for each method to answer
+    pub timeout: Duration,
+    /// Label shown in the router's mapping table
+    pub description: String,
+}
+
+impl Default for PortMappingConfig {
+    fn default() -> Self {
+        Self {
+            nat_pmp: true,
+            upnp: true,
+            lease: Duration::from_secs(3600),
+            timeout: Duration::from_secs(3),
+            description: "Kizuna".to_string(),
+        }
+    }
+}
+
+/// Protocol a mapping was made with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingMethod {
+    NatPmp,
+    Upnp,
+}
+
+impl fmt::Display for MappingMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappingMethod::NatPmp => write!(f, "NAT-PMP"),
+            MappingMethod::Upnp => write!(f, "UPnP"),
+        }
+    }
+}
+
+/// A port the router forwards to this device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortMapping {
+    pub protocol: PortProtocol,
+    pub internal_port: u16,
+    pub external_port: u16,
+    /// Router's public address, when it reported one
+    pub external_ip: Option<IpAddr>,
+    pub method: MappingMethod,
+    /// Lease granted; zero means the mapping does not expire
+    pub lease: Duration,
+    pub mapped_at: SystemTime,
+}
+
+impl PortMapping {
+    /// Address peers outside the LAN can connect to
+    pub fn external_addr(&self) -> Option<SocketAddr> {
+        self.external_ip.map(|ip| SocketAddr::new(ip, self.external_port))
+    }
+
+    /// When the mapping should be renewed
+    ///
+    /// Mappings without a lease are refreshed as if they had `default_lease`,
+    /// in case the router restarted and lost them.
+    pub fn renew_at(&self, default_lease: Duration) -> SystemTime {
+        self.mapped_at + self.renewal_lease(default_lease) / 2
+    }
+
+    fn renewal_lease(&self, default_lease: Duration) -> Duration {
+        if self.lease.is_zero() { default_lease } else { self.lease }
+    }
+}
+
+impl fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.external_ip {
+            Some(ip) => write!(f, "{} {}:{}", self.protocol, ip, self.external_port)?,
+            None => write!(f, "{} external port {}", self.protocol, self.external_port)?,
+        }
+        write!(f, " -> local port {} via {}", self.internal_port, self.method)
+    }
+}
+
+/// Requests, renews and removes port mappings on the local router
+pub struct PortMapper {
+    config: PortMappingConfig,
+    /// UPnP control endpoint, found on first use
+    upnp: RwLock<Option<UpnpService>>,
+    mappings: RwLock<Vec<PortMapping>>,
+}
+
+impl PortMapper {
+    pub fn new(config: PortMappingConfig) -> Self {
+        Self {
+            config,
+            upnp: RwLock::new(None),
+            mappings: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn config(&self) -> &PortMappingConfig {
+        &self.config
+    }
+
+    /// Ask the router to forward `internal_port`, trying each enabled method
+    ///
+    /// The mapping is kept and renewed by [`PortMapper::spawn`].
+    pub async fn map(&self, protocol: PortProtocol, internal_port: u16) -> Result<PortMapping, TransportError> {
+        let mut failures = Vec::new();
+        let methods = [
+            (self.config.nat_pmp, MappingMethod::NatPmp),
+            (self.config.upnp, MappingMethod::Upnp),
+        ];
+
+        for method in methods.into_iter().filter(|(enabled, _)| *enabled).map(|(_, method)| method) {
+            match self.request(method, protocol, internal_port, internal_port).await {
+                Ok(mapping) => {
+                    log::info!("Mapped {}", mapping);
+                    let mut mappings = self.mappings.write().await;
+                    mappings.retain(|m| !(m.protocol == protocol && m.internal_port == internal_port));
+                    mappings.push(mapping.clone());
+                    return Ok(mapping);
+                }
+                Err(e) => {
+                    log::debug!("{} mapping of {} port {} failed: {}", method, protocol, internal_port, e);
+                    failures.push(match e {
+                        TransportError::NatTraversalFailed { method } => method,
+                        other => format!("{} ({})", method, other),
+                    });
+                }
+            }
+        }
+
+        Err(TransportError::NatTraversalFailed {
+            method: if failures.is_empty() {
+                "port mapping (no method enabled)".to_string()
+            } else {
+                format!("port mapping ({})", failures.join("; "))
+            },
+        })
+    }
+
+    /// Remove a mapping from the router
+    pub async fn unmap(&self, mapping: &PortMapping) -> Result<(), TransportError> {
+        self.mappings
+            .write()
+            .await
+            .retain(|m| !(m.protocol == mapping.protocol && m.internal_port == mapping.internal_port));
+
+        match mapping.method {
+            MappingMethod::NatPmp => {
+                let gateway = default_gateway().ok_or_else(no_gateway)?;
+                nat_pmp_map(gateway, mapping.protocol, mapping.internal_port, 0, 0, self.config.timeout).await?;
+            }
+            MappingMethod::Upnp => {
+                let service = self.upnp_service().await?;
+                let args = format!(
+                    "<NewRemoteHost></NewRemoteHost><NewExternalPort>{}</NewExternalPort><NewProtocol>{}</NewProtocol>",
+                    mapping.external_port, mapping.protocol
+                );
+                service.call("DeletePortMapping", &args, self.config.timeout).await?;
+            }
+        }
+        log::info!("Removed mapping {}", mapping);
+        Ok(())
+    }
+
+    /// Remove every mapping this mapper made
+    pub async fn unmap_all(&self) {
+        let mappings = self.mappings().await;
+        for mapping in &mappings {
+            if let Err(e) = self.unmap(mapping).await {
+                log::warn!("Failed to remove port mapping {}: {}", mapping, e);
+            }
+        }
+    }
+
+    /// Mappings currently held
+    pub async fn mappings(&self) -> Vec<PortMapping> {
+        self.mappings.read().await.clone()
+    }
+
+    /// Map `ports` in the background, then renew them before their leases run out
+    ///
+    /// Ports that cannot be mapped are logged and skipped. Abort the task and
+    /// call [`PortMapper::unmap_all`] to stop.
+    pub fn spawn(self: Arc<Self>, ports: Vec<(PortProtocol, u16)>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            for (protocol, port) in ports {
+                if let Err(e) = self.map(protocol, port).await {
+                    log::warn!("Inbound {} connections on port {} may not work: {}", protocol, port, e);
+                }
+            }
+
+            loop {
+                let next = self
+                    .mappings()
+                    .await
+                    .iter()
+                    .map(|m| m.renew_at(self.config.lease))
+                    .min()
+                    .and_then(|at| at.duration_since(SystemTime::now()).ok())
+                    .unwrap_or(RENEW_RETRY);
+                sleep(next.max(Duration::from_secs(1))).await;
+
+                let now = SystemTime::now();
+                for mapping in self.mappings().await {
+                    if mapping.renew_at(self.config.lease) > now {
+                        continue;
+                    }
+                    match self.request(mapping.method, mapping.protocol, mapping.internal_port, mapping.external_port).await {
+                        Ok(renewed) => {
+                            let mut mappings = self.mappings.write().await;
+                            if let Some(slot) = mappings
+                                .iter_mut()
+                                .find(|m| m.protocol == renewed.protocol && m.internal_port == renewed.internal_port)
+                            {
+                                *slot = renewed;
+                            }
+                        }
+                        Err(e) => {
+                            log::warn!("Failed to renew port mapping {}: {}", mapping, e);
+                            // Try again shortly rather than at the next half lease
+                            let mut mappings = self.mappings.write().await;
+                            if let Some(slot) = mappings.iter_mut().find(|m| **m == mapping) {
+                                slot.mapped_at = now + RENEW_RETRY - slot.renewal_lease(self.config.lease) / 2;
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    async fn request(
+        &self,
+        method: MappingMethod,
+        protocol: PortProtocol,
+        internal_port: u16,
+        external_port: u16,
+    ) -> Result<PortMapping, TransportError> {
+        let lease = self.config.lease;
+        match method {
+            MappingMethod::NatPmp => {
+                let gateway = default_gateway().ok_or_else(no_gateway)?;
+                let (external_port, granted) =
+                    nat_pmp_map(gateway, protocol, internal_port, external_port, lease.as_secs() as u32, self.config.timeout)
+                        .await?;
+                let external_ip = nat_pmp_external_ip(gateway, self.config.timeout).await.ok();
+                Ok(PortMapping {
+                    protocol,
+                    internal_port,
+                    external_port,
+                    external_ip: external_ip.map(IpAddr::V4),
+                    method,
+                    lease: Duration::from_secs(granted as u64),
+                    mapped_at: SystemTime::now(),
+                })
+            }
+            MappingMethod::Upnp => {
+                let service = self.upnp_service().await?;
+                let (external_port, lease) = service
+                    .add_mapping(protocol, internal_port, external_port, lease, &self.config, self.config.timeout)
+                    .await?;
+                let external_ip = service.external_ip(self.config.timeout).await.ok();
+                Ok(PortMapping {
+                    protocol,
+                    internal_port,
+                    external_port,
+                    external_ip,
+                    method,
+                    lease,
+                    mapped_at: SystemTime::now(),
+                })
+            }
+        }
+    }
+
+    async fn upnp_service(&self) -> Result<UpnpService, TransportError> {
+        if let Some(service) = self.upnp.read().await.clone() {
+            return Ok(service);
+        }
+        let service = UpnpService::discover(self.config.timeout).await?;
+        *self.upnp.write().await = Some(service.clone());
+        Ok(service)
+    }
+}
+
+fn no_gateway() -> TransportError {
+    TransportError::NatTraversalFailed {
+        method: "NAT-PMP (no default gateway)".to_string(),
+    }
+}
+
+/// IPv4 default gateway
+///
+/// Read from the routing table on Linux; elsewhere assumed to be the `.1`
+/// address of the local network, which is what home routers use.
+fn default_gateway() -> Option<Ipv4Addr> {
+    if let Some(gateway) = routing_table_gateway() {
+        return Some(gateway);
+    }
+
+    match outbound_ip(SSDP_ADDR)? {
+        IpAddr::V4(ip) if !ip.is_unspecified() => {
+            let [a, b, c, _] = ip.octets();
+            Some(Ipv4Addr::new(a, b, c, 1))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn routing_table_gateway() -> Option<Ipv4Addr> {
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    // Columns: Iface Destination Gateway ..., addresses as raw network-order words
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields[2], 16).ok().filter(|&gateway| gateway != 0)?;
+        Some(Ipv4Addr::from(gateway.to_ne_bytes()))
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn routing_table_gateway() -> Option<Ipv4Addr> {
+    None
+}
+
+/// NAT-PMP opcode for a mapping request
+fn nat_pmp_opcode(protocol: PortProtocol) -> u8 {
+    match protocol {
+        PortProtocol::Udp => 1,
+        PortProtocol::Tcp => 2,
+    }
+}
+
+fn nat_pmp_request(protocol: PortProtocol, internal_port: u16, external_port: u16, lifetime: u32) -> [u8; 12] {
+    let mut request = [0u8; 12];
+    request[1] = nat_pmp_opcode(protocol);
+    request[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    request[6..8].copy_from_slice(&external_port.to_be_bytes());
+    request[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    request
+}
+
+/// Check a NAT-PMP response header, returning its payload after the epoch
+fn nat_pmp_payload(response: &[u8], opcode: u8, len: usize) -> Result<&[u8], TransportError> {
+    let failed = |reason: String| TransportError::NatTraversalFailed { method: format!("NAT-PMP ({})", reason) };
+    if response.len() < len || response[0] != 0 || response[1] != 128 + opcode {
+        return Err(failed("malformed response".to_string()));
+    }
+    match u16::from_be_bytes([response[2], response[3]]) {
+        0 => Ok(&response[8..len]),
+        1 => Err(failed("unsupported version".to_string())),
+        2 => Err(failed("mapping refused by the router".to_string())),
+        3 => Err(failed("router has no public address".to_string())),
+        4 => Err(failed("router is out of resources".to_string())),
+        code => Err(failed(format!("error {}", code))),
+    }
+}
+
+/// Parse a mapping response into the external port and granted lifetime
+fn parse_nat_pmp_mapping(response: &[u8], protocol: PortProtocol, internal_port: u16) -> Result<(u16, u32), TransportError> {
+    let payload = nat_pmp_payload(response, nat_pmp_opcode(protocol), 16)?;
+    if u16::from_be_bytes([payload[0], payload[1]]) != internal_port {
+        return Err(TransportError::NatTraversalFailed {
+            method: "NAT-PMP (response for another port)".to_string(),
+        });
+    }
+    let external_port = u16::from_be_bytes([payload[2], payload[3]]);
+    let lifetime = u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]);
+    Ok((external_port, lifetime))
+}
+
+/// Send a NAT-PMP request, retransmitting until `limit` runs out
+async fn nat_pmp_exchange(gateway: Ipv4Addr, request: &[u8], limit: Duration) -> Result<Vec<u8>, TransportError> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.connect((gateway, NAT_PMP_PORT)).await?;
+
+    let deadline = tokio::time::Instant::now() + limit;
+    let mut wait = NAT_PMP_RETRANSMIT;
+    let mut buf = [0u8; 16];
+    while tokio::time::Instant::now() < deadline {
+        socket.send(request).await?;
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if let Ok(received) = timeout(wait.min(remaining), socket.recv(&mut buf)).await {
+            let len = received?;
+            return Ok(buf[..len].to_vec());
+        }
+        wait *= 2;
+    }
+    Err(TransportError::NatTraversalFailed {
+        method: format!("NAT-PMP (no answer from {})", gateway),
+    })
+}
+
+async fn nat_pmp_map(
+    gateway: Ipv4Addr,
+    protocol: PortProtocol,
+    internal_port: u16,
+    external_port: u16,
+    lifetime: u32,
+    limit: Duration,
+) -> Result<(u16, u32), TransportError> {
+    let request = nat_pmp_request(protocol, internal_port, external_port, lifetime);
+    let response = nat_pmp_exchange(gateway, &request, limit).await?;
+    parse_nat_pmp_mapping(&response, protocol, internal_port)
+}
+
+async fn nat_pmp_external_ip(gateway: Ipv4Addr, limit: Duration) -> Result<Ipv4Addr, TransportError> {
+    let response = nat_pmp_exchange(gateway, &[0, 0], limit).await?;
+    let payload = nat_pmp_payload(&response, 0, 12)?;
+    Ok(Ipv4Addr::new(payload[0], payload[1], payload[2], payload[3]))
+}
+
+/// A router's UPnP WAN connection service
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UpnpService {
+    /// Router's HTTP endpoint
+    host: String,
+    port: u16,
+    control_path: String,
+    service_type: String,
+}
+
+impl UpnpService {
+    /// Find the gateway over SSDP and read its device description
+    async fn discover(limit: Duration) -> Result<Self, TransportError> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        let search = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+            SSDP_ADDR, IGD_SEARCH_TARGET
+        );
+        socket.send_to(search.as_bytes(), SSDP_ADDR).await?;
+
+        let mut buf = [0u8; 2048];
+        let location = timeout(limit, async {
+            loop {
+                let (len, _) = socket.recv_from(&mut buf).await?;
+                if let Some(location) = header(&String::from_utf8_lossy(&buf[..len]), "location") {
+                    return Ok::<_, std::io::Error>(location.to_string());
+                }
+            }
+        })
+        .await
+        .map_err(|_| TransportError::NatTraversalFailed {
+            method: "UPnP (no gateway answered)".to_string(),
+        })??;
+
+        let (host, port, path) = split_http_url(&location).ok_or_else(|| upnp_error(format!("bad location {}", location)))?;
+        let description = http_request(&host, port, "GET", &path, &[], "", limit).await?;
+        let (service_type, control_url) =
+            find_wan_service(&description).ok_or_else(|| upnp_error("gateway has no WAN connection service".to_string()))?;
+
+        let (host, port, control_path) = if control_url.starts_with("http://") {
+            split_http_url(&control_url).ok_or_else(|| upnp_error(format!("bad control URL {}", control_url)))?
+        } else if control_url.starts_with('/') {
+            (host, port, control_url)
+        } else {
+            (host, port, format!("/{}", control_url))
+        };
+
+        Ok(Self {
+            host,
+            port,
+            control_path,
+            service_type,
+        })
+    }
+
+    /// Call a SOAP action, returning the response body
+    async fn call(&self, action: &str, args: &str, limit: Duration) -> Result<String, TransportError> {
+        let body = format!(
+            "<?xml version=\"1.0\"?>\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+             <s:Body><u:{action} xmlns:u=\"{service}\">{args}</u:{action}></s:Body></s:Envelope>",
+            action = action,
+            service = self.service_type,
+            args = args,
+        );
+        let soap_action = format!("\"{}#{}\"", self.service_type, action);
+        let headers = [
+            ("Content-Type", "text/xml; charset=\"utf-8\""),
+            ("SOAPAction", soap_action.as_str()),
+        ];
+        http_request(&self.host, self.port, "POST", &self.control_path, &headers, &body, limit).await
+    }
+
+    /// Add a mapping, moving to the next external port on conflicts
+    ///
+    /// Returns the external port and the lease granted.
+    async fn add_mapping(
+        &self,
+        protocol: PortProtocol,
+        internal_port: u16,
+        external_port: u16,
+        lease: Duration,
+        config: &PortMappingConfig,
+        limit: Duration,
+    ) -> Result<(u16, Duration), TransportError> {
+        let gateway = SocketAddr::new(
+            self.host.parse().map_err(|_| upnp_error(format!("gateway {} is not an address", self.host)))?,
+            self.port,
+        );
+        let client = outbound_ip(gateway).ok_or_else(|| upnp_error("no route to the gateway".to_string()))?;
+
+        let mut lease = lease;
+        let mut last_error = None;
+        for external_port in (0..EXTERNAL_PORT_ATTEMPTS).filter_map(|i| external_port.checked_add(i)) {
+            let args = |lease: Duration| {
+                format!(
+                    "<NewRemoteHost></NewRemoteHost><NewExternalPort>{}</NewExternalPort>\
+                     <NewProtocol>{}</NewProtocol><NewInternalPort>{}</NewInternalPort>\
+                     <NewInternalClient>{}</NewInternalClient><NewEnabled>1</NewEnabled>\
+                     <NewPortMappingDescription>{}</NewPortMappingDescription>\
+                     <NewLeaseDuration>{}</NewLeaseDuration>",
+                    external_port,
+                    protocol,
+                    internal_port,
+                    client,
+                    xml_escape(&config.description),
+                    lease.as_secs()
+                )
+            };
+
+            let mut result = self.call("AddPortMapping", &args(lease), limit).await;
+            if matches!(&result, Err(e) if upnp_error_code(e) == Some(UPNP_PERMANENT_ONLY)) {
+                lease = Duration::ZERO;
+                result = self.call("AddPortMapping", &args(lease), limit).await;
+            }
+            match result {
+                Ok(_) => return Ok((external_port, lease)),
+                Err(e) if upnp_error_code(&e) == Some(UPNP_CONFLICT) => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| upnp_error("no external port available".to_string())))
+    }
+
+    async fn external_ip(&self, limit: Duration) -> Result<IpAddr, TransportError> {
+        let response = self.call("GetExternalIPAddress", "", limit).await?;
+        xml_text(&response, "NewExternalIPAddress")
+            .and_then(|ip| ip.trim().parse().ok())
+            .ok_or_else(|| upnp_error("gateway did not report its public address".to_string()))
+    }
+}
+
+fn upnp_error(reason: String) -> TransportError {
+    TransportError::NatTraversalFailed {
+        method: format!("UPnP ({})", reason),
+    }
+}
+
+/// UPnP error code carried in a failed call's error
+fn upnp_error_code(error: &TransportError) -> Option<u16> {
+    match error {
+        TransportError::NatTraversalFailed { method } => method
+            .strip_prefix("UPnP (error ")?
+            .split(|c: char| !c.is_ascii_digit())
+            .next()?
+            .parse()
+            .ok(),
+        _ => None,
+    }
+}
+
+/// Minimal HTTP/1.1 exchange with the router, returning the body of a 2xx response
+///
+/// SOAP faults come back as 500 with a `<errorCode>`, which is kept in the
+/// error so callers can react to specific codes.
+async fn http_request(
+    host: &str,
+    port: u16,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+    limit: Duration,
+) -> Result<String, TransportError> {
+    let exchange = async {
+        let mut stream = TcpStream::connect((host, port)).await?;
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}:{}\r\nConnection: close\r\nContent-Length: {}\r\n",
+            method,
+            path,
+            host,
+            port,
+            body.len()
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut response = Vec::new();
+        stream.take(MAX_HTTP_RESPONSE).read_to_end(&mut response).await?;
+        Ok::<_, std::io::Error>(String::from_utf8_lossy(&response).into_owned())
+    };
+    let response = timeout(limit, exchange)
+        .await
+        .map_err(|_| upnp_error(format!("{}:{} did not answer", host, port)))??;
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status: u16 = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| upnp_error("malformed HTTP response".to_string()))?;
+    if (200..300).contains(&status) {
+        return Ok(body.to_string());
+    }
+
+    Err(match xml_text(body, "errorCode") {
+        Some(code) => upnp_error(format!(
+            "error {}: {}",
+            code.trim(),
+            xml_text(body, "errorDescription").unwrap_or("").trim()
+        )),
+        None => upnp_error(format!("HTTP {}", status)),
+    })
+}
+
+/// Value of an HTTP header, matched case-insensitively
+fn header<'a>(message: &'a str, name: &str) -> Option<&'a str> {
+    message.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim().eq_ignore_ascii_case(name).then_some(value.trim())
+    })
+}
+
+/// Split `http://host[:port]/path` into its parts
+fn split_http_url(url: &str) -> Option<(String, u16, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, 80),
+    };
+    Some((host.to_string(), port, path.to_string()))
+}
+
+/// Text inside the first `<tag>` element, ignoring namespace prefixes
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let mut search = xml;
+    loop {
+        let open = search.find('<')?;
+        let rest = &search[open + 1..];
+        let end = rest.find('>')?;
+        let name = rest[..end].split_whitespace().next().unwrap_or("");
+        let local = name.rsplit(':').next().unwrap_or(name);
+        if local == tag && !name.starts_with('/') {
+            let content = &rest[end + 1..];
+            let close = content.find(&format!("</{}>", name))?;
+            return Some(&content[..close]);
+        }
+        search = &rest[end + 1..];
+    }
+}
+
+/// Service type and control URL of the device's WAN connection service
+fn find_wan_service(description: &str) -> Option<(String, String)> {
+    description.split("<service>").skip(1).find_map(|service| {
+        let service_type = xml_text(service, "serviceType")?.trim();
+        if !WAN_SERVICES.iter().any(|wan| service_type.contains(wan)) {
+            return None;
+        }
+        Some((service_type.to_string(), xml_text(service, "controlURL")?.trim().to_string()))
+    })
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nat_pmp_messages() {
+        let request = nat_pmp_request(PortProtocol::Tcp, 41340, 41340, 3600);
+        assert_eq!(request, [0, 2, 0, 0, 0xa1, 0x7c, 0xa1, 0x7c, 0, 0, 0x0e, 0x10]);
+
+        let mut response = [0u8; 16];
+        response[1] = 130;
+        response[8..10].copy_from_slice(&41340u16.to_be_bytes());
+        response[10..12].copy_from_slice(&50000u16.to_be_bytes());
+        response[12..16].copy_from_slice(&1800u32.to_be_bytes());
+        assert_eq!(parse_nat_pmp_mapping(&response, PortProtocol::Tcp, 41340).unwrap(), (50000, 1800));
+
+        // A UDP answer does not satisfy a TCP request, and errors are reported
+        assert!(parse_nat_pmp_mapping(&response, PortProtocol::Udp, 41340).is_err());
+        response[3] = 2;
+        assert!(parse_nat_pmp_mapping(&response, PortProtocol::Tcp, 41340).is_err());
+    }
+
+    #[test]
+    fn test_upnp_description_parsing() {
+        let description = r#"<root><device><serviceList>
+            <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>
+            <controlURL>/l3f</controlURL></service>
+            <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>
+            <controlURL>/ctl/IPConn</controlURL></service>
+            </serviceList></device></root>"#;
+        assert_eq!(
+            find_wan_service(description),
+            Some(("urn:schemas-upnp-org:service:WANIPConnection:1".to_string(), "/ctl/IPConn".to_string()))
+        );
+
+        assert_eq!(
+            split_http_url("http://192.168.1.1:49152/rootDesc.xml"),
+            Some(("192.168.1.1".to_string(), 49152, "/rootDesc.xml".to_string()))
+        );
+        assert_eq!(header("HTTP/1.1 200 OK\r\nLOCATION: http://10.0.0.1/\r\n", "location"), Some("http://10.0.0.1/"));
+
+        let fault = "<s:Fault><detail><UPnPError><errorCode>718</errorCode>\
+                     <errorDescription>ConflictInMappingEntry</errorDescription></UPnPError></detail></s:Fault>";
+        let error = upnp_error(format!("error {}: {}", xml_text(fault, "errorCode").unwrap(), "ConflictInMappingEntry"));
+        assert_eq!(upnp_error_code(&error), Some(UPNP_CONFLICT));
+    }
+}
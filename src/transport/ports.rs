@@ -0,0 +1,282 @@
+// Listening port configuration
+//
+// Each transport listens on a configured port. If that port is taken, the next
+// free port in its range is used, and once the range is exhausted the system
+// picks an ephemeral port (unless that fallback is turned off). TCP-based
+// transports (tcp, websocket) and UDP-based ones (quic, webrtc) are probed with
+// the matching socket type, so tcp and quic can share a port number.
+//
+// Selection probes a port by binding and releasing it, so another process can
+// still take it before the transport binds; transports report that as a normal
+// listen error.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr, TcpListener, UdpSocket};
+use std::str::FromStr;
+
+use crate::transport::TransportError;
+
+/// Port tried first by the tcp and quic transports
+pub const DEFAULT_SERVICE_PORT: u16 = 41340;
+/// Port tried first by the websocket transport
+pub const DEFAULT_WEBSOCKET_PORT: u16 = 41350;
+/// Ports tried after the preferred one is taken
+const DEFAULT_RANGE_LEN: u16 = 10;
+
+/// Socket type a port is bound with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortProtocol {
+    Tcp,
+    Udp,
+}
+
+impl PortProtocol {
+    /// Socket type used by a transport, by protocol name
+    pub fn for_transport(protocol: &str) -> Self {
+        match protocol {
+            "quic" | "webrtc" => PortProtocol::Udp,
+            _ => PortProtocol::Tcp,
+        }
+    }
+}
+
+impl fmt::Display for PortProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortProtocol::Tcp => write!(f, "TCP"),
+            PortProtocol::Udp => write!(f, "UDP"),
+        }
+    }
+}
+
+/// Inclusive range of ports, written `start-end` or as a single port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    /// Create a range, validating that it is non-empty and excludes port 0
+    pub fn new(start: u16, end: u16) -> Result<Self, TransportError> {
+        if start == 0 || start > end {
+            return Err(TransportError::ConfigurationError {
+                field: "port_range".to_string(),
+                reason: format!("{}-{} is not a valid port range", start, end),
+            });
+        }
+        Ok(Self { start, end })
+    }
+
+    pub fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+
+    pub fn ports(&self) -> impl Iterator<Item = u16> {
+        self.start..=self.end
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+impl FromStr for PortRange {
+    type Err = TransportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |port: &str| {
+            port.trim().parse::<u16>().map_err(|_| TransportError::ConfigurationError {
+                field: "port_range".to_string(),
+                reason: format!("'{}' is not a port", port.trim()),
+            })
+        };
+        match s.split_once('-') {
+            Some((start, end)) => Self::new(parse(start)?, parse(end)?),
+            None => {
+                let port = parse(s)?;
+                Self::new(port, port)
+            }
+        }
+    }
+}
+
+/// Where one transport listens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortConfig {
+    /// Port tried first; 0 lets the system choose
+    pub port: u16,
+    /// Ports tried, in order, when `port` is taken
+    #[serde(default)]
+    pub range: Option<PortRange>,
+    /// Let the system choose a port once the range is exhausted
+    #[serde(default = "default_true")]
+    pub ephemeral_fallback: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl PortConfig {
+    /// Listen on `port`, falling back to the `DEFAULT_RANGE_LEN` ports after it
+    pub fn new(port: u16) -> Self {
+        Self {
+            port,
+            range: (port != 0).then(|| PortRange {
+                start: port,
+                end: port.saturating_add(DEFAULT_RANGE_LEN - 1),
+            }),
+            ephemeral_fallback: true,
+        }
+    }
+
+    /// Let the system choose the port
+    pub fn ephemeral() -> Self {
+        Self {
+            port: 0,
+            range: None,
+            ephemeral_fallback: true,
+        }
+    }
+
+    /// Listen only on ports in `range`
+    pub fn in_range(range: PortRange) -> Self {
+        Self {
+            port: range.start,
+            range: Some(range),
+            ephemeral_fallback: false,
+        }
+    }
+
+    /// Ports to try, in order; 0 stands for a system-chosen port
+    pub fn candidates(&self) -> Vec<u16> {
+        let mut ports = Vec::new();
+        if self.port != 0 {
+            ports.push(self.port);
+        }
+        if let Some(range) = self.range {
+            ports.extend(range.ports().filter(|&port| port != self.port));
+        }
+        if self.port == 0 || self.ephemeral_fallback {
+            ports.push(0);
+        }
+        ports
+    }
+}
+
+/// Listening ports for every transport
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListenPorts {
+    pub tcp: PortConfig,
+    pub quic: PortConfig,
+    pub websocket: PortConfig,
+    /// WebRTC negotiates its own ports through ICE, so this defaults to ephemeral
+    pub webrtc: PortConfig,
+}
+
+impl Default for ListenPorts {
+    fn default() -> Self {
+        Self {
+            tcp: PortConfig::new(DEFAULT_SERVICE_PORT),
+            quic: PortConfig::new(DEFAULT_SERVICE_PORT),
+            websocket: PortConfig::new(DEFAULT_WEBSOCKET_PORT),
+            webrtc: PortConfig::ephemeral(),
+        }
+    }
+}
+
+impl ListenPorts {
+    /// Port configuration for a transport, by protocol name
+    pub fn for_transport(&self, protocol: &str) -> PortConfig {
+        match protocol {
+            "tcp" => self.tcp,
+            "quic" => self.quic,
+            "websocket" => self.websocket,
+            "webrtc" => self.webrtc,
+            _ => PortConfig::ephemeral(),
+        }
+    }
+}
+
+/// Pick a free port for `config` on `ip`
+///
+/// Returns the address to bind. A system-chosen port is resolved to the actual
+/// port number so it can be advertised and mapped.
+pub fn select_port(ip: IpAddr, config: &PortConfig, protocol: PortProtocol) -> Result<SocketAddr, TransportError> {
+    let mut last_error = None;
+    for port in config.candidates() {
+        match probe(SocketAddr::new(ip, port), protocol) {
+            Ok(addr) => {
+                if port != config.port && config.port != 0 {
+                    log::info!("{} port {} is in use, listening on {} instead", protocol, config.port, addr.port());
+                }
+                return Ok(addr);
+            }
+            Err(e) => {
+                log::debug!("{} port {} unavailable: {}", protocol, port, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(TransportError::ConfigurationError {
+        field: "listen_port".to_string(),
+        reason: format!(
+            "no free {} port among {}{}",
+            protocol,
+            config.range.map_or(config.port.to_string(), |range| range.to_string()),
+            last_error.map_or(String::new(), |e| format!(" (last error: {})", e)),
+        ),
+    })
+}
+
+/// Bind and release a socket, returning the address it got
+fn probe(addr: SocketAddr, protocol: PortProtocol) -> std::io::Result<SocketAddr> {
+    match protocol {
+        PortProtocol::Tcp => TcpListener::bind(addr)?.local_addr(),
+        PortProtocol::Udp => UdpSocket::bind(addr)?.local_addr(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn test_parse_port_range() {
+        assert_eq!("41000-41010".parse::<PortRange>().unwrap(), PortRange { start: 41000, end: 41010 });
+        assert_eq!("8080".parse::<PortRange>().unwrap().to_string(), "8080");
+        assert!("41010-41000".parse::<PortRange>().is_err());
+        assert!("0-10".parse::<PortRange>().is_err());
+        assert!("http".parse::<PortRange>().is_err());
+    }
+
+    #[test]
+    fn test_select_port_skips_taken_ports() {
+        let taken = TcpListener::bind((LOCALHOST, 0)).unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        // The same port number is still free for UDP
+        let config = PortConfig::in_range(PortRange::new(port, port).unwrap());
+        assert!(select_port(LOCALHOST, &config, PortProtocol::Tcp).is_err());
+        assert_eq!(select_port(LOCALHOST, &config, PortProtocol::Udp).unwrap().port(), port);
+
+        // With the fallback a taken port still yields a real port number
+        let config = PortConfig { port, range: None, ephemeral_fallback: true };
+        let addr = select_port(LOCALHOST, &config, PortProtocol::Tcp).unwrap();
+        assert_ne!(addr.port(), port);
+        assert_ne!(addr.port(), 0);
+    }
+}
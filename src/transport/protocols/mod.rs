@@ -1,10 +1,14 @@
 pub mod tcp;
+#[cfg(feature = "transport-quic")]
 pub mod quic;
+#[cfg(feature = "transport-webrtc")]
 pub mod webrtc;
 pub mod websocket;
 
 pub use tcp::{TcpTransport, TcpConnection, TcpListener, TcpConfig, TcpServer, TcpServerStats};
+#[cfg(feature = "transport-quic")]
 pub use quic::{QuicTransport, QuicConnection, QuicConfig, QuicConnectionStats, CongestionControl};
+#[cfg(feature = "transport-webrtc")]
 pub use webrtc::{WebRtcTransport, WebRtcConnection, WebRtcConfig, IceServerConfig, SignalingHandler, SignalingMessage, DefaultSignalingHandler};
 pub use websocket::{
     WebSocketTransport, WebSocketConnection, WebSocketListener, WebSocketConfig, 
@@ -8,7 +8,8 @@ use rustls::pki_types::{CertificateDer, PrivateKeyDer};
 use tokio::sync::{Mutex, RwLock};
 
 use crate::transport::{
-    Connection, ConnectionInfo, PeerAddress, PeerId, Transport, TransportCapabilities, TransportError,
+    Connection, ConnectionInfo, DatagramChannel, PeerAddress, PeerId, Transport,
+    TransportCapabilities, TransportError,
 };
 
 /// QUIC transport implementation using Quinn with advanced features
@@ -139,8 +140,10 @@ pub struct QuicConfig {
     pub idle_timeout: Duration,
     /// Keep-alive interval
     pub keep_alive_interval: Option<Duration>,
-    /// Maximum datagram size
+    /// Largest unreliable datagram payload to send; `None` disables datagram support
     pub max_datagram_size: Option<usize>,
+    /// Bytes of incoming and outgoing datagrams buffered before the oldest are dropped
+    pub datagram_buffer_size: usize,
     /// Enable 0-RTT resumption
     pub enable_0rtt: bool,
     /// Congestion control algorithm
@@ -167,6 +170,7 @@ impl Default for QuicConfig {
             idle_timeout: Duration::from_secs(30),
             keep_alive_interval: Some(Duration::from_secs(5)),
             max_datagram_size: Some(1200),
+            datagram_buffer_size: 1024 * 1024,
             enable_0rtt: true,
             congestion_control: CongestionControl::Cubic,
             max_migration_attempts: 3,
@@ -229,8 +233,11 @@ impl QuicTransport {
             transport_config.keep_alive_interval(Some(keep_alive));
         }
         
-        if let Some(datagram_size) = config.max_datagram_size {
-            transport_config.datagram_receive_buffer_size(Some(datagram_size));
+        if config.max_datagram_size.is_some() {
+            transport_config.datagram_receive_buffer_size(Some(config.datagram_buffer_size));
+            transport_config.datagram_send_buffer_size(config.datagram_buffer_size);
+        } else {
+            transport_config.datagram_receive_buffer_size(None);
         }
 
         // Configure congestion control
@@ -466,6 +473,17 @@ impl QuicTransport {
         connections.retain(|_, conn| conn.close_reason().is_none());
     }
 
+    /// Largest datagram payload that can currently be sent to a connected peer
+    ///
+    /// Returns `None` if datagrams are disabled, the peer has no open connection, or
+    /// the peer did not enable datagram support during the handshake.
+    pub async fn max_datagram_size(&self, peer_id: &PeerId) -> Option<usize> {
+        let local = self.config.max_datagram_size?;
+        let connections = self.active_connections.read().await;
+        let connection = connections.get(peer_id)?;
+        connection.max_datagram_size().map(|size| size.min(local))
+    }
+
     /// Get connection statistics
     pub async fn get_connection_stats(&self) -> HashMap<PeerId, QuicConnectionStats> {
         let connections = self.active_connections.read().await;
@@ -1140,6 +1158,73 @@ impl Connection for QuicConnection {
     fn is_connected(&self) -> bool {
         self.connection.close_reason().is_none()
     }
+
+    fn datagrams(&self) -> Option<Arc<dyn DatagramChannel>> {
+        let max_size = self.config.max_datagram_size?;
+        Some(Arc::new(QuicDatagrams {
+            connection: self.connection.clone(),
+            max_size,
+        }))
+    }
+}
+
+impl QuicConnection {
+    /// Largest datagram payload the peer currently accepts
+    ///
+    /// Returns `None` if datagrams are disabled locally or not supported by the peer.
+    pub fn max_datagram_size(&self) -> Option<usize> {
+        let local = self.config.max_datagram_size?;
+        self.connection.max_datagram_size().map(|size| size.min(local))
+    }
+}
+
+/// Datagram channel over QUIC DATAGRAM frames
+///
+/// Shares the underlying Quinn connection, so datagrams can be received while the
+/// owning `QuicConnection` is busy reading its stream.
+#[derive(Debug, Clone)]
+pub struct QuicDatagrams {
+    connection: QuinnConnection,
+    max_size: usize,
+}
+
+#[async_trait]
+impl DatagramChannel for QuicDatagrams {
+    fn max_datagram_size(&self) -> Option<usize> {
+        self.connection.max_datagram_size().map(|size| size.min(self.max_size))
+    }
+
+    async fn send_datagram(&self, data: &[u8]) -> Result<(), TransportError> {
+        if data.len() > self.max_size {
+            return Err(TransportError::ResourceLimitExceeded {
+                resource: format!("datagram size ({} > {} bytes)", data.len(), self.max_size),
+            });
+        }
+
+        self.connection
+            .send_datagram(bytes::Bytes::copy_from_slice(data))
+            .map_err(|e| match e {
+                quinn::SendDatagramError::UnsupportedByPeer | quinn::SendDatagramError::Disabled => {
+                    TransportError::UnsupportedProtocol {
+                        protocol: "quic datagrams".to_string(),
+                    }
+                }
+                quinn::SendDatagramError::TooLarge => TransportError::ResourceLimitExceeded {
+                    resource: format!("datagram size ({} bytes)", data.len()),
+                },
+                quinn::SendDatagramError::ConnectionLost(e) => {
+                    TransportError::Quic(format!("Connection lost: {}", e))
+                }
+            })
+    }
+
+    async fn recv_datagram(&self) -> Result<Vec<u8>, TransportError> {
+        self.connection
+            .read_datagram()
+            .await
+            .map(|data| data.to_vec())
+            .map_err(|e| TransportError::Quic(format!("Datagram receive error: {}", e)))
+    }
 }
 
 impl Drop for QuicConnection {
@@ -1189,6 +1274,7 @@ mod tests {
         assert_eq!(config.idle_timeout, Duration::from_secs(30));
         assert_eq!(config.keep_alive_interval, Some(Duration::from_secs(5)));
         assert_eq!(config.max_datagram_size, Some(1200));
+        assert_eq!(config.datagram_buffer_size, 1024 * 1024);
         assert!(config.enable_0rtt);
         assert!(matches!(config.congestion_control, CongestionControl::Cubic));
         assert_eq!(config.max_migration_attempts, 3);
@@ -6,7 +6,7 @@ use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
 use tokio::time::timeout;
 use tokio_tungstenite::{
-    WebSocketStream, MaybeTlsStream, connect_async, accept_async,
+    WebSocketStream, MaybeTlsStream, connect_async, accept_async, client_async_tls,
     tungstenite::{Message, protocol::CloseFrame}
 };
 use futures_util::{SinkExt, StreamExt};
@@ -18,6 +18,7 @@ use crate::transport::{
     Connection, ConnectionInfo, PeerAddress, PeerId, Transport, 
     TransportCapabilities, TransportError
 };
+use crate::transport::proxy::ProxyConfig;
 
 /// Configuration for WebSocket transport
 #[derive(Debug, Clone)]
@@ -44,6 +45,8 @@ pub struct WebSocketConfig {
     pub max_upgrade_time: Duration,
     /// Buffer size for message queuing
     pub message_buffer_size: usize,
+    /// Proxy for relay server connections; direct peer connections never use it
+    pub proxy: Option<ProxyConfig>,
 }
 
 impl Default for WebSocketConfig {
@@ -60,6 +63,7 @@ impl Default for WebSocketConfig {
             upgrade_attempt_interval: Duration::from_secs(60),
             max_upgrade_time: Duration::from_secs(300), // 5 minutes
             message_buffer_size: 1024,
+            proxy: None,
         }
     }
 }
@@ -79,6 +83,7 @@ impl WebSocketConfig {
             upgrade_attempt_interval: Duration::from_secs(120),
             max_upgrade_time: Duration::from_secs(600),
             message_buffer_size: 512,
+            proxy: None,
         }
     }
 
@@ -96,6 +101,7 @@ impl WebSocketConfig {
             upgrade_attempt_interval: Duration::from_secs(30),
             max_upgrade_time: Duration::from_secs(120),
             message_buffer_size: 2048,
+            proxy: None,
         }
     }
 }
@@ -118,7 +124,9 @@ impl WebSocketTransport {
 
     /// Create a new WebSocket transport with custom configuration
     pub fn with_config(config: WebSocketConfig) -> Self {
-        let relay_manager = Arc::new(RelayManager::new_with_servers(config.relay_servers.clone()));
+        let relay_manager = Arc::new(
+            RelayManager::new_with_servers(config.relay_servers.clone()).with_proxy(config.proxy.clone()),
+        );
         Self { 
             config,
             relay_manager,
@@ -211,14 +219,13 @@ impl WebSocketTransport {
         }
 
         // Connect to relay server
-        let (mut ws_stream, _response) = timeout(
+        let mut ws_stream = timeout(
             self.config.connect_timeout,
-            connect_async(relay_url.as_str())
+            connect_relay(&relay_url, self.config.proxy.as_ref())
         ).await
         .map_err(|_| TransportError::ConnectionTimeout {
             timeout: self.config.connect_timeout,
-        })?
-        .map_err(|e| TransportError::WebSocket(format!("Relay connection failed: {}", e)))?;
+        })??;
 
         // Send relay request message
         let relay_request = RelayMessage::ConnectRequest {
@@ -849,10 +856,32 @@ impl RelayServer {
     }
 }
 
+/// Open a WebSocket to a relay server, through the proxy unless the relay bypasses it
+async fn connect_relay(
+    url: &Url,
+    proxy: Option<&ProxyConfig>,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, TransportError> {
+    let proxy = proxy.filter(|proxy| url.host_str().is_some_and(|host| !proxy.bypasses(host)));
+    let result = match proxy {
+        Some(proxy) => {
+            let host = url.host_str().unwrap_or_default();
+            let port = url.port_or_known_default().unwrap_or(80);
+            let stream = proxy.connect(host, port).await?;
+            client_async_tls(url.as_str(), stream).await
+        }
+        None => connect_async(url.as_str()).await,
+    };
+
+    result
+        .map(|(ws_stream, _response)| ws_stream)
+        .map_err(|e| TransportError::WebSocket(format!("Relay connection failed: {}", e)))
+}
+
 /// Relay manager for handling relay server selection and management
 #[derive(Debug)]
 pub struct RelayManager {
     servers: Arc<std::sync::Mutex<Vec<RelayServer>>>,
+    proxy: Option<ProxyConfig>,
 }
 
 impl RelayManager {
@@ -860,6 +889,7 @@ impl RelayManager {
     pub fn new() -> Self {
         Self {
             servers: Arc::new(std::sync::Mutex::new(Vec::new())),
+            proxy: None,
         }
     }
 
@@ -877,9 +907,16 @@ impl RelayManager {
 
         Self {
             servers: Arc::new(std::sync::Mutex::new(servers)),
+            proxy: None,
         }
     }
 
+    /// Reach relay servers through a proxy
+    pub fn with_proxy(mut self, proxy: Option<ProxyConfig>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
     /// Add a relay server
     pub fn add_server(&self, url: Url) -> Result<(), TransportError> {
         let host = url.host_str().ok_or_else(|| TransportError::InvalidPeerAddress)?;
@@ -1128,6 +1165,7 @@ impl RelayManager {
         for server in servers {
             let server_clone = server.clone();
             let servers_ref = self.servers.clone();
+            let proxy = self.proxy.clone();
             
             let task = tokio::spawn(async move {
                 let start_time = Instant::now();
@@ -1135,9 +1173,9 @@ impl RelayManager {
                 // Attempt to connect to relay server for health check
                 match timeout(
                     Duration::from_secs(5),
-                    connect_async(server_clone.url.as_str())
+                    connect_relay(&server_clone.url, proxy.as_ref())
                 ).await {
-                    Ok(Ok((mut ws_stream, _))) => {
+                    Ok(Ok(mut ws_stream)) => {
                         let latency = start_time.elapsed();
                         
                         // Send ping and wait for pong
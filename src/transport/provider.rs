@@ -0,0 +1,576 @@
+// Shared connection provider
+//
+// Hands out one pooled, multiplexed session per peer so that every subsystem
+// (clipboard, file transfer, control, notifications) opens streams over the same
+// connection instead of dialing its own. Sessions that have no open streams and have
+// not been used for the idle timeout are evicted; closed sessions are dropped and
+// redialed on next use. Sessions that have heard nothing from their peer for the
+// stale timeout are evicted even with streams open, since the peer is most
+// likely gone without having closed the connection.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::error::KizunaResult;
+use crate::shutdown::{ShutdownHook, ShutdownReason, ShutdownStage};
+use crate::transport::api::{ConnectionHandle, KizunaTransport};
+use crate::transport::handshake::{perform_handshake, CapabilityFlags, NegotiatedProtocol, ProtocolHello};
+use crate::transport::mux::{Multiplexer, MuxConfig, MuxRole, MuxStream};
+use crate::transport::{
+    Connection, ConnectionInfo, DatagramChannel, PeerAddress, PeerId, Transport, TransportError,
+};
+
+/// Something that can establish a raw connection to a peer
+#[async_trait]
+pub trait PeerDialer: Send + Sync {
+    /// Dial `peer` and return the established connection
+    async fn dial(&self, peer: &PeerAddress) -> Result<Box<dyn Connection>, TransportError>;
+}
+
+#[async_trait]
+impl<T: Transport> PeerDialer for T {
+    async fn dial(&self, peer: &PeerAddress) -> Result<Box<dyn Connection>, TransportError> {
+        self.connect(peer).await
+    }
+}
+
+#[async_trait]
+impl PeerDialer for KizunaTransport {
+    async fn dial(&self, peer: &PeerAddress) -> Result<Box<dyn Connection>, TransportError> {
+        let handle = self.connect_to_peer(peer).await?;
+        let info = handle.info().await;
+        let datagrams = handle.datagrams().await;
+        Ok(Box::new(HandleConnection {
+            handle,
+            info,
+            connected: true,
+            datagrams,
+        }))
+    }
+}
+
+/// Adapts a `ConnectionHandle` to the `Connection` trait
+struct HandleConnection {
+    handle: ConnectionHandle,
+    info: ConnectionInfo,
+    connected: bool,
+    datagrams: Option<Arc<dyn DatagramChannel>>,
+}
+
+impl std::fmt::Debug for HandleConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandleConnection")
+            .field("info", &self.info)
+            .field("connected", &self.connected)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Connection for HandleConnection {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, TransportError> {
+        let result = self.handle.read(buf).await;
+        if !matches!(result, Ok(n) if n > 0) {
+            self.connected = false;
+        }
+        result
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, TransportError> {
+        self.handle.write(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), TransportError> {
+        self.handle.flush().await
+    }
+
+    async fn close(&mut self) -> Result<(), TransportError> {
+        self.connected = false;
+        self.handle.close().await
+    }
+
+    fn info(&self) -> ConnectionInfo {
+        self.info.clone()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn datagrams(&self) -> Option<Arc<dyn DatagramChannel>> {
+        self.datagrams.clone()
+    }
+}
+
+/// Told about every session a provider establishes
+pub trait SessionObserver: Send + Sync {
+    /// `session` with `peer_id` is ready; `role` is `Initiator` when we dialed
+    ///
+    /// Called with the peer's pool slot locked, so anything that uses the
+    /// provider has to be spawned rather than awaited here.
+    fn session_established(&self, peer_id: &PeerId, session: &Arc<Multiplexer>, role: MuxRole);
+}
+
+/// Configuration for a `ConnectionProvider`
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    /// How long a session with no open streams is kept before eviction
+    pub idle_timeout: Duration,
+    /// How long a session is kept after its peer last sent anything, even
+    /// with streams open
+    pub stale_timeout: Duration,
+    /// How often the background eviction task runs
+    pub eviction_interval: Duration,
+    /// Timeout for dialing and handshaking a new session
+    pub connect_timeout: Duration,
+    /// Stream multiplexing settings for every session
+    pub mux: MuxConfig,
+    /// Hello exchanged before multiplexing starts; `None` skips the handshake
+    pub handshake: Option<ProtocolHello>,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(120),
+            stale_timeout: Duration::from_secs(30 * 60),
+            eviction_interval: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(30),
+            mux: MuxConfig::default(),
+            handshake: None,
+        }
+    }
+}
+
+/// Snapshot of provider activity
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderStats {
+    /// Sessions currently pooled
+    pub active_sessions: usize,
+    /// Streams open across all sessions
+    pub open_streams: usize,
+    /// Requests served from an existing session
+    pub reused: u64,
+    /// Sessions established by dialing
+    pub dialed: u64,
+    /// Sessions registered from inbound connections
+    pub adopted: u64,
+    /// Dial or handshake attempts that failed
+    pub dial_failures: u64,
+    /// Sessions dropped for being idle or closed
+    pub evicted: u64,
+}
+
+/// A session held by the pool
+#[derive(Debug)]
+struct PooledSession {
+    mux: Arc<Multiplexer>,
+    negotiated: Option<NegotiatedProtocol>,
+    last_used: Instant,
+}
+
+impl PooledSession {
+    fn is_usable(&self) -> bool {
+        !self.mux.is_closed()
+    }
+}
+
+/// Per-peer slot; the async lock serializes dials to the same peer only
+type SessionSlot = Arc<Mutex<Option<PooledSession>>>;
+
+/// Crate-wide source of pooled, multiplexed peer connections
+pub struct ConnectionProvider {
+    dialer: Arc<dyn PeerDialer>,
+    config: ProviderConfig,
+    slots: StdMutex<HashMap<PeerId, SessionSlot>>,
+    stats: StdMutex<ProviderStats>,
+    observers: StdMutex<Vec<Arc<dyn SessionObserver>>>,
+}
+
+impl ConnectionProvider {
+    /// Create a provider that dials new sessions with `dialer`
+    pub fn new(dialer: Arc<dyn PeerDialer>, config: ProviderConfig) -> Self {
+        Self {
+            dialer,
+            config,
+            slots: StdMutex::new(HashMap::new()),
+            stats: StdMutex::new(ProviderStats::default()),
+            observers: StdMutex::new(Vec::new()),
+        }
+    }
+
+    /// Tell `observer` about every session established from now on
+    pub fn observe_sessions(&self, observer: Arc<dyn SessionObserver>) {
+        self.observers.lock().unwrap().push(observer);
+    }
+
+    /// Provider configuration
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    /// Get the pooled session for a peer, dialing one if needed
+    pub async fn session(&self, peer: &PeerAddress) -> Result<Arc<Multiplexer>, TransportError> {
+        let slot = self.slot(&peer.peer_id);
+        let mut guard = slot.lock().await;
+
+        if let Some(pooled) = guard.as_mut().filter(|pooled| pooled.is_usable()) {
+            pooled.last_used = Instant::now();
+            self.stats.lock().unwrap().reused += 1;
+            return Ok(Arc::clone(&pooled.mux));
+        }
+
+        let result = tokio::time::timeout(self.config.connect_timeout, async {
+            let connection = self.dialer.dial(peer).await?;
+            self.establish(connection, MuxRole::Initiator).await
+        })
+        .await
+        .unwrap_or(Err(TransportError::ConnectionTimeout {
+            timeout: self.config.connect_timeout,
+        }));
+
+        match result {
+            Ok(pooled) => {
+                let mux = Arc::clone(&pooled.mux);
+                *guard = Some(pooled);
+                self.stats.lock().unwrap().dialed += 1;
+                self.notify_established(&peer.peer_id, &mux, MuxRole::Initiator);
+                Ok(mux)
+            }
+            Err(e) => {
+                *guard = None;
+                self.stats.lock().unwrap().dial_failures += 1;
+                Err(e)
+            }
+        }
+    }
+
+    /// Open a stream to a peer on the given channel
+    pub async fn open_stream(&self, peer: &PeerAddress, channel: &str) -> Result<MuxStream, TransportError> {
+        let session = self.session(peer).await?;
+        session.open_stream(channel)
+    }
+
+    /// Pooled session for a peer, without dialing
+    pub fn existing_session(&self, peer_id: &PeerId) -> Option<Arc<Multiplexer>> {
+        let slot = self.slots.lock().unwrap().get(peer_id).cloned()?;
+        let mut guard = slot.try_lock().ok()?;
+        let pooled = guard.as_mut().filter(|pooled| pooled.is_usable())?;
+        pooled.last_used = Instant::now();
+        Some(Arc::clone(&pooled.mux))
+    }
+
+    /// Protocol parameters negotiated with a peer, if it has a pooled session
+    pub fn negotiated_protocol(&self, peer_id: &PeerId) -> Option<NegotiatedProtocol> {
+        let slot = self.slots.lock().unwrap().get(peer_id).cloned()?;
+        let guard = slot.try_lock().ok()?;
+        guard.as_ref().and_then(|pooled| pooled.negotiated.clone())
+    }
+
+    /// Register a session for an inbound connection
+    ///
+    /// Replaces any existing session for the same peer.
+    pub async fn adopt(&self, connection: Box<dyn Connection>) -> Result<Arc<Multiplexer>, TransportError> {
+        let peer_id = connection.info().peer_id;
+        let pooled = match self.establish(connection, MuxRole::Responder).await {
+            Ok(pooled) => pooled,
+            Err(e) => {
+                self.stats.lock().unwrap().dial_failures += 1;
+                return Err(e);
+            }
+        };
+
+        let mux = Arc::clone(&pooled.mux);
+        let slot = self.slot(&peer_id);
+        let mut guard = slot.lock().await;
+        *guard = Some(pooled);
+        self.stats.lock().unwrap().adopted += 1;
+        self.notify_established(&peer_id, &mux, MuxRole::Responder);
+        Ok(mux)
+    }
+
+    /// Close and forget the session for a peer
+    pub async fn remove(&self, peer_id: &PeerId) {
+        let slot = self.slots.lock().unwrap().remove(peer_id);
+        if let Some(slot) = slot && let Some(pooled) = slot.lock().await.take() {
+            pooled.mux.close();
+        }
+    }
+
+    /// Say goodbye to every pooled session and empty the pool
+    ///
+    /// Peers are told `reason` before their connection closes, so they see a
+    /// deliberate departure rather than a network failure. Returns the number of
+    /// sessions closed.
+    pub async fn close_all(&self, reason: &str) -> usize {
+        let slots: Vec<SessionSlot> = self.slots.lock().unwrap().drain().map(|(_, slot)| slot).collect();
+        let mut closed = 0;
+        for slot in slots {
+            if let Some(pooled) = slot.lock().await.take() && pooled.is_usable() {
+                let _ = pooled.mux.goodbye(reason);
+                closed += 1;
+            }
+        }
+        closed
+    }
+
+    /// Evict sessions that are closed, idle with no open streams, or stale
+    ///
+    /// Returns the number of sessions evicted. Slots that are busy dialing are skipped.
+    pub fn evict_idle(&self) -> usize {
+        let mut slots = self.slots.lock().unwrap();
+        let mut evicted = Vec::new();
+
+        for (peer_id, slot) in slots.iter() {
+            let Ok(mut guard) = slot.try_lock() else {
+                continue;
+            };
+            let expired = match guard.as_ref() {
+                Some(pooled) => {
+                    !pooled.is_usable()
+                        || (pooled.mux.stream_count() == 0
+                            && pooled.last_used.elapsed() >= self.config.idle_timeout)
+                        || pooled.mux.idle_time() >= self.config.stale_timeout
+                }
+                None => true,
+            };
+            if expired {
+                if let Some(pooled) = guard.take() {
+                    pooled.mux.close();
+                }
+                evicted.push(peer_id.clone());
+            }
+        }
+
+        let count = evicted.len();
+        for peer_id in evicted {
+            slots.remove(&peer_id);
+        }
+        drop(slots);
+
+        self.stats.lock().unwrap().evicted += count as u64;
+        count
+    }
+
+    /// Spawn a task that evicts idle sessions periodically
+    ///
+    /// The task stops once the provider is dropped.
+    pub fn start_eviction(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let provider: Weak<Self> = Arc::downgrade(self);
+        let interval = self.config.eviction_interval;
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match provider.upgrade() {
+                    Some(provider) => {
+                        provider.evict_idle();
+                    }
+                    None => break,
+                }
+            }
+        })
+    }
+
+    /// Current pool statistics
+    pub fn stats(&self) -> ProviderStats {
+        let slots: Vec<SessionSlot> = self.slots.lock().unwrap().values().cloned().collect();
+        let mut stats = self.stats.lock().unwrap().clone();
+
+        stats.active_sessions = 0;
+        stats.open_streams = 0;
+        for slot in slots {
+            if let Ok(guard) = slot.try_lock()
+                && let Some(pooled) = guard.as_ref().filter(|pooled| pooled.is_usable())
+            {
+                stats.active_sessions += 1;
+                stats.open_streams += pooled.mux.stream_count();
+            }
+        }
+        stats
+    }
+
+    fn slot(&self, peer_id: &PeerId) -> SessionSlot {
+        Arc::clone(
+            self.slots
+                .lock()
+                .unwrap()
+                .entry(peer_id.clone())
+                .or_insert_with(|| Arc::new(Mutex::new(None))),
+        )
+    }
+
+    fn notify_established(&self, peer_id: &PeerId, session: &Arc<Multiplexer>, role: MuxRole) {
+        let observers = self.observers.lock().unwrap().clone();
+        for observer in observers {
+            observer.session_established(peer_id, session, role);
+        }
+    }
+
+    async fn establish(
+        &self,
+        mut connection: Box<dyn Connection>,
+        role: MuxRole,
+    ) -> Result<PooledSession, TransportError> {
+        let negotiated = match &self.config.handshake {
+            Some(hello) => {
+                let negotiated = match perform_handshake(connection.as_mut(), hello, self.config.connect_timeout).await {
+                    Ok(negotiated) => negotiated,
+                    Err(e) => {
+                        let _ = connection.close().await;
+                        return Err(e);
+                    }
+                };
+                if let Err(e) = negotiated.ensure(CapabilityFlags::MULTIPLEXING) {
+                    let _ = connection.close().await;
+                    return Err(e);
+                }
+                Some(negotiated)
+            }
+            None => None,
+        };
+
+        Ok(PooledSession {
+            mux: Arc::new(Multiplexer::new(connection, role, self.config.mux.clone())),
+            negotiated,
+            last_used: Instant::now(),
+        })
+    }
+}
+
+#[async_trait]
+impl ShutdownHook for ConnectionProvider {
+    fn name(&self) -> &str {
+        "transport sessions"
+    }
+
+    fn stage(&self) -> ShutdownStage {
+        ShutdownStage::Disconnect
+    }
+
+    async fn shutdown(&self, reason: &ShutdownReason) -> KizunaResult<()> {
+        self.close_all(&reason.to_string()).await;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ConnectionProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionProvider")
+            .field("config", &self.config)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::transport::MEMORY_PROTOCOL;
+    use crate::testing::{MemoryTransport, VirtualNetwork};
+    use crate::transport::mux::CHANNEL_CLIPBOARD;
+
+    async fn setup(config: ProviderConfig) -> (ConnectionProvider, Arc<MemoryTransport>, PeerAddress) {
+        let network = VirtualNetwork::new();
+        let server = Arc::new(MemoryTransport::new("server", network.clone()));
+        let client = Arc::new(MemoryTransport::new("client", network.clone()));
+
+        let addr = network.allocate_address(41000);
+        server.listen(&addr).await.unwrap();
+
+        let peer = PeerAddress::new(
+            "server".to_string(),
+            vec![addr],
+            vec![MEMORY_PROTOCOL.to_string()],
+            server.capabilities(),
+        );
+        (ConnectionProvider::new(client, config), server, peer)
+    }
+
+    #[tokio::test]
+    async fn test_session_reused_across_subsystems() {
+        let (provider, server, peer) = setup(ProviderConfig::default()).await;
+        let remote = ConnectionProvider::new(Arc::clone(&server) as Arc<dyn PeerDialer>, ProviderConfig::default());
+
+        let clipboard = provider.open_stream(&peer, CHANNEL_CLIPBOARD).await.unwrap();
+        let _control = provider.open_stream(&peer, "control").await.unwrap();
+
+        let stats = provider.stats();
+        assert_eq!(stats.dialed, 1);
+        assert_eq!(stats.reused, 1);
+        assert_eq!(stats.active_sessions, 1);
+        assert_eq!(stats.open_streams, 2);
+
+        let session = remote.adopt(Box::new(server.accept().await.unwrap())).await.unwrap();
+        clipboard.send_message(b"hi").await.unwrap();
+        let inbound = session.accept_stream().await.unwrap();
+        assert_eq!(inbound.channel(), CHANNEL_CLIPBOARD);
+        assert_eq!(inbound.recv_message(16).await.unwrap().unwrap(), b"hi");
+    }
+
+    #[tokio::test]
+    async fn test_idle_sessions_evicted() {
+        let config = ProviderConfig {
+            idle_timeout: Duration::ZERO,
+            ..Default::default()
+        };
+        let (provider, _server, peer) = setup(config).await;
+
+        let stream = provider.open_stream(&peer, CHANNEL_CLIPBOARD).await.unwrap();
+        assert_eq!(provider.evict_idle(), 0, "session with open streams is kept");
+
+        drop(stream);
+        assert_eq!(provider.evict_idle(), 1);
+        assert!(provider.existing_session(&peer.peer_id).is_none());
+        assert_eq!(provider.stats().evicted, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stale_sessions_evicted_with_open_streams() {
+        let config = ProviderConfig {
+            stale_timeout: Duration::ZERO,
+            ..Default::default()
+        };
+        let (provider, _server, peer) = setup(config).await;
+
+        let _stream = provider.open_stream(&peer, CHANNEL_CLIPBOARD).await.unwrap();
+        assert_eq!(provider.evict_idle(), 1);
+        assert!(provider.existing_session(&peer.peer_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_close_all_says_goodbye() {
+        let (provider, server, peer) = setup(ProviderConfig::default()).await;
+        let remote = ConnectionProvider::new(Arc::clone(&server) as Arc<dyn PeerDialer>, ProviderConfig::default());
+
+        let _stream = provider.open_stream(&peer, CHANNEL_CLIPBOARD).await.unwrap();
+        let session = remote.adopt(Box::new(server.accept().await.unwrap())).await.unwrap();
+
+        assert_eq!(provider.close_all("Shutting down").await, 1);
+        assert_eq!(provider.stats().active_sessions, 0);
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while session.remote_goodbye().is_none() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(session.remote_goodbye().as_deref(), Some("Shutting down"));
+    }
+
+    #[tokio::test]
+    async fn test_dial_failure_counted() {
+        let (provider, _server, mut peer) = setup(ProviderConfig::default()).await;
+        peer.peer_id = "nobody".to_string();
+
+        assert!(provider.session(&peer).await.is_err());
+        assert_eq!(provider.stats().dial_failures, 1);
+    }
+}
@@ -11,6 +11,7 @@ use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::security::policy::{IpEntryPoint, IpFilter};
 use crate::transport::{
     PeerId, TransportError
 };
@@ -586,6 +587,8 @@ pub struct RelayManager {
     stats: Arc<RwLock<RelayStats>>,
     /// Connection counter
     connection_counter: AtomicUsize,
+    /// Address filter applied to incoming relay connections
+    ip_filter: Option<Arc<IpFilter>>,
 }
 
 impl RelayManager {
@@ -604,9 +607,16 @@ impl RelayManager {
             active_sessions: Arc::new(RwLock::new(HashMap::new())),
             stats: Arc::new(RwLock::new(RelayStats::default())),
             connection_counter: AtomicUsize::new(0),
+            ip_filter: None,
         }
     }
 
+    /// Refuse incoming relay connections from addresses the filter blocks
+    pub fn with_ip_filter(mut self, ip_filter: Arc<IpFilter>) -> Self {
+        self.ip_filter = Some(ip_filter);
+        self
+    }
+
     /// Register a relay node
     pub async fn register_relay_node(&self, node_info: RelayNodeInfo) -> Result<(), TransportError> {
         let mut nodes = self.relay_nodes.write().await;
@@ -954,8 +964,8 @@ impl RelayManager {
         }
     }
 
-    /// Clean up idle relay sessions
-    pub async fn cleanup_idle_sessions(&self) -> Result<(), TransportError> {
+    /// Clean up idle relay sessions, returning how many were removed
+    pub async fn cleanup_idle_sessions(&self) -> Result<usize, TransportError> {
         let mut sessions_to_remove = Vec::new();
         
         {
@@ -967,11 +977,12 @@ impl RelayManager {
             }
         }
 
+        let removed = sessions_to_remove.len();
         for session_id in sessions_to_remove {
             self.remove_relay_session(&session_id).await?;
         }
 
-        Ok(())
+        Ok(removed)
     }
 
     /// Get current relay statistics
@@ -1020,6 +1031,14 @@ impl RelayManager {
         loop {
             match listener.accept().await {
                 Ok((stream, addr)) => {
+                    // Dropping the stream closes it before the WebSocket upgrade
+                    if let Some(ip_filter) = &manager_clone.ip_filter
+                        && let Err(e) = ip_filter.check(addr.ip(), IpEntryPoint::Relay)
+                    {
+                        log::debug!("Refused relay connection: {}", e);
+                        continue;
+                    }
+
                     let manager = manager_clone.clone();
                     tokio::spawn(async move {
                         if let Err(e) = manager.handle_relay_connection(stream, addr).await {
@@ -1227,6 +1246,7 @@ impl Clone for RelayManager {
             global_bandwidth_limiter: BandwidthLimiter::new(self.config.total_bandwidth_limit),
             stats: self.stats.clone(),
             connection_counter: AtomicUsize::new(self.connection_counter.load(Ordering::Relaxed)),
+            ip_filter: self.ip_filter.clone(),
         }
     }
 }
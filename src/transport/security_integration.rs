@@ -6,7 +6,7 @@ use crate::security::{Security, SecurityResult};
 use crate::security::encryption::{EncryptionEngine, SessionId};
 use crate::security::identity::PeerId;
 use crate::security::policy::{PolicyEngine, ConnectionType, SecurityEvent, SecurityEventType};
-use crate::transport::{TransportError, Connection, ConnectionInfo};
+use crate::transport::{TransportError, Connection, ConnectionInfo, DatagramChannel, Watermarks};
 
 /// Secure connection wrapper that automatically encrypts/decrypts data
 pub struct SecureConnection {
@@ -106,6 +106,24 @@ impl Connection for SecureConnection {
     fn is_connected(&self) -> bool {
         self.inner.is_connected()
     }
+    
+    fn buffered_bytes(&self) -> usize {
+        self.inner.buffered_bytes()
+    }
+    
+    fn watermarks(&self) -> Watermarks {
+        self.inner.watermarks()
+    }
+    
+    async fn ready(&mut self) -> Result<(), TransportError> {
+        self.inner.ready().await
+    }
+    
+    fn datagrams(&self) -> Option<Arc<dyn DatagramChannel>> {
+        // Session replay protection expects nonces in order, which datagrams cannot
+        // guarantee, so encrypted connections only offer reliable delivery
+        None
+    }
 }
 
 /// Security hooks for transport layer
@@ -135,6 +153,18 @@ impl TransportSecurityHooks {
         }
     }
     
+    /// Screen an inbound connection before its handshake
+    ///
+    /// Refuses addresses banned for port scanning, so they never reach the
+    /// key exchange.
+    pub async fn screen_inbound_connection(
+        &self,
+        remote: std::net::SocketAddr,
+        local_port: u16,
+    ) -> SecurityResult<()> {
+        self.policy_engine.screen_connection_probe(remote.ip(), local_port).await
+    }
+    
     /// Validate connection attempt before establishing
     pub async fn validate_connection(
         &self,
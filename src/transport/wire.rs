@@ -0,0 +1,526 @@
+// Versioned wire format for Kizuna protocol messages
+//
+// Every message travels in a length-prefixed frame carrying a protocol version and a
+// message type tag:
+//
+//   0      2        3        4             6       7                11
+//   +------+--------+--------+-------------+-------+-----------------+---------
+//   | "KZ" | major  | minor  | type (u16)  | flags | length (u32 BE) | payload
+//   +------+--------+--------+-------------+-------+-----------------+---------
+//
+// Compatibility rules:
+// - A different major version is rejected outright.
+// - A newer minor version is accepted; decoders ignore trailing payload bytes they
+//   do not understand, so newer peers may only append fields.
+// - Unknown message types are surfaced as `MessageKind::Unknown` so receivers can
+//   skip them instead of tearing down the connection.
+//
+// All parse functions are total: they return `WireError` for any input and never
+// panic, which makes them suitable as cargo-fuzz targets (see `fuzz_parse`).
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+#[cfg(feature = "clipboard")]
+use crate::clipboard::ClipboardMessage;
+use crate::discovery::ServiceRecord;
+use crate::file_transfer::types::ChunkMetadata;
+use crate::transport::handshake::ProtocolHello;
+use crate::transport::mux::{MuxDatagram, MuxFrame};
+use crate::transport::RelayMessage;
+
+/// Frame magic bytes
+pub const WIRE_MAGIC: [u8; 2] = *b"KZ";
+
+/// Current protocol major version
+pub const WIRE_VERSION_MAJOR: u8 = 1;
+
+/// Current protocol minor version
+pub const WIRE_VERSION_MINOR: u8 = 0;
+
+/// Size of the fixed frame header in bytes
+pub const FRAME_HEADER_LEN: usize = 11;
+
+/// Default maximum payload size accepted by the decoder (16 MiB)
+pub const DEFAULT_MAX_PAYLOAD: usize = 16 * 1024 * 1024;
+
+/// Errors produced while encoding or decoding wire frames
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    #[error("Incomplete frame: need {needed} more bytes")]
+    Incomplete { needed: usize },
+
+    #[error("Invalid frame magic")]
+    BadMagic,
+
+    #[error("Unsupported protocol version {major}.{minor}")]
+    UnsupportedVersion { major: u8, minor: u8 },
+
+    #[error("Payload of {length} bytes exceeds limit of {limit}")]
+    PayloadTooLarge { length: usize, limit: usize },
+
+    #[error("Unexpected message type: expected {expected:?}, got {actual:?}")]
+    UnexpectedKind { expected: MessageKind, actual: MessageKind },
+
+    #[error("Malformed payload: {0}")]
+    Malformed(String),
+}
+
+/// Message type tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    DiscoveryAnnouncement,
+    RelayMessage,
+    ChunkHeader,
+    ClipboardMessage,
+    Handshake,
+    StreamFrame,
+    Datagram,
+    Benchmark,
+    /// A message type this build does not know; receivers should skip it
+    Unknown(u16),
+}
+
+impl MessageKind {
+    /// Numeric tag on the wire
+    pub fn code(&self) -> u16 {
+        match self {
+            MessageKind::DiscoveryAnnouncement => 0x0001,
+            MessageKind::RelayMessage => 0x0002,
+            MessageKind::ChunkHeader => 0x0003,
+            MessageKind::ClipboardMessage => 0x0004,
+            MessageKind::Handshake => 0x0005,
+            MessageKind::StreamFrame => 0x0006,
+            MessageKind::Datagram => 0x0007,
+            MessageKind::Benchmark => 0x0008,
+            MessageKind::Unknown(code) => *code,
+        }
+    }
+
+    /// Decode a numeric tag
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            0x0001 => MessageKind::DiscoveryAnnouncement,
+            0x0002 => MessageKind::RelayMessage,
+            0x0003 => MessageKind::ChunkHeader,
+            0x0004 => MessageKind::ClipboardMessage,
+            0x0005 => MessageKind::Handshake,
+            0x0006 => MessageKind::StreamFrame,
+            0x0007 => MessageKind::Datagram,
+            0x0008 => MessageKind::Benchmark,
+            other => MessageKind::Unknown(other),
+        }
+    }
+}
+
+/// Protocol version carried in each frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WireVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl WireVersion {
+    /// Version produced by this build
+    pub const CURRENT: WireVersion = WireVersion {
+        major: WIRE_VERSION_MAJOR,
+        minor: WIRE_VERSION_MINOR,
+    };
+
+    /// Whether frames of this version can be decoded by this build
+    pub fn is_compatible(&self) -> bool {
+        self.major == WIRE_VERSION_MAJOR
+    }
+}
+
+/// A decoded frame borrowing its payload from the input buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub version: WireVersion,
+    pub kind: MessageKind,
+    pub flags: u8,
+    pub payload: &'a [u8],
+}
+
+/// Encode a frame with the current protocol version
+pub fn encode_frame(kind: MessageKind, flags: u8, payload: &[u8]) -> Result<Vec<u8>, WireError> {
+    if payload.len() > u32::MAX as usize {
+        return Err(WireError::PayloadTooLarge {
+            length: payload.len(),
+            limit: u32::MAX as usize,
+        });
+    }
+
+    let mut out = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    out.extend_from_slice(&WIRE_MAGIC);
+    out.push(WIRE_VERSION_MAJOR);
+    out.push(WIRE_VERSION_MINOR);
+    out.extend_from_slice(&kind.code().to_be_bytes());
+    out.push(flags);
+    out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    out.extend_from_slice(payload);
+    Ok(out)
+}
+
+/// Parse one frame from the front of `input`
+///
+/// Returns the frame and the number of bytes consumed. `WireError::Incomplete`
+/// signals that more data is needed, which lets stream readers buffer and retry.
+pub fn parse_frame(input: &[u8], max_payload: usize) -> Result<(Frame<'_>, usize), WireError> {
+    if input.len() < FRAME_HEADER_LEN {
+        // Reject bad magic as early as possible so garbage streams fail fast
+        if input.len() >= 2 && input[..2] != WIRE_MAGIC {
+            return Err(WireError::BadMagic);
+        }
+        return Err(WireError::Incomplete {
+            needed: FRAME_HEADER_LEN - input.len(),
+        });
+    }
+
+    if input[..2] != WIRE_MAGIC {
+        return Err(WireError::BadMagic);
+    }
+
+    let version = WireVersion {
+        major: input[2],
+        minor: input[3],
+    };
+    if !version.is_compatible() {
+        return Err(WireError::UnsupportedVersion {
+            major: version.major,
+            minor: version.minor,
+        });
+    }
+
+    let kind = MessageKind::from_code(u16::from_be_bytes([input[4], input[5]]));
+    let flags = input[6];
+    let length = u32::from_be_bytes([input[7], input[8], input[9], input[10]]) as usize;
+
+    if length > max_payload {
+        return Err(WireError::PayloadTooLarge {
+            length,
+            limit: max_payload,
+        });
+    }
+
+    let total = FRAME_HEADER_LEN + length;
+    if input.len() < total {
+        return Err(WireError::Incomplete {
+            needed: total - input.len(),
+        });
+    }
+
+    Ok((
+        Frame {
+            version,
+            kind,
+            flags,
+            payload: &input[FRAME_HEADER_LEN..total],
+        },
+        total,
+    ))
+}
+
+/// A message type with a stable wire representation
+pub trait WireMessage: Sized {
+    /// Type tag for this message
+    const KIND: MessageKind;
+
+    /// Append the payload encoding to `out`
+    fn encode_payload(&self, out: &mut Vec<u8>) -> Result<(), WireError>;
+
+    /// Decode the payload produced by a peer speaking `version`
+    fn decode_payload(payload: &[u8], version: WireVersion) -> Result<Self, WireError>;
+
+    /// Encode into a complete frame
+    fn to_frame(&self) -> Result<Vec<u8>, WireError> {
+        let mut payload = Vec::new();
+        self.encode_payload(&mut payload)?;
+        encode_frame(Self::KIND, 0, &payload)
+    }
+
+    /// Decode from a parsed frame, checking the type tag
+    fn from_frame(frame: &Frame<'_>) -> Result<Self, WireError> {
+        if frame.kind != Self::KIND {
+            return Err(WireError::UnexpectedKind {
+                expected: Self::KIND,
+                actual: frame.kind,
+            });
+        }
+        Self::decode_payload(frame.payload, frame.version)
+    }
+}
+
+/// Bounds-checked reader over a payload
+pub struct WireReader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> WireReader<'a> {
+    /// Create a reader over `input`
+    pub fn new(input: &'a [u8]) -> Self {
+        Self { input, position: 0 }
+    }
+
+    /// Bytes not yet consumed
+    pub fn remaining(&self) -> usize {
+        self.input.len() - self.position
+    }
+
+    /// Read exactly `len` bytes
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], WireError> {
+        if self.remaining() < len {
+            return Err(WireError::Malformed(format!(
+                "Need {} bytes at offset {}, only {} remain",
+                len,
+                self.position,
+                self.remaining()
+            )));
+        }
+        let slice = &self.input[self.position..self.position + len];
+        self.position += len;
+        Ok(slice)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, WireError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, WireError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, WireError> {
+        let b = self.bytes(8)?;
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(b);
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, WireError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(WireError::Malformed(format!("Invalid boolean byte {}", other))),
+        }
+    }
+
+    /// Read a u32-length-prefixed UTF-8 string
+    pub fn string(&mut self, max_len: usize) -> Result<String, WireError> {
+        let len = self.u32()? as usize;
+        if len > max_len {
+            return Err(WireError::PayloadTooLarge { length: len, limit: max_len });
+        }
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| WireError::Malformed("Invalid UTF-8 string".to_string()))
+    }
+}
+
+/// Append a u32-length-prefixed string
+pub fn write_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Maximum length of a path inside a chunk header
+const MAX_PATH_LEN: usize = 4096;
+
+impl WireMessage for ChunkMetadata {
+    const KIND: MessageKind = MessageKind::ChunkHeader;
+
+    fn encode_payload(&self, out: &mut Vec<u8>) -> Result<(), WireError> {
+        let size = u32::try_from(self.size)
+            .map_err(|_| WireError::Malformed(format!("Chunk size {} exceeds u32", self.size)))?;
+        out.extend_from_slice(&self.chunk_id.to_be_bytes());
+        out.extend_from_slice(&self.offset.to_be_bytes());
+        out.extend_from_slice(&size.to_be_bytes());
+        out.extend_from_slice(&self.checksum);
+        out.push(self.compressed as u8);
+        write_string(out, &self.file_path.to_string_lossy());
+        Ok(())
+    }
+
+    fn decode_payload(payload: &[u8], _version: WireVersion) -> Result<Self, WireError> {
+        let mut reader = WireReader::new(payload);
+        let chunk_id = reader.u64()?;
+        let offset = reader.u64()?;
+        let size = reader.u32()? as usize;
+        let mut checksum = [0u8; 32];
+        checksum.copy_from_slice(reader.bytes(32)?);
+        let compressed = reader.bool()?;
+        let file_path = reader.string(MAX_PATH_LEN)?.into();
+        // Fields appended by newer minor versions are left unread
+
+        Ok(ChunkMetadata {
+            chunk_id,
+            file_path,
+            offset,
+            size,
+            checksum,
+            compressed,
+        })
+    }
+}
+
+/// Encode a serde value as a JSON payload
+fn encode_json<T: Serialize>(value: &T, out: &mut Vec<u8>) -> Result<(), WireError> {
+    serde_json::to_writer(out, value).map_err(|e| WireError::Malformed(e.to_string()))
+}
+
+/// Decode a JSON payload, tolerating unknown fields added by newer peers
+fn decode_json<T: DeserializeOwned>(payload: &[u8]) -> Result<T, WireError> {
+    serde_json::from_slice(payload).map_err(|e| WireError::Malformed(e.to_string()))
+}
+
+impl WireMessage for ServiceRecord {
+    const KIND: MessageKind = MessageKind::DiscoveryAnnouncement;
+
+    fn encode_payload(&self, out: &mut Vec<u8>) -> Result<(), WireError> {
+        encode_json(self, out)
+    }
+
+    fn decode_payload(payload: &[u8], _version: WireVersion) -> Result<Self, WireError> {
+        decode_json(payload)
+    }
+}
+
+impl WireMessage for RelayMessage {
+    const KIND: MessageKind = MessageKind::RelayMessage;
+
+    fn encode_payload(&self, out: &mut Vec<u8>) -> Result<(), WireError> {
+        encode_json(self, out)
+    }
+
+    fn decode_payload(payload: &[u8], _version: WireVersion) -> Result<Self, WireError> {
+        decode_json(payload)
+    }
+}
+
+#[cfg(feature = "clipboard")]
+impl WireMessage for ClipboardMessage {
+    const KIND: MessageKind = MessageKind::ClipboardMessage;
+
+    fn encode_payload(&self, out: &mut Vec<u8>) -> Result<(), WireError> {
+        encode_json(self, out)
+    }
+
+    fn decode_payload(payload: &[u8], _version: WireVersion) -> Result<Self, WireError> {
+        decode_json(payload)
+    }
+}
+
+/// Entry point for fuzz targets: runs every parser over arbitrary input
+///
+/// A cargo-fuzz target only needs `kizuna::transport::wire::fuzz_parse(data)`.
+pub fn fuzz_parse(data: &[u8]) {
+    let mut rest = data;
+    while let Ok((frame, consumed)) = parse_frame(rest, DEFAULT_MAX_PAYLOAD) {
+        let _ = match frame.kind {
+            MessageKind::ChunkHeader => ChunkMetadata::from_frame(&frame).map(|_| ()),
+            MessageKind::DiscoveryAnnouncement => ServiceRecord::from_frame(&frame).map(|_| ()),
+            MessageKind::RelayMessage => RelayMessage::from_frame(&frame).map(|_| ()),
+            #[cfg(feature = "clipboard")]
+            MessageKind::ClipboardMessage => ClipboardMessage::from_frame(&frame).map(|_| ()),
+            MessageKind::Handshake => ProtocolHello::from_frame(&frame).map(|_| ()),
+            MessageKind::StreamFrame => MuxFrame::from_frame(&frame).map(|_| ()),
+            MessageKind::Datagram => MuxDatagram::from_frame(&frame).map(|_| ()),
+            #[cfg(not(feature = "clipboard"))]
+            MessageKind::ClipboardMessage => Ok(()),
+            MessageKind::Benchmark | MessageKind::Unknown(_) => Ok(()),
+        };
+        rest = &rest[consumed..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sample_chunk() -> ChunkMetadata {
+        ChunkMetadata {
+            chunk_id: 7,
+            file_path: PathBuf::from("docs/report.pdf"),
+            offset: 65536,
+            size: 4096,
+            checksum: [0xab; 32],
+            compressed: true,
+        }
+    }
+
+    #[test]
+    fn test_chunk_header_round_trip() {
+        let encoded = sample_chunk().to_frame().unwrap();
+        let (frame, consumed) = parse_frame(&encoded, DEFAULT_MAX_PAYLOAD).unwrap();
+        assert_eq!(consumed, encoded.len());
+        assert_eq!(frame.kind, MessageKind::ChunkHeader);
+
+        let decoded = ChunkMetadata::from_frame(&frame).unwrap();
+        assert_eq!(decoded.chunk_id, 7);
+        assert_eq!(decoded.offset, 65536);
+        assert_eq!(decoded.file_path, PathBuf::from("docs/report.pdf"));
+        assert!(decoded.compressed);
+    }
+
+    #[test]
+    fn test_truncated_input_is_incomplete() {
+        let encoded = sample_chunk().to_frame().unwrap();
+        for len in 0..encoded.len() {
+            match parse_frame(&encoded[..len], DEFAULT_MAX_PAYLOAD) {
+                Err(WireError::Incomplete { .. }) => {}
+                other => panic!("length {} gave {:?}", len, other.map(|(f, _)| f.kind)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_version_rules() {
+        let mut encoded = sample_chunk().to_frame().unwrap();
+
+        // Newer minor version with an appended field still decodes
+        encoded[3] = WIRE_VERSION_MINOR + 1;
+        encoded.push(0xff);
+        let new_len = (encoded.len() - FRAME_HEADER_LEN) as u32;
+        encoded[7..11].copy_from_slice(&new_len.to_be_bytes());
+        let (frame, _) = parse_frame(&encoded, DEFAULT_MAX_PAYLOAD).unwrap();
+        assert!(ChunkMetadata::from_frame(&frame).is_ok());
+
+        // Different major version is rejected
+        encoded[2] = WIRE_VERSION_MAJOR + 1;
+        assert!(matches!(
+            parse_frame(&encoded, DEFAULT_MAX_PAYLOAD),
+            Err(WireError::UnsupportedVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unknown_kind_is_skippable() {
+        let encoded = encode_frame(MessageKind::Unknown(0x7777), 0, b"future").unwrap();
+        let (frame, _) = parse_frame(&encoded, DEFAULT_MAX_PAYLOAD).unwrap();
+        assert_eq!(frame.kind, MessageKind::Unknown(0x7777));
+        assert!(matches!(
+            ChunkMetadata::from_frame(&frame),
+            Err(WireError::UnexpectedKind { .. })
+        ));
+    }
+
+    #[test]
+    fn test_oversized_and_garbage_rejected() {
+        let encoded = encode_frame(MessageKind::RelayMessage, 0, &[0u8; 64]).unwrap();
+        assert!(matches!(parse_frame(&encoded, 32), Err(WireError::PayloadTooLarge { .. })));
+        assert_eq!(parse_frame(b"GARBAGE-DATA", DEFAULT_MAX_PAYLOAD), Err(WireError::BadMagic));
+    }
+
+    #[test]
+    fn test_fuzz_parse_handles_arbitrary_bytes() {
+        let mut data = sample_chunk().to_frame().unwrap();
+        data.extend_from_slice(&encode_frame(MessageKind::ClipboardMessage, 0, b"{not json").unwrap());
+        data.extend_from_slice(&[0x4b, 0x5a, 0x01, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x02, 0x01]);
+        fuzz_parse(&data);
+        fuzz_parse(&[]);
+        fuzz_parse(&[0xff; 37]);
+    }
+}
@@ -0,0 +1,48 @@
+// Runs the built kizuna binary to check that commands the discovery front end
+// does not know about reach the full command-line interface
+
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Run kizuna with its home and data directories in a scratch folder
+fn kizuna(args: &[&str]) -> std::process::Output {
+    let home = TempDir::new().unwrap();
+    Command::new(env!("CARGO_BIN_EXE_kizuna"))
+        .args(args)
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path().join("config"))
+        .env("XDG_DATA_HOME", home.path().join("data"))
+        .output()
+        .expect("failed to run kizuna")
+}
+
+#[test]
+fn test_cli_commands_are_routed() {
+    // A missing argument is reported by the CLI parser, not as an unknown command
+    let output = kizuna(&["ping"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Unknown command"));
+    assert!(stderr.contains("<PEER>"), "unexpected error: {}", stderr);
+}
+
+#[test]
+fn test_command_help_succeeds() {
+    let output = kizuna(&["send", "--help"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--dry-run"));
+}
+
+#[test]
+fn test_unknown_command_fails() {
+    let output = kizuna(&["no-such-command"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_non_interactive_wipe_needs_confirmation() {
+    let output = kizuna(&["wipe", "--all", "--non-interactive"]);
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--yes"));
+}
@@ -2,15 +2,17 @@
 
 use kizuna::cli::config::{
     TOMLConfigParser, ProfileManager, ConfigMerger, ParsedArgs,
-    load_or_create_config, ValidationResult,
+    load_or_create_config, ValidationResult, EnvLookup, CONFIG_SCHEMA_VERSION,
 };
 use kizuna::cli::types::{CLIConfig, ConfigProfile, OutputFormat, ColorMode};
+use kizuna::security::{EncryptedStorage, StorageKey};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[tokio::test]
 async fn test_toml_parser_serialize_deserialize() {
     let config = CLIConfig::default();
-    let parser = TOMLConfigParser::new(None).unwrap();
+    let parser = TOMLConfigParser::new(None, None).unwrap();
     
     // Serialize to TOML
     let toml_str = parser.serialize_toml(&config).unwrap();
@@ -25,7 +27,7 @@ async fn test_toml_parser_serialize_deserialize() {
 #[tokio::test]
 async fn test_config_validation() {
     let mut config = CLIConfig::default();
-    let parser = TOMLConfigParser::new(None).unwrap();
+    let parser = TOMLConfigParser::new(None, None).unwrap();
     
     // Valid configuration
     let validation = parser.validate(&config);
@@ -212,3 +214,96 @@ async fn test_conflict_detection() {
     assert!(!conflicts.is_empty());
     assert_eq!(conflicts.len(), 2); // compression and output_format differ
 }
+
+#[tokio::test]
+async fn test_unversioned_config_is_migrated_and_backed_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+
+    // Files written before versioning have no schema_version and may omit [profiles]
+    let v1 = TOMLConfigParser::generate_default_with_comments()
+        .lines()
+        .filter(|line| !line.starts_with("schema_version") && *line != "[profiles]")
+        .collect::<Vec<_>>()
+        .join("\n");
+    tokio::fs::write(&path, &v1).await.unwrap();
+
+    let parser = TOMLConfigParser::new(Some(path.clone()), None).unwrap();
+    let config = parser.load().await.unwrap();
+    assert!(config.profiles.is_empty());
+
+    let backup = dir.path().join("config.toml.v1.bak");
+    assert_eq!(tokio::fs::read_to_string(&backup).await.unwrap(), v1);
+    let rewritten = tokio::fs::read_to_string(&path).await.unwrap();
+    assert!(rewritten.contains(&format!("schema_version = {}", CONFIG_SCHEMA_VERSION)));
+
+    // Already current: nothing to do
+    assert!(parser.migrate().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_config_is_encrypted_at_rest() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    let storage = EncryptedStorage::new(StorageKey::from_bytes([5; 32]));
+    let mut config = CLIConfig::default();
+    config.default_peer = Some("office-desktop".to_string());
+
+    let parser = TOMLConfigParser::new(Some(path.clone()), Some(storage)).unwrap();
+    parser.save(&config).await.unwrap();
+    let raw = tokio::fs::read(&path).await.unwrap();
+    assert!(!String::from_utf8_lossy(&raw).contains("office-desktop"));
+    assert_eq!(parser.load().await.unwrap().default_peer.as_deref(), Some("office-desktop"));
+
+    // Without the key the sealed file is refused rather than read as garbage
+    let plaintext = TOMLConfigParser::new(Some(path), None).unwrap();
+    assert!(plaintext.load().await.is_err());
+}
+
+#[tokio::test]
+async fn test_future_config_version_is_rejected() {
+    let parser = TOMLConfigParser::new(None, None).unwrap();
+    let content = parser.serialize_toml(&CLIConfig::default()).unwrap().replace(
+        &format!("schema_version = {}", CONFIG_SCHEMA_VERSION),
+        &format!("schema_version = {}", CONFIG_SCHEMA_VERSION + 1),
+    );
+
+    let err = parser.parse_toml(&content).unwrap_err().to_string();
+    assert!(err.contains("upgrade kizuna"), "unexpected error: {}", err);
+}
+
+#[tokio::test]
+async fn test_secret_reference_is_resolved_and_kept_on_save() {
+    let env: EnvLookup = Arc::new(|name| {
+        (name == "KIZUNA_TEST_DEFAULT_PEER").then(|| "office-desktop".to_string())
+    });
+    let parser = TOMLConfigParser::new(None, None).unwrap().with_env(env);
+    let content = parser
+        .serialize_toml(&CLIConfig::default())
+        .unwrap()
+        .replacen('\n', "\ndefault_peer = \"env:KIZUNA_TEST_DEFAULT_PEER\"\n", 1);
+
+    let config = parser.parse_toml(&content).unwrap();
+    assert_eq!(config.default_peer.as_deref(), Some("office-desktop"));
+
+    let saved = parser.serialize_toml(&config).unwrap();
+    assert!(saved.contains("env:KIZUNA_TEST_DEFAULT_PEER"));
+    assert!(!saved.contains("office-desktop"));
+}
+
+#[tokio::test]
+async fn test_peer_overrides_from_toml() {
+    let parser = TOMLConfigParser::new(None, None).unwrap();
+    let content = format!(
+        "{}\n[peers.\"NAS\"]\nbandwidth_limit = 5000000\n\n[peers.phone]\ncompression = false\n\n[peers.\"work-laptop\"]\nstreaming = false\n",
+        parser.serialize_toml(&CLIConfig::default()).unwrap()
+    );
+
+    let config = parser.parse_toml(&content).unwrap();
+    assert_eq!(config.peers.get("nas").and_then(|p| p.bandwidth_limit), Some(5_000_000));
+    assert!(!config.transfer_settings_for("phone").compression);
+    assert!(config.transfer_settings_for("nas").compression);
+    assert!(!config.peers.for_peer("work-laptop").allows_streaming());
+    assert!(config.peers.for_peer("work-laptop").allows_clipboard_sync());
+    assert!(parser.validate(&config).is_valid());
+}
@@ -1,6 +1,8 @@
 // Integration test for file transfer chunk engine
 
-use kizuna::file_transfer::{ChunkEngine, ChunkEngineImpl};
+use kizuna::file_transfer::{
+    ChunkEngine, ChunkEngineImpl, FsyncPolicy, HashAlgorithm, PathSanitizer, PlatformFamily, TransferManifest,
+};
 use std::fs;
 use tempfile::TempDir;
 
@@ -73,3 +75,113 @@ async fn test_small_file_chunking() {
     let output_data = fs::read_to_string(&output_path).unwrap();
     assert_eq!(output_data, test_data);
 }
+
+#[tokio::test]
+async fn test_hash_algorithm_selection() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("input.bin");
+    let output_path = temp_dir.path().join("output.bin");
+    fs::write(&input_path, vec![7u8; 300_000]).unwrap();
+
+    let blake3 = ChunkEngineImpl::new();
+    let sha256 = ChunkEngineImpl::new().with_hash_algorithm(HashAlgorithm::Sha256);
+    assert_eq!(blake3.hash_algorithm(), HashAlgorithm::Blake3);
+
+    // Chunks only verify with the algorithm they were created with
+    let chunks = sha256.create_chunks(input_path).await.unwrap();
+    assert!(sha256.verify_chunk(&chunks[0]).await.unwrap());
+    assert!(!blake3.verify_chunk(&chunks[0]).await.unwrap());
+
+    sha256.reassemble_file(chunks, output_path.clone()).await.unwrap();
+    assert_eq!(fs::read(&output_path).unwrap().len(), 300_000);
+
+    // Manifests from peers without algorithm selection are SHA-256
+    let mut value = serde_json::to_value(TransferManifest::new("peer".to_string())).unwrap();
+    value.as_object_mut().unwrap().remove("hash_algorithm");
+    let legacy: TransferManifest = serde_json::from_value(value).unwrap();
+    assert_eq!(legacy.hash_algorithm, HashAlgorithm::Sha256);
+}
+
+#[tokio::test]
+async fn test_planned_chunks_match_created_chunks() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("planned.bin");
+    let test_data: Vec<u8> = (0..200_000u32).map(|i| (i % 253) as u8).collect();
+    fs::write(&input_path, &test_data).unwrap();
+
+    let engine = ChunkEngineImpl::new();
+    let created = engine.create_chunks(input_path.clone()).await.unwrap();
+    let plan = engine.plan_chunks(test_data.len() as u64);
+    assert_eq!(plan.len(), created.len());
+
+    for (spec, expected) in plan.zip(&created) {
+        let chunk = engine.read_chunk(&input_path, spec).await.unwrap();
+        assert_eq!(chunk.offset, expected.offset);
+        assert_eq!(chunk.data, expected.data);
+        assert_eq!(chunk.checksum, expected.checksum);
+    }
+
+    assert_eq!(engine.plan_chunks(0).count(), 0);
+}
+
+#[tokio::test]
+async fn test_sparse_reassembly_preserves_contents() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("sparse.bin");
+    let output_path = temp_dir.path().join("sparse_output.bin");
+
+    // Data, a run of zero chunks, then more data and a trailing hole
+    let mut test_data = vec![1u8; 70_000];
+    test_data.extend(vec![0u8; 200_000]);
+    test_data.extend(vec![2u8; 10_000]);
+    test_data.extend(vec![0u8; 131_072]);
+    fs::write(&input_path, &test_data).unwrap();
+
+    let engine = ChunkEngineImpl::new().with_sparse_output(true);
+    let chunks = engine.create_chunks(input_path).await.unwrap();
+    engine.reassemble_file(chunks, output_path.clone()).await.unwrap();
+
+    assert_eq!(fs::read(&output_path).unwrap(), test_data);
+}
+
+#[tokio::test]
+async fn test_received_file_is_sanitized() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("input.txt");
+    let download_dir = temp_dir.path().join("downloads");
+    fs::write(&input_path, "report").unwrap();
+
+    let engine = ChunkEngineImpl::new();
+    let sanitizer = PathSanitizer::new().with_target(PlatformFamily::Windows);
+    let chunks = engine.create_chunks(input_path).await.unwrap();
+
+    let received = engine
+        .reassemble_received(chunks, &download_dir, std::path::Path::new("q3: final?.txt"), &sanitizer)
+        .await
+        .unwrap();
+
+    assert_eq!(received.path, download_dir.join("q3_ final_.txt"));
+    assert_eq!(received.original, std::path::PathBuf::from("q3: final?.txt"));
+    assert_eq!(fs::read_to_string(&received.path).unwrap(), "report");
+}
+
+#[tokio::test]
+async fn test_failed_reassembly_leaves_no_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("input.bin");
+    let output_dir = temp_dir.path().join("out");
+    let output_path = output_dir.join("output.bin");
+    fs::write(&input_path, vec![9u8; 150_000]).unwrap();
+
+    let engine = ChunkEngineImpl::new().with_fsync_policy(FsyncPolicy::File);
+    let mut chunks = engine.create_chunks(input_path).await.unwrap();
+    chunks[1].data[0] ^= 0xFF;
+
+    assert!(engine.reassemble_file(chunks, output_path.clone()).await.is_err());
+    assert!(!output_path.exists());
+    assert_eq!(fs::read_dir(&output_dir).unwrap().count(), 0, "partial file should be removed");
+
+    // Stale partial files from a crash are cleaned up explicitly
+    fs::write(output_dir.join(".output.bin.0123.kizuna-partial"), b"half").unwrap();
+    assert_eq!(ChunkEngineImpl::remove_partial_files(&output_dir).await.unwrap(), 1);
+}
@@ -23,7 +23,7 @@ fn create_test_request() -> TransferRequest {
 #[tokio::test]
 async fn test_queue_enqueue_and_get() {
     let temp_dir = TempDir::new().unwrap();
-    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4));
+    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4, None));
     queue_manager.initialize().await.unwrap();
 
     let request = create_test_request();
@@ -41,7 +41,7 @@ async fn test_queue_enqueue_and_get() {
 #[tokio::test]
 async fn test_queue_priority_ordering() {
     let temp_dir = TempDir::new().unwrap();
-    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4));
+    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4, None));
     queue_manager.initialize().await.unwrap();
 
     // Enqueue items with different priorities
@@ -73,7 +73,7 @@ async fn test_queue_priority_ordering() {
 #[tokio::test]
 async fn test_queue_cancel_item() {
     let temp_dir = TempDir::new().unwrap();
-    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4));
+    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4, None));
     queue_manager.initialize().await.unwrap();
 
     let queue_id = queue_manager
@@ -90,7 +90,7 @@ async fn test_queue_cancel_item() {
 #[tokio::test]
 async fn test_queue_scheduler() {
     let temp_dir = TempDir::new().unwrap();
-    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 2));
+    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 2, None));
     queue_manager.initialize().await.unwrap();
 
     let scheduler = Arc::new(QueueScheduler::new(queue_manager.clone(), 2));
@@ -119,7 +119,7 @@ async fn test_queue_scheduler() {
 #[tokio::test]
 async fn test_queue_operations_pause_resume() {
     let temp_dir = TempDir::new().unwrap();
-    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4));
+    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4, None));
     queue_manager.initialize().await.unwrap();
 
     let scheduler = Arc::new(QueueScheduler::new(queue_manager.clone(), 4));
@@ -144,7 +144,7 @@ async fn test_queue_operations_pause_resume() {
 #[tokio::test]
 async fn test_queue_operations_change_priority() {
     let temp_dir = TempDir::new().unwrap();
-    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4));
+    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 4, None));
     queue_manager.initialize().await.unwrap();
 
     let scheduler = Arc::new(QueueScheduler::new(queue_manager.clone(), 4));
@@ -171,7 +171,7 @@ async fn test_queue_persistence() {
     let persistence_dir = temp_dir.path().to_path_buf();
 
     let queue_id = {
-        let queue_manager = Arc::new(QueueManagerImpl::new(persistence_dir.clone(), 4));
+        let queue_manager = Arc::new(QueueManagerImpl::new(persistence_dir.clone(), 4, None));
         queue_manager.initialize().await.unwrap();
 
         queue_manager
@@ -181,7 +181,7 @@ async fn test_queue_persistence() {
     };
 
     // Create new manager and verify item was loaded
-    let queue_manager = Arc::new(QueueManagerImpl::new(persistence_dir, 4));
+    let queue_manager = Arc::new(QueueManagerImpl::new(persistence_dir, 4, None));
     queue_manager.initialize().await.unwrap();
 
     let item = queue_manager.get_queue_item(queue_id).await.unwrap();
@@ -192,7 +192,7 @@ async fn test_queue_persistence() {
 #[tokio::test]
 async fn test_queue_statistics() {
     let temp_dir = TempDir::new().unwrap();
-    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 2));
+    let queue_manager = Arc::new(QueueManagerImpl::new(temp_dir.path().to_path_buf(), 2, None));
     queue_manager.initialize().await.unwrap();
 
     let scheduler = Arc::new(QueueScheduler::new(queue_manager.clone(), 2));